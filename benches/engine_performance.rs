@@ -46,6 +46,7 @@ fn create_benchmark_config(num_signals: usize, num_blocks: usize) -> Config {
             category: Some("Benchmark".to_string()),
             source: Some("Generator".to_string()),
             update_frequency_ms: Some(100),
+            display: None,
             metadata: HashMap::new(),
             tags: vec![],
             #[cfg(feature = "engineering-types")]
@@ -94,6 +95,8 @@ fn create_benchmark_config(num_signals: usize, num_blocks: usize) -> Config {
             tags: vec![],
             category: Some("Logic".to_string()),
             metadata: HashMap::new(),
+            budget: None,
+            phase: None,
             #[cfg(feature = "circuit-breaker")]
             circuit_breaker: None,
             #[cfg(feature = "enhanced-monitoring")]
@@ -113,6 +116,17 @@ fn create_benchmark_config(num_signals: usize, num_blocks: usize) -> Config {
         // Signal and block definitions
         signals,
         blocks,
+        block_state: None,
+        clock: None,
+        startup: None,
+        watchdog: None,
+        output_latch: None,
+        degradation: None,
+        signal_groups: Vec::new(),
+        templates: Vec::new(),
+        instances: Vec::new(),
+        composite_blocks: Vec::new(),
+        aliases: std::collections::BTreeMap::new(),
 
         // Metadata fields
         version: "1.0.0".to_string(),
@@ -135,6 +149,12 @@ fn create_benchmark_config(num_signals: usize, num_blocks: usize) -> Config {
         history: None,
         #[cfg(feature = "alarms")]
         alarms: None,
+        #[cfg(feature = "oee")]
+        oee: None,
+        #[cfg(feature = "downtime")]
+        downtime: None,
+        #[cfg(feature = "time-sync")]
+        time_sync: None,
         #[cfg(feature = "web")]
         web: None,
         #[cfg(feature = "metrics")]
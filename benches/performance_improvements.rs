@@ -90,6 +90,7 @@ fn create_benchmark_config_sequential(num_blocks: usize) -> petra::config::Confi
             source: Some("Benchmark".to_string()),
             update_frequency_ms: Some(50),
             tags: vec!["sequential".to_string()],
+            display: None,
             metadata: HashMap::new(),
             #[cfg(feature = "engineering-types")]
             units: None,
@@ -132,6 +133,8 @@ fn create_benchmark_config_sequential(num_blocks: usize) -> petra::config::Confi
             metadata: HashMap::new(),
             #[cfg(feature = "enhanced-errors")]
             error_handling: None,
+            budget: None,
+            phase: None,
             #[cfg(feature = "circuit-breaker")]
             circuit_breaker: None,
             #[cfg(feature = "enhanced-monitoring")]
@@ -8,6 +8,10 @@ use std::env;
 use std::process::Command;
 
 fn main() {
+    // Generate gRPC service code
+    #[cfg(feature = "grpc")]
+    compile_protos();
+
     // Only run validation in non-test builds
     if env::var("CARGO_CFG_TEST").is_ok() {
         return;
@@ -30,6 +34,19 @@ fn main() {
     print_build_summary(&enabled_features);
 }
 
+/// Generate the gRPC server and client from `proto/petra.proto`
+///
+/// Uses the vendored `protoc` so no system protobuf compiler is needed.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/petra.proto");
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc not found");
+    env::set_var("PROTOC", protoc);
+    tonic_build::configure()
+        .compile_protos(&["proto/petra.proto"], &["proto"])
+        .expect("Failed to compile proto/petra.proto");
+}
+
 /// Set build environment variables that the code expects
 fn set_build_env_vars() {
     // Set build timestamp
@@ -154,6 +171,8 @@ fn validate_feature_dependencies(features: &HashSet<String>) -> Result<(), Strin
         // Alarm dependencies
         ("email", vec!["alarms"]),
         ("twilio", vec!["alarms", "web"]),
+        // API dependencies
+        ("grpc", vec!["rbac", "audit", "hot-reload"]),
         // Web dependencies
         ("health-metrics", vec!["health", "metrics"]),
         ("health-history", vec!["health", "history"]),
@@ -0,0 +1,71 @@
+// src/hooks/useDashboards.ts
+import { useCallback, useEffect, useState } from 'react'
+import { toast } from 'react-hot-toast'
+
+export interface WidgetPosition {
+  x: number
+  y: number
+  w: number
+  h: number
+}
+
+export type DashboardWidget = {
+  id: string
+  title?: string
+  position?: WidgetPosition
+} & (
+  | { type: 'trend'; signals: string[]; window_secs?: number }
+  | { type: 'gauge'; signal: string; min: number; max: number; unit?: string }
+  | { type: 'boolean_indicator'; signal: string; true_label?: string; false_label?: string }
+  | { type: 'alarm_banner'; signals: string[] }
+)
+
+export interface Dashboard {
+  name: string
+  widgets: DashboardWidget[]
+  updated_at?: number
+}
+
+/** Signals a dashboard needs, for the WebSocket `subscribe_signals` message */
+export function dashboardSignals(dashboard: Dashboard): string[] {
+  const signals = dashboard.widgets.flatMap((w) => ('signals' in w ? w.signals : [w.signal]))
+  return Array.from(new Set(signals))
+}
+
+/** Server-side dashboard layouts for the given user */
+export function useDashboards(user?: string) {
+  const [dashboards, setDashboards] = useState<Dashboard[]>([])
+
+  const headers: Record<string, string> = user ? { 'X-Petra-User': user } : {}
+
+  const refresh = useCallback(async () => {
+    const response = await fetch('/api/dashboards', { headers })
+    if (response.ok) setDashboards(await response.json())
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [user])
+
+  useEffect(() => {
+    refresh()
+  }, [refresh])
+
+  const saveDashboard = async (dashboard: Dashboard) => {
+    const response = await fetch(`/api/dashboards/${encodeURIComponent(dashboard.name)}`, {
+      method: 'PUT',
+      headers: { ...headers, 'Content-Type': 'application/json' },
+      body: JSON.stringify(dashboard)
+    })
+    if (!response.ok) {
+      const { error } = await response.json()
+      toast.error(`Failed to save dashboard: ${error}`)
+      return
+    }
+    await refresh()
+  }
+
+  const deleteDashboard = async (name: string) => {
+    await fetch(`/api/dashboards/${encodeURIComponent(name)}`, { method: 'DELETE', headers })
+    await refresh()
+  }
+
+  return { dashboards, refresh, saveDashboard, deleteDashboard }
+}
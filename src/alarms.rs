@@ -82,7 +82,7 @@ pub struct AlarmConfig {
 }
 
 /// ISA-18.2 Alarm Priority Levels (Section 6.5)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
 pub enum AlarmPriority {
     /// Priority 1: Critical - Immediate operator action required
     Critical = 1,
@@ -261,8 +261,15 @@ pub struct AlarmManager {
     /// Alarm flood detection
     #[cfg(feature = "alarm-flood-detection")]
     flood_detector: AlarmFloodDetector,
+    
+    /// Areas whose alarms are suppressed while they are in maintenance
+    #[cfg(feature = "maintenance-mode")]
+    maintenance_mode: Option<crate::maintenance_mode::MaintenanceMode>,
 }
 
+/// Alarm manager shared between the engine and the web interface
+pub type SharedAlarmManager = std::sync::Arc<tokio::sync::RwLock<AlarmManager>>;
+
 /// Alarm system events
 #[derive(Debug, Clone)]
 pub enum AlarmEvent {
@@ -271,12 +278,16 @@ pub enum AlarmEvent {
         alarm: AlarmConfig,
         value: Value,
         timestamp: DateTime<Utc>,
+        /// False if the wall clock was not synchronized at `timestamp`
+        clock_synchronized: bool,
     },
     
     /// Alarm cleared
     Cleared {
         name: String,
         timestamp: DateTime<Utc>,
+        /// False if the wall clock was not synchronized at `timestamp`
+        clock_synchronized: bool,
     },
     
     /// Alarm acknowledged
@@ -351,19 +362,58 @@ impl AlarmManager {
             rationalization: AlarmRationalization::new(),
             #[cfg(feature = "alarm-flood-detection")]
             flood_detector: AlarmFloodDetector::new(),
+            #[cfg(feature = "maintenance-mode")]
+            maintenance_mode: None,
         })
     }
     
+    /// Suppress alarms on signals of areas that `mode` puts in maintenance
+    #[cfg(feature = "maintenance-mode")]
+    #[must_use]
+    pub fn with_maintenance_mode(mut self, mode: crate::maintenance_mode::MaintenanceMode) -> Self {
+        self.maintenance_mode = Some(mode);
+        self
+    }
+    
+    /// Whether the signal of `alarm` belongs to an area in maintenance
+    #[cfg_attr(not(feature = "maintenance-mode"), allow(clippy::unused_self))]
+    fn in_maintenance(&self, alarm: &Alarm) -> bool {
+        #[cfg(feature = "maintenance-mode")]
+        if let Some(mode) = &self.maintenance_mode {
+            return mode.signal_area(&alarm.config.signal).is_some();
+        }
+        let _ = alarm;
+        false
+    }
+    
     /// Process alarms - main execution loop
     pub async fn process(&mut self) -> Result<()> {
+        crate::scan_budget::instrument(crate::scan_budget::Subsystem::Alarms, self.process_alarms()).await
+    }
+
+    async fn process_alarms(&mut self) -> Result<()> {
         // Check for alarm flood condition
         #[cfg(feature = "alarm-flood-detection")]
         if self.flood_detector.is_flood_condition() {
             self.handle_alarm_flood().await?;
         }
         
-        // Process each alarm
-        for alarm in &mut self.alarms {
+        // Take the alarms out so each can be updated while events are emitted
+        let mut alarms = std::mem::take(&mut self.alarms);
+        let result = self.evaluate_alarms(&mut alarms).await;
+        self.alarms = alarms;
+        result?;
+        
+        // Update statistics
+        #[cfg(feature = "alarm-statistics")]
+        self.update_statistics();
+        
+        Ok(())
+    }
+    
+    /// Evaluate each alarm against its signal and advance its state
+    async fn evaluate_alarms(&self, alarms: &mut [Alarm]) -> Result<()> {
+        for alarm in alarms {
             if !alarm.config.enabled {
                 continue;
             }
@@ -390,6 +440,11 @@ impl AlarmManager {
                 continue;
             }
             
+            // Areas in maintenance are out of service
+            if self.in_maintenance(alarm) {
+                continue;
+            }
+            
             // Get current value
             let current_value = match self.bus.get(&alarm.config.signal) {
                 Some(value) => value,
@@ -417,15 +472,11 @@ impl AlarmManager {
             self.handle_state_transition(alarm, is_active).await?;
         }
         
-        // Update statistics
-        #[cfg(feature = "alarm-statistics")]
-        self.update_statistics();
-        
         Ok(())
     }
     
     /// Handle alarm state transitions per ISA-18.2
-    async fn handle_state_transition(&mut self, alarm: &mut Alarm, is_active: bool) -> Result<()> {
+    async fn handle_state_transition(&self, alarm: &mut Alarm, is_active: bool) -> Result<()> {
         use AlarmState::*;
         
         let new_state = match (alarm.state, is_active) {
@@ -439,6 +490,7 @@ impl AlarmManager {
                     alarm: alarm.config.clone(),
                     value: alarm.current_value.clone().unwrap_or(Value::Integer(0)),
                     timestamp: Utc::now(),
+                    clock_synchronized: crate::time_sync::clock_synchronized(),
                 }).await?;
                 
                 Unacknowledged
@@ -449,6 +501,7 @@ impl AlarmManager {
                 self.emit_event(AlarmEvent::Cleared {
                     name: alarm.config.name.clone(),
                     timestamp: Utc::now(),
+                    clock_synchronized: crate::time_sync::clock_synchronized(),
                 }).await?;
                 
                 ReturnToNormalUnacknowledged
@@ -461,6 +514,7 @@ impl AlarmManager {
                 self.emit_event(AlarmEvent::Cleared {
                     name: alarm.config.name.clone(),
                     timestamp: Utc::now(),
+                    clock_synchronized: crate::time_sync::clock_synchronized(),
                 }).await?;
                 
                 Normal
@@ -551,6 +605,7 @@ impl AlarmManager {
     pub fn get_active_alarms(&self) -> Vec<AlarmSummary> {
         self.alarms.iter()
             .filter(|a| matches!(a.state, AlarmState::Unacknowledged | AlarmState::Acknowledged))
+            .filter(|a| !self.in_maintenance(a))
             .map(|a| AlarmSummary {
                 name: a.config.name.clone(),
                 description: a.config.description.clone(),
@@ -731,6 +786,7 @@ struct AlarmHistoryEntry {
     pub value: Option<Value>,
     pub user: Option<String>,
     pub duration: Option<Duration>,
+    pub clock_synchronized: bool,
 }
 
 /// Alarm performance statistics
@@ -916,4 +972,50 @@ mod tests {
         alarm.state = AlarmState::Acknowledged;
         assert_eq!(alarm.state, AlarmState::Acknowledged);
     }
+    
+    #[cfg(feature = "maintenance-mode")]
+    #[tokio::test]
+    async fn test_alarm_suppressed_in_maintenance() {
+        use crate::maintenance_mode::{MaintenanceMode, MaintenanceModeConfig};
+        
+        let config: AlarmConfig = serde_yaml::from_str(
+            "name: high_level\n\
+             description: Tank level high\n\
+             tag_name: LT-101\n\
+             signal: line1.level\n\
+             condition: { type: High, threshold: 90.0 }\n\
+             priority: High\n\
+             consequence: Overflow\n\
+             corrective_action: Stop the feed pump\n\
+             max_response_time: 5\n\
+             classification: Process\n\
+             enabled: true\n\
+             setpoint: 90.0\n\
+             units: \"%\"\n\
+             area: line1\n\
+             equipment: tank1\n",
+        )
+        .unwrap();
+        let areas: MaintenanceModeConfig =
+            serde_yaml::from_str("areas:\n- { name: line1, signals: [\"line1.*\"] }\n").unwrap();
+        
+        let bus = SignalBus::new();
+        let mode = MaintenanceMode::new(areas, bus.clone()).unwrap();
+        let mut manager = AlarmManager::new(vec![config], bus.clone())
+            .unwrap()
+            .with_maintenance_mode(mode.clone());
+        
+        mode.enter("line1", "tech1", Some("level transmitter calibration")).unwrap();
+        bus.set("line1.level", Value::Float(95.0)).unwrap();
+        manager.process().await.unwrap();
+        assert_eq!(manager.alarms[0].state, AlarmState::Normal);
+        assert_eq!(manager.alarms[0].activation_count, 0);
+        assert!(manager.get_active_alarms().is_empty());
+        
+        // Back in service the alarm is evaluated again
+        mode.exit("line1", "tech1").unwrap();
+        manager.process().await.unwrap();
+        assert_eq!(manager.alarms[0].state, AlarmState::Unacknowledged);
+        assert_eq!(manager.get_active_alarms().len(), 1);
+    }
 }
@@ -0,0 +1,404 @@
+// src/backup.rs
+//! Backup and restore of the complete node state
+//!
+//! `petra backup create` writes one gzip-compressed tar archive holding
+//! everything needed to rebuild a node:
+//!
+//! - `manifest.json`: format version, PETRA version, creation time and the
+//!   list of files in the archive
+//! - `config/<file>`: the configuration file, including the users, roles and
+//!   command clients of the `security` and `mqtt.commands` sections
+//! - `state/block_state.json`: block state snapshots and retained signal
+//!   values (`block_state.path`)
+//! - `dashboards/`: per-user dashboard layouts (`web.dashboard_dir`)
+//! - `history/`: with `--history-days`, the history files modified within
+//!   that many days (`history.data_dir`)
+//!
+//! `petra backup restore` writes the configuration to its original path, or
+//! to `--config` when cloning a node, and places the other files where the
+//! restored configuration expects them. Existing files are only replaced
+//! with `--force`.
+
+use crate::config::Config;
+use crate::error::{PlcError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info};
+
+/// Archive format written by this version
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CONFIG_DIR: &str = "config";
+const BLOCK_STATE: &str = "state/block_state.json";
+const DASHBOARDS_DIR: &str = "dashboards";
+const HISTORY_DIR: &str = "history";
+
+/// What goes into a backup besides configuration and state
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    /// Include history files modified within this many days
+    pub history_days: Option<u32>,
+}
+
+/// Where and how a backup is restored
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// Configuration path, instead of the path the backup was taken from
+    pub config_path: Option<PathBuf>,
+
+    /// Replace existing files
+    pub force: bool,
+}
+
+/// Description of a backup archive, stored as its first entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Archive format version
+    pub format: u32,
+
+    /// PETRA version that wrote the archive
+    pub petra_version: String,
+
+    /// When the backup was taken
+    pub created_at: DateTime<Utc>,
+
+    /// Configuration path on the node that was backed up
+    pub config_path: PathBuf,
+
+    /// Oldest modification time of included history files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_since: Option<DateTime<Utc>>,
+
+    /// Archived files, configuration first
+    pub entries: Vec<BackupEntry>,
+}
+
+/// One file in a backup archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// Path inside the archive
+    pub path: String,
+
+    /// File size in bytes
+    pub size: u64,
+}
+
+/// Files written by a restore
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    /// Where the configuration was written
+    pub config_path: PathBuf,
+
+    /// Every file written, configuration first
+    pub files: Vec<PathBuf>,
+}
+
+// ============================================================================
+// CREATE
+// ============================================================================
+
+/// Write a backup of the node configured by `config_path` to `output`
+///
+/// # Errors
+///
+/// Returns an error if the configuration does not load or a file cannot be
+/// read or written.
+pub fn create(config_path: &Path, output: &Path, options: &BackupOptions) -> Result<BackupManifest> {
+    let config = Config::from_file(config_path)?;
+    let config_name = config_path
+        .file_name()
+        .ok_or_else(|| PlcError::Config(format!("'{}' is not a file", config_path.display())))?;
+
+    let mut files = vec![(archive_path(Path::new(CONFIG_DIR), Path::new(config_name)), config_path.to_path_buf())];
+    if let Some(block_state) = config.block_state.as_ref().filter(|b| b.path.is_file()) {
+        files.push((BLOCK_STATE.to_string(), block_state.path.clone()));
+    }
+    if let Some(dir) = dashboard_dir(&config) {
+        collect(&dir, Path::new(DASHBOARDS_DIR), None, &mut files)?;
+    }
+    let history_since = options
+        .history_days
+        .map(|days| SystemTime::now() - Duration::from_secs(u64::from(days) * 86_400));
+    if let (Some(since), Some(dir)) = (history_since, history_dir(&config)) {
+        collect(&dir, Path::new(HISTORY_DIR), Some(since), &mut files)?;
+    }
+
+    let entries = files
+        .iter()
+        .map(|(path, source)| {
+            Ok(BackupEntry {
+                path: path.clone(),
+                size: std::fs::metadata(source)?.len(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    let manifest = BackupManifest {
+        format: FORMAT_VERSION,
+        petra_version: crate::VERSION.to_string(),
+        created_at: Utc::now(),
+        config_path: config_path.to_path_buf(),
+        history_since: history_since.map(DateTime::from),
+        entries,
+    };
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let encoder = flate2::write::GzEncoder::new(File::create(output)?, flate2::Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    let json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(u64::try_from(manifest.created_at.timestamp()).unwrap_or_default());
+    header.set_cksum();
+    archive.append_data(&mut header, MANIFEST, json.as_slice())?;
+    for (path, source) in &files {
+        archive.append_path_with_name(source, path)?;
+    }
+    archive.into_inner()?.finish()?;
+
+    info!("Backed up {} files to {}", files.len(), output.display());
+    Ok(manifest)
+}
+
+/// Add the regular files below `dir` modified at or after `since`
+fn collect(dir: &Path, prefix: &Path, since: Option<SystemTime>, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        debug!("Skipping missing directory {}", dir.display());
+        return Ok(());
+    };
+    let mut entries: Vec<_> = entries.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+    for entry in entries {
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        let name = prefix.join(entry.file_name());
+        if metadata.is_dir() {
+            collect(&path, &name, since, files)?;
+        } else if metadata.is_file() && since.is_none_or(|since| metadata.modified().is_ok_and(|m| m >= since)) {
+            files.push((archive_path(Path::new(""), &name), path));
+        }
+    }
+    Ok(())
+}
+
+/// Archive paths always use `/`, whatever the platform separator
+fn archive_path(prefix: &Path, path: &Path) -> String {
+    prefix
+        .join(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn dashboard_dir(config: &Config) -> Option<PathBuf> {
+    #[cfg(feature = "web")]
+    if let Some(dir) = config.web.as_ref().and_then(|w| w.dashboard_dir.clone()) {
+        return Some(dir);
+    }
+    let _ = config;
+    None
+}
+
+fn history_dir(config: &Config) -> Option<PathBuf> {
+    #[cfg(feature = "history")]
+    if let Some(history) = &config.history {
+        return Some(history.data_dir.clone());
+    }
+    let _ = config;
+    None
+}
+
+// ============================================================================
+// RESTORE
+// ============================================================================
+
+fn open(archive: &Path) -> Result<tar::Archive<flate2::read::GzDecoder<File>>> {
+    Ok(tar::Archive::new(flate2::read::GzDecoder::new(File::open(archive)?)))
+}
+
+fn not_a_backup(archive: &Path, reason: &str) -> PlcError {
+    PlcError::Config(format!("'{}' is not a PETRA backup: {reason}", archive.display()))
+}
+
+/// Read the manifest of a backup archive
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be read, does not start with a
+/// manifest or was written in a newer format.
+pub fn read_manifest(archive: &Path) -> Result<BackupManifest> {
+    let mut tar = open(archive)?;
+    let mut entries = tar.entries()?;
+    let mut entry = entries.next().ok_or_else(|| not_a_backup(archive, "archive is empty"))??;
+    if entry.path()?.as_ref() != Path::new(MANIFEST) {
+        return Err(not_a_backup(archive, "no manifest"));
+    }
+    let mut json = String::new();
+    entry.read_to_string(&mut json)?;
+    let manifest: BackupManifest = serde_json::from_str(&json)?;
+    if manifest.format > FORMAT_VERSION {
+        return Err(PlcError::Config(format!(
+            "Backup format {} is newer than the supported format {FORMAT_VERSION}",
+            manifest.format
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Restore the backup in `archive`
+///
+/// The configuration is restored first and decides where the state,
+/// dashboard and history files go. Nothing is written if any target file
+/// exists and `force` is not set.
+///
+/// # Errors
+///
+/// Returns an error if the archive is not a valid backup, a target exists
+/// without `force`, the restored configuration does not load or a file
+/// cannot be written.
+pub fn restore(archive: &Path, options: &RestoreOptions) -> Result<RestoreReport> {
+    let manifest = read_manifest(archive)?;
+    let config_entry = manifest
+        .entries
+        .first()
+        .filter(|e| e.path.starts_with(&format!("{CONFIG_DIR}/")))
+        .ok_or_else(|| not_a_backup(archive, "no configuration"))?;
+    let config_path = options.config_path.clone().unwrap_or_else(|| manifest.config_path.clone());
+
+    // Read the configuration before anything is written
+    let mut tar = open(archive)?;
+    let mut entries = tar.entries()?.skip(1);
+    let mut entry = entries.next().ok_or_else(|| not_a_backup(archive, "no configuration"))??;
+    if entry.path()?.to_string_lossy() != config_entry.path {
+        return Err(not_a_backup(archive, "configuration is not the first file"));
+    }
+    let mut yaml = Vec::new();
+    entry.read_to_end(&mut yaml)?;
+    let config: Config = serde_yaml::from_slice(&yaml)
+        .map_err(|e| PlcError::Config(format!("Backed up configuration does not parse: {e}")))?;
+
+    let targets: Vec<PathBuf> = std::iter::once(Ok(config_path.clone()))
+        .chain(manifest.entries.iter().skip(1).map(|e| target(&config, &e.path)))
+        .collect::<Result<_>>()?;
+    if !options.force {
+        if let Some(existing) = targets.iter().find(|t| t.exists()) {
+            return Err(PlcError::Config(format!(
+                "'{}' already exists; use --force to replace it",
+                existing.display()
+            )));
+        }
+    }
+
+    write_file(&config_path, &yaml)?;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let target = target(&config, &path)?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        write_file(&target, &data)?;
+    }
+
+    info!("Restored {} files from {}", targets.len(), archive.display());
+    Ok(RestoreReport { config_path, files: targets })
+}
+
+/// Where the archive file `path` is restored to under `config`
+fn target(config: &Config, path: &str) -> Result<PathBuf> {
+    let relative = |prefix: &str| -> Option<PathBuf> {
+        let rest = Path::new(path.strip_prefix(prefix)?.strip_prefix('/')?);
+        rest.components().all(|c| matches!(c, Component::Normal(_))).then(|| rest.to_path_buf())
+    };
+    let unexpected = || PlcError::Config(format!("Backup file '{path}' has no place in the restored configuration"));
+
+    if path == BLOCK_STATE {
+        return config.block_state.as_ref().map(|b| b.path.clone()).ok_or_else(unexpected);
+    }
+    if let Some(rest) = relative(DASHBOARDS_DIR) {
+        return dashboard_dir(config).map(|dir| dir.join(rest)).ok_or_else(unexpected);
+    }
+    if let Some(rest) = relative(HISTORY_DIR) {
+        return history_dir(config).map(|dir| dir.join(rest)).ok_or_else(unexpected);
+    }
+    Err(unexpected())
+}
+
+/// Write through a temporary sibling so an interrupted restore leaves no
+/// truncated file behind
+fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".restore");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)?;
+    debug!("Restored {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(dir: &Path) -> PathBuf {
+        let config = dir.join("petra.yaml");
+        std::fs::write(
+            &config,
+            format!(
+                "scan_time_ms: 100\n\
+                 max_scan_jitter_ms: 50\n\
+                 signals:\n  - {{ name: pump.hours, type: float, retain: true }}\n\
+                 blocks: []\n\
+                 block_state: {{ path: {} }}\n",
+                dir.join("state.json").display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.join("state.json"), r#"{"saved_at":"2026-01-01T00:00:00Z","blocks":{}}"#).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let config = node(source.path());
+        let archive = source.path().join("backup/node.tar.gz");
+        let manifest = create(&config, &archive, &BackupOptions::default()).unwrap();
+        let paths: Vec<_> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["config/petra.yaml", BLOCK_STATE]);
+        assert_eq!(read_manifest(&archive).unwrap().entries.len(), 2);
+
+        // Restoring over the node is refused unless forced
+        assert!(restore(&archive, &RestoreOptions::default()).is_err());
+        std::fs::write(source.path().join("state.json"), "{}").unwrap();
+        let report = restore(&archive, &RestoreOptions { force: true, ..Default::default() }).unwrap();
+        assert_eq!(report.config_path, config);
+        assert!(std::fs::read_to_string(source.path().join("state.json")).unwrap().contains("saved_at"));
+
+        // Cloning writes the configuration elsewhere
+        let clone = tempfile::tempdir().unwrap();
+        let clone_config = clone.path().join("clone.yaml");
+        std::fs::remove_file(source.path().join("state.json")).unwrap();
+        let options = RestoreOptions { config_path: Some(clone_config.clone()), force: false };
+        let report = restore(&archive, &options).unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(std::fs::read(&clone_config).unwrap(), std::fs::read(&config).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_paths_outside_their_directory() {
+        let config: Config = serde_yaml::from_str("signals: []\nblocks: []\n").unwrap();
+        assert!(target(&config, BLOCK_STATE).is_err());
+        assert!(target(&config, "history/../../etc/passwd").is_err());
+        assert!(target(&config, "other/file").is_err());
+    }
+}
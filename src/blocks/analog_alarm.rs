@@ -0,0 +1,355 @@
+// src/blocks/analog_alarm.rs - Analog alarm limit block for PETRA
+//
+// Purpose:
+// --------
+// Implements ANALOG_ALARM, which compares one analog input against
+// ISA-style high-high, high, low and low-low limits and drives a boolean
+// output per limit, each with its own deadband and on/off delays, so a
+// noisy measurement does not chatter its alarms.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs; with the
+//   `alarms` feature, alarm definitions from alarms.rs
+// - Used by: blocks/mod.rs factory, and the alarm manager through
+//   [`Block::alarm_configs`], which maps each limit's `priority` onto a
+//   discrete alarm on its output
+// - Reads: the numeric input `in`
+// - Writes: the boolean outputs `hh`, `h`, `l` and `ll` of the configured
+//   limits, and the optional outputs `active` (any limit in alarm) and
+//   `state` (2 for HH, 1 for H, 0 normal, -1 for L, -2 for LL)
+//
+// Configuration:
+// --------------
+//   - name: tank_level_alarms
+//     type: ANALOG_ALARM
+//     inputs: { in: tank.level }
+//     outputs: { hh: tank.level_hh, h: tank.level_h, l: tank.level_l, ll: tank.level_ll }
+//     params:
+//       deadband: 1.0                 # default for every limit
+//       on_delay_ms: 2000             # default for every limit
+//       hh: { value: 95, deadband: 0.5, on_delay_ms: 0, priority: critical }
+//       h: 85
+//       l: { value: 15, off_delay_ms: 10000 }
+//       ll: { value: 5, priority: high }
+//
+// A high limit trips above its value and clears below value - deadband; a
+// low limit trips below its value and clears above value + deadband. The
+// condition must hold for the on delay to raise the output and its clear
+// condition for the off delay to drop it. Inputs that are not finite hold
+// the outputs. Default priorities are high for HH and LL and medium for H
+// and L.
+
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, get_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Limit as configured: a bare value or a map with overrides
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum LimitConfig {
+    Value(f64),
+    Detailed {
+        value: f64,
+        deadband: Option<f64>,
+        on_delay_ms: Option<u64>,
+        off_delay_ms: Option<u64>,
+        priority: Option<Priority>,
+    },
+}
+
+/// Alarm priority of a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+struct Limit {
+    /// `hh`, `h`, `l` or `ll`
+    key: &'static str,
+    output: String,
+    value: f64,
+    deadband: f64,
+    high: bool,
+    on_delay: Duration,
+    off_delay: Duration,
+    #[cfg_attr(not(feature = "alarms"), allow(dead_code))]
+    priority: Priority,
+    active: bool,
+    /// Since when the condition to change `active` has held
+    pending: Option<Instant>,
+}
+
+impl Limit {
+    fn update(&mut self, x: f64, now: Instant) {
+        let change = if self.high {
+            if self.active { x < self.value - self.deadband } else { x > self.value }
+        } else if self.active {
+            x > self.value + self.deadband
+        } else {
+            x < self.value
+        };
+        if !change {
+            self.pending = None;
+            return;
+        }
+        let since = *self.pending.get_or_insert(now);
+        let delay = if self.active { self.off_delay } else { self.on_delay };
+        if now.saturating_duration_since(since) >= delay {
+            self.active = !self.active;
+            self.pending = None;
+        }
+    }
+}
+
+/// HH/H/L/LL alarm limits on an analog input
+pub struct AnalogAlarmBlock {
+    name: String,
+    input: String,
+    active_output: Option<String>,
+    state_output: Option<String>,
+    /// Configured limits, in the order hh, h, l, ll
+    limits: Vec<Limit>,
+}
+
+impl Block for AnalogAlarmBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let x = bus.get_float(&self.input)?;
+        if x.is_finite() {
+            let now = bus.now();
+            for limit in &mut self.limits {
+                limit.update(x, now);
+            }
+        }
+
+        for limit in &self.limits {
+            bus.set(&limit.output, Value::Bool(limit.active))?;
+        }
+        if let Some(output) = &self.active_output {
+            bus.set(output, Value::Bool(self.limits.iter().any(|limit| limit.active)))?;
+        }
+        if let Some(output) = &self.state_output {
+            let active = |key: &str| self.limits.iter().any(|limit| limit.key == key && limit.active);
+            let state = [("hh", 2), ("ll", -2), ("h", 1), ("l", -1)]
+                .into_iter()
+                .find(|(key, _)| active(key))
+                .map_or(0, |(_, state)| state);
+            bus.set(output, Value::Integer(state))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "ANALOG_ALARM"
+    }
+
+    fn category(&self) -> &'static str {
+        "comparison"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        for limit in &mut self.limits {
+            limit.active = false;
+            limit.pending = None;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "alarms")]
+    fn alarm_configs(&self) -> Vec<crate::alarms::AlarmConfig> {
+        use crate::alarms::{AlarmClassification, AlarmCondition, AlarmConfig, AlarmPriority};
+
+        self.limits
+            .iter()
+            .map(|limit| AlarmConfig {
+                name: format!("{}.{}", self.name, limit.key),
+                description: format!("{} {}", self.input, limit.key.to_uppercase()),
+                tag_name: self.input.clone(),
+                signal: limit.output.clone(),
+                condition: AlarmCondition::Discrete { expected_state: false },
+                priority: match limit.priority {
+                    Priority::Critical => AlarmPriority::Critical,
+                    Priority::High => AlarmPriority::High,
+                    Priority::Medium => AlarmPriority::Medium,
+                    Priority::Low => AlarmPriority::Low,
+                },
+                consequence: String::new(),
+                corrective_action: String::new(),
+                max_response_time: None,
+                classification: AlarmClassification::Process,
+                enabled: true,
+                setpoint: limit.value,
+                units: String::new(),
+                area: String::new(),
+                equipment: String::new(),
+            })
+            .collect()
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        vec![self.input.as_str()]
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.limits
+            .iter()
+            .map(|limit| limit.output.as_str())
+            .chain([&self.active_output, &self.state_output].into_iter().filter_map(Option::as_deref))
+            .collect()
+    }
+}
+
+/// Factory function for `ANALOG_ALARM` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input or any limit, for a
+/// limit without its output or an output without its limit, for a negative
+/// deadband, or for limits out of the order ll <= l <= h <= hh.
+pub fn create_analog_alarm_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let error = |message: String| PlcError::Config(format!("ANALOG_ALARM block '{}' {}", config.name, message));
+    let deadband: f64 = get_numeric_parameter(config, "deadband", Some(0.0))?;
+    let on_delay_ms: u64 = get_numeric_parameter(config, "on_delay_ms", Some(0))?;
+    let off_delay_ms: u64 = get_numeric_parameter(config, "off_delay_ms", Some(0))?;
+
+    let mut limits = Vec::new();
+    for (key, high, priority) in [
+        ("hh", true, Priority::High),
+        ("h", true, Priority::Medium),
+        ("l", false, Priority::Medium),
+        ("ll", false, Priority::High),
+    ] {
+        let output = get_output_signal(config, key, false)?;
+        let limit = if config.params.contains_key(key) {
+            Some(get_parameter::<LimitConfig>(config, key, None)?)
+        } else {
+            None
+        };
+        let (output, limit) = match (output, limit) {
+            (Some(output), Some(limit)) => (output, limit),
+            (None, None) => continue,
+            (Some(_), None) => return Err(error(format!("has output '{key}' but no '{key}' limit"))),
+            (None, Some(_)) => return Err(error(format!("has limit '{key}' but no '{key}' output"))),
+        };
+        let limit = match limit {
+            LimitConfig::Value(value) => Limit {
+                key,
+                output,
+                value,
+                deadband,
+                high,
+                on_delay: Duration::from_millis(on_delay_ms),
+                off_delay: Duration::from_millis(off_delay_ms),
+                priority,
+                active: false,
+                pending: None,
+            },
+            LimitConfig::Detailed {
+                value,
+                deadband: own_deadband,
+                on_delay_ms: on,
+                off_delay_ms: off,
+                priority: own_priority,
+            } => Limit {
+                key,
+                output,
+                value,
+                deadband: own_deadband.unwrap_or(deadband),
+                high,
+                on_delay: Duration::from_millis(on.unwrap_or(on_delay_ms)),
+                off_delay: Duration::from_millis(off.unwrap_or(off_delay_ms)),
+                priority: own_priority.unwrap_or(priority),
+                active: false,
+                pending: None,
+            },
+        };
+        if !(limit.value.is_finite() && limit.deadband.is_finite() && limit.deadband >= 0.0) {
+            return Err(error(format!("limit '{key}' needs a finite value and a deadband of at least 0")));
+        }
+        limits.push(limit);
+    }
+    if limits.is_empty() {
+        return Err(error("needs at least one of the limits hh, h, l and ll".to_string()));
+    }
+    // Limits are in the order hh, h, l, ll, so each must not exceed the one before
+    if let Some(pair) = limits.windows(2).find(|pair| pair[1].value > pair[0].value) {
+        return Err(error(format!("limit '{}' is above limit '{}'", pair[1].key, pair[0].key)));
+    }
+
+    Ok(Box::new(AnalogAlarmBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        active_output: get_output_signal(config, "active", false)?,
+        state_output: get_output_signal(config, "state", false)?,
+        limits,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+
+    fn alarm(params: &str) -> Result<Box<dyn Block>> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: level, type: ANALOG_ALARM, inputs: {{ in: x }}, \
+             outputs: {{ hh: hh, h: h, l: l, ll: ll, state: state }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config)
+    }
+
+    #[test]
+    fn test_limits_with_deadband_and_delays() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let mut block = alarm(
+            "deadband: 2, on_delay_ms: 1000, hh: { value: 95, on_delay_ms: 0 }, h: 85, l: 15, \
+             ll: { value: 5, deadband: 0 }",
+        )
+        .unwrap();
+        let mut scan = |x: f64, advance_ms: u64| {
+            clock.advance(Duration::from_millis(advance_ms));
+            bus.set("x", Value::Float(x)).unwrap();
+            block.execute(&bus).unwrap();
+            (bus.get_bool("h").unwrap(), bus.get_bool("hh").unwrap(), bus.get_integer("state").unwrap())
+        };
+
+        assert_eq!(scan(50.0, 0), (false, false, 0));
+        // H waits for its on delay, HH has none
+        assert_eq!(scan(96.0, 0), (false, true, 2));
+        assert_eq!(scan(86.0, 600), (false, false, 0));
+        assert_eq!(scan(86.0, 600), (true, false, 1));
+        // A dip inside the deadband keeps H, leaving it clears at once
+        assert_eq!(scan(84.0, 100), (true, false, 1));
+        assert_eq!(scan(82.0, 100), (false, false, 0));
+
+        assert_eq!(scan(4.0, 0), (false, false, 0));
+        assert_eq!(scan(4.0, 1000), (false, false, -2));
+        assert_eq!(bus.get("l"), Some(Value::Bool(true)));
+        assert_eq!(scan(f64::NAN, 1000), (false, false, -2));
+    }
+
+    #[test]
+    fn test_limits_must_be_ordered_and_paired_with_outputs() {
+        assert!(alarm("h: 80, l: 20").is_err());
+        assert!(alarm("hh: 90, h: 95, l: 20, ll: 10").is_err());
+        assert!(alarm("hh: 90, h: 80, l: 20, ll: { value: 10, deadband: -1 }").is_err());
+        assert!(alarm("hh: 90, h: 80, l: 20, ll: { value: 10, priority: urgent }").is_err());
+        assert!(alarm("hh: 90, h: 80, l: 20, ll: { value: 10, priority: critical }").is_ok());
+    }
+}
@@ -338,13 +338,20 @@ pub fn create_xor_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
 // COMPARISON BLOCKS
 // ============================================================================
 
+/// Right-hand side of a comparison
+enum Operand {
+    Signal(String),
+    /// The `value` parameter, for blocks with a single input
+    Constant(f64),
+}
+
 /// Generic comparison block for numeric comparisons
 ///
 /// Uses function pointers for efficient comparison operations
 struct ComparisonBlock {
     name: String,
     input_a: String,
-    input_b: String,
+    input_b: Operand,
     output: String,
     comparison_fn: fn(f64, f64) -> bool,
     block_type: String,
@@ -354,7 +361,10 @@ impl Block for ComparisonBlock {
     fn execute(&mut self, bus: &SignalBus) -> Result<()> {
         // Get numeric values from signals
         let a = bus.get_float(&self.input_a)?;
-        let b = bus.get_float(&self.input_b)?;
+        let b = match &self.input_b {
+            Operand::Signal(signal) => bus.get_float(signal)?,
+            Operand::Constant(value) => *value,
+        };
         
         // Apply comparison function
         let result = (self.comparison_fn)(a, b);
@@ -373,12 +383,25 @@ impl Block for ComparisonBlock {
 
 /// Generic factory for comparison blocks
 ///
-/// Handles both named inputs (a, b) and positional inputs
+/// Handles both named inputs (a, b) and positional inputs. A block with a
+/// single input compares it against its `value` parameter; labels of enum
+/// signals are resolved to their raw values before the block is created.
 fn create_comparison_block(
     config: &BlockConfig,
     block_type: &str,
     comparison_fn: fn(f64, f64) -> bool,
 ) -> Result<Box<dyn Block>> {
+    if config.inputs.len() == 1 {
+        let value = config.params.get("value").ok_or_else(|| PlcError::Config(
+            format!("{block_type} block with one input requires a 'value' parameter")
+        ))?;
+        let value = value.as_f64().ok_or_else(|| PlcError::Config(
+            format!("{} block '{}' value must be a number", block_type, config.name)
+        ))?;
+        let input_a = config.inputs.values().next().expect("checked to have one input").clone();
+        return create_comparison_with(config, block_type, comparison_fn, input_a, Operand::Constant(value));
+    }
+    
     // Validate we have exactly 2 inputs and 1 output
     if config.inputs.len() != 2 {
         return Err(PlcError::Config(
@@ -386,12 +409,6 @@ fn create_comparison_block(
         ));
     }
     
-    if config.outputs.len() != 1 {
-        return Err(PlcError::Config(
-            format!("{} block requires exactly one output", block_type)
-        ));
-    }
-    
     // Try named inputs first (a, b), fall back to positional
     let input_a = config.inputs.get("a")
         .or_else(|| config.inputs.values().nth(0))
@@ -403,6 +420,22 @@ fn create_comparison_block(
         .expect("validated to have two inputs")
         .clone();
     
+    create_comparison_with(config, block_type, comparison_fn, input_a, Operand::Signal(input_b))
+}
+
+fn create_comparison_with(
+    config: &BlockConfig,
+    block_type: &str,
+    comparison_fn: fn(f64, f64) -> bool,
+    input_a: String,
+    input_b: Operand,
+) -> Result<Box<dyn Block>> {
+    if config.outputs.len() != 1 {
+        return Err(PlcError::Config(
+            format!("{block_type} block requires exactly one output")
+        ));
+    }
+    
     let output = config.outputs.values()
         .next()
         .expect("validated to have one output")
@@ -476,6 +509,7 @@ mod tests {
             params: HashMap::new(),
             description: None,
             tags: vec![],
+            ..Default::default()
         }
     }
     
@@ -512,12 +546,12 @@ mod tests {
     
     #[test]
     fn test_and_block_validation() {
-        let mut config = create_test_config("AND", "test_and");
+        let config = create_test_config("AND", "test_and");
         
         // Test missing inputs
         let result = create_and_block(&config);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("at least one input"));
+        assert!(result.err().unwrap().to_string().contains("at least one input"));
     }
     
     #[test]
@@ -654,6 +688,6 @@ mod tests {
         config.inputs.insert("a".to_string(), "a".to_string());
         let result = create_gt_block(&config);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("exactly two inputs"));
+        assert!(result.err().unwrap().to_string().contains("requires a 'value' parameter"));
     }
 }
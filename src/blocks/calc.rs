@@ -0,0 +1,534 @@
+// src/blocks/calc.rs - Expression evaluator block for PETRA
+//
+// Purpose:
+// --------
+// Implements the CALC block, which evaluates an arithmetic or boolean
+// expression over its named inputs, replacing chains of ADD, MUL and GT
+// blocks. The expression is parsed once when the block is created; each
+// scan only walks the parsed tree.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: every input the expression names, by its input name
+// - Writes: the result to `out`, a bool for comparisons and logic and a
+//   float otherwise, or an integer with `integer: true`
+//
+// Configuration:
+// --------------
+//   - name: oven_overtemp
+//     type: CALC
+//     inputs: { a: oven.temp_c, limit: oven.limit_f, enable: oven.enabled }
+//     outputs: { out: oven.overtemp }
+//     params:
+//       expression: "(a * 9 / 5) + 32 > limit && enable"
+//
+// Expressions:
+// ------------
+// From lowest to highest precedence: `||`, `&&`, comparisons (`< <= > >=
+// == !=`), `+ -`, `* / %`, and unary `-` and `!`, with parentheses for
+// grouping. Literals are numbers, `true` and `false`. Functions are `abs`,
+// `round`, `floor`, `ceil`, `sqrt`, `min`, `max` and `if(condition, then,
+// else)`. Booleans count as 1 and 0 in arithmetic, and numbers other than 0
+// as true in logic. `&&`, `||` and `if` only evaluate the operand they need.
+
+use super::{get_bool_parameter, get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+
+// ============================================================================
+// EXPRESSIONS
+// ============================================================================
+
+/// Result of evaluating an expression or one of its parts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Scalar {
+    Bool(bool),
+    Number(f64),
+}
+
+impl Scalar {
+    fn number(self) -> f64 {
+        match self {
+            Self::Bool(b) => f64::from(u8::from(b)),
+            Self::Number(n) => n,
+        }
+    }
+
+    pub(super) fn truth(self) -> bool {
+        match self {
+            Self::Bool(b) => b,
+            Self::Number(n) => n != 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+/// Parsed expression, with inputs referred to by position
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Expr {
+    Literal(Scalar),
+    Input(usize),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(&'static str, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, inputs: &[Scalar]) -> Scalar {
+        match self {
+            Self::Literal(value) => *value,
+            Self::Input(index) => inputs[*index],
+            Self::Neg(e) => Scalar::Number(-e.eval(inputs).number()),
+            Self::Not(e) => Scalar::Bool(!e.eval(inputs).truth()),
+            Self::Binary(BinaryOp::And, a, b) => Scalar::Bool(a.eval(inputs).truth() && b.eval(inputs).truth()),
+            Self::Binary(BinaryOp::Or, a, b) => Scalar::Bool(a.eval(inputs).truth() || b.eval(inputs).truth()),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(inputs), b.eval(inputs));
+                let (x, y) = (a.number(), b.number());
+                #[allow(clippy::float_cmp)]
+                match op {
+                    BinaryOp::Add => Scalar::Number(x + y),
+                    BinaryOp::Sub => Scalar::Number(x - y),
+                    BinaryOp::Mul => Scalar::Number(x * y),
+                    BinaryOp::Div => Scalar::Number(x / y),
+                    BinaryOp::Rem => Scalar::Number(x % y),
+                    BinaryOp::Lt => Scalar::Bool(x < y),
+                    BinaryOp::Le => Scalar::Bool(x <= y),
+                    BinaryOp::Gt => Scalar::Bool(x > y),
+                    BinaryOp::Ge => Scalar::Bool(x >= y),
+                    BinaryOp::Eq => Scalar::Bool(x == y),
+                    BinaryOp::Ne => Scalar::Bool(x != y),
+                    BinaryOp::And | BinaryOp::Or => unreachable!("logical operators short-circuit above"),
+                }
+            }
+            Self::Call("if", args) => {
+                if args[0].eval(inputs).truth() {
+                    args[1].eval(inputs)
+                } else {
+                    args[2].eval(inputs)
+                }
+            }
+            Self::Call(name, args) => {
+                let args: Vec<f64> = args.iter().map(|a| a.eval(inputs).number()).collect();
+                Scalar::Number(match (*name, args.as_slice()) {
+                    ("abs", [a]) => a.abs(),
+                    ("round", [a]) => a.round(),
+                    ("floor", [a]) => a.floor(),
+                    ("ceil", [a]) => a.ceil(),
+                    ("sqrt", [a]) => a.sqrt(),
+                    ("min", [a, b]) => a.min(*b),
+                    ("max", [a, b]) => a.max(*b),
+                    _ => f64::NAN,
+                })
+            }
+        }
+    }
+}
+
+/// Name and number of arguments of each function
+const FUNCTIONS: [(&str, usize); 8] = [
+    ("abs", 1),
+    ("round", 1),
+    ("floor", 1),
+    ("ceil", 1),
+    ("sqrt", 1),
+    ("min", 2),
+    ("max", 2),
+    ("if", 3),
+];
+
+/// Recursive-descent parser for CALC expressions
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    source: &'a str,
+    /// Input names, indexed by [`Expr::Input`]
+    inputs: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+    fn parse(source: &'a str, inputs: &'a [String]) -> std::result::Result<Expr, String> {
+        let mut parser = Self {
+            chars: source.char_indices().peekable(),
+            source,
+            inputs,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{c}'")),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    /// Consume `token` if the input continues with it
+    fn eat(&mut self, token: &str) -> bool {
+        self.peek();
+        let Some(&(start, _)) = self.chars.peek() else {
+            return false;
+        };
+        if !self.source[start..].starts_with(token) {
+            return false;
+        }
+        for _ in token.chars() {
+            self.chars.next();
+        }
+        true
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.comparison()?;
+        while self.eat("&&") {
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> std::result::Result<Expr, String> {
+        let expr = self.sum()?;
+        // Two-character operators first, so `<=` is not read as `<`
+        let operators = [
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ];
+        match operators.into_iter().find(|(token, _)| self.eat(token)) {
+            Some((_, op)) => Ok(Expr::Binary(op, Box::new(expr), Box::new(self.sum()?))),
+            None => Ok(expr),
+        }
+    }
+
+    fn sum(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some('+') => BinaryOp::Add,
+                Some('-') => BinaryOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some('*') => BinaryOp::Mul,
+                Some('/') => BinaryOp::Div,
+                Some('%') => BinaryOp::Rem,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> std::result::Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        // `!=` never starts an operand, so `!` here is a negation
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> std::result::Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.or()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                text.parse()
+                    .map(|n| Expr::Literal(Scalar::Number(n)))
+                    .map_err(|_| format!("invalid number '{text}'"))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if self.peek() == Some('(') {
+                    return self.call(name);
+                }
+                match name {
+                    "true" => Ok(Expr::Literal(Scalar::Bool(true))),
+                    "false" => Ok(Expr::Literal(Scalar::Bool(false))),
+                    _ => self
+                        .inputs
+                        .iter()
+                        .position(|input| input == name)
+                        .map(Expr::Input)
+                        .ok_or_else(|| format!("unknown input '{name}'")),
+                }
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> std::result::Result<Expr, String> {
+        let &(name, arity) = FUNCTIONS
+            .iter()
+            .find(|(function, _)| *function == name)
+            .ok_or_else(|| format!("unknown function '{name}'"))?;
+        self.expect('(')?;
+        let mut args = vec![self.or()?];
+        while self.peek() == Some(',') {
+            self.chars.next();
+            args.push(self.or()?);
+        }
+        self.expect(')')?;
+        if args.len() != arity {
+            return Err(format!("{name} takes {arity} argument(s)"));
+        }
+        Ok(Expr::Call(name, args))
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let start = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        while self.chars.next_if(|&(_, c)| accept(c)).is_some() {}
+        let end = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        &self.source[start..end]
+    }
+
+    fn expect(&mut self, expected: char) -> std::result::Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected '{expected}', found '{c}'")),
+            None => Err(format!("expected '{expected}'")),
+        }
+    }
+}
+
+// ============================================================================
+// EXPRESSION INPUTS
+// ============================================================================
+
+/// Named inputs of a block evaluating expressions, and their values in the
+/// current scan
+///
+/// Also used by the SEQUENCER block, whose conditions are CALC expressions.
+pub(super) struct ExprInputs {
+    /// Input names, sorted, in the order parsed expressions refer to them
+    names: Vec<String>,
+    /// Signal of each input name
+    signals: Vec<String>,
+    /// Values of the current scan, kept to avoid an allocation per scan
+    values: Vec<Scalar>,
+}
+
+impl ExprInputs {
+    /// Every input of `config`
+    pub(super) fn new(config: &BlockConfig) -> Self {
+        let mut mapped: Vec<(&String, &String)> = config.inputs.iter().collect();
+        mapped.sort();
+        let (names, signals): (Vec<String>, Vec<String>) =
+            mapped.into_iter().map(|(name, signal)| (name.clone(), signal.clone())).unzip();
+        Self {
+            values: vec![Scalar::Number(0.0); names.len()],
+            names,
+            signals,
+        }
+    }
+
+    /// Parse an expression over the inputs
+    pub(super) fn parse(&self, source: &str) -> std::result::Result<Expr, String> {
+        Parser::parse(source, &self.names)
+    }
+
+    /// Read the current values of the inputs
+    pub(super) fn read(&mut self, bus: &SignalBus, block_type: &str) -> Result<()> {
+        for ((signal, name), value) in self.signals.iter().zip(&self.names).zip(&mut self.values) {
+            *value = match bus.get_required(signal)? {
+                Value::Bool(b) => Scalar::Bool(b),
+                #[allow(clippy::cast_precision_loss)]
+                Value::Integer(i) => Scalar::Number(i as f64),
+                Value::Float(f) => Scalar::Number(f),
+                #[allow(unreachable_patterns)]
+                other => {
+                    return Err(PlcError::TypeMismatch {
+                        expected: format!("bool or number for {block_type} input '{name}'"),
+                        actual: other.type_name().to_string(),
+                    })
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Evaluate `expr` over the values last read
+    pub(super) fn eval(&self, expr: &Expr) -> Scalar {
+        expr.eval(&self.values)
+    }
+
+    pub(super) fn signals(&self) -> Vec<&str> {
+        self.signals.iter().map(String::as_str).collect()
+    }
+}
+
+// ============================================================================
+// CALC BLOCK
+// ============================================================================
+
+/// Expression over named inputs
+pub struct CalcBlock {
+    name: String,
+    inputs: ExprInputs,
+    output: String,
+    expr: Expr,
+    integer: bool,
+}
+
+impl Block for CalcBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        self.inputs.read(bus, "CALC")?;
+        let result = match self.inputs.eval(&self.expr) {
+            Scalar::Bool(b) => Value::Bool(b),
+            Scalar::Number(n) if !n.is_finite() => {
+                return Err(PlcError::Runtime(format!("CALC block '{}' result {n} is not finite", self.name)));
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            Scalar::Number(n) if self.integer => Value::Integer(n.round() as i64),
+            Scalar::Number(n) => Value::Float(n),
+        };
+        bus.set(&self.output, result)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "CALC"
+    }
+
+    fn category(&self) -> &'static str {
+        "math"
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        self.inputs.signals()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+/// Factory function for CALC blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the expression is missing or does not
+/// parse, names an input the block does not map, or without an `out`
+/// output.
+pub fn create_calc_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let expression = get_string_parameter(config, "expression", None)?;
+    let output = get_output_signal(config, "out", true)?.unwrap_or_default();
+    let inputs = ExprInputs::new(config);
+    let expr = inputs
+        .parse(&expression)
+        .map_err(|e| PlcError::Config(format!("CALC block '{}' invalid expression '{expression}': {e}", config.name)))?;
+
+    Ok(Box::new(CalcBlock {
+        name: config.name.clone(),
+        inputs,
+        output,
+        expr,
+        integer: get_bool_parameter(config, "integer", Some(false))?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(inputs: &str, expression: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: calc, type: CALC, inputs: {{ {inputs} }}, outputs: {{ out: result }}, params: {{ expression: '{expression}' }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    #[test]
+    fn test_calc_evaluates_expression() {
+        let bus = SignalBus::new();
+        bus.set("oven.temp_c", Value::Float(90.0)).unwrap();
+        bus.set("oven.limit_f", Value::Integer(190)).unwrap();
+        bus.set("oven.enabled", Value::Bool(true)).unwrap();
+
+        let mut overtemp = calc(
+            "a: oven.temp_c, limit: oven.limit_f, enable: oven.enabled",
+            "(a*9/5)+32 > limit && enable",
+        );
+        overtemp.execute(&bus).unwrap();
+        assert_eq!(bus.get("result"), Some(Value::Bool(true)));
+        bus.set("oven.enabled", Value::Bool(false)).unwrap();
+        overtemp.execute(&bus).unwrap();
+        assert_eq!(bus.get("result"), Some(Value::Bool(false)));
+
+        let mut mixed = calc("a: oven.temp_c, b: oven.limit_f", "if(a >= 100 || !(b != 190), max(a, 5) % 7, -1) + 0.5");
+        mixed.execute(&bus).unwrap();
+        assert_eq!(bus.get("result"), Some(Value::Float(6.5)));
+
+        let mut division = calc("a: oven.temp_c, b: oven.zero", "a / b");
+        bus.set("oven.zero", Value::Integer(0)).unwrap();
+        assert!(division.execute(&bus).is_err());
+    }
+
+    #[test]
+    fn test_calc_rejects_invalid_expressions() {
+        for expression in ["a +", "a > > b", "unknown * 2", "min(a)", "nope(a)", "a b", "(a"] {
+            let config: BlockConfig = serde_yaml::from_str(&format!(
+                "{{ name: calc, type: CALC, inputs: {{ a: x, b: y }}, outputs: {{ out: z }}, params: {{ expression: '{expression}' }} }}"
+            ))
+            .unwrap();
+            assert!(super::super::create_block(&config).is_err(), "{expression}");
+        }
+    }
+}
@@ -0,0 +1,358 @@
+// src/blocks/composite.rs - User-defined composite blocks for PETRA
+//
+// Purpose:
+// --------
+// Lets a configuration define its own block types out of existing blocks,
+// such as a motor starter or a valve with feedback, and use them in
+// `blocks` like any built-in type. Each use is expanded into the internal
+// signals and blocks of the composite when the configuration is loaded, so
+// the engine only ever sees built-in blocks.
+//
+// Interactions:
+// -------------
+// - Uses: InstanceConfig from config.rs for `./` relative names, and
+//   get_available_block_types from blocks/mod.rs
+// - Used by: Config::expand in config.rs
+//
+// Configuration:
+// --------------
+//   composite_blocks:
+//     - name: MOTOR_STARTER
+//       inputs: [start, stop]
+//       outputs: [running]
+//       params: { start_delay_ms: 2000 }   # default, or ~ for a required parameter
+//       signals:
+//         - { name: ./requested, type: bool }
+//       blocks:
+//         - name: ./latch
+//           type: SR_LATCH
+//           inputs: { set: $start, reset: $stop }
+//           outputs: { q: ./requested }
+//         - name: ./start_delay
+//           type: ON_DELAY
+//           inputs: { in: ./requested }
+//           outputs: { out: $running }
+//           params: { preset_ms: $start_delay_ms }
+//
+//   blocks:
+//     - name: pump1
+//       type: MOTOR_STARTER
+//       inputs: { start: hmi.pump1_start, stop: hmi.pump1_stop }
+//       outputs: { running: pump1.run }
+//       params: { start_delay_ms: 5000 }
+//
+// Inside a composite, `./` names are relative to the name of each use, as in
+// templates, so the example defines `pump1.requested` and the blocks
+// `pump1.latch` and `pump1.start_delay`. An input or output that is exactly
+// `$port` is connected to the signal mapped to that port, and a parameter
+// that is exactly `$param` takes the value given by the use or the default.
+// Every port must be mapped. Composites may use other composites; a
+// disabled use disables its blocks, and its task applies to the blocks
+// without one.
+
+use crate::config::{BlockConfig, InstanceConfig, SignalConfig};
+use crate::error::{PlcError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::debug;
+
+/// Levels of composites used inside composites before expansion gives up,
+/// which is how a composite that uses itself is reported
+const MAX_NESTING: usize = 8;
+
+/// A block type made of other blocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct CompositeBlockConfig {
+    /// Block type the composite is used as
+    pub name: String,
+
+    /// What the composite does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Input ports, referenced inside as `$port`
+    #[serde(default)]
+    pub inputs: Vec<String>,
+
+    /// Output ports, referenced inside as `$port`
+    #[serde(default)]
+    pub outputs: Vec<String>,
+
+    /// Parameters with their default values, referenced inside as `$param`;
+    /// a null default makes the parameter required
+    #[serde(default)]
+    pub params: BTreeMap<String, serde_yaml::Value>,
+
+    /// Signals of every use
+    #[serde(default)]
+    pub signals: Vec<SignalConfig>,
+
+    /// Blocks of every use
+    #[serde(default)]
+    pub blocks: Vec<BlockConfig>,
+}
+
+impl CompositeBlockConfig {
+    fn error(&self, message: &str) -> PlcError {
+        PlcError::Config(format!("Composite block '{}' {}", self.name, message))
+    }
+
+    /// Check the definition itself, independent of its uses
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(PlcError::Config("Composite block name cannot be empty".to_string()));
+        }
+        if super::get_available_block_types().contains(&self.name.as_str()) {
+            return Err(self.error("has the name of a built-in block type"));
+        }
+        if self.blocks.is_empty() {
+            return Err(self.error("has no blocks"));
+        }
+        let mut ports = HashSet::new();
+        for port in self.inputs.iter().chain(&self.outputs) {
+            if port.is_empty() || !ports.insert(port.as_str()) {
+                return Err(self.error(&format!("has an empty or duplicate port '{port}'")));
+            }
+        }
+        for block in &self.blocks {
+            for signal in block.inputs.values().chain(block.outputs.values()) {
+                if let Some(port) = signal.strip_prefix('$') {
+                    if !ports.contains(port) {
+                        return Err(self.error(&format!("block '{}' uses undeclared port '{port}'", block.name)));
+                    }
+                }
+            }
+            for value in block.params.values() {
+                if let Some(param) = value.as_str().and_then(|v| v.strip_prefix('$')) {
+                    if !self.params.contains_key(param) {
+                        return Err(self.error(&format!("block '{}' uses undeclared parameter '{param}'", block.name)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Signals and blocks of one use
+    fn instantiate(&self, instance: &BlockConfig) -> Result<(Vec<SignalConfig>, Vec<BlockConfig>)> {
+        let error = |message: String| {
+            PlcError::Config(format!("Block '{}' of composite type '{}' {}", instance.name, self.name, message))
+        };
+        if instance.name.is_empty() {
+            return Err(error("has an empty name".to_string()));
+        }
+        let ports = [(&self.inputs, &instance.inputs, "input"), (&self.outputs, &instance.outputs, "output")];
+        for (declared, mapped, kind) in ports {
+            if let Some(port) = mapped.keys().find(|port| !declared.contains(port)) {
+                return Err(error(format!("maps unknown {kind} '{port}'")));
+            }
+            if let Some(port) = declared.iter().find(|port| !mapped.contains_key(*port)) {
+                return Err(error(format!("does not map {kind} '{port}'")));
+            }
+        }
+        if let Some(param) = instance.params.keys().find(|param| !self.params.contains_key(*param)) {
+            return Err(error(format!("sets unknown parameter '{param}'")));
+        }
+
+        let namespace = InstanceConfig { template: self.name.clone(), namespace: instance.name.clone() };
+        let params = self
+            .params
+            .iter()
+            .map(|(param, default)| {
+                let value = match (instance.params.get(param), default) {
+                    (Some(value), _) => value.clone(),
+                    (None, serde_yaml::Value::Null) => return Err(error(format!("requires parameter '{param}'"))),
+                    (None, serde_yaml::Value::String(name)) => serde_yaml::Value::String(namespace.qualify(name)),
+                    (None, default) => default.clone(),
+                };
+                Ok((param.as_str(), value))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let signals = self.signals.iter().map(|signal| namespace.signal(signal)).collect();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|internal| {
+                let mut block = namespace.block(internal);
+                for signal in block.inputs.values_mut().chain(block.outputs.values_mut()) {
+                    if let Some(port) = signal.strip_prefix('$') {
+                        if let Some(mapped) = instance.inputs.get(port).or_else(|| instance.outputs.get(port)) {
+                            signal.clone_from(mapped);
+                        }
+                    }
+                }
+                for value in block.params.values_mut() {
+                    if let Some(param) = value.as_str().and_then(|v| v.strip_prefix('$')) {
+                        if let Some(resolved) = params.get(param) {
+                            *value = resolved.clone();
+                        }
+                    }
+                }
+                block.enabled &= instance.enabled;
+                if block.task.is_none() {
+                    block.task.clone_from(&instance.task);
+                }
+                block
+            })
+            .collect();
+        Ok((signals, blocks))
+    }
+}
+
+/// Replace every use of a composite in `blocks` by its signals and blocks
+///
+/// The internal blocks take the place of the use, and the internal signals
+/// are appended to `signals`.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] for an invalid or duplicate composite, a
+/// use that does not map exactly the declared ports or sets an unknown
+/// parameter, or composites nested deeper than 8 levels.
+pub fn expand(
+    composites: &[CompositeBlockConfig],
+    signals: &mut Vec<SignalConfig>,
+    blocks: &mut Vec<BlockConfig>,
+) -> Result<()> {
+    let mut by_name = HashMap::new();
+    for composite in composites {
+        composite.validate()?;
+        if by_name.insert(composite.name.as_str(), composite).is_some() {
+            return Err(PlcError::Config(format!("Duplicate composite block name: '{}'", composite.name)));
+        }
+    }
+
+    for _ in 0..=MAX_NESTING {
+        if !blocks.iter().any(|block| by_name.contains_key(block.block_type.as_str())) {
+            return Ok(());
+        }
+        let mut expanded = Vec::with_capacity(blocks.len());
+        for block in blocks.drain(..) {
+            match by_name.get(block.block_type.as_str()) {
+                Some(composite) => {
+                    let (internal_signals, internal_blocks) = composite.instantiate(&block)?;
+                    debug!(
+                        "Expanded composite block '{}' of type '{}': {} signals, {} blocks",
+                        block.name,
+                        composite.name,
+                        internal_signals.len(),
+                        internal_blocks.len()
+                    );
+                    signals.extend(internal_signals);
+                    expanded.extend(internal_blocks);
+                }
+                None => expanded.push(block),
+            }
+        }
+        *blocks = expanded;
+    }
+    Err(PlcError::Config(format!(
+        "Composite blocks are nested more than {MAX_NESTING} levels deep, check for a composite that uses itself"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+
+    const MOTOR_STARTER: &str = r"
+composite_blocks:
+  - name: MOTOR_STARTER
+    inputs: [start, stop]
+    outputs: [running]
+    params: { start_delay_ms: 2000, limit: ~ }
+    signals:
+      - { name: ./requested, type: bool }
+    blocks:
+      - name: ./request
+        type: AND
+        inputs: { a: $start, b: $stop }
+        outputs: { out: ./requested }
+      - name: ./start_delay
+        type: ON_DELAY
+        inputs: { in: ./requested }
+        outputs: { out: $running }
+        params: { preset_ms: $start_delay_ms, limit: $limit }
+  - name: MOTOR_PAIR
+    inputs: [start]
+    outputs: [a_running, b_running]
+    blocks:
+      - { name: ./a, type: MOTOR_STARTER, inputs: { start: $start, stop: plant.stop },
+          outputs: { running: $a_running }, params: { limit: 1 } }
+      - { name: ./b, type: MOTOR_STARTER, inputs: { start: $start, stop: plant.stop },
+          outputs: { running: $b_running }, params: { limit: 2, start_delay_ms: 500 } }
+";
+
+    fn config(blocks: &str) -> Config {
+        serde_yaml::from_str(&format!(
+            "signals:\n  - {{ name: plant.stop, type: bool }}\n{MOTOR_STARTER}blocks:\n{blocks}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_composites_expand_with_ports_and_params() {
+        let mut config = config(
+            r"
+  - { name: pump1, type: MOTOR_STARTER, inputs: { start: hmi.start, stop: hmi.stop },
+      outputs: { running: pump1.run }, params: { limit: 5 }, task: slow }
+  - { name: fans, type: MOTOR_PAIR, inputs: { start: hmi.fans },
+      outputs: { a_running: fan_a.run, b_running: fan_b.run }, enabled: false }
+",
+        );
+        config.expand().unwrap();
+        assert!(config.composite_blocks.is_empty());
+
+        let signals: Vec<&str> = config.signals.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(signals, ["plant.stop", "pump1.requested", "fans.a.requested", "fans.b.requested"]);
+        let names: Vec<&str> = config.blocks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["pump1.request", "pump1.start_delay", "fans.a.request", "fans.a.start_delay", "fans.b.request", "fans.b.start_delay"]
+        );
+
+        let request = &config.blocks[0];
+        assert_eq!(request.inputs["a"], "hmi.start");
+        assert_eq!(request.outputs["out"], "pump1.requested");
+        assert_eq!(request.task.as_deref(), Some("slow"));
+        let delay = &config.blocks[1];
+        assert_eq!(delay.outputs["out"], "pump1.run");
+        assert_eq!(delay.params["preset_ms"], serde_yaml::Value::from(2000));
+        assert_eq!(delay.params["limit"], serde_yaml::Value::from(5));
+
+        let fan_b = &config.blocks[5];
+        assert_eq!(fan_b.inputs["in"], "fans.b.requested");
+        assert_eq!(fan_b.outputs["out"], "fan_b.run");
+        assert_eq!(fan_b.params["preset_ms"], serde_yaml::Value::from(500));
+        assert!(!fan_b.enabled);
+        assert_eq!(config.blocks[2].inputs["a"], "hmi.fans");
+    }
+
+    #[test]
+    fn test_invalid_composites_and_uses_are_rejected() {
+        let use_with = |mapping: &str| {
+            config(&format!("  - {{ name: pump1, type: MOTOR_STARTER, {mapping} }}\n")).expand()
+        };
+        let ports = "inputs: { start: a, stop: b }, outputs: { running: c }";
+        assert!(use_with(&format!("{ports}, params: {{ limit: 1 }}")).is_ok());
+        // Required parameter, unmapped or unknown ports, unknown parameter
+        assert!(use_with(ports).is_err());
+        assert!(use_with("inputs: { start: a }, outputs: { running: c }, params: { limit: 1 }").is_err());
+        assert!(use_with("inputs: { start: a, stop: b }, outputs: { running: c, fault: d }, params: { limit: 1 }").is_err());
+        assert!(use_with(&format!("{ports}, params: {{ limit: 1, speed: 3 }}")).is_err());
+
+        let mut recursive = config("  - { name: loop1, type: LOOP }\n");
+        recursive.composite_blocks.push(
+            serde_yaml::from_str("{ name: LOOP, blocks: [{ name: ./inner, type: LOOP }] }").unwrap(),
+        );
+        assert!(recursive.expand().is_err());
+
+        let mut shadowing = config("  []\n");
+        shadowing.composite_blocks.push(
+            serde_yaml::from_str("{ name: AND, blocks: [{ name: ./inner, type: OR }] }").unwrap(),
+        );
+        assert!(shadowing.expand().is_err());
+    }
+}
@@ -1,7 +1,8 @@
 // src/blocks/data.rs - Data manipulation and utility blocks
-use super::{Block, BlockConfig, get_numeric_parameter, get_primary_input, get_primary_output};
+use super::{Block, BlockConfig, get_input_signal, get_numeric_parameter, get_output_signal, get_primary_input, get_primary_output};
 use crate::{error::{PlcError, Result}, signal::SignalBus, value::Value};
 use rand::Rng;
+use std::time::Instant;
 
 // ============================================================================
 // SCALE BLOCK
@@ -117,6 +118,108 @@ pub fn create_limit_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
     }))
 }
 
+// ============================================================================
+// RATE LIMIT BLOCK
+// ============================================================================
+
+/// Slew-rate limiter - the output follows the input at a bounded rate
+///
+/// Rising and falling rates are in units per second and apply to increasing
+/// and decreasing output values respectively. The output starts at the
+/// `initial` parameter, or at the input on the first scan without one.
+pub struct RateLimitBlock {
+    name: String,
+    input: String,
+    output: String,
+    active_output: Option<String>,
+    rising_rate: f64,
+    falling_rate: f64,
+    initial: Option<f64>,
+    value: Option<f64>,
+    /// Bus time of the previous scan
+    last_scan: Option<Instant>,
+}
+
+impl Block for RateLimitBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let target = bus.get_float(&self.input)?;
+        let now = bus.now();
+        let value = match (self.value, self.last_scan) {
+            (Some(last), Some(time)) => {
+                let dt = now.saturating_duration_since(time).as_secs_f64();
+                last + (target - last).clamp(-self.falling_rate * dt, self.rising_rate * dt)
+            }
+            (Some(initial), None) => initial,
+            (None, _) => target,
+        };
+        self.value = Some(value);
+        self.last_scan = Some(now);
+
+        bus.set(&self.output, Value::Float(value))?;
+        if let Some(active) = &self.active_output {
+            #[allow(clippy::float_cmp)] // exact when the target was reached
+            bus.set(active, Value::Bool(value != target))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "RATE_LIMIT"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.value = self.initial;
+        self.last_scan = None;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        vec![self.input.as_str()]
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        std::iter::once(self.output.as_str()).chain(self.active_output.as_deref()).collect()
+    }
+}
+
+/// Factory function for `RATE_LIMIT` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input, `out` output or
+/// `rising_rate` parameter, or with a rate that is not greater than 0.
+pub fn create_rate_limit_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let rising_rate: f64 = get_numeric_parameter(config, "rising_rate", None)?;
+    let falling_rate = get_numeric_parameter(config, "falling_rate", Some(rising_rate))?;
+    if !(rising_rate > 0.0 && falling_rate > 0.0) {
+        return Err(PlcError::Config(format!(
+            "RATE_LIMIT block '{}' rising_rate and falling_rate must be greater than 0",
+            config.name
+        )));
+    }
+    let initial: Option<f64> = if config.params.contains_key("initial") {
+        Some(get_numeric_parameter(config, "initial", None)?)
+    } else {
+        None
+    };
+
+    Ok(Box::new(RateLimitBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+        active_output: get_output_signal(config, "active", false)?,
+        rising_rate,
+        falling_rate,
+        initial,
+        value: initial,
+        last_scan: None,
+    }))
+}
+
 // ============================================================================
 // SELECT BLOCK
 // ============================================================================
@@ -418,6 +521,7 @@ mod tests {
             params: HashMap::new(),
             description: None,
             tags: vec![],
+            ..Default::default()
         }
     }
     
@@ -461,6 +565,39 @@ mod tests {
         assert_eq!(result, 100.0); // Limited to max value
     }
     
+    #[test]
+    fn test_rate_limit_block() {
+        let clock = std::sync::Arc::new(crate::clock::SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("speed_ref", Value::Float(50.0)).unwrap();
+
+        let mut config = create_test_config("RATE_LIMIT", "ramp");
+        config.inputs.insert("in".to_string(), "speed_ref".to_string());
+        config.outputs.insert("out".to_string(), "speed_cmd".to_string());
+        config.outputs.insert("active".to_string(), "ramping".to_string());
+        config.params.insert("rising_rate".to_string(), serde_yaml::Value::Number(serde_yaml::Number::from(10)));
+        config.params.insert("falling_rate".to_string(), serde_yaml::Value::Number(serde_yaml::Number::from(20)));
+        config.params.insert("initial".to_string(), serde_yaml::Value::Number(serde_yaml::Number::from(0)));
+
+        let mut block = create_rate_limit_block(&config).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_float("speed_cmd").unwrap(), 0.0);
+        clock.advance(std::time::Duration::from_secs(2));
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_float("speed_cmd").unwrap(), 20.0);
+        assert!(bus.get_bool("ramping").unwrap());
+
+        // Falling is faster, and the output stops at the target
+        bus.set("speed_ref", Value::Float(15.0)).unwrap();
+        clock.advance(std::time::Duration::from_secs(1));
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_float("speed_cmd").unwrap(), 15.0);
+        assert!(!bus.get_bool("ramping").unwrap());
+
+        config.params.insert("rising_rate".to_string(), serde_yaml::Value::Number(serde_yaml::Number::from(0)));
+        assert!(create_rate_limit_block(&config).is_err());
+    }
+
     #[test]
     fn test_data_generator_sine() {
         let bus = SignalBus::new();
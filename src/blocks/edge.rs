@@ -1,5 +1,6 @@
 // src/blocks/edge.rs - Edge detection block implementations
 use super::{Block, BlockConfig};
+use super::persistence::{decode_state, encode_state};
 use crate::{error::{PlcError, Result}, signal::SignalBus, value::Value};
 use std::collections::HashMap;
 
@@ -35,6 +36,17 @@ impl Block for RisingEdgeBlock {
         self.last_value = false;
         Ok(())
     }
+
+    // The last input is retained so the first scan after a restart does not
+    // report an edge that never happened
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.last_value)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.last_value = decode_state(&self.name, state)?;
+        Ok(())
+    }
 }
 
 pub fn create_rising_edge_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -90,6 +102,15 @@ impl Block for FallingEdgeBlock {
         self.last_value = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.last_value)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.last_value = decode_state(&self.name, state)?;
+        Ok(())
+    }
 }
 
 pub fn create_falling_edge_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -161,6 +182,15 @@ impl Block for EdgeDetectBlock {
         self.last_value = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.last_value)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.last_value = decode_state(&self.name, state)?;
+        Ok(())
+    }
 }
 
 pub fn create_edge_detect_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -0,0 +1,222 @@
+// src/blocks/format.rs - String formatting block for PETRA
+//
+// Purpose:
+// --------
+// Implements FORMAT, which builds a string signal from a template and any
+// number of inputs, such as an MQTT payload or a line of text for an operator
+// display, without custom code.
+//
+// Interactions:
+// -------------
+// - Uses: Value::as_string from value.rs and parameter helpers from
+//   blocks/mod.rs
+// - Used by: blocks/mod.rs factory (extended-types feature)
+// - Reads: every input named in the template
+// - Writes: the formatted string to `out`
+//
+// Configuration:
+// --------------
+//   - name: tank1_text
+//     type: FORMAT
+//     inputs: { a: tank1.name, b: tank1.level_pct }
+//     outputs: { out: display.tank1 }
+//     params:
+//       template: "Tank {a}: {b:.1} %"
+//
+// A placeholder is the name of an input, optionally followed by `:.N` to
+// write a number with N decimals. `{{` and `}}` write literal braces. The
+// template is checked when the block is created, so a placeholder without a
+// matching input is a configuration error.
+
+use super::{get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use std::fmt::Write;
+
+/// Part of a parsed template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Input { signal: String, precision: Option<usize> },
+}
+
+/// Split a template into literal text and input placeholders
+fn parse_template(config: &BlockConfig, template: &str) -> Result<Vec<Segment>> {
+    let error = |message: String| PlcError::Config(format!("FORMAT block '{}' {}", config.name, message));
+
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(error("template has an unclosed '{'".to_string())),
+                    }
+                }
+                let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder.as_str(), ""));
+                let name = name.trim();
+                let signal = config
+                    .inputs
+                    .get(name)
+                    .ok_or_else(|| error(format!("template placeholder '{{{placeholder}}}' has no matching input")))?;
+                let precision = match spec.trim() {
+                    "" => None,
+                    spec => Some(
+                        spec.strip_prefix('.')
+                            .and_then(|digits| digits.parse().ok())
+                            .ok_or_else(|| error(format!("unsupported format '{spec}' for '{name}', expected .N")))?,
+                    ),
+                };
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Input { signal: signal.clone(), precision });
+            }
+            '}' => return Err(error("template has an unmatched '}', write '}}' for a literal brace".to_string())),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// String built from a template and input signals
+pub struct FormatBlock {
+    name: String,
+    segments: Vec<Segment>,
+    output: String,
+}
+
+impl Block for FormatBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let mut formatted = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => formatted.push_str(text),
+                Segment::Input { signal, precision } => {
+                    let value = bus.get_required(signal)?;
+                    match (precision, &value) {
+                        (Some(precision), Value::Float(f)) => write!(formatted, "{f:.precision$}"),
+                        #[allow(clippy::cast_precision_loss)]
+                        (Some(precision), Value::Integer(i)) => write!(formatted, "{:.precision$}", *i as f64),
+                        _ => write!(formatted, "{value}"),
+                    }
+                    .map_err(|e| PlcError::Runtime(format!("FORMAT block '{}': {e}", self.name)))?;
+                }
+            }
+        }
+        bus.set(&self.output, Value::String(formatted))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "FORMAT"
+    }
+
+    fn category(&self) -> &'static str {
+        "data"
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        let mut inputs: Vec<&str> = self
+            .segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Input { signal, .. } => Some(signal.as_str()),
+                Segment::Text(_) => None,
+            })
+            .collect();
+        inputs.sort_unstable();
+        inputs.dedup();
+        inputs
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+/// Factory function for `FORMAT` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without a `template` parameter or `out`
+/// output, or with a template that has a placeholder without a matching
+/// input, an unsupported format or an unmatched brace.
+pub fn create_format_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let template = get_string_parameter(config, "template", None)?;
+    Ok(Box::new(FormatBlock {
+        name: config.name.clone(),
+        segments: parse_template(config, &template)?,
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_block(inputs: &str, template: &str) -> Result<Box<dyn Block>> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: text, type: FORMAT, inputs: {{ {inputs} }}, outputs: {{ out: text }}, \
+             params: {{ template: '{template}' }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config)
+    }
+
+    #[test]
+    fn test_formats_inputs_into_template() {
+        let bus = SignalBus::new();
+        bus.set("tank.name", Value::String("T-101".to_string())).unwrap();
+        bus.set("tank.level", Value::Float(42.57)).unwrap();
+        bus.set("tank.count", Value::Integer(3)).unwrap();
+        bus.set("tank.high", Value::Bool(false)).unwrap();
+
+        let mut block = format_block(
+            "a: tank.name, b: tank.level, c: tank.count, d: tank.high",
+            "{{\"tank\": \"{a}\"}} Tank {a}: {b:.1} % ({c:.2}, {c}) high={d}",
+        )
+        .unwrap();
+        assert_eq!(block.input_dependencies(), vec!["tank.count", "tank.high", "tank.level", "tank.name"]);
+        block.execute(&bus).unwrap();
+        assert_eq!(
+            bus.get("text"),
+            Some(Value::String("{\"tank\": \"T-101\"} Tank T-101: 42.6 % (3.00, 3) high=false".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_templates() {
+        assert!(format_block("a: x", "{b}").is_err());
+        assert!(format_block("a: x", "{a:>5}").is_err());
+        assert!(format_block("a: x", "{a} }").is_err());
+        assert!(format_block("a: x", "{a").is_err());
+        assert!(format_block("a: x", "{{a}}").is_ok());
+
+        let bus = SignalBus::new();
+        let mut block = format_block("a: missing", "{a}").unwrap();
+        assert!(block.execute(&bus).is_err());
+    }
+}
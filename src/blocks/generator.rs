@@ -0,0 +1,334 @@
+// src/blocks/generator.rs - Signal generator blocks for PETRA
+//
+// Purpose:
+// --------
+// Implements SINE_GENERATOR, SQUARE_GENERATOR, RAMP_GENERATOR and
+// NOISE_GENERATOR, which write a test signal for demos, simulation configs
+// and burn-in tests. Unlike DATA_GENERATOR, which assumes a 100 ms scan, the
+// waveforms follow the bus clock, so they keep their frequency at any scan
+// rate and step deterministically under a simulated clock.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: nothing; the signal depends only on the time since the first scan
+// - Writes: the signal to `out`
+//
+// Configuration:
+// --------------
+//   - name: demo_level
+//     type: SINE_GENERATOR
+//     outputs: { out: demo.level }
+//     params:
+//       frequency: 0.01   # Hz, one cycle every 100 s
+//       amplitude: 20     # swings 20 above and below the offset
+//       offset: 50
+//       phase_deg: 90     # start at the top of the wave
+//       noise: 0.5        # standard deviation of added gaussian noise
+//       seed: 42          # repeatable noise; unseeded noise differs every run
+//
+// SQUARE_GENERATOR takes a `duty_cycle` (fraction of the period spent high,
+// default 0.5) and RAMP_GENERATOR a `shape` (`sawtooth`, the default, or
+// `triangle`). Every waveform moves between offset - amplitude and
+// offset + amplitude. NOISE_GENERATOR writes offset plus noise of the given
+// `distribution`: `gaussian` (default) with `amplitude` as standard deviation,
+// or `uniform` within offset ± amplitude.
+
+use super::{get_numeric_parameter, get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::TAU;
+use std::time::Instant;
+
+/// Signal written by a generator block
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Waveform {
+    Sine,
+    Square { duty_cycle: f64 },
+    Sawtooth,
+    Triangle,
+    Gaussian,
+    Uniform,
+}
+
+impl Waveform {
+    const fn is_periodic(self) -> bool {
+        !matches!(self, Self::Gaussian | Self::Uniform)
+    }
+}
+
+/// Sample of a standard normal distribution (Box-Muller transform)
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Waveform generator driven by the bus clock
+pub struct SignalGeneratorBlock {
+    name: String,
+    block_type: &'static str,
+    output: String,
+    waveform: Waveform,
+    amplitude: f64,
+    offset: f64,
+    frequency: f64,
+    /// Phase at the first scan, as a fraction of a period
+    phase: f64,
+    noise: f64,
+    seed: Option<u64>,
+    rng: StdRng,
+    /// Bus time of the first scan
+    started: Option<Instant>,
+}
+
+impl SignalGeneratorBlock {
+    fn seeded(seed: Option<u64>) -> StdRng {
+        seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+    }
+
+    /// Unit wave in [-1, 1] at `phase` in [0, 1)
+    fn sample(&mut self, phase: f64) -> f64 {
+        match self.waveform {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square { duty_cycle } => {
+                if phase < duty_cycle {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Gaussian => gaussian(&mut self.rng),
+            Waveform::Uniform => self.rng.gen_range(-1.0..=1.0),
+        }
+    }
+}
+
+impl Block for SignalGeneratorBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let now = bus.now();
+        let started = *self.started.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(started).as_secs_f64();
+        let phase = (elapsed * self.frequency + self.phase).fract();
+
+        let mut value = self.offset + self.amplitude * self.sample(phase);
+        if self.noise > 0.0 {
+            value += self.noise * gaussian(&mut self.rng);
+        }
+        bus.set(&self.output, Value::Float(value))?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        self.block_type
+    }
+
+    fn category(&self) -> &'static str {
+        "simulation"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.started = None;
+        self.rng = Self::seeded(self.seed);
+        Ok(())
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+fn create_generator_block(
+    config: &BlockConfig,
+    block_type: &'static str,
+    waveform: Waveform,
+) -> Result<Box<dyn Block>> {
+    let error = |message: &str| PlcError::Config(format!("{} block '{}' {}", block_type, config.name, message));
+
+    let amplitude: f64 = get_numeric_parameter(config, "amplitude", Some(1.0))?;
+    let offset: f64 = get_numeric_parameter(config, "offset", Some(0.0))?;
+    let noise: f64 = get_numeric_parameter(config, "noise", Some(0.0))?;
+    if !(amplitude.is_finite() && amplitude >= 0.0) {
+        return Err(error("amplitude cannot be negative"));
+    }
+    if !(noise.is_finite() && noise >= 0.0) {
+        return Err(error("noise cannot be negative"));
+    }
+    let (frequency, phase) = if waveform.is_periodic() {
+        let frequency: f64 = get_numeric_parameter(config, "frequency", Some(0.1))?;
+        if !(frequency.is_finite() && frequency > 0.0) {
+            return Err(error("frequency must be greater than 0"));
+        }
+        let phase_deg: f64 = get_numeric_parameter(config, "phase_deg", Some(0.0))?;
+        (frequency, (phase_deg / 360.0).rem_euclid(1.0))
+    } else {
+        (0.0, 0.0)
+    };
+    let seed = if config.params.contains_key("seed") {
+        Some(get_numeric_parameter::<u64>(config, "seed", None)?)
+    } else {
+        None
+    };
+
+    Ok(Box::new(SignalGeneratorBlock {
+        name: config.name.clone(),
+        block_type,
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+        waveform,
+        amplitude,
+        offset,
+        frequency,
+        phase,
+        noise,
+        seed,
+        rng: SignalGeneratorBlock::seeded(seed),
+        started: None,
+    }))
+}
+
+/// Factory function for `SINE_GENERATOR` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `out` output, or with a negative
+/// amplitude or noise or a frequency that is not greater than 0.
+pub fn create_sine_generator_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    create_generator_block(config, "SINE_GENERATOR", Waveform::Sine)
+}
+
+/// Factory function for `SQUARE_GENERATOR` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] like [`create_sine_generator_block`], or with
+/// a `duty_cycle` outside 0 to 1.
+pub fn create_square_generator_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let duty_cycle: f64 = get_numeric_parameter(config, "duty_cycle", Some(0.5))?;
+    if !(0.0..=1.0).contains(&duty_cycle) {
+        return Err(PlcError::Config(format!(
+            "SQUARE_GENERATOR block '{}' duty_cycle must be between 0 and 1",
+            config.name
+        )));
+    }
+    create_generator_block(config, "SQUARE_GENERATOR", Waveform::Square { duty_cycle })
+}
+
+/// Factory function for `RAMP_GENERATOR` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] like [`create_sine_generator_block`], or with
+/// a `shape` other than `sawtooth` or `triangle`.
+pub fn create_ramp_generator_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let waveform = match get_string_parameter(config, "shape", Some("sawtooth"))?.as_str() {
+        "sawtooth" => Waveform::Sawtooth,
+        "triangle" => Waveform::Triangle,
+        other => {
+            return Err(PlcError::Config(format!(
+                "RAMP_GENERATOR block '{}' unknown shape '{}', expected sawtooth or triangle",
+                config.name, other
+            )))
+        }
+    };
+    create_generator_block(config, "RAMP_GENERATOR", waveform)
+}
+
+/// Factory function for `NOISE_GENERATOR` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `out` output, with a negative
+/// amplitude or noise, or with a `distribution` other than `gaussian` or
+/// `uniform`.
+pub fn create_noise_generator_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let waveform = match get_string_parameter(config, "distribution", Some("gaussian"))?.as_str() {
+        "gaussian" => Waveform::Gaussian,
+        "uniform" => Waveform::Uniform,
+        other => {
+            return Err(PlcError::Config(format!(
+                "NOISE_GENERATOR block '{}' unknown distribution '{}', expected gaussian or uniform",
+                config.name, other
+            )))
+        }
+    };
+    create_generator_block(config, "NOISE_GENERATOR", waveform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn generator(block_type: &str, params: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: gen, type: {block_type}, outputs: {{ out: out }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    fn samples(block: &mut Box<dyn Block>, step: Duration, count: usize) -> Vec<f64> {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        (0..count)
+            .map(|_| {
+                block.execute(&bus).unwrap();
+                clock.advance(step);
+                bus.get_float("out").unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_periodic_waveforms_follow_bus_clock() {
+        let step = Duration::from_millis(250);
+        let mut sine = generator("SINE_GENERATOR", "frequency: 1, amplitude: 2, offset: 10");
+        let values = samples(&mut sine, step, 4);
+        let expected = [10.0, 12.0, 10.0, 8.0];
+        assert!(values.iter().zip(expected).all(|(v, e)| (v - e).abs() < 1e-9), "{values:?}");
+
+        let mut square = generator("SQUARE_GENERATOR", "frequency: 1, duty_cycle: 0.25");
+        assert_eq!(samples(&mut square, step, 5), vec![1.0, -1.0, -1.0, -1.0, 1.0]);
+
+        let mut ramp = generator("RAMP_GENERATOR", "frequency: 0.5, shape: triangle, offset: 1");
+        let values = samples(&mut ramp, Duration::from_millis(500), 4);
+        assert_eq!(values, vec![0.0, 1.0, 2.0, 1.0]);
+
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: bad, type: SQUARE_GENERATOR, outputs: { out: out }, params: { frequency: 0 } }",
+        )
+        .unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+
+    #[test]
+    fn test_seeded_noise_repeats_after_reset() {
+        let step = Duration::from_millis(100);
+        let mut noise = generator("NOISE_GENERATOR", "distribution: uniform, amplitude: 0.5, offset: 5, seed: 7");
+        let first = samples(&mut noise, step, 50);
+        assert!(first.iter().all(|v| (4.5..=5.5).contains(v)));
+        assert!(first.windows(2).any(|w| w[0] != w[1]));
+
+        noise.reset().unwrap();
+        assert_eq!(samples(&mut noise, step, 50), first);
+
+        let mut noisy_sine = generator("SINE_GENERATOR", "frequency: 1, noise: 0.1, seed: 7");
+        let values = samples(&mut noisy_sine, Duration::from_secs(1), 20);
+        assert!(values.iter().all(|v| v.abs() < 1.0));
+        assert!(values.iter().any(|v| *v != 0.0));
+    }
+}
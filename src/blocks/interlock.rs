@@ -0,0 +1,487 @@
+// src/blocks/interlock.rs - Interlock / permissive matrix block for PETRA
+//
+// Purpose:
+// --------
+// Implements the INTERLOCK block, which evaluates a cause-and-effect matrix:
+// each cause is a permissive input, and each effect is released only while
+// none of the causes mapped to it are tripped. The first cause to trip is
+// recorded as the first-out so operators can see what started a cascade of
+// trips, and each cause has its own status output.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs
+// - Used by: blocks/mod.rs factory, `petra config interlocks` report command
+// - Reads: one boolean input per cause, optional `reset` input
+// - Writes: one boolean output per effect, `first_out` (1-based cause index,
+//   0 when healthy) and optional `status.<cause>` outputs
+//
+// Configuration:
+// --------------
+//   - name: pump1_interlock
+//     type: INTERLOCK
+//     inputs:
+//       suction_level: tank1.level_ok
+//       seal_water: pump1.seal_water_ok
+//       reset: pump1.interlock_reset
+//     outputs:
+//       pump1_start: pump1.start_permissive
+//       first_out: pump1.first_out
+//       status.seal_water: pump1.seal_water_tripped
+//     params:
+//       latch: true
+//       causes:
+//         - name: suction_level
+//           description: Suction tank level low
+//         - name: seal_water
+//           description: Seal water pressure lost
+//       effects:
+//         - name: pump1_start
+//           description: Pump 1 start permissive
+//           causes: [suction_level, seal_water]
+//
+// A cause trips when its input equals `trip_on` (default `false`, i.e. the
+// permissive is lost). With `latch: true` (the default) a tripped cause stays
+// tripped until the `reset` input is high and the cause has cleared.
+
+use super::persistence::{decode_state, encode_state};
+use super::{get_bool_parameter, get_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+/// Input name reserved for the latch reset
+const RESET_INPUT: &str = "reset";
+
+/// Output name for the first-out cause index
+const FIRST_OUT_OUTPUT: &str = "first_out";
+
+/// Prefix of per-cause status output names
+const STATUS_PREFIX: &str = "status.";
+
+/// One row of the cause-and-effect matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockCause {
+    /// Cause name, also the input name of its signal
+    pub name: String,
+    /// Text shown in the cause-and-effect report
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Input value that trips the cause
+    #[serde(default)]
+    pub trip_on: bool,
+}
+
+/// One column of the cause-and-effect matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockEffect {
+    /// Effect name, also the output name of its permissive signal
+    pub name: String,
+    /// Text shown in the cause-and-effect report
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Causes that block this effect when tripped
+    pub causes: Vec<String>,
+}
+
+struct CauseState {
+    input: String,
+    trip_on: bool,
+    status_output: Option<String>,
+    tripped: bool,
+}
+
+struct EffectState {
+    output: String,
+    causes: Vec<usize>,
+}
+
+/// Saved state of an INTERLOCK block; latched trips survive a restart
+#[derive(Serialize, Deserialize)]
+struct InterlockState {
+    tripped: Vec<String>,
+    first_out: Option<String>,
+}
+
+/// Interlock block evaluating a permissive matrix with first-out detection
+pub struct InterlockBlock {
+    name: String,
+    causes: Vec<InterlockCause>,
+    state: Vec<CauseState>,
+    effects: Vec<EffectState>,
+    reset_input: Option<String>,
+    first_out_output: Option<String>,
+    latch: bool,
+    first_out: Option<usize>,
+}
+
+impl InterlockBlock {
+    fn new(config: &BlockConfig) -> Result<Self> {
+        let causes: Vec<InterlockCause> = get_parameter(config, "causes", None)?;
+        let effects: Vec<InterlockEffect> = get_parameter(config, "effects", None)?;
+        let latch = get_bool_parameter(config, "latch", Some(true))?;
+        validate_matrix(&config.name, &causes, &effects)?;
+
+        let state = causes
+            .iter()
+            .map(|cause| {
+                let input = config.inputs.get(&cause.name).cloned().ok_or_else(|| {
+                    PlcError::Config(format!(
+                        "INTERLOCK block '{}' missing input for cause '{}'",
+                        config.name, cause.name
+                    ))
+                })?;
+                Ok(CauseState {
+                    input,
+                    trip_on: cause.trip_on,
+                    status_output: config
+                        .outputs
+                        .get(&format!("{STATUS_PREFIX}{}", cause.name))
+                        .cloned(),
+                    tripped: false,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let effects = effects
+            .iter()
+            .map(|effect| {
+                let output = config.outputs.get(&effect.name).cloned().ok_or_else(|| {
+                    PlcError::Config(format!(
+                        "INTERLOCK block '{}' missing output for effect '{}'",
+                        config.name, effect.name
+                    ))
+                })?;
+                let causes = effect
+                    .causes
+                    .iter()
+                    .filter_map(|name| causes.iter().position(|c| &c.name == name))
+                    .collect();
+                Ok(EffectState { output, causes })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name: config.name.clone(),
+            causes,
+            state,
+            effects,
+            reset_input: config.inputs.get(RESET_INPUT).cloned(),
+            first_out_output: config.outputs.get(FIRST_OUT_OUTPUT).cloned(),
+            latch,
+            first_out: None,
+        })
+    }
+}
+
+impl Block for InterlockBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let reset = match &self.reset_input {
+            Some(signal) => bus.get_bool(signal)?,
+            None => false,
+        };
+
+        let mut newly_tripped = None;
+        for (index, cause) in self.state.iter_mut().enumerate() {
+            let active = bus.get_bool(&cause.input)? == cause.trip_on;
+            let tripped = if self.latch {
+                active || (cause.tripped && !reset)
+            } else {
+                active
+            };
+            if tripped && !cause.tripped && newly_tripped.is_none() {
+                newly_tripped = Some(index);
+            }
+            cause.tripped = tripped;
+        }
+
+        // Causes are scanned in matrix order, so simultaneous trips report
+        // the first row as the first-out.
+        if self.state.iter().all(|c| !c.tripped) {
+            self.first_out = None;
+        } else if self.first_out.is_none() {
+            self.first_out = newly_tripped;
+        }
+
+        for cause in &self.state {
+            if let Some(output) = &cause.status_output {
+                bus.set(output, Value::Bool(cause.tripped))?;
+            }
+        }
+        for effect in &self.effects {
+            let permitted = effect.causes.iter().all(|&i| !self.state[i].tripped);
+            bus.set(&effect.output, Value::Bool(permitted))?;
+        }
+        if let Some(output) = &self.first_out_output {
+            let index = self.first_out.map_or(0, |i| i64::try_from(i + 1).unwrap_or(i64::MAX));
+            bus.set(output, Value::Integer(index))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "INTERLOCK"
+    }
+
+    fn category(&self) -> &'static str {
+        "safety"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        for cause in &mut self.state {
+            cause.tripped = false;
+        }
+        self.first_out = None;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&InterlockState {
+            tripped: self
+                .causes
+                .iter()
+                .zip(&self.state)
+                .filter(|(_, state)| state.tripped)
+                .map(|(cause, _)| cause.name.clone())
+                .collect(),
+            first_out: self.first_out.map(|i| self.causes[i].name.clone()),
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let saved: InterlockState = decode_state(&self.name, state)?;
+        for (cause, state) in self.causes.iter().zip(&mut self.state) {
+            state.tripped = saved.tripped.contains(&cause.name);
+        }
+        self.first_out = saved
+            .first_out
+            .and_then(|name| self.causes.iter().position(|c| c.name == name));
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        self.state
+            .iter()
+            .map(|c| c.input.as_str())
+            .chain(self.reset_input.as_deref())
+            .collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.effects
+            .iter()
+            .map(|e| e.output.as_str())
+            .chain(self.state.iter().filter_map(|c| c.status_output.as_deref()))
+            .chain(self.first_out_output.as_deref())
+            .collect()
+    }
+}
+
+fn validate_matrix(block: &str, causes: &[InterlockCause], effects: &[InterlockEffect]) -> Result<()> {
+    let invalid = |msg: String| Err(PlcError::Config(format!("INTERLOCK block '{block}': {msg}")));
+
+    if causes.is_empty() || effects.is_empty() {
+        return invalid("at least one cause and one effect are required".to_string());
+    }
+
+    let mut cause_names = HashSet::new();
+    for cause in causes {
+        if cause.name == RESET_INPUT {
+            return invalid(format!("cause name '{RESET_INPUT}' is reserved"));
+        }
+        if !cause_names.insert(cause.name.as_str()) {
+            return invalid(format!("duplicate cause '{}'", cause.name));
+        }
+    }
+
+    let mut effect_names = HashSet::new();
+    for effect in effects {
+        if effect.name == FIRST_OUT_OUTPUT || effect.name.starts_with(STATUS_PREFIX) {
+            return invalid(format!("effect name '{}' is reserved", effect.name));
+        }
+        if !effect_names.insert(effect.name.as_str()) {
+            return invalid(format!("duplicate effect '{}'", effect.name));
+        }
+        if effect.causes.is_empty() {
+            return invalid(format!("effect '{}' has no causes", effect.name));
+        }
+        if let Some(unknown) = effect.causes.iter().find(|c| !cause_names.contains(c.as_str())) {
+            return invalid(format!("effect '{}' references unknown cause '{unknown}'", effect.name));
+        }
+    }
+    Ok(())
+}
+
+/// Factory function for INTERLOCK blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the matrix is inconsistent or a cause
+/// input or effect output is not mapped.
+pub fn create_interlock_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    Ok(Box::new(InterlockBlock::new(config)?))
+}
+
+/// Render the cause-and-effect matrix of an INTERLOCK block as Markdown
+///
+/// Rows are causes in first-out order and columns are effects; an `X` marks
+/// each cause that blocks an effect.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if `config` is not a valid INTERLOCK block.
+pub fn cause_effect_report(config: &BlockConfig) -> Result<String> {
+    let block = InterlockBlock::new(config)?;
+    let effects: Vec<InterlockEffect> = get_parameter(config, "effects", None)?;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "## Interlock `{}`", block.name);
+    let _ = writeln!(out);
+    if let Some(description) = &config.description {
+        let _ = writeln!(out, "{description}");
+        let _ = writeln!(out);
+    }
+    let _ = writeln!(
+        out,
+        "Trips are {}.",
+        if block.latch {
+            match &block.reset_input {
+                Some(reset) => format!("latched until `{reset}` is set"),
+                None => "latched until the block is reset".to_string(),
+            }
+        } else {
+            "not latched".to_string()
+        }
+    );
+    if let Some(first_out) = &block.first_out_output {
+        let _ = writeln!(out, "The first-out cause number is written to `{first_out}`.");
+    }
+    let _ = writeln!(out);
+
+    let _ = write!(out, "| # | Cause | Signal | Trips when |");
+    for effect in &effects {
+        let _ = write!(out, " {} |", effect.name);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "|---|---|---|---|{}", "---|".repeat(effects.len()));
+    for (index, (cause, state)) in block.causes.iter().zip(&block.state).enumerate() {
+        let _ = write!(
+            out,
+            "| {} | {} | `{}` | {} |",
+            index + 1,
+            cause.description.as_deref().unwrap_or(&cause.name),
+            state.input,
+            cause.trip_on
+        );
+        for effect in &effects {
+            let mark = if effect.causes.contains(&cause.name) { "X" } else { "" };
+            let _ = write!(out, " {mark} |");
+        }
+        let _ = writeln!(out);
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "| Effect | Description | Permissive signal |");
+    let _ = writeln!(out, "|---|---|---|");
+    for (effect, state) in effects.iter().zip(&block.effects) {
+        let _ = writeln!(
+            out,
+            "| {} | {} | `{}` |",
+            effect.name,
+            effect.description.as_deref().unwrap_or(""),
+            state.output
+        );
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interlock(latch: bool) -> BlockConfig {
+        serde_yaml::from_str(&format!(
+            r"
+name: pump1_interlock
+type: INTERLOCK
+inputs:
+  suction_level: tank1.level_ok
+  seal_water: pump1.seal_ok
+  reset: pump1.reset
+outputs:
+  pump1_start: pump1.permissive
+  mixer_start: mixer.permissive
+  first_out: pump1.first_out
+  status.seal_water: pump1.seal_tripped
+params:
+  latch: {latch}
+  causes:
+    - name: suction_level
+      description: Suction tank level low
+    - name: seal_water
+  effects:
+    - name: pump1_start
+      causes: [suction_level, seal_water]
+    - name: mixer_start
+      causes: [suction_level]
+"
+        ))
+        .unwrap()
+    }
+
+    fn scan(block: &mut Box<dyn Block>, bus: &SignalBus, level: bool, seal: bool, reset: bool) {
+        bus.set("tank1.level_ok", Value::Bool(level)).unwrap();
+        bus.set("pump1.seal_ok", Value::Bool(seal)).unwrap();
+        bus.set("pump1.reset", Value::Bool(reset)).unwrap();
+        block.execute(bus).unwrap();
+    }
+
+    #[test]
+    fn test_first_out_and_latching() {
+        let bus = SignalBus::new();
+        let mut block = create_interlock_block(&interlock(true)).unwrap();
+
+        scan(&mut block, &bus, true, true, false);
+        assert_eq!(bus.get("pump1.permissive"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("pump1.first_out"), Some(Value::Integer(0)));
+
+        // Seal water trips first, then the level; first-out stays on seal water
+        scan(&mut block, &bus, true, false, false);
+        scan(&mut block, &bus, false, false, false);
+        assert_eq!(bus.get("pump1.first_out"), Some(Value::Integer(2)));
+        assert_eq!(bus.get("mixer.permissive"), Some(Value::Bool(false)));
+
+        // Conditions clear but trips stay latched until reset
+        scan(&mut block, &bus, true, true, false);
+        assert_eq!(bus.get("pump1.permissive"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("pump1.seal_tripped"), Some(Value::Bool(true)));
+
+        scan(&mut block, &bus, true, true, true);
+        assert_eq!(bus.get("pump1.permissive"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("pump1.first_out"), Some(Value::Integer(0)));
+    }
+
+    #[test]
+    fn test_report_and_validation() {
+        let report = cause_effect_report(&interlock(false)).unwrap();
+        assert!(report.contains("| 1 | Suction tank level low | `tank1.level_ok` | false | X | X |"));
+        assert!(report.contains("| 2 | seal_water | `pump1.seal_ok` | false | X |  |"));
+        assert!(report.contains("not latched"));
+
+        let mut config = interlock(true);
+        config.params.insert(
+            "effects".to_string(),
+            serde_yaml::from_str("[{ name: pump1_start, causes: [missing] }]").unwrap(),
+        );
+        assert!(create_interlock_block(&config).is_err());
+    }
+}
@@ -0,0 +1,491 @@
+// src/blocks/lead_lag.rs - Lead/lag equipment rotation block for PETRA
+//
+// Purpose:
+// --------
+// Implements LEAD_LAG, which runs as many of N interchangeable units (pumps,
+// compressors, fans) as the demand asks for: it stages units on and off
+// with delays, balances their run hours, rotates the lead unit, and
+// replaces units that fault. Duty/standby is the case of two units and a
+// demand of one.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait, parameter helpers and state persistence from
+//   blocks/mod.rs and blocks/persistence.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: `demand`, the number of units required (a boolean demand asks
+//   for one), optional `fault_<n>` inputs that lock unit n out, and an
+//   optional `reset` input
+// - Writes: `run_<n>` for every unit, and the optional outputs `lead`
+//   (number of the running unit started first, 0 when none runs), `running`,
+//   `available`, `shortfall` (demand above the available units) and
+//   `runtime_<n>` (run hours of unit n)
+//
+// Configuration:
+// --------------
+//   - name: booster_pumps
+//     type: LEAD_LAG
+//     inputs: { demand: station.pumps_required, fault_1: p1.trip, fault_2: p2.trip, fault_3: p3.trip }
+//     outputs: { run_1: p1.run, run_2: p2.run, run_3: p3.run, lead: station.lead_pump }
+//     params:
+//       rotation: runtime          # runtime (default), round_robin or fixed
+//       stage_delay_ms: 10000      # between starting units
+//       destage_delay_ms: 30000    # between stopping units
+//       rotate_after_ms: 86400000  # swap a unit out after a day of running (optional)
+//
+// Units are numbered from 1. With `runtime` rotation the idle unit with the
+// fewest hours starts and the running unit with the most hours stops; with
+// `round_robin` starts take turns and the last unit started stops first;
+// `fixed` always prefers the lowest numbers. The first unit starts without
+// delay, a demand of zero stops every unit at once, and a unit replacing a
+// faulted one starts without waiting for the stage delay. A fault stops the
+// unit; with a `reset` input the lockout holds until reset after the fault
+// clears. Run hours are part of the block state.
+
+use super::persistence::{decode_state, encode_state};
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How units take turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rotation {
+    Runtime,
+    RoundRobin,
+    Fixed,
+}
+
+struct Unit {
+    run_output: String,
+    fault_input: Option<String>,
+    runtime_output: Option<String>,
+    runtime: Duration,
+    /// Bus time the unit last started, while it runs
+    started: Option<Instant>,
+    /// Start sequence number, to stop the last started unit first
+    start_seq: u64,
+    locked_out: bool,
+}
+
+/// Saved state of a `LEAD_LAG` block
+#[derive(Serialize, Deserialize)]
+struct LeadLagState {
+    runtime_ms: Vec<u64>,
+    last_started: Option<usize>,
+}
+
+/// Staged, rotated control of interchangeable units
+pub struct LeadLagBlock {
+    name: String,
+    demand_input: String,
+    reset_input: Option<String>,
+    lead_output: Option<String>,
+    running_output: Option<String>,
+    available_output: Option<String>,
+    shortfall_output: Option<String>,
+    rotation: Rotation,
+    stage_delay: Duration,
+    destage_delay: Duration,
+    rotate_after: Option<Duration>,
+    units: Vec<Unit>,
+    last_started: Option<usize>,
+    start_seq: u64,
+    /// Direction the running count must move in, and since when
+    pending: Option<(Ordering, Instant)>,
+    /// Units stopped by a fault and not yet replaced
+    unreplaced: usize,
+    last_scan: Option<Instant>,
+}
+
+impl LeadLagBlock {
+    fn running(&self) -> usize {
+        self.units.iter().filter(|unit| unit.started.is_some()).count()
+    }
+
+    /// Idle unit to start next
+    fn next_to_start(&self) -> Option<usize> {
+        let idle = |i: &usize| !self.units[*i].locked_out && self.units[*i].started.is_none();
+        let n = self.units.len();
+        match self.rotation {
+            Rotation::Runtime => (0..n).filter(idle).min_by_key(|&i| self.units[i].runtime),
+            Rotation::RoundRobin => {
+                let first = self.last_started.map_or(0, |last| last + 1);
+                (first..first + n).map(|i| i % n).find(idle)
+            }
+            Rotation::Fixed => (0..n).find(idle),
+        }
+    }
+
+    /// Running unit to stop next
+    fn next_to_stop(&self) -> Option<usize> {
+        let running = (0..self.units.len()).filter(|&i| self.units[i].started.is_some());
+        match self.rotation {
+            // The earliest index wins ties, like starts
+            Rotation::Runtime => running.rev().max_by_key(|&i| self.units[i].runtime),
+            Rotation::RoundRobin | Rotation::Fixed => running.max_by_key(|&i| self.units[i].start_seq),
+        }
+    }
+
+    fn start(&mut self, i: usize, now: Instant) {
+        info!("LEAD_LAG block '{}' starting unit {}", self.name, i + 1);
+        self.start_seq += 1;
+        let unit = &mut self.units[i];
+        unit.started = Some(now);
+        unit.start_seq = self.start_seq;
+        self.last_started = Some(i);
+    }
+
+    fn stop(&mut self, i: usize) {
+        info!("LEAD_LAG block '{}' stopping unit {}", self.name, i + 1);
+        self.units[i].started = None;
+    }
+
+    fn update_lockouts(&mut self, bus: &SignalBus, now: Instant) -> Result<()> {
+        let reset = match &self.reset_input {
+            Some(input) => Some(bus.get_bool(input)?),
+            None => None,
+        };
+        for i in 0..self.units.len() {
+            let fault = match &self.units[i].fault_input {
+                Some(input) => bus.get_bool(input)?,
+                None => false,
+            };
+            if fault && !self.units[i].locked_out {
+                warn!("LEAD_LAG block '{}' unit {} faulted and is locked out", self.name, i + 1);
+                self.units[i].locked_out = true;
+                if self.units[i].started.is_some() {
+                    self.stop(i);
+                    self.unreplaced += 1;
+                    self.pending = Some((Ordering::Greater, now));
+                }
+            } else if !fault && self.units[i].locked_out && reset.unwrap_or(true) {
+                info!("LEAD_LAG block '{}' unit {} available again", self.name, i + 1);
+                self.units[i].locked_out = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start or stop at most one unit to move towards `demand`
+    fn stage(&mut self, demand: usize, now: Instant) {
+        let running = self.running();
+        let direction = demand.cmp(&running);
+        if direction == Ordering::Equal {
+            self.pending = None;
+            self.unreplaced = 0;
+            return;
+        }
+        let since = match self.pending {
+            Some((pending, since)) if pending == direction => since,
+            _ => self.pending.insert((direction, now)).1,
+        };
+        let waited = now.saturating_duration_since(since);
+        match direction {
+            Ordering::Greater => {
+                if running > 0 && self.unreplaced == 0 && waited < self.stage_delay {
+                    return;
+                }
+                if let Some(i) = self.next_to_start() {
+                    self.start(i, now);
+                    self.unreplaced = self.unreplaced.saturating_sub(1);
+                    self.pending = Some((direction, now));
+                }
+            }
+            Ordering::Less => {
+                if demand == 0 {
+                    for i in 0..self.units.len() {
+                        if self.units[i].started.is_some() {
+                            self.stop(i);
+                        }
+                    }
+                } else if waited >= self.destage_delay {
+                    if let Some(i) = self.next_to_stop() {
+                        self.stop(i);
+                        self.pending = Some((direction, now));
+                    }
+                }
+            }
+            Ordering::Equal => {}
+        }
+    }
+
+    /// Swap out a unit that has run continuously for `rotate_after`
+    fn rotate(&mut self, now: Instant) {
+        let Some(rotate_after) = self.rotate_after else {
+            return;
+        };
+        let due = (0..self.units.len())
+            .filter(|&i| {
+                self.units[i]
+                    .started
+                    .is_some_and(|started| now.saturating_duration_since(started) >= rotate_after)
+            })
+            .min_by_key(|&i| self.units[i].started);
+        if let (Some(out), Some(standby)) = (due, self.next_to_start()) {
+            self.start(standby, now);
+            self.stop(out);
+        }
+    }
+}
+
+impl Block for LeadLagBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let now = bus.now();
+        if let Some(last) = self.last_scan {
+            let elapsed = now.saturating_duration_since(last);
+            for unit in self.units.iter_mut().filter(|unit| unit.started.is_some()) {
+                unit.runtime += elapsed;
+            }
+        }
+        self.last_scan = Some(now);
+
+        self.update_lockouts(bus, now)?;
+        let demand = usize::try_from(bus.get_integer(&self.demand_input)?.max(0)).unwrap_or(usize::MAX);
+        let available = self.units.iter().filter(|unit| !unit.locked_out).count();
+        self.stage(demand.min(self.units.len()), now);
+        if self.pending.is_none() {
+            self.rotate(now);
+        }
+
+        for unit in &self.units {
+            bus.set(&unit.run_output, Value::Bool(unit.started.is_some()))?;
+            if let Some(output) = &unit.runtime_output {
+                bus.set(output, Value::Float(unit.runtime.as_secs_f64() / 3600.0))?;
+            }
+        }
+        if let Some(output) = &self.lead_output {
+            let lead = (0..self.units.len())
+                .filter(|&i| self.units[i].started.is_some())
+                .min_by_key(|&i| self.units[i].start_seq)
+                .map_or(0, |i| i + 1);
+            bus.set(output, Value::Integer(i64::try_from(lead).unwrap_or(i64::MAX)))?;
+        }
+        if let Some(output) = &self.running_output {
+            bus.set(output, Value::Integer(i64::try_from(self.running()).unwrap_or(i64::MAX)))?;
+        }
+        if let Some(output) = &self.available_output {
+            bus.set(output, Value::Integer(i64::try_from(available).unwrap_or(i64::MAX)))?;
+        }
+        if let Some(output) = &self.shortfall_output {
+            bus.set(output, Value::Bool(demand > available))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "LEAD_LAG"
+    }
+
+    fn category(&self) -> &'static str {
+        "control"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        for unit in &mut self.units {
+            unit.started = None;
+            unit.locked_out = false;
+        }
+        self.pending = None;
+        self.unreplaced = 0;
+        self.last_scan = None;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&LeadLagState {
+            runtime_ms: self
+                .units
+                .iter()
+                .map(|unit| u64::try_from(unit.runtime.as_millis()).unwrap_or(u64::MAX))
+                .collect(),
+            last_started: self.last_started,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: LeadLagState = decode_state(&self.name, state)?;
+        for (unit, ms) in self.units.iter_mut().zip(state.runtime_ms) {
+            unit.runtime = Duration::from_millis(ms);
+        }
+        self.last_started = state.last_started.filter(|&i| i < self.units.len());
+        self.last_scan = None;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        std::iter::once(self.demand_input.as_str())
+            .chain(self.reset_input.as_deref())
+            .chain(self.units.iter().filter_map(|unit| unit.fault_input.as_deref()))
+            .collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.units
+            .iter()
+            .flat_map(|unit| std::iter::once(unit.run_output.as_str()).chain(unit.runtime_output.as_deref()))
+            .chain(
+                [&self.lead_output, &self.running_output, &self.available_output, &self.shortfall_output]
+                    .into_iter()
+                    .filter_map(Option::as_deref),
+            )
+            .collect()
+    }
+}
+
+/// Factory function for `LEAD_LAG` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without a `demand` input, with fewer than
+/// two units (`run_1`, `run_2`, ...), with an unknown `rotation`, or with
+/// `rotate_after_ms` and `fixed` rotation.
+pub fn create_lead_lag_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let error = |message: String| PlcError::Config(format!("LEAD_LAG block '{}' {}", config.name, message));
+
+    let mut units = Vec::new();
+    while let Some(run_output) = get_output_signal(config, &format!("run_{}", units.len() + 1), false)? {
+        let n = units.len() + 1;
+        units.push(Unit {
+            run_output,
+            fault_input: get_input_signal(config, &format!("fault_{n}"), false)?,
+            runtime_output: get_output_signal(config, &format!("runtime_{n}"), false)?,
+            runtime: Duration::ZERO,
+            started: None,
+            start_seq: 0,
+            locked_out: false,
+        });
+    }
+    if units.len() < 2 {
+        return Err(error("needs the outputs run_1 and run_2 at least".to_string()));
+    }
+
+    let rotation = match get_string_parameter(config, "rotation", Some("runtime"))?.as_str() {
+        "runtime" => Rotation::Runtime,
+        "round_robin" => Rotation::RoundRobin,
+        "fixed" => Rotation::Fixed,
+        other => return Err(error(format!("rotation must be runtime, round_robin or fixed, not '{other}'"))),
+    };
+    let rotate_after = if config.params.contains_key("rotate_after_ms") {
+        if rotation == Rotation::Fixed {
+            return Err(error("cannot rotate units with fixed rotation".to_string()));
+        }
+        Some(Duration::from_millis(get_numeric_parameter(config, "rotate_after_ms", None)?))
+    } else {
+        None
+    };
+
+    Ok(Box::new(LeadLagBlock {
+        name: config.name.clone(),
+        demand_input: get_input_signal(config, "demand", true)?.unwrap_or_default(),
+        reset_input: get_input_signal(config, "reset", false)?,
+        lead_output: get_output_signal(config, "lead", false)?,
+        running_output: get_output_signal(config, "running", false)?,
+        available_output: get_output_signal(config, "available", false)?,
+        shortfall_output: get_output_signal(config, "shortfall", false)?,
+        rotation,
+        stage_delay: Duration::from_millis(get_numeric_parameter(config, "stage_delay_ms", Some(0))?),
+        destage_delay: Duration::from_millis(get_numeric_parameter(config, "destage_delay_ms", Some(0))?),
+        rotate_after,
+        units,
+        last_started: None,
+        start_seq: 0,
+        pending: None,
+        unreplaced: 0,
+        last_scan: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+
+    fn pumps(params: &str) -> (Box<dyn Block>, SignalBus, Arc<SimulatedClock>) {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("demand", Value::Integer(0)).unwrap();
+        for fault in ["f1", "f2", "f3", "reset"] {
+            bus.set(fault, Value::Bool(false)).unwrap();
+        }
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: pumps, type: LEAD_LAG, \
+             inputs: {{ demand: demand, fault_1: f1, fault_2: f2, fault_3: f3, reset: reset }}, \
+             outputs: {{ run_1: r1, run_2: r2, run_3: r3, lead: lead, shortfall: short }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        (super::super::create_block(&config).unwrap(), bus, clock)
+    }
+
+    fn running(bus: &SignalBus) -> [bool; 3] {
+        ["r1", "r2", "r3"].map(|run| bus.get_bool(run).unwrap())
+    }
+
+    #[test]
+    fn test_staging_balancing_and_fault_replacement() {
+        let (mut block, bus, clock) = pumps("stage_delay_ms: 1000, destage_delay_ms: 2000");
+        let mut scan = |demand: i64, advance_ms: u64| {
+            clock.advance(Duration::from_millis(advance_ms));
+            bus.set("demand", Value::Integer(demand)).unwrap();
+            block.execute(&bus).unwrap();
+            running(&bus)
+        };
+
+        // The first unit starts at once, the second after the stage delay
+        assert_eq!(scan(2, 0), [true, false, false]);
+        assert_eq!(scan(2, 500), [true, false, false]);
+        assert_eq!(scan(2, 500), [true, true, false]);
+        assert_eq!(bus.get("lead"), Some(Value::Integer(1)));
+        // Unit 1 has the most hours, so it stops first after the destage delay
+        assert_eq!(scan(1, 0), [true, true, false]);
+        assert_eq!(scan(1, 2000), [false, true, false]);
+        // Unit 3 has no hours and leads the next start
+        assert_eq!(scan(0, 0), [false, false, false]);
+        assert_eq!(scan(1, 0), [false, false, true]);
+
+        // A fault is replaced at once and stays locked out until reset
+        bus.set("f3", Value::Bool(true)).unwrap();
+        assert_eq!(scan(1, 100), [false, true, false]);
+        bus.set("f3", Value::Bool(false)).unwrap();
+        bus.set("f2", Value::Bool(true)).unwrap();
+        bus.set("f1", Value::Bool(true)).unwrap();
+        assert_eq!(scan(1, 100), [false, false, false]);
+        assert_eq!(bus.get("short"), Some(Value::Bool(true)));
+        bus.set("reset", Value::Bool(true)).unwrap();
+        assert_eq!(scan(1, 100), [false, false, true]);
+    }
+
+    #[test]
+    fn test_round_robin_rotation_and_saved_hours() {
+        let (mut block, bus, clock) = pumps("rotation: round_robin, rotate_after_ms: 60000");
+        bus.set("demand", Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(running(&bus), [true, false, false]);
+        clock.advance(Duration::from_secs(60));
+        block.execute(&bus).unwrap();
+        assert_eq!(running(&bus), [false, true, false]);
+        clock.advance(Duration::from_secs(60));
+        block.execute(&bus).unwrap();
+        assert_eq!(running(&bus), [false, false, true]);
+
+        let state = block.save_state().unwrap();
+        let (mut restored, bus, _) = pumps("rotation: round_robin");
+        restored.load_state(state).unwrap();
+        bus.set("demand", Value::Integer(1)).unwrap();
+        restored.execute(&bus).unwrap();
+        assert_eq!(running(&bus), [true, false, false]);
+        let saved: LeadLagState = serde_json::from_value(restored.save_state().unwrap()).unwrap();
+        assert_eq!(saved.runtime_ms, [60000, 60000, 0]);
+    }
+}
@@ -441,6 +441,8 @@ mod tests {
             tags: vec![],
             #[cfg(feature = "enhanced-errors")]
             error_handling: None,
+            budget: None,
+            phase: None,
             #[cfg(feature = "circuit-breaker")]
             circuit_breaker: None,
         }
@@ -1,5 +1,7 @@
 // src/blocks/memory.rs - Memory block implementations
 use super::{Block, BlockConfig};
+use super::persistence::{decode_state, encode_state};
+use serde::{Deserialize, Serialize};
 use crate::{error::{PlcError, Result}, signal::SignalBus, value::Value};
 use std::collections::HashMap;
 
@@ -43,6 +45,15 @@ impl Block for SrLatchBlock {
         self.state = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.state)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.state = decode_state(&self.name, state)?;
+        Ok(())
+    }
 }
 
 pub fn create_sr_latch_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -115,6 +126,15 @@ impl Block for RsLatchBlock {
         self.state = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.state)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.state = decode_state(&self.name, state)?;
+        Ok(())
+    }
 }
 
 pub fn create_rs_latch_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -161,6 +181,13 @@ pub struct FlipFlopBlock {
     last_clock: bool,
 }
 
+/// Retained state of a [`FlipFlopBlock`]
+#[derive(Serialize, Deserialize)]
+struct FlipFlopState {
+    state: bool,
+    last_clock: bool,
+}
+
 impl Block for FlipFlopBlock {
     fn execute(&mut self, bus: &SignalBus) -> Result<()> {
         let data = bus.get_bool(&self.data_input)?;
@@ -196,6 +223,17 @@ impl Block for FlipFlopBlock {
         self.last_clock = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&FlipFlopState { state: self.state, last_clock: self.last_clock })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: FlipFlopState = decode_state(&self.name, state)?;
+        self.state = state.state;
+        self.last_clock = state.last_clock;
+        Ok(())
+    }
 }
 
 pub fn create_flip_flop_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
@@ -1,11 +1,23 @@
 // src/blocks/mod.rs - Block system implementation with fixed parameter references
 
 pub mod base;
+pub mod analog_alarm;
 pub mod timer;
+pub mod interlock;
 pub mod arithmetic;  // Changed from math to arithmetic
+pub mod calc;
+pub mod composite;
 pub mod data;
+pub mod generator;
+pub mod lead_lag;
 pub mod cache_optimized;
 pub mod simulation;
+pub mod persistence;
+pub mod rolling;
+pub mod runtime_meter;
+pub mod sequencer;
+pub mod spc;
+pub mod totalizer;
 
 #[cfg(feature = "edge-detection")]
 pub mod edge;
@@ -16,6 +28,9 @@ pub mod memory;
 #[cfg(feature = "pid-control")]
 pub mod pid;
 
+#[cfg(feature = "schedules")]
+pub mod schedule;
+
 #[cfg(feature = "communication")]
 pub mod comm;
 
@@ -34,6 +49,12 @@ pub mod circuit_breaker;
 #[cfg(feature = "simd-math")]
 pub mod simd_math;
 
+#[cfg(feature = "extended-types")]
+pub mod structured;
+
+#[cfg(feature = "extended-types")]
+pub mod format;
+
 use crate::{
     config::BlockConfig,
     error::{PlcError, Result},
@@ -125,6 +146,33 @@ pub trait Block: Send + Sync {
         Ok(())
     }
     
+    /// Capture internal state that should survive an engine restart
+    /// 
+    /// Blocks holding timers, counts or integrators return their state here
+    /// (see [`persistence::encode_state`]); the engine saves it when
+    /// `block_state` is configured. Stateless blocks keep the default.
+    fn save_state(&self) -> Option<serde_json::Value> {
+        None
+    }
+    
+    /// Restore state previously returned by [`Block::save_state`]
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if `state` does not match the block's state format.
+    fn load_state(&mut self, _state: serde_json::Value) -> Result<()> {
+        Ok(())
+    }
+    
+    /// Alarms the alarm manager should raise on this block's outputs
+    /// 
+    /// Alarm blocks such as `ANALOG_ALARM` return one definition per output;
+    /// other blocks keep the default.
+    #[cfg(feature = "alarms")]
+    fn alarm_configs(&self) -> Vec<crate::alarms::AlarmConfig> {
+        Vec::new()
+    }
+    
     /// Get block description
     fn description(&self) -> Option<&str> {
         None
@@ -186,6 +234,7 @@ pub trait Block: Send + Sync {
 ///     params: HashMap::new(),
 ///     description: None,
 ///     tags: vec![],
+///     ..Default::default()
 /// };
 /// 
 /// let block = create_block(&config)?;
@@ -212,22 +261,48 @@ pub fn create_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
         "ON_DELAY" => timer::create_on_delay_block(config),
         "OFF_DELAY" => timer::create_off_delay_block(config),
         "PULSE" => timer::create_pulse_block(config),
+        "TONR" => timer::create_retentive_timer_block(config),
+        "DEBOUNCE" => timer::create_debounce_block(config),
+        "TOTALIZER" => totalizer::create_totalizer_block(config),
+        "RUNTIME_METER" => runtime_meter::create_runtime_meter_block(config),
         
         // Math blocks (always available)
         "ADD" => arithmetic::create_add_block(config),
         "SUB" => arithmetic::create_subtract_block(config),
         "MUL" => arithmetic::create_multiply_block(config),
         "DIV" => arithmetic::create_divide_block(config),
+        "CALC" => calc::create_calc_block(config),
+        "ROLLING_STATS" => rolling::create_rolling_stats_block(config),
+        "SPC" => spc::create_spc_block(config),
         
         // Data blocks (always available)
         "SCALE" => data::create_scale_block(config),
         "LIMIT" => data::create_limit_block(config),
+        "RATE_LIMIT" => data::create_rate_limit_block(config),
         "SELECT" => data::create_select_block(config),
         "MUX" => data::create_mux_block(config),
         "DEMUX" => data::create_demux_block(config),
         "DATA_GENERATOR" => data::create_data_generator_block(config),
+        "SINE_GENERATOR" => generator::create_sine_generator_block(config),
+        "SQUARE_GENERATOR" => generator::create_square_generator_block(config),
+        "RAMP_GENERATOR" => generator::create_ramp_generator_block(config),
+        "NOISE_GENERATOR" => generator::create_noise_generator_block(config),
         "TANK_SIMULATION" => simulation::create_tank_simulation_block(config),
         
+        // Array and object blocks (feature-gated)
+        #[cfg(feature = "extended-types")]
+        "ARRAY_INDEX" => structured::create_array_index_block(config),
+        #[cfg(feature = "extended-types")]
+        "OBJECT_GET" => structured::create_object_get_block(config),
+        #[cfg(feature = "extended-types")]
+        "FORMAT" => format::create_format_block(config),
+        
+        // Interlock blocks (always available)
+        "INTERLOCK" => interlock::create_interlock_block(config),
+        "ANALOG_ALARM" => analog_alarm::create_analog_alarm_block(config),
+        "SEQUENCER" => sequencer::create_sequencer_block(config),
+        "LEAD_LAG" => lead_lag::create_lead_lag_block(config),
+        
         // Edge detection blocks (feature-gated)
         #[cfg(feature = "edge-detection")]
         "RISING_EDGE" => edge::create_rising_edge_block(config),
@@ -250,7 +325,11 @@ pub fn create_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
         #[cfg(feature = "pid-control")]
         "PID" => pid::create_pid_block(config),
         #[cfg(feature = "pid-control")]
-        "TUNE_PID" => pid::create_tune_pid_block(config),
+        "PID_AUTOTUNE" => pid::create_pid_autotune_block(config),
+        
+        // Schedule blocks (feature-gated)
+        #[cfg(feature = "schedules")]
+        "SCHEDULE" => schedule::create_schedule_block(config),
         
         // Communication blocks (feature-gated)
         #[cfg(feature = "communication")]
@@ -296,11 +375,17 @@ pub fn create_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
             )))
         }
         
-        _ => Err(PlcError::Config(format!(
-            "Unknown block type: '{}'. Available types: {}",
-            config.block_type,
-            get_available_block_types().join(", ")
-        ))),
+        _ => {
+            #[cfg(feature = "plugins")]
+            if let Some(factory) = plugin_factory(&config.block_type) {
+                return factory(config);
+            }
+            Err(PlcError::Config(format!(
+                "Unknown block type: '{}'. Available types: {}",
+                config.block_type,
+                get_available_block_types().join(", ")
+            )))
+        }
     }
 }
 
@@ -310,10 +395,17 @@ pub fn get_available_block_types() -> Vec<&'static str> {
         // Always available
         "AND", "OR", "NOT", "XOR",
         "GT", "LT", "GTE", "LTE", "EQ", "NEQ",
-        "ON_DELAY", "OFF_DELAY", "PULSE",
-        "ADD", "SUB", "MUL", "DIV",
-        "SCALE", "LIMIT", "SELECT", "MUX", "DEMUX", "DATA_GENERATOR",
-        "TANK_SIMULATION",
+        "ON_DELAY", "OFF_DELAY", "PULSE", "TONR", "DEBOUNCE", "TOTALIZER", "RUNTIME_METER",
+        "ADD", "SUB", "MUL", "DIV", "CALC", "ROLLING_STATS", "SPC",
+        "SCALE", "LIMIT", "RATE_LIMIT", "SELECT", "MUX", "DEMUX", "DATA_GENERATOR",
+        "SINE_GENERATOR", "SQUARE_GENERATOR", "RAMP_GENERATOR", "NOISE_GENERATOR", "TANK_SIMULATION",
+        "INTERLOCK", "ANALOG_ALARM", "SEQUENCER", "LEAD_LAG",
+        #[cfg(feature = "extended-types")]
+        "ARRAY_INDEX",
+        #[cfg(feature = "extended-types")]
+        "OBJECT_GET",
+        #[cfg(feature = "extended-types")]
+        "FORMAT",
         #[cfg(feature = "edge-detection")]
         "RISING_EDGE",
         #[cfg(feature = "edge-detection")]
@@ -331,7 +423,9 @@ pub fn get_available_block_types() -> Vec<&'static str> {
         #[cfg(feature = "pid-control")]
         "PID",
         #[cfg(feature = "pid-control")]
-        "TUNE_PID",
+        "PID_AUTOTUNE",
+        #[cfg(feature = "schedules")]
+        "SCHEDULE",
         #[cfg(feature = "communication")]
         "MODBUS_READ",
         #[cfg(feature = "communication")]
@@ -361,6 +455,62 @@ pub fn get_available_block_types() -> Vec<&'static str> {
     types
 }
 
+/// Factory for a block type implemented by a plugin
+#[cfg(feature = "plugins")]
+pub type PluginFactory = std::sync::Arc<dyn Fn(&BlockConfig) -> Result<Box<dyn Block>> + Send + Sync>;
+
+/// Plugin block types with the name of the plugin that registered them
+#[cfg(feature = "plugins")]
+static PLUGIN_BLOCK_TYPES: std::sync::RwLock<std::collections::BTreeMap<String, (String, PluginFactory)>> =
+    std::sync::RwLock::new(std::collections::BTreeMap::new());
+
+#[cfg(feature = "plugins")]
+fn plugin_factory(block_type: &str) -> Option<PluginFactory> {
+    PLUGIN_BLOCK_TYPES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(block_type)
+        .map(|(_, factory)| std::sync::Arc::clone(factory))
+}
+
+/// Register a block type implemented by a plugin
+///
+/// A plugin registering one of its own types again replaces the factory.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the type is built in or was registered
+/// by another plugin.
+#[cfg(feature = "plugins")]
+pub fn register_plugin_block_type(block_type: &str, plugin: &str, factory: PluginFactory) -> Result<()> {
+    if get_available_block_types().contains(&block_type) {
+        return Err(PlcError::Config(format!(
+            "Plugin '{plugin}' cannot register built-in block type '{block_type}'"
+        )));
+    }
+    let mut types = PLUGIN_BLOCK_TYPES.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((owner, _)) = types.get(block_type) {
+        if owner != plugin {
+            return Err(PlcError::Config(format!(
+                "Plugin '{plugin}' cannot register block type '{block_type}', already registered by plugin '{owner}'"
+            )));
+        }
+    }
+    types.insert(block_type.to_string(), (plugin.to_string(), factory));
+    Ok(())
+}
+
+/// Block types registered by plugins, in sorted order
+#[cfg(feature = "plugins")]
+pub fn plugin_block_types() -> Vec<String> {
+    PLUGIN_BLOCK_TYPES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
 /// Enhanced monitoring metadata for blocks
 #[cfg(feature = "enhanced-monitoring")]
 #[derive(Debug, Clone)]
@@ -669,6 +819,7 @@ pub mod test_utils {
             params: HashMap::new(),
             description: Some(format!("Test {} block", block_type)),
             tags: vec!["test".to_string()],
+            ..Default::default()
         };
 
         config
@@ -0,0 +1,342 @@
+// src/blocks/persistence.rs - Block state persistence across engine restarts
+//
+// Blocks that opt in through `Block::save_state` have their internal state
+// written to a JSON file at a configurable interval and when the engine
+// stops. On startup the engine restores each saved state into the block
+// with the same name and type, so timers, counters, latches, edge detectors
+// and PID integrators continue where they left off instead of starting from
+// zero.
+//
+// Entries whose block no longer exists or changed type are skipped, which
+// keeps a state file usable across configuration edits.
+//
+// The same file carries the values of signals configured with `retain`,
+// which are written back to the bus after the configured initial values.
+//
+// Checkpoints are flushed to disk before they replace the previous one,
+// which is kept next to it with a `.bak` extension. If power fails while a
+// checkpoint is written, or the file is found damaged, startup falls back
+// to the previous checkpoint.
+
+use super::Block;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Block state persistence settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct BlockStateConfig {
+    /// State file location
+    pub path: PathBuf,
+
+    /// Interval between periodic saves while the engine runs
+    #[serde(default = "default_save_interval_ms")]
+    pub save_interval_ms: u64,
+}
+
+const fn default_save_interval_ms() -> u64 {
+    5000
+}
+
+impl BlockStateConfig {
+    /// Validate the persistence settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the path is empty or the save interval
+    /// is zero.
+    pub fn validate(&self) -> Result<()> {
+        if self.path.as_os_str().is_empty() {
+            return Err(PlcError::Config("block_state.path must not be empty".to_string()));
+        }
+        if self.save_interval_ms == 0 {
+            return Err(PlcError::Config(
+                "block_state.save_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateFile {
+    saved_at: DateTime<Utc>,
+    blocks: BTreeMap<String, SavedBlock>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    signals: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedBlock {
+    block_type: String,
+    state: serde_json::Value,
+}
+
+/// Serialize a block's state struct for [`Block::save_state`]
+pub fn encode_state<T: Serialize>(state: &T) -> Option<serde_json::Value> {
+    serde_json::to_value(state).ok()
+}
+
+/// Deserialize a block's state struct in [`Block::load_state`]
+///
+/// # Errors
+///
+/// Returns [`PlcError::Block`] if `state` does not match `T`.
+pub fn decode_state<T: DeserializeOwned>(block: &str, state: serde_json::Value) -> Result<T> {
+    serde_json::from_value(state)
+        .map_err(|e| PlcError::Block(format!("Invalid saved state for block '{block}': {e}")))
+}
+
+/// Write the state of all opted-in blocks and the retained signal values
+/// to `path`
+///
+/// The file is written and synced to a temporary sibling and renamed into
+/// place, after the previous checkpoint is moved to [`backup_path`], so a
+/// crash or power loss during the write never leaves only a truncated state
+/// file behind.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save_block_states<'a>(
+    path: &Path,
+    blocks: impl IntoIterator<Item = &'a dyn Block>,
+    signals: BTreeMap<String, Value>,
+) -> Result<usize> {
+    let states: BTreeMap<String, SavedBlock> = blocks
+        .into_iter()
+        .filter_map(|block| {
+            block.save_state().map(|state| {
+                (
+                    block.name().to_string(),
+                    SavedBlock {
+                        block_type: block.block_type().to_string(),
+                        state,
+                    },
+                )
+            })
+        })
+        .collect();
+    let count = states.len();
+
+    let file = StateFile {
+        saved_at: Utc::now(),
+        blocks: states,
+        signals,
+    };
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut out = std::fs::File::create(&tmp)?;
+    out.write_all(&serde_json::to_vec_pretty(&file)?)?;
+    out.sync_all()?;
+    drop(out);
+    if path.exists() {
+        std::fs::rename(path, backup_path(path))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    // Make the renames themselves durable; not supported on every platform
+    if let Ok(dir) = std::fs::File::open(parent.unwrap_or_else(|| Path::new("."))) {
+        let _ = dir.sync_all();
+    }
+
+    debug!("Saved state of {} blocks to {}", count, path.display());
+    Ok(count)
+}
+
+/// Previous checkpoint kept next to the state file at `path`
+#[must_use]
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+fn read_file(path: &Path) -> Result<Option<StateFile>> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read the checkpoint at `path`, or the previous one if it is missing or
+/// damaged
+fn read_state_file(path: &Path) -> Result<Option<StateFile>> {
+    let error = match read_file(path) {
+        Ok(Some(file)) => return Ok(Some(file)),
+        Ok(None) => None,
+        Err(e) => Some(e),
+    };
+    let backup = backup_path(path);
+    match (read_file(&backup), error) {
+        (Ok(Some(file)), error) => {
+            warn!(
+                "Using previous checkpoint {}: {}",
+                backup.display(),
+                error.map_or_else(|| "state file is missing".to_string(), |e| e.to_string())
+            );
+            Ok(Some(file))
+        }
+        (_, Some(error)) => Err(error),
+        (result, None) => result,
+    }
+}
+
+/// Restore saved block state from `path`
+///
+/// A missing file is not an error. Blocks whose saved state cannot be
+/// applied are logged and keep their initial state.
+///
+/// # Errors
+///
+/// Returns an error if neither the file nor the previous checkpoint can be
+/// read and parsed.
+pub fn restore_block_states(path: &Path, blocks: &mut [Box<dyn Block>]) -> Result<usize> {
+    let Some(mut file) = read_state_file(path)? else {
+        return Ok(0);
+    };
+
+    let mut restored = 0;
+    for block in blocks.iter_mut() {
+        let Some(saved) = file.blocks.remove(block.name()) else {
+            continue;
+        };
+        if saved.block_type != block.block_type() {
+            warn!(
+                "Ignoring saved state for block '{}': type changed from {} to {}",
+                block.name(),
+                saved.block_type,
+                block.block_type()
+            );
+            continue;
+        }
+        match block.load_state(saved.state) {
+            Ok(()) => restored += 1,
+            Err(e) => warn!("Failed to restore state for block '{}': {}", block.name(), e),
+        }
+    }
+
+    info!(
+        "Restored state of {} blocks from {} (saved {})",
+        restored,
+        path.display(),
+        file.saved_at
+    );
+    Ok(restored)
+}
+
+/// Write the retained signal values saved in `path` back to `bus`
+///
+/// Only signals named in `retained` are restored, so a signal that lost its
+/// `retain` flag starts from its initial value again. A missing file is not
+/// an error.
+///
+/// # Errors
+///
+/// Returns an error if neither the file nor the previous checkpoint can be
+/// read and parsed.
+pub fn restore_retained_signals(path: &Path, bus: &SignalBus, retained: &[&str]) -> Result<usize> {
+    let Some(file) = read_state_file(path)? else {
+        return Ok(0);
+    };
+
+    let mut restored = 0;
+    for (name, value) in file.signals {
+        if !retained.contains(&name.as_str()) {
+            continue;
+        }
+        match bus.set(&name, value) {
+            Ok(()) => restored += 1,
+            Err(e) => warn!("Failed to restore retained signal '{}': {}", name, e),
+        }
+    }
+    debug!("Restored {} retained signals from {}", restored, path.display());
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BlockConfig;
+    use crate::signal::SignalBus;
+    use crate::value::Value;
+
+    fn off_delay(name: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "name: {name}\n\
+             type: OFF_DELAY\n\
+             inputs: {{ in: run }}\n\
+             outputs: {{ out: {name}.out }}\n\
+             params: {{ preset_ms: 60000 }}\n"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    #[test]
+    fn test_off_delay_survives_restart() {
+        let bus = SignalBus::new();
+        let mut blocks = vec![off_delay("fan")];
+        for run in [true, false] {
+            bus.set("run", Value::Bool(run)).unwrap();
+            blocks[0].execute(&bus).unwrap();
+        }
+        assert_eq!(bus.get("fan.out"), Some(Value::Bool(true)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let retained = BTreeMap::from([("fan.hours".to_string(), Value::Float(12.5))]);
+        assert_eq!(save_block_states(&path, blocks.iter().map(AsRef::as_ref), retained).unwrap(), 1);
+
+        // A fresh block would see no falling edge and switch off immediately
+        let mut restarted = vec![off_delay("fan"), off_delay("other")];
+        assert_eq!(restore_block_states(&path, &mut restarted).unwrap(), 1);
+        restarted[0].execute(&bus).unwrap();
+        assert_eq!(bus.get("fan.out"), Some(Value::Bool(true)));
+
+        let mut fresh = off_delay("fan");
+        fresh.execute(&bus).unwrap();
+        assert_eq!(bus.get("fan.out"), Some(Value::Bool(false)));
+
+        let restarted_bus = SignalBus::new();
+        assert_eq!(restore_retained_signals(&path, &restarted_bus, &["fan.hours"]).unwrap(), 1);
+        assert_eq!(restarted_bus.get("fan.hours"), Some(Value::Float(12.5)));
+        assert_eq!(restore_retained_signals(&path, &SignalBus::new(), &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_damaged_checkpoint_falls_back_to_previous() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let hours = |h: f64| BTreeMap::from([("pump.hours".to_string(), Value::Float(h))]);
+        save_block_states(&path, std::iter::empty(), hours(1.0)).unwrap();
+        save_block_states(&path, std::iter::empty(), hours(2.0)).unwrap();
+        assert!(backup_path(&path).exists());
+        assert!(!path.with_extension("tmp").exists());
+
+        let restore = || {
+            let bus = SignalBus::new();
+            restore_retained_signals(&path, &bus, &["pump.hours"]).map(|_| bus.get("pump.hours"))
+        };
+        assert_eq!(restore().unwrap(), Some(Value::Float(2.0)));
+
+        // Power lost halfway through writing the newest checkpoint
+        std::fs::write(&path, b"{\"saved_at\": \"2026-").unwrap();
+        assert_eq!(restore().unwrap(), Some(Value::Float(1.0)));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restore().unwrap(), Some(Value::Float(1.0)));
+
+        std::fs::write(backup_path(&path), b"garbage").unwrap();
+        assert!(restore().is_err());
+    }
+}
@@ -0,0 +1,764 @@
+// src/blocks/pid.rs - PID controller block for PETRA
+//
+// Purpose:
+// --------
+// Implements the PID block: a PID controller with output limits,
+// anti-windup, auto/manual mode with bumpless transfer, derivative on error
+// or on measurement, and gain scheduling. PID_AUTOTUNE suggests PID gains
+// with a relay-feedback experiment.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait, parameter helpers and state persistence from
+//   blocks/mod.rs and blocks/persistence.rs
+// - Used by: blocks/mod.rs factory (pid-control feature)
+// - Reads: `setpoint` and `process_variable`; optionally `auto` (true for
+//   automatic, default), `manual_output` (the output in manual) and
+//   `schedule` (the variable the gains are scheduled on)
+// - Writes: the controller output to `output`
+// - PID_AUTOTUNE reads `setpoint`, `process_variable` and `start`, and
+//   writes the relay output to `output` and the suggested gains to `kp`,
+//   `ki` and `kd`
+//
+// Configuration:
+// --------------
+//   - name: pump_control
+//     type: PID
+//     inputs:
+//       setpoint: tank1.setpoint
+//       process_variable: tank1.level
+//       auto: tank1.auto
+//       manual_output: tank1.manual_speed
+//       schedule: tank1.level
+//     outputs: { output: pump1.speed }
+//     params:
+//       kp: 1.0
+//       ki: 0.1
+//       kd: 0.01
+//       output_min: 0.0
+//       output_max: 100.0
+//       anti_windup: clamp          # clamp (default), back_calculation or none
+//       tracking_gain: 1.0          # back_calculation only
+//       derivative: measurement     # error (default) or measurement
+//       schedule:                   # gains interpolated on `schedule`
+//         - { at: 0.0, kp: 2.0, ki: 0.2, kd: 0.0 }
+//         - { at: 50.0, kp: 1.0, ki: 0.1, kd: 0.01 }
+//
+// Behavior:
+// ---------
+// The integral is kept as its contribution to the output, so changing the
+// gains, by hand or through the schedule, does not make the output jump. In
+// manual the output follows `manual_output`, or holds its last value without
+// one, and the integral tracks it, so switching back to automatic continues
+// from the manual output. Derivative on measurement avoids the kick of a
+// setpoint step. Scan intervals are read from the bus clock.
+//
+// Autotuning:
+// -----------
+//   - name: pump_autotune
+//     type: PID_AUTOTUNE
+//     inputs: { setpoint: tank1.setpoint, process_variable: tank1.level, start: tank1.tune }
+//     outputs:
+//       output: pump1.tune_speed
+//       active: tank1.tuning       # select output over the PID's while true
+//       done: tank1.tuned
+//       kp: tank1.suggested_kp
+//       ki: tank1.suggested_ki
+//       kd: tank1.suggested_kd
+//     params:
+//       bias: 50.0                 # output the relay switches around
+//       amplitude: 10.0            # relay step above and below bias
+//       hysteresis: 0.5            # process variable noise band
+//       cycles: 3                  # oscillations averaged
+//       rule: ziegler_nichols      # ziegler_nichols (default), tyreus_luyben or pi
+//       timeout_ms: 600000
+//
+// A rising edge of `start` begins the experiment (Åström–Hägglund): the
+// output is switched to bias + amplitude while the process variable is
+// below the setpoint and to bias - amplitude while it is above, which makes
+// the process oscillate. From the period Pu and amplitude a of the
+// oscillation the ultimate gain is Ku = 4 * amplitude / (π * a), and the
+// rule turns Ku and Pu into gains in the form the PID block takes. The
+// suggestions are only written to signals; the operator decides whether to
+// apply them. Dropping `start` or reaching the timeout aborts.
+
+use super::persistence::{decode_state, encode_state};
+use super::{get_array_parameter, get_input_signal, get_numeric_parameter, get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Integral handling while the output is saturated
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AntiWindup {
+    /// Integrate regardless of saturation
+    None,
+    /// Stop integrating while the error drives the output further into
+    /// saturation
+    Clamp,
+    /// Bleed the integral by the saturation excess times this gain
+    BackCalculation(f64),
+}
+
+/// Signal the derivative term acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Derivative {
+    Error,
+    Measurement,
+}
+
+/// Controller gains
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+struct Gains {
+    kp: f64,
+    ki: f64,
+    kd: f64,
+}
+
+/// Gains at one value of the schedule variable
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct GainPoint {
+    at: f64,
+    #[serde(flatten)]
+    gains: Gains,
+}
+
+/// Retained state of a [`PidBlock`]
+#[derive(Serialize, Deserialize)]
+struct PidState {
+    integral: f64,
+    output: Option<f64>,
+}
+
+/// PID controller
+pub struct PidBlock {
+    name: String,
+    setpoint_input: String,
+    process_variable_input: String,
+    auto_input: Option<String>,
+    manual_input: Option<String>,
+    schedule_input: Option<String>,
+    output: String,
+    gains: Gains,
+    /// Gain schedule, sorted by `at`
+    schedule: Vec<GainPoint>,
+    output_min: f64,
+    output_max: f64,
+    anti_windup: AntiWindup,
+    derivative: Derivative,
+    /// Integral contribution to the output
+    integral: f64,
+    last_output: Option<f64>,
+    /// Error, process variable and bus time of the previous scan
+    last: Option<(f64, f64, Instant)>,
+}
+
+impl PidBlock {
+    /// Gains at `x`, interpolated linearly between schedule points and held
+    /// beyond the first and last
+    fn scheduled_gains(&self, x: f64) -> Gains {
+        let Some(next) = self.schedule.iter().position(|point| point.at > x) else {
+            return self.schedule.last().map_or(self.gains, |point| point.gains);
+        };
+        if next == 0 {
+            return self.schedule[0].gains;
+        }
+        let (a, b) = (self.schedule[next - 1], self.schedule[next]);
+        let t = (x - a.at) / (b.at - a.at);
+        let lerp = |from: f64, to: f64| from + (to - from) * t;
+        Gains {
+            kp: lerp(a.gains.kp, b.gains.kp),
+            ki: lerp(a.gains.ki, b.gains.ki),
+            kd: lerp(a.gains.kd, b.gains.kd),
+        }
+    }
+}
+
+impl Block for PidBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let setpoint = bus.get_float(&self.setpoint_input)?;
+        let pv = bus.get_float(&self.process_variable_input)?;
+        let auto = match &self.auto_input {
+            Some(input) => bus.get_bool(input)?,
+            None => true,
+        };
+        let gains = match &self.schedule_input {
+            Some(input) => self.scheduled_gains(bus.get_float(input)?),
+            None => self.gains,
+        };
+        let now = bus.now();
+        let error = setpoint - pv;
+
+        let dt = self
+            .last
+            .map(|(_, _, time)| now.saturating_duration_since(time).as_secs_f64())
+            .filter(|dt| *dt > 0.0);
+        let proportional = gains.kp * error;
+        let derivative = match (dt, self.last) {
+            (Some(dt), Some((last_error, last_pv, _))) => match self.derivative {
+                Derivative::Error => gains.kd * (error - last_error) / dt,
+                Derivative::Measurement => -gains.kd * (pv - last_pv) / dt,
+            },
+            _ => 0.0,
+        };
+
+        let output = if auto {
+            let integral = self.integral + dt.map_or(0.0, |dt| gains.ki * error * dt);
+            let unclamped = proportional + integral + derivative;
+            let output = unclamped.clamp(self.output_min, self.output_max);
+            self.integral = match (self.anti_windup, dt) {
+                (AntiWindup::None, _) | (AntiWindup::BackCalculation(_), None) => integral,
+                (AntiWindup::Clamp, _) => {
+                    let winding = (unclamped > self.output_max && gains.ki * error > 0.0)
+                        || (unclamped < self.output_min && gains.ki * error < 0.0);
+                    if winding {
+                        self.integral
+                    } else {
+                        integral
+                    }
+                }
+                (AntiWindup::BackCalculation(gain), Some(dt)) => integral + gain * (output - unclamped) * dt,
+            };
+            output
+        } else {
+            let manual = match &self.manual_input {
+                Some(input) => bus.get_float(input)?,
+                None => self.last_output.unwrap_or(0.0),
+            };
+            let output = manual.clamp(self.output_min, self.output_max);
+            // Track the manual output, so automatic resumes from it
+            self.integral = output - proportional - derivative;
+            output
+        };
+
+        self.last = Some((error, pv, now));
+        self.last_output = Some(output);
+        bus.set(&self.output, Value::Float(output))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "PID"
+    }
+
+    fn category(&self) -> &'static str {
+        "control"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.integral = 0.0;
+        self.last_output = None;
+        self.last = None;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&PidState {
+            integral: self.integral,
+            output: self.last_output,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: PidState = decode_state(&self.name, state)?;
+        self.integral = state.integral;
+        self.last_output = state.output;
+        // Instants do not survive a restart; integration and the derivative
+        // resume from the second scan
+        self.last = None;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        [
+            Some(self.setpoint_input.as_str()),
+            Some(self.process_variable_input.as_str()),
+            self.auto_input.as_deref(),
+            self.manual_input.as_deref(),
+            self.schedule_input.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+// ============================================================================
+// PID_AUTOTUNE
+// ============================================================================
+
+/// Tuning rule turning the ultimate gain and period into PID gains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TuningRule {
+    ZieglerNichols,
+    TyreusLuyben,
+    /// Ziegler–Nichols PI, without derivative
+    Pi,
+}
+
+impl TuningRule {
+    /// Gains for ultimate gain `ku` and period `pu` in seconds
+    fn gains(self, ku: f64, pu: f64) -> Gains {
+        // Proportional gain, integral time and derivative time
+        let (kp, ti, td) = match self {
+            Self::ZieglerNichols => (0.6 * ku, pu / 2.0, pu / 8.0),
+            Self::TyreusLuyben => (ku / 2.2, 2.2 * pu, pu / 6.3),
+            Self::Pi => (0.45 * ku, pu / 1.2, 0.0),
+        };
+        Gains { kp, ki: kp / ti, kd: kp * td }
+    }
+}
+
+/// Relay experiment in progress
+#[derive(Debug)]
+struct Experiment {
+    started: Instant,
+    /// Relay above bias
+    high: bool,
+    /// Start of the current oscillation, at the relay's last switch to high
+    cycle_start: Option<Instant>,
+    pv_min: f64,
+    pv_max: f64,
+    /// Period and peak-to-peak amplitude of each completed oscillation
+    cycles: Vec<(f64, f64)>,
+}
+
+/// Relay-feedback PID autotuner
+pub struct PidAutotuneBlock {
+    name: String,
+    setpoint_input: String,
+    process_variable_input: String,
+    start_input: String,
+    output: String,
+    active_output: Option<String>,
+    done_output: Option<String>,
+    gain_outputs: [String; 3],
+    bias: f64,
+    amplitude: f64,
+    hysteresis: f64,
+    cycles: usize,
+    rule: TuningRule,
+    timeout: Option<std::time::Duration>,
+    experiment: Option<Experiment>,
+    last_start: bool,
+}
+
+impl PidAutotuneBlock {
+    fn publish_status(&self, bus: &SignalBus, active: bool, done: bool) -> Result<()> {
+        if let Some(output) = &self.active_output {
+            bus.set(output, Value::Bool(active))?;
+        }
+        if let Some(output) = &self.done_output {
+            bus.set(output, Value::Bool(done))?;
+        }
+        Ok(())
+    }
+
+    /// Gains from the oscillations measured, once there are enough
+    fn result(&self, experiment: &Experiment) -> Option<Gains> {
+        if experiment.cycles.len() < self.cycles {
+            return None;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let n = experiment.cycles.len() as f64;
+        let period = experiment.cycles.iter().map(|(period, _)| period).sum::<f64>() / n;
+        let peak_to_peak = experiment.cycles.iter().map(|(_, amplitude)| amplitude).sum::<f64>() / n;
+        let ku = 4.0 * self.amplitude / (std::f64::consts::PI * peak_to_peak / 2.0);
+        Some(self.rule.gains(ku, period))
+    }
+}
+
+impl Block for PidAutotuneBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let start = bus.get_bool(&self.start_input)?;
+        let now = bus.now();
+        let rising = start && !self.last_start;
+        self.last_start = start;
+
+        if rising {
+            self.experiment = Some(Experiment {
+                started: now,
+                high: false,
+                cycle_start: None,
+                pv_min: f64::INFINITY,
+                pv_max: f64::NEG_INFINITY,
+                cycles: Vec::new(),
+            });
+            self.publish_status(bus, true, false)?;
+        }
+        let Some(mut experiment) = self.experiment.take() else {
+            return Ok(());
+        };
+        if !start || self.timeout.is_some_and(|timeout| now.saturating_duration_since(experiment.started) > timeout) {
+            tracing::warn!("PID_AUTOTUNE block '{}' aborted", self.name);
+            return self.publish_status(bus, false, false);
+        }
+
+        let setpoint = bus.get_float(&self.setpoint_input)?;
+        let pv = bus.get_float(&self.process_variable_input)?;
+        experiment.pv_min = experiment.pv_min.min(pv);
+        experiment.pv_max = experiment.pv_max.max(pv);
+        if rising {
+            experiment.high = pv < setpoint;
+        } else if !experiment.high && pv < setpoint - self.hysteresis {
+            experiment.high = true;
+            if let Some(cycle_start) = experiment.cycle_start {
+                let period = now.saturating_duration_since(cycle_start).as_secs_f64();
+                experiment.cycles.push((period, experiment.pv_max - experiment.pv_min));
+            }
+            // Oscillations are measured from one switch to high to the next
+            experiment.cycle_start = Some(now);
+            experiment.pv_min = pv;
+            experiment.pv_max = pv;
+        } else if experiment.high && pv > setpoint + self.hysteresis {
+            experiment.high = false;
+        }
+
+        if let Some(gains) = self.result(&experiment) {
+            tracing::info!(
+                "PID_AUTOTUNE block '{}' suggests kp {:.4}, ki {:.4}, kd {:.4}",
+                self.name, gains.kp, gains.ki, gains.kd
+            );
+            for (output, gain) in self.gain_outputs.iter().zip([gains.kp, gains.ki, gains.kd]) {
+                bus.set(output, Value::Float(gain))?;
+            }
+            bus.set(&self.output, Value::Float(self.bias))?;
+            return self.publish_status(bus, false, true);
+        }
+
+        let relay = if experiment.high { self.amplitude } else { -self.amplitude };
+        bus.set(&self.output, Value::Float(self.bias + relay))?;
+        self.experiment = Some(experiment);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "PID_AUTOTUNE"
+    }
+
+    fn category(&self) -> &'static str {
+        "control"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.experiment = None;
+        self.last_start = false;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        vec![
+            self.setpoint_input.as_str(),
+            self.process_variable_input.as_str(),
+            self.start_input.as_str(),
+        ]
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        std::iter::once(self.output.as_str())
+            .chain(self.gain_outputs.iter().map(String::as_str))
+            .chain(self.active_output.as_deref())
+            .chain(self.done_output.as_deref())
+            .collect()
+    }
+
+    // A restart never resumes an experiment, but a `start` held high across
+    // it must not begin a new one
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&self.last_start)
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        self.last_start = decode_state(&self.name, state)?;
+        Ok(())
+    }
+}
+
+/// Factory function for `PID_AUTOTUNE` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without the `setpoint`, `process_variable`
+/// and `start` inputs or the `output`, `kp`, `ki` and `kd` outputs, with an
+/// `amplitude` or `cycles` that is not greater than 0, a negative
+/// `hysteresis`, or an unknown `rule`.
+pub fn create_pid_autotune_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let amplitude: f64 = get_numeric_parameter(config, "amplitude", Some(10.0))?;
+    let hysteresis: f64 = get_numeric_parameter(config, "hysteresis", Some(0.0))?;
+    let cycles: usize = get_numeric_parameter(config, "cycles", Some(3))?;
+    if !(amplitude > 0.0 && hysteresis >= 0.0 && cycles > 0) {
+        return Err(PlcError::Config(format!(
+            "PID_AUTOTUNE block '{}' needs amplitude and cycles greater than 0 and hysteresis of at least 0",
+            config.name
+        )));
+    }
+    let rule = match get_string_parameter(config, "rule", Some("ziegler_nichols"))?.as_str() {
+        "ziegler_nichols" => TuningRule::ZieglerNichols,
+        "tyreus_luyben" => TuningRule::TyreusLuyben,
+        "pi" => TuningRule::Pi,
+        other => {
+            return Err(PlcError::Config(format!(
+                "PID_AUTOTUNE block '{}' rule must be ziegler_nichols, tyreus_luyben or pi, not '{other}'",
+                config.name
+            )))
+        }
+    };
+    let timeout = match get_numeric_parameter(config, "timeout_ms", Some(0))? {
+        0 => None,
+        ms => Some(std::time::Duration::from_millis(ms)),
+    };
+    let required_output = |port: &str| get_output_signal(config, port, true).map(Option::unwrap_or_default);
+
+    Ok(Box::new(PidAutotuneBlock {
+        name: config.name.clone(),
+        setpoint_input: get_input_signal(config, "setpoint", true)?.unwrap_or_default(),
+        process_variable_input: get_input_signal(config, "process_variable", true)?.unwrap_or_default(),
+        start_input: get_input_signal(config, "start", true)?.unwrap_or_default(),
+        output: required_output("output")?,
+        active_output: get_output_signal(config, "active", false)?,
+        done_output: get_output_signal(config, "done", false)?,
+        gain_outputs: [required_output("kp")?, required_output("ki")?, required_output("kd")?],
+        bias: get_numeric_parameter(config, "bias", Some(50.0))?,
+        amplitude,
+        hysteresis,
+        cycles,
+        rule,
+        timeout,
+        experiment: None,
+        last_start: false,
+    }))
+}
+
+/// Optional numeric parameter
+fn optional_parameter(config: &BlockConfig, name: &str) -> Result<Option<f64>> {
+    if config.params.contains_key(name) {
+        get_numeric_parameter(config, name, None).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Factory function for PID blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without the `setpoint` and
+/// `process_variable` inputs or the `output` output, with `output_min`
+/// above `output_max`, an unknown `anti_windup` or `derivative`, a
+/// `schedule` without a `schedule` input or with points out of order, or
+/// a `schedule` input without a schedule.
+pub fn create_pid_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let gains = Gains {
+        kp: get_numeric_parameter(config, "kp", Some(1.0))?,
+        ki: get_numeric_parameter(config, "ki", Some(0.0))?,
+        kd: get_numeric_parameter(config, "kd", Some(0.0))?,
+    };
+    let output_min = optional_parameter(config, "output_min")?.unwrap_or(f64::NEG_INFINITY);
+    let output_max = optional_parameter(config, "output_max")?.unwrap_or(f64::INFINITY);
+    if output_min > output_max {
+        return Err(PlcError::Config(format!(
+            "PID block '{}' output_min ({}) must not exceed output_max ({})",
+            config.name, output_min, output_max
+        )));
+    }
+
+    let anti_windup = match get_string_parameter(config, "anti_windup", Some("clamp"))?.as_str() {
+        "none" => AntiWindup::None,
+        "clamp" => AntiWindup::Clamp,
+        "back_calculation" => {
+            AntiWindup::BackCalculation(get_numeric_parameter(config, "tracking_gain", Some(1.0))?)
+        }
+        other => {
+            return Err(PlcError::Config(format!(
+                "PID block '{}' anti_windup must be none, clamp or back_calculation, not '{other}'",
+                config.name
+            )))
+        }
+    };
+    let derivative = match get_string_parameter(config, "derivative", Some("error"))?.as_str() {
+        "error" => Derivative::Error,
+        "measurement" => Derivative::Measurement,
+        other => {
+            return Err(PlcError::Config(format!(
+                "PID block '{}' derivative must be error or measurement, not '{other}'",
+                config.name
+            )))
+        }
+    };
+
+    let schedule: Vec<GainPoint> = get_array_parameter(config, "schedule", Some(Vec::new()))?;
+    if schedule.windows(2).any(|pair| pair[0].at >= pair[1].at) {
+        return Err(PlcError::Config(format!(
+            "PID block '{}' schedule points must be in increasing order of 'at'",
+            config.name
+        )));
+    }
+    let schedule_input = get_input_signal(config, "schedule", false)?;
+    if schedule.is_empty() != schedule_input.is_none() {
+        return Err(PlcError::Config(format!(
+            "PID block '{}' gain scheduling needs both the 'schedule' parameter and the 'schedule' input",
+            config.name
+        )));
+    }
+
+    Ok(Box::new(PidBlock {
+        name: config.name.clone(),
+        setpoint_input: get_input_signal(config, "setpoint", true)?.unwrap_or_default(),
+        process_variable_input: get_input_signal(config, "process_variable", true)?.unwrap_or_default(),
+        auto_input: get_input_signal(config, "auto", false)?,
+        manual_input: get_input_signal(config, "manual_output", false)?,
+        schedule_input,
+        output: get_output_signal(config, "output", true)?.unwrap_or_default(),
+        gains,
+        schedule,
+        output_min,
+        output_max,
+        anti_windup,
+        derivative,
+        integral: 0.0,
+        last_output: None,
+        last: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn pid(inputs: &str, params: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: pid, type: PID, inputs: {{ setpoint: sp, process_variable: pv, {inputs} }}, \
+             outputs: {{ output: out }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    fn scan(block: &mut Box<dyn Block>, bus: &SignalBus, clock: &SimulatedClock) -> f64 {
+        block.execute(bus).unwrap();
+        clock.advance(Duration::from_secs(1));
+        bus.get_float("out").unwrap()
+    }
+
+    #[test]
+    fn test_anti_windup_and_bumpless_transfer() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("sp", Value::Float(10.0)).unwrap();
+        bus.set("pv", Value::Float(0.0)).unwrap();
+        bus.set("auto", Value::Bool(true)).unwrap();
+        bus.set("manual", Value::Float(40.0)).unwrap();
+        let mut block = pid(
+            "auto: auto, manual_output: manual",
+            "kp: 1.0, ki: 1.0, output_min: 0.0, output_max: 20.0",
+        );
+
+        // Saturated at 20: the integral stops growing, so the output leaves
+        // saturation as soon as the error reverses
+        assert_eq!(scan(&mut block, &bus, &clock), 10.0);
+        for _ in 0..10 {
+            assert_eq!(scan(&mut block, &bus, &clock), 20.0);
+        }
+        bus.set("pv", Value::Float(25.0)).unwrap();
+        assert!(scan(&mut block, &bus, &clock) < 20.0);
+
+        // Manual follows the operator; automatic resumes from there
+        bus.set("auto", Value::Bool(false)).unwrap();
+        bus.set("manual", Value::Float(12.0)).unwrap();
+        assert_eq!(scan(&mut block, &bus, &clock), 12.0);
+        bus.set("pv", Value::Float(10.0)).unwrap();
+        assert_eq!(scan(&mut block, &bus, &clock), 12.0);
+        bus.set("auto", Value::Bool(true)).unwrap();
+        assert_eq!(scan(&mut block, &bus, &clock), 12.0);
+    }
+
+    #[test]
+    fn test_autotune_finds_ultimate_gain_of_delayed_integrator() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("sp", Value::Float(0.0)).unwrap();
+        bus.set("pv", Value::Float(0.0)).unwrap();
+        bus.set("start", Value::Bool(true)).unwrap();
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: tune, type: PID_AUTOTUNE, inputs: { setpoint: sp, process_variable: pv, start: start }, \
+             outputs: { output: u, done: done, kp: kp, ki: ki, kd: kd }, params: { bias: 0.0, amplitude: 10.0 } }",
+        )
+        .unwrap();
+        let mut tune = super::super::create_block(&config).unwrap();
+
+        // dpv/dt = u delayed by 1 s: the relay oscillation has Pu = 4 s and
+        // an amplitude of 10, so Ku = 4 / π
+        let step = Duration::from_millis(10);
+        let mut delayed = std::collections::VecDeque::from(vec![0.0; 100]);
+        let mut pv = 0.0;
+        for _ in 0..3000 {
+            tune.execute(&bus).unwrap();
+            if bus.get("done") == Some(Value::Bool(true)) {
+                break;
+            }
+            delayed.push_back(bus.get_float("u").unwrap());
+            pv += delayed.pop_front().unwrap() * step.as_secs_f64();
+            bus.set("pv", Value::Float(pv)).unwrap();
+            clock.advance(step);
+        }
+
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+        let ku = 4.0 / std::f64::consts::PI;
+        let kp = bus.get_float("kp").unwrap();
+        assert!((kp - 0.6 * ku).abs() < 0.05 * 0.6 * ku, "kp {kp}");
+        let ti = kp / bus.get_float("ki").unwrap();
+        assert!((ti - 2.0).abs() < 0.1, "ti {ti}");
+        assert_eq!(bus.get_float("u").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_gain_schedule_and_derivative_on_measurement() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("sp", Value::Float(10.0)).unwrap();
+        bus.set("pv", Value::Float(0.0)).unwrap();
+        bus.set("load", Value::Float(25.0)).unwrap();
+        let mut block = pid(
+            "schedule: load",
+            "schedule: [{ at: 0.0, kp: 2.0, ki: 0.0, kd: 0.0 }, { at: 50.0, kp: 4.0, ki: 0.0, kd: 0.0 }]",
+        );
+        // Interpolated between the points, held beyond the last
+        assert_eq!(scan(&mut block, &bus, &clock), 30.0);
+        bus.set("sp", Value::Float(20.0)).unwrap();
+        assert_eq!(scan(&mut block, &bus, &clock), 60.0);
+        bus.set("load", Value::Float(80.0)).unwrap();
+        assert_eq!(scan(&mut block, &bus, &clock), 80.0);
+
+        let mut measured = pid("", "kp: 0.0, kd: 2.0, derivative: measurement");
+        scan(&mut measured, &bus, &clock);
+        bus.set("sp", Value::Float(50.0)).unwrap();
+        assert_eq!(scan(&mut measured, &bus, &clock), 0.0);
+        bus.set("pv", Value::Float(3.0)).unwrap();
+        assert_eq!(scan(&mut measured, &bus, &clock), -6.0);
+
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: bad, type: PID, inputs: { setpoint: sp, process_variable: pv }, outputs: { output: out }, \
+             params: { anti_windup: sometimes } }",
+        )
+        .unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+// src/blocks/rolling.rs - Moving window statistics block for PETRA
+//
+// Purpose:
+// --------
+// Implements ROLLING_STATS, which keeps the mean, minimum, maximum and
+// standard deviation of an input over a moving window of the last N samples
+// or the last N milliseconds. Every scan costs O(1) amortized regardless of
+// the window length, so long windows are usable at 10 ms scan times.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: the numeric input `in`, sampled once per scan, and an optional
+//   boolean `reset` input that empties the window while it is true
+// - Writes: any of the outputs `mean`, `min`, `max`, `stddev` (population)
+//   and `count`; nothing is written while the window is empty
+//
+// Configuration:
+// --------------
+//   - name: pressure_stats
+//     type: ROLLING_STATS
+//     inputs: { in: line.pressure }
+//     outputs: { mean: line.pressure_avg, max: line.pressure_peak, stddev: line.pressure_noise }
+//     params:
+//       window_ms: 60000     # or window_samples: 6000
+//
+// Algorithm:
+// ----------
+// Samples are kept in a ring buffer. The mean and variance are updated with
+// Welford's method as samples enter and leave the window, and recomputed
+// from the buffer once per window turnover so rounding errors cannot build
+// up. Minimum and maximum come from monotonic queues holding only the
+// samples that can still become the extreme. Samples that are not finite
+// are skipped.
+
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Length of the moving window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowLength {
+    Samples(usize),
+    Time(Duration),
+}
+
+/// Samples of the window and their running statistics
+#[derive(Debug, Default)]
+struct Window {
+    samples: VecDeque<(Instant, f64)>,
+    /// Sequence number of the oldest sample
+    first_seq: u64,
+    /// Candidates for the minimum, increasing, with their sequence numbers
+    mins: VecDeque<(u64, f64)>,
+    /// Candidates for the maximum, decreasing, with their sequence numbers
+    maxs: VecDeque<(u64, f64)>,
+    mean: f64,
+    /// Sum of squared deviations from the mean
+    m2: f64,
+    /// Samples removed since the statistics were last recomputed
+    removed: usize,
+}
+
+impl Window {
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn push(&mut self, time: Instant, x: f64) {
+        let seq = self.first_seq + self.samples.len() as u64;
+        self.samples.push_back((time, x));
+        while self.mins.back().is_some_and(|&(_, v)| v >= x) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((seq, x));
+        while self.maxs.back().is_some_and(|&(_, v)| v <= x) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((seq, x));
+
+        #[allow(clippy::cast_precision_loss)]
+        let n = self.samples.len() as f64;
+        let delta = x - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn pop(&mut self) {
+        let Some((_, x)) = self.samples.pop_front() else {
+            return;
+        };
+        if self.mins.front().is_some_and(|&(seq, _)| seq == self.first_seq) {
+            self.mins.pop_front();
+        }
+        if self.maxs.front().is_some_and(|&(seq, _)| seq == self.first_seq) {
+            self.maxs.pop_front();
+        }
+        self.first_seq += 1;
+
+        self.removed += 1;
+        if self.removed >= self.samples.len() {
+            self.recompute();
+            return;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let n = self.samples.len() as f64;
+        let delta = x - self.mean;
+        self.mean -= delta / n;
+        self.m2 -= delta * (x - self.mean);
+    }
+
+    /// Statistics from scratch, in O(n) once every n removals
+    fn recompute(&mut self) {
+        self.removed = 0;
+        if self.samples.is_empty() {
+            self.mean = 0.0;
+            self.m2 = 0.0;
+            return;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let n = self.samples.len() as f64;
+        self.mean = self.samples.iter().map(|&(_, x)| x).sum::<f64>() / n;
+        self.m2 = self.samples.iter().map(|&(_, x)| (x - self.mean).powi(2)).sum();
+    }
+
+    fn stddev(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let n = self.samples.len() as f64;
+        (self.m2.max(0.0) / n).sqrt()
+    }
+}
+
+/// Statistics of an input over a moving window
+pub struct RollingStatsBlock {
+    name: String,
+    input: String,
+    reset_input: Option<String>,
+    mean_output: Option<String>,
+    min_output: Option<String>,
+    max_output: Option<String>,
+    stddev_output: Option<String>,
+    count_output: Option<String>,
+    length: WindowLength,
+    window: Window,
+}
+
+impl RollingStatsBlock {
+    fn outputs(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.mean_output,
+            &self.min_output,
+            &self.max_output,
+            &self.stddev_output,
+            &self.count_output,
+        ]
+        .into_iter()
+        .filter_map(Option::as_deref)
+    }
+}
+
+impl Block for RollingStatsBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        if let Some(reset) = &self.reset_input {
+            if bus.get_bool(reset)? {
+                self.window.clear();
+                return Ok(());
+            }
+        }
+
+        let x = bus.get_float(&self.input)?;
+        let now = bus.now();
+        if x.is_finite() {
+            self.window.push(now, x);
+        }
+        match self.length {
+            WindowLength::Samples(samples) => {
+                while self.window.len() > samples {
+                    self.window.pop();
+                }
+            }
+            WindowLength::Time(duration) => {
+                while self
+                    .window
+                    .samples
+                    .front()
+                    .is_some_and(|&(time, _)| now.saturating_duration_since(time) > duration)
+                {
+                    self.window.pop();
+                }
+            }
+        }
+
+        let (Some(&(_, min)), Some(&(_, max))) = (self.window.mins.front(), self.window.maxs.front()) else {
+            return Ok(());
+        };
+        if let Some(output) = &self.mean_output {
+            bus.set(output, Value::Float(self.window.mean))?;
+        }
+        if let Some(output) = &self.min_output {
+            bus.set(output, Value::Float(min))?;
+        }
+        if let Some(output) = &self.max_output {
+            bus.set(output, Value::Float(max))?;
+        }
+        if let Some(output) = &self.stddev_output {
+            bus.set(output, Value::Float(self.window.stddev()))?;
+        }
+        if let Some(output) = &self.count_output {
+            bus.set(output, Value::Integer(i64::try_from(self.window.len()).unwrap_or(i64::MAX)))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "ROLLING_STATS"
+    }
+
+    fn category(&self) -> &'static str {
+        "math"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.window.clear();
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        std::iter::once(self.input.as_str()).chain(self.reset_input.as_deref()).collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.outputs().collect()
+    }
+}
+
+/// Factory function for `ROLLING_STATS` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input or any output, or
+/// unless exactly one of `window_samples` and `window_ms` is given and
+/// greater than 0.
+pub fn create_rolling_stats_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let length = match (config.params.contains_key("window_samples"), config.params.contains_key("window_ms")) {
+        (true, false) => WindowLength::Samples(get_numeric_parameter(config, "window_samples", None)?),
+        (false, true) => WindowLength::Time(Duration::from_millis(get_numeric_parameter(config, "window_ms", None)?)),
+        _ => {
+            return Err(PlcError::Config(format!(
+                "ROLLING_STATS block '{}' needs either 'window_samples' or 'window_ms'",
+                config.name
+            )))
+        }
+    };
+    if matches!(length, WindowLength::Samples(0)) || length == WindowLength::Time(Duration::ZERO) {
+        return Err(PlcError::Config(format!(
+            "ROLLING_STATS block '{}' window must be greater than 0",
+            config.name
+        )));
+    }
+
+    let block = RollingStatsBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        reset_input: get_input_signal(config, "reset", false)?,
+        mean_output: get_output_signal(config, "mean", false)?,
+        min_output: get_output_signal(config, "min", false)?,
+        max_output: get_output_signal(config, "max", false)?,
+        stddev_output: get_output_signal(config, "stddev", false)?,
+        count_output: get_output_signal(config, "count", false)?,
+        length,
+        window: Window::default(),
+    };
+    if block.outputs().next().is_none() {
+        return Err(PlcError::Config(format!(
+            "ROLLING_STATS block '{}' needs at least one of the outputs mean, min, max, stddev or count",
+            config.name
+        )));
+    }
+    Ok(Box::new(block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+
+    fn rolling_stats(params: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: stats, type: ROLLING_STATS, inputs: {{ in: x }}, \
+             outputs: {{ mean: mean, min: min, max: max, stddev: sd, count: n }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    fn float(bus: &SignalBus, name: &str) -> f64 {
+        bus.get_float(name).unwrap()
+    }
+
+    #[test]
+    fn test_sample_window_matches_direct_computation() {
+        let bus = SignalBus::new();
+        let mut stats = rolling_stats("window_samples: 5");
+        let inputs: Vec<f64> = (0..200).map(|i| f64::from((i * 37) % 23) + 1e6).collect();
+        for (i, &x) in inputs.iter().enumerate() {
+            bus.set("x", Value::Float(x)).unwrap();
+            stats.execute(&bus).unwrap();
+
+            let window = &inputs[i.saturating_sub(4)..=i];
+            let n = window.len() as f64;
+            let mean = window.iter().sum::<f64>() / n;
+            let sd = (window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+            assert!((float(&bus, "mean") - mean).abs() < 1e-6);
+            assert!((float(&bus, "sd") - sd).abs() < 1e-6);
+            assert_eq!(float(&bus, "min"), window.iter().copied().fold(f64::INFINITY, f64::min));
+            assert_eq!(float(&bus, "max"), window.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+            assert_eq!(bus.get("n"), Some(Value::Integer(window.len() as i64)));
+        }
+    }
+
+    #[test]
+    fn test_time_window_drops_old_samples() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let mut stats = rolling_stats("window_ms: 1000");
+        for x in [10.0, 2.0, 6.0] {
+            bus.set("x", Value::Float(x)).unwrap();
+            stats.execute(&bus).unwrap();
+            clock.advance(Duration::from_millis(400));
+        }
+        // 10.0 is now 1200 ms old
+        bus.set("x", Value::Float(f64::NAN)).unwrap();
+        stats.execute(&bus).unwrap();
+        assert_eq!(bus.get("n"), Some(Value::Integer(2)));
+        assert_eq!(bus.get("mean"), Some(Value::Float(4.0)));
+        assert_eq!(bus.get("max"), Some(Value::Float(6.0)));
+        assert_eq!(bus.get("sd"), Some(Value::Float(2.0)));
+
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: bad, type: ROLLING_STATS, inputs: { in: x }, outputs: { mean: m }, params: { window_ms: 10, window_samples: 3 } }",
+        )
+        .unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
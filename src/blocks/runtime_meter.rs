@@ -0,0 +1,339 @@
+// src/blocks/runtime_meter.rs - Run hour meter block for PETRA
+//
+// Purpose:
+// --------
+// Implements RUNTIME_METER, which counts the running hours and starts of a
+// piece of equipment, and raises a service due output once the hours or
+// starts since the last service reach their thresholds. The counters are
+// part of the block state, so with `block_state` configured they survive
+// engine restarts.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait, parameter helpers and state persistence from
+//   blocks/mod.rs and blocks/persistence.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: the boolean `run` input, and an optional `serviced` input whose
+//   rising edge restarts the counters since the last service
+// - Writes: `hours` and the optional outputs `starts`, `hours_since_service`,
+//   `starts_since_service` and `service_due`
+//
+// Configuration:
+// --------------
+//   - name: compressor_hours
+//     type: RUNTIME_METER
+//     inputs: { run: comp1.running, serviced: comp1.service_done }
+//     outputs: { hours: comp1.run_hours, starts: comp1.starts, service_due: comp1.service_due }
+//     params:
+//       service_hours: 2000     # service every 2000 running hours
+//       service_starts: 5000    # or every 5000 starts, whichever is first
+//       initial_hours: 12850.5  # meter reading of the equipment when fitted
+//
+// A start is a rising edge of `run`; equipment already running when the
+// engine restarts is not counted as started again. Time while the engine is
+// stopped is not counted.
+
+use super::persistence::{decode_state, encode_state};
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tracing::info;
+
+/// Saved state of a `RUNTIME_METER` block
+#[derive(Serialize, Deserialize)]
+struct RuntimeMeterState {
+    hours: f64,
+    starts: i64,
+    hours_since_service: f64,
+    starts_since_service: i64,
+    running: bool,
+    serviced: bool,
+}
+
+/// Run hours and starts of a piece of equipment
+pub struct RuntimeMeterBlock {
+    name: String,
+    run_input: String,
+    serviced_input: Option<String>,
+    hours_output: String,
+    starts_output: Option<String>,
+    hours_since_service_output: Option<String>,
+    starts_since_service_output: Option<String>,
+    service_due_output: Option<String>,
+    service_hours: Option<f64>,
+    service_starts: Option<i64>,
+    initial_hours: f64,
+    initial_starts: i64,
+    hours: f64,
+    starts: i64,
+    hours_since_service: f64,
+    starts_since_service: i64,
+    running: bool,
+    serviced: bool,
+    /// Bus time of the previous scan
+    last_scan: Option<Instant>,
+}
+
+impl RuntimeMeterBlock {
+    fn service_due(&self) -> bool {
+        self.service_hours.is_some_and(|hours| self.hours_since_service >= hours)
+            || self.service_starts.is_some_and(|starts| self.starts_since_service >= starts)
+    }
+}
+
+impl Block for RuntimeMeterBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let run = bus.get_bool(&self.run_input)?;
+        let now = bus.now();
+        if let Some(last) = self.last_scan {
+            if self.running {
+                let hours = now.saturating_duration_since(last).as_secs_f64() / 3600.0;
+                self.hours += hours;
+                self.hours_since_service += hours;
+            }
+        }
+        self.last_scan = Some(now);
+        if run && !self.running {
+            self.starts = self.starts.saturating_add(1);
+            self.starts_since_service = self.starts_since_service.saturating_add(1);
+        }
+        self.running = run;
+
+        if let Some(input) = &self.serviced_input {
+            let serviced = bus.get_bool(input)?;
+            if serviced && !self.serviced {
+                info!(
+                    "RUNTIME_METER block '{}' serviced after {:.1} h and {} starts",
+                    self.name,
+                    self.hours_since_service,
+                    self.starts_since_service
+                );
+                self.hours_since_service = 0.0;
+                self.starts_since_service = 0;
+            }
+            self.serviced = serviced;
+        }
+
+        bus.set(&self.hours_output, Value::Float(self.hours))?;
+        if let Some(output) = &self.starts_output {
+            bus.set(output, Value::Integer(self.starts))?;
+        }
+        if let Some(output) = &self.hours_since_service_output {
+            bus.set(output, Value::Float(self.hours_since_service))?;
+        }
+        if let Some(output) = &self.starts_since_service_output {
+            bus.set(output, Value::Integer(self.starts_since_service))?;
+        }
+        if let Some(output) = &self.service_due_output {
+            bus.set(output, Value::Bool(self.service_due()))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "RUNTIME_METER"
+    }
+
+    fn category(&self) -> &'static str {
+        "timer"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.hours = self.initial_hours;
+        self.starts = self.initial_starts;
+        self.hours_since_service = 0.0;
+        self.starts_since_service = 0;
+        self.running = false;
+        self.serviced = false;
+        self.last_scan = None;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&RuntimeMeterState {
+            hours: self.hours,
+            starts: self.starts,
+            hours_since_service: self.hours_since_service,
+            starts_since_service: self.starts_since_service,
+            running: self.running,
+            serviced: self.serviced,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: RuntimeMeterState = decode_state(&self.name, state)?;
+        self.hours = state.hours;
+        self.starts = state.starts;
+        self.hours_since_service = state.hours_since_service;
+        self.starts_since_service = state.starts_since_service;
+        self.running = state.running;
+        self.serviced = state.serviced;
+        self.last_scan = None;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        std::iter::once(self.run_input.as_str()).chain(self.serviced_input.as_deref()).collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        std::iter::once(self.hours_output.as_str())
+            .chain(
+                [
+                    &self.starts_output,
+                    &self.hours_since_service_output,
+                    &self.starts_since_service_output,
+                    &self.service_due_output,
+                ]
+                .into_iter()
+                .filter_map(Option::as_deref),
+            )
+            .collect()
+    }
+}
+
+/// Factory function for `RUNTIME_METER` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without a `run` input or `hours` output, with
+/// a service threshold that is not greater than 0, or with negative initial
+/// counters.
+pub fn create_runtime_meter_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let error = |message: &str| PlcError::Config(format!("RUNTIME_METER block '{}' {}", config.name, message));
+
+    let service_hours = if config.params.contains_key("service_hours") {
+        let hours: f64 = get_numeric_parameter(config, "service_hours", None)?;
+        if !(hours.is_finite() && hours > 0.0) {
+            return Err(error("service_hours must be greater than 0"));
+        }
+        Some(hours)
+    } else {
+        None
+    };
+    let service_starts = if config.params.contains_key("service_starts") {
+        let starts: i64 = get_numeric_parameter(config, "service_starts", None)?;
+        if starts <= 0 {
+            return Err(error("service_starts must be greater than 0"));
+        }
+        Some(starts)
+    } else {
+        None
+    };
+    let initial_hours: f64 = get_numeric_parameter(config, "initial_hours", Some(0.0))?;
+    let initial_starts: i64 = get_numeric_parameter(config, "initial_starts", Some(0))?;
+    if !(initial_hours.is_finite() && initial_hours >= 0.0) || initial_starts < 0 {
+        return Err(error("initial_hours and initial_starts cannot be negative"));
+    }
+
+    Ok(Box::new(RuntimeMeterBlock {
+        name: config.name.clone(),
+        run_input: get_input_signal(config, "run", true)?.unwrap_or_default(),
+        serviced_input: get_input_signal(config, "serviced", false)?,
+        hours_output: get_output_signal(config, "hours", true)?.unwrap_or_default(),
+        starts_output: get_output_signal(config, "starts", false)?,
+        hours_since_service_output: get_output_signal(config, "hours_since_service", false)?,
+        starts_since_service_output: get_output_signal(config, "starts_since_service", false)?,
+        service_due_output: get_output_signal(config, "service_due", false)?,
+        service_hours,
+        service_starts,
+        initial_hours,
+        initial_starts,
+        hours: initial_hours,
+        starts: initial_starts,
+        hours_since_service: 0.0,
+        starts_since_service: 0,
+        running: false,
+        serviced: false,
+        last_scan: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn meter(params: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: meter, type: RUNTIME_METER, inputs: {{ run: run, serviced: serviced }}, \
+             outputs: {{ hours: hours, starts: starts, service_due: due }}, params: {{ {params} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    fn setup() -> (SignalBus, Arc<SimulatedClock>) {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("run", Value::Bool(false)).unwrap();
+        bus.set("serviced", Value::Bool(false)).unwrap();
+        (bus, clock)
+    }
+
+    #[test]
+    fn test_counts_hours_starts_and_service_due() {
+        let (bus, clock) = setup();
+        let mut block = meter("service_hours: 2, service_starts: 3, initial_hours: 100");
+        let mut scan = |run: bool, advance: Duration| {
+            bus.set("run", Value::Bool(run)).unwrap();
+            block.execute(&bus).unwrap();
+            clock.advance(advance);
+        };
+        scan(true, Duration::from_secs(3600));
+        scan(false, Duration::from_secs(7200));
+        scan(true, Duration::from_secs(1800));
+        assert_eq!(bus.get("hours"), Some(Value::Float(101.0)));
+        scan(true, Duration::ZERO);
+        assert_eq!(bus.get("hours"), Some(Value::Float(101.5)));
+        assert_eq!(bus.get("starts"), Some(Value::Integer(2)));
+        assert_eq!(bus.get("due"), Some(Value::Bool(false)));
+
+        // The third start reaches service_starts
+        scan(false, Duration::ZERO);
+        scan(true, Duration::ZERO);
+        assert_eq!(bus.get("due"), Some(Value::Bool(true)));
+
+        bus.set("serviced", Value::Bool(true)).unwrap();
+        scan(true, Duration::ZERO);
+        assert_eq!(bus.get("due"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("starts"), Some(Value::Integer(3)));
+    }
+
+    #[test]
+    fn test_counters_survive_restart_without_extra_start() {
+        let (bus, clock) = setup();
+        let mut block = meter("service_hours: 1");
+        bus.set("run", Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        clock.advance(Duration::from_secs(2700));
+        block.execute(&bus).unwrap();
+        let state = block.save_state().unwrap();
+
+        let mut restored = meter("service_hours: 1");
+        restored.load_state(state).unwrap();
+        clock.advance(Duration::from_secs(10_000));
+        restored.execute(&bus).unwrap();
+        clock.advance(Duration::from_secs(900));
+        restored.execute(&bus).unwrap();
+        assert_eq!(bus.get("starts"), Some(Value::Integer(1)));
+        assert_eq!(bus.get("due"), Some(Value::Bool(true)));
+
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: bad, type: RUNTIME_METER, inputs: { run: run }, outputs: { hours: h }, params: { service_hours: 0 } }",
+        )
+        .unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
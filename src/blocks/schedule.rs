@@ -0,0 +1,226 @@
+// src/blocks/schedule.rs - Time-of-day scheduler block for PETRA
+//
+// Purpose:
+// --------
+// Implements SCHEDULE, which switches a boolean output on during weekly
+// wall-clock windows in a time zone, with holidays and other date
+// exceptions, for lighting, HVAC occupancy and shift-bound equipment.
+//
+// Interactions:
+// -------------
+// - Uses: schedule evaluation from schedule.rs, block state persistence from
+//   blocks/persistence.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: the wall-clock time of the bus clock
+// - Writes: the boolean output `out`
+// - Shares: its schedule in the bus schedule table under the block name,
+//   where `PUT /api/schedules/:name` replaces it at runtime and
+//   `DELETE /api/schedules/:name` restores the configured one
+//
+// Configuration:
+// --------------
+//   - name: hall_lighting
+//     type: SCHEDULE
+//     outputs: { out: hall.lights_on }
+//     params:
+//       timezone: America/Chicago     # IANA name, default UTC
+//       weekly:
+//         - { days: [mon, tue, wed, thu, fri], start: "06:00", end: "20:00" }
+//         - { days: [sat], start: "08:00", end: "14:00" }
+//       exceptions:
+//         - { name: thanksgiving, date: 2026-11-26 }
+//         - { date: 2026-12-24, windows: [{ start: "06:00", end: "12:00" }] }
+//
+// A schedule replaced at runtime is part of the block state, so with
+// `block_state` configured the edit survives an engine restart.
+
+use super::persistence::{decode_state, encode_state};
+use super::{get_array_parameter, get_output_signal, get_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    schedule::{DateException, Schedule, WeeklyWindow},
+    signal::SignalBus,
+    value::Value,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Saved state of a SCHEDULE block
+#[derive(Serialize, Deserialize)]
+struct ScheduleBlockState {
+    /// Schedule replaced at runtime, if any
+    edited: Option<Schedule>,
+}
+
+/// Output switched by a weekly schedule
+pub struct ScheduleBlock {
+    name: String,
+    output: String,
+    configured: Arc<Schedule>,
+    current: Arc<Schedule>,
+    registered: bool,
+}
+
+impl Block for ScheduleBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let schedules = bus.schedules();
+        if !self.registered {
+            schedules.register(&self.name, Arc::clone(&self.configured), Arc::clone(&self.current));
+            self.registered = true;
+        }
+        if let Some(current) = schedules.current(&self.name) {
+            if !Arc::ptr_eq(&current, &self.current) {
+                info!("SCHEDULE block '{}' now follows an updated schedule", self.name);
+                self.current = current;
+            }
+        }
+
+        let now = DateTime::<Utc>::from(bus.clock().system_time());
+        bus.set(&self.output, Value::Bool(self.current.is_on(now)))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "SCHEDULE"
+    }
+
+    fn category(&self) -> &'static str {
+        "timer"
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let edited = (!Arc::ptr_eq(&self.current, &self.configured)).then(|| (*self.current).clone());
+        encode_state(&ScheduleBlockState { edited })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: ScheduleBlockState = decode_state(&self.name, state)?;
+        self.current = state.edited.map_or_else(|| Arc::clone(&self.configured), Arc::new);
+        self.registered = false;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+/// Factory function for SCHEDULE blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `out` output, for an unknown time
+/// zone, or for a schedule that does not parse or validate.
+pub fn create_schedule_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let schedule = Schedule {
+        timezone: get_parameter(config, "timezone", Some(Tz::UTC))?,
+        weekly: get_array_parameter::<WeeklyWindow>(config, "weekly", Some(Vec::new()))?,
+        exceptions: get_array_parameter::<DateException>(config, "exceptions", Some(Vec::new()))?,
+    };
+    schedule
+        .validate()
+        .map_err(|e| PlcError::Config(format!("SCHEDULE block '{}': {}", config.name, e)))?;
+    let configured = Arc::new(schedule);
+
+    Ok(Box::new(ScheduleBlock {
+        name: config.name.clone(),
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+        current: Arc::clone(&configured),
+        configured,
+        registered: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    const LIGHTS: &str = r#"
+name: lights
+type: SCHEDULE
+outputs: { out: lights_on }
+params:
+  timezone: America/Chicago
+  weekly:
+    - { days: [mon, tue, wed, thu, fri], start: "06:00", end: "20:00" }
+  exceptions:
+    - { name: thanksgiving, date: 2026-11-26 }
+"#;
+
+    /// Bus whose wall clock starts at the Chicago local time given
+    fn bus_at(y: i32, m: u32, d: u32, h: u32) -> (SignalBus, Arc<SimulatedClock>) {
+        let start = chrono_tz::America::Chicago.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap();
+        let clock = Arc::new(SimulatedClock::stepped_at(start.with_timezone(&Utc).into()));
+        (SignalBus::new().with_clock(clock.clone()), clock)
+    }
+
+    fn lights() -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(LIGHTS).unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    #[test]
+    fn test_schedule_follows_wall_clock_and_holidays() {
+        // Wednesday 2026-11-25, 05:00 in Chicago
+        let (bus, clock) = bus_at(2026, 11, 25, 5);
+        let mut block = lights();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(false)));
+        clock.advance(Duration::from_secs(3600));
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(true)));
+
+        // Thanksgiving
+        clock.advance(Duration::from_secs(24 * 3600));
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(false)));
+
+        let config: BlockConfig =
+            serde_yaml::from_str(&LIGHTS.replace("America/Chicago", "Mars/Olympus_Mons")).unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+
+    #[test]
+    fn test_runtime_edit_is_kept_in_block_state() {
+        // Saturday 2026-11-28, 10:00 in Chicago
+        let (bus, _) = bus_at(2026, 11, 28, 10);
+        let mut block = lights();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(false)));
+
+        let weekend: Schedule = serde_yaml::from_str(
+            r#"{ timezone: America/Chicago, weekly: [{ days: [sat, sun], start: "09:00", end: "17:00" }] }"#,
+        )
+        .unwrap();
+        let now = DateTime::<Utc>::from(bus.clock().system_time());
+        bus.schedules().replace("lights", weekend, now).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(true)));
+
+        // A restarted engine keeps the edited schedule
+        let state = block.save_state().unwrap();
+        let bus = SignalBus::new().with_clock(bus.clock().clone());
+        let mut restored = lights();
+        restored.load_state(state).unwrap();
+        restored.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(true)));
+        assert!(bus.schedules().get("lights", now).unwrap().edited);
+
+        bus.schedules().restore("lights", now).unwrap();
+        restored.execute(&bus).unwrap();
+        assert_eq!(bus.get("lights_on"), Some(Value::Bool(false)));
+    }
+}
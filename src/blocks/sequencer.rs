@@ -0,0 +1,499 @@
+// src/blocks/sequencer.rs - Step sequencer block for PETRA
+//
+// Purpose:
+// --------
+// Implements SEQUENCER, a lightweight sequential function chart for batch
+// and CIP sequences: an ordered list of steps, each writing its outputs
+// while it is active and leaving it through condition transitions, a
+// timeout, or an abort of the whole sequence.
+//
+// Interactions:
+// -------------
+// - Uses: CALC expressions (blocks/calc.rs) for every condition, and block
+//   state persistence from blocks/persistence.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: every block input, by its input name, in the conditions
+// - Writes: the outputs of the active step, `idle_outputs` for every signal
+//   the active step does not set or while no step is active, and the
+//   optional block outputs `step` (1-based number of the
+//   active step, 0 when none), `done`, `aborted` and `step_time` (ms)
+//
+// Configuration:
+// --------------
+//   - name: cip
+//     type: SEQUENCER
+//     inputs: { start: cip.start, stop: cip.stop, level: tank.level, temp: tank.temp }
+//     outputs: { step: cip.step, done: cip.done, aborted: cip.aborted }
+//     params:
+//       start: start                  # rising edge starts the first step
+//       abort: stop                   # aborts from any step (optional)
+//       idle_outputs: { cip.fill_valve: false, cip.heater: false, cip.drain_valve: false }
+//       steps:
+//         - name: fill
+//           outputs: { cip.fill_valve: true }
+//           next: level > 80          # advance to the following step
+//           timeout_ms: 600000        # then go to on_timeout (default abort)
+//         - name: heat
+//           outputs: { cip.heater: true }
+//           transitions:              # checked in order, before next
+//             - { when: level < 20, goto: fill }
+//           next: temp >= 70
+//         - name: drain
+//           outputs: { cip.drain_valve: true }
+//           next: level < 1           # after the last step the sequence is done
+//
+// Transition targets are step names, `done` and `abort`. One transition is
+// taken per scan. A rising edge of `start` restarts a finished or aborted
+// sequence, but is ignored while a step is active. The active step and its
+// elapsed time are part of the block state, so with `block_state`
+// configured a sequence resumes after an engine restart.
+
+use super::calc::{Expr, ExprInputs};
+use super::persistence::{decode_state, encode_state};
+use super::{get_array_parameter, get_output_signal, get_parameter, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::{from_yaml_value, Value},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Step as configured
+#[derive(Debug, Clone, Deserialize)]
+struct StepConfig {
+    name: String,
+    #[serde(default)]
+    outputs: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    transitions: Vec<TransitionConfig>,
+    /// Condition to advance to the following step
+    next: Option<String>,
+    timeout_ms: Option<u64>,
+    on_timeout: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TransitionConfig {
+    when: String,
+    goto: String,
+}
+
+/// Where a transition leads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Step(usize),
+    Done,
+    Abort,
+}
+
+struct Step {
+    name: String,
+    outputs: Vec<(String, Value)>,
+    transitions: Vec<(Expr, Target)>,
+    timeout: Option<(Duration, Target)>,
+}
+
+/// Where the sequence is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Step(usize),
+    Done,
+    Aborted,
+}
+
+/// Saved state of a SEQUENCER block
+///
+/// The active step is saved by name, so a state file survives steps being
+/// added or reordered.
+#[derive(Serialize, Deserialize)]
+struct SequencerState {
+    step: Option<String>,
+    done: bool,
+    aborted: bool,
+    step_elapsed_ms: u64,
+    last_start: bool,
+}
+
+/// Sequential function chart
+pub struct SequencerBlock {
+    name: String,
+    inputs: ExprInputs,
+    start: Expr,
+    abort: Option<Expr>,
+    steps: Vec<Step>,
+    idle_outputs: Vec<(String, Value)>,
+    step_output: Option<String>,
+    done_output: Option<String>,
+    aborted_output: Option<String>,
+    step_time_output: Option<String>,
+    phase: Phase,
+    /// Time in the active step before `entered`, from a restored state
+    elapsed_before: Duration,
+    /// Bus time the active step was entered or resumed
+    entered: Option<Instant>,
+    /// Time in the active step as of the last scan
+    elapsed: Duration,
+    last_start: bool,
+}
+
+impl SequencerBlock {
+    fn step_elapsed(&self, now: Instant) -> Duration {
+        self.elapsed_before + self.entered.map_or(Duration::ZERO, |entered| now.saturating_duration_since(entered))
+    }
+
+    fn go(&mut self, target: Target, now: Instant) {
+        let from = match self.phase {
+            Phase::Step(i) => self.steps[i].name.as_str(),
+            _ => "idle",
+        };
+        let (phase, to) = match target {
+            Target::Step(i) => (Phase::Step(i), self.steps[i].name.as_str()),
+            Target::Done => (Phase::Done, "done"),
+            Target::Abort => (Phase::Aborted, "abort"),
+        };
+        if target == Target::Abort {
+            warn!("SEQUENCER block '{}' aborted in {}", self.name, from);
+        } else {
+            info!("SEQUENCER block '{}': {} -> {}", self.name, from, to);
+        }
+        self.phase = phase;
+        self.elapsed_before = Duration::ZERO;
+        self.entered = Some(now);
+    }
+
+    /// Transition to take from step `i`, if any
+    fn transition(&self, i: usize, now: Instant) -> Option<Target> {
+        if self.abort.as_ref().is_some_and(|abort| self.inputs.eval(abort).truth()) {
+            return Some(Target::Abort);
+        }
+        let step = &self.steps[i];
+        step.transitions
+            .iter()
+            .find(|(when, _)| self.inputs.eval(when).truth())
+            .map(|(_, target)| *target)
+            .or_else(|| {
+                step.timeout
+                    .filter(|(timeout, _)| self.step_elapsed(now) >= *timeout)
+                    .map(|(_, target)| target)
+            })
+    }
+}
+
+impl Block for SequencerBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        self.inputs.read(bus, "SEQUENCER")?;
+        let now = bus.now();
+        let start = self.inputs.eval(&self.start).truth();
+        let rising = start && !self.last_start;
+        self.last_start = start;
+
+        match self.phase {
+            Phase::Step(i) => {
+                // A restored step resumes timing from its first scan
+                self.entered.get_or_insert(now);
+                if let Some(target) = self.transition(i, now) {
+                    self.go(target, now);
+                }
+            }
+            Phase::Idle | Phase::Done | Phase::Aborted if rising => self.go(Target::Step(0), now),
+            Phase::Idle | Phase::Done | Phase::Aborted => {}
+        }
+
+        let outputs = match self.phase {
+            Phase::Step(i) => &self.steps[i].outputs,
+            _ => &self.idle_outputs,
+        };
+        for (signal, value) in outputs {
+            bus.set(signal, value.clone())?;
+        }
+        let step = match self.phase {
+            Phase::Step(i) => i + 1,
+            _ => 0,
+        };
+        self.elapsed = if step > 0 { self.step_elapsed(now) } else { Duration::ZERO };
+        if let Some(output) = &self.step_output {
+            bus.set(output, Value::Integer(i64::try_from(step).unwrap_or(i64::MAX)))?;
+        }
+        if let Some(output) = &self.done_output {
+            bus.set(output, Value::Bool(self.phase == Phase::Done))?;
+        }
+        if let Some(output) = &self.aborted_output {
+            bus.set(output, Value::Bool(self.phase == Phase::Aborted))?;
+        }
+        if let Some(output) = &self.step_time_output {
+            bus.set(output, Value::Integer(i64::try_from(self.elapsed.as_millis()).unwrap_or(i64::MAX)))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "SEQUENCER"
+    }
+
+    fn category(&self) -> &'static str {
+        "control"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.phase = Phase::Idle;
+        self.elapsed_before = Duration::ZERO;
+        self.entered = None;
+        self.elapsed = Duration::ZERO;
+        self.last_start = false;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let step = match self.phase {
+            Phase::Step(i) => Some(self.steps[i].name.clone()),
+            _ => None,
+        };
+        encode_state(&SequencerState {
+            step,
+            done: self.phase == Phase::Done,
+            aborted: self.phase == Phase::Aborted,
+            step_elapsed_ms: u64::try_from(self.elapsed.as_millis()).unwrap_or(u64::MAX),
+            last_start: self.last_start,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: SequencerState = decode_state(&self.name, state)?;
+        self.phase = match (&state.step, state.done, state.aborted) {
+            (Some(name), _, _) => {
+                if let Some(i) = self.steps.iter().position(|step| step.name == *name) {
+                    Phase::Step(i)
+                } else {
+                    warn!("SEQUENCER block '{}' saved step '{}' no longer exists; sequence reset", self.name, name);
+                    Phase::Idle
+                }
+            }
+            (None, true, _) => Phase::Done,
+            (None, false, true) => Phase::Aborted,
+            (None, false, false) => Phase::Idle,
+        };
+        self.elapsed_before = Duration::from_millis(state.step_elapsed_ms);
+        self.elapsed = self.elapsed_before;
+        self.entered = None;
+        self.last_start = state.last_start;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        self.inputs.signals()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        let mut signals: Vec<&str> = self
+            .steps
+            .iter()
+            .flat_map(|step| &step.outputs)
+            .chain(&self.idle_outputs)
+            .map(|(signal, _)| signal.as_str())
+            .chain(
+                [&self.step_output, &self.done_output, &self.aborted_output, &self.step_time_output]
+                    .into_iter()
+                    .filter_map(Option::as_deref),
+            )
+            .collect();
+        signals.sort_unstable();
+        signals.dedup();
+        signals
+    }
+}
+
+fn output_values(config: &BlockConfig, outputs: BTreeMap<String, serde_yaml::Value>) -> Result<Vec<(String, Value)>> {
+    outputs
+        .into_iter()
+        .map(|(signal, value)| {
+            let value = from_yaml_value(value).map_err(|e| {
+                PlcError::Config(format!("SEQUENCER block '{}' output '{}': {}", config.name, signal, e))
+            })?;
+            Ok((signal, value))
+        })
+        .collect()
+}
+
+/// Factory function for SEQUENCER blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without a `start` condition or steps, with
+/// duplicate step names or steps named `done` or `abort`, with a condition
+/// that does not parse or names an input the block does not map, or with a
+/// transition to an unknown step.
+pub fn create_sequencer_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let error = |message: String| PlcError::Config(format!("SEQUENCER block '{}' {}", config.name, message));
+    let inputs = ExprInputs::new(config);
+    let parse = |source: &str| inputs.parse(source).map_err(|e| error(format!("invalid condition '{source}': {e}")));
+
+    let start = parse(&get_string_parameter(config, "start", None)?)?;
+    let abort = if config.params.contains_key("abort") {
+        Some(parse(&get_string_parameter(config, "abort", None)?)?)
+    } else {
+        None
+    };
+
+    let configured: Vec<StepConfig> = get_array_parameter(config, "steps", None)?;
+    if configured.is_empty() {
+        return Err(error("needs at least one step".to_string()));
+    }
+    let mut names = HashSet::new();
+    for step in &configured {
+        if matches!(step.name.as_str(), "done" | "abort") || !names.insert(step.name.as_str()) {
+            return Err(error(format!("step name '{}' is reserved or used twice", step.name)));
+        }
+    }
+    let target = |name: &str| match name {
+        "done" => Ok(Target::Done),
+        "abort" => Ok(Target::Abort),
+        _ => configured
+            .iter()
+            .position(|step| step.name == name)
+            .map(Target::Step)
+            .ok_or_else(|| error(format!("transition to unknown step '{name}'"))),
+    };
+
+    let idle_outputs = output_values(config, get_parameter(config, "idle_outputs", Some(BTreeMap::new()))?)?;
+    let mut steps = Vec::with_capacity(configured.len());
+    for (i, step) in configured.iter().enumerate() {
+        let mut transitions = step
+            .transitions
+            .iter()
+            .map(|transition| Ok((parse(&transition.when)?, target(&transition.goto)?)))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(next) = &step.next {
+            let following = if i + 1 < configured.len() { Target::Step(i + 1) } else { Target::Done };
+            transitions.push((parse(next)?, following));
+        }
+        let timeout = match step.timeout_ms {
+            Some(ms) => Some((Duration::from_millis(ms), target(step.on_timeout.as_deref().unwrap_or("abort"))?)),
+            None => None,
+        };
+        // Signals the step does not set keep their idle value
+        let mut outputs = idle_outputs.clone();
+        for (signal, value) in output_values(config, step.outputs.clone())? {
+            outputs.retain(|(idle, _)| *idle != signal);
+            outputs.push((signal, value));
+        }
+        steps.push(Step {
+            name: step.name.clone(),
+            outputs,
+            transitions,
+            timeout,
+        });
+    }
+    Ok(Box::new(SequencerBlock {
+        name: config.name.clone(),
+        inputs,
+        start,
+        abort,
+        steps,
+        idle_outputs,
+        step_output: get_output_signal(config, "step", false)?,
+        done_output: get_output_signal(config, "done", false)?,
+        aborted_output: get_output_signal(config, "aborted", false)?,
+        step_time_output: get_output_signal(config, "step_time", false)?,
+        phase: Phase::Idle,
+        elapsed_before: Duration::ZERO,
+        entered: None,
+        elapsed: Duration::ZERO,
+        last_start: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use std::sync::Arc;
+
+    const CIP: &str = r"
+name: cip
+type: SEQUENCER
+inputs: { start: start, stop: stop, level: level, temp: temp }
+outputs: { step: step, done: done, aborted: aborted }
+params:
+  start: start
+  abort: stop
+  idle_outputs: { fill: false, heater: false }
+  steps:
+    - { name: fill, outputs: { fill: true }, next: level > 80, timeout_ms: 1000 }
+    - name: heat
+      outputs: { heater: true }
+      transitions: [{ when: level < 20, goto: fill }]
+      next: temp >= 70
+";
+
+    fn setup() -> (Box<dyn Block>, SignalBus, Arc<SimulatedClock>) {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        for (name, value) in [("start", false), ("stop", false)] {
+            bus.set(name, Value::Bool(value)).unwrap();
+        }
+        bus.set("level", Value::Float(0.0)).unwrap();
+        bus.set("temp", Value::Float(20.0)).unwrap();
+        let config: BlockConfig = serde_yaml::from_str(CIP).unwrap();
+        (super::super::create_block(&config).unwrap(), bus, clock)
+    }
+
+    #[test]
+    fn test_sequence_runs_through_steps() {
+        let (mut cip, bus, _) = setup();
+        let mut scan = |signal: &str, value: Value| {
+            bus.set(signal, value).unwrap();
+            cip.execute(&bus).unwrap();
+            (bus.get_integer("step").unwrap(), bus.get_bool("fill").unwrap(), bus.get_bool("heater").unwrap())
+        };
+
+        assert_eq!(scan("start", Value::Bool(false)), (0, false, false));
+        assert_eq!(scan("start", Value::Bool(true)), (1, true, false));
+        assert_eq!(scan("level", Value::Float(90.0)), (2, false, true));
+        // Branch back while heating
+        assert_eq!(scan("level", Value::Float(10.0)), (1, true, false));
+        assert_eq!(scan("level", Value::Float(85.0)), (2, false, true));
+        assert_eq!(scan("temp", Value::Float(75.0)), (0, false, false));
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+
+        // Holding start does not restart; a new rising edge does
+        assert_eq!(scan("temp", Value::Float(20.0)), (0, false, false));
+        scan("start", Value::Bool(false));
+        assert_eq!(scan("start", Value::Bool(true)), (1, true, false));
+        // One transition per scan
+        assert_eq!(scan("start", Value::Bool(true)), (2, false, true));
+    }
+
+    #[test]
+    fn test_timeout_abort_and_restored_state() {
+        let (mut cip, bus, clock) = setup();
+        bus.set("start", Value::Bool(true)).unwrap();
+        cip.execute(&bus).unwrap();
+        clock.advance(Duration::from_millis(600));
+        cip.execute(&bus).unwrap();
+
+        // A restarted block resumes the step with its elapsed time
+        let state = cip.save_state().unwrap();
+        let config: BlockConfig = serde_yaml::from_str(CIP).unwrap();
+        let mut cip = super::super::create_block(&config).unwrap();
+        cip.load_state(state).unwrap();
+        cip.execute(&bus).unwrap();
+        assert_eq!(bus.get("step"), Some(Value::Integer(1)));
+        clock.advance(Duration::from_millis(500));
+        cip.execute(&bus).unwrap();
+        assert_eq!(bus.get("step"), Some(Value::Integer(0)));
+        assert_eq!(bus.get("aborted"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("fill"), Some(Value::Bool(false)));
+
+        let bad = CIP.replace("goto: fill", "goto: rinse");
+        let config: BlockConfig = serde_yaml::from_str(&bad).unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+// src/blocks/spc.rs - Statistical process control block for PETRA
+//
+// Purpose:
+// --------
+// Implements SPC, which groups measurements into subgroups, keeps an X-bar/R
+// control chart over a rolling window of subgroups, and flags the Western
+// Electric rules when a subgroup mean shows the process drifting out of
+// statistical control.
+//
+// Interactions:
+// -------------
+// - Uses: Block trait and parameter helpers from blocks/mod.rs
+// - Used by: blocks/mod.rs factory
+// - Reads: the numeric input `in`, and an optional boolean `sample` input
+//   whose rising edge takes a measurement (without it every scan does), and
+//   an optional `reset` input that clears the chart while it is true
+// - Writes: any of the chart outputs `xbar`, `range`, `center`, `ucl`, `lcl`,
+//   `range_center` and `range_ucl`, and the boolean outputs `rule1` to
+//   `rule4`, `range_violation` and `out_of_control`
+//
+// Configuration:
+// --------------
+//   - name: fill_weight_spc
+//     type: SPC
+//     inputs: { in: filler.weight_g, sample: filler.weighed }
+//     outputs: { xbar: spc.weight_mean, ucl: spc.weight_ucl, lcl: spc.weight_lcl, out_of_control: spc.weight_alarm }
+//     params:
+//       subgroup_size: 5   # measurements per subgroup, 2 to 10
+//       subgroups: 25      # subgroups the control limits are computed from
+//
+// Algorithm:
+// ----------
+// When a subgroup is complete its mean and range are compared against the
+// limits of the preceding `subgroups` subgroups, then added to the window, so
+// a shift in the process does not widen the limits it is judged by. Limits
+// are X-double-bar ± A2 R-bar for the means and D3 R-bar to D4 R-bar for the
+// ranges; no rule is flagged until the window is full. The rules, with sigma
+// of the mean taken as A2 R-bar / 3, are:
+//   rule1: the mean is beyond a 3-sigma limit
+//   rule2: 2 of the last 3 means are beyond 2 sigma on the same side
+//   rule3: 4 of the last 5 means are beyond 1 sigma on the same side
+//   rule4: the last 8 means are on the same side of the center line
+// The boolean outputs describe the latest subgroup and hold until the next
+// one is complete. Measurements that are not finite are skipped.
+
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use std::collections::VecDeque;
+
+/// A2, D3 and D4 control chart constants for subgroup sizes 2 to 10
+const CHART_CONSTANTS: [(f64, f64, f64); 9] = [
+    (1.880, 0.0, 3.267),
+    (1.023, 0.0, 2.574),
+    (0.729, 0.0, 2.282),
+    (0.577, 0.0, 2.114),
+    (0.483, 0.0, 2.004),
+    (0.419, 0.076, 1.924),
+    (0.373, 0.136, 1.864),
+    (0.337, 0.184, 1.816),
+    (0.308, 0.223, 1.777),
+];
+
+/// Subgroup means kept for the run rules
+const RULE_HISTORY: usize = 8;
+
+/// Control limits of a window of subgroups
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    center: f64,
+    /// Sigma of a subgroup mean
+    sigma: f64,
+    range_center: f64,
+    range_lcl: f64,
+    range_ucl: f64,
+}
+
+impl Limits {
+    /// Number of sigmas between `xbar` and the center line
+    fn zone(&self, xbar: f64) -> f64 {
+        if self.sigma > 0.0 {
+            (xbar - self.center) / self.sigma
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Western Electric rules broken by the latest subgroup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Violations {
+    rules: [bool; 4],
+    range: bool,
+}
+
+impl Violations {
+    fn any(self) -> bool {
+        self.range || self.rules.iter().any(|&rule| rule)
+    }
+}
+
+/// True if at least `count` of the last `of` zones are beyond `sigmas` on one side
+fn beyond(zones: &VecDeque<f64>, count: usize, of: usize, sigmas: f64) -> bool {
+    if zones.len() < of {
+        return false;
+    }
+    let recent = zones.iter().skip(zones.len() - of);
+    let above = recent.clone().filter(|&&z| z > sigmas).count();
+    let below = recent.filter(|&&z| z < -sigmas).count();
+    above >= count || below >= count
+}
+
+/// X-bar/R control chart with Western Electric rules
+pub struct SpcBlock {
+    name: String,
+    input: String,
+    sample_input: Option<String>,
+    reset_input: Option<String>,
+    xbar_output: Option<String>,
+    range_output: Option<String>,
+    center_output: Option<String>,
+    ucl_output: Option<String>,
+    lcl_output: Option<String>,
+    range_center_output: Option<String>,
+    range_ucl_output: Option<String>,
+    rule_outputs: [Option<String>; 4],
+    range_violation_output: Option<String>,
+    out_of_control_output: Option<String>,
+    subgroup_size: usize,
+    subgroups: usize,
+    /// A2, D3 and D4 for the subgroup size
+    constants: (f64, f64, f64),
+    /// Measurements of the subgroup being collected
+    current: Vec<f64>,
+    /// Mean and range of the completed subgroups the limits come from
+    window: VecDeque<(f64, f64)>,
+    /// Zones of the latest subgroup means, judged when they were completed
+    zones: VecDeque<f64>,
+    latest: Option<(f64, f64)>,
+    violations: Violations,
+    sampled: bool,
+}
+
+impl SpcBlock {
+    fn limits(&self) -> Option<Limits> {
+        if self.window.len() < self.subgroups {
+            return None;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let k = self.window.len() as f64;
+        let center = self.window.iter().map(|&(xbar, _)| xbar).sum::<f64>() / k;
+        let range_center = self.window.iter().map(|&(_, range)| range).sum::<f64>() / k;
+        let (a2, d3, d4) = self.constants;
+        Some(Limits {
+            center,
+            sigma: a2 * range_center / 3.0,
+            range_center,
+            range_lcl: d3 * range_center,
+            range_ucl: d4 * range_center,
+        })
+    }
+
+    fn clear(&mut self) {
+        self.current.clear();
+        self.window.clear();
+        self.zones.clear();
+        self.latest = None;
+        self.violations = Violations::default();
+    }
+
+    /// Judge a completed subgroup against the window, then add it
+    fn complete_subgroup(&mut self) {
+        #[allow(clippy::cast_precision_loss)]
+        let xbar = self.current.iter().sum::<f64>() / self.current.len() as f64;
+        let (min, max) = self
+            .current
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| (min.min(x), max.max(x)));
+        let range = max - min;
+        self.current.clear();
+        self.latest = Some((xbar, range));
+
+        if let Some(limits) = self.limits() {
+            self.zones.push_back(limits.zone(xbar));
+            if self.zones.len() > RULE_HISTORY {
+                self.zones.pop_front();
+            }
+            let same_side = self.zones.len() == RULE_HISTORY
+                && (self.zones.iter().all(|&z| z > 0.0) || self.zones.iter().all(|&z| z < 0.0));
+            self.violations = Violations {
+                rules: [
+                    beyond(&self.zones, 1, 1, 3.0),
+                    beyond(&self.zones, 2, 3, 2.0),
+                    beyond(&self.zones, 4, 5, 1.0),
+                    same_side,
+                ],
+                range: range > limits.range_ucl || range < limits.range_lcl,
+            };
+        }
+
+        self.window.push_back((xbar, range));
+        if self.window.len() > self.subgroups {
+            self.window.pop_front();
+        }
+    }
+
+    fn chart_outputs(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.xbar_output,
+            &self.range_output,
+            &self.center_output,
+            &self.ucl_output,
+            &self.lcl_output,
+            &self.range_center_output,
+            &self.range_ucl_output,
+        ]
+        .into_iter()
+        .filter_map(Option::as_deref)
+    }
+
+    fn flag_outputs(&self) -> impl Iterator<Item = &str> {
+        self.rule_outputs
+            .iter()
+            .chain([&self.range_violation_output, &self.out_of_control_output])
+            .filter_map(Option::as_deref)
+    }
+}
+
+impl Block for SpcBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let reset = match &self.reset_input {
+            Some(input) => bus.get_bool(input)?,
+            None => false,
+        };
+        if reset {
+            self.clear();
+        } else {
+            let take = match &self.sample_input {
+                Some(input) => {
+                    let sample = bus.get_bool(input)?;
+                    let rising = sample && !self.sampled;
+                    self.sampled = sample;
+                    rising
+                }
+                None => true,
+            };
+            if take {
+                let x = bus.get_float(&self.input)?;
+                if x.is_finite() {
+                    self.current.push(x);
+                    if self.current.len() >= self.subgroup_size {
+                        self.complete_subgroup();
+                    }
+                }
+            }
+        }
+
+        if let Some((xbar, range)) = self.latest {
+            let set = |output: &Option<String>, value: f64| match output {
+                Some(output) => bus.set(output, Value::Float(value)),
+                None => Ok(()),
+            };
+            set(&self.xbar_output, xbar)?;
+            set(&self.range_output, range)?;
+            if let Some(limits) = self.limits() {
+                set(&self.center_output, limits.center)?;
+                set(&self.ucl_output, limits.center + 3.0 * limits.sigma)?;
+                set(&self.lcl_output, limits.center - 3.0 * limits.sigma)?;
+                set(&self.range_center_output, limits.range_center)?;
+                set(&self.range_ucl_output, limits.range_ucl)?;
+            }
+        }
+        for (output, rule) in self.rule_outputs.iter().zip(self.violations.rules) {
+            if let Some(output) = output {
+                bus.set(output, Value::Bool(rule))?;
+            }
+        }
+        if let Some(output) = &self.range_violation_output {
+            bus.set(output, Value::Bool(self.violations.range))?;
+        }
+        if let Some(output) = &self.out_of_control_output {
+            bus.set(output, Value::Bool(self.violations.any()))?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "SPC"
+    }
+
+    fn category(&self) -> &'static str {
+        "math"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.clear();
+        self.sampled = false;
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        std::iter::once(self.input.as_str())
+            .chain(self.sample_input.as_deref())
+            .chain(self.reset_input.as_deref())
+            .collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.chart_outputs().chain(self.flag_outputs()).collect()
+    }
+}
+
+/// Factory function for `SPC` blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input or any output, with a
+/// `subgroup_size` outside 2 to 10, or with fewer than 2 `subgroups`.
+pub fn create_spc_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let error = |message: &str| PlcError::Config(format!("SPC block '{}' {}", config.name, message));
+
+    let subgroup_size: usize = get_numeric_parameter(config, "subgroup_size", Some(5))?;
+    let constants = subgroup_size
+        .checked_sub(2)
+        .and_then(|i| CHART_CONSTANTS.get(i))
+        .copied()
+        .ok_or_else(|| error("subgroup_size must be between 2 and 10"))?;
+    let subgroups: usize = get_numeric_parameter(config, "subgroups", Some(25))?;
+    if subgroups < 2 {
+        return Err(error("subgroups must be at least 2"));
+    }
+
+    let block = SpcBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        sample_input: get_input_signal(config, "sample", false)?,
+        reset_input: get_input_signal(config, "reset", false)?,
+        xbar_output: get_output_signal(config, "xbar", false)?,
+        range_output: get_output_signal(config, "range", false)?,
+        center_output: get_output_signal(config, "center", false)?,
+        ucl_output: get_output_signal(config, "ucl", false)?,
+        lcl_output: get_output_signal(config, "lcl", false)?,
+        range_center_output: get_output_signal(config, "range_center", false)?,
+        range_ucl_output: get_output_signal(config, "range_ucl", false)?,
+        rule_outputs: [
+            get_output_signal(config, "rule1", false)?,
+            get_output_signal(config, "rule2", false)?,
+            get_output_signal(config, "rule3", false)?,
+            get_output_signal(config, "rule4", false)?,
+        ],
+        range_violation_output: get_output_signal(config, "range_violation", false)?,
+        out_of_control_output: get_output_signal(config, "out_of_control", false)?,
+        subgroup_size,
+        subgroups,
+        constants,
+        current: Vec::with_capacity(subgroup_size),
+        window: VecDeque::with_capacity(subgroups + 1),
+        zones: VecDeque::with_capacity(RULE_HISTORY + 1),
+        latest: None,
+        violations: Violations::default(),
+        sampled: false,
+    };
+    if block.output_signals().is_empty() {
+        return Err(error("needs at least one output"));
+    }
+    Ok(Box::new(block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spc(subgroups: usize) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(&format!(
+            "{{ name: spc, type: SPC, inputs: {{ in: x, sample: take }}, \
+             outputs: {{ xbar: xbar, center: center, ucl: ucl, lcl: lcl, range_ucl: rucl, \
+             rule1: r1, rule2: r2, rule4: r4, range_violation: rv, out_of_control: ooc }}, \
+             params: {{ subgroup_size: 2, subgroups: {subgroups} }} }}"
+        ))
+        .unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    /// Take one subgroup of two measurements, each on a rising edge of `take`
+    fn subgroup(block: &mut Box<dyn Block>, bus: &SignalBus, a: f64, b: f64) {
+        for x in [a, b] {
+            bus.set("x", Value::Float(x)).unwrap();
+            bus.set("take", Value::Bool(true)).unwrap();
+            block.execute(bus).unwrap();
+            bus.set("take", Value::Bool(false)).unwrap();
+            block.execute(bus).unwrap();
+        }
+    }
+
+    fn flag(bus: &SignalBus, name: &str) -> bool {
+        bus.get_bool(name).unwrap()
+    }
+
+    #[test]
+    fn test_control_limits_and_rule1() {
+        let bus = SignalBus::new();
+        let mut block = spc(4);
+        // Four baseline subgroups: means 10 and ranges 2, so sigma = 1.880 * 2 / 3
+        for _ in 0..4 {
+            subgroup(&mut block, &bus, 9.0, 11.0);
+        }
+        assert_eq!(bus.get("center"), Some(Value::Float(10.0)));
+        assert!((bus.get_float("ucl").unwrap() - 13.76).abs() < 1e-9);
+        assert!((bus.get_float("lcl").unwrap() - 6.24).abs() < 1e-9);
+        assert!((bus.get_float("rucl").unwrap() - 6.534).abs() < 1e-9);
+        assert!(!flag(&bus, "ooc"));
+
+        subgroup(&mut block, &bus, 14.0, 15.0);
+        assert_eq!(bus.get("xbar"), Some(Value::Float(14.5)));
+        assert!(flag(&bus, "r1"));
+        assert!(!flag(&bus, "rv"));
+        assert!(flag(&bus, "ooc"));
+
+        // Holding `take` high takes no further measurements
+        bus.set("take", Value::Bool(true)).unwrap();
+        for _ in 0..3 {
+            block.execute(&bus).unwrap();
+        }
+        assert_eq!(bus.get("xbar"), Some(Value::Float(14.5)));
+    }
+
+    #[test]
+    fn test_run_rules_and_range_violation() {
+        let bus = SignalBus::new();
+        let mut block = spc(20);
+        for _ in 0..20 {
+            subgroup(&mut block, &bus, 9.0, 11.0);
+        }
+
+        // The shifted means move the center of a 20 subgroup window only
+        // slowly, so the eighth one in a row breaks rule 4
+        for i in 0..8 {
+            subgroup(&mut block, &bus, 10.4, 12.0);
+            assert_eq!(flag(&bus, "r4"), i == 7, "subgroup {i}");
+        }
+
+        block.reset().unwrap();
+        for _ in 0..20 {
+            subgroup(&mut block, &bus, 9.0, 11.0);
+        }
+        subgroup(&mut block, &bus, 6.0, 14.0);
+        assert!(flag(&bus, "rv"));
+        assert!(!flag(&bus, "r1"));
+        assert!(!flag(&bus, "r2"));
+
+        let config: BlockConfig = serde_yaml::from_str(
+            "{ name: bad, type: SPC, inputs: { in: x }, outputs: { xbar: x }, params: { subgroup_size: 11 } }",
+        )
+        .unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+// src/blocks/structured.rs - Array and object decomposition blocks for PETRA
+//
+// Purpose:
+// --------
+// Implements ARRAY_INDEX and OBJECT_GET, which pick one element out of an
+// array or object signal, such as a structure read from an OPC-UA server or
+// a JSON payload received over MQTT, so the rest of the logic works on
+// plain signals.
+//
+// Interactions:
+// -------------
+// - Uses: Value::index and Value::path from value.rs
+// - Used by: blocks/mod.rs factory (extended-types feature)
+// - Reads: the array or object input `in`, and for ARRAY_INDEX an optional
+//   integer `index` input overriding the `index` parameter
+// - Writes: the element to `out`, and whether it was found to the optional
+//   `valid` output; `out` keeps its last value while the element is missing
+//
+// Configuration:
+// --------------
+//   - name: last_temp
+//     type: ARRAY_INDEX
+//     inputs: { in: oven.zone_temps }
+//     outputs: { out: oven.last_zone_temp, valid: oven.last_zone_ok }
+//     params: { index: -1 }
+//
+//   - name: motor_speed
+//     type: OBJECT_GET
+//     inputs: { in: line1.drive_status }
+//     outputs: { out: line1.motor_speed }
+//     params: { field: motor.speed }
+//
+// Object fields are a dotted path; on an array a path segment is an element
+// index, so `motor.temps.0` reads the first element of a nested array.
+
+use super::{get_input_signal, get_numeric_parameter, get_output_signal, get_string_parameter, Block, BlockConfig};
+use crate::{
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+
+/// Write the element found, if any, and whether it was found
+fn publish(bus: &SignalBus, output: &str, valid_output: Option<&str>, element: Option<Value>) -> Result<()> {
+    let found = element.is_some();
+    if let Some(element) = element {
+        bus.set(output, element)?;
+    }
+    if let Some(valid) = valid_output {
+        bus.set(valid, Value::Bool(found))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// ARRAY_INDEX
+// ============================================================================
+
+/// Element of an array signal
+pub struct ArrayIndexBlock {
+    name: String,
+    input: String,
+    index_input: Option<String>,
+    output: String,
+    valid_output: Option<String>,
+    /// Element read without an `index` input, negative from the end
+    index: i64,
+}
+
+impl Block for ArrayIndexBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let array = bus.get_required(&self.input)?;
+        if !array.is_array() {
+            return Err(PlcError::TypeMismatch {
+                expected: "array".to_string(),
+                actual: array.type_name().to_string(),
+            });
+        }
+        let index = match &self.index_input {
+            Some(input) => bus.get_integer(input)?,
+            None => self.index,
+        };
+        publish(bus, &self.output, self.valid_output.as_deref(), array.index(index).cloned())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "ARRAY_INDEX"
+    }
+
+    fn category(&self) -> &'static str {
+        "data"
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        std::iter::once(self.input.as_str()).chain(self.index_input.as_deref()).collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        std::iter::once(self.output.as_str()).chain(self.valid_output.as_deref()).collect()
+    }
+}
+
+/// Factory function for ARRAY_INDEX blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input or `out` output, or
+/// with an `index` parameter that is not an integer.
+pub fn create_array_index_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    Ok(Box::new(ArrayIndexBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        index_input: get_input_signal(config, "index", false)?,
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+        valid_output: get_output_signal(config, "valid", false)?,
+        index: get_numeric_parameter(config, "index", Some(0))?,
+    }))
+}
+
+// ============================================================================
+// OBJECT_GET
+// ============================================================================
+
+/// Field of an object signal, at a dotted path
+pub struct ObjectGetBlock {
+    name: String,
+    input: String,
+    output: String,
+    valid_output: Option<String>,
+    field: String,
+}
+
+impl Block for ObjectGetBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let object = bus.get_required(&self.input)?;
+        if !object.is_object() {
+            return Err(PlcError::TypeMismatch {
+                expected: "object".to_string(),
+                actual: object.type_name().to_string(),
+            });
+        }
+        publish(bus, &self.output, self.valid_output.as_deref(), object.path(&self.field).cloned())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "OBJECT_GET"
+    }
+
+    fn category(&self) -> &'static str {
+        "data"
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        vec![self.input.as_str()]
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        std::iter::once(self.output.as_str()).chain(self.valid_output.as_deref()).collect()
+    }
+}
+
+/// Factory function for OBJECT_GET blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input, `out` output or
+/// `field` parameter.
+pub fn create_object_get_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let field = get_string_parameter(config, "field", None)?;
+    if field.is_empty() {
+        return Err(PlcError::Config(format!("OBJECT_GET block '{}' field cannot be empty", config.name)));
+    }
+    Ok(Box::new(ObjectGetBlock {
+        name: config.name.clone(),
+        input: get_input_signal(config, "in", true)?.unwrap_or_default(),
+        output: get_output_signal(config, "out", true)?.unwrap_or_default(),
+        valid_output: get_output_signal(config, "valid", false)?,
+        field,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn block(yaml: &str) -> Box<dyn Block> {
+        let config: BlockConfig = serde_yaml::from_str(yaml).unwrap();
+        super::super::create_block(&config).unwrap()
+    }
+
+    #[test]
+    fn test_array_index() {
+        let bus = SignalBus::new();
+        bus.set("temps", Value::Array(vec![Value::Float(20.0), Value::Float(21.5)])).unwrap();
+        let mut last = block(
+            "{ name: last, type: ARRAY_INDEX, inputs: { in: temps }, outputs: { out: last, valid: ok }, params: { index: -1 } }",
+        );
+        last.execute(&bus).unwrap();
+        assert_eq!(bus.get("last"), Some(Value::Float(21.5)));
+        assert_eq!(bus.get("ok"), Some(Value::Bool(true)));
+
+        // The index input overrides the parameter; a missing element keeps the output
+        let mut picked = block(
+            "{ name: pick, type: ARRAY_INDEX, inputs: { in: temps, index: i }, outputs: { out: last, valid: ok } }",
+        );
+        bus.set("i", Value::Integer(5)).unwrap();
+        picked.execute(&bus).unwrap();
+        assert_eq!(bus.get("last"), Some(Value::Float(21.5)));
+        assert_eq!(bus.get("ok"), Some(Value::Bool(false)));
+
+        bus.set("temps", Value::Float(1.0)).unwrap();
+        assert!(last.execute(&bus).is_err());
+    }
+
+    #[test]
+    fn test_object_get() {
+        let bus = SignalBus::new();
+        let motor = Value::Object(HashMap::from([
+            ("speed".to_string(), Value::Float(1450.0)),
+            ("temps".to_string(), Value::Array(vec![Value::Float(61.0)])),
+        ]));
+        bus.set("status", Value::Object(HashMap::from([("motor".to_string(), motor)]))).unwrap();
+
+        let mut speed = block(
+            "{ name: speed, type: OBJECT_GET, inputs: { in: status }, outputs: { out: speed }, params: { field: motor.speed } }",
+        );
+        speed.execute(&bus).unwrap();
+        assert_eq!(bus.get("speed"), Some(Value::Float(1450.0)));
+
+        let mut temp = block(
+            "{ name: temp, type: OBJECT_GET, inputs: { in: status }, outputs: { out: temp, valid: ok }, params: { field: motor.temps.0 } }",
+        );
+        temp.execute(&bus).unwrap();
+        assert_eq!(bus.get("temp"), Some(Value::Float(61.0)));
+        assert_eq!(bus.get("ok"), Some(Value::Bool(true)));
+
+        let config: BlockConfig =
+            serde_yaml::from_str("{ name: bad, type: OBJECT_GET, inputs: { in: status }, outputs: { out: x } }").unwrap();
+        assert!(super::super::create_block(&config).is_err());
+    }
+}
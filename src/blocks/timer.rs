@@ -2,7 +2,7 @@
 //
 // Purpose:
 // --------
-// Implements industrial-standard timer blocks (TON, TOF, TP) and counter blocks
+// Implements industrial-standard timer blocks (TON, TOF, TP, TONR) and counter blocks
 // (CTU, CTD) that provide time-based and event-counting control logic. These blocks
 // are essential for implementing delays, pulses, and counting operations in PLC programs.
 //
@@ -19,17 +19,108 @@
 // 1. TON (Timer On Delay) - Delays output activation after input goes high
 // 2. TOF (Timer Off Delay) - Delays output deactivation after input goes low
 // 3. TP (Timer Pulse) - Generates fixed-width pulse on rising edge
-// 4. CTU (Count Up) - Increments counter on rising edges
-// 5. CTD (Count Down) - Decrements counter on rising edges
+// 4. TONR (Retentive On Delay) - Accumulates on-time across interruptions until reset
+// 5. DEBOUNCE - Follows the input only once it has been stable for a delay
+// 6. CTU (Count Up) - Increments counter on rising edges
+// 7. CTD (Count Down) - Decrements counter on rising edges
 
+use super::persistence::{decode_state, encode_state};
 use super::{get_numeric_parameter, Block, BlockConfig};
 use crate::{
     error::{PlcError, Result},
     signal::SignalBus,
     value::Value,
 };
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
+// ============================================================================
+// PERSISTED STATE
+// ============================================================================
+
+/// Time accumulated across scans, read from the bus clock
+///
+/// Timers add the time between consecutive scans rather than comparing
+/// against a start instant, so a simulated clock drives them like the
+/// system clock and a restored timer continues from its saved elapsed time.
+#[derive(Debug, Clone, Copy, Default)]
+struct Stopwatch {
+    elapsed: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl Stopwatch {
+    /// Stopwatch continuing from `elapsed` at its next tick
+    const fn resumed(elapsed: Duration) -> Self {
+        Self {
+            elapsed,
+            last_tick: None,
+        }
+    }
+
+    /// Add the time since the previous tick and return the total
+    fn tick(&mut self, now: Instant) -> Duration {
+        if let Some(last) = self.last_tick {
+            self.elapsed += now.saturating_duration_since(last);
+        }
+        self.last_tick = Some(now);
+        self.elapsed
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        u64::try_from(self.elapsed.as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+/// Saved state of a TON/TOF/TP block
+///
+/// A running timer is stored as its elapsed time, so after a restart it
+/// resumes where it stopped; time spent while the engine was down is not
+/// counted.
+#[derive(Serialize, Deserialize)]
+struct TimerState {
+    elapsed_ms: Option<u64>,
+    last_input: bool,
+}
+
+impl TimerState {
+    fn capture(timer: Option<&Stopwatch>, last_input: bool) -> Self {
+        Self {
+            elapsed_ms: timer.map(Stopwatch::elapsed_ms),
+            last_input,
+        }
+    }
+
+    fn timer(&self) -> Option<Stopwatch> {
+        self.elapsed_ms.map(|ms| Stopwatch::resumed(Duration::from_millis(ms)))
+    }
+}
+
+/// Saved state of a TONR block
+///
+/// Holds the accumulated time including any interval that was running when
+/// the state was captured.
+#[derive(Serialize, Deserialize)]
+struct RetentiveTimerState {
+    accumulated_ms: u64,
+    running: bool,
+}
+
+/// Saved state of a DEBOUNCE block
+#[derive(Serialize, Deserialize)]
+struct DebounceState {
+    output: bool,
+    /// Time the input has differed from the output
+    pending_ms: Option<u64>,
+}
+
+/// Saved state of a CTU/CTD block
+#[derive(Serialize, Deserialize)]
+struct CounterState {
+    count: i64,
+    last_count_input: bool,
+}
+
 // ============================================================================
 // TIMER ON DELAY (TON)
 // ============================================================================
@@ -46,7 +137,7 @@ pub struct TimerOnBlock {
     output: String,
     elapsed_output: Option<String>,
     preset_ms: u64,
-    start_time: Option<Instant>,
+    timer: Option<Stopwatch>,
     last_input: bool,
 }
 
@@ -65,7 +156,7 @@ impl TimerOnBlock {
             output,
             elapsed_output,
             preset_ms,
-            start_time: None,
+            timer: None,
             last_input: false,
         }
     }
@@ -87,11 +178,11 @@ impl Block for TimerOnBlock {
         match (input, self.last_input) {
             // Rising edge - start timer
             (true, false) => {
-                self.start_time = Some(Instant::now());
+                self.timer = Some(Stopwatch::default());
             }
             // Falling edge - reset timer
             (false, true) => {
-                self.start_time = None;
+                self.timer = None;
                 self.update_elapsed(bus, 0)?;
             }
             _ => {} // No edge - continue current state
@@ -100,8 +191,8 @@ impl Block for TimerOnBlock {
         self.last_input = input;
 
         // Calculate output based on timer state
-        let (output, _elapsed_ms) = if let Some(start) = self.start_time {
-            let elapsed = start.elapsed();
+        let (output, _elapsed_ms) = if let Some(timer) = &mut self.timer {
+            let elapsed = timer.tick(bus.now());
             let elapsed_ms = elapsed.as_millis() as u64;
             
             // Clamp elapsed time to preset to avoid overflow in display
@@ -154,10 +245,21 @@ impl Block for TimerOnBlock {
     }
 
     fn reset(&mut self) -> Result<()> {
-        self.start_time = None;
+        self.timer = None;
         self.last_input = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&TimerState::capture(self.timer.as_ref(), self.last_input))
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: TimerState = decode_state(&self.name, state)?;
+        self.timer = state.timer();
+        self.last_input = state.last_input;
+        Ok(())
+    }
 }
 
 /// Factory function for TON blocks
@@ -224,7 +326,7 @@ pub struct TimerOffBlock {
     output: String,
     elapsed_output: Option<String>,
     preset_ms: u64,
-    stop_time: Option<Instant>,
+    timer: Option<Stopwatch>,
     last_input: bool,
 }
 
@@ -243,7 +345,7 @@ impl TimerOffBlock {
             output,
             elapsed_output,
             preset_ms,
-            stop_time: None,
+            timer: None,
             last_input: false,
         }
     }
@@ -265,12 +367,12 @@ impl Block for TimerOffBlock {
         match (input, self.last_input) {
             // Rising edge - cancel timer
             (true, false) => {
-                self.stop_time = None;
+                self.timer = None;
                 self.update_elapsed(bus, 0)?;
             }
             // Falling edge - start timer
             (false, true) => {
-                self.stop_time = Some(Instant::now());
+                self.timer = Some(Stopwatch::default());
             }
             _ => {} // No edge - continue current state
         }
@@ -281,8 +383,8 @@ impl Block for TimerOffBlock {
         let output = if input {
             // Input is high, output follows
             true
-        } else if let Some(stop) = self.stop_time {
-            let elapsed = stop.elapsed();
+        } else if let Some(timer) = &mut self.timer {
+            let elapsed = timer.tick(bus.now());
             let elapsed_ms = elapsed.as_millis() as u64;
             
             // Update elapsed output
@@ -310,10 +412,21 @@ impl Block for TimerOffBlock {
     }
 
     fn reset(&mut self) -> Result<()> {
-        self.stop_time = None;
+        self.timer = None;
         self.last_input = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&TimerState::capture(self.timer.as_ref(), self.last_input))
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: TimerState = decode_state(&self.name, state)?;
+        self.timer = state.timer();
+        self.last_input = state.last_input;
+        Ok(())
+    }
 }
 
 /// Factory function for TOF blocks
@@ -372,7 +485,7 @@ pub struct TimerPulseBlock {
     output: String,
     elapsed_output: Option<String>,
     preset_ms: u64,
-    start_time: Option<Instant>,
+    timer: Option<Stopwatch>,
     last_input: bool,
 }
 
@@ -391,7 +504,7 @@ impl TimerPulseBlock {
             output,
             elapsed_output,
             preset_ms,
-            start_time: None,
+            timer: None,
             last_input: false,
         }
     }
@@ -410,20 +523,20 @@ impl Block for TimerPulseBlock {
         let input = bus.get_bool(&self.input)?;
 
         // Rising edge detection - start pulse only if not already running
-        if input && !self.last_input && self.start_time.is_none() {
-            self.start_time = Some(Instant::now());
+        if input && !self.last_input && self.timer.is_none() {
+            self.timer = Some(Stopwatch::default());
         }
 
         self.last_input = input;
 
         // Calculate output based on pulse state
-        let output = if let Some(start) = self.start_time {
-            let elapsed = start.elapsed();
+        let output = if let Some(timer) = &mut self.timer {
+            let elapsed = timer.tick(bus.now());
             let elapsed_ms = elapsed.as_millis() as u64;
 
             if elapsed >= Duration::from_millis(self.preset_ms) {
                 // Pulse complete
-                self.start_time = None;
+                self.timer = None;
                 self.update_elapsed(bus, 0)?;
                 false
             } else {
@@ -450,10 +563,21 @@ impl Block for TimerPulseBlock {
     }
 
     fn reset(&mut self) -> Result<()> {
-        self.start_time = None;
+        self.timer = None;
         self.last_input = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&TimerState::capture(self.timer.as_ref(), self.last_input))
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: TimerState = decode_state(&self.name, state)?;
+        self.timer = state.timer();
+        self.last_input = state.last_input;
+        Ok(())
+    }
 }
 
 /// Factory function for TP blocks
@@ -496,6 +620,281 @@ pub fn create_timer_pulse_block(config: &BlockConfig) -> Result<Box<dyn Block>>
     )))
 }
 
+// ============================================================================
+// RETENTIVE TIMER ON DELAY (TONR)
+// ============================================================================
+
+/// Retentive timer ON delay block - accumulates on-time until reset
+///
+/// IEC 61131-3 TONR (RTO) behavior:
+/// - Elapsed time accumulates while the input is high
+/// - A low input pauses the timer without clearing the accumulated time
+/// - Output goes high once the accumulated time reaches the preset
+/// - Only the reset input clears the accumulated time and the output
+pub struct RetentiveTimerBlock {
+    name: String,
+    input: String,
+    reset_input: String,
+    output: String,
+    elapsed_output: Option<String>,
+    preset_ms: u64,
+    accumulated: Stopwatch,
+    running: bool,
+}
+
+impl RetentiveTimerBlock {
+    /// Create a new TONR block with validated configuration
+    fn new(
+        name: String,
+        input: String,
+        reset_input: String,
+        output: String,
+        elapsed_output: Option<String>,
+        preset_ms: u64,
+    ) -> Self {
+        Self {
+            name,
+            input,
+            reset_input,
+            output,
+            elapsed_output,
+            preset_ms,
+            accumulated: Stopwatch::default(),
+            running: false,
+        }
+    }
+}
+
+impl Block for RetentiveTimerBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let input = bus.get_bool(&self.input)?;
+        let reset = bus.get_bool(&self.reset_input)?;
+        let preset = Duration::from_millis(self.preset_ms);
+
+        if reset {
+            // Reset dominates; timing resumes once reset is released
+            self.accumulated = Stopwatch::default();
+            self.running = false;
+        } else if input {
+            // Resume timing from the accumulated value
+            self.accumulated.tick(bus.now());
+            self.running = true;
+        } else if self.running {
+            // Input dropped - bank the interval and pause
+            let total = self.accumulated.tick(bus.now()).min(preset);
+            self.accumulated = Stopwatch::resumed(total);
+            self.running = false;
+        }
+
+        let elapsed = self.accumulated.elapsed.min(preset);
+        if let Some(elapsed_signal) = &self.elapsed_output {
+            let elapsed_ms = i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX);
+            bus.set(elapsed_signal, Value::Integer(elapsed_ms))?;
+        }
+        bus.set(&self.output, Value::Bool(elapsed >= preset))?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "TONR"
+    }
+
+    fn validate_config(config: &BlockConfig) -> Result<()> {
+        if !config.params.contains_key("preset_ms") {
+            return Err(PlcError::Config(format!(
+                "TONR block '{}' missing required parameter 'preset_ms'",
+                config.name
+            )));
+        }
+
+        if !config.inputs.contains_key("in") || !config.inputs.contains_key("reset") {
+            return Err(PlcError::Config(format!(
+                "TONR block '{}' requires 'in' and 'reset' inputs",
+                config.name
+            )));
+        }
+
+        if !config.outputs.contains_key("out") {
+            return Err(PlcError::Config(format!(
+                "TONR block '{}' requires an 'out' output",
+                config.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.accumulated = Stopwatch::default();
+        self.running = false;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&RetentiveTimerState {
+            accumulated_ms: self.accumulated.elapsed_ms(),
+            running: self.running,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: RetentiveTimerState = decode_state(&self.name, state)?;
+        self.accumulated = Stopwatch::resumed(Duration::from_millis(state.accumulated_ms));
+        // A timer that was running keeps running from the restored total
+        self.running = state.running;
+        Ok(())
+    }
+}
+
+/// Factory function for TONR blocks
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if `preset_ms`, the `in`/`reset` inputs or
+/// the `out` output are missing or invalid.
+pub fn create_retentive_timer_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    RetentiveTimerBlock::validate_config(config)?;
+
+    let preset_ms = get_numeric_parameter(config, "preset_ms", None)?;
+    if preset_ms == 0 {
+        return Err(PlcError::Config(format!(
+            "TONR block '{}' preset_ms must be greater than 0",
+            config.name
+        )));
+    }
+
+    Ok(Box::new(RetentiveTimerBlock::new(
+        config.name.clone(),
+        config.inputs["in"].clone(),
+        config.inputs["reset"].clone(),
+        config.outputs["out"].clone(),
+        config.outputs.get("elapsed").cloned(),
+        preset_ms,
+    )))
+}
+
+// ============================================================================
+// DEBOUNCE
+// ============================================================================
+
+/// Debounce block - rejects input changes shorter than a delay
+///
+/// The output takes the input's value once the input has held it for
+/// `on_delay_ms` (turning on) or `off_delay_ms` (turning off). A change
+/// that reverts before its delay has passed restarts the timing, so
+/// glitches and contact bounce never reach the output in either direction.
+/// Unlike TON, which drops its output as soon as the input falls, a short
+/// low pulse does not turn a debounced output off.
+pub struct DebounceBlock {
+    name: String,
+    input: String,
+    output: String,
+    on_delay: Duration,
+    off_delay: Duration,
+    state: bool,
+    /// Time since the input started to differ from the output
+    pending: Option<Stopwatch>,
+}
+
+impl Block for DebounceBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        let input = bus.get_bool(&self.input)?;
+
+        if input == self.state {
+            // Change reverted (or none) - timing restarts on the next change
+            self.pending = None;
+        } else {
+            let delay = if input { self.on_delay } else { self.off_delay };
+            let elapsed = self.pending.get_or_insert_with(Stopwatch::default).tick(bus.now());
+            if elapsed >= delay {
+                self.state = input;
+                self.pending = None;
+            }
+        }
+
+        bus.set(&self.output, Value::Bool(self.state))?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &'static str {
+        "DEBOUNCE"
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.state = false;
+        self.pending = None;
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&DebounceState {
+            output: self.state,
+            pending_ms: self.pending.as_ref().map(Stopwatch::elapsed_ms),
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: DebounceState = decode_state(&self.name, state)?;
+        self.state = state.output;
+        self.pending = state.pending_ms.map(|ms| Stopwatch::resumed(Duration::from_millis(ms)));
+        Ok(())
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        vec![self.input.as_str()]
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        vec![self.output.as_str()]
+    }
+}
+
+/// Factory function for DEBOUNCE blocks
+///
+/// `delay_ms` sets both delays; `on_delay_ms` and `off_delay_ms` override
+/// it for one direction.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] without an `in` input or `out` output, or
+/// if both delays are 0.
+pub fn create_debounce_block(config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let delay_ms: u64 = get_numeric_parameter(config, "delay_ms", Some(0))?;
+    let on_delay_ms: u64 = get_numeric_parameter(config, "on_delay_ms", Some(delay_ms))?;
+    let off_delay_ms: u64 = get_numeric_parameter(config, "off_delay_ms", Some(delay_ms))?;
+    if on_delay_ms == 0 && off_delay_ms == 0 {
+        return Err(PlcError::Config(format!(
+            "DEBOUNCE block '{}' needs delay_ms, on_delay_ms or off_delay_ms greater than 0",
+            config.name
+        )));
+    }
+
+    let input = config.inputs.get("in").cloned().ok_or_else(|| {
+        PlcError::Config(format!("DEBOUNCE block '{}' missing input 'in'", config.name))
+    })?;
+    let output = config.outputs.get("out").cloned().ok_or_else(|| {
+        PlcError::Config(format!("DEBOUNCE block '{}' missing output 'out'", config.name))
+    })?;
+
+    Ok(Box::new(DebounceBlock {
+        name: config.name.clone(),
+        input,
+        output,
+        on_delay: Duration::from_millis(on_delay_ms),
+        off_delay: Duration::from_millis(off_delay_ms),
+        state: false,
+        pending: None,
+    }))
+}
+
 // ============================================================================
 // COUNTER UP (CTU)
 // ============================================================================
@@ -577,6 +976,20 @@ impl Block for CountUpBlock {
         self.last_count_input = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&CounterState {
+            count: self.count,
+            last_count_input: self.last_count_input,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: CounterState = decode_state(&self.name, state)?;
+        self.count = state.count;
+        self.last_count_input = state.last_count_input;
+        Ok(())
+    }
 }
 
 /// Factory function for CTU blocks
@@ -700,6 +1113,20 @@ impl Block for CountDownBlock {
         self.last_count_input = false;
         Ok(())
     }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        encode_state(&CounterState {
+            count: self.count,
+            last_count_input: self.last_count_input,
+        })
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> Result<()> {
+        let state: CounterState = decode_state(&self.name, state)?;
+        self.count = state.count;
+        self.last_count_input = state.last_count_input;
+        Ok(())
+    }
 }
 
 /// Factory function for CTD blocks
@@ -781,6 +1208,7 @@ mod tests {
             params: HashMap::new(),
             description: None,
             tags: vec![],
+            ..Default::default()
         };
 
         config.params.insert(
@@ -795,6 +1223,7 @@ mod tests {
     async fn test_timer_on_block() {
         let bus = SignalBus::new();
         bus.set("timer_input", Value::Bool(false)).unwrap();
+        bus.set("timer_elapsed", Value::Integer(0)).unwrap();
 
         let mut config = create_test_config("TON", 100);
         config
@@ -903,6 +1332,88 @@ mod tests {
         assert_eq!(bus.get_bool("timer_output").unwrap(), false);
     }
 
+    #[tokio::test]
+    async fn test_retentive_timer_block() {
+        let bus = SignalBus::new();
+        bus.set("timer_input", Value::Bool(false)).unwrap();
+        bus.set("timer_reset", Value::Bool(false)).unwrap();
+
+        let mut config = create_test_config("TONR", 100);
+        config
+            .inputs
+            .insert("in".to_string(), "timer_input".to_string());
+        config
+            .inputs
+            .insert("reset".to_string(), "timer_reset".to_string());
+        config
+            .outputs
+            .insert("out".to_string(), "timer_output".to_string());
+
+        let mut block = create_retentive_timer_block(&config).unwrap();
+
+        // Accumulate 60ms, pause, then 60ms more - total exceeds preset
+        bus.set("timer_input", Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        bus.set("timer_input", Value::Bool(false)).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_bool("timer_output").unwrap(), false);
+
+        // Accumulated time is retained across a restart
+        let state = block.save_state().unwrap();
+        let mut block = create_retentive_timer_block(&config).unwrap();
+        block.load_state(state).unwrap();
+
+        bus.set("timer_input", Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_bool("timer_output").unwrap(), true);
+
+        // Output holds when input drops; only reset clears it
+        bus.set("timer_input", Value::Bool(false)).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_bool("timer_output").unwrap(), true);
+
+        bus.set("timer_reset", Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get_bool("timer_output").unwrap(), false);
+    }
+
+    #[test]
+    fn test_debounce_block() {
+        let clock = std::sync::Arc::new(crate::clock::SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        bus.set("contact", Value::Bool(false)).unwrap();
+
+        let mut config = create_test_config("DEBOUNCE", 0);
+        config.inputs.insert("in".to_string(), "contact".to_string());
+        config.outputs.insert("out".to_string(), "contact_ok".to_string());
+        config.params.insert("on_delay_ms".to_string(), serde_yaml::Value::Number(50.into()));
+        config.params.insert("off_delay_ms".to_string(), serde_yaml::Value::Number(200.into()));
+        let mut block = create_debounce_block(&config).unwrap();
+        let mut step = |input: bool, ms: u64| {
+            bus.set("contact", Value::Bool(input)).unwrap();
+            block.execute(&bus).unwrap();
+            clock.advance(Duration::from_millis(ms));
+            bus.get_bool("contact_ok").unwrap()
+        };
+
+        // A 40 ms pulse is rejected; a stable input passes after 50 ms
+        assert!(!step(true, 40));
+        assert!(!step(false, 10));
+        assert!(!step(true, 30));
+        assert!(!step(true, 30));
+        assert!(step(true, 100));
+
+        // A short drop does not turn the output off, a long one does
+        assert!(step(false, 150));
+        assert!(step(true, 10));
+        assert!(step(false, 150));
+        assert!(step(false, 60));
+        assert!(!step(false, 0));
+    }
+
     #[test]
     fn test_count_up_block() {
         let bus = SignalBus::new();
@@ -915,6 +1426,7 @@ mod tests {
             params: HashMap::new(),
             description: None,
             tags: vec![],
+            ..Default::default()
         };
 
         config.params.insert(
@@ -970,20 +1482,22 @@ mod tests {
 
     #[test]
     fn test_timer_validation() {
-        let mut config = create_test_config("TON", 0);
+        let mut config = create_test_config("TON", 100);
 
-        // Test zero preset
+        // Test missing input
         let result = create_timer_on_block(&config);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("greater than 0"));
+        assert!(result.err().unwrap().to_string().contains("requires at least one input"));
 
-        // Test missing input
+        // Test zero preset
+        config.inputs.insert("in".to_string(), "timer_input".to_string());
+        config.outputs.insert("out".to_string(), "timer_output".to_string());
         config.params.insert(
             "preset_ms".to_string(),
-            serde_yaml::Value::Number(serde_yaml::Number::from(100)),
+            serde_yaml::Value::Number(serde_yaml::Number::from(0)),
         );
         let result = create_timer_on_block(&config);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("missing input"));
+        assert!(result.err().unwrap().to_string().contains("greater than 0"));
     }
 }
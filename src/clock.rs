@@ -0,0 +1,225 @@
+// src/clock.rs
+//! Engine clock sources
+//!
+//! Timer blocks and the simulator read time from the clock of the signal bus
+//! they run on ([`SignalBus::now`](crate::SignalBus::now)) instead of the
+//! system clock, so the same logic can run against virtual time:
+//!
+//! ```yaml
+//! clock:
+//!   source: simulated
+//!   speed: 60        # one virtual minute per real second
+//! ```
+//!
+//! The default `system` source is the monotonic system clock. A simulated
+//! clock runs `speed` times faster than real time, and so do the engine's
+//! scan cycles: with a 100 ms scan time and a speed of 60 the engine scans
+//! every 1.67 ms of real time. Tests build an engine on a stepped clock with
+//! [`Engine::new_stepped`](crate::Engine::new_stepped) and move it forward
+//! with [`Engine::step`](crate::Engine::step), which runs exactly the scan
+//! cycles due in that virtual time and makes timer behaviour deterministic.
+
+use crate::error::{PlcError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the current time
+pub trait Clock: Debug + Send + Sync {
+    /// Monotonic time, used to measure intervals
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time matching [`now`](Self::now)
+    fn system_time(&self) -> SystemTime;
+}
+
+/// The real system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Virtual time running at a multiple of real time, or only when stepped
+#[derive(Debug)]
+pub struct SimulatedClock {
+    origin: Instant,
+    wall_origin: SystemTime,
+    speed: f64,
+    /// Time added by [`advance`](Self::advance), in nanoseconds
+    advanced_ns: AtomicU64,
+}
+
+impl SimulatedClock {
+    /// Clock running `speed` times faster than real time
+    ///
+    /// A speed of zero gives a stepped clock.
+    #[must_use]
+    pub fn accelerated(speed: f64) -> Self {
+        Self {
+            origin: Instant::now(),
+            wall_origin: SystemTime::now(),
+            speed: speed.max(0.0),
+            advanced_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Clock that only moves when advanced
+    #[must_use]
+    pub fn stepped() -> Self {
+        Self::accelerated(0.0)
+    }
+
+    /// Stepped clock whose wall time starts at `wall`
+    #[must_use]
+    pub fn stepped_at(wall: SystemTime) -> Self {
+        Self {
+            wall_origin: wall,
+            ..Self::stepped()
+        }
+    }
+
+    /// Virtual seconds per real second; zero for a stepped clock
+    #[must_use]
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Whether the clock only moves when advanced
+    #[must_use]
+    pub fn is_stepped(&self) -> bool {
+        self.speed == 0.0
+    }
+
+    /// Move the clock forward by `step`
+    pub fn advance(&self, step: Duration) {
+        let ns = u64::try_from(step.as_nanos()).unwrap_or(u64::MAX);
+        self.advanced_ns.fetch_add(ns, Ordering::Relaxed);
+    }
+
+    /// Virtual time since the clock was created
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        let advanced = Duration::from_nanos(self.advanced_ns.load(Ordering::Relaxed));
+        if self.is_stepped() {
+            return advanced;
+        }
+        self.origin.elapsed().mul_f64(self.speed) + advanced
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.wall_origin + self.elapsed()
+    }
+}
+
+/// Clock the engine runs on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ClockConfig {
+    /// Real time
+    #[default]
+    System,
+    /// Virtual time at a multiple of real time
+    Simulated {
+        /// Virtual seconds per real second
+        #[serde(default = "default_speed")]
+        speed: f64,
+    },
+}
+
+const fn default_speed() -> f64 {
+    1.0
+}
+
+impl ClockConfig {
+    /// Validate the clock settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if a simulated clock's speed is not a
+    /// positive number.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::System => Ok(()),
+            Self::Simulated { speed } if speed.is_finite() && *speed > 0.0 => Ok(()),
+            Self::Simulated { speed } => Err(PlcError::Config(format!(
+                "Simulated clock speed must be a positive number, got {speed}"
+            ))),
+        }
+    }
+
+    /// Create the configured clock
+    #[must_use]
+    pub fn build(&self) -> Arc<dyn Clock> {
+        match self {
+            Self::System => Arc::new(SystemClock),
+            Self::Simulated { speed } => Arc::new(SimulatedClock::accelerated(*speed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stepped_clock_moves_only_when_advanced() {
+        let clock = SimulatedClock::stepped();
+        let start = clock.now();
+        let wall = clock.system_time();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now() - start, Duration::from_secs(90));
+        assert_eq!(clock.system_time().duration_since(wall).unwrap(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_timer_blocks_follow_the_bus_clock() {
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = crate::SignalBus::new().with_clock(clock.clone());
+        let config: crate::config::BlockConfig = serde_yaml::from_str(
+            "name: start_delay\ntype: ON_DELAY\ninputs: { in: start }\noutputs: { out: run }\nparams: { preset_ms: 5000 }\n",
+        )
+        .unwrap();
+        let mut block = crate::blocks::create_block(&config).unwrap();
+
+        bus.set("start", crate::Value::Bool(true)).unwrap();
+        block.execute(&bus).unwrap();
+        clock.advance(Duration::from_millis(4999));
+        block.execute(&bus).unwrap();
+        assert!(!bus.get_bool("run").unwrap());
+
+        clock.advance(Duration::from_millis(1));
+        block.execute(&bus).unwrap();
+        assert!(bus.get_bool("run").unwrap());
+    }
+
+    #[test]
+    fn test_config() {
+        let config: ClockConfig = serde_yaml::from_str("source: simulated\nspeed: 60\n").unwrap();
+        assert_eq!(config, ClockConfig::Simulated { speed: 60.0 });
+        config.validate().unwrap();
+        assert!(ClockConfig::Simulated { speed: 0.0 }.validate().is_err());
+
+        let config: ClockConfig = serde_yaml::from_str("source: system\n").unwrap();
+        let clock = config.build();
+        assert!(clock.now() <= Instant::now());
+    }
+}
@@ -90,10 +90,11 @@
 
 use crate::{
     error::{PlcError, Result},
+    value::Value,
     Features,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 #[cfg(feature = "mqtt")]
@@ -105,7 +106,7 @@ use tracing::{debug, info, warn};
 // ============================================================================
 
 /// Severity levels for configuration lint rules
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum LintSeverity {
     /// Critical issues that will cause runtime failures
     Error,
@@ -116,7 +117,7 @@ pub enum LintSeverity {
 }
 
 /// Result of a configuration lint check
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LintResult {
     /// Lint rule that was violated
     pub rule: String,
@@ -232,6 +233,91 @@ pub struct Config {
     #[serde(default)]
     pub blocks: Vec<BlockConfig>,
     
+    /// Scan tasks with their own cycle times
+    /// 
+    /// Blocks name the task they run in; blocks without a task run in the
+    /// main scan every `scan_time_ms`. See [`TaskConfig`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<TaskConfig>,
+    
+    /// End-of-scan latching of block outputs
+    /// 
+    /// See [`OutputLatchConfig`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_latch: Option<OutputLatchConfig>,
+    
+    /// Load shedding when the main scan keeps overrunning
+    /// 
+    /// See [`DegradationConfig`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degradation: Option<DegradationConfig>,
+    
+    /// Named groups of signals snapshotted together between scan cycles
+    /// 
+    /// See [`SignalGroupConfig`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signal_groups: Vec<SignalGroupConfig>,
+    
+    /// Reusable signal and block definitions
+    /// 
+    /// Instantiated under a namespace by `instances`. See [`TemplateConfig`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub templates: Vec<TemplateConfig>,
+    
+    /// Templates instantiated under a namespace
+    /// 
+    /// See [`InstanceConfig`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<InstanceConfig>,
+    
+    /// Block types defined out of other blocks
+    /// 
+    /// Used in `blocks` like built-in types and expanded when the
+    /// configuration is loaded. See
+    /// [`CompositeBlockConfig`](crate::blocks::composite::CompositeBlockConfig).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub composite_blocks: Vec<crate::blocks::composite::CompositeBlockConfig>,
+    
+    /// Alternative names of signals, keyed by alias
+    /// 
+    /// Block references are rewritten to the target when the configuration
+    /// is loaded; the signal bus resolves aliases for every other reader
+    /// and writer.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    
+    /// Block state persistence across restarts
+    /// 
+    /// When set, timers, counters and other stateful blocks are saved to the
+    /// given file periodically and on shutdown, and restored on startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_state: Option<crate::blocks::persistence::BlockStateConfig>,
+    
+    /// Write-behind store of the signals configured with `retain`
+    /// 
+    /// Only included when the "retained-store" feature is enabled.
+    #[cfg(feature = "retained-store")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub retained_store: Option<crate::retained::RetainedStoreConfig>,
+    
+    /// Clock timers and simulations run on
+    /// 
+    /// Real time unless a simulated clock is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub clock: Option<crate::clock::ClockConfig>,
+    
+    /// Timeouts and retries of the startup stages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub startup: Option<crate::startup::StartupConfig>,
+    
+    /// Scan watchdog with heartbeat and fail-safe actions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub watchdog: Option<crate::watchdog::WatchdogConfig>,
+    
     // ========================================================================
     // PROTOCOL CONFIGURATION (conditionally present)
     // ========================================================================
@@ -278,6 +364,80 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub alarms: Option<AlarmConfig>,
     
+    /// OEE and production counter configuration
+    /// 
+    /// Only included when the "oee" feature is enabled. Defines the
+    /// production lines, their signals and the shift calendar.
+    #[cfg(feature = "oee")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oee: Option<crate::oee::OeeConfig>,
+    
+    /// Downtime tracking configuration
+    /// 
+    /// Only included when the "downtime" feature is enabled. Lists the
+    /// monitored assets and the reason codes operators can assign.
+    #[cfg(feature = "downtime")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downtime: Option<crate::downtime::DowntimeConfig>,
+    
+    /// Condition-based maintenance configuration
+    /// 
+    /// Only included when the "maintenance" feature is enabled. Lists the
+    /// maintained assets and the rules that raise work items.
+    #[cfg(feature = "maintenance")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<crate::maintenance::MaintenanceConfig>,
+    
+    /// Maintenance mode configuration
+    /// 
+    /// Only included when the "maintenance-mode" feature is enabled. Lists
+    /// the devices and areas operators can take out of service.
+    #[cfg(feature = "maintenance-mode")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub maintenance_mode: Option<crate::maintenance_mode::MaintenanceModeConfig>,
+    
+    /// Energy monitoring configuration
+    /// 
+    /// Only included when the "energy" feature is enabled. Lists the meters
+    /// whose consumption, cost and demand are aggregated.
+    #[cfg(feature = "energy")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<crate::energy::EnergyConfig>,
+    
+    /// Scheduled report configuration
+    /// 
+    /// Only included when the "reports" feature is enabled. Defines the
+    /// periodic reports and where they are delivered.
+    #[cfg(feature = "reports")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reports: Option<crate::reports::ReportsConfig>,
+    
+    /// Simulated signal overrides
+    /// 
+    /// Only included when the "simulation" feature is enabled. Takes effect
+    /// only under one of its environment profiles, e.g. `--profile sim`.
+    #[cfg(feature = "simulation")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<crate::simulation::SimulationConfig>,
+    
+    /// Clock synchronization monitoring
+    /// 
+    /// Only included when the "time-sync" feature is enabled. Selects the
+    /// NTP or PTP reference and the offset tolerance.
+    #[cfg(feature = "time-sync")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_sync: Option<crate::time_sync::TimeSyncConfig>,
+    
+    /// Hot-standby redundancy
+    /// 
+    /// Only included when the "redundancy" feature is enabled. Names this
+    /// node, its peer and the heartbeat and failover timing.
+    #[cfg(feature = "redundancy")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub redundancy: Option<crate::redundancy::RedundancyConfig>,
+    
     /// Web server and API configuration
     /// 
     /// Only included when the "web" feature is enabled. Configures
@@ -286,6 +446,14 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub web: Option<WebConfig>,
     
+    /// gRPC API configuration
+    /// 
+    /// Only included when the "grpc" feature is enabled. Lists the clients
+    /// allowed to call the service and the permissions of their roles.
+    #[cfg(feature = "grpc")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<crate::grpc::GrpcConfig>,
+    
     /// Validation rules configuration
     /// 
     /// Only included when the "validation" feature is enabled. Configures
@@ -310,6 +478,24 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub realtime: Option<RealtimeConfig>,
     
+    /// License file configuration
+    /// 
+    /// Only included when the "licensing" feature is enabled. Names the
+    /// signed license that limits what this node may run.
+    #[cfg(feature = "licensing")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub license: Option<crate::license::LicenseConfig>,
+    
+    /// Block plugin configuration
+    /// 
+    /// Only included when the "plugins" feature is enabled. Lists the
+    /// shared libraries and WASM modules whose block types are registered
+    /// before the blocks are created.
+    #[cfg(feature = "plugins")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<crate::plugins::PluginsConfig>,
+    
     // ========================================================================
     // METADATA AND VERSIONING
     // ========================================================================
@@ -486,6 +672,37 @@ pub struct SignalConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub validation: Option<SignalValidationConfig>,
     
+    /// Display formatting for operator-facing output
+    /// 
+    /// Units, decimal places and enumeration labels applied when the value
+    /// is rendered by the web API, MQTT JSON payloads and reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<crate::display::DisplayFormat>,
+    
+    /// Raw values and labels of an `enum` signal
+    /// 
+    /// The bus and protocol drivers carry the raw integer; the initial
+    /// value, block parameters and displayed values may use the label.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<i64, String>,
+    
+    /// Keep the value across restarts
+    /// 
+    /// Saved with the block state (`block_state.path`) and, with the
+    /// `retained-store` feature and a `retained_store` section, stored on
+    /// every write; restored over the initial value at startup. Ignored
+    /// without either.
+    #[serde(default, alias = "retained")]
+    pub retain: bool,
+    
+    /// Clamps, rate limits, deadband and NaN/Inf handling for writes to this
+    /// signal
+    /// 
+    /// Enforced by the signal bus for writes from blocks, protocols, the
+    /// API and every other source (see [`crate::signal::WritePolicy`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_policy: Option<crate::signal::WritePolicy>,
+    
     /// Additional custom metadata for extensibility
     #[serde(default)]
     pub metadata: HashMap<String, serde_yaml::Value>,
@@ -595,11 +812,26 @@ pub struct BlockConfig {
     
     /// Block execution priority (higher = earlier execution)
     /// 
-    /// Determines execution order within a scan cycle. Higher priority
+    /// Orders blocks within their [`phase`](Self::phase). Higher priority
     /// blocks execute before lower priority blocks.
     #[serde(default)]
     pub priority: i32,
     
+    /// Execution phase of the block
+    /// 
+    /// When unset, a `category` naming a phase (case-insensitive) assigns
+    /// the block to that phase; other blocks run in `control`. See
+    /// [`BlockPhase`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<BlockPhase>,
+    
+    /// Scan task the block runs in (main scan when unset)
+    /// 
+    /// Must name one of the configured `tasks`. Phase and priority order
+    /// blocks within their task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    
     /// Whether block is enabled (disabled blocks are skipped)
     /// 
     /// Disabled blocks are not executed but remain in the configuration
@@ -627,6 +859,13 @@ pub struct BlockConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub circuit_breaker: Option<CircuitBreakerConfig>,
     
+    /// Execution time budget
+    /// 
+    /// Blocks that take longer than the budget are logged and counted, and
+    /// can be skipped for a few cycles or taken out of the scan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BlockBudgetConfig>,
+    
     /// Enhanced monitoring for this block
     /// 
     /// Only available with the "enhanced-monitoring" feature. Enables
@@ -640,6 +879,430 @@ pub struct BlockConfig {
     pub metadata: HashMap<String, serde_yaml::Value>,
 }
 
+impl Default for BlockConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            block_type: String::new(),
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+            params: HashMap::new(),
+            priority: 0,
+            phase: None,
+            task: None,
+            enabled: default_enabled(),
+            description: None,
+            category: None,
+            tags: Vec::new(),
+            #[cfg(feature = "circuit-breaker")]
+            circuit_breaker: None,
+            budget: None,
+            #[cfg(feature = "enhanced-monitoring")]
+            enhanced_monitoring: false,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
+/// A scan task running its blocks on its own timer
+/// 
+/// Tasks let fast control loops run more often than slow supervisory
+/// logic without shortening the main scan:
+/// 
+/// ```yaml
+/// scan_time_ms: 100
+/// tasks:
+///   - { name: fast, interval_ms: 5 }
+///   - { name: slow, interval_ms: 1000 }
+/// blocks:
+///   - { name: pressure_pid, type: PID, task: fast, ... }
+///   - { name: shift_report, type: COUNTER, task: slow, ... }
+/// ```
+/// 
+/// Each task works on a process image: at the start of a cycle it copies
+/// the signals its blocks use from the signal bus, executes its blocks on
+/// the copy and then writes the changed outputs back in one transaction.
+/// Copies and write-backs of different tasks never overlap, so a task sees
+/// all outputs of another task's cycle or none of them. A signal may only
+/// be written by blocks of one task.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct TaskConfig {
+    /// Name blocks refer to the task by
+    pub name: String,
+    
+    /// Time between the starts of two cycles (milliseconds)
+    pub interval_ms: u64,
+}
+
+/// End-of-scan output latch
+/// 
+/// Without tasks, blocks of the main scan write their outputs to the
+/// signal bus as they execute, so protocol drivers can pick up
+/// intermediate values of a cycle. With an output latch the main scan
+/// works on a process image like a task does, and the outputs a cycle
+/// changed reach the bus together when the cycle ends:
+/// 
+/// ```yaml
+/// output_latch:
+///   order: [interlock_ok, "valve_*", pump_run]
+/// ```
+/// 
+/// The latch guarantees atomicity only: a reader of the bus sees all of a
+/// cycle's outputs or none of them. `order` sets the order in which the
+/// transaction applies the outputs and notifies bus subscribers; each
+/// entry is a signal name or a prefix ending in `*`, an output takes the
+/// position of the first entry it matches, and outputs matching no entry
+/// follow in name order. It does not order protocol writes: drivers pick
+/// changed signals up in their own mapping order, so field devices may
+/// receive a cycle's outputs in any order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct OutputLatchConfig {
+    /// Signals applied to the bus first, in this order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+}
+
+impl OutputLatchConfig {
+    /// Position of `signal` in the write order; unmatched signals sort last
+    #[must_use]
+    pub fn rank(&self, signal: &str) -> usize {
+        self.order
+            .iter()
+            .position(|entry| match entry.strip_suffix('*') {
+                Some(prefix) => signal.starts_with(prefix),
+                None => entry == signal,
+            })
+            .unwrap_or(self.order.len())
+    }
+    
+    fn validate(&self) -> Result<()> {
+        let mut entries = HashSet::new();
+        for entry in &self.order {
+            if entry.is_empty() || entry == "*" || entry.strip_suffix('*').unwrap_or(entry).contains('*') {
+                return Err(PlcError::Config(format!(
+                    "Invalid output_latch order entry '{entry}': expected a signal name or a prefix ending in '*'"
+                )));
+            }
+            if !entries.insert(entry.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate output_latch order entry '{entry}'")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Signals snapshotted together
+/// 
+/// At the end of every main scan cycle the engine copies the values of each
+/// group, so
+/// [`SignalBus::snapshot_group`](crate::signal::SignalBus::snapshot_group)
+/// hands reporting and history a set of values from one cycle:
+/// 
+/// ```yaml
+/// signal_groups:
+///   - name: press1
+///     signals: [press1.position, press1.force, press1.cycle_count]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct SignalGroupConfig {
+    /// Name the group is requested by
+    pub name: String,
+    
+    /// Configured signals in the group
+    pub signals: Vec<String>,
+}
+
+/// Signals and blocks defined once and instantiated per unit
+/// 
+/// Names starting with `./` are relative to the namespace of the instance,
+/// in signal and block names, block inputs and outputs, and string block
+/// parameters. Other names refer to the same signal in every instance:
+/// 
+/// ```yaml
+/// templates:
+///   - name: tank
+///     signals:
+///       - { name: ./level, type: float }
+///       - { name: ./high, type: bool }
+///     blocks:
+///       - name: ./high_alarm
+///         type: GT
+///         inputs: { a: ./level, b: plant.high_limit }
+///         outputs: { out: ./high }
+/// instances:
+///   - { template: tank, namespace: tank1 }
+///   - { template: tank, namespace: tank2 }
+/// ```
+/// 
+/// defines `tank1.level`, `tank2.level` and so on when the configuration is
+/// loaded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct TemplateConfig {
+    /// Name instances refer to the template by
+    pub name: String,
+    
+    /// Signals of every instance
+    #[serde(default)]
+    pub signals: Vec<SignalConfig>,
+    
+    /// Blocks of every instance
+    #[serde(default)]
+    pub blocks: Vec<BlockConfig>,
+}
+
+/// A template instantiated under a namespace
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct InstanceConfig {
+    /// Name of the template
+    pub template: String,
+    
+    /// Prefix replacing `./` in the template's names
+    pub namespace: String,
+}
+
+impl InstanceConfig {
+    /// `name` with a leading `./` replaced by the namespace
+    pub(crate) fn qualify(&self, name: &str) -> String {
+        match name.strip_prefix("./") {
+            Some(relative) => format!("{}.{}", self.namespace, relative),
+            None => name.to_string(),
+        }
+    }
+    
+    pub(crate) fn signal(&self, template: &SignalConfig) -> SignalConfig {
+        SignalConfig { name: self.qualify(&template.name), ..template.clone() }
+    }
+    
+    pub(crate) fn block(&self, template: &BlockConfig) -> BlockConfig {
+        let qualify_all = |names: &HashMap<String, String>| {
+            names.iter().map(|(port, signal)| (port.clone(), self.qualify(signal))).collect()
+        };
+        let params = template
+            .params
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_yaml::Value::String(name) => serde_yaml::Value::String(self.qualify(name)),
+                    other => other.clone(),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        BlockConfig {
+            name: self.qualify(&template.name),
+            inputs: qualify_all(&template.inputs),
+            outputs: qualify_all(&template.outputs),
+            params,
+            ..template.clone()
+        }
+    }
+}
+
+/// Degraded mode policy
+/// 
+/// After `enter_after` overrunning scans in a row the engine stops
+/// executing the shed blocks and runs the stretched tasks less often, until
+/// `exit_after` scans in a row stay under `recover_percent` of the scan
+/// time:
+/// 
+/// ```yaml
+/// degradation:
+///   enter_after: 5
+///   shed_below_priority: 0
+///   shed_phases: [diagnostics]
+///   stretch_tasks: [reporting]
+///   stretch_factor: 4
+/// ```
+/// 
+/// The mode is published as `engine.degraded`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct DegradationConfig {
+    /// Overrunning scans in a row that switch to degraded mode
+    #[serde(default = "default_enter_after")]
+    pub enter_after: u32,
+    
+    /// Scans in a row under `recover_percent` that end degraded mode
+    #[serde(default = "default_exit_after")]
+    pub exit_after: u32,
+    
+    /// Share of the scan time a scan must stay under to count towards
+    /// recovery (percent)
+    #[serde(default = "default_recover_percent")]
+    pub recover_percent: u32,
+    
+    /// Blocks with a lower priority are shed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shed_below_priority: Option<i32>,
+    
+    /// Blocks in these phases are shed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shed_phases: Vec<BlockPhase>,
+    
+    /// Tasks that run less often while degraded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stretch_tasks: Vec<String>,
+    
+    /// Stretched tasks run every `stretch_factor`-th cycle
+    #[serde(default = "default_stretch_factor")]
+    pub stretch_factor: u32,
+}
+
+impl DegradationConfig {
+    /// Validate the policy against the configured `tasks`
+    fn validate(&self, tasks: &[TaskConfig]) -> Result<()> {
+        if self.enter_after == 0 || self.exit_after == 0 {
+            return Err(PlcError::Config(
+                "Degradation enter_after and exit_after must be greater than 0".to_string(),
+            ));
+        }
+        if !(1..=100).contains(&self.recover_percent) {
+            return Err(PlcError::Config(format!(
+                "Degradation recover_percent must be between 1 and 100, got {}", self.recover_percent
+            )));
+        }
+        if self.stretch_factor == 0 {
+            return Err(PlcError::Config("Degradation stretch_factor must be at least 1".to_string()));
+        }
+        if let Some(task) = self.stretch_tasks.iter().find(|t| !tasks.iter().any(|c| &c.name == *t)) {
+            return Err(PlcError::Config(format!("Degradation stretches unknown task '{task}'")));
+        }
+        Ok(())
+    }
+}
+
+/// Execution time budget of a block
+/// 
+/// ```yaml
+/// budget: { max_us: 500, on_overrun: skip, skip_cycles: 10 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct BlockBudgetConfig {
+    /// Longest execution allowed per cycle (microseconds)
+    pub max_us: u64,
+    
+    /// What happens beyond logging and counting the overrun
+    #[serde(default)]
+    pub on_overrun: OverrunAction,
+    
+    /// Cycles the block sits out after an overrun, with `on_overrun: skip`
+    #[serde(default = "default_skip_cycles")]
+    pub skip_cycles: u32,
+    
+    /// Consecutive overruns that trip the block, with `on_overrun: trip`
+    #[serde(default = "default_trip_after")]
+    pub trip_after: u32,
+    
+    /// Time a tripped block stays out of the scan before it is retried
+    /// (milliseconds)
+    #[serde(default = "default_trip_reset_ms")]
+    pub reset_ms: u64,
+}
+
+/// Execution phase of a block within a scan cycle
+/// 
+/// Phases run in this order; within a phase, blocks run by descending
+/// `priority` and then in configuration order:
+/// 
+/// ```yaml
+/// blocks:
+///   - { name: level_scale, type: SCALE, phase: input, ... }
+///   - { name: level_pid, type: PID, ... }                  # control
+///   - { name: pump_limit, type: LIMIT, phase: output, ... }
+///   - { name: pid_watch, type: GT, category: Diagnostics, ... }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BlockPhase {
+    /// Scaling, filtering and validating raw inputs
+    #[serde(alias = "input_processing")]
+    Input,
+    /// Control logic
+    #[default]
+    Control,
+    /// Limiting and conditioning outputs before they are written
+    #[serde(alias = "output_processing")]
+    Output,
+    /// Monitoring the scan's results
+    Diagnostics,
+}
+
+impl BlockPhase {
+    /// All phases in execution order
+    pub const ALL: [Self; 4] = [Self::Input, Self::Control, Self::Output, Self::Diagnostics];
+    
+    /// Name used in configurations
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Control => "control",
+            Self::Output => "output",
+            Self::Diagnostics => "diagnostics",
+        }
+    }
+    
+    /// Phase named by `name`, also accepting the long forms
+    /// `input_processing` and `output_processing`, in any case
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase().replace('-', "_");
+        match name.as_str() {
+            "input" | "input_processing" => Some(Self::Input),
+            "control" => Some(Self::Control),
+            "output" | "output_processing" => Some(Self::Output),
+            "diagnostics" => Some(Self::Diagnostics),
+            _ => None,
+        }
+    }
+}
+
+/// Handling of a block that overran its budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum OverrunAction {
+    /// Keep executing the block every cycle
+    #[default]
+    Log,
+    /// Skip the block for `skip_cycles` cycles
+    Skip,
+    /// Stop executing the block after `trip_after` overruns in a row
+    Trip,
+}
+
+impl BlockBudgetConfig {
+    /// Validate the budget of block `block`
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::Config`] for a zero budget, skip count, trip
+    /// count or reset time.
+    pub fn validate(&self, block: &str) -> Result<()> {
+        if self.max_us == 0 {
+            return Err(PlcError::Config(format!("Block '{block}' has a zero execution budget")));
+        }
+        let zero = match self.on_overrun {
+            OverrunAction::Log => None,
+            OverrunAction::Skip => (self.skip_cycles == 0).then_some("skip_cycles"),
+            OverrunAction::Trip => (self.trip_after == 0)
+                .then_some("trip_after")
+                .or((self.reset_ms == 0).then_some("reset_ms")),
+        };
+        match zero {
+            Some(field) => Err(PlcError::Config(format!("Block '{block}' budget {field} must be at least 1"))),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Circuit breaker configuration for fault tolerance
 /// 
 /// Only available with the "circuit-breaker" feature. Implements the circuit
@@ -696,6 +1359,123 @@ pub struct ProtocolConfig {
     #[cfg(feature = "opcua-support")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub opcua: Option<OpcuaConfig>,
+    
+    /// OPC-UA server configuration
+    /// 
+    /// Only available with the "opcua-server" feature. Exposes signals to
+    /// OPC-UA clients, with historical access when history is enabled.
+    #[cfg(feature = "opcua-server")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcua_server: Option<OpcuaServerConfig>,
+    
+    /// DNP3 protocol configuration
+    /// 
+    /// Only available with the "dnp3-support" feature. Configures DNP3 masters
+    /// polling remote outstations and outstations serving upstream SCADA.
+    #[cfg(feature = "dnp3-support")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dnp3: Option<Dnp3Config>,
+    
+    /// `EtherCAT` master configuration
+    /// 
+    /// Only available with the "ethercat" feature. Maps the process data of
+    /// slaves on a raw Ethernet segment to signals, exchanged every scan.
+    #[cfg(feature = "ethercat")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub ethercat: Option<crate::protocols::ethercat::EthercatConfig>,
+    
+    /// PROFINET IO device configuration
+    /// 
+    /// Only available with the "profinet" feature. Exposes signals as the
+    /// cyclic IO data of GSDML modules to a PROFINET IO controller.
+    #[cfg(feature = "profinet")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub profinet: Option<crate::protocols::profinet::ProfinetConfig>,
+    
+    /// CoAP client configuration
+    /// 
+    /// Only available with the "coap" feature. Observes or polls resources
+    /// of constrained devices, optionally secured with DTLS-PSK.
+    #[cfg(feature = "coap")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub coap: Option<crate::protocols::coap::CoapConfig>,
+    
+    /// HTTP driver configuration
+    /// 
+    /// Only available with the "http" feature. Polls JSON or XML endpoints
+    /// into signals and posts signal changes to web services.
+    #[cfg(feature = "http")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub http: Option<crate::protocols::http::HttpConfig>,
+    
+    /// Kafka connector configuration
+    /// 
+    /// Only available with the "kafka" feature. Publishes signal changes to
+    /// a Kafka topic and consumes topics into signals.
+    #[cfg(feature = "kafka")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub kafka: Option<crate::protocols::kafka::KafkaConfig>,
+    
+    /// NATS connector configuration
+    /// 
+    /// Only available with the "nats" feature. Exchanges signals with other
+    /// PETRA instances over NATS, optionally persisted in JetStream.
+    #[cfg(feature = "nats")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub nats: Option<crate::protocols::nats::NatsConfig>,
+    
+    /// Signal bridges to other PETRA instances
+    /// 
+    /// Only available with the "bridge" feature. Mirrors signals selected by
+    /// patterns over NATS or gRPC links.
+    #[cfg(feature = "bridge")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub bridge: Option<crate::protocols::bridge::BridgeConfig>,
+    
+    /// Simulated drivers
+    /// 
+    /// Only available with the "protocol-sim" feature. Devices answering
+    /// polling and routes from waveforms, random values or recordings.
+    #[cfg(feature = "protocol-sim")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub sim: Vec<crate::protocols::sim::SimDriverConfig>,
+    
+    /// Failover groups of redundant connections to the same device
+    /// 
+    /// Each group lists protocol connections in order of preference; reads
+    /// and writes addressed to the group use the first healthy member.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub failover_groups: Vec<crate::protocols::failover::FailoverGroupConfig>,
+    
+    /// History backfill from devices that buffer values during outages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub backfill: Vec<crate::protocols::backfill::BackfillConfig>,
+    
+    /// Addresses polled in scan classes with independent rates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub polling: Option<crate::protocols::scheduler::PollingConfig>,
+    
+    /// Addresses routed directly from one protocol driver to another
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub routes: Vec<crate::protocols::routing::RouteConfig>,
+    
+    /// Queue taking over writes that keep failing, with retries and
+    /// operator review
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub dead_letters: Option<crate::protocols::dead_letter::DeadLetterConfig>,
 }
 
 // ============================================================================
@@ -712,6 +1492,7 @@ pub struct ProtocolConfig {
 #[cfg(feature = "mqtt")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+#[allow(clippy::struct_excessive_bools)] // independent YAML switches
 pub struct MqttConfig {
     /// MQTT broker hostname or IP address
     pub host: String,
@@ -767,6 +1548,40 @@ pub struct MqttConfig {
     /// Reconnection delay in seconds
     #[serde(default = "default_reconnect_delay")]
     pub reconnect_delay_secs: u64,
+
+    /// Start a clean session instead of resuming the one the broker kept
+    #[serde(default = "default_true")]
+    pub clean_session: bool,
+
+    /// Standby brokers taken over in order when the current broker fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub brokers: Vec<MqttBroker>,
+
+    /// Consecutive connection errors before moving to the next broker
+    #[serde(default = "default_mqtt_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Brokers that also receive everything published, each over a
+    /// session of its own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fan_out: Vec<MqttBroker>,
+
+    /// TLS settings; when present the connection always uses TLS
+    #[cfg(feature = "mqtt-tls")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<MqttTlsConfig>,
+
+    /// Authenticated inbound command channel
+    #[cfg(feature = "mqtt-commands")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commands: Option<crate::protocols::mqtt_commands::MqttCommandConfig>,
+
+    /// Sparkplug B edge node publishing signals as metrics
+    #[cfg(feature = "mqtt-sparkplug")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparkplug: Option<crate::protocols::sparkplug::SparkplugConfig>,
 }
 
 #[cfg(feature = "mqtt")]
@@ -788,6 +1603,16 @@ impl Default for MqttConfig {
             auto_reconnect: default_true(),
             max_reconnect_attempts: 0,
             reconnect_delay_secs: default_reconnect_delay(),
+            clean_session: true,
+            brokers: Vec::new(),
+            failure_threshold: default_mqtt_failure_threshold(),
+            fan_out: Vec::new(),
+            #[cfg(feature = "mqtt-tls")]
+            tls: None,
+            #[cfg(feature = "mqtt-commands")]
+            commands: None,
+            #[cfg(feature = "mqtt-sparkplug")]
+            sparkplug: None,
         }
     }
 }
@@ -825,25 +1650,115 @@ impl MqttConfig {
             }
         }
 
+        #[cfg(feature = "mqtt-tls")]
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+
+        if self.failure_threshold == 0 {
+            return Err(PlcError::Config("MQTT failure_threshold must be at least 1".to_string()));
+        }
+        for broker in self.brokers.iter().chain(&self.fan_out) {
+            broker.validate()?;
+        }
+
+        #[cfg(feature = "mqtt-commands")]
+        if let Some(commands) = &self.commands {
+            commands.validate()?;
+        }
+
+        #[cfg(feature = "mqtt-sparkplug")]
+        if let Some(sparkplug) = &self.sparkplug {
+            sparkplug.validate()?;
+        }
+
         Ok(())
     }
 
+    /// Whether the broker connection uses TLS
+    pub fn tls_enabled(&self) -> bool {
+        #[cfg(feature = "mqtt-tls")]
+        if self.tls.is_some() {
+            return true;
+        }
+        self.use_tls
+    }
+
     /// Get broker URL for connection
     pub fn broker_url(&self) -> String {
-        let protocol = if self.use_tls { "mqtts" } else { "mqtt" };
+        let protocol = if self.tls_enabled() { "mqtts" } else { "mqtt" };
         format!("{}://{}:{}", protocol, self.host, self.port)
     }
 
+    /// The same settings, connected to `broker` instead
+    ///
+    /// The broker's TLS settings replace the main ones rather than adding to
+    /// them; client id, credentials and the clean-session flag fall back to
+    /// the main settings where the broker leaves them unset. The result has
+    /// no standby or fan-out brokers of its own.
+    #[must_use]
+    pub fn on_broker(&self, broker: &MqttBroker) -> Self {
+        let (username, password) = if broker.username.is_some() {
+            (broker.username.clone(), broker.password.clone())
+        } else {
+            (self.username.clone(), self.password.clone())
+        };
+        Self {
+            host: broker.host.clone(),
+            port: broker.port,
+            client_id: broker.client_id.clone().unwrap_or_else(|| self.client_id.clone()),
+            username,
+            password,
+            use_tls: broker.use_tls,
+            clean_session: broker.clean_session.unwrap_or(self.clean_session),
+            brokers: Vec::new(),
+            fan_out: Vec::new(),
+            #[cfg(feature = "mqtt-tls")]
+            tls: broker.tls.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Settings for the main broker followed by each standby broker
+    #[must_use]
+    pub fn failover_configs(&self) -> Vec<Self> {
+        let main = Self {
+            brokers: Vec::new(),
+            fan_out: Vec::new(),
+            ..self.clone()
+        };
+        std::iter::once(main)
+            .chain(self.brokers.iter().map(|broker| self.on_broker(broker)))
+            .collect()
+    }
+
+    /// Settings for each fan-out broker
+    #[must_use]
+    pub fn fan_out_configs(&self) -> Vec<Self> {
+        self.fan_out.iter().map(|broker| self.on_broker(broker)).collect()
+    }
+
     /// Get connection options for rumqttc
-    pub fn connection_options(&self) -> rumqttc::MqttOptions {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TLS certificates cannot be loaded.
+    pub fn connection_options(&self) -> Result<rumqttc::MqttOptions> {
         let mut options = rumqttc::MqttOptions::new(&self.client_id, &self.host, self.port);
 
         options.set_keep_alive(Duration::from_secs(self.keepalive_secs as u64));
+        options.set_clean_session(self.clean_session);
 
         if let (Some(username), Some(password)) = (&self.username, &self.password) {
             options.set_credentials(username, password);
         }
 
+        #[cfg(feature = "mqtt-tls")]
+        if let Some(tls) = &self.tls {
+            options.set_transport(rumqttc::Transport::Tls(crate::protocols::mqtt_tls::client_config(tls)?));
+            return Ok(options);
+        }
+
         if self.use_tls {
             options.set_transport(rumqttc::Transport::Tls(
                 rumqttc::TlsConfiguration::Simple {
@@ -854,7 +1769,74 @@ impl MqttConfig {
             ));
         }
 
-        options
+        Ok(options)
+    }
+}
+
+/// Standby or fan-out MQTT broker
+///
+/// Unset client id, credentials and clean-session flag are taken from the
+/// main `mqtt` settings; TLS is configured per broker.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct MqttBroker {
+    /// Broker hostname or IP address
+    pub host: String,
+
+    /// Broker port
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+
+    /// Client identifier on this broker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// Username, replacing the main credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Password for `username`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Enable TLS/SSL connection
+    #[serde(default)]
+    pub use_tls: bool,
+
+    /// TLS settings; when present the connection always uses TLS
+    #[cfg(feature = "mqtt-tls")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<MqttTlsConfig>,
+
+    /// Start a clean session on this broker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clean_session: Option<bool>,
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttBroker {
+    /// Validate the broker settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an empty host or client id, a zero port or
+    /// invalid TLS settings.
+    pub fn validate(&self) -> Result<()> {
+        if self.host.is_empty() {
+            return Err(PlcError::Config("MQTT broker host cannot be empty".to_string()));
+        }
+        if self.port == 0 {
+            return Err(PlcError::Config(format!("MQTT broker {} port must be greater than 0", self.host)));
+        }
+        if self.client_id.as_deref().is_some_and(str::is_empty) {
+            return Err(PlcError::Config(format!("MQTT broker {} client_id cannot be empty", self.host)));
+        }
+        #[cfg(feature = "mqtt-tls")]
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+        Ok(())
     }
 }
 
@@ -921,6 +1903,44 @@ pub struct MqttTlsConfig {
     /// Whether to verify server certificate
     #[serde(default = "default_verify_server")]
     pub verify_server: bool,
+
+    /// ALPN protocols offered during the handshake
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpn: Vec<String>,
+
+    /// Fetch and renew the client certificate from the ca-service at startup
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_service: Option<crate::protocols::mqtt_tls::CaServiceConfig>,
+}
+
+#[cfg(all(feature = "mqtt", feature = "mqtt-tls"))]
+impl MqttTlsConfig {
+    /// Validate the TLS settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if only one of the client certificate and key is
+    /// given, or if the ca-service has nowhere to store what it issues.
+    pub fn validate(&self) -> Result<()> {
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err(PlcError::Config(
+                "MQTT TLS client_cert and client_key must be set together".to_string(),
+            ));
+        }
+        if self.alpn.iter().any(String::is_empty) {
+            return Err(PlcError::Config("MQTT TLS ALPN protocols cannot be empty".to_string()));
+        }
+        if let Some(ca_service) = &self.ca_service {
+            if self.client_cert.is_none() {
+                return Err(PlcError::Config(
+                    "MQTT TLS ca_service requires client_cert and client_key paths".to_string(),
+                ));
+            }
+            ca_service.validate()?;
+        }
+        Ok(())
+    }
 }
 
 /// MQTT last will configuration
@@ -974,6 +1994,11 @@ pub struct SecurityConfig {
     #[cfg(feature = "audit")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub audit: Option<AuditConfig>,
+    
+    /// Electronic signature requirements for critical actions
+    #[cfg(feature = "esignature")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub esignature: Option<crate::security::esignature::ESignatureConfig>,
 }
 
 /// Basic authentication configuration
@@ -1049,6 +2074,12 @@ pub struct AuditConfig {
     /// Events to audit
     #[serde(default = "default_audit_events")]
     pub events: Vec<String>,
+    
+    /// Signals whose operator writes must give a reason
+    /// 
+    /// Names may end in `*` to match a prefix.
+    #[serde(default)]
+    pub critical_signals: Vec<String>,
 }
 
 /// Historical data storage configuration
@@ -1238,6 +2269,12 @@ pub struct WebConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub static_dir: Option<PathBuf>,
     
+    /// Directory where per-user dashboard layouts are stored
+    /// 
+    /// When unset, dashboards are kept in memory and lost on restart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dashboard_dir: Option<PathBuf>,
+    
     /// CORS allowed origins
     #[serde(default)]
     pub cors_origins: Vec<String>,
@@ -1246,6 +2283,12 @@ pub struct WebConfig {
     #[cfg(feature = "web-tls")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tls: Option<WebTlsConfig>,
+    
+    /// Clients allowed to write signals and acknowledge alarms over WebSocket
+    #[cfg(feature = "rbac")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema-validation", schemars(skip))]
+    pub websocket_auth: Option<crate::web::websocket::WebSocketAuthConfig>,
 }
 
 /// Web TLS configuration
@@ -1374,9 +2417,53 @@ pub struct S7Connection {
     #[serde(default = "default_s7_connection_type")]
     pub connection_type: String,
     
-    /// Data areas to read/write
+    /// ISO-on-TCP port
+    #[serde(default = "default_s7_port")]
+    pub port: u16,
+    
+    /// Poll interval (milliseconds)
+    #[serde(default = "default_s7_poll_interval")]
+    pub poll_interval_ms: u64,
+    
+    /// Parallel connections to the PLC, each polling part of the tags
+    #[serde(default = "default_s7_pool_size")]
+    pub pool_size: usize,
+    
+    /// PDU size proposed to the PLC, which may negotiate it down
+    #[serde(default = "default_s7_pdu_size")]
+    pub pdu_size: u16,
+    
+    /// Unused bytes read to combine two nearby tags into one item
+    #[serde(default = "default_s7_merge_gap")]
+    pub merge_gap: u32,
+    
+    /// Data areas to read, one byte signal per address
     #[serde(default)]
     pub data_areas: Vec<S7DataArea>,
+    
+    /// Typed variables to read
+    #[serde(default)]
+    pub tags: Vec<S7Tag>,
+    
+    /// TIA Portal DB sources that tag symbols are resolved against
+    #[serde(default)]
+    pub symbol_sources: Vec<S7SymbolSource>,
+}
+
+/// Data block source exported from TIA Portal
+#[cfg(feature = "s7-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct S7SymbolSource {
+    /// Path of the `.db` source file
+    pub file: PathBuf,
+    
+    /// Number of the DB in the PLC
+    pub db_number: u16,
+    
+    /// Import every member as a signal under this prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal_prefix: Option<String>,
 }
 
 /// S7 data area configuration
@@ -1401,6 +2488,40 @@ pub struct S7DataArea {
     pub signal_prefix: String,
 }
 
+/// S7 variable mapped to a signal
+#[cfg(feature = "s7-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct S7Tag {
+    /// Signal receiving the value
+    pub signal: String,
+    
+    /// DB member such as `"Press".Motor.Speed`, replacing the address and
+    /// data type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    
+    /// Area type (DB, MB, IB, QB)
+    #[serde(default)]
+    pub area: String,
+    
+    /// DB number (for DB areas)
+    #[serde(default)]
+    pub db_number: u16,
+    
+    /// Byte offset
+    #[serde(default)]
+    pub offset: u32,
+    
+    /// Bit number (for bool tags)
+    #[serde(default)]
+    pub bit: u8,
+    
+    /// Data type (bool, byte, word, int, dword, dint, real, lreal)
+    #[serde(default)]
+    pub data_type: String,
+}
+
 /// Modbus protocol configuration
 #[cfg(feature = "modbus-support")]
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -1436,6 +2557,57 @@ pub struct ModbusConnection {
     /// Register mappings
     #[serde(default)]
     pub registers: Vec<ModbusRegister>,
+    
+    /// Serial line settings (for RTU)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<ModbusSerial>,
+}
+
+/// Serial line settings of a Modbus RTU connection
+///
+/// RTU connections on the same serial port share the line and must use the
+/// same settings.
+#[cfg(feature = "modbus-support")]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct ModbusSerial {
+    /// Baud rate
+    #[serde(default = "default_modbus_baud_rate")]
+    pub baud_rate: u32,
+    
+    /// Data bits (7, 8)
+    #[serde(default = "default_modbus_data_bits")]
+    pub data_bits: u8,
+    
+    /// Parity (none, even, odd)
+    #[serde(default = "default_modbus_parity")]
+    pub parity: String,
+    
+    /// Stop bits (1, 2)
+    #[serde(default = "default_modbus_stop_bits")]
+    pub stop_bits: u8,
+    
+    /// Silence between two frames (microseconds), 3.5 characters if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inter_frame_delay_us: Option<u64>,
+    
+    /// Time to poll every unit on the line once (milliseconds)
+    #[serde(default = "default_modbus_cycle")]
+    pub cycle_ms: u64,
+}
+
+#[cfg(feature = "modbus-support")]
+impl Default for ModbusSerial {
+    fn default() -> Self {
+        Self {
+            baud_rate: default_modbus_baud_rate(),
+            data_bits: default_modbus_data_bits(),
+            parity: default_modbus_parity(),
+            stop_bits: default_modbus_stop_bits(),
+            inter_frame_delay_us: None,
+            cycle_ms: default_modbus_cycle(),
+        }
+    }
 }
 
 /// Modbus register mapping
@@ -1461,6 +2633,125 @@ pub struct ModbusRegister {
     pub data_type: String,
 }
 
+/// DNP3 protocol configuration
+#[cfg(feature = "dnp3-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct Dnp3Config {
+    /// Master sessions polling remote outstations
+    #[serde(default)]
+    pub masters: Vec<Dnp3MasterConfig>,
+    
+    /// Outstations serving data to upstream SCADA masters
+    #[serde(default)]
+    pub outstations: Vec<Dnp3OutstationConfig>,
+}
+
+/// DNP3 master session polling one outstation over TCP
+#[cfg(feature = "dnp3-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct Dnp3MasterConfig {
+    /// Session name
+    pub name: String,
+    
+    /// Outstation address (host:port)
+    pub address: String,
+    
+    /// Link address of this master
+    #[serde(default = "default_dnp3_master_address")]
+    pub local_address: u16,
+    
+    /// Link address of the outstation
+    #[serde(default = "default_dnp3_outstation_address")]
+    pub remote_address: u16,
+    
+    /// Interval between integrity (class 0123) polls (milliseconds)
+    #[serde(default = "default_dnp3_integrity_poll")]
+    pub integrity_poll_ms: u64,
+    
+    /// Interval between event (class 123) polls (milliseconds)
+    #[serde(default = "default_dnp3_event_poll")]
+    pub event_poll_ms: u64,
+    
+    /// Response timeout (milliseconds)
+    #[serde(default = "default_connection_timeout")]
+    pub timeout_ms: u64,
+    
+    /// Point to signal mappings
+    #[serde(default)]
+    pub points: Vec<Dnp3Point>,
+}
+
+/// DNP3 outstation listening for a master over TCP
+#[cfg(feature = "dnp3-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct Dnp3OutstationConfig {
+    /// Outstation name
+    pub name: String,
+    
+    /// Listen address (host:port)
+    #[serde(default = "default_dnp3_bind")]
+    pub bind: String,
+    
+    /// Link address of this outstation
+    #[serde(default = "default_dnp3_outstation_address")]
+    pub local_address: u16,
+    
+    /// Link address of the master
+    #[serde(default = "default_dnp3_master_address")]
+    pub remote_address: u16,
+    
+    /// Maximum number of buffered events before the oldest are dropped
+    #[serde(default = "default_dnp3_event_buffer")]
+    pub event_buffer_size: usize,
+    
+    /// Interval for detecting signal changes (milliseconds)
+    #[serde(default = "default_dnp3_change_scan")]
+    pub change_scan_ms: u64,
+    
+    /// Point to signal mappings
+    #[serde(default)]
+    pub points: Vec<Dnp3Point>,
+}
+
+/// DNP3 point to signal mapping
+#[cfg(feature = "dnp3-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct Dnp3Point {
+    /// Point type (`binary_input`, `analog_input`)
+    #[serde(rename = "type")]
+    pub point_type: Dnp3PointType,
+    
+    /// Point index
+    pub index: u16,
+    
+    /// Associated signal name
+    pub signal: String,
+    
+    /// Event class 1-3, or 0 for static data only (outstation only)
+    #[serde(default = "default_dnp3_event_class")]
+    pub class: u8,
+    
+    /// Minimum change that generates an analog event (outstation only)
+    #[serde(default)]
+    pub deadband: f64,
+}
+
+/// DNP3 point types
+#[cfg(feature = "dnp3-support")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Dnp3PointType {
+    /// Binary input (groups 1 and 2)
+    BinaryInput,
+    /// Analog input (groups 30 and 32)
+    AnalogInput,
+}
+
 /// OPC-UA configuration
 #[cfg(feature = "opcua-support")]
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -1488,6 +2779,18 @@ pub struct OpcuaConfig {
     /// Subscriptions
     #[serde(default)]
     pub subscriptions: Vec<OpcuaSubscription>,
+    
+    /// Publishing interval of subscriptions that do not set one (milliseconds)
+    #[serde(default = "default_sampling_interval")]
+    pub publishing_interval_ms: u64,
+    
+    /// Most monitored items created per request
+    #[serde(default = "default_opcua_max_items_per_request")]
+    pub max_items_per_request: usize,
+    
+    /// Wait before reconnecting after the session was lost (milliseconds)
+    #[serde(default = "default_opcua_reconnect_interval")]
+    pub reconnect_interval_ms: u64,
 }
 
 /// OPC-UA authentication
@@ -1537,6 +2840,79 @@ pub struct OpcuaSubscription {
     /// Sampling interval (milliseconds)
     #[serde(default = "default_sampling_interval")]
     pub sampling_interval_ms: u64,
+    
+    /// Publishing interval (milliseconds); items with the same interval share
+    /// one subscription
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publishing_interval_ms: Option<u64>,
+    
+    /// Values the server queues between two publishes
+    #[serde(default = "default_opcua_queue_size")]
+    pub queue_size: u32,
+    
+    /// Drop the oldest queued value when the queue is full
+    #[serde(default = "default_enabled")]
+    pub discard_oldest: bool,
+    
+    /// Only report changes larger than the deadband
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadband: Option<OpcuaDeadband>,
+}
+
+/// OPC-UA data change deadband
+#[cfg(feature = "opcua-support")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct OpcuaDeadband {
+    /// Deadband type (absolute, percent of the EU range)
+    #[serde(rename = "type")]
+    pub deadband_type: String,
+    
+    /// Smallest change reported
+    pub value: f64,
+}
+
+/// OPC-UA server exposing signals as variables
+///
+/// Variables live in namespace `namespace_uri` with the signal name as
+/// string node id. With the "history" feature they are historizing and
+/// HistoryRead (raw values) is answered from the history storage.
+#[cfg(feature = "opcua-server")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema-validation", derive(JsonSchema))]
+pub struct OpcuaServerConfig {
+    /// Address to listen on
+    #[serde(default = "default_opcua_server_host")]
+    pub host: String,
+    
+    /// TCP port
+    #[serde(default = "default_opcua_server_port")]
+    pub port: u16,
+    
+    /// Application name announced to clients
+    #[serde(default = "default_opcua_application_name")]
+    pub application_name: String,
+    
+    /// Namespace of the signal variables
+    #[serde(default = "default_opcua_namespace_uri")]
+    pub namespace_uri: String,
+    
+    /// Directory of the server certificate and trusted client certificates
+    #[serde(default = "default_opcua_pki_dir")]
+    pub pki_dir: PathBuf,
+    
+    /// Signals to expose (all signals if empty)
+    #[serde(default)]
+    pub signals: Vec<String>,
+    
+    /// Interval at which variable values are refreshed (milliseconds)
+    #[serde(default = "default_sampling_interval")]
+    pub update_interval_ms: u64,
+    
+    /// Most values returned per node by one HistoryRead call; clients get a
+    /// continuation point for the rest
+    #[serde(default = "default_opcua_max_history_values")]
+    pub max_history_values: usize,
 }
 
 // ============================================================================
@@ -1559,9 +2935,36 @@ fn default_version() -> String { "1.0".to_string() }
 const fn default_connection_timeout() -> u64 { 5000 }
 const fn default_retry_count() -> u32 { 3 }
 fn default_s7_connection_type() -> String { "PG".to_string() }
+const fn default_s7_port() -> u16 { 102 }
+const fn default_s7_poll_interval() -> u64 { 1000 }
+const fn default_s7_pool_size() -> usize { 2 }
+const fn default_s7_pdu_size() -> u16 { 960 }
+const fn default_s7_merge_gap() -> u32 { 16 }
 fn default_modbus_data_type() -> String { "int16".to_string() }
+const fn default_modbus_baud_rate() -> u32 { 19_200 }
+const fn default_modbus_data_bits() -> u8 { 8 }
+fn default_modbus_parity() -> String { "even".to_string() }
+const fn default_modbus_stop_bits() -> u8 { 1 }
+const fn default_modbus_cycle() -> u64 { 1000 }
+const fn default_dnp3_master_address() -> u16 { 1 }
+const fn default_dnp3_outstation_address() -> u16 { 1024 }
+const fn default_dnp3_integrity_poll() -> u64 { 60_000 }
+const fn default_dnp3_event_poll() -> u64 { 1000 }
+const fn default_dnp3_event_buffer() -> usize { 1000 }
+const fn default_dnp3_change_scan() -> u64 { 100 }
+const fn default_dnp3_event_class() -> u8 { 1 }
+fn default_dnp3_bind() -> String { "0.0.0.0:20000".to_string() }
 fn default_opcua_security() -> String { "None".to_string() }
 fn default_opcua_security_mode() -> String { "None".to_string() }
+fn default_opcua_server_host() -> String { "0.0.0.0".to_string() }
+const fn default_opcua_server_port() -> u16 { 4840 }
+fn default_opcua_application_name() -> String { "PETRA".to_string() }
+fn default_opcua_namespace_uri() -> String { "urn:petra:signals".to_string() }
+fn default_opcua_pki_dir() -> PathBuf { PathBuf::from("pki") }
+const fn default_opcua_max_history_values() -> usize { 10_000 }
+const fn default_opcua_max_items_per_request() -> usize { 500 }
+const fn default_opcua_reconnect_interval() -> u64 { 5000 }
+const fn default_opcua_queue_size() -> u32 { 1 }
 const fn default_sampling_interval() -> u64 { 1000 }
 
 // MQTT defaults
@@ -1570,6 +2973,7 @@ fn default_keepalive_secs() -> u16 { 60 }
 fn default_timeout_ms() -> u64 { 5000 }
 fn default_true() -> bool { true }
 fn default_reconnect_delay() -> u64 { 5 }
+fn default_mqtt_failure_threshold() -> u32 { 3 }
 fn default_mqtt_client_id() -> String { "petra".to_string() }
 const fn default_mqtt_keep_alive() -> u16 { 60 }
 const fn default_clean_session() -> bool { true }
@@ -1640,6 +3044,15 @@ const fn default_validation_action() -> ValidationAction { ValidationAction::Log
 #[cfg(feature = "validation")]
 const fn default_log_failures() -> bool { true }
 
+// Block budget defaults
+const fn default_skip_cycles() -> u32 { 1 }
+const fn default_trip_after() -> u32 { 3 }
+const fn default_trip_reset_ms() -> u64 { 10000 }
+const fn default_enter_after() -> u32 { 5 }
+const fn default_exit_after() -> u32 { 50 }
+const fn default_recover_percent() -> u32 { 80 }
+const fn default_stretch_factor() -> u32 { 2 }
+
 // Circuit breaker defaults
 #[cfg(feature = "circuit-breaker")]
 const fn default_failure_threshold() -> u32 { 5 }
@@ -1705,6 +3118,49 @@ pub struct ValidationRule {
     pub error_message: Option<String>,
 }
 
+#[cfg(feature = "validation")]
+impl ValidationRule {
+    /// Evaluate this rule against `value`
+    /// 
+    /// `range` rules read optional `min` and `max` parameters and `enum`
+    /// rules a `values` list. Other rule types are not evaluated here.
+    fn check(&self, value: &Value) -> std::result::Result<(), String> {
+        match self.rule_type.as_str() {
+            "range" => {
+                let Some(v) = value.as_float() else {
+                    return Err(format!("{} is not numeric", value.type_name()));
+                };
+                let bound = |key: &str| self.parameters.get(key).and_then(serde_yaml::Value::as_f64);
+                if let Some(min) = bound("min").filter(|min| v < *min) {
+                    return Err(format!("{v} is below minimum {min}"));
+                }
+                if let Some(max) = bound("max").filter(|max| v > *max) {
+                    return Err(format!("{v} is above maximum {max}"));
+                }
+                Ok(())
+            }
+            "enum" => {
+                let allowed = self
+                    .parameters
+                    .get("values")
+                    .and_then(serde_yaml::Value::as_sequence)
+                    .is_some_and(|values| {
+                        values
+                            .iter()
+                            .filter_map(|v| crate::value::from_yaml_value(v.clone()).ok())
+                            .any(|v| v == *value)
+                    });
+                if allowed {
+                    Ok(())
+                } else {
+                    Err(format!("{value} is not an allowed value"))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 // ============================================================================
 // CORE IMPLEMENTATION
 // ============================================================================
@@ -1759,6 +3215,8 @@ impl Config {
         // Set metadata
         config.modified_at = Some(SystemTime::now());
         
+        config.expand()?;
+        
         // Perform comprehensive validation
         config.validate()?;
         
@@ -1771,6 +3229,70 @@ impl Config {
         Ok(config)
     }
     
+    /// Instantiate the templates, expand the composite blocks and resolve
+    /// aliases in block and signal group references
+    /// 
+    /// The expanded signals and blocks are appended to `signals` and
+    /// `blocks`, and `templates`, `instances` and `composite_blocks` are
+    /// left empty, so expanding again changes nothing.
+    /// [`from_file`](Self::from_file) does this before validating.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error for duplicate template names or namespaces, for
+    /// instances of unknown templates and for invalid composite blocks or
+    /// uses of them.
+    pub fn expand(&mut self) -> Result<()> {
+        let templates = std::mem::take(&mut self.templates);
+        let instances = std::mem::take(&mut self.instances);
+        
+        let mut by_name = HashMap::new();
+        for template in &templates {
+            if by_name.insert(template.name.as_str(), template).is_some() {
+                return Err(PlcError::Config(format!("Duplicate template name: '{}'", template.name)));
+            }
+        }
+        let mut namespaces = HashSet::new();
+        for instance in &instances {
+            if instance.namespace.is_empty() {
+                return Err(PlcError::Config(format!(
+                    "Instance of template '{}' has an empty namespace", instance.template
+                )));
+            }
+            if !namespaces.insert(instance.namespace.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate instance namespace: '{}'", instance.namespace)));
+            }
+            let template = by_name.get(instance.template.as_str()).ok_or_else(|| PlcError::Config(format!(
+                "Instance '{}' references unknown template '{}'", instance.namespace, instance.template
+            )))?;
+            self.signals.extend(template.signals.iter().map(|signal| instance.signal(signal)));
+            self.blocks.extend(template.blocks.iter().map(|block| instance.block(block)));
+            debug!(
+                "Instantiated template '{}' as '{}': {} signals, {} blocks",
+                template.name, instance.namespace, template.signals.len(), template.blocks.len()
+            );
+        }
+        
+        let composite_blocks = std::mem::take(&mut self.composite_blocks);
+        crate::blocks::composite::expand(&composite_blocks, &mut self.signals, &mut self.blocks)?;
+        
+        if !self.aliases.is_empty() {
+            let aliases = &self.aliases;
+            let resolve = |name: &mut String| {
+                if let Some(target) = aliases.get(name.as_str()) {
+                    name.clone_from(target);
+                }
+            };
+            for block in &mut self.blocks {
+                block.inputs.values_mut().chain(block.outputs.values_mut()).for_each(resolve);
+            }
+            for group in &mut self.signal_groups {
+                group.signals.iter_mut().for_each(resolve);
+            }
+        }
+        Ok(())
+    }
+    
     /// Save configuration to a YAML file
     /// 
     /// Updates the modification timestamp and writes the configuration
@@ -1849,6 +3371,7 @@ impl Config {
         
         // Block validation  
         self.validate_blocks()?;
+        self.validate_tasks()?;
         
         // Feature-specific validation
         self.validate_feature_configs()?;
@@ -1958,6 +3481,15 @@ impl Config {
             block.validate()?;
         }
         
+        if let Some(block_state) = &self.block_state {
+            block_state.validate()?;
+        }
+        
+        #[cfg(feature = "retained-store")]
+        if let Some(retained_store) = &self.retained_store {
+            retained_store.validate()?;
+        }
+        
         Ok(())
     }
     
@@ -1991,24 +3523,176 @@ impl Config {
             alarms.validate()?;
         }
         
+        #[cfg(feature = "oee")]
+        if let Some(oee) = &self.oee {
+            oee.validate()?;
+        }
+        
+        #[cfg(feature = "downtime")]
+        if let Some(downtime) = &self.downtime {
+            downtime.validate()?;
+        }
+        
+        #[cfg(feature = "maintenance")]
+        if let Some(maintenance) = &self.maintenance {
+            maintenance.validate()?;
+        }
+        
+        #[cfg(feature = "maintenance-mode")]
+        if let Some(maintenance_mode) = &self.maintenance_mode {
+            maintenance_mode.validate()?;
+        }
+        
+        #[cfg(feature = "energy")]
+        if let Some(energy) = &self.energy {
+            energy.validate()?;
+        }
+        
+        #[cfg(feature = "reports")]
+        if let Some(reports) = &self.reports {
+            reports.validate()?;
+        }
+        
+        if let Some(clock) = &self.clock {
+            clock.validate()?;
+        }
+        
+        if let Some(startup) = &self.startup {
+            startup.validate()?;
+        }
+        
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.validate()?;
+        }
+        
+        #[cfg(feature = "simulation")]
+        if let Some(simulation) = &self.simulation {
+            simulation.validate()?;
+        }
+        
+        #[cfg(feature = "time-sync")]
+        if let Some(time_sync) = &self.time_sync {
+            time_sync.validate()?;
+        }
+        
+        #[cfg(feature = "redundancy")]
+        if let Some(redundancy) = &self.redundancy {
+            redundancy.validate()?;
+        }
+        
         #[cfg(feature = "web")]
         if let Some(web) = &self.web {
             web.validate()?;
         }
         
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = &self.grpc {
+            grpc.validate()?;
+        }
+        
         #[cfg(feature = "validation")]
         if let Some(validation) = &self.validation {
             validation.validate()?;
         }
         
-        #[cfg(feature = "metrics")]
-        if let Some(metrics) = &self.metrics {
-            metrics.validate()?;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.validate()?;
+        }
+        
+        #[cfg(feature = "realtime")]
+        if let Some(realtime) = &self.realtime {
+            realtime.validate()?;
+        }
+        
+        #[cfg(feature = "plugins")]
+        if let Some(plugins) = &self.plugins {
+            plugins.validate()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Validate scan tasks and the blocks assigned to them
+    /// 
+    /// Task names must be unique, blocks must name a configured task, and
+    /// no signal may be written by blocks of two different tasks, which
+    /// would let the tasks overwrite each other's results.
+    fn validate_tasks(&self) -> Result<()> {
+        let mut task_names = HashSet::new();
+        for task in &self.tasks {
+            if task.name.is_empty() {
+                return Err(PlcError::Config("Task name cannot be empty".to_string()));
+            }
+            if !task_names.insert(task.name.as_str()) {
+                return Err(PlcError::Config(format!(
+                    "Duplicate task name: '{}'", task.name
+                )));
+            }
+            if task.interval_ms == 0 || task.interval_ms > 60_000 {
+                return Err(PlcError::Config(format!(
+                    "Task '{}' interval must be between 1 and 60000 ms", task.name
+                )));
+            }
+        }
+        
+        if let Some(output_latch) = &self.output_latch {
+            output_latch.validate()?;
+        }
+        
+        if let Some(degradation) = &self.degradation {
+            degradation.validate(&self.tasks)?;
+        }
+        
+        let mut group_names = HashSet::new();
+        for group in &self.signal_groups {
+            if group.name.is_empty() {
+                return Err(PlcError::Config("Signal group name cannot be empty".to_string()));
+            }
+            if !group_names.insert(group.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate signal group name: '{}'", group.name)));
+            }
+            if group.signals.is_empty() {
+                return Err(PlcError::Config(format!("Signal group '{}' has no signals", group.name)));
+            }
+            if let Some(signal) = group.signals.iter().find(|name| !self.signals.iter().any(|s| &s.name == *name)) {
+                return Err(PlcError::Config(format!(
+                    "Signal group '{}' references unknown signal '{}'", group.name, signal
+                )));
+            }
+        }
+        
+        for (alias, target) in &self.aliases {
+            if alias.is_empty() {
+                return Err(PlcError::Config("Signal alias cannot be empty".to_string()));
+            }
+            if self.signals.iter().any(|s| &s.name == alias) {
+                return Err(PlcError::Config(format!("Alias '{alias}' is also the name of a signal")));
+            }
+            if !self.signals.iter().any(|s| &s.name == target) {
+                return Err(PlcError::Config(format!("Alias '{alias}' references unknown signal '{target}'")));
+            }
         }
         
-        #[cfg(feature = "realtime")]
-        if let Some(realtime) = &self.realtime {
-            realtime.validate()?;
+        let mut writers: HashMap<&str, Option<&str>> = HashMap::new();
+        for block in self.blocks.iter().filter(|b| b.enabled) {
+            let task = block.task.as_deref();
+            if let Some(task) = task.filter(|t| !task_names.contains(t)) {
+                return Err(PlcError::Config(format!(
+                    "Block '{}' references unknown task '{}'", block.name, task
+                )));
+            }
+            for signal in block.outputs.values() {
+                let writer = *writers.entry(signal.as_str()).or_insert(task);
+                if writer != task {
+                    return Err(PlcError::Config(format!(
+                        "Signal '{}' is written by blocks in tasks '{}' and '{}'",
+                        signal,
+                        writer.unwrap_or("main"),
+                        task.unwrap_or("main")
+                    )));
+                }
+            }
         }
         
         Ok(())
@@ -2086,6 +3770,41 @@ impl Config {
                     "Configuration uses OPC-UA but opcua-support feature is not enabled".to_string()
                 ));
             }
+            
+            #[cfg(feature = "dnp3-support")]
+            if protocols.dnp3.is_some() && !features.has_dnp3() {
+                return Err(PlcError::Config(
+                    "Configuration uses DNP3 but dnp3-support feature is not enabled".to_string()
+                ));
+            }
+            
+            #[cfg(feature = "ethercat")]
+            if protocols.ethercat.is_some() && !features.has_ethercat() {
+                return Err(PlcError::Config(
+                    "Configuration uses EtherCAT but ethercat feature is not enabled".to_string()
+                ));
+            }
+            
+            #[cfg(feature = "profinet")]
+            if protocols.profinet.is_some() && !features.has_profinet() {
+                return Err(PlcError::Config(
+                    "Configuration uses PROFINET but profinet feature is not enabled".to_string()
+                ));
+            }
+            
+            #[cfg(feature = "coap")]
+            if protocols.coap.is_some() && !features.has_coap() {
+                return Err(PlcError::Config(
+                    "Configuration uses CoAP but coap feature is not enabled".to_string()
+                ));
+            }
+            
+            #[cfg(feature = "http")]
+            if protocols.http.is_some() && !features.has_http() {
+                return Err(PlcError::Config(
+                    "Configuration uses the HTTP driver but http feature is not enabled".to_string()
+                ));
+            }
         }
         
         // Check other feature compatibility
@@ -2180,6 +3899,10 @@ impl Config {
                     update_frequency_ms: None,
                     #[cfg(feature = "validation")]
                     validation: None,
+                    display: None,
+                    values: BTreeMap::new(),
+                    retain: false,
+                    write_policy: None,
                     metadata: HashMap::new(),
                 },
                 SignalConfig {
@@ -2207,6 +3930,10 @@ impl Config {
                     update_frequency_ms: Some(1000),
                     #[cfg(feature = "validation")]
                     validation: None,
+                    display: None,
+                    values: BTreeMap::new(),
+                    retain: false,
+                    write_policy: None,
                     metadata: HashMap::new(),
                 },
             ],
@@ -2231,10 +3958,13 @@ impl Config {
                         params
                     },
                     priority: 0,
+                    task: None,
                     enabled: true,
                     description: Some("Generates system heartbeat signal".to_string()),
                     category: Some("System".to_string()),
                     tags: vec!["system".to_string(), "heartbeat".to_string()],
+                    budget: None,
+                    phase: None,
                     #[cfg(feature = "circuit-breaker")]
                     circuit_breaker: None,
                     #[cfg(feature = "enhanced-monitoring")]
@@ -2242,6 +3972,20 @@ impl Config {
                     metadata: HashMap::new(),
                 },
             ],
+            tasks: Vec::new(),
+            output_latch: None,
+            degradation: None,
+            signal_groups: Vec::new(),
+            templates: Vec::new(),
+            instances: Vec::new(),
+            composite_blocks: Vec::new(),
+            aliases: BTreeMap::new(),
+            block_state: None,
+            #[cfg(feature = "retained-store")]
+            retained_store: None,
+            clock: None,
+            startup: None,
+            watchdog: None,
             
             // No protocols in basic example
             protocols: None,
@@ -2264,14 +4008,38 @@ impl Config {
             history: None,
             #[cfg(feature = "alarms")]
             alarms: None,
+            #[cfg(feature = "oee")]
+            oee: None,
+            #[cfg(feature = "downtime")]
+            downtime: None,
+            #[cfg(feature = "maintenance")]
+            maintenance: None,
+            #[cfg(feature = "maintenance-mode")]
+            maintenance_mode: None,
+            #[cfg(feature = "energy")]
+            energy: None,
+            #[cfg(feature = "reports")]
+            reports: None,
+            #[cfg(feature = "simulation")]
+            simulation: None,
+            #[cfg(feature = "time-sync")]
+            time_sync: None,
+            #[cfg(feature = "redundancy")]
+            redundancy: None,
             #[cfg(feature = "web")]
             web: None,
+            #[cfg(feature = "grpc")]
+            grpc: None,
             #[cfg(feature = "validation")]
             validation: None,
             #[cfg(feature = "metrics")]
             metrics: None,
             #[cfg(feature = "realtime")]
             realtime: None,
+            #[cfg(feature = "licensing")]
+            license: None,
+            #[cfg(feature = "plugins")]
+            plugins: None,
         })
     }
     
@@ -2397,14 +4165,14 @@ impl Validatable for SignalConfig {
         
         // Type validation
         match self.signal_type.to_lowercase().as_str() {
-            "bool" | "int" | "integer" | "float" => {}
+            "bool" | "int" | "integer" | "float" | "enum" => {}
             #[cfg(feature = "extended-types")]
             "string" | "binary" | "timestamp" | "array" | "object" => {}
             _ => {
                 let valid_types = if cfg!(feature = "extended-types") {
-                    "bool, int, float, string, binary, timestamp, array, object"
+                    "bool, int, float, enum, string, binary, timestamp, array, object"
                 } else {
-                    "bool, int, float"
+                    "bool, int, float, enum"
                 };
                 return Err(PlcError::Config(format!(
                     "Unknown signal type: '{}' (valid types: {})",
@@ -2413,8 +4181,24 @@ impl Validatable for SignalConfig {
             }
         }
         
+        if let Some(enum_type) = self.enum_type()? {
+            if let Some(initial) = &self.initial {
+                enum_type.resolve_yaml(&self.name, initial)
+                    .map_err(|e| PlcError::Config(format!("Invalid initial value: {e}")))?;
+            }
+        } else if !self.values.is_empty() {
+            return Err(PlcError::Config(format!(
+                "Signal '{}' declares enum values but has type '{}'",
+                self.name, self.signal_type
+            )));
+        }
+        
+        if let Some(policy) = &self.write_policy {
+            policy.validate(&self.name)?;
+        }
+        
         // Initial value type consistency check
-        if let Some(initial) = &self.initial {
+        if let Some(initial) = self.initial.as_ref().filter(|_| !self.is_enum()) {
             let type_matches = match (self.signal_type.to_lowercase().as_str(), initial) {
                 ("bool", serde_yaml::Value::Bool(_)) => true,
                 ("int" | "integer", serde_yaml::Value::Number(n)) => n.is_i64(),
@@ -2445,10 +4229,102 @@ impl Validatable for SignalConfig {
             }
         }
         
+        if let Some(display) = &self.display {
+            display.validate(&self.name)?;
+        }
+        
         Ok(())
     }
 }
 
+impl SignalConfig {
+    /// Check a value about to be written to this signal
+    /// 
+    /// Integers written to float signals are converted. The value must match
+    /// the signal type, lie within the engineering range and satisfy the
+    /// signal's `range` and `enum` validation rules; rules whose failure
+    /// action is `Log` only produce a warning.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::Validation`] describing the first failed check.
+    pub fn check_write(&self, value: Value) -> Result<Value> {
+        if let Some(enum_type) = self.enum_type()? {
+            return enum_type.resolve(&self.name, &value).map(Value::Integer);
+        }
+        
+        let value = match (self.signal_type.to_lowercase().as_str(), value) {
+            ("float", Value::Integer(i)) => {
+                #[allow(clippy::cast_precision_loss)]
+                let f = i as f64;
+                Value::Float(f)
+            }
+            (signal_type, value) => {
+                let matches = match signal_type {
+                    "bool" => matches!(value, Value::Bool(_)),
+                    "int" | "integer" => matches!(value, Value::Integer(_)),
+                    "float" => matches!(value, Value::Float(_)),
+                    other => other == value.type_name(),
+                };
+                if !matches {
+                    return Err(PlcError::Validation(format!(
+                        "Signal '{}' expects {}, got {}",
+                        self.name, self.signal_type, value.type_name()
+                    )));
+                }
+                value
+            }
+        };
+        
+        #[cfg(feature = "engineering-types")]
+        if let Some(v) = value.as_float() {
+            if self.min_value.is_some_and(|min| v < min) || self.max_value.is_some_and(|max| v > max) {
+                return Err(PlcError::Validation(format!(
+                    "Signal '{}' value {} is outside its range",
+                    self.name, v
+                )));
+            }
+        }
+        
+        #[cfg(feature = "validation")]
+        if let Some(validation) = &self.validation {
+            for rule in &validation.rules {
+                let Err(message) = rule.check(&value) else {
+                    continue;
+                };
+                let message = rule.error_message.clone().unwrap_or(message);
+                if matches!(validation.on_failure, ValidationAction::Log) {
+                    warn!("Signal '{}' rule '{}' failed: {}", self.name, rule.name, message);
+                } else {
+                    return Err(PlcError::Validation(format!(
+                        "Signal '{}' rule '{}' failed: {}",
+                        self.name, rule.name, message
+                    )));
+                }
+            }
+        }
+        
+        Ok(value)
+    }
+}
+
+impl BlockConfig {
+    /// Phase the block executes in
+    #[must_use]
+    pub fn phase(&self) -> BlockPhase {
+        self.phase
+            .or_else(|| self.category.as_deref().and_then(BlockPhase::from_name))
+            .unwrap_or_default()
+    }
+    
+    /// Sort key of the execution order: by phase, then by descending
+    /// priority
+    #[must_use]
+    pub fn execution_order(&self) -> (BlockPhase, std::cmp::Reverse<i32>) {
+        (self.phase(), std::cmp::Reverse(self.priority))
+    }
+}
+
 impl Validatable for BlockConfig {
     fn validate(&self) -> Result<()> {
         // Name validation
@@ -2470,6 +4346,10 @@ impl Validatable for BlockConfig {
             }
         }
         
+        if let Some(budget) = &self.budget {
+            budget.validate(&self.name)?;
+        }
+        
         Ok(())
     }
 }
@@ -2497,10 +4377,94 @@ impl Validatable for ProtocolConfig {
             _protocol_count += 1;
         }
         
+        #[cfg(feature = "opcua-server")]
+        if let Some(server) = &self.opcua_server {
+            server.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "dnp3-support")]
+        if let Some(dnp3) = &self.dnp3 {
+            dnp3.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "ethercat")]
+        if let Some(ethercat) = &self.ethercat {
+            ethercat.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "profinet")]
+        if let Some(profinet) = &self.profinet {
+            profinet.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "coap")]
+        if let Some(coap) = &self.coap {
+            coap.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "http")]
+        if let Some(http) = &self.http {
+            http.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = &self.kafka {
+            kafka.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "nats")]
+        if let Some(nats) = &self.nats {
+            nats.validate()?;
+            _protocol_count += 1;
+        }
+        
+        #[cfg(feature = "bridge")]
+        if let Some(bridge) = &self.bridge {
+            bridge.validate()?;
+            _protocol_count += bridge.links.len();
+        }
+        
+        #[cfg(feature = "protocol-sim")]
+        {
+            crate::protocols::sim::validate_drivers(&self.sim)?;
+            _protocol_count += self.sim.len();
+        }
+        
         if _protocol_count == 0 {
             warn!("No protocols configured - system will only use internal signals");
         }
         
+        let mut group_names = HashSet::new();
+        for group in &self.failover_groups {
+            group.validate()?;
+            if !group_names.insert(&group.name) {
+                return Err(PlcError::Config(format!(
+                    "Duplicate failover group name: '{}'", group.name
+                )));
+            }
+        }
+        
+        for backfill in &self.backfill {
+            backfill.validate()?;
+        }
+        
+        if let Some(polling) = &self.polling {
+            polling.validate()?;
+        }
+        
+        crate::protocols::routing::validate_routes(&self.routes)?;
+        
+        if let Some(dead_letters) = &self.dead_letters {
+            dead_letters.validate()?;
+        }
+        
         Ok(())
     }
 }
@@ -2545,6 +4509,21 @@ impl Validatable for SecurityConfig {
             }
         }
         
+        #[cfg(feature = "esignature")]
+        if let Some(esignature) = &self.esignature {
+            esignature.validate()?;
+            if self.basic_auth.as_ref().is_none_or(|b| b.users.is_empty()) {
+                return Err(PlcError::Config(
+                    "Electronic signatures require basic_auth users".to_string()
+                ));
+            }
+            if self.audit.is_none() {
+                return Err(PlcError::Config(
+                    "Electronic signatures require an audit log".to_string()
+                ));
+            }
+        }
+        
         Ok(())
     }
 }
@@ -2678,6 +4657,11 @@ impl Validatable for WebConfig {
             }
         }
         
+        #[cfg(feature = "rbac")]
+        if let Some(auth) = &self.websocket_auth {
+            auth.validate()?;
+        }
+        
         Ok(())
     }
 }
@@ -2808,6 +4792,130 @@ impl Validatable for S7Config {
                     ));
                 }
             }
+            
+            for source in &conn.symbol_sources {
+                if source.file.as_os_str().is_empty() {
+                    return Err(PlcError::Config(format!(
+                        "S7 connection '{}' has a symbol source without a file", conn.name
+                    )));
+                }
+            }
+            
+            for tag in &conn.tags {
+                if tag.symbol.is_some() {
+                    if conn.symbol_sources.is_empty() {
+                        return Err(PlcError::Config(format!(
+                            "S7 tag '{}' uses a symbol but connection '{}' has no symbol_sources",
+                            tag.signal, conn.name
+                        )));
+                    }
+                    continue;
+                }
+                if !matches!(tag.area.to_uppercase().as_str(), "DB" | "MB" | "IB" | "QB") {
+                    return Err(PlcError::Config(format!(
+                        "Invalid S7 area type: '{}'", tag.area
+                    )));
+                }
+                if !matches!(
+                    tag.data_type.to_lowercase().as_str(),
+                    "bool" | "byte" | "word" | "int" | "dword" | "dint" | "real" | "lreal"
+                ) {
+                    return Err(PlcError::Config(format!(
+                        "Invalid S7 data type '{}' for '{}'", tag.data_type, tag.signal
+                    )));
+                }
+                if tag.bit > 7 {
+                    return Err(PlcError::Config(format!(
+                        "S7 tag '{}' bit must be 0-7", tag.signal
+                    )));
+                }
+            }
+            
+            if conn.pool_size == 0 || conn.poll_interval_ms == 0 {
+                return Err(PlcError::Config(format!(
+                    "S7 connection '{}' needs a pool_size and poll_interval_ms above 0", conn.name
+                )));
+            }
+            if conn.pdu_size < 240 {
+                return Err(PlcError::Config(format!(
+                    "S7 connection '{}' pdu_size cannot be below 240", conn.name
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(feature = "dnp3-support")]
+impl Validatable for Dnp3Config {
+    fn validate(&self) -> Result<()> {
+        if self.masters.is_empty() && self.outstations.is_empty() {
+            return Err(PlcError::Config(
+                "DNP3 configuration has no masters or outstations".to_string()
+            ));
+        }
+        
+        let mut names = HashSet::new();
+        let sessions = self
+            .masters
+            .iter()
+            .map(|m| (&m.name, &m.address, m.local_address, m.remote_address, &m.points))
+            .chain(self.outstations.iter().map(|o| {
+                (&o.name, &o.bind, o.local_address, o.remote_address, &o.points)
+            }));
+        for (name, address, local, remote, points) in sessions {
+            if !names.insert(name) {
+                return Err(PlcError::Config(format!(
+                    "Duplicate DNP3 session name: '{name}'"
+                )));
+            }
+            if !address.contains(':') {
+                return Err(PlcError::Config(format!(
+                    "DNP3 session '{name}' address must be host:port"
+                )));
+            }
+            // 0xFFF0 and above are reserved for broadcast and self-address
+            if local >= 0xFFF0 || remote >= 0xFFF0 || local == remote {
+                return Err(PlcError::Config(format!(
+                    "DNP3 session '{name}' has invalid link addresses {local} / {remote}"
+                )));
+            }
+            
+            let mut indices = HashSet::new();
+            for point in points {
+                if !indices.insert((point.point_type, point.index)) {
+                    return Err(PlcError::Config(format!(
+                        "DNP3 session '{}' maps {:?} index {} twice", name, point.point_type, point.index
+                    )));
+                }
+                if point.class > 3 {
+                    return Err(PlcError::Config(format!(
+                        "DNP3 session '{}' point '{}' has invalid event class {}", name, point.signal, point.class
+                    )));
+                }
+                if point.deadband < 0.0 {
+                    return Err(PlcError::Config(format!(
+                        "DNP3 session '{}' point '{}' has negative deadband", name, point.signal
+                    )));
+                }
+            }
+        }
+        
+        for master in &self.masters {
+            if master.integrity_poll_ms == 0 || master.event_poll_ms == 0 || master.timeout_ms == 0 {
+                return Err(PlcError::Config(format!(
+                    "DNP3 master '{}' poll intervals and timeout must be greater than 0", master.name
+                )));
+            }
+        }
+        for outstation in &self.outstations {
+            if outstation.event_buffer_size == 0 || outstation.change_scan_ms == 0 {
+                return Err(PlcError::Config(format!(
+                    "DNP3 outstation '{}' event buffer and change scan interval must be greater than 0",
+                    outstation.name
+                )));
+            }
         }
         
         Ok(())
@@ -2822,6 +4930,7 @@ impl Validatable for ModbusConfig {
         }
         
         let mut connection_names = HashSet::new();
+        let mut lines: HashMap<&str, (ModbusSerial, HashSet<u8>)> = HashMap::new();
         for conn in &self.connections {
             if !connection_names.insert(&conn.name) {
                 return Err(PlcError::Config(format!(
@@ -2843,6 +4952,34 @@ impl Validatable for ModbusConfig {
                 ))),
             }
             
+            if conn.connection_type.eq_ignore_ascii_case("rtu") {
+                let serial = conn.serial.clone().unwrap_or_default();
+                validate_modbus_serial(&conn.name, &serial)?;
+                if !(1..=247).contains(&conn.unit_id) {
+                    return Err(PlcError::Config(format!(
+                        "Modbus RTU connection '{}' unit_id must be 1-247", conn.name
+                    )));
+                }
+                let (line, units) = lines
+                    .entry(conn.address.as_str())
+                    .or_insert_with(|| (serial.clone(), HashSet::new()));
+                if *line != serial {
+                    return Err(PlcError::Config(format!(
+                        "Modbus RTU connection '{}' uses different serial settings than other connections on {}",
+                        conn.name, conn.address
+                    )));
+                }
+                if !units.insert(conn.unit_id) {
+                    return Err(PlcError::Config(format!(
+                        "Modbus unit {} is used twice on {}", conn.unit_id, conn.address
+                    )));
+                }
+            } else if conn.serial.is_some() {
+                return Err(PlcError::Config(format!(
+                    "Modbus connection '{}' has serial settings but is not an RTU connection", conn.name
+                )));
+            }
+            
             // Validate registers
             for reg in &conn.registers {
                 match reg.register_type.to_lowercase().as_str() {
@@ -2864,6 +5001,26 @@ impl Validatable for ModbusConfig {
     }
 }
 
+#[cfg(feature = "modbus-support")]
+fn validate_modbus_serial(name: &str, serial: &ModbusSerial) -> Result<()> {
+    if serial.baud_rate == 0 || serial.cycle_ms == 0 {
+        return Err(PlcError::Config(format!(
+            "Modbus RTU connection '{name}' needs a baud_rate and cycle_ms above 0"
+        )));
+    }
+    if !matches!(serial.data_bits, 7 | 8) || !matches!(serial.stop_bits, 1 | 2) {
+        return Err(PlcError::Config(format!(
+            "Modbus RTU connection '{name}' needs 7 or 8 data bits and 1 or 2 stop bits"
+        )));
+    }
+    if !matches!(serial.parity.to_lowercase().as_str(), "none" | "even" | "odd") {
+        return Err(PlcError::Config(format!(
+            "Invalid parity '{}' for Modbus RTU connection '{name}'", serial.parity
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(feature = "opcua-support")]
 impl Validatable for OpcuaConfig {
     fn validate(&self) -> Result<()> {
@@ -2882,8 +5039,58 @@ impl Validatable for OpcuaConfig {
             if sub.sampling_interval_ms == 0 {
                 return Err(PlcError::Config("OPC-UA sampling interval must be greater than 0".to_string()));
             }
+            if sub.publishing_interval_ms == Some(0) {
+                return Err(PlcError::Config(format!(
+                    "OPC-UA publishing interval of {} must be greater than 0", sub.node_id
+                )));
+            }
+            if sub.queue_size == 0 {
+                return Err(PlcError::Config(format!(
+                    "OPC-UA queue size of {} must be greater than 0", sub.node_id
+                )));
+            }
+            if let Some(deadband) = &sub.deadband {
+                let valid = match deadband.deadband_type.as_str() {
+                    "absolute" => deadband.value.is_finite() && deadband.value >= 0.0,
+                    "percent" => (0.0..=100.0).contains(&deadband.value),
+                    other => return Err(PlcError::Config(format!(
+                        "Unknown OPC-UA deadband type '{}' for {}", other, sub.node_id
+                    ))),
+                };
+                if !valid {
+                    return Err(PlcError::Config(format!(
+                        "OPC-UA {} deadband of {} is out of range", deadband.deadband_type, sub.node_id
+                    )));
+                }
+            }
         }
         
+        if self.publishing_interval_ms == 0 {
+            return Err(PlcError::Config("OPC-UA publishing interval must be greater than 0".to_string()));
+        }
+        if self.max_items_per_request == 0 {
+            return Err(PlcError::Config("OPC-UA max_items_per_request must be greater than 0".to_string()));
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(feature = "opcua-server")]
+impl Validatable for OpcuaServerConfig {
+    fn validate(&self) -> Result<()> {
+        if self.port == 0 {
+            return Err(PlcError::Config("OPC-UA server port must be greater than 0".to_string()));
+        }
+        if self.namespace_uri.is_empty() {
+            return Err(PlcError::Config("OPC-UA server namespace URI cannot be empty".to_string()));
+        }
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config("OPC-UA server update interval must be greater than 0".to_string()));
+        }
+        if self.max_history_values == 0 {
+            return Err(PlcError::Config("OPC-UA server max_history_values must be greater than 0".to_string()));
+        }
         Ok(())
     }
 }
@@ -2988,6 +5195,48 @@ mod tests {
         assert!(config.validate().is_err());
     }
     
+    #[test]
+    fn test_templates_expand_per_instance() {
+        let mut config: Config = serde_yaml::from_str(r#"
+signals:
+  - { name: plant.high_limit, type: float }
+blocks: []
+templates:
+  - name: tank
+    signals:
+      - { name: ./level, type: float }
+      - { name: ./high, type: bool }
+    blocks:
+      - name: ./high_alarm
+        type: GT
+        inputs: { a: ./level, b: plant.high_limit }
+        outputs: { out: ./high }
+instances:
+  - { template: tank, namespace: tank1 }
+  - { template: tank, namespace: tank2 }
+aliases:
+  feed.level: tank1.level
+signal_groups:
+  - { name: feed, signals: [feed.level] }
+"#).unwrap();
+        config.expand().unwrap();
+        config.validate().unwrap();
+        
+        let names: Vec<&str> = config.signals.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["plant.high_limit", "tank1.level", "tank1.high", "tank2.level", "tank2.high"]);
+        let alarm = &config.blocks[1];
+        assert_eq!(alarm.name, "tank2.high_alarm");
+        assert_eq!(alarm.inputs["a"], "tank2.level");
+        assert_eq!(alarm.inputs["b"], "plant.high_limit");
+        assert_eq!(config.signal_groups[0].signals, ["tank1.level"]);
+        assert!(config.templates.is_empty() && config.instances.is_empty());
+        
+        config.aliases.insert("tank2.level".to_string(), "tank1.level".to_string());
+        assert!(config.validate().is_err());
+        config.instances.push(InstanceConfig { template: "pump".to_string(), namespace: "p1".to_string() });
+        assert!(config.expand().is_err());
+    }
+    
     #[test]
     fn test_config_summary() {
         let config = Config::example_basic().unwrap();
@@ -2998,6 +5247,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mqtt")]
     fn test_mqtt_config_validation() {
         let mut config = MqttConfig::default();
         assert!(config.validate().is_ok());
@@ -3019,6 +5269,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mqtt")]
     fn test_config_conversion() {
         let user_config = MqttConfig {
             host: "test.broker.com".to_string(),
@@ -3036,9 +5287,10 @@ mod tests {
             auto_reconnect: true,
             max_reconnect_attempts: 5,
             reconnect_delay_secs: 10,
+            ..Default::default()
         };
 
-        let internal_config = InternalMqttConfig::from(user_config.clone());
+        let internal_config = crate::protocols::mqtt::InternalMqttConfig::from(user_config.clone());
 
         assert_eq!(internal_config.broker_host, user_config.host);
         assert_eq!(internal_config.broker_port, user_config.port);
@@ -3048,6 +5300,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mqtt")]
     fn test_broker_url_generation() {
         let config = MqttConfig {
             host: "example.com".to_string(),
@@ -3067,6 +5320,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mqtt")]
     fn test_connection_options() {
         let config = MqttConfig {
             host: "test.com".to_string(),
@@ -3079,7 +5333,7 @@ mod tests {
             ..Default::default()
         };
 
-        let _options = config.connection_options();
+        config.connection_options().unwrap();
     }
 
     #[test]
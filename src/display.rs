@@ -0,0 +1,235 @@
+// src/display.rs - Engineering-unit display formatting
+//
+// Signals carry raw values on the bus. Operator-facing output (web API,
+// MQTT JSON payloads, reports) renders them through a `DisplayFormatter`
+// built from the signal configuration, so every consumer shows the same
+// units, decimal places and enumeration labels:
+//
+// ```yaml
+// signals:
+//   - name: pump.mode
+//     type: int
+//     display:
+//       labels: { 0: Manual, 1: Auto }
+//   - name: pump.state
+//     type: enum
+//     values: { 0: Stopped, 1: Running, 2: Faulted }
+//   - name: tank.level
+//     type: float
+//     display:
+//       units: "%"
+//       decimals: 1
+// ```
+
+use crate::config::SignalConfig;
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+/// Most decimal places a signal may request
+const MAX_DECIMALS: u8 = 12;
+
+/// Display settings for one signal
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct DisplayFormat {
+    /// Units appended to numeric values (e.g. "°C", "bar", "%")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+
+    /// Fixed number of decimal places for float values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+
+    /// Labels for enumerated values; booleans map to 0 and 1
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<i64, String>,
+}
+
+impl DisplayFormat {
+    /// Validate the display settings of `signal`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if more than 12 decimals are requested
+    /// or a label is empty.
+    pub fn validate(&self, signal: &str) -> Result<()> {
+        if self.decimals.is_some_and(|d| d > MAX_DECIMALS) {
+            return Err(PlcError::Config(format!(
+                "Signal '{signal}' display decimals must be at most {MAX_DECIMALS}"
+            )));
+        }
+        if let Some((value, _)) = self.labels.iter().find(|(_, label)| label.trim().is_empty()) {
+            return Err(PlcError::Config(format!(
+                "Signal '{signal}' has an empty display label for value {value}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Enumeration label for `value`, if one is configured
+    #[must_use]
+    pub fn label(&self, value: &Value) -> Option<&str> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        let key = if let Value::Integer(i) = value {
+            *i
+        } else {
+            // Booleans select 0/1 and whole-number floats select labels too
+            let f = value.as_float().filter(|f| f.is_finite() && f.fract() == 0.0)?;
+            #[allow(clippy::cast_possible_truncation)]
+            let key = f as i64;
+            key
+        };
+        self.labels.get(&key).map(String::as_str)
+    }
+
+    /// Render `value` as operator-facing text
+    #[must_use]
+    pub fn format(&self, value: &Value) -> String {
+        if let Some(label) = self.label(value) {
+            return label.to_string();
+        }
+        let mut text = match (value, self.decimals) {
+            (Value::Float(f), Some(decimals)) => format!("{f:.prec$}", prec = usize::from(decimals)),
+            _ => value.to_string(),
+        };
+        if let Some(units) = self.units.as_deref().filter(|u| !u.is_empty()) {
+            if matches!(value, Value::Integer(_) | Value::Float(_)) {
+                let _ = write!(text, " {units}");
+            }
+        }
+        text
+    }
+}
+
+/// A value together with its rendered form
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormattedValue {
+    /// Raw signal value
+    pub value: Value,
+    /// Text for display, including units or the enumeration label
+    pub display: String,
+    /// Configured units
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+}
+
+/// Applies each signal's display settings when rendering values
+#[derive(Debug, Clone, Default)]
+pub struct DisplayFormatter {
+    formats: HashMap<String, DisplayFormat>,
+}
+
+impl DisplayFormatter {
+    /// Build a formatter from the configured signals
+    ///
+    /// Engineering units declared on a signal are used when its display
+    /// settings do not name units of their own, and enum signals are
+    /// labelled with their declared values unless display labels are set.
+    #[must_use]
+    pub fn from_signals(signals: &[SignalConfig]) -> Self {
+        let formats = signals
+            .iter()
+            .filter_map(|signal| {
+                let mut format = signal.display.clone();
+                if let Ok(Some(enum_type)) = signal.enum_type() {
+                    let format = format.get_or_insert_with(DisplayFormat::default);
+                    if format.labels.is_empty() {
+                        format.labels = enum_type.labels().clone();
+                    }
+                }
+                #[cfg(feature = "engineering-types")]
+                if let Some(units) = &signal.units {
+                    let format = format.get_or_insert_with(DisplayFormat::default);
+                    format.units.get_or_insert_with(|| units.clone());
+                }
+                format.map(|format| (signal.name.clone(), format))
+            })
+            .collect();
+        Self { formats }
+    }
+
+    /// Display settings of `signal`, if any
+    #[must_use]
+    pub fn get(&self, signal: &str) -> Option<&DisplayFormat> {
+        self.formats.get(signal)
+    }
+
+    /// Render the value of `signal` as text
+    #[must_use]
+    pub fn format(&self, signal: &str, value: &Value) -> String {
+        self.get(signal)
+            .map_or_else(|| value.to_string(), |format| format.format(value))
+    }
+
+    /// Pair the value of `signal` with its rendered form
+    #[must_use]
+    pub fn render(&self, signal: &str, value: Value) -> FormattedValue {
+        FormattedValue {
+            display: self.format(signal, &value),
+            units: self.get(signal).and_then(|f| f.units.clone()),
+            value,
+        }
+    }
+
+    /// Render a set of signal values, sorted by name
+    #[must_use]
+    pub fn render_all(&self, values: HashMap<String, Value>) -> BTreeMap<String, FormattedValue> {
+        values
+            .into_iter()
+            .map(|(name, value)| {
+                let formatted = self.render(&name, value);
+                (name, formatted)
+            })
+            .collect()
+    }
+
+    /// Markdown table of formatted signal values
+    #[must_use]
+    pub fn report(&self, values: HashMap<String, Value>) -> String {
+        let mut report = String::from("| Signal | Value |\n|---|---|\n");
+        for (name, formatted) in self.render_all(values) {
+            let _ = writeln!(report, "| {name} | {} |", formatted.display.replace('|', "\\|"));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_decimals_and_labels() {
+        let signals: Vec<SignalConfig> = serde_yaml::from_str(
+            "- name: tank.level\n  type: float\n  display: { units: '%', decimals: 1 }\n\
+             - name: pump.state\n  type: int\n  display: { labels: { 0: Stopped, 1: Running } }\n\
+             - name: pump.run\n  type: bool\n  display: { labels: { 0: 'Off', 1: 'On' } }\n",
+        )
+        .unwrap();
+        let formatter = DisplayFormatter::from_signals(&signals);
+
+        assert_eq!(formatter.format("tank.level", &Value::Float(72.456)), "72.5 %");
+        assert_eq!(formatter.format("pump.state", &Value::Integer(1)), "Running");
+        assert_eq!(formatter.format("pump.state", &Value::Integer(7)), "7");
+        assert_eq!(formatter.format("pump.run", &Value::Bool(false)), "Off");
+        assert_eq!(formatter.format("other", &Value::Float(1.5)), "1.5");
+
+        let rendered = formatter.render("tank.level", Value::Float(3.0));
+        assert_eq!(rendered.units.as_deref(), Some("%"));
+        assert_eq!(
+            serde_json::to_value(&rendered).unwrap()["display"],
+            serde_json::json!("3.0 %")
+        );
+
+        let invalid = DisplayFormat {
+            decimals: Some(20),
+            ..DisplayFormat::default()
+        };
+        assert!(invalid.validate("x").is_err());
+    }
+}
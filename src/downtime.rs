@@ -0,0 +1,563 @@
+// src/downtime.rs
+//! Downtime tracking with operator reason codes
+//!
+//! Each configured asset is considered stopped while its `run_signal` is
+//! false. A stop opens a [`DowntimeEvent`]; the event is closed when the asset
+//! runs again. Stops shorter than `min_duration_s` are discarded as micro-stops.
+//!
+//! Reasons come from two places:
+//!
+//! - automatically, by mapping the value of an asset's `fault_code_signal`
+//!   through `auto_reasons` when the stop begins
+//! - manually, by an operator assigning one of the configured reason codes
+//!   through the web API
+//!
+//! Closed events are kept in memory (bounded by `max_events`) and written to
+//! the JSON file at `storage_path`, which feeds the Pareto report.
+//!
+//! ```yaml
+//! downtime:
+//!   storage_path: data/downtime.json
+//!   min_duration_s: 30
+//!   reason_codes:
+//!     - { code: MECH, description: Mechanical failure }
+//!     - { code: MAT, description: Waiting for material }
+//!   assets:
+//!     - name: line1
+//!       run_signal: line1.running
+//!       fault_code_signal: line1.fault_code
+//!       auto_reasons: { 12: MECH }
+//! ```
+
+use crate::scan_budget::{measure, Subsystem};
+use crate::shifts::ShiftCalendar;
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Downtime subsystem configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DowntimeConfig {
+    /// Assets whose stops are tracked
+    pub assets: Vec<DowntimeAssetConfig>,
+
+    /// Reason codes operators may assign
+    #[serde(default)]
+    pub reason_codes: Vec<ReasonCode>,
+
+    /// Stops shorter than this are not recorded
+    #[serde(default)]
+    pub min_duration_s: f64,
+
+    /// Shift calendar used to tag events
+    #[serde(default)]
+    pub shifts: ShiftCalendar,
+
+    /// JSON file where events are persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<PathBuf>,
+
+    /// How often run signals are sampled
+    #[serde(default = "default_update_interval_ms")]
+    pub update_interval_ms: u64,
+
+    /// Maximum number of closed events kept in memory
+    #[serde(default = "default_max_events")]
+    pub max_events: usize,
+}
+
+/// A monitored asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DowntimeAssetConfig {
+    /// Asset name, unique within the downtime configuration
+    pub name: String,
+
+    /// Boolean signal, false while the asset is stopped
+    pub run_signal: String,
+
+    /// Optional integer signal carrying the PLC fault code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_code_signal: Option<String>,
+
+    /// Fault code to reason code mapping applied when a stop begins
+    #[serde(default)]
+    pub auto_reasons: HashMap<i64, String>,
+}
+
+/// A downtime reason operators can assign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasonCode {
+    /// Short code, e.g. "MECH"
+    pub code: String,
+    /// Human-readable description
+    pub description: String,
+    /// Optional grouping such as "planned" or "unplanned"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl DowntimeConfig {
+    /// Validate assets, reason codes and the shift calendar
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config("Downtime update interval cannot be 0".to_string()));
+        }
+        if self.min_duration_s < 0.0 {
+            return Err(PlcError::Config("Downtime min_duration_s cannot be negative".to_string()));
+        }
+        self.shifts.validate()?;
+
+        let mut codes = HashSet::new();
+        for reason in &self.reason_codes {
+            if !codes.insert(reason.code.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate reason code '{}'", reason.code)));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for asset in &self.assets {
+            if !names.insert(asset.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate downtime asset '{}'", asset.name)));
+            }
+            if let Some(code) = asset.auto_reasons.values().find(|c| !codes.contains(c.as_str())) {
+                return Err(PlcError::Config(format!(
+                    "Downtime asset '{}' maps to unknown reason code '{}'",
+                    asset.name, code
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_update_interval_ms() -> u64 {
+    500
+}
+
+const fn default_max_events() -> usize {
+    10_000
+}
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+/// How a reason was attached to an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonSource {
+    /// Mapped from the PLC fault code
+    Automatic,
+    /// Assigned by an operator
+    Operator,
+}
+
+/// A single stop of an asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DowntimeEvent {
+    /// Monotonic event identifier
+    pub id: u64,
+    /// Asset that stopped
+    pub asset: String,
+    /// Shift the stop began in
+    pub shift: String,
+    /// When the stop began
+    pub started_at: DateTime<Utc>,
+    /// When the asset ran again, `None` while still stopped
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Fault code present when the stop began
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_code: Option<i64>,
+    /// Assigned reason code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+    /// Origin of the reason code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_source: Option<ReasonSource>,
+    /// Operator comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Operator who assigned the reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assigned_by: Option<String>,
+}
+
+impl DowntimeEvent {
+    /// Duration of the stop, measured up to `now` for open events
+    #[must_use]
+    pub fn duration_s(&self, now: DateTime<Utc>) -> f64 {
+        let end = self.ended_at.unwrap_or(now);
+        (end - self.started_at).to_std().map_or(0.0, |d| d.as_secs_f64())
+    }
+}
+
+/// Reason assignment submitted by an operator
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReasonAssignment {
+    /// Reason code to assign
+    pub reason_code: String,
+    /// Optional free-text comment
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Operator name
+    pub user: String,
+}
+
+/// One bar of the downtime Pareto chart
+#[derive(Debug, Clone, Serialize)]
+pub struct ParetoEntry {
+    /// Reason code, or `"unassigned"`
+    pub reason_code: String,
+    /// Number of events
+    pub count: u64,
+    /// Total downtime in seconds
+    pub total_duration_s: f64,
+    /// Running share of total downtime, in percent
+    pub cumulative_percent: f64,
+}
+
+/// Reason code reported for events without a reason
+pub const UNASSIGNED_REASON: &str = "unassigned";
+
+// ============================================================================
+// TRACKING
+// ============================================================================
+
+/// Tracks stops for all configured assets
+pub struct DowntimeManager {
+    config: DowntimeConfig,
+    open: HashMap<String, DowntimeEvent>,
+    events: VecDeque<DowntimeEvent>,
+    next_id: u64,
+}
+
+impl DowntimeManager {
+    /// Create a manager, loading persisted events if present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the event file
+    /// exists but cannot be read.
+    pub fn new(config: DowntimeConfig) -> Result<Self> {
+        config.validate()?;
+
+        let mut events = VecDeque::new();
+        if let Some(path) = &config.storage_path {
+            match std::fs::read_to_string(path) {
+                Ok(json) => events = serde_json::from_str(&json)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let next_id = events.iter().map(|e: &DowntimeEvent| e.id + 1).max().unwrap_or(1);
+
+        info!(
+            "Downtime tracking enabled for {} assets ({} stored events)",
+            config.assets.len(),
+            events.len()
+        );
+        Ok(Self {
+            config,
+            open: HashMap::new(),
+            events,
+            next_id,
+        })
+    }
+
+    /// Configured update interval
+    #[must_use]
+    pub fn update_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.update_interval_ms)
+    }
+
+    /// Configured reason codes
+    #[must_use]
+    pub fn reason_codes(&self) -> &[ReasonCode] {
+        &self.config.reason_codes
+    }
+
+    /// Sample run signals, open and close events, and publish status
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured signal is missing or has the wrong
+    /// type, or if closed events cannot be persisted.
+    pub fn process(&mut self, bus: &SignalBus, now: DateTime<Utc>) -> Result<()> {
+        let mut changed = false;
+
+        for asset in &self.config.assets {
+            let running = bus.get_bool(&asset.run_signal)?;
+
+            match (running, self.open.contains_key(&asset.name)) {
+                (false, false) => {
+                    let fault_code = match &asset.fault_code_signal {
+                        Some(signal) => Some(bus.get_integer(signal)?),
+                        None => None,
+                    };
+                    let reason_code = fault_code.and_then(|c| asset.auto_reasons.get(&c).cloned());
+                    let event = DowntimeEvent {
+                        id: self.next_id,
+                        asset: asset.name.clone(),
+                        shift: self.config.shifts.instance_at(now)?.name,
+                        started_at: now,
+                        ended_at: None,
+                        fault_code,
+                        reason_source: reason_code.as_ref().map(|_| ReasonSource::Automatic),
+                        reason_code,
+                        comment: None,
+                        assigned_by: None,
+                    };
+                    self.next_id += 1;
+                    debug!("Downtime started on '{}' (event {})", asset.name, event.id);
+                    self.open.insert(asset.name.clone(), event);
+                }
+                (true, true) => {
+                    if let Some(mut event) = self.open.remove(&asset.name) {
+                        event.ended_at = Some(now);
+                        if event.duration_s(now) >= self.config.min_duration_s {
+                            debug!("Downtime ended on '{}' after {:.1}s", asset.name, event.duration_s(now));
+                            self.events.push_back(event);
+                            changed = true;
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            let current = self.open.get(&asset.name);
+            let prefix = format!("downtime.{}", asset.name);
+            bus.write_batch([
+                (format!("{prefix}.active"), Value::Bool(current.is_some())),
+                (
+                    format!("{prefix}.duration_s"),
+                    Value::Float(current.map_or(0.0, |e| e.duration_s(now))),
+                ),
+            ])?;
+        }
+
+        while self.events.len() > self.config.max_events {
+            self.events.pop_front();
+        }
+        if changed {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Assign a reason code to an open or closed event
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown event,
+    /// [`PlcError::Validation`] for an unknown reason code, or an I/O error
+    /// if the events cannot be persisted.
+    pub fn assign_reason(&mut self, id: u64, assignment: ReasonAssignment) -> Result<DowntimeEvent> {
+        if !self.config.reason_codes.iter().any(|r| r.code == assignment.reason_code) {
+            return Err(PlcError::Validation(format!(
+                "Unknown reason code '{}'",
+                assignment.reason_code
+            )));
+        }
+
+        let event = self
+            .open
+            .values_mut()
+            .chain(self.events.iter_mut())
+            .find(|e| e.id == id)
+            .ok_or_else(|| PlcError::NotFound(format!("Downtime event {id}")))?;
+
+        event.reason_code = Some(assignment.reason_code);
+        event.reason_source = Some(ReasonSource::Operator);
+        event.comment = assignment.comment;
+        event.assigned_by = Some(assignment.user);
+        let event = event.clone();
+
+        info!(
+            "Downtime event {} on '{}' assigned reason '{}' by {}",
+            event.id,
+            event.asset,
+            event.reason_code.as_deref().unwrap_or_default(),
+            event.assigned_by.as_deref().unwrap_or_default()
+        );
+        self.persist()?;
+        Ok(event)
+    }
+
+    /// Open and closed events, optionally filtered, newest first
+    #[must_use]
+    pub fn events(&self, asset: Option<&str>, unassigned_only: bool) -> Vec<DowntimeEvent> {
+        let mut events: Vec<DowntimeEvent> = self
+            .open
+            .values()
+            .chain(self.events.iter())
+            .filter(|e| asset.is_none_or(|a| e.asset == a))
+            .filter(|e| !unassigned_only || e.reason_code.is_none())
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| std::cmp::Reverse(e.started_at));
+        events
+    }
+
+    /// Downtime by reason, largest first, for events starting after `since`
+    #[must_use]
+    pub fn pareto(
+        &self,
+        asset: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Vec<ParetoEntry> {
+        let mut totals: HashMap<&str, (u64, f64)> = HashMap::new();
+        for event in self.open.values().chain(self.events.iter()) {
+            if asset.is_some_and(|a| event.asset != a) || since.is_some_and(|s| event.started_at < s) {
+                continue;
+            }
+            let entry = totals
+                .entry(event.reason_code.as_deref().unwrap_or(UNASSIGNED_REASON))
+                .or_default();
+            entry.0 += 1;
+            entry.1 += event.duration_s(now);
+        }
+
+        let grand_total: f64 = totals.values().map(|(_, d)| d).sum();
+        let mut entries: Vec<ParetoEntry> = totals
+            .into_iter()
+            .map(|(code, (count, total))| ParetoEntry {
+                reason_code: code.to_string(),
+                count,
+                total_duration_s: total,
+                cumulative_percent: 0.0,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.total_duration_s.total_cmp(&a.total_duration_s));
+
+        let mut running = 0.0;
+        for entry in &mut entries {
+            running += entry.total_duration_s;
+            entry.cumulative_percent = if grand_total > 0.0 {
+                running / grand_total * 100.0
+            } else {
+                0.0
+            };
+        }
+        entries
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.config.storage_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.events)?)?;
+        Ok(())
+    }
+
+    /// Run the periodic sampling loop until the task is cancelled
+    pub async fn run(manager: SharedDowntimeManager, bus: SignalBus) {
+        let period = manager.read().await.update_interval();
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let mut manager = manager.write().await;
+            let result = measure(Subsystem::Analytics, || manager.process(&bus, Utc::now()));
+            if let Err(e) = result {
+                warn!("Downtime update failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Shared downtime manager handle used by the engine task and the web API
+pub type SharedDowntimeManager = std::sync::Arc<tokio::sync::RwLock<DowntimeManager>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn config() -> DowntimeConfig {
+        serde_yaml::from_str(
+            r#"
+min_duration_s: 10
+reason_codes:
+  - { code: MECH, description: Mechanical }
+  - { code: MAT, description: Material }
+assets:
+  - name: line1
+    run_signal: run
+    fault_code_signal: fault
+    auto_reasons: { 12: MECH }
+"#,
+        )
+        .unwrap()
+    }
+
+    fn stop(dt: &mut DowntimeManager, bus: &SignalBus, at: DateTime<Utc>, secs: i64, fault: i64) {
+        bus.set("fault", Value::Integer(fault)).unwrap();
+        bus.set("run", Value::Bool(false)).unwrap();
+        dt.process(bus, at).unwrap();
+        bus.set("run", Value::Bool(true)).unwrap();
+        dt.process(bus, at + Duration::seconds(secs)).unwrap();
+    }
+
+    #[test]
+    fn test_events_and_micro_stops() {
+        let bus = SignalBus::new();
+        let mut dt = DowntimeManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+
+        stop(&mut dt, &bus, t0, 5, 0); // micro-stop, dropped
+        stop(&mut dt, &bus, t0 + Duration::minutes(1), 60, 12);
+
+        let events = dt.events(None, false);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason_code.as_deref(), Some("MECH"));
+        assert_eq!(events[0].reason_source, Some(ReasonSource::Automatic));
+        assert!(!bus.get_bool("downtime.line1.active").unwrap());
+    }
+
+    #[test]
+    fn test_operator_assignment_and_pareto() {
+        let bus = SignalBus::new();
+        let mut dt = DowntimeManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+
+        stop(&mut dt, &bus, t0, 300, 0);
+        stop(&mut dt, &bus, t0 + Duration::hours(1), 100, 12);
+
+        let unassigned = dt.events(None, true);
+        assert_eq!(unassigned.len(), 1);
+
+        let assignment = ReasonAssignment {
+            reason_code: "NOPE".into(),
+            comment: None,
+            user: "op".into(),
+        };
+        assert!(dt.assign_reason(unassigned[0].id, assignment).is_err());
+
+        let assignment = ReasonAssignment {
+            reason_code: "MAT".into(),
+            comment: Some("no pallets".into()),
+            user: "op".into(),
+        };
+        dt.assign_reason(unassigned[0].id, assignment).unwrap();
+
+        let pareto = dt.pareto(None, None, t0 + Duration::hours(2));
+        assert_eq!(pareto[0].reason_code, "MAT");
+        assert!((pareto[0].cumulative_percent - 75.0).abs() < 1e-9);
+        assert!((pareto[1].cumulative_percent - 100.0).abs() < 1e-9);
+    }
+}
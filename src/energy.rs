@@ -0,0 +1,753 @@
+// src/energy.rs
+//! Energy monitoring and consumption aggregation
+//!
+//! Each meter reads either a cumulative counter (e.g. a kWh register) or an
+//! instantaneous rate (kW, m³/h) that is integrated over time. Consumption is
+//! aggregated into hourly, daily and shift totals with cost and, optionally,
+//! the production in the same period, giving the specific consumption
+//! (energy per unit produced) used as an energy performance indicator.
+//!
+//! Demand is the average rate over fixed `demand_interval_minutes` windows,
+//! as billed by utilities; the highest demand of each hour, day and shift is
+//! recorded with the window in which it occurred.
+//!
+//! Consumption between two samples is assumed to accrue evenly and is split
+//! at hour, day, shift and demand window boundaries. Counter decreases are
+//! taken as resets. Cost uses the meter's `cost_per_unit` unless a
+//! time-of-use tariff covers the sample time.
+//!
+//! Running totals are published as `<output_prefix>.*` signals. Completed
+//! periods are kept as [`EnergyRecord`]s and persisted to `storage_path`.
+//!
+//! ```yaml
+//! energy:
+//!   storage_path: data/energy.json
+//!   demand_interval_minutes: 15
+//!   shifts:
+//!     definitions:
+//!       - { name: day, start: "06:00", end: "18:00" }
+//!       - { name: night, start: "18:00", end: "06:00" }
+//!   meters:
+//!     - name: main
+//!       counter_signal: main.kwh_total
+//!       unit: kWh
+//!       cost_per_unit: 0.18
+//!       tariffs:
+//!         - { start: "07:00", end: "20:00", cost_per_unit: 0.27 }
+//!       production_signal: line1.parts_total
+//!     - name: compressed_air
+//!       rate_signal: air.flow_m3h
+//!       unit: m3
+//! ```
+
+use crate::scan_budget::{measure, Subsystem};
+use crate::shifts::ShiftCalendar;
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::{DateTime, Duration, DurationRound, FixedOffset, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Energy subsystem configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyConfig {
+    /// Meters to aggregate
+    pub meters: Vec<EnergyMeterConfig>,
+
+    /// Shift calendar for shift totals; its UTC offset also sets local hours and days
+    #[serde(default)]
+    pub shifts: ShiftCalendar,
+
+    /// Length of the demand averaging window
+    #[serde(default = "default_demand_interval_minutes")]
+    pub demand_interval_minutes: u32,
+
+    /// JSON file where completed period records are persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<PathBuf>,
+
+    /// How often meters are sampled
+    #[serde(default = "default_update_interval_ms")]
+    pub update_interval_ms: u64,
+
+    /// Maximum number of records kept per meter and period kind
+    #[serde(default = "default_max_records")]
+    pub max_records: usize,
+}
+
+/// One energy or utility meter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyMeterConfig {
+    /// Meter name, unique within the energy configuration
+    pub name: String,
+
+    /// Cumulative consumption signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter_signal: Option<String>,
+
+    /// Instantaneous consumption rate signal, in units per hour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_signal: Option<String>,
+
+    /// Consumption unit, e.g. `kWh` or `m3`
+    #[serde(default = "default_unit")]
+    pub unit: String,
+
+    /// Price per unit outside any tariff window
+    #[serde(default)]
+    pub cost_per_unit: f64,
+
+    /// Time-of-use prices in plant local time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tariffs: Vec<Tariff>,
+
+    /// Cumulative production counter for specific consumption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production_signal: Option<String>,
+
+    /// Prefix for result signals, defaults to `energy.<name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_prefix: Option<String>,
+}
+
+/// A daily time-of-use price window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tariff {
+    /// Wall-clock start time (`HH:MM`)
+    pub start: NaiveTime,
+    /// Wall-clock end time (`HH:MM`), exclusive; may wrap past midnight
+    pub end: NaiveTime,
+    /// Price per unit within the window
+    pub cost_per_unit: f64,
+}
+
+impl Tariff {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.end <= self.start {
+            time >= self.start || time < self.end
+        } else {
+            time >= self.start && time < self.end
+        }
+    }
+}
+
+impl EnergyMeterConfig {
+    fn prefix(&self) -> String {
+        self.output_prefix
+            .clone()
+            .unwrap_or_else(|| format!("energy.{}", self.name))
+    }
+
+    fn price_at(&self, local_time: NaiveTime) -> f64 {
+        self.tariffs
+            .iter()
+            .find(|t| t.contains(local_time))
+            .map_or(self.cost_per_unit, |t| t.cost_per_unit)
+    }
+}
+
+impl EnergyConfig {
+    /// Validate meters, tariffs and the shift calendar
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config("Energy update interval cannot be 0".to_string()));
+        }
+        if self.demand_interval_minutes == 0 || 1440 % self.demand_interval_minutes != 0 {
+            return Err(PlcError::Config(format!(
+                "Energy demand interval of {} minutes must divide a day",
+                self.demand_interval_minutes
+            )));
+        }
+        self.shifts.validate()?;
+
+        let mut names = HashSet::new();
+        for meter in &self.meters {
+            if !names.insert(meter.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate energy meter '{}'", meter.name)));
+            }
+            if meter.counter_signal.is_some() == meter.rate_signal.is_some() {
+                return Err(PlcError::Config(format!(
+                    "Energy meter '{}' needs exactly one of counter_signal or rate_signal",
+                    meter.name
+                )));
+            }
+            let mut prices = std::iter::once(meter.cost_per_unit).chain(meter.tariffs.iter().map(|t| t.cost_per_unit));
+            if prices.any(|p| !p.is_finite() || p < 0.0) {
+                return Err(PlcError::Config(format!(
+                    "Energy meter '{}' prices must be non-negative",
+                    meter.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_unit() -> String {
+    "kWh".to_string()
+}
+
+const fn default_demand_interval_minutes() -> u32 {
+    15
+}
+
+const fn default_update_interval_ms() -> u64 {
+    1000
+}
+
+const fn default_max_records() -> usize {
+    2000
+}
+
+// ============================================================================
+// RESULTS
+// ============================================================================
+
+/// Aggregation period of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyPeriod {
+    Hourly,
+    Daily,
+    Shift,
+}
+
+impl EnergyPeriod {
+    const ALL: [Self; 3] = [Self::Hourly, Self::Daily, Self::Shift];
+}
+
+/// Consumption totals of one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyTotals {
+    /// Start of the period
+    pub started_at: DateTime<Utc>,
+    /// Shift name for shift periods
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shift: Option<String>,
+    /// Consumption in the meter unit
+    pub consumption: f64,
+    /// Cost of the consumption
+    pub cost: f64,
+    /// Highest demand window average, in units per hour
+    pub peak_demand: f64,
+    /// Start of the peak demand window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_demand_at: Option<DateTime<Utc>>,
+    /// Units produced, when the meter has a production signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production: Option<f64>,
+}
+
+impl EnergyTotals {
+    fn new(started_at: DateTime<Utc>, shift: Option<String>, tracks_production: bool) -> Self {
+        Self {
+            started_at,
+            shift,
+            consumption: 0.0,
+            cost: 0.0,
+            peak_demand: 0.0,
+            peak_demand_at: None,
+            production: tracks_production.then_some(0.0),
+        }
+    }
+
+    fn add(&mut self, usage: Usage) {
+        self.consumption += usage.consumption;
+        self.cost += usage.cost;
+        if let Some(production) = &mut self.production {
+            *production += usage.production;
+        }
+    }
+
+    /// Consumption per unit produced
+    #[must_use]
+    pub fn specific_consumption(&self) -> Option<f64> {
+        self.production.filter(|p| *p > 0.0).map(|p| self.consumption / p)
+    }
+}
+
+/// Totals of a completed period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyRecord {
+    /// Meter name
+    pub meter: String,
+    /// Kind of period
+    pub period: EnergyPeriod,
+    /// End of the period
+    pub ended_at: DateTime<Utc>,
+    /// Totals of the period
+    #[serde(flatten)]
+    pub totals: EnergyTotals,
+}
+
+/// Running totals of a meter
+#[derive(Debug, Clone, Serialize)]
+pub struct MeterSnapshot {
+    /// Meter name
+    pub meter: String,
+    /// Consumption unit
+    pub unit: String,
+    /// Current hour
+    pub hour: EnergyTotals,
+    /// Current local day
+    pub day: EnergyTotals,
+    /// Current shift
+    pub shift: EnergyTotals,
+    /// Average rate in the current demand window so far
+    pub demand: f64,
+    /// Average rate of the last completed demand window
+    pub last_demand: f64,
+}
+
+// ============================================================================
+// CALCULATION
+// ============================================================================
+
+/// Consumption, cost and production accrued between two samples
+#[derive(Debug, Clone, Copy, Default)]
+struct Usage {
+    consumption: f64,
+    cost: f64,
+    production: f64,
+}
+
+impl Usage {
+    fn scaled(self, fraction: f64) -> Self {
+        Self {
+            consumption: self.consumption * fraction,
+            cost: self.cost * fraction,
+            production: self.production * fraction,
+        }
+    }
+}
+
+struct MeterState {
+    config: EnergyMeterConfig,
+    /// Open totals in `EnergyPeriod::ALL` order
+    open: Option<[EnergyTotals; 3]>,
+    demand_started_at: Option<DateTime<Utc>>,
+    demand_consumption: f64,
+    last_demand: f64,
+    last_sample: Option<DateTime<Utc>>,
+    last_counter: Option<f64>,
+    last_production: Option<f64>,
+    history: VecDeque<EnergyRecord>,
+}
+
+impl MeterState {
+    fn new(config: EnergyMeterConfig) -> Self {
+        Self {
+            config,
+            open: None,
+            demand_started_at: None,
+            demand_consumption: 0.0,
+            last_demand: 0.0,
+            last_sample: None,
+            last_counter: None,
+            last_production: None,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+/// Counter increase since the last sample, treating a decrease as a reset
+fn counter_delta(last: &mut Option<f64>, current: f64) -> f64 {
+    let delta = match *last {
+        Some(prev) if current >= prev => current - prev,
+        Some(_) => current.max(0.0),
+        None => 0.0,
+    };
+    *last = Some(current);
+    delta
+}
+
+/// Share of `[from, to]` that lies before `boundary`
+fn fraction_before(from: DateTime<Utc>, boundary: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    let total = (to - from).num_milliseconds();
+    if total <= 0 {
+        return 1.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let before = (boundary - from).num_milliseconds().clamp(0, total) as f64 / total as f64;
+    before
+}
+
+fn hours(duration: Duration) -> f64 {
+    #[allow(clippy::cast_precision_loss)]
+    let ms = duration.num_milliseconds() as f64;
+    ms / 3_600_000.0
+}
+
+/// Aggregates consumption for all configured meters
+pub struct EnergyManager {
+    config: EnergyConfig,
+    meters: Vec<MeterState>,
+}
+
+impl EnergyManager {
+    /// Create a manager, loading persisted records if present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the storage file
+    /// cannot be read.
+    pub fn new(config: EnergyConfig) -> Result<Self> {
+        config.validate()?;
+        let mut meters: Vec<MeterState> = config.meters.iter().cloned().map(MeterState::new).collect();
+
+        if let Some(path) = &config.storage_path {
+            match std::fs::read_to_string(path) {
+                Ok(json) => {
+                    let records: Vec<EnergyRecord> = serde_json::from_str(&json)?;
+                    for record in records {
+                        if let Some(meter) = meters.iter_mut().find(|m| m.config.name == record.meter) {
+                            meter.history.push_back(record);
+                        }
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        info!("Energy monitoring enabled for {} meters", meters.len());
+        Ok(Self { config, meters })
+    }
+
+    /// Configured update interval
+    #[must_use]
+    pub fn update_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.update_interval_ms)
+    }
+
+    fn offset(&self) -> Result<FixedOffset> {
+        FixedOffset::east_opt(self.config.shifts.utc_offset_minutes * 60).ok_or_else(|| {
+            PlcError::Config(format!(
+                "Invalid energy UTC offset: {} minutes",
+                self.config.shifts.utc_offset_minutes
+            ))
+        })
+    }
+
+    /// Start of the `period` containing `at`, with the shift name for shifts
+    fn period_start(&self, period: EnergyPeriod, at: DateTime<Utc>) -> Result<(DateTime<Utc>, Option<String>)> {
+        let length = match period {
+            EnergyPeriod::Hourly => Duration::hours(1),
+            EnergyPeriod::Daily => Duration::days(1),
+            EnergyPeriod::Shift => {
+                let shift = self.config.shifts.instance_at(at)?;
+                return Ok((shift.started_at, Some(shift.name)));
+            }
+        };
+        // Truncation of a zoned time rounds its local time
+        let start = at
+            .with_timezone(&self.offset()?)
+            .duration_trunc(length)
+            .map_err(|e| PlcError::Runtime(format!("Energy period rounding failed: {e}")))?;
+        Ok((start.with_timezone(&Utc), None))
+    }
+
+    fn demand_window(&self) -> Duration {
+        Duration::minutes(i64::from(self.config.demand_interval_minutes))
+    }
+
+    /// Sample all meters, roll over completed periods and publish totals
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured signal is missing or not numeric, or
+    /// if completed records cannot be persisted.
+    pub fn process(&mut self, bus: &SignalBus, now: DateTime<Utc>) -> Result<()> {
+        let mut starts = Vec::with_capacity(EnergyPeriod::ALL.len());
+        for period in EnergyPeriod::ALL {
+            starts.push(self.period_start(period, now)?);
+        }
+        let window = self.demand_window();
+        let demand_start = now
+            .duration_trunc(window)
+            .map_err(|e| PlcError::Runtime(format!("Energy demand window rounding failed: {e}")))?;
+        let local_time = now.with_timezone(&self.offset()?).time();
+        let max_records = self.config.max_records;
+        let mut completed = false;
+
+        for meter in &mut self.meters {
+            // Consumption since the last sample
+            let tracks_production = meter.config.production_signal.is_some();
+            let previous = meter.last_sample.unwrap_or(now);
+            let consumption = match (&meter.config.counter_signal, &meter.config.rate_signal) {
+                (Some(signal), _) => counter_delta(&mut meter.last_counter, bus.get_float(signal)?),
+                (None, Some(signal)) => bus.get_float(signal)?.max(0.0) * hours(now - previous),
+                (None, None) => 0.0,
+            };
+            let production = match &meter.config.production_signal {
+                Some(signal) => counter_delta(&mut meter.last_production, bus.get_float(signal)?),
+                None => 0.0,
+            };
+            let usage = Usage { consumption, cost: consumption * meter.config.price_at(local_time), production };
+            meter.last_sample = Some(now);
+
+            let open = meter.open.get_or_insert_with(|| {
+                std::array::from_fn(|i| EnergyTotals::new(starts[i].0, starts[i].1.clone(), tracks_production))
+            });
+
+            // Close the demand window first so its peak counts toward the periods it belongs to
+            let window_start = *meter.demand_started_at.get_or_insert(demand_start);
+            let mut remaining = 1.0;
+            if window_start != demand_start {
+                let before = fraction_before(previous, demand_start, now);
+                meter.demand_consumption += consumption * before;
+                remaining -= before;
+                meter.last_demand = meter.demand_consumption / hours(window);
+                for totals in open.iter_mut().filter(|t| meter.last_demand > t.peak_demand) {
+                    totals.peak_demand = meter.last_demand;
+                    totals.peak_demand_at = Some(window_start);
+                }
+                meter.demand_consumption = 0.0;
+                meter.demand_started_at = Some(demand_start);
+            }
+            meter.demand_consumption += consumption * remaining;
+
+            for (i, period) in EnergyPeriod::ALL.into_iter().enumerate() {
+                let (start, shift) = &starts[i];
+                if open[i].started_at == *start && open[i].shift == *shift {
+                    open[i].add(usage);
+                    continue;
+                }
+                let before = fraction_before(previous, *start, now);
+                open[i].add(usage.scaled(before));
+                let next = EnergyTotals::new(*start, shift.clone(), tracks_production);
+                let totals = std::mem::replace(&mut open[i], next);
+                open[i].add(usage.scaled(1.0 - before));
+                debug!("Energy meter '{}' closed {:?} period at {}", meter.config.name, period, start);
+
+                meter.history.push_back(EnergyRecord {
+                    meter: meter.config.name.clone(),
+                    period,
+                    ended_at: *start,
+                    totals,
+                });
+                while meter.history.iter().filter(|r| r.period == period).count() > max_records {
+                    if let Some(index) = meter.history.iter().position(|r| r.period == period) {
+                        meter.history.remove(index);
+                    }
+                }
+                completed = true;
+            }
+
+            Self::publish(bus, meter)?;
+        }
+
+        if completed {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    fn publish(bus: &SignalBus, meter: &MeterState) -> Result<()> {
+        let Some([hour, day, shift]) = &meter.open else {
+            return Ok(());
+        };
+        let prefix = meter.config.prefix();
+        let mut updates = vec![
+            (format!("{prefix}.hour"), Value::Float(hour.consumption)),
+            (format!("{prefix}.day"), Value::Float(day.consumption)),
+            (format!("{prefix}.shift"), Value::Float(shift.consumption)),
+            (format!("{prefix}.cost_day"), Value::Float(day.cost)),
+            (format!("{prefix}.cost_shift"), Value::Float(shift.cost)),
+            (format!("{prefix}.demand"), Value::Float(meter.last_demand)),
+            (format!("{prefix}.peak_demand_day"), Value::Float(day.peak_demand)),
+        ];
+        if let Some(specific) = shift.specific_consumption() {
+            updates.push((format!("{prefix}.specific_shift"), Value::Float(specific)));
+        }
+        bus.write_batch(updates)
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.config.storage_path else {
+            return Ok(());
+        };
+        let records: Vec<&EnergyRecord> = self.meters.iter().flat_map(|m| m.history.iter()).collect();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&records)?)?;
+        Ok(())
+    }
+
+    /// Running totals for all meters
+    #[must_use]
+    pub fn snapshots(&self) -> Vec<MeterSnapshot> {
+        self.meters.iter().filter_map(|m| self.snapshot_of(m)).collect()
+    }
+
+    /// Running totals for one meter
+    #[must_use]
+    pub fn snapshot(&self, meter: &str) -> Option<MeterSnapshot> {
+        self.meters
+            .iter()
+            .find(|m| m.config.name == meter)
+            .and_then(|m| self.snapshot_of(m))
+    }
+
+    fn snapshot_of(&self, meter: &MeterState) -> Option<MeterSnapshot> {
+        let [hour, day, shift] = meter.open.clone()?;
+        let elapsed = meter
+            .demand_started_at
+            .zip(meter.last_sample)
+            .map_or(Duration::zero(), |(start, last)| last - start);
+        let demand = if elapsed > Duration::zero() {
+            meter.demand_consumption / hours(elapsed.min(self.demand_window()))
+        } else {
+            0.0
+        };
+        Some(MeterSnapshot {
+            meter: meter.config.name.clone(),
+            unit: meter.config.unit.clone(),
+            hour,
+            day,
+            shift,
+            demand,
+            last_demand: meter.last_demand,
+        })
+    }
+
+    /// Completed records of a meter, oldest first
+    ///
+    /// Records can be restricted to one period kind and to periods ending
+    /// within `[since, until]`.
+    #[must_use]
+    pub fn records(
+        &self,
+        meter: &str,
+        period: Option<EnergyPeriod>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Option<Vec<EnergyRecord>> {
+        let meter = self.meters.iter().find(|m| m.config.name == meter)?;
+        Some(
+            meter
+                .history
+                .iter()
+                .filter(|r| period.is_none_or(|p| r.period == p))
+                .filter(|r| since.is_none_or(|s| r.ended_at >= s))
+                .filter(|r| until.is_none_or(|u| r.ended_at <= u))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Run the periodic update loop until the task is cancelled
+    pub async fn run(manager: SharedEnergyManager, bus: SignalBus) {
+        let period = manager.read().await.update_interval();
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let mut manager = manager.write().await;
+            let result = measure(Subsystem::Analytics, || manager.process(&bus, Utc::now()));
+            if let Err(e) = result {
+                warn!("Energy update failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Shared energy manager handle used by the engine task and the web API
+pub type SharedEnergyManager = std::sync::Arc<tokio::sync::RwLock<EnergyManager>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config() -> EnergyConfig {
+        serde_yaml::from_str(
+            r#"
+demand_interval_minutes: 15
+shifts:
+  definitions:
+    - { name: day, start: "06:00", end: "18:00" }
+    - { name: night, start: "18:00", end: "06:00" }
+meters:
+  - name: main
+    counter_signal: kwh
+    cost_per_unit: 0.2
+    tariffs:
+      - { start: "17:00", end: "20:00", cost_per_unit: 0.5 }
+    production_signal: parts
+  - name: air
+    rate_signal: flow
+    unit: m3
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_counter_meter_splits_at_shift_boundary() {
+        let bus = SignalBus::new();
+        bus.set("kwh", Value::Float(1000.0)).unwrap();
+        bus.set("parts", Value::Integer(0)).unwrap();
+        bus.set("flow", Value::Float(0.0)).unwrap();
+
+        let mut energy = EnergyManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 16, 50, 0).unwrap();
+        energy.process(&bus, t0).unwrap();
+
+        // 10 kWh over 16:50-17:10, priced at the peak tariff of the sample time
+        bus.set("kwh", Value::Float(1010.0)).unwrap();
+        bus.set("parts", Value::Integer(100)).unwrap();
+        energy.process(&bus, t0 + Duration::minutes(20)).unwrap();
+
+        // 40 kWh over 17:10-18:10, split at the shift change
+        bus.set("kwh", Value::Float(1050.0)).unwrap();
+        energy.process(&bus, t0 + Duration::minutes(80)).unwrap();
+
+        let shifts = energy.records("main", Some(EnergyPeriod::Shift), None, None).unwrap();
+        assert_eq!(shifts.len(), 1);
+        let day_shift = &shifts[0].totals;
+        assert_eq!(day_shift.shift.as_deref(), Some("day"));
+        assert!((day_shift.consumption - (10.0 + 40.0 * 50.0 / 60.0)).abs() < 1e-9);
+        assert!((day_shift.cost - (10.0 * 0.5 + 40.0 * 50.0 / 60.0 * 0.5)).abs() < 1e-9);
+        assert_eq!(day_shift.production, Some(100.0));
+
+        let snapshot = energy.snapshot("main").unwrap();
+        assert!((snapshot.shift.consumption - 40.0 / 6.0).abs() < 1e-9);
+        assert!((bus.get_float("energy.main.day").unwrap() - 50.0).abs() < 1e-9);
+        assert_eq!(energy.records("main", Some(EnergyPeriod::Hourly), None, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_rate_meter_peak_demand() {
+        let bus = SignalBus::new();
+        bus.set("kwh", Value::Float(0.0)).unwrap();
+        bus.set("parts", Value::Integer(0)).unwrap();
+        bus.set("flow", Value::Float(100.0)).unwrap();
+
+        let mut energy = EnergyManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        energy.process(&bus, t0).unwrap();
+        energy.process(&bus, t0 + Duration::minutes(15)).unwrap();
+
+        // Higher draw in the second window
+        bus.set("flow", Value::Float(300.0)).unwrap();
+        energy.process(&bus, t0 + Duration::minutes(30)).unwrap();
+        energy.process(&bus, t0 + Duration::minutes(31)).unwrap();
+
+        let snapshot = energy.snapshot("air").unwrap();
+        assert!((snapshot.hour.consumption - (25.0 + 75.0 + 5.0)).abs() < 1e-9);
+        assert!((snapshot.last_demand - 300.0).abs() < 1e-9);
+        assert!((snapshot.hour.peak_demand - 300.0).abs() < 1e-9);
+        assert_eq!(snapshot.hour.peak_demand_at, Some(t0 + Duration::minutes(15)));
+    }
+}
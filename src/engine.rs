@@ -46,16 +46,26 @@
 //! - **Thread Safety**: Safe concurrent access using Arc<Mutex<>> patterns
 
 use crate::{
-    blocks::{create_block, Block},
-    config::Config,
+    blocks::{
+        create_block,
+        persistence::{restore_block_states, restore_retained_signals, save_block_states},
+        Block,
+    },
+    clock::{ClockConfig, SimulatedClock},
+    config::{BlockConfig, Config},
+    enums::EnumRegistry,
     value::from_yaml_value,
     error::PlcError,
+    events::{EventKind, EventLog},
+    scan_budget::{self, Subsystem},
     signal::SignalBus,
     value::Value,
+    staleness::StalenessMonitor,
+    watchdog::{Watchdog, WatchdogConfig},
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
@@ -63,7 +73,7 @@ use std::{
     time::{Duration, Instant, SystemTime},
 };
 use tokio::{
-    sync::{Mutex, RwLock},
+    sync::{watch, Mutex, RwLock},
     task::JoinHandle,
     time::{interval, sleep, MissedTickBehavior},
 };
@@ -75,6 +85,25 @@ use crate::metrics::EngineMetrics;
 #[cfg(feature = "parallel-execution")]
 mod parallel_executor;
 
+mod tasks;
+pub use tasks::TaskStats;
+
+mod simulation;
+
+mod budget;
+mod degradation;
+
+mod phases;
+pub use phases::{CycleBreakdown, CycleTimings, PhaseStats, ScanPhase};
+
+mod debug;
+pub use debug::{BlockStep, DebugStatus, Debugger, SignalValue, StepTrace, Watchpoint, WatchpointHit};
+
+#[cfg(feature = "hot-reload")]
+mod reload;
+#[cfg(feature = "hot-reload")]
+pub use reload::ReloadReport;
+
 #[cfg(feature = "realtime")]
 use libc::{sched_param, sched_setscheduler, SCHED_FIFO};
 
@@ -124,7 +153,8 @@ pub struct EngineConfig {
     /// Enable cache optimization
     pub cache_optimized: bool,
     
-    /// Watchdog timeout (0 = disabled)
+    /// Watchdog timeout (0 = disabled); the `watchdog` section of the
+    /// configuration takes precedence and adds the fail-safe actions
     pub watchdog_timeout_ms: u64,
     
     /// Behavior when scan cycle is missed
@@ -234,6 +264,8 @@ pub enum EngineState {
     Error,
     /// Engine is in recovery mode
     Recovering,
+    /// Scan loop is running but block execution is suspended
+    Paused,
 }
 
 impl Default for EngineState {
@@ -254,6 +286,9 @@ pub struct EngineStats {
     /// Errors by block name
     pub block_errors: HashMap<String, u64>,
     
+    /// Budget overruns by block name
+    pub block_overruns: HashMap<String, u64>,
+    
     /// Minimum scan time observed
     pub min_scan_time: Duration,
     
@@ -272,6 +307,9 @@ pub struct EngineStats {
     /// Number of scan overruns
     pub scan_overruns: u64,
     
+    /// Signals that missed their `update_frequency_ms`
+    pub stale_signals: usize,
+    
     /// Engine uptime
     pub uptime: Duration,
     
@@ -322,12 +360,32 @@ pub struct Engine {
     /// Logic blocks for automation processing
     /// 
     /// Protected by Arc<Mutex<>> to allow safe async access for hot-reload
-    /// and runtime block management.
+    /// and runtime block management. Blocks assigned to a scan task are
+    /// held by their task in `tasks` instead.
     blocks: Arc<Mutex<Vec<Box<dyn Block>>>>,
     
+    /// Scan tasks running blocks on their own timers
+    tasks: Vec<tasks::TaskGroup>,
+    
+    /// Process image of the main scan, present when tasks or an output
+    /// latch are configured
+    main_image: Option<tasks::SharedImage>,
+    
+    /// Keeps process image copies and write-backs of tasks apart
+    image_lock: Arc<tasks::ImageLock>,
+    
     /// System configuration
     config: Config,
     
+    /// Configuration the signals and blocks currently follow
+    /// 
+    /// Starts as `config` and is replaced by every hot reload.
+    applied_config: Arc<Mutex<Config>>,
+    
+    /// License the configuration is checked against
+    #[cfg(feature = "licensing")]
+    entitlement: Option<Arc<crate::license::Entitlement>>,
+    
     /// Engine-specific configuration
     engine_config: EngineConfig,
     
@@ -341,21 +399,45 @@ pub struct Engine {
     /// Current engine state for lifecycle management
     state: Arc<RwLock<EngineState>>,
     
+    /// Block execution suspended by a remote command
+    paused: Arc<AtomicBool>,
+    
     /// Total scan cycles completed
     scan_count: Arc<AtomicU64>,
     
+    /// Publishes the scan count after every completed cycle
+    scan_tick: watch::Sender<u64>,
+    
     /// Total errors encountered
     error_count: Arc<AtomicU64>,
     
+    /// Blocks whose last execution failed
+    failing_blocks: Mutex<HashSet<String>>,
+    
     /// Consecutive errors without successful scan
     consecutive_errors: Arc<AtomicU64>,
     
     /// Engine start timestamp
     start_time: Instant,
     
+    /// State changes, block failures and reloads for event subscribers
+    events: EventLog,
+    
+    /// Execution time budgets of the blocks
+    budgets: budget::SharedBudgets,
+    
+    /// Load shedding on repeated scan overruns
+    degradation: degradation::SharedDegradation,
+    
+    /// Phase histograms of the main scan
+    cycle_timings: CycleTimings,
+    
     /// Target scan cycle duration
     target_scan_time: Duration,
     
+    /// Watchpoints and step requests of the online debugger
+    debug: Arc<debug::DebugState>,
+    
     // ========================================================================
     // PERFORMANCE MONITORING
     // ========================================================================
@@ -377,11 +459,31 @@ pub struct Engine {
     /// Enhanced monitoring metrics collector
     metrics: Arc<EngineMetrics>,
     
-    /// Watchdog timer handle
+    /// Scan watchdog, fed after every healthy cycle
+    watchdog: Option<Watchdog>,
+    
+    /// Task supervising the scan loop for the watchdog
     watchdog_handle: Option<JoinHandle<()>>,
     
-    /// Last watchdog ping time
-    last_watchdog_ping: Arc<RwLock<Instant>>,
+    /// Monitor of the signals' expected update intervals
+    staleness: StalenessMonitor,
+    
+    /// Task checking the signals for staleness
+    staleness_handle: Option<JoinHandle<()>>,
+    
+    /// Write-behind store of the retained signals
+    #[cfg(feature = "retained-store")]
+    retained_store: Option<crate::retained::RetainedStore>,
+    
+    /// Task flushing the retained signal store
+    #[cfg(feature = "retained-store")]
+    retained_handle: Option<JoinHandle<()>>,
+    
+    /// Running scan tasks
+    task_handles: Vec<JoinHandle<()>>,
+    
+    /// Virtual clock driving the scan cycles in simulation mode
+    simulation: Option<simulation::Simulation>,
 
     #[cfg(feature = "parallel-execution")]
     parallel_executor: Option<Arc<parallel_executor::ParallelExecutor>>,
@@ -456,8 +558,12 @@ impl Engine {
     /// # Ok::<(), petra::PlcError>(())
     /// ```
     pub fn new_with_config(config: Config, engine_config: EngineConfig) -> Result<Self, PlcError> {
-        let bus = SignalBus::new();
-        Self::new_with_bus_and_config(config, bus, engine_config)
+        match config.clock {
+            Some(ClockConfig::Simulated { speed }) => {
+                Self::new_simulated(config, engine_config, Arc::new(SimulatedClock::accelerated(speed)))
+            }
+            _ => Self::new_with_bus_and_config(config, SignalBus::new(), engine_config),
+        }
     }
     
     /// Create a new engine with both custom bus and engine configuration
@@ -471,14 +577,15 @@ impl Engine {
     /// * `bus` - Pre-initialized signal bus
     /// * `engine_config` - Engine-specific configuration
     pub fn new_with_bus_and_config(
-        config: Config,
+        mut config: Config,
         bus: SignalBus,
         engine_config: EngineConfig,
     ) -> Result<Self, PlcError> {
         let _span = span!(Level::INFO, "engine_init").entered();
         info!("Initializing PETRA engine v{}", env!("CARGO_PKG_VERSION"));
         
-        // Validate configuration
+        // Expand templates and validate configuration
+        config.expand()?;
         config.validate()?;
 
         debug!(
@@ -493,19 +600,87 @@ impl Engine {
             ));
         }
         
+        #[cfg(feature = "licensing")]
+        let entitlement = crate::license::Entitlement::load(&config)?.map(Arc::new);
+        #[cfg(feature = "licensing")]
+        if let Some(entitlement) = &entitlement {
+            let report = entitlement.enforce(&config)?;
+            info!("{}: {} signals, {} blocks, protocols [{}]",
+                report, report.usage.signals, report.usage.blocks, report.usage.protocols.join(", "));
+        }
+        
         // Initialize signals from configuration
         Self::initialize_signals(&bus, &config)?;
         
+        // Register plugin block types before the blocks using them are created
+        #[cfg(feature = "plugins")]
+        if let Some(plugins) = &config.plugins {
+            crate::plugins::load(plugins)?;
+        }
+
         // Create and initialize blocks
-        let blocks = Self::create_blocks(&config)?;
+        let mut blocks = Self::create_blocks(&config)?;
+        
+        // Restore block state and retained signals saved by a previous run
+        if let Some(block_state) = &config.block_state {
+            if let Err(e) = restore_block_states(&block_state.path, &mut blocks) {
+                warn!(
+                    "Failed to restore block state from {}: {}",
+                    block_state.path.display(),
+                    e
+                );
+            }
+            let retained: Vec<&str> = config.signals.iter()
+                .filter(|s| s.retain)
+                .map(|s| s.name.as_str())
+                .collect();
+            if let Err(e) = restore_retained_signals(&block_state.path, &bus, &retained) {
+                warn!(
+                    "Failed to restore retained signals from {}: {}",
+                    block_state.path.display(),
+                    e
+                );
+            }
+        }
+        
+        // Stored on every write, so newer than the block state
+        #[cfg(feature = "retained-store")]
+        let retained_store = config.retained_store.as_ref()
+            .map(|store| crate::retained::RetainedStore::open(store, &config, bus.clone()))
+            .transpose()?;
+
+        // Blocks assigned to tasks leave the main scan
+        let split = tasks::TaskSplit::new(&config, &bus, blocks);
+        let blocks = split.main;
+        let main_image = split.main_image.map(|image| Arc::new(std::sync::RwLock::new(Arc::new(image))));
+        let task_groups = tasks::TaskSplit::groups(split.tasks, &config);
 
         #[cfg(feature = "parallel-execution")]
         let parallel_executor = if engine_config.parallel_execution {
-            Some(Arc::new(parallel_executor::ParallelExecutor::new(&blocks)))
+            Some(Arc::new(parallel_executor::ParallelExecutor::new(&blocks, &config)))
         } else {
             None
         };
         
+        let budgets = Arc::new(std::sync::Mutex::new(budget::BlockBudgets::new(&config)));
+        let degradation = Arc::new(degradation::Degradation::new(&config, &bus));
+        bus.configure_groups(&config.signal_groups);
+        bus.configure_aliases(&config.aliases);
+        let staleness = StalenessMonitor::new(&config, bus.clone());
+        
+        // An engine-level timeout without a `watchdog` section only reports
+        // stalls
+        let events = EventLog::default();
+        let watchdog = match (&config.watchdog, engine_config.watchdog_timeout_ms) {
+            (Some(watchdog), _) => Some(Watchdog::new(watchdog.clone(), bus.clone(), events.clone())?),
+            (None, 0) => None,
+            (None, timeout_ms) => Some(Watchdog::new(
+                WatchdogConfig { timeout_ms, latch: false, ..WatchdogConfig::default() },
+                bus.clone(),
+                events.clone(),
+            )?),
+        };
+        
         // Calculate EMA alpha based on scan time
         let ema_alpha = 2.0 / (10.0 + 1.0); // 10-period EMA
         
@@ -515,29 +690,52 @@ impl Engine {
         let metrics = Arc::new(EngineMetrics::new(&registry)
             .map_err(|e| PlcError::Runtime(e.to_string()))?);
 
+        let scan_start = bus.now();
         let engine = Self {
             bus,
             blocks: Arc::new(Mutex::new(blocks)),
+            tasks: task_groups,
+            main_image,
+            image_lock: Arc::new(tasks::ImageLock::default()),
             target_scan_time: Duration::from_millis(config.scan_time_ms),
+            debug: Arc::new(debug::DebugState::new(Duration::from_millis(config.scan_time_ms))),
+            applied_config: Arc::new(Mutex::new(config.clone())),
+            #[cfg(feature = "licensing")]
+            entitlement,
             config,
             engine_config,
             running: Arc::new(AtomicBool::new(false)),
             state: Arc::new(RwLock::new(EngineState::Stopped)),
+            paused: Arc::new(AtomicBool::new(false)),
             scan_count: Arc::new(AtomicU64::new(0)),
+            scan_tick: watch::Sender::new(0),
             error_count: Arc::new(AtomicU64::new(0)),
+            failing_blocks: Mutex::new(HashSet::new()),
             consecutive_errors: Arc::new(AtomicU64::new(0)),
             start_time: Instant::now(),
+            events,
+            budgets,
+            degradation,
+            cycle_timings: CycleTimings::default(),
             stats: Arc::new(RwLock::new(EngineStats {
                 min_scan_time: Duration::MAX,
                 max_scan_time: Duration::ZERO,
                 ..Default::default()
             })),
-            last_scan_start: Arc::new(RwLock::new(Instant::now())),
+            last_scan_start: Arc::new(RwLock::new(scan_start)),
             ema_alpha,
             #[cfg(feature = "enhanced-monitoring")]
             metrics,
+            watchdog,
             watchdog_handle: None,
-            last_watchdog_ping: Arc::new(RwLock::new(Instant::now())),
+            staleness,
+            staleness_handle: None,
+            #[cfg(feature = "retained-store")]
+            retained_store,
+            #[cfg(feature = "retained-store")]
+            retained_handle: None,
+            task_handles: Vec::new(),
+            simulation: None,
             #[cfg(feature = "parallel-execution")]
             parallel_executor,
         };
@@ -548,17 +746,33 @@ impl Engine {
             engine.blocks.try_lock().map_or(0, |b| b.len()),
             engine.config.scan_time_ms
         );
+        for task in &engine.tasks {
+            info!(
+                "Task '{}': {} blocks, interval={:?}",
+                task.name,
+                task.blocks.try_lock().map_or(0, |b| b.len()),
+                task.interval
+            );
+        }
         
         Ok(engine)
     }
     
     /// Initialize signals in the signal bus from configuration
-    fn initialize_signals(bus: &SignalBus, config: &Config) -> Result<(), PlcError> {
+    pub(crate) fn initialize_signals(bus: &SignalBus, config: &Config) -> Result<(), PlcError> {
         let _span = span!(Level::DEBUG, "init_signals").entered();
         
         for signal_config in &config.signals {
             // Convert initial value from configuration
-            let value = if let Some(initial_yaml) = &signal_config.initial {
+            let value = if let Some(enum_type) = signal_config.enum_type()? {
+                // Enum signals start at their configured label or lowest value
+                let raw = match &signal_config.initial {
+                    Some(initial) => enum_type.resolve_yaml(&signal_config.name, initial)
+                        .map_err(|e| PlcError::Config(e.to_string()))?,
+                    None => enum_type.default_value(),
+                };
+                Value::Integer(raw)
+            } else if let Some(initial_yaml) = &signal_config.initial {
                 from_yaml_value(initial_yaml.clone())
                     .map_err(|e| PlcError::Config(format!(
                         "Signal '{}' initial value conversion failed: {}",
@@ -578,8 +792,11 @@ impl Engine {
                 }
             };
             
-            // Set initial value in signal bus
+            // Set initial value in signal bus, then restrict later writes
             bus.set(&signal_config.name, value)?;
+            if let Some(policy) = &signal_config.write_policy {
+                bus.set_write_policy(&signal_config.name, policy.clone())?;
+            }
             
             debug!("Initialized signal '{}' with type '{}'", 
                 signal_config.name, signal_config.signal_type);
@@ -590,25 +807,31 @@ impl Engine {
     }
     
     /// Create and initialize all blocks from configuration
-    fn create_blocks(config: &Config) -> Result<Vec<Box<dyn Block>>, PlcError> {
+    pub(crate) fn create_blocks(config: &Config) -> Result<Vec<Box<dyn Block>>, PlcError> {
         let _span = span!(Level::DEBUG, "create_blocks").entered();
         
         let mut blocks = Vec::with_capacity(config.blocks.len());
         
-        // Sort blocks by priority (higher priority = earlier execution)
+        // Sort blocks by phase, then priority (higher priority = earlier
+        // execution)
         let mut sorted_configs = config.blocks.clone();
-        sorted_configs.sort_by(|a, b| b.priority.cmp(&a.priority));
+        sorted_configs.sort_by_key(BlockConfig::execution_order);
+        
+        // Blocks compare enum inputs against labels; give them raw values
+        let enums = EnumRegistry::from_signals(&config.signals)?;
         
-        for block_config in sorted_configs {
+        for mut block_config in sorted_configs {
             if !block_config.enabled {
                 debug!("Skipping disabled block '{}'", block_config.name);
                 continue;
             }
             
+            enums.resolve_block(&mut block_config)?;
+            
             match create_block(&block_config) {
                 Ok(block) => {
-                    debug!("Created block '{}' of type '{}' with priority {}", 
-                        block_config.name, block_config.block_type, block_config.priority);
+                    debug!("Created block '{}' of type '{}' in phase {} with priority {}", 
+                        block_config.name, block_config.block_type, block_config.phase().as_str(), block_config.priority);
                     blocks.push(block);
                 }
                 Err(e) => {
@@ -624,34 +847,21 @@ impl Engine {
         Ok(blocks)
     }
     
-    /// Start the watchdog timer
-    fn start_watchdog(&mut self, timeout: Duration) {
-        let running = Arc::clone(&self.running);
-        let last_ping = Arc::clone(&self.last_watchdog_ping);
-        
-        let handle = tokio::spawn(async move {
-            let mut interval = interval(timeout / 4); // Check 4x per timeout period
-            
-            while running.load(Ordering::Relaxed) {
-                interval.tick().await;
-                
-                let last_ping_time = *last_ping.read().await;
-                if last_ping_time.elapsed() > timeout {
-                    error!("Engine watchdog timeout! Last ping: {:?} ago", last_ping_time.elapsed());
-                    // In a production system, this might trigger a restart
-                    break;
-                }
+    /// Start supervising the scan loop and the scan tasks
+    fn start_watchdog(&mut self) {
+        if let Some(watchdog) = &self.watchdog {
+            for task in &self.tasks {
+                watchdog.supervise(&task.name);
             }
-        });
-        
-        self.watchdog_handle = Some(handle);
-        debug!("Started engine watchdog with timeout: {:?}", timeout);
+            self.watchdog_handle = Some(watchdog.spawn(self.engine_control()));
+            debug!("Started engine watchdog with timeout: {:?}", watchdog.timeout());
+        }
     }
     
-    /// Update watchdog ping timestamp
-    async fn ping_watchdog(&self) {
-        if self.watchdog_handle.is_some() {
-            *self.last_watchdog_ping.write().await = Instant::now();
+    /// Report a healthy cycle to the watchdog
+    fn ping_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.feed();
         }
     }
 }
@@ -711,36 +921,83 @@ impl Engine {
             self.set_cpu_affinity(cpus)?;
         }
         
+        let speed = self.simulation.as_ref().map_or(1.0, simulation::Simulation::speed);
+        if speed == 0.0 {
+            return Err(PlcError::Runtime(
+                "An engine on a stepped clock runs with step(), not run()".to_string(),
+            ));
+        }
+        
         // Start watchdog if configured
-        if self.engine_config.watchdog_timeout_ms > 0 {
-            self.start_watchdog(Duration::from_millis(self.engine_config.watchdog_timeout_ms));
+        self.start_watchdog();
+        if self.staleness.period().is_some() {
+            self.staleness_handle = Some(self.staleness.spawn());
+        }
+        #[cfg(feature = "retained-store")]
+        if let Some(store) = &self.retained_store {
+            self.retained_handle = Some(store.spawn());
         }
         
         // Update state
-        *self.state.write().await = EngineState::Starting;
+        self.set_state(EngineState::Starting).await;
         self.running.store(true, Ordering::Release);
         
         info!("Engine starting with scan time: {:?}", self.target_scan_time);
         
-        // Create scan interval with configured behavior
-        let mut scan_interval = interval(self.target_scan_time);
+        // Create scan interval with configured behavior, paced by the
+        // simulated clock in simulation mode
+        let mut scan_interval = interval(tasks::paced(self.target_scan_time, speed));
         scan_interval.set_missed_tick_behavior(self.engine_config.missed_tick_behavior);
         
         // Update state to running
-        *self.state.write().await = EngineState::Running;
+        self.set_state(if self.paused.load(Ordering::Acquire) {
+            EngineState::Paused
+        } else {
+            EngineState::Running
+        })
+        .await;
         self.start_time = Instant::now();
         
+        let context = self.task_context();
+        for task in &self.tasks {
+            self.task_handles.push(tokio::spawn(tasks::run(task.clone(), context.clone())));
+        }
+        
+        let state_save_interval = self
+            .config
+            .block_state
+            .as_ref()
+            .map(|c| Duration::from_millis(c.save_interval_ms));
+        let mut last_state_save = Instant::now();
+        
         // Main scan loop
         while self.running.load(Ordering::Acquire) {
             scan_interval.tick().await;
             
+            // Outputs hold their last values while paused, unless the
+            // debugger steps a cycle
+            if self.paused.load(Ordering::Acquire) {
+                self.ping_watchdog();
+                if self.debug.step_requested() {
+                    self.serve_steps().await;
+                }
+                continue;
+            }
+            
+            if state_save_interval.is_some_and(|i| last_state_save.elapsed() >= i) {
+                last_state_save = Instant::now();
+                if let Err(e) = self.save_block_state().await {
+                    warn!("Failed to save block state: {}", e);
+                }
+            }
+            
             match self.execute_scan_cycle().await {
                 Ok(()) => {
                     // Reset consecutive error counter on success
                     self.consecutive_errors.store(0, Ordering::Relaxed);
                     
                     // Ping watchdog
-                    self.ping_watchdog().await;
+                    self.ping_watchdog();
                 }
                 Err(e) => {
                     error!("Scan cycle error: {}", e);
@@ -762,15 +1019,16 @@ impl Engine {
                         );
                         
                         if self.engine_config.error_recovery {
-                            *self.state.write().await = EngineState::Recovering;
+                            self.set_state(EngineState::Recovering).await;
                             warn!("Attempting recovery in {}ms", self.engine_config.recovery_delay_ms);
                             sleep(Duration::from_millis(self.engine_config.recovery_delay_ms)).await;
                             
                             // Reset error counter for recovery attempt
                             self.consecutive_errors.store(0, Ordering::Relaxed);
-                            *self.state.write().await = EngineState::Running;
+                            self.set_state(EngineState::Running).await;
                         } else {
-                            *self.state.write().await = EngineState::Error;
+                            self.set_state(EngineState::Error).await;
+                            self.running.store(false, Ordering::Release);
                             return Err(PlcError::Runtime(format!(
                                 "Engine shutdown due to {} consecutive errors", consecutive
                             )));
@@ -781,15 +1039,38 @@ impl Engine {
         }
         
         // Graceful shutdown
-        *self.state.write().await = EngineState::Stopping;
+        self.set_state(EngineState::Stopping).await;
         info!("Engine shutting down gracefully");
         
         // Stop watchdog
         if let Some(handle) = self.watchdog_handle.take() {
             handle.abort();
         }
+        if let Some(handle) = self.staleness_handle.take() {
+            handle.abort();
+        }
+        #[cfg(feature = "retained-store")]
+        if let Some(handle) = self.retained_handle.take() {
+            handle.abort();
+        }
+        
+        // Tasks are only cancelled while waiting, never inside a cycle
+        for handle in self.task_handles.drain(..) {
+            handle.abort();
+            let _ = handle.await;
+        }
+        
+        #[cfg(feature = "retained-store")]
+        if let Some(store) = &self.retained_store {
+            if let Err(e) = store.flush() {
+                warn!("Failed to store retained signals: {}", e);
+            }
+        }
+        if let Err(e) = self.save_block_state().await {
+            warn!("Failed to save block state: {}", e);
+        }
         
-        *self.state.write().await = EngineState::Stopped;
+        self.set_state(EngineState::Stopped).await;
         Ok(())
     }
     
@@ -799,20 +1080,53 @@ impl Engine {
     /// performance statistics and handling errors for individual blocks.
     pub async fn execute_scan_cycle(&self) -> Result<(), PlcError> {
         let scan_start = Instant::now();
-        let _span = span!(Level::TRACE, "scan_cycle", scan = %self.scan_count()).entered();
+        let mut phases = phases::PhaseClock::start();
+        let _span = span!(Level::TRACE, "scan_cycle", scan = self.scan_count()).entered();
+        let mut recorder = self.debug_recorder().await;
         
-        // Execute all blocks
+        // Execute all blocks; recorded cycles run in order
         #[cfg(feature = "parallel-execution")]
-        if let Some(executor) = &self.parallel_executor {
-            executor.execute_parallel(Arc::clone(&self.blocks), &self.bus).await?;
+        if let Some(executor) = self.parallel_executor.as_ref().filter(|_| recorder.is_none()) {
+            let mut blocks = self.blocks.lock().await;
+            let image = self.load_image()?;
+            let bus = image.as_ref().map_or(&self.bus, |(image, _)| image.bus());
+            phases.lap(ScanPhase::Inputs);
+            let gate = parallel_executor::BlockGate {
+                budgets: Arc::clone(&self.budgets),
+                degradation: Arc::clone(&self.degradation),
+                events: self.events.clone(),
+                scan_start,
+            };
+            scan_budget::measure(Subsystem::Blocks, || executor.execute_parallel(&mut blocks, bus, &gate));
+            phases.lap(ScanPhase::Blocks);
+            self.store_image(image)?;
+            drop(blocks);
+            phases.lap(ScanPhase::Outputs);
         } else {
             let mut blocks = self.blocks.lock().await;
             let mut block_errors = Vec::new();
+            let image = self.load_image()?;
+            let bus = image.as_ref().map_or(&self.bus, |(image, _)| image.bus());
+            phases.lap(ScanPhase::Inputs);
 
             for block in blocks.iter_mut() {
+                if self.degradation.sheds(block.name())
+                    || !budget::lock(&self.budgets).admit(block.name(), scan_start)
+                {
+                    continue;
+                }
                 let block_start = Instant::now();
+                let span = span!(Level::TRACE, "block", block = %block.name(), block_type = %block.block_type());
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.before(block.as_ref(), bus);
+                }
 
-                match block.execute(&self.bus) {
+                let result = span.in_scope(|| scan_budget::measure(Subsystem::Blocks, || block.execute(bus)));
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.after(block.as_ref(), bus, block_start.elapsed(), result.as_ref().err());
+                }
+                budget::lock(&self.budgets).record(block.name(), block_start.elapsed(), Instant::now(), &self.events);
+                match result {
                     Ok(()) => {
                         let block_elapsed = block_start.elapsed();
 
@@ -826,15 +1140,15 @@ impl Engine {
                         }
 
                         if block_elapsed > self.target_scan_time / 10 {
-                            warn!(
+                            span.in_scope(|| warn!(
                                 "Slow block '{}' took {:?} (>10% of scan time)",
                                 block.name(),
                                 block_elapsed
-                            );
+                            ));
                         }
                     }
                     Err(e) => {
-                        error!("Block '{}' execution failed: {}", block.name(), e);
+                        span.in_scope(|| error!("Block '{}' execution failed: {}", block.name(), e));
                         block_errors.push((block.name().to_string(), e));
 
                         let mut stats = self.stats.write().await;
@@ -843,7 +1157,14 @@ impl Engine {
                 }
             }
 
+            phases.lap(ScanPhase::Blocks);
+
+            self.track_block_failures(&blocks, &block_errors).await;
+            phases.lap(ScanPhase::Housekeeping);
+            self.store_image(image)?;
+            phases.lap(ScanPhase::Outputs);
             drop(blocks);
+            self.finish_recording(recorder).await;
 
             if !block_errors.is_empty() {
                 let error_msg = block_errors
@@ -863,11 +1184,28 @@ impl Engine {
         {
             let mut blocks = self.blocks.lock().await;
             let mut block_errors = Vec::new();
+            let image = self.load_image()?;
+            let bus = image.as_ref().map_or(&self.bus, |(image, _)| image.bus());
+            phases.lap(ScanPhase::Inputs);
 
             for block in blocks.iter_mut() {
+                if self.degradation.sheds(block.name())
+                    || !budget::lock(&self.budgets).admit(block.name(), scan_start)
+                {
+                    continue;
+                }
                 let block_start = Instant::now();
+                let span = span!(Level::TRACE, "block", block = %block.name(), block_type = %block.block_type());
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.before(block.as_ref(), bus);
+                }
 
-                match block.execute(&self.bus) {
+                let result = span.in_scope(|| scan_budget::measure(Subsystem::Blocks, || block.execute(bus)));
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.after(block.as_ref(), bus, block_start.elapsed(), result.as_ref().err());
+                }
+                budget::lock(&self.budgets).record(block.name(), block_start.elapsed(), Instant::now(), &self.events);
+                match result {
                     Ok(()) => {
                         let block_elapsed = block_start.elapsed();
 
@@ -881,15 +1219,15 @@ impl Engine {
                         }
 
                         if block_elapsed > self.target_scan_time / 10 {
-                            warn!(
+                            span.in_scope(|| warn!(
                                 "Slow block '{}' took {:?} (>10% of scan time)",
                                 block.name(),
                                 block_elapsed
-                            );
+                            ));
                         }
                     }
                     Err(e) => {
-                        error!("Block '{}' execution failed: {}", block.name(), e);
+                        span.in_scope(|| error!("Block '{}' execution failed: {}", block.name(), e));
                         block_errors.push((block.name().to_string(), e));
 
                         let mut stats = self.stats.write().await;
@@ -898,7 +1236,14 @@ impl Engine {
                 }
             }
 
+            phases.lap(ScanPhase::Blocks);
+
+            self.track_block_failures(&blocks, &block_errors).await;
+            phases.lap(ScanPhase::Housekeeping);
+            self.store_image(image)?;
+            phases.lap(ScanPhase::Outputs);
             drop(blocks);
+            self.finish_recording(recorder).await;
 
             if !block_errors.is_empty() {
                 let error_msg = block_errors
@@ -916,16 +1261,68 @@ impl Engine {
         
         // Update scan statistics
         let scan_elapsed = scan_start.elapsed();
-        self.update_statistics(scan_elapsed).await;
+        let jitter = self.update_statistics(scan_elapsed).await;
         
         // Increment scan counter
-        self.scan_count.fetch_add(1, Ordering::Relaxed);
+        let scan_count = self.scan_count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.capture_snapshots(scan_count);
+        self.scan_tick.send_replace(scan_count);
+        scan_budget::record_scan();
+        phases.lap(ScanPhase::Housekeeping);
+        self.cycle_timings.record(&phases, jitter);
         
         Ok(())
     }
     
-    /// Update performance statistics after a scan cycle
-    async fn update_statistics(&self, scan_elapsed: Duration) {
+    /// Engine state the scan tasks run against
+    fn task_context(&self) -> tasks::TaskContext {
+        tasks::TaskContext {
+            bus: self.bus.clone(),
+            running: Arc::clone(&self.running),
+            paused: Arc::clone(&self.paused),
+            error_count: Arc::clone(&self.error_count),
+            events: self.events.clone(),
+            budgets: Arc::clone(&self.budgets),
+            degradation: Arc::clone(&self.degradation),
+            image_lock: Arc::clone(&self.image_lock),
+            watchdog: self.watchdog.clone(),
+            missed_tick_behavior: self.engine_config.missed_tick_behavior,
+            speed: self.simulation.as_ref().map_or(1.0, simulation::Simulation::speed),
+        }
+    }
+    
+    /// Copy the main scan's process image in, when it has one
+    /// 
+    /// Called with the block lock held, so a reload cannot swap the image
+    /// between this and [`store_image`](Self::store_image).
+    fn load_image(&self) -> Result<Option<tasks::LoadedImage>, PlcError> {
+        let Some(shared) = &self.main_image else {
+            return Ok(None);
+        };
+        let image = Arc::clone(&shared.read().unwrap_or_else(std::sync::PoisonError::into_inner));
+        let loaded = image.load(&self.bus, &self.image_lock)?;
+        Ok(Some((image, loaded)))
+    }
+    
+    /// Write back the outputs the main scan changed in its process image
+    fn store_image(&self, image: Option<tasks::LoadedImage>) -> Result<(), PlcError> {
+        match image {
+            Some((image, loaded)) => image.store(&self.bus, &loaded, &self.image_lock),
+            None => Ok(()),
+        }
+    }
+    
+    /// Snapshot the signal groups between two scan cycles
+    /// 
+    /// Taken under the shared side of the image lock, so no task is halfway
+    /// through writing its outputs back.
+    fn capture_snapshots(&self, scan: u64) {
+        let _guard = self.image_lock.read().unwrap_or_else(std::sync::PoisonError::into_inner);
+        self.bus.capture_snapshots(scan);
+    }
+    
+    /// Update performance statistics after a scan cycle, returning its jitter
+    async fn update_statistics(&self, scan_elapsed: Duration) -> Duration {
         let mut stats = self.stats.write().await;
         
         // Update scan count
@@ -951,7 +1348,7 @@ impl Engine {
         
         // Calculate jitter
         let last_start = *self.last_scan_start.read().await;
-        let actual_interval = last_start.elapsed();
+        let actual_interval = self.bus.now().saturating_duration_since(last_start);
         let jitter = if actual_interval > self.target_scan_time {
             actual_interval - self.target_scan_time
         } else {
@@ -963,7 +1360,7 @@ impl Engine {
             stats.max_jitter = jitter;
         }
         
-        // Check for scan overrun
+        // Check for scan overrun; repeated overruns degrade the engine
         if scan_elapsed > self.target_scan_time {
             stats.scan_overruns += 1;
             warn!(
@@ -971,6 +1368,10 @@ impl Engine {
                 scan_elapsed, self.target_scan_time
             );
         }
+        self.degradation.observe(scan_elapsed, self.target_scan_time, &self.bus);
+        #[cfg(feature = "enhanced-monitoring")]
+        #[allow(clippy::cast_precision_loss)]
+        self.metrics.set_stale_signals(self.bus.stale_signals().len() as f64);
         
         // Update timestamps
         stats.uptime = self.start_time.elapsed();
@@ -978,14 +1379,33 @@ impl Engine {
         
         // Update last scan start for next jitter calculation
         drop(stats);
-        *self.last_scan_start.write().await = Instant::now();
+        *self.last_scan_start.write().await = self.bus.now();
         
-        // Log performance warnings
-        if jitter > self.target_scan_time / 5 {
+        // Log performance warnings; an accelerated clock magnifies the timer
+        // resolution into virtual jitter, so it is only reported in real time
+        if jitter > self.target_scan_time / 5 && self.simulation.is_none() {
             warn!("High jitter detected: {:?} (>20% of scan time)", jitter);
         }
+        
+        jitter
     }
     
+    /// Move to a new lifecycle state, publishing the change
+    async fn set_state(&self, to: EngineState) {
+        let from = std::mem::replace(&mut *self.state.write().await, to);
+        if from != to {
+            self.events.publish(EventKind::StateChanged { from, to });
+        }
+    }
+
+    /// Publish blocks that started failing or recovered in this scan
+    ///
+    /// A block failing every scan is reported once, when it starts failing.
+    async fn track_block_failures(&self, blocks: &[Box<dyn Block>], errors: &[(String, PlcError)]) {
+        let mut failing = self.failing_blocks.lock().await;
+        tasks::track_failures(&mut failing, &self.events, blocks, errors);
+    }
+
     /// Stop the engine gracefully
     /// 
     /// This method signals the engine to stop and waits for the current
@@ -996,6 +1416,50 @@ impl Engine {
         
         // Give the engine time to complete current scan
         sleep(self.target_scan_time * 2).await;
+        
+        if let Err(e) = self.save_block_state().await {
+            warn!("Failed to save block state: {}", e);
+        }
+    }
+    
+    /// Save the state of stateful blocks to the configured state file
+    /// 
+    /// Does nothing unless `block_state` is configured. Called periodically
+    /// by the scan loop and when the engine stops. The values of signals
+    /// configured with `retain` are saved with the block state.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the state file cannot be written.
+    pub async fn save_block_state(&self) -> Result<(), PlcError> {
+        if let Some(block_state) = &self.config.block_state {
+            let config = self.applied_config.lock().await;
+            let retained = config.signals.iter()
+                .filter(|s| s.retain)
+                .filter_map(|s| self.bus.get(&s.name).map(|value| (s.name.clone(), value)))
+                .collect();
+            let mut groups = Vec::with_capacity(self.tasks.len() + 1);
+            for group in self.block_groups() {
+                groups.push(group.lock().await);
+            }
+            let blocks = groups.iter().flat_map(|blocks| blocks.iter().map(AsRef::as_ref));
+            save_block_states(&block_state.path, blocks, retained)?;
+        }
+        Ok(())
+    }
+    
+    /// Alarm definitions contributed by the configured blocks
+    /// 
+    /// Passed to the alarm manager alongside the configured alarms, so
+    /// limits evaluated by blocks such as `ANALOG_ALARM` are alarmed with
+    /// their configured priorities.
+    #[cfg(feature = "alarms")]
+    pub async fn block_alarm_configs(&self) -> Vec<crate::alarms::AlarmConfig> {
+        let mut configs = Vec::new();
+        for group in self.block_groups() {
+            configs.extend(group.lock().await.iter().flat_map(|block| block.alarm_configs()));
+        }
+        configs
     }
     
     /// Force an immediate engine stop
@@ -1005,7 +1469,10 @@ impl Engine {
     pub fn force_stop(&self) {
         warn!("Engine force stop requested");
         self.running.store(false, Ordering::Release);
-        *self.state.try_write().unwrap() = EngineState::Stopped;
+        let from = std::mem::replace(&mut *self.state.try_write().unwrap(), EngineState::Stopped);
+        if from != EngineState::Stopped {
+            self.events.publish(EventKind::StateChanged { from, to: EngineState::Stopped });
+        }
     }
 }
 
@@ -1092,9 +1559,20 @@ impl Engine {
         self.start_time.elapsed()
     }
     
+    /// Get a handle to the phase, cycle and jitter histograms of the main
+    /// scan
+    #[cfg(feature = "enhanced-monitoring")]
+    #[must_use]
+    pub fn cycle_timings(&self) -> CycleTimings {
+        self.cycle_timings.clone()
+    }
+    
     /// Get a copy of current statistics
     pub async fn stats(&self) -> EngineStats {
-        self.stats.read().await.clone()
+        let mut stats = self.stats.read().await.clone();
+        stats.block_overruns = budget::lock(&self.budgets).overruns();
+        stats.stale_signals = self.bus.stale_signals().len();
+        stats
     }
     
     /// Get basic performance metrics as a formatted string
@@ -1147,12 +1625,16 @@ impl Engine {
     pub async fn reset_blocks(&self) -> Result<(), PlcError> {
         let _span = span!(Level::INFO, "reset_blocks").entered();
         
-        let mut blocks = self.blocks.lock().await;
-        for block in blocks.iter_mut() {
-            if let Err(e) = block.reset() {
-                error!("Failed to reset block '{}': {}", block.name(), e);
-                return Err(e);
+        let mut count = 0;
+        for group in self.block_groups() {
+            let mut blocks = group.lock().await;
+            for block in blocks.iter_mut() {
+                if let Err(e) = block.reset() {
+                    error!("Failed to reset block '{}': {}", block.name(), e);
+                    return Err(e);
+                }
             }
+            count += blocks.len();
         }
         
         // Reset statistics
@@ -1168,49 +1650,57 @@ impl Engine {
             ..Default::default()
         };
         
-        info!("Reset {} blocks and cleared statistics", blocks.len());
+        info!("Reset {} blocks and cleared statistics", count);
         Ok(())
     }
     
     /// Reload configuration and blocks without stopping
     /// 
-    /// This method allows hot-reloading of configuration and blocks
-    /// while the engine continues running. New blocks are created and
-    /// swapped atomically.
+    /// Compares `config` with the running configuration and adds, removes
+    /// and updates only the signals and blocks that differ, between scan
+    /// cycles. Unchanged blocks keep their state. If anything fails the
+    /// engine is left as it was; see [`ReloadHandle::reload`].
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the configuration is invalid, changes the scan
+    /// tasks, or a signal or block cannot be created.
+    #[cfg(feature = "hot-reload")]
+    pub async fn reload(&self, config: &Config) -> Result<ReloadReport, PlcError> {
+        self.reload_handle().reload(config).await
+    }
+    
+    /// Reload configuration and blocks without stopping
+    /// 
+    /// Same as [`reload`](Self::reload), without the report.
     #[cfg(feature = "hot-reload")]
     pub async fn reload_config(&self, new_config: Config) -> Result<(), PlcError> {
-        let _span = span!(Level::INFO, "reload_config").entered();
-        info!("Starting configuration reload");
-        
-        // Validate new configuration
-        new_config.validate()?;
-        
-        // Create new blocks
-        let new_blocks = Self::create_blocks(&new_config)?;
-        
-        // Atomically swap blocks
-        let mut blocks = self.blocks.lock().await;
-        *blocks = new_blocks;
-        
-        info!("Configuration reloaded successfully");
-        Ok(())
+        self.reload(&new_config).await.map(|_| ())
     }
     
     /// Add a new block to the running engine
     /// 
     /// This method allows adding new blocks dynamically without restart.
+    /// Added blocks run in the main scan; when scan tasks are configured
+    /// they only see the signals of the main scan's process image.
     pub async fn add_block(&self, block: Box<dyn Block>) -> Result<(), PlcError> {
-        let mut blocks = self.blocks.lock().await;
         let block_name = block.name().to_string();
         
         // Check for duplicate names
-        if blocks.iter().any(|b| b.name() == block_name) {
-            return Err(PlcError::Config(format!(
-                "Block with name '{}' already exists", block_name
-            )));
+        for group in self.block_groups() {
+            if group.lock().await.iter().any(|b| b.name() == block_name) {
+                return Err(PlcError::Config(format!(
+                    "Block with name '{block_name}' already exists"
+                )));
+            }
         }
         
+        let mut blocks = self.blocks.lock().await;
         blocks.push(block);
+        #[cfg(feature = "parallel-execution")]
+        if let Some(executor) = &self.parallel_executor {
+            executor.rebuild(&blocks);
+        }
         info!("Added new block '{}'", block_name);
         Ok(())
     }
@@ -1219,19 +1709,314 @@ impl Engine {
     /// 
     /// This method allows removing blocks dynamically without restart.
     pub async fn remove_block(&self, block_name: &str) -> Result<(), PlcError> {
-        let mut blocks = self.blocks.lock().await;
-        let initial_count = blocks.len();
-        
-        blocks.retain(|b| b.name() != block_name);
-        
-        if blocks.len() == initial_count {
-            return Err(PlcError::Config(format!(
-                "Block '{}' not found", block_name
-            )));
+        for group in self.block_groups() {
+            let mut blocks = group.lock().await;
+            let initial_count = blocks.len();
+            
+            blocks.retain(|b| b.name() != block_name);
+            
+            if blocks.len() != initial_count {
+                #[cfg(feature = "parallel-execution")]
+                if let Some(executor) = self.parallel_executor.as_ref().filter(|_| Arc::ptr_eq(group, &self.blocks)) {
+                    executor.rebuild(&blocks);
+                }
+                info!("Removed block '{}'", block_name);
+                return Ok(());
+            }
         }
         
-        info!("Removed block '{}'", block_name);
-        Ok(())
+        Err(PlcError::Config(format!(
+            "Block '{block_name}' not found"
+        )))
+    }
+    
+    /// Blocks of the main scan followed by the blocks of each scan task
+    fn block_groups(&self) -> impl Iterator<Item = &tasks::SharedBlocks> {
+        std::iter::once(&self.blocks).chain(self.tasks.iter().map(|task| &task.blocks))
+    }
+    
+    /// Cycle statistics of the configured scan tasks
+    /// 
+    /// Empty when no tasks are configured; the main scan is reported by
+    /// [`stats`](Self::stats).
+    pub fn task_stats(&self) -> Vec<TaskStats> {
+        self.tasks.iter().map(tasks::TaskGroup::stats).collect()
+    }
+    
+    /// Get a cloneable handle for reloading configuration from other tasks
+    /// 
+    /// `run()` holds `&mut self` for the lifetime of the engine, so
+    /// components such as the web API use this handle to deploy new
+    /// configurations while the scan loop keeps running.
+    #[cfg(feature = "hot-reload")]
+    #[must_use]
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle {
+            bus: self.bus.clone(),
+            blocks: Arc::clone(&self.blocks),
+            config: Arc::clone(&self.applied_config),
+            #[cfg(feature = "licensing")]
+            entitlement: self.entitlement.clone(),
+            main_image: self.main_image.clone(),
+            tasks: self.tasks.clone(),
+            events: self.events.clone(),
+            budgets: Arc::clone(&self.budgets),
+            degradation: Arc::clone(&self.degradation),
+            staleness: self.staleness.clone(),
+            #[cfg(feature = "retained-store")]
+            retained_store: self.retained_store.clone(),
+            #[cfg(feature = "parallel-execution")]
+            parallel_executor: self.parallel_executor.clone(),
+        }
+    }
+    
+    /// Get a cloneable handle for pausing and resuming block execution
+    /// 
+    /// Used by remote control APIs, which run in their own tasks while
+    /// `run()` owns the engine.
+    #[must_use]
+    pub fn engine_control(&self) -> EngineControl {
+        EngineControl {
+            paused: Arc::clone(&self.paused),
+            state: Arc::clone(&self.state),
+            scan_count: Arc::clone(&self.scan_count),
+            error_count: Arc::clone(&self.error_count),
+            events: self.events.clone(),
+        }
+    }
+    
+    /// Get a handle to the engine's event stream
+    /// 
+    /// State changes, block failures and applied configurations are
+    /// published here; protocol drivers started alongside the engine add
+    /// their connection events to the same stream.
+    #[must_use]
+    pub fn events(&self) -> EventLog {
+        self.events.clone()
+    }
+    
+    /// Subscribe to completed scan cycles
+    /// 
+    /// The receiver sees the scan count change after every cycle. I/O
+    /// drivers that must exchange data in step with the engine, such as
+    /// fieldbus masters, wait on it instead of running their own timer.
+    #[must_use]
+    pub fn scan_ticks(&self) -> watch::Receiver<u64> {
+        self.scan_tick.subscribe()
+    }
+    
+    /// Get a cloneable handle for operating on individual blocks
+    /// 
+    /// Used by remote command channels, which run in their own tasks while
+    /// `run()` owns the engine.
+    #[must_use]
+    pub fn block_control(&self) -> BlockControl {
+        BlockControl {
+            groups: self.block_groups().map(Arc::clone).collect(),
+        }
+    }
+}
+
+// ============================================================================
+// ENGINE CONTROL HANDLE
+// ============================================================================
+
+/// Handle for pausing and resuming a running engine
+/// 
+/// Obtained from [`Engine::engine_control`]. While paused the scan loop keeps
+/// ticking but executes no blocks, so outputs hold their last values and
+/// signals stay readable and writable.
+#[derive(Clone)]
+pub struct EngineControl {
+    paused: Arc<AtomicBool>,
+    state: Arc<RwLock<EngineState>>,
+    scan_count: Arc<AtomicU64>,
+    error_count: Arc<AtomicU64>,
+    events: EventLog,
+}
+
+impl EngineControl {
+    /// Suspend block execution from the next scan cycle
+    pub async fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+        let mut state = self.state.write().await;
+        if *state == EngineState::Running {
+            *state = EngineState::Paused;
+            self.events.publish(EventKind::StateChanged {
+                from: EngineState::Running,
+                to: EngineState::Paused,
+            });
+        }
+        info!("Engine paused");
+    }
+    
+    /// Resume block execution
+    pub async fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+        let mut state = self.state.write().await;
+        if *state == EngineState::Paused {
+            *state = EngineState::Running;
+            self.events.publish(EventKind::StateChanged {
+                from: EngineState::Paused,
+                to: EngineState::Running,
+            });
+        }
+        info!("Engine resumed");
+    }
+    
+    /// Whether block execution is suspended
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+    
+    /// Current engine state
+    pub async fn state(&self) -> EngineState {
+        *self.state.read().await
+    }
+    
+    /// Total scan cycles completed
+    #[must_use]
+    pub fn scan_count(&self) -> u64 {
+        self.scan_count.load(Ordering::Relaxed)
+    }
+    
+    /// Total scan errors encountered
+    #[must_use]
+    pub fn error_count(&self) -> u64 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+    
+    /// Event stream of the engine, including the changes made through
+    /// this handle
+    #[must_use]
+    pub fn events(&self) -> EventLog {
+        self.events.clone()
+    }
+}
+
+// ============================================================================
+// BLOCK CONTROL HANDLE
+// ============================================================================
+
+/// Handle for operating on blocks of a running engine
+/// 
+/// Obtained from [`Engine::block_control`]. Operations take the block lock
+/// of the main scan or scan task the block runs in, so they are applied
+/// between that task's cycles.
+#[derive(Clone)]
+pub struct BlockControl {
+    groups: Vec<tasks::SharedBlocks>,
+}
+
+impl BlockControl {
+    /// Reset a single block to its initial state
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::NotFound`] if no block has this name, or the
+    /// block's own error if the reset fails.
+    pub async fn reset(&self, block_name: &str) -> Result<(), PlcError> {
+        for group in &self.groups {
+            let mut blocks = group.lock().await;
+            if let Some(block) = blocks.iter_mut().find(|b| b.name() == block_name) {
+                block.reset()?;
+                
+                info!("Reset block '{}'", block_name);
+                return Ok(());
+            }
+        }
+        Err(PlcError::NotFound(format!("Block '{block_name}'")))
+    }
+    
+    /// Internal state of every block that has any, by block name
+    ///
+    /// See [`Block::save_state`].
+    pub async fn save_states(&self) -> HashMap<String, serde_json::Value> {
+        let mut states = HashMap::new();
+        for group in &self.groups {
+            for block in group.lock().await.iter() {
+                if let Some(state) = block.save_state() {
+                    states.insert(block.name().to_string(), state);
+                }
+            }
+        }
+        states
+    }
+
+    /// Restore block states returned by [`save_states`](Self::save_states)
+    ///
+    /// Returns the number of blocks restored. States of unknown blocks and
+    /// states a block rejects are skipped.
+    pub async fn load_states(&self, states: &HashMap<String, serde_json::Value>) -> usize {
+        let mut restored = 0;
+        for group in &self.groups {
+            for block in group.lock().await.iter_mut() {
+                let Some(state) = states.get(block.name()) else {
+                    continue;
+                };
+                match block.load_state(state.clone()) {
+                    Ok(()) => restored += 1,
+                    Err(e) => warn!("Could not restore state of block '{}': {}", block.name(), e),
+                }
+            }
+        }
+        restored
+    }
+
+    /// Whether a block with this name exists
+    pub async fn contains(&self, block_name: &str) -> bool {
+        for group in &self.groups {
+            if group.lock().await.iter().any(|b| b.name() == block_name) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// ============================================================================
+// HOT RELOAD HANDLE
+// ============================================================================
+
+/// Handle for reloading the configuration of a running engine
+/// 
+/// Obtained from [`Engine::reload_handle`]. Signals that are new in the
+/// deployed configuration are created with their initial values; existing
+/// signals keep their current values so a deploy does not disturb the process.
+#[cfg(feature = "hot-reload")]
+#[derive(Clone)]
+pub struct ReloadHandle {
+    bus: SignalBus,
+    blocks: Arc<Mutex<Vec<Box<dyn Block>>>>,
+    config: Arc<Mutex<Config>>,
+    #[cfg(feature = "licensing")]
+    entitlement: Option<Arc<crate::license::Entitlement>>,
+    main_image: Option<tasks::SharedImage>,
+    tasks: Vec<tasks::TaskGroup>,
+    events: EventLog,
+    budgets: budget::SharedBudgets,
+    degradation: degradation::SharedDegradation,
+    staleness: StalenessMonitor,
+    #[cfg(feature = "retained-store")]
+    retained_store: Option<crate::retained::RetainedStore>,
+    #[cfg(feature = "parallel-execution")]
+    parallel_executor: Option<Arc<parallel_executor::ParallelExecutor>>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl ReloadHandle {
+    /// Validate and apply a configuration to the running engine
+    /// 
+    /// Returns the number of active blocks after the reload; see
+    /// [`reload`](Self::reload) for what changes.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the configuration fails validation or any block
+    /// cannot be created. The engine is left untouched on error.
+    pub async fn apply(&self, config: &Config) -> Result<usize, PlcError> {
+        self.reload(config).await.map(|report| report.blocks)
     }
 }
 
@@ -1242,103 +2027,27 @@ impl Engine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{SignalConfig, BlockConfig};
-    use std::collections::HashMap;
     
     fn create_test_config() -> Config {
-        Config {
-            scan_time_ms: 100,
-            max_scan_jitter_ms: 10,
-            error_recovery: true,
-            max_consecutive_errors: 5,
-            restart_delay_ms: 1000,
-            
-            signals: vec![
-                SignalConfig {
-                    name: "test_signal".to_string(),
-                    signal_type: "bool".to_string(),
-                    initial: Some(serde_yaml::Value::Bool(false)),
-                    description: Some("Test signal".to_string()),
-                    unit: None,
-                    min: None,
-                    max: None,
-                    access: None,
-                    #[cfg(feature = "extended-types")]
-                    metadata: HashMap::new(),
-                },
-                SignalConfig {
-                    name: "output_signal".to_string(),
-                    signal_type: "bool".to_string(),
-                    initial: None,
-                    description: Some("Output signal".to_string()),
-                    unit: None,
-                    min: None,
-                    max: None,
-                    access: None,
-                    #[cfg(feature = "extended-types")]
-                    metadata: HashMap::new(),
-                },
-            ],
-            
-            blocks: vec![
-                BlockConfig {
-                    name: "test_block".to_string(),
-                    block_type: "NOT".to_string(),
-                    inputs: {
-                        let mut inputs = HashMap::new();
-                        inputs.insert("input".to_string(), "test_signal".to_string());
-                        inputs
-                    },
-                    outputs: {
-                        let mut outputs = HashMap::new();
-                        outputs.insert("output".to_string(), "output_signal".to_string());
-                        outputs
-                    },
-                    parameters: HashMap::new(),
-                    priority: 0,
-                    enabled: true,
-                    description: Some("Test NOT block".to_string()),
-                    category: Some("Test".to_string()),
-                    tags: vec!["test".to_string()],
-                    #[cfg(feature = "circuit-breaker")]
-                    circuit_breaker: None,
-                    #[cfg(feature = "enhanced-monitoring")]
-                    enhanced_monitoring: false,
-                    metadata: HashMap::new(),
-                },
-            ],
-            
-            protocols: None,
-            version: "1.0".to_string(),
-            description: Some("Test configuration".to_string()),
-            author: Some("Test".to_string()),
-            created: None,
-            modified: None,
-            metadata: HashMap::new(),
-            
-            #[cfg(feature = "mqtt")]
-            mqtt: None,
-            #[cfg(feature = "security")]
-            security: None,
-            #[cfg(feature = "history")]
-            history: None,
-            #[cfg(feature = "alarms")]
-            alarms: None,
-            #[cfg(feature = "web")]
-            web: None,
-            #[cfg(feature = "advanced-storage")]
-            storage: None,
-            #[cfg(feature = "s7-support")]
-            s7: None,
-            #[cfg(feature = "modbus-support")]
-            modbus: None,
-            #[cfg(feature = "opcua-support")]
-            opcua: None,
-            #[cfg(feature = "twilio")]
-            twilio: None,
-            #[cfg(feature = "email")]
-            email: None,
-        }
+        serde_yaml::from_str(
+            r#"
+scan_time_ms: 100
+max_scan_jitter_ms: 10
+description: Test configuration
+signals:
+  - { name: test_signal, type: bool, initial: false, description: Test signal }
+  - { name: output_signal, type: bool, description: Output signal }
+blocks:
+  - name: test_block
+    type: NOT
+    inputs: { input: test_signal }
+    outputs: { output: output_signal }
+    description: Test NOT block
+    category: Test
+    tags: [test]
+"#,
+        )
+        .unwrap()
     }
     
     #[test]
@@ -1361,12 +2070,10 @@ mod tests {
         let engine = Engine::new_with_bus(config, bus).unwrap();
         
         // Verify pre-existing signal
-        let value = engine.signal_bus().get("pre_existing").unwrap();
-        assert_eq!(value, Some(Value::Float(42.0)));
+        assert_eq!(engine.signal_bus().get("pre_existing"), Some(Value::Float(42.0)));
         
         // Verify configured signals were initialized
-        let test_signal = engine.signal_bus().get("test_signal").unwrap();
-        assert_eq!(test_signal, Some(Value::Bool(false)));
+        assert_eq!(engine.signal_bus().get("test_signal"), Some(Value::Bool(false)));
     }
     
     #[test]
@@ -1395,29 +2102,20 @@ mod tests {
     #[tokio::test]
     async fn test_engine_lifecycle() {
         let config = create_test_config();
-        let engine = Engine::new(config).unwrap();
+        let mut engine = Engine::new(config).unwrap();
         
         // Initial state
         assert_eq!(engine.state().await, EngineState::Stopped);
         assert!(!engine.is_running());
         
-        // Start and immediately stop
-        let engine_clone = engine.clone();
-        let handle = tokio::spawn(async move {
-            let mut engine = engine_clone;
-            engine.run().await
-        });
-        
-        // Give it time to start
-        tokio::time::sleep(Duration::from_millis(50)).await;
-        assert!(engine.is_running());
-        assert_eq!(engine.state().await, EngineState::Running);
-        
-        // Stop the engine
-        engine.stop().await;
-        handle.abort();
-        
-        assert!(!engine.is_running());
+        // Run until the engine has started, then drop the scan loop
+        let control = engine.engine_control();
+        tokio::select! {
+            result = engine.run() => panic!("engine stopped on its own: {result:?}"),
+            () = tokio::time::sleep(Duration::from_millis(50)) => {}
+        }
+        assert_eq!(control.state().await, EngineState::Running);
+        assert!(control.scan_count() > 0);
     }
     
     #[tokio::test]
@@ -1432,13 +2130,14 @@ mod tests {
         engine.execute_scan_cycle().await.unwrap();
         
         // Check that NOT block inverted the signal
-        let output = engine.signal_bus().get("output_signal").unwrap();
-        assert_eq!(output, Some(Value::Bool(false)));
+        assert_eq!(engine.signal_bus().get("output_signal"), Some(Value::Bool(false)));
         
         // Verify statistics
         assert_eq!(engine.scan_count(), 1);
         assert_eq!(engine.error_count(), 0);
         
+        // The scan loop records the cycle time in the statistics
+        engine.update_statistics(Duration::from_millis(1)).await;
         let stats = engine.stats().await;
         assert_eq!(stats.scan_count, 1);
         assert!(stats.avg_scan_time > Duration::ZERO);
@@ -1450,7 +2149,7 @@ mod tests {
         let engine = Engine::new(config).unwrap();
         
         // Create a simple test block
-        use crate::blocks::{Block, BlockConfig};
+        use crate::blocks::Block;
         
         struct TestBlock {
             name: String,
@@ -0,0 +1,243 @@
+// src/engine/budget.rs
+//! Per-block execution time budgets
+//!
+//! A block configured with a `budget` is timed on every execution. When it
+//! takes longer than `max_us` the overrun is logged and counted in
+//! [`EngineStats::block_overruns`](super::EngineStats::block_overruns), and
+//! `on_overrun` decides what else happens:
+//!
+//! - `log` - nothing more, the block keeps running every cycle
+//! - `skip` - the block sits out the next `skip_cycles` cycles, so a slow
+//!   supervisory block cannot stretch every scan
+//! - `trip` - after `trip_after` overruns in a row the block's breaker
+//!   opens and the block stops executing; its outputs hold their last
+//!   value. After `reset_ms` the block runs once more: within budget closes
+//!   the breaker again, another overrun reopens it
+//!
+//! ```yaml
+//! blocks:
+//!   - name: recipe_lookup
+//!     type: DATA_GENERATOR
+//!     budget: { max_us: 500, on_overrun: trip, trip_after: 3, reset_ms: 30000 }
+//! ```
+//!
+//! Tripping and recovery are published as `block_error` and
+//! `block_recovered` events.
+
+use crate::{
+    config::{BlockBudgetConfig, Config, OverrunAction},
+    events::{EventKind, EventLog},
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Breaker state of a block with a `trip` budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breaker {
+    Closed,
+    /// Out of the scan until the instant
+    Open(Instant),
+    /// Running once on probation after the reset time
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Budget {
+    config: BlockBudgetConfig,
+    /// Overruns since startup
+    overruns: u64,
+    /// Overruns without an execution within budget in between
+    consecutive: u32,
+    /// Cycles still to skip
+    skip: u32,
+    breaker: Breaker,
+}
+
+impl Budget {
+    fn new(config: BlockBudgetConfig) -> Self {
+        Self { config, overruns: 0, consecutive: 0, skip: 0, breaker: Breaker::Closed }
+    }
+}
+
+/// Budgets of all blocks, shared by the main scan and the scan tasks
+#[derive(Debug, Default)]
+pub(crate) struct BlockBudgets {
+    budgets: HashMap<String, Budget>,
+}
+
+pub(crate) type SharedBudgets = Arc<Mutex<BlockBudgets>>;
+
+/// Lock shared budgets; never held across an await
+pub(crate) fn lock(budgets: &SharedBudgets) -> MutexGuard<'_, BlockBudgets> {
+    budgets.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl BlockBudgets {
+    /// Budgets of the blocks in `config`
+    pub(crate) fn new(config: &Config) -> Self {
+        let mut budgets = Self::default();
+        budgets.configure(config);
+        budgets
+    }
+
+    /// Follow a reloaded configuration
+    ///
+    /// Blocks whose budget is unchanged keep their counters and breaker.
+    pub(crate) fn configure(&mut self, config: &Config) {
+        let mut previous = std::mem::take(&mut self.budgets);
+        for block in &config.blocks {
+            let Some(budget) = &block.budget else {
+                continue;
+            };
+            let kept = previous.remove(&block.name).filter(|b| b.config == *budget);
+            self.budgets
+                .insert(block.name.clone(), kept.unwrap_or_else(|| Budget::new(budget.clone())));
+        }
+    }
+
+    /// Whether `block` executes in the cycle starting at `now`
+    pub(crate) fn admit(&mut self, block: &str, now: Instant) -> bool {
+        let Some(budget) = self.budgets.get_mut(block) else {
+            return true;
+        };
+        if budget.skip > 0 {
+            budget.skip -= 1;
+            return false;
+        }
+        match budget.breaker {
+            Breaker::Open(until) if now < until => false,
+            Breaker::Open(_) => {
+                debug!("Retrying tripped block '{}'", block);
+                budget.breaker = Breaker::HalfOpen;
+                true
+            }
+            Breaker::Closed | Breaker::HalfOpen => true,
+        }
+    }
+
+    /// Check an execution of `block` that took `elapsed` against its budget
+    pub(crate) fn record(&mut self, block: &str, elapsed: Duration, now: Instant, events: &EventLog) {
+        let Some(budget) = self.budgets.get_mut(block) else {
+            return;
+        };
+        let limit = Duration::from_micros(budget.config.max_us);
+        if elapsed <= limit {
+            budget.consecutive = 0;
+            if budget.breaker == Breaker::HalfOpen {
+                budget.breaker = Breaker::Closed;
+                info!("Block '{}' is back within its budget", block);
+                events.publish(EventKind::BlockRecovered { block: block.to_string() });
+            }
+            return;
+        }
+
+        budget.overruns += 1;
+        budget.consecutive = budget.consecutive.saturating_add(1);
+        if budget.consecutive == 1 {
+            warn!("Block '{}' overran its budget: {:?} > {:?}", block, elapsed, limit);
+        } else {
+            debug!("Block '{}' overran its budget {} times in a row: {:?}", block, budget.consecutive, elapsed);
+        }
+
+        match budget.config.on_overrun {
+            OverrunAction::Log => {}
+            OverrunAction::Skip => budget.skip = budget.config.skip_cycles,
+            OverrunAction::Trip => {
+                let retry = budget.breaker == Breaker::HalfOpen;
+                if retry || budget.consecutive >= budget.config.trip_after {
+                    let reset = Duration::from_millis(budget.config.reset_ms);
+                    budget.breaker = Breaker::Open(now + reset);
+                    warn!("Block '{}' tripped after {} overruns, retrying in {:?}", block, budget.consecutive, reset);
+                    if !retry {
+                        events.publish(EventKind::BlockError {
+                            block: block.to_string(),
+                            error: format!(
+                                "tripped after {} executions over its {:?} budget",
+                                budget.consecutive, limit
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Overruns per block since startup, for blocks that overran
+    pub(crate) fn overruns(&self) -> HashMap<String, u64> {
+        self.budgets
+            .iter()
+            .filter(|(_, b)| b.overruns > 0)
+            .map(|(name, b)| (name.clone(), b.overruns))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budgets(budget: &str) -> BlockBudgets {
+        let config: Config = serde_yaml::from_str(&format!(
+            "signals:\n\
+             \x20 - {{ name: a, type: bool }}\n\
+             blocks:\n\
+             \x20 - {{ name: slow, type: NOT, inputs: {{ in: a }}, outputs: {{ out: a }}, budget: {budget} }}\n\
+             \x20 - {{ name: other, type: NOT, inputs: {{ in: a }}, outputs: {{ out: a }} }}\n"
+        ))
+        .unwrap();
+        BlockBudgets::new(&config)
+    }
+
+    const FAST: Duration = Duration::from_micros(50);
+    const SLOW: Duration = Duration::from_micros(500);
+
+    #[test]
+    fn test_skip_after_overrun() {
+        let events = EventLog::default();
+        let now = Instant::now();
+        let mut budgets = budgets("{ max_us: 100, on_overrun: skip, skip_cycles: 2 }");
+
+        assert!(budgets.admit("slow", now));
+        budgets.record("slow", SLOW, now, &events);
+        assert!(!budgets.admit("slow", now));
+        assert!(!budgets.admit("slow", now));
+        assert!(budgets.admit("slow", now));
+        budgets.record("slow", FAST, now, &events);
+        assert!(budgets.admit("slow", now));
+
+        // Blocks without a budget always run and are never counted
+        budgets.record("other", SLOW, now, &events);
+        assert!(budgets.admit("other", now));
+        assert_eq!(budgets.overruns(), HashMap::from([("slow".to_string(), 1)]));
+        assert_eq!(events.last_sequence(), 0);
+    }
+
+    #[test]
+    fn test_trip_and_reset() {
+        let events = EventLog::default();
+        let start = Instant::now();
+        let mut budgets = budgets("{ max_us: 100, on_overrun: trip, trip_after: 2, reset_ms: 1000 }");
+        let at = |ms| start + Duration::from_millis(ms);
+
+        budgets.record("slow", SLOW, at(0), &events);
+        assert!(budgets.admit("slow", at(10)));
+        budgets.record("slow", SLOW, at(10), &events);
+        assert!(!budgets.admit("slow", at(20)));
+        assert!(!budgets.admit("slow", at(1000)));
+        assert!(matches!(events.since(0)[0].kind, EventKind::BlockError { ref block, .. } if block == "slow"));
+
+        // Probation run overruns again and reopens the breaker at once
+        assert!(budgets.admit("slow", at(1010)));
+        budgets.record("slow", SLOW, at(1010), &events);
+        assert!(!budgets.admit("slow", at(1020)));
+
+        assert!(budgets.admit("slow", at(2010)));
+        budgets.record("slow", FAST, at(2010), &events);
+        assert!(budgets.admit("slow", at(2020)));
+        let kinds: Vec<_> = events.since(0).into_iter().map(|e| e.kind.name()).collect();
+        assert_eq!(kinds, ["block_error", "block_recovered"]);
+        assert_eq!(budgets.overruns()["slow"], 3);
+    }
+}
@@ -0,0 +1,529 @@
+// src/engine/debug.rs
+//! Online debugging of the main scan
+//!
+//! A [`Debugger`] from [`Engine::debugger`] lets an operator pause the
+//! running engine, execute single scan cycles while it is paused and see
+//! what every block read and wrote in them, and set watchpoints on signals.
+//!
+//! A watchpoint halts the engine at the end of the scan cycle in which its
+//! signal changed, naming the block that changed it, or none when the value
+//! was written outside the main scan (by a protocol, an operator or a scan
+//! task). The rest of the cycle still runs, so the outputs stay consistent.
+//!
+//! A step executes the main scan once; blocks in scan tasks stay paused.
+//! Steps are served by [`Engine::run`] between scan ticks, so a step takes
+//! up to one scan time to start.
+
+use super::{Engine, EngineControl};
+use crate::{
+    blocks::Block,
+    error::{PlcError, Result},
+    signal::SignalBus,
+    value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Shortest time a step request waits for the scan loop
+const MIN_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A signal and its value at one point of a step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalValue {
+    /// Signal name
+    pub signal: String,
+    /// Value, unset if the signal does not exist
+    pub value: Option<Value>,
+}
+
+/// What one block did in a step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockStep {
+    /// Block name
+    pub block: String,
+    /// Block type
+    pub block_type: String,
+    /// Inputs by block input name, as read before the block executed
+    pub inputs: BTreeMap<String, SignalValue>,
+    /// Outputs by block output name, as written by the block
+    pub outputs: BTreeMap<String, SignalValue>,
+    /// Execution time in microseconds
+    pub duration_us: u64,
+    /// Error of a failed execution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A watched signal changing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchpointHit {
+    /// Signal watched
+    pub signal: String,
+    /// Block that wrote the new value; unset for writes outside the main scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<String>,
+    /// Scan cycle the change was seen in
+    pub scan: u64,
+    /// Value before the change
+    pub from: Option<Value>,
+    /// Value after the change
+    pub to: Option<Value>,
+}
+
+/// Result of executing a single scan cycle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepTrace {
+    /// Number of the scan cycle executed
+    pub scan: u64,
+    /// Blocks of the main scan in execution order
+    pub blocks: Vec<BlockStep>,
+    /// Watched signals that changed in the cycle
+    pub hits: Vec<WatchpointHit>,
+}
+
+/// A signal the engine halts on when it changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watchpoint {
+    /// Signal watched
+    pub signal: String,
+    /// Value last seen
+    pub value: Option<Value>,
+    /// Times the signal changed since the watchpoint was set
+    pub hits: u64,
+}
+
+/// Debugger state reported to operators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugStatus {
+    /// Whether block execution is suspended
+    pub paused: bool,
+    /// Scan cycles completed
+    pub scan: u64,
+    /// Watchpoints set
+    pub watchpoints: Vec<Watchpoint>,
+    /// Changes that halted the engine, until it is resumed
+    pub halted_by: Vec<WatchpointHit>,
+    /// Trace of the last step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_step: Option<StepTrace>,
+}
+
+#[derive(Default)]
+struct Session {
+    watchpoints: BTreeMap<String, Watchpoint>,
+    steps: Vec<oneshot::Sender<StepTrace>>,
+    halted_by: Vec<WatchpointHit>,
+    last_step: Option<StepTrace>,
+}
+
+/// Debugger state shared by the engine and its [`Debugger`] handles
+pub(super) struct DebugState {
+    session: Mutex<Session>,
+    /// Whether scan cycles have to be recorded at all
+    active: AtomicBool,
+    /// Whether the cycle being executed is a step
+    stepping: AtomicBool,
+    step_timeout: Duration,
+}
+
+impl DebugState {
+    pub(super) fn new(scan_time: Duration) -> Self {
+        Self {
+            session: Mutex::new(Session::default()),
+            active: AtomicBool::new(false),
+            stepping: AtomicBool::new(false),
+            step_timeout: MIN_STEP_TIMEOUT.max(scan_time * 10),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Session> {
+        self.session.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update_active(&self, session: &Session) {
+        self.active.store(!session.watchpoints.is_empty() || !session.steps.is_empty(), Ordering::Release);
+    }
+
+    /// Whether a step has been requested
+    pub(super) fn step_requested(&self) -> bool {
+        self.active.load(Ordering::Acquire) && !self.lock().steps.is_empty()
+    }
+}
+
+/// Block input or output names and the signals they are connected to
+type Connections = Vec<(String, String)>;
+
+/// Per-block record of one scan cycle
+pub(super) struct Recorder {
+    scan: u64,
+    /// Block inputs and outputs, recorded only for steps
+    io: Option<HashMap<String, (Connections, Connections)>>,
+    watched: Vec<(String, Option<Value>)>,
+    inputs: BTreeMap<String, SignalValue>,
+    blocks: Vec<BlockStep>,
+    hits: Vec<WatchpointHit>,
+}
+
+fn sorted(map: &HashMap<String, String>) -> Connections {
+    let mut pairs: Vec<_> = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    pairs.sort();
+    pairs
+}
+
+fn read(bus: &SignalBus, pairs: &[(String, String)]) -> BTreeMap<String, SignalValue> {
+    pairs
+        .iter()
+        .map(|(name, signal)| (name.clone(), SignalValue { signal: signal.clone(), value: bus.get(signal) }))
+        .collect()
+}
+
+impl Recorder {
+    /// Check the watched signals for changes, attributing them to `block`
+    fn check(&mut self, bus: &SignalBus, block: Option<&str>) {
+        for (signal, seen) in &mut self.watched {
+            let signal: &str = signal;
+            let value = bus.get(signal);
+            if value != *seen {
+                self.hits.push(WatchpointHit {
+                    signal: signal.to_string(),
+                    block: block.map(str::to_string),
+                    scan: self.scan,
+                    from: seen.clone(),
+                    to: value.clone(),
+                });
+                *seen = value;
+            }
+        }
+    }
+
+    /// Record the inputs of a block about to execute
+    pub(super) fn before(&mut self, block: &dyn Block, bus: &SignalBus) {
+        if let Some((inputs, _)) = self.io.as_ref().and_then(|io| io.get(block.name())) {
+            self.inputs = read(bus, inputs);
+        }
+    }
+
+    /// Record the outputs of an executed block
+    pub(super) fn after(&mut self, block: &dyn Block, bus: &SignalBus, elapsed: Duration, error: Option<&PlcError>) {
+        self.check(bus, Some(block.name()));
+        let Some(io) = &self.io else {
+            return;
+        };
+        let outputs = io.get(block.name()).map(|(_, outputs)| read(bus, outputs)).unwrap_or_default();
+        self.blocks.push(BlockStep {
+            block: block.name().to_string(),
+            block_type: block.block_type().to_string(),
+            inputs: std::mem::take(&mut self.inputs),
+            outputs,
+            duration_us: u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX),
+            error: error.map(ToString::to_string),
+        });
+    }
+}
+
+impl Engine {
+    /// Start recording a scan cycle, if a step or watchpoint needs it
+    ///
+    /// Called before the block lock is taken, as the running configuration
+    /// is locked before the blocks during a reload.
+    pub(super) async fn debug_recorder(&self) -> Option<Recorder> {
+        if !self.debug.active.load(Ordering::Acquire) {
+            return None;
+        }
+        let io = if self.debug.stepping.load(Ordering::Acquire) {
+            let config = self.applied_config.lock().await;
+            Some(
+                config
+                    .blocks
+                    .iter()
+                    .map(|b| (b.name.clone(), (sorted(&b.inputs), sorted(&b.outputs))))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        let watched = self
+            .debug
+            .lock()
+            .watchpoints
+            .values()
+            .map(|w| (w.signal.clone(), w.value.clone()))
+            .collect();
+        let mut recorder = Recorder {
+            scan: self.scan_count() + 1,
+            io,
+            watched,
+            inputs: BTreeMap::new(),
+            blocks: Vec::new(),
+            hits: Vec::new(),
+        };
+        recorder.check(&self.bus, None);
+        Some(recorder)
+    }
+
+    /// Update the watchpoints from a recorded cycle, halting on changes
+    pub(super) async fn finish_recording(&self, recorder: Option<Recorder>) {
+        let Some(recorder) = recorder else {
+            return;
+        };
+        let halt = {
+            let mut session = self.debug.lock();
+            for (signal, value) in &recorder.watched {
+                if let Some(watchpoint) = session.watchpoints.get_mut(signal) {
+                    watchpoint.value.clone_from(value);
+                }
+            }
+            for hit in &recorder.hits {
+                if let Some(watchpoint) = session.watchpoints.get_mut(&hit.signal) {
+                    watchpoint.hits += 1;
+                }
+                let block = hit.block.as_deref().unwrap_or("outside the main scan");
+                warn!(
+                    "Watchpoint: '{}' changed from {:?} to {:?} in scan {} ({})",
+                    hit.signal, hit.from, hit.to, hit.scan, block
+                );
+            }
+            session.halted_by.extend(recorder.hits.iter().cloned());
+            if recorder.io.is_some() {
+                session.last_step = Some(StepTrace { scan: recorder.scan, blocks: recorder.blocks, hits: recorder.hits.clone() });
+            }
+            !recorder.hits.is_empty() && !self.paused.load(Ordering::Acquire)
+        };
+        if halt {
+            self.engine_control().pause().await;
+        }
+    }
+
+    /// Execute one scan cycle for every pending step request
+    pub(super) async fn serve_steps(&self) {
+        let requests = std::mem::take(&mut self.debug.lock().steps);
+        if requests.is_empty() {
+            return;
+        }
+        let scan = self.scan_count() + 1;
+        self.debug.stepping.store(true, Ordering::Release);
+        let result = self.execute_scan_cycle().await;
+        self.debug.stepping.store(false, Ordering::Release);
+        if let Err(e) = result {
+            warn!("Stepped scan cycle failed: {}", e);
+        }
+        let session = self.debug.lock();
+        self.debug.update_active(&session);
+        // A cycle that failed before its blocks ran leaves no trace, and the
+        // requests are dropped
+        if let Some(trace) = session.last_step.as_ref().filter(|t| t.scan == scan) {
+            for request in requests {
+                let _ = request.send(trace.clone());
+            }
+        }
+    }
+
+    /// Get a cloneable handle for debugging the running engine
+    #[must_use]
+    pub fn debugger(&self) -> Debugger {
+        Debugger {
+            state: std::sync::Arc::clone(&self.debug),
+            control: self.engine_control(),
+            bus: self.bus.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// DEBUGGER HANDLE
+// ============================================================================
+
+/// Handle for stepping and watching a running engine
+///
+/// Obtained from [`Engine::debugger`].
+#[derive(Clone)]
+pub struct Debugger {
+    state: std::sync::Arc<DebugState>,
+    control: EngineControl,
+    bus: SignalBus,
+}
+
+impl Debugger {
+    /// Current debugger state
+    #[must_use]
+    pub fn status(&self) -> DebugStatus {
+        let session = self.state.lock();
+        DebugStatus {
+            paused: self.control.is_paused(),
+            scan: self.control.scan_count(),
+            watchpoints: session.watchpoints.values().cloned().collect(),
+            halted_by: session.halted_by.clone(),
+            last_step: session.last_step.clone(),
+        }
+    }
+
+    /// Suspend block execution from the next scan cycle
+    pub async fn pause(&self) {
+        self.control.pause().await;
+    }
+
+    /// Resume block execution, clearing the watchpoint hits that halted it
+    pub async fn resume(&self) {
+        self.state.lock().halted_by.clear();
+        self.control.resume().await;
+    }
+
+    /// Execute a single scan cycle of the paused engine
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Runtime`] if the engine is not paused, or if the
+    /// scan loop does not take up the step in time because it is not
+    /// running.
+    pub async fn step(&self) -> Result<StepTrace> {
+        if !self.control.is_paused() {
+            return Err(PlcError::Runtime("The engine must be paused to step".to_string()));
+        }
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut session = self.state.lock();
+            session.steps.push(tx);
+            self.state.update_active(&session);
+        }
+        if let Ok(Ok(trace)) = tokio::time::timeout(self.state.step_timeout, rx).await {
+            return Ok(trace);
+        }
+        let mut session = self.state.lock();
+        session.steps.retain(|s| !s.is_closed());
+        self.state.update_active(&session);
+        Err(PlcError::Runtime("The scan loop did not execute the step".to_string()))
+    }
+
+    /// Halt the engine whenever `signal` changes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown signal.
+    pub fn watch(&self, signal: &str) -> Result<Watchpoint> {
+        let value = self
+            .bus
+            .get(signal)
+            .ok_or_else(|| PlcError::NotFound(format!("Signal '{signal}'")))?;
+        let mut session = self.state.lock();
+        let watchpoint = session
+            .watchpoints
+            .entry(signal.to_string())
+            .or_insert_with(|| Watchpoint { signal: signal.to_string(), value: Some(value), hits: 0 })
+            .clone();
+        self.state.update_active(&session);
+        info!("Watchpoint set on '{}'", signal);
+        Ok(watchpoint)
+    }
+
+    /// Remove the watchpoint on `signal`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] if the signal is not watched.
+    pub fn unwatch(&self, signal: &str) -> Result<Watchpoint> {
+        let mut session = self.state.lock();
+        let watchpoint = session
+            .watchpoints
+            .remove(signal)
+            .ok_or_else(|| PlcError::NotFound(format!("Watchpoint on '{signal}'")))?;
+        self.state.update_active(&session);
+        info!("Watchpoint on '{}' removed", signal);
+        Ok(watchpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const CONFIG: &str = "\
+scan_time_ms: 10
+max_scan_jitter_ms: 5
+signals:
+  - { name: a, type: bool }
+  - { name: b, type: bool }
+  - { name: both, type: bool }
+  - { name: inverted, type: bool }
+blocks:
+  - { name: gate, type: AND, inputs: { a: a, b: b }, outputs: { out: both } }
+  - { name: invert, type: NOT, inputs: { in: both }, outputs: { out: inverted } }
+";
+
+    /// Run the engine on the current `LocalSet`, as `run` is not `Send`
+    fn start() -> (Debugger, SignalBus, tokio::task::JoinHandle<()>) {
+        let mut engine = Engine::new(serde_yaml::from_str::<Config>(CONFIG).unwrap()).unwrap();
+        let debugger = engine.debugger();
+        let bus = engine.signal_bus().clone();
+        let handle = tokio::task::spawn_local(async move {
+            engine.run().await.unwrap();
+        });
+        (debugger, bus, handle)
+    }
+
+    #[tokio::test]
+    async fn test_step_records_block_inputs_and_outputs() {
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let (debugger, bus, handle) = start();
+                assert!(debugger.step().await.is_err());
+                debugger.pause().await;
+                bus.set("a", Value::Bool(true)).unwrap();
+                bus.set("b", Value::Bool(true)).unwrap();
+
+                let scan = debugger.status().scan;
+                let trace = debugger.step().await.unwrap();
+                assert_eq!(trace.scan, scan + 1);
+                let names: Vec<_> = trace.blocks.iter().map(|b| b.block.as_str()).collect();
+                assert_eq!(names, ["gate", "invert"]);
+                assert_eq!(trace.blocks[0].inputs["a"].value, Some(Value::Bool(true)));
+                assert_eq!(trace.blocks[0].outputs["out"], SignalValue { signal: "both".into(), value: Some(Value::Bool(true)) });
+                assert_eq!(trace.blocks[1].outputs["out"].value, Some(Value::Bool(false)));
+                assert_eq!(debugger.status().scan, scan + 1);
+                assert_eq!(debugger.status().last_step, Some(trace));
+                handle.abort();
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_watchpoint_halts_on_change() {
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let (debugger, bus, handle) = start();
+                assert!(debugger.watch("missing").is_err());
+                debugger.watch("inverted").unwrap();
+                tokio::time::sleep(Duration::from_millis(50)).await;
+
+                // NOT turns `inverted` on in the first scan
+                let status = debugger.status();
+                assert!(status.paused);
+                assert_eq!(status.halted_by.len(), 1);
+                assert_eq!(status.halted_by[0].block.as_deref(), Some("invert"));
+                assert_eq!(status.watchpoints[0].value, Some(Value::Bool(true)));
+
+                // Changes outside the main scan are caught at the next cycle
+                bus.set("inverted", Value::Bool(false)).unwrap();
+                let trace = debugger.step().await.unwrap();
+                let blocks: Vec<_> = trace.hits.iter().map(|h| h.block.as_deref()).collect();
+                assert_eq!(blocks, [None, Some("invert")]);
+
+                debugger.unwatch("inverted").unwrap();
+                debugger.resume().await;
+                assert!(debugger.status().halted_by.is_empty());
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                assert!(!debugger.status().paused);
+                handle.abort();
+            })
+            .await;
+    }
+}
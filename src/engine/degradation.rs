@@ -0,0 +1,236 @@
+// src/engine/degradation.rs
+//! Degraded mode on repeated scan overruns
+//!
+//! When the main scan overruns `scan_time_ms` for `enter_after` scans in a
+//! row, the engine sheds load instead of only logging the overruns:
+//!
+//! - blocks below `shed_below_priority`, and blocks in one of the
+//!   `shed_phases`, stop executing and hold their outputs
+//! - the `stretch_tasks` run only every `stretch_factor`-th cycle
+//!
+//! ```yaml
+//! degradation:
+//!   enter_after: 5
+//!   exit_after: 50
+//!   recover_percent: 80
+//!   shed_below_priority: 0
+//!   shed_phases: [diagnostics]
+//!   stretch_tasks: [reporting]
+//!   stretch_factor: 4
+//! ```
+//!
+//! The engine returns to normal after `exit_after` scans in a row that take
+//! less than `recover_percent` of the scan time, measured with the load
+//! still shed so the mode does not flap. Shedding applies to sequentially
+//! executed blocks, like block budgets. The mode is published as the
+//! boolean signal `engine.degraded`.
+
+use crate::config::{Config, DegradationConfig};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Signal holding whether the engine is degraded
+pub const DEGRADED_SIGNAL: &str = "engine.degraded";
+
+#[derive(Debug, Default)]
+struct Policy {
+    config: Option<DegradationConfig>,
+    /// Blocks that stop executing while degraded
+    shed: HashSet<String>,
+    /// Consecutive overrunning scans
+    overruns: u32,
+    /// Consecutive scans below the recovery threshold
+    recovered: u32,
+}
+
+/// Degraded mode of an engine, shared by the main scan and the scan tasks
+#[derive(Debug, Default)]
+pub(crate) struct Degradation {
+    degraded: AtomicBool,
+    policy: Mutex<Policy>,
+}
+
+pub(crate) type SharedDegradation = Arc<Degradation>;
+
+impl Degradation {
+    /// Degraded mode following the `degradation` section of `config`
+    pub(crate) fn new(config: &Config, bus: &SignalBus) -> Self {
+        let degradation = Self::default();
+        degradation.configure(config);
+        if config.degradation.is_some() {
+            degradation.publish(bus);
+        }
+        degradation
+    }
+
+    /// Follow a reloaded configuration, keeping the current mode
+    pub(crate) fn configure(&self, config: &Config) {
+        let mut policy = self.policy.lock().unwrap_or_else(PoisonError::into_inner);
+        policy.shed = config
+            .degradation
+            .as_ref()
+            .map(|degradation| {
+                config
+                    .blocks
+                    .iter()
+                    .filter(|block| {
+                        degradation.shed_below_priority.is_some_and(|min| block.priority < min)
+                            || degradation.shed_phases.contains(&block.phase())
+                    })
+                    .map(|block| block.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        policy.config.clone_from(&config.degradation);
+        if policy.config.is_none() {
+            self.degraded.store(false, Ordering::Release);
+        }
+    }
+
+    /// Whether the engine is degraded
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
+
+    /// Whether `block` is shed in the current mode
+    pub(crate) fn sheds(&self, block: &str) -> bool {
+        self.is_degraded() && self.policy.lock().unwrap_or_else(PoisonError::into_inner).shed.contains(block)
+    }
+
+    /// Run task `task` in every how-many-th cycle in the current mode
+    pub(crate) fn divider(&self, task: &str) -> u32 {
+        if !self.is_degraded() {
+            return 1;
+        }
+        let policy = self.policy.lock().unwrap_or_else(PoisonError::into_inner);
+        policy
+            .config
+            .as_ref()
+            .filter(|config| config.stretch_tasks.iter().any(|t| t == task))
+            .map_or(1, |config| config.stretch_factor.max(1))
+    }
+
+    /// Account for a main scan that took `elapsed` of `target`, switching
+    /// the mode when the policy says so
+    pub(crate) fn observe(&self, elapsed: Duration, target: Duration, bus: &SignalBus) {
+        let mut policy = self.policy.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(config) = policy.config.clone() else {
+            return;
+        };
+        if elapsed > target {
+            policy.overruns = policy.overruns.saturating_add(1);
+        } else {
+            policy.overruns = 0;
+        }
+        if elapsed < target * config.recover_percent / 100 {
+            policy.recovered = policy.recovered.saturating_add(1);
+        } else {
+            policy.recovered = 0;
+        }
+
+        let degraded = self.is_degraded();
+        if !degraded && policy.overruns >= config.enter_after {
+            warn!(
+                "Scan overran {:?} {} times in a row, degrading: shedding {} blocks",
+                target,
+                policy.overruns,
+                policy.shed.len()
+            );
+            policy.recovered = 0;
+            self.degraded.store(true, Ordering::Release);
+        } else if degraded && policy.recovered >= config.exit_after {
+            info!("Scan back within {}% of {:?}, leaving degraded mode", config.recover_percent, target);
+            policy.overruns = 0;
+            self.degraded.store(false, Ordering::Release);
+        } else {
+            return;
+        }
+        drop(policy);
+        self.publish(bus);
+    }
+
+    fn publish(&self, bus: &SignalBus) {
+        if let Err(e) = bus.set(DEGRADED_SIGNAL, Value::Bool(self.is_degraded())) {
+            warn!("Failed to publish degraded mode: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degradation(policy: &str) -> (Degradation, SignalBus) {
+        let config: Config = serde_yaml::from_str(&format!(
+            "signals:\n\
+             \x20 - {{ name: a, type: bool }}\n\
+             blocks:\n\
+             \x20 - {{ name: control, type: NOT, priority: 5, inputs: {{ in: a }}, outputs: {{ out: a }} }}\n\
+             \x20 - {{ name: trend, type: NOT, priority: -1, inputs: {{ in: a }}, outputs: {{ out: a }} }}\n\
+             \x20 - {{ name: watch, type: NOT, phase: diagnostics, inputs: {{ in: a }}, outputs: {{ out: a }} }}\n\
+             tasks:\n\
+             \x20 - {{ name: reporting, interval_ms: 1000 }}\n\
+             degradation: {policy}\n"
+        ))
+        .unwrap();
+        config.validate().unwrap();
+        let bus = SignalBus::new();
+        (Degradation::new(&config, &bus), bus)
+    }
+
+    const TARGET: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_sheds_load_after_repeated_overruns() {
+        let (degradation, bus) = degradation(
+            "{ enter_after: 3, shed_below_priority: 0, shed_phases: [diagnostics], \
+             stretch_tasks: [reporting], stretch_factor: 4 }",
+        );
+        assert_eq!(bus.get(DEGRADED_SIGNAL), Some(Value::Bool(false)));
+
+        for _ in 0..2 {
+            degradation.observe(Duration::from_millis(12), TARGET, &bus);
+        }
+        // An on-time scan restarts the count
+        degradation.observe(Duration::from_millis(9), TARGET, &bus);
+        degradation.observe(Duration::from_millis(12), TARGET, &bus);
+        assert!(!degradation.is_degraded());
+        assert!(!degradation.sheds("trend"));
+        assert_eq!(degradation.divider("reporting"), 1);
+
+        for _ in 0..2 {
+            degradation.observe(Duration::from_millis(12), TARGET, &bus);
+        }
+        assert!(degradation.is_degraded());
+        assert_eq!(bus.get(DEGRADED_SIGNAL), Some(Value::Bool(true)));
+        assert!(degradation.sheds("trend"));
+        assert!(degradation.sheds("watch"));
+        assert!(!degradation.sheds("control"));
+        assert_eq!(degradation.divider("reporting"), 4);
+        assert_eq!(degradation.divider("other"), 1);
+    }
+
+    #[test]
+    fn test_recovers_below_threshold() {
+        let (degradation, bus) =
+            degradation("{ enter_after: 1, exit_after: 2, recover_percent: 50, shed_below_priority: 0 }");
+        degradation.observe(Duration::from_millis(11), TARGET, &bus);
+        assert!(degradation.is_degraded());
+
+        // On time but above half the scan time does not count as recovered
+        for _ in 0..5 {
+            degradation.observe(Duration::from_millis(8), TARGET, &bus);
+        }
+        assert!(degradation.is_degraded());
+
+        degradation.observe(Duration::from_millis(4), TARGET, &bus);
+        degradation.observe(Duration::from_millis(4), TARGET, &bus);
+        assert!(!degradation.is_degraded());
+        assert_eq!(bus.get(DEGRADED_SIGNAL), Some(Value::Bool(false)));
+    }
+}
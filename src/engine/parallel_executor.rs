@@ -1,8 +1,9 @@
-use crate::{Block, Result, SignalBus, PlcError};
+use super::budget::{self, SharedBudgets};
+use super::degradation::SharedDegradation;
+use crate::{blocks::Block, config::{BlockPhase, Config}, events::EventLog, SignalBus};
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::task::JoinSet;
+use std::sync::{PoisonError, RwLock};
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 #[derive(Debug, Clone)]
@@ -16,7 +17,11 @@ pub struct BlockDependencyGraph {
 }
 
 impl BlockDependencyGraph {
-    pub fn analyze(blocks: &[Box<dyn Block + Send + Sync>]) -> Self {
+    /// Analyze `blocks`, which run in the phases given by `phases`
+    /// 
+    /// Blocks missing from `phases`, such as blocks added at runtime, run in
+    /// the default phase.
+    pub fn analyze(blocks: &[Box<dyn Block>], phases: &HashMap<String, BlockPhase>) -> Self {
         let mut graph = Self {
             dependents: HashMap::new(),
             dependencies: HashMap::new(),
@@ -53,27 +58,40 @@ impl BlockDependencyGraph {
         }
         
         // Calculate parallel execution groups using topological sort
-        graph.calculate_parallel_groups(blocks);
+        for phase in BlockPhase::ALL {
+            let members: Vec<&dyn Block> = blocks
+                .iter()
+                .map(AsRef::as_ref)
+                .filter(|block| phases.get(block.name()).copied().unwrap_or_default() == phase)
+                .collect();
+            graph.calculate_parallel_groups(&members);
+        }
+        debug!("Calculated {} parallel execution groups", graph.parallel_groups.len());
         
         graph
     }
     
-    fn calculate_parallel_groups(&mut self, blocks: &[Box<dyn Block + Send + Sync>]) {
+    /// Append the groups of the blocks of one phase
+    /// 
+    /// Producers in other phases do not hold a block back: earlier phases
+    /// have already run, and outputs of later phases are read from the
+    /// previous cycle, as in sequential execution.
+    fn calculate_parallel_groups(&mut self, blocks: &[&dyn Block]) {
+        let in_phase: HashSet<&str> = blocks.iter().map(|block| block.name()).collect();
         let mut visited = HashSet::new();
-        let mut groups = Vec::new();
         let mut current_level = Vec::new();
         
-        // Find blocks with no dependencies
+        // Find blocks with no dependencies in this phase
         for block in blocks {
             let name = block.name().to_string();
-            if self.dependencies[&name].is_empty() && block.is_parallelizable() {
+            if block.is_parallelizable() && !self.dependencies[&name].iter().any(|dep| in_phase.contains(dep.as_str())) {
                 current_level.push(name);
             }
         }
         
         // Process levels
         while !current_level.is_empty() {
-            groups.push(current_level.clone());
+            self.parallel_groups.push(current_level.clone());
             
             for name in &current_level {
                 visited.insert(name.clone());
@@ -86,7 +104,7 @@ impl BlockDependencyGraph {
                 let name = block.name().to_string();
                 if !visited.contains(&name) && block.is_parallelizable() {
                     let deps = &self.dependencies[&name];
-                    if deps.iter().all(|dep| visited.contains(dep)) {
+                    if deps.iter().all(|dep| visited.contains(dep) || !in_phase.contains(dep.as_str())) {
                         next_level.push(name);
                     }
                 }
@@ -99,92 +117,182 @@ impl BlockDependencyGraph {
         for block in blocks {
             let name = block.name().to_string();
             if !block.is_parallelizable() || !visited.contains(&name) {
-                groups.push(vec![name]);
+                self.parallel_groups.push(vec![name]);
             }
         }
-        
-        self.parallel_groups = groups;
-        debug!("Calculated {} parallel execution groups", groups.len());
+    }
+}
+
+/// Load shedding and execution budgets checked for every block the
+/// executor runs
+#[derive(Clone)]
+pub(crate) struct BlockGate {
+    pub(crate) budgets: SharedBudgets,
+    pub(crate) degradation: SharedDegradation,
+    pub(crate) events: EventLog,
+    /// Start of the scan cycle
+    pub(crate) scan_start: Instant,
+}
+
+impl BlockGate {
+    /// Whether `block` executes in this cycle
+    fn admit(&self, block: &str) -> bool {
+        !self.degradation.sheds(block) && budget::lock(&self.budgets).admit(block, self.scan_start)
+    }
+    
+    /// Check an execution of `block` against its budget
+    fn record(&self, block: &str, elapsed: Duration) {
+        budget::lock(&self.budgets).record(block, elapsed, Instant::now(), &self.events);
     }
 }
 
 pub struct ParallelExecutor {
-    dependency_graph: BlockDependencyGraph,
-    #[cfg(feature = "thread-pool")]
-    thread_pool: Option<Arc<tokio::runtime::Runtime>>,
+    dependency_graph: RwLock<BlockDependencyGraph>,
+    /// Phase of each configured block
+    phases: RwLock<HashMap<String, BlockPhase>>,
 }
 
 impl ParallelExecutor {
-    pub fn new(blocks: &[Box<dyn Block + Send + Sync>]) -> Self {
-        let dependency_graph = BlockDependencyGraph::analyze(blocks);
-        
-        #[cfg(feature = "thread-pool")]
-        let thread_pool = Some(Arc::new(
-            tokio::runtime::Builder::new_multi_thread()
-                .worker_threads(num_cpus::get())
-                .thread_name("petra-block-executor")
-                .enable_all()
-                .build()
-                .unwrap()
-        ));
-        
+    /// Executor for `blocks`, configured in `config`
+    /// 
+    /// Phases run one after the other as in sequential execution; only
+    /// blocks of the same phase share a group.
+    pub fn new(blocks: &[Box<dyn Block>], config: &Config) -> Self {
+        let phases = phases(config);
         Self {
-            dependency_graph,
-            #[cfg(feature = "thread-pool")]
-            thread_pool,
+            dependency_graph: RwLock::new(BlockDependencyGraph::analyze(blocks, &phases)),
+            phases: RwLock::new(phases),
         }
     }
     
-    pub async fn execute_parallel(
-        &self,
-        blocks: Arc<Mutex<Vec<Box<dyn Block + Send + Sync>>>>,
-        bus: &SignalBus,
-    ) -> Result<()> {
-        let blocks_guard = blocks.lock().await;
-        let block_map: HashMap<String, usize> = blocks_guard
-            .iter()
-            .enumerate()
-            .map(|(i, b)| (b.name().to_string(), i))
-            .collect();
-        drop(blocks_guard);
-        
-        for group in &self.dependency_graph.parallel_groups {
-            if group.len() == 1 {
-                // Execute single block directly
-                let mut blocks_guard = blocks.lock().await;
-                if let Some(&idx) = block_map.get(&group[0]) {
-                    if let Err(e) = blocks_guard[idx].execute(bus) {
-                        warn!("Block '{}' execution failed: {}", group[0], e);
-                    }
-                }
+    /// Analyze the dependencies again after blocks were added, removed or
+    /// recreated
+    pub fn rebuild(&self, blocks: &[Box<dyn Block>]) {
+        let graph = BlockDependencyGraph::analyze(blocks, &self.phases.read().unwrap_or_else(PoisonError::into_inner));
+        *self.dependency_graph.write().unwrap_or_else(PoisonError::into_inner) = graph;
+    }
+    
+    /// Take the block phases of a reloaded `config` and rebuild
+    pub fn configure(&self, blocks: &[Box<dyn Block>], config: &Config) {
+        *self.phases.write().unwrap_or_else(PoisonError::into_inner) = phases(config);
+        self.rebuild(blocks);
+    }
+    
+    /// Run `blocks` group by group, the blocks of a group on parallel threads
+    /// 
+    /// The caller holds the block lock for the whole cycle, so a reload
+    /// cannot swap the blocks between two groups.
+    pub fn execute_parallel(&self, blocks: &mut [Box<dyn Block>], bus: &SignalBus, gate: &BlockGate) {
+        let graph = self.dependency_graph.read().unwrap_or_else(PoisonError::into_inner);
+        for group in &graph.parallel_groups {
+            let admitted: HashSet<&str> = group.iter().map(String::as_str).filter(|name| gate.admit(name)).collect();
+            let mut members: Vec<&mut dyn Block> = blocks
+                .iter_mut()
+                .map(AsMut::as_mut)
+                .filter(|block| admitted.contains(block.name()))
+                .collect();
+            if let [block] = members.as_mut_slice() {
+                run_block(&mut **block, bus, gate);
             } else {
-                // Execute group in parallel
-                let mut join_set = JoinSet::new();
-                
-                for block_name in group {
-                    if let Some(&idx) = block_map.get(block_name) {
-                        let blocks_clone = Arc::clone(&blocks);
-                        let bus_clone = bus.clone();
-                        let block_name_clone = block_name.clone();
-                        
-                        join_set.spawn(async move {
-                            let mut blocks_guard = blocks_clone.lock().await;
-                            if let Err(e) = blocks_guard[idx].execute(&bus_clone) {
-                                warn!("Block '{}' execution failed: {}", block_name_clone, e);
-                            }
-                        });
-                    }
-                }
-                
-                // Wait for all blocks in group to complete
-                while let Some(result) = join_set.join_next().await {
-                    if let Err(e) = result {
-                        warn!("Parallel block execution task failed: {}", e);
+                std::thread::scope(|scope| {
+                    for block in members {
+                        scope.spawn(move || run_block(block, bus, gate));
                     }
-                }
+                });
             }
         }
-        
-        Ok(())
+    }
+}
+
+fn phases(config: &Config) -> HashMap<String, BlockPhase> {
+    config.blocks.iter().map(|block| (block.name.clone(), block.phase())).collect()
+}
+
+fn run_block(block: &mut dyn Block, bus: &SignalBus, gate: &BlockGate) {
+    let block_start = Instant::now();
+    let result = block.execute(bus);
+    gate.record(block.name(), block_start.elapsed());
+    if let Err(e) = result {
+        warn!("Block '{}' execution failed: {}", block.name(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, engine::{Engine, EngineConfig}, value::Value};
+
+    fn engine(block: &str, extra: &str) -> Engine {
+        let config: Config = serde_yaml::from_str(&format!(
+            "signals:\n\
+             \x20 - {{ name: start, type: bool }}\n\
+             \x20 - {{ name: done, type: bool }}\n\
+             blocks:\n\
+             \x20 - {{ name: invert, type: NOT, inputs: {{ in: start }}, outputs: {{ out: done }}{block} }}\n\
+             {extra}"
+        ))
+        .unwrap();
+        let engine_config = EngineConfig { parallel_execution: true, ..Default::default() };
+        Engine::new_with_config(config, engine_config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_budget_skips_block() {
+        let engine = engine(", budget: { max_us: 1000000, on_overrun: skip, skip_cycles: 1 }", "");
+        let bus = engine.signal_bus().clone();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+
+        // An overrun skips the next cycle
+        budget::lock(&engine.budgets).record("invert", Duration::from_secs(2), Instant::now(), &engine.events);
+        bus.set("done", Value::Bool(false)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(false)));
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+    }
+
+    #[tokio::test]
+    async fn test_degradation_sheds_block() {
+        let engine = engine(
+            ", priority: -1",
+            "degradation: { enter_after: 1, exit_after: 1, shed_below_priority: 0 }\n",
+        );
+        let bus = engine.signal_bus().clone();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+
+        // Shed while degraded, back once recovered
+        engine.degradation.observe(Duration::from_secs(1), Duration::from_millis(10), &bus);
+        bus.set("done", Value::Bool(false)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(false)));
+        engine.degradation.observe(Duration::ZERO, Duration::from_millis(10), &bus);
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+    }
+
+    #[tokio::test]
+    async fn test_groups_follow_phases() {
+        let config: Config = serde_yaml::from_str(
+            "signals:\n\
+             \x20 - { name: start, type: bool }\n\
+             \x20 - { name: mid, type: bool }\n\
+             \x20 - { name: done, type: bool }\n\
+             blocks:\n\
+             \x20 - { name: control, type: NOT, inputs: { in: start }, outputs: { out: mid } }\n\
+             \x20 - { name: input, type: NOT, phase: input, inputs: { in: mid }, outputs: { out: done } }\n",
+        )
+        .unwrap();
+        let engine_config = EngineConfig { parallel_execution: true, ..Default::default() };
+        let engine = Engine::new_with_config(config, engine_config).unwrap();
+        let bus = engine.signal_bus().clone();
+
+        // The input phase runs first and sees the previous cycle's `mid`
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("mid"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(false)));
     }
 }
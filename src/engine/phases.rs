@@ -0,0 +1,311 @@
+// src/engine/phases.rs
+//! Scan cycle phase timings
+//!
+//! Every scan cycle of the main scan is split into four phases:
+//!
+//! - `inputs` - copying the process image in from the signal bus
+//! - `blocks` - executing the blocks
+//! - `outputs` - writing changed outputs back to the bus
+//! - `housekeeping` - failure tracking, debugger traces and statistics
+//!
+//! With the `enhanced-monitoring` feature each phase, the whole cycle and
+//! the scan jitter are recorded into HDR histograms with microsecond
+//! resolution, so tail latencies show up even when the averages in
+//! [`EngineStats`](super::EngineStats) look fine. The histograms are read
+//! with [`CycleTimings::breakdown`], served by `/api/engine/cycle` (JSON) and
+//! `/api/engine/cycle/metrics` (Prometheus summaries) and shown by
+//! `petra engine stats`. Without the feature recording does nothing, so the
+//! scan loop needs no feature gates.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::time::Duration;
+
+#[cfg(feature = "enhanced-monitoring")]
+use hdrhistogram::Histogram;
+#[cfg(feature = "enhanced-monitoring")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "enhanced-monitoring")]
+use std::time::Instant;
+
+/// Phase of a scan cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanPhase {
+    Inputs,
+    Blocks,
+    Outputs,
+    Housekeeping,
+}
+
+impl ScanPhase {
+    /// All phases in cycle order
+    pub const ALL: [Self; 4] = [Self::Inputs, Self::Blocks, Self::Outputs, Self::Housekeeping];
+
+    /// Name used in reports and metric labels
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Inputs => "inputs",
+            Self::Blocks => "blocks",
+            Self::Outputs => "outputs",
+            Self::Housekeeping => "housekeeping",
+        }
+    }
+}
+
+/// Time spent in each phase of one cycle
+#[derive(Debug)]
+pub(crate) struct PhaseClock {
+    #[cfg(feature = "enhanced-monitoring")]
+    last: Instant,
+    #[cfg(feature = "enhanced-monitoring")]
+    laps: [Duration; 4],
+}
+
+impl PhaseClock {
+    /// Start timing a cycle
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "enhanced-monitoring")]
+            last: Instant::now(),
+            #[cfg(feature = "enhanced-monitoring")]
+            laps: [Duration::ZERO; 4],
+        }
+    }
+
+    /// Charge the time since the previous lap to `phase`
+    #[cfg_attr(not(feature = "enhanced-monitoring"), allow(clippy::unused_self))]
+    pub(crate) fn lap(&mut self, phase: ScanPhase) {
+        #[cfg(feature = "enhanced-monitoring")]
+        {
+            let now = Instant::now();
+            self.laps[phase as usize] += now - self.last;
+            self.last = now;
+        }
+        #[cfg(not(feature = "enhanced-monitoring"))]
+        let _ = phase;
+    }
+}
+
+/// Highest value recorded, in microseconds; longer cycles count as this
+#[cfg(feature = "enhanced-monitoring")]
+const MAX_US: u64 = 60_000_000;
+
+#[cfg(feature = "enhanced-monitoring")]
+#[derive(Debug)]
+struct Histograms {
+    phases: [Histogram<u64>; 4],
+    cycle: Histogram<u64>,
+    jitter: Histogram<u64>,
+}
+
+#[cfg(feature = "enhanced-monitoring")]
+impl Histograms {
+    fn new() -> Self {
+        let histogram = || Histogram::new_with_max(MAX_US, 3).expect("valid histogram bounds");
+        Self {
+            phases: std::array::from_fn(|_| histogram()),
+            cycle: histogram(),
+            jitter: histogram(),
+        }
+    }
+}
+
+#[cfg(feature = "enhanced-monitoring")]
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Phase, cycle and jitter histograms of an engine's main scan
+///
+/// Cloning shares the histograms.
+#[derive(Debug, Clone)]
+pub struct CycleTimings {
+    #[cfg(feature = "enhanced-monitoring")]
+    histograms: Arc<Mutex<Histograms>>,
+}
+
+#[cfg_attr(not(feature = "enhanced-monitoring"), allow(clippy::derivable_impls))]
+impl Default for CycleTimings {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "enhanced-monitoring")]
+            histograms: Arc::new(Mutex::new(Histograms::new())),
+        }
+    }
+}
+
+impl CycleTimings {
+    /// Record a finished cycle and the jitter of its start
+    #[cfg_attr(not(feature = "enhanced-monitoring"), allow(clippy::unused_self))]
+    pub(crate) fn record(&self, clock: &PhaseClock, jitter: Duration) {
+        #[cfg(feature = "enhanced-monitoring")]
+        {
+            let mut histograms = self.histograms.lock().unwrap_or_else(PoisonError::into_inner);
+            for (histogram, lap) in histograms.phases.iter_mut().zip(clock.laps) {
+                histogram.saturating_record(micros(lap));
+            }
+            histograms.cycle.saturating_record(micros(clock.laps.iter().sum()));
+            histograms.jitter.saturating_record(micros(jitter));
+        }
+        #[cfg(not(feature = "enhanced-monitoring"))]
+        let _ = (clock, jitter);
+    }
+
+    /// Percentiles of everything recorded so far
+    #[must_use]
+    pub fn breakdown(&self) -> CycleBreakdown {
+        #[cfg(feature = "enhanced-monitoring")]
+        {
+            let histograms = self.histograms.lock().unwrap_or_else(PoisonError::into_inner);
+            CycleBreakdown {
+                cycles: histograms.cycle.len(),
+                phases: ScanPhase::ALL
+                    .iter()
+                    .zip(&histograms.phases)
+                    .map(|(phase, histogram)| PhaseStats::from_histogram(phase.as_str(), histogram))
+                    .collect(),
+                cycle: PhaseStats::from_histogram("cycle", &histograms.cycle),
+                jitter: PhaseStats::from_histogram("jitter", &histograms.jitter),
+            }
+        }
+        #[cfg(not(feature = "enhanced-monitoring"))]
+        CycleBreakdown::default()
+    }
+}
+
+/// Distribution of one phase, the whole cycle or the jitter, in microseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseStats {
+    pub name: String,
+    pub count: u64,
+    pub mean_us: f64,
+    pub min_us: u64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+}
+
+impl PhaseStats {
+    #[cfg(feature = "enhanced-monitoring")]
+    fn from_histogram(name: &str, histogram: &Histogram<u64>) -> Self {
+        Self {
+            name: name.to_string(),
+            count: histogram.len(),
+            mean_us: histogram.mean(),
+            min_us: histogram.min(),
+            p50_us: histogram.value_at_quantile(0.5),
+            p90_us: histogram.value_at_quantile(0.9),
+            p99_us: histogram.value_at_quantile(0.99),
+            p999_us: histogram.value_at_quantile(0.999),
+            max_us: histogram.max(),
+        }
+    }
+
+    /// Quantiles in the order they are reported
+    fn quantiles(&self) -> [(&'static str, u64); 5] {
+        [
+            ("0.5", self.p50_us),
+            ("0.9", self.p90_us),
+            ("0.99", self.p99_us),
+            ("0.999", self.p999_us),
+            ("1", self.max_us),
+        ]
+    }
+}
+
+/// Cycle time breakdown of the main scan since startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CycleBreakdown {
+    /// Cycles recorded
+    pub cycles: u64,
+    /// Per phase, in cycle order
+    pub phases: Vec<PhaseStats>,
+    /// Whole cycles
+    pub cycle: PhaseStats,
+    /// Deviation of the cycle start interval from the scan time
+    pub jitter: PhaseStats,
+}
+
+impl CycleBreakdown {
+    /// Summaries in the Prometheus text exposition format
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let families = [
+            ("petra_scan_phase_seconds", "Scan cycle time per phase", "phase", self.phases.iter().collect::<Vec<_>>()),
+            ("petra_scan_cycle_seconds", "Scan cycle time", "", vec![&self.cycle]),
+            ("petra_scan_jitter_seconds", "Scan start jitter", "", vec![&self.jitter]),
+        ];
+        for (name, help, label, series) in families {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} summary");
+            for stats in series {
+                let labels = if label.is_empty() { String::new() } else { format!("{label}=\"{}\",", stats.name) };
+                for (quantile, us) in stats.quantiles() {
+                    let _ = writeln!(out, "{name}{{{labels}quantile=\"{quantile}\"}} {}", seconds(us));
+                }
+                let labels = labels.trim_end_matches(',');
+                let braces = if labels.is_empty() { String::new() } else { format!("{{{labels}}}") };
+                #[allow(clippy::cast_precision_loss)]
+                let sum = stats.mean_us * stats.count as f64 / 1e6;
+                let _ = writeln!(out, "{name}_sum{braces} {sum}");
+                let _ = writeln!(out, "{name}_count{braces} {}", stats.count);
+            }
+        }
+        out
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn seconds(us: u64) -> f64 {
+    us as f64 / 1e6
+}
+
+#[cfg(all(test, feature = "enhanced-monitoring"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_percentiles() {
+        let timings = CycleTimings::default();
+        for i in 1..=100u64 {
+            let mut clock = PhaseClock::start();
+            clock.laps = [
+                Duration::from_micros(10),
+                Duration::from_micros(i * 100),
+                Duration::from_micros(5),
+                Duration::from_micros(1),
+            ];
+            timings.record(&clock, Duration::from_micros(i));
+        }
+
+        let breakdown = timings.breakdown();
+        assert_eq!(breakdown.cycles, 100);
+        let blocks = &breakdown.phases[1];
+        assert_eq!(blocks.name, "blocks");
+        assert!((10_000..=10_010).contains(&blocks.max_us));
+        assert!((4_990..=5_010).contains(&blocks.p50_us));
+        assert!((9_890..=9_910).contains(&blocks.p99_us));
+        assert_eq!(breakdown.phases[0].p99_us, 10);
+        assert_eq!(breakdown.jitter.max_us, 100);
+    }
+
+    #[test]
+    fn test_prometheus_summaries() {
+        let timings = CycleTimings::default();
+        let mut clock = PhaseClock::start();
+        clock.lap(ScanPhase::Inputs);
+        clock.laps[1] = Duration::from_millis(2);
+        timings.record(&clock, Duration::ZERO);
+
+        let text = timings.breakdown().to_prometheus();
+        assert!(text.contains("# TYPE petra_scan_phase_seconds summary"));
+        assert!(text.contains("petra_scan_phase_seconds{phase=\"blocks\",quantile=\"0.99\"} 0.002"));
+        assert!(text.contains("petra_scan_phase_seconds_count{phase=\"housekeeping\"} 1"));
+        assert!(text.contains("petra_scan_cycle_seconds_count 1"));
+        assert!(text.contains("petra_scan_jitter_seconds{quantile=\"0.5\"} 0"));
+    }
+}
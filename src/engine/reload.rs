@@ -0,0 +1,381 @@
+// src/engine/reload.rs
+//! Hot configuration reload
+//!
+//! A reload compares the new configuration with the one the engine runs
+//! and changes only what differs:
+//!
+//! - signals new in the configuration are created with their initial
+//!   value, signals no longer configured are removed, and changed signals
+//!   get their new write policy; their value is kept unless the type or
+//!   enum values changed
+//! - unchanged blocks keep running with their state; changed blocks are
+//!   recreated and take over the saved state of the old instance where
+//!   the block type supports it; new blocks are added and dropped ones
+//!   removed; the parallel executor re-analyzes the dependencies of the
+//!   new block set
+//!
+//! Everything that can fail, from validation to creating the blocks, runs
+//! before the engine is touched. The changes are then applied with every
+//! block list locked, so they land between scan cycles, and the signal
+//! changes are undone if one of them fails. A reload either takes effect
+//! completely or leaves the engine as it was.
+
+use super::{tasks::TaskSplit, Engine, ReloadHandle};
+use crate::{
+    config::{Config, SignalConfig},
+    error::{PlcError, Result},
+    events::EventKind,
+    signal::{SignalBus, WritePolicy},
+    value::Value,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info, warn};
+
+/// What a hot reload changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReloadReport {
+    /// Signals created
+    pub signals_added: Vec<String>,
+    /// Signals whose configuration changed
+    pub signals_updated: Vec<String>,
+    /// Signals removed from the bus
+    pub signals_removed: Vec<String>,
+    /// Blocks created
+    pub blocks_added: Vec<String>,
+    /// Blocks recreated with a changed configuration
+    pub blocks_updated: Vec<String>,
+    /// Blocks no longer running
+    pub blocks_removed: Vec<String>,
+    /// Blocks running after the reload
+    pub blocks: usize,
+}
+
+impl ReloadReport {
+    /// Whether the reload changed nothing
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.signals_added.is_empty()
+            && self.signals_updated.is_empty()
+            && self.signals_removed.is_empty()
+            && self.blocks_added.is_empty()
+            && self.blocks_updated.is_empty()
+            && self.blocks_removed.is_empty()
+    }
+}
+
+/// Whether two configuration entries are the same
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    matches!((serde_yaml::to_value(a), serde_yaml::to_value(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// A signal change and how to undo it
+struct SignalChange<'a> {
+    config: &'a SignalConfig,
+    /// Write the initial value instead of keeping the current one
+    reset: bool,
+    previous: Option<(Value, Option<WritePolicy>)>,
+}
+
+impl ReloadHandle {
+    /// Apply the differences between `config` and the running
+    /// configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration fails validation, changes the
+    /// scan tasks, exceeds the license, or a block or signal cannot be
+    /// created. The engine is
+    /// unchanged on error.
+    pub async fn reload(&self, config: &Config) -> Result<ReloadReport> {
+        config.validate()?;
+        super::tasks::check_unchanged(config, &self.tasks, self.main_image.as_ref())?;
+        #[cfg(feature = "licensing")]
+        if let Some(entitlement) = &self.entitlement {
+            entitlement.enforce(config)?;
+        }
+        let mut running = self.config.lock().await;
+        let mut report = ReloadReport::default();
+
+        // Prepare: create the blocks and check the new signal values on a
+        // scratch bus
+        let fresh = Engine::create_blocks(config)?;
+        let old_signals: HashMap<&str, &SignalConfig> =
+            running.signals.iter().map(|s| (s.name.as_str(), s)).collect();
+        let mut changes = Vec::new();
+        for signal in &config.signals {
+            match old_signals.get(signal.name.as_str()) {
+                None => {
+                    report.signals_added.push(signal.name.clone());
+                    changes.push(SignalChange { config: signal, reset: !self.bus.exists(&signal.name), previous: None });
+                }
+                Some(old) if !same(*old, signal) => {
+                    report.signals_updated.push(signal.name.clone());
+                    let reset = old.signal_type != signal.signal_type || old.values != signal.values;
+                    changes.push(SignalChange { config: signal, reset, previous: None });
+                }
+                Some(_) => {}
+            }
+        }
+        let new_names: HashSet<&str> = config.signals.iter().map(|s| s.name.as_str()).collect();
+        report.signals_removed = running
+            .signals
+            .iter()
+            .filter(|s| !new_names.contains(s.name.as_str()))
+            .map(|s| s.name.clone())
+            .collect();
+        let scratch = SignalBus::new();
+        Engine::initialize_signals(
+            &scratch,
+            &Config {
+                signals: changes.iter().map(|c| c.config.clone()).collect(),
+                ..config.clone()
+            },
+        )?;
+
+        // Apply between scan cycles
+        let mut locked = Vec::with_capacity(self.tasks.len() + 1);
+        locked.push(self.blocks.lock().await);
+        for task in &self.tasks {
+            locked.push(task.blocks.lock().await);
+        }
+
+        if let Err(e) = self.apply_signals(&scratch, &mut changes) {
+            self.undo_signals(&changes);
+            return Err(e);
+        }
+        for name in &report.signals_removed {
+            self.bus.remove(name);
+        }
+
+        let old_blocks: HashMap<&str, _> = running.blocks.iter().map(|b| (b.name.as_str(), b)).collect();
+        let new_blocks: HashMap<&str, _> = config.blocks.iter().map(|b| (b.name.as_str(), b)).collect();
+        let mut previous: HashMap<String, _> = locked
+            .iter_mut()
+            .flat_map(|blocks| blocks.drain(..))
+            .map(|block| (block.name().to_string(), block))
+            .collect();
+        let blocks: Vec<_> = fresh
+            .into_iter()
+            .map(|mut block| {
+                let name = block.name().to_string();
+                let Some(old) = previous.remove(&name) else {
+                    report.blocks_added.push(name);
+                    return block;
+                };
+                match (old_blocks.get(name.as_str()), new_blocks.get(name.as_str())) {
+                    (Some(a), Some(b)) if same(*a, *b) => old,
+                    _ => {
+                        if old.block_type() == block.block_type() {
+                            if let Some(state) = old.save_state() {
+                                if let Err(e) = block.load_state(state) {
+                                    warn!("Block '{}' restarts without its state: {}", name, e);
+                                }
+                            }
+                        }
+                        report.blocks_updated.push(name);
+                        block
+                    }
+                }
+            })
+            .collect();
+        report.blocks_removed = previous.into_keys().collect();
+        report.blocks_removed.sort();
+        report.blocks = blocks.len();
+
+        TaskSplit::new(config, &self.bus, blocks).install(&mut locked, self.main_image.as_ref(), &self.tasks);
+        #[cfg(feature = "parallel-execution")]
+        if let Some(executor) = &self.parallel_executor {
+            executor.configure(&locked[0], config);
+        }
+        super::budget::lock(&self.budgets).configure(config);
+        self.degradation.configure(config);
+        self.bus.configure_groups(&config.signal_groups);
+        self.bus.configure_aliases(&config.aliases);
+        self.staleness.configure(config);
+        #[cfg(feature = "retained-store")]
+        if let Some(store) = &self.retained_store {
+            store.configure(config);
+        }
+        *running = config.clone();
+        drop(locked);
+
+        self.events.publish(EventKind::ConfigApplied {
+            blocks: report.blocks,
+            new_signals: report.signals_added.len(),
+        });
+        info!(
+            "Hot reload applied: {} blocks ({} added, {} updated, {} removed), signals {} added, {} updated, {} removed",
+            report.blocks,
+            report.blocks_added.len(),
+            report.blocks_updated.len(),
+            report.blocks_removed.len(),
+            report.signals_added.len(),
+            report.signals_updated.len(),
+            report.signals_removed.len()
+        );
+        Ok(report)
+    }
+
+    /// Write new signal values and policies, recording what they replace
+    fn apply_signals(&self, scratch: &SignalBus, changes: &mut [SignalChange<'_>]) -> Result<()> {
+        for change in changes {
+            let name = &change.config.name;
+            if let Some(value) = self.bus.get(name) {
+                change.previous = Some((value, self.bus.write_policy(name)));
+            }
+            self.bus.remove_write_policy(name);
+            if change.reset {
+                let value = scratch.get(name).ok_or_else(|| PlcError::NotFound(format!("Signal '{name}'")))?;
+                self.bus.set(name, value)?;
+            }
+            if let Some(policy) = &change.config.write_policy {
+                self.bus.set_write_policy(name, policy.clone())?;
+            }
+            debug!("Reloaded signal '{}'", name);
+        }
+        Ok(())
+    }
+
+    /// Restore the signals [`apply_signals`](Self::apply_signals) changed
+    fn undo_signals(&self, changes: &[SignalChange<'_>]) {
+        for change in changes {
+            let name = &change.config.name;
+            self.bus.remove_write_policy(name);
+            match &change.previous {
+                Some((value, policy)) => {
+                    let _ = self.bus.set(name, value.clone());
+                    if let Some(policy) = policy {
+                        let _ = self.bus.set_write_policy(name, policy.clone());
+                    }
+                }
+                None => {
+                    self.bus.remove(name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(blocks: &str, extra_signal: &str) -> Config {
+        serde_yaml::from_str(&format!(
+            "scan_time_ms: 100\n\
+             max_scan_jitter_ms: 50\n\
+             signals:\n\
+             \x20 - {{ name: start, type: bool }}\n\
+             \x20 - {{ name: reset, type: bool }}\n\
+             \x20 - {{ name: done, type: bool }}\n\
+             \x20 - {{ name: elapsed, type: int }}\n\
+             {extra_signal}\
+             blocks:\n{blocks}"
+        ))
+        .unwrap()
+    }
+
+    const TIMER: &str = "  - { name: tonr, type: TONR, inputs: { in: start, reset: reset }, outputs: { out: done, elapsed: elapsed }, params: { preset_ms: 10000 } }\n";
+
+    #[tokio::test]
+    async fn test_reload_keeps_block_state_and_applies_differences() {
+        let engine = Engine::new(config(TIMER, "")).unwrap();
+        let bus = engine.signal_bus().clone();
+        bus.set("start", Value::Bool(true)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        bus.set("start", Value::Bool(false)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        let banked = bus.get_integer("elapsed").unwrap();
+        assert!(banked >= 30);
+
+        // Adding a signal leaves the timer running as it was
+        let report = engine
+            .reload(&config(TIMER, "  - { name: extra, type: float, initial: 1.5 }\n"))
+            .await
+            .unwrap();
+        assert_eq!(report.signals_added, ["extra"]);
+        assert!(report.blocks_updated.is_empty());
+        assert_eq!(bus.get("extra"), Some(Value::Float(1.5)));
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get_integer("elapsed").unwrap(), banked);
+
+        // A changed preset recreates the timer with its accumulated time
+        let report = engine.reload(&config(&TIMER.replace("10000", "20000"), "")).await.unwrap();
+        assert_eq!(report.blocks_updated, ["tonr"]);
+        assert_eq!(report.signals_removed, ["extra"]);
+        assert!(!bus.exists("extra"));
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get_integer("elapsed").unwrap(), banked);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reload_leaves_engine_unchanged() {
+        let engine = Engine::new(config(TIMER, "")).unwrap();
+        let broken = config(
+            "  - { name: unknown, type: NO_SUCH_BLOCK }\n",
+            "  - { name: extra, type: bool }\n",
+        );
+        assert!(engine.reload(&broken).await.is_err());
+        assert!(!engine.signal_bus().exists("extra"));
+        assert!(engine.block_control().contains("tonr").await);
+        assert!(engine.reload(&config(TIMER, "")).await.unwrap().is_empty());
+    }
+
+    #[cfg(feature = "parallel-execution")]
+    #[tokio::test]
+    async fn test_reload_rebuilds_parallel_executor() {
+        const INVERT: &str = "  - { name: invert, type: NOT, inputs: { in: start }, outputs: { out: done } }\n";
+        const ECHO: &str = "  - { name: echo, type: NOT, inputs: { in: done }, outputs: { out: reset } }\n";
+        let engine_config = crate::engine::EngineConfig { parallel_execution: true, ..Default::default() };
+        let engine = Engine::new_with_config(config(INVERT, ""), engine_config).unwrap();
+        let bus = engine.signal_bus().clone();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+
+        // An added block runs from the next cycle
+        let report = engine.reload(&config(&format!("{INVERT}{ECHO}"), "")).await.unwrap();
+        assert_eq!(report.blocks_added, ["echo"]);
+        bus.set("reset", Value::Bool(true)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("reset"), Some(Value::Bool(false)));
+
+        // A removed block no longer does
+        let report = engine.reload(&config(ECHO, "")).await.unwrap();
+        assert_eq!(report.blocks_removed, ["invert"]);
+        bus.set("start", Value::Bool(true)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("done"), Some(Value::Bool(true)));
+    }
+
+    #[cfg(feature = "parallel-execution")]
+    #[tokio::test]
+    async fn test_reload_to_fewer_blocks_during_parallel_cycle() {
+        const SIGNALS: &str = "  - { name: a, type: bool }\n  - { name: b, type: bool }\n  - { name: c, type: bool }\n  - { name: d, type: bool }\n";
+        const FIRST: &str = "  - { name: x1, type: NOT, inputs: { in: start }, outputs: { out: a } }\n\
+                             \x20 - { name: x2, type: NOT, inputs: { in: start }, outputs: { out: b } }\n";
+        const SECOND: &str = "  - { name: y1, type: NOT, inputs: { in: a }, outputs: { out: c } }\n\
+                              \x20 - { name: y2, type: NOT, inputs: { in: b }, outputs: { out: d } }\n";
+        let engine_config = crate::engine::EngineConfig { parallel_execution: true, ..Default::default() };
+        let engine = Engine::new_with_config(config(&format!("{FIRST}{SECOND}"), SIGNALS), engine_config).unwrap();
+        let bus = engine.signal_bus().clone();
+        let smaller = config("  - { name: x1, type: NOT, inputs: { in: start }, outputs: { out: a } }\n", SIGNALS);
+
+        // The reload waits for the cycle, which finishes with the blocks it started with
+        bus.set("c", Value::Bool(true)).unwrap();
+        bus.set("d", Value::Bool(true)).unwrap();
+        let (cycle, report) = tokio::join!(engine.execute_scan_cycle(), engine.reload(&smaller));
+        cycle.unwrap();
+        assert_eq!(report.unwrap().blocks_removed, ["x2", "y1", "y2"]);
+        assert_eq!(bus.get("c"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("d"), Some(Value::Bool(false)));
+
+        bus.set("a", Value::Bool(false)).unwrap();
+        bus.set("b", Value::Bool(false)).unwrap();
+        engine.execute_scan_cycle().await.unwrap();
+        assert_eq!(bus.get("a"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("b"), Some(Value::Bool(false)));
+    }
+}
@@ -0,0 +1,202 @@
+// src/engine/simulation.rs
+//! Simulation mode
+//!
+//! An engine on a simulated clock paces its scan cycles and tasks by
+//! virtual time. An accelerated clock (`clock: { source: simulated, speed:
+//! 100 }`) runs the normal scan loop with every interval divided by the
+//! speed. A stepped clock never moves on its own: [`Engine::step`] advances
+//! it to each cycle due in the requested span and runs that cycle, main scan
+//! before tasks at equal times, so a test gets the same cycles at the same
+//! virtual times on every run without sleeping.
+
+use super::{tasks, Engine, EngineConfig};
+use crate::{
+    clock::SimulatedClock,
+    config::Config,
+    error::{PlcError, Result},
+    signal::SignalBus,
+};
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+/// Virtual clock of an engine in simulation mode
+pub(super) struct Simulation {
+    pub(super) clock: Arc<SimulatedClock>,
+    schedule: Mutex<Schedule>,
+}
+
+/// Virtual times of the next cycles of a stepped engine
+struct Schedule {
+    now: Duration,
+    next_scan: Duration,
+    next_tasks: Vec<Duration>,
+}
+
+impl Simulation {
+    fn new(clock: Arc<SimulatedClock>, tasks: usize) -> Self {
+        Self {
+            clock,
+            schedule: Mutex::new(Schedule {
+                now: Duration::ZERO,
+                next_scan: Duration::ZERO,
+                next_tasks: vec![Duration::ZERO; tasks],
+            }),
+        }
+    }
+
+    /// Virtual seconds per real second
+    pub(super) fn speed(&self) -> f64 {
+        self.clock.speed()
+    }
+}
+
+impl Engine {
+    /// Create an engine on a simulated clock
+    ///
+    /// The clock replaces any `clock` section of the configuration.
+    pub(super) fn new_simulated(
+        config: Config,
+        engine_config: EngineConfig,
+        clock: Arc<SimulatedClock>,
+    ) -> Result<Self> {
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let mut engine = Self::new_with_bus_and_config(config, bus, engine_config)?;
+        engine.simulation = Some(Simulation::new(clock, engine.tasks.len()));
+        Ok(engine)
+    }
+
+    /// Create an engine whose time only moves with [`step`](Self::step)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use petra::{Config, Engine};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> petra::Result<()> {
+    /// let engine = Engine::new_stepped(Config::from_file("petra.yaml")?)?;
+    /// engine.signal_bus().set("start", true.into())?;
+    /// let scans = engine.step(Duration::from_secs(10)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_stepped(config: Config) -> Result<Self> {
+        Self::new_simulated(config, EngineConfig::default(), Arc::new(SimulatedClock::stepped()))
+    }
+
+    /// The simulated clock the engine runs on, if any
+    #[must_use]
+    pub fn simulated_clock(&self) -> Option<&Arc<SimulatedClock>> {
+        self.simulation.as_ref().map(|s| &s.clock)
+    }
+
+    /// Advance virtual time by `duration`, running the scan and task
+    /// cycles due in it
+    ///
+    /// The first call runs the cycles at virtual time zero, as
+    /// [`run`](Self::run) does when it starts. Cycles due while the engine
+    /// is paused are skipped. Returns the number of main scans run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the engine is not on a stepped clock, or the
+    /// first scan cycle that fails.
+    pub async fn step(&self, duration: Duration) -> Result<u64> {
+        let simulation = self
+            .simulation
+            .as_ref()
+            .filter(|s| s.clock.is_stepped())
+            .ok_or_else(|| PlcError::Runtime("Only an engine on a stepped clock can be stepped".to_string()))?;
+        let context = self.task_context();
+        let mut schedule = simulation.schedule.lock().await;
+        let end = schedule.now + duration;
+        let mut scans = 0;
+
+        loop {
+            let next = schedule.next_tasks.iter().fold(schedule.next_scan, |next, &t| next.min(t));
+            if next > end {
+                break;
+            }
+            simulation.clock.advance(next.saturating_sub(schedule.now));
+            schedule.now = next;
+            let paused = self.paused.load(Ordering::Acquire);
+
+            if schedule.next_scan == next {
+                schedule.next_scan += self.target_scan_time;
+                if !paused {
+                    self.execute_scan_cycle().await?;
+                    scans += 1;
+                }
+            }
+            for (group, due) in self.tasks.iter().zip(schedule.next_tasks.iter_mut()) {
+                if *due == next {
+                    *due += group.interval;
+                    if !paused {
+                        tasks::cycle(group, &context).await;
+                    }
+                }
+            }
+        }
+
+        simulation.clock.advance(end.saturating_sub(schedule.now));
+        schedule.now = end;
+        Ok(scans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+scan_time_ms: 100
+max_scan_jitter_ms: 50
+signals:
+  - { name: start, type: bool }
+  - { name: done, type: bool }
+  - { name: idle, type: bool }
+blocks:
+  - { name: delay, type: ON_DELAY, inputs: { in: start }, outputs: { out: done }, params: { preset_ms: 5000 } }
+  - { name: invert, type: NOT, inputs: { in: start }, outputs: { out: idle }, task: slow }
+tasks:
+  - { name: slow, interval_ms: 1000 }
+";
+
+    #[tokio::test]
+    async fn test_stepping_runs_due_cycles_in_virtual_time() {
+        let engine = Engine::new_stepped(serde_yaml::from_str(CONFIG).unwrap()).unwrap();
+        let bus = engine.signal_bus().clone();
+        bus.set("start", true.into()).unwrap();
+
+        assert_eq!(engine.step(Duration::from_millis(4950)).await.unwrap(), 50);
+        assert!(!bus.get_bool("done").unwrap());
+        assert_eq!(engine.step(Duration::from_millis(50)).await.unwrap(), 1);
+        assert!(bus.get_bool("done").unwrap());
+        assert_eq!(engine.scan_count(), 51);
+        assert_eq!(engine.task_stats()[0].cycles, 6);
+
+        engine.engine_control().pause().await;
+        assert_eq!(engine.step(Duration::from_secs(1)).await.unwrap(), 0);
+        assert_eq!(engine.simulated_clock().unwrap().elapsed(), Duration::from_secs(6));
+    }
+
+    #[tokio::test]
+    async fn test_only_stepped_engines_step() {
+        let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        let engine = Engine::new(config.clone()).unwrap();
+        assert!(engine.simulated_clock().is_none());
+        assert!(engine.step(Duration::from_secs(1)).await.is_err());
+
+        let config = Config { clock: Some(crate::clock::ClockConfig::Simulated { speed: 100.0 }), ..config };
+        let engine = Engine::new(config).unwrap();
+        assert_eq!(engine.simulated_clock().unwrap().speed(), 100.0);
+        assert!(engine.step(Duration::from_secs(1)).await.is_err());
+    }
+}
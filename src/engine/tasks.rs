@@ -0,0 +1,597 @@
+// src/engine/tasks.rs
+//! Scan tasks with independent cycle times
+//!
+//! Blocks assigned to a task in the configuration run in a Tokio task of
+//! their own with its own interval timer, next to the main scan that runs
+//! the unassigned blocks. A slow task therefore never delays a fast one,
+//! and a fast task does not need the main scan time to shrink.
+//!
+//! As soon as tasks are configured every task, the main scan included,
+//! works on a [`ProcessImage`]; an output latch gives the main scan one
+//! without tasks. A cycle copies the signals its blocks use from the bus,
+//! executes its blocks on that copy and writes the outputs it changed back
+//! in a single bus transaction. Copies are taken under the shared side of
+//! the engine's image lock and write-backs under the exclusive side, so a
+//! cycle sees either all outputs of another task's cycle or none of them,
+//! and its inputs cannot change while it runs.
+
+use super::budget::{self, SharedBudgets};
+use super::degradation::SharedDegradation;
+use crate::{
+    blocks::Block,
+    config::{BlockConfig, Config, TaskConfig},
+    error::{PlcError, Result},
+    events::{EventKind, EventLog},
+    scan_budget::{self, Subsystem},
+    signal::SignalBus,
+    value::Value,
+    watchdog::Watchdog,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(feature = "hot-reload")]
+use tokio::sync::MutexGuard;
+use tokio::{
+    sync::Mutex,
+    time::{interval, MissedTickBehavior},
+};
+use tracing::{debug, error, info, span, warn, Level};
+
+/// Orders image copies against write-backs of all tasks
+pub(crate) type ImageLock = RwLock<()>;
+
+/// Process image that a hot reload replaces together with the blocks
+pub(crate) type SharedImage = Arc<RwLock<Arc<ProcessImage>>>;
+
+/// An image copied in for a cycle, with its outputs as copied
+pub(crate) type LoadedImage = (Arc<ProcessImage>, Vec<Option<Value>>);
+
+/// Blocks shared between a task and the engine's control handles
+pub(crate) type SharedBlocks = Arc<Mutex<Vec<Box<dyn Block>>>>;
+
+// ============================================================================
+// PROCESS IMAGE
+// ============================================================================
+
+/// Local copy of the signals one task's blocks read and write
+#[derive(Debug)]
+pub(crate) struct ProcessImage {
+    /// Bus the task's blocks execute on
+    bus: SignalBus,
+    /// Signals copied in at the start of a cycle
+    signals: Vec<String>,
+    /// Signals written back at the end of a cycle
+    outputs: Vec<String>,
+}
+
+impl ProcessImage {
+    /// Image of the signals used by `blocks`
+    ///
+    /// Besides the mapped inputs and outputs, parameters that name a
+    /// configured signal are included, since some blocks read signals
+    /// given as parameters. Outputs are applied to the bus in the
+    /// configured output latch order; protocol writes are not ordered.
+    pub(crate) fn new<'a>(
+        shared: &SignalBus,
+        config: &Config,
+        blocks: impl IntoIterator<Item = &'a BlockConfig>,
+    ) -> Self {
+        let configured: HashSet<&str> = config.signals.iter().map(|s| s.name.as_str()).collect();
+        let mut signals = BTreeSet::new();
+        let mut outputs = BTreeSet::new();
+        for block in blocks {
+            signals.extend(block.inputs.values().map(String::as_str));
+            signals.extend(
+                block
+                    .params
+                    .values()
+                    .filter_map(serde_yaml::Value::as_str)
+                    .filter(|name| configured.contains(name)),
+            );
+            outputs.extend(block.outputs.values().map(String::as_str));
+        }
+        signals.extend(&outputs);
+        let mut outputs: Vec<String> = outputs.into_iter().map(str::to_string).collect();
+        if let Some(latch) = &config.output_latch {
+            outputs.sort_by_key(|name| latch.rank(name));
+        }
+
+        Self {
+            bus: SignalBus::new().with_clock(Arc::clone(shared.clock())),
+            signals: signals.into_iter().map(str::to_string).collect(),
+            outputs,
+        }
+    }
+
+    /// Bus the blocks execute on during a cycle
+    pub(crate) fn bus(&self) -> &SignalBus {
+        &self.bus
+    }
+
+    /// Copy the image's signals from the shared bus
+    ///
+    /// Returns the outputs as copied, so [`store`](Self::store) can tell
+    /// which of them the cycle changed.
+    pub(crate) fn load(&self, shared: &SignalBus, lock: &ImageLock) -> Result<Vec<Option<Value>>> {
+        let _guard = lock.read().unwrap_or_else(PoisonError::into_inner);
+        for name in &self.signals {
+            if let Some(value) = shared.get(name) {
+                self.bus.set(name, value)?;
+            }
+        }
+        Ok(self.outputs.iter().map(|name| self.bus.get(name)).collect())
+    }
+
+    /// Write the outputs changed since [`load`](Self::load) to the shared bus
+    ///
+    /// The changes are written in one transaction, so a write policy
+    /// rejecting one of them rejects the whole cycle's outputs.
+    pub(crate) fn store(&self, shared: &SignalBus, loaded: &[Option<Value>], lock: &ImageLock) -> Result<()> {
+        let changed: Vec<(&str, Value)> = self
+            .outputs
+            .iter()
+            .zip(loaded)
+            .filter_map(|(name, loaded)| {
+                let value = self.bus.get(name)?;
+                (loaded.as_ref() != Some(&value)).then_some((name.as_str(), value))
+            })
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = lock.write().unwrap_or_else(PoisonError::into_inner);
+        shared.write_transaction(changed)
+    }
+}
+
+// ============================================================================
+// TASK GROUPS
+// ============================================================================
+
+/// Cycle statistics of one scan task
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskStats {
+    /// Task name
+    pub name: String,
+    /// Configured time between cycle starts
+    pub interval: Duration,
+    /// Completed cycles
+    pub cycles: u64,
+    /// Cycles that took longer than the interval
+    pub overruns: u64,
+    /// Cycles in which a block or the write-back failed
+    pub errors: u64,
+    /// Duration of the last cycle
+    pub last_cycle_time: Duration,
+    /// Longest cycle so far
+    pub max_cycle_time: Duration,
+}
+
+/// A task's blocks, process image and statistics
+#[derive(Clone)]
+pub(crate) struct TaskGroup {
+    pub(crate) name: String,
+    pub(crate) interval: Duration,
+    pub(crate) blocks: SharedBlocks,
+    pub(crate) image: SharedImage,
+    stats: Arc<std::sync::Mutex<TaskStats>>,
+    /// Blocks that failed in the last cycle
+    failing: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl TaskGroup {
+    fn new(task: &TaskConfig, blocks: Vec<Box<dyn Block>>, image: ProcessImage) -> Self {
+        let interval = Duration::from_millis(task.interval_ms);
+        Self {
+            name: task.name.clone(),
+            interval,
+            blocks: Arc::new(Mutex::new(blocks)),
+            image: Arc::new(RwLock::new(Arc::new(image))),
+            stats: Arc::new(std::sync::Mutex::new(TaskStats {
+                name: task.name.clone(),
+                interval,
+                ..TaskStats::default()
+            })),
+            failing: Arc::default(),
+        }
+    }
+
+    /// Statistics of the cycles run so far
+    pub(crate) fn stats(&self) -> TaskStats {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Blocks split by the task they run in
+pub(crate) struct TaskSplit {
+    /// Blocks of the main scan
+    pub(crate) main: Vec<Box<dyn Block>>,
+    /// Image of the main scan, when tasks or an output latch are
+    /// configured
+    pub(crate) main_image: Option<ProcessImage>,
+    /// Blocks and image of each configured task, in configuration order
+    pub(crate) tasks: Vec<(Vec<Box<dyn Block>>, ProcessImage)>,
+}
+
+impl TaskSplit {
+    /// Split `blocks`, created from `config`, by task
+    pub(crate) fn new(config: &Config, bus: &SignalBus, blocks: Vec<Box<dyn Block>>) -> Self {
+        if !uses_images(config) {
+            return Self { main: blocks, main_image: None, tasks: Vec::new() };
+        }
+
+        let task_of = |block: &dyn Block| {
+            config
+                .blocks
+                .iter()
+                .find(|b| b.name == block.name())
+                .and_then(|b| b.task.as_deref())
+        };
+        let mut main = Vec::new();
+        let mut tasks: Vec<_> = config.tasks.iter().map(|_| Vec::new()).collect();
+        for block in blocks {
+            match task_of(block.as_ref()).and_then(|t| config.tasks.iter().position(|c| c.name == t)) {
+                Some(index) => tasks[index].push(block),
+                None => main.push(block),
+            }
+        }
+
+        let image = |task: Option<&str>| {
+            ProcessImage::new(bus, config, config.blocks.iter().filter(|b| b.enabled && b.task.as_deref() == task))
+        };
+        Self {
+            main,
+            main_image: Some(image(None)),
+            tasks: tasks
+                .into_iter()
+                .zip(&config.tasks)
+                .map(|(blocks, task)| (blocks, image(Some(&task.name))))
+                .collect(),
+        }
+    }
+
+    /// Start a group for each task
+    pub(crate) fn groups(tasks: Vec<(Vec<Box<dyn Block>>, ProcessImage)>, config: &Config) -> Vec<TaskGroup> {
+        tasks
+            .into_iter()
+            .zip(&config.tasks)
+            .map(|((blocks, image), task)| TaskGroup::new(task, blocks, image))
+            .collect()
+    }
+
+    /// Install the split in a running engine
+    ///
+    /// `locked` holds the block lists of the main scan and of each task,
+    /// in that order, locked by the caller so no cycle runs while blocks
+    /// and images change. The split must be made for the tasks the engine
+    /// runs, as checked by [`check_unchanged`].
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn install(
+        self,
+        locked: &mut [MutexGuard<'_, Vec<Box<dyn Block>>>],
+        main_image: Option<&SharedImage>,
+        groups: &[TaskGroup],
+    ) {
+        let mut locked = locked.iter_mut();
+        if let Some(main) = locked.next() {
+            **main = self.main;
+        }
+        if let (Some(shared), Some(image)) = (main_image, self.main_image) {
+            *shared.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(image);
+        }
+        for (((blocks, image), group), locked) in self.tasks.into_iter().zip(groups).zip(locked) {
+            **locked = blocks;
+            *group.image.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(image);
+        }
+    }
+}
+
+/// Whether the main scan of `config` works on a process image
+fn uses_images(config: &Config) -> bool {
+    !config.tasks.is_empty() || config.output_latch.is_some()
+}
+
+/// Error if `config` names other tasks than the running engine, or
+/// switches the main scan's process image on or off
+#[cfg(feature = "hot-reload")]
+pub(crate) fn check_unchanged(config: &Config, groups: &[TaskGroup], main_image: Option<&SharedImage>) -> Result<()> {
+    let unchanged = config.tasks.len() == groups.len()
+        && config
+            .tasks
+            .iter()
+            .zip(groups)
+            .all(|(task, group)| task.name == group.name && Duration::from_millis(task.interval_ms) == group.interval);
+    if !unchanged {
+        return Err(PlcError::Config(
+            "Scan tasks cannot change while the engine runs; restart to apply them".to_string(),
+        ));
+    }
+    if uses_images(config) != main_image.is_some() {
+        return Err(PlcError::Config(
+            "The output latch cannot be switched on or off while the engine runs; restart to apply it".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TASK EXECUTION
+// ============================================================================
+
+/// Engine state a task runs against
+#[derive(Clone)]
+pub(crate) struct TaskContext {
+    pub(crate) bus: SignalBus,
+    pub(crate) running: Arc<AtomicBool>,
+    pub(crate) paused: Arc<AtomicBool>,
+    pub(crate) error_count: Arc<AtomicU64>,
+    pub(crate) events: EventLog,
+    pub(crate) budgets: SharedBudgets,
+    pub(crate) degradation: SharedDegradation,
+    pub(crate) image_lock: Arc<ImageLock>,
+    /// Watchdog supervising each task, fed after every healthy cycle
+    pub(crate) watchdog: Option<Watchdog>,
+    pub(crate) missed_tick_behavior: MissedTickBehavior,
+    /// Virtual seconds per real second of a simulated clock, 1.0 in real
+    /// time
+    pub(crate) speed: f64,
+}
+
+impl TaskContext {
+    /// Report a healthy cycle of `task` to the watchdog
+    fn feed_watchdog(&self, task: &str) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.feed_task(task);
+        }
+    }
+}
+
+/// Real time between cycles that are `period` apart on a clock running at
+/// `speed`
+pub(crate) fn paced(period: Duration, speed: f64) -> Duration {
+    if speed > 0.0 {
+        period.div_f64(speed)
+    } else {
+        period
+    }
+}
+
+/// Run a task's cycles until the engine stops
+pub(crate) async fn run(group: TaskGroup, ctx: TaskContext) {
+    let mut ticker = interval(paced(group.interval, ctx.speed));
+    ticker.set_missed_tick_behavior(ctx.missed_tick_behavior);
+    info!("Task '{}' started with interval {:?}", group.name, group.interval);
+
+    let mut ticks = 0u32;
+    while ctx.running.load(Ordering::Acquire) {
+        ticker.tick().await;
+        // A paused task and a stretched task of a degraded engine, which
+        // sits out ticks, are not stalled
+        if ctx.paused.load(Ordering::Acquire) {
+            ctx.feed_watchdog(&group.name);
+            continue;
+        }
+        ticks = ticks.wrapping_add(1);
+        if !ticks.is_multiple_of(ctx.degradation.divider(&group.name)) {
+            ctx.feed_watchdog(&group.name);
+            continue;
+        }
+        cycle(&group, &ctx).await;
+    }
+
+    debug!("Task '{}' stopped", group.name);
+}
+
+/// Run one cycle of a task
+pub(crate) async fn cycle(group: &TaskGroup, ctx: &TaskContext) {
+    let start = Instant::now();
+    let cycle = group.stats.lock().unwrap_or_else(PoisonError::into_inner).cycles;
+    let span = span!(Level::TRACE, "task_cycle", task = %group.name, cycle);
+    let mut blocks = group.blocks.lock().await;
+    let image = Arc::clone(&group.image.read().unwrap_or_else(PoisonError::into_inner));
+    let result = span.in_scope(|| {
+        image.load(&ctx.bus, &ctx.image_lock).and_then(|loaded| {
+            let errors = execute(&mut blocks, image.bus(), start, ctx);
+            image.store(&ctx.bus, &loaded, &ctx.image_lock)?;
+            Ok(errors)
+        })
+    });
+    let failed = match result {
+        Ok(errors) => {
+            ctx.feed_watchdog(&group.name);
+            let mut failing = group.failing.lock().unwrap_or_else(PoisonError::into_inner);
+            track_failures(&mut failing, &ctx.events, &blocks, &errors);
+            !errors.is_empty()
+        }
+        Err(e) => {
+            error!("Task '{}' could not exchange its process image: {}", group.name, e);
+            true
+        }
+    };
+    drop(blocks);
+
+    let elapsed = start.elapsed();
+    let period = paced(group.interval, ctx.speed);
+    let mut stats = group.stats.lock().unwrap_or_else(PoisonError::into_inner);
+    stats.cycles += 1;
+    stats.last_cycle_time = elapsed;
+    stats.max_cycle_time = stats.max_cycle_time.max(elapsed);
+    if failed {
+        stats.errors += 1;
+        ctx.error_count.fetch_add(1, Ordering::Relaxed);
+    }
+    if elapsed > period {
+        stats.overruns += 1;
+        warn!("Task '{}' overrun: {:?} > {:?}", group.name, elapsed, period);
+    }
+}
+
+/// Execute blocks in order, returning the ones that failed
+///
+/// Blocks sitting out an overrun of their budget, and blocks shed by a
+/// degraded engine, are passed over.
+fn execute(
+    blocks: &mut [Box<dyn Block>],
+    bus: &SignalBus,
+    start: Instant,
+    ctx: &TaskContext,
+) -> Vec<(String, PlcError)> {
+    let mut errors = Vec::new();
+    for block in blocks {
+        if ctx.degradation.sheds(block.name()) || !budget::lock(&ctx.budgets).admit(block.name(), start) {
+            continue;
+        }
+        let _span = span!(Level::TRACE, "block", block = %block.name(), block_type = %block.block_type()).entered();
+        let block_start = Instant::now();
+        let result = scan_budget::measure(Subsystem::Blocks, || block.execute(bus));
+        budget::lock(&ctx.budgets).record(block.name(), block_start.elapsed(), Instant::now(), &ctx.events);
+        if let Err(e) = result {
+            error!("Block '{}' execution failed: {}", block.name(), e);
+            errors.push((block.name().to_string(), e));
+        }
+    }
+    errors
+}
+
+/// Publish blocks that started failing or recovered in this cycle
+///
+/// A block failing every cycle is reported once, when it starts failing.
+pub(crate) fn track_failures(
+    failing: &mut HashSet<String>,
+    events: &EventLog,
+    blocks: &[Box<dyn Block>],
+    errors: &[(String, PlcError)],
+) {
+    if errors.is_empty() && failing.is_empty() {
+        return;
+    }
+
+    for (block, error) in errors {
+        if failing.insert(block.clone()) {
+            events.publish(EventKind::BlockError {
+                block: block.clone(),
+                error: error.to_string(),
+            });
+        }
+    }
+
+    let recovered: Vec<String> = failing
+        .iter()
+        .filter(|name| !errors.iter().any(|(block, _)| block == *name))
+        .cloned()
+        .collect();
+    for block in recovered {
+        failing.remove(&block);
+        // Blocks removed by a reload are dropped without an event
+        if blocks.iter().any(|b| b.name() == block) {
+            events.publish(EventKind::BlockRecovered { block });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_image_writes_back_only_changed_outputs() {
+        let config = config(
+            "signals:\n\
+             \x20 - { name: a, type: bool }\n\
+             \x20 - { name: b, type: bool }\n\
+             \x20 - { name: y, type: bool }\n\
+             \x20 - { name: z, type: bool }\n\
+             blocks:\n\
+             \x20 - { name: and1, type: AND, inputs: { a: a, b: b }, outputs: { out: y } }\n\
+             \x20 - { name: not1, type: NOT, inputs: { in: a }, outputs: { out: z } }\n",
+        );
+        let shared = SignalBus::new();
+        for name in ["a", "b", "y", "z"] {
+            shared.set(name, Value::Bool(false)).unwrap();
+        }
+        let lock = ImageLock::default();
+        let image = ProcessImage::new(&shared, &config, &config.blocks);
+        assert_eq!(image.signals, ["a", "b", "y", "z"]);
+        assert_eq!(image.outputs, ["y", "z"]);
+
+        let loaded = image.load(&shared, &lock).unwrap();
+        image.bus().set("y", Value::Bool(true)).unwrap();
+        // Nothing reaches the shared bus before the write-back
+        assert_eq!(shared.get("y"), Some(Value::Bool(false)));
+
+        // Another writer changed z; the cycle left it alone, so z survives
+        shared.set("z", Value::Bool(true)).unwrap();
+        image.store(&shared, &loaded, &lock).unwrap();
+        assert_eq!(shared.get("y"), Some(Value::Bool(true)));
+        assert_eq!(shared.get("z"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_split_by_task() {
+        let config = config(
+            "tasks:\n\
+             \x20 - { name: fast, interval_ms: 5 }\n\
+             signals:\n\
+             \x20 - { name: a, type: bool }\n\
+             \x20 - { name: y, type: bool }\n\
+             \x20 - { name: z, type: bool }\n\
+             blocks:\n\
+             \x20 - { name: fast_not, type: NOT, task: fast, inputs: { in: a }, outputs: { out: y } }\n\
+             \x20 - { name: main_not, type: NOT, inputs: { in: y }, outputs: { out: z } }\n",
+        );
+        let blocks = crate::engine::Engine::create_blocks(&config).unwrap();
+        let split = TaskSplit::new(&config, &SignalBus::new(), blocks);
+        assert_eq!(split.main.len(), 1);
+        assert_eq!(split.main[0].name(), "main_not");
+        assert_eq!(split.tasks.len(), 1);
+        assert_eq!(split.tasks[0].0[0].name(), "fast_not");
+        assert_eq!(split.tasks[0].1.outputs, ["y"]);
+        assert_eq!(split.main_image.unwrap().signals, ["y", "z"]);
+    }
+
+    #[test]
+    fn test_output_latch_orders_main_scan_outputs() {
+        let yaml = "signals:\n\
+             \x20 - { name: a, type: bool }\n\
+             \x20 - { name: alarm, type: bool }\n\
+             \x20 - { name: pump, type: bool }\n\
+             \x20 - { name: valve_a, type: bool }\n\
+             \x20 - { name: valve_b, type: bool }\n\
+             blocks:\n\
+             \x20 - { name: n1, type: NOT, inputs: { in: a }, outputs: { out: alarm } }\n\
+             \x20 - { name: n2, type: NOT, inputs: { in: a }, outputs: { out: valve_b } }\n\
+             \x20 - { name: n3, type: NOT, inputs: { in: a }, outputs: { out: pump } }\n\
+             \x20 - { name: n4, type: NOT, inputs: { in: a }, outputs: { out: valve_a } }\n";
+        let unlatched = config(yaml);
+        let blocks = crate::engine::Engine::create_blocks(&unlatched).unwrap();
+        assert!(TaskSplit::new(&unlatched, &SignalBus::new(), blocks).main_image.is_none());
+
+        let latched = config(&format!("output_latch:\n  order: [pump, \"valve_*\"]\n{yaml}"));
+        latched.validate().unwrap();
+        let blocks = crate::engine::Engine::create_blocks(&latched).unwrap();
+        let split = TaskSplit::new(&latched, &SignalBus::new(), blocks);
+        assert_eq!(split.main.len(), 4);
+        assert_eq!(split.main_image.unwrap().outputs, ["pump", "valve_a", "valve_b", "alarm"]);
+
+        let invalid = config(&format!("output_latch:\n  order: [\"*\"]\n{yaml}"));
+        assert!(invalid.validate().is_err());
+
+        // Entries ending in a multi-byte character
+        let accented = config(&format!("output_latch:\n  order: [plant.température, plant.débit_m³, \"plant.é*\"]\n{yaml}"));
+        accented.validate().unwrap();
+        let invalid = config(&format!("output_latch:\n  order: [\"plant.*é\"]\n{yaml}"));
+        assert!(invalid.validate().is_err());
+    }
+}
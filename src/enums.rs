@@ -0,0 +1,281 @@
+// src/enums.rs - Enumerated signal types
+//
+// A signal of type `enum` declares the raw integers it may hold and a label
+// for each. The bus and the protocol drivers only ever see the integers;
+// configuration, blocks and operator-facing output use the labels:
+//
+// ```yaml
+// signals:
+//   - name: pump.state
+//     type: enum
+//     values: { 0: Stopped, 1: Running, 2: Faulted }
+//     initial: Stopped
+// blocks:
+//   - name: pump_faulted
+//     type: EQ
+//     inputs: { a: pump.state }
+//     outputs: { output: pump.fault_alarm }
+//     params: { value: Faulted }
+// ```
+
+use crate::config::{BlockConfig, SignalConfig};
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Signal type name for enumerations
+pub const ENUM_TYPE: &str = "enum";
+
+/// Block parameters that may name a label of the block's enum input
+const LABEL_PARAMS: &[&str] = &["value"];
+
+/// The values and labels of one enumerated signal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnumType {
+    labels: BTreeMap<i64, String>,
+    values: HashMap<String, i64>,
+}
+
+impl EnumType {
+    /// Build an enumeration from its value to label map
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if no values are declared, a label is
+    /// empty or two values share a label.
+    pub fn new(signal: &str, labels: &BTreeMap<i64, String>) -> Result<Self> {
+        if labels.is_empty() {
+            return Err(PlcError::Config(format!(
+                "Enum signal '{signal}' must declare at least one value"
+            )));
+        }
+        let mut values = HashMap::with_capacity(labels.len());
+        for (&value, label) in labels {
+            if label.trim().is_empty() {
+                return Err(PlcError::Config(format!(
+                    "Enum signal '{signal}' has an empty label for value {value}"
+                )));
+            }
+            if let Some(other) = values.insert(label.clone(), value) {
+                return Err(PlcError::Config(format!(
+                    "Enum signal '{signal}' uses label '{label}' for both {other} and {value}"
+                )));
+            }
+        }
+        Ok(Self { labels: labels.clone(), values })
+    }
+
+    /// Label of a raw value
+    #[must_use]
+    pub fn label(&self, value: i64) -> Option<&str> {
+        self.labels.get(&value).map(String::as_str)
+    }
+
+    /// Raw value of a label
+    #[must_use]
+    pub fn value(&self, label: &str) -> Option<i64> {
+        self.values.get(label).copied()
+    }
+
+    /// Value the signal starts with when no initial value is configured
+    #[must_use]
+    pub fn default_value(&self) -> i64 {
+        self.labels.keys().next().copied().unwrap_or_default()
+    }
+
+    /// Value to label map, as used for display
+    #[must_use]
+    pub fn labels(&self) -> &BTreeMap<i64, String> {
+        &self.labels
+    }
+
+    /// Convert a declared raw value, or a label, into the raw integer
+    ///
+    /// Labels arrive as string values, which need the `extended-types`
+    /// feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] for unknown labels, undeclared
+    /// integers and values of any other type.
+    pub fn resolve(&self, signal: &str, value: &Value) -> Result<i64> {
+        match value {
+            Value::Integer(i) if self.labels.contains_key(i) => Ok(*i),
+            #[cfg(feature = "extended-types")]
+            Value::String(label) => self.lookup(signal, label),
+            other => Err(self.invalid(signal, other)),
+        }
+    }
+
+    /// Convert a configured YAML value (label or integer) into the raw integer
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] if the value is not a declared
+    /// label or value.
+    pub fn resolve_yaml(&self, signal: &str, value: &serde_yaml::Value) -> Result<i64> {
+        match value {
+            serde_yaml::Value::String(label) => self.lookup(signal, label),
+            serde_yaml::Value::Number(n) => match n.as_i64() {
+                Some(i) if self.labels.contains_key(&i) => Ok(i),
+                _ => Err(self.invalid(signal, n)),
+            },
+            other => Err(self.invalid(signal, format_args!("{other:?}"))),
+        }
+    }
+
+    fn lookup(&self, signal: &str, label: &str) -> Result<i64> {
+        self.value(label).ok_or_else(|| {
+            PlcError::Validation(format!("Signal '{signal}' has no enum label '{label}'"))
+        })
+    }
+
+    fn invalid(&self, signal: &str, got: impl std::fmt::Display) -> PlcError {
+        PlcError::Validation(format!(
+            "Signal '{signal}' expects one of {}, got {got}",
+            self.labels.values().cloned().collect::<Vec<_>>().join(", ")
+        ))
+    }
+}
+
+impl SignalConfig {
+    /// Whether this signal is an enumeration
+    #[must_use]
+    pub fn is_enum(&self) -> bool {
+        self.signal_type.eq_ignore_ascii_case(ENUM_TYPE)
+    }
+
+    /// Enumeration declared by this signal, if it is an enum signal
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the declared values are invalid.
+    pub fn enum_type(&self) -> Result<Option<EnumType>> {
+        if !self.is_enum() {
+            return Ok(None);
+        }
+        EnumType::new(&self.name, &self.values).map(Some)
+    }
+}
+
+/// Enumerations of all configured enum signals
+#[derive(Debug, Clone, Default)]
+pub struct EnumRegistry {
+    types: HashMap<String, EnumType>,
+}
+
+impl EnumRegistry {
+    /// Collect the enumerations declared by `signals`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if an enum signal declares invalid values.
+    pub fn from_signals(signals: &[SignalConfig]) -> Result<Self> {
+        let mut types = HashMap::new();
+        for signal in signals {
+            if let Some(enum_type) = signal.enum_type()? {
+                types.insert(signal.name.clone(), enum_type);
+            }
+        }
+        Ok(Self { types })
+    }
+
+    /// Enumeration of `signal`, if it is an enum signal
+    #[must_use]
+    pub fn get(&self, signal: &str) -> Option<&EnumType> {
+        self.types.get(signal)
+    }
+
+    /// Replace labels in a block's parameters with their raw values
+    ///
+    /// A label parameter is resolved against the enumeration of the block's
+    /// first enum input, so comparison blocks can be written as
+    /// `params: { value: Running }`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if a label is not declared by that
+    /// enumeration.
+    pub fn resolve_block(&self, block: &mut BlockConfig) -> Result<()> {
+        let mut inputs: Vec<_> = block.inputs.iter().collect();
+        inputs.sort();
+        let Some((signal, enum_type)) = inputs
+            .into_iter()
+            .find_map(|(_, signal)| self.get(signal).map(|t| (signal.clone(), t)))
+        else {
+            return Ok(());
+        };
+
+        for param in LABEL_PARAMS {
+            let Some(serde_yaml::Value::String(label)) = block.params.get(*param) else {
+                continue;
+            };
+            let value = enum_type.value(label).ok_or_else(|| {
+                PlcError::Config(format!(
+                    "Block '{}' parameter '{param}': signal '{signal}' has no enum label '{label}'",
+                    block.name
+                ))
+            })?;
+            block.params.insert((*param).to_string(), serde_yaml::Value::from(value));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::DisplayFormatter;
+
+    fn signals() -> Vec<SignalConfig> {
+        serde_yaml::from_str(
+            "- name: pump.state\n  type: enum\n  values: { 0: Stopped, 1: Running, 2: Faulted }\n  initial: Running\n\
+             - name: pump.speed\n  type: float\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_labels_and_values() {
+        let registry = EnumRegistry::from_signals(&signals()).unwrap();
+        let state = registry.get("pump.state").unwrap();
+        assert!(registry.get("pump.speed").is_none());
+
+        assert_eq!(state.label(2), Some("Faulted"));
+        assert_eq!(state.value("Running"), Some(1));
+        assert_eq!(state.default_value(), 0);
+        assert_eq!(state.resolve_yaml("pump.state", &"Stopped".into()).unwrap(), 0);
+        assert_eq!(state.resolve("pump.state", &Value::Integer(2)).unwrap(), 2);
+        assert!(state.resolve("pump.state", &Value::Integer(5)).is_err());
+        assert!(state.resolve_yaml("pump.state", &"Idle".into()).is_err());
+
+        let duplicate: BTreeMap<i64, String> = [(0, "On".to_string()), (1, "On".to_string())].into();
+        assert!(EnumType::new("x", &duplicate).is_err());
+        assert!(EnumType::new("x", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_block_labels_and_writes_resolve_to_raw_values() {
+        let signals = signals();
+        let registry = EnumRegistry::from_signals(&signals).unwrap();
+        let mut block: BlockConfig = serde_yaml::from_str(
+            "name: pump_faulted\ntype: EQ\ninputs: { a: pump.state }\n\
+             outputs: { output: pump.alarm }\nparams: { value: Faulted }\n",
+        )
+        .unwrap();
+        registry.resolve_block(&mut block).unwrap();
+        assert_eq!(block.params["value"], serde_yaml::Value::from(2));
+
+        block.params.insert("value".into(), serde_yaml::Value::from("Idle"));
+        assert!(registry.resolve_block(&mut block).is_err());
+
+        let state = &signals[0];
+        assert_eq!(state.check_write(Value::Integer(1)).unwrap(), Value::Integer(1));
+        assert!(state.check_write(Value::Integer(9)).is_err());
+        #[cfg(feature = "extended-types")]
+        assert_eq!(state.check_write(Value::String("Faulted".into())).unwrap(), Value::Integer(2));
+
+        let formatter = DisplayFormatter::from_signals(&signals);
+        assert_eq!(formatter.format("pump.state", &Value::Integer(2)), "Faulted");
+    }
+}
@@ -1005,7 +1005,7 @@ impl axum::response::IntoResponse for PlcError {
     fn into_response(self) -> axum::response::Response {
         use axum::{Json, http::StatusCode};
         let status = match self {
-            PlcError::SignalNotFound(_) => StatusCode::NOT_FOUND,
+            PlcError::SignalNotFound(_) | PlcError::NotFound(_) => StatusCode::NOT_FOUND,
             PlcError::Validation(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
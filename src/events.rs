@@ -0,0 +1,386 @@
+// src/events.rs
+//! Engine event stream
+//!
+//! Engine state changes, block failures, protocol connections, failed write
+//! verifications and applied configurations are published to an [`EventLog`] shared by the engine and
+//! its handles. Each event carries a sequence number, counting up from 1
+//! without gaps, and the log keeps the most recent events so a client that
+//! reconnects can resume after the last sequence it received:
+//!
+//! ```rust
+//! use petra::events::{EventKind, EventLog};
+//!
+//! # tokio_test::block_on(async {
+//! let log = EventLog::default();
+//! log.publish(EventKind::ConfigApplied { blocks: 3, new_signals: 0 });
+//!
+//! // Replay everything after sequence 0, then follow new events
+//! let mut events = log.subscribe(Some(0));
+//! assert_eq!(events.next().await.unwrap().sequence, 1);
+//! # });
+//! ```
+//!
+//! A jump in sequence numbers tells a client that events it asked for were
+//! no longer retained. The stream is served by the gRPC `StreamEvents` call,
+//! the WebSocket `subscribe_events` message and `/api/events`.
+
+use crate::engine::EngineState;
+use crate::value::Value;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::broadcast;
+
+/// Events kept for resuming subscribers by [`EventLog::default`]
+pub const DEFAULT_HISTORY: usize = 1000;
+
+/// Live events buffered per subscriber before it must catch up from the
+/// history
+const LIVE_BUFFER: usize = 64;
+
+/// Event published by the engine or a protocol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineEvent {
+    /// Position in the stream, starting at 1
+    pub sequence: u64,
+
+    /// When the event was published
+    pub timestamp: DateTime<Utc>,
+
+    /// What happened
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Kinds of engine events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// The engine moved to another lifecycle state
+    StateChanged {
+        /// State before the change
+        from: EngineState,
+        /// State after the change
+        to: EngineState,
+    },
+
+    /// A block started failing; repeated failures are not reported again
+    BlockError {
+        /// Block name
+        block: String,
+        /// Error of the first failure
+        error: String,
+    },
+
+    /// A failing block executed successfully again
+    BlockRecovered {
+        /// Block name
+        block: String,
+    },
+
+    /// A protocol driver connected
+    ProtocolConnected {
+        /// Driver name
+        protocol: String,
+    },
+
+    /// A protocol driver disconnected or stopped
+    ProtocolDisconnected {
+        /// Driver name
+        protocol: String,
+        /// Error that ended the connection, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+
+    /// A verified write was not read back from the device in time
+    WriteVerifyFailed {
+        /// Driver or failover group written to
+        protocol: String,
+        /// Protocol address written
+        address: String,
+        /// Signal the value came from
+        signal: String,
+        /// Value written
+        written: Value,
+        /// Last value read back, if any read succeeded
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        read: Option<Value>,
+    },
+
+    /// A write kept failing and its dead letter ran out of retries
+    WriteDeadLettered {
+        /// Driver or failover group written to
+        protocol: String,
+        /// Protocol address written
+        address: String,
+        /// Value held for an operator to retry or discard
+        value: Value,
+        /// Error of the last attempt
+        error: String,
+        /// Failed attempts so far
+        attempts: u32,
+    },
+
+    /// This node of a redundant pair took over or gave up control
+    RedundancyRoleChanged {
+        /// Role before the change: `starting`, `active` or `standby`
+        from: String,
+        /// Role after the change
+        to: String,
+        /// Why the role changed
+        reason: String,
+    },
+
+    /// An area was put in or taken out of maintenance
+    MaintenanceModeChanged {
+        /// Maintenance area
+        area: String,
+        /// Whether the area is now in maintenance
+        active: bool,
+        /// User who made the change
+        user: String,
+        /// Reason given when entering maintenance
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+
+    /// No healthy scan cycle completed within the watchdog timeout; the
+    /// safe state was applied
+    WatchdogTripped {
+        /// Time since the last healthy cycle
+        stalled_ms: u64,
+    },
+
+    /// A watchdog trip was cleared
+    WatchdogReset {
+        /// `reset` for a requested reset, otherwise why it cleared
+        reason: String,
+    },
+
+    /// A new configuration was applied to the running engine
+    ConfigApplied {
+        /// Active blocks after the change
+        blocks: usize,
+        /// Signals created by the new configuration
+        new_signals: usize,
+    },
+}
+
+impl EventKind {
+    /// Name of the kind, as serialized in the `event` field
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::StateChanged { .. } => "state_changed",
+            Self::BlockError { .. } => "block_error",
+            Self::BlockRecovered { .. } => "block_recovered",
+            Self::ProtocolConnected { .. } => "protocol_connected",
+            Self::ProtocolDisconnected { .. } => "protocol_disconnected",
+            Self::WriteVerifyFailed { .. } => "write_verify_failed",
+            Self::WriteDeadLettered { .. } => "write_dead_lettered",
+            Self::RedundancyRoleChanged { .. } => "redundancy_role_changed",
+            Self::MaintenanceModeChanged { .. } => "maintenance_mode_changed",
+            Self::WatchdogTripped { .. } => "watchdog_tripped",
+            Self::WatchdogReset { .. } => "watchdog_reset",
+            Self::ConfigApplied { .. } => "config_applied",
+        }
+    }
+}
+
+/// Retained events and the latest sequence number
+struct History {
+    events: VecDeque<EngineEvent>,
+    capacity: usize,
+    last_sequence: u64,
+}
+
+impl History {
+    /// Retained events after `sequence`
+    fn after(&self, sequence: u64) -> VecDeque<EngineEvent> {
+        self.events.iter().filter(|e| e.sequence > sequence).cloned().collect()
+    }
+}
+
+/// Publisher of engine events with a bounded history
+///
+/// Cloning yields another handle to the same stream.
+#[derive(Clone)]
+pub struct EventLog {
+    history: Arc<Mutex<History>>,
+    sender: broadcast::Sender<EngineEvent>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY)
+    }
+}
+
+impl EventLog {
+    /// Create a log retaining the last `capacity` events
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: Arc::new(Mutex::new(History {
+                events: VecDeque::with_capacity(capacity.min(DEFAULT_HISTORY)),
+                capacity,
+                last_sequence: 0,
+            })),
+            sender: broadcast::channel(LIVE_BUFFER).0,
+        }
+    }
+
+    /// Publish an event, returning its sequence number
+    pub fn publish(&self, kind: EventKind) -> u64 {
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        history.last_sequence += 1;
+        let event = EngineEvent {
+            sequence: history.last_sequence,
+            timestamp: Utc::now(),
+            kind,
+        };
+        if history.capacity > 0 {
+            if history.events.len() == history.capacity {
+                history.events.pop_front();
+            }
+            history.events.push_back(event.clone());
+        }
+        // Sent under the lock so subscribers see each event exactly once
+        let _ = self.sender.send(event);
+        history.last_sequence
+    }
+
+    /// Sequence number of the latest event, 0 before the first
+    #[must_use]
+    pub fn last_sequence(&self) -> u64 {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner).last_sequence
+    }
+
+    /// Retained events after `sequence`, oldest first
+    #[must_use]
+    pub fn since(&self, sequence: u64) -> Vec<EngineEvent> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        history.after(sequence).into()
+    }
+
+    /// Follow the stream
+    ///
+    /// With `after` set, retained events after that sequence are replayed
+    /// first; `Some(0)` replays the whole history. Without it only events
+    /// published from now on are delivered.
+    ///
+    /// Sequence numbers restart with the engine, so a resume point beyond
+    /// the latest event also replays the whole history.
+    #[must_use]
+    pub fn subscribe(&self, after: Option<u64>) -> EventSubscription {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let last = match after {
+            Some(sequence) if sequence <= history.last_sequence => sequence,
+            Some(_) => 0,
+            None => history.last_sequence,
+        };
+        EventSubscription {
+            pending: history.after(last),
+            receiver: self.sender.subscribe(),
+            history: Arc::clone(&self.history),
+            last,
+        }
+    }
+}
+
+/// Subscriber's position in the event stream
+pub struct EventSubscription {
+    pending: VecDeque<EngineEvent>,
+    receiver: broadcast::Receiver<EngineEvent>,
+    history: Arc<Mutex<History>>,
+    last: u64,
+}
+
+impl EventSubscription {
+    /// Wait for the next event
+    ///
+    /// A subscriber that falls behind the live stream catches up from the
+    /// history. Returns `None` once every [`EventLog`] handle is dropped.
+    pub async fn next(&mut self) -> Option<EngineEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.last = event.sequence;
+                return Some(event);
+            }
+            match self.receiver.recv().await {
+                Ok(event) if event.sequence > self.last => {
+                    self.last = event.sequence;
+                    return Some(event);
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+                    self.pending = history.after(self.last);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Sequence number of the last event delivered
+    #[must_use]
+    pub const fn last_sequence(&self) -> u64 {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(blocks: usize) -> EventKind {
+        EventKind::ConfigApplied { blocks, new_signals: 0 }
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_sequence() {
+        let log = EventLog::new(3);
+        for blocks in 1..=5 {
+            log.publish(applied(blocks));
+        }
+        assert_eq!(log.last_sequence(), 5);
+
+        // Sequences 1 and 2 have been dropped from the history
+        let mut events = log.subscribe(Some(1));
+        assert_eq!(events.next().await.unwrap().sequence, 3);
+        assert_eq!(events.next().await.unwrap().sequence, 4);
+        assert_eq!(events.next().await.unwrap().sequence, 5);
+
+        log.publish(applied(6));
+        let event = events.next().await.unwrap();
+        assert_eq!(event.sequence, 6);
+        assert_eq!(event.kind, applied(6));
+
+        let mut live = log.subscribe(None);
+        log.publish(applied(7));
+        assert_eq!(live.next().await.unwrap().sequence, 7);
+        assert_eq!(log.since(5).len(), 2);
+
+        // A resume point from before a restart replays the history
+        let mut restarted = log.subscribe(Some(1000));
+        assert_eq!(restarted.next().await.unwrap().sequence, 5);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber_catches_up_from_history() {
+        let log = EventLog::new(200);
+        let mut events = log.subscribe(None);
+        for blocks in 0..100 {
+            log.publish(applied(blocks));
+        }
+        for sequence in 1..=100 {
+            assert_eq!(events.next().await.unwrap().sequence, sequence);
+        }
+
+        let json = serde_json::to_value(log.since(99)).unwrap();
+        assert_eq!(json[0]["event"], "config_applied");
+        assert_eq!(json[0]["sequence"], 100);
+    }
+}
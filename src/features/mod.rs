@@ -5,8 +5,11 @@
 //! - Feature dependency validation
 //! - Feature conflict detection
 //! - Feature information and reporting
+//! - A serializable [`FeatureReport`] for remote inspection, served by
+//!   `GET /api/features` and the gRPC `GetFeatures` call
 
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 
 /// Runtime feature detection and information
@@ -31,6 +34,11 @@ pub struct ProtocolFeatures {
     pub s7: bool,
     pub modbus: bool,
     pub opcua: bool,
+    pub dnp3: bool,
+    pub ethercat: bool,
+    pub profinet: bool,
+    pub coap: bool,
+    pub http: bool,
 }
 
 pub struct StorageFeatures {
@@ -56,6 +64,10 @@ impl RuntimeFeatures {
             enabled.insert("mqtt".to_string());
             categories.entry("Protocols".to_string()).or_default().push("mqtt".to_string());
         }
+        if cfg!(feature = "mqtt-sparkplug") {
+            enabled.insert("mqtt-sparkplug".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("mqtt-sparkplug".to_string());
+        }
         
         if cfg!(feature = "optimized") {
             enabled.insert("optimized".to_string());
@@ -81,6 +93,14 @@ impl RuntimeFeatures {
             enabled.insert("metrics".to_string());
             categories.entry("Monitoring".to_string()).or_default().push("metrics".to_string());
         }
+        if cfg!(feature = "time-sync") {
+            enabled.insert("time-sync".to_string());
+            categories.entry("Monitoring".to_string()).or_default().push("time-sync".to_string());
+        }
+        if cfg!(feature = "scan-budget") {
+            enabled.insert("scan-budget".to_string());
+            categories.entry("Monitoring".to_string()).or_default().push("scan-budget".to_string());
+        }
         
         // Protocol features
         if cfg!(feature = "s7-support") {
@@ -95,6 +115,30 @@ impl RuntimeFeatures {
             enabled.insert("opcua-support".to_string());
             categories.entry("Protocols".to_string()).or_default().push("opcua-support".to_string());
         }
+        if cfg!(feature = "opcua-server") {
+            enabled.insert("opcua-server".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("opcua-server".to_string());
+        }
+        if cfg!(feature = "dnp3-support") {
+            enabled.insert("dnp3-support".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("dnp3-support".to_string());
+        }
+        if cfg!(feature = "ethercat") {
+            enabled.insert("ethercat".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("ethercat".to_string());
+        }
+        if cfg!(feature = "profinet") {
+            enabled.insert("profinet".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("profinet".to_string());
+        }
+        if cfg!(feature = "coap") {
+            enabled.insert("coap".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("coap".to_string());
+        }
+        if cfg!(feature = "http") {
+            enabled.insert("http".to_string());
+            categories.entry("Protocols".to_string()).or_default().push("http".to_string());
+        }
         
         // Storage features
         if cfg!(feature = "history") {
@@ -143,6 +187,10 @@ impl RuntimeFeatures {
             enabled.insert("audit".to_string());
             categories.entry("Security".to_string()).or_default().push("audit".to_string());
         }
+        if cfg!(feature = "esignature") {
+            enabled.insert("esignature".to_string());
+            categories.entry("Security".to_string()).or_default().push("esignature".to_string());
+        }
         if cfg!(feature = "signing") {
             enabled.insert("signing".to_string());
             categories.entry("Security".to_string()).or_default().push("signing".to_string());
@@ -197,12 +245,42 @@ impl RuntimeFeatures {
             enabled.insert("twilio".to_string());
             categories.entry("Alarms".to_string()).or_default().push("twilio".to_string());
         }
+        if cfg!(feature = "maintenance-mode") {
+            enabled.insert("maintenance-mode".to_string());
+            categories.entry("Alarms".to_string()).or_default().push("maintenance-mode".to_string());
+        }
+        
+        // Production analytics features
+        if cfg!(feature = "oee") {
+            enabled.insert("oee".to_string());
+            categories.entry("Analytics".to_string()).or_default().push("oee".to_string());
+        }
+        if cfg!(feature = "downtime") {
+            enabled.insert("downtime".to_string());
+            categories.entry("Analytics".to_string()).or_default().push("downtime".to_string());
+        }
+        if cfg!(feature = "maintenance") {
+            enabled.insert("maintenance".to_string());
+            categories.entry("Analytics".to_string()).or_default().push("maintenance".to_string());
+        }
+        if cfg!(feature = "energy") {
+            enabled.insert("energy".to_string());
+            categories.entry("Analytics".to_string()).or_default().push("energy".to_string());
+        }
+        if cfg!(feature = "reports") {
+            enabled.insert("reports".to_string());
+            categories.entry("Analytics".to_string()).or_default().push("reports".to_string());
+        }
         
         // Web features
         if cfg!(feature = "web") {
             enabled.insert("web".to_string());
             categories.entry("Web/API".to_string()).or_default().push("web".to_string());
         }
+        if cfg!(feature = "grpc") {
+            enabled.insert("grpc".to_string());
+            categories.entry("Web/API".to_string()).or_default().push("grpc".to_string());
+        }
         if cfg!(feature = "health") {
             enabled.insert("health".to_string());
             categories.entry("Web/API".to_string()).or_default().push("health".to_string());
@@ -217,6 +295,10 @@ impl RuntimeFeatures {
             enabled.insert("examples".to_string());
             categories.entry("Development".to_string()).or_default().push("examples".to_string());
         }
+        if cfg!(feature = "simulation") {
+            enabled.insert("simulation".to_string());
+            categories.entry("Development".to_string()).or_default().push("simulation".to_string());
+        }
         if cfg!(feature = "burn-in") {
             enabled.insert("burn-in".to_string());
             categories.entry("Development".to_string()).or_default().push("burn-in".to_string());
@@ -284,6 +366,11 @@ impl RuntimeFeatures {
             s7: cfg!(feature = "s7-support"),
             modbus: cfg!(feature = "modbus-support"),
             opcua: cfg!(feature = "opcua-support"),
+            dnp3: cfg!(feature = "dnp3-support"),
+            ethercat: cfg!(feature = "ethercat"),
+            profinet: cfg!(feature = "profinet"),
+            coap: cfg!(feature = "coap"),
+            http: cfg!(feature = "http"),
         };
 
         let storage = StorageFeatures {
@@ -337,6 +424,36 @@ impl RuntimeFeatures {
         self.protocols.opcua || self.enabled.contains("opcua-support")
     }
 
+    /// Check if DNP3 protocol support is enabled
+    #[must_use]
+    pub fn has_dnp3(&self) -> bool {
+        self.protocols.dnp3 || self.enabled.contains("dnp3-support")
+    }
+
+    /// Check if `EtherCAT` master support is enabled
+    #[must_use]
+    pub fn has_ethercat(&self) -> bool {
+        self.protocols.ethercat || self.enabled.contains("ethercat")
+    }
+
+    /// Check if PROFINET IO device support is enabled
+    #[must_use]
+    pub fn has_profinet(&self) -> bool {
+        self.protocols.profinet || self.enabled.contains("profinet")
+    }
+
+    /// Check if CoAP client support is enabled
+    #[must_use]
+    pub fn has_coap(&self) -> bool {
+        self.protocols.coap || self.enabled.contains("coap")
+    }
+
+    /// Check if the HTTP polling driver is enabled
+    #[must_use]
+    pub fn has_http(&self) -> bool {
+        self.protocols.http || self.enabled.contains("http")
+    }
+
     /// Check if web features are enabled
     pub fn has_web(&self) -> bool {
         self.enabled.contains("web")
@@ -533,6 +650,55 @@ impl RuntimeFeatures {
     fn count_category(&self, category: &str) -> usize {
         self.categories.get(category).map(|v| v.len()).unwrap_or(0)
     }
+    
+    /// Build capabilities in a form that can be sent to another system
+    #[must_use]
+    pub fn to_report(&self) -> FeatureReport {
+        let categories = self
+            .categories
+            .iter()
+            .filter(|(_, features)| !features.is_empty())
+            .map(|(category, features)| {
+                let mut features = features.clone();
+                features.sort();
+                features.dedup();
+                (category.clone(), features)
+            })
+            .collect();
+        
+        FeatureReport {
+            version: crate::VERSION.to_string(),
+            enabled: self.enabled_features().into_iter().map(str::to_string).collect(),
+            categories,
+            bundles: self.detect_bundles().into_iter().map(str::to_string).collect(),
+            validation_errors: self.validate().err().unwrap_or_default(),
+        }
+    }
+}
+
+/// Build capabilities of a node, for fleet management tools
+/// 
+/// Lists are sorted so reports of identical builds compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureReport {
+    /// PETRA version
+    pub version: String,
+    /// Compiled-in features
+    pub enabled: Vec<String>,
+    /// Compiled-in features by category
+    pub categories: BTreeMap<String, Vec<String>>,
+    /// Feature bundles the build satisfies
+    pub bundles: Vec<String>,
+    /// Conflicts and missing dependencies, empty for a valid combination
+    pub validation_errors: Vec<String>,
+}
+
+impl FeatureReport {
+    /// Whether the feature combination passed validation
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.validation_errors.is_empty()
+    }
 }
 
 /// Feature configuration summary
@@ -642,4 +808,19 @@ mod tests {
         // Should have at least one category
         assert!(!categories.is_empty());
     }
+    
+    #[test]
+    fn test_feature_report_round_trip() {
+        let features = RuntimeFeatures::detect();
+        let report = features.to_report();
+        
+        assert_eq!(report.version, crate::VERSION);
+        assert!(report.is_valid());
+        assert_eq!(report.enabled.len(), features.enabled.len());
+        assert!(report.enabled.windows(2).all(|w| w[0] < w[1]));
+        assert!(report.categories.values().flatten().all(|f| features.is_enabled(f)));
+        
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<FeatureReport>(&json).unwrap(), report);
+    }
 }
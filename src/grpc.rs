@@ -0,0 +1,885 @@
+// src/grpc.rs
+//! gRPC API for signal access and engine control
+//!
+//! Serves the `petra.v1.Petra` service from `proto/petra.proto` so
+//! orchestration systems can read, write and watch signals and pause, resume
+//! or reload the engine. Clients authenticate with a bearer token in the
+//! `authorization` metadata and every call is checked against the client's
+//! role, using the same permissions as the other command channels:
+//!
+//! ```yaml
+//! grpc:
+//!   bind_address: "0.0.0.0:50051"
+//!   clients:
+//!     - { id: orchestrator, token: "change-me-please!", role: supervisor }
+//!   rbac:
+//!     roles:
+//!       supervisor:
+//!         permissions:
+//!           - "signal.read:*"
+//!           - "signal.write:line1.*"
+//!           - "engine.control:*"
+//! ```
+//!
+//! Engine control resources are `start`, `stop` and `reload`;
+//! `GetEngineStatus` and `GetFeatures` only need a valid token.
+//! `StreamEvents` only delivers the event kinds the role may read, checked
+//! as `event.read:<kind>` with kinds such as `state_changed` or
+//! `block_error`; a client resumes after a reconnect by passing the last
+//! sequence number it received. Writes and
+//! control calls are appended to the audit log whether or not they succeed;
+//! each written signal is recorded with its old and new value and the
+//! request's `reason`, which writes to critical signals must give. With
+//! the `esignature` feature, writes covered by a signature rule also need
+//! the request's `signature`; one signature signs the whole request.
+
+// Handlers return tonic's `Status`, which is large but the only error type
+// the generated service accepts
+#![allow(clippy::result_large_err)]
+
+use crate::config::Config;
+use crate::engine::{EngineControl, ReloadHandle};
+use crate::events::{EngineEvent, EventKind};
+use crate::features::FeatureReport;
+use crate::security::audit::{AuditEntry, AuditLog, SignatureRecord, DEFAULT_AUDIT_LOG_PATH};
+#[cfg(feature = "esignature")]
+use crate::security::esignature::{ESignature, ESignatureVerifier};
+use crate::security::rbac::{
+    RbacConfig, ENGINE_CONTROL_PERMISSION, READ_EVENTS_PERMISSION, READ_SIGNAL_PERMISSION,
+    WRITE_SIGNAL_PERMISSION,
+};
+use crate::protocols::ChangeTracker;
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::Utc;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+#[cfg(feature = "esignature")]
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+/// Generated protobuf messages and service traits
+#[allow(clippy::all, clippy::pedantic, missing_docs)]
+pub mod proto {
+    tonic::include_proto!("petra.v1");
+}
+
+use proto::petra_server::{Petra, PetraServer};
+use proto::value::Kind;
+
+/// Poll interval of `StreamSignalChanges` when the request sets none
+const DEFAULT_STREAM_INTERVAL_MS: u32 = 100;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// gRPC server configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcConfig {
+    /// Listen address
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+
+    /// Clients allowed to call the service
+    pub clients: Vec<GrpcClient>,
+
+    /// Roles and their permissions
+    #[serde(default)]
+    pub rbac: RbacConfig,
+
+    /// Audit log file
+    #[serde(default = "default_audit_log")]
+    pub audit_log: PathBuf,
+}
+
+/// A remote system allowed to call the service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcClient {
+    /// Client name recorded in the audit log
+    pub id: String,
+    /// Bearer token sent in the `authorization` metadata
+    pub token: String,
+    /// Role checked against the RBAC configuration
+    pub role: String,
+}
+
+fn default_bind_address() -> String {
+    "0.0.0.0:50051".to_string()
+}
+
+fn default_audit_log() -> PathBuf {
+    PathBuf::from(DEFAULT_AUDIT_LOG_PATH)
+}
+
+impl GrpcConfig {
+    /// Validate the listen address, clients and roles
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        self.bind_address.parse::<SocketAddr>().map_err(|e| {
+            PlcError::Config(format!("Invalid gRPC bind address '{}': {e}", self.bind_address))
+        })?;
+        if self.clients.is_empty() {
+            return Err(PlcError::Config("gRPC requires at least one client".to_string()));
+        }
+        self.rbac.validate()?;
+
+        let mut ids = HashSet::new();
+        let mut tokens = HashSet::new();
+        for client in &self.clients {
+            if !ids.insert(client.id.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate gRPC client '{}'", client.id)));
+            }
+            if client.token.len() < 16 {
+                return Err(PlcError::Config(format!(
+                    "gRPC client '{}' token must be at least 16 characters",
+                    client.id
+                )));
+            }
+            if !tokens.insert(client.token.as_str()) {
+                return Err(PlcError::Config(format!(
+                    "gRPC client '{}' shares its token with another client",
+                    client.id
+                )));
+            }
+            if !self.rbac.roles.contains_key(&client.role) {
+                return Err(PlcError::Config(format!(
+                    "gRPC client '{}' has unknown role '{}'",
+                    client.id, client.role
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// VALUE CONVERSION
+// ============================================================================
+
+impl From<&Value> for proto::Value {
+    fn from(value: &Value) -> Self {
+        let kind = match value {
+            Value::Bool(b) => Kind::BoolValue(*b),
+            Value::Integer(i) => Kind::IntValue(*i),
+            Value::Float(f) => Kind::FloatValue(*f),
+            #[allow(unreachable_patterns)]
+            other => Kind::StringValue(other.to_string()),
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<proto::Value> for Value {
+    type Error = PlcError;
+
+    fn try_from(value: proto::Value) -> Result<Self> {
+        match value.kind {
+            Some(Kind::BoolValue(b)) => Ok(Self::Bool(b)),
+            Some(Kind::IntValue(i)) => Ok(Self::Integer(i)),
+            Some(Kind::FloatValue(f)) => Ok(Self::Float(f)),
+            #[cfg(feature = "extended-types")]
+            Some(Kind::StringValue(s)) => Ok(Self::String(s)),
+            #[cfg(not(feature = "extended-types"))]
+            Some(Kind::StringValue(_)) => Err(PlcError::Validation(
+                "String values require the extended-types feature".to_string(),
+            )),
+            None => Err(PlcError::Validation("Value has no kind".to_string())),
+        }
+    }
+}
+
+impl From<FeatureReport> for proto::FeatureReport {
+    fn from(report: FeatureReport) -> Self {
+        Self {
+            version: report.version,
+            enabled: report.enabled,
+            categories: report
+                .categories
+                .into_iter()
+                .map(|(category, features)| (category, proto::FeatureList { features }))
+                .collect(),
+            bundles: report.bundles,
+            validation_errors: report.validation_errors,
+        }
+    }
+}
+
+pub(crate) fn signal_message(name: String, value: &Value) -> proto::Signal {
+    proto::Signal {
+        name,
+        value: Some(value.into()),
+        timestamp_ms: Utc::now().timestamp_millis(),
+    }
+}
+
+impl From<&EngineEvent> for proto::Event {
+    fn from(event: &EngineEvent) -> Self {
+        use proto::event;
+
+        let count = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+        let kind = match &event.kind {
+            EventKind::StateChanged { from, to } => event::Kind::StateChanged(event::StateChanged {
+                from: format!("{from:?}"),
+                to: format!("{to:?}"),
+            }),
+            EventKind::BlockError { block, error } => event::Kind::BlockError(event::BlockError {
+                block: block.clone(),
+                error: error.clone(),
+            }),
+            EventKind::BlockRecovered { block } => {
+                event::Kind::BlockRecovered(event::BlockRecovered { block: block.clone() })
+            }
+            EventKind::ProtocolConnected { protocol } => {
+                event::Kind::ProtocolConnected(event::ProtocolConnected { protocol: protocol.clone() })
+            }
+            EventKind::ProtocolDisconnected { protocol, reason } => {
+                event::Kind::ProtocolDisconnected(event::ProtocolDisconnected {
+                    protocol: protocol.clone(),
+                    reason: reason.clone().unwrap_or_default(),
+                })
+            }
+            EventKind::WriteVerifyFailed { protocol, address, signal, written, read } => {
+                event::Kind::WriteVerifyFailed(event::WriteVerifyFailed {
+                    protocol: protocol.clone(),
+                    address: address.clone(),
+                    signal: signal.clone(),
+                    written: Some(written.into()),
+                    read: read.as_ref().map(Into::into),
+                })
+            }
+            EventKind::WriteDeadLettered { protocol, address, value, error, attempts } => {
+                event::Kind::WriteDeadLettered(event::WriteDeadLettered {
+                    protocol: protocol.clone(),
+                    address: address.clone(),
+                    value: Some(value.into()),
+                    error: error.clone(),
+                    attempts: *attempts,
+                })
+            }
+            EventKind::RedundancyRoleChanged { from, to, reason } => {
+                event::Kind::RedundancyRoleChanged(event::RedundancyRoleChanged {
+                    from: from.clone(),
+                    to: to.clone(),
+                    reason: reason.clone(),
+                })
+            }
+            EventKind::MaintenanceModeChanged { area, active, user, reason } => {
+                event::Kind::MaintenanceModeChanged(event::MaintenanceModeChanged {
+                    area: area.clone(),
+                    active: *active,
+                    user: user.clone(),
+                    reason: reason.clone().unwrap_or_default(),
+                })
+            }
+            EventKind::WatchdogTripped { stalled_ms } => {
+                event::Kind::WatchdogTripped(event::WatchdogTripped { stalled_ms: *stalled_ms })
+            }
+            EventKind::WatchdogReset { reason } => {
+                event::Kind::WatchdogReset(event::WatchdogReset { reason: reason.clone() })
+            }
+            EventKind::ConfigApplied { blocks, new_signals } => {
+                event::Kind::ConfigApplied(event::ConfigApplied {
+                    blocks: count(*blocks),
+                    new_signals: count(*new_signals),
+                })
+            }
+        };
+        Self {
+            sequence: event.sequence,
+            timestamp_ms: event.timestamp.timestamp_millis(),
+            kind: Some(kind),
+        }
+    }
+}
+
+fn status(e: &PlcError) -> Status {
+    match e {
+        PlcError::NotFound(_) | PlcError::SignalNotFound(_) => Status::not_found(e.to_string()),
+        PlcError::Config(_) | PlcError::Validation(_) | PlcError::Yaml(_) => {
+            Status::invalid_argument(e.to_string())
+        }
+        PlcError::AuthenticationFailed(_) => Status::unauthenticated(e.to_string()),
+        PlcError::AuthorizationDenied(_) => Status::permission_denied(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+// ============================================================================
+// SERVICE
+// ============================================================================
+
+/// Authenticated client of one call
+struct Caller {
+    id: String,
+    role: String,
+    source: String,
+}
+
+/// `petra.v1.Petra` service implementation
+pub struct PetraService {
+    config: GrpcConfig,
+    bus: SignalBus,
+    engine: EngineControl,
+    reload: ReloadHandle,
+    config_path: Option<PathBuf>,
+    audit: AuditLog,
+    #[cfg(feature = "esignature")]
+    esignature: Option<Arc<ESignatureVerifier>>,
+}
+
+impl PetraService {
+    /// Create the service for a running engine
+    ///
+    /// `config_path` is reloaded when `ReloadConfig` carries no configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the audit log
+    /// cannot be opened.
+    pub fn new(
+        config: GrpcConfig,
+        bus: SignalBus,
+        engine: EngineControl,
+        reload: ReloadHandle,
+        config_path: Option<PathBuf>,
+    ) -> Result<Self> {
+        config.validate()?;
+        let audit = AuditLog::new(&config.audit_log)?;
+        Ok(Self {
+            config,
+            bus,
+            engine,
+            reload,
+            config_path,
+            audit,
+            #[cfg(feature = "esignature")]
+            esignature: None,
+        })
+    }
+
+    /// Require a reason for writes to signals matching `patterns`
+    #[must_use]
+    pub fn with_critical_signals(mut self, patterns: Vec<String>) -> Self {
+        self.audit = self.audit.with_critical_signals(patterns);
+        self
+    }
+
+    /// Require signatures on writes covered by `verifier`'s rules
+    #[cfg(feature = "esignature")]
+    #[must_use]
+    pub fn with_esignature(mut self, verifier: Arc<ESignatureVerifier>) -> Self {
+        self.esignature = Some(verifier);
+        self
+    }
+
+    /// Verify the request's signature against its first critical write
+    #[cfg(feature = "esignature")]
+    fn check_signature(
+        &self,
+        writes: &[(String, Value)],
+        signature: Option<&ESignature>,
+        reason: Option<&str>,
+    ) -> std::result::Result<Option<SignatureRecord>, Status> {
+        let Some(verifier) = &self.esignature else {
+            return Ok(None);
+        };
+        writes
+            .iter()
+            .find(|(name, value)| verifier.requires_signature(name, value))
+            .map_or(Ok(None), |(name, value)| verifier.check_write(name, value, signature, reason))
+            .map_err(|e| status(&e))
+    }
+
+    /// Identify the caller from its bearer token
+    fn authenticate<T>(&self, request: &Request<T>) -> std::result::Result<Caller, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+        let client = self
+            .config
+            .clients
+            .iter()
+            .find(|c| constant_time_eq(c.token.as_bytes(), token.as_bytes()))
+            .ok_or_else(|| Status::unauthenticated("Invalid token"))?;
+        Ok(Caller {
+            id: client.id.clone(),
+            role: client.role.clone(),
+            source: request
+                .remote_addr()
+                .map_or_else(|| "grpc".to_string(), |addr| addr.ip().to_string()),
+        })
+    }
+
+    fn authorize(&self, caller: &Caller, action: &str, resource: &str) -> std::result::Result<(), Status> {
+        self.config
+            .rbac
+            .check(&caller.role, action, resource)
+            .map_err(|e| status(&e))
+    }
+
+    fn audit<T>(&self, caller: &Caller, action: &str, details: String, result: &std::result::Result<T, Status>) {
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            user: caller.id.clone(),
+            source_ip: caller.source.clone(),
+            action: action.to_string(),
+            details: match result {
+                Ok(_) => details,
+                Err(e) => format!("{details}: rejected: {}", e.message()),
+            },
+            success: result.is_ok(),
+            ..AuditEntry::default()
+        };
+        if let Err(e) = self.audit.record(&entry) {
+            error!("Failed to write audit entry for gRPC call: {}", e);
+        }
+    }
+
+    async fn engine_status(&self) -> proto::EngineStatus {
+        proto::EngineStatus {
+            state: format!("{:?}", self.engine.state().await),
+            paused: self.engine.is_paused(),
+            scan_count: self.engine.scan_count(),
+            error_count: self.engine.error_count(),
+        }
+    }
+
+    async fn reload_config(&self, config_yaml: &str) -> std::result::Result<usize, Status> {
+        let config = if config_yaml.is_empty() {
+            let path = self
+                .config_path
+                .as_ref()
+                .ok_or_else(|| Status::failed_precondition("No configuration file to reload"))?;
+            Config::from_file(path).map_err(|e| status(&e))?
+        } else {
+            let mut config = serde_yaml::from_str::<Config>(config_yaml).map_err(|e| status(&e.into()))?;
+            config.expand().map_err(|e| status(&e))?;
+            config
+        };
+        self.reload.apply(&config).await.map_err(|e| status(&e))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Stream of signal changes returned by `StreamSignalChanges`
+pub type SignalStream = Pin<Box<dyn Stream<Item = std::result::Result<proto::Signal, Status>> + Send>>;
+
+/// Stream of engine events returned by `StreamEvents`
+pub type EventStream = Pin<Box<dyn Stream<Item = std::result::Result<proto::Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Petra for PetraService {
+    async fn read_signals(
+        &self,
+        request: Request<proto::ReadSignalsRequest>,
+    ) -> std::result::Result<Response<proto::ReadSignalsResponse>, Status> {
+        let caller = self.authenticate(&request)?;
+        let names = request.into_inner().names;
+
+        let signals = if names.is_empty() {
+            let mut names = self.bus.signal_names();
+            names.sort();
+            names
+                .into_iter()
+                .filter(|name| self.config.rbac.is_allowed(&caller.role, READ_SIGNAL_PERMISSION, name))
+                .filter_map(|name| self.bus.get(&name).map(|value| signal_message(name, &value)))
+                .collect()
+        } else {
+            let mut signals = Vec::with_capacity(names.len());
+            for name in names {
+                self.authorize(&caller, READ_SIGNAL_PERMISSION, &name)?;
+                let value = self
+                    .bus
+                    .get(&name)
+                    .ok_or_else(|| Status::not_found(format!("Signal '{name}' not found")))?;
+                signals.push(signal_message(name, &value));
+            }
+            signals
+        };
+        Ok(Response::new(proto::ReadSignalsResponse { signals }))
+    }
+
+    async fn write_signals(
+        &self,
+        request: Request<proto::WriteSignalsRequest>,
+    ) -> std::result::Result<Response<proto::WriteSignalsResponse>, Status> {
+        let caller = self.authenticate(&request)?;
+        let request = request.into_inner();
+        let reason = Some(request.reason.as_str()).filter(|r| !r.is_empty());
+        let details = request.signals.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(",");
+        #[cfg(feature = "esignature")]
+        let signature = request.signature.map(|s| ESignature {
+            user: s.user,
+            password: s.password,
+            meaning: s.meaning,
+        });
+
+        // Check everything first so a denied write leaves the bus untouched
+        let checked = (|| {
+            let mut writes = Vec::with_capacity(request.signals.len());
+            for signal in request.signals {
+                self.authorize(&caller, WRITE_SIGNAL_PERMISSION, &signal.name)?;
+                if !self.bus.exists(&signal.name) {
+                    return Err(Status::not_found(format!("Signal '{}' not found", signal.name)));
+                }
+                let value = signal
+                    .value
+                    .ok_or_else(|| Status::invalid_argument(format!("Signal '{}' has no value", signal.name)))?;
+                self.audit.require_reason(&signal.name, reason).map_err(|e| status(&e))?;
+                writes.push((signal.name, Value::try_from(value).map_err(|e| status(&e))?));
+            }
+            #[cfg(feature = "esignature")]
+            let record = self.check_signature(&writes, signature.as_ref(), reason)?;
+            #[cfg(not(feature = "esignature"))]
+            let record: Option<SignatureRecord> = None;
+            Ok((writes, record))
+        })();
+        if checked.is_err() {
+            self.audit(&caller, WRITE_SIGNAL_PERMISSION, details, &checked);
+        }
+        let (writes, record) = checked?;
+
+        for (name, value) in &writes {
+            let old_value = self.bus.get(name);
+            let result = self.bus.set(name, value.clone()).map_err(|e| e.to_string());
+            let entry = AuditEntry::signal_write(
+                &caller.id,
+                &caller.source,
+                name,
+                old_value,
+                value.clone(),
+                reason,
+                result.as_ref().copied().map_err(String::as_str),
+            )
+            .with_signature(record.clone());
+            if let Err(e) = self.audit.record(&entry) {
+                error!("Failed to write audit entry for gRPC call: {}", e);
+            }
+            result.map_err(Status::internal)?;
+        }
+
+        let written = u32::try_from(writes.len()).unwrap_or(u32::MAX);
+        Ok(Response::new(proto::WriteSignalsResponse { written }))
+    }
+
+    type StreamSignalChangesStream = SignalStream;
+
+    async fn stream_signal_changes(
+        &self,
+        request: Request<proto::StreamSignalChangesRequest>,
+    ) -> std::result::Result<Response<Self::StreamSignalChangesStream>, Status> {
+        let caller = self.authenticate(&request)?;
+        let request = request.into_inner();
+        for name in &request.names {
+            self.authorize(&caller, READ_SIGNAL_PERMISSION, name)?;
+        }
+
+        let interval_ms = if request.interval_ms == 0 {
+            DEFAULT_STREAM_INTERVAL_MS
+        } else {
+            request.interval_ms
+        };
+        let bus = self.bus.clone();
+        let rbac = self.config.rbac.clone();
+        let (tx, rx) = mpsc::channel(64);
+
+        debug!("gRPC client '{}' streaming {} signals", caller.id, request.names.len());
+        tokio::spawn(async move {
+            let mut tracker = ChangeTracker::new(request.names);
+            let mut interval = tokio::time::interval(Duration::from_millis(u64::from(interval_ms)));
+            loop {
+                interval.tick().await;
+                for (name, value) in tracker.changes(&bus) {
+                    if !rbac.is_allowed(&caller.role, READ_SIGNAL_PERMISSION, &name) {
+                        continue;
+                    }
+                    if tx.send(Ok(signal_message(name, &value))).await.is_err() {
+                        debug!("gRPC client '{}' stopped streaming", caller.id);
+                        return;
+                    }
+                }
+            }
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|item| (item, rx)) });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_engine_status(
+        &self,
+        request: Request<proto::GetEngineStatusRequest>,
+    ) -> std::result::Result<Response<proto::EngineStatus>, Status> {
+        self.authenticate(&request)?;
+        Ok(Response::new(self.engine_status().await))
+    }
+
+    async fn get_features(
+        &self,
+        request: Request<proto::GetFeaturesRequest>,
+    ) -> std::result::Result<Response<proto::FeatureReport>, Status> {
+        self.authenticate(&request)?;
+        Ok(Response::new(crate::features::current().to_report().into()))
+    }
+
+    async fn start_engine(
+        &self,
+        request: Request<proto::StartEngineRequest>,
+    ) -> std::result::Result<Response<proto::EngineStatus>, Status> {
+        let caller = self.authenticate(&request)?;
+        let result = self.authorize(&caller, ENGINE_CONTROL_PERMISSION, "start");
+        self.audit(&caller, ENGINE_CONTROL_PERMISSION, "start".to_string(), &result);
+        result?;
+
+        self.engine.resume().await;
+        Ok(Response::new(self.engine_status().await))
+    }
+
+    async fn stop_engine(
+        &self,
+        request: Request<proto::StopEngineRequest>,
+    ) -> std::result::Result<Response<proto::EngineStatus>, Status> {
+        let caller = self.authenticate(&request)?;
+        let result = self.authorize(&caller, ENGINE_CONTROL_PERMISSION, "stop");
+        self.audit(&caller, ENGINE_CONTROL_PERMISSION, "stop".to_string(), &result);
+        result?;
+
+        self.engine.pause().await;
+        Ok(Response::new(self.engine_status().await))
+    }
+
+    async fn reload_config(
+        &self,
+        request: Request<proto::ReloadConfigRequest>,
+    ) -> std::result::Result<Response<proto::ReloadConfigResponse>, Status> {
+        let caller = self.authenticate(&request)?;
+        let config_yaml = request.into_inner().config_yaml;
+
+        let result = match self.authorize(&caller, ENGINE_CONTROL_PERMISSION, "reload") {
+            Ok(()) => self.reload_config(&config_yaml).await,
+            Err(e) => Err(e),
+        };
+        let details = if config_yaml.is_empty() { "reload file" } else { "reload inline" };
+        self.audit(&caller, ENGINE_CONTROL_PERMISSION, details.to_string(), &result);
+
+        let blocks = u32::try_from(result?).unwrap_or(u32::MAX);
+        info!("gRPC client '{}' reloaded configuration with {} blocks", caller.id, blocks);
+        Ok(Response::new(proto::ReloadConfigResponse { blocks }))
+    }
+
+    type StreamEventsStream = EventStream;
+
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> std::result::Result<Response<Self::StreamEventsStream>, Status> {
+        let caller = self.authenticate(&request)?;
+        let after = request.into_inner().after_sequence;
+
+        let mut events = self.engine.events().subscribe(after);
+        let rbac = self.config.rbac.clone();
+        let (tx, rx) = mpsc::channel(64);
+
+        debug!("gRPC client '{}' streaming events after {:?}", caller.id, after);
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    event = events.next() => event,
+                    () = tx.closed() => None,
+                };
+                let Some(event) = event else {
+                    break;
+                };
+                if !rbac.is_allowed(&caller.role, READ_EVENTS_PERMISSION, event.kind.name()) {
+                    continue;
+                }
+                if tx.send(Ok(proto::Event::from(&event))).await.is_err() {
+                    break;
+                }
+            }
+            debug!("gRPC client '{}' stopped streaming events", caller.id);
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|item| (item, rx)) });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serve the gRPC API until the server fails
+///
+/// # Errors
+///
+/// Returns an error if the bind address is invalid or the server cannot
+/// bind or fails while running.
+pub async fn serve(service: PetraService) -> Result<()> {
+    let addr: SocketAddr = service.config.bind_address.parse()?;
+    info!("gRPC API listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(PetraServer::new(service))
+        .serve(addr)
+        .await
+        .map_err(|e| PlcError::Runtime(format!("gRPC server error: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    fn service(dir: &std::path::Path) -> PetraService {
+        let mut grpc: GrpcConfig = serde_yaml::from_str(
+            r#"
+clients:
+  - { id: scada, token: "0123456789abcdef", role: operator }
+  - { id: viewer, token: "fedcba9876543210", role: viewer }
+rbac:
+  roles:
+    operator:
+      permissions: ["signal.read:*", "signal.write:*", "engine.control:stop", "event.read:*"]
+    viewer:
+      permissions: ["signal.read:*", "event.read:config_applied"]
+"#,
+        )
+        .unwrap();
+        grpc.audit_log = dir.join("audit.jsonl");
+
+        let config = Config {
+            blocks: Vec::new(),
+            ..Config::example_basic().unwrap()
+        };
+        let engine = Engine::new(config).unwrap();
+        PetraService::new(
+            grpc,
+            engine.signal_bus().clone(),
+            engine.engine_control(),
+            engine.reload_handle(),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn request<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_write_requires_role_and_is_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(dir.path());
+        let name = service.bus.signal_names().into_iter().next().unwrap();
+        let write = || proto::WriteSignalsRequest {
+            signals: vec![signal_message(name.clone(), &Value::Integer(0))],
+            reason: String::new(),
+            signature: None,
+        };
+
+        let err = service.write_signals(Request::new(write())).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+        let err = service.write_signals(request(write(), "fedcba9876543210")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+
+        let read = service
+            .read_signals(request(proto::ReadSignalsRequest { names: vec![name.clone()] }, "fedcba9876543210"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(read.signals.len(), 1);
+
+        service
+            .write_signals(request(write(), "0123456789abcdef"))
+            .await
+            .unwrap();
+
+        let audit = service.audit.read(10, None, None).unwrap();
+        assert_eq!(audit.len(), 2);
+        assert_eq!(audit[0].user, "scada");
+        assert_eq!(audit[0].target.as_deref(), Some(name.as_str()));
+        assert_eq!(audit[0].new_value, Some(Value::Integer(0)));
+        assert_eq!(audit[1].user, "viewer");
+        assert!(!audit[1].success);
+    }
+
+    #[tokio::test]
+    async fn test_features_need_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(dir.path());
+
+        let err = service.get_features(Request::new(proto::GetFeaturesRequest {})).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+        let report = service
+            .get_features(request(proto::GetFeaturesRequest {}, "fedcba9876543210"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(report.enabled.iter().any(|f| f == "grpc"));
+        assert!(report.categories.values().flat_map(|l| &l.features).any(|f| f == "grpc"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_pauses_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(dir.path());
+
+        let status = service
+            .stop_engine(request(proto::StopEngineRequest {}, "0123456789abcdef"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(status.paused);
+        let err = service
+            .start_engine(request(proto::StartEngineRequest {}, "0123456789abcdef"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        assert!(service.engine.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_resumes_and_filters_by_role() {
+        use futures::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(dir.path());
+        let events = service.engine.events();
+        events.publish(EventKind::BlockError {
+            block: "pid".to_string(),
+            error: "division by zero".to_string(),
+        });
+        events.publish(EventKind::ConfigApplied { blocks: 2, new_signals: 1 });
+
+        let replay = |token| {
+            service.stream_events(request(proto::StreamEventsRequest { after_sequence: Some(0) }, token))
+        };
+        let mut operator = replay("0123456789abcdef").await.unwrap().into_inner();
+        let first = operator.next().await.unwrap().unwrap();
+        assert_eq!(first.sequence, 1);
+        assert!(matches!(first.kind, Some(proto::event::Kind::BlockError(_))));
+
+        let mut viewer = replay("fedcba9876543210").await.unwrap().into_inner();
+        let first = viewer.next().await.unwrap().unwrap();
+        assert_eq!(first.sequence, 2);
+        let Some(proto::event::Kind::ConfigApplied(applied)) = first.kind else {
+            panic!("expected config_applied, got {first:?}");
+        };
+        assert_eq!((applied.blocks, applied.new_signals), (2, 1));
+    }
+}
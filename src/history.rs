@@ -1,5 +1,5 @@
 // src/history.rs - Fixed with proper imports and BoxFuture handling
-use crate::{error::{PlcError, Result}, value::Value};
+use crate::{error::{PlcError, Result}, snapshot::SignalSnapshot, subscription::{SignalChange, SignalSubscription}, value::Value};
 use chrono::{DateTime, Utc, Duration};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -7,6 +7,7 @@ use tokio::sync::{RwLock, mpsc};
 use serde::{Serialize, Deserialize};
 use futures::future::BoxFuture;  // Added import
 use futures::FutureExt;          // Added import
+use futures::StreamExt;
 
 #[cfg(feature = "parquet")]
 use parquet::{
@@ -34,6 +35,104 @@ pub struct HistoryEntry {
     pub metadata: Option<serde_json::Value>,
 }
 
+impl HistoryEntry {
+    /// Entry recording a change delivered by a bus subscription
+    /// 
+    /// The entry is timestamped when the value was written to the bus,
+    /// which is the device timestamp for values a protocol driver received
+    /// with one, and notes the writer and its sequence number under
+    /// [`SOURCE_KEY`] and [`SEQUENCE_KEY`].
+    pub fn from_change(change: SignalChange) -> Self {
+        let mut map = serde_json::Map::new();
+        if let Some(source) = change.meta.source {
+            map.insert(SOURCE_KEY.to_string(), serde_json::Value::String(source));
+        }
+        if let Some(sequence) = change.meta.sequence {
+            map.insert(SEQUENCE_KEY.to_string(), serde_json::Value::from(sequence));
+        }
+        Self {
+            timestamp: change.meta.timestamp.into(),
+            signal_name: change.name,
+            value: change.value,
+            quality: None,
+            metadata: (!map.is_empty()).then_some(serde_json::Value::Object(map)),
+        }
+    }
+    
+    /// Entries recording every value of a signal group snapshot
+    /// 
+    /// All entries carry the time the snapshot was taken, so the group's
+    /// values line up when queried, and note the group under [`GROUP_KEY`]
+    /// and the scan cycle under [`SCAN_KEY`].
+    pub fn from_snapshot(snapshot: SignalSnapshot) -> Vec<Self> {
+        let mut map = serde_json::Map::new();
+        map.insert(GROUP_KEY.to_string(), serde_json::Value::String(snapshot.group));
+        if let Some(scan) = snapshot.scan {
+            map.insert(SCAN_KEY.to_string(), serde_json::Value::from(scan));
+        }
+        let metadata = serde_json::Value::Object(map);
+        snapshot
+            .values
+            .into_iter()
+            .map(|(signal_name, value)| Self {
+                timestamp: snapshot.taken_at,
+                signal_name,
+                value,
+                quality: None,
+                metadata: Some(metadata.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Metadata key holding the signal group an entry was recorded from
+pub const GROUP_KEY: &str = "group";
+
+/// Metadata key holding the scan cycle a group snapshot was taken after
+pub const SCAN_KEY: &str = "scan";
+
+/// Metadata key set on entries recorded while the clock was not synchronized
+pub const CLOCK_UNSYNCHRONIZED_KEY: &str = "clock_unsynchronized";
+
+/// Metadata key holding the component that wrote a recorded value
+pub const SOURCE_KEY: &str = "source";
+
+/// Metadata key holding the sequence number the writer gave a recorded value
+pub const SEQUENCE_KEY: &str = "sequence";
+
+/// Extension of the checksum file written next to every history file
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+
+/// Checksum file of the history file at `path`
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(CHECKSUM_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Hex encoded SHA-256 digest of a history file's contents
+pub fn checksum(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn flag_unsynchronized(entry: &mut HistoryEntry) {
+    match &mut entry.metadata {
+        Some(serde_json::Value::Object(map)) => {
+            map.insert(CLOCK_UNSYNCHRONIZED_KEY.to_string(), serde_json::Value::Bool(true));
+        }
+        metadata => {
+            let mut map = serde_json::Map::new();
+            if let Some(previous) = metadata.take() {
+                map.insert("value".to_string(), previous);
+            }
+            map.insert(CLOCK_UNSYNCHRONIZED_KEY.to_string(), serde_json::Value::Bool(true));
+            *metadata = Some(serde_json::Value::Object(map));
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryConfig {
     pub data_dir: PathBuf,
@@ -122,7 +221,35 @@ impl HistoryManager {
         Ok(manager)
     }
     
-    pub async fn write(&self, entry: HistoryEntry) -> Result<()> {
+    pub async fn write(&self, mut entry: HistoryEntry) -> Result<()> {
+        if !crate::time_sync::clock_synchronized() {
+            flag_unsynchronized(&mut entry);
+        }
+        
+        self.tx.send(HistoryCommand::Write(entry)).await
+            .map_err(|e| PlcError::Runtime(format!("Failed to send write command: {}", e)))
+    }
+    
+    /// Record the changes delivered by `changes` as they arrive
+    /// 
+    /// Subscribe with [`SignalBus::subscribe`](crate::SignalBus::subscribe) to the signals to log instead
+    /// of sampling the bus. Returns when the manager stops accepting
+    /// entries.
+    pub async fn record(&self, mut changes: SignalSubscription) {
+        while let Some(change) = changes.next().await {
+            if let Err(e) = self.write(HistoryEntry::from_change(change)).await {
+                tracing::warn!("Stopped recording signal changes: {}", e);
+                return;
+            }
+        }
+    }
+    
+    /// Write an entry timestamped by its data source
+    /// 
+    /// Used for values backfilled from device buffers; the entry keeps its
+    /// source timestamp and is not flagged when the local clock is
+    /// unsynchronized.
+    pub async fn write_backfilled(&self, entry: HistoryEntry) -> Result<()> {
         self.tx.send(HistoryCommand::Write(entry)).await
             .map_err(|e| PlcError::Runtime(format!("Failed to send write command: {}", e)))
     }
@@ -149,29 +276,32 @@ impl HistoryManager {
     
     async fn background_task(self, mut rx: mpsc::Receiver<HistoryCommand>) {
         while let Some(command) = rx.recv().await {
-            match command {
-                HistoryCommand::Write(entry) => {
-                    if let Err(e) = self.handle_write(entry).await {
-                        tracing::error!("Failed to write history entry: {}", e);
+            let work = async {
+                match command {
+                    HistoryCommand::Write(entry) => {
+                        if let Err(e) = self.handle_write(entry).await {
+                            tracing::error!("Failed to write history entry: {}", e);
+                        }
                     }
-                }
-                HistoryCommand::Flush => {
-                    if let Err(e) = self.handle_flush().await {
-                        tracing::error!("Failed to flush history buffer: {}", e);
+                    HistoryCommand::Flush => {
+                        if let Err(e) = self.handle_flush().await {
+                            tracing::error!("Failed to flush history buffer: {}", e);
+                        }
                     }
-                }
-                HistoryCommand::Query(query, response_tx) => {
-                    let result = self.handle_query(query).await;
-                    if let Err(_) = response_tx.send(result).await {
-                        tracing::warn!("Failed to send query response");
+                    HistoryCommand::Query(query, response_tx) => {
+                        let result = self.handle_query(query).await;
+                        if let Err(_) = response_tx.send(result).await {
+                            tracing::warn!("Failed to send query response");
+                        }
                     }
-                }
-                HistoryCommand::Compact(days) => {
-                    if let Err(e) = self.handle_compact(days).await {
-                        tracing::error!("Failed to compact history: {}", e);
+                    HistoryCommand::Compact(days) => {
+                        if let Err(e) = self.handle_compact(days).await {
+                            tracing::error!("Failed to compact history: {}", e);
+                        }
                     }
                 }
-            }
+            };
+            crate::scan_budget::instrument(crate::scan_budget::Subsystem::History, work).await;
         }
     }
     
@@ -293,6 +423,10 @@ impl HistoryManager {
             tokio::fs::write(&filepath, data).await?;
         }
         
+        // Lets `petra storage verify` detect files changed after the fact
+        let written = tokio::fs::read(&filepath).await?;
+        tokio::fs::write(checksum_path(&filepath), checksum(&written)).await?;
+        
         tracing::debug!("Wrote {} entries to {}", entries.len(), filepath.display());
         Ok(())
     }
@@ -548,4 +682,26 @@ mod tests {
             assert_eq!(result.signal_name, "even");
         }
     }
+    
+    #[test]
+    fn test_entry_keeps_device_timestamp() {
+        use crate::signal::{SignalBus, WriteMeta};
+        
+        let bus = SignalBus::new();
+        let current = |bus: &SignalBus| {
+            let (value, meta) = bus.get_with_meta("tank.level").unwrap();
+            HistoryEntry::from_change(SignalChange { name: "tank.level".to_string(), value, meta })
+        };
+        let sampled = Utc::now() - Duration::seconds(30);
+        let meta = WriteMeta::from_source(Some("kafka")).at(sampled.into()).with_sequence(41);
+        bus.set_with_meta("tank.level", Value::Float(2.5), meta).unwrap();
+        
+        let entry = current(&bus);
+        assert_eq!(entry.timestamp, sampled);
+        assert_eq!(entry.value, Value::Float(2.5));
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "source": "kafka", "sequence": 41 })));
+        
+        bus.set("tank.level", Value::Float(2.6)).unwrap();
+        assert!(current(&bus).metadata.is_none());
+    }
 }
@@ -86,6 +86,16 @@ pub mod memory_pool;
 /// All data exchange between components flows through this signal bus.
 pub mod signal;
 
+/// Signal change subscriptions
+/// 
+/// Pattern subscriptions delivering bus changes as async streams.
+pub mod subscription;
+
+/// Signal group snapshots
+/// 
+/// Consistent values of named signal groups, taken between scan cycles.
+pub mod snapshot;
+
 /// Configuration loading and validation system
 /// 
 /// YAML-based configuration with comprehensive validation and feature-specific
@@ -98,6 +108,74 @@ pub mod config;
 /// and maintains deterministic timing with jitter monitoring.
 pub mod engine;
 
+/// Clock synchronization state and offset monitoring
+/// 
+/// Tracks whether the wall clock is disciplined so time-stamped records can
+/// be flagged, with an NTP/PTP monitor behind the `time-sync` feature.
+pub mod time_sync;
+
+/// Engine clock sources
+/// 
+/// System time or accelerated and stepped virtual time for timer blocks
+/// and the simulator, selected by the `clock` configuration section.
+pub mod clock;
+
+/// Scan-cycle time and allocation budget per subsystem
+/// 
+/// Attributes time and allocations to blocks, protocols, history, alarms and
+/// analytics; counting is active with the `scan-budget` feature.
+pub mod scan_budget;
+
+/// Offline scan-time recommendations
+///
+/// Times the configured blocks and polling scan classes without protocol
+/// drivers and suggests the scan time, parallel execution and class
+/// intervals, as printed by `petra dev tune-scan`.
+pub mod scan_tuning;
+
+/// Sequenced stream of engine events
+///
+/// State changes, block failures, protocol connections and applied
+/// configurations, retained so subscribers can resume after a reconnect.
+pub mod events;
+
+/// Staged startup
+///
+/// Storage, protocols, blocks and web brought up in order with per-stage
+/// timeouts and retries, the current stage published as
+/// `system.startup_stage`.
+pub mod startup;
+
+/// Scan watchdog
+///
+/// Heartbeat signal toggled every healthy cycle, and a safe state and
+/// shutdown blocks applied when the scan loop stalls.
+pub mod watchdog;
+
+/// Stale signal detection
+///
+/// Flags signals written less often than their `update_frequency_ms`.
+pub mod staleness;
+
+/// Engineering-unit display formatting
+/// 
+/// Renders signal values with the units, decimal places and enumeration
+/// labels from the signal configuration for the web API, MQTT and reports.
+pub mod display;
+
+/// Enumerated signal types
+///
+/// Maps the raw integers of `enum` signals to labels so configuration and
+/// blocks can refer to states by name.
+pub mod enums;
+
+/// Messages for external integrators
+///
+/// Signal updates, alarm events and history batches as published to Kafka,
+/// NATS and gRPC consumers, with the protobuf and JSON Schema package
+/// generated from them.
+pub mod wire;
+
 /// Feature detection and validation system
 /// 
 /// Runtime feature detection, validation of feature dependencies,
@@ -115,6 +193,14 @@ pub mod build_info;
 /// implementations. All logic processing flows through this system.
 pub mod blocks;
 
+#[cfg(feature = "plugins")]
+#[cfg_attr(docsrs, doc(cfg(feature = "plugins")))]
+/// Block types loaded at startup from shared libraries or WASM modules
+///
+/// Registers third-party block implementations in the block factory
+/// through a versioned ABI, without recompiling PETRA.
+pub mod plugins;
+
 // ============================================================================
 // PROTOCOL MODULES (Feature-Gated)
 // ============================================================================
@@ -158,6 +244,9 @@ pub mod storage {
     pub mod cli;
     pub use cli::{initialize_storage, backup_data, restore_data, compact_storage};
 
+    /// History data integrity checks behind `petra storage verify`
+    pub mod verify;
+
     #[cfg(feature = "clickhouse")]
     #[cfg_attr(docsrs, doc(cfg(feature = "clickhouse")))]
     /// ClickHouse backend for analytical workloads
@@ -174,6 +263,22 @@ pub mod storage {
     pub mod s3;
 }
 
+#[cfg(feature = "retained-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "retained-store")))]
+/// Retained signals persisted on write
+///
+/// Write-behind sled store restoring setpoints and operator entries at
+/// startup.
+pub mod retained;
+
+#[cfg(feature = "backup")]
+#[cfg_attr(docsrs, doc(cfg(feature = "backup")))]
+/// Node backup and restore
+///
+/// Single-archive backups of configuration, block state, retained signals
+/// and recent history for disaster recovery and node cloning.
+pub mod backup;
+
 // ============================================================================
 // SECURITY MODULES (Feature-Gated)
 // ============================================================================
@@ -186,6 +291,22 @@ pub mod storage {
 /// for multiple authentication methods and role-based access control.
 pub mod security;
 
+#[cfg(feature = "licensing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "licensing")))]
+/// License files and entitlement enforcement
+/// 
+/// Limits on signal, block and protocol counts from a signed license,
+/// with a grace period after expiry.
+pub mod license;
+
+#[cfg(feature = "redundancy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redundancy")))]
+/// Hot-standby redundancy
+/// 
+/// A pair of nodes exchanging signal and block state, with heartbeat-based
+/// failover and bumpless takeover of outputs.
+pub mod redundancy;
+
 // ============================================================================
 // VALIDATION MODULES (Feature-Gated)
 // ============================================================================
@@ -222,6 +343,13 @@ pub mod validation {
 // MONITORING MODULES (Feature-Gated)
 // ============================================================================
 
+#[cfg(feature = "log-export")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log-export")))]
+/// Structured JSON log files
+///
+/// Rotated log files with scan and block context for log collectors.
+pub mod log_export;
+
 #[cfg(any(feature = "metrics", feature = "enhanced-monitoring"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
 /// Prometheus metrics server for observability
@@ -270,6 +398,78 @@ pub mod email;
 /// confirmation and fallback options for maximum reliability.
 pub mod twilio;
 
+// ============================================================================
+// PRODUCTION ANALYTICS MODULES (Feature-Gated)
+// ============================================================================
+
+#[cfg(any(feature = "oee", feature = "downtime", feature = "energy"))]
+/// Production shift calendar shared by shift-based aggregations
+pub mod shifts;
+
+#[cfg(feature = "schedules")]
+#[cfg_attr(docsrs, doc(cfg(feature = "schedules")))]
+/// Weekly time-of-day schedules with holidays and time zones
+///
+/// Evaluated by SCHEDULE blocks and edited at runtime over the web API.
+pub mod schedule;
+
+#[cfg(feature = "oee")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oee")))]
+/// Overall Equipment Effectiveness and production counters
+/// 
+/// Derives availability, performance and quality from run, count and
+/// reject signals, with shift-based aggregates persisted to disk.
+pub mod oee;
+
+#[cfg(feature = "downtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "downtime")))]
+/// Downtime event tracking with reason codes
+/// 
+/// Opens an event whenever an asset stops, lets operators assign reason
+/// codes and aggregates stored events into a Pareto report.
+pub mod downtime;
+
+#[cfg(feature = "maintenance")]
+#[cfg_attr(docsrs, doc(cfg(feature = "maintenance")))]
+/// Condition-based maintenance rules and work items
+/// 
+/// Combines runtime hours, start counts and analog trends into rules that
+/// raise maintenance work items, published as signals and over the web API.
+pub mod maintenance;
+
+#[cfg(feature = "maintenance-mode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "maintenance-mode")))]
+/// Maintenance mode for devices and plant areas
+/// 
+/// Takes configured areas out of service on an operator's request,
+/// suppressing their alarms and marking their signal quality until the
+/// work is done.
+pub mod maintenance_mode;
+
+#[cfg(feature = "energy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "energy")))]
+/// Energy monitoring with cost and peak demand
+/// 
+/// Aggregates meter counters and power or flow rates into hourly, daily and
+/// shift totals with time-of-use cost, demand peaks and specific consumption.
+pub mod energy;
+
+#[cfg(feature = "simulation")]
+#[cfg_attr(docsrs, doc(cfg(feature = "simulation")))]
+/// Simulated signal overrides for hardware-in-the-loop testing
+/// 
+/// Replaces selected device-bound signals with generated waveforms when the
+/// active environment profile enables the simulation section.
+pub mod simulation;
+
+#[cfg(feature = "reports")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reports")))]
+/// Scheduled report generation
+/// 
+/// Aggregates signal history into daily production, alarm and energy
+/// reports rendered as HTML, CSV or PDF and delivered to disk, email or S3.
+pub mod reports;
+
 // ============================================================================
 // WEB & API MODULES (Feature-Gated)
 // ============================================================================
@@ -282,6 +482,14 @@ pub mod twilio;
 /// for real-time updates, and responsive UI for mobile devices.
 pub mod web;
 
+#[cfg(feature = "grpc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grpc")))]
+/// gRPC API for signal access and engine control
+/// 
+/// Token-authenticated service for orchestration systems to read, write
+/// and stream signals and to pause, resume or reload the engine.
+pub mod grpc;
+
 // ============================================================================
 // DEVELOPMENT MODULES (Feature-Gated)
 // ============================================================================
@@ -532,7 +740,7 @@ pub mod test_utils {
     
     /// Create a minimal test configuration
     pub fn create_test_config() -> Config {
-        Config::default()
+        Config::example_basic().expect("example configuration is valid")
     }
 }
 
@@ -0,0 +1,437 @@
+// src/license.rs
+//! License files and entitlement enforcement
+//!
+//! Products embedding PETRA can limit what a node may run with a license
+//! file signed by the OEM:
+//!
+//! ```json
+//! {
+//!   "terms": {
+//!     "license_id": "ACME-0042",
+//!     "licensee": "Example Foods Ltd",
+//!     "expires": "2027-06-30T00:00:00Z",
+//!     "grace_days": 14,
+//!     "max_signals": 500,
+//!     "max_blocks": 200,
+//!     "max_protocols": 2,
+//!     "protocols": ["modbus", "mqtt"]
+//!   },
+//!   "signature": "<base64 Ed25519 signature of the terms>"
+//! }
+//! ```
+//!
+//! Enforcement is active when the binary was built with the Ed25519 public
+//! key in `PETRA_LICENSE_PUBLIC_KEY` (base64), or when the configuration
+//! names a license with `license.public_key`. A build with an embedded key
+//! refuses to start without a valid license, and ignores keys given in the
+//! configuration.
+//!
+//! The engine checks the license on start and on every hot reload. Counts
+//! above a limit, or a protocol the license does not list, refuse the
+//! configuration with one message naming every violation. An expired
+//! license keeps working for `grace_days`, logging a warning on each check;
+//! after that the engine no longer starts.
+//!
+//! `petra license key-gen` creates the product key, `petra license sign`
+//! issues license files with it, and `petra license check` shows how a
+//! configuration compares to its license.
+
+use crate::config::Config;
+use crate::error::{PlcError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Public key embedded at build time
+pub const EMBEDDED_PUBLIC_KEY: Option<&str> = option_env!("PETRA_LICENSE_PUBLIC_KEY");
+
+/// Protocol sections that configure routing between drivers rather than a
+/// driver of their own
+const NOT_DRIVERS: &[&str] = &["failover_groups", "backfill", "polling", "routes", "dead_letters"];
+
+/// License section of the configuration
+///
+/// ```yaml
+/// license:
+///   file: /etc/petra/license.json
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LicenseConfig {
+    /// License file
+    pub file: PathBuf,
+
+    /// Base64 Ed25519 public key the license is signed with; ignored when
+    /// the build embeds one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// What a license grants
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LicenseTerms {
+    /// License identifier shown in diagnostics
+    pub license_id: String,
+
+    /// Customer the license was issued to
+    pub licensee: String,
+
+    /// End of the license; licenses without one do not expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+
+    /// Days an expired license keeps working
+    #[serde(default)]
+    pub grace_days: u32,
+
+    /// Maximum number of configured signals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_signals: Option<usize>,
+
+    /// Maximum number of configured blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_blocks: Option<usize>,
+
+    /// Maximum number of protocols in use at the same time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_protocols: Option<usize>,
+
+    /// Protocols that may be used; empty allows every protocol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
+}
+
+/// Signed license file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseFile {
+    pub terms: LicenseTerms,
+    /// Base64 Ed25519 signature of the JSON encoded terms
+    pub signature: String,
+}
+
+impl LicenseFile {
+    /// Read a license file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a license.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| PlcError::Config(format!("Cannot read license file {}: {}", path.display(), e)))?;
+        serde_json::from_str(&content)
+            .map_err(|e| PlcError::Config(format!("Invalid license file {}: {}", path.display(), e)))
+    }
+
+    /// Sign license terms with an Ed25519 key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terms cannot be encoded.
+    pub fn sign(terms: LicenseTerms, key: &SigningKey) -> Result<Self> {
+        let signature = key.sign(&serde_json::to_vec(&terms)?);
+        Ok(Self { terms, signature: STANDARD.encode(signature.to_bytes()) })
+    }
+
+    /// Check the signature against a base64 public key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key or signature is malformed or the terms
+    /// were not signed with that key.
+    pub fn verify(&self, public_key: &str) -> Result<()> {
+        let key: [u8; 32] = STANDARD
+            .decode(public_key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| PlcError::Config("License public key is not a base64 Ed25519 key".to_string()))?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| PlcError::Config(format!("Invalid license public key: {e}")))?;
+        let signature = STANDARD
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| PlcError::Config(format!("License '{}' has a malformed signature", self.terms.license_id)))?;
+        key.verify(&serde_json::to_vec(&self.terms)?, &signature).map_err(|_| {
+            PlcError::Config(format!(
+                "License '{}' is not signed by this product's key",
+                self.terms.license_id
+            ))
+        })
+    }
+}
+
+/// Base64 public key of a signing key, for `PETRA_LICENSE_PUBLIC_KEY`
+#[must_use]
+pub fn public_key(key: &SigningKey) -> String {
+    STANDARD.encode(key.verifying_key().to_bytes())
+}
+
+/// Validity of a license at some point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LicenseStatus {
+    Valid,
+    /// Expired, but still within the grace period
+    Grace { expired: DateTime<Utc>, grace_ends: DateTime<Utc> },
+    Expired { expired: DateTime<Utc> },
+}
+
+/// What a configuration uses of the licensed resources
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub signals: usize,
+    pub blocks: usize,
+    /// Protocols with a configured driver, sorted
+    pub protocols: Vec<String>,
+}
+
+impl Usage {
+    /// Count the resources a configuration uses
+    #[must_use]
+    pub fn of(config: &Config) -> Self {
+        let mut protocols: Vec<String> = match config.protocols.as_ref().map(serde_json::to_value) {
+            Some(Ok(serde_json::Value::Object(sections))) => sections
+                .into_iter()
+                .filter(|(name, section)| !NOT_DRIVERS.contains(&name.as_str()) && configured(section))
+                .map(|(name, _)| name)
+                .collect(),
+            _ => Vec::new(),
+        };
+        #[cfg(feature = "mqtt")]
+        if config.mqtt.is_some() {
+            protocols.push("mqtt".to_string());
+        }
+        protocols.sort();
+        Self { signals: config.signals.len(), blocks: config.blocks.len(), protocols }
+    }
+}
+
+fn configured(section: &serde_json::Value) -> bool {
+    match section {
+        serde_json::Value::Null => false,
+        serde_json::Value::Array(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+/// Comparison of a configuration with a license
+#[derive(Debug, Clone, Serialize)]
+pub struct EntitlementReport {
+    pub license_id: String,
+    pub licensee: String,
+    pub status: LicenseStatus,
+    pub usage: Usage,
+    /// Ways the configuration exceeds the license
+    pub violations: Vec<String>,
+}
+
+impl EntitlementReport {
+    /// Whether the engine may run the configuration
+    #[must_use]
+    pub fn permitted(&self) -> bool {
+        self.violations.is_empty() && !matches!(self.status, LicenseStatus::Expired { .. })
+    }
+}
+
+impl fmt::Display for EntitlementReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "License '{}' ({})", self.license_id, self.licensee)?;
+        match self.status {
+            LicenseStatus::Valid => {}
+            LicenseStatus::Grace { expired, grace_ends } => write!(
+                f,
+                " expired on {}; grace period ends {}",
+                expired.format("%Y-%m-%d"),
+                grace_ends.format("%Y-%m-%d")
+            )?,
+            LicenseStatus::Expired { expired } => write!(f, " expired on {}", expired.format("%Y-%m-%d"))?,
+        }
+        if !self.violations.is_empty() {
+            let separator = if self.status == LicenseStatus::Valid { " " } else { "; it " };
+            write!(f, "{separator}does not cover this configuration: {}", self.violations.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A verified license
+#[derive(Debug, Clone)]
+pub struct Entitlement {
+    terms: LicenseTerms,
+}
+
+impl Entitlement {
+    /// Load and verify the license a configuration requires
+    ///
+    /// Returns `None` when licensing is not active, i.e. the build embeds no
+    /// public key and the configuration has no `license` section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a license is required but missing, unreadable, or
+    /// not signed with the product key.
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        let Some(section) = &config.license else {
+            return match EMBEDDED_PUBLIC_KEY {
+                None => Ok(None),
+                Some(_) => Err(PlcError::Config(
+                    "This build of PETRA requires a license; set license.file to the license file".to_string(),
+                )),
+            };
+        };
+        let key = match EMBEDDED_PUBLIC_KEY {
+            Some(key) => key,
+            None => section.public_key.as_deref().ok_or_else(|| {
+                PlcError::Config("license.public_key is required to verify the license file".to_string())
+            })?,
+        };
+        Self::verify(&LicenseFile::from_file(&section.file)?, key).map(Some)
+    }
+
+    /// Verify a license file against a base64 public key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signature does not match.
+    pub fn verify(file: &LicenseFile, public_key: &str) -> Result<Self> {
+        file.verify(public_key)?;
+        Ok(Self { terms: file.terms.clone() })
+    }
+
+    /// The licensed terms
+    #[must_use]
+    pub fn terms(&self) -> &LicenseTerms {
+        &self.terms
+    }
+
+    /// Validity at `now`
+    #[must_use]
+    pub fn status(&self, now: DateTime<Utc>) -> LicenseStatus {
+        match self.terms.expires {
+            Some(expired) if now >= expired => {
+                let grace_ends = expired + Duration::days(i64::from(self.terms.grace_days));
+                if now < grace_ends {
+                    LicenseStatus::Grace { expired, grace_ends }
+                } else {
+                    LicenseStatus::Expired { expired }
+                }
+            }
+            _ => LicenseStatus::Valid,
+        }
+    }
+
+    /// Compare a configuration with the license at `now`
+    #[must_use]
+    pub fn check(&self, config: &Config, now: DateTime<Utc>) -> EntitlementReport {
+        let usage = Usage::of(config);
+        let terms = &self.terms;
+        let mut violations = Vec::new();
+        for (what, used, limit) in [
+            ("signals", usage.signals, terms.max_signals),
+            ("blocks", usage.blocks, terms.max_blocks),
+            ("protocols", usage.protocols.len(), terms.max_protocols),
+        ] {
+            if let Some(limit) = limit.filter(|&limit| used > limit) {
+                violations.push(format!("{used} {what} configured, {limit} licensed"));
+            }
+        }
+        if !terms.protocols.is_empty() {
+            for protocol in usage.protocols.iter().filter(|p| !terms.protocols.contains(p)) {
+                violations.push(format!(
+                    "protocol '{}' is not licensed (licensed: {})",
+                    protocol,
+                    terms.protocols.join(", ")
+                ));
+            }
+        }
+        EntitlementReport {
+            license_id: terms.license_id.clone(),
+            licensee: terms.licensee.clone(),
+            status: self.status(now),
+            usage,
+            violations,
+        }
+    }
+
+    /// Refuse a configuration the license does not cover
+    ///
+    /// Logs a warning while the license is in its grace period.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming every violation, or the expiry date once the
+    /// grace period has ended.
+    pub fn enforce(&self, config: &Config) -> Result<EntitlementReport> {
+        let report = self.check(config, Utc::now());
+        if !report.permitted() {
+            return Err(PlcError::Config(report.to_string()));
+        }
+        if matches!(report.status, LicenseStatus::Grace { .. }) {
+            warn!("{}; renew the license to keep running", report);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms() -> LicenseTerms {
+        LicenseTerms {
+            license_id: "ACME-0042".to_string(),
+            licensee: "Example Foods Ltd".to_string(),
+            expires: Some("2027-06-30T00:00:00Z".parse().unwrap()),
+            grace_days: 14,
+            max_signals: Some(2),
+            max_blocks: None,
+            max_protocols: None,
+            protocols: vec!["modbus".to_string()],
+        }
+    }
+
+    fn config(signals: usize) -> Config {
+        let signals: String = (0..signals).map(|i| format!("  - {{ name: s{i}, type: bool }}\n")).collect();
+        serde_yaml::from_str(&format!("scan_time_ms: 100\nsignals:\n{signals}blocks: []\n")).unwrap()
+    }
+
+    #[test]
+    fn test_signature_covers_terms() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let file = LicenseFile::sign(terms(), &key).unwrap();
+        assert!(Entitlement::verify(&file, &public_key(&key)).is_ok());
+
+        let mut tampered = file.clone();
+        tampered.terms.max_signals = Some(10_000);
+        let err = Entitlement::verify(&tampered, &public_key(&key)).unwrap_err();
+        assert!(err.to_string().contains("not signed by this product's key"));
+
+        let other = SigningKey::from_bytes(&[8; 32]);
+        assert!(Entitlement::verify(&file, &public_key(&other)).is_err());
+    }
+
+    #[test]
+    fn test_limits_and_grace_period() {
+        let entitlement = Entitlement { terms: terms() };
+        let before: DateTime<Utc> = "2027-01-01T00:00:00Z".parse().unwrap();
+        let report = entitlement.check(&config(2), before);
+        assert!(report.permitted());
+        assert_eq!(report.status, LicenseStatus::Valid);
+
+        let report = entitlement.check(&config(3), before);
+        assert!(!report.permitted());
+        assert_eq!(report.violations, ["3 signals configured, 2 licensed"]);
+        assert!(report.to_string().contains("ACME-0042"));
+
+        let grace = entitlement.check(&config(1), "2027-07-10T00:00:00Z".parse().unwrap());
+        assert!(matches!(grace.status, LicenseStatus::Grace { .. }));
+        assert!(grace.permitted());
+
+        let expired = entitlement.check(&config(1), "2027-07-20T00:00:00Z".parse().unwrap());
+        assert!(!expired.permitted());
+        assert!(expired.to_string().contains("expired on 2027-06-30"));
+    }
+}
@@ -0,0 +1,224 @@
+// src/log_export.rs
+//! Structured JSON log files
+//!
+//! `petra run --log-file /var/log/petra/petra.json` writes every log event
+//! as one JSON object per line, independently of the format and level of
+//! the console output, for collectors such as Filebeat or Vector:
+//!
+//! ```json
+//! {"timestamp":"2026-10-16T08:00:01.123Z","level":"ERROR","target":"petra::engine",
+//!  "fields":{"message":"Block 'tank_level' execution failed: ..."},
+//!  "span":{"block":"tank_level","block_type":"SCALE","name":"block"},
+//!  "spans":[{"name":"scan_cycle","scan":48211},{"block":"tank_level","block_type":"SCALE","name":"block"}]}
+//! ```
+//!
+//! Events inside a scan carry the `scan_cycle` span with the scan number,
+//! or the `task_cycle` span with the task name and cycle, and the `block`
+//! span of the block being executed.
+//!
+//! The file is rotated once a write would take it past the size limit:
+//! `petra.json` becomes `petra.json.1`, older files move up by one and the
+//! oldest beyond the number of files to keep is deleted.
+
+use crate::error::{PlcError, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::{filter::filter_fn, fmt, registry::LookupSpan, Layer};
+
+/// Where and how much to log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileConfig {
+    /// Log file; rotated files get `.1`, `.2`, ... appended
+    pub path: PathBuf,
+    /// Size at which the file is rotated
+    pub max_size_bytes: u64,
+    /// Rotated files kept next to the current one
+    pub max_files: usize,
+    /// Least severe level written
+    pub level: Level,
+}
+
+impl LogFileConfig {
+    /// 100 MB files, five rotated files kept, `INFO` and above
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), max_size_bytes: 100 * 1024 * 1024, max_files: 5, level: Level::INFO }
+    }
+}
+
+/// Log file rotated by size
+///
+/// Clones write to the same file. Every `write` call is expected to hold
+/// whole lines, as the formatter writes one event per call, so files are
+/// only split between events.
+#[derive(Debug, Clone)]
+pub struct RotatingFile {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    path: PathBuf,
+    max_size_bytes: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open the log file, appending to an existing one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file or its directory cannot be created.
+    pub fn open(config: &LogFileConfig) -> Result<Self> {
+        if let Some(dir) = config.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = append(&config.path)
+            .map_err(|e| PlcError::Config(format!("Cannot open log file {}: {}", config.path.display(), e)))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            state: Arc::new(Mutex::new(State {
+                path: config.path.clone(),
+                max_size_bytes: config.max_size_bytes,
+                max_files: config.max_files,
+                file: Some(file),
+                size,
+            })),
+        })
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+impl State {
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(&self.path, self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = rotated(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let len = buf.len() as u64;
+        if state.size > 0 && state.size + len > state.max_size_bytes {
+            state.rotate()?;
+        }
+        if state.file.is_none() {
+            state.file = Some(append(&state.path)?);
+        }
+        if let Some(file) = state.file.as_mut() {
+            file.write_all(buf)?;
+        }
+        state.size += len;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.file.as_mut().map_or(Ok(()), Write::flush)
+    }
+}
+
+/// Tracing layer writing JSON lines to a rotated file
+///
+/// The layer filters on its own: it takes events at `config.level` and
+/// above, and the spans of PETRA at every level so the scan and block
+/// context is recorded even when the console shows less.
+///
+/// # Errors
+///
+/// Returns an error if the log file cannot be opened.
+pub fn layer<S>(config: &LogFileConfig) -> Result<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let file = RotatingFile::open(config)?;
+    let level = config.level;
+    Ok(fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_thread_names(true)
+        .with_writer(move || file.clone())
+        .with_filter(filter_fn(move |meta| {
+            if meta.is_span() {
+                meta.target().starts_with("petra")
+            } else {
+                *meta.level() <= level
+            }
+        }))
+        .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogFileConfig {
+            max_size_bytes: 10,
+            max_files: 2,
+            ..LogFileConfig::new(dir.path().join("petra.json"))
+        };
+        let mut file = RotatingFile::open(&config).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(config.path.clone()), "fourth\n");
+        assert_eq!(read(rotated(&config.path, 1)), "third\n");
+        assert_eq!(read(rotated(&config.path, 2)), "second\n");
+        assert!(!rotated(&config.path, 3).exists());
+    }
+
+    #[test]
+    fn test_events_carry_scan_and_block_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogFileConfig { level: Level::WARN, ..LogFileConfig::new(dir.path().join("petra.json")) };
+        let subscriber = tracing_subscriber::registry().with(layer(&config).unwrap());
+        tracing::subscriber::with_default(subscriber, || {
+            let _scan = tracing::trace_span!(target: "petra::engine", "scan_cycle", scan = 42_u64).entered();
+            let _block = tracing::trace_span!(target: "petra::engine", "block", block = "tank").entered();
+            tracing::info!("not written");
+            tracing::warn!("tank level high");
+        });
+
+        let content = fs::read_to_string(&config.path).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["fields"]["message"], "tank level high");
+        assert_eq!(lines[0]["span"]["block"], "tank");
+        assert_eq!(lines[0]["spans"][0]["scan"], 42);
+    }
+}
@@ -55,9 +55,20 @@ use petra::{
     init_petra,
 };
 use petra::build_info;
+use petra::startup::StartupStage;
 use std::path::PathBuf;
 use std::process;
-#[cfg(feature = "web")]
+#[cfg(any(
+    feature = "web",
+    feature = "oee",
+    feature = "downtime",
+    feature = "maintenance",
+    feature = "energy",
+    feature = "protocol-sim",
+    feature = "s7-support",
+    feature = "modbus-support",
+    all(feature = "esignature", any(feature = "mqtt-commands", feature = "grpc"))
+))]
 use std::sync::Arc;
 #[cfg(feature = "web")]
 use petra::web;
@@ -80,6 +91,10 @@ use petra::health::{HealthMonitor, HealthConfig};
 #[cfg(feature = "realtime")]
 use petra::realtime::RealtimeScheduler;
 
+#[cfg(feature = "scan-budget")]
+#[global_allocator]
+static ALLOCATOR: petra::scan_budget::CountingAllocator = petra::scan_budget::CountingAllocator;
+
 
 // ============================================================================
 // CLI ARGUMENT DEFINITIONS
@@ -135,6 +150,26 @@ struct Cli {
     #[arg(long, value_enum, default_value = "pretty")]
     log_format: LogFormat,
     
+    /// Also write logs as JSON lines to this file
+    #[cfg(feature = "log-export")]
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+    
+    /// Rotate the log file when it reaches this size
+    #[cfg(feature = "log-export")]
+    #[arg(long, value_name = "MB", default_value = "100")]
+    log_file_max_mb: u64,
+    
+    /// Number of rotated log files to keep
+    #[cfg(feature = "log-export")]
+    #[arg(long, value_name = "COUNT", default_value = "5")]
+    log_file_keep: usize,
+    
+    /// Least severe level written to the log file
+    #[cfg(feature = "log-export")]
+    #[arg(long, value_name = "LEVEL", default_value = "info")]
+    log_file_level: Level,
+    
     /// CPU affinity (Linux only, comma-separated core IDs)
     #[cfg(target_os = "linux")]
     #[arg(short = 'a', long, value_delimiter = ',')]
@@ -145,6 +180,11 @@ struct Cli {
     #[arg(long)]
     thread_priority: Option<u8>,
     
+    /// Environment profile, e.g. `sim` (defaults to `PETRA_PROFILE`)
+    #[cfg(feature = "simulation")]
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -233,8 +273,18 @@ enum Commands {
         config_cmd: ConfigCommands,
     },
     
+    /// Write the protobuf and JSON schemas of the messages sent to integrators
+    Schema {
+        /// Directory of the schema package
+        #[arg(short, long, default_value = "schema/wire/v1")]
+        output: PathBuf,
+        
+        /// Only check that the package is up to date
+        #[arg(long)]
+        check: bool,
+    },
+    
     /// Development and testing utilities  
-    #[cfg(any(feature = "examples", feature = "burn-in", feature = "profiling"))]
     Dev {
         #[command(subcommand)]
         dev_cmd: DevCommands,
@@ -284,6 +334,55 @@ enum Commands {
         check_interval: u64,
     },
     
+    /// Show scan time and allocations per subsystem of a running engine
+    #[cfg(all(feature = "scan-budget", feature = "web"))]
+    Top {
+        /// Base URL of the engine web server
+        #[arg(short, long, default_value = "http://localhost:8080")]
+        url: String,
+        
+        /// Refresh interval in milliseconds
+        #[arg(short, long, default_value = "1000")]
+        interval_ms: u64,
+        
+        /// Number of refreshes before exiting (runs until interrupted if omitted)
+        #[arg(short = 'n', long)]
+        count: Option<u32>,
+    },
+    
+    /// Inspect the scan cycle of a running engine
+    #[cfg(all(feature = "enhanced-monitoring", feature = "web"))]
+    Engine {
+        /// Base URL of the engine web server
+        #[arg(short, long, default_value = "http://localhost:8080")]
+        url: String,
+        
+        #[command(subcommand)]
+        engine_cmd: EngineCommands,
+    },
+    
+    /// Pause, step and watch the scan of a running engine
+    #[cfg(feature = "web")]
+    Debug {
+        /// Base URL of the engine web server
+        #[arg(short, long, default_value = "http://localhost:8080")]
+        url: String,
+        
+        #[command(subcommand)]
+        debug_cmd: DebugCommands,
+    },
+    
+    /// Pin signals of a running engine to a value for commissioning
+    #[cfg(feature = "web")]
+    Force {
+        /// Base URL of the engine web server
+        #[arg(short, long, default_value = "http://localhost:8080")]
+        url: String,
+        
+        #[command(subcommand)]
+        force_cmd: ForceCommands,
+    },
+    
     /// Security management utilities
     #[cfg(feature = "security")]
     Security {
@@ -297,6 +396,20 @@ enum Commands {
         #[command(subcommand)]
         storage_cmd: StorageCommands,
     },
+    
+    /// Node backup and restore
+    #[cfg(feature = "backup")]
+    Backup {
+        #[command(subcommand)]
+        backup_cmd: BackupCommands,
+    },
+    
+    /// License files and entitlements
+    #[cfg(feature = "licensing")]
+    License {
+        #[command(subcommand)]
+        license_cmd: LicenseCommands,
+    },
 }
 
 /// Configuration management subcommands
@@ -369,12 +482,41 @@ enum ConfigCommands {
         #[arg(long)]
         fix: bool,
     },
+    
+    /// Generate the cause-and-effect report for INTERLOCK blocks
+    Interlocks {
+        /// Configuration file to document
+        #[arg(value_name = "CONFIG_FILE")]
+        config: PathBuf,
+        
+        /// Write the Markdown report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
 
 /// Development and testing subcommands
-#[cfg(any(feature = "examples", feature = "burn-in", feature = "profiling"))]
 #[derive(Subcommand)]
 enum DevCommands {
+    /// Measure blocks and scan classes offline and recommend scan settings
+    TuneScan {
+        /// Configuration file to tune
+        #[arg(value_name = "CONFIG_FILE")]
+        config: PathBuf,
+        
+        /// Measured scan cycles
+        #[arg(short, long, default_value = "1000")]
+        cycles: usize,
+        
+        /// Assumed round trip of one protocol request in milliseconds
+        #[arg(short, long, default_value = "5")]
+        latency_ms: u64,
+        
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
     /// Run example scenarios
     #[cfg(feature = "examples")]
     Examples {
@@ -465,7 +607,7 @@ enum ProtocolCommands {
         count: usize,
     },
     
-    /// Test Modbus connectivity
+    /// Test Modbus connectivity or scan register ranges
     #[cfg(feature = "modbus-support")]
     Modbus {
         /// Modbus server address
@@ -476,9 +618,33 @@ enum ProtocolCommands {
         #[arg(short, long, default_value = "1")]
         unit_id: u8,
         
-        /// Register address to read
+        /// Register address to read, or the first address to scan
         #[arg(short, long, default_value = "0")]
         register: u16,
+        
+        /// Sweep register ranges and suggest a register mapping
+        #[arg(long)]
+        scan: bool,
+        
+        /// Number of addresses to scan in each register type
+        #[arg(long, default_value = "100", requires = "scan")]
+        count: u16,
+        
+        /// Register types to scan (coil, discrete, holding, input)
+        #[arg(long, value_delimiter = ',', default_value = "holding,input,coil,discrete", requires = "scan")]
+        types: Vec<petra::modbus::RegisterKind>,
+        
+        /// Addresses requested per read
+        #[arg(long, default_value = "16", requires = "scan")]
+        block_size: u16,
+        
+        /// Response timeout in milliseconds
+        #[arg(long, default_value = "1000", requires = "scan")]
+        timeout_ms: u64,
+        
+        /// Write the suggested mapping to a file instead of printing it
+        #[arg(short, long, requires = "scan")]
+        output: Option<PathBuf>,
     },
     
     /// Test Siemens S7 connectivity
@@ -559,6 +725,154 @@ enum SecurityCommands {
     },
 }
 
+/// Backup subcommands
+#[cfg(feature = "backup")]
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// Write config, block state, retained signals and dashboards to one archive
+    Create {
+        /// Configuration file of the node
+        #[arg(short, long, default_value = "petra.yaml")]
+        config: PathBuf,
+        
+        /// Output archive (.tar.gz)
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// Also include history files from the last N days
+        #[arg(long, value_name = "DAYS")]
+        history_days: Option<u32>,
+    },
+    
+    /// Restore a node from an archive
+    Restore {
+        /// Backup archive
+        #[arg(value_name = "BACKUP_FILE")]
+        archive: PathBuf,
+        
+        /// Write the configuration here instead of its original path
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// Show the contents of an archive
+    List {
+        /// Backup archive
+        #[arg(value_name = "BACKUP_FILE")]
+        archive: PathBuf,
+    },
+}
+
+/// Engine subcommands
+#[cfg(all(feature = "enhanced-monitoring", feature = "web"))]
+#[derive(Subcommand)]
+enum EngineCommands {
+    /// Show phase, cycle and jitter percentiles of the scan since startup
+    Stats,
+}
+
+/// Debugger subcommands
+#[cfg(feature = "web")]
+#[derive(Subcommand)]
+enum DebugCommands {
+    /// Show whether the engine is paused, its watchpoints and what halted it
+    Status,
+    
+    /// Suspend block execution
+    Pause,
+    
+    /// Resume block execution
+    Resume,
+    
+    /// Execute one scan cycle of the paused engine and show every block
+    Step,
+    
+    /// Halt the engine when a signal changes
+    Watch {
+        /// Signal name
+        signal: String,
+    },
+    
+    /// Remove a watchpoint
+    Unwatch {
+        /// Signal name
+        signal: String,
+    },
+}
+
+/// Force subcommands
+#[cfg(feature = "web")]
+#[derive(Subcommand)]
+enum ForceCommands {
+    /// List forced signals
+    List,
+    
+    /// Force a signal to a value, ignoring writes until it is cleared
+    Set {
+        /// Signal name
+        signal: String,
+        
+        /// Value to force, parsed like `true`, `42` or `1.5`
+        value: String,
+        
+        /// Why the signal is forced
+        #[arg(short, long)]
+        reason: Option<String>,
+        
+        /// Operator recorded as forcing the signal
+        #[arg(long)]
+        user: String,
+    },
+    
+    /// Release a forced signal
+    Clear {
+        /// Signal name
+        signal: String,
+        
+        /// Operator recorded as releasing the signal
+        #[arg(long)]
+        user: String,
+    },
+}
+
+/// License subcommands
+#[cfg(feature = "licensing")]
+#[derive(Subcommand)]
+enum LicenseCommands {
+    /// Compare a configuration with its license
+    Check {
+        /// Configuration file
+        #[arg(value_name = "CONFIG_FILE")]
+        config: PathBuf,
+    },
+    
+    /// Generate the product key licenses are signed with
+    KeyGen {
+        /// Output key file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
+    /// Issue a license file
+    Sign {
+        /// License terms (YAML or JSON)
+        #[arg(value_name = "TERMS_FILE")]
+        terms: PathBuf,
+        
+        /// Key file from `petra license key-gen`
+        #[arg(short, long)]
+        key: PathBuf,
+        
+        /// Output license file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
 /// Storage management subcommands
 #[cfg(feature = "advanced-storage")]
 #[derive(Subcommand)]
@@ -606,6 +920,25 @@ enum StorageCommands {
         #[arg(long)]
         dry_run: bool,
     },
+    
+    /// Check history data for checksum, ordering and duplicate problems
+    Verify {
+        /// Configuration file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        
+        /// Start of the checked range (RFC 3339)
+        #[arg(long)]
+        start_time: Option<String>,
+        
+        /// End of the checked range (RFC 3339)
+        #[arg(long)]
+        end_time: Option<String>,
+        
+        /// Fix what can be fixed; damaged files are moved to quarantine
+        #[arg(long)]
+        repair: bool,
+    },
 }
 
 // ============================================================================
@@ -717,6 +1050,8 @@ async fn main() -> Result<()> {
                 thread_priority,
                 #[cfg(feature = "realtime")]
                 force_realtime,
+                #[cfg(feature = "simulation")]
+                cli.profile,
             ).await
         }
         
@@ -744,7 +1079,10 @@ async fn main() -> Result<()> {
             handle_config_command(config_cmd).await
         }
         
-        #[cfg(any(feature = "examples", feature = "burn-in", feature = "profiling"))]
+        Some(Commands::Schema { output, check }) => {
+            wire_schema(output, check)
+        }
+        
         Some(Commands::Dev { dev_cmd }) => {
             handle_dev_command(dev_cmd).await
         }
@@ -769,6 +1107,26 @@ async fn main() -> Result<()> {
             start_health_server(port, bind, check_interval).await
         }
         
+        #[cfg(all(feature = "scan-budget", feature = "web"))]
+        Some(Commands::Top { url, interval_ms, count }) => {
+            show_scan_budget(url, interval_ms, count).await
+        }
+        
+        #[cfg(all(feature = "enhanced-monitoring", feature = "web"))]
+        Some(Commands::Engine { url, engine_cmd: EngineCommands::Stats }) => {
+            show_engine_stats(&url).await
+        }
+        
+        #[cfg(feature = "web")]
+        Some(Commands::Debug { url, debug_cmd }) => {
+            handle_debug_command(&url, debug_cmd).await
+        }
+        
+        #[cfg(feature = "web")]
+        Some(Commands::Force { url, force_cmd }) => {
+            handle_force_command(&url, force_cmd).await
+        }
+        
         #[cfg(feature = "security")]
         Some(Commands::Security { security_cmd }) => {
             handle_security_command(security_cmd).await
@@ -779,6 +1137,16 @@ async fn main() -> Result<()> {
             handle_storage_command(storage_cmd).await
         }
         
+        #[cfg(feature = "backup")]
+        Some(Commands::Backup { backup_cmd }) => {
+            handle_backup_command(backup_cmd)
+        }
+        
+        #[cfg(feature = "licensing")]
+        Some(Commands::License { license_cmd }) => {
+            handle_license_command(license_cmd)
+        }
+        
         None => {
             // Default behavior based on CLI flags
             if let Some(config_path) = cli.config {
@@ -804,6 +1172,8 @@ async fn main() -> Result<()> {
                         cli.thread_priority,
                         #[cfg(feature = "realtime")]
                         false,
+                        #[cfg(feature = "simulation")]
+                        cli.profile,
                     ).await
                 }
             } else {
@@ -851,7 +1221,6 @@ fn init_logging(cli: &Cli) -> Result<()> {
     match cli.log_format {
         LogFormat::Pretty => {
             tracing_subscriber::registry()
-                .with(env_filter)
                 .with(
                     fmt::layer()
                         .with_target(true)
@@ -860,12 +1229,13 @@ fn init_logging(cli: &Cli) -> Result<()> {
                         .with_file(cli.verbose)
                         .with_line_number(cli.verbose)
                         .pretty()
+                        .with_filter(env_filter)
                 )
+                .with(log_file_layer(cli)?)
                 .init();
         }
         LogFormat::Json => {
             tracing_subscriber::registry()
-                .with(env_filter)
                 .with(
                     fmt::layer()
                         .with_target(true)
@@ -874,18 +1244,21 @@ fn init_logging(cli: &Cli) -> Result<()> {
                         .with_file(true)
                         .with_line_number(true)
                         .json()
+                        .with_filter(env_filter)
                 )
+                .with(log_file_layer(cli)?)
                 .init();
         }
         LogFormat::Compact => {
             tracing_subscriber::registry()
-                .with(env_filter)
                 .with(
                     fmt::layer()
                         .with_target(false)
                         .with_thread_ids(false)
                         .compact()
+                        .with_filter(env_filter)
                 )
+                .with(log_file_layer(cli)?)
                 .init();
         }
     }
@@ -893,10 +1266,53 @@ fn init_logging(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+/// JSON log file layer, filtered independently of the console output
+#[cfg(feature = "log-export")]
+fn log_file_layer<S>(cli: &Cli) -> Result<Option<Box<dyn tracing_subscriber::Layer<S> + Send + Sync>>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use petra::log_export::{self, LogFileConfig};
+    
+    cli.log_file
+        .as_ref()
+        .map(|path| log_export::layer(&LogFileConfig {
+            path: path.clone(),
+            max_size_bytes: cli.log_file_max_mb.saturating_mul(1024 * 1024),
+            max_files: cli.log_file_keep,
+            level: cli.log_file_level,
+        }))
+        .transpose()
+}
+
+#[cfg(not(feature = "log-export"))]
+#[allow(clippy::unnecessary_wraps)]
+fn log_file_layer(_cli: &Cli) -> Result<Option<tracing_subscriber::layer::Identity>> {
+    Ok(None)
+}
+
 // ============================================================================
 // CORE ENGINE EXECUTION
 // ============================================================================
 
+/// Stores opened in the storage stage of [`run_engine`]
+struct Storage {
+    #[cfg(feature = "maintenance-mode")]
+    maintenance_mode: Option<petra::maintenance_mode::MaintenanceMode>,
+    #[cfg(feature = "protocol-sim")]
+    dead_letters: Option<Arc<petra::protocols::dead_letter::DeadLetterQueue>>,
+    #[cfg(all(feature = "web", feature = "audit"))]
+    audit_log: Option<Arc<petra::security::AuditLog>>,
+}
+
+/// Connections made in the protocols stage of [`run_engine`]
+struct Connections {
+    #[cfg(feature = "redundancy")]
+    redundancy: Option<petra::redundancy::Redundancy>,
+    #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+    protocol_manager: Option<Arc<petra::protocols::ProtocolManager>>,
+}
+
 /// Run the main PETRA engine with comprehensive configuration
 async fn run_engine(
     config_path: PathBuf,
@@ -911,6 +1327,8 @@ async fn run_engine(
     thread_priority: Option<u8>,
     #[cfg(feature = "realtime")]
     force_realtime: bool,
+    #[cfg(feature = "simulation")]
+    profile: Option<String>,
 ) -> Result<()> {
     info!("Loading configuration from: {}", config_path.display());
     
@@ -923,6 +1341,25 @@ async fn run_engine(
     
     info!("Configuration loaded successfully");
     
+    // Replace device-bound signals with generated ones under a simulation profile
+    #[cfg(feature = "simulation")]
+    let simulator = {
+        let profile = profile.or_else(|| std::env::var(petra::simulation::PROFILE_ENV).ok());
+        match &config.simulation {
+            Some(simulation) if simulation.is_active(profile.as_deref()) => {
+                let unbound = config.protocols.as_mut().map_or(0, |p| simulation.apply(p));
+                info!(
+                    "Profile '{}': simulating {} signals, {} protocol mappings unbound",
+                    profile.as_deref().unwrap_or_default(),
+                    simulation.signals.len(),
+                    unbound
+                );
+                Some(petra::simulation::Simulator::new(simulation.clone()))
+            }
+            _ => None,
+        }
+    };
+    
     // Create engine
     let mut engine = Engine::new(config.clone())?;
     
@@ -951,20 +1388,616 @@ async fn run_engine(
         set_cpu_affinity(&affinity)?;
     }
 
+    // Operator audit settings shared by the web, MQTT command and gRPC channels
+    #[cfg(all(feature = "audit", any(feature = "web", feature = "mqtt-commands", feature = "grpc")))]
+    let operator_audit = config
+        .security
+        .as_ref()
+        .and_then(|security| security.audit.as_ref())
+        .filter(|audit| audit.enabled);
+    #[cfg(all(feature = "audit", any(feature = "web", feature = "mqtt-commands", feature = "grpc")))]
+    let critical_signals = operator_audit.map(|audit| audit.critical_signals.clone()).unwrap_or_default();
+    #[cfg(all(feature = "esignature", any(feature = "web", feature = "mqtt-commands", feature = "grpc")))]
+    let esignature = match config.security.as_ref() {
+        Some(security) => match (&security.esignature, &security.basic_auth) {
+            (Some(rules), Some(basic_auth)) => Some(Arc::new(
+                petra::security::esignature::ESignatureVerifier::new(rules.clone(), basic_auth.users.clone())?,
+            )),
+            _ => None,
+        },
+        None => None,
+    };
+
+    // Bring the configuration up in order: storage, protocols, blocks, web
+    let mut startup = petra::startup::Startup::new(
+        config.startup.clone().unwrap_or_default(),
+        engine.signal_bus().clone(),
+    );
+
+    // Restore persisted state and open the stores written to later
+    let Storage {
+        #[cfg(feature = "maintenance-mode")]
+        maintenance_mode,
+        #[cfg(feature = "protocol-sim")]
+        dead_letters,
+        #[cfg(all(feature = "web", feature = "audit"))]
+        audit_log,
+    } = startup
+        .stage(StartupStage::Storage, || async {
+            Ok(Storage {
+                // Areas left in maintenance before the last shutdown
+                #[cfg(feature = "maintenance-mode")]
+                maintenance_mode: match &config.maintenance_mode {
+                    Some(maintenance_mode_config) => Some(
+                        petra::maintenance_mode::MaintenanceMode::new(
+                            maintenance_mode_config.clone(),
+                            engine.signal_bus().clone(),
+                        )?
+                        .with_events(engine.events()),
+                    ),
+                    None => None,
+                },
+                #[cfg(feature = "protocol-sim")]
+                dead_letters: match config.protocols.as_ref().and_then(|p| p.dead_letters.as_ref()) {
+                    Some(dead_letters) => Some(Arc::new(
+                        petra::protocols::dead_letter::DeadLetterQueue::open(dead_letters.clone())?,
+                    )),
+                    None => None,
+                },
+                #[cfg(all(feature = "web", feature = "audit"))]
+                audit_log: match operator_audit {
+                    Some(audit) => Some(Arc::new(
+                        petra::security::AuditLog::new(&audit.log_file)?
+                            .with_critical_signals(critical_signals.clone()),
+                    )),
+                    None => None,
+                },
+            })
+        })
+        .await?;
+    #[cfg(feature = "maintenance-mode")]
+    if let (Some(_), Some(maintenance_mode_config)) = (&maintenance_mode, &config.maintenance_mode) {
+        info!("Maintenance mode enabled for {} areas", maintenance_mode_config.areas.len());
+    }
+
+    // Provision certificates, join the redundant pair and connect the
+    // drivers; everything started after this only spawns
+    let Connections {
+        #[cfg(feature = "redundancy")]
+        redundancy,
+        #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+        protocol_manager,
+    } = startup
+        .stage(StartupStage::Protocols, || async {
+            // Fetch or renew the MQTT client certificate before any broker connection
+            #[cfg(feature = "mqtt-tls")]
+            if let Some(mqtt_config) = &config.mqtt {
+                for broker in mqtt_config.failover_configs().iter().chain(&mqtt_config.fan_out_configs()) {
+                    if let Some(tls) = &broker.tls {
+                        petra::protocols::mqtt_tls::provision(tls, &broker.client_id).await?;
+                    }
+                }
+            }
+
+            #[cfg(feature = "redundancy")]
+            let redundancy = match &config.redundancy {
+                Some(redundancy_config) => Some(petra::redundancy::Redundancy::new(
+                    redundancy_config.clone(),
+                    engine.signal_bus().clone(),
+                    engine.engine_control(),
+                    engine.block_control(),
+                )?),
+                None => None,
+            };
+
+            // Scan-class polling and routes run against the simulated
+            // drivers; S7 PLCs and Modbus RTU lines are registered too, so
+            // they can be reconfigured while the engine runs
+            #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+            let protocol_manager = match &config.protocols {
+                Some(protocols) => {
+                    let manager = petra::protocols::ProtocolManager::new(engine.signal_bus().clone())
+                        .with_events(engine.events());
+                    #[cfg(feature = "protocol-sim")]
+                    let manager = match &dead_letters {
+                        Some(queue) => manager.with_dead_letters(Arc::clone(queue)),
+                        None => manager,
+                    };
+                    #[cfg(feature = "maintenance-mode")]
+                    let manager = match &maintenance_mode {
+                        Some(mode) => manager.with_maintenance_mode(mode.clone()),
+                        None => manager,
+                    };
+                    #[cfg(feature = "protocol-sim")]
+                    for sim in &protocols.sim {
+                        let driver = petra::protocols::sim::SimDriver::new(sim)?;
+                        manager.add_driver(sim.name.clone(), Box::new(driver)).await?;
+                    }
+                    #[cfg(feature = "s7-support")]
+                    if let Some(s7) = &protocols.s7 {
+                        for driver in petra::protocols::s7::S7Driver::from_config(s7, engine.signal_bus())? {
+                            manager.add_driver(driver.name().to_string(), Box::new(driver)).await?;
+                        }
+                        info!("S7 driver started for {} PLCs", s7.connections.len());
+                    }
+                    #[cfg(feature = "modbus-support")]
+                    if let Some(modbus) = &protocols.modbus {
+                        let lines = petra::protocols::modbus_rtu::ModbusRtuDriver::from_config(modbus, engine.signal_bus())?;
+                        if !lines.is_empty() {
+                            info!("Modbus RTU driver started for {} serial lines", lines.len());
+                        }
+                        for driver in lines {
+                            manager.add_driver(driver.port().to_string(), Box::new(driver)).await?;
+                        }
+                    }
+                    if manager.all_protocols().await.is_empty() {
+                        None
+                    } else {
+                        for group in &protocols.failover_groups {
+                            manager.add_failover_group(group.clone()).await?;
+                        }
+                        manager.connect_all().await?;
+                        Some(Arc::new(manager))
+                    }
+                }
+                None => None,
+            };
+
+            Ok(Connections {
+                #[cfg(feature = "redundancy")]
+                redundancy,
+                #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+                protocol_manager,
+            })
+        })
+        .await?;
+
+    // The engine stays paused until this node is active
+    #[cfg(feature = "redundancy")]
+    if let (Some(node), Some(redundancy_config)) = (&redundancy, &config.redundancy) {
+        engine.engine_control().pause().await;
+        let runner = node.clone();
+        tokio::spawn(async move {
+            if let Err(e) = runner.run().await {
+                error!("Redundancy channel error: {}", e);
+            }
+        });
+        info!("Redundancy started as node '{}'", redundancy_config.node_id);
+    }
+
+    // Start clock synchronization monitoring if configured
+    #[cfg(feature = "simulation")]
+    if let Some(simulator) = simulator {
+        tokio::spawn(simulator.run(engine.signal_bus().clone()));
+    }
+    
+    #[cfg(feature = "time-sync")]
+    if let Some(time_sync_config) = &config.time_sync {
+        let monitor = petra::time_sync::TimeSyncMonitor::new(time_sync_config.clone())?;
+        tokio::spawn(monitor.run(engine.signal_bus().clone()));
+        info!("Time sync monitoring started");
+    }
+
+    // Start the OPC-UA client if configured
+    #[cfg(feature = "opcua-support")]
+    if let Some(opcua) = config.protocols.as_ref().and_then(|p| p.opcua.clone()) {
+        let items = opcua.subscriptions.len();
+        let client = petra::protocols::opcua::OpcuaClient::new(opcua, engine.signal_bus().clone());
+        std::thread::Builder::new()
+            .name("opcua-client".to_string())
+            .spawn(move || client.run())?;
+        info!("OPC-UA client started for {} monitored items", items);
+    }
+
+    // Start the OPC-UA server if configured
+    #[cfg(feature = "opcua-server")]
+    if let Some(server_config) = config.protocols.as_ref().and_then(|p| p.opcua_server.clone()) {
+        let server = petra::protocols::opcua_server::OpcuaServer::new(server_config, engine.signal_bus().clone());
+
+        // HistoryRead only queries the storage, so the write settings are left at their defaults
+        #[cfg(feature = "history")]
+        let server = match &config.history {
+            Some(history) => server.with_history(Arc::new(petra::history::HistoryManager::new(
+                petra::history::HistoryConfig {
+                    data_dir: history.data_dir.clone(),
+                    retention_days: history.retention_days,
+                    max_batch_size: history.batch_size,
+                    max_memory_entries: history.batch_size,
+                    compression: petra::history::CompressionType::None,
+                    compact_after_days: 7,
+                    enable_index: false,
+                    sync_writes: false,
+                },
+            )?)),
+            None => server,
+        };
+
+        let events = engine.events();
+        std::thread::Builder::new()
+            .name("opcua-server".to_string())
+            .spawn(move || {
+                if let Err(e) = server.run() {
+                    error!("OPC-UA server error: {}", e);
+                    events.publish(petra::events::EventKind::ProtocolDisconnected {
+                        protocol: "opcua".to_string(),
+                        reason: Some(e.to_string()),
+                    });
+                }
+            })?;
+        info!("OPC-UA server started");
+    }
+
+    // Start DNP3 masters and outstations if configured
+    #[cfg(feature = "dnp3-support")]
+    if let Some(dnp3) = config.protocols.as_ref().and_then(|p| p.dnp3.as_ref()) {
+        for master in &dnp3.masters {
+            let master = petra::protocols::dnp3::Dnp3Master::new(master.clone());
+            tokio::spawn(master.run(engine.signal_bus().clone()));
+        }
+        for outstation_config in &dnp3.outstations {
+            let name = outstation_config.name.clone();
+            let outstation = petra::protocols::dnp3::Dnp3Outstation::new(outstation_config.clone());
+            let bus = engine.signal_bus().clone();
+            let events = engine.events();
+            tokio::spawn(async move {
+                if let Err(e) = outstation.run(bus).await {
+                    error!("DNP3 outstation '{}' error: {}", name, e);
+                    events.publish(petra::events::EventKind::ProtocolDisconnected {
+                        protocol: format!("dnp3:{name}"),
+                        reason: Some(e.to_string()),
+                    });
+                }
+            });
+        }
+        info!(
+            "DNP3 started ({} masters, {} outstations)",
+            dnp3.masters.len(),
+            dnp3.outstations.len()
+        );
+    }
+
+    // Start the EtherCAT master if configured, exchanging once per scan
+    #[cfg(feature = "ethercat")]
+    if let Some(ethercat) = config.protocols.as_ref().and_then(|p| p.ethercat.clone()) {
+        let bus = engine.signal_bus().clone();
+        let ticks = engine.scan_ticks();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::ethercat::run(ethercat, bus, ticks).await {
+                error!("EtherCAT master error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "ethercat".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("EtherCAT master started");
+    }
+
+    // Start the PROFINET IO device if configured
+    #[cfg(feature = "profinet")]
+    if let Some(profinet) = config.protocols.as_ref().and_then(|p| p.profinet.clone()) {
+        let bus = engine.signal_bus().clone();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::profinet::run(profinet, bus).await {
+                error!("PROFINET device error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "profinet".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("PROFINET device started");
+    }
+
+    // Start the CoAP client if configured
+    #[cfg(feature = "coap")]
+    if let Some(coap) = config.protocols.as_ref().and_then(|p| p.coap.clone()) {
+        let bus = engine.signal_bus().clone();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::coap::run(coap, bus).await {
+                error!("CoAP client error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "coap".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("CoAP client started");
+    }
+
+    // Start the HTTP polling driver if configured
+    #[cfg(feature = "http")]
+    if let Some(http) = config.protocols.as_ref().and_then(|p| p.http.clone()) {
+        let bus = engine.signal_bus().clone();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::http::run(http, bus).await {
+                error!("HTTP driver error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "http".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("HTTP driver started");
+    }
+
+    // Start the authenticated MQTT command channel if configured
+    #[cfg(feature = "mqtt-commands")]
+    if let Some(mqtt_config) = &config.mqtt {
+        if let Some(commands) = &mqtt_config.commands {
+            let processor = petra::protocols::mqtt_commands::CommandProcessor::new(
+                commands.clone(),
+                engine.signal_bus().clone(),
+                Some(engine.block_control()),
+            )?
+            .with_critical_signals(critical_signals.clone());
+            #[cfg(feature = "esignature")]
+            let processor = match &esignature {
+                Some(verifier) => processor.with_esignature(Arc::clone(verifier)),
+                None => processor,
+            };
+            let mqtt_config = mqtt_config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = petra::protocols::mqtt_commands::run(mqtt_config, processor).await {
+                    error!("MQTT command channel error: {}", e);
+                }
+            });
+        }
+    }
+
+    // Start the Kafka connector if configured
+    #[cfg(feature = "kafka")]
+    if let Some(kafka) = config.protocols.as_ref().and_then(|p| p.kafka.clone()) {
+        let bus = engine.signal_bus().clone();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::kafka::run(kafka, bus).await {
+                error!("Kafka connector error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "kafka".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("Kafka connector started");
+    }
+
+    // Start the NATS connector if configured
+    #[cfg(feature = "nats")]
+    if let Some(nats) = config.protocols.as_ref().and_then(|p| p.nats.clone()) {
+        let bus = engine.signal_bus().clone();
+        let events = engine.events();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::nats::run(nats, bus).await {
+                error!("NATS connector error: {}", e);
+                events.publish(petra::events::EventKind::ProtocolDisconnected {
+                    protocol: "nats".to_string(),
+                    reason: Some(e.to_string()),
+                });
+            }
+        });
+        info!("NATS connector started");
+    }
+
+    // Start the signal bridges if configured
+    #[cfg(feature = "bridge")]
+    if let Some(bridge) = config.protocols.as_ref().and_then(|p| p.bridge.clone()) {
+        let bus = engine.signal_bus().clone();
+        let links = bridge.links.len();
+        tokio::spawn(async move {
+            if let Err(e) = petra::protocols::bridge::run(bridge, bus).await {
+                error!("Signal bridge error: {}", e);
+            }
+        });
+        info!("Signal bridge started with {} link(s)", links);
+    }
+
+    #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+    if let (Some(manager), Some(protocols)) = (&protocol_manager, &config.protocols) {
+        let bus = engine.signal_bus().clone();
+        if let Some(polling) = &protocols.polling {
+            let _classes = petra::protocols::scheduler::PollScheduler::new(polling).spawn(manager, &bus);
+        }
+        let routers = petra::protocols::routing::Router::from_config(&protocols.routes)?;
+        let _routers = petra::protocols::routing::spawn_routers(routers, manager, &bus);
+        let _retries = petra::protocols::dead_letter::spawn_retries(manager);
+        info!("Protocol drivers started: {}", manager.all_protocols().await.join(", "));
+    }
+
+    // Start the Sparkplug B edge node if configured
+    #[cfg(feature = "mqtt-sparkplug")]
+    if let Some(mqtt_config) = &config.mqtt {
+        if let Some(sparkplug) = &mqtt_config.sparkplug {
+            // One edge node session on the main broker (with its standbys)
+            // and one on every fan-out broker
+            for mqtt_config in std::iter::once(mqtt_config.clone()).chain(mqtt_config.fan_out_configs()) {
+                let sparkplug = sparkplug.clone();
+                let bus = engine.signal_bus().clone();
+                let events = engine.events();
+                tokio::spawn(async move {
+                    if let Err(e) = petra::protocols::sparkplug::run(mqtt_config, sparkplug, bus).await {
+                        error!("Sparkplug edge node error: {}", e);
+                        events.publish(petra::events::EventKind::ProtocolDisconnected {
+                            protocol: "sparkplug".to_string(),
+                            reason: Some(e.to_string()),
+                        });
+                    }
+                });
+            }
+        }
+    }
+
+    // Run the first scan so outputs hold computed values before anything
+    // serves them; a standby node leaves its blocks to the active one
+    startup
+        .stage(StartupStage::Blocks, || async {
+            if engine.engine_control().is_paused() {
+                return Ok(());
+            }
+            engine.execute_scan_cycle().await
+        })
+        .await?;
+
+    // Start OEE tracking if configured
+    #[cfg(feature = "oee")]
+    let oee_manager = match &config.oee {
+        Some(oee_config) => {
+            let manager = Arc::new(tokio::sync::RwLock::new(
+                petra::oee::OeeManager::new(oee_config.clone())?,
+            ));
+            tokio::spawn(petra::oee::OeeManager::run(
+                Arc::clone(&manager),
+                engine.signal_bus().clone(),
+            ));
+            info!("OEE tracking started for {} lines", oee_config.lines.len());
+            Some(manager)
+        }
+        None => None,
+    };
+
+    // Start downtime tracking if configured
+    #[cfg(feature = "downtime")]
+    let downtime_manager = match &config.downtime {
+        Some(downtime_config) => {
+            let manager = Arc::new(tokio::sync::RwLock::new(
+                petra::downtime::DowntimeManager::new(downtime_config.clone())?,
+            ));
+            tokio::spawn(petra::downtime::DowntimeManager::run(
+                Arc::clone(&manager),
+                engine.signal_bus().clone(),
+            ));
+            info!("Downtime tracking started for {} assets", downtime_config.assets.len());
+            Some(manager)
+        }
+        None => None,
+    };
+
+    // Start condition-based maintenance if configured
+    #[cfg(feature = "maintenance")]
+    let maintenance_manager = match &config.maintenance {
+        Some(maintenance_config) => {
+            let manager = Arc::new(tokio::sync::RwLock::new(
+                petra::maintenance::MaintenanceManager::new(maintenance_config.clone())?,
+            ));
+            tokio::spawn(petra::maintenance::MaintenanceManager::run(
+                Arc::clone(&manager),
+                engine.signal_bus().clone(),
+            ));
+            info!("Maintenance rules started: {} rules", maintenance_config.rules.len());
+            Some(manager)
+        }
+        None => None,
+    };
+
+    // Start energy monitoring if configured
+    #[cfg(feature = "energy")]
+    let energy_manager = match &config.energy {
+        Some(energy_config) => {
+            let manager = Arc::new(tokio::sync::RwLock::new(
+                petra::energy::EnergyManager::new(energy_config.clone())?,
+            ));
+            tokio::spawn(petra::energy::EnergyManager::run(
+                Arc::clone(&manager),
+                engine.signal_bus().clone(),
+            ));
+            info!("Energy monitoring started for {} meters", energy_config.meters.len());
+            Some(manager)
+        }
+        None => None,
+    };
+
+    // Start scheduled reports if configured
+    #[cfg(feature = "reports")]
+    if let Some(reports_config) = &config.reports {
+        let recorder = std::sync::Arc::new(petra::reports::SignalRecorder::new(reports_config));
+        tokio::spawn(petra::reports::SignalRecorder::run(
+            std::sync::Arc::clone(&recorder),
+            engine.signal_bus().clone(),
+            std::time::Duration::from_millis(reports_config.sample_interval_ms),
+        ));
+        let scheduler = petra::reports::ReportScheduler::new(reports_config.clone(), recorder, chrono::Utc::now())?;
+        tokio::spawn(scheduler.run());
+        info!("Report scheduler started: {} reports", reports_config.definitions.len());
+    }
+
+    // Wait for the web port, then serve the web and gRPC APIs
+    #[cfg(feature = "web")]
+    let listener = startup
+        .stage(StartupStage::Web, || async {
+            match &config.web {
+                Some(_) => web::bind().await.map(Some),
+                None => Ok(None),
+            }
+        })
+        .await?;
+    #[cfg(not(feature = "web"))]
+    startup.stage(StartupStage::Web, || async { Ok(()) }).await?;
+
     // Start the web server if configured
     #[cfg(feature = "web")]
     {
-        if let Some(web_config) = &config.web {
+        if let (Some(web_config), Some(listener)) = (&config.web, listener) {
             let web_bus = engine.signal_bus().clone();
-            let web_config_clone = config.clone();
+            let web_state = web::AppState::new(Arc::new(web_bus), config.clone())
+                .with_config_path(&config_path)
+                .with_events(engine.events())
+                .with_debug(engine.debugger());
+            #[cfg(feature = "enhanced-monitoring")]
+            let web_state = web_state.with_cycle_timings(engine.cycle_timings());
+            #[cfg(feature = "hot-reload")]
+            let web_state = web_state.with_reload(engine.reload_handle());
+            #[cfg(feature = "oee")]
+            let web_state = match &oee_manager {
+                Some(manager) => web_state.with_oee(Arc::clone(manager)),
+                None => web_state,
+            };
+            #[cfg(feature = "downtime")]
+            let web_state = match &downtime_manager {
+                Some(manager) => web_state.with_downtime(Arc::clone(manager)),
+                None => web_state,
+            };
+            #[cfg(feature = "energy")]
+            let web_state = match &energy_manager {
+                Some(manager) => web_state.with_energy(Arc::clone(manager)),
+                None => web_state,
+            };
+            #[cfg(feature = "redundancy")]
+            let web_state = match &redundancy {
+                Some(node) => web_state.with_redundancy(node.clone()),
+                None => web_state,
+            };
+            #[cfg(any(feature = "protocol-sim", feature = "s7-support", feature = "modbus-support"))]
+            let web_state = match &protocol_manager {
+                Some(manager) => web_state.with_protocols(Arc::clone(manager)),
+                None => web_state,
+            };
+            #[cfg(feature = "maintenance")]
+            let web_state = match &maintenance_manager {
+                Some(manager) => web_state.with_maintenance(Arc::clone(manager)),
+                None => web_state,
+            };
+            #[cfg(feature = "maintenance-mode")]
+            let web_state = match &maintenance_mode {
+                Some(mode) => web_state.with_maintenance_mode(mode.clone()),
+                None => web_state,
+            };
+            #[cfg(feature = "audit")]
+            let web_state = match &audit_log {
+                Some(log) => web_state.with_audit(Arc::clone(log)),
+                None => web_state,
+            };
+            #[cfg(feature = "esignature")]
+            let web_state = match &esignature {
+                Some(verifier) => web_state.with_esignature(Arc::clone(verifier)),
+                None => web_state,
+            };
 
             tokio::spawn(async move {
-                if let Err(e) = web::create_server(
-                    Arc::new(web_bus.clone()),
-                    web_config_clone,
-                )
-                .await
-                {
+                if let Err(e) = web::serve_on(listener, web_state).await {
                     error!("Web server error: {}", e);
                 }
             });
@@ -976,6 +2009,32 @@ async fn run_engine(
             );
         }
     }
+
+    // Start the gRPC API if configured
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_config) = &config.grpc {
+        let service = petra::grpc::PetraService::new(
+            grpc_config.clone(),
+            engine.signal_bus().clone(),
+            engine.engine_control(),
+            engine.reload_handle(),
+            Some(config_path.clone()),
+        )?
+        .with_critical_signals(critical_signals.clone());
+        #[cfg(feature = "esignature")]
+        let service = match &esignature {
+            Some(verifier) => service.with_esignature(Arc::clone(verifier)),
+            None => service,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = petra::grpc::serve(service).await {
+                error!("gRPC server error: {}", e);
+            }
+        });
+    }
+
+    startup.finish()?;
+
     // Start the engine
     info!("Starting PETRA engine with {}ms scan time", scan_time);
     let shutdown_signal = setup_shutdown_handler();
@@ -1075,7 +2134,7 @@ fn print_detailed_config_info(config: &Config) {
     
     #[cfg(feature = "alarms")]
     if let Some(alarm_config) = &config.alarms {
-        println!("  {} {} configured", "Alarms:".blue().bold(), alarm_config.definitions.len());
+        println!("  {} {} configured", "Alarms:".blue().bold(), alarm_config.alarms.len());
     }
     
     #[cfg(feature = "security")]
@@ -1122,6 +2181,11 @@ async fn show_features(
     print_feature_status("s7-support", features.is_enabled("s7-support"));
     print_feature_status("modbus-support", features.is_enabled("modbus-support"));
     print_feature_status("opcua-support", features.is_enabled("opcua-support"));
+    print_feature_status("dnp3-support", features.is_enabled("dnp3-support"));
+    print_feature_status("ethercat", features.is_enabled("ethercat"));
+    print_feature_status("profinet", features.is_enabled("profinet"));
+    print_feature_status("coap", features.is_enabled("coap"));
+    print_feature_status("http", features.is_enabled("http"));
     
     // Storage features
     println!("\n{}", "Storage Features:".yellow().bold());
@@ -1199,9 +2263,39 @@ async fn handle_config_command(cmd: ConfigCommands) -> Result<()> {
         ConfigCommands::Lint { config, fix } => {
             lint_config(config, fix).await
         }
+        ConfigCommands::Interlocks { config, output } => {
+            interlock_report(config, output).await
+        }
     }
 }
 
+/// Write or check the wire schema package
+fn wire_schema(output: PathBuf, check: bool) -> Result<()> {
+    if !check {
+        petra::wire::write_package(&output)?;
+        println!("{} Wrote wire schema {} to {}",
+            "SUCCESS".green().bold(),
+            petra::wire::WIRE_VERSION,
+            output.display()
+        );
+        return Ok(());
+    }
+    
+    let outdated = petra::wire::outdated_files(&output)?;
+    if outdated.is_empty() {
+        println!("{} Wire schema {} is up to date", "SUCCESS".green().bold(), petra::wire::WIRE_VERSION);
+        return Ok(());
+    }
+    for name in &outdated {
+        println!("{} {}", "OUTDATED".yellow().bold(), output.join(name).display());
+    }
+    Err(PlcError::Config(format!(
+        "{} wire schema files are outdated, run `petra schema --output {}`",
+        outdated.len(),
+        output.display()
+    )))
+}
+
 /// Generate example configuration files
 async fn generate_example_config(
     output: PathBuf,
@@ -1324,6 +2418,34 @@ async fn show_config_schema(
 }
 
 /// Lint configuration file for best practices
+/// Write the cause-and-effect matrices of all INTERLOCK blocks as Markdown
+async fn interlock_report(config_path: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let config = Config::from_file(&config_path)?;
+    
+    let mut report = format!("# Cause and effect: {}\n\n", config_path.display());
+    let mut count = 0;
+    for block in config.blocks.iter().filter(|b| b.block_type == "INTERLOCK") {
+        report.push_str(&petra::blocks::interlock::cause_effect_report(block)?);
+        report.push('\n');
+        count += 1;
+    }
+    
+    if count == 0 {
+        println!("{}", "No INTERLOCK blocks in configuration".yellow());
+        return Ok(());
+    }
+    
+    match output {
+        Some(path) => {
+            std::fs::write(&path, report)?;
+            println!("Wrote {} interlock matrices to {}", count, path.display());
+        }
+        None => print!("{}", report),
+    }
+    
+    Ok(())
+}
+
 async fn lint_config(config_path: PathBuf, apply_fixes: bool) -> Result<()> {
     info!("Linting configuration: {}", config_path.display());
     
@@ -1440,6 +2562,11 @@ async fn show_help_and_features() -> Result<()> {
             ("Modbus", features.is_enabled("modbus-support")),
             ("S7", features.is_enabled("s7-support")),
             ("OPC-UA", features.is_enabled("opcua-support")),
+            ("DNP3", features.is_enabled("dnp3-support")),
+            ("EtherCAT", features.is_enabled("ethercat")),
+            ("PROFINET", features.is_enabled("profinet")),
+            ("CoAP", features.is_enabled("coap")),
+            ("HTTP", features.is_enabled("http")),
         ]),
         ("Storage", vec![
             ("History", features.is_enabled("history")),
@@ -1473,9 +2600,22 @@ async fn show_help_and_features() -> Result<()> {
 // ============================================================================
 
 /// Handle development and testing commands
-#[cfg(any(feature = "examples", feature = "burn-in", feature = "profiling"))]
 async fn handle_dev_command(cmd: DevCommands) -> Result<()> {
     match cmd {
+        DevCommands::TuneScan { config, cycles, latency_ms, json } => {
+            let config = Config::from_file(&config)?;
+            let options = petra::scan_tuning::TuneOptions {
+                cycles,
+                request_latency: std::time::Duration::from_millis(latency_ms),
+            };
+            let report = petra::scan_tuning::tune(&config, &options)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{report}");
+            }
+        }
+        
         #[cfg(feature = "examples")]
         DevCommands::Examples { list, run, generate_data } => {
             if list {
@@ -1527,10 +2667,31 @@ async fn handle_protocol_command(cmd: ProtocolCommands) -> Result<()> {
         }
         
         #[cfg(feature = "modbus-support")]
-        ProtocolCommands::Modbus { address, unit_id, register } => {
+        ProtocolCommands::Modbus { address, unit_id, register, scan: false, .. } => {
             petra::modbus::test_connection(&address, unit_id, register).await?;
         }
         
+        #[cfg(feature = "modbus-support")]
+        ProtocolCommands::Modbus { address, unit_id, register, scan: true, count, types, block_size, timeout_ms, output } => {
+            let options = petra::modbus::ScanOptions {
+                kinds: types,
+                start: register,
+                count,
+                block_size,
+                timeout: std::time::Duration::from_millis(timeout_ms),
+            };
+            let report = petra::modbus::scan(&address, unit_id, &options).await?;
+            println!("{report}");
+            
+            let yaml = report.suggested_yaml(&format!("unit_{unit_id}"), options.timeout)?;
+            if let Some(path) = output {
+                std::fs::write(&path, yaml)?;
+                println!("\n{} {}", "Suggested mapping written to".green(), path.display());
+            } else {
+                println!("\n{}\n{yaml}", "Suggested mapping:".yellow().bold());
+            }
+        }
+        
         #[cfg(feature = "s7-support")]
         ProtocolCommands::S7 { address, rack, slot } => {
             petra::s7::test_connection(&address, rack, slot).await?;
@@ -1583,6 +2744,260 @@ async fn start_health_server(port: u16, bind: String, check_interval: u64) -> Re
     Ok(())
 }
 
+/// Poll a running engine's scan budget and print per-subsystem usage
+#[cfg(all(feature = "scan-budget", feature = "web"))]
+async fn show_scan_budget(url: String, interval_ms: u64, count: Option<u32>) -> Result<()> {
+    use petra::scan_budget::BudgetSnapshot;
+
+    let endpoint = format!("{}/api/budget", url.trim_end_matches('/'));
+    let client = reqwest::Client::new();
+    let fetch = || async {
+        client
+            .get(&endpoint)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| PlcError::WebServer(format!("Failed to query {}: {}", endpoint, e)))?
+            .json::<BudgetSnapshot>()
+            .await
+            .map_err(|e| PlcError::WebServer(format!("Invalid budget response: {}", e)))
+    };
+
+    let mut previous = fetch().await?;
+    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(interval_ms.max(100)));
+    ticker.tick().await;
+    let mut refreshes = 0u32;
+
+    while count.is_none_or(|count| refreshes < count) {
+        ticker.tick().await;
+        let current = fetch().await?;
+        let scans = current.scans.saturating_sub(previous.scans);
+
+        // Clear the screen and home the cursor
+        print!("\x1B[2J\x1B[H");
+        println!("{} {}  {} scans in last interval", "petra top".bold(), endpoint, scans);
+        println!();
+        println!(
+            "{:<12} {:>8} {:>12} {:>14} {:>14}",
+            "SUBSYSTEM".bold(),
+            "CPU%".bold(),
+            "us/scan".bold(),
+            "allocs/scan".bold(),
+            "KiB/s".bold()
+        );
+        for rate in current.rates_since(&previous) {
+            println!(
+                "{:<12} {:>8.2} {:>12.1} {:>14.1} {:>14.1}",
+                rate.name,
+                rate.cpu_percent,
+                rate.us_per_scan,
+                rate.allocs_per_scan,
+                rate.bytes_per_sec / 1024.0
+            );
+        }
+
+        previous = current;
+        refreshes += 1;
+    }
+
+    Ok(())
+}
+
+/// Print the scan cycle breakdown of a running engine
+#[cfg(all(feature = "enhanced-monitoring", feature = "web"))]
+async fn show_engine_stats(url: &str) -> Result<()> {
+    use petra::engine::CycleBreakdown;
+
+    let endpoint = format!("{}/api/engine/cycle", url.trim_end_matches('/'));
+    let breakdown = reqwest::Client::new()
+        .get(&endpoint)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| PlcError::WebServer(format!("Failed to query {}: {}", endpoint, e)))?
+        .json::<CycleBreakdown>()
+        .await
+        .map_err(|e| PlcError::WebServer(format!("Invalid cycle response: {}", e)))?;
+
+    println!("{} {}  {} cycles", "petra engine stats".bold(), endpoint, breakdown.cycles);
+    println!();
+    println!(
+        "{:<14} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "PHASE (us)".bold(),
+        "MIN".bold(),
+        "MEAN".bold(),
+        "P50".bold(),
+        "P90".bold(),
+        "P99".bold(),
+        "P99.9".bold(),
+        "MAX".bold()
+    );
+    for stats in breakdown.phases.iter().chain([&breakdown.cycle, &breakdown.jitter]) {
+        println!(
+            "{:<14} {:>10} {:>10.1} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            stats.name, stats.min_us, stats.mean_us, stats.p50_us, stats.p90_us, stats.p99_us, stats.p999_us, stats.max_us
+        );
+    }
+
+    Ok(())
+}
+
+/// Drive the debugger of a running engine through its web server
+#[cfg(feature = "web")]
+async fn handle_debug_command(url: &str, cmd: DebugCommands) -> Result<()> {
+    use petra::engine::{DebugStatus, StepTrace, Watchpoint};
+    
+    let base = format!("{}/api/debug", url.trim_end_matches('/'));
+    let client = reqwest::Client::new();
+    let request = |method: reqwest::Method, path: &str| {
+        let endpoint = format!("{base}{path}");
+        let request = client.request(method, &endpoint);
+        async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| PlcError::WebServer(format!("Failed to query {}: {}", endpoint, e)))?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(PlcError::WebServer(format!("{} returned {}: {}", endpoint, status, body)));
+            }
+            Ok::<_, PlcError>(response)
+        }
+    };
+    let json = |e: reqwest::Error| PlcError::WebServer(format!("Invalid debugger response: {}", e));
+    
+    let print_status = |status: &DebugStatus| {
+        let state = if status.paused { "paused".yellow().bold() } else { "running".green().bold() };
+        println!("Engine {} after {} scans", state, status.scan);
+        for watchpoint in &status.watchpoints {
+            println!("  watch {:<24} {:>12} ({} changes)", watchpoint.signal, show(watchpoint.value.as_ref()), watchpoint.hits);
+        }
+        for hit in &status.halted_by {
+            println!(
+                "  {} {} changed {} -> {} in scan {} ({})",
+                "halted:".red().bold(),
+                hit.signal,
+                show(hit.from.as_ref()),
+                show(hit.to.as_ref()),
+                hit.scan,
+                hit.block.as_deref().unwrap_or("outside the main scan")
+            );
+        }
+    };
+    
+    match cmd {
+        DebugCommands::Status => {
+            print_status(&request(reqwest::Method::GET, "").await?.json().await.map_err(json)?);
+        }
+        DebugCommands::Pause => {
+            print_status(&request(reqwest::Method::POST, "/pause").await?.json().await.map_err(json)?);
+        }
+        DebugCommands::Resume => {
+            print_status(&request(reqwest::Method::POST, "/resume").await?.json().await.map_err(json)?);
+        }
+        DebugCommands::Step => {
+            let trace: StepTrace = request(reqwest::Method::POST, "/step").await?.json().await.map_err(json)?;
+            println!("{} {}", "Scan".bold(), trace.scan);
+            for block in &trace.blocks {
+                let error = block.error.as_deref().map(|e| format!("  {}", e.red())).unwrap_or_default();
+                println!("  {} ({}, {} us){}", block.block.bold(), block.block_type, block.duration_us, error);
+                for (name, input) in &block.inputs {
+                    println!("    in  {:<10} {:<24} {}", name, input.signal, show(input.value.as_ref()));
+                }
+                for (name, output) in &block.outputs {
+                    println!("    out {:<10} {:<24} {}", name, output.signal, show(output.value.as_ref()));
+                }
+            }
+            for hit in &trace.hits {
+                println!(
+                    "  {} {} changed {} -> {} ({})",
+                    "watch:".red().bold(),
+                    hit.signal,
+                    show(hit.from.as_ref()),
+                    show(hit.to.as_ref()),
+                    hit.block.as_deref().unwrap_or("outside the main scan")
+                );
+            }
+        }
+        DebugCommands::Watch { signal } => {
+            let watchpoint: Watchpoint =
+                request(reqwest::Method::PUT, &format!("/watchpoints/{signal}")).await?.json().await.map_err(json)?;
+            println!("Watching {} (now {})", watchpoint.signal, show(watchpoint.value.as_ref()));
+        }
+        DebugCommands::Unwatch { signal } => {
+            request(reqwest::Method::DELETE, &format!("/watchpoints/{signal}")).await?;
+            println!("Removed watchpoint on {}", signal);
+        }
+    }
+    Ok(())
+}
+
+/// Force and release signals of a running engine through its web server
+#[cfg(feature = "web")]
+async fn handle_force_command(url: &str, cmd: ForceCommands) -> Result<()> {
+    use petra::signal::ForcedSignal;
+    
+    let base = url.trim_end_matches('/');
+    let client = reqwest::Client::new();
+    let send = |request: reqwest::RequestBuilder, endpoint: String| async move {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PlcError::WebServer(format!("Failed to query {}: {}", endpoint, e)))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PlcError::WebServer(format!("{} returned {}: {}", endpoint, status, body)));
+        }
+        Ok::<_, PlcError>(response)
+    };
+    let json = |e: reqwest::Error| PlcError::WebServer(format!("Invalid force response: {}", e));
+    
+    match cmd {
+        ForceCommands::List => {
+            let endpoint = format!("{base}/api/forces");
+            let forces: Vec<ForcedSignal> = send(client.get(&endpoint), endpoint).await?.json().await.map_err(json)?;
+            if forces.is_empty() {
+                println!("No signals are forced");
+            }
+            for forced in &forces {
+                println!(
+                    "{:<32} {:>12}  by {} since {}{}",
+                    forced.signal.bold(),
+                    forced.value,
+                    forced.user,
+                    forced.since.format("%Y-%m-%d %H:%M:%S"),
+                    forced.reason.as_deref().map(|r| format!(" ({r})")).unwrap_or_default()
+                );
+            }
+        }
+        ForceCommands::Set { signal, value, reason, user } => {
+            let value: petra::Value = value.parse()?;
+            let endpoint = format!("{base}/api/signals/{signal}/force");
+            let request = client
+                .put(&endpoint)
+                .header("x-petra-user", user)
+                .json(&serde_json::json!({ "value": value, "reason": reason }));
+            let forced: ForcedSignal = send(request, endpoint).await?.json().await.map_err(json)?;
+            println!("{} {} to {}", "Forced".yellow().bold(), forced.signal, forced.value);
+        }
+        ForceCommands::Clear { signal, user } => {
+            let endpoint = format!("{base}/api/signals/{signal}/force");
+            let forced: ForcedSignal =
+                send(client.delete(&endpoint).header("x-petra-user", user), endpoint).await?.json().await.map_err(json)?;
+            println!("Released {} (was forced to {})", forced.signal, forced.value);
+        }
+    }
+    Ok(())
+}
+
+/// A signal value as shown by `petra debug`
+#[cfg(feature = "web")]
+fn show(value: Option<&petra::Value>) -> String {
+    value.map_or_else(|| "-".to_string(), ToString::to_string)
+}
+
 /// Handle security management commands
 #[cfg(feature = "security")]
 async fn handle_security_command(cmd: SecurityCommands) -> Result<()> {
@@ -1647,6 +3062,113 @@ async fn handle_security_command(cmd: SecurityCommands) -> Result<()> {
     Ok(())
 }
 
+/// Handle backup commands
+#[cfg(feature = "backup")]
+fn handle_backup_command(cmd: BackupCommands) -> Result<()> {
+    use petra::backup::{self, BackupOptions, RestoreOptions};
+    
+    match cmd {
+        BackupCommands::Create { config, output, history_days } => {
+            let manifest = backup::create(&config, &output, &BackupOptions { history_days })?;
+            println!("{} Backup written to {} ({} files)",
+                "SUCCESS".green().bold(),
+                output.display(),
+                manifest.entries.len()
+            );
+        }
+        
+        BackupCommands::Restore { archive, config, force } => {
+            let report = backup::restore(&archive, &RestoreOptions { config_path: config, force })?;
+            for file in &report.files {
+                println!("  {}", file.display());
+            }
+            println!("{} Restored {} files; start the node with: petra run {}",
+                "SUCCESS".green().bold(),
+                report.files.len(),
+                report.config_path.display()
+            );
+        }
+        
+        BackupCommands::List { archive } => {
+            let manifest = backup::read_manifest(&archive)?;
+            println!("Backup of {} taken {} by PETRA {}",
+                manifest.config_path.display(),
+                manifest.created_at.to_rfc3339(),
+                manifest.petra_version
+            );
+            if let Some(since) = manifest.history_since {
+                println!("History since {}", since.to_rfc3339());
+            }
+            for entry in &manifest.entries {
+                println!("  {:>10}  {}", entry.size, entry.path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle license commands
+#[cfg(feature = "licensing")]
+fn handle_license_command(cmd: LicenseCommands) -> Result<()> {
+    use petra::license::{self, Entitlement, LicenseFile, LicenseStatus, LicenseTerms};
+    
+    match cmd {
+        LicenseCommands::Check { config } => {
+            let config = Config::from_file(&config)?;
+            let Some(entitlement) = Entitlement::load(&config)? else {
+                println!("Licensing is not enabled for this configuration");
+                return Ok(());
+            };
+            let report = entitlement.check(&config, chrono::Utc::now());
+            let terms = entitlement.terms();
+            println!("License {} issued to {}", report.license_id, report.licensee);
+            match report.status {
+                LicenseStatus::Valid => match terms.expires {
+                    Some(expires) => println!("  Expires:   {}", expires.to_rfc3339()),
+                    None => println!("  Expires:   never"),
+                },
+                LicenseStatus::Grace { expired, grace_ends } => println!("  Expired:   {} ({} until {})",
+                    expired.to_rfc3339(), "grace period".yellow(), grace_ends.to_rfc3339()),
+                LicenseStatus::Expired { expired } => println!("  Expired:   {}", expired.to_rfc3339().red()),
+            }
+            let limit = |limit: Option<usize>| limit.map_or("unlimited".to_string(), |l| l.to_string());
+            println!("  Signals:   {} / {}", report.usage.signals, limit(terms.max_signals));
+            println!("  Blocks:    {} / {}", report.usage.blocks, limit(terms.max_blocks));
+            println!("  Protocols: {} / {} [{}]", report.usage.protocols.len(),
+                limit(terms.max_protocols), report.usage.protocols.join(", "));
+            for violation in &report.violations {
+                println!("  {} {}", "VIOLATION".red().bold(), violation);
+            }
+            if !report.permitted() {
+                return Err(PlcError::Config(report.to_string()));
+            }
+            println!("{} Configuration is covered by the license", "SUCCESS".green().bold());
+        }
+        
+        LicenseCommands::KeyGen { output } => {
+            use rand::RngCore;
+            let mut secret = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut secret);
+            std::fs::write(&output, secret)?;
+            let key = ed25519_dalek::SigningKey::from_bytes(&secret);
+            println!("{} Key written to {}; keep it secret", "SUCCESS".green().bold(), output.display());
+            println!("Public key (PETRA_LICENSE_PUBLIC_KEY): {}", license::public_key(&key));
+        }
+        
+        LicenseCommands::Sign { terms, key, output } => {
+            let terms: LicenseTerms = serde_yaml::from_str(&std::fs::read_to_string(&terms)?)?;
+            let key: [u8; 32] = std::fs::read(&key)?.try_into()
+                .map_err(|_| PlcError::Config(format!("{} is not an Ed25519 key", key.display())))?;
+            let key = ed25519_dalek::SigningKey::from_bytes(&key);
+            let file = LicenseFile::sign(terms, &key)?;
+            std::fs::write(&output, serde_json::to_string_pretty(&file)?)?;
+            println!("{} License {} written to {}", "SUCCESS".green().bold(), file.terms.license_id, output.display());
+            println!("Public key (PETRA_LICENSE_PUBLIC_KEY): {}", license::public_key(&key));
+        }
+    }
+    Ok(())
+}
+
 /// Handle storage management commands
 #[cfg(feature = "advanced-storage")]
 async fn handle_storage_command(cmd: StorageCommands) -> Result<()> {
@@ -1697,6 +3219,55 @@ async fn handle_storage_command(cmd: StorageCommands) -> Result<()> {
                 println!("  Space reclaimed: {} MB", stats.space_reclaimed_mb);
             }
         }
+        
+        StorageCommands::Verify { config, start_time, end_time, repair } => {
+            use petra::storage::verify::{self, VerifyOptions};
+            
+            let parse = |time: Option<String>| -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+                time.map(|t| {
+                    chrono::DateTime::parse_from_rfc3339(&t)
+                        .map(|t| t.with_timezone(&chrono::Utc))
+                        .map_err(|e| PlcError::Config(format!("Invalid time '{t}': {e}")))
+                })
+                .transpose()
+            };
+            let options = VerifyOptions {
+                start: parse(start_time)?,
+                end: parse(end_time)?,
+                repair,
+            };
+            let config = Config::from_file(config.unwrap_or_else(|| PathBuf::from("petra.yaml")))?;
+            let history = config
+                .history
+                .ok_or_else(|| PlcError::Config("No history storage is configured".to_string()))?;
+            
+            #[cfg_attr(not(feature = "clickhouse"), allow(unused_mut))]
+            let mut report = verify::verify_history(&history.data_dir, &options)?;
+            #[cfg(feature = "clickhouse")]
+            if let Some(clickhouse) = &history.clickhouse {
+                let storage = petra::storage::clickhouse::ClickHouseStorage::new(
+                    &clickhouse.url,
+                    &clickhouse.database,
+                    None,
+                    None,
+                    history.batch_size,
+                    3,
+                    1000,
+                    false,
+                )
+                .await?;
+                verify::verify_clickhouse(&storage, &history.data_dir, &options, &mut report).await?;
+            }
+            
+            print!("{report}");
+            if !report.is_clean() {
+                return Err(PlcError::Runtime(format!(
+                    "{} storage issues need attention",
+                    report.unresolved().count()
+                )));
+            }
+            println!("{} History data verified", "SUCCESS".green().bold());
+        }
     }
     Ok(())
 }
@@ -1715,15 +3286,14 @@ async fn handle_run(config_file: PathBuf) -> Result<()> {
     {
         if let Some(web_config) = &config.web {
             let web_bus = engine.signal_bus().clone();
-            let web_config_clone = config.clone();
+            let web_state = web::AppState::new(Arc::new(web_bus), config.clone())
+                .with_config_path(&config_file)
+                .with_events(engine.events());
+            #[cfg(feature = "hot-reload")]
+            let web_state = web_state.with_reload(engine.reload_handle());
 
                 tokio::spawn(async move {
-                    if let Err(e) = web::create_server(
-                        Arc::new(web_bus.clone()),
-                        web_config_clone,
-                    )
-                    .await
-                    {
+                    if let Err(e) = web::serve(web_state).await {
                         error!("Web server error: {}", e);
                     }
                 });
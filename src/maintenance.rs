@@ -0,0 +1,1000 @@
+// src/maintenance.rs
+//! Condition-based maintenance rules
+//!
+//! Maintenance rules watch asset usage and process trends and raise a work
+//! item when service is due. Each rule combines any of:
+//!
+//! - `runtime_hours`: running hours since the rule was last serviced
+//! - `starts`: starts since the rule was last serviced
+//! - `trend`: the mean or least-squares slope of an analog signal over a
+//!   sliding window, such as rising bearing temperature or vibration
+//!
+//! Runtime and starts come from counter signals when an asset has them, for
+//! example the outputs of runtime and totalizer logic in the PLC program, and
+//! are otherwise accumulated here from the asset's `run_signal`.
+//!
+//! A rule is due when any (or, with `combine: all`, every) condition holds.
+//! The manager then opens a [`WorkItem`] for it; while that item is open no
+//! further items are raised for the rule. Completing the item resets the
+//! rule's runtime and start baselines. Rule state is published as signals
+//! (`maintenance.<rule>.due`, `.runtime_hours`, `.starts`) that alarms can
+//! watch, and work items are served through the web API. Counters, baselines
+//! and work items are persisted to `storage_path`.
+//!
+//! ```yaml
+//! maintenance:
+//!   storage_path: data/maintenance.json
+//!   assets:
+//!     - name: pump1
+//!       run_signal: pump1.running
+//!     - name: compressor
+//!       runtime_signal: compressor.run_hours
+//!       starts_signal: compressor.starts
+//!   rules:
+//!     - name: pump1_bearings
+//!       asset: pump1
+//!       description: Inspect and regrease bearings
+//!       priority: high
+//!       conditions:
+//!         - { type: runtime_hours, hours: 2000 }
+//!         - { type: trend, signal: pump1.bearing_temp, window_minutes: 240, slope_above: 0.5 }
+//!     - name: compressor_valves
+//!       asset: compressor
+//!       combine: all
+//!       conditions:
+//!         - { type: starts, count: 10000 }
+//!         - { type: runtime_hours, hours: 4000 }
+//! ```
+
+use crate::scan_budget::{measure, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+/// How often counters are persisted when no work item changed
+const PERSIST_INTERVAL_S: i64 = 60;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Maintenance subsystem configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceConfig {
+    /// Assets whose usage is tracked
+    pub assets: Vec<MaintenanceAssetConfig>,
+
+    /// Rules raising work items
+    pub rules: Vec<MaintenanceRule>,
+
+    /// JSON file where counters and work items are persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<PathBuf>,
+
+    /// How often rules are evaluated
+    #[serde(default = "default_update_interval_ms")]
+    pub update_interval_ms: u64,
+
+    /// Maximum number of completed work items kept
+    #[serde(default = "default_max_work_items")]
+    pub max_work_items: usize,
+}
+
+/// A maintained asset and where its usage counters come from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceAssetConfig {
+    /// Asset name, unique among assets and rules
+    pub name: String,
+
+    /// Boolean signal, true while the asset runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_signal: Option<String>,
+
+    /// Float signal carrying total running hours
+    ///
+    /// When unset, running hours are accumulated from `run_signal`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_signal: Option<String>,
+
+    /// Integer signal carrying the total number of starts
+    ///
+    /// When unset, rising edges of `run_signal` are counted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_signal: Option<String>,
+}
+
+/// Urgency of a maintenance rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenancePriority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// How the conditions of a rule are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Combine {
+    /// Due when any condition holds
+    #[default]
+    Any,
+    /// Due when every condition holds
+    All,
+}
+
+/// A condition-based maintenance rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRule {
+    /// Rule name, unique among assets and rules
+    pub name: String,
+
+    /// Asset the rule applies to
+    pub asset: String,
+
+    /// Work to be done, copied into raised work items
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub priority: MaintenancePriority,
+
+    #[serde(default)]
+    pub combine: Combine,
+
+    pub conditions: Vec<Condition>,
+}
+
+/// One condition of a maintenance rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// Running hours since the last service reach `hours`
+    RuntimeHours { hours: f64 },
+
+    /// Starts since the last service reach `count`
+    Starts { count: u64 },
+
+    /// Statistics of `signal` over the last `window_minutes` cross a limit
+    Trend {
+        signal: String,
+        #[serde(default = "default_window_minutes")]
+        window_minutes: u64,
+        /// Samples needed in the window before the condition can hold
+        #[serde(default = "default_min_samples")]
+        min_samples: usize,
+        /// Holds when the mean exceeds this value
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mean_above: Option<f64>,
+        /// Holds when the slope, in units per hour, exceeds this value
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        slope_above: Option<f64>,
+    },
+}
+
+impl MaintenanceConfig {
+    /// Validate assets and rules
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config("Maintenance update interval cannot be 0".to_string()));
+        }
+
+        let mut names = HashSet::new();
+        let mut assets = HashMap::new();
+        for asset in &self.assets {
+            if !names.insert(asset.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate maintenance name '{}'", asset.name)));
+            }
+            assets.insert(asset.name.as_str(), asset);
+        }
+
+        for rule in &self.rules {
+            if !names.insert(rule.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate maintenance name '{}'", rule.name)));
+            }
+            let asset = assets.get(rule.asset.as_str()).ok_or_else(|| {
+                PlcError::Config(format!(
+                    "Maintenance rule '{}' refers to unknown asset '{}'",
+                    rule.name, rule.asset
+                ))
+            })?;
+            if rule.conditions.is_empty() {
+                return Err(PlcError::Config(format!(
+                    "Maintenance rule '{}' has no conditions",
+                    rule.name
+                )));
+            }
+            for condition in &rule.conditions {
+                condition.validate(&rule.name, asset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Condition {
+    fn validate(&self, rule: &str, asset: &MaintenanceAssetConfig) -> Result<()> {
+        let error = |message: &str| Err(PlcError::Config(format!("Maintenance rule '{rule}': {message}")));
+        match self {
+            Self::RuntimeHours { hours } => {
+                if *hours <= 0.0 {
+                    return error("runtime_hours must be positive");
+                }
+                if asset.runtime_signal.is_none() && asset.run_signal.is_none() {
+                    return error("runtime_hours needs an asset with runtime_signal or run_signal");
+                }
+            }
+            Self::Starts { count } => {
+                if *count == 0 {
+                    return error("starts count must be at least 1");
+                }
+                if asset.starts_signal.is_none() && asset.run_signal.is_none() {
+                    return error("starts needs an asset with starts_signal or run_signal");
+                }
+            }
+            Self::Trend { window_minutes, min_samples, mean_above, slope_above, .. } => {
+                if *window_minutes == 0 {
+                    return error("trend window_minutes cannot be 0");
+                }
+                if *min_samples < 2 {
+                    return error("trend min_samples must be at least 2");
+                }
+                if mean_above.is_none() && slope_above.is_none() {
+                    return error("trend needs mean_above or slope_above");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_update_interval_ms() -> u64 {
+    1000
+}
+
+const fn default_max_work_items() -> usize {
+    1000
+}
+
+const fn default_window_minutes() -> u64 {
+    60
+}
+
+const fn default_min_samples() -> usize {
+    10
+}
+
+// ============================================================================
+// STATE
+// ============================================================================
+
+/// Usage counters of an asset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageCounters {
+    pub runtime_hours: f64,
+    pub starts: u64,
+}
+
+/// Lifecycle of a work item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkItemStatus {
+    Open,
+    Acknowledged,
+    Completed,
+}
+
+/// Maintenance work raised by a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkItem {
+    /// Monotonic work item identifier
+    pub id: u64,
+    pub rule: String,
+    pub asset: String,
+    pub description: String,
+    pub priority: MaintenancePriority,
+    pub status: WorkItemStatus,
+    pub raised_at: DateTime<Utc>,
+    /// Conditions that held when the item was raised
+    pub reasons: Vec<String>,
+    /// Usage since the last service when the item was raised
+    pub usage: UsageCounters,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl WorkItem {
+    fn is_closed(&self) -> bool {
+        self.status == WorkItemStatus::Completed
+    }
+}
+
+/// Operator update of a work item
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkItemUpdate {
+    /// Operator name
+    pub user: String,
+    /// Optional free-text comment
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Current evaluation of a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleStatus {
+    pub rule: String,
+    pub asset: String,
+    pub due: bool,
+    /// Usage since the last service
+    pub usage: UsageCounters,
+    /// Conditions currently holding
+    pub reasons: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_serviced: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_item: Option<u64>,
+}
+
+/// Counter values at the last service of a rule
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ServiceBaseline {
+    counters: UsageCounters,
+    serviced_at: Option<DateTime<Utc>>,
+}
+
+/// Contents of the storage file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredState {
+    #[serde(default)]
+    counters: HashMap<String, UsageCounters>,
+    #[serde(default)]
+    baselines: HashMap<String, ServiceBaseline>,
+    #[serde(default)]
+    work_items: Vec<WorkItem>,
+}
+
+/// Mean and least-squares slope of a trend window
+#[derive(Debug, Clone, Copy)]
+struct TrendStats {
+    mean: f64,
+    slope_per_hour: f64,
+}
+
+fn trend_stats<'a>(samples: impl Iterator<Item = &'a (DateTime<Utc>, f64)>) -> Option<(usize, TrendStats)> {
+    let points: Vec<(f64, f64)> = {
+        let mut origin = None;
+        samples
+            .map(|(at, value)| {
+                let origin = *origin.get_or_insert(*at);
+                (hours_between(origin, *at), *value)
+            })
+            .collect()
+    };
+    if points.is_empty() {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+        (cov + (x - mean_x) * (y - mean), var + (x - mean_x).powi(2))
+    });
+    let slope_per_hour = if variance > 0.0 { covariance / variance } else { 0.0 };
+    Some((points.len(), TrendStats { mean, slope_per_hour }))
+}
+
+fn hours_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).to_std().map_or(0.0, |d| d.as_secs_f64() / 3600.0)
+}
+
+// ============================================================================
+// EVALUATION
+// ============================================================================
+
+/// Evaluates maintenance rules and tracks work items
+pub struct MaintenanceManager {
+    config: MaintenanceConfig,
+    counters: HashMap<String, UsageCounters>,
+    baselines: HashMap<String, ServiceBaseline>,
+    work_items: VecDeque<WorkItem>,
+    statuses: HashMap<String, RuleStatus>,
+    trends: HashMap<String, VecDeque<(DateTime<Utc>, f64)>>,
+    running: HashMap<String, bool>,
+    last_update: Option<DateTime<Utc>>,
+    last_persist: Option<DateTime<Utc>>,
+    next_id: u64,
+}
+
+impl MaintenanceManager {
+    /// Create a manager, loading persisted state if present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the storage file
+    /// exists but cannot be read.
+    pub fn new(config: MaintenanceConfig) -> Result<Self> {
+        config.validate()?;
+
+        let mut stored = StoredState::default();
+        if let Some(path) = &config.storage_path {
+            match std::fs::read_to_string(path) {
+                Ok(json) => stored = serde_json::from_str(&json)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let next_id = stored.work_items.iter().map(|w| w.id + 1).max().unwrap_or(1);
+
+        info!(
+            "Maintenance rules enabled: {} rules on {} assets ({} stored work items)",
+            config.rules.len(),
+            config.assets.len(),
+            stored.work_items.len()
+        );
+        Ok(Self {
+            config,
+            counters: stored.counters,
+            baselines: stored.baselines,
+            work_items: stored.work_items.into(),
+            statuses: HashMap::new(),
+            trends: HashMap::new(),
+            running: HashMap::new(),
+            last_update: None,
+            last_persist: None,
+            next_id,
+        })
+    }
+
+    /// Configured update interval
+    #[must_use]
+    pub fn update_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.update_interval_ms)
+    }
+
+    /// Signals sampled for trend conditions, with the longest window using each
+    fn trend_windows(&self) -> HashMap<&str, Duration> {
+        let mut windows: HashMap<&str, Duration> = HashMap::new();
+        for condition in self.config.rules.iter().flat_map(|r| &r.conditions) {
+            if let Condition::Trend { signal, window_minutes, .. } = condition {
+                let window = Duration::minutes(i64::try_from(*window_minutes).unwrap_or(i64::MAX / 60_000));
+                let entry = windows.entry(signal.as_str()).or_insert(window);
+                *entry = (*entry).max(window);
+            }
+        }
+        windows
+    }
+
+    /// Record a trend sample, discarding samples older than the window
+    fn push_sample(&mut self, signal: &str, window: Duration, at: DateTime<Utc>, value: f64) {
+        let samples = self.trends.entry(signal.to_string()).or_default();
+        if samples.back().is_some_and(|(last, _)| *last >= at) {
+            return;
+        }
+        samples.push_back((at, value));
+        while samples.front().is_some_and(|(first, _)| *first < at - window) {
+            samples.pop_front();
+        }
+    }
+
+    /// Seed trend windows from recorded history
+    ///
+    /// Call before the first [`process`](Self::process) so trend conditions
+    /// can hold immediately after a restart.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the history query fails.
+    #[cfg(feature = "history")]
+    pub async fn load_trends(&mut self, history: &crate::history::HistoryManager, now: DateTime<Utc>) -> Result<()> {
+        let windows: Vec<(String, Duration)> = self
+            .trend_windows()
+            .into_iter()
+            .map(|(signal, window)| (signal.to_string(), window))
+            .collect();
+        for (signal, window) in windows {
+            let mut entries = history
+                .query(crate::history::HistoryQuery {
+                    signal_name: Some(signal.clone()),
+                    start_time: Some(now - window),
+                    end_time: Some(now),
+                    limit: None,
+                })
+                .await?;
+            entries.sort_by_key(|e| e.timestamp);
+            let count = entries.len();
+            for entry in entries {
+                if let Some(value) = entry.value.as_float() {
+                    self.push_sample(&signal, window, entry.timestamp, value);
+                }
+            }
+            debug!("Loaded {} history samples for maintenance trend '{}'", count, signal);
+        }
+        Ok(())
+    }
+
+    /// Update counters and trends, evaluate rules and publish their state
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured signal is missing or has the wrong
+    /// type, or if state cannot be persisted.
+    pub fn process(&mut self, bus: &SignalBus, now: DateTime<Utc>) -> Result<()> {
+        let elapsed_hours = self.last_update.map_or(0.0, |last| hours_between(last, now));
+        self.last_update = Some(now);
+
+        for asset in &self.config.assets {
+            let counters = self.counters.entry(asset.name.clone()).or_default();
+            if let Some(run_signal) = &asset.run_signal {
+                let running = bus.get_bool(run_signal)?;
+                let was_running = self.running.insert(asset.name.clone(), running);
+                if was_running == Some(true) {
+                    counters.runtime_hours += elapsed_hours;
+                } else if was_running == Some(false) && running {
+                    counters.starts += 1;
+                }
+            }
+            if let Some(signal) = &asset.runtime_signal {
+                counters.runtime_hours = bus.get_float(signal)?;
+            }
+            if let Some(signal) = &asset.starts_signal {
+                counters.starts = u64::try_from(bus.get_integer(signal)?).unwrap_or_default();
+            }
+            bus.write_batch([
+                (format!("maintenance.{}.runtime_hours", asset.name), Value::Float(counters.runtime_hours)),
+                (
+                    format!("maintenance.{}.starts", asset.name),
+                    Value::Integer(i64::try_from(counters.starts).unwrap_or(i64::MAX)),
+                ),
+            ])?;
+        }
+
+        let windows: Vec<(String, Duration)> = self
+            .trend_windows()
+            .into_iter()
+            .map(|(signal, window)| (signal.to_string(), window))
+            .collect();
+        for (signal, window) in windows {
+            let value = bus.get_float(&signal)?;
+            self.push_sample(&signal, window, now, value);
+        }
+
+        let mut changed = false;
+        for rule in &self.config.rules {
+            let current = self.counters.get(&rule.asset).copied().unwrap_or_default();
+            let baseline = self.baselines.entry(rule.name.clone()).or_default();
+            // A counter below its baseline was reset at the source
+            if current.runtime_hours < baseline.counters.runtime_hours
+                || current.starts < baseline.counters.starts
+            {
+                debug!("Usage counters of '{}' were reset, rebasing rule '{}'", rule.asset, rule.name);
+            }
+            if current.runtime_hours < baseline.counters.runtime_hours {
+                baseline.counters.runtime_hours = 0.0;
+            }
+            if current.starts < baseline.counters.starts {
+                baseline.counters.starts = 0;
+            }
+            let usage = UsageCounters {
+                runtime_hours: current.runtime_hours - baseline.counters.runtime_hours,
+                starts: current.starts - baseline.counters.starts,
+            };
+
+            let results: Vec<Option<String>> = rule
+                .conditions
+                .iter()
+                .map(|c| evaluate(c, usage, &self.trends, now))
+                .collect();
+            let due = match rule.combine {
+                Combine::Any => results.iter().any(Option::is_some),
+                Combine::All => results.iter().all(Option::is_some),
+            };
+            let reasons: Vec<String> = results.into_iter().flatten().collect();
+
+            let mut work_item = self
+                .work_items
+                .iter()
+                .find(|w| w.rule == rule.name && !w.is_closed())
+                .map(|w| w.id);
+            if due && work_item.is_none() {
+                let item = WorkItem {
+                    id: self.next_id,
+                    rule: rule.name.clone(),
+                    asset: rule.asset.clone(),
+                    description: rule.description.clone(),
+                    priority: rule.priority,
+                    status: WorkItemStatus::Open,
+                    raised_at: now,
+                    reasons: reasons.clone(),
+                    usage,
+                    acknowledged_by: None,
+                    completed_at: None,
+                    completed_by: None,
+                    comment: None,
+                };
+                self.next_id += 1;
+                info!(
+                    "Maintenance due for '{}' on '{}' (work item {}): {}",
+                    rule.name,
+                    rule.asset,
+                    item.id,
+                    reasons.join(", ")
+                );
+                work_item = Some(item.id);
+                self.work_items.push_back(item);
+                changed = true;
+            }
+
+            bus.write_batch([
+                (format!("maintenance.{}.due", rule.name), Value::Bool(work_item.is_some())),
+                (format!("maintenance.{}.runtime_hours", rule.name), Value::Float(usage.runtime_hours)),
+                (
+                    format!("maintenance.{}.starts", rule.name),
+                    Value::Integer(i64::try_from(usage.starts).unwrap_or(i64::MAX)),
+                ),
+            ])?;
+            self.statuses.insert(
+                rule.name.clone(),
+                RuleStatus {
+                    rule: rule.name.clone(),
+                    asset: rule.asset.clone(),
+                    due,
+                    usage,
+                    reasons,
+                    last_serviced: baseline.serviced_at,
+                    work_item,
+                },
+            );
+        }
+
+        self.prune();
+        let persist_due = self
+            .last_persist
+            .is_none_or(|last| now - last >= Duration::seconds(PERSIST_INTERVAL_S));
+        if changed || persist_due {
+            self.persist()?;
+            self.last_persist = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Acknowledge an open work item
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown work item,
+    /// [`PlcError::Validation`] if it is already completed, or an I/O error
+    /// if state cannot be persisted.
+    pub fn acknowledge(&mut self, id: u64, update: WorkItemUpdate) -> Result<WorkItem> {
+        let item = self.open_item(id)?;
+        item.status = WorkItemStatus::Acknowledged;
+        item.acknowledged_by = Some(update.user);
+        if update.comment.is_some() {
+            item.comment = update.comment;
+        }
+        let item = item.clone();
+        info!(
+            "Maintenance work item {} acknowledged by {}",
+            item.id,
+            item.acknowledged_by.as_deref().unwrap_or_default()
+        );
+        self.persist()?;
+        Ok(item)
+    }
+
+    /// Complete a work item and reset its rule's usage baseline
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown work item,
+    /// [`PlcError::Validation`] if it is already completed, or an I/O error
+    /// if state cannot be persisted.
+    pub fn complete(&mut self, id: u64, update: WorkItemUpdate, now: DateTime<Utc>) -> Result<WorkItem> {
+        let item = self.open_item(id)?;
+        item.status = WorkItemStatus::Completed;
+        item.completed_at = Some(now);
+        item.completed_by = Some(update.user);
+        if update.comment.is_some() {
+            item.comment = update.comment;
+        }
+        let item = item.clone();
+
+        let counters = self.counters.get(&item.asset).copied().unwrap_or_default();
+        self.baselines.insert(
+            item.rule.clone(),
+            ServiceBaseline {
+                counters,
+                serviced_at: Some(now),
+            },
+        );
+        if let Some(status) = self.statuses.get_mut(&item.rule) {
+            status.work_item = None;
+            status.last_serviced = Some(now);
+        }
+        info!(
+            "Maintenance work item {} for '{}' completed by {}",
+            item.id,
+            item.rule,
+            item.completed_by.as_deref().unwrap_or_default()
+        );
+        self.prune();
+        self.persist()?;
+        Ok(item)
+    }
+
+    fn open_item(&mut self, id: u64) -> Result<&mut WorkItem> {
+        let item = self
+            .work_items
+            .iter_mut()
+            .find(|w| w.id == id)
+            .ok_or_else(|| PlcError::NotFound(format!("Maintenance work item {id}")))?;
+        if item.is_closed() {
+            return Err(PlcError::Validation(format!("Maintenance work item {id} is already completed")));
+        }
+        Ok(item)
+    }
+
+    /// Work items, optionally filtered, newest first
+    #[must_use]
+    pub fn work_items(&self, asset: Option<&str>, open_only: bool) -> Vec<WorkItem> {
+        let mut items: Vec<WorkItem> = self
+            .work_items
+            .iter()
+            .filter(|w| asset.is_none_or(|a| w.asset == a))
+            .filter(|w| !open_only || !w.is_closed())
+            .cloned()
+            .collect();
+        items.sort_by_key(|w| std::cmp::Reverse(w.id));
+        items
+    }
+
+    /// Latest evaluation of every rule, in configuration order
+    #[must_use]
+    pub fn rule_statuses(&self) -> Vec<RuleStatus> {
+        self.config
+            .rules
+            .iter()
+            .filter_map(|r| self.statuses.get(&r.name).cloned())
+            .collect()
+    }
+
+    /// One discrete alarm per rule, active while maintenance is due
+    #[cfg(feature = "alarms")]
+    #[must_use]
+    pub fn alarm_configs(&self) -> Vec<crate::alarms::AlarmConfig> {
+        use crate::alarms::{AlarmClassification, AlarmCondition, AlarmConfig, AlarmPriority};
+
+        self.config
+            .rules
+            .iter()
+            .map(|rule| AlarmConfig {
+                name: format!("maintenance.{}", rule.name),
+                description: if rule.description.is_empty() {
+                    format!("Maintenance due: {}", rule.name)
+                } else {
+                    rule.description.clone()
+                },
+                tag_name: rule.asset.clone(),
+                signal: format!("maintenance.{}.due", rule.name),
+                condition: AlarmCondition::Discrete { expected_state: true },
+                priority: match rule.priority {
+                    MaintenancePriority::Low => AlarmPriority::Low,
+                    MaintenancePriority::Medium => AlarmPriority::Medium,
+                    MaintenancePriority::High => AlarmPriority::High,
+                },
+                consequence: "Increased risk of equipment failure".to_string(),
+                corrective_action: "Carry out and complete the maintenance work item".to_string(),
+                max_response_time: None,
+                classification: AlarmClassification::Equipment,
+                enabled: true,
+                setpoint: 1.0,
+                units: String::new(),
+                area: String::new(),
+                equipment: rule.asset.clone(),
+                #[cfg(feature = "alarm-suppression")]
+                suppression_groups: Vec::new(),
+                #[cfg(feature = "alarm-delay")]
+                on_delay_ms: None,
+                #[cfg(feature = "alarm-delay")]
+                off_delay_ms: None,
+                #[cfg(feature = "alarm-hysteresis")]
+                hysteresis: None,
+                #[cfg(feature = "alarm-actions")]
+                actions: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Drop the oldest completed work items beyond `max_work_items`
+    fn prune(&mut self) {
+        let mut excess = self
+            .work_items
+            .iter()
+            .filter(|w| w.is_closed())
+            .count()
+            .saturating_sub(self.config.max_work_items);
+        self.work_items.retain(|w| {
+            if excess > 0 && w.is_closed() {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.config.storage_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let stored = StoredState {
+            counters: self.counters.clone(),
+            baselines: self.baselines.clone(),
+            work_items: self.work_items.iter().cloned().collect(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
+
+    /// Run the periodic evaluation loop until the task is cancelled
+    pub async fn run(manager: SharedMaintenanceManager, bus: SignalBus) {
+        let period = manager.read().await.update_interval();
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let mut manager = manager.write().await;
+            let result = measure(Subsystem::Analytics, || manager.process(&bus, Utc::now()));
+            if let Err(e) = result {
+                warn!("Maintenance update failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Check one condition, describing it if it holds
+fn evaluate(
+    condition: &Condition,
+    usage: UsageCounters,
+    trends: &HashMap<String, VecDeque<(DateTime<Utc>, f64)>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    match condition {
+        Condition::RuntimeHours { hours } => (usage.runtime_hours >= *hours)
+            .then(|| format!("runtime {:.1} h >= {hours} h", usage.runtime_hours)),
+        Condition::Starts { count } => {
+            (usage.starts >= *count).then(|| format!("starts {} >= {count}", usage.starts))
+        }
+        Condition::Trend { signal, window_minutes, min_samples, mean_above, slope_above } => {
+            let since = now - Duration::minutes(i64::try_from(*window_minutes).unwrap_or(i64::MAX / 60_000));
+            let (samples, stats) = trend_stats(trends.get(signal)?.iter().filter(|(at, _)| *at >= since))?;
+            if samples < *min_samples {
+                return None;
+            }
+            if let Some(limit) = mean_above.filter(|limit| stats.mean > *limit) {
+                return Some(format!("{signal} mean {:.3} > {limit}", stats.mean));
+            }
+            slope_above
+                .filter(|limit| stats.slope_per_hour > *limit)
+                .map(|limit| format!("{signal} rising {:.3}/h > {limit}/h", stats.slope_per_hour))
+        }
+    }
+}
+
+/// Shared maintenance manager handle used by the engine task and the web API
+pub type SharedMaintenanceManager = std::sync::Arc<tokio::sync::RwLock<MaintenanceManager>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config() -> MaintenanceConfig {
+        serde_yaml::from_str(
+            r#"
+assets:
+  - name: pump
+    run_signal: pump.running
+rules:
+  - name: pump_service
+    asset: pump
+    description: Service pump
+    conditions:
+      - { type: runtime_hours, hours: 2 }
+      - { type: starts, count: 3 }
+  - name: pump_bearing
+    asset: pump
+    combine: all
+    conditions:
+      - { type: runtime_hours, hours: 1 }
+      - { type: trend, signal: pump.temp, window_minutes: 60, min_samples: 5, slope_above: 1.0 }
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(MaintenanceManager::new(config()).is_ok());
+
+        let mut bad = config();
+        bad.rules[0].asset = "missing".into();
+        assert!(bad.validate().is_err());
+
+        let mut bad = config();
+        bad.assets[0].run_signal = None;
+        assert!(bad.validate().is_err());
+
+        let mut bad = config();
+        bad.rules[1].name = "pump".into();
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_runtime_starts_and_trend() {
+        let bus = SignalBus::new();
+        let mut mm = MaintenanceManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        bus.set("pump.temp", Value::Float(40.0)).unwrap();
+
+        // Three starts raise the service item
+        for i in 0..3 {
+            let at = t0 + Duration::minutes(i * 2);
+            bus.set("pump.running", Value::Bool(false)).unwrap();
+            mm.process(&bus, at).unwrap();
+            bus.set("pump.running", Value::Bool(true)).unwrap();
+            mm.process(&bus, at + Duration::minutes(1)).unwrap();
+        }
+        let items = mm.work_items(None, true);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].rule, "pump_service");
+        assert!(bus.get_bool("maintenance.pump_service.due").unwrap());
+        assert_eq!(bus.get_integer("maintenance.pump.starts").unwrap(), 3);
+
+        // Completing resets the baseline
+        let update = WorkItemUpdate { user: "tech".into(), comment: None };
+        mm.complete(items[0].id, update.clone(), t0 + Duration::minutes(6)).unwrap();
+        assert!(mm.complete(items[0].id, update, t0 + Duration::minutes(6)).is_err());
+        mm.process(&bus, t0 + Duration::minutes(7)).unwrap();
+        assert!(!bus.get_bool("maintenance.pump_service.due").unwrap());
+        assert_eq!(bus.get_integer("maintenance.pump_service.starts").unwrap(), 0);
+
+        // Runtime alone does not satisfy the bearing rule until the temperature rises
+        let mut at = t0 + Duration::minutes(7);
+        for step in 0..70 {
+            at += Duration::minutes(1);
+            bus.set("pump.temp", Value::Float(40.0 + f64::from(step % 2) * 0.1)).unwrap();
+            mm.process(&bus, at).unwrap();
+        }
+        let status = mm.rule_statuses();
+        assert!(!status[1].due);
+        assert!(status[1].usage.runtime_hours > 1.0);
+
+        for _ in 0..30 {
+            at += Duration::minutes(1);
+            let temp = bus.get_float("pump.temp").unwrap() + 0.5;
+            bus.set("pump.temp", Value::Float(temp)).unwrap();
+            mm.process(&bus, at).unwrap();
+        }
+        let open = mm.work_items(Some("pump"), true);
+        assert!(open.iter().any(|w| w.rule == "pump_bearing"));
+        assert!(bus.get_bool("maintenance.pump_bearing.due").unwrap());
+    }
+}
@@ -0,0 +1,443 @@
+// src/maintenance_mode.rs
+//! Maintenance mode for devices and plant areas
+//!
+//! While a device or part of the plant is being worked on, its alarms are
+//! nuisance and its values cannot be trusted. Maintenance mode lets an
+//! operator take a configured area out of service until the work is done:
+//!
+//! - alarms on the area's signals are not evaluated
+//! - the area's signals report [`MAINTENANCE_QUALITY`] instead of `good`
+//! - writes to the area's protocol connections that fail are not handed to
+//!   the dead-letter queue, so they are not replayed once the device returns
+//! - entering and leaving is published as an engine event with the user and
+//!   reason, and recorded in the audit log when one is configured
+//!
+//! An area lists signal patterns, where a trailing `*` matches any suffix,
+//! and the names of protocol connections. To put a single device in
+//! maintenance, configure an area with its connection and the signals it
+//! feeds:
+//!
+//! ```yaml
+//! maintenance_mode:
+//!   storage_path: data/maintenance_mode.json
+//!   areas:
+//!     - name: line1
+//!       description: Packaging line 1
+//!       signals: ["line1.*"]
+//!     - name: plc2
+//!       protocols: [plc2]
+//!       signals: ["plc2.*", "boiler.feed_pump.*"]
+//! ```
+//!
+//! Each area publishes a boolean signal `maintenance_mode.<area>` (prefix
+//! configurable) that logic can use to interlock outputs. Areas in
+//! maintenance are persisted to `storage_path` and stay in maintenance
+//! across restarts until someone takes them out.
+
+use crate::events::{EventKind, EventLog};
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{info, warn};
+
+/// Quality reported for signals of an area in maintenance
+pub const MAINTENANCE_QUALITY: &str = "maintenance";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Maintenance mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceModeConfig {
+    /// Areas that can be put in maintenance
+    pub areas: Vec<MaintenanceArea>,
+
+    /// JSON file where areas in maintenance are persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<PathBuf>,
+
+    /// Prefix of the published per-area signals
+    #[serde(default = "default_signal_prefix")]
+    pub signal_prefix: String,
+}
+
+/// A device or plant area that is taken out of service as a whole
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceArea {
+    /// Area name, unique among areas
+    pub name: String,
+
+    /// Shown to operators next to the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Signal names or patterns ending in `*`
+    #[serde(default)]
+    pub signals: Vec<String>,
+
+    /// Protocol connections, by driver or failover group name
+    #[serde(default)]
+    pub protocols: Vec<String>,
+}
+
+fn default_signal_prefix() -> String {
+    "maintenance_mode".to_string()
+}
+
+impl MaintenanceModeConfig {
+    /// Validate area names and contents
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty or duplicate area name, or
+    /// an area without signals and protocols.
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for area in &self.areas {
+            if area.name.is_empty() {
+                return Err(PlcError::Config("Maintenance area name cannot be empty".to_string()));
+            }
+            if !names.insert(area.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate maintenance area '{}'", area.name)));
+            }
+            if area.signals.is_empty() && area.protocols.is_empty() {
+                return Err(PlcError::Config(format!(
+                    "Maintenance area '{}' has no signals or protocols",
+                    area.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MaintenanceArea {
+    fn covers_signal(&self, signal: &str) -> bool {
+        self.signals.iter().any(|pattern| {
+            pattern
+                .strip_suffix('*')
+                .map_or(pattern == signal, |prefix| signal.starts_with(prefix))
+        })
+    }
+}
+
+// ============================================================================
+// STATE
+// ============================================================================
+
+/// Who put an area in maintenance, when and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceEntry {
+    pub area: String,
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub since: DateTime<Utc>,
+}
+
+/// An area and, while it is in maintenance, its entry
+#[derive(Debug, Clone, Serialize)]
+pub struct AreaStatus {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub signals: Vec<String>,
+    pub protocols: Vec<String>,
+    pub maintenance: Option<MaintenanceEntry>,
+}
+
+/// Areas in maintenance shared by the web API, alarms and protocols
+#[derive(Clone)]
+pub struct MaintenanceMode {
+    config: Arc<MaintenanceModeConfig>,
+    active: Arc<Mutex<BTreeMap<String, MaintenanceEntry>>>,
+    bus: SignalBus,
+    events: Option<EventLog>,
+}
+
+impl MaintenanceMode {
+    /// Load persisted entries and publish the per-area signals
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the storage file
+    /// exists but cannot be read.
+    pub fn new(config: MaintenanceModeConfig, bus: SignalBus) -> Result<Self> {
+        config.validate()?;
+
+        let mut active: BTreeMap<String, MaintenanceEntry> = BTreeMap::new();
+        if let Some(path) = &config.storage_path {
+            match std::fs::read_to_string(path) {
+                Ok(json) => active = serde_json::from_str(&json)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        active.retain(|area, _| {
+            let known = config.areas.iter().any(|a| &a.name == area);
+            if !known {
+                warn!("Dropping maintenance entry for unconfigured area '{}'", area);
+            }
+            known
+        });
+
+        for area in &config.areas {
+            let signal = format!("{}.{}", config.signal_prefix, area.name);
+            bus.set(&signal, Value::Bool(active.contains_key(&area.name)))?;
+        }
+        if !active.is_empty() {
+            info!(
+                "Areas still in maintenance: {}",
+                active.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        Ok(Self {
+            config: Arc::new(config),
+            active: Arc::new(Mutex::new(active)),
+            bus,
+            events: None,
+        })
+    }
+
+    /// Publish entering and leaving maintenance to an event stream
+    #[must_use]
+    pub fn with_events(mut self, events: EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn area(&self, name: &str) -> Result<&MaintenanceArea> {
+        self.config
+            .areas
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| PlcError::NotFound(format!("Maintenance area '{name}' not found")))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, MaintenanceEntry>> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Put `area` in maintenance on behalf of `user`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown area,
+    /// [`PlcError::Validation`] if it is already in maintenance, or an error
+    /// if the change cannot be persisted.
+    pub fn enter(&self, area: &str, user: &str, reason: Option<&str>) -> Result<MaintenanceEntry> {
+        self.area(area)?;
+        let entry = MaintenanceEntry {
+            area: area.to_string(),
+            user: user.to_string(),
+            reason: reason.map(str::to_string),
+            since: Utc::now(),
+        };
+        {
+            let mut active = self.lock();
+            if let Some(current) = active.get(area) {
+                return Err(PlcError::Validation(format!(
+                    "Area '{area}' is already in maintenance by {} since {}",
+                    current.user, current.since
+                )));
+            }
+            active.insert(area.to_string(), entry.clone());
+            if let Err(e) = self.persist(&active) {
+                active.remove(area);
+                return Err(e);
+            }
+        }
+
+        info!("Area '{}' put in maintenance by {}", area, user);
+        self.changed(area, true, user, reason);
+        Ok(entry)
+    }
+
+    /// Take `area` out of maintenance on behalf of `user`
+    ///
+    /// Returns the entry that ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown area,
+    /// [`PlcError::Validation`] if it is not in maintenance, or an error if
+    /// the change cannot be persisted.
+    pub fn exit(&self, area: &str, user: &str) -> Result<MaintenanceEntry> {
+        self.area(area)?;
+        let entry = {
+            let mut active = self.lock();
+            let entry = active
+                .remove(area)
+                .ok_or_else(|| PlcError::Validation(format!("Area '{area}' is not in maintenance")))?;
+            if let Err(e) = self.persist(&active) {
+                active.insert(area.to_string(), entry);
+                return Err(e);
+            }
+            entry
+        };
+
+        info!("Area '{}' taken out of maintenance by {} (entered by {})", area, user, entry.user);
+        self.changed(area, false, user, None);
+        Ok(entry)
+    }
+
+    fn changed(&self, area: &str, active: bool, user: &str, reason: Option<&str>) {
+        let signal = format!("{}.{}", self.config.signal_prefix, area);
+        if let Err(e) = self.bus.set(&signal, Value::Bool(active)) {
+            warn!("Failed to publish {}: {}", signal, e);
+        }
+        if let Some(events) = &self.events {
+            events.publish(EventKind::MaintenanceModeChanged {
+                area: area.to_string(),
+                active,
+                user: user.to_string(),
+                reason: reason.map(str::to_string),
+            });
+        }
+    }
+
+    fn persist(&self, active: &BTreeMap<String, MaintenanceEntry>) -> Result<()> {
+        let Some(path) = &self.config.storage_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(active)?)?;
+        Ok(())
+    }
+
+    /// Every configured area with its entry, if it is in maintenance
+    #[must_use]
+    pub fn areas(&self) -> Vec<AreaStatus> {
+        let active = self.lock();
+        self.config
+            .areas
+            .iter()
+            .map(|area| AreaStatus {
+                name: area.name.clone(),
+                description: area.description.clone(),
+                signals: area.signals.clone(),
+                protocols: area.protocols.clone(),
+                maintenance: active.get(&area.name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Area in maintenance that `signal` belongs to, if any
+    #[must_use]
+    pub fn signal_area(&self, signal: &str) -> Option<String> {
+        let active = self.lock();
+        self.config
+            .areas
+            .iter()
+            .find(|area| active.contains_key(&area.name) && area.covers_signal(signal))
+            .map(|area| area.name.clone())
+    }
+
+    /// Whether `protocol` belongs to an area in maintenance
+    #[must_use]
+    pub fn covers_protocol(&self, protocol: &str) -> bool {
+        let active = self.lock();
+        self.config
+            .areas
+            .iter()
+            .any(|area| active.contains_key(&area.name) && area.protocols.iter().any(|p| p == protocol))
+    }
+
+    /// Quality to report for `signal`: [`MAINTENANCE_QUALITY`] or `good`
+    #[must_use]
+    pub fn quality(&self, signal: &str) -> &'static str {
+        if self.signal_area(signal).is_some() {
+            MAINTENANCE_QUALITY
+        } else {
+            "good"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(storage_path: Option<PathBuf>) -> MaintenanceModeConfig {
+        serde_yaml::from_str::<MaintenanceModeConfig>(
+            "areas:\n\
+             - { name: line1, signals: [\"line1.*\"] }\n\
+             - { name: plc2, protocols: [plc2], signals: [boiler.feed_pump] }\n",
+        )
+        .map(|c| MaintenanceModeConfig { storage_path, ..c })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enter_and_exit() {
+        let mut invalid = config(None);
+        invalid.areas[1].name = "line1".to_string();
+        assert!(invalid.validate().is_err());
+
+        let bus = SignalBus::new();
+        let events = EventLog::new(16);
+        let mode = MaintenanceMode::new(config(None), bus.clone()).unwrap().with_events(events.clone());
+        assert_eq!(bus.get("maintenance_mode.line1"), Some(Value::Bool(false)));
+        assert_eq!(mode.quality("line1.speed"), "good");
+
+        mode.enter("line1", "tech1", Some("replace conveyor belt")).unwrap();
+        assert_eq!(bus.get("maintenance_mode.line1"), Some(Value::Bool(true)));
+        assert_eq!(mode.signal_area("line1.speed").as_deref(), Some("line1"));
+        assert_eq!(mode.quality("line1.speed"), MAINTENANCE_QUALITY);
+        assert_eq!(mode.quality("line10.speed"), "good");
+        assert_eq!(mode.quality("boiler.feed_pump"), "good");
+        assert!(!mode.covers_protocol("plc2"));
+        assert!(mode.enter("line1", "tech2", None).is_err());
+        assert!(matches!(mode.enter("line9", "tech1", None), Err(PlcError::NotFound(_))));
+
+        mode.enter("plc2", "tech2", None).unwrap();
+        assert!(mode.covers_protocol("plc2"));
+        assert_eq!(mode.quality("boiler.feed_pump"), MAINTENANCE_QUALITY);
+
+        let ended = mode.exit("line1", "supervisor").unwrap();
+        assert_eq!(ended.user, "tech1");
+        assert_eq!(mode.quality("line1.speed"), "good");
+        assert!(mode.exit("line1", "supervisor").is_err());
+
+        let changes: Vec<_> = events
+            .since(0)
+            .into_iter()
+            .filter_map(|e| match e.kind {
+                EventKind::MaintenanceModeChanged { area, active, user, .. } => Some((area, active, user)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("line1".to_string(), true, "tech1".to_string()),
+                ("plc2".to_string(), true, "tech2".to_string()),
+                ("line1".to_string(), false, "supervisor".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_maintenance_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("maintenance_mode.json");
+        let mode = MaintenanceMode::new(config(Some(path.clone())), SignalBus::new()).unwrap();
+        mode.enter("plc2", "tech1", Some("firmware update")).unwrap();
+
+        let bus = SignalBus::new();
+        let restarted = MaintenanceMode::new(config(Some(path)), bus.clone()).unwrap();
+        assert!(restarted.covers_protocol("plc2"));
+        assert_eq!(bus.get("maintenance_mode.plc2"), Some(Value::Bool(true)));
+        let status = restarted.areas();
+        let entry = status.iter().find(|a| a.name == "plc2").and_then(|a| a.maintenance.clone()).unwrap();
+        assert_eq!(entry.user, "tech1");
+        assert_eq!(entry.reason.as_deref(), Some("firmware update"));
+    }
+}
@@ -10,6 +10,7 @@ pub struct EngineMetrics {
     pub block_executions: Counter,
     pub signal_updates: Counter,
     pub active_signals: Gauge,
+    pub stale_signals: Gauge,
     pub errors: Counter,
 }
 
@@ -48,6 +49,14 @@ impl EngineMetrics {
         )?;
         registry.register(Box::new(active_signals.clone()))?;
 
+        let stale_signals = Gauge::with_opts(
+            Opts::new(
+                "petra_stale_signals",
+                "Number of signals that missed their expected update",
+            ),
+        )?;
+        registry.register(Box::new(stale_signals.clone()))?;
+
         let errors = Counter::with_opts(
             Opts::new("petra_errors_total", "Total number of errors"),
         )?;
@@ -58,6 +67,7 @@ impl EngineMetrics {
             block_executions,
             signal_updates,
             active_signals,
+            stale_signals,
             errors,
         })
     }
@@ -78,6 +88,10 @@ impl EngineMetrics {
         self.active_signals.set(count);
     }
 
+    pub fn set_stale_signals(&self, count: f64) {
+        self.stale_signals.set(count);
+    }
+
     pub fn increment_errors(&self) {
         self.errors.inc();
     }
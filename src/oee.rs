@@ -0,0 +1,537 @@
+// src/oee.rs
+//! Overall Equipment Effectiveness (OEE) and production counters
+//!
+//! Each configured line derives the three OEE factors from signals already on
+//! the bus:
+//!
+//! - **Availability** = run time / planned production time
+//! - **Performance** = total count / (run time × ideal rate)
+//! - **Quality** = good count / total count
+//!
+//! Planned production time is the time elapsed in the current shift minus
+//! time flagged by the optional `planned_stop_signal` (breaks, changeovers).
+//! Count signals are treated as monotonic PLC counters; a decrease is taken
+//! as a counter reset rather than negative production.
+//!
+//! Results are written back to the bus under `<output_prefix>.*` every update
+//! and, when a shift ends, frozen into a [`ShiftRecord`] that is appended to
+//! the JSON history file at `storage_path`.
+//!
+//! ```yaml
+//! oee:
+//!   storage_path: data/oee_history.json
+//!   shifts:
+//!     definitions:
+//!       - { name: day, start: "06:00", end: "18:00" }
+//!   lines:
+//!     - name: line1
+//!       run_signal: line1.running
+//!       count_signal: line1.parts_total
+//!       reject_signal: line1.parts_rejected
+//!       ideal_rate_per_min: 60.0
+//! ```
+
+use crate::scan_budget::{measure, Subsystem};
+use crate::shifts::{ShiftCalendar, ShiftInstance};
+use crate::{PlcError, Result, SignalBus, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// OEE subsystem configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OeeConfig {
+    /// Production lines to track
+    pub lines: Vec<OeeLineConfig>,
+
+    /// Shift calendar used for aggregation
+    #[serde(default)]
+    pub shifts: ShiftCalendar,
+
+    /// JSON file where completed shift records are persisted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_path: Option<PathBuf>,
+
+    /// How often OEE is recalculated
+    #[serde(default = "default_update_interval_ms")]
+    pub update_interval_ms: u64,
+
+    /// Maximum number of shift records kept in memory per line
+    #[serde(default = "default_max_records")]
+    pub max_records: usize,
+}
+
+/// Signals and targets for one production line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OeeLineConfig {
+    /// Line name, unique within the OEE configuration
+    pub name: String,
+
+    /// Boolean signal, true while the line is producing
+    pub run_signal: String,
+
+    /// Optional boolean signal, true during planned stops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub planned_stop_signal: Option<String>,
+
+    /// Cumulative count of all produced parts
+    pub count_signal: String,
+
+    /// Optional cumulative count of rejected parts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reject_signal: Option<String>,
+
+    /// Ideal production rate in parts per minute
+    pub ideal_rate_per_min: f64,
+
+    /// Prefix for result signals, defaults to `oee.<name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_prefix: Option<String>,
+}
+
+impl OeeLineConfig {
+    fn prefix(&self) -> String {
+        self.output_prefix
+            .clone()
+            .unwrap_or_else(|| format!("oee.{}", self.name))
+    }
+}
+
+impl OeeConfig {
+    /// Validate line definitions and the shift calendar
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config("OEE update interval cannot be 0".to_string()));
+        }
+        self.shifts.validate()?;
+
+        let mut names = std::collections::HashSet::new();
+        for line in &self.lines {
+            if !names.insert(line.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate OEE line '{}'", line.name)));
+            }
+            if !(line.ideal_rate_per_min > 0.0 && line.ideal_rate_per_min.is_finite()) {
+                return Err(PlcError::Config(format!(
+                    "OEE line '{}' ideal_rate_per_min must be positive",
+                    line.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_update_interval_ms() -> u64 {
+    1000
+}
+
+const fn default_max_records() -> usize {
+    1000
+}
+
+// ============================================================================
+// RESULTS
+// ============================================================================
+
+/// Production totals and OEE factors for a period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OeeMetrics {
+    /// Planned production time in seconds
+    pub planned_time_s: f64,
+    /// Time the line was running in seconds
+    pub run_time_s: f64,
+    /// Total parts produced
+    pub total_count: u64,
+    /// Rejected parts
+    pub reject_count: u64,
+    /// Availability factor (0..=1)
+    pub availability: f64,
+    /// Performance factor (0..=1, may exceed 1 if the ideal rate is too low)
+    pub performance: f64,
+    /// Quality factor (0..=1)
+    pub quality: f64,
+    /// Overall equipment effectiveness (0..=1)
+    pub oee: f64,
+}
+
+impl OeeMetrics {
+    fn recalculate(&mut self, ideal_rate_per_min: f64) {
+        self.availability = ratio(self.run_time_s, self.planned_time_s);
+        #[allow(clippy::cast_precision_loss)]
+        let total = self.total_count as f64;
+        self.performance = ratio(total, self.run_time_s / 60.0 * ideal_rate_per_min);
+        #[allow(clippy::cast_precision_loss)]
+        let good = self.total_count.saturating_sub(self.reject_count) as f64;
+        self.quality = if self.total_count == 0 { 1.0 } else { good / total };
+        self.oee = self.availability * self.performance * self.quality;
+    }
+
+    /// Good parts produced
+    #[must_use]
+    pub fn good_count(&self) -> u64 {
+        self.total_count.saturating_sub(self.reject_count)
+    }
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+/// Frozen OEE results of a completed shift
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShiftRecord {
+    /// Line name
+    pub line: String,
+    /// Shift the record covers
+    pub shift: ShiftInstance,
+    /// End of the recorded period
+    pub ended_at: DateTime<Utc>,
+    /// Totals and factors for the shift
+    pub metrics: OeeMetrics,
+}
+
+/// Current OEE state of a line
+#[derive(Debug, Clone, Serialize)]
+pub struct LineSnapshot {
+    /// Line name
+    pub line: String,
+    /// Shift currently being aggregated
+    pub shift: ShiftInstance,
+    /// Totals and factors so far in the shift
+    pub metrics: OeeMetrics,
+}
+
+// ============================================================================
+// CALCULATION
+// ============================================================================
+
+struct LineState {
+    config: OeeLineConfig,
+    shift: Option<ShiftInstance>,
+    metrics: OeeMetrics,
+    last_sample: Option<DateTime<Utc>>,
+    last_count: Option<i64>,
+    last_rejects: Option<i64>,
+    history: VecDeque<ShiftRecord>,
+}
+
+impl LineState {
+    fn new(config: OeeLineConfig) -> Self {
+        Self {
+            config,
+            shift: None,
+            metrics: OeeMetrics::default(),
+            last_sample: None,
+            last_count: None,
+            last_rejects: None,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+/// Add the time between two samples to the planned and run totals
+fn accumulate(
+    metrics: &mut OeeMetrics,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    running: bool,
+    planned_stop: bool,
+) {
+    if planned_stop {
+        return;
+    }
+    let elapsed = (to - from).to_std().map_or(0.0, |d| d.as_secs_f64());
+    metrics.planned_time_s += elapsed;
+    if running {
+        metrics.run_time_s += elapsed;
+    }
+}
+
+/// Counter increase since the last sample, treating a decrease as a reset
+fn counter_delta(last: &mut Option<i64>, current: i64) -> u64 {
+    let delta = match *last {
+        Some(prev) if current >= prev => current - prev,
+        Some(_) => current.max(0),
+        None => 0,
+    };
+    *last = Some(current);
+    delta.unsigned_abs()
+}
+
+/// Tracks OEE for all configured lines
+pub struct OeeManager {
+    config: OeeConfig,
+    lines: Vec<LineState>,
+}
+
+impl OeeManager {
+    /// Create a manager, loading persisted shift history if present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid.
+    pub fn new(config: OeeConfig) -> Result<Self> {
+        config.validate()?;
+        let mut lines: Vec<LineState> = config.lines.iter().cloned().map(LineState::new).collect();
+
+        if let Some(path) = &config.storage_path {
+            match std::fs::read_to_string(path) {
+                Ok(json) => {
+                    let records: Vec<ShiftRecord> = serde_json::from_str(&json)?;
+                    for record in records {
+                        if let Some(line) = lines.iter_mut().find(|l| l.config.name == record.line) {
+                            line.history.push_back(record);
+                        }
+                    }
+                    for line in &mut lines {
+                        while line.history.len() > config.max_records {
+                            line.history.pop_front();
+                        }
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        info!("OEE tracking enabled for {} lines", lines.len());
+        Ok(Self { config, lines })
+    }
+
+    /// Configured update interval
+    #[must_use]
+    pub fn update_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.update_interval_ms)
+    }
+
+    /// Sample the bus, update all lines and publish results
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured signal is missing or has the wrong
+    /// type, or if a completed shift cannot be persisted.
+    pub fn process(&mut self, bus: &SignalBus, now: DateTime<Utc>) -> Result<()> {
+        let shift = self.config.shifts.instance_at(now)?;
+        let mut completed = Vec::new();
+
+        for line in &mut self.lines {
+            let planned_stop = match &line.config.planned_stop_signal {
+                Some(signal) => bus.get_bool(signal)?,
+                None => false,
+            };
+            let running = bus.get_bool(&line.config.run_signal)?;
+
+            // Time since the last sample is split at the shift boundary
+            let mut period_start = line.last_sample.unwrap_or(now);
+            if line.shift.as_ref() != Some(&shift) {
+                if let Some(previous) = line.shift.take() {
+                    let boundary = shift.started_at.clamp(period_start, now);
+                    accumulate(&mut line.metrics, period_start, boundary, running, planned_stop);
+                    line.metrics.recalculate(line.config.ideal_rate_per_min);
+                    period_start = boundary;
+
+                    let record = ShiftRecord {
+                        line: line.config.name.clone(),
+                        shift: previous,
+                        ended_at: boundary,
+                        metrics: std::mem::take(&mut line.metrics),
+                    };
+                    debug!("OEE line '{}' closed shift '{}'", record.line, record.shift.name);
+                    line.history.push_back(record.clone());
+                    if line.history.len() > self.config.max_records {
+                        line.history.pop_front();
+                    }
+                    completed.push(record);
+                }
+                line.shift = Some(shift.clone());
+            }
+            accumulate(&mut line.metrics, period_start, now, running, planned_stop);
+            line.last_sample = Some(now);
+
+            let count = bus.get_integer(&line.config.count_signal)?;
+            line.metrics.total_count += counter_delta(&mut line.last_count, count);
+            if let Some(signal) = &line.config.reject_signal {
+                let rejects = bus.get_integer(signal)?;
+                line.metrics.reject_count += counter_delta(&mut line.last_rejects, rejects);
+            }
+
+            line.metrics.recalculate(line.config.ideal_rate_per_min);
+            Self::publish(bus, &line.config, &line.metrics)?;
+        }
+
+        if !completed.is_empty() {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    fn publish(bus: &SignalBus, line: &OeeLineConfig, metrics: &OeeMetrics) -> Result<()> {
+        let prefix = line.prefix();
+        bus.write_batch([
+            (format!("{prefix}.availability"), Value::Float(metrics.availability)),
+            (format!("{prefix}.performance"), Value::Float(metrics.performance)),
+            (format!("{prefix}.quality"), Value::Float(metrics.quality)),
+            (format!("{prefix}.oee"), Value::Float(metrics.oee)),
+            (format!("{prefix}.run_time_s"), Value::Float(metrics.run_time_s)),
+            (
+                format!("{prefix}.total_count"),
+                Value::Integer(i64::try_from(metrics.total_count).unwrap_or(i64::MAX)),
+            ),
+            (
+                format!("{prefix}.good_count"),
+                Value::Integer(i64::try_from(metrics.good_count()).unwrap_or(i64::MAX)),
+            ),
+        ])
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.config.storage_path else {
+            return Ok(());
+        };
+        let records: Vec<&ShiftRecord> = self.lines.iter().flat_map(|l| l.history.iter()).collect();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&records)?)?;
+        Ok(())
+    }
+
+    /// Current shift results for all lines
+    #[must_use]
+    pub fn snapshots(&self) -> Vec<LineSnapshot> {
+        self.lines.iter().filter_map(Self::snapshot_of).collect()
+    }
+
+    /// Current shift results for one line
+    #[must_use]
+    pub fn snapshot(&self, line: &str) -> Option<LineSnapshot> {
+        self.lines
+            .iter()
+            .find(|l| l.config.name == line)
+            .and_then(Self::snapshot_of)
+    }
+
+    fn snapshot_of(line: &LineState) -> Option<LineSnapshot> {
+        line.shift.as_ref().map(|shift| LineSnapshot {
+            line: line.config.name.clone(),
+            shift: shift.clone(),
+            metrics: line.metrics.clone(),
+        })
+    }
+
+    /// Completed shift records for a line, oldest first
+    #[must_use]
+    pub fn shift_history(&self, line: &str) -> Option<Vec<ShiftRecord>> {
+        self.lines
+            .iter()
+            .find(|l| l.config.name == line)
+            .map(|l| l.history.iter().cloned().collect())
+    }
+
+    /// Run the periodic update loop until the task is cancelled
+    pub async fn run(manager: SharedOeeManager, bus: SignalBus) {
+        let period = manager.read().await.update_interval();
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let mut manager = manager.write().await;
+            let result = measure(Subsystem::Analytics, || manager.process(&bus, Utc::now()));
+            if let Err(e) = result {
+                warn!("OEE update failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Shared OEE manager handle used by the engine task and the web API
+pub type SharedOeeManager = std::sync::Arc<tokio::sync::RwLock<OeeManager>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config() -> OeeConfig {
+        serde_yaml::from_str(
+            r#"
+shifts:
+  definitions:
+    - { name: day, start: "06:00", end: "18:00" }
+    - { name: night, start: "18:00", end: "06:00" }
+lines:
+  - name: line1
+    run_signal: run
+    count_signal: count
+    reject_signal: rejects
+    ideal_rate_per_min: 60.0
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_oee_factors() {
+        let bus = SignalBus::new();
+        bus.set("run", Value::Bool(true)).unwrap();
+        bus.set("count", Value::Integer(100)).unwrap();
+        bus.set("rejects", Value::Integer(0)).unwrap();
+
+        let mut oee = OeeManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        oee.process(&bus, t0).unwrap();
+
+        // 60 s running, 45 parts (ideal 60), 5 rejects
+        bus.set("count", Value::Integer(145)).unwrap();
+        bus.set("rejects", Value::Integer(5)).unwrap();
+        oee.process(&bus, t0 + chrono::Duration::seconds(60)).unwrap();
+
+        // 60 s stopped
+        bus.set("run", Value::Bool(false)).unwrap();
+        oee.process(&bus, t0 + chrono::Duration::seconds(120)).unwrap();
+
+        let m = oee.snapshot("line1").unwrap().metrics;
+        assert!((m.availability - 0.5).abs() < 1e-9);
+        assert!((m.performance - 0.75).abs() < 1e-9);
+        assert!((m.quality - 40.0 / 45.0).abs() < 1e-9);
+        assert_eq!(bus.get_integer("oee.line1.good_count").unwrap(), 40);
+    }
+
+    #[test]
+    fn test_counter_reset_and_shift_rollover() {
+        let bus = SignalBus::new();
+        bus.set("run", Value::Bool(true)).unwrap();
+        bus.set("count", Value::Integer(500)).unwrap();
+        bus.set("rejects", Value::Integer(0)).unwrap();
+
+        let mut oee = OeeManager::new(config()).unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 17, 59, 0).unwrap();
+        oee.process(&bus, t0).unwrap();
+
+        // PLC counter reset to 10 at shift change
+        bus.set("count", Value::Integer(10)).unwrap();
+        oee.process(&bus, t0 + chrono::Duration::seconds(120)).unwrap();
+
+        let history = oee.shift_history("line1").unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].shift.name, "day");
+
+        let current = oee.snapshot("line1").unwrap();
+        assert_eq!(current.shift.name, "night");
+        assert_eq!(current.metrics.total_count, 10);
+    }
+}
@@ -0,0 +1,452 @@
+// src/plugins/mod.rs
+//! Third-party block types loaded at startup
+//!
+//! Plugins add block types without recompiling PETRA. Each module listed in
+//! the `plugins` section is loaded before the blocks are created, and the
+//! block types it implements are registered in the block factory, where
+//! they are used like built-in types:
+//!
+//! ```yaml
+//! plugins:
+//!   modules:
+//!     - { name: vendor, path: /opt/petra/plugins/libvendor_blocks.so }
+//!     - { name: filters, path: /opt/petra/plugins/filters.wasm, fuel: 1000000, max_memory_kb: 1024 }
+//! blocks:
+//!   - name: pressure_filter
+//!     type: KALMAN_FILTER
+//!     inputs: { in: line.pressure_raw }
+//!     outputs: { out: line.pressure }
+//!     params: { q: 0.01, r: 0.5 }
+//! ```
+//!
+//! Shared libraries (`plugins-native` feature) run with the full rights of
+//! the PETRA process and must be trusted. WASM modules (`plugins-wasm`
+//! feature) run sandboxed: they cannot import anything from the host, each
+//! block instance has its own memory up to `max_memory_kb`, and an
+//! execution that uses more than `fuel` instructions fails instead of
+//! stalling the scan.
+//!
+//! # ABI, version 1
+//!
+//! Both kinds of plugin exchange values as 16-byte [`PluginValue`]s: a
+//! `u32` kind (0 none, 1 bool, 2 integer, 3 float), 4 reserved bytes and
+//! a `u64` payload holding 0 or 1, an `i64`, or the bits of an `f64`, all
+//! little-endian in WASM memory. A block instance is created from a JSON
+//! description `{"name", "type", "inputs", "outputs", "params"}`, where
+//! `inputs` and `outputs` are the port names of the configured block in
+//! sorted order. Every scan the inputs are passed in that order, with kind
+//! 0 for a missing signal, and outputs left at kind 0 are not written.
+//!
+//! A shared library exports `petra_plugin_entry`, returning a pointer to a
+//! [`native::NativePluginV1`] function table whose first field is the ABI
+//! version. A WASM module exports `memory` and:
+//!
+//! - `petra_abi_version() -> i32`
+//! - `petra_block_types() -> i64`: newline-separated type names
+//! - `petra_alloc(len: i32) -> i32`: buffer the host writes into
+//! - `petra_create(config: i32, len: i32) -> i32`: 0 on success
+//! - `petra_execute(inputs: i32, input_count: i32, outputs: i32, output_count: i32) -> i32`: 0 on success
+//! - optionally `petra_error() -> i64`: message of the last failure
+//!
+//! Strings returned as `i64` are a pointer in the upper and a length in the
+//! lower 32 bits. A plugin built for another ABI version is rejected when
+//! it is loaded.
+
+#[cfg(feature = "plugins-native")]
+pub mod native;
+
+#[cfg(feature = "plugins-wasm")]
+pub mod wasm;
+
+use crate::blocks::{register_plugin_block_type, Block};
+use crate::config::BlockConfig;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+/// Version of the plugin ABI this build implements
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Plugin modules loaded at startup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct PluginsConfig {
+    /// Modules in load order
+    #[serde(default)]
+    pub modules: Vec<PluginModuleConfig>,
+}
+
+/// One plugin module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct PluginModuleConfig {
+    /// Name used in logs and errors
+    pub name: String,
+
+    /// Shared library or `.wasm` file
+    pub path: PathBuf,
+
+    /// Kind of module, by default WASM for a `.wasm` file and a shared
+    /// library otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<PluginKind>,
+
+    /// WASM only: instructions one block execution may use
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+
+    /// WASM only: memory limit of one block instance (KiB)
+    #[serde(default = "default_max_memory_kb")]
+    pub max_memory_kb: u32,
+}
+
+/// How a plugin module is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    /// Shared library loaded into the process
+    Native,
+    /// Sandboxed WebAssembly module
+    Wasm,
+}
+
+const fn default_fuel() -> u64 {
+    10_000_000
+}
+
+const fn default_max_memory_kb() -> u32 {
+    16 * 1024
+}
+
+impl PluginModuleConfig {
+    /// Configured kind, or the kind implied by the file extension
+    #[must_use]
+    pub fn kind(&self) -> PluginKind {
+        self.kind.unwrap_or_else(|| {
+            if self.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wasm")) {
+                PluginKind::Wasm
+            } else {
+                PluginKind::Native
+            }
+        })
+    }
+}
+
+impl PluginsConfig {
+    /// Validate the plugin modules
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty or duplicate name, an empty
+    /// path, or a zero fuel or memory limit.
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for module in &self.modules {
+            if module.name.is_empty() || !names.insert(module.name.as_str()) {
+                return Err(PlcError::Config(format!("Empty or duplicate plugin name: '{}'", module.name)));
+            }
+            if module.path.as_os_str().is_empty() {
+                return Err(PlcError::Config(format!("Plugin '{}' path must not be empty", module.name)));
+            }
+            if module.fuel == 0 || module.max_memory_kb == 0 {
+                return Err(PlcError::Config(format!(
+                    "Plugin '{}' fuel and max_memory_kb must be greater than 0",
+                    module.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// VALUES
+// ============================================================================
+
+/// Value passed between PETRA and a plugin
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PluginValue {
+    /// 0 none, 1 bool, 2 integer, 3 float
+    pub kind: u32,
+    /// Always 0
+    pub reserved: u32,
+    /// Payload of the kind
+    pub bits: u64,
+}
+
+impl PluginValue {
+    pub const NONE: u32 = 0;
+    pub const BOOL: u32 = 1;
+    pub const INTEGER: u32 = 2;
+    pub const FLOAT: u32 = 3;
+
+    /// Size of a value in WASM memory
+    pub const SIZE: usize = 16;
+
+    /// Encode a signal value, or none for a missing signal
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::TypeMismatch`] for values other than booleans,
+    /// integers and floats.
+    pub fn from_value(value: Option<&Value>) -> Result<Self> {
+        let (kind, bits) = match value {
+            None => (Self::NONE, 0),
+            Some(Value::Bool(b)) => (Self::BOOL, u64::from(*b)),
+            Some(Value::Integer(i)) => (Self::INTEGER, i.cast_unsigned()),
+            Some(Value::Float(f)) => (Self::FLOAT, f.to_bits()),
+            #[allow(unreachable_patterns)]
+            Some(other) => {
+                return Err(PlcError::TypeMismatch {
+                    expected: "bool, integer or float".to_string(),
+                    actual: other.type_name().to_string(),
+                })
+            }
+        };
+        Ok(Self { kind, reserved: 0, bits })
+    }
+
+    /// Decode the value, `None` for kind 0
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Block`] for an unknown kind.
+    pub fn to_value(self) -> Result<Option<Value>> {
+        Ok(Some(match self.kind {
+            Self::NONE => return Ok(None),
+            Self::BOOL => Value::Bool(self.bits != 0),
+            Self::INTEGER => Value::Integer(self.bits.cast_signed()),
+            Self::FLOAT => Value::Float(f64::from_bits(self.bits)),
+            kind => return Err(PlcError::Block(format!("Plugin returned a value of unknown kind {kind}"))),
+        }))
+    }
+
+    /// Little-endian encoding used in WASM memory
+    #[must_use]
+    pub fn to_le_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&self.kind.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.bits.to_le_bytes());
+        bytes
+    }
+
+    /// Value from its little-endian encoding
+    #[must_use]
+    pub fn from_le_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        let mut kind = [0; 4];
+        kind.copy_from_slice(&bytes[..4]);
+        let mut bits = [0; 8];
+        bits.copy_from_slice(&bytes[8..]);
+        Self { kind: u32::from_le_bytes(kind), reserved: 0, bits: u64::from_le_bytes(bits) }
+    }
+}
+
+// ============================================================================
+// BLOCKS
+// ============================================================================
+
+/// Block instance created by a plugin
+pub trait PluginInstance: Send + Sync {
+    /// Run one scan; `outputs` are reset to none beforehand
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Block`] if the plugin reports a failure or, for
+    /// WASM, traps or runs out of fuel.
+    fn execute(&mut self, inputs: &[PluginValue], outputs: &mut [PluginValue]) -> Result<()>;
+}
+
+/// Loaded plugin module
+pub trait PluginModule: Send + Sync {
+    /// Block types the module implements
+    fn block_types(&self) -> &[String];
+
+    /// Create a block instance from its JSON description
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the plugin rejects the block.
+    fn instantiate(&self, description: &str) -> Result<Box<dyn PluginInstance>>;
+}
+
+/// Block whose logic runs in a plugin
+pub struct PluginBlock {
+    name: String,
+    block_type: String,
+    /// Input signals, ordered by port name
+    inputs: Vec<String>,
+    /// Output signals, ordered by port name
+    outputs: Vec<String>,
+    input_values: Vec<PluginValue>,
+    output_values: Vec<PluginValue>,
+    instance: Box<dyn PluginInstance>,
+}
+
+impl Block for PluginBlock {
+    fn execute(&mut self, bus: &SignalBus) -> Result<()> {
+        for (value, signal) in self.input_values.iter_mut().zip(&self.inputs) {
+            *value = PluginValue::from_value(bus.get(signal).as_ref())?;
+        }
+        self.output_values.fill(PluginValue::default());
+        self.instance.execute(&self.input_values, &mut self.output_values)?;
+        for (value, signal) in self.output_values.iter().zip(&self.outputs) {
+            if let Some(value) = value.to_value()? {
+                bus.set(signal, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn block_type(&self) -> &str {
+        &self.block_type
+    }
+
+    fn category(&self) -> &'static str {
+        "plugin"
+    }
+
+    fn input_dependencies(&self) -> Vec<&str> {
+        self.inputs.iter().map(String::as_str).collect()
+    }
+
+    fn output_signals(&self) -> Vec<&str> {
+        self.outputs.iter().map(String::as_str).collect()
+    }
+}
+
+fn sorted_ports(ports: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut ports: Vec<_> = ports.iter().collect();
+    ports.sort_unstable();
+    ports
+}
+
+/// Create a block of a plugin type
+fn create_plugin_block(module: &dyn PluginModule, config: &BlockConfig) -> Result<Box<dyn Block>> {
+    let inputs = sorted_ports(&config.inputs);
+    let outputs = sorted_ports(&config.outputs);
+    let description = serde_json::json!({
+        "name": config.name,
+        "type": config.block_type,
+        "inputs": inputs.iter().map(|(port, _)| port).collect::<Vec<_>>(),
+        "outputs": outputs.iter().map(|(port, _)| port).collect::<Vec<_>>(),
+        "params": config.params,
+    });
+    let instance = module.instantiate(&description.to_string()).map_err(|e| {
+        PlcError::Config(format!("{} block '{}' rejected by its plugin: {}", config.block_type, config.name, e))
+    })?;
+    Ok(Box::new(PluginBlock {
+        name: config.name.clone(),
+        block_type: config.block_type.clone(),
+        input_values: vec![PluginValue::default(); inputs.len()],
+        output_values: vec![PluginValue::default(); outputs.len()],
+        inputs: inputs.into_iter().map(|(_, signal)| signal.clone()).collect(),
+        outputs: outputs.into_iter().map(|(_, signal)| signal.clone()).collect(),
+        instance,
+    }))
+}
+
+// ============================================================================
+// LOADING
+// ============================================================================
+
+fn load_module(config: &PluginModuleConfig) -> Result<Arc<dyn PluginModule>> {
+    match config.kind() {
+        #[cfg(feature = "plugins-native")]
+        PluginKind::Native => Ok(Arc::new(native::NativeModule::load(&config.path)?)),
+        #[cfg(feature = "plugins-wasm")]
+        PluginKind::Wasm => Ok(Arc::new(wasm::WasmModule::load(&config.path, config.fuel, config.max_memory_kb)?)),
+        #[allow(unreachable_patterns)]
+        kind => Err(PlcError::Config(format!(
+            "Plugin '{}' is a {kind:?} module, but PETRA was built without the plugins-{} feature",
+            config.name,
+            if kind == PluginKind::Wasm { "wasm" } else { "native" }
+        ))),
+    }
+}
+
+/// Load the plugin modules and register their block types
+///
+/// Loading the same modules again, as when a second engine is created from
+/// the configuration, replaces their registrations.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if a module cannot be loaded, was built for
+/// another ABI version, or implements a block type that is built in or
+/// registered by another plugin.
+pub fn load(config: &PluginsConfig) -> Result<()> {
+    config.validate()?;
+    for module_config in &config.modules {
+        let module = load_module(module_config).map_err(|e| {
+            PlcError::Config(format!(
+                "Failed to load plugin '{}' from {}: {}",
+                module_config.name,
+                module_config.path.display(),
+                e
+            ))
+        })?;
+        for block_type in module.block_types() {
+            let module = Arc::clone(&module);
+            register_plugin_block_type(
+                block_type,
+                &module_config.name,
+                Arc::new(move |config: &BlockConfig| create_plugin_block(module.as_ref(), config)),
+            )?;
+        }
+        info!(
+            "Loaded plugin '{}' from {}: {}",
+            module_config.name,
+            module_config.path.display(),
+            module.block_types().join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_values_round_trip() {
+        for value in [Value::Bool(true), Value::Integer(-42), Value::Float(2.5)] {
+            let encoded = PluginValue::from_value(Some(&value)).unwrap();
+            let decoded = PluginValue::from_le_bytes(encoded.to_le_bytes());
+            assert_eq!(decoded.to_value().unwrap(), Some(value));
+        }
+        assert_eq!(PluginValue::from_value(None).unwrap().to_value().unwrap(), None);
+        assert!(PluginValue { kind: 9, reserved: 0, bits: 0 }.to_value().is_err());
+        assert_eq!(std::mem::size_of::<PluginValue>(), PluginValue::SIZE);
+    }
+
+    #[test]
+    fn test_modules_validate_and_infer_kind() {
+        let config: PluginsConfig = serde_yaml::from_str(
+            "modules:\n  - { name: a, path: plugins/filters.WASM }\n  - { name: b, path: plugins/libvendor.so }\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.modules[0].kind(), PluginKind::Wasm);
+        assert_eq!(config.modules[1].kind(), PluginKind::Native);
+        assert_eq!(config.modules[0].fuel, 10_000_000);
+
+        let mut duplicate = config.clone();
+        duplicate.modules[1].name = "a".to_string();
+        assert!(duplicate.validate().is_err());
+
+        let missing: PluginsConfig =
+            serde_yaml::from_str("modules:\n  - { name: gone, path: /nonexistent/libgone.so }\n").unwrap();
+        assert!(load(&missing).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+// src/plugins/native.rs
+//! Block plugins in shared libraries
+//!
+//! The library exports `petra_plugin_entry`, a C function without arguments
+//! returning a pointer to a static [`NativePluginV1`]. In C:
+//!
+//! ```c
+//! typedef struct { uint32_t kind; uint32_t reserved; uint64_t bits; } PetraValue;
+//!
+//! typedef struct {
+//!     uint32_t abi_version;          /* 1 */
+//!     const char *block_types;       /* "KALMAN_FILTER\nMEDIAN" */
+//!     int32_t (*create)(const uint8_t *description, size_t len, void **instance,
+//!                       char *error, size_t error_len);
+//!     int32_t (*execute)(void *instance, const PetraValue *inputs, size_t input_count,
+//!                        PetraValue *outputs, size_t output_count);
+//!     void (*destroy)(void *instance);
+//! } PetraPluginV1;
+//!
+//! const PetraPluginV1 *petra_plugin_entry(void);
+//! ```
+//!
+//! `create` and `execute` return 0 on success. On failure `create` may
+//! write a NUL-terminated message into `error`. An instance is used by one
+//! thread at a time, but not always the same thread. The library stays
+//! loaded until the last of its blocks is dropped.
+
+use super::{PluginInstance, PluginModule, PluginValue, PLUGIN_ABI_VERSION};
+use crate::error::{PlcError, Result};
+use std::ffi::{c_char, c_void, CStr};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Name of the exported entry point
+const ENTRY_SYMBOL: &[u8] = b"petra_plugin_entry\0";
+
+/// Capacity of the buffer `create` writes its error message into
+const ERROR_CAPACITY: usize = 512;
+
+/// Function table of a version 1 shared library plugin
+#[repr(C)]
+pub struct NativePluginV1 {
+    /// ABI version the plugin was built for, checked before anything else
+    pub abi_version: u32,
+    /// NUL-terminated, newline-separated block types
+    pub block_types: *const c_char,
+    /// Create a block instance from its JSON description
+    pub create: unsafe extern "C" fn(*const u8, usize, *mut *mut c_void, *mut c_char, usize) -> i32,
+    /// Run one scan of an instance
+    pub execute: unsafe extern "C" fn(*mut c_void, *const PluginValue, usize, *mut PluginValue, usize) -> i32,
+    /// Free an instance
+    pub destroy: unsafe extern "C" fn(*mut c_void),
+}
+
+/// Library and its function table, shared by the module and its instances
+struct Library {
+    table: *const NativePluginV1,
+    /// Keeps `table` and the functions it points to loaded
+    _library: libloading::Library,
+}
+
+// SAFETY: the function table is immutable static data of the library,
+// which stays loaded as long as this value.
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn table(&self) -> &NativePluginV1 {
+        // SAFETY: checked non-null and of the right version in
+        // `NativeModule::load`, and valid while the library is loaded.
+        unsafe { &*self.table }
+    }
+}
+
+/// Loaded shared library plugin
+pub struct NativeModule {
+    library: Arc<Library>,
+    block_types: Vec<String>,
+}
+
+impl NativeModule {
+    /// Load a library and check its ABI version
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the library cannot be loaded, has no
+    /// entry point, or was built for another ABI version.
+    pub fn load(path: &Path) -> Result<Self> {
+        // SAFETY: loading runs the library's initializers; native plugins
+        // are trusted code installed by the operator.
+        let library = unsafe { libloading::Library::new(path) }.map_err(|e| PlcError::Config(e.to_string()))?;
+        // SAFETY: the symbol has the signature documented for the ABI.
+        let table = unsafe {
+            let entry = library
+                .get::<unsafe extern "C" fn() -> *const NativePluginV1>(ENTRY_SYMBOL)
+                .map_err(|e| PlcError::Config(e.to_string()))?;
+            entry()
+        };
+        if table.is_null() {
+            return Err(PlcError::Config("petra_plugin_entry returned null".to_string()));
+        }
+        // SAFETY: every ABI version starts with the version field; the rest
+        // of the table is only read once the version matches.
+        let abi_version = unsafe { std::ptr::addr_of!((*table).abi_version).read() };
+        if abi_version != PLUGIN_ABI_VERSION {
+            return Err(PlcError::Config(format!(
+                "plugin ABI version {abi_version} is not supported, expected {PLUGIN_ABI_VERSION}"
+            )));
+        }
+        // SAFETY: version 1 tables point to a NUL-terminated type list.
+        let block_types = unsafe { CStr::from_ptr((*table).block_types) }
+            .to_str()
+            .map_err(|e| PlcError::Config(format!("block types are not UTF-8: {e}")))?
+            .lines()
+            .map(str::trim)
+            .filter(|block_type| !block_type.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self { library: Arc::new(Library { table, _library: library }), block_types })
+    }
+}
+
+impl PluginModule for NativeModule {
+    fn block_types(&self) -> &[String] {
+        &self.block_types
+    }
+
+    fn instantiate(&self, description: &str) -> Result<Box<dyn PluginInstance>> {
+        let mut handle = std::ptr::null_mut();
+        let mut error = [0 as c_char; ERROR_CAPACITY];
+        // SAFETY: the buffers outlive the call and their lengths are passed
+        // along.
+        let status = unsafe {
+            (self.library.table().create)(
+                description.as_ptr(),
+                description.len(),
+                &raw mut handle,
+                error.as_mut_ptr(),
+                error.len(),
+            )
+        };
+        if status != 0 {
+            error[ERROR_CAPACITY - 1] = 0;
+            // SAFETY: the last byte was just set to NUL.
+            let message = unsafe { CStr::from_ptr(error.as_ptr()) }.to_string_lossy();
+            return Err(PlcError::Config(format!("status {status}: {message}")));
+        }
+        Ok(Box::new(NativeInstance { library: Arc::clone(&self.library), handle }))
+    }
+}
+
+/// Block instance owned by a shared library plugin
+struct NativeInstance {
+    library: Arc<Library>,
+    handle: *mut c_void,
+}
+
+// SAFETY: the ABI requires instances to be usable from any thread, one at a
+// time, which `&mut self` in `execute` guarantees.
+unsafe impl Send for NativeInstance {}
+unsafe impl Sync for NativeInstance {}
+
+impl PluginInstance for NativeInstance {
+    fn execute(&mut self, inputs: &[PluginValue], outputs: &mut [PluginValue]) -> Result<()> {
+        // SAFETY: the handle came from `create` and the slices outlive the call.
+        let status = unsafe {
+            (self.library.table().execute)(
+                self.handle,
+                inputs.as_ptr(),
+                inputs.len(),
+                outputs.as_mut_ptr(),
+                outputs.len(),
+            )
+        };
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(PlcError::Block(format!("plugin block failed with status {status}")))
+        }
+    }
+}
+
+impl Drop for NativeInstance {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from `create` and is not used again.
+        unsafe { (self.library.table().destroy)(self.handle) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_files_that_are_not_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("libnot_a_plugin.so");
+        std::fs::write(&path, b"not a shared library").unwrap();
+        assert!(NativeModule::load(&path).is_err());
+        assert!(NativeModule::load(&dir.path().join("missing.so")).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+// src/plugins/wasm.rs
+//! Block plugins in sandboxed WebAssembly modules
+//!
+//! Modules are interpreted by wasmi and may not import anything, so a plugin
+//! has no access to the host beyond the values of its own block. Every block
+//! instance gets its own store and memory, limited to `max_memory_kb`, and
+//! each call into the module is given `fuel` instructions; a plugin that
+//! loops forever fails its block instead of stalling the scan.
+
+use super::{PluginInstance, PluginModule, PluginValue, PLUGIN_ABI_VERSION};
+use crate::error::{PlcError, Result};
+use std::path::Path;
+use wasmi::core::TrapCode;
+use wasmi::{
+    Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc, WasmParams,
+    WasmResults,
+};
+
+/// Data of a store, limiting the resources of one instance
+struct HostState {
+    limits: StoreLimits,
+}
+
+/// Loaded WebAssembly plugin
+pub struct WasmModule {
+    engine: Engine,
+    module: Module,
+    block_types: Vec<String>,
+    fuel: u64,
+    max_memory_kb: u32,
+}
+
+impl WasmModule {
+    /// Compile a module and check its ABI version
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the file cannot be read or compiled,
+    /// the module has imports or misses an export, or was built for another
+    /// ABI version.
+    pub fn load(path: &Path, fuel: u64, max_memory_kb: u32) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| PlcError::Config(e.to_string()))?;
+        Self::from_bytes(&bytes, fuel, max_memory_kb)
+    }
+
+    /// Compile a module from its binary format
+    ///
+    /// # Errors
+    ///
+    /// See [`WasmModule::load`].
+    pub fn from_bytes(bytes: &[u8], fuel: u64, max_memory_kb: u32) -> Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| PlcError::Config(e.to_string()))?;
+        if let Some(import) = module.imports().next() {
+            return Err(PlcError::Config(format!(
+                "plugin imports '{}::{}', but WASM plugins may not import anything",
+                import.module(),
+                import.name()
+            )));
+        }
+
+        let mut probe = WasmModule { engine, module, block_types: Vec::new(), fuel, max_memory_kb };
+        let (mut store, instance) = probe.instantiate_store()?;
+        let abi_version = call::<(), i32>(&mut store, instance, "petra_abi_version", ())?;
+        if u32::try_from(abi_version) != Ok(PLUGIN_ABI_VERSION) {
+            return Err(PlcError::Config(format!(
+                "plugin ABI version {abi_version} is not supported, expected {PLUGIN_ABI_VERSION}"
+            )));
+        }
+        let memory = exported_memory(&store, instance)?;
+        let packed = call::<(), i64>(&mut store, instance, "petra_block_types", ())?;
+        probe.block_types = read_string(&store, memory, packed)?
+            .lines()
+            .map(str::trim)
+            .filter(|block_type| !block_type.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(probe)
+    }
+
+    /// Instantiate the module in a new store with the configured limits
+    fn instantiate_store(&self) -> Result<(Store<HostState>, Instance)> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_kb as usize * 1024)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        let mut store = Store::new(&self.engine, HostState { limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel).map_err(|e| PlcError::Config(e.to_string()))?;
+        let instance = Linker::<HostState>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| PlcError::Config(e.to_string()))?;
+        Ok((store, instance))
+    }
+}
+
+impl PluginModule for WasmModule {
+    fn block_types(&self) -> &[String] {
+        &self.block_types
+    }
+
+    fn instantiate(&self, description: &str) -> Result<Box<dyn PluginInstance>> {
+        let (mut store, instance) = self.instantiate_store()?;
+        let memory = exported_memory(&store, instance)?;
+        let execute = instance
+            .get_typed_func::<(i32, i32, i32, i32), i32>(&store, "petra_execute")
+            .map_err(|e| PlcError::Config(format!("petra_execute: {e}")))?;
+
+        let config = alloc(&mut store, instance, description.len(), self.fuel)?;
+        write_memory(&mut store, memory, config, description.as_bytes())?;
+        let len = wasm_len(description.len())?;
+        store.set_fuel(self.fuel).map_err(|e| PlcError::Config(e.to_string()))?;
+        let status = call::<(i32, i32), i32>(&mut store, instance, "petra_create", (config, len))?;
+        if status != 0 {
+            return Err(PlcError::Config(last_error(&mut store, instance, memory, status)));
+        }
+        Ok(Box::new(WasmInstance { store, instance, memory, execute, fuel: self.fuel, buffers: None }))
+    }
+}
+
+/// Block instance running in its own store
+struct WasmInstance {
+    store: Store<HostState>,
+    instance: Instance,
+    memory: Memory,
+    execute: TypedFunc<(i32, i32, i32, i32), i32>,
+    fuel: u64,
+    /// Input and output buffers in module memory, allocated on first use
+    buffers: Option<(i32, i32)>,
+}
+
+impl PluginInstance for WasmInstance {
+    fn execute(&mut self, inputs: &[PluginValue], outputs: &mut [PluginValue]) -> Result<()> {
+        let (input_ptr, output_ptr) = if let Some(buffers) = self.buffers {
+            buffers
+        } else {
+            let input_ptr = alloc(&mut self.store, self.instance, inputs.len() * PluginValue::SIZE, self.fuel)?;
+            let output_ptr = alloc(&mut self.store, self.instance, outputs.len() * PluginValue::SIZE, self.fuel)?;
+            *self.buffers.insert((input_ptr, output_ptr))
+        };
+
+        let encoded: Vec<u8> = inputs.iter().flat_map(|value| value.to_le_bytes()).collect();
+        write_memory(&mut self.store, self.memory, input_ptr, &encoded)?;
+        let mut encoded = vec![0; outputs.len() * PluginValue::SIZE];
+        write_memory(&mut self.store, self.memory, output_ptr, &encoded)?;
+
+        self.store.set_fuel(self.fuel).map_err(|e| PlcError::Block(e.to_string()))?;
+        let params = (input_ptr, wasm_len(inputs.len())?, output_ptr, wasm_len(outputs.len())?);
+        let status = self.execute.call(&mut self.store, params).map_err(|e| PlcError::Block(trap_message(&e)))?;
+        if status != 0 {
+            return Err(PlcError::Block(last_error(&mut self.store, self.instance, self.memory, status)));
+        }
+
+        read_memory(&self.store, self.memory, output_ptr, &mut encoded)?;
+        for (value, bytes) in outputs.iter_mut().zip(encoded.chunks_exact(PluginValue::SIZE)) {
+            let mut buffer = [0; PluginValue::SIZE];
+            buffer.copy_from_slice(bytes);
+            *value = PluginValue::from_le_bytes(buffer);
+        }
+        Ok(())
+    }
+}
+
+fn trap_message(error: &wasmi::Error) -> String {
+    if error.as_trap_code() == Some(TrapCode::OutOfFuel) {
+        "plugin ran out of fuel".to_string()
+    } else {
+        format!("plugin trapped: {error}")
+    }
+}
+
+fn call<P: WasmParams, R: WasmResults>(
+    store: &mut Store<HostState>,
+    instance: Instance,
+    name: &str,
+    params: P,
+) -> Result<R> {
+    instance
+        .get_typed_func::<P, R>(&*store, name)
+        .and_then(|func| func.call(&mut *store, params))
+        .map_err(|e| PlcError::Config(format!("{name}: {}", trap_message(&e))))
+}
+
+fn exported_memory(store: &Store<HostState>, instance: Instance) -> Result<Memory> {
+    instance
+        .get_memory(store, "memory")
+        .ok_or_else(|| PlcError::Config("plugin does not export 'memory'".to_string()))
+}
+
+fn wasm_len(len: usize) -> Result<i32> {
+    i32::try_from(len).map_err(|_| PlcError::Block(format!("{len} bytes do not fit in WASM memory")))
+}
+
+/// Allocate a buffer with the module's `petra_alloc`
+fn alloc(store: &mut Store<HostState>, instance: Instance, len: usize, fuel: u64) -> Result<i32> {
+    store.set_fuel(fuel).map_err(|e| PlcError::Config(e.to_string()))?;
+    call::<i32, i32>(store, instance, "petra_alloc", wasm_len(len)?)
+}
+
+fn offset(ptr: i32) -> Result<usize> {
+    usize::try_from(ptr).map_err(|_| PlcError::Block(format!("plugin returned invalid pointer {ptr}")))
+}
+
+fn read_memory(store: &Store<HostState>, memory: Memory, ptr: i32, buffer: &mut [u8]) -> Result<()> {
+    memory.read(store, offset(ptr)?, buffer).map_err(|e| PlcError::Block(format!("plugin memory: {e}")))
+}
+
+fn write_memory(store: &mut Store<HostState>, memory: Memory, ptr: i32, buffer: &[u8]) -> Result<()> {
+    memory.write(store, offset(ptr)?, buffer).map_err(|e| PlcError::Block(format!("plugin memory: {e}")))
+}
+
+/// Read a string returned as pointer and length packed into an `i64`
+fn read_string(store: &Store<HostState>, memory: Memory, packed: i64) -> Result<String> {
+    let packed = packed.cast_unsigned();
+    let ptr = usize::try_from(packed >> 32).unwrap_or(usize::MAX);
+    let len = usize::try_from(packed & 0xFFFF_FFFF).unwrap_or(usize::MAX);
+    let mut bytes = vec![0; len.min(memory.data(store).len())];
+    memory
+        .read(store, ptr, &mut bytes)
+        .map_err(|e| PlcError::Block(format!("plugin string out of bounds: {e}")))?;
+    String::from_utf8(bytes).map_err(|e| PlcError::Block(format!("plugin string is not UTF-8: {e}")))
+}
+
+/// Message for a failed call, from `petra_error` if the module exports it
+fn last_error(store: &mut Store<HostState>, instance: Instance, memory: Memory, status: i32) -> String {
+    let message = instance
+        .get_typed_func::<(), i64>(&*store, "petra_error")
+        .ok()
+        .and_then(|func| func.call(&mut *store, ()).ok())
+        .and_then(|packed| read_string(store, memory, packed).ok());
+    match message {
+        Some(message) => format!("status {status}: {message}"),
+        None => format!("status {status}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Implements DOUBLE (out = 2 * in as float) with a bump allocator
+    const DOUBLE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "DOUBLE\nLOOP")
+          (func (export "petra_abi_version") (result i32) (i32.const 1))
+          (func (export "petra_block_types") (result i64) (i64.const 11))
+          (func (export "petra_alloc") (param $len i32) (result i32)
+            (global.get $next)
+            (global.set $next (i32.add (global.get $next) (local.get $len))))
+          (func (export "petra_create") (param i32 i32) (result i32) (i32.const 0))
+          (func (export "petra_execute") (param $in i32) (param $n i32) (param $out i32) (param $m i32) (result i32)
+            (if (i32.ne (i32.load (local.get $in)) (i32.const 3)) (then (return (i32.const 1))))
+            (i32.store (local.get $out) (i32.const 3))
+            (f64.store offset=8 (local.get $out) (f64.mul (f64.load offset=8 (local.get $in)) (f64.const 2)))
+            (i32.const 0))
+          (func (export "loop") (loop $l (br $l))))
+    "#;
+
+    fn module(wat: &str) -> Result<WasmModule> {
+        WasmModule::from_bytes(&wat::parse_str(wat).unwrap(), 100_000, 1024)
+    }
+
+    fn float(f: f64) -> PluginValue {
+        PluginValue::from_value(Some(&crate::value::Value::Float(f))).unwrap()
+    }
+
+    #[test]
+    fn test_executes_block_in_sandbox() {
+        let module = module(DOUBLE).unwrap();
+        assert_eq!(module.block_types(), ["DOUBLE", "LOOP"]);
+
+        let mut instance = module.instantiate("{}").unwrap();
+        let mut outputs = [PluginValue::default()];
+        instance.execute(&[float(1.25)], &mut outputs).unwrap();
+        assert_eq!(outputs[0], float(2.5));
+        instance.execute(&[PluginValue::default()], &mut outputs).unwrap_err();
+
+        let (mut store, instance) = module.instantiate_store().unwrap();
+        let error = call::<(), ()>(&mut store, instance, "loop", ()).unwrap_err();
+        assert!(error.to_string().contains("out of fuel"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("double.wasm");
+        std::fs::write(&path, wat::parse_str(DOUBLE).unwrap()).unwrap();
+        let plugins: super::super::PluginsConfig =
+            serde_yaml::from_str(&format!("modules: [{{ name: double, path: '{}' }}]", path.display())).unwrap();
+        super::super::load(&plugins).unwrap();
+        let config: crate::config::BlockConfig =
+            serde_yaml::from_str("{ name: twice, type: DOUBLE, inputs: { in: x }, outputs: { out: y } }").unwrap();
+        let mut block = crate::blocks::create_block(&config).unwrap();
+        let bus = crate::signal::SignalBus::new();
+        bus.set("x", crate::value::Value::Float(4.0)).unwrap();
+        block.execute(&bus).unwrap();
+        assert_eq!(bus.get("y"), Some(crate::value::Value::Float(8.0)));
+    }
+
+    #[test]
+    fn test_rejects_unsafe_or_incompatible_modules() {
+        let imports = r#"(module (import "env" "system" (func (param i32))))"#;
+        assert!(module(imports).err().unwrap().to_string().contains("env::system"));
+
+        let version = DOUBLE.replacen("(i32.const 1))", "(i32.const 2))", 1);
+        assert!(module(&version).err().unwrap().to_string().contains("ABI version 2"));
+
+        let memory = DOUBLE.replace("(memory (export \"memory\") 1)", "(memory (export \"memory\") 32)");
+        assert!(module(&memory).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+// src/protocols/backfill.rs
+//! History gap backfill from device buffers
+//!
+//! Some devices keep recording while PETRA cannot reach them: OPC-UA
+//! servers with historical access, RTUs with event buffers. A [`Backfiller`]
+//! watches one protocol connection and, when it comes back after an outage,
+//! asks the driver for the values recorded during the gap through
+//! [`ProtocolDriver::read_history`](super::ProtocolDriver::read_history). The
+//! samples are written to history under their source timestamps, so trends
+//! show what the device saw rather than a flat line.
+//!
+//! ```yaml
+//! protocols:
+//!   backfill:
+//!     - protocol: rtu_north
+//!       points:
+//!         - { address: "AI:0", signal: north.flow }
+//!         - { address: "AI:1", signal: north.pressure }
+//!       min_gap_secs: 10
+//!       max_window_hours: 24
+//!       chunk_minutes: 60
+//! ```
+//!
+//! Only the window between the last poll that found the connection up and
+//! the poll that found it restored is requested; live data covers the rest.
+//! Gaps longer than `max_window_hours` are truncated to their most recent
+//! part. A failed backfill is retried every `retry_secs` until it succeeds
+//! or a later outage extends the window.
+
+use super::ProtocolManager;
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Metadata key naming the protocol a backfilled history entry came from
+pub const BACKFILL_SOURCE_KEY: &str = "backfill_source";
+
+/// A value recorded by a device, with its source timestamp
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistorySample {
+    /// Protocol address of the point
+    pub address: String,
+    /// When the device recorded the value
+    pub timestamp: DateTime<Utc>,
+    /// Recorded value
+    pub value: Value,
+    /// Protocol-specific quality code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+}
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Backfill settings for one protocol connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillConfig {
+    /// Protocol driver or failover group to read history from
+    pub protocol: String,
+
+    /// Device points and the signals their history is stored under
+    pub points: Vec<BackfillPoint>,
+
+    /// Shortest outage worth backfilling
+    #[serde(default = "default_min_gap_secs")]
+    pub min_gap_secs: u64,
+
+    /// Longest window requested after an outage
+    #[serde(default = "default_max_window_hours")]
+    pub max_window_hours: u64,
+
+    /// Window covered by a single history read
+    #[serde(default = "default_chunk_minutes")]
+    pub chunk_minutes: u64,
+
+    /// How often the connection state is checked
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Delay before a failed backfill is retried
+    #[serde(default = "default_retry_secs")]
+    pub retry_secs: u64,
+}
+
+/// A device point whose buffered history is backfilled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillPoint {
+    /// Protocol address passed to the driver
+    pub address: String,
+    /// Signal the samples are recorded under
+    pub signal: String,
+}
+
+const fn default_min_gap_secs() -> u64 {
+    10
+}
+
+const fn default_max_window_hours() -> u64 {
+    24
+}
+
+const fn default_chunk_minutes() -> u64 {
+    60
+}
+
+const fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+const fn default_retry_secs() -> u64 {
+    30
+}
+
+impl BackfillConfig {
+    /// Validate the backfill settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for a missing protocol or points, a
+    /// repeated address, or a zero window, chunk or poll interval.
+    pub fn validate(&self) -> Result<()> {
+        if self.protocol.is_empty() {
+            return Err(PlcError::Config("Backfill protocol cannot be empty".to_string()));
+        }
+        if self.points.is_empty() {
+            return Err(PlcError::Config(format!(
+                "Backfill for '{}' has no points",
+                self.protocol
+            )));
+        }
+        let mut addresses = HashSet::new();
+        for point in &self.points {
+            if !addresses.insert(point.address.as_str()) {
+                return Err(PlcError::Config(format!(
+                    "Backfill for '{}' lists address '{}' more than once",
+                    self.protocol, point.address
+                )));
+            }
+        }
+        if self.max_window_hours == 0 || self.chunk_minutes == 0 || self.poll_interval_ms == 0 {
+            return Err(PlcError::Config(format!(
+                "Backfill for '{}' needs a non-zero max_window_hours, chunk_minutes and poll_interval_ms",
+                self.protocol
+            )));
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// HISTORY SINK
+// ============================================================================
+
+/// Destination for backfilled samples
+#[async_trait]
+pub trait HistorySink: Send + Sync {
+    /// Record `sample` for `signal` under its source timestamp
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample cannot be stored.
+    async fn insert_sample(&self, signal: &str, sample: &HistorySample, source: &str) -> Result<()>;
+}
+
+#[cfg(feature = "history")]
+#[async_trait]
+impl HistorySink for crate::history::HistoryManager {
+    async fn insert_sample(&self, signal: &str, sample: &HistorySample, source: &str) -> Result<()> {
+        self.write_backfilled(crate::history::HistoryEntry {
+            timestamp: sample.timestamp,
+            signal_name: signal.to_string(),
+            value: sample.value.clone(),
+            quality: sample.quality,
+            metadata: Some(serde_json::json!({ BACKFILL_SOURCE_KEY: source })),
+        })
+        .await
+    }
+}
+
+// ============================================================================
+// BACKFILLER
+// ============================================================================
+
+/// Detects outages of one connection and backfills them
+#[derive(Debug)]
+pub struct Backfiller {
+    config: BackfillConfig,
+    addresses: Vec<String>,
+    signals: HashMap<String, String>,
+    connected: bool,
+    last_connected: Option<DateTime<Utc>>,
+    pending: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    retry_at: Option<DateTime<Utc>>,
+}
+
+impl Backfiller {
+    /// Create a backfiller for a validated configuration
+    #[must_use]
+    pub fn new(config: BackfillConfig) -> Self {
+        let addresses = config.points.iter().map(|p| p.address.clone()).collect();
+        let signals = config
+            .points
+            .iter()
+            .map(|p| (p.address.clone(), p.signal.clone()))
+            .collect();
+        Self {
+            config,
+            addresses,
+            signals,
+            connected: false,
+            last_connected: None,
+            pending: None,
+            retry_at: None,
+        }
+    }
+
+    /// Record the connection state seen at `now`
+    ///
+    /// Returns the window to backfill once the connection is up and a gap is
+    /// waiting, either because it was just restored or because an earlier
+    /// attempt is due to be retried.
+    pub fn observe(&mut self, connected: bool, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !connected {
+            self.connected = false;
+            return None;
+        }
+
+        let restored = !self.connected;
+        self.connected = true;
+        if let Some(since) = self.last_connected.replace(now) {
+            let gap = now - since;
+            if restored && gap >= seconds(self.config.min_gap_secs) {
+                let oldest = now
+                    .checked_sub_signed(hours(self.config.max_window_hours))
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC);
+                if since < oldest {
+                    tracing::warn!(
+                        "Outage of {} lasted {} minutes, backfilling only the last {} hours",
+                        self.config.protocol,
+                        gap.num_minutes(),
+                        self.config.max_window_hours
+                    );
+                }
+                let start = self.pending.map_or(since, |(start, _)| start.min(since)).max(oldest);
+                self.pending = Some((start, now));
+                self.retry_at = None;
+            }
+        }
+
+        match (self.pending, self.retry_at) {
+            (Some(_), Some(retry_at)) if now < retry_at => None,
+            (pending, _) => pending,
+        }
+    }
+
+    /// Mark the pending window as done, or schedule a retry after a failure
+    pub fn complete(&mut self, succeeded: bool, now: DateTime<Utc>) {
+        if succeeded {
+            self.pending = None;
+            self.retry_at = None;
+        } else {
+            self.retry_at = now.checked_add_signed(seconds(self.config.retry_secs));
+        }
+    }
+
+    /// Read the device history for `start..end` and store it in `sink`
+    ///
+    /// Samples at the window edges are skipped, since live data covers them.
+    /// Returns the number of samples stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first history read or sink error.
+    pub async fn backfill(
+        &self,
+        manager: &ProtocolManager,
+        sink: &dyn HistorySink,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<usize> {
+        let chunk = minutes(self.config.chunk_minutes);
+        let mut stored = 0;
+        let mut from = start;
+        while from < end {
+            let to = from.checked_add_signed(chunk).map_or(end, |to| to.min(end));
+            let samples = manager
+                .read_history(&self.config.protocol, &self.addresses, from, to)
+                .await?;
+            for sample in samples {
+                // Chunks share their edges; keep each sample in exactly one
+                if sample.timestamp <= start || sample.timestamp < from || sample.timestamp >= to {
+                    continue;
+                }
+                let Some(signal) = self.signals.get(&sample.address) else {
+                    tracing::debug!("Ignoring history for unmapped address {}", sample.address);
+                    continue;
+                };
+                sink.insert_sample(signal, &sample, &self.config.protocol).await?;
+                stored += 1;
+            }
+            from = to;
+        }
+        Ok(stored)
+    }
+
+    /// Watch the connection and backfill each outage until the task is dropped
+    pub async fn run(mut self, manager: Arc<ProtocolManager>, sink: Arc<dyn HistorySink>) {
+        let mut ticker =
+            tokio::time::interval(std::time::Duration::from_millis(self.config.poll_interval_ms));
+        loop {
+            ticker.tick().await;
+            let connected = manager.is_connected(&self.config.protocol).await;
+            let Some((start, end)) = self.observe(connected, Utc::now()) else {
+                continue;
+            };
+
+            tracing::info!("Backfilling {} from {} to {}", self.config.protocol, start, end);
+            match self.backfill(&manager, sink.as_ref(), start, end).await {
+                Ok(count) => {
+                    tracing::info!("Backfilled {} samples from {}", count, self.config.protocol);
+                    self.complete(true, Utc::now());
+                }
+                Err(e) => {
+                    tracing::warn!("Backfill from {} failed, will retry: {}", self.config.protocol, e);
+                    self.complete(false, Utc::now());
+                }
+            }
+        }
+    }
+}
+
+fn seconds(secs: u64) -> Duration {
+    Duration::seconds(i64::try_from(secs).unwrap_or(i64::MAX / 1000))
+}
+
+fn minutes(mins: u64) -> Duration {
+    Duration::minutes(i64::try_from(mins).unwrap_or(i64::MAX / 60_000))
+}
+
+fn hours(hours: u64) -> Duration {
+    Duration::hours(i64::try_from(hours).unwrap_or(i64::MAX / 3_600_000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::ProtocolDriver;
+    use crate::SignalBus;
+    use tokio::sync::Mutex;
+
+    /// Device that buffered one sample per minute for every address
+    struct BufferingDriver {
+        buffer: Vec<HistorySample>,
+    }
+
+    #[async_trait]
+    impl ProtocolDriver for BufferingDriver {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn read_values(&self, _addresses: &[String]) -> Result<HashMap<String, Value>> {
+            Ok(HashMap::new())
+        }
+
+        async fn write_values(&mut self, _values: &HashMap<String, Value>) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn protocol_name(&self) -> &'static str {
+            "buffering"
+        }
+
+        async fn read_history(
+            &self,
+            addresses: &[String],
+            start: DateTime<Utc>,
+            end: DateTime<Utc>,
+        ) -> Result<Vec<HistorySample>> {
+            Ok(self
+                .buffer
+                .iter()
+                .filter(|s| addresses.contains(&s.address) && s.timestamp >= start && s.timestamp <= end)
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<(String, DateTime<Utc>, String)>>);
+
+    #[async_trait]
+    impl HistorySink for MemorySink {
+        async fn insert_sample(&self, signal: &str, sample: &HistorySample, source: &str) -> Result<()> {
+            self.0
+                .lock()
+                .await
+                .push((signal.to_string(), sample.timestamp, source.to_string()));
+            Ok(())
+        }
+    }
+
+    fn config() -> BackfillConfig {
+        serde_yaml::from_str(
+            "protocol: rtu\npoints:\n  - { address: 'AI:0', signal: flow }\nmin_gap_secs: 60\nchunk_minutes: 7\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_outage_detection() {
+        let t0 = Utc::now();
+        let mut backfiller = Backfiller::new(config());
+
+        assert_eq!(backfiller.observe(true, t0), None);
+        assert_eq!(backfiller.observe(false, t0 + Duration::seconds(1)), None);
+        // Short outages are ignored
+        assert_eq!(backfiller.observe(true, t0 + Duration::seconds(30)), None);
+
+        let t1 = t0 + Duration::seconds(31);
+        assert_eq!(backfiller.observe(true, t1), None);
+        assert_eq!(backfiller.observe(false, t1 + Duration::seconds(1)), None);
+        let t2 = t1 + Duration::minutes(20);
+        assert_eq!(backfiller.observe(true, t2), Some((t1, t2)));
+
+        // A failed attempt waits for the retry delay
+        backfiller.complete(false, t2);
+        assert_eq!(backfiller.observe(true, t2 + Duration::seconds(1)), None);
+        assert_eq!(backfiller.observe(true, t2 + Duration::seconds(31)), Some((t1, t2)));
+        backfiller.complete(true, t2 + Duration::seconds(31));
+        assert_eq!(backfiller.observe(true, t2 + Duration::seconds(32)), None);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_uses_source_timestamps() {
+        let start = Utc::now() - Duration::minutes(30);
+        let end = start + Duration::minutes(30);
+        let buffer = (0..=30i32)
+            .flat_map(|minute| {
+                ["AI:0", "AI:9"].map(|address| HistorySample {
+                    address: address.to_string(),
+                    timestamp: start + Duration::minutes(minute.into()),
+                    value: Value::Float(f64::from(minute)),
+                    quality: None,
+                })
+            })
+            .collect();
+
+        let manager = ProtocolManager::new(SignalBus::new());
+        manager
+            .add_driver("rtu".to_string(), Box::new(BufferingDriver { buffer }))
+            .await
+            .unwrap();
+        let sink = MemorySink::default();
+
+        let backfiller = Backfiller::new(config());
+        let stored = backfiller.backfill(&manager, &sink, start, end).await.unwrap();
+
+        // Minutes 1..=29 of the mapped address, each once despite the chunking
+        assert_eq!(stored, 29);
+        let entries = sink.0.lock().await;
+        assert!(entries.iter().all(|(signal, _, source)| signal == "flow" && source == "rtu"));
+        assert_eq!(entries[0].1, start + Duration::minutes(1));
+        assert_eq!(entries[28].1, start + Duration::minutes(29));
+    }
+}
@@ -0,0 +1,560 @@
+// src/protocols/bridge.rs
+//! Signal bridging between PETRA instances
+//!
+//! A bridge mirrors signals selected by name patterns between this instance
+//! and other PETRA instances, for architectures where cell controllers
+//! report to a line controller that hands setpoints back down. Patterns are
+//! signal names where `*` matches any run of characters.
+//!
+//! Each link lists the signals it carries:
+//!
+//! - `mirror` signals are exchanged in both directions
+//! - `export` signals are only sent to the remote side
+//! - `import` signals are only accepted from the remote side
+//!
+//! Links run over one of two transports:
+//!
+//! - `nats`: every instance on the link publishes changes to
+//!   `<subject_prefix>.<signal>` and subscribes to `<subject_prefix>.>`, so
+//!   any number of instances can share a link
+//! - `grpc`: this instance connects to the gRPC service of the remote
+//!   instance, streams its changes and writes local changes with
+//!   `WriteSignals`; only one side of the link is configured
+//!
+//! ```yaml
+//! protocols:
+//!   bridge:
+//!     instance_id: cell3
+//!     links:
+//!       - name: line
+//!         transport: { type: grpc, endpoint: "http://line-plc:50051", token: "0123456789abcdef" }
+//!         export: ["cell3.*"]
+//!         import: ["line.setpoint.cell3.*"]
+//!       - name: cells
+//!         transport: { type: nats, url: "nats://nats1:4222", subject_prefix: petra.bridge.line1 }
+//!         mirror: ["line1.mode", "line1.estop"]
+//! ```
+//!
+//! # Loop prevention
+//!
+//! A link remembers the last value it exchanged for every signal, with the
+//! time of the change. A received value equal to it is the echo of a value
+//! this instance sent and is dropped, so is a value older than it, and a
+//! local value equal to it is not sent back. Conflicting changes on both
+//! sides resolve to the later one, which needs the instances' clocks to be
+//! synchronised.
+//!
+//! NATS messages also name the instance the change originated on. An
+//! instance drops messages of its own origin and keeps the origin when it
+//! forwards a received value over another link, so values do not circulate
+//! through rings of instances.
+
+// Without a transport, links only fail to start
+#![cfg_attr(not(any(feature = "nats", feature = "grpc")), allow(dead_code, unused_imports, unused_variables, clippy::unused_async))]
+
+use crate::{PlcError, Result, SignalBus, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+pub use crate::subscription::pattern_matches;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Bridges of this instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Name of this instance, unique among bridged instances
+    pub instance_id: String,
+
+    /// Links to other instances
+    pub links: Vec<BridgeLinkConfig>,
+}
+
+/// Signals exchanged with other instances over one transport
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeLinkConfig {
+    /// Link name used in logs
+    pub name: String,
+
+    /// How the link reaches the other instances
+    pub transport: BridgeTransport,
+
+    /// Patterns of signals exchanged in both directions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirror: Vec<String>,
+
+    /// Patterns of signals only sent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub export: Vec<String>,
+
+    /// Patterns of signals only received
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub import: Vec<String>,
+
+    /// Interval for checking local signals for changes
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+
+    /// Delay before a failed link is reconnected
+    #[serde(default = "default_reconnect_ms")]
+    pub reconnect_ms: u64,
+}
+
+/// Transport of a bridge link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BridgeTransport {
+    /// Shared subjects on a NATS server (requires the "nats" feature)
+    Nats {
+        /// Server URL, or several separated by commas
+        url: String,
+        /// Subject namespace shared by the instances on the link
+        subject_prefix: String,
+        /// Authentication token
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        /// NATS credentials file (JWT and `NKey` seed)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        credentials_file: Option<PathBuf>,
+    },
+    /// gRPC service of the remote instance (requires the "grpc" feature)
+    Grpc {
+        /// Service URL, e.g. `http://line-plc:50051`
+        endpoint: String,
+        /// Client token configured on the remote service
+        token: String,
+    },
+}
+
+const fn default_interval_ms() -> u64 {
+    250
+}
+
+const fn default_reconnect_ms() -> u64 {
+    5000
+}
+
+impl BridgeConfig {
+    /// Validate the instance id, link names, patterns and transports
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.instance_id.trim().is_empty() {
+            return Err(PlcError::Config("Bridge instance_id cannot be empty".to_string()));
+        }
+        let mut names = std::collections::HashSet::new();
+        for link in &self.links {
+            if !names.insert(link.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate bridge link '{}'", link.name)));
+            }
+            link.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl BridgeLinkConfig {
+    fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| PlcError::Config(format!("Bridge link '{}' {}", self.name, reason));
+        if self.name.trim().is_empty() {
+            return Err(PlcError::Config("Bridge link name cannot be empty".to_string()));
+        }
+        if self.mirror.is_empty() && self.export.is_empty() && self.import.is_empty() {
+            return Err(invalid("selects no signals"));
+        }
+        if self.mirror.iter().chain(&self.export).chain(&self.import).any(|p| p.trim().is_empty()) {
+            return Err(invalid("has an empty pattern"));
+        }
+        if self.interval_ms == 0 {
+            return Err(invalid("interval_ms cannot be 0"));
+        }
+        match &self.transport {
+            BridgeTransport::Nats { url, subject_prefix, .. } => {
+                if !cfg!(feature = "nats") {
+                    return Err(invalid("uses NATS, which requires the nats feature"));
+                }
+                if url.trim().is_empty() {
+                    return Err(invalid("has an empty NATS url"));
+                }
+                let valid = !subject_prefix.is_empty()
+                    && subject_prefix
+                        .split('.')
+                        .all(|t| !t.is_empty() && t != "*" && t != ">" && !t.contains(char::is_whitespace));
+                if !valid {
+                    return Err(invalid(&format!("has an invalid subject_prefix '{subject_prefix}'")));
+                }
+            }
+            BridgeTransport::Grpc { endpoint, token } => {
+                if !cfg!(feature = "grpc") {
+                    return Err(invalid("uses gRPC, which requires the grpc feature"));
+                }
+                if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                    return Err(invalid(&format!("has an invalid gRPC endpoint '{endpoint}'")));
+                }
+                if token.is_empty() {
+                    return Err(invalid("needs a gRPC token"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sends(&self, signal: &str) -> bool {
+        self.mirror.iter().chain(&self.export).any(|p| pattern_matches(p, signal))
+    }
+
+    fn receives(&self, signal: &str) -> bool {
+        self.mirror.iter().chain(&self.import).any(|p| pattern_matches(p, signal))
+    }
+}
+
+// ============================================================================
+// LOOP PREVENTION
+// ============================================================================
+
+/// Payload of a bridged change on NATS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeMessage {
+    pub value: Value,
+    /// Instance the change originated on
+    pub origin: String,
+    /// Milliseconds since the Unix epoch when the change was sent
+    pub timestamp: i64,
+}
+
+/// Instances that values received over any link originated on, shared by
+/// the links of this instance
+#[derive(Debug, Clone, Default)]
+struct Origins(Arc<Mutex<HashMap<String, (Value, String)>>>);
+
+impl Origins {
+    fn record(&self, signal: &str, value: &Value, origin: &str) {
+        let mut origins = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        origins.insert(signal.to_string(), (value.clone(), origin.to_string()));
+    }
+
+    /// Origin of `value` if it is the value last received for `signal`
+    fn of(&self, signal: &str, value: &Value) -> Option<String> {
+        let origins = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        origins.get(signal).filter(|(v, _)| v == value).map(|(_, origin)| origin.clone())
+    }
+}
+
+/// A change to send, with the instance it originated on
+#[derive(Debug, Clone, PartialEq)]
+struct Outgoing {
+    signal: String,
+    value: Value,
+    origin: String,
+    timestamp: i64,
+}
+
+/// Echo suppression state of one link
+struct LinkState {
+    instance_id: String,
+    config: BridgeLinkConfig,
+    origins: Origins,
+    /// Last value exchanged per signal, with the time of the change
+    exchanged: HashMap<String, (Value, i64)>,
+}
+
+impl LinkState {
+    fn new(instance_id: &str, config: BridgeLinkConfig, origins: Origins) -> Self {
+        Self { instance_id: instance_id.to_string(), config, origins, exchanged: HashMap::new() }
+    }
+
+    /// Local changes to send since the last call
+    fn outgoing(&mut self, bus: &SignalBus) -> Vec<Outgoing> {
+        let timestamp = now_ms();
+        let mut changes: Vec<Outgoing> = bus
+            .snapshot()
+            .into_iter()
+            .filter(|(signal, value)| {
+                self.config.sends(signal) && self.exchanged.get(signal).is_none_or(|(last, _)| last != value)
+            })
+            .map(|(signal, value)| Outgoing {
+                origin: self.origins.of(&signal, &value).unwrap_or_else(|| self.instance_id.clone()),
+                signal,
+                value,
+                timestamp,
+            })
+            .collect();
+        changes.sort_by(|a, b| a.signal.cmp(&b.signal));
+        for change in &changes {
+            self.exchanged.insert(change.signal.clone(), (change.value.clone(), timestamp));
+        }
+        changes
+    }
+
+    /// Apply a received change; returns whether it was written
+    fn incoming(&mut self, bus: &SignalBus, signal: &str, value: Value, origin: &str, timestamp: i64) -> bool {
+        if origin == self.instance_id || !self.config.receives(signal) {
+            return false;
+        }
+        if let Some((last, at)) = self.exchanged.get(signal) {
+            if *last == value || timestamp < *at {
+                return false;
+            }
+        }
+        let source = format!("bridge:{}", self.config.name);
+        if let Err(e) = bus.set_with_source(signal, value.clone(), Some(&source)) {
+            warn!("Bridge link '{}' could not write '{}': {}", self.config.name, signal, e);
+            return false;
+        }
+        self.origins.record(signal, &value, origin);
+        self.exchanged.insert(signal.to_string(), (value, timestamp));
+        true
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+// ============================================================================
+// TRANSPORTS
+// ============================================================================
+
+#[cfg(feature = "nats")]
+async fn run_nats(
+    state: &mut LinkState,
+    bus: &SignalBus,
+    url: &str,
+    subject_prefix: &str,
+    token: Option<&String>,
+    credentials_file: Option<&PathBuf>,
+) -> Result<()> {
+    use futures::StreamExt;
+    use super::nats::{connect, NatsConfig};
+
+    let config = NatsConfig {
+        url: url.to_string(),
+        name: format!("petra-bridge-{}", state.instance_id),
+        subject_prefix: subject_prefix.to_string(),
+        token: token.cloned(),
+        credentials_file: credentials_file.cloned(),
+        publish: None,
+        jetstream: None,
+        subscriptions: Vec::new(),
+        serve_reads: false,
+    };
+    let client = connect(&config).await?;
+    let nats_error = |e: &dyn std::fmt::Display| PlcError::Protocol(format!("NATS: {e}"));
+    let mut subscriber = client
+        .subscribe(format!("{subject_prefix}.>"))
+        .await
+        .map_err(|e| nats_error(&e))?;
+    let base = format!("{subject_prefix}.");
+    info!("Bridge link '{}' joined '{}' on NATS", state.config.name, subject_prefix);
+
+    let mut ticker = tokio::time::interval(Duration::from_millis(state.config.interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                for change in state.outgoing(bus) {
+                    let message = BridgeMessage { value: change.value, origin: change.origin, timestamp: change.timestamp };
+                    let subject = format!("{base}{}", change.signal);
+                    let payload = serde_json::to_vec(&message)?;
+                    client.publish(subject, payload.into()).await.map_err(|e| nats_error(&e))?;
+                }
+            }
+            message = subscriber.next() => {
+                let Some(message) = message else {
+                    return Err(PlcError::Protocol(format!("NATS subscription to '{subject_prefix}' ended")));
+                };
+                let Some(signal) = message.subject.as_str().strip_prefix(&base) else {
+                    continue;
+                };
+                match serde_json::from_slice::<BridgeMessage>(&message.payload) {
+                    Ok(m) => {
+                        state.incoming(bus, signal, m.value, &m.origin, m.timestamp);
+                    }
+                    Err(e) => debug!("Skipping malformed bridge message on '{}': {}", message.subject, e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn authorized<T>(token: &str, message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    if let Ok(value) = format!("Bearer {token}").parse() {
+        request.metadata_mut().insert("authorization", value);
+    }
+    request
+}
+
+#[cfg(feature = "grpc")]
+async fn run_grpc(state: &mut LinkState, bus: &SignalBus, endpoint: &str, token: &str) -> Result<()> {
+    use crate::grpc::{proto, signal_message};
+
+    let grpc_error = |e: &dyn std::fmt::Display| PlcError::Protocol(format!("gRPC bridge to {endpoint}: {e}"));
+
+    let mut client = proto::petra_client::PetraClient::connect(endpoint.to_string())
+        .await
+        .map_err(|e| grpc_error(&e))?;
+    let interval_ms = u32::try_from(state.config.interval_ms).unwrap_or(u32::MAX);
+    let mut changes = client
+        .stream_signal_changes(authorized(token, proto::StreamSignalChangesRequest { names: Vec::new(), interval_ms }))
+        .await
+        .map_err(|e| grpc_error(&e))?
+        .into_inner();
+    info!("Bridge link '{}' connected to {}", state.config.name, endpoint);
+
+    let mut ticker = tokio::time::interval(Duration::from_millis(state.config.interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                for change in state.outgoing(bus) {
+                    let request = proto::WriteSignalsRequest {
+                        signals: vec![signal_message(change.signal.clone(), &change.value)],
+                        reason: format!("Bridged from {}", change.origin),
+                        signature: None,
+                    };
+                    match client.write_signals(authorized(token, request)).await {
+                        Ok(_) => {}
+                        Err(status) if status.code() == tonic::Code::NotFound => {
+                            debug!("Bridge link '{}': '{}' does not exist remotely", state.config.name, change.signal);
+                        }
+                        Err(status) => return Err(grpc_error(&status)),
+                    }
+                }
+            }
+            signal = changes.message() => {
+                let Some(signal) = signal.map_err(|e| grpc_error(&e))? else {
+                    return Err(grpc_error(&"signal stream ended"));
+                };
+                let Some(value) = signal.value.and_then(|v| Value::try_from(v).ok()) else {
+                    continue;
+                };
+                // The remote side does not tag origins; treat it as one
+                state.incoming(bus, &signal.name, value, endpoint, signal.timestamp_ms);
+            }
+        }
+    }
+}
+
+/// Run one link until it fails
+async fn run_link(state: &mut LinkState, bus: &SignalBus) -> Result<()> {
+    match state.config.transport.clone() {
+        #[cfg(feature = "nats")]
+        BridgeTransport::Nats { url, subject_prefix, token, credentials_file } => {
+            run_nats(state, bus, &url, &subject_prefix, token.as_ref(), credentials_file.as_ref()).await
+        }
+        #[cfg(feature = "grpc")]
+        BridgeTransport::Grpc { endpoint, token } => run_grpc(state, bus, &endpoint, &token).await,
+        #[allow(unreachable_patterns)]
+        _ => Err(PlcError::Config(format!(
+            "Bridge link '{}' uses a transport this build does not include",
+            state.config.name
+        ))),
+    }
+}
+
+/// Run every link until the task is cancelled, reconnecting failed links
+///
+/// A reconnected link sends all its signals again, as the other side may
+/// have missed changes in the meantime.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the configuration is invalid.
+pub async fn run(config: BridgeConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let origins = Origins::default();
+    let mut tasks = tokio::task::JoinSet::new();
+    for link in config.links {
+        let instance_id = config.instance_id.clone();
+        let origins = origins.clone();
+        let bus = bus.clone();
+        tasks.spawn(async move {
+            let retry = Duration::from_millis(link.reconnect_ms);
+            loop {
+                let mut state = LinkState::new(&instance_id, link.clone(), origins.clone());
+                if let Err(e) = run_link(&mut state, &bus).await {
+                    warn!("Bridge link '{}' failed, reconnecting in {:?}: {}", link.name, retry, e);
+                }
+                tokio::time::sleep(retry).await;
+            }
+        });
+    }
+    while tasks.join_next().await.is_some() {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(name: &str) -> BridgeLinkConfig {
+        serde_yaml::from_str(&format!(
+            "name: {name}\ntransport: {{ type: grpc, endpoint: 'http://line:50051', token: secret }}\nmirror: ['line.mode']\nexport: ['cell3.*']\nimport: ['line.setpoint.*']\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_patterns_and_validation() {
+        assert!(pattern_matches("cell3.*", "cell3.temp"));
+        assert!(pattern_matches("*.speed", "line1.speed"));
+        assert!(pattern_matches("a*b*c", "a_b_c"));
+        assert!(!pattern_matches("a*b*c", "a_c"));
+        assert!(pattern_matches("line.mode", "line.mode"));
+        assert!(!pattern_matches("line.mode", "line.mode2"));
+
+        let config = BridgeConfig { instance_id: "cell3".into(), links: vec![link("line")] };
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "grpc"));
+
+        let duplicate = BridgeConfig { instance_id: "cell3".into(), links: vec![link("line"), link("line")] };
+        assert!(duplicate.validate().is_err());
+        let mut empty = link("line");
+        empty.mirror.clear();
+        empty.export.clear();
+        empty.import.clear();
+        assert!(BridgeConfig { instance_id: "cell3".into(), links: vec![empty] }.validate().is_err());
+    }
+
+    #[test]
+    fn test_echoes_and_own_origin_are_dropped() {
+        let bus = SignalBus::new();
+        bus.set("cell3.temp", Value::Float(21.0)).unwrap();
+        bus.set("line.mode", Value::Integer(1)).unwrap();
+        let origins = Origins::default();
+        let mut up = LinkState::new("cell3", link("up"), origins.clone());
+        let mut peers = LinkState::new("cell3", link("peers"), origins);
+
+        let sent = up.outgoing(&bus);
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|c| c.origin == "cell3"));
+        assert!(up.outgoing(&bus).is_empty());
+
+        // The echo of a sent value and anything older than it are dropped
+        assert!(!up.incoming(&bus, "line.mode", Value::Integer(1), "line", now_ms()));
+        assert!(!up.incoming(&bus, "line.mode", Value::Integer(2), "line", 0));
+        // Export-only signals and changes of this instance are never applied
+        assert!(!up.incoming(&bus, "cell3.temp", Value::Float(5.0), "line", now_ms()));
+        assert!(!peers.incoming(&bus, "line.mode", Value::Integer(3), "cell3", now_ms()));
+
+        // A newer remote change is applied and not sent back on its link...
+        assert!(up.incoming(&bus, "line.mode", Value::Integer(2), "line", now_ms() + 1));
+        assert_eq!(bus.get("line.mode"), Some(Value::Integer(2)));
+        assert!(up.outgoing(&bus).is_empty());
+        // ...but forwarded over other links, keeping its origin
+        let forwarded = peers.outgoing(&bus);
+        let mode = forwarded.iter().find(|c| c.signal == "line.mode").unwrap();
+        assert_eq!((mode.value.clone(), mode.origin.as_str()), (Value::Integer(2), "line"));
+    }
+}
@@ -0,0 +1,1182 @@
+// src/protocols/coap.rs
+//! CoAP client for constrained devices
+//!
+//! Reads resources of battery-powered sensors and other constrained devices
+//! over CoAP (RFC 7252) and writes their values to signals. Resources are
+//! observed (RFC 7641) so a sleeping device only sends a notification when
+//! its value changes; resources that cannot be observed are polled.
+//!
+//! ```yaml
+//! protocols:
+//!   coap:
+//!     devices:
+//!       - name: greenhouse_probe
+//!         address: "probe-12.local"
+//!         online_signal: greenhouse.probe_online
+//!         dtls:
+//!           identity: probe-12
+//!           key_hex: "5f2a9c0e41b7d3865f2a9c0e41b7d386"
+//!         resources:
+//!           - path: sensors/temperature
+//!             signal: greenhouse.temperature
+//!           - path: sensors/env?format=json
+//!             signal: greenhouse.humidity
+//!             field: humidity
+//!             observe: false
+//!             poll_interval_ms: 60000
+//! ```
+//!
+//! Without a port the address defaults to 5683, or 5684 with `dtls`. DTLS
+//! uses a pre-shared key (`TLS_PSK_WITH_AES_128_CCM_8` by default, the
+//! cipher suite RFC 7252 requires of constrained devices); certificates are
+//! not supported.
+//!
+//! Payloads may be `text/plain` or `application/json`. Text is read as a
+//! boolean or number and `field` selects a member of a JSON object, with
+//! dots separating nested members. Values are converted to the type the
+//! signal currently holds.
+//!
+//! Observations are registered again when a notification is overdue by the
+//! resource's Max-Age. Requests are confirmable and retransmitted with
+//! exponential back-off; when a device stops acknowledging, `online_signal`
+//! is cleared and the session is re-established, including a new DTLS
+//! handshake for devices that lost theirs while asleep.
+
+use crate::scan_budget::{self, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
+use openssl::error::ErrorStack;
+use openssl::ssl::{Ssl, SslContext, SslMethod, SslOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::UdpSocket;
+use tokio_openssl::SslStream;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// CoAP client configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoapConfig {
+    /// Devices read by the client
+    pub devices: Vec<CoapDeviceConfig>,
+}
+
+/// A CoAP server and the resources read from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoapDeviceConfig {
+    /// Device name used in logs
+    pub name: String,
+
+    /// Host and optional port
+    pub address: String,
+
+    /// Secure the exchange with DTLS and a pre-shared key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dtls: Option<CoapDtlsConfig>,
+
+    /// Boolean signal set while the device answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub online_signal: Option<String>,
+
+    /// Initial retransmission timeout (RFC 7252 `ACK_TIMEOUT`)
+    #[serde(default = "default_ack_timeout_ms")]
+    pub ack_timeout_ms: u64,
+
+    /// Retransmissions before the device is considered unreachable
+    #[serde(default = "default_max_retransmit")]
+    pub max_retransmit: u32,
+
+    /// Delay before re-establishing a failed session
+    #[serde(default = "default_reconnect_delay_ms")]
+    pub reconnect_delay_ms: u64,
+
+    /// Resources mapped to signals
+    pub resources: Vec<CoapResourceConfig>,
+}
+
+/// DTLS pre-shared key settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoapDtlsConfig {
+    /// PSK identity sent to the device
+    pub identity: String,
+
+    /// Pre-shared key in hexadecimal
+    pub key_hex: String,
+
+    /// OpenSSL cipher list
+    #[serde(default = "default_ciphers")]
+    pub ciphers: String,
+}
+
+/// A resource whose value is written to a signal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoapResourceConfig {
+    /// Resource path, optionally with a query such as `sensors/env?unit=c`
+    pub path: String,
+
+    /// Signal receiving the value
+    pub signal: String,
+
+    /// Observe the resource instead of polling it
+    #[serde(default = "default_observe")]
+    pub observe: bool,
+
+    /// Poll interval, also the retry delay after errors and refused
+    /// observations
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Member of a JSON payload holding the value, dot separated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+const fn default_ack_timeout_ms() -> u64 {
+    2000
+}
+
+const fn default_max_retransmit() -> u32 {
+    4
+}
+
+const fn default_reconnect_delay_ms() -> u64 {
+    5000
+}
+
+fn default_ciphers() -> String {
+    "PSK-AES128-CCM8:PSK-AES128-CBC-SHA256".to_string()
+}
+
+const fn default_observe() -> bool {
+    true
+}
+
+const fn default_poll_interval_ms() -> u64 {
+    30_000
+}
+
+impl CoapConfig {
+    /// Validate devices, keys and resources
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.devices.is_empty() {
+            return Err(PlcError::Config("CoAP requires at least one device".to_string()));
+        }
+        let mut names = HashSet::new();
+        for device in &self.devices {
+            if !names.insert(device.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate CoAP device '{}'", device.name)));
+            }
+            device.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl CoapDeviceConfig {
+    fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| PlcError::Config(format!("CoAP device '{}': {reason}", self.name));
+        if self.address.trim().is_empty() {
+            return Err(invalid("address cannot be empty".to_string()));
+        }
+        if self.ack_timeout_ms == 0 {
+            return Err(invalid("ack_timeout_ms cannot be 0".to_string()));
+        }
+        if self.max_retransmit > 8 {
+            return Err(invalid("max_retransmit cannot exceed 8".to_string()));
+        }
+        if self.resources.is_empty() {
+            return Err(invalid("no resources configured".to_string()));
+        }
+        if let Some(dtls) = &self.dtls {
+            if dtls.identity.is_empty() {
+                return Err(invalid("DTLS identity cannot be empty".to_string()));
+            }
+            let key = decode_hex(&dtls.key_hex).map_err(|e| invalid(e.to_string()))?;
+            if key.is_empty() || key.len() > 64 {
+                return Err(invalid("DTLS key must be 1 to 64 bytes".to_string()));
+            }
+        }
+        for resource in &self.resources {
+            if resource.poll_interval_ms == 0 {
+                return Err(invalid(format!("resource '{}' has poll_interval_ms 0", resource.path)));
+            }
+            let (segments, queries) = split_path(&resource.path);
+            if segments.iter().chain(&queries).any(|s| s.len() > 255) {
+                return Err(invalid(format!("resource '{}' has a segment over 255 bytes", resource.path)));
+            }
+        }
+        Ok(())
+    }
+
+    const fn default_port(&self) -> u16 {
+        if self.dtls.is_some() {
+            5684
+        } else {
+            5683
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(PlcError::Config("DTLS key_hex must be an even number of hex digits".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| PlcError::Config(format!("Invalid hex digits '{}' in DTLS key", &hex[i..i + 2])))
+        })
+        .collect()
+}
+
+/// Path segments and query parameters of a resource path
+fn split_path(path: &str) -> (Vec<&str>, Vec<&str>) {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    (
+        path.split('/').filter(|s| !s.is_empty()).collect(),
+        query.split('&').filter(|s| !s.is_empty()).collect(),
+    )
+}
+
+// ============================================================================
+// MESSAGES
+// ============================================================================
+
+const VERSION: u8 = 1;
+const PAYLOAD_MARKER: u8 = 0xFF;
+
+const CODE_EMPTY: u8 = 0x00;
+const CODE_GET: u8 = 0x01;
+
+const OPTION_OBSERVE: u16 = 6;
+const OPTION_URI_PATH: u16 = 11;
+const OPTION_CONTENT_FORMAT: u16 = 12;
+const OPTION_MAX_AGE: u16 = 14;
+const OPTION_URI_QUERY: u16 = 15;
+
+const FORMAT_TEXT: u32 = 0;
+const FORMAT_JSON: u32 = 50;
+
+/// Max-Age of a response without the option, in seconds
+const DEFAULT_MAX_AGE: u64 = 60;
+
+/// Grace period after Max-Age before an observation is registered again
+const OBSERVE_MARGIN: Duration = Duration::from_secs(10);
+
+/// Observe sequence numbers are compared modulo 2^24 (RFC 7641 section 3.4)
+const OBSERVE_WINDOW: u32 = 1 << 23;
+
+/// Age after which a notification is newer regardless of its sequence
+const OBSERVE_REORDER_LIMIT: Duration = Duration::from_secs(128);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageType {
+    Confirmable,
+    NonConfirmable,
+    Acknowledgement,
+    Reset,
+}
+
+/// A CoAP message with options sorted by number
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Message {
+    kind: MessageType,
+    code: u8,
+    id: u16,
+    token: Vec<u8>,
+    options: Vec<(u16, Vec<u8>)>,
+    payload: Vec<u8>,
+}
+
+impl Message {
+    /// Empty acknowledgement or reset of `message_id`
+    const fn empty(kind: MessageType, message_id: u16) -> Self {
+        Self {
+            kind,
+            code: CODE_EMPTY,
+            id: message_id,
+            token: Vec::new(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let kind = match self.kind {
+            MessageType::Confirmable => 0,
+            MessageType::NonConfirmable => 1,
+            MessageType::Acknowledgement => 2,
+            MessageType::Reset => 3,
+        };
+        let token_length = u8::try_from(self.token.len()).unwrap_or(0);
+        let mut out = Vec::with_capacity(4 + self.token.len() + self.payload.len() + 16);
+        out.push(VERSION << 6 | kind << 4 | token_length);
+        out.push(self.code);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.token);
+
+        let mut previous = 0;
+        for (number, value) in &self.options {
+            let (delta, delta_ext) = option_nibble(number - previous);
+            let (length, length_ext) = option_nibble(u16::try_from(value.len()).unwrap_or(u16::MAX));
+            out.push(delta << 4 | length);
+            out.extend_from_slice(&delta_ext);
+            out.extend_from_slice(&length_ext);
+            out.extend_from_slice(value);
+            previous = *number;
+        }
+        if !self.payload.is_empty() {
+            out.push(PAYLOAD_MARKER);
+            out.extend_from_slice(&self.payload);
+        }
+        out
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let malformed = |what: &str| PlcError::Protocol(format!("Malformed CoAP message: {what}"));
+        if data.len() < 4 {
+            return Err(malformed("shorter than the header"));
+        }
+        if data[0] >> 6 != VERSION {
+            return Err(malformed("unknown version"));
+        }
+        let kind = match (data[0] >> 4) & 0x03 {
+            0 => MessageType::Confirmable,
+            1 => MessageType::NonConfirmable,
+            2 => MessageType::Acknowledgement,
+            _ => MessageType::Reset,
+        };
+        let token_length = usize::from(data[0] & 0x0F);
+        if token_length > 8 || data.len() < 4 + token_length {
+            return Err(malformed("invalid token length"));
+        }
+        let mut message = Self {
+            kind,
+            code: data[1],
+            id: u16::from_be_bytes([data[2], data[3]]),
+            token: data[4..4 + token_length].to_vec(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+
+        let mut rest = &data[4 + token_length..];
+        let mut number = 0u16;
+        while let Some((&first, tail)) = rest.split_first() {
+            if first == PAYLOAD_MARKER {
+                if tail.is_empty() {
+                    return Err(malformed("payload marker without payload"));
+                }
+                message.payload = tail.to_vec();
+                break;
+            }
+            rest = tail;
+            let delta = read_extended(first >> 4, &mut rest).ok_or_else(|| malformed("invalid option delta"))?;
+            let length = read_extended(first & 0x0F, &mut rest).ok_or_else(|| malformed("invalid option length"))?;
+            number = number.checked_add(delta).ok_or_else(|| malformed("option number overflow"))?;
+            let length = usize::from(length);
+            if rest.len() < length {
+                return Err(malformed("truncated option"));
+            }
+            message.options.push((number, rest[..length].to_vec()));
+            rest = &rest[length..];
+        }
+        Ok(message)
+    }
+
+    fn option(&self, number: u16) -> Option<&[u8]> {
+        self.options.iter().find(|(n, _)| *n == number).map(|(_, v)| v.as_slice())
+    }
+
+    /// Value of an unsigned integer option
+    fn uint_option(&self, number: u16) -> Option<u32> {
+        self.option(number)
+            .filter(|v| v.len() <= 4)
+            .map(|v| v.iter().fold(0, |acc, &b| acc << 8 | u32::from(b)))
+    }
+}
+
+/// Nibble and extended bytes of an option delta or length
+fn option_nibble(value: u16) -> (u8, Vec<u8>) {
+    match value {
+        0..=12 => (value.to_be_bytes()[1], Vec::new()),
+        13..=268 => (13, vec![(value - 13).to_be_bytes()[1]]),
+        _ => (14, (value - 269).to_be_bytes().to_vec()),
+    }
+}
+
+fn read_extended(nibble: u8, rest: &mut &[u8]) -> Option<u16> {
+    match nibble {
+        0..=12 => Some(u16::from(nibble)),
+        13 => {
+            let (&byte, tail) = rest.split_first()?;
+            *rest = tail;
+            Some(u16::from(byte) + 13)
+        }
+        14 => {
+            let bytes = rest.get(..2)?;
+            let value = u16::from_be_bytes([bytes[0], bytes[1]]).checked_add(269)?;
+            *rest = &rest[2..];
+            Some(value)
+        }
+        _ => None,
+    }
+}
+
+/// Response code as written in RFC 7252, e.g. `4.04`
+fn code_name(code: u8) -> String {
+    format!("{}.{:02}", code >> 5, code & 0x1F)
+}
+
+const fn is_success(code: u8) -> bool {
+    code >> 5 == 2
+}
+
+/// Whether notification `next` received `elapsed` after `previous` is newer
+/// (RFC 7641 section 3.4)
+const fn is_fresh(previous: u32, next: u32, elapsed: Duration) -> bool {
+    (previous < next && next - previous < OBSERVE_WINDOW)
+        || (previous > next && previous - next > OBSERVE_WINDOW)
+        || elapsed.as_secs() > OBSERVE_REORDER_LIMIT.as_secs()
+}
+
+// ============================================================================
+// PAYLOADS
+// ============================================================================
+
+/// Value carried by a response payload
+fn parse_payload(payload: &[u8], format: Option<u32>, field: Option<&str>) -> Result<Value> {
+    let invalid = |what: String| PlcError::Protocol(format!("Unusable CoAP payload: {what}"));
+    let text = std::str::from_utf8(payload).map_err(|_| invalid("not UTF-8".to_string()))?.trim();
+    match format {
+        Some(FORMAT_JSON) => {
+            let json: serde_json::Value = serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?;
+            let member = field
+                .into_iter()
+                .flat_map(|f| f.split('.'))
+                .try_fold(&json, |value, key| value.get(key))
+                .ok_or_else(|| invalid(format!("no member '{}'", field.unwrap_or_default())))?;
+            match member {
+                serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
+                serde_json::Value::Number(n) => Ok(n.as_i64().map_or_else(|| Value::Float(n.as_f64().unwrap_or(f64::NAN)), Value::Integer)),
+                serde_json::Value::String(s) => parse_text(s).ok_or_else(|| invalid(format!("'{s}' is not a number"))),
+                other => Err(invalid(format!("{other} is not a scalar"))),
+            }
+        }
+        None | Some(FORMAT_TEXT) => parse_text(text).ok_or_else(|| invalid(format!("'{text}' is not a number"))),
+        Some(other) => Err(invalid(format!("content format {other} is not supported"))),
+    }
+}
+
+fn parse_text(text: &str) -> Option<Value> {
+    match text {
+        "true" | "on" => Some(Value::Bool(true)),
+        "false" | "off" => Some(Value::Bool(false)),
+        _ => text
+            .parse::<i64>()
+            .map(Value::Integer)
+            .or_else(|_| text.parse::<f64>().map(Value::Float))
+            .ok(),
+    }
+}
+
+/// Convert a received value to the type the signal holds
+fn convert(value: Value, current: Option<&Value>) -> Value {
+    let converted = match current {
+        Some(Value::Bool(_)) => value.as_bool().map(Value::Bool),
+        Some(Value::Integer(_)) => value.as_integer().map(Value::Integer),
+        Some(Value::Float(_)) => value.as_float().map(Value::Float),
+        _ => None,
+    };
+    converted.unwrap_or(value)
+}
+
+// ============================================================================
+// TRANSPORT
+// ============================================================================
+
+/// Largest datagram accepted from a device
+const MAX_DATAGRAM: usize = 1500;
+
+/// Record size limit for DTLS, leaving room for IPv6 and UDP headers
+const DTLS_MTU: u32 = 1280 - 48;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connected UDP socket as a stream carrying one datagram per read or write
+struct Datagram(UdpSocket);
+
+impl AsyncRead for Datagram {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        self.0.poll_recv(cx, buf)
+    }
+}
+
+impl AsyncWrite for Datagram {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.0.poll_send(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    Dtls(Pin<Box<SslStream<Datagram>>>),
+}
+
+fn dtls_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Protocol(format!("DTLS: {e}"))
+}
+
+impl CoapDtlsConfig {
+    fn client(&self) -> Result<Ssl> {
+        let identity = self.identity.clone().into_bytes();
+        let key = decode_hex(&self.key_hex)?;
+        let mut context = SslContext::builder(SslMethod::dtls_client()).map_err(dtls_error)?;
+        context.set_cipher_list(&self.ciphers).map_err(dtls_error)?;
+        context.set_options(SslOptions::NO_QUERY_MTU);
+        context.set_psk_client_callback(move |_, _hint, identity_out, key_out| {
+            // The identity is passed to OpenSSL as a C string
+            if identity.len() >= identity_out.len() || key.len() > key_out.len() {
+                return Err(ErrorStack::get());
+            }
+            identity_out[..identity.len()].copy_from_slice(&identity);
+            identity_out[identity.len()] = 0;
+            key_out[..key.len()].copy_from_slice(&key);
+            Ok(key.len())
+        });
+        let mut ssl = Ssl::new(&context.build()).map_err(dtls_error)?;
+        ssl.set_mtu(DTLS_MTU).map_err(dtls_error)?;
+        Ok(ssl)
+    }
+}
+
+impl Transport {
+    async fn connect(device: &CoapDeviceConfig) -> Result<Self> {
+        let address = resolve(&device.address, device.default_port()).await?;
+        let local: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(address).await?;
+
+        let Some(dtls) = &device.dtls else {
+            return Ok(Self::Udp(socket));
+        };
+        let mut stream = Box::pin(SslStream::new(dtls.client()?, Datagram(socket)).map_err(dtls_error)?);
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, stream.as_mut().connect())
+            .await
+            .map_err(|_| dtls_error("handshake timed out"))?
+            .map_err(dtls_error)?;
+        Ok(Self::Dtls(stream))
+    }
+
+    async fn send(&mut self, datagram: &[u8]) -> Result<()> {
+        match self {
+            Self::Udp(socket) => {
+                socket.send(datagram).await?;
+            }
+            Self::Dtls(stream) => stream.write_all(datagram).await?,
+        }
+        Ok(())
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            Self::Udp(socket) => Ok(socket.recv(buf).await?),
+            Self::Dtls(stream) => match stream.read(buf).await? {
+                0 => Err(dtls_error("device closed the session")),
+                len => Ok(len),
+            },
+        }
+    }
+}
+
+/// Resolve `address`, adding `default_port` when it has none
+async fn resolve(address: &str, default_port: u16) -> Result<SocketAddr> {
+    let with_port = if address.parse::<SocketAddr>().is_ok() || has_port(address) {
+        address.to_string()
+    } else if address.contains(':') {
+        format!("[{}]:{default_port}", address.trim_start_matches('[').trim_end_matches(']'))
+    } else {
+        format!("{address}:{default_port}")
+    };
+    tokio::net::lookup_host(with_port)
+        .await?
+        .next()
+        .ok_or_else(|| PlcError::Protocol(format!("CoAP address '{address}' did not resolve")))
+}
+
+/// Whether a host name or bracketed IPv6 address ends in a port
+fn has_port(address: &str) -> bool {
+    match address.rsplit_once(':') {
+        Some((host, port)) => {
+            port.parse::<u16>().is_ok() && (!host.contains(':') || host.ends_with(']'))
+        }
+        None => false,
+    }
+}
+
+// ============================================================================
+// SESSION
+// ============================================================================
+
+/// How often retransmissions and due requests are checked
+const TICK: Duration = Duration::from_millis(100);
+
+/// Confirmable message IDs remembered to detect duplicates
+const RECENT_MESSAGES: usize = 32;
+
+/// Registration or poll state of one resource
+struct ResourceState {
+    /// Token of the latest request, matching its responses and notifications
+    token: Vec<u8>,
+    /// A request is outstanding
+    waiting: bool,
+    next_request: Instant,
+    /// Last notification sequence and when it arrived
+    observed: Option<(u32, Instant)>,
+}
+
+/// Confirmable request awaiting its acknowledgement or separate response
+struct Exchange {
+    resource: usize,
+    message_id: u16,
+    datagram: Vec<u8>,
+    retransmissions: u32,
+    timeout: Duration,
+    deadline: Instant,
+    /// Acknowledged with an empty ACK; the response follows separately
+    acknowledged: bool,
+}
+
+/// Message exchange with one device, independent of the transport
+struct Session<'a> {
+    device: &'a CoapDeviceConfig,
+    bus: &'a SignalBus,
+    resources: Vec<ResourceState>,
+    exchanges: Vec<Exchange>,
+    next_message_id: u16,
+    recent: VecDeque<u16>,
+}
+
+impl<'a> Session<'a> {
+    fn new(device: &'a CoapDeviceConfig, bus: &'a SignalBus, now: Instant) -> Self {
+        Self {
+            device,
+            bus,
+            resources: device
+                .resources
+                .iter()
+                .map(|_| ResourceState {
+                    token: Vec::new(),
+                    waiting: false,
+                    next_request: now,
+                    observed: None,
+                })
+                .collect(),
+            exchanges: Vec::new(),
+            next_message_id: rand::random(),
+            recent: VecDeque::with_capacity(RECENT_MESSAGES),
+        }
+    }
+
+    fn ack_timeout(&self) -> Duration {
+        Duration::from_millis(self.device.ack_timeout_ms)
+    }
+
+    /// Time to wait for a separate response after an empty acknowledgement
+    /// (RFC 7252 `MAX_TRANSMIT_WAIT`)
+    fn max_transmit_wait(&self) -> Duration {
+        self.ack_timeout() * ((2 << self.device.max_retransmit) - 1) * 3 / 2
+    }
+
+    fn poll_interval(&self, index: usize) -> Duration {
+        Duration::from_millis(self.device.resources[index].poll_interval_ms)
+    }
+
+    /// GET requests for resources whose registration or poll is due
+    fn due_requests(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        let mut requests = Vec::new();
+        for index in 0..self.resources.len() {
+            let state = &self.resources[index];
+            if state.waiting || state.next_request > now {
+                continue;
+            }
+            let resource = &self.device.resources[index];
+            let (segments, queries) = split_path(&resource.path);
+            let mut options = Vec::new();
+            if resource.observe {
+                options.push((OPTION_OBSERVE, Vec::new()));
+            }
+            options.extend(segments.iter().map(|s| (OPTION_URI_PATH, s.as_bytes().to_vec())));
+            options.extend(queries.iter().map(|q| (OPTION_URI_QUERY, q.as_bytes().to_vec())));
+
+            // A new token per request, so notifications of an earlier
+            // registration are reset and cancelled by the device
+            let token = rand::random::<[u8; 4]>().to_vec();
+            let message_id = self.next_message_id;
+            self.next_message_id = self.next_message_id.wrapping_add(1);
+            let datagram = Message {
+                kind: MessageType::Confirmable,
+                code: CODE_GET,
+                id: message_id,
+                token: token.clone(),
+                options,
+                payload: Vec::new(),
+            }
+            .encode();
+
+            let timeout = self.ack_timeout().mul_f64(rand::random::<f64>().mul_add(0.5, 1.0));
+            self.exchanges.push(Exchange {
+                resource: index,
+                message_id,
+                datagram: datagram.clone(),
+                retransmissions: 0,
+                timeout,
+                deadline: now + timeout,
+                acknowledged: false,
+            });
+            let state = &mut self.resources[index];
+            state.token = token;
+            state.waiting = true;
+            requests.push(datagram);
+        }
+        requests
+    }
+
+    /// Requests whose acknowledgement is overdue
+    ///
+    /// # Errors
+    ///
+    /// Fails once a request exhausts its retransmissions.
+    fn retransmissions(&mut self, now: Instant) -> Result<Vec<Vec<u8>>> {
+        let mut datagrams = Vec::new();
+        let mut expired = Vec::new();
+        for (position, exchange) in self.exchanges.iter_mut().enumerate() {
+            if exchange.deadline > now {
+                continue;
+            }
+            if exchange.acknowledged {
+                expired.push(position);
+            } else if exchange.retransmissions < self.device.max_retransmit {
+                exchange.retransmissions += 1;
+                exchange.timeout *= 2;
+                exchange.deadline = now + exchange.timeout;
+                datagrams.push(exchange.datagram.clone());
+            } else {
+                return Err(PlcError::Protocol(format!(
+                    "'{}' did not acknowledge '{}'",
+                    self.device.name, self.device.resources[exchange.resource].path
+                )));
+            }
+        }
+        for position in expired.into_iter().rev() {
+            let exchange = self.exchanges.remove(position);
+            debug!(
+                "CoAP device '{}' never sent the response for '{}'",
+                self.device.name, self.device.resources[exchange.resource].path
+            );
+            self.retry_later(exchange.resource, now);
+        }
+        Ok(datagrams)
+    }
+
+    fn retry_later(&mut self, index: usize, now: Instant) {
+        let next_request = now + self.poll_interval(index);
+        let state = &mut self.resources[index];
+        state.waiting = false;
+        state.observed = None;
+        state.next_request = next_request;
+    }
+
+    /// Process a received datagram, returning the replies to send
+    fn handle(&mut self, datagram: &[u8], now: Instant) -> Vec<Vec<u8>> {
+        let message = match Message::decode(datagram) {
+            Ok(message) => message,
+            Err(e) => {
+                debug!("CoAP device '{}' sent an unusable datagram: {}", self.device.name, e);
+                return Vec::new();
+            }
+        };
+
+        match message.kind {
+            MessageType::Acknowledgement | MessageType::Reset => {
+                let Some(position) = self.exchanges.iter().position(|e| e.message_id == message.id) else {
+                    return Vec::new();
+                };
+                if message.kind == MessageType::Reset {
+                    let exchange = self.exchanges.remove(position);
+                    warn!(
+                        "CoAP device '{}' reset the request for '{}'",
+                        self.device.name, self.device.resources[exchange.resource].path
+                    );
+                    self.retry_later(exchange.resource, now);
+                } else if message.code == CODE_EMPTY {
+                    let wait = self.max_transmit_wait();
+                    let exchange = &mut self.exchanges[position];
+                    exchange.acknowledged = true;
+                    exchange.deadline = now + wait;
+                } else {
+                    let exchange = self.exchanges.remove(position);
+                    if self.resources[exchange.resource].token == message.token {
+                        self.response(exchange.resource, &message, now);
+                    }
+                }
+                Vec::new()
+            }
+            MessageType::Confirmable | MessageType::NonConfirmable => {
+                let confirmable = message.kind == MessageType::Confirmable;
+                let ack = Message::empty(MessageType::Acknowledgement, message.id).encode();
+                if confirmable && self.recent.contains(&message.id) {
+                    return vec![ack];
+                }
+                let resource = (!message.token.is_empty())
+                    .then(|| self.resources.iter().position(|r| r.token == message.token))
+                    .flatten();
+                let Some(index) = resource else {
+                    // Unknown token: ends an observation we no longer hold
+                    return vec![Message::empty(MessageType::Reset, message.id).encode()];
+                };
+                self.exchanges.retain(|e| e.resource != index);
+                self.response(index, &message, now);
+                if !confirmable {
+                    return Vec::new();
+                }
+                if self.recent.len() == RECENT_MESSAGES {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(message.id);
+                vec![ack]
+            }
+        }
+    }
+
+    /// Apply a response or notification to resource `index`
+    fn response(&mut self, index: usize, message: &Message, now: Instant) {
+        let resource = &self.device.resources[index];
+        if !is_success(message.code) {
+            warn!(
+                "CoAP device '{}' answered {} for '{}'",
+                self.device.name,
+                code_name(message.code),
+                resource.path
+            );
+            self.retry_later(index, now);
+            return;
+        }
+        self.set_online(true);
+
+        let sequence = message.uint_option(OPTION_OBSERVE).filter(|_| resource.observe);
+        let state = &mut self.resources[index];
+        state.waiting = false;
+        if let Some(sequence) = sequence {
+            if let Some((previous, received)) = state.observed {
+                if !is_fresh(previous, sequence, now - received) {
+                    debug!("Skipping reordered notification for '{}'", resource.path);
+                    return;
+                }
+            }
+            let max_age = message.uint_option(OPTION_MAX_AGE).map_or(DEFAULT_MAX_AGE, u64::from);
+            state.observed = Some((sequence, now));
+            state.next_request = now + Duration::from_secs(max_age) + OBSERVE_MARGIN;
+        } else {
+            if resource.observe {
+                debug!("'{}' cannot be observed, polling it", resource.path);
+            }
+            state.observed = None;
+            state.next_request = now + Duration::from_millis(resource.poll_interval_ms);
+        }
+
+        let format = message.uint_option(OPTION_CONTENT_FORMAT);
+        match parse_payload(&message.payload, format, resource.field.as_deref()) {
+            Ok(value) => {
+                let value = convert(value, self.bus.get(&resource.signal).as_ref());
+                if let Err(e) = self.bus.set(&resource.signal, value) {
+                    warn!("CoAP could not write '{}': {}", resource.signal, e);
+                }
+            }
+            Err(e) => warn!("CoAP device '{}' resource '{}': {}", self.device.name, resource.path, e),
+        }
+    }
+
+    fn set_online(&self, online: bool) {
+        if let Some(signal) = &self.device.online_signal {
+            if self.bus.get(signal) != Some(Value::Bool(online)) {
+                if let Err(e) = self.bus.set(signal, Value::Bool(online)) {
+                    warn!("CoAP could not write '{}': {}", signal, e);
+                }
+            }
+        }
+    }
+
+    /// Exchange messages until the device stops answering
+    async fn exchange(&mut self, transport: &mut Transport) -> Result<()> {
+        let mut buf = vec![0; MAX_DATAGRAM];
+        let mut ticker = tokio::time::interval(TICK);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            let datagrams = tokio::select! {
+                received = transport.recv(&mut buf) => {
+                    let len = received?;
+                    scan_budget::measure(Subsystem::Protocols, || self.handle(&buf[..len], Instant::now()))
+                }
+                _ = ticker.tick() => {
+                    let now = Instant::now();
+                    let mut datagrams = self.retransmissions(now)?;
+                    datagrams.extend(self.due_requests(now));
+                    datagrams
+                }
+            };
+            for datagram in datagrams {
+                transport.send(&datagram).await?;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// CLIENT
+// ============================================================================
+
+/// Read one device until the task is cancelled, re-establishing the session
+/// after errors
+async fn run_device(device: CoapDeviceConfig, bus: SignalBus) {
+    let delay = Duration::from_millis(device.reconnect_delay_ms);
+    loop {
+        let mut session = Session::new(&device, &bus, Instant::now());
+        match Transport::connect(&device).await {
+            Ok(mut transport) => {
+                info!("CoAP device '{}' session started with {}", device.name, device.address);
+                if let Err(e) = session.exchange(&mut transport).await {
+                    warn!("CoAP device '{}' session ended: {}", device.name, e);
+                }
+            }
+            Err(e) => warn!("CoAP device '{}' at {} is unreachable: {}", device.name, device.address, e),
+        }
+        session.set_online(false);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Read every configured device until the task is cancelled
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the configuration is invalid.
+pub async fn run(config: CoapConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    info!("CoAP client reading {} devices", config.devices.len());
+    let devices: Vec<_> = config
+        .devices
+        .into_iter()
+        .map(|device| tokio::spawn(run_device(device, bus.clone())))
+        .collect();
+    for device in devices {
+        device.await.map_err(|e| PlcError::Runtime(format!("CoAP device task failed: {e}")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+/// Minimal big-endian encoding of an unsigned option value
+fn uint_bytes(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+    fn device(address: String, dtls: Option<CoapDtlsConfig>) -> CoapDeviceConfig {
+        serde_yaml::from_str::<CoapDeviceConfig>(&format!(
+            r#"
+name: probe
+address: "{address}"
+online_signal: probe.online
+ack_timeout_ms: 200
+resources:
+  - path: sensors/temp
+    signal: probe.temp
+"#
+        ))
+        .map(|device| CoapDeviceConfig { dtls, ..device })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        let message = Message {
+            kind: MessageType::Confirmable,
+            code: CODE_GET,
+            id: 0x1234,
+            token: vec![1, 2, 3, 4],
+            options: vec![
+                (OPTION_OBSERVE, Vec::new()),
+                (OPTION_URI_PATH, b"sensors".to_vec()),
+                (OPTION_URI_PATH, vec![b'x'; 20]),
+                (OPTION_URI_QUERY, b"unit=c".to_vec()),
+                (2048, vec![7]),
+            ],
+            payload: b"21.5".to_vec(),
+        };
+        let encoded = message.encode();
+        assert_eq!(&encoded[..4], &[0x44, 0x01, 0x12, 0x34]);
+        // Observe (6) with no value, then Uri-Path with delta 5
+        assert_eq!(&encoded[8..10], &[0x60, 0x57]);
+        assert_eq!(Message::decode(&encoded).unwrap(), message);
+
+        assert_eq!(uint_bytes(0), Vec::<u8>::new());
+        assert_eq!(uint_bytes(300), vec![1, 44]);
+        assert!(Message::decode(&[0x44, 0x01, 0x00]).is_err());
+        assert!(Message::decode(&[0x40, 0x45, 0x00, 0x01, 0xFF]).is_err());
+        assert_eq!(code_name(0x84), "4.04");
+
+        assert_eq!(parse_payload(b" 21.5\n", None, None).unwrap(), Value::Float(21.5));
+        let json = br#"{"env":{"humidity":48,"ok":true}}"#;
+        assert_eq!(parse_payload(json, Some(FORMAT_JSON), Some("env.humidity")).unwrap(), Value::Integer(48));
+        assert!(parse_payload(json, Some(FORMAT_JSON), Some("env.missing")).is_err());
+        assert_eq!(convert(Value::Integer(48), Some(&Value::Float(0.0))), Value::Float(48.0));
+
+        assert!(is_fresh(5, 6, Duration::ZERO));
+        assert!(!is_fresh(6, 5, Duration::ZERO));
+        assert!(is_fresh(0xFF_FFFF, 1, Duration::ZERO));
+        assert!(is_fresh(6, 5, Duration::from_secs(200)));
+        assert!(has_port("probe.local:5683") && has_port("[::1]:5683"));
+        assert!(!has_port("probe.local") && !has_port("fd00::12"));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = CoapConfig {
+            devices: vec![device("127.0.0.1".to_string(), None)],
+        };
+        config.validate().unwrap();
+
+        config.devices[0].dtls = Some(CoapDtlsConfig {
+            identity: "probe".to_string(),
+            key_hex: "abc".to_string(),
+            ciphers: default_ciphers(),
+        });
+        assert!(config.validate().is_err());
+        config.devices[0].dtls.as_mut().unwrap().key_hex = "00112233".to_string();
+        config.validate().unwrap();
+        assert_eq!(config.devices[0].default_port(), 5684);
+
+        config.devices.push(config.devices[0].clone());
+        assert!(config.validate().is_err());
+    }
+
+    /// Answer the registration, then push a confirmable notification and
+    /// return the acknowledgement it received
+    async fn observe_once<S: AsyncRead + AsyncWrite + Unpin>(server: &mut S) -> Message {
+        let mut buf = vec![0; MAX_DATAGRAM];
+        let len = server.read(&mut buf).await.unwrap();
+        let request = Message::decode(&buf[..len]).unwrap();
+        assert_eq!(request.code, CODE_GET);
+        assert_eq!(request.option(OPTION_OBSERVE), Some(&[][..]));
+        assert_eq!(request.option(OPTION_URI_PATH), Some(&b"sensors"[..]));
+
+        let mut response = Message {
+            kind: MessageType::Acknowledgement,
+            code: 0x45,
+            id: request.id,
+            token: request.token.clone(),
+            options: vec![(OPTION_OBSERVE, uint_bytes(1))],
+            payload: b"21".to_vec(),
+        };
+        server.write_all(&response.encode()).await.unwrap();
+
+        response.kind = MessageType::Confirmable;
+        response.id = 7;
+        response.options = vec![(OPTION_OBSERVE, uint_bytes(2)), (OPTION_CONTENT_FORMAT, uint_bytes(FORMAT_TEXT))];
+        response.payload = b"22.5".to_vec();
+        server.write_all(&response.encode()).await.unwrap();
+
+        let len = server.read(&mut buf).await.unwrap();
+        Message::decode(&buf[..len]).unwrap()
+    }
+
+    async fn wait_for(bus: &SignalBus, signal: &str, value: Value) {
+        for _ in 0..100 {
+            if bus.get(signal) == Some(value.clone()) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{signal} never became {value:?}, is {:?}", bus.get(signal));
+    }
+
+    #[tokio::test]
+    async fn test_observe_over_dtls_psk() {
+        let key = "0123456789abcdef0123456789abcdef";
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dtls = CoapDtlsConfig {
+            identity: "probe".to_string(),
+            key_hex: key.to_string(),
+            ciphers: default_ciphers(),
+        };
+        let device = device(server.local_addr().unwrap().to_string(), Some(dtls));
+        let bus = SignalBus::new();
+        bus.set("probe.temp", Value::Float(0.0)).unwrap();
+        let client = tokio::spawn(run_device(device, bus.clone()));
+
+        // Follow the client's address, then accept its handshake
+        let (_, peer) = server.peek_from(&mut [0; MAX_DATAGRAM]).await.unwrap();
+        server.connect(peer).await.unwrap();
+        let mut context = SslContext::builder(SslMethod::dtls_server()).unwrap();
+        context.set_cipher_list(&default_ciphers()).unwrap();
+        context.set_psk_server_callback(move |_, identity, key_out| {
+            assert_eq!(identity, Some(&b"probe"[..]));
+            let key = decode_hex(key).unwrap();
+            key_out[..key.len()].copy_from_slice(&key);
+            Ok(key.len())
+        });
+        let ssl = Ssl::new(&context.build()).unwrap();
+        let mut stream = Box::pin(SslStream::new(ssl, Datagram(server)).unwrap());
+        stream.as_mut().accept().await.unwrap();
+
+        let ack = observe_once(&mut stream).await;
+        assert_eq!(ack, Message::empty(MessageType::Acknowledgement, 7));
+        wait_for(&bus, "probe.temp", Value::Float(22.5)).await;
+        assert_eq!(bus.get("probe.online"), Some(Value::Bool(true)));
+
+        // A notification for a token the client does not hold is reset
+        let stale = Message {
+            kind: MessageType::NonConfirmable,
+            code: 0x45,
+            id: 8,
+            token: vec![9; 4],
+            options: Vec::new(),
+            payload: b"1".to_vec(),
+        };
+        stream.write_all(&stale.encode()).await.unwrap();
+        let mut buf = vec![0; MAX_DATAGRAM];
+        let len = stream.read(&mut buf).await.unwrap();
+        assert_eq!(Message::decode(&buf[..len]).unwrap(), Message::empty(MessageType::Reset, 8));
+        client.abort();
+    }
+}
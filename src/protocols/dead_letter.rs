@@ -0,0 +1,610 @@
+// src/protocols/dead_letter.rs
+//! Dead-letter queue for protocol writes that keep failing
+//!
+//! With a queue attached to the [`ProtocolManager`], an address whose writes
+//! fail `after_failures` times in a row is taken over by the queue: the
+//! value becomes a dead letter, and later writes to the address replace the
+//! held value instead of going to the device. The queue retries the letter
+//! with exponential backoff and delivers the latest value once a write goes
+//! through. After `max_retries` failed retries the letter is dead: it is no
+//! longer retried on its own, a `write_dead_lettered` event is published,
+//! and it waits for an operator to retry or discard it through
+//! `/api/dead-letters`.
+//!
+//! ```yaml
+//! protocols:
+//!   dead_letters:
+//!     path: /var/lib/petra/dead_letters.json
+//!     after_failures: 3
+//!     max_retries: 5
+//!     retry_initial_ms: 1000
+//!     retry_max_ms: 60000
+//! ```
+//!
+//! Letters are saved to `path` on every change and loaded again at start,
+//! so writes held while PETRA restarts are not lost.
+
+use super::ProtocolManager;
+use crate::error::{PlcError, Result};
+use crate::events::EventKind;
+use crate::value::Value;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Dead-letter queue settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterConfig {
+    /// JSON file the letters are persisted to; kept in memory only if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// Consecutive failed writes of an address before it is dead-lettered
+    #[serde(default = "default_after_failures")]
+    pub after_failures: u32,
+
+    /// Retries by the queue before a letter waits for an operator
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for every further retry
+    #[serde(default = "default_retry_initial_ms")]
+    pub retry_initial_ms: u64,
+
+    /// Longest delay between retries
+    #[serde(default = "default_retry_max_ms")]
+    pub retry_max_ms: u64,
+
+    /// Letters kept; the oldest is dropped when a new one does not fit
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+}
+
+const fn default_after_failures() -> u32 {
+    3
+}
+
+const fn default_max_retries() -> u32 {
+    5
+}
+
+const fn default_retry_initial_ms() -> u64 {
+    1000
+}
+
+const fn default_retry_max_ms() -> u64 {
+    60_000
+}
+
+const fn default_capacity() -> usize {
+    1000
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            after_failures: default_after_failures(),
+            max_retries: default_max_retries(),
+            retry_initial_ms: default_retry_initial_ms(),
+            retry_max_ms: default_retry_max_ms(),
+            capacity: default_capacity(),
+        }
+    }
+}
+
+impl DeadLetterConfig {
+    /// Validate the queue settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for a zero failure count, retry delay
+    /// or capacity, or a maximum delay below the initial one.
+    pub fn validate(&self) -> Result<()> {
+        if self.after_failures == 0 || self.retry_initial_ms == 0 || self.capacity == 0 {
+            return Err(PlcError::Config(
+                "Dead letters need a non-zero after_failures, retry_initial_ms and capacity".to_string(),
+            ));
+        }
+        if self.retry_max_ms < self.retry_initial_ms {
+            return Err(PlcError::Config(format!(
+                "Dead letter retry_max_ms ({}) is below retry_initial_ms ({})",
+                self.retry_max_ms, self.retry_initial_ms
+            )));
+        }
+        Ok(())
+    }
+
+    /// Delay before retry number `retry`, counting from zero
+    fn backoff(&self, retry: u32) -> chrono::Duration {
+        let ms = self.retry_initial_ms.saturating_mul(1 << retry.min(20)).min(self.retry_max_ms);
+        chrono::Duration::milliseconds(i64::try_from(ms).unwrap_or(i64::MAX))
+    }
+}
+
+// ============================================================================
+// LETTERS
+// ============================================================================
+
+/// Whether the queue still retries a letter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LetterState {
+    /// Retried at `next_retry`
+    Retrying,
+    /// Retries exhausted; waits for an operator
+    Dead,
+}
+
+/// A write held by the queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Identifier used by the REST endpoints
+    pub id: u64,
+    /// Driver or failover group written to
+    pub protocol: String,
+    /// Protocol address written
+    pub address: String,
+    /// Latest value written to the address
+    pub value: Value,
+    /// Error of the last failed attempt
+    pub error: String,
+    /// Failed attempts, including those before the address was dead-lettered
+    pub attempts: u32,
+    /// When the first failed attempt was made
+    pub first_failed: DateTime<Utc>,
+    /// When the last failed attempt was made
+    pub last_failed: DateTime<Utc>,
+    /// When the queue retries next; unset once the letter is dead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_retry: Option<DateTime<Utc>>,
+    /// Whether the letter is still retried
+    pub state: LetterState,
+}
+
+/// Letters and the failures of addresses not dead-lettered yet
+#[derive(Default)]
+struct Letters {
+    by_id: BTreeMap<u64, DeadLetter>,
+    /// Consecutive failures and the time of the first one
+    failures: HashMap<(String, String), (u32, DateTime<Utc>)>,
+    next_id: u64,
+}
+
+impl Letters {
+    fn find_mut(&mut self, protocol: &str, address: &str) -> Option<&mut DeadLetter> {
+        self.by_id.values_mut().find(|l| l.protocol == protocol && l.address == address)
+    }
+}
+
+/// Dead-lettered writes, shared by a [`ProtocolManager`] and the REST API
+pub struct DeadLetterQueue {
+    config: DeadLetterConfig,
+    letters: Mutex<Letters>,
+}
+
+impl DeadLetterQueue {
+    /// Create the queue, loading the letters persisted at `config.path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or an existing
+    /// letter file cannot be read.
+    pub fn open(config: DeadLetterConfig) -> Result<Self> {
+        config.validate()?;
+        let mut letters = Letters::default();
+        if let Some(path) = config.path.as_ref().filter(|p| p.exists()) {
+            let saved: Vec<DeadLetter> = serde_json::from_slice(&std::fs::read(path)?).map_err(|e| {
+                PlcError::Config(format!("Cannot read dead letters from {}: {}", path.display(), e))
+            })?;
+            letters.next_id = saved.iter().map(|l| l.id + 1).max().unwrap_or_default();
+            letters.by_id = saved.into_iter().map(|l| (l.id, l)).collect();
+            if !letters.by_id.is_empty() {
+                tracing::info!("Loaded {} dead letters from {}", letters.by_id.len(), path.display());
+            }
+        }
+        Ok(Self { config, letters: Mutex::new(letters) })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Letters> {
+        self.letters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Write the letters through a temporary sibling of the letter file
+    fn save(&self, letters: &Letters) {
+        let Some(path) = &self.config.path else {
+            return;
+        };
+        let write = || -> Result<()> {
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".tmp");
+            let all: Vec<&DeadLetter> = letters.by_id.values().collect();
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&all)?)?;
+            std::fs::rename(&tmp, path)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            tracing::error!("Cannot save dead letters to {}: {}", path.display(), e);
+        }
+    }
+
+    /// All letters, oldest first
+    #[must_use]
+    pub fn letters(&self) -> Vec<DeadLetter> {
+        self.lock().by_id.values().cloned().collect()
+    }
+
+    /// The letter with the given id
+    #[must_use]
+    pub fn get(&self, id: u64) -> Option<DeadLetter> {
+        self.lock().by_id.get(&id).cloned()
+    }
+
+    /// Drop a letter without writing it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] for an unknown letter.
+    pub fn discard(&self, id: u64) -> Result<DeadLetter> {
+        let mut letters = self.lock();
+        let letter = letters
+            .by_id
+            .remove(&id)
+            .ok_or_else(|| PlcError::NotFound(format!("Dead letter {id}")))?;
+        self.save(&letters);
+        tracing::info!("Discarded dead letter {}: {} of {}", id, letter.address, letter.protocol);
+        Ok(letter)
+    }
+
+    /// Take the new values of dead-lettered addresses, returning the
+    /// values still to be written
+    pub(super) fn hold(&self, protocol: &str, values: &HashMap<String, Value>) -> HashMap<String, Value> {
+        let mut letters = self.lock();
+        if letters.by_id.is_empty() {
+            return values.clone();
+        }
+        let mut pending = HashMap::new();
+        let mut held = false;
+        for (address, value) in values {
+            match letters.find_mut(protocol, address) {
+                Some(letter) => {
+                    letter.value = value.clone();
+                    held = true;
+                }
+                None => {
+                    pending.insert(address.clone(), value.clone());
+                }
+            }
+        }
+        if held {
+            self.save(&letters);
+        }
+        pending
+    }
+
+    /// Count a failed write, dead-lettering addresses that failed too often
+    pub(super) fn record_failure(
+        &self,
+        protocol: &str,
+        values: &HashMap<String, Value>,
+        error: &str,
+        now: DateTime<Utc>,
+    ) {
+        let mut letters = self.lock();
+        let mut added = false;
+        for (address, value) in values {
+            let key = (protocol.to_string(), address.clone());
+            let (attempts, first_failed) = {
+                let entry = letters.failures.entry(key.clone()).or_insert((0, now));
+                entry.0 += 1;
+                *entry
+            };
+            if attempts < self.config.after_failures {
+                continue;
+            }
+            letters.failures.remove(&key);
+            if letters.by_id.len() >= self.config.capacity {
+                if let Some((_, dropped)) = letters.by_id.pop_first() {
+                    tracing::warn!(
+                        "Dead-letter queue full, dropped write of {} to {} {}",
+                        dropped.value, dropped.protocol, dropped.address
+                    );
+                }
+            }
+            let id = letters.next_id;
+            letters.next_id += 1;
+            tracing::warn!(
+                "Write of {} to {} {} failed {} times, held for retry as dead letter {}: {}",
+                value, protocol, address, attempts, id, error
+            );
+            letters.by_id.insert(
+                id,
+                DeadLetter {
+                    id,
+                    protocol: protocol.to_string(),
+                    address: address.clone(),
+                    value: value.clone(),
+                    error: error.to_string(),
+                    attempts,
+                    first_failed,
+                    last_failed: now,
+                    next_retry: Some(now + self.config.backoff(0)),
+                    state: LetterState::Retrying,
+                },
+            );
+            added = true;
+        }
+        if added {
+            self.save(&letters);
+        }
+    }
+
+    /// Reset the failure count of written addresses
+    pub(super) fn record_success(&self, protocol: &str, values: &HashMap<String, Value>) {
+        let mut letters = self.lock();
+        if !letters.failures.is_empty() {
+            for address in values.keys() {
+                letters.failures.remove(&(protocol.to_string(), address.clone()));
+            }
+        }
+    }
+
+    /// Letters whose retry is due
+    pub(super) fn due(&self, now: DateTime<Utc>) -> Vec<DeadLetter> {
+        self.lock()
+            .by_id
+            .values()
+            .filter(|l| l.state == LetterState::Retrying && l.next_retry.is_some_and(|t| t <= now))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove a letter whose value was written, unless a newer value was
+    /// held in the meantime
+    pub(super) fn delivered(&self, letter: &DeadLetter) {
+        let mut letters = self.lock();
+        if letters.by_id.get(&letter.id).is_some_and(|l| l.value == letter.value) {
+            letters.by_id.remove(&letter.id);
+            self.save(&letters);
+            tracing::info!("Dead letter {} delivered: {} to {} {}", letter.id, letter.value, letter.protocol, letter.address);
+        }
+    }
+
+    /// Count a failed retry, returning the letter if its retries just ran out
+    pub(super) fn retry_failed(&self, id: u64, error: &str, now: DateTime<Utc>) -> Option<DeadLetter> {
+        let mut letters = self.lock();
+        let letter = letters.by_id.get_mut(&id)?;
+        letter.attempts += 1;
+        letter.error = error.to_string();
+        letter.last_failed = now;
+        let mut dead = None;
+        if letter.state == LetterState::Retrying {
+            let retries = letter.attempts.saturating_sub(self.config.after_failures);
+            if retries >= self.config.max_retries {
+                letter.state = LetterState::Dead;
+                letter.next_retry = None;
+                dead = Some(letter.clone());
+            } else {
+                letter.next_retry = Some(now + self.config.backoff(retries));
+            }
+        }
+        self.save(&letters);
+        dead
+    }
+
+    /// How often the retry task looks for due letters
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.config.retry_initial_ms.min(1000))
+    }
+}
+
+// ============================================================================
+// DELIVERY
+// ============================================================================
+
+impl ProtocolManager {
+    /// Write a held value to its device
+    async fn deliver(&self, queue: &DeadLetterQueue, letter: &DeadLetter) -> Result<()> {
+        let values = HashMap::from([(letter.address.clone(), letter.value.clone())]);
+        match self.write_routed(&letter.protocol, &values).await {
+            Ok(()) => {
+                queue.delivered(letter);
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(dead) = queue.retry_failed(letter.id, &e.to_string(), Utc::now()) {
+                    tracing::error!(
+                        "Write of {} to {} {} failed {} times, giving up until an operator retries dead letter {}",
+                        dead.value, dead.protocol, dead.address, dead.attempts, dead.id
+                    );
+                    self.publish(EventKind::WriteDeadLettered {
+                        protocol: dead.protocol,
+                        address: dead.address,
+                        value: dead.value,
+                        error: dead.error,
+                        attempts: dead.attempts,
+                    });
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Write a dead letter to its device now
+    ///
+    /// On success the letter is removed; dead letters are retried too.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] without a queue or for an unknown
+    /// letter, or the error of the failed write.
+    pub async fn retry_dead_letter(&self, id: u64) -> Result<()> {
+        let queue = self
+            .dead_letters
+            .as_ref()
+            .ok_or_else(|| PlcError::NotFound("Dead letters are not configured".to_string()))?;
+        let letter = queue.get(id).ok_or_else(|| PlcError::NotFound(format!("Dead letter {id}")))?;
+        self.deliver(queue, &letter).await
+    }
+
+    /// Retry the letters that are due, returning how many were delivered
+    pub async fn retry_due_dead_letters(&self) -> usize {
+        let Some(queue) = &self.dead_letters else {
+            return 0;
+        };
+        let mut delivered = 0;
+        for letter in queue.due(Utc::now()) {
+            if self.deliver(queue, &letter).await.is_ok() {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+}
+
+/// Retry due letters on their own task, if the manager has a queue
+#[must_use]
+pub fn spawn_retries(manager: &Arc<ProtocolManager>) -> Option<tokio::task::JoinHandle<()>> {
+    let interval = manager.dead_letters.as_ref()?.poll_interval();
+    let manager = Arc::clone(manager);
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            manager.retry_due_dead_letters().await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventLog;
+    use crate::protocols::ProtocolDriver;
+    use crate::signal::SignalBus;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Device whose writes fail while `failing` is set
+    struct FlakyDriver {
+        failing: Arc<AtomicBool>,
+        writes: Arc<Mutex<Vec<HashMap<String, Value>>>>,
+    }
+
+    #[async_trait]
+    impl ProtocolDriver for FlakyDriver {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn read_values(&self, _addresses: &[String]) -> Result<HashMap<String, Value>> {
+            Ok(HashMap::new())
+        }
+
+        async fn write_values(&mut self, values: &HashMap<String, Value>) -> Result<()> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(PlcError::Protocol("device busy".to_string()));
+            }
+            self.writes.lock().unwrap().push(values.clone());
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn protocol_name(&self) -> &'static str {
+            "flaky"
+        }
+    }
+
+    fn write(address: &str, value: i64) -> HashMap<String, Value> {
+        HashMap::from([(address.to_string(), Value::Integer(value))])
+    }
+
+    #[tokio::test]
+    async fn test_failing_writes_are_held_retried_and_delivered() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let events = EventLog::default();
+        let config = DeadLetterConfig { after_failures: 2, max_retries: 1, retry_initial_ms: 1, ..Default::default() };
+        let manager = ProtocolManager::new(SignalBus::new())
+            .with_events(events.clone())
+            .with_dead_letters(Arc::new(DeadLetterQueue::open(config).unwrap()));
+        let driver = FlakyDriver { failing: failing.clone(), writes: writes.clone() };
+        manager.add_driver("plc".to_string(), Box::new(driver)).await.unwrap();
+        let queue = manager.dead_letters().unwrap().clone();
+
+        assert!(manager.write_to("plc", &write("HR1", 1)).await.is_err());
+        assert!(queue.letters().is_empty());
+        assert!(manager.write_to("plc", &write("HR1", 2)).await.is_err());
+        let letter = queue.letters().pop().unwrap();
+        assert_eq!((letter.attempts, letter.state), (2, LetterState::Retrying));
+
+        // The queue owns the address now: new values replace the held one
+        manager.write_to("plc", &write("HR1", 3)).await.unwrap();
+        assert_eq!(queue.get(letter.id).unwrap().value, Value::Integer(3));
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(manager.retry_due_dead_letters().await, 0);
+        let letter = queue.get(letter.id).unwrap();
+        assert_eq!((letter.attempts, letter.state, letter.next_retry), (3, LetterState::Dead, None));
+        let kinds: Vec<_> = events.since(0).iter().map(|e| e.kind.name()).collect();
+        assert_eq!(kinds, ["write_dead_lettered"]);
+
+        // Dead letters wait for an operator
+        failing.store(false, Ordering::SeqCst);
+        assert_eq!(manager.retry_due_dead_letters().await, 0);
+        manager.retry_dead_letter(letter.id).await.unwrap();
+        assert!(queue.letters().is_empty());
+        assert_eq!(*writes.lock().unwrap(), [write("HR1", 3)]);
+        manager.write_to("plc", &write("HR1", 4)).await.unwrap();
+        assert_eq!(writes.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_letters_are_persisted_and_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DeadLetterConfig {
+            path: Some(dir.path().join("dead_letters.json")),
+            after_failures: 1,
+            capacity: 2,
+            ..Default::default()
+        };
+        let queue = DeadLetterQueue::open(config.clone()).unwrap();
+        let now = Utc::now();
+        for (address, value) in [("HR1", 1), ("HR2", 2), ("HR3", 3)] {
+            queue.record_failure("plc", &write(address, value), "timeout", now);
+        }
+        let letters = queue.letters();
+        let addresses: Vec<_> = letters.iter().map(|l| l.address.as_str()).collect();
+        assert_eq!(addresses, ["HR2", "HR3"]);
+        assert_eq!(letters[0].next_retry, Some(now + chrono::Duration::seconds(1)));
+
+        let reopened = DeadLetterQueue::open(config.clone()).unwrap();
+        assert_eq!(reopened.letters(), letters);
+        reopened.discard(letters[0].id).unwrap();
+        assert!(reopened.discard(letters[0].id).is_err());
+
+        let reopened = DeadLetterQueue::open(config).unwrap();
+        assert_eq!(reopened.letters().len(), 1);
+        reopened.record_failure("plc", &write("HR4", 4), "timeout", now);
+        assert_eq!(reopened.letters()[1].id, letters[1].id + 1);
+        assert!(DeadLetterConfig { retry_max_ms: 10, ..Default::default() }.validate().is_err());
+    }
+}
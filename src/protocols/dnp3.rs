@@ -0,0 +1,1115 @@
+//! DNP3 master and outstation over TCP
+//!
+//! Implements the subset of IEEE 1815 needed to exchange binary and analog
+//! inputs with SCADA systems:
+//!
+//! - **Master**: polls remote outstations with periodic integrity (class
+//!   0123) and event (class 123) reads and writes the received points to the
+//!   signal bus. Event responses are confirmed and the outstation's restart
+//!   indication is cleared after every restart.
+//! - **Outstation**: serves signal bus values to an upstream master. A change
+//!   scan turns signal changes into timestamped events that are buffered per
+//!   class until the master confirms them. When the buffer is full the oldest
+//!   event is dropped and the event buffer overflow indication is raised.
+//!
+//! Only unconfirmed link-layer user data is sent, which is the common choice
+//! for DNP3 over TCP. Controls (groups 12 and 41) and unsolicited responses
+//! are not supported.
+
+use crate::config::{Dnp3MasterConfig, Dnp3OutstationConfig, Dnp3Point, Dnp3PointType};
+use crate::error::{PlcError, Result};
+use crate::scan_budget::{self, Subsystem};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// LINK LAYER
+// ============================================================================
+
+const START_BYTES: [u8; 2] = [0x05, 0x64];
+const LINK_DIR: u8 = 0x80;
+const LINK_PRM: u8 = 0x40;
+const LINK_RESET_LINK_STATES: u8 = 0x00;
+const LINK_TEST_LINK: u8 = 0x02;
+const LINK_CONFIRMED_USER_DATA: u8 = 0x03;
+const LINK_UNCONFIRMED_USER_DATA: u8 = 0x04;
+const LINK_REQUEST_STATUS: u8 = 0x09;
+const LINK_ACK: u8 = 0x00;
+const LINK_STATUS: u8 = 0x0B;
+const MAX_LINK_DATA: usize = 250;
+
+/// CRC-16/DNP over `data`
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 { crc >> 1 } else { (crc >> 1) ^ 0xA6BC };
+        }
+    }
+    !crc
+}
+
+/// A single FT3 link-layer frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LinkFrame {
+    control: u8,
+    destination: u16,
+    source: u16,
+    data: Vec<u8>,
+}
+
+impl LinkFrame {
+    fn function(&self) -> u8 {
+        self.control & 0x0F
+    }
+
+    fn is_primary(&self) -> bool {
+        self.control & LINK_PRM != 0
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let length = u8::try_from(5 + self.data.len()).expect("link data exceeds frame size");
+        let mut frame = Vec::with_capacity(10 + self.data.len() + self.data.len() / 16 * 2 + 2);
+        frame.extend_from_slice(&START_BYTES);
+        frame.push(length);
+        frame.push(self.control);
+        frame.extend_from_slice(&self.destination.to_le_bytes());
+        frame.extend_from_slice(&self.source.to_le_bytes());
+        frame.extend_from_slice(&crc16(&frame).to_le_bytes());
+        for block in self.data.chunks(16) {
+            frame.extend_from_slice(block);
+            frame.extend_from_slice(&crc16(block).to_le_bytes());
+        }
+        frame
+    }
+
+    async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Self> {
+        let mut header = [0u8; 10];
+        reader.read_exact(&mut header).await?;
+        if header[..2] != START_BYTES {
+            return Err(PlcError::Protocol("DNP3 frame has invalid start bytes".to_string()));
+        }
+        if crc16(&header[..8]).to_le_bytes() != header[8..] {
+            return Err(PlcError::Protocol("DNP3 frame header CRC mismatch".to_string()));
+        }
+        let length = usize::from(header[2]);
+        if length < 5 {
+            return Err(PlcError::Protocol(format!("DNP3 frame length {length} too short")));
+        }
+
+        let mut remaining = length - 5;
+        let mut data = Vec::with_capacity(remaining);
+        let mut block = [0u8; 18];
+        while remaining > 0 {
+            let size = remaining.min(16);
+            reader.read_exact(&mut block[..size + 2]).await?;
+            if crc16(&block[..size]).to_le_bytes() != block[size..size + 2] {
+                return Err(PlcError::Protocol("DNP3 frame data CRC mismatch".to_string()));
+            }
+            data.extend_from_slice(&block[..size]);
+            remaining -= size;
+        }
+
+        Ok(Self {
+            control: header[3],
+            destination: u16::from_le_bytes([header[4], header[5]]),
+            source: u16::from_le_bytes([header[6], header[7]]),
+            data,
+        })
+    }
+}
+
+// ============================================================================
+// TRANSPORT LAYER
+// ============================================================================
+
+const TRANSPORT_FIN: u8 = 0x80;
+const TRANSPORT_FIR: u8 = 0x40;
+
+/// Split an application fragment into transport segments
+fn segment(apdu: &[u8], sequence: &mut u8) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = apdu.chunks(MAX_LINK_DATA - 1).collect();
+    let last = chunks.len().saturating_sub(1);
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut header = *sequence & 0x3F;
+            if i == 0 {
+                header |= TRANSPORT_FIR;
+            }
+            if i == last {
+                header |= TRANSPORT_FIN;
+            }
+            *sequence = sequence.wrapping_add(1) & 0x3F;
+            let mut tpdu = Vec::with_capacity(chunk.len() + 1);
+            tpdu.push(header);
+            tpdu.extend_from_slice(chunk);
+            tpdu
+        })
+        .collect()
+}
+
+/// Reassembles transport segments into application fragments
+#[derive(Debug, Default)]
+struct Reassembler {
+    buffer: Vec<u8>,
+    next_sequence: Option<u8>,
+}
+
+impl Reassembler {
+    fn push(&mut self, tpdu: &[u8]) -> Option<Vec<u8>> {
+        let (&header, payload) = tpdu.split_first()?;
+        let sequence = header & 0x3F;
+        if header & TRANSPORT_FIR != 0 {
+            self.buffer.clear();
+        } else if self.next_sequence != Some(sequence) {
+            // Out of order segment: discard the partial fragment
+            self.buffer.clear();
+            self.next_sequence = None;
+            return None;
+        }
+        self.buffer.extend_from_slice(payload);
+        self.next_sequence = Some((sequence + 1) & 0x3F);
+        if header & TRANSPORT_FIN != 0 {
+            self.next_sequence = None;
+            Some(std::mem::take(&mut self.buffer))
+        } else {
+            None
+        }
+    }
+}
+
+/// Link and transport state for one TCP connection
+struct Channel<S> {
+    stream: S,
+    local: u16,
+    remote: u16,
+    is_master: bool,
+    transport_sequence: u8,
+    reassembler: Reassembler,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Channel<S> {
+    fn new(stream: S, local: u16, remote: u16, is_master: bool) -> Self {
+        Self {
+            stream,
+            local,
+            remote,
+            is_master,
+            transport_sequence: 0,
+            reassembler: Reassembler::default(),
+        }
+    }
+
+    fn direction(&self) -> u8 {
+        if self.is_master {
+            LINK_DIR
+        } else {
+            0
+        }
+    }
+
+    async fn send(&mut self, apdu: &[u8]) -> Result<()> {
+        let control = self.direction() | LINK_PRM | LINK_UNCONFIRMED_USER_DATA;
+        for tpdu in segment(apdu, &mut self.transport_sequence) {
+            let frame = LinkFrame {
+                control,
+                destination: self.remote,
+                source: self.local,
+                data: tpdu,
+            };
+            self.stream.write_all(&frame.encode()).await?;
+        }
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    async fn reply_secondary(&mut self, function: u8) -> Result<()> {
+        let frame = LinkFrame {
+            control: self.direction() | function,
+            destination: self.remote,
+            source: self.local,
+            data: Vec::new(),
+        };
+        self.stream.write_all(&frame.encode()).await?;
+        Ok(())
+    }
+
+    /// Receive the next complete application fragment
+    async fn receive(&mut self) -> Result<Vec<u8>> {
+        loop {
+            let frame = LinkFrame::read(&mut self.stream).await?;
+            if frame.destination != self.local {
+                debug!("Ignoring DNP3 frame for address {}", frame.destination);
+                continue;
+            }
+            if !frame.is_primary() {
+                continue;
+            }
+            match frame.function() {
+                LINK_RESET_LINK_STATES | LINK_TEST_LINK => self.reply_secondary(LINK_ACK).await?,
+                LINK_REQUEST_STATUS => self.reply_secondary(LINK_STATUS).await?,
+                LINK_CONFIRMED_USER_DATA | LINK_UNCONFIRMED_USER_DATA => {
+                    if frame.function() == LINK_CONFIRMED_USER_DATA {
+                        self.reply_secondary(LINK_ACK).await?;
+                    }
+                    if let Some(apdu) = self.reassembler.push(&frame.data) {
+                        return Ok(apdu);
+                    }
+                }
+                other => debug!("Ignoring DNP3 link function {}", other),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// APPLICATION LAYER
+// ============================================================================
+
+const APP_FIR: u8 = 0x80;
+const APP_FIN: u8 = 0x40;
+const APP_CON: u8 = 0x20;
+const APP_UNS: u8 = 0x10;
+
+const FUNC_CONFIRM: u8 = 0;
+const FUNC_READ: u8 = 1;
+const FUNC_WRITE: u8 = 2;
+const FUNC_RESPONSE: u8 = 129;
+const FUNC_UNSOLICITED_RESPONSE: u8 = 130;
+
+const IIN1_CLASS1_EVENTS: u8 = 0x02;
+const IIN1_CLASS2_EVENTS: u8 = 0x04;
+const IIN1_CLASS3_EVENTS: u8 = 0x08;
+const IIN1_DEVICE_RESTART: u8 = 0x80;
+const IIN2_NO_FUNC_CODE_SUPPORT: u8 = 0x01;
+const IIN2_OBJECT_UNKNOWN: u8 = 0x02;
+const IIN2_EVENT_BUFFER_OVERFLOW: u8 = 0x08;
+
+const QUALIFIER_START_STOP_8: u8 = 0x00;
+const QUALIFIER_START_STOP_16: u8 = 0x01;
+const QUALIFIER_ALL: u8 = 0x06;
+const QUALIFIER_COUNT_8: u8 = 0x07;
+const QUALIFIER_COUNT_16: u8 = 0x08;
+const QUALIFIER_INDEX_8: u8 = 0x17;
+const QUALIFIER_INDEX_16: u8 = 0x28;
+
+const FLAG_ONLINE: u8 = 0x01;
+const FLAG_STATE: u8 = 0x80;
+
+/// Largest response fragment the outstation builds
+const MAX_FRAGMENT: usize = 2048;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+fn put_time(out: &mut Vec<u8>, time_ms: u64) {
+    out.extend_from_slice(&time_ms.to_le_bytes()[..6]);
+}
+
+/// A point value received from or reported by an outstation
+#[derive(Debug, Clone, PartialEq)]
+struct Measurement {
+    point_type: Dnp3PointType,
+    index: u16,
+    value: Value,
+}
+
+/// Byte cursor over object data
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.position + count;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or_else(|| PlcError::Protocol("Truncated DNP3 object data".to_string()))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(u32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default())))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_bits(u64::from_le_bytes(self.take(8)?.try_into().unwrap_or_default())))
+    }
+}
+
+fn read_object_header(cursor: &mut Cursor<'_>) -> Result<(u8, u8, u8)> {
+    Ok((cursor.u8()?, cursor.u8()?, cursor.u8()?))
+}
+
+/// Object header range: either explicit indices or a start/stop range
+enum Range {
+    All,
+    Indices(Vec<u16>),
+}
+
+fn read_range(cursor: &mut Cursor<'_>, qualifier: u8) -> Result<(Range, bool)> {
+    // Returns the range and whether every object carries an index prefix
+    let (range, prefixed) = match qualifier {
+        QUALIFIER_START_STOP_8 => {
+            let (start, stop) = (u16::from(cursor.u8()?), u16::from(cursor.u8()?));
+            (Range::Indices((start..=stop).collect()), false)
+        }
+        QUALIFIER_START_STOP_16 => {
+            let (start, stop) = (cursor.u16()?, cursor.u16()?);
+            (Range::Indices((start..=stop).collect()), false)
+        }
+        QUALIFIER_ALL => (Range::All, false),
+        QUALIFIER_COUNT_8 => (Range::Indices((0..u16::from(cursor.u8()?)).collect()), false),
+        QUALIFIER_COUNT_16 => (Range::Indices((0..cursor.u16()?).collect()), false),
+        QUALIFIER_INDEX_8 => (Range::Indices(vec![0; usize::from(cursor.u8()?)]), true),
+        QUALIFIER_INDEX_16 => (Range::Indices(vec![0; usize::from(cursor.u16()?)]), true),
+        other => {
+            return Err(PlcError::Protocol(format!("Unsupported DNP3 qualifier 0x{other:02X}")));
+        }
+    };
+    Ok((range, prefixed))
+}
+
+/// Parse the object headers of a response into measurements
+///
+/// Objects the master does not map (counters, outputs, ...) stop parsing,
+/// because their size cannot be known without a full object library.
+fn parse_objects(data: &[u8]) -> Result<Vec<Measurement>> {
+    let mut cursor = Cursor::new(data);
+    let mut measurements = Vec::new();
+
+    while !cursor.is_empty() {
+        let (group, variation, qualifier) = read_object_header(&mut cursor)?;
+        let (range, prefixed) = read_range(&mut cursor, qualifier)?;
+        let Range::Indices(indices) = range else {
+            continue;
+        };
+
+        // Packed binary inputs carry one bit per point
+        if (group, variation) == (1, 1) {
+            let bits = cursor.take(indices.len().div_ceil(8))?;
+            for (i, index) in indices.into_iter().enumerate() {
+                measurements.push(Measurement {
+                    point_type: Dnp3PointType::BinaryInput,
+                    index,
+                    value: Value::Bool(bits[i / 8] & (1 << (i % 8)) != 0),
+                });
+            }
+            continue;
+        }
+
+        for mut index in indices {
+            if prefixed {
+                index = if qualifier == QUALIFIER_INDEX_8 {
+                    u16::from(cursor.u8()?)
+                } else {
+                    cursor.u16()?
+                };
+            }
+            let (point_type, value) = match (group, variation) {
+                (1, 2) | (2, 1..=3) => {
+                    let flags = cursor.u8()?;
+                    // Skip the absolute or relative event time
+                    match (group, variation) {
+                        (2, 2) => cursor.take(6)?,
+                        (2, 3) => cursor.take(2)?,
+                        _ => &[],
+                    };
+                    (Dnp3PointType::BinaryInput, Value::Bool(flags & FLAG_STATE != 0))
+                }
+                (30 | 32, _) => {
+                    let has_flags = !(group == 30 && matches!(variation, 3 | 4));
+                    if has_flags {
+                        cursor.u8()?;
+                    }
+                    let value = match (group, variation) {
+                        (30 | 32, 1 | 3) => f64::from(cursor.i32()?),
+                        (30 | 32, 2 | 4) => f64::from(cursor.i16()?),
+                        (30 | 32, 5) | (32, 7) => f64::from(cursor.f32()?),
+                        (30 | 32, 6) | (32, 8) => cursor.f64()?,
+                        _ => {
+                            return Err(PlcError::Protocol(format!(
+                                "Unsupported DNP3 object g{group}v{variation}"
+                            )));
+                        }
+                    };
+                    if group == 32 && matches!(variation, 3 | 4 | 7 | 8) {
+                        cursor.take(6)?;
+                    }
+                    (Dnp3PointType::AnalogInput, Value::Float(value))
+                }
+                _ => {
+                    return Err(PlcError::Protocol(format!(
+                        "Unsupported DNP3 object g{group}v{variation}"
+                    )));
+                }
+            };
+            measurements.push(Measurement {
+                point_type,
+                index,
+                value,
+            });
+        }
+    }
+
+    Ok(measurements)
+}
+
+fn point_map(points: &[Dnp3Point]) -> HashMap<(Dnp3PointType, u16), String> {
+    points
+        .iter()
+        .map(|p| ((p.point_type, p.index), p.signal.clone()))
+        .collect()
+}
+
+// ============================================================================
+// MASTER
+// ============================================================================
+
+/// Build a class read request for the given classes (0 = static data)
+fn class_read_request(sequence: u8, classes: &[u8]) -> Vec<u8> {
+    let mut apdu = vec![APP_FIR | APP_FIN | (sequence & 0x0F), FUNC_READ];
+    // Events are requested before static data so a response is consistent
+    for &class in classes.iter().filter(|&&c| c != 0).chain(classes.iter().filter(|&&c| c == 0)) {
+        apdu.extend_from_slice(&[60, class + 1, QUALIFIER_ALL]);
+    }
+    apdu
+}
+
+/// Polls one remote outstation and publishes its points to the signal bus
+pub struct Dnp3Master {
+    config: Dnp3MasterConfig,
+    signals: HashMap<(Dnp3PointType, u16), String>,
+    sequence: u8,
+}
+
+impl Dnp3Master {
+    /// Create a master session
+    #[must_use]
+    pub fn new(config: Dnp3MasterConfig) -> Self {
+        Self {
+            signals: point_map(&config.points),
+            config,
+            sequence: 0,
+        }
+    }
+
+    /// Connect and poll until the task is cancelled, reconnecting on errors
+    pub async fn run(mut self, bus: SignalBus) {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            match TcpStream::connect(&self.config.address).await {
+                Ok(stream) => {
+                    info!("DNP3 master '{}' connected to {}", self.config.name, self.config.address);
+                    backoff = Duration::from_secs(1);
+                    let mut channel = Channel::new(
+                        stream,
+                        self.config.local_address,
+                        self.config.remote_address,
+                        true,
+                    );
+                    if let Err(e) = self.poll_loop(&mut channel, &bus).await {
+                        warn!("DNP3 master '{}' session ended: {}", self.config.name, e);
+                    }
+                }
+                Err(e) => warn!(
+                    "DNP3 master '{}' failed to connect to {}: {}",
+                    self.config.name, self.config.address, e
+                ),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(Duration::from_mins(1));
+        }
+    }
+
+    async fn poll_loop<S>(&mut self, channel: &mut Channel<S>, bus: &SignalBus) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut integrity = tokio::time::interval(Duration::from_millis(self.config.integrity_poll_ms));
+        let mut events = tokio::time::interval(Duration::from_millis(self.config.event_poll_ms));
+        integrity.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        events.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        events.tick().await;
+
+        loop {
+            let classes: &[u8] = tokio::select! {
+                _ = integrity.tick() => &[0, 1, 2, 3],
+                _ = events.tick() => &[1, 2, 3],
+            };
+            let request = class_read_request(self.next_sequence(), classes);
+            scan_budget::instrument(Subsystem::Protocols, self.transact(channel, bus, &request)).await?;
+        }
+    }
+
+    fn next_sequence(&mut self) -> u8 {
+        let sequence = self.sequence;
+        self.sequence = (self.sequence + 1) & 0x0F;
+        sequence
+    }
+
+    /// Send a request and process every response fragment
+    async fn transact<S>(&mut self, channel: &mut Channel<S>, bus: &SignalBus, request: &[u8]) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        channel.send(request).await?;
+
+        let mut restarted = false;
+        loop {
+            let response = tokio::time::timeout(timeout, channel.receive())
+                .await
+                .map_err(|_| PlcError::Protocol("DNP3 response timed out".to_string()))??;
+            if response.len() < 4 {
+                return Err(PlcError::Protocol("DNP3 response too short".to_string()));
+            }
+            let (control, function, iin1) = (response[0], response[1], response[2]);
+            if function != FUNC_RESPONSE && function != FUNC_UNSOLICITED_RESPONSE {
+                debug!("Ignoring DNP3 application function {}", function);
+                continue;
+            }
+            if control & APP_CON != 0 {
+                let unsolicited = control & APP_UNS;
+                channel
+                    .send(&[APP_FIR | APP_FIN | unsolicited | (control & 0x0F), FUNC_CONFIRM])
+                    .await?;
+            }
+            restarted |= iin1 & IIN1_DEVICE_RESTART != 0;
+            self.publish(bus, &response[4..]);
+
+            if function == FUNC_RESPONSE && control & APP_FIN != 0 {
+                break;
+            }
+        }
+
+        if restarted {
+            // Clear IIN1.7 (index 7 of g80v1) so the next restart is noticed
+            let sequence = self.next_sequence();
+            let clear = [APP_FIR | APP_FIN | sequence, FUNC_WRITE, 80, 1, QUALIFIER_START_STOP_8, 7, 7, 0];
+            channel.send(&clear).await?;
+            tokio::time::timeout(timeout, channel.receive())
+                .await
+                .map_err(|_| PlcError::Protocol("DNP3 response timed out".to_string()))??;
+            info!("DNP3 master '{}' cleared outstation restart", self.config.name);
+        }
+        Ok(())
+    }
+
+    fn publish(&self, bus: &SignalBus, objects: &[u8]) {
+        let measurements = match parse_objects(objects) {
+            Ok(measurements) => measurements,
+            Err(e) => {
+                warn!("DNP3 master '{}': {}", self.config.name, e);
+                return;
+            }
+        };
+        let updates = measurements.into_iter().filter_map(|m| {
+            self.signals
+                .get(&(m.point_type, m.index))
+                .map(|signal| (signal.as_str(), m.value))
+        });
+        if let Err(e) = bus.write_batch(updates) {
+            warn!("DNP3 master '{}' failed to update signals: {}", self.config.name, e);
+        }
+    }
+}
+
+// ============================================================================
+// OUTSTATION
+// ============================================================================
+
+#[derive(Debug, Clone)]
+struct Event {
+    id: u64,
+    class: u8,
+    measurement: Measurement,
+    time_ms: u64,
+}
+
+/// Point database and event buffer shared by the change scan and sessions
+struct Database {
+    points: Vec<Dnp3Point>,
+    values: HashMap<(Dnp3PointType, u16), Value>,
+    last_reported: HashMap<(Dnp3PointType, u16), Value>,
+    events: VecDeque<Event>,
+    capacity: usize,
+    next_event_id: u64,
+    overflow: bool,
+    restart: bool,
+}
+
+impl Database {
+    fn new(points: Vec<Dnp3Point>, capacity: usize) -> Self {
+        Self {
+            points,
+            values: HashMap::new(),
+            last_reported: HashMap::new(),
+            events: VecDeque::new(),
+            capacity,
+            next_event_id: 0,
+            overflow: false,
+            restart: true,
+        }
+    }
+
+    /// Read the signal bus and record events for changed points
+    fn scan(&mut self, bus: &SignalBus) {
+        let time_ms = now_ms();
+        let readings: Vec<_> = self
+            .points
+            .iter()
+            .filter_map(|point| {
+                let value = bus.get(&point.signal)?;
+                let value = match point.point_type {
+                    Dnp3PointType::BinaryInput => Value::Bool(value.as_bool()?),
+                    Dnp3PointType::AnalogInput => Value::Float(value.as_float()?),
+                };
+                Some(((point.point_type, point.index), point.class, point.deadband, value))
+            })
+            .collect();
+
+        for (key, class, deadband, value) in readings {
+            self.values.insert(key, value.clone());
+
+            let changed = match (self.last_reported.get(&key), &value) {
+                (None, _) => {
+                    // The first value seeds the baseline without an event
+                    self.last_reported.insert(key, value);
+                    continue;
+                }
+                (Some(Value::Float(old)), Value::Float(new)) => (new - old).abs() > deadband,
+                (Some(old), new) => old != new,
+            };
+            if changed && class != 0 {
+                self.last_reported.insert(key, value.clone());
+                self.push_event(class, Measurement { point_type: key.0, index: key.1, value }, time_ms);
+            }
+        }
+    }
+
+    fn push_event(&mut self, class: u8, measurement: Measurement, time_ms: u64) {
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+            self.overflow = true;
+        }
+        self.events.push_back(Event {
+            id: self.next_event_id,
+            class,
+            measurement,
+            time_ms,
+        });
+        self.next_event_id += 1;
+    }
+
+    fn iin(&self) -> [u8; 2] {
+        let mut iin1 = 0;
+        for event in &self.events {
+            iin1 |= match event.class {
+                1 => IIN1_CLASS1_EVENTS,
+                2 => IIN1_CLASS2_EVENTS,
+                _ => IIN1_CLASS3_EVENTS,
+            };
+        }
+        if self.restart {
+            iin1 |= IIN1_DEVICE_RESTART;
+        }
+        let iin2 = if self.overflow { IIN2_EVENT_BUFFER_OVERFLOW } else { 0 };
+        [iin1, iin2]
+    }
+
+    /// Encode static values of one point type as contiguous ranges
+    fn encode_static(&self, point_type: Dnp3PointType, out: &mut Vec<u8>) {
+        let mut indices: Vec<u16> = self
+            .values
+            .keys()
+            .filter(|(t, _)| *t == point_type)
+            .map(|(_, index)| *index)
+            .collect();
+        indices.sort_unstable();
+
+        let mut start = 0;
+        while start < indices.len() {
+            let mut end = start;
+            while end + 1 < indices.len() && indices[end + 1] == indices[end] + 1 {
+                end += 1;
+            }
+            let (group, variation) = match point_type {
+                Dnp3PointType::BinaryInput => (1, 2),
+                Dnp3PointType::AnalogInput => (30, 5),
+            };
+            out.extend_from_slice(&[group, variation, QUALIFIER_START_STOP_16]);
+            out.extend_from_slice(&indices[start].to_le_bytes());
+            out.extend_from_slice(&indices[end].to_le_bytes());
+            for index in &indices[start..=end] {
+                encode_value(&self.values[&(point_type, *index)], out);
+            }
+            start = end + 1;
+        }
+    }
+
+    /// Encode buffered events of the requested classes, returning their ids
+    fn encode_events(&self, classes: &[u8], out: &mut Vec<u8>) -> Vec<u64> {
+        let mut selected = Vec::new();
+        for point_type in [Dnp3PointType::BinaryInput, Dnp3PointType::AnalogInput] {
+            let (group, variation, size) = match point_type {
+                Dnp3PointType::BinaryInput => (2, 2, 9),
+                Dnp3PointType::AnalogInput => (32, 7, 13),
+            };
+            let events: Vec<&Event> = self
+                .events
+                .iter()
+                .filter(|e| e.measurement.point_type == point_type && classes.contains(&e.class))
+                .take(MAX_FRAGMENT.saturating_sub(out.len() + 5) / size)
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+            out.extend_from_slice(&[group, variation, QUALIFIER_INDEX_16]);
+            out.extend_from_slice(&u16::try_from(events.len()).unwrap_or(u16::MAX).to_le_bytes());
+            for event in events {
+                out.extend_from_slice(&event.measurement.index.to_le_bytes());
+                encode_value(&event.measurement.value, out);
+                put_time(out, event.time_ms);
+                selected.push(event.id);
+            }
+        }
+        selected
+    }
+
+    fn confirm(&mut self, ids: &[u64]) {
+        self.events.retain(|e| !ids.contains(&e.id));
+        self.overflow = false;
+    }
+}
+
+/// Encode flags plus value for g1v2, g2v2, g30v5 and g32v7
+#[allow(clippy::cast_possible_truncation)]
+fn encode_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Bool(state) => out.push(FLAG_ONLINE | if *state { FLAG_STATE } else { 0 }),
+        other => {
+            out.push(FLAG_ONLINE);
+            let value = other.as_float().unwrap_or_default() as f32;
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+    }
+}
+
+/// Serves signal bus values to an upstream DNP3 master
+pub struct Dnp3Outstation {
+    config: Dnp3OutstationConfig,
+    database: Arc<Mutex<Database>>,
+}
+
+/// Per-connection outstation session state
+#[derive(Default)]
+struct Session {
+    /// Application sequence and event ids awaiting confirmation
+    pending_confirm: Option<(u8, Vec<u64>)>,
+}
+
+impl Dnp3Outstation {
+    /// Create an outstation
+    #[must_use]
+    pub fn new(config: Dnp3OutstationConfig) -> Self {
+        let database = Database::new(config.points.clone(), config.event_buffer_size);
+        Self {
+            config,
+            database: Arc::new(Mutex::new(database)),
+        }
+    }
+
+    /// Scan for changes and serve masters until the task is cancelled
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the listen address cannot be bound.
+    pub async fn run(self, bus: SignalBus) -> Result<()> {
+        let listener = TcpListener::bind(&self.config.bind).await?;
+        info!("DNP3 outstation '{}' listening on {}", self.config.name, self.config.bind);
+
+        let database = self.database.clone();
+        let scan_bus = bus.clone();
+        let scan_interval = Duration::from_millis(self.config.change_scan_ms);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(scan_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                database.lock().await.scan(&scan_bus);
+            }
+        });
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            info!("DNP3 outstation '{}' accepted master {}", self.config.name, peer);
+            let mut channel = Channel::new(
+                stream,
+                self.config.local_address,
+                self.config.remote_address,
+                false,
+            );
+            let database = self.database.clone();
+            let name = self.config.name.clone();
+            tokio::spawn(async move {
+                let mut session = Session::default();
+                loop {
+                    let request = match channel.receive().await {
+                        Ok(request) => request,
+                        Err(e) => {
+                            info!("DNP3 outstation '{}' master {} disconnected: {}", name, peer, e);
+                            break;
+                        }
+                    };
+                    let response = scan_budget::instrument(
+                        Subsystem::Protocols,
+                        handle_request(&database, &mut session, &request),
+                    )
+                    .await;
+                    if let Some(response) = response {
+                        if let Err(e) = channel.send(&response).await {
+                            warn!("DNP3 outstation '{}' send failed: {}", name, e);
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Process one request fragment and build the response, if any
+async fn handle_request(database: &Mutex<Database>, session: &mut Session, request: &[u8]) -> Option<Vec<u8>> {
+    let (&control, rest) = request.split_first()?;
+    let (&function, objects) = rest.split_first()?;
+    let sequence = control & 0x0F;
+    let mut database = database.lock().await;
+
+    if function == FUNC_CONFIRM {
+        if let Some((expected, ids)) = session.pending_confirm.take() {
+            if expected == sequence {
+                database.confirm(&ids);
+            } else {
+                session.pending_confirm = Some((expected, ids));
+            }
+        }
+        return None;
+    }
+
+    let mut body = Vec::new();
+    let mut iin2 = 0;
+    let mut response_control = APP_FIR | APP_FIN | sequence;
+    match function {
+        FUNC_READ => {
+            let mut cursor = Cursor::new(objects);
+            let mut classes = Vec::new();
+            let mut statics = Vec::new();
+            while !cursor.is_empty() {
+                let Ok((group, variation, qualifier)) = read_object_header(&mut cursor) else {
+                    iin2 |= IIN2_OBJECT_UNKNOWN;
+                    break;
+                };
+                if read_range(&mut cursor, qualifier).is_err() {
+                    iin2 |= IIN2_OBJECT_UNKNOWN;
+                    break;
+                }
+                match (group, variation) {
+                    (60, 1) => statics.extend([Dnp3PointType::BinaryInput, Dnp3PointType::AnalogInput]),
+                    (60, v @ 2..=4) => classes.push(v - 1),
+                    (1, 0 | 2) => statics.push(Dnp3PointType::BinaryInput),
+                    (30, 0 | 5) => statics.push(Dnp3PointType::AnalogInput),
+                    _ => iin2 |= IIN2_OBJECT_UNKNOWN,
+                }
+            }
+            if !classes.is_empty() {
+                let ids = database.encode_events(&classes, &mut body);
+                if !ids.is_empty() {
+                    response_control |= APP_CON;
+                    session.pending_confirm = Some((sequence, ids));
+                }
+            }
+            for point_type in statics {
+                database.encode_static(point_type, &mut body);
+            }
+        }
+        FUNC_WRITE => {
+            // Only clearing IIN1.7 (g80v1 index 7) is supported
+            if objects.len() >= 6 && objects[..3] == [80, 1, QUALIFIER_START_STOP_8] && objects[3] <= 7 && objects[4] >= 7 {
+                database.restart = false;
+            } else {
+                iin2 |= IIN2_OBJECT_UNKNOWN;
+            }
+        }
+        _ => iin2 |= IIN2_NO_FUNC_CODE_SUPPORT,
+    }
+
+    let iin = database.iin();
+    let mut response = vec![response_control, FUNC_RESPONSE, iin[0], iin[1] | iin2];
+    response.extend_from_slice(&body);
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(point_type: Dnp3PointType, index: u16, signal: &str, deadband: f64) -> Dnp3Point {
+        Dnp3Point {
+            point_type,
+            index,
+            signal: signal.to_string(),
+            class: 1,
+            deadband,
+        }
+    }
+
+    #[test]
+    fn test_crc_and_frame_round_trip() {
+        assert_eq!(crc16(b"123456789"), 0xEA82);
+
+        let frame = LinkFrame {
+            control: LINK_DIR | LINK_PRM | LINK_UNCONFIRMED_USER_DATA,
+            destination: 1024,
+            source: 1,
+            data: (0..40).collect(),
+        };
+        let bytes = frame.encode();
+        assert_eq!(bytes.len(), 10 + 40 + 3 * 2);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let decoded = runtime.block_on(LinkFrame::read(&mut bytes.as_slice())).unwrap();
+        assert_eq!(decoded, frame);
+
+        let mut corrupted = bytes;
+        corrupted[12] ^= 0xFF;
+        assert!(runtime.block_on(LinkFrame::read(&mut corrupted.as_slice())).is_err());
+    }
+
+    #[test]
+    fn test_transport_segmentation() {
+        let apdu: Vec<u8> = (0..600u32).map(|i| i.to_le_bytes()[0]).collect();
+        let mut sequence = 62;
+        let segments = segment(&apdu, &mut sequence);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(sequence, 1);
+
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.push(&segments[0]), None);
+        assert_eq!(reassembler.push(&segments[1]), None);
+        assert_eq!(reassembler.push(&segments[2]), Some(apdu));
+    }
+
+    #[tokio::test]
+    async fn test_outstation_events_and_master_parsing() {
+        let bus = SignalBus::new();
+        bus.set("pump.running", Value::Bool(false)).unwrap();
+        bus.set("tank.level", Value::Float(10.0)).unwrap();
+
+        let database = Mutex::new(Database::new(
+            vec![
+                point(Dnp3PointType::BinaryInput, 0, "pump.running", 0.0),
+                point(Dnp3PointType::AnalogInput, 3, "tank.level", 1.0),
+            ],
+            2,
+        ));
+        database.lock().await.scan(&bus);
+        let mut session = Session::default();
+
+        // Integrity poll returns static values and the restart indication
+        let response = handle_request(&database, &mut session, &class_read_request(0, &[0, 1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(response[2] & IIN1_DEVICE_RESTART, IIN1_DEVICE_RESTART);
+        assert_eq!(response[0] & APP_CON, 0);
+        let values = parse_objects(&response[4..]).unwrap();
+        assert!(values.contains(&Measurement {
+            point_type: Dnp3PointType::BinaryInput,
+            index: 0,
+            value: Value::Bool(false)
+        }));
+        assert!(values.contains(&Measurement {
+            point_type: Dnp3PointType::AnalogInput,
+            index: 3,
+            value: Value::Float(10.0)
+        }));
+
+        // Changes within the deadband are not reported
+        bus.set("tank.level", Value::Float(10.5)).unwrap();
+        bus.set("pump.running", Value::Bool(true)).unwrap();
+        database.lock().await.scan(&bus);
+        bus.set("tank.level", Value::Float(12.0)).unwrap();
+        database.lock().await.scan(&bus);
+
+        let response = handle_request(&database, &mut session, &class_read_request(1, &[1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(response[2] & IIN1_CLASS1_EVENTS, IIN1_CLASS1_EVENTS);
+        assert_eq!(response[0] & APP_CON, APP_CON);
+        let events = parse_objects(&response[4..]).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].value, Value::Float(12.0));
+
+        // Events stay buffered until confirmed
+        assert!(handle_request(&database, &mut session, &[APP_FIR | APP_FIN | 1, FUNC_CONFIRM])
+            .await
+            .is_none());
+        let response = handle_request(&database, &mut session, &class_read_request(2, &[1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(response.len(), 4);
+        assert_eq!(response[2] & IIN1_CLASS1_EVENTS, 0);
+
+        // A full buffer drops the oldest event and raises the overflow bit
+        for level in [20.0, 30.0, 40.0] {
+            bus.set("tank.level", Value::Float(level)).unwrap();
+            database.lock().await.scan(&bus);
+        }
+        let db = database.lock().await;
+        assert_eq!(db.events.len(), 2);
+        assert_eq!(db.iin()[1], IIN2_EVENT_BUFFER_OVERFLOW);
+    }
+}
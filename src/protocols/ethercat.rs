@@ -0,0 +1,1086 @@
+//! `EtherCAT` master over a raw Ethernet socket
+//!
+//! Drives one `EtherCAT` segment attached to a dedicated network interface:
+//!
+//! - **Discovery**: counts the slaves on the segment, assigns each a station
+//!   address (`0x1000 + position`) and reads its identity from the SII
+//!   EEPROM. Configured slaves are checked against the vendor ID and product
+//!   code found at their position.
+//! - **Configuration**: in PRE-OP the process data sync managers and FMMUs
+//!   of every configured slave are set up from the configuration, mapping all
+//!   outputs and then all inputs into one logical process image. Slaves are
+//!   then taken through SAFE-OP to OP.
+//! - **Cyclic exchange**: after every engine scan the process image is
+//!   exchanged with a single LRW datagram. Outputs are packed from their
+//!   signals, inputs written back to theirs, and the working counter is
+//!   checked against the number of mapped slaves. After `max_missed_cycles`
+//!   consecutive failed exchanges the segment is discovered and configured
+//!   again.
+//!
+//! ```yaml
+//! protocols:
+//!   ethercat:
+//!     interface: eth1
+//!     slaves:
+//!       - name: di8
+//!         position: 1
+//!         vendor_id: 0x2
+//!         product_code: 0x03f03052
+//!         inputs:
+//!           sync_manager: 0
+//!           address: 0x1000
+//!           length: 1
+//!           entries:
+//!             - { signal: line1.start_button, offset: 0, bit: 0, type: bool }
+//!       - name: drive
+//!         position: 2
+//!         outputs:
+//!           sync_manager: 2
+//!           address: 0x1100
+//!           length: 4
+//!           entries:
+//!             - { signal: drive.control_word, offset: 0, type: uint16 }
+//!             - { signal: drive.target_velocity, offset: 2, type: int16 }
+//!         inputs:
+//!           sync_manager: 3
+//!           address: 0x1180
+//!           length: 4
+//!           entries:
+//!             - { signal: drive.status_word, offset: 0, type: uint16 }
+//!             - { signal: drive.actual_velocity, offset: 2, type: int16 }
+//! ```
+//!
+//! The PDO layout of each slave is its default assignment; mailbox (`CoE`)
+//! configuration and distributed clocks are not supported. Opening the raw
+//! socket requires Linux and `CAP_NET_RAW`.
+
+#[cfg(target_os = "linux")]
+pub use super::raw_ethernet::RawSocket;
+pub use super::raw_ethernet::Transport;
+use crate::scan_budget::{self, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// `EtherCAT` master configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EthercatConfig {
+    /// Network interface connected to the first slave
+    pub interface: String,
+
+    /// Time to wait for a frame to return (microseconds)
+    #[serde(default = "default_timeout_us")]
+    pub timeout_us: u64,
+
+    /// Consecutive failed exchanges before the segment is restarted
+    #[serde(default = "default_max_missed_cycles")]
+    pub max_missed_cycles: u32,
+
+    /// Slaves whose process data is mapped to signals
+    pub slaves: Vec<SlaveConfig>,
+}
+
+/// Expected slave and its process data mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaveConfig {
+    /// Slave name used in logs
+    pub name: String,
+
+    /// Position on the segment, starting at 0 next to the master
+    pub position: u16,
+
+    /// Expected vendor ID, checked during discovery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<u32>,
+
+    /// Expected product code, checked during discovery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_code: Option<u32>,
+
+    /// Process data written by the master (`RxPDOs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<ProcessData>,
+
+    /// Process data read by the master (`TxPDOs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<ProcessData>,
+}
+
+/// Sync manager buffer holding one direction of a slave's process data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessData {
+    /// Sync manager carrying the process data
+    pub sync_manager: u8,
+
+    /// Physical start address of the sync manager buffer
+    pub address: u16,
+
+    /// Size of the process data in bytes
+    pub length: u16,
+
+    /// PDO entries mapped to signals
+    #[serde(default)]
+    pub entries: Vec<PdoEntry>,
+}
+
+/// PDO entry mapped to a signal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdoEntry {
+    /// Signal holding the entry's value
+    pub signal: String,
+
+    /// Byte offset within the slave's process data
+    pub offset: u16,
+
+    /// Bit within the byte, for `bool` entries
+    #[serde(default)]
+    pub bit: u8,
+
+    /// Encoding of the entry
+    #[serde(rename = "type")]
+    pub data_type: PdoType,
+}
+
+/// Encodings of PDO entries, all little-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PdoType {
+    /// Single bit, read as `Bool`
+    Bool,
+    /// Signed 8-bit integer
+    Int8,
+    /// Unsigned 8-bit integer
+    Uint8,
+    /// Signed 16-bit integer
+    Int16,
+    /// Unsigned 16-bit integer
+    Uint16,
+    /// Signed 32-bit integer
+    Int32,
+    /// Unsigned 32-bit integer
+    Uint32,
+    /// IEEE 754 single precision float
+    Float32,
+}
+
+const fn default_timeout_us() -> u64 {
+    1000
+}
+
+const fn default_max_missed_cycles() -> u32 {
+    3
+}
+
+impl EthercatConfig {
+    /// Validate the slave list and process data mappings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for duplicate slave names or positions,
+    /// entries outside their process data, signals mapped twice or a process
+    /// image too large for one frame.
+    pub fn validate(&self) -> Result<()> {
+        if self.interface.is_empty() {
+            return Err(PlcError::Config("EtherCAT interface must not be empty".to_string()));
+        }
+        if self.timeout_us == 0 || self.max_missed_cycles == 0 {
+            return Err(PlcError::Config(
+                "EtherCAT timeout and max_missed_cycles must be greater than 0".to_string(),
+            ));
+        }
+        if self.slaves.is_empty() {
+            return Err(PlcError::Config("EtherCAT configuration has no slaves".to_string()));
+        }
+
+        let mut names = HashSet::new();
+        let mut positions = HashSet::new();
+        let mut signals = HashSet::new();
+        let mut image_len = 0usize;
+        for slave in &self.slaves {
+            if !names.insert(&slave.name) {
+                return Err(PlcError::Config(format!("Duplicate EtherCAT slave name: '{}'", slave.name)));
+            }
+            if !positions.insert(slave.position) {
+                return Err(PlcError::Config(format!(
+                    "EtherCAT slaves share position {}",
+                    slave.position
+                )));
+            }
+            if slave.outputs.is_none() && slave.inputs.is_none() {
+                return Err(PlcError::Config(format!(
+                    "EtherCAT slave '{}' has no outputs or inputs",
+                    slave.name
+                )));
+            }
+            let directions = [&slave.outputs, &slave.inputs];
+            if let [Some(outputs), Some(inputs)] = directions {
+                if outputs.sync_manager == inputs.sync_manager {
+                    return Err(PlcError::Config(format!(
+                        "EtherCAT slave '{}' uses sync manager {} for outputs and inputs",
+                        slave.name, outputs.sync_manager
+                    )));
+                }
+            }
+            for data in directions.into_iter().flatten() {
+                if data.length == 0 || data.sync_manager >= MAX_SYNC_MANAGERS {
+                    return Err(PlcError::Config(format!(
+                        "EtherCAT slave '{}' has invalid process data (sync manager {}, length {})",
+                        slave.name, data.sync_manager, data.length
+                    )));
+                }
+                image_len += usize::from(data.length);
+                for entry in &data.entries {
+                    let end = usize::from(entry.offset) + entry.data_type.size();
+                    if end > usize::from(data.length) || entry.bit > 7 {
+                        return Err(PlcError::Config(format!(
+                            "EtherCAT entry '{}' lies outside slave '{}' process data",
+                            entry.signal, slave.name
+                        )));
+                    }
+                    if !signals.insert(&entry.signal) {
+                        return Err(PlcError::Config(format!(
+                            "EtherCAT maps signal '{}' more than once",
+                            entry.signal
+                        )));
+                    }
+                }
+            }
+        }
+        if image_len > MAX_DATAGRAM_DATA {
+            return Err(PlcError::Config(format!(
+                "EtherCAT process image of {image_len} bytes exceeds one frame ({MAX_DATAGRAM_DATA} bytes)"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl PdoType {
+    /// Bytes occupied in the process image
+    #[must_use]
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Bool | Self::Int8 | Self::Uint8 => 1,
+            Self::Int16 | Self::Uint16 => 2,
+            Self::Int32 | Self::Uint32 | Self::Float32 => 4,
+        }
+    }
+
+    /// Read an entry from `data`, which starts at the entry's offset
+    fn decode(self, data: &[u8], bit: u8) -> Value {
+        let bytes = |n: usize| {
+            let mut buf = [0u8; 4];
+            buf[..n].copy_from_slice(&data[..n]);
+            buf
+        };
+        match self {
+            Self::Bool => Value::Bool(data[0] & (1 << bit) != 0),
+            Self::Int8 => Value::Integer(i64::from(i8::from_le_bytes([data[0]]))),
+            Self::Uint8 => Value::Integer(i64::from(data[0])),
+            Self::Int16 => Value::Integer(i64::from(i16::from_le_bytes([data[0], data[1]]))),
+            Self::Uint16 => Value::Integer(i64::from(u16::from_le_bytes([data[0], data[1]]))),
+            Self::Int32 => Value::Integer(i64::from(i32::from_le_bytes(bytes(4)))),
+            Self::Uint32 => Value::Integer(i64::from(u32::from_le_bytes(bytes(4)))),
+            Self::Float32 => Value::Float(f64::from(f32::from_le_bytes(bytes(4)))),
+        }
+    }
+
+    /// Write `value` into `data`, which starts at the entry's offset
+    ///
+    /// Integers outside the entry's range are saturated. Values that cannot
+    /// be converted leave the previous output in place.
+    fn encode(self, value: &Value, data: &mut [u8], bit: u8) {
+        fn saturate<T: TryFrom<i64>>(v: i64, min: T, max: T) -> T {
+            T::try_from(v).unwrap_or(if v < 0 { min } else { max })
+        }
+        let bytes: Vec<u8> = match self {
+            Self::Bool => {
+                let Some(on) = value.as_bool() else { return };
+                if on {
+                    data[0] |= 1 << bit;
+                } else {
+                    data[0] &= !(1 << bit);
+                }
+                return;
+            }
+            Self::Float32 => {
+                let Some(v) = value.as_float() else { return };
+                #[allow(clippy::cast_possible_truncation)]
+                let v = v as f32;
+                v.to_le_bytes().to_vec()
+            }
+            _ => {
+                let Some(v) = value.as_integer() else { return };
+                match self {
+                    Self::Int8 => saturate(v, i8::MIN, i8::MAX).to_le_bytes().to_vec(),
+                    Self::Uint8 => saturate(v, u8::MIN, u8::MAX).to_le_bytes().to_vec(),
+                    Self::Int16 => saturate(v, i16::MIN, i16::MAX).to_le_bytes().to_vec(),
+                    Self::Uint16 => saturate(v, u16::MIN, u16::MAX).to_le_bytes().to_vec(),
+                    Self::Int32 => saturate(v, i32::MIN, i32::MAX).to_le_bytes().to_vec(),
+                    _ => saturate(v, u32::MIN, u32::MAX).to_le_bytes().to_vec(),
+                }
+            }
+        };
+        data[..bytes.len()].copy_from_slice(&bytes);
+    }
+}
+
+// ============================================================================
+// FRAMES
+// ============================================================================
+
+const ETHERTYPE: u16 = 0x88A4;
+const ETH_HEADER_LEN: usize = 14;
+const ECAT_HEADER_LEN: usize = 2;
+const DATAGRAM_HEADER_LEN: usize = 10;
+const WKC_LEN: usize = 2;
+const MIN_FRAME_LEN: usize = 60;
+const MAX_FRAME_LEN: usize = 1514;
+const MAX_DATAGRAM_DATA: usize =
+    MAX_FRAME_LEN - ETH_HEADER_LEN - ECAT_HEADER_LEN - DATAGRAM_HEADER_LEN - WKC_LEN;
+/// `EtherCAT` header type for datagrams
+const ECAT_TYPE_DATAGRAMS: u16 = 0x1000;
+/// Locally administered source address of the master
+const MASTER_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+/// Datagram commands used by the master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum Command {
+    /// Auto-increment physical read (addressed by position)
+    Aprd = 1,
+    /// Auto-increment physical write
+    Apwr = 2,
+    /// Configured address physical read (addressed by station address)
+    Fprd = 4,
+    /// Configured address physical write
+    Fpwr = 5,
+    /// Broadcast read
+    Brd = 7,
+    /// Broadcast write
+    Bwr = 8,
+    /// Logical read/write of the process image
+    Lrw = 12,
+}
+
+impl Command {
+    fn from_u8(code: u8) -> Option<Self> {
+        Some(match code {
+            1 => Self::Aprd,
+            2 => Self::Apwr,
+            4 => Self::Fprd,
+            5 => Self::Fpwr,
+            7 => Self::Brd,
+            8 => Self::Bwr,
+            12 => Self::Lrw,
+            _ => return None,
+        })
+    }
+}
+
+/// Physical address of a register: slave address in the low word and
+/// register offset in the high word
+const fn physical(slave: u16, register: u16) -> u32 {
+    (register as u32) << 16 | slave as u32
+}
+
+/// A single datagram, sent one per frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Datagram {
+    command: Command,
+    index: u8,
+    address: u32,
+    data: Vec<u8>,
+    wkc: u16,
+}
+
+impl Datagram {
+    fn encode_frame(&self) -> Vec<u8> {
+        let datagram_len = DATAGRAM_HEADER_LEN + self.data.len() + WKC_LEN;
+        let mut frame = Vec::with_capacity((ETH_HEADER_LEN + ECAT_HEADER_LEN + datagram_len).max(MIN_FRAME_LEN));
+        frame.extend_from_slice(&[0xFF; 6]);
+        frame.extend_from_slice(&MASTER_MAC);
+        frame.extend_from_slice(&ETHERTYPE.to_be_bytes());
+        let ecat_len = u16::try_from(datagram_len).expect("datagram exceeds frame size");
+        frame.extend_from_slice(&(ecat_len | ECAT_TYPE_DATAGRAMS).to_le_bytes());
+        frame.push(self.command as u8);
+        frame.push(self.index);
+        frame.extend_from_slice(&self.address.to_le_bytes());
+        let data_len = u16::try_from(self.data.len()).expect("datagram exceeds frame size");
+        frame.extend_from_slice(&data_len.to_le_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&self.data);
+        frame.extend_from_slice(&self.wkc.to_le_bytes());
+        frame.resize(frame.len().max(MIN_FRAME_LEN), 0);
+        frame
+    }
+
+    fn decode_frame(frame: &[u8]) -> Result<Self> {
+        let invalid = |reason: &str| PlcError::Runtime(format!("Invalid EtherCAT frame: {reason}"));
+        let header_end = ETH_HEADER_LEN + ECAT_HEADER_LEN + DATAGRAM_HEADER_LEN;
+        if frame.len() < header_end + WKC_LEN {
+            return Err(invalid("too short"));
+        }
+        if u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE {
+            return Err(invalid("wrong EtherType"));
+        }
+        if u16::from_le_bytes([frame[14], frame[15]]) & 0xF000 != ECAT_TYPE_DATAGRAMS {
+            return Err(invalid("not a datagram frame"));
+        }
+        let datagram = &frame[ETH_HEADER_LEN + ECAT_HEADER_LEN..];
+        let command = Command::from_u8(datagram[0]).ok_or_else(|| invalid("unknown command"))?;
+        let address = u32::from_le_bytes([datagram[2], datagram[3], datagram[4], datagram[5]]);
+        let data_len = usize::from(u16::from_le_bytes([datagram[6], datagram[7]]) & 0x07FF);
+        let data_end = DATAGRAM_HEADER_LEN + data_len;
+        if datagram.len() < data_end + WKC_LEN {
+            return Err(invalid("truncated datagram"));
+        }
+        Ok(Self {
+            command,
+            index: datagram[1],
+            address,
+            data: datagram[DATAGRAM_HEADER_LEN..data_end].to_vec(),
+            wkc: u16::from_le_bytes([datagram[data_end], datagram[data_end + 1]]),
+        })
+    }
+}
+
+// ============================================================================
+// MASTER
+// ============================================================================
+
+const MAX_SYNC_MANAGERS: u8 = 16;
+const STATION_ADDRESS_BASE: u16 = 0x1000;
+
+const REG_TYPE: u16 = 0x0000;
+const REG_STATION_ADDRESS: u16 = 0x0010;
+const REG_AL_CONTROL: u16 = 0x0120;
+const REG_AL_STATUS: u16 = 0x0130;
+const REG_AL_STATUS_CODE: u16 = 0x0134;
+const REG_SII_CONTROL: u16 = 0x0502;
+const REG_SII_DATA: u16 = 0x0508;
+const REG_FMMU: u16 = 0x0600;
+const REG_SYNC_MANAGER: u16 = 0x0800;
+
+const SII_READ: u16 = 0x0100;
+const SII_BUSY: u16 = 0x8000;
+const SII_VENDOR_ID: u32 = 0x0008;
+const SII_PRODUCT_CODE: u32 = 0x000A;
+const SII_REVISION: u32 = 0x000C;
+
+const AL_ERROR: u16 = 0x0010;
+const FMMU_READ: u8 = 1;
+const FMMU_WRITE: u8 = 2;
+/// Sync manager control: buffered, ECAT writes, PDI interrupt, watchdog
+const SM_CONTROL_OUTPUTS: u8 = 0x64;
+/// Sync manager control: buffered, ECAT reads, PDI interrupt
+const SM_CONTROL_INPUTS: u8 = 0x20;
+
+const REGISTER_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+const REGISTER_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Application layer states of a slave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum AlState {
+    Init = 1,
+    PreOp = 2,
+    SafeOp = 4,
+    Op = 8,
+}
+
+/// Identity of a slave found during discovery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlaveInfo {
+    /// Position on the segment
+    pub position: u16,
+    /// Assigned station address
+    pub station_address: u16,
+    /// Vendor ID from the SII EEPROM
+    pub vendor_id: u32,
+    /// Product code from the SII EEPROM
+    pub product_code: u32,
+    /// Revision number from the SII EEPROM
+    pub revision: u32,
+}
+
+/// PDO entry placed in the process image
+#[derive(Debug)]
+struct Mapping {
+    signal: String,
+    offset: usize,
+    bit: u8,
+    data_type: PdoType,
+}
+
+/// Master driving one segment through a [`Transport`]
+pub struct EthercatMaster<T> {
+    config: EthercatConfig,
+    transport: T,
+    timeout: Duration,
+    index: u8,
+    image: Vec<u8>,
+    outputs: Vec<Mapping>,
+    inputs: Vec<Mapping>,
+    expected_wkc: u16,
+}
+
+impl<T: Transport> EthercatMaster<T> {
+    /// Create a master for a validated configuration
+    #[must_use]
+    pub fn new(config: EthercatConfig, transport: T) -> Self {
+        Self {
+            timeout: Duration::from_micros(config.timeout_us),
+            config,
+            transport,
+            index: 0,
+            image: Vec::new(),
+            outputs: Vec::new(),
+            inputs: Vec::new(),
+            expected_wkc: 0,
+        }
+    }
+
+    /// Discover and configure the segment and bring the slaves to OP
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured slave is missing or does not match,
+    /// refuses a state change, or the segment does not answer.
+    pub fn start(&mut self, bus: &SignalBus) -> Result<Vec<SlaveInfo>> {
+        // Reset every slave and clear mappings left by a previous master
+        self.broadcast_write(REG_AL_CONTROL, &u16::from(AlState::Init as u8).to_le_bytes())?;
+        self.broadcast_write(REG_FMMU, &[0; 256])?;
+        self.broadcast_write(REG_SYNC_MANAGER, &[0; 128])?;
+
+        let slaves = self.discover()?;
+        self.check_slaves(&slaves)?;
+        self.map_process_image();
+
+        let configured = self.config.slaves.clone();
+        for slave in &configured {
+            self.set_state(slave, AlState::PreOp)?;
+        }
+        let mut logical = 0u32;
+        for slave in &configured {
+            if let Some(outputs) = &slave.outputs {
+                self.map_sync_manager(slave, outputs, SM_CONTROL_OUTPUTS, 0, FMMU_WRITE, logical)?;
+                logical += u32::from(outputs.length);
+            }
+        }
+        for slave in &configured {
+            if let Some(inputs) = &slave.inputs {
+                self.map_sync_manager(slave, inputs, SM_CONTROL_INPUTS, 1, FMMU_READ, logical)?;
+                logical += u32::from(inputs.length);
+            }
+        }
+        for slave in &configured {
+            self.set_state(slave, AlState::SafeOp)?;
+        }
+        // Slaves expect valid outputs before entering OP
+        self.exchange(bus)?;
+        for slave in &configured {
+            self.set_state(slave, AlState::Op)?;
+        }
+        info!(
+            "EtherCAT segment on {} in OP: {} slaves, {} byte process image",
+            self.config.interface,
+            slaves.len(),
+            self.image.len()
+        );
+        Ok(slaves)
+    }
+
+    /// Count the slaves, assign station addresses and read their identities
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment does not answer.
+    pub fn discover(&mut self) -> Result<Vec<SlaveInfo>> {
+        let count = self.transact(Command::Brd, physical(0, REG_TYPE), &[0])?.wkc;
+        let mut slaves = Vec::with_capacity(usize::from(count));
+        for position in 0..count {
+            let station_address = STATION_ADDRESS_BASE + position;
+            let reply = self.transact(
+                Command::Apwr,
+                physical(0u16.wrapping_sub(position), REG_STATION_ADDRESS),
+                &station_address.to_le_bytes(),
+            )?;
+            if reply.wkc != 1 {
+                return Err(PlcError::Runtime(format!(
+                    "EtherCAT slave at position {position} did not accept its station address"
+                )));
+            }
+            let slave = SlaveInfo {
+                position,
+                station_address,
+                vendor_id: self.read_sii(station_address, SII_VENDOR_ID)?,
+                product_code: self.read_sii(station_address, SII_PRODUCT_CODE)?,
+                revision: self.read_sii(station_address, SII_REVISION)?,
+            };
+            debug!("EtherCAT slave found: {:?}", slave);
+            slaves.push(slave);
+        }
+        Ok(slaves)
+    }
+
+    /// Exchange the process image once
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is lost or not every mapped slave
+    /// processed it.
+    pub fn exchange(&mut self, bus: &SignalBus) -> Result<()> {
+        for mapping in &self.outputs {
+            if let Some(value) = bus.get(&mapping.signal) {
+                mapping.data_type.encode(&value, &mut self.image[mapping.offset..], mapping.bit);
+            }
+        }
+
+        let image = std::mem::take(&mut self.image);
+        let reply = self.transact(Command::Lrw, 0, &image);
+        self.image = image;
+        let reply = reply?;
+        if reply.wkc != self.expected_wkc || reply.data.len() != self.image.len() {
+            return Err(PlcError::Runtime(format!(
+                "EtherCAT working counter {} (expected {})",
+                reply.wkc, self.expected_wkc
+            )));
+        }
+
+        for mapping in &self.inputs {
+            let value = mapping.data_type.decode(&reply.data[mapping.offset..], mapping.bit);
+            if bus.get(&mapping.signal).as_ref() != Some(&value) {
+                bus.set(&mapping.signal, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_slaves(&self, found: &[SlaveInfo]) -> Result<()> {
+        for slave in &self.config.slaves {
+            let info = found.get(usize::from(slave.position)).ok_or_else(|| {
+                PlcError::Runtime(format!(
+                    "EtherCAT slave '{}' not found at position {} ({} slaves on the segment)",
+                    slave.name,
+                    slave.position,
+                    found.len()
+                ))
+            })?;
+            let mismatch = slave.vendor_id.is_some_and(|id| id != info.vendor_id)
+                || slave.product_code.is_some_and(|code| code != info.product_code);
+            if mismatch {
+                return Err(PlcError::Runtime(format!(
+                    "EtherCAT slave '{}' at position {} is vendor {:#x} product {:#x}, not the configured device",
+                    slave.name, slave.position, info.vendor_id, info.product_code
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lay out all outputs followed by all inputs, in configuration order
+    fn map_process_image(&mut self) {
+        self.outputs.clear();
+        self.inputs.clear();
+        self.expected_wkc = 0;
+        let mut offset = 0usize;
+        for slave in &self.config.slaves {
+            if let Some(outputs) = &slave.outputs {
+                self.outputs.extend(mappings(outputs, offset));
+                offset += usize::from(outputs.length);
+                // LRW counts a write as 2
+                self.expected_wkc += 2;
+            }
+        }
+        for slave in &self.config.slaves {
+            if let Some(inputs) = &slave.inputs {
+                self.inputs.extend(mappings(inputs, offset));
+                offset += usize::from(inputs.length);
+                self.expected_wkc += 1;
+            }
+        }
+        self.image = vec![0; offset];
+    }
+
+    fn map_sync_manager(
+        &mut self,
+        slave: &SlaveConfig,
+        data: &ProcessData,
+        control: u8,
+        fmmu: u16,
+        fmmu_type: u8,
+        logical: u32,
+    ) -> Result<()> {
+        let station = STATION_ADDRESS_BASE + slave.position;
+        let mut sync_manager = Vec::with_capacity(8);
+        sync_manager.extend_from_slice(&data.address.to_le_bytes());
+        sync_manager.extend_from_slice(&data.length.to_le_bytes());
+        sync_manager.extend_from_slice(&[control, 0, 1, 0]);
+        self.write(station, REG_SYNC_MANAGER + u16::from(data.sync_manager) * 8, &sync_manager)?;
+
+        let mut entry = Vec::with_capacity(16);
+        entry.extend_from_slice(&logical.to_le_bytes());
+        entry.extend_from_slice(&data.length.to_le_bytes());
+        entry.extend_from_slice(&[0, 7]);
+        entry.extend_from_slice(&data.address.to_le_bytes());
+        entry.extend_from_slice(&[0, fmmu_type, 1, 0, 0, 0]);
+        self.write(station, REG_FMMU + fmmu * 16, &entry)
+    }
+
+    fn set_state(&mut self, slave: &SlaveConfig, state: AlState) -> Result<()> {
+        let station = STATION_ADDRESS_BASE + slave.position;
+        self.write(station, REG_AL_CONTROL, &u16::from(state as u8).to_le_bytes())?;
+        let deadline = Instant::now() + REGISTER_POLL_TIMEOUT;
+        loop {
+            let status = self.read_u16(station, REG_AL_STATUS)?;
+            if status & AL_ERROR != 0 {
+                let code = self.read_u16(station, REG_AL_STATUS_CODE)?;
+                return Err(PlcError::Runtime(format!(
+                    "EtherCAT slave '{}' refused {:?}: AL status code {:#06x}",
+                    slave.name, state, code
+                )));
+            }
+            if status & 0x0F == u16::from(state as u8) {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(PlcError::Runtime(format!(
+                    "EtherCAT slave '{}' did not reach {:?}",
+                    slave.name, state
+                )));
+            }
+            std::thread::sleep(REGISTER_POLL_INTERVAL);
+        }
+    }
+
+    fn read_sii(&mut self, station: u16, word: u32) -> Result<u32> {
+        let mut request = SII_READ.to_le_bytes().to_vec();
+        request.extend_from_slice(&word.to_le_bytes());
+        self.write(station, REG_SII_CONTROL, &request)?;
+        let deadline = Instant::now() + REGISTER_POLL_TIMEOUT;
+        while self.read_u16(station, REG_SII_CONTROL)? & SII_BUSY != 0 {
+            if Instant::now() >= deadline {
+                return Err(PlcError::Runtime(format!(
+                    "EtherCAT slave {station:#06x} SII read timed out"
+                )));
+            }
+            std::thread::sleep(REGISTER_POLL_INTERVAL);
+        }
+        let data = self.read(station, REG_SII_DATA, 4)?;
+        Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+    }
+
+    fn read_u16(&mut self, station: u16, register: u16) -> Result<u16> {
+        let data = self.read(station, register, 2)?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    fn read(&mut self, station: u16, register: u16, len: usize) -> Result<Vec<u8>> {
+        let reply = self.transact(Command::Fprd, physical(station, register), &vec![0; len])?;
+        if reply.wkc != 1 || reply.data.len() != len {
+            return Err(PlcError::Runtime(format!(
+                "EtherCAT slave {station:#06x} did not answer read of register {register:#06x}"
+            )));
+        }
+        Ok(reply.data)
+    }
+
+    fn write(&mut self, station: u16, register: u16, data: &[u8]) -> Result<()> {
+        if self.transact(Command::Fpwr, physical(station, register), data)?.wkc != 1 {
+            return Err(PlcError::Runtime(format!(
+                "EtherCAT slave {station:#06x} did not answer write of register {register:#06x}"
+            )));
+        }
+        Ok(())
+    }
+
+    fn broadcast_write(&mut self, register: u16, data: &[u8]) -> Result<()> {
+        self.transact(Command::Bwr, physical(0, register), data).map(|_| ())
+    }
+
+    /// Send a datagram and wait for it to come back around the segment
+    fn transact(&mut self, command: Command, address: u32, data: &[u8]) -> Result<Datagram> {
+        let index = self.index;
+        self.index = self.index.wrapping_add(1);
+        let request = Datagram {
+            command,
+            index,
+            address,
+            data: data.to_vec(),
+            wkc: 0,
+        };
+        self.transport.send(&request.encode_frame())?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        while Instant::now() < deadline {
+            let Some(len) = self.transport.recv(&mut buf)? else {
+                continue;
+            };
+            match Datagram::decode_frame(&buf[..len]) {
+                Ok(reply) if reply.index == index && reply.command == command => return Ok(reply),
+                Ok(reply) => debug!("Ignoring stale EtherCAT datagram {}", reply.index),
+                Err(e) => debug!("Ignoring frame: {}", e),
+            }
+        }
+        Err(PlcError::Runtime(format!("EtherCAT {command:?} datagram timed out")))
+    }
+}
+
+fn mappings(data: &ProcessData, base: usize) -> impl Iterator<Item = Mapping> + '_ {
+    data.entries.iter().map(move |entry| Mapping {
+        signal: entry.signal.clone(),
+        offset: base + usize::from(entry.offset),
+        bit: entry.bit,
+        data_type: entry.data_type,
+    })
+}
+
+/// Run the master on `config.interface`, exchanging once per engine scan
+///
+/// `ticks` comes from [`crate::Engine::scan_ticks`]; the master runs on a
+/// blocking thread and returns when the engine is dropped. Failed starts and
+/// lost segments are retried with backoff.
+///
+/// # Errors
+///
+/// Returns an error if the raw socket cannot be opened.
+pub async fn run(config: EthercatConfig, bus: SignalBus, ticks: watch::Receiver<u64>) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        let transport = RawSocket::open(&config.interface, ETHERTYPE, Duration::from_micros(config.timeout_us))?;
+        let master = EthercatMaster::new(config, transport);
+        tokio::task::spawn_blocking(move || cycle(master, &bus, ticks))
+            .await
+            .map_err(|e| PlcError::Runtime(format!("EtherCAT master task failed: {e}")))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (config, bus, ticks);
+        Err(PlcError::Config("EtherCAT requires Linux raw sockets".to_string()))
+    }
+}
+
+/// Start the segment and exchange after every scan until the engine stops
+#[cfg(target_os = "linux")]
+fn cycle<T: Transport>(mut master: EthercatMaster<T>, bus: &SignalBus, mut ticks: watch::Receiver<u64>) {
+    let runtime = tokio::runtime::Handle::current();
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match master.start(bus) {
+            Ok(_) => {
+                backoff = Duration::from_secs(1);
+                let mut missed = 0;
+                while missed < master.config.max_missed_cycles {
+                    if runtime.block_on(ticks.changed()).is_err() {
+                        return;
+                    }
+                    match scan_budget::measure(Subsystem::Protocols, || master.exchange(bus)) {
+                        Ok(()) => missed = 0,
+                        Err(e) => {
+                            missed += 1;
+                            warn!("EtherCAT exchange failed ({}/{}): {}", missed, master.config.max_missed_cycles, e);
+                        }
+                    }
+                }
+                error!("EtherCAT segment on {} lost, restarting", master.config.interface);
+            }
+            Err(e) => warn!("EtherCAT segment on {} failed to start: {}", master.config.interface, e),
+        }
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_mins(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Register memory of one simulated slave controller
+    struct SimSlave {
+        memory: Vec<u8>,
+        eeprom: Vec<u16>,
+    }
+
+    impl SimSlave {
+        fn new(vendor_id: u32, product_code: u32) -> Self {
+            let mut eeprom = vec![0u16; 0x10];
+            eeprom[0x08..0x0A].copy_from_slice(&[vendor_id as u16, (vendor_id >> 16) as u16]);
+            eeprom[0x0A..0x0C].copy_from_slice(&[product_code as u16, (product_code >> 16) as u16]);
+            Self {
+                memory: vec![0; 0x2000],
+                eeprom,
+            }
+        }
+
+        fn station(&self) -> u16 {
+            u16::from_le_bytes([self.memory[0x10], self.memory[0x11]])
+        }
+
+        fn access(&mut self, register: u16, data: &mut [u8], write: bool) {
+            let start = usize::from(register);
+            if write {
+                self.memory[start..start + data.len()].copy_from_slice(data);
+                self.apply_write(register);
+            } else {
+                data.copy_from_slice(&self.memory[start..start + data.len()]);
+            }
+        }
+
+        fn apply_write(&mut self, register: u16) {
+            match register {
+                REG_AL_CONTROL => self.memory[0x130] = self.memory[0x120],
+                REG_SII_CONTROL => {
+                    let word = usize::from(self.memory[0x504]);
+                    for (i, w) in self.eeprom[word..word + 2].iter().enumerate() {
+                        self.memory[0x508 + i * 2..0x50A + i * 2].copy_from_slice(&w.to_le_bytes());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        /// Apply an LRW through the slave's FMMUs, returning its wkc share
+        fn logical(&mut self, address: u32, data: &mut [u8]) -> u16 {
+            let mut wkc = 0;
+            for fmmu in 0..2 {
+                let entry = &self.memory[0x600 + fmmu * 16..0x610 + fmmu * 16];
+                if entry[12] != 1 {
+                    continue;
+                }
+                let logical = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+                let len = usize::from(u16::from_le_bytes([entry[4], entry[5]]));
+                let physical = usize::from(u16::from_le_bytes([entry[8], entry[9]]));
+                let offset = (logical - address) as usize;
+                if entry[11] == FMMU_WRITE {
+                    self.memory[physical..physical + len].copy_from_slice(&data[offset..offset + len]);
+                    wkc += 2;
+                } else {
+                    data[offset..offset + len].copy_from_slice(&self.memory[physical..physical + len]);
+                    wkc += 1;
+                }
+            }
+            wkc
+        }
+    }
+
+    /// Segment of simulated slaves answering every frame immediately
+    struct SimSegment {
+        slaves: Vec<SimSlave>,
+        replies: VecDeque<Vec<u8>>,
+    }
+
+    impl Transport for SimSegment {
+        fn send(&mut self, frame: &[u8]) -> Result<()> {
+            let mut datagram = Datagram::decode_frame(frame)?;
+            let adp = (datagram.address & 0xFFFF) as u16;
+            let ado = (datagram.address >> 16) as u16;
+            let mut wkc = 0;
+            for (position, slave) in self.slaves.iter_mut().enumerate() {
+                let write = matches!(datagram.command, Command::Apwr | Command::Fpwr | Command::Bwr);
+                let addressed = match datagram.command {
+                    Command::Aprd | Command::Apwr => adp.wrapping_add(position as u16) == 0,
+                    Command::Fprd | Command::Fpwr => slave.station() == adp,
+                    Command::Brd | Command::Bwr => true,
+                    Command::Lrw => {
+                        wkc += slave.logical(datagram.address, &mut datagram.data);
+                        false
+                    }
+                };
+                if addressed {
+                    slave.access(ado, &mut datagram.data, write);
+                    wkc += 1;
+                }
+            }
+            datagram.wkc = wkc;
+            self.replies.push_back(datagram.encode_frame());
+            Ok(())
+        }
+
+        fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+            Ok(self.replies.pop_front().map(|frame| {
+                buf[..frame.len()].copy_from_slice(&frame);
+                frame.len()
+            }))
+        }
+    }
+
+    #[test]
+    fn test_discovery_and_process_data_exchange() {
+        let config: EthercatConfig = serde_yaml::from_str(
+            r"
+interface: sim0
+slaves:
+  - name: coupler
+    position: 0
+    inputs:
+      sync_manager: 3
+      address: 0x1180
+      length: 5
+      entries:
+        - { signal: io.button, offset: 0, bit: 2, type: bool }
+        - { signal: io.temperature, offset: 1, type: float32 }
+  - name: drive
+    position: 1
+    vendor_id: 0x2
+    product_code: 0x1234
+    outputs:
+      sync_manager: 2
+      address: 0x1100
+      length: 2
+      entries:
+        - { signal: drive.speed, offset: 0, type: int16 }
+",
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let segment = SimSegment {
+            slaves: vec![SimSlave::new(0x2, 0x1000), SimSlave::new(0x2, 0x1234)],
+            replies: VecDeque::new(),
+        };
+        let bus = SignalBus::new();
+        bus.set("drive.speed", Value::Integer(-40_000)).unwrap();
+        let mut master = EthercatMaster::new(config.clone(), segment);
+
+        let slaves = master.start(&bus).unwrap();
+        assert_eq!(slaves.len(), 2);
+        assert_eq!(slaves[1].product_code, 0x1234);
+        assert_eq!(slaves[1].station_address, 0x1001);
+        assert_eq!(master.transport.slaves[1].memory[0x130], AlState::Op as u8);
+
+        // Outputs reach the drive, saturated to int16
+        assert_eq!(master.transport.slaves[1].memory[0x1100..0x1102], i16::MIN.to_le_bytes());
+
+        // Inputs from the coupler reach the bus
+        let coupler = &mut master.transport.slaves[0].memory;
+        coupler[0x1180] = 0b100;
+        coupler[0x1181..0x1185].copy_from_slice(&21.5f32.to_le_bytes());
+        master.exchange(&bus).unwrap();
+        assert_eq!(bus.get("io.button"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("io.temperature"), Some(Value::Float(21.5)));
+
+        // A slave dropping off the segment fails the working counter check
+        master.transport.slaves.pop();
+        assert!(master.exchange(&bus).is_err());
+
+        // The wrong device at a position is rejected
+        let mut master = EthercatMaster::new(
+            config,
+            SimSegment {
+                slaves: vec![SimSlave::new(0x2, 0x1000), SimSlave::new(0x2, 0x9999)],
+                replies: VecDeque::new(),
+            },
+        );
+        assert!(master.start(&bus).is_err());
+    }
+}
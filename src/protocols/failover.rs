@@ -0,0 +1,288 @@
+// src/protocols/failover.rs
+//! Failover groups for redundant device connections
+//!
+//! A failover group names two or more registered protocol drivers that reach
+//! the same device, such as a pair of Modbus gateways, in order of
+//! preference. Reads and writes addressed to the group through
+//! [`ProtocolManager`](super::ProtocolManager) go to the active member:
+//!
+//! - After `failure_threshold` consecutive errors, or when the active member
+//!   is disconnected, the manager switches to the first other member that is
+//!   connected or can be connected and retries the operation there
+//! - While a backup is active the primary is probed every
+//!   `primary_retry_ms`, and the group returns to it as soon as it connects
+//! - The index of the active member (0 for the primary) is written to
+//!   `status_signal` and reported in the manager's diagnostics
+//!
+//! ```yaml
+//! protocols:
+//!   failover_groups:
+//!     - name: line1_plc
+//!       members: [gateway_a, gateway_b]
+//!       failure_threshold: 3
+//!       primary_retry_ms: 30000
+//!       status_signal: line1_plc.active_path
+//! ```
+
+use crate::error::{PlcError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Redundant connections to one device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverGroupConfig {
+    /// Name reads and writes address the group by
+    pub name: String,
+
+    /// Driver names, primary first
+    pub members: Vec<String>,
+
+    /// Consecutive errors on the active member before switching
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How often the primary is retried while a backup is active
+    #[serde(default = "default_primary_retry_ms")]
+    pub primary_retry_ms: u64,
+
+    /// Integer signal receiving the index of the active member
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_signal: Option<String>,
+}
+
+const fn default_failure_threshold() -> u32 {
+    3
+}
+
+const fn default_primary_retry_ms() -> u64 {
+    30_000
+}
+
+impl FailoverGroupConfig {
+    /// Validate the group definition
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty name, fewer than two
+    /// members, a repeated member or a zero failure threshold.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(PlcError::Config("Failover group name cannot be empty".to_string()));
+        }
+        if self.members.len() < 2 {
+            return Err(PlcError::Config(format!(
+                "Failover group '{}' needs at least two members",
+                self.name
+            )));
+        }
+        let mut seen = HashSet::new();
+        if let Some(member) = self.members.iter().find(|m| !seen.insert(m.as_str())) {
+            return Err(PlcError::Config(format!(
+                "Failover group '{}' lists member '{member}' more than once",
+                self.name
+            )));
+        }
+        if self.failure_threshold == 0 {
+            return Err(PlcError::Config(format!(
+                "Failover group '{}' failure_threshold must be at least 1",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Runtime state of a failover group
+#[derive(Debug)]
+pub(crate) struct FailoverGroup {
+    pub(crate) config: FailoverGroupConfig,
+    active: usize,
+    consecutive_failures: u32,
+    last_primary_attempt: Instant,
+    switches: u64,
+}
+
+impl FailoverGroup {
+    pub(crate) fn new(config: FailoverGroupConfig) -> Self {
+        Self {
+            config,
+            active: 0,
+            consecutive_failures: 0,
+            last_primary_attempt: Instant::now(),
+            switches: 0,
+        }
+    }
+
+    /// Index of the active member
+    pub(crate) const fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Driver name of the active member
+    pub(crate) fn active_member(&self) -> &str {
+        &self.config.members[self.active]
+    }
+
+    /// Number of times the active member has changed
+    pub(crate) const fn switches(&self) -> u64 {
+        self.switches
+    }
+
+    pub(crate) fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    /// Count an error; true once the failure threshold is reached
+    pub(crate) fn record_failure(&mut self) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.consecutive_failures >= self.config.failure_threshold
+    }
+
+    /// Whether a backup is active and the primary is due to be retried
+    pub(crate) fn primary_retry_due(&mut self) -> bool {
+        if self.active == 0
+            || self.last_primary_attempt.elapsed() < Duration::from_millis(self.config.primary_retry_ms)
+        {
+            return false;
+        }
+        self.last_primary_attempt = Instant::now();
+        true
+    }
+
+    /// Members other than the active one, in order of preference
+    pub(crate) fn candidates(&self) -> Vec<(usize, String)> {
+        self.config
+            .members
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != self.active)
+            .map(|(index, member)| (index, member.clone()))
+            .collect()
+    }
+
+    /// Make member `index` active
+    pub(crate) fn switch_to(&mut self, index: usize) {
+        if index != self.active {
+            self.active = index;
+            self.switches += 1;
+            self.last_primary_attempt = Instant::now();
+        }
+        self.consecutive_failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::{ProtocolDriver, ProtocolManager};
+    use crate::signal::SignalBus;
+    use crate::value::Value;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// Gateway that is unreachable while `up` is cleared
+    struct Gateway {
+        up: Arc<AtomicBool>,
+        connected: bool,
+    }
+
+    #[async_trait]
+    impl ProtocolDriver for Gateway {
+        async fn connect(&mut self) -> Result<()> {
+            if !self.up.load(Ordering::SeqCst) {
+                return Err(PlcError::Protocol("gateway unreachable".to_string()));
+            }
+            self.connected = true;
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            self.connected = false;
+            Ok(())
+        }
+
+        async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+            Ok(addresses.iter().map(|address| (address.clone(), Value::Integer(1))).collect())
+        }
+
+        async fn write_values(&mut self, _values: &HashMap<String, Value>) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.connected && self.up.load(Ordering::SeqCst)
+        }
+
+        fn protocol_name(&self) -> &'static str {
+            "gateway"
+        }
+    }
+
+    /// Manager with group `plc` over `gateway_a` and `gateway_b`, both
+    /// connected, and the switches that take each of them down
+    async fn manager(bus: &SignalBus) -> (ProtocolManager, Arc<AtomicBool>, Arc<AtomicBool>) {
+        let manager = ProtocolManager::new(bus.clone());
+        let primary = Arc::new(AtomicBool::new(true));
+        let backup = Arc::new(AtomicBool::new(true));
+        for (name, up) in [("gateway_a", &primary), ("gateway_b", &backup)] {
+            let gateway = Gateway { up: up.clone(), connected: false };
+            manager.add_driver(name.to_string(), Box::new(gateway)).await.unwrap();
+        }
+        manager.connect_all().await.unwrap();
+        let config: FailoverGroupConfig = serde_yaml::from_str(
+            "name: plc\nmembers: [gateway_a, gateway_b]\nfailure_threshold: 1\n\
+             primary_retry_ms: 0\nstatus_signal: plc.active_path\n",
+        )
+        .unwrap();
+        manager.add_failover_group(config).await.unwrap();
+        (manager, primary, backup)
+    }
+
+    #[tokio::test]
+    async fn test_returns_to_primary() {
+        let bus = SignalBus::new();
+        let (manager, primary, _backup) = manager(&bus).await;
+        let addresses = vec!["40001".to_string()];
+
+        // A lost primary connection moves the group to the backup
+        primary.store(false, Ordering::SeqCst);
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_b"));
+        assert_eq!(bus.get("plc.active_path"), Some(Value::Integer(1)));
+
+        // The primary is probed while it stays down and taken back once up
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_b"));
+        primary.store(true, Ordering::SeqCst);
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_a"));
+        assert_eq!(bus.get("plc.active_path"), Some(Value::Integer(0)));
+
+        let diag = manager.all_diagnostics().await;
+        assert_eq!(diag["plc"].get("switch_count"), Some(&Value::Integer(2)));
+    }
+
+    #[tokio::test]
+    async fn test_all_members_failing() {
+        let bus = SignalBus::new();
+        let (manager, primary, backup) = manager(&bus).await;
+        let addresses = vec!["40001".to_string()];
+
+        // With every member down operations fail and the group stays put
+        primary.store(false, Ordering::SeqCst);
+        backup.store(false, Ordering::SeqCst);
+        assert!(manager.read_from("plc", &addresses).await.is_err());
+        assert!(manager.write_to("plc", &HashMap::from([("40001".to_string(), Value::Integer(2))])).await.is_err());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_a"));
+        assert_eq!(bus.get("plc.active_path"), Some(Value::Integer(0)));
+
+        // The first member to come back takes over
+        backup.store(true, Ordering::SeqCst);
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_b"));
+        assert_eq!(bus.get("plc.active_path"), Some(Value::Integer(1)));
+    }
+}
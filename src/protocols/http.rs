@@ -0,0 +1,662 @@
+// src/protocols/http.rs
+//! Generic HTTP polling and push driver
+//!
+//! Each endpoint is requested every `interval_ms`. Endpoints with `values`
+//! are fetched with GET and the values selected by their `JSONPath`
+//! expressions are written to signals. Endpoints with `post` receive the
+//! listed signals as a JSON body whenever one of them changes:
+//!
+//! ```json
+//! {"timestamp": 1718000000000, "values": {"line1.count": 42}}
+//! ```
+//!
+//! ```yaml
+//! protocols:
+//!   http:
+//!     endpoints:
+//!       - name: weather
+//!         url: https://weather.example.com/v1/current?site=plant2
+//!         interval_ms: 60000
+//!         auth:
+//!           type: bearer
+//!           token: "eyJhbGciOi..."
+//!         values:
+//!           - path: $.current.temperature
+//!             signal: site.outdoor_temp
+//!           - path: $.alerts[0].active
+//!             signal: site.weather_alert
+//!       - name: chiller
+//!         url: http://10.0.7.31/status.xml
+//!         format: xml
+//!         auth:
+//!           type: basic
+//!           username: reader
+//!           password: "..."
+//!         values:
+//!           - path: $.status.circuit[1].@pressure
+//!             signal: chiller.circuit2_pressure
+//!       - name: mes
+//!         url: https://mes.example.com/api/line1/values
+//!         interval_ms: 500
+//!         auth:
+//!           type: header
+//!           name: X-Api-Key
+//!           value: "..."
+//!         post: [line1.count, line1.speed]
+//! ```
+//!
+//! Paths support the `JSONPath` subset used to address a single value: member
+//! names (`.name` or `['name']`) and array indices (`[0]`, `[-1]` counting
+//! from the end). XML documents are read as JSON with the root element as
+//! the only member of the document, attributes as `@name` members, text of
+//! elements with attributes or children as `#text`, and repeated child
+//! elements as arrays. Without `format` the response's `Content-Type`
+//! decides.
+//!
+//! Numbers and booleans given as strings are parsed, and values are
+//! converted to the type the signal currently holds.
+
+use super::ChangeTracker;
+use crate::scan_budget::{self, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// HTTP driver configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Polled and pushed endpoints
+    pub endpoints: Vec<HttpEndpointConfig>,
+}
+
+/// An endpoint polled for values, pushed signal changes, or both
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpEndpointConfig {
+    /// Endpoint name used in logs
+    pub name: String,
+
+    /// Request URL
+    pub url: String,
+
+    /// Poll interval, and how often `post` signals are checked for changes
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+
+    /// Request timeout
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Credentials sent with every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+
+    /// Additional request headers
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Response format; taken from the `Content-Type` header when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<HttpFormat>,
+
+    /// Values read from the response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<HttpValueConfig>,
+
+    /// Signals posted when one of them changes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post: Vec<String>,
+}
+
+/// Request authentication
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    /// HTTP basic authentication
+    Basic { username: String, password: String },
+    /// `Authorization: Bearer` token
+    Bearer { token: String },
+    /// API key in a custom header
+    Header { name: String, value: String },
+}
+
+/// Response body format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpFormat {
+    Json,
+    Xml,
+}
+
+/// A value selected from the response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpValueConfig {
+    /// `JSONPath` of the value, e.g. `$.sensors[0].value`
+    pub path: String,
+
+    /// Signal receiving the value
+    pub signal: String,
+}
+
+const fn default_interval_ms() -> u64 {
+    10_000
+}
+
+const fn default_timeout_ms() -> u64 {
+    5000
+}
+
+impl HttpConfig {
+    /// Validate URLs, headers and paths
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.endpoints.is_empty() {
+            return Err(PlcError::Config("HTTP driver requires at least one endpoint".to_string()));
+        }
+        let mut names = HashSet::new();
+        for endpoint in &self.endpoints {
+            if !names.insert(endpoint.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate HTTP endpoint '{}'", endpoint.name)));
+            }
+            endpoint.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl HttpEndpointConfig {
+    fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| PlcError::Config(format!("HTTP endpoint '{}': {reason}", self.name));
+        let url = reqwest::Url::parse(&self.url).map_err(|e| invalid(format!("invalid url: {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid(format!("unsupported scheme '{}'", url.scheme())));
+        }
+        if self.interval_ms == 0 || self.timeout_ms == 0 {
+            return Err(invalid("interval_ms and timeout_ms must be greater than 0".to_string()));
+        }
+        if self.values.is_empty() && self.post.is_empty() {
+            return Err(invalid("needs values to poll or signals to post".to_string()));
+        }
+        self.headers().map_err(|e| invalid(e.to_string()))?;
+        for value in &self.values {
+            JsonPath::parse(&value.path).map_err(|e| invalid(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Configured headers and header authentication
+    fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        let extra = match &self.auth {
+            Some(HttpAuth::Header { name, value }) => Some((name, value)),
+            _ => None,
+        };
+        for (name, value) in self.headers.iter().chain(extra) {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| PlcError::Config(format!("invalid header name '{name}'")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| PlcError::Config(format!("invalid value for header '{name}'")))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+// ============================================================================
+// JSONPATH
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Member(String),
+    Index(i64),
+}
+
+/// Path to a single value in a JSON document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    /// Parse a path such as `$.sensors[0]['flow rate']`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for syntax outside the supported subset.
+    pub fn parse(path: &str) -> Result<Self> {
+        let unsupported = |reason: &str| PlcError::Config(format!("JSONPath '{path}': {reason}"));
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| unsupported("must start with '$'"))?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(member) = rest.strip_prefix('.') {
+                if member.starts_with('.') {
+                    return Err(unsupported("recursive descent is not supported"));
+                }
+                let end = member.find(['.', '[']).unwrap_or(member.len());
+                let name = &member[..end];
+                if name.is_empty() || name == "*" {
+                    return Err(unsupported("expected a member name after '.'"));
+                }
+                segments.push(Segment::Member(name.to_string()));
+                rest = &member[end..];
+            } else if let Some(bracket) = rest.strip_prefix('[') {
+                let end = bracket.find(']').ok_or_else(|| unsupported("unclosed '['"))?;
+                let inner = bracket[..end].trim();
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                if let Some(name) = quoted {
+                    segments.push(Segment::Member(name.to_string()));
+                } else {
+                    let index = inner
+                        .parse()
+                        .map_err(|_| unsupported("only quoted names and indices are supported in brackets"))?;
+                    segments.push(Segment::Index(index));
+                }
+                rest = &bracket[end + 1..];
+            } else {
+                return Err(unsupported("expected '.' or '['"));
+            }
+        }
+        Ok(Self(segments))
+    }
+
+    /// The value at this path, if the document has one
+    #[must_use]
+    pub fn select<'a>(&self, document: &'a Json) -> Option<&'a Json> {
+        self.0.iter().try_fold(document, |value, segment| match segment {
+            Segment::Member(name) => value.get(name),
+            Segment::Index(index) => {
+                let array = value.as_array()?;
+                let position = if *index < 0 {
+                    array.len().checked_sub(usize::try_from(index.unsigned_abs()).ok()?)?
+                } else {
+                    usize::try_from(*index).ok()?
+                };
+                array.get(position)
+            }
+        })
+    }
+}
+
+// ============================================================================
+// DOCUMENTS
+// ============================================================================
+
+/// XML document as JSON, see the module documentation for the mapping
+fn xml_to_json(text: &str) -> Result<Json> {
+    let document =
+        roxmltree::Document::parse(text).map_err(|e| PlcError::Protocol(format!("Invalid XML response: {e}")))?;
+    let root = document.root_element();
+    let mut object = Map::new();
+    object.insert(root.tag_name().name().to_string(), element_to_json(root));
+    Ok(Json::Object(object))
+}
+
+fn element_to_json(element: roxmltree::Node<'_, '_>) -> Json {
+    let text: String = element
+        .children()
+        .filter(roxmltree::Node::is_text)
+        .filter_map(|n| n.text())
+        .collect();
+    let text = text.trim();
+    let has_children = element.children().any(|n| n.is_element());
+    if element.attributes().len() == 0 && !has_children {
+        return Json::String(text.to_string());
+    }
+
+    let mut object = Map::new();
+    for attribute in element.attributes() {
+        object.insert(format!("@{}", attribute.name()), Json::String(attribute.value().to_string()));
+    }
+    for child in element.children().filter(roxmltree::Node::is_element) {
+        let name = child.tag_name().name().to_string();
+        let value = element_to_json(child);
+        match object.get_mut(&name) {
+            Some(Json::Array(items)) => items.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Json::Array(vec![first, value]);
+            }
+            None => {
+                object.insert(name, value);
+            }
+        }
+    }
+    if !text.is_empty() {
+        object.insert("#text".to_string(), Json::String(text.to_string()));
+    }
+    Json::Object(object)
+}
+
+/// Signal value of a selected JSON value
+fn json_to_value(json: &Json) -> Option<Value> {
+    match json {
+        Json::Bool(b) => Some(Value::Bool(*b)),
+        Json::Number(n) => n.as_i64().map(Value::Integer).or_else(|| n.as_f64().map(Value::Float)),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Plain JSON form of a signal value for posted bodies
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Integer(i) => Json::from(*i),
+        Value::Float(f) => Json::from(*f),
+        #[allow(unreachable_patterns)]
+        other => Json::String(other.to_string()),
+    }
+}
+
+/// Convert a received value to the type the signal holds
+fn convert(value: Value, current: Option<&Value>) -> Value {
+    let converted = match current {
+        Some(Value::Bool(_)) => value.as_bool().map(Value::Bool),
+        Some(Value::Integer(_)) => value.as_integer().map(Value::Integer),
+        Some(Value::Float(_)) => value.as_float().map(Value::Float),
+        _ => None,
+    };
+    converted.unwrap_or(value)
+}
+
+// ============================================================================
+// DRIVER
+// ============================================================================
+
+fn http_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Protocol(format!("HTTP: {e}"))
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+/// One configured endpoint with its client and parsed paths
+struct Endpoint {
+    config: HttpEndpointConfig,
+    client: reqwest::Client,
+    paths: Vec<JsonPath>,
+    tracker: ChangeTracker,
+}
+
+impl Endpoint {
+    fn new(config: HttpEndpointConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .default_headers(config.headers()?)
+            .build()
+            .map_err(http_error)?;
+        let paths = config
+            .values
+            .iter()
+            .map(|v| JsonPath::parse(&v.path))
+            .collect::<Result<_>>()?;
+        let tracker = ChangeTracker::new(config.post.clone());
+        Ok(Self {
+            config,
+            client,
+            paths,
+            tracker,
+        })
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.auth {
+            Some(HttpAuth::Basic { username, password }) => request.basic_auth(username, Some(password)),
+            Some(HttpAuth::Bearer { token }) => request.bearer_auth(token),
+            Some(HttpAuth::Header { .. }) | None => request,
+        }
+    }
+
+    /// Fetch the endpoint and write the selected values
+    async fn poll(&self, bus: &SignalBus) -> Result<()> {
+        let response = self
+            .authorize(self.client.get(&self.config.url))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(http_error)?;
+        let format = self.config.format.unwrap_or_else(|| {
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            if content_type.contains("xml") {
+                HttpFormat::Xml
+            } else {
+                HttpFormat::Json
+            }
+        });
+        let body = response.text().await.map_err(http_error)?;
+        scan_budget::measure(Subsystem::Protocols, || self.apply(bus, &body, format))
+    }
+
+    fn apply(&self, bus: &SignalBus, body: &str, format: HttpFormat) -> Result<()> {
+        let document = match format {
+            HttpFormat::Json => serde_json::from_str(body)?,
+            HttpFormat::Xml => xml_to_json(body)?,
+        };
+        for (value, path) in self.config.values.iter().zip(&self.paths) {
+            let Some(selected) = path.select(&document) else {
+                debug!("HTTP endpoint '{}' has no value at {}", self.config.name, value.path);
+                continue;
+            };
+            let Some(received) = json_to_value(selected) else {
+                warn!("HTTP endpoint '{}': {} is not a scalar value", self.config.name, value.path);
+                continue;
+            };
+            let received = convert(received, bus.get(&value.signal).as_ref());
+            if let Err(e) = bus.set(&value.signal, received) {
+                warn!("HTTP could not write '{}': {}", value.signal, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Post the watched signals if any changed since the last post
+    async fn push(&mut self, bus: &SignalBus) -> Result<()> {
+        if self.tracker.changes(bus).is_empty() {
+            return Ok(());
+        }
+        let values: Map<String, Json> = self
+            .config
+            .post
+            .iter()
+            .filter_map(|signal| bus.get(signal).map(|v| (signal.clone(), value_to_json(&v))))
+            .collect();
+        let body = serde_json::json!({ "timestamp": now_ms(), "values": values });
+        self.authorize(self.client.post(&self.config.url))
+            .json(&body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(http_error)?;
+        Ok(())
+    }
+}
+
+async fn run_endpoint(mut endpoint: Endpoint, bus: SignalBus) {
+    let mut ticker = tokio::time::interval(Duration::from_millis(endpoint.config.interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut failing = false;
+    loop {
+        ticker.tick().await;
+        let mut result = Ok(());
+        if !endpoint.config.values.is_empty() {
+            result = endpoint.poll(&bus).await;
+        }
+        if result.is_ok() && !endpoint.config.post.is_empty() {
+            result = endpoint.push(&bus).await;
+            if result.is_err() {
+                // Post the same values again on the next interval
+                endpoint.tracker = ChangeTracker::new(endpoint.config.post.clone());
+            }
+        }
+        match result {
+            Ok(()) if failing => {
+                info!("HTTP endpoint '{}' recovered", endpoint.config.name);
+                failing = false;
+            }
+            Ok(()) => {}
+            // Log the first failure only, an unreachable endpoint fails every interval
+            Err(e) if !failing => {
+                warn!("HTTP endpoint '{}' failed: {}", endpoint.config.name, e);
+                failing = true;
+            }
+            Err(e) => debug!("HTTP endpoint '{}' still failing: {}", endpoint.config.name, e),
+        }
+    }
+}
+
+/// Poll and push every configured endpoint until the task is cancelled
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the configuration is invalid and
+/// [`PlcError::Protocol`] if an HTTP client cannot be created.
+pub async fn run(config: HttpConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let endpoints = config
+        .endpoints
+        .into_iter()
+        .map(Endpoint::new)
+        .collect::<Result<Vec<_>>>()?;
+    info!("HTTP driver started with {} endpoints", endpoints.len());
+    let tasks: Vec<_> = endpoints
+        .into_iter()
+        .map(|endpoint| tokio::spawn(run_endpoint(endpoint, bus.clone())))
+        .collect();
+    for task in tasks {
+        task.await
+            .map_err(|e| PlcError::Runtime(format!("HTTP endpoint task failed: {e}")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_json_path_and_xml() {
+        let document = serde_json::json!({
+            "sensors": [{"value": 1}, {"value": "2.5", "flow rate": true}],
+        });
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&document).cloned();
+        assert_eq!(select("$.sensors[1].value"), Some(Json::from("2.5")));
+        assert_eq!(select("$.sensors[-1]['flow rate']"), Some(Json::Bool(true)));
+        assert_eq!(select("$.sensors[2].value"), None);
+        assert!(JsonPath::parse("$..value").is_err());
+        assert!(JsonPath::parse("$.sensors[?(@.value)]").is_err());
+        assert!(JsonPath::parse("sensors").is_err());
+
+        let xml = xml_to_json(
+            r#"<status unit="1"><circuit pressure="4.2">on</circuit><circuit pressure="3.9"/><mode>auto</mode></status>"#,
+        )
+        .unwrap();
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&xml).and_then(json_to_value);
+        assert_eq!(select("$.status.circuit[1].@pressure"), Some(Value::Float(3.9)));
+        assert_eq!(select("$.status.circuit[0].#text"), Some(Value::Bool(true)));
+        assert_eq!(select("$.status.@unit"), Some(Value::Integer(1)));
+        assert_eq!(JsonPath::parse("$.status.mode").unwrap().select(&xml), Some(&Json::from("auto")));
+    }
+
+    /// Serve `body` to every request, forwarding each request to `requests`
+    async fn serve(content_type: &'static str, body: &'static str) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                // Read the headers, then as much body as they announce
+                while let Ok(len) = stream.read(&mut buf).await {
+                    request.extend_from_slice(&buf[..len]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, rest)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                            .unwrap_or(0);
+                        if len == 0 || rest.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                tx.send(String::from_utf8_lossy(&request).into_owned()).unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_poll_and_post() {
+        let (url, mut requests) = serve("application/json; charset=utf-8", r#"{"data":{"temp":"21.5","on":1}}"#).await;
+        let bus = SignalBus::new();
+        bus.set("line.on", Value::Bool(false)).unwrap();
+        bus.set("line.count", Value::Integer(7)).unwrap();
+
+        let config: HttpConfig = serde_yaml::from_str(&format!(
+            r#"
+endpoints:
+  - name: line
+    url: "{url}"
+    auth: {{type: bearer, token: secret}}
+    headers: {{X-Site: plant2}}
+    values:
+      - {{path: $.data.temp, signal: line.temp}}
+      - {{path: "$['data'].on", signal: line.on}}
+    post: [line.count]
+"#
+        ))
+        .unwrap();
+        config.validate().unwrap();
+        let mut endpoint = Endpoint::new(config.endpoints[0].clone()).unwrap();
+
+        endpoint.poll(&bus).await.unwrap();
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("GET /data"));
+        assert!(request.contains("authorization: Bearer secret"));
+        assert!(request.contains("x-site: plant2"));
+        assert_eq!(bus.get("line.temp"), Some(Value::Float(21.5)));
+        assert_eq!(bus.get("line.on"), Some(Value::Bool(true)));
+
+        endpoint.push(&bus).await.unwrap();
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("POST /data"));
+        let body: Json = serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(body["values"], serde_json::json!({"line.count": 7}));
+
+        // Nothing changed, so nothing is posted
+        endpoint.push(&bus).await.unwrap();
+        assert!(requests.try_recv().is_err());
+    }
+}
@@ -0,0 +1,596 @@
+// src/protocols/kafka.rs
+//! Kafka sink and source connector
+//!
+//! The sink publishes signal changes to a Kafka topic; sources consume
+//! topics and write the received values into signals. Every message carries
+//! one signal value and is keyed by the signal name, so all updates of a
+//! signal land on the same partition and stay in order.
+//!
+//! - The sink scans its signals every `scan_interval_ms` and produces a
+//!   record for each signal whose value changed. librdkafka batches the
+//!   records per partition according to `batch_size` and `linger_ms`.
+//! - A source subscribes to one or more topics as a member of `group_id` and
+//!   applies records in arrival order. `signals` restricts which signals a
+//!   source may write, and `prefix` is prepended to received signal names.
+//!
+//! The record timestamp is the time the value was written on the bus, which
+//! is the device timestamp for values a driver received with one. Sources
+//! keep it on the signals they write, with the record offset as sequence
+//! number (see [`SignalBus::get_with_meta`]).
+//!
+//! Records are encoded as JSON or as Avro binary using [`AVRO_SCHEMA`]:
+//!
+//! ```json
+//! { "signal": "line1.speed", "value": 42.5, "timestamp": 1700000000000 }
+//! ```
+//!
+//! With `schema_id` set, Avro records use the Confluent wire format (a zero
+//! byte and the big-endian schema ID before the body) so they can be read
+//! with a schema registry.
+//!
+//! ```yaml
+//! protocols:
+//!   kafka:
+//!     brokers: kafka1:9092,kafka2:9092
+//!     client_id: petra-line1
+//!     format: avro
+//!     schema_id: 12
+//!     properties:
+//!       compression.type: lz4
+//!     sink:
+//!       topic: plant.signals
+//!       signals: [line1.speed, line1.count]
+//!       linger_ms: 100
+//!     sources:
+//!       - topics: [plant.setpoints]
+//!         group_id: petra-line1
+//!         signals: [line1.speed_sp]
+//! ```
+
+use super::{ChangeTracker, SignalFilter};
+use crate::signal::WriteMeta;
+use crate::{PlcError, Result, SignalBus, Value};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::Message;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+
+/// Avro schema of a signal record
+pub const AVRO_SCHEMA: &str = r#"{"type":"record","name":"SignalValue","namespace":"petra","fields":[{"name":"signal","type":"string"},{"name":"value","type":["null","boolean","long","double","string"]},{"name":"timestamp","type":{"type":"long","logicalType":"timestamp-millis"}}]}"#;
+
+/// How long a record may wait for space in the producer queue
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Kafka connector configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaConfig {
+    /// Comma-separated bootstrap servers
+    pub brokers: String,
+
+    /// Client identifier reported to the brokers
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+
+    /// Record encoding
+    #[serde(default)]
+    pub format: KafkaFormat,
+
+    /// Schema registry ID written with Avro records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<u32>,
+
+    /// Additional librdkafka settings, e.g. security or compression
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+
+    /// Publishes signal changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink: Option<KafkaSinkConfig>,
+
+    /// Consume topics into signals
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<KafkaSourceConfig>,
+}
+
+/// Record encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaFormat {
+    #[default]
+    Json,
+    Avro,
+}
+
+/// Topic signal changes are published to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaSinkConfig {
+    pub topic: String,
+
+    /// Published signals; every signal on the bus when empty
+    #[serde(default)]
+    pub signals: Vec<String>,
+
+    /// Interval for checking signals for changes
+    #[serde(default = "default_scan_interval_ms")]
+    pub scan_interval_ms: u64,
+
+    /// Most records sent to the brokers in one batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// How long records wait to fill a batch
+    #[serde(default = "default_linger_ms")]
+    pub linger_ms: u64,
+}
+
+/// Topics consumed into signals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaSourceConfig {
+    pub topics: Vec<String>,
+
+    /// Consumer group sharing the topic partitions
+    pub group_id: String,
+
+    /// Signals the source may write; any signal when empty
+    #[serde(default)]
+    pub signals: Vec<String>,
+
+    /// Prepended to received signal names
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+fn default_client_id() -> String {
+    "petra".to_string()
+}
+
+const fn default_scan_interval_ms() -> u64 {
+    500
+}
+
+const fn default_batch_size() -> usize {
+    1000
+}
+
+const fn default_linger_ms() -> u64 {
+    50
+}
+
+impl KafkaConfig {
+    /// Validate brokers, sink and sources
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.brokers.trim().is_empty() {
+            return Err(PlcError::Config("Kafka brokers cannot be empty".to_string()));
+        }
+        if self.schema_id.is_some() && self.format != KafkaFormat::Avro {
+            return Err(PlcError::Config("Kafka schema_id requires the avro format".to_string()));
+        }
+        if self.sink.is_none() && self.sources.is_empty() {
+            return Err(PlcError::Config("Kafka configuration has no sink or sources".to_string()));
+        }
+        if let Some(sink) = &self.sink {
+            if sink.topic.is_empty() {
+                return Err(PlcError::Config("Kafka sink topic cannot be empty".to_string()));
+            }
+            if sink.scan_interval_ms == 0 || sink.batch_size == 0 {
+                return Err(PlcError::Config(
+                    "Kafka sink scan_interval_ms and batch_size must be at least 1".to_string(),
+                ));
+            }
+        }
+        for source in &self.sources {
+            if source.topics.is_empty() || source.topics.iter().any(String::is_empty) {
+                return Err(PlcError::Config(format!(
+                    "Kafka source of group '{}' needs non-empty topics",
+                    source.group_id
+                )));
+            }
+            if source.group_id.is_empty() {
+                return Err(PlcError::Config("Kafka source group_id cannot be empty".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn client_config(&self) -> ClientConfig {
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &self.brokers)
+            .set("client.id", &self.client_id);
+        for (key, value) in &self.properties {
+            client.set(key, value);
+        }
+        client
+    }
+}
+
+// ============================================================================
+// RECORD ENCODING
+// ============================================================================
+
+/// One signal value as carried in a Kafka record
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalRecord {
+    pub signal: String,
+    pub value: Value,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+}
+
+impl SignalRecord {
+    /// Encode the record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Json`] if JSON encoding fails.
+    pub fn encode(&self, format: KafkaFormat, schema_id: Option<u32>) -> Result<Vec<u8>> {
+        match format {
+            KafkaFormat::Json => {
+                #[allow(unreachable_patterns)]
+                let value = match &self.value {
+                    Value::Bool(b) => serde_json::Value::from(*b),
+                    Value::Integer(i) => serde_json::Value::from(*i),
+                    Value::Float(f) => serde_json::Value::from(*f),
+                    other => serde_json::Value::from(other.to_string()),
+                };
+                let record = serde_json::json!({
+                    "signal": self.signal,
+                    "value": value,
+                    "timestamp": self.timestamp,
+                });
+                Ok(serde_json::to_vec(&record)?)
+            }
+            KafkaFormat::Avro => {
+                let mut out = Vec::with_capacity(self.signal.len() + 24);
+                if let Some(id) = schema_id {
+                    out.push(0);
+                    out.extend_from_slice(&id.to_be_bytes());
+                }
+                put_avro_string(&mut out, &self.signal);
+                #[allow(unreachable_patterns)]
+                match &self.value {
+                    Value::Bool(b) => {
+                        put_avro_long(&mut out, 1);
+                        out.push(u8::from(*b));
+                    }
+                    Value::Integer(i) => {
+                        put_avro_long(&mut out, 2);
+                        put_avro_long(&mut out, *i);
+                    }
+                    Value::Float(f) => {
+                        put_avro_long(&mut out, 3);
+                        out.extend_from_slice(&f.to_le_bytes());
+                    }
+                    other => {
+                        put_avro_long(&mut out, 4);
+                        put_avro_string(&mut out, &other.to_string());
+                    }
+                }
+                put_avro_long(&mut out, self.timestamp);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Decode a record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Protocol`] for a malformed record, a missing value
+    /// or, without extended types, a string value.
+    pub fn decode(bytes: &[u8], format: KafkaFormat, schema_id: Option<u32>) -> Result<Self> {
+        match format {
+            KafkaFormat::Json => {
+                let record: serde_json::Value =
+                    serde_json::from_slice(bytes).map_err(|e| malformed(&e.to_string()))?;
+                let signal = record["signal"]
+                    .as_str()
+                    .ok_or_else(|| malformed("missing signal"))?
+                    .to_string();
+                let value = match &record["value"] {
+                    serde_json::Value::Bool(b) => Value::Bool(*b),
+                    serde_json::Value::Number(n) => match n.as_i64() {
+                        Some(i) => Value::Integer(i),
+                        None => Value::Float(n.as_f64().ok_or_else(|| malformed("invalid number"))?),
+                    },
+                    serde_json::Value::String(s) => string_value(s.clone())?,
+                    _ => return Err(malformed("missing value")),
+                };
+                let timestamp = record["timestamp"].as_i64().unwrap_or_else(now_ms);
+                Ok(Self { signal, value, timestamp })
+            }
+            KafkaFormat::Avro => {
+                let mut reader = AvroReader { bytes, pos: 0 };
+                if schema_id.is_some() {
+                    let header = reader.take(5)?;
+                    if header[0] != 0 {
+                        return Err(malformed("missing schema registry header"));
+                    }
+                }
+                let signal = reader.string()?;
+                let value = match reader.long()? {
+                    1 => Value::Bool(reader.take(1)?[0] != 0),
+                    2 => Value::Integer(reader.long()?),
+                    3 => {
+                        let bytes: [u8; 8] = reader.take(8)?.try_into().map_err(|_| malformed("short double"))?;
+                        Value::Float(f64::from_le_bytes(bytes))
+                    }
+                    4 => string_value(reader.string()?)?,
+                    0 => return Err(malformed("missing value")),
+                    index => return Err(malformed(&format!("invalid union index {index}"))),
+                };
+                let timestamp = reader.long()?;
+                Ok(Self { signal, value, timestamp })
+            }
+        }
+    }
+}
+
+fn malformed(reason: &str) -> PlcError {
+    PlcError::Protocol(format!("Malformed Kafka record: {reason}"))
+}
+
+#[cfg(feature = "extended-types")]
+#[allow(clippy::unnecessary_wraps)]
+fn string_value(s: String) -> Result<Value> {
+    Ok(Value::String(s))
+}
+
+#[cfg(not(feature = "extended-types"))]
+fn string_value(_s: String) -> Result<Value> {
+    Err(malformed("string values require extended types"))
+}
+
+fn put_avro_long(out: &mut Vec<u8>, value: i64) {
+    #[allow(clippy::cast_sign_loss)]
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        #[allow(clippy::cast_possible_truncation)]
+        out.push((zigzag as u8 & 0x7f) | 0x80);
+        zigzag >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    out.push(zigzag as u8);
+}
+
+fn put_avro_string(out: &mut Vec<u8>, s: &str) {
+    put_avro_long(out, i64::try_from(s.len()).unwrap_or(i64::MAX));
+    out.extend_from_slice(s.as_bytes());
+}
+
+struct AvroReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AvroReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or_else(|| malformed("truncated record"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn long(&mut self) -> Result<i64> {
+        let mut zigzag = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            zigzag |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                #[allow(clippy::cast_possible_wrap)]
+                return Ok((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+            }
+        }
+        Err(malformed("varint too long"))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = usize::try_from(self.long()?).map_err(|_| malformed("negative length"))?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| malformed("invalid UTF-8"))
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+// ============================================================================
+// SINK
+// ============================================================================
+
+async fn run_sink(config: KafkaConfig, sink: KafkaSinkConfig, bus: SignalBus) -> Result<()> {
+    let producer: FutureProducer = config
+        .client_config()
+        .set("linger.ms", sink.linger_ms.to_string())
+        .set("batch.num.messages", sink.batch_size.to_string())
+        .create()
+        .map_err(kafka_error)?;
+    info!("Kafka sink publishing to '{}' via {}", sink.topic, config.brokers);
+
+    let mut tracker = ChangeTracker::new(sink.signals.clone());
+    let mut ticker = tokio::time::interval(Duration::from_millis(sink.scan_interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let timestamp = now_ms();
+        for batch in tracker.changes(&bus).chunks(sink.batch_size) {
+            let mut deliveries = Vec::with_capacity(batch.len());
+            for (signal, value) in batch {
+                let record = SignalRecord {
+                    signal: signal.clone(),
+                    value: value.clone(),
+                    timestamp: bus.get_with_meta(signal).map_or(timestamp, |(_, meta)| meta.unix_millis()),
+                };
+                let payload = match record.encode(config.format, config.schema_id) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!("Cannot encode Kafka record for '{}': {}", signal, e);
+                        continue;
+                    }
+                };
+                let record = FutureRecord::to(&sink.topic)
+                    .key(signal.as_str())
+                    .payload(&payload)
+                    .timestamp(timestamp);
+                match producer.send_result(record) {
+                    Ok(delivery) => deliveries.push(delivery),
+                    // Queue full: wait for space, then continue the batch
+                    Err((_, record)) => {
+                        if let Err((e, _)) = producer.send(record, QUEUE_TIMEOUT).await {
+                            warn!("Kafka delivery of '{}' failed: {}", signal, e);
+                        }
+                    }
+                }
+            }
+
+            let sent = deliveries.len();
+            let mut failed = 0;
+            for delivery in deliveries {
+                if !matches!(delivery.await, Ok(Ok(_))) {
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                warn!("Kafka sink: {} of {} records to '{}' failed", failed, sent, sink.topic);
+            } else {
+                debug!("Kafka sink published {} records to '{}'", sent, sink.topic);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SOURCE
+// ============================================================================
+
+async fn run_source(config: KafkaConfig, source: KafkaSourceConfig, bus: SignalBus) -> Result<()> {
+    let consumer: StreamConsumer = config
+        .client_config()
+        .set("group.id", &source.group_id)
+        .create()
+        .map_err(kafka_error)?;
+    let topics: Vec<&str> = source.topics.iter().map(String::as_str).collect();
+    consumer.subscribe(&topics).map_err(kafka_error)?;
+    info!("Kafka source consuming {:?} as group '{}'", source.topics, source.group_id);
+
+    let filter = SignalFilter::new(&source.signals, source.prefix.as_deref());
+    loop {
+        let message = match consumer.recv().await {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Kafka consumer error: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let Some(payload) = message.payload() else {
+            continue;
+        };
+        let record = match SignalRecord::decode(payload, config.format, config.schema_id) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Skipping record from '{}' at offset {}: {}", message.topic(), message.offset(), e);
+                continue;
+            }
+        };
+        let Some(signal) = filter.target(&record.signal) else {
+            debug!("Kafka source ignored signal '{}'", record.signal);
+            continue;
+        };
+        let mut meta = WriteMeta::from_source(Some("kafka"))
+            .at(UNIX_EPOCH + Duration::from_millis(u64::try_from(record.timestamp).unwrap_or(0)));
+        if let Ok(offset) = u64::try_from(message.offset()) {
+            meta = meta.with_sequence(offset);
+        }
+        if let Err(e) = bus.set_with_meta(&signal, record.value, meta) {
+            warn!("Kafka source could not write '{}': {}", signal, e);
+        }
+    }
+}
+
+fn kafka_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Protocol(format!("Kafka: {e}"))
+}
+
+/// Run the configured sink and sources until the task is cancelled
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid or a client cannot be
+/// created.
+pub async fn run(config: KafkaConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let mut tasks = tokio::task::JoinSet::new();
+    if let Some(sink) = config.sink.clone() {
+        tasks.spawn(run_sink(config.clone(), sink, bus.clone()));
+    }
+    for source in config.sources.clone() {
+        tasks.spawn(run_source(config.clone(), source, bus.clone()));
+    }
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(Err(e)) => {
+                tasks.abort_all();
+                return Err(e);
+            }
+            Err(e) => error!("Kafka task failed: {}", e),
+            Ok(Ok(())) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_encoding() {
+        let records = [
+            SignalRecord { signal: "line1.speed".into(), value: Value::Float(42.5), timestamp: 1_700_000_000_000 },
+            SignalRecord { signal: "line1.count".into(), value: Value::Integer(-300), timestamp: 1 },
+            SignalRecord { signal: "line1.run".into(), value: Value::Bool(true), timestamp: 0 },
+        ];
+        for record in &records {
+            for (format, schema_id) in [(KafkaFormat::Json, None), (KafkaFormat::Avro, None), (KafkaFormat::Avro, Some(12))] {
+                let bytes = record.encode(format, schema_id).unwrap();
+                assert_eq!(&SignalRecord::decode(&bytes, format, schema_id).unwrap(), record);
+            }
+        }
+
+        let bytes = records[0].encode(KafkaFormat::Json, None).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["value"], 42.5);
+
+        // Avro: zigzag-encoded string length, union index, value, timestamp
+        let bytes = records[1].encode(KafkaFormat::Avro, Some(12)).unwrap();
+        assert_eq!(&bytes[..5], &[0, 0, 0, 0, 12]);
+        assert_eq!(bytes[5], 22);
+        assert_eq!(&bytes[17..], &[4, 215, 4, 2]);
+        assert!(SignalRecord::decode(&bytes[..bytes.len() - 1], KafkaFormat::Avro, Some(12)).is_err());
+    }
+
+    #[test]
+    fn test_source_config() {
+        let source: KafkaSourceConfig =
+            serde_yaml::from_str("topics: [t]\ngroup_id: g\nsignals: [a]\nprefix: remote.\n").unwrap();
+        let filter = SignalFilter::new(&source.signals, source.prefix.as_deref());
+        assert_eq!(filter.target("a").as_deref(), Some("remote.a"));
+        assert_eq!(filter.target("b"), None);
+    }
+}
@@ -24,12 +24,17 @@
 //
 // ================================================================================
 
-use crate::{error::Result, value::Value, signal::SignalBus};
+use crate::{error::Result, events::{EventKind, EventLog}, scan_budget::{instrument, Subsystem}, value::Value, signal::SignalBus};
+use failover::{FailoverGroup, FailoverGroupConfig};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Default time a driver reload waits for in-flight requests
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
 // ================================================================================
 // PROTOCOL DRIVER TRAIT
 // ================================================================================
@@ -226,6 +231,49 @@ pub trait ProtocolDriver: Send + Sync {
     fn capabilities(&self) -> HashMap<&'static str, Value> {
         HashMap::new()
     }
+    
+    /// Apply new driver parameters while the engine keeps running (optional)
+    /// 
+    /// `config` has the shape of the driver's configuration section, e.g.
+    /// a new poll rate or address map. [`ProtocolManager::reload_driver`]
+    /// calls this only once in-flight reads and writes have drained. The
+    /// driver may drop its connection if the change requires it; the
+    /// manager reconnects it afterwards.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `PlcError::Config` if the parameters are rejected, in which
+    /// case the driver must keep its previous parameters. The default
+    /// implementation reports that the driver cannot be reconfigured.
+    async fn reconfigure(&mut self, _config: &serde_yaml::Value) -> Result<()> {
+        Err(crate::error::PlcError::Config(format!(
+            "{} does not support runtime reconfiguration",
+            self.protocol_name()
+        )))
+    }
+    
+    /// Read values buffered by the device between `start` and `end` (optional)
+    /// 
+    /// Devices that keep their own history, such as OPC-UA servers with
+    /// historical access or RTUs with event buffers, return the recorded
+    /// samples with their source timestamps. Used to backfill PETRA history
+    /// after an outage (see [`backfill`]).
+    /// 
+    /// # Errors
+    /// 
+    /// Returns `PlcError::Protocol` if the read fails. The default
+    /// implementation reports that the protocol does not buffer history.
+    async fn read_history(
+        &self,
+        _addresses: &[String],
+        _start: chrono::DateTime<chrono::Utc>,
+        _end: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<backfill::HistorySample>> {
+        Err(crate::error::PlcError::Protocol(format!(
+            "{} does not provide buffered history",
+            self.protocol_name()
+        )))
+    }
 }
 
 // ================================================================================
@@ -239,6 +287,10 @@ pub trait ProtocolDriver: Send + Sync {
 /// It handles driver lifecycle, connection management, and routing of
 /// read/write operations to the appropriate drivers.
 /// 
+/// Redundant drivers for the same device can be combined into a failover
+/// group (see [`failover`]); reads and writes addressed to the group name
+/// are routed to whichever member is currently active.
+/// 
 /// # Thread Safety
 /// 
 /// All methods are thread-safe and can be called concurrently from
@@ -273,9 +325,25 @@ pub struct ProtocolManager {
     /// Thread-safe collection of protocol drivers
     drivers: Arc<RwLock<HashMap<String, Box<dyn ProtocolDriver>>>>,
     
+    /// Failover groups keyed by group name
+    groups: Arc<RwLock<HashMap<String, FailoverGroup>>>,
+    
     /// Reference to the signal bus for data exchange
     signal_bus: SignalBus,
     
+    /// Event stream that connects and disconnects are published to
+    events: Option<EventLog>,
+    
+    /// How long a reload waits for in-flight requests to finish
+    drain_timeout: Duration,
+    
+    /// Queue taking over writes that keep failing
+    dead_letters: Option<Arc<dead_letter::DeadLetterQueue>>,
+    
+    /// Connections in maintenance bypass the dead-letter queue
+    #[cfg(feature = "maintenance-mode")]
+    maintenance_mode: Option<crate::maintenance_mode::MaintenanceMode>,
+    
     /// Performance metrics (when monitoring features are enabled)
     #[cfg(feature = "enhanced-monitoring")]
     metrics: Arc<RwLock<ProtocolMetrics>>,
@@ -298,7 +366,13 @@ impl ProtocolManager {
     pub fn new(signal_bus: SignalBus) -> Self {
         Self {
             drivers: Arc::new(RwLock::new(HashMap::new())),
+            groups: Arc::new(RwLock::new(HashMap::new())),
             signal_bus,
+            events: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            dead_letters: None,
+            #[cfg(feature = "maintenance-mode")]
+            maintenance_mode: None,
             #[cfg(feature = "enhanced-monitoring")]
             metrics: Arc::new(RwLock::new(ProtocolMetrics {
                 read_count: HashMap::new(),
@@ -309,10 +383,71 @@ impl ProtocolManager {
         }
     }
     
+    /// Publish driver connects and disconnects to an event stream
+    /// 
+    /// Usually the engine's, from [`Engine::events`](crate::Engine::events).
+    #[must_use]
+    pub fn with_events(mut self, events: EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
+    
+    /// Limit how long [`reload_driver`](Self::reload_driver) waits for
+    /// in-flight reads and writes before giving up (default 5 s)
+    #[must_use]
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+    
+    /// Hand writes that keep failing to a dead-letter queue
+    /// 
+    /// See [`dead_letter`] for how letters are retried.
+    #[must_use]
+    pub fn with_dead_letters(mut self, queue: Arc<dead_letter::DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(queue);
+        self
+    }
+    
+    /// Stop dead-lettering writes to connections `mode` puts in maintenance
+    /// 
+    /// A device being worked on is expected to reject writes, and replaying
+    /// them once it returns to service would be surprising.
+    #[cfg(feature = "maintenance-mode")]
+    #[must_use]
+    pub fn with_maintenance_mode(mut self, mode: crate::maintenance_mode::MaintenanceMode) -> Self {
+        self.maintenance_mode = Some(mode);
+        self
+    }
+    
+    #[cfg_attr(not(feature = "maintenance-mode"), allow(clippy::unused_self))]
+    fn in_maintenance(&self, protocol: &str) -> bool {
+        #[cfg(feature = "maintenance-mode")]
+        if let Some(mode) = &self.maintenance_mode {
+            return mode.covers_protocol(protocol);
+        }
+        let _ = protocol;
+        false
+    }
+    
+    /// The dead-letter queue, if one is attached
+    #[must_use]
+    pub fn dead_letters(&self) -> Option<&Arc<dead_letter::DeadLetterQueue>> {
+        self.dead_letters.as_ref()
+    }
+    
+    fn publish(&self, kind: EventKind) {
+        if let Some(events) = &self.events {
+            events.publish(kind);
+        }
+    }
+    
     /// Add a protocol driver to the manager
     /// 
     /// The driver name must be unique. If a driver with the same name
-    /// already exists, it will be replaced after disconnecting the old one.
+    /// already exists, it will be replaced after disconnecting the old one,
+    /// and the new driver is connected straight away if the old one was
+    /// connected, so drivers can be swapped while the engine runs.
     /// 
     /// # Arguments
     /// 
@@ -332,15 +467,69 @@ impl ProtocolManager {
         let mut drivers = self.drivers.write().await;
         
         // Disconnect existing driver if present
+        let mut was_connected = false;
         if let Some(mut old_driver) = drivers.remove(&name) {
             log::info!("Replacing existing {} driver", name);
             if old_driver.is_connected() {
                 old_driver.disconnect().await?;
+                self.publish(EventKind::ProtocolDisconnected { protocol: name.clone(), reason: None });
+                was_connected = true;
             }
         }
         
         log::info!("Adding {} protocol driver", name);
-        drivers.insert(name, driver);
+        let driver = drivers.entry(name.clone()).or_insert(driver);
+        if was_connected && !driver.is_connected() {
+            driver.connect().await?;
+            self.publish(EventKind::ProtocolConnected { protocol: name });
+        }
+        Ok(())
+    }
+    
+    /// Change a driver's parameters at runtime
+    /// 
+    /// Reads and writes already in flight are allowed to finish and new
+    /// ones are held back until the driver has applied `config` (see
+    /// [`ProtocolDriver::reconfigure`]), so no request runs against a
+    /// half-applied configuration. If the requests do not drain within
+    /// the drain timeout the reload is abandoned and the driver is left
+    /// untouched. A driver that dropped its connection to apply the change
+    /// is reconnected.
+    /// 
+    /// # Errors
+    /// 
+    /// - `PlcError::NotFound` if the driver doesn't exist
+    /// - `PlcError::Protocol` if requests did not drain in time or the
+    ///   driver fails to reconnect
+    /// - `PlcError::Config` if the driver rejects the parameters
+    pub async fn reload_driver(&self, name: &str, config: &serde_yaml::Value) -> Result<()> {
+        let Ok(mut drivers) = tokio::time::timeout(self.drain_timeout, self.drivers.write()).await else {
+            return Err(crate::error::PlcError::Protocol(format!(
+                "Reload of '{name}' abandoned: in-flight requests did not drain within {:?}",
+                self.drain_timeout
+            )));
+        };
+        let Some(driver) = drivers.get_mut(name) else {
+            return Err(crate::error::PlcError::NotFound(
+                format!("Protocol driver '{name}' not found")
+            ));
+        };
+        
+        let was_connected = driver.is_connected();
+        if let Err(e) = driver.reconfigure(config).await {
+            log::error!("Rejected new parameters for {name} protocol: {e}");
+            return Err(e);
+        }
+        log::info!("Reconfigured {name} protocol driver");
+        
+        if was_connected && !driver.is_connected() {
+            self.publish(EventKind::ProtocolDisconnected {
+                protocol: name.to_string(),
+                reason: Some("reconfigured".to_string()),
+            });
+            driver.connect().await?;
+            self.publish(EventKind::ProtocolConnected { protocol: name.to_string() });
+        }
         Ok(())
     }
     
@@ -362,6 +551,7 @@ impl ProtocolManager {
             log::info!("Removing {} protocol driver", name);
             if driver.is_connected() {
                 driver.disconnect().await?;
+                self.publish(EventKind::ProtocolDisconnected { protocol: name.to_string(), reason: None });
             }
             Ok(())
         } else {
@@ -395,6 +585,7 @@ impl ProtocolManager {
                 match driver.connect().await {
                     Ok(()) => {
                         log::info!("Successfully connected to {} protocol", name);
+                        self.publish(EventKind::ProtocolConnected { protocol: name.clone() });
                         any_connected = true;
                     }
                     Err(e) => {
@@ -431,10 +622,17 @@ impl ProtocolManager {
         for (name, driver) in drivers.iter_mut() {
             if driver.is_connected() {
                 log::info!("Disconnecting from {} protocol", name);
-                match driver.disconnect().await {
-                    Ok(()) => log::info!("Successfully disconnected from {} protocol", name),
-                    Err(e) => log::error!("Error disconnecting from {} protocol: {}", name, e),
-                }
+                let reason = match driver.disconnect().await {
+                    Ok(()) => {
+                        log::info!("Successfully disconnected from {name} protocol");
+                        None
+                    }
+                    Err(e) => {
+                        log::error!("Error disconnecting from {name} protocol: {e}");
+                        Some(e.to_string())
+                    }
+                };
+                self.publish(EventKind::ProtocolDisconnected { protocol: name.clone(), reason });
             }
         }
         
@@ -444,6 +642,8 @@ impl ProtocolManager {
     /// Read values from a specific protocol
     /// 
     /// Routes the read request to the appropriate driver based on protocol name.
+    /// If `protocol` names a failover group the active member is read, failing
+    /// over to another member once the group's failure threshold is reached.
     /// 
     /// # Arguments
     /// 
@@ -462,6 +662,35 @@ impl ProtocolManager {
         &self, 
         protocol: &str, 
         addresses: &[String]
+    ) -> Result<HashMap<String, Value>> {
+        let mut groups = self.groups.write().await;
+        let Some(group) = groups.get_mut(protocol) else {
+            drop(groups);
+            return self.read_driver(protocol, addresses).await;
+        };
+        
+        let member = self.group_member(group).await;
+        match self.read_driver(&member, addresses).await {
+            Ok(values) => {
+                group.record_success();
+                Ok(values)
+            }
+            Err(e) if group.record_failure() && self.fail_over(group).await => {
+                log::warn!("Retrying read for {protocol} after error: {e}");
+                let result = self.read_driver(group.active_member(), addresses).await;
+                if result.is_ok() {
+                    group.record_success();
+                }
+                result
+            }
+            Err(e) => Err(e),
+        }
+    }
+    
+    async fn read_driver(
+        &self, 
+        protocol: &str, 
+        addresses: &[String]
     ) -> Result<HashMap<String, Value>> {
         let drivers = self.drivers.read().await;
         
@@ -472,7 +701,8 @@ impl ProtocolManager {
                 ));
             }
             
-            let result = driver.read_values(addresses).await;
+            let result =
+                instrument(Subsystem::Protocols, driver.read_values(addresses)).await;
             
             #[cfg(feature = "enhanced-monitoring")]
             {
@@ -499,6 +729,7 @@ impl ProtocolManager {
     /// Write values to a specific protocol
     /// 
     /// Routes the write request to the appropriate driver based on protocol name.
+    /// Failover groups are handled as in [`read_from`](Self::read_from).
     /// 
     /// # Arguments
     /// 
@@ -509,10 +740,64 @@ impl ProtocolManager {
     /// 
     /// - `PlcError::NotFound` if protocol doesn't exist
     /// - `PlcError::Protocol` if write operation fails
+    /// 
+    /// With a dead-letter queue attached, values for addresses the queue
+    /// holds are handed to it instead of being written. Connections in
+    /// maintenance are written directly.
     pub async fn write_to(
         &self, 
         protocol: &str, 
         values: &HashMap<String, Value>
+    ) -> Result<()> {
+        let Some(queue) = self.dead_letters.as_ref().filter(|_| !self.in_maintenance(protocol)) else {
+            return self.write_routed(protocol, values).await;
+        };
+        let pending = queue.hold(protocol, values);
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let result = self.write_routed(protocol, &pending).await;
+        match &result {
+            Ok(()) => queue.record_success(protocol, &pending),
+            Err(e) => queue.record_failure(protocol, &pending, &e.to_string(), chrono::Utc::now()),
+        }
+        result
+    }
+    
+    /// Write to a driver, or to the active member of a failover group
+    async fn write_routed(
+        &self, 
+        protocol: &str, 
+        values: &HashMap<String, Value>
+    ) -> Result<()> {
+        let mut groups = self.groups.write().await;
+        let Some(group) = groups.get_mut(protocol) else {
+            drop(groups);
+            return self.write_driver(protocol, values).await;
+        };
+        
+        let member = self.group_member(group).await;
+        match self.write_driver(&member, values).await {
+            Ok(()) => {
+                group.record_success();
+                Ok(())
+            }
+            Err(e) if group.record_failure() && self.fail_over(group).await => {
+                log::warn!("Retrying write for {protocol} after error: {e}");
+                let result = self.write_driver(group.active_member(), values).await;
+                if result.is_ok() {
+                    group.record_success();
+                }
+                result
+            }
+            Err(e) => Err(e),
+        }
+    }
+    
+    async fn write_driver(
+        &self, 
+        protocol: &str, 
+        values: &HashMap<String, Value>
     ) -> Result<()> {
         let mut drivers = self.drivers.write().await;
         
@@ -523,7 +808,8 @@ impl ProtocolManager {
                 ));
             }
             
-            let result = driver.write_values(values).await;
+            let result =
+                instrument(Subsystem::Protocols, driver.write_values(values)).await;
             
             #[cfg(feature = "enhanced-monitoring")]
             {
@@ -547,6 +833,161 @@ impl ProtocolManager {
         }
     }
     
+    /// Whether a protocol, or the active member of a failover group, is connected
+    pub async fn is_connected(&self, protocol: &str) -> bool {
+        let target = {
+            let groups = self.groups.read().await;
+            groups.get(protocol).map_or_else(|| protocol.to_string(), |g| g.active_member().to_string())
+        };
+        let drivers = self.drivers.read().await;
+        drivers.get(&target).is_some_and(|d| d.is_connected())
+    }
+    
+    /// Read buffered device history from a specific protocol
+    /// 
+    /// For a failover group the active member is read.
+    /// 
+    /// # Errors
+    /// 
+    /// - `PlcError::NotFound` if protocol doesn't exist
+    /// - `PlcError::Protocol` if the driver is disconnected, does not buffer
+    ///   history or the read fails
+    pub async fn read_history(
+        &self,
+        protocol: &str,
+        addresses: &[String],
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<backfill::HistorySample>> {
+        let target = {
+            let groups = self.groups.read().await;
+            groups.get(protocol).map_or_else(|| protocol.to_string(), |g| g.active_member().to_string())
+        };
+        
+        let drivers = self.drivers.read().await;
+        let driver = drivers.get(&target).ok_or_else(|| {
+            crate::error::PlcError::NotFound(format!("Protocol '{target}' not found"))
+        })?;
+        if !driver.is_connected() {
+            return Err(crate::error::PlcError::Protocol(
+                format!("Protocol '{target}' is not connected")
+            ));
+        }
+        instrument(Subsystem::Protocols, driver.read_history(addresses, start, end)).await
+    }
+    
+    /// Register a failover group of drivers that were already added
+    /// 
+    /// # Arguments
+    /// 
+    /// * `config` - Group name and members, primary first
+    /// 
+    /// # Errors
+    /// 
+    /// - `PlcError::Config` if the group is invalid or a driver has its name
+    /// - `PlcError::NotFound` if a member is not a registered driver
+    pub async fn add_failover_group(&self, config: FailoverGroupConfig) -> Result<()> {
+        config.validate()?;
+        {
+            let drivers = self.drivers.read().await;
+            if drivers.contains_key(&config.name) {
+                return Err(crate::error::PlcError::Config(format!(
+                    "Failover group '{}' has the same name as a protocol driver",
+                    config.name
+                )));
+            }
+            if let Some(member) = config.members.iter().find(|m| !drivers.contains_key(*m)) {
+                return Err(crate::error::PlcError::NotFound(format!(
+                    "Failover group '{}' member '{}' is not a registered protocol",
+                    config.name, member
+                )));
+            }
+        }
+        
+        log::info!("Adding failover group {} ({})", config.name, config.members.join(", "));
+        let group = FailoverGroup::new(config);
+        self.publish_active(&group);
+        self.groups.write().await.insert(group.config.name.clone(), group);
+        Ok(())
+    }
+    
+    /// Driver currently serving a failover group
+    /// 
+    /// Returns `None` if no group with that name exists.
+    pub async fn active_path(&self, group: &str) -> Option<String> {
+        let groups = self.groups.read().await;
+        groups.get(group).map(|g| g.active_member().to_string())
+    }
+    
+    /// Pick the member for the next group operation
+    /// 
+    /// Returns to the primary once it connects again and leaves a member
+    /// that has lost its connection.
+    async fn group_member(&self, group: &mut FailoverGroup) -> String {
+        if group.primary_retry_due() && self.try_connect(&group.config.members[0]).await {
+            log::info!(
+                "Failover group {} returning to primary {}",
+                group.config.name, group.config.members[0]
+            );
+            group.switch_to(0);
+            self.publish_active(group);
+        }
+        
+        let connected = {
+            let drivers = self.drivers.read().await;
+            drivers.get(group.active_member()).is_some_and(|d| d.is_connected())
+        };
+        if !connected {
+            self.fail_over(group).await;
+        }
+        group.active_member().to_string()
+    }
+    
+    /// Switch a group to the first other member that is or can be connected
+    async fn fail_over(&self, group: &mut FailoverGroup) -> bool {
+        for (index, member) in group.candidates() {
+            if self.try_connect(&member).await {
+                log::warn!(
+                    "Failover group {} switching from {} to {}",
+                    group.config.name, group.active_member(), member
+                );
+                group.switch_to(index);
+                self.publish_active(group);
+                return true;
+            }
+        }
+        log::error!("Failover group {} has no healthy member", group.config.name);
+        false
+    }
+    
+    /// Connect a driver if it is not connected; true if it ends up connected
+    async fn try_connect(&self, name: &str) -> bool {
+        let mut drivers = self.drivers.write().await;
+        let Some(driver) = drivers.get_mut(name) else {
+            return false;
+        };
+        if driver.is_connected() {
+            return true;
+        }
+        match driver.connect().await {
+            Ok(()) => driver.is_connected(),
+            Err(e) => {
+                log::warn!("Failed to connect to {name} protocol: {e}");
+                false
+            }
+        }
+    }
+    
+    /// Write the active member index to the group's status signal
+    fn publish_active(&self, group: &FailoverGroup) {
+        if let Some(signal) = &group.config.status_signal {
+            let index = i64::try_from(group.active()).unwrap_or(i64::MAX);
+            if let Err(e) = self.signal_bus.set(signal, Value::Integer(index)) {
+                log::warn!("Failed to publish active path of {}: {}", group.config.name, e);
+            }
+        }
+    }
+    
     /// Get list of all connected protocols
     /// 
     /// Returns the names of all protocol drivers that are currently connected.
@@ -619,6 +1060,26 @@ impl ProtocolManager {
             
             diagnostics.insert(name.clone(), diag);
         }
+        drop(drivers);
+        
+        let groups = self.groups.read().await;
+        for (name, group) in groups.iter() {
+            let mut diag = HashMap::new();
+            diag.insert(
+                "active_index".to_string(),
+                Value::Integer(i64::try_from(group.active()).unwrap_or(i64::MAX)),
+            );
+            diag.insert(
+                "switch_count".to_string(),
+                Value::Integer(i64::try_from(group.switches()).unwrap_or(i64::MAX)),
+            );
+            #[cfg(feature = "extended-types")]
+            diag.insert(
+                "active_member".to_string(),
+                Value::String(group.active_member().to_string()),
+            );
+            diagnostics.insert(name.clone(), diag);
+        }
         
         diagnostics
     }
@@ -656,23 +1117,156 @@ impl ProtocolManager {
     }
 }
 
+// ================================================================================
+// SIGNAL EXCHANGE HELPERS
+// ================================================================================
+// Shared by connectors that stream signal values to and from message brokers
+
+/// Tracks which signals changed since they were last published
+pub struct ChangeTracker {
+    signals: Vec<String>,
+    last_values: HashMap<String, Value>,
+}
+
+impl ChangeTracker {
+    /// Track `signals`, or every signal on the bus when empty
+    #[must_use]
+    pub fn new(signals: Vec<String>) -> Self {
+        Self {
+            signals,
+            last_values: HashMap::new(),
+        }
+    }
+
+    /// Signals whose value differs from the last one returned
+    pub fn changes(&mut self, bus: &SignalBus) -> Vec<(String, Value)> {
+        let names = if self.signals.is_empty() {
+            bus.signal_names()
+        } else {
+            self.signals.clone()
+        };
+        let mut changed = Vec::new();
+        for name in names {
+            let Some(value) = bus.get(&name) else {
+                continue;
+            };
+            if self.last_values.get(&name) != Some(&value) {
+                self.last_values.insert(name.clone(), value.clone());
+                changed.push((name, value));
+            }
+        }
+        changed
+    }
+}
+
+/// Maps signal names received from a broker to local signals
+pub struct SignalFilter {
+    allowed: std::collections::HashSet<String>,
+    prefix: String,
+}
+
+impl SignalFilter {
+    /// Accept `allowed` signals, or any signal when empty, renamed with `prefix`
+    #[must_use]
+    pub fn new(allowed: &[String], prefix: Option<&str>) -> Self {
+        Self {
+            allowed: allowed.iter().cloned().collect(),
+            prefix: prefix.unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Local signal a received value is written to, `None` if not allowed
+    #[must_use]
+    pub fn target(&self, signal: &str) -> Option<String> {
+        (self.allowed.is_empty() || self.allowed.contains(signal))
+            .then(|| format!("{}{signal}", self.prefix))
+    }
+}
+
 // ================================================================================
 // PROTOCOL IMPLEMENTATIONS
 // ================================================================================
 // Each protocol implementation is feature-gated to minimize binary size
 
+pub mod backfill;
+
+pub mod dead_letter;
+
+pub mod scheduler;
+
+pub mod routing;
+
+pub mod failover;
+
 #[cfg(feature = "s7-support")]
 pub mod s7;
 
+#[cfg(feature = "s7-support")]
+pub mod s7_symbols;
+
 #[cfg(feature = "modbus-support")]
 pub mod modbus;
 
+#[cfg(feature = "modbus-support")]
+pub mod modbus_rtu;
+
 #[cfg(feature = "opcua-support")]
 pub mod opcua;
 
+#[cfg(feature = "opcua-server")]
+pub mod opcua_server;
+
+#[cfg(all(feature = "opcua-server", feature = "history"))]
+pub mod opcua_history;
+
+#[cfg(feature = "dnp3-support")]
+pub mod dnp3;
+
+#[cfg(feature = "ethercat")]
+pub mod ethercat;
+
+#[cfg(feature = "profinet")]
+pub mod profinet;
+
+#[cfg(feature = "coap")]
+pub mod coap;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(feature = "protocol-sim")]
+pub mod sim;
+
+#[cfg(any(feature = "ethercat", feature = "profinet"))]
+pub mod raw_ethernet;
+
 #[cfg(feature = "mqtt")]
 pub mod mqtt;
 
+#[cfg(feature = "mqtt")]
+pub mod mqtt_codec;
+
+#[cfg(feature = "mqtt")]
+pub mod mqtt_failover;
+
+#[cfg(feature = "mqtt-tls")]
+pub mod mqtt_tls;
+
+#[cfg(feature = "mqtt-commands")]
+pub mod mqtt_commands;
+
+#[cfg(feature = "mqtt-sparkplug")]
+pub mod sparkplug;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+
+#[cfg(feature = "nats")]
+pub mod nats;
+
+#[cfg(feature = "bridge")]
+pub mod bridge;
+
 #[cfg(feature = "zero-copy-protocols")]
 pub mod zero_copy;
 
@@ -691,6 +1285,8 @@ mod tests {
         connect_fail: bool,
         read_fail: bool,
         write_fail: bool,
+        address: String,
+        read_delay: Duration,
     }
     
     impl MockDriver {
@@ -701,6 +1297,8 @@ mod tests {
                 connect_fail: false,
                 read_fail: false,
                 write_fail: false,
+                address: "10.0.0.1".to_string(),
+                read_delay: Duration::ZERO,
             }
         }
         
@@ -732,6 +1330,7 @@ mod tests {
             if self.read_fail {
                 return Err(crate::error::PlcError::Protocol("Mock read failed".to_string()));
             }
+            tokio::time::sleep(self.read_delay).await;
             
             let mut result = HashMap::new();
             for addr in addresses {
@@ -768,6 +1367,18 @@ mod tests {
             diag.insert("test_mode".to_string(), Value::Bool(true));
             diag
         }
+        
+        async fn reconfigure(&mut self, config: &serde_yaml::Value) -> Result<()> {
+            let address = config["address"].as_str().ok_or_else(|| {
+                crate::error::PlcError::Config("address is required".to_string())
+            })?;
+            if address != self.address {
+                self.address = address.to_string();
+                self.connected = false;
+            }
+            self.read_delay = Duration::from_millis(config["read_delay_ms"].as_u64().unwrap_or(0));
+            Ok(())
+        }
     }
     
     #[tokio::test]
@@ -848,6 +1459,44 @@ mod tests {
         assert!(all_diag.contains_key("mock"));
     }
     
+    #[tokio::test]
+    async fn test_failover_group() {
+        let signal_bus = SignalBus::new();
+        let manager = ProtocolManager::new(signal_bus.clone());
+        
+        // Primary reads fail, backup works
+        let primary = Box::new(MockDriver::new().with_failure(false, true, false));
+        manager.add_driver("gateway_a".to_string(), primary).await.unwrap();
+        manager.add_driver("gateway_b".to_string(), Box::new(MockDriver::new())).await.unwrap();
+        manager.connect_all().await.unwrap();
+        
+        let config: FailoverGroupConfig = serde_yaml::from_str(
+            "name: plc\nmembers: [gateway_a, gateway_b]\nfailure_threshold: 2\n\
+             primary_retry_ms: 0\nstatus_signal: plc.active_path\n",
+        )
+        .unwrap();
+        manager.add_failover_group(config).await.unwrap();
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_a"));
+        assert_eq!(signal_bus.get("plc.active_path"), Some(Value::Integer(0)));
+        
+        // Below the threshold the error is returned, at the threshold the
+        // read is retried on the backup
+        let addresses = vec!["x".to_string()];
+        assert!(manager.read_from("plc", &addresses).await.is_err());
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_b"));
+        assert_eq!(signal_bus.get("plc.active_path"), Some(Value::Integer(1)));
+        
+        // A healthy primary is preferred again on the next retry
+        manager.add_driver("gateway_a".to_string(), Box::new(MockDriver::new())).await.unwrap();
+        assert!(manager.read_from("plc", &addresses).await.is_ok());
+        assert_eq!(manager.active_path("plc").await.as_deref(), Some("gateway_a"));
+        assert_eq!(signal_bus.get("plc.active_path"), Some(Value::Integer(0)));
+        
+        let diag = manager.all_diagnostics().await;
+        assert_eq!(diag["plc"].get("switch_count"), Some(&Value::Integer(2)));
+    }
+    
     #[tokio::test]
     async fn test_protocol_manager_replace_driver() {
         let signal_bus = SignalBus::new();
@@ -862,8 +1511,89 @@ mod tests {
         let driver2 = Box::new(MockDriver::new());
         manager.add_driver("test".to_string(), driver2).await.unwrap();
         
-        // Verify still only one driver
+        // Verify still only one driver, connected in place of the old one
         let all_protocols = manager.all_protocols().await;
         assert_eq!(all_protocols.len(), 1);
+        assert_eq!(manager.connected_protocols().await, vec!["test"]);
+    }
+    
+    #[tokio::test]
+    async fn test_reload_driver() {
+        let events = EventLog::default();
+        let manager = ProtocolManager::new(SignalBus::new()).with_events(events.clone());
+        manager.add_driver("plc".to_string(), Box::new(MockDriver::new())).await.unwrap();
+        manager.connect_all().await.unwrap();
+        let config = |yaml: &str| serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap();
+        
+        // Same address: applied in place without touching the connection
+        manager.reload_driver("plc", &config("address: 10.0.0.1\nread_delay_ms: 1")).await.unwrap();
+        assert!(manager.is_connected("plc").await);
+        
+        // Rejected parameters leave the driver as it was
+        let result = manager.reload_driver("plc", &config("read_delay_ms: 1")).await;
+        assert!(matches!(result, Err(crate::error::PlcError::Config(_))));
+        assert!(manager.is_connected("plc").await);
+        
+        // A new address drops the connection, which the manager restores
+        let before = events.last_sequence();
+        manager.reload_driver("plc", &config("address: 10.0.0.2")).await.unwrap();
+        assert!(manager.is_connected("plc").await);
+        let kinds: Vec<_> = events.since(before).iter().map(|e| e.kind.name()).collect();
+        assert_eq!(kinds, ["protocol_disconnected", "protocol_connected"]);
+        
+        let result = manager.reload_driver("missing", &config("address: x")).await;
+        assert!(matches!(result, Err(crate::error::PlcError::NotFound(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_reload_driver_drains_in_flight_requests() {
+        let manager = Arc::new(ProtocolManager::new(SignalBus::new()).with_drain_timeout(Duration::from_millis(20)));
+        let mut slow = MockDriver::new();
+        slow.read_delay = Duration::from_millis(200);
+        manager.add_driver("plc".to_string(), Box::new(slow)).await.unwrap();
+        manager.connect_all().await.unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str("address: 10.0.0.1").unwrap();
+        
+        let read = |manager: Arc<ProtocolManager>| {
+            tokio::spawn(async move { manager.read_from("plc", &["x".to_string()]).await })
+        };
+        
+        // The read outlasts the drain timeout, so the reload is abandoned
+        let in_flight = read(Arc::clone(&manager));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let result = manager.reload_driver("plc", &config).await;
+        assert!(matches!(result, Err(crate::error::PlcError::Protocol(_))));
+        assert!(in_flight.await.unwrap().is_ok());
+        
+        // With enough time the read completes first and the reload applies
+        // before the next read, which then runs with the new parameters
+        let manager = Arc::new(ProtocolManager::new(SignalBus::new()));
+        let mut slow = MockDriver::new();
+        slow.read_delay = Duration::from_millis(100);
+        manager.add_driver("plc".to_string(), Box::new(slow)).await.unwrap();
+        manager.connect_all().await.unwrap();
+        let in_flight = read(Arc::clone(&manager));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        manager.reload_driver("plc", &config).await.unwrap();
+        assert!(in_flight.is_finished(), "reload must wait for the in-flight read");
+        assert!(in_flight.await.unwrap().is_ok());
+        
+        let started = std::time::Instant::now();
+        manager.read_from("plc", &["x".to_string()]).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+    
+    #[test]
+    fn test_change_tracker() {
+        let bus = SignalBus::new();
+        bus.set("a", Value::Integer(1)).unwrap();
+        bus.set("b", Value::Integer(2)).unwrap();
+        let mut tracker = ChangeTracker::new(vec!["a".into()]);
+        assert_eq!(tracker.changes(&bus), vec![("a".to_string(), Value::Integer(1))]);
+        assert!(tracker.changes(&bus).is_empty());
+        bus.set("a", Value::Integer(3)).unwrap();
+        assert_eq!(tracker.changes(&bus).len(), 1);
+        
+        assert_eq!(ChangeTracker::new(Vec::new()).changes(&bus).len(), 2);
     }
 }
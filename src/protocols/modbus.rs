@@ -1,11 +1,28 @@
 //! Modbus protocol implementation
 //!
-//! This module provides Modbus RTU and TCP communication capabilities.
+//! This module provides Modbus RTU and TCP communication capabilities, and
+//! the diagnostics behind `petra protocol modbus`: a single register read
+//! and a scan that sweeps register ranges of a TCP device, reports which
+//! addresses respond and how fast, and suggests a register mapping. Serial
+//! RTU lines are polled by [`modbus_rtu`](super::modbus_rtu).
+//!
+//! ```bash
+//! petra protocol modbus --address 10.0.4.20:502 --unit-id 3 \
+//!     --scan --register 0 --count 200 --types holding,coil
+//! ```
 
-use crate::{Result, Value};
+use crate::config::{ModbusConfig, ModbusConnection, ModbusRegister};
+use crate::{PlcError, Result, Value};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use tracing::info;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio_modbus::client::Context;
+use tokio_modbus::prelude::*;
+use tracing::{debug, info};
 
 pub struct ModbusDriver {
     // Implementation details
@@ -42,9 +59,492 @@ impl crate::protocols::ProtocolDriver for ModbusDriver {
     }
 }
 
-/// Test connectivity to a Modbus device.
-/// This is a lightweight stub used by the command-line interface.
-pub async fn test_connection(_address: &str, _unit_id: u8, _register: u16) -> Result<()> {
-    info!("Modbus test_connection stub called");
+// ============================================================================
+// DIAGNOSTICS
+// ============================================================================
+
+/// Modbus data table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RegisterKind {
+    Coil,
+    Discrete,
+    Holding,
+    Input,
+}
+
+impl RegisterKind {
+    /// Name used for the register `type` in configuration files
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Coil => "coil",
+            Self::Discrete => "discrete",
+            Self::Holding => "holding",
+            Self::Input => "input",
+        }
+    }
+
+    /// Most addresses a single read may request
+    pub(crate) const fn max_read(self) -> u16 {
+        match self {
+            Self::Coil | Self::Discrete => 2000,
+            Self::Holding | Self::Input => 125,
+        }
+    }
+
+    const fn data_type(self) -> &'static str {
+        match self {
+            Self::Coil | Self::Discrete => "bool",
+            Self::Holding | Self::Input => "int16",
+        }
+    }
+}
+
+impl FromStr for RegisterKind {
+    type Err = PlcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "coil" | "coils" => Ok(Self::Coil),
+            "discrete" | "discrete_inputs" => Ok(Self::Discrete),
+            "holding" => Ok(Self::Holding),
+            "input" => Ok(Self::Input),
+            _ => Err(PlcError::Config(format!(
+                "Unknown Modbus register type '{s}', expected coil, discrete, holding or input"
+            ))),
+        }
+    }
+}
+
+/// Outcome of one read request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    /// Every requested address answered
+    Responded,
+    /// The device answered with an exception, usually an illegal address
+    Refused,
+    /// No answer within the timeout
+    TimedOut,
+}
+
+/// Source of register reads, a Modbus client outside of tests
+#[async_trait]
+trait RegisterReader: Send {
+    /// Read `count` addresses starting at `address`
+    ///
+    /// # Errors
+    ///
+    /// Fails when the connection is lost; exception responses are
+    /// [`Probe::Refused`].
+    async fn read(&mut self, kind: RegisterKind, address: u16, count: u16) -> Result<Probe>;
+
+    /// Recover after a request timed out, so a late answer is not taken as
+    /// the response to the next request
+    async fn reset(&mut self) -> Result<()>;
+}
+
+/// Modbus TCP client used by the diagnostics
+struct TcpReader {
+    address: SocketAddr,
+    unit_id: u8,
+    context: Context,
+}
+
+impl TcpReader {
+    async fn connect(address: &str, unit_id: u8) -> Result<Self> {
+        let address = tokio::net::lookup_host(address)
+            .await?
+            .next()
+            .ok_or_else(|| PlcError::Config(format!("Modbus address '{address}' did not resolve")))?;
+        let context = tcp::connect_slave(address, Slave(unit_id))
+            .await
+            .map_err(|source| PlcError::Modbus { source })?;
+        Ok(Self { address, unit_id, context })
+    }
+}
+
+#[async_trait]
+impl RegisterReader for TcpReader {
+    async fn read(&mut self, kind: RegisterKind, address: u16, count: u16) -> Result<Probe> {
+        let result = match kind {
+            RegisterKind::Coil => self.context.read_coils(address, count).await.map(|_| ()),
+            RegisterKind::Discrete => self.context.read_discrete_inputs(address, count).await.map(|_| ()),
+            RegisterKind::Holding => self.context.read_holding_registers(address, count).await.map(|_| ()),
+            RegisterKind::Input => self.context.read_input_registers(address, count).await.map(|_| ()),
+        };
+        match result {
+            Ok(()) => Ok(Probe::Responded),
+            // Exception responses are reported as `Other`, everything else
+            // is a transport failure
+            Err(e) if e.kind() == io::ErrorKind::Other => {
+                debug!("{} {}+{}: {}", kind.name(), address, count, e);
+                Ok(Probe::Refused)
+            }
+            Err(source) => Err(PlcError::Modbus { source }),
+        }
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.context = tcp::connect_slave(self.address, Slave(self.unit_id))
+            .await
+            .map_err(|source| PlcError::Modbus { source })?;
+        Ok(())
+    }
+}
+
+/// Ranges and pacing of a register scan
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Tables to sweep
+    pub kinds: Vec<RegisterKind>,
+    /// First address of the sweep
+    pub start: u16,
+    /// Number of addresses swept in every table
+    pub count: u16,
+    /// Addresses requested per read; failing blocks are split until single
+    /// addresses are isolated
+    pub block_size: u16,
+    /// Time allowed for each response
+    pub timeout: Duration,
+}
+
+/// Round-trip times of the requests sent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Latency {
+    pub requests: u32,
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl Latency {
+    fn record(&mut self, elapsed: Duration) {
+        if self.requests == 0 || elapsed < self.min {
+            self.min = elapsed;
+        }
+        self.max = self.max.max(elapsed);
+        self.total += elapsed;
+        self.requests += 1;
+    }
+
+    /// Mean round-trip time, zero before any request
+    #[must_use]
+    pub fn average(&self) -> Duration {
+        self.total.checked_div(self.requests).unwrap_or_default()
+    }
+}
+
+/// Scan result of one register table
+#[derive(Debug, Clone)]
+pub struct TableScan {
+    pub kind: RegisterKind,
+    /// Responding addresses as `(first, count)` runs in address order
+    pub responding: Vec<(u16, u16)>,
+    /// Addresses answered with an exception
+    pub refused: u32,
+    /// Addresses that never answered
+    pub timed_out: u32,
+    /// Round-trip times of successful and refused reads
+    pub latency: Latency,
+}
+
+/// Result of [`scan`]
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    pub address: String,
+    pub unit_id: u8,
+    pub tables: Vec<TableScan>,
+}
+
+impl ScanReport {
+    /// Latency over every table
+    #[must_use]
+    pub fn latency(&self) -> Latency {
+        let mut latency = Latency::default();
+        for table in self.tables.iter().filter(|t| t.latency.requests > 0) {
+            if latency.requests == 0 || table.latency.min < latency.min {
+                latency.min = table.latency.min;
+            }
+            latency.max = latency.max.max(table.latency.max);
+            latency.total += table.latency.total;
+            latency.requests += table.latency.requests;
+        }
+        latency
+    }
+
+    /// Connection mapping every responding run to a signal named after its
+    /// table and first address, split at the largest read the table allows
+    #[must_use]
+    pub fn suggested_connection(&self, name: &str) -> ModbusConnection {
+        let mut registers = Vec::new();
+        for table in &self.tables {
+            let max = table.kind.max_read();
+            for &(first, count) in &table.responding {
+                let end = u32::from(first) + u32::from(count);
+                let mut address = u32::from(first);
+                while address < end {
+                    let chunk = u16::try_from((end - address).min(u32::from(max))).unwrap_or(max);
+                    let start = u16::try_from(address).unwrap_or(u16::MAX);
+                    registers.push(ModbusRegister {
+                        register_type: table.kind.name().to_string(),
+                        address: start,
+                        count: chunk,
+                        signal: format!("{name}.{}_{start}", table.kind.name()),
+                        data_type: table.kind.data_type().to_string(),
+                    });
+                    address += u32::from(chunk);
+                }
+            }
+        }
+        ModbusConnection {
+            name: name.to_string(),
+            connection_type: "tcp".to_string(),
+            address: self.address.clone(),
+            unit_id: self.unit_id,
+            registers,
+            serial: None,
+        }
+    }
+
+    /// `protocols.modbus` configuration snippet for the responding registers
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Yaml`] if the snippet cannot be serialized.
+    pub fn suggested_yaml(&self, name: &str, timeout: Duration) -> Result<String> {
+        let modbus = ModbusConfig {
+            connections: vec![self.suggested_connection(name)],
+            timeout_ms: u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX),
+        };
+        let snippet = BTreeMap::from([("protocols", BTreeMap::from([("modbus", modbus)]))]);
+        Ok(serde_yaml::to_string(&snippet)?)
+    }
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Modbus scan of {} unit {}", self.address, self.unit_id)?;
+        for table in &self.tables {
+            let responding: u32 = table.responding.iter().map(|&(_, count)| u32::from(count)).sum();
+            writeln!(
+                f,
+                "  {:<9} {} responding, {} refused, {} timed out",
+                table.kind.name(),
+                responding,
+                table.refused,
+                table.timed_out
+            )?;
+            for &(first, count) in &table.responding {
+                let last = u32::from(first) + u32::from(count) - 1;
+                writeln!(f, "    {first}..={last} ({count})")?;
+            }
+        }
+        let latency = self.latency();
+        write!(
+            f,
+            "  latency   min {:.1} ms, avg {:.1} ms, max {:.1} ms over {} requests",
+            latency.min.as_secs_f64() * 1000.0,
+            latency.average().as_secs_f64() * 1000.0,
+            latency.max.as_secs_f64() * 1000.0,
+            latency.requests
+        )
+    }
+}
+
+/// Sweep one table, splitting refused or unanswered blocks down to single
+/// addresses
+async fn scan_table<R: RegisterReader>(reader: &mut R, kind: RegisterKind, options: &ScanOptions) -> Result<TableScan> {
+    let mut table = TableScan {
+        kind,
+        responding: Vec::new(),
+        refused: 0,
+        timed_out: 0,
+        latency: Latency::default(),
+    };
+    let block = options.block_size.clamp(1, kind.max_read());
+    let end = (u32::from(options.start) + u32::from(options.count)).min(u32::from(u16::MAX) + 1);
+
+    let mut responding = Vec::new();
+    let mut address = u32::from(options.start);
+    while address < end {
+        let len = u16::try_from((end - address).min(u32::from(block))).unwrap_or(block);
+        let mut pending = vec![(u16::try_from(address).unwrap_or(u16::MAX), len)];
+        while let Some((first, count)) = pending.pop() {
+            let started = Instant::now();
+            let probe = if let Ok(probe) = tokio::time::timeout(options.timeout, reader.read(kind, first, count)).await {
+                probe?
+            } else {
+                reader.reset().await?;
+                Probe::TimedOut
+            };
+            if probe != Probe::TimedOut {
+                table.latency.record(started.elapsed());
+            }
+            match probe {
+                Probe::Responded => responding.push((first, count)),
+                _ if count > 1 => {
+                    // Upper half first so runs pop in address order
+                    let half = count / 2;
+                    pending.push((first + half, count - half));
+                    pending.push((first, half));
+                }
+                Probe::Refused => table.refused += 1,
+                Probe::TimedOut => table.timed_out += 1,
+            }
+        }
+        address += u32::from(len);
+    }
+
+    // Merge adjacent runs found by separate reads
+    responding.sort_unstable();
+    for (first, count) in responding {
+        match table.responding.last_mut() {
+            Some((previous, length)) if u32::from(*previous) + u32::from(*length) == u32::from(first) => {
+                *length += count;
+            }
+            _ => table.responding.push((first, count)),
+        }
+    }
+    Ok(table)
+}
+
+async fn scan_with<R: RegisterReader>(reader: &mut R, address: &str, unit_id: u8, options: &ScanOptions) -> Result<ScanReport> {
+    let mut tables = Vec::with_capacity(options.kinds.len());
+    for &kind in &options.kinds {
+        info!("Scanning {} addresses {}+{}", kind.name(), options.start, options.count);
+        tables.push(scan_table(reader, kind, options).await?);
+    }
+    Ok(ScanReport {
+        address: address.to_string(),
+        unit_id,
+        tables,
+    })
+}
+
+/// Sweep register ranges of a Modbus TCP device
+///
+/// # Errors
+///
+/// Fails if the device cannot be reached or the connection drops during the
+/// scan. Addresses the device refuses or leaves unanswered are counted in
+/// the report instead.
+pub async fn scan(address: &str, unit_id: u8, options: &ScanOptions) -> Result<ScanReport> {
+    let mut reader = TcpReader::connect(address, unit_id).await?;
+    scan_with(&mut reader, address, unit_id, options).await
+}
+
+/// Test connectivity to a Modbus device by reading one holding register
+///
+/// # Errors
+///
+/// Fails if the device cannot be reached, refuses the register or does not
+/// answer within five seconds.
+pub async fn test_connection(address: &str, unit_id: u8, register: u16) -> Result<()> {
+    println!("Testing Modbus connection to {address} unit {unit_id}");
+    let mut reader = TcpReader::connect(address, unit_id).await?;
+    let started = Instant::now();
+    let values = tokio::time::timeout(Duration::from_secs(5), reader.context.read_holding_registers(register, 1))
+        .await
+        .map_err(|_| PlcError::Protocol(format!("Modbus device {address} did not answer")))?
+        .map_err(|source| PlcError::Modbus { source })?;
+    println!(
+        "Holding register {register} = {} ({:.1} ms)",
+        values.first().copied().unwrap_or_default(),
+        started.elapsed().as_secs_f64() * 1000.0
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Device answering for a fixed set of addresses and never for `silent`
+    struct FakeDevice {
+        valid: HashSet<(RegisterKind, u16)>,
+        silent: Option<(RegisterKind, u16)>,
+        resets: u32,
+    }
+
+    #[async_trait]
+    impl RegisterReader for FakeDevice {
+        async fn read(&mut self, kind: RegisterKind, address: u16, count: u16) -> Result<Probe> {
+            let addresses = address..address + count;
+            if let Some((silent_kind, silent)) = self.silent {
+                if silent_kind == kind && addresses.contains(&silent) {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+            }
+            if addresses.clone().all(|a| self.valid.contains(&(kind, a))) {
+                Ok(Probe::Responded)
+            } else {
+                Ok(Probe::Refused)
+            }
+        }
+
+        async fn reset(&mut self) -> Result<()> {
+            self.resets += 1;
+            Ok(())
+        }
+    }
+
+    fn options(kinds: Vec<RegisterKind>) -> ScanOptions {
+        ScanOptions {
+            kinds,
+            start: 0,
+            count: 40,
+            block_size: 8,
+            timeout: Duration::from_millis(50),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_isolates_responding_runs() {
+        let mut valid: HashSet<_> = (0..12).map(|a| (RegisterKind::Holding, a)).collect();
+        valid.extend((20..23).map(|a| (RegisterKind::Holding, a)));
+        valid.insert((RegisterKind::Coil, 5));
+        let mut device = FakeDevice {
+            valid,
+            silent: Some((RegisterKind::Holding, 30)),
+            resets: 0,
+        };
+
+        let report = scan_with(&mut device, "10.0.0.5:502", 3, &options(vec![RegisterKind::Holding, RegisterKind::Coil]))
+            .await
+            .unwrap();
+        let holding = &report.tables[0];
+        assert_eq!(holding.responding, vec![(0, 12), (20, 3)]);
+        assert_eq!(holding.timed_out, 1);
+        assert_eq!(holding.refused, 40 - 15 - 1);
+        assert_eq!(report.tables[1].responding, vec![(5, 1)]);
+        assert!(device.resets > 0);
+        assert!(report.to_string().contains("0..=11 (12)"));
+    }
+
+    #[test]
+    fn test_suggested_mapping() {
+        let report = ScanReport {
+            address: "10.0.0.5:502".to_string(),
+            unit_id: 3,
+            tables: vec![TableScan {
+                kind: RegisterKind::Input,
+                responding: vec![(100, 200)],
+                refused: 0,
+                timed_out: 0,
+                latency: Latency::default(),
+            }],
+        };
+        let connection = report.suggested_connection("boiler");
+        let runs: Vec<_> = connection.registers.iter().map(|r| (r.address, r.count)).collect();
+        assert_eq!(runs, vec![(100, 125), (225, 75)]);
+        assert_eq!(connection.registers[1].signal, "boiler.input_225");
+
+        let yaml = report.suggested_yaml("boiler", Duration::from_secs(1)).unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed["protocols"]["modbus"]["connections"][0]["unit_id"], 3);
+        assert_eq!(parsed["protocols"]["modbus"]["timeout_ms"], 1000);
+        assert!("holding,coil".split(',').all(|k| k.parse::<RegisterKind>().is_ok()));
+        assert!("register".parse::<RegisterKind>().is_err());
+    }
+}
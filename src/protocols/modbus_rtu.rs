@@ -0,0 +1,721 @@
+//! Modbus RTU over serial lines
+//!
+//! RTU connections name the serial port as their `address`. Connections on
+//! the same port share one RS-485 line, polled by a single task:
+//!
+//! ```yaml
+//! protocols:
+//!   modbus:
+//!     timeout_ms: 300
+//!     connections:
+//!       - name: dryer_vfd
+//!         type: rtu
+//!         address: /dev/ttyUSB0
+//!         unit_id: 1
+//!         serial: { baud_rate: 19200, parity: even, stop_bits: 1, cycle_ms: 500 }
+//!         registers:
+//!           - { type: holding, address: 0, count: 4, signal: dryer.vfd }
+//!       - name: dryer_meter
+//!         type: rtu
+//!         address: /dev/ttyUSB0
+//!         unit_id: 7
+//!         serial: { baud_rate: 19200, parity: even, stop_bits: 1, cycle_ms: 500 }
+//!         registers:
+//!           - { type: input, address: 100, count: 2, signal: dryer.power, data_type: float32 }
+//! ```
+//!
+//! # Bus arbitration
+//!
+//! Each polling cycle the units of a line take turns, one request each,
+//! starting with a different unit every cycle. A request that times out ends
+//! that unit's turns for the cycle, so a unit that is switched off costs one
+//! timeout per cycle instead of one per register. When a cycle runs longer
+//! than `cycle_ms` the remaining requests are postponed, and every unit
+//! continues with its next request in the following cycle.
+//!
+//! Frames are separated by the inter-frame delay: 3.5 character times, or
+//! 1.75 ms above 19200 baud as the Modbus serial line specification allows.
+//!
+//! # Values
+//!
+//! A mapping holding a single value writes it to `signal`; longer mappings
+//! write `<signal>.<address>` for each value. Registers are decoded as
+//! `int16` (default), `uint16`, `int32`, `uint32` or `float32`, with 32-bit
+//! values high word first; coils and discrete inputs are booleans.
+//!
+//! # Runtime reconfiguration
+//!
+//! Each line is also available as a [`ModbusRtuDriver`] named after its
+//! port. Reloading it with the line's connections applies the new units,
+//! registers and `cycle_ms` from the next cycle on; the port is only
+//! reopened if its serial settings change.
+
+use super::modbus::RegisterKind;
+use crate::config::{ModbusConfig, ModbusConnection, ModbusRegister, ModbusSerial, Validatable};
+use crate::{PlcError, Result, SignalBus, Value};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_modbus::client::{rtu, Context};
+use tokio_modbus::prelude::*;
+use tokio_serial::{DataBits, Parity, SerialPortBuilderExt, StopBits};
+use tracing::{debug, info, warn};
+
+/// Baud rate above which the inter-frame delay no longer shrinks
+const FIXED_DELAY_BAUD: u32 = 19_200;
+
+/// Inter-frame delay used above [`FIXED_DELAY_BAUD`]
+const FIXED_FRAME_DELAY: Duration = Duration::from_micros(1750);
+
+/// Time to transmit one character, start and stop bits included
+#[must_use]
+pub fn char_time(serial: &ModbusSerial) -> Duration {
+    let parity = u32::from(!serial.parity.eq_ignore_ascii_case("none"));
+    let bits = 1 + u32::from(serial.data_bits) + parity + u32::from(serial.stop_bits);
+    Duration::from_secs_f64(f64::from(bits) / f64::from(serial.baud_rate.max(1)))
+}
+
+/// Silence required between two frames on the line
+#[must_use]
+pub fn frame_delay(serial: &ModbusSerial) -> Duration {
+    match serial.inter_frame_delay_us {
+        Some(us) => Duration::from_micros(us),
+        None if serial.baud_rate > FIXED_DELAY_BAUD => FIXED_FRAME_DELAY,
+        None => char_time(serial).mul_f64(3.5),
+    }
+}
+
+// ============================================================================
+// REQUESTS
+// ============================================================================
+
+/// How the addresses of a mapping are turned into values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Bool,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+}
+
+impl Format {
+    fn parse(kind: RegisterKind, data_type: &str) -> Result<Self> {
+        if matches!(kind, RegisterKind::Coil | RegisterKind::Discrete) {
+            return Ok(Self::Bool);
+        }
+        match data_type.to_lowercase().as_str() {
+            "int16" => Ok(Self::Int16),
+            "uint16" => Ok(Self::UInt16),
+            "int32" => Ok(Self::Int32),
+            "uint32" => Ok(Self::UInt32),
+            "float32" => Ok(Self::Float32),
+            other => Err(PlcError::Config(format!("Unsupported Modbus data type '{other}'"))),
+        }
+    }
+
+    /// Addresses taken by one value
+    const fn width(self) -> u16 {
+        match self {
+            Self::Int32 | Self::UInt32 | Self::Float32 => 2,
+            Self::Bool | Self::Int16 | Self::UInt16 => 1,
+        }
+    }
+
+    /// Value of `words`, exactly [`Self::width`] long
+    fn decode(self, words: &[u16]) -> Value {
+        let long = || u32::from(words[0]) << 16 | u32::from(words[1]);
+        match self {
+            Self::Bool => Value::Bool(words[0] != 0),
+            Self::Int16 => Value::Integer(i64::from(i16::from_be_bytes(words[0].to_be_bytes()))),
+            Self::UInt16 => Value::Integer(i64::from(words[0])),
+            Self::Int32 => Value::Integer(i64::from(i32::from_be_bytes(long().to_be_bytes()))),
+            Self::UInt32 => Value::Integer(i64::from(long())),
+            Self::Float32 => Value::Float(f64::from(f32::from_bits(long()))),
+        }
+    }
+}
+
+/// One read request of a unit
+#[derive(Debug, Clone)]
+struct Request {
+    /// Mapping of the unit the request reads
+    register: usize,
+    kind: RegisterKind,
+    format: Format,
+    address: u16,
+    count: u16,
+}
+
+/// Requests reading every mapping of `connection`, split at the largest read
+/// a table allows without splitting a value
+fn requests(connection: &ModbusConnection) -> Result<Vec<Request>> {
+    let mut requests = Vec::new();
+    for (index, register) in connection.registers.iter().enumerate() {
+        let kind: RegisterKind = register.register_type.parse()?;
+        let format = Format::parse(kind, &register.data_type)?;
+        let step = u32::from(kind.max_read() / format.width() * format.width());
+        let end = u32::from(register.address) + u32::from(register.count);
+        let mut address = u32::from(register.address);
+        while address < end {
+            let count = (end - address).min(step);
+            requests.push(Request {
+                register: index,
+                kind,
+                format,
+                address: u16::try_from(address).unwrap_or(u16::MAX),
+                count: u16::try_from(count).unwrap_or_default(),
+            });
+            address += count;
+        }
+    }
+    Ok(requests)
+}
+
+/// Data returned for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reading {
+    Bits(Vec<bool>),
+    Words(Vec<u16>),
+}
+
+/// Signals and values of a reading
+fn values(register: &ModbusRegister, request: &Request, reading: &Reading) -> Vec<(String, Value)> {
+    let width = request.format.width();
+    let signal = |address: u32| {
+        if register.count <= width {
+            register.signal.clone()
+        } else {
+            format!("{}.{address}", register.signal)
+        }
+    };
+    let count = usize::from(request.count);
+    match reading {
+        Reading::Bits(bits) => bits
+            .iter()
+            .take(count)
+            .zip(u32::from(request.address)..)
+            .map(|(bit, address)| (signal(address), Value::Bool(*bit)))
+            .collect(),
+        Reading::Words(words) => words[..count.min(words.len())]
+            .chunks_exact(usize::from(width))
+            .zip((u32::from(request.address)..).step_by(usize::from(width)))
+            .map(|(value, address)| (signal(address), request.format.decode(value)))
+            .collect(),
+    }
+}
+
+// ============================================================================
+// ARBITRATION
+// ============================================================================
+
+/// Order in which the units of a line send their requests
+#[derive(Debug)]
+struct Arbiter {
+    /// Requests per unit
+    sizes: Vec<usize>,
+    /// Next request of each unit
+    cursors: Vec<usize>,
+    /// Requests each unit may still send in this cycle
+    budgets: Vec<usize>,
+    /// Unit asked next
+    turn: usize,
+    /// Unit starting the next cycle
+    first: usize,
+}
+
+impl Arbiter {
+    fn new(sizes: Vec<usize>) -> Self {
+        Self {
+            cursors: vec![0; sizes.len()],
+            budgets: vec![0; sizes.len()],
+            sizes,
+            turn: 0,
+            first: 0,
+        }
+    }
+
+    /// Start a cycle in which every unit may send each of its requests once
+    fn begin_cycle(&mut self) {
+        self.budgets.clone_from(&self.sizes);
+        self.turn = self.first;
+        self.first = (self.first + 1) % self.sizes.len().max(1);
+    }
+
+    /// Unit and request to send next, or `None` when the cycle is complete
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let units = self.sizes.len();
+        for _ in 0..units {
+            let unit = self.turn;
+            self.turn = (self.turn + 1) % units;
+            if self.budgets[unit] > 0 {
+                self.budgets[unit] -= 1;
+                let request = self.cursors[unit];
+                self.cursors[unit] = (request + 1) % self.sizes[unit];
+                return Some((unit, request));
+            }
+        }
+        None
+    }
+
+    /// Give up the remaining turns of `unit` in this cycle
+    fn skip(&mut self, unit: usize) {
+        self.budgets[unit] = 0;
+    }
+}
+
+// ============================================================================
+// LINES
+// ============================================================================
+
+struct Unit {
+    connection: ModbusConnection,
+    requests: Vec<Request>,
+}
+
+#[derive(Debug, Default)]
+struct CycleStats {
+    requests: usize,
+    timeouts: usize,
+    elapsed: Duration,
+}
+
+/// The units sharing one serial port
+struct Line {
+    port: String,
+    serial: ModbusSerial,
+    units: Vec<Unit>,
+    arbiter: Arbiter,
+    timeout: Duration,
+    frame_delay: Duration,
+    context: Option<Context>,
+    /// End of the last exchange on the line
+    last_frame: Option<Instant>,
+}
+
+impl Line {
+    fn new(port: String, connections: Vec<ModbusConnection>, timeout: Duration) -> Result<Self> {
+        let serial = connections.first().and_then(|c| c.serial.clone()).unwrap_or_default();
+        let mut units = Vec::with_capacity(connections.len());
+        for connection in connections {
+            units.push(Unit {
+                requests: requests(&connection)?,
+                connection,
+            });
+        }
+        Ok(Self {
+            arbiter: Arbiter::new(units.iter().map(|u| u.requests.len()).collect()),
+            frame_delay: frame_delay(&serial),
+            port,
+            serial,
+            units,
+            timeout,
+            context: None,
+            last_frame: None,
+        })
+    }
+
+    async fn open(&mut self) -> Result<Context> {
+        let data_bits = if self.serial.data_bits == 7 { DataBits::Seven } else { DataBits::Eight };
+        let parity = match self.serial.parity.to_lowercase().as_str() {
+            "odd" => Parity::Odd,
+            "none" => Parity::None,
+            _ => Parity::Even,
+        };
+        let stop_bits = if self.serial.stop_bits == 2 { StopBits::Two } else { StopBits::One };
+        let stream = tokio_serial::new(&self.port, self.serial.baud_rate)
+            .data_bits(data_bits)
+            .parity(parity)
+            .stop_bits(stop_bits)
+            .open_native_async()
+            .map_err(|e| PlcError::Protocol(format!("Cannot open serial port '{}': {e}", self.port)))?;
+        let first = self.units.first().map_or(1, |u| u.connection.unit_id);
+        rtu::connect_slave(stream, Slave(first)).await.map_err(|source| PlcError::Modbus { source })
+    }
+
+    /// Send one request, `None` if the unit did not answer in time
+    async fn exchange(&mut self, unit: usize, request: usize) -> Result<Option<Reading>> {
+        if self.context.is_none() {
+            self.context = Some(self.open().await?);
+        }
+        if let Some(last) = self.last_frame {
+            tokio::time::sleep_until((last + self.frame_delay).into()).await;
+        }
+        let unit_id = self.units[unit].connection.unit_id;
+        let Request { kind, address, count, .. } = self.units[unit].requests[request];
+        let Some(context) = self.context.as_mut() else {
+            return Ok(None);
+        };
+        context.set_slave(Slave(unit_id));
+        let read = async {
+            match kind {
+                RegisterKind::Coil => context.read_coils(address, count).await.map(Reading::Bits),
+                RegisterKind::Discrete => context.read_discrete_inputs(address, count).await.map(Reading::Bits),
+                RegisterKind::Holding => context.read_holding_registers(address, count).await.map(Reading::Words),
+                RegisterKind::Input => context.read_input_registers(address, count).await.map(Reading::Words),
+            }
+        };
+        let result = tokio::time::timeout(self.timeout, read).await;
+        self.last_frame = Some(Instant::now());
+        match result {
+            Ok(Ok(reading)) => Ok(Some(reading)),
+            // Exception responses are reported as `Other`: the unit answered
+            Ok(Err(e)) if e.kind() == io::ErrorKind::Other => {
+                debug!("Modbus unit {unit_id} {} {address}+{count}: {e}", kind.name());
+                Ok(Some(Reading::Words(Vec::new())))
+            }
+            Ok(Err(source)) => Err(PlcError::Modbus { source }),
+            Err(_) => {
+                // A late answer would be taken for the next response
+                self.context = None;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Give every unit its turns until the cycle is complete or overdue
+    async fn cycle(&mut self, bus: &SignalBus) -> Result<CycleStats> {
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(self.serial.cycle_ms);
+        let mut stats = CycleStats::default();
+        self.arbiter.begin_cycle();
+        while Instant::now() < deadline {
+            let Some((unit, request)) = self.arbiter.next() else {
+                break;
+            };
+            let Some(reading) = self.exchange(unit, request).await? else {
+                debug!("Modbus unit {} on {} timed out", self.units[unit].connection.unit_id, self.port);
+                self.arbiter.skip(unit);
+                stats.timeouts += 1;
+                continue;
+            };
+            stats.requests += 1;
+            let unit = &self.units[unit];
+            let request = &unit.requests[request];
+            for (signal, value) in values(&unit.connection.registers[request.register], request, &reading) {
+                if let Err(e) = bus.set(&signal, value) {
+                    warn!("Modbus could not write '{signal}': {e}");
+                }
+            }
+        }
+        stats.elapsed = started.elapsed();
+        Ok(stats)
+    }
+
+    /// Poll `connections` from now on, keeping the port open unless its
+    /// serial settings changed
+    fn reconfigure(&mut self, connections: Vec<ModbusConnection>) -> Result<()> {
+        let mut line = Self::new(self.port.clone(), connections, self.timeout)?;
+        let settings = |s: &ModbusSerial| (s.baud_rate, s.data_bits, s.parity.to_lowercase(), s.stop_bits);
+        if settings(&line.serial) == settings(&self.serial) {
+            line.context = self.context.take();
+            line.last_frame = self.last_frame;
+        }
+        *self = line;
+        Ok(())
+    }
+
+    fn ticker(&self) -> tokio::time::Interval {
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.serial.cycle_ms));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        ticker
+    }
+
+    /// Poll the line, switching to every set of units sent through `updates`
+    async fn run(mut self, mut updates: watch::Receiver<Vec<ModbusConnection>>, bus: SignalBus) -> Result<()> {
+        let mut ticker = self.ticker();
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                Ok(()) = updates.changed() => {
+                    let connections = updates.borrow_and_update().clone();
+                    match self.reconfigure(connections) {
+                        Ok(()) => {
+                            ticker = self.ticker();
+                            info!(
+                                "Modbus RTU line {} reconfigured: {} units every {} ms",
+                                self.port,
+                                self.units.len(),
+                                self.serial.cycle_ms
+                            );
+                        }
+                        Err(e) => warn!("Modbus RTU line {} keeps its previous configuration: {}", self.port, e),
+                    }
+                    continue;
+                }
+            }
+            match self.cycle(&bus).await {
+                Ok(stats) => {
+                    backoff = Duration::from_secs(1);
+                    debug!(
+                        "Modbus RTU {}: {} requests, {} timeouts in {:?}",
+                        self.port, stats.requests, stats.timeouts, stats.elapsed
+                    );
+                }
+                Err(e) => {
+                    warn!("Modbus RTU line {} failed, reopening: {}", self.port, e);
+                    self.context = None;
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Duration::from_mins(1));
+                }
+            }
+        }
+    }
+}
+
+/// RTU connections grouped by serial port
+#[must_use]
+pub fn lines(connections: Vec<ModbusConnection>) -> BTreeMap<String, Vec<ModbusConnection>> {
+    let mut lines: BTreeMap<String, Vec<ModbusConnection>> = BTreeMap::new();
+    for connection in connections {
+        if connection.connection_type.eq_ignore_ascii_case("rtu") {
+            lines.entry(connection.address.clone()).or_default().push(connection);
+        }
+    }
+    lines
+}
+
+/// Poll every RTU line until the task is cancelled
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the configuration is invalid or a
+/// mapping uses an unsupported data type.
+pub async fn run(config: ModbusConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut tasks = JoinSet::new();
+    for (port, connections) in lines(config.connections) {
+        let line = Line::new(port, connections, timeout)?;
+        info!(
+            "Modbus RTU line {} at {} baud: {} units, {} requests per cycle",
+            line.port,
+            line.serial.baud_rate,
+            line.units.len(),
+            line.arbiter.sizes.iter().sum::<usize>()
+        );
+        let (_, updates) = watch::channel(Vec::new());
+        tasks.spawn(line.run(updates, bus.clone()));
+    }
+    while let Some(joined) = tasks.join_next().await {
+        joined.map_err(|e| PlcError::Runtime(format!("Modbus RTU line task failed: {e}")))??;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// DRIVER
+// ============================================================================
+
+/// One serial line polled in the background, registered with the
+/// [`ProtocolManager`](super::ProtocolManager) under its port so the units,
+/// their registers and the cycle time can be changed with
+/// [`reload_driver`](super::ProtocolManager::reload_driver)
+///
+/// Reads return the values of the last cycle; the driver does not write.
+pub struct ModbusRtuDriver {
+    port: String,
+    timeout: Duration,
+    bus: SignalBus,
+    updates: watch::Sender<Vec<ModbusConnection>>,
+    task: Option<JoinHandle<Result<()>>>,
+}
+
+impl ModbusRtuDriver {
+    /// Drivers for every RTU line of `config`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid or a
+    /// mapping uses an unsupported data type.
+    pub fn from_config(config: &ModbusConfig, bus: &SignalBus) -> Result<Vec<Self>> {
+        config.validate()?;
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let mut drivers = Vec::new();
+        for (port, connections) in lines(config.connections.clone()) {
+            Line::new(port.clone(), connections.clone(), timeout)?;
+            let (updates, _) = watch::channel(connections);
+            drivers.push(Self {
+                port,
+                timeout,
+                bus: bus.clone(),
+                updates,
+                task: None,
+            });
+        }
+        Ok(drivers)
+    }
+
+    /// Serial port the driver is registered under
+    #[must_use]
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+}
+
+#[async_trait]
+impl super::ProtocolDriver for ModbusRtuDriver {
+    async fn connect(&mut self) -> Result<()> {
+        if !self.is_connected() {
+            let line = Line::new(self.port.clone(), self.updates.borrow().clone(), self.timeout)?;
+            let updates = self.updates.subscribe();
+            self.task = Some(tokio::spawn(line.run(updates, self.bus.clone())));
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+        Ok(addresses
+            .iter()
+            .filter_map(|signal| self.bus.get(signal).map(|value| (signal.clone(), value)))
+            .collect())
+    }
+
+    async fn write_values(&mut self, _values: &HashMap<String, Value>) -> Result<()> {
+        Err(PlcError::Protocol(format!("Writing to Modbus RTU line {} is not supported", self.port)))
+    }
+
+    fn is_connected(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "modbus_rtu"
+    }
+
+    /// Takes the RTU connections of the line, e.g. with new `registers` or
+    /// a new `serial.cycle_ms`. The port stays open unless its serial
+    /// settings change.
+    async fn reconfigure(&mut self, config: &serde_yaml::Value) -> Result<()> {
+        let connections: Vec<ModbusConnection> = serde_yaml::from_value(config.clone())
+            .map_err(|e| PlcError::Config(format!("Invalid Modbus RTU connections: {e}")))?;
+        if let Some(other) = connections
+            .iter()
+            .find(|c| !c.connection_type.eq_ignore_ascii_case("rtu") || c.address != self.port)
+        {
+            return Err(PlcError::Config(format!(
+                "Modbus connection '{}' is not an RTU connection on {}",
+                other.name, self.port
+            )));
+        }
+        ModbusConfig {
+            connections: connections.clone(),
+            timeout_ms: 0,
+        }
+        .validate()?;
+        Line::new(self.port.clone(), connections.clone(), self.timeout)?;
+        self.updates.send_replace(connections);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_delay_and_fair_turns() {
+        let serial = ModbusSerial {
+            baud_rate: 9600,
+            ..ModbusSerial::default()
+        };
+        // 11 bit characters at 9600 baud
+        assert_eq!(frame_delay(&serial).as_micros(), 4010);
+        let fast = ModbusSerial { baud_rate: 115_200, ..serial.clone() };
+        assert_eq!(frame_delay(&fast), FIXED_FRAME_DELAY);
+        let fixed = ModbusSerial { inter_frame_delay_us: Some(5000), ..serial };
+        assert_eq!(frame_delay(&fixed), Duration::from_millis(5));
+
+        // A unit with many requests does not hold the line
+        let mut arbiter = Arbiter::new(vec![3, 1, 0, 2]);
+        arbiter.begin_cycle();
+        let order: Vec<_> = std::iter::from_fn(|| arbiter.next()).collect();
+        assert_eq!(order, vec![(0, 0), (1, 0), (3, 0), (0, 1), (3, 1), (0, 2)]);
+
+        // The next cycle starts with another unit; a unit that timed out
+        // gives up its remaining turns
+        arbiter.begin_cycle();
+        assert_eq!(arbiter.next(), Some((1, 0)));
+        assert_eq!(arbiter.next(), Some((3, 0)));
+        assert_eq!(arbiter.next(), Some((0, 0)));
+        arbiter.skip(0);
+        let rest: Vec<_> = std::iter::from_fn(|| arbiter.next()).collect();
+        assert_eq!(rest, vec![(3, 1)]);
+    }
+
+    #[test]
+    fn test_requests_and_values() {
+        let connection: ModbusConnection = serde_yaml::from_str(
+            "name: meter\ntype: rtu\naddress: /dev/ttyUSB0\nunit_id: 7\nregisters:\n\
+             - { type: input, address: 100, count: 2, signal: power, data_type: float32 }\n\
+             - { type: holding, address: 0, count: 300, signal: log }\n\
+             - { type: coil, address: 8, count: 3, signal: relay }\n",
+        )
+        .unwrap();
+        let requests = requests(&connection).unwrap();
+        let spans: Vec<_> = requests.iter().map(|r| (r.address, r.count)).collect();
+        assert_eq!(spans, vec![(100, 2), (0, 125), (125, 125), (250, 50), (8, 3)]);
+
+        let words = 1.5f32.to_bits();
+        let reading = Reading::Words(vec![u16::try_from(words >> 16).unwrap(), u16::try_from(words & 0xFFFF).unwrap()]);
+        let power = values(&connection.registers[0], &requests[0], &reading);
+        assert_eq!(power, vec![("power".to_string(), Value::Float(1.5))]);
+
+        let log = values(&connection.registers[1], &requests[2], &Reading::Words(vec![0xFFFF; 125]));
+        assert_eq!(log[0], ("log.125".to_string(), Value::Integer(-1)));
+
+        let relays = values(&connection.registers[2], &requests[4], &Reading::Bits(vec![true, false, true, false]));
+        assert_eq!(relays.len(), 3);
+        assert_eq!(relays[2], ("relay.10".to_string(), Value::Bool(true)));
+    }
+
+    #[tokio::test]
+    async fn test_reload_line() {
+        let unit = |registers: &str, cycle_ms: u64| {
+            format!(
+                "- {{ name: vfd, type: rtu, address: /dev/ttyPETRA0, unit_id: 1, \
+                 serial: {{ baud_rate: 19200, cycle_ms: {cycle_ms} }}, registers: [{registers}] }}\n"
+            )
+        };
+        let config: ModbusConfig =
+            serde_yaml::from_str(&format!("connections:\n{}", unit("{ type: holding, address: 0, count: 2, signal: a }", 500)))
+                .unwrap();
+        let bus = SignalBus::new();
+        let driver = ModbusRtuDriver::from_config(&config, &bus).unwrap().remove(0);
+        assert_eq!(driver.port(), "/dev/ttyPETRA0");
+        let manager = super::super::ProtocolManager::new(bus);
+        manager.add_driver("/dev/ttyPETRA0".to_string(), Box::new(driver)).await.unwrap();
+        manager.connect_all().await.unwrap();
+
+        let reload = |yaml: String| {
+            let manager = &manager;
+            async move { manager.reload_driver("/dev/ttyPETRA0", &serde_yaml::from_str(&yaml).unwrap()).await }
+        };
+        let registers = "{ type: holding, address: 0, count: 2, signal: a }, { type: coil, address: 8, count: 3, signal: b }";
+        reload(unit(registers, 100)).await.unwrap();
+        assert!(manager.is_connected("/dev/ttyPETRA0").await);
+        let result = reload(unit(registers, 100).replace("ttyPETRA0", "ttyPETRA1")).await;
+        assert!(matches!(result, Err(PlcError::Config(_))));
+        let result = reload(unit("{ type: holding, address: 0, count: 2, signal: a, data_type: text }", 100)).await;
+        assert!(matches!(result, Err(PlcError::Config(_))));
+
+        // The line polls the new registers at the new cycle time
+        let connections: Vec<ModbusConnection> = serde_yaml::from_str(&unit(registers, 100)).unwrap();
+        let mut line = Line::new("/dev/ttyPETRA0".to_string(), config.connections, Duration::from_millis(300)).unwrap();
+        line.reconfigure(connections).unwrap();
+        assert_eq!(line.serial.cycle_ms, 100);
+        assert_eq!(line.arbiter.sizes, vec![2]);
+    }
+}
@@ -3,8 +3,14 @@
 //! 
 //! Provides MQTT client functionality with support for subscriptions, publications,
 //! and various MQTT features based on enabled feature flags.
+//! 
+//! Publications are sent whenever their signal changes on the bus, through
+//! a change subscription rather than by polling the signals.
 
-use crate::{error::*, signal::SignalBus, value::Value};
+use crate::{display::DisplayFormatter, error::*, signal::SignalBus, value::Value};
+use crate::subscription::{Coalesce, SubscribeOptions};
+use futures::StreamExt;
+use super::mqtt_codec::PayloadCodec;
 use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, QoS, Packet};
 use serde::{Deserialize, Serialize};
 use tracing::{info, error, debug, trace};
@@ -15,9 +21,6 @@ use std::path::PathBuf;
 #[cfg(feature = "mqtt-tls")]
 use rumqttc::{TlsConfiguration, Transport};
 
-#[cfg(feature = "mqtt-tls")]
-use std::fs::File;
-
 #[cfg(feature = "metrics")]
 use std::sync::Arc;
 
@@ -131,6 +134,10 @@ pub struct MqttSubscription {
     /// Data transformation configuration (requires validation feature)
     #[cfg(feature = "validation")]
     pub transform: Option<TransformConfig>,
+    
+    /// Payload decoding; replaces the plain text parsing and transform
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<PayloadCodec>,
 }
 
 /// MQTT publication configuration
@@ -153,6 +160,26 @@ pub struct MqttPublication {
     /// Publication interval in milliseconds (None for event-driven)
     #[serde(default)]
     pub interval_ms: Option<u64>,
+    
+    /// Payload encoding
+    #[serde(default)]
+    pub payload: PayloadFormat,
+    
+    /// Payload encoding by codec; takes precedence over `payload`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<PayloadCodec>,
+}
+
+/// MQTT publication payload encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    /// Bare value text (`72.456`, `true`)
+    #[default]
+    Raw,
+    
+    /// JSON object with the raw value, its display text and units
+    Json,
 }
 
 fn default_qos() -> u8 { 1 }
@@ -319,6 +346,9 @@ pub struct MqttClient {
     
     /// Reconnection strategy (enabled by default)
     reconnect_strategy: ReconnectStrategy,
+    
+    /// Display formatting for JSON payloads
+    display: DisplayFormatter,
 }
 
 // rumqttc's EventLoop is not `Sync`, which prevents the auto-derivation of `Send`
@@ -371,6 +401,12 @@ impl Default for ReconnectStrategy {
 impl MqttClient {
     /// Create a new MQTT client instance
     pub fn new(config: MqttConfig, bus: SignalBus) -> Result<Self> {
+        let codecs = config.subscriptions.iter().filter_map(|s| s.codec.as_ref())
+            .chain(config.publications.iter().filter_map(|p| p.codec.as_ref()));
+        for codec in codecs {
+            codec.validate()?;
+        }
+        
         let mut mqtt_options = MqttOptions::new(
             &config.client_id,
             &config.broker_host,
@@ -412,9 +448,17 @@ impl MqttClient {
             #[cfg(feature = "metrics")]
             statistics: Arc::new(RwLock::new(MqttStatistics::default())),
             reconnect_strategy: ReconnectStrategy::default(),
+            display: DisplayFormatter::default(),
         })
     }
     
+    /// Apply signal display formatting to JSON publications
+    #[must_use]
+    pub fn with_display(mut self, display: DisplayFormatter) -> Self {
+        self.display = display;
+        self
+    }
+    
     /// Run the MQTT client event loop
     pub async fn run(mut self) -> Result<()> {
         info!("Starting MQTT client for broker {}:{}", 
@@ -440,18 +484,38 @@ impl MqttClient {
                   subscription.topic, subscription.signal);
         }
         
+        // Publications follow the changes of their signals
+        let mut publications = self.bus.subscribe_with(
+            self.config.publications.iter().map(|publication| publication.signal.as_str()),
+            SubscribeOptions::default().coalesce(Coalesce::Latest),
+        );
+        
         // Main event loop with reconnection handling
         let mut reconnect_attempts = 0;
         let mut reconnect_delay = self.reconnect_strategy.initial_delay_ms;
         
         loop {
-            match self.eventloop.poll().await {
+            let polled = tokio::select! {
+                polled = self.eventloop.poll() => polled,
+                Some(change) = publications.next() => {
+                    if let Err(e) = self.publish_signal(&change.name, &change.value).await {
+                        error!("Failed to publish signal '{}': {}", change.name, e);
+                    }
+                    continue;
+                }
+            };
+            match polled {
                 Ok(event) => {
                     // Reset reconnection state on successful event
                     reconnect_attempts = 0;
                     reconnect_delay = self.reconnect_strategy.initial_delay_ms;
                     
-                    if let Err(e) = self.handle_event(event).await {
+                    if let Err(e) = crate::scan_budget::instrument(
+                        crate::scan_budget::Subsystem::Protocols,
+                        self.handle_event(event),
+                    )
+                    .await
+                    {
                         error!("Error handling MQTT event: {}", e);
                     }
                 }
@@ -520,7 +584,7 @@ impl MqttClient {
                 for sub in &self.config.subscriptions {
                     if topic_matches(&sub.topic, &publish.topic) {
                         let value = self.parse_payload(&publish.payload, sub)?;
-                        self.bus.set(&sub.signal, value)?;
+                        self.bus.set_with_source(&sub.signal, value, Some("mqtt"))?;
                         
                         debug!("Updated signal '{}' from topic '{}'", sub.signal, publish.topic);
                         break;
@@ -555,13 +619,17 @@ impl MqttClient {
     }
     
     /// Parse MQTT payload into a PETRA Value - FIXED VERSION
-    fn parse_payload(&self, payload: &[u8], _sub: &MqttSubscription) -> Result<Value> {
+    fn parse_payload(&self, payload: &[u8], sub: &MqttSubscription) -> Result<Value> {
+        if let Some(codec) = &sub.codec {
+            return codec.decode(payload);
+        }
+        
         let text = std::str::from_utf8(payload)
             .map_err(|e| PlcError::Mqtt(format!("Invalid UTF-8 in payload: {}", e)))?;
         
         // Apply data transformation if configured
         #[cfg(feature = "validation")]
-        if let Some(transform) = &_sub.transform {
+        if let Some(transform) = &sub.transform {
             return self.apply_transform(text, transform);
         }
         
@@ -657,7 +725,19 @@ impl MqttClient {
         // Find publication configuration for this signal
         for pub_config in &self.config.publications {
             if pub_config.signal == signal_name {
-                let payload = self.format_value_for_mqtt(value)?;
+                let payload = match (&pub_config.codec, pub_config.payload) {
+                    (Some(codec), _) => {
+                        let timestamp = self.bus.get_with_meta(signal_name)
+                            .map_or_else(|| chrono::Utc::now().timestamp_millis(), |(_, meta)| meta.unix_millis());
+                        codec.encode(signal_name, value.clone(), timestamp)?
+                    }
+                    (None, PayloadFormat::Raw) => self.format_value_for_mqtt(value)?.into_bytes(),
+                    (None, PayloadFormat::Json) => {
+                        let formatted = self.display.render(signal_name, value.clone());
+                        serde_json::to_vec(&formatted)
+                            .map_err(|e| PlcError::Mqtt(format!("Failed to serialize payload: {}", e)))?
+                    }
+                };
                 
                 let qos = match pub_config.qos {
                     0 => QoS::AtMostOnce,
@@ -670,7 +750,7 @@ impl MqttClient {
                     &pub_config.topic,
                     qos,
                     pub_config.retain,
-                    payload.as_bytes(),
+                    payload.clone(),
                 ).await.map_err(|e| PlcError::Mqtt(format!(
                     "Failed to publish to topic '{}': {}", pub_config.topic, e
                 )))?;
@@ -723,7 +803,7 @@ impl MqttClient {
 // ============================================================================
 
 /// Check if an MQTT topic matches a topic pattern (with wildcards)
-fn topic_matches(pattern: &str, topic: &str) -> bool {
+pub(crate) fn topic_matches(pattern: &str, topic: &str) -> bool {
     let pattern_parts: Vec<&str> = pattern.split('/').collect();
     let topic_parts: Vec<&str> = topic.split('/').collect();
     
@@ -761,36 +841,18 @@ fn topic_matches(pattern: &str, topic: &str) -> bool {
 /// Create TLS configuration for secure MQTT connections
 #[cfg(feature = "mqtt-tls")]
 fn create_tls_config(config: &TlsConfig) -> Result<TlsConfiguration> {
-    let mut tls_config = TlsConfiguration::Simple {
-        ca: Vec::new(),
-        alpn: config.alpn_protocols.clone().map(|protocols| {
-            protocols.into_iter().map(|p| p.into_bytes()).collect()
-        }),
-        client_auth: None,
-    };
-    
-    if config.insecure {
-        warn!("MQTT TLS configured with insecure mode - certificate verification disabled!");
-        // In a real implementation, you'd configure to skip verification
-    }
-    
-    if let Some(ca_path) = &config.ca_cert {
-        let _ca_file = File::open(ca_path)
-            .map_err(|e| PlcError::Mqtt(format!("Cannot open CA cert file: {}", e)))?;
-        // Load CA certificate
-        // Full implementation would parse and add CA cert to TLS config
-    }
-    
-    if let (Some(cert_path), Some(key_path)) = (&config.client_cert, &config.client_key) {
-        let _cert_file = File::open(cert_path)
-            .map_err(|e| PlcError::Mqtt(format!("Cannot open client cert file: {}", e)))?;
-        let _key_file = File::open(key_path)
-            .map_err(|e| PlcError::Mqtt(format!("Cannot open client key file: {}", e)))?;
-        // Load client certificate and key
-        // Full implementation would parse and add client cert/key to TLS config
-    }
-    
-    Ok(tls_config)
+    let ca_cert = config
+        .ca_cert
+        .as_ref()
+        .ok_or_else(|| PlcError::Mqtt("TLS: ca_cert is required".to_string()))?;
+    crate::protocols::mqtt_tls::client_config(&crate::config::MqttTlsConfig {
+        ca_cert: ca_cert.into(),
+        client_cert: config.client_cert.as_ref().map(Into::into),
+        client_key: config.client_key.as_ref().map(Into::into),
+        verify_server: !config.insecure,
+        alpn: config.alpn_protocols.clone().unwrap_or_default(),
+        ca_service: None,
+    })
 }
 
 /// Convert payload data based on signal type - FIXED VERSION
@@ -0,0 +1,365 @@
+// src/protocols/mqtt_codec.rs
+//! Payload codecs for MQTT subscriptions and publications
+//!
+//! A codec turns received payloads into signal values and signal values into
+//! published payloads without a custom block in between:
+//!
+//! ```yaml
+//! subscriptions:
+//!   - topic: plant/tank1/level
+//!     signal: tank1.level
+//!     codec: { pointer: /value, scale: 0.01 }        # {"value": 4210} -> 42.1
+//!   - topic: plant/meter/raw
+//!     signal: meter.power
+//!     codec: { encoding: base64, binary: float32, byte_order: little }
+//! publications:
+//!   - topic: plant/tank1/setpoint
+//!     signal: tank1.setpoint
+//!     codec: { template: '{"value": {value}, "ts": {timestamp}}' }
+//! ```
+//!
+//! Decoding runs in this order: the payload is base64-decoded if needed, a
+//! binary value is read from the bytes or the text is parsed (through the
+//! JSON pointer if one is set), and finally `value * scale + offset` is
+//! applied. Encoding scales first, then writes the binary value or renders
+//! the template, and base64-encodes last. Scaling always follows the data
+//! flow, so a publication scales signal values on their way out.
+
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+
+/// Transport encoding of the payload bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEncoding {
+    /// UTF-8 text, or raw bytes for binary values
+    #[default]
+    Text,
+    /// Base64 text of the bytes
+    Base64,
+}
+
+/// Layout of a binary value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryType {
+    Bool,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Int64,
+    Float32,
+    Float64,
+}
+
+impl BinaryType {
+    /// Size in bytes
+    #[must_use]
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Bool => 1,
+            Self::Int16 | Self::Uint16 => 2,
+            Self::Int32 | Self::Uint32 | Self::Float32 => 4,
+            Self::Int64 | Self::Float64 => 8,
+        }
+    }
+}
+
+/// Byte order of binary values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrder {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Conversion between payloads and signal values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayloadCodec {
+    #[serde(default)]
+    pub encoding: PayloadEncoding,
+
+    /// Binary value layout; the payload is text when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<BinaryType>,
+
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+
+    /// JSON pointer of the value in received payloads (`/value`, `/data/0/temp`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+
+    /// Text of published payloads with `{value}`, `{signal}` and
+    /// `{timestamp}` (milliseconds since the Unix epoch) placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    #[serde(default)]
+    pub offset: f64,
+}
+
+const fn default_scale() -> f64 {
+    1.0
+}
+
+impl Default for PayloadCodec {
+    fn default() -> Self {
+        Self {
+            encoding: PayloadEncoding::default(),
+            binary: None,
+            byte_order: ByteOrder::default(),
+            pointer: None,
+            template: None,
+            scale: default_scale(),
+            offset: 0.0,
+        }
+    }
+}
+
+impl PayloadCodec {
+    /// Check the codec settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the JSON pointer does not start with
+    /// `/`, a binary value is combined with a pointer or template, or the
+    /// scaling is not a finite number.
+    pub fn validate(&self) -> Result<()> {
+        if self.pointer.as_ref().is_some_and(|p| !p.is_empty() && !p.starts_with('/')) {
+            return Err(PlcError::Config("MQTT codec pointer must start with '/'".to_string()));
+        }
+        if self.binary.is_some() && (self.pointer.is_some() || self.template.is_some()) {
+            return Err(PlcError::Config(
+                "MQTT codec cannot combine a binary value with a pointer or template".to_string(),
+            ));
+        }
+        if !self.scale.is_finite() || !self.offset.is_finite() {
+            return Err(PlcError::Config("MQTT codec scale and offset must be finite".to_string()));
+        }
+        Ok(())
+    }
+
+    // The defaults are exact, so unscaled values keep their type
+    #[allow(clippy::float_cmp)]
+    fn scaled(&self, value: Value) -> Result<Value> {
+        if self.scale == 1.0 && self.offset == 0.0 {
+            return Ok(value);
+        }
+        let v = value
+            .as_float()
+            .filter(|_| !value.is_bool())
+            .ok_or_else(|| PlcError::Mqtt(format!("Cannot scale non-numeric value {value}")))?;
+        Ok(Value::Float(v * self.scale + self.offset))
+    }
+
+    /// Signal value of a received payload
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Mqtt`] if the payload does not hold a value in the
+    /// configured form.
+    pub fn decode(&self, payload: &[u8]) -> Result<Value> {
+        let decoded;
+        let bytes = match self.encoding {
+            PayloadEncoding::Text => payload,
+            PayloadEncoding::Base64 => {
+                decoded = BASE64
+                    .decode(payload.trim_ascii())
+                    .map_err(|e| PlcError::Mqtt(format!("Invalid base64 payload: {e}")))?;
+                &decoded
+            }
+        };
+
+        let value = if let Some(binary) = self.binary {
+            read_binary(bytes, binary, self.byte_order)?
+        } else {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|e| PlcError::Mqtt(format!("Invalid UTF-8 in payload: {e}")))?
+                .trim();
+            match &self.pointer {
+                Some(pointer) => {
+                    let json: serde_json::Value = serde_json::from_str(text)
+                        .map_err(|e| PlcError::Mqtt(format!("Invalid JSON payload: {e}")))?;
+                    let found = json
+                        .pointer(pointer)
+                        .ok_or_else(|| PlcError::Mqtt(format!("Payload has no value at {pointer}")))?;
+                    json_value(found)?
+                }
+                None => parse_text(text)?,
+            }
+        };
+        self.scaled(value)
+    }
+
+    /// Payload published for a signal value
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Mqtt`] if the value cannot be scaled or does not
+    /// fit the binary layout.
+    pub fn encode(&self, signal: &str, value: Value, timestamp_ms: i64) -> Result<Vec<u8>> {
+        let value = self.scaled(value)?;
+        let bytes = match (self.binary, &self.template) {
+            (Some(binary), _) => write_binary(&value, binary, self.byte_order)?,
+            (None, Some(template)) => template
+                .replace("{value}", &text(&value))
+                .replace("{signal}", signal)
+                .replace("{timestamp}", &timestamp_ms.to_string())
+                .into_bytes(),
+            (None, None) => text(&value).into_bytes(),
+        };
+        Ok(match self.encoding {
+            PayloadEncoding::Text => bytes,
+            PayloadEncoding::Base64 => BASE64.encode(bytes).into_bytes(),
+        })
+    }
+}
+
+/// Plain text of a value
+fn text(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        #[cfg(feature = "extended-types")]
+        Value::String(s) => s.clone(),
+        #[allow(unreachable_patterns)]
+        other => other.to_string(),
+    }
+}
+
+fn parse_text(text: &str) -> Result<Value> {
+    if let Ok(b) = text.parse::<bool>() {
+        Ok(Value::Bool(b))
+    } else if let Ok(i) = text.parse::<i64>() {
+        Ok(Value::Integer(i))
+    } else if let Ok(f) = text.parse::<f64>() {
+        Ok(Value::Float(f))
+    } else {
+        #[cfg(feature = "extended-types")]
+        return Ok(Value::String(text.to_string()));
+        #[cfg(not(feature = "extended-types"))]
+        Err(PlcError::Mqtt(format!("Cannot parse payload as basic type: {text}")))
+    }
+}
+
+fn json_value(json: &serde_json::Value) -> Result<Value> {
+    match json {
+        serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .map(Value::Integer)
+            .or_else(|| n.as_f64().map(Value::Float))
+            .ok_or_else(|| PlcError::Mqtt(format!("Number {n} is out of range"))),
+        // Numbers and booleans sent as strings
+        serde_json::Value::String(s) => parse_text(s),
+        other => Err(PlcError::Mqtt(format!("Payload value {other} is not a scalar"))),
+    }
+}
+
+fn read_binary(bytes: &[u8], binary: BinaryType, order: ByteOrder) -> Result<Value> {
+    let size = binary.size();
+    if bytes.len() != size {
+        return Err(PlcError::Mqtt(format!(
+            "Binary {binary:?} payload needs {size} bytes, got {}",
+            bytes.len()
+        )));
+    }
+    let mut raw = [0u8; 8];
+    raw[..size].copy_from_slice(bytes);
+    if order == ByteOrder::Little {
+        raw[..size].reverse();
+    }
+    // `raw` now holds the value big-endian in its first `size` bytes
+    Ok(match binary {
+        BinaryType::Bool => Value::Bool(raw[0] != 0),
+        BinaryType::Int16 => Value::Integer(i64::from(i16::from_be_bytes([raw[0], raw[1]]))),
+        BinaryType::Uint16 => Value::Integer(i64::from(u16::from_be_bytes([raw[0], raw[1]]))),
+        BinaryType::Int32 => Value::Integer(i64::from(i32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]))),
+        BinaryType::Uint32 => Value::Integer(i64::from(u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]))),
+        BinaryType::Int64 => Value::Integer(i64::from_be_bytes(raw)),
+        BinaryType::Float32 => Value::Float(f64::from(f32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]))),
+        BinaryType::Float64 => Value::Float(f64::from_be_bytes(raw)),
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)] // float32 payloads are lossy by design
+fn write_binary(value: &Value, binary: BinaryType, order: ByteOrder) -> Result<Vec<u8>> {
+    let out_of_range = || PlcError::Mqtt(format!("Value {value} does not fit binary {binary:?}"));
+    let integer = || -> Result<i64> {
+        match value {
+            Value::Float(f) if f.fract() != 0.0 => Err(out_of_range()),
+            other => other.as_integer().ok_or_else(out_of_range),
+        }
+    };
+    let mut bytes = match binary {
+        BinaryType::Bool => vec![u8::from(value.as_bool().ok_or_else(out_of_range)?)],
+        BinaryType::Int16 => i16::try_from(integer()?).map_err(|_| out_of_range())?.to_be_bytes().to_vec(),
+        BinaryType::Uint16 => u16::try_from(integer()?).map_err(|_| out_of_range())?.to_be_bytes().to_vec(),
+        BinaryType::Int32 => i32::try_from(integer()?).map_err(|_| out_of_range())?.to_be_bytes().to_vec(),
+        BinaryType::Uint32 => u32::try_from(integer()?).map_err(|_| out_of_range())?.to_be_bytes().to_vec(),
+        BinaryType::Int64 => integer()?.to_be_bytes().to_vec(),
+        BinaryType::Float32 => (value.as_float().ok_or_else(out_of_range)? as f32).to_be_bytes().to_vec(),
+        BinaryType::Float64 => value.as_float().ok_or_else(out_of_range)?.to_be_bytes().to_vec(),
+    };
+    if order == ByteOrder::Little {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec(yaml: &str) -> PayloadCodec {
+        let codec: PayloadCodec = serde_yaml::from_str(yaml).unwrap();
+        codec.validate().unwrap();
+        codec
+    }
+
+    #[test]
+    fn test_json_pointer_and_scaling() {
+        let codec = codec("pointer: /value\nscale: 0.5\noffset: -1\n");
+        assert_eq!(codec.decode(br#"{"value": 85}"#).unwrap(), Value::Float(41.5));
+
+        let codec = self::codec("pointer: /data/1/on\n");
+        assert_eq!(codec.decode(br#"{"data": [{}, {"on": "true"}]}"#).unwrap(), Value::Bool(true));
+        assert!(codec.decode(br#"{"data": []}"#).is_err());
+
+        let plain = PayloadCodec::default();
+        assert_eq!(plain.decode(b" 17\n").unwrap(), Value::Integer(17));
+        assert!(serde_yaml::from_str::<PayloadCodec>("pointer: value\n").unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_binary_and_base64() {
+        let codec = codec("encoding: base64\nbinary: float32\nbyte_order: little\n");
+        let payload = BASE64.encode(1.5f32.to_le_bytes());
+        assert_eq!(codec.decode(payload.as_bytes()).unwrap(), Value::Float(1.5));
+        assert_eq!(codec.encode("x", Value::Float(1.5), 0).unwrap(), payload.into_bytes());
+
+        let codec = self::codec("binary: int16\nscale: 10\n");
+        assert_eq!(codec.encode("x", Value::Float(-1.5), 0).unwrap(), (-15i16).to_be_bytes().to_vec());
+        assert!(codec.encode("x", Value::Integer(5000), 0).is_err());
+        assert!(codec.decode(&[0x01]).is_err());
+    }
+
+    #[test]
+    fn test_template() {
+        let codec = codec("template: '{\"signal\": \"{signal}\", \"value\": {value}, \"ts\": {timestamp}}'\n");
+        let payload = codec.encode("tank1.level", Value::Integer(42), 1_700_000_000_000).unwrap();
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            r#"{"signal": "tank1.level", "value": 42, "ts": 1700000000000}"#
+        );
+    }
+}
@@ -0,0 +1,634 @@
+// src/protocols/mqtt_commands.rs
+//! Authenticated MQTT command channel
+//!
+//! Lets remote systems write signals and operate blocks by publishing signed
+//! JSON commands to a configured topic. Every command passes through the same
+//! pipeline before it touches the process:
+//!
+//! 1. The envelope names a configured client and carries an HMAC-SHA256
+//!    signature of the payload made with that client's shared secret
+//! 2. The payload timestamp must be within `max_age_secs` of local time and
+//!    its id must not have been seen before, which stops replays
+//! 3. The client's role must grant the permission for the action, checked
+//!    through [`RbacConfig`]
+//! 4. The outcome, accepted or rejected, is appended to the audit log;
+//!    signal writes are recorded with the old and new value and the
+//!    command's optional `reason`, which critical signals require, and
+//!    writes covered by e-signature rules must carry a `signature`
+//!
+//! ```yaml
+//! mqtt:
+//!   host: broker.local
+//!   client_id: petra
+//!   commands:
+//!     topic: petra/commands
+//!     response_topic: petra/commands/result
+//!     clients:
+//!       - { id: scada1, secret: "change-me", role: operator }
+//!     rbac:
+//!       roles:
+//!         operator:
+//!           permissions: ["signal.write:line1.*", "block.reset:*"]
+//! ```
+//!
+//! A command message looks like this; `signature` is the base64 HMAC of the
+//! exact `payload` string:
+//!
+//! ```json
+//! {
+//!   "client": "scada1",
+//!   "payload": "{\"id\":\"c-17\",\"timestamp\":\"2024-05-01T10:00:00Z\",\"action\":\"write_signal\",\"signal\":\"line1.speed\",\"value\":42.5}",
+//!   "signature": "q0v5…"
+//! }
+//! ```
+
+use crate::engine::BlockControl;
+use crate::security::audit::{AuditEntry, AuditLog, DEFAULT_AUDIT_LOG_PATH, SIGNAL_WRITE_ACTION};
+use crate::security::rbac::RbacConfig;
+use crate::{PlcError, Result, SignalBus};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Utc};
+use rumqttc::{AsyncClient, Event, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tracing::{debug, error, info, warn};
+
+pub use crate::security::rbac::{RESET_BLOCK_PERMISSION, WRITE_SIGNAL_PERMISSION};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Inbound command channel configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttCommandConfig {
+    /// Topic filter commands are received on
+    pub topic: String,
+
+    /// Topic command results are published to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_topic: Option<String>,
+
+    /// Maximum difference between command timestamp and local time
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: u64,
+
+    /// Clients allowed to send commands
+    pub clients: Vec<CommandClient>,
+
+    /// Roles and their permissions
+    #[serde(default)]
+    pub rbac: RbacConfig,
+
+    /// Audit log file
+    #[serde(default = "default_audit_log")]
+    pub audit_log: PathBuf,
+}
+
+/// A remote system allowed to send commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandClient {
+    /// Client name used in the command envelope
+    pub id: String,
+    /// Shared HMAC secret
+    pub secret: String,
+    /// Role checked against the RBAC configuration
+    pub role: String,
+}
+
+impl MqttCommandConfig {
+    /// Validate topics, clients and roles
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.topic.is_empty() {
+            return Err(PlcError::Config("MQTT command topic cannot be empty".to_string()));
+        }
+        if let Some(response) = &self.response_topic {
+            if super::mqtt::topic_matches(&self.topic, response) {
+                return Err(PlcError::Config(format!(
+                    "MQTT command response topic '{response}' overlaps the command topic"
+                )));
+            }
+        }
+        if self.max_age_secs == 0 {
+            return Err(PlcError::Config("MQTT command max_age_secs cannot be 0".to_string()));
+        }
+        self.rbac.validate()?;
+
+        let mut ids = HashSet::new();
+        for client in &self.clients {
+            if !ids.insert(client.id.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate command client '{}'", client.id)));
+            }
+            if client.secret.len() < 16 {
+                return Err(PlcError::Config(format!(
+                    "Command client '{}' secret must be at least 16 characters",
+                    client.id
+                )));
+            }
+            if !self.rbac.roles.contains_key(&client.role) {
+                return Err(PlcError::Config(format!(
+                    "Command client '{}' has unknown role '{}'",
+                    client.id, client.role
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_max_age_secs() -> u64 {
+    30
+}
+
+fn default_audit_log() -> PathBuf {
+    PathBuf::from(DEFAULT_AUDIT_LOG_PATH)
+}
+
+// ============================================================================
+// MESSAGES
+// ============================================================================
+
+/// Signed command message as received from the broker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandEnvelope {
+    /// Sending client id
+    pub client: String,
+    /// JSON-encoded [`CommandPayload`], signed verbatim
+    pub payload: String,
+    /// Base64 HMAC-SHA256 of `payload`
+    pub signature: String,
+}
+
+/// Command body inside the envelope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPayload {
+    /// Unique command id, used for replay protection and correlation
+    pub id: String,
+    /// When the client issued the command
+    pub timestamp: DateTime<Utc>,
+    /// Requested operation
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// Operations available over the command channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Command {
+    /// Write a value to an existing signal
+    WriteSignal {
+        /// Target signal
+        signal: String,
+        /// New value, must match the signal's current type
+        value: serde_json::Value,
+        /// Operator's reason, required for critical signals
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        /// Operator's electronic signature, required by signature rules
+        #[cfg(feature = "esignature")]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<crate::security::esignature::ESignature>,
+    },
+    /// Reset a block to its initial state
+    ResetBlock {
+        /// Target block
+        block: String,
+    },
+}
+
+impl Command {
+    fn permission(&self) -> (&'static str, &str) {
+        match self {
+            Self::WriteSignal { signal, .. } => (WRITE_SIGNAL_PERMISSION, signal),
+            Self::ResetBlock { block } => (RESET_BLOCK_PERMISSION, block),
+        }
+    }
+
+    #[cfg(feature = "esignature")]
+    fn signature(&self) -> Option<&crate::security::esignature::ESignature> {
+        match self {
+            Self::WriteSignal { signature, .. } => signature.as_ref(),
+            Self::ResetBlock { .. } => None,
+        }
+    }
+}
+
+/// Result published to the response topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResponse {
+    /// Command id, if the payload could be read
+    pub id: Option<String>,
+    /// Client the command claimed to come from
+    pub client: Option<String>,
+    /// Whether the command was executed
+    pub success: bool,
+    /// Reason for rejection or failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Sign a payload the way clients are expected to
+#[must_use]
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    BASE64.encode(hmac_sha256(secret.as_bytes(), payload.as_bytes()))
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block_key = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_pad = block_key.map(|b| b ^ 0x36);
+    let outer_pad = block_key.map(|b| b ^ 0x5c);
+    let inner = Sha256::new().chain_update(inner_pad).chain_update(message).finalize();
+    Sha256::new().chain_update(outer_pad).chain_update(inner).finalize().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ============================================================================
+// PROCESSING
+// ============================================================================
+
+/// Authenticates, authorizes, executes and audits commands
+pub struct CommandProcessor {
+    config: MqttCommandConfig,
+    bus: SignalBus,
+    blocks: Option<BlockControl>,
+    audit: AuditLog,
+    #[cfg(feature = "esignature")]
+    esignature: Option<std::sync::Arc<crate::security::esignature::ESignatureVerifier>>,
+    seen: HashMap<String, DateTime<Utc>>,
+}
+
+impl CommandProcessor {
+    /// Create a processor writing to `bus`
+    ///
+    /// Block commands are rejected when `blocks` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the audit log
+    /// directory cannot be created.
+    pub fn new(config: MqttCommandConfig, bus: SignalBus, blocks: Option<BlockControl>) -> Result<Self> {
+        config.validate()?;
+        let audit = AuditLog::new(&config.audit_log)?;
+        Ok(Self {
+            config,
+            bus,
+            blocks,
+            audit,
+            #[cfg(feature = "esignature")]
+            esignature: None,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// Require a reason for writes to signals matching `patterns`
+    #[must_use]
+    pub fn with_critical_signals(mut self, patterns: Vec<String>) -> Self {
+        self.audit = self.audit.with_critical_signals(patterns);
+        self
+    }
+
+    /// Require signatures on writes covered by `verifier`'s rules
+    #[cfg(feature = "esignature")]
+    #[must_use]
+    pub fn with_esignature(mut self, verifier: std::sync::Arc<crate::security::esignature::ESignatureVerifier>) -> Self {
+        self.esignature = Some(verifier);
+        self
+    }
+
+    /// Handle one raw message and audit the outcome
+    pub async fn handle(&mut self, raw: &[u8], now: DateTime<Utc>) -> CommandResponse {
+        let envelope = serde_json::from_slice::<CommandEnvelope>(raw).ok();
+        let client = envelope.as_ref().map(|e| e.client.clone());
+
+        let (id, mut entry, result) = match envelope {
+            Some(envelope) => self.process(&envelope, now).await,
+            None => (
+                None,
+                command_entry("malformed envelope".to_string()),
+                Err(PlcError::Validation("Malformed command envelope".to_string())),
+            ),
+        };
+
+        entry.timestamp = now;
+        entry.user = client.clone().unwrap_or_else(|| "unknown".to_string());
+        entry.source_ip = "mqtt".to_string();
+        entry.success = result.is_ok();
+        if let Err(e) = &result {
+            entry.details = format!("{}: rejected: {e}", entry.details);
+        }
+        if let Err(e) = self.audit.record(&entry) {
+            error!("Failed to write audit entry for MQTT command: {}", e);
+        }
+
+        match &result {
+            Ok(()) => info!("MQTT command {} from {}: {}", id.as_deref().unwrap_or("?"), entry.user, entry.details),
+            Err(e) => warn!("MQTT command from {} rejected: {}", entry.user, e),
+        }
+
+        CommandResponse {
+            id,
+            client,
+            success: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+
+    async fn process(
+        &mut self,
+        envelope: &CommandEnvelope,
+        now: DateTime<Utc>,
+    ) -> (Option<String>, AuditEntry, Result<()>) {
+        let client = match self.authenticate(envelope) {
+            Ok(client) => client.clone(),
+            Err(e) => return (None, command_entry("authentication".to_string()), Err(e)),
+        };
+
+        let payload: CommandPayload = match serde_json::from_str(&envelope.payload) {
+            Ok(payload) => payload,
+            Err(e) => return (None, command_entry("malformed payload".to_string()), Err(e.into())),
+        };
+        let id = Some(payload.id.clone());
+        let mut entry = describe(&payload);
+
+        let result = match self.check_freshness(&payload, now) {
+            Ok(()) => {
+                let (action, resource) = payload.command.permission();
+                match self.config.rbac.check(&client.role, action, resource) {
+                    Ok(()) => self.execute(&payload.command, &mut entry).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        (id, entry, result)
+    }
+
+    fn authenticate(&self, envelope: &CommandEnvelope) -> Result<&CommandClient> {
+        let client = self
+            .config
+            .clients
+            .iter()
+            .find(|c| c.id == envelope.client)
+            .ok_or_else(|| PlcError::AuthenticationFailed(format!("Unknown client '{}'", envelope.client)))?;
+
+        let signature = BASE64
+            .decode(&envelope.signature)
+            .map_err(|_| PlcError::AuthenticationFailed("Signature is not valid base64".to_string()))?;
+        let expected = hmac_sha256(client.secret.as_bytes(), envelope.payload.as_bytes());
+        if !constant_time_eq(&signature, &expected) {
+            return Err(PlcError::AuthenticationFailed("Invalid signature".to_string()));
+        }
+        Ok(client)
+    }
+
+    fn check_freshness(&mut self, payload: &CommandPayload, now: DateTime<Utc>) -> Result<()> {
+        let max_age = chrono::Duration::seconds(i64::try_from(self.config.max_age_secs).unwrap_or(i64::MAX));
+        if (now - payload.timestamp).abs() > max_age {
+            return Err(PlcError::AuthenticationFailed(format!(
+                "Command timestamp {} is outside the accepted window",
+                payload.timestamp
+            )));
+        }
+
+        // Ids only need remembering for as long as their timestamp is accepted
+        self.seen.retain(|_, at| now - *at <= max_age);
+        if self.seen.insert(payload.id.clone(), payload.timestamp).is_some() {
+            return Err(PlcError::AuthenticationFailed(format!(
+                "Command id '{}' was already used",
+                payload.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Carry out an authorized command, noting written values in `entry`
+    async fn execute(&self, command: &Command, entry: &mut AuditEntry) -> Result<()> {
+        match command {
+            Command::WriteSignal { signal, value, reason, .. } => {
+                let current = self
+                    .bus
+                    .get(signal)
+                    .ok_or_else(|| PlcError::SignalNotFound(signal.clone()))?;
+                entry.old_value = Some(current.clone());
+                let value = crate::value::from_yaml_value(serde_yaml::to_value(value)?)?;
+                entry.new_value = Some(value.clone());
+                self.audit.require_reason(signal, reason.as_deref())?;
+                #[cfg(feature = "esignature")]
+                if let Some(verifier) = &self.esignature {
+                    entry.signature = verifier.check_write(signal, &value, command.signature(), reason.as_deref())?;
+                }
+                if value.value_type() != current.value_type() {
+                    return Err(PlcError::Validation(format!(
+                        "Signal '{}' expects {}, got {}",
+                        signal,
+                        current.type_name(),
+                        value.type_name()
+                    )));
+                }
+                self.bus.set_with_source(signal, value, Some("mqtt-command"))
+            }
+            Command::ResetBlock { block } => match &self.blocks {
+                Some(blocks) => blocks.reset(block).await,
+                None => Err(PlcError::Runtime("Block commands are not available".to_string())),
+            },
+        }
+    }
+}
+
+/// Audit entry of a command other than a signal write
+fn command_entry(details: String) -> AuditEntry {
+    AuditEntry {
+        action: "mqtt.command".to_string(),
+        details,
+        ..AuditEntry::default()
+    }
+}
+
+fn describe(payload: &CommandPayload) -> AuditEntry {
+    match &payload.command {
+        Command::WriteSignal { signal, value, reason, .. } => AuditEntry {
+            action: SIGNAL_WRITE_ACTION.to_string(),
+            details: format!("id {}: write {signal} = {value}", payload.id),
+            target: Some(signal.clone()),
+            reason: reason.clone(),
+            ..AuditEntry::default()
+        },
+        Command::ResetBlock { block } => command_entry(format!("id {}: reset block {block}", payload.id)),
+    }
+}
+
+// ============================================================================
+// CHANNEL
+// ============================================================================
+
+/// Run the command channel on its own broker connection
+///
+/// Uses the connection settings of `mqtt` with a `-commands` client id
+/// suffix so it does not displace the data client. Subscribes again after
+/// every reconnect.
+///
+/// # Errors
+///
+/// Returns an error if the command configuration is missing or the MQTT TLS
+/// certificates cannot be loaded.
+pub async fn run(mqtt: crate::config::MqttConfig, mut processor: CommandProcessor) -> Result<()> {
+    let topic = processor.config.topic.clone();
+    let response_topic = processor.config.response_topic.clone();
+
+    let mut failover = crate::protocols::mqtt_failover::BrokerFailover::new(&mqtt);
+    let connect = |broker: &crate::config::MqttConfig| {
+        let options = crate::config::MqttConfig {
+            client_id: format!("{}-commands", broker.client_id),
+            ..broker.clone()
+        }
+        .connection_options()?;
+        Ok::<_, crate::PlcError>(AsyncClient::new(options, 32))
+    };
+    let (mut client, mut eventloop) = connect(failover.current())?;
+    info!("MQTT command channel listening on '{}'", topic);
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                failover.connected();
+                if let Err(e) = client.subscribe(&topic, QoS::AtLeastOnce).await {
+                    error!("Failed to subscribe to command topic '{}': {}", topic, e);
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                debug!("Received command on '{}'", publish.topic);
+                let response = crate::scan_budget::instrument(
+                    crate::scan_budget::Subsystem::Protocols,
+                    processor.handle(&publish.payload, Utc::now()),
+                )
+                .await;
+                if let Some(response_topic) = &response_topic {
+                    let body = serde_json::to_vec(&response)?;
+                    if let Err(e) = client.publish(response_topic, QoS::AtLeastOnce, false, body).await {
+                        warn!("Failed to publish command response: {}", e);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("MQTT command channel connection error: {}", e);
+                if failover.failed() {
+                    (client, eventloop) = connect(failover.current())?;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(mqtt.reconnect_delay_secs)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn processor(bus: &SignalBus) -> (CommandProcessor, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config: MqttCommandConfig = serde_yaml::from_str(
+            r#"
+topic: petra/commands
+clients:
+  - { id: scada1, secret: "0123456789abcdef", role: operator }
+rbac:
+  roles:
+    operator:
+      permissions: ["signal.write:line1.*"]
+"#,
+        )
+        .unwrap();
+        config.audit_log = dir.path().join("audit.jsonl");
+        (CommandProcessor::new(config, bus.clone(), None).unwrap(), dir)
+    }
+
+    fn envelope(secret: &str, id: &str, now: DateTime<Utc>, signal: &str) -> Vec<u8> {
+        let payload = serde_json::to_string(&CommandPayload {
+            id: id.to_string(),
+            timestamp: now,
+            command: Command::WriteSignal {
+                signal: signal.to_string(),
+                value: serde_json::json!(42.5),
+                reason: None,
+                #[cfg(feature = "esignature")]
+                signature: None,
+            },
+        })
+        .unwrap();
+        serde_json::to_vec(&CommandEnvelope {
+            client: "scada1".to_string(),
+            signature: sign_payload(secret, &payload),
+            payload,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hmac_rfc4231_vector() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[tokio::test]
+    async fn test_authorized_write_and_replay() {
+        let bus = SignalBus::new();
+        bus.set("line1.speed", Value::Float(0.0)).unwrap();
+        let (mut processor, _dir) = processor(&bus);
+        let now = Utc::now();
+
+        let message = envelope("0123456789abcdef", "c-1", now, "line1.speed");
+        assert!(processor.handle(&message, now).await.success);
+        assert_eq!(bus.get_float("line1.speed").unwrap(), 42.5);
+
+        let replay = processor.handle(&message, now).await;
+        assert!(!replay.success);
+
+        let audit = processor.audit.read(10, Some("scada1"), None).unwrap();
+        assert_eq!(audit.len(), 2);
+        assert!(!audit[0].success);
+        assert_eq!(audit[1].old_value, Some(Value::Float(0.0)));
+        assert_eq!(audit[1].new_value, Some(Value::Float(42.5)));
+    }
+
+    #[tokio::test]
+    async fn test_rejections() {
+        let bus = SignalBus::new();
+        bus.set("line1.speed", Value::Float(0.0)).unwrap();
+        bus.set("line2.speed", Value::Float(0.0)).unwrap();
+        let (mut processor, _dir) = processor(&bus);
+        let now = Utc::now();
+
+        let forged = envelope("wrong-secret-0000", "c-1", now, "line1.speed");
+        assert!(!processor.handle(&forged, now).await.success);
+
+        let stale = envelope("0123456789abcdef", "c-2", now - chrono::Duration::minutes(5), "line1.speed");
+        assert!(!processor.handle(&stale, now).await.success);
+
+        let denied = envelope("0123456789abcdef", "c-3", now, "line2.speed");
+        let response = processor.handle(&denied, now).await;
+        assert!(response.error.unwrap().contains("Authorization denied"));
+
+        assert_eq!(bus.get_float("line1.speed").unwrap(), 0.0);
+        assert_eq!(bus.get_float("line2.speed").unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,136 @@
+// src/protocols/mqtt_failover.rs
+//! MQTT broker failover and fan-out
+//!
+//! The `mqtt` section names a main broker and optionally a list of standby
+//! brokers. MQTT sessions start on the main broker; after
+//! `failure_threshold` consecutive connection errors they move to the next
+//! broker in the list, wrapping around to the main broker after the last
+//! one. A session stays on whichever broker accepts it.
+//!
+//! Brokers listed under `fan_out` are not standbys: each gets a session of
+//! its own that publishes the same data, so a local broker and a cloud
+//! broker can both be fed. Every broker has its own TLS settings and
+//! session state; client id and credentials default to the main ones.
+//!
+//! ```yaml
+//! mqtt:
+//!   host: broker-a.local
+//!   client_id: petra-line1
+//!   failure_threshold: 3
+//!   brokers:
+//!     - { host: broker-b.local }
+//!   fan_out:
+//!     - host: cloud.example.com
+//!       port: 8883
+//!       client_id: petra-line1-cloud
+//!       clean_session: false
+//!       tls:
+//!         ca_cert: /etc/petra/cloud-ca.pem
+//! ```
+
+use crate::config::MqttConfig;
+use tracing::warn;
+
+/// Broker selection for one MQTT session
+#[derive(Debug, Clone)]
+pub struct BrokerFailover {
+    configs: Vec<MqttConfig>,
+    current: usize,
+    consecutive_failures: u32,
+    failure_threshold: u32,
+}
+
+impl BrokerFailover {
+    /// Start on the main broker of `mqtt`
+    #[must_use]
+    pub fn new(mqtt: &MqttConfig) -> Self {
+        Self {
+            configs: mqtt.failover_configs(),
+            current: 0,
+            consecutive_failures: 0,
+            failure_threshold: mqtt.failure_threshold.max(1),
+        }
+    }
+
+    /// Settings for the broker the next connection should use
+    #[must_use]
+    pub fn current(&self) -> &MqttConfig {
+        &self.configs[self.current]
+    }
+
+    /// Record an accepted connection
+    pub fn connected(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    /// Record a connection error; true when this switched brokers
+    pub fn failed(&mut self) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.configs.len() < 2 || self.consecutive_failures < self.failure_threshold {
+            return false;
+        }
+        let from = self.current().broker_url();
+        self.current = (self.current + 1) % self.configs.len();
+        self.consecutive_failures = 0;
+        warn!("MQTT broker {} failed, switching to {}", from, self.current().broker_url());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MqttBroker;
+
+    fn broker(host: &str) -> MqttBroker {
+        serde_yaml::from_str(&format!("host: {host}")).unwrap()
+    }
+
+    #[test]
+    fn test_switches_after_threshold_and_wraps_around() {
+        let mqtt = MqttConfig {
+            host: "a".to_string(),
+            failure_threshold: 2,
+            brokers: vec![broker("b")],
+            ..Default::default()
+        };
+        let mut failover = BrokerFailover::new(&mqtt);
+        assert_eq!(failover.current().host, "a");
+
+        assert!(!failover.failed());
+        failover.connected();
+        assert!(!failover.failed());
+        assert!(failover.failed());
+        assert_eq!(failover.current().host, "b");
+        assert!(failover.current().brokers.is_empty());
+
+        assert!(!failover.failed());
+        assert!(failover.failed());
+        assert_eq!(failover.current().host, "a");
+    }
+
+    #[test]
+    fn test_broker_settings_fall_back_to_main() {
+        let mut cloud = broker("cloud");
+        cloud.port = 8883;
+        cloud.clean_session = Some(false);
+        let mqtt = MqttConfig {
+            host: "local".to_string(),
+            client_id: "line1".to_string(),
+            username: Some("plc".to_string()),
+            password: Some("secret".to_string()),
+            fan_out: vec![cloud],
+            ..Default::default()
+        };
+
+        let configs = mqtt.fan_out_configs();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].host, "cloud");
+        assert_eq!(configs[0].port, 8883);
+        assert_eq!(configs[0].client_id, "line1");
+        assert_eq!(configs[0].username.as_deref(), Some("plc"));
+        assert!(!configs[0].clean_session);
+        assert!(configs[0].fan_out.is_empty());
+        assert_eq!(mqtt.failover_configs().len(), 1);
+    }
+}
@@ -0,0 +1,549 @@
+// src/protocols/nats.rs
+//! NATS signal exchange with optional `JetStream` persistence
+//!
+//! PETRA instances exchange signals over NATS using subjects under each
+//! instance's `subject_prefix`:
+//!
+//! - `<prefix>.signals.<signal>` carries value changes of `<signal>`,
+//!   published every `scan_interval_ms` for the signals listed in `publish`
+//! - `<prefix>.read` answers requests for current values; the request is a
+//!   JSON array of signal names and the reply a JSON object mapping each name
+//!   to its value, or `null` for unknown signals (see [`read_remote`])
+//!
+//! Each subscription names the prefix of a remote instance and writes its
+//! signal changes locally, optionally restricted to `signals` and renamed
+//! with `prefix`.
+//!
+//! With `jetstream` configured, value changes are published into a stream
+//! covering `<prefix>.signals.>` and each publication waits for the stream's
+//! acknowledgement. A subscription with `stream` and `durable` set consumes
+//! the remote instance's stream through a durable consumer, so changes
+//! published while this instance was offline are applied when it returns.
+//!
+//! ```yaml
+//! protocols:
+//!   nats:
+//!     url: nats://nats1:4222
+//!     subject_prefix: petra.line1
+//!     credentials_file: /etc/petra/nats.creds
+//!     publish:
+//!       signals: [line1.speed, line1.count]
+//!     jetstream:
+//!       stream: PETRA_LINE1
+//!       max_age_hours: 24
+//!     subscriptions:
+//!       - from: petra.line2
+//!         prefix: line2.
+//!         stream: PETRA_LINE2
+//!         durable: line1_from_line2
+//! ```
+//!
+//! Messages carry the value in its tagged JSON form together with a
+//! millisecond timestamp, see [`SignalMessage`].
+
+use super::{ChangeTracker, SignalFilter};
+use crate::{PlcError, Result, SignalBus, Value};
+use async_nats::jetstream;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// NATS connector configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsConfig {
+    /// Server URL, or several separated by commas
+    pub url: String,
+
+    /// Connection name reported to the server
+    #[serde(default = "default_name")]
+    pub name: String,
+
+    /// Subject namespace of this instance
+    pub subject_prefix: String,
+
+    /// Authentication token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// NATS credentials file (JWT and `NKey` seed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_file: Option<PathBuf>,
+
+    /// Signals published on change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish: Option<NatsPublishConfig>,
+
+    /// Persist published changes in a `JetStream` stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jetstream: Option<JetStreamConfig>,
+
+    /// Remote instances whose signals are applied locally
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<NatsSubscriptionConfig>,
+
+    /// Answer remote read requests on `<subject_prefix>.read`
+    #[serde(default = "default_serve_reads")]
+    pub serve_reads: bool,
+}
+
+/// Published signals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsPublishConfig {
+    /// Published signals; every signal on the bus when empty
+    #[serde(default)]
+    pub signals: Vec<String>,
+
+    /// Interval for checking signals for changes
+    #[serde(default = "default_scan_interval_ms")]
+    pub scan_interval_ms: u64,
+}
+
+/// `JetStream` stream holding this instance's published changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JetStreamConfig {
+    /// Stream name, created if it does not exist
+    pub stream: String,
+
+    /// How long changes are retained
+    #[serde(default = "default_max_age_hours")]
+    pub max_age_hours: u64,
+}
+
+/// Signal changes consumed from a remote instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsSubscriptionConfig {
+    /// Subject prefix of the remote instance
+    pub from: String,
+
+    /// Signals applied locally; any signal when empty
+    #[serde(default)]
+    pub signals: Vec<String>,
+
+    /// Prepended to received signal names
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    /// `JetStream` stream of the remote instance to consume durably
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
+
+    /// Durable consumer name, required with `stream`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub durable: Option<String>,
+}
+
+fn default_name() -> String {
+    "petra".to_string()
+}
+
+const fn default_serve_reads() -> bool {
+    true
+}
+
+const fn default_scan_interval_ms() -> u64 {
+    500
+}
+
+const fn default_max_age_hours() -> u64 {
+    24
+}
+
+/// Whether `subject` is a valid subject without wildcards
+fn valid_subject(subject: &str) -> bool {
+    !subject.is_empty()
+        && subject
+            .split('.')
+            .all(|token| !token.is_empty() && token != "*" && token != ">" && !token.contains(char::is_whitespace))
+}
+
+/// Whether `name` is a valid `JetStream` stream or consumer name
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', '*', '>', '/', '\\']) && !name.contains(char::is_whitespace)
+}
+
+impl NatsConfig {
+    /// Validate subjects, stream and consumer names
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.url.trim().is_empty() {
+            return Err(PlcError::Config("NATS url cannot be empty".to_string()));
+        }
+        if !valid_subject(&self.subject_prefix) {
+            return Err(PlcError::Config(format!(
+                "Invalid NATS subject_prefix '{}'",
+                self.subject_prefix
+            )));
+        }
+        if self.publish.as_ref().is_some_and(|p| p.scan_interval_ms == 0) {
+            return Err(PlcError::Config("NATS publish scan_interval_ms cannot be 0".to_string()));
+        }
+        if let Some(jetstream) = &self.jetstream {
+            if !valid_name(&jetstream.stream) {
+                return Err(PlcError::Config(format!(
+                    "Invalid JetStream stream name '{}'",
+                    jetstream.stream
+                )));
+            }
+            if self.publish.is_none() {
+                return Err(PlcError::Config("NATS jetstream requires a publish section".to_string()));
+            }
+        }
+        for subscription in &self.subscriptions {
+            if !valid_subject(&subscription.from) {
+                return Err(PlcError::Config(format!(
+                    "Invalid NATS subscription prefix '{}'",
+                    subscription.from
+                )));
+            }
+            match (&subscription.stream, &subscription.durable) {
+                (None, None) => {}
+                (Some(stream), Some(durable)) if valid_name(stream) && valid_name(durable) => {}
+                _ => {
+                    return Err(PlcError::Config(format!(
+                        "NATS subscription to '{}' needs a valid stream and durable name together",
+                        subscription.from
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MESSAGES
+// ============================================================================
+
+/// Payload of a signal change message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalMessage {
+    pub value: Value,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+}
+
+/// Subject carrying changes of `signal` published under `prefix`
+#[must_use]
+pub fn signal_subject(prefix: &str, signal: &str) -> String {
+    format!("{prefix}.signals.{signal}")
+}
+
+/// Subject answering read requests for the instance at `prefix`
+#[must_use]
+pub fn read_subject(prefix: &str) -> String {
+    format!("{prefix}.read")
+}
+
+/// Reply to a read request for signals on `bus`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Json`] if the request is not a JSON array of names.
+pub fn answer_read(bus: &SignalBus, request: &[u8]) -> Result<Vec<u8>> {
+    let names: Vec<String> = serde_json::from_slice(request)?;
+    let values: HashMap<String, Option<Value>> = names
+        .into_iter()
+        .map(|name| {
+            let value = bus.get(&name);
+            (name, value)
+        })
+        .collect();
+    Ok(serde_json::to_vec(&values)?)
+}
+
+/// Read current values from the instance at `prefix`
+///
+/// Unknown signals map to `None`.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Protocol`] if the request fails or times out and
+/// [`PlcError::Json`] for a malformed reply.
+pub async fn read_remote(
+    client: &async_nats::Client,
+    prefix: &str,
+    signals: &[String],
+) -> Result<HashMap<String, Option<Value>>> {
+    let request = serde_json::to_vec(signals)?;
+    let reply = client
+        .request(read_subject(prefix), request.into())
+        .await
+        .map_err(nats_error)?;
+    Ok(serde_json::from_slice(&reply.payload)?)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+fn nats_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Protocol(format!("NATS: {e}"))
+}
+
+// ============================================================================
+// CONNECTOR
+// ============================================================================
+
+/// Connect to the configured server
+///
+/// # Errors
+///
+/// Returns [`PlcError::Protocol`] if the credentials file cannot be read or
+/// the connection fails.
+pub async fn connect(config: &NatsConfig) -> Result<async_nats::Client> {
+    let mut options = async_nats::ConnectOptions::new().name(&config.name);
+    if let Some(token) = &config.token {
+        options = options.token(token.clone());
+    }
+    if let Some(path) = &config.credentials_file {
+        options = options.credentials_file(path).await.map_err(nats_error)?;
+    }
+    options.connect(config.url.as_str()).await.map_err(nats_error)
+}
+
+async fn run_publisher(
+    client: async_nats::Client,
+    config: NatsConfig,
+    publish: NatsPublishConfig,
+    bus: SignalBus,
+) -> Result<()> {
+    let stream = match &config.jetstream {
+        Some(settings) => {
+            let context = jetstream::new(client.clone());
+            context
+                .get_or_create_stream(jetstream::stream::Config {
+                    name: settings.stream.clone(),
+                    subjects: vec![signal_subject(&config.subject_prefix, ">")],
+                    max_age: Duration::from_secs(settings.max_age_hours.saturating_mul(3600)),
+                    ..Default::default()
+                })
+                .await
+                .map_err(nats_error)?;
+            info!("Publishing NATS signal changes into stream '{}'", settings.stream);
+            Some(context)
+        }
+        None => None,
+    };
+
+    let mut tracker = ChangeTracker::new(publish.signals.clone());
+    let mut ticker = tokio::time::interval(Duration::from_millis(publish.scan_interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let timestamp = now_ms();
+        let mut acks = Vec::new();
+        for (signal, value) in tracker.changes(&bus) {
+            let subject = signal_subject(&config.subject_prefix, &signal);
+            if !valid_subject(&subject) {
+                debug!("Signal '{}' cannot be used in a NATS subject", signal);
+                continue;
+            }
+            let payload = serde_json::to_vec(&SignalMessage { value, timestamp })?;
+            let result = match &stream {
+                Some(context) => context
+                    .publish(subject, payload.into())
+                    .await
+                    .map(|ack| acks.push((signal.clone(), ack)))
+                    .map_err(nats_error),
+                None => client.publish(subject, payload.into()).await.map_err(nats_error),
+            };
+            if let Err(e) = result {
+                warn!("Failed to publish '{}': {}", signal, e);
+            }
+        }
+        for (signal, ack) in acks {
+            if let Err(e) = ack.await {
+                warn!("JetStream did not acknowledge '{}': {}", signal, e);
+            }
+        }
+    }
+}
+
+/// Apply a received change, given its subject and payload
+fn apply(bus: &SignalBus, base: &str, filter: &SignalFilter, subject: &str, payload: &[u8]) {
+    let Some(signal) = subject.strip_prefix(base) else {
+        return;
+    };
+    let Some(target) = filter.target(signal) else {
+        debug!("NATS subscription ignored signal '{}'", signal);
+        return;
+    };
+    match serde_json::from_slice::<SignalMessage>(payload) {
+        Ok(message) => {
+            if let Err(e) = bus.set(&target, message.value) {
+                warn!("NATS subscription could not write '{}': {}", target, e);
+            }
+        }
+        Err(e) => warn!("Skipping malformed NATS message on '{}': {}", subject, e),
+    }
+}
+
+async fn run_subscription(
+    client: async_nats::Client,
+    subscription: NatsSubscriptionConfig,
+    bus: SignalBus,
+) -> Result<()> {
+    let base = signal_subject(&subscription.from, "");
+    let filter = SignalFilter::new(&subscription.signals, subscription.prefix.as_deref());
+
+    if let (Some(stream), Some(durable)) = (&subscription.stream, &subscription.durable) {
+        let consumer: jetstream::consumer::PullConsumer = jetstream::new(client)
+            .get_stream(stream)
+            .await
+            .map_err(nats_error)?
+            .get_or_create_consumer(
+                durable,
+                jetstream::consumer::pull::Config {
+                    durable_name: Some(durable.clone()),
+                    filter_subject: signal_subject(&subscription.from, ">"),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(nats_error)?;
+        let mut messages = consumer.messages().await.map_err(nats_error)?;
+        info!("Consuming '{}' from stream '{}' as '{}'", subscription.from, stream, durable);
+        while let Some(message) = messages.next().await {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("JetStream consumer '{}' error: {}", durable, e);
+                    continue;
+                }
+            };
+            apply(&bus, &base, &filter, message.subject.as_str(), &message.payload);
+            if let Err(e) = message.ack().await {
+                warn!("Failed to acknowledge JetStream message: {}", e);
+            }
+        }
+    } else {
+        let mut subscriber = client
+            .subscribe(signal_subject(&subscription.from, ">"))
+            .await
+            .map_err(nats_error)?;
+        info!("Subscribed to NATS signals from '{}'", subscription.from);
+        while let Some(message) = subscriber.next().await {
+            apply(&bus, &base, &filter, message.subject.as_str(), &message.payload);
+        }
+    }
+    Err(PlcError::Protocol(format!(
+        "NATS subscription to '{}' ended",
+        subscription.from
+    )))
+}
+
+async fn serve_reads(client: async_nats::Client, prefix: String, bus: SignalBus) -> Result<()> {
+    let mut requests = client.subscribe(read_subject(&prefix)).await.map_err(nats_error)?;
+    info!("Answering NATS read requests on '{}'", read_subject(&prefix));
+    while let Some(request) = requests.next().await {
+        let Some(reply) = request.reply else {
+            continue;
+        };
+        let response = answer_read(&bus, &request.payload).unwrap_or_else(|e| {
+            debug!("Malformed NATS read request: {}", e);
+            b"{}".to_vec()
+        });
+        if let Err(e) = client.publish(reply, response.into()).await {
+            warn!("Failed to answer NATS read request: {}", e);
+        }
+    }
+    Err(PlcError::Protocol("NATS read subscription ended".to_string()))
+}
+
+/// Run the configured publisher, subscriptions and read responder until the
+/// task is cancelled
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid, the connection fails,
+/// or a stream or consumer cannot be set up.
+pub async fn run(config: NatsConfig, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let client = connect(&config).await?;
+    info!("Connected to NATS at {} as '{}'", config.url, config.subject_prefix);
+
+    let mut tasks = tokio::task::JoinSet::new();
+    if let Some(publish) = config.publish.clone() {
+        tasks.spawn(run_publisher(client.clone(), config.clone(), publish, bus.clone()));
+    }
+    for subscription in config.subscriptions.clone() {
+        tasks.spawn(run_subscription(client.clone(), subscription, bus.clone()));
+    }
+    if config.serve_reads {
+        tasks.spawn(serve_reads(client.clone(), config.subject_prefix.clone(), bus.clone()));
+    }
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(Err(e)) => {
+                tasks.abort_all();
+                return Err(e);
+            }
+            Err(e) => error!("NATS task failed: {}", e),
+            Ok(Ok(())) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_validation() {
+        let config: NatsConfig = serde_yaml::from_str(
+            "url: nats://localhost:4222\nsubject_prefix: petra.line1\npublish: {}\njetstream: { stream: PETRA_LINE1 }\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut bad = config.clone();
+        bad.subject_prefix = "petra.*".into();
+        assert!(bad.validate().is_err());
+
+        let mut bad = config.clone();
+        bad.jetstream = Some(JetStreamConfig { stream: "PETRA.LINE1".into(), max_age_hours: 1 });
+        assert!(bad.validate().is_err());
+
+        let mut bad = config;
+        bad.subscriptions.push(NatsSubscriptionConfig {
+            from: "petra.line2".into(),
+            signals: Vec::new(),
+            prefix: None,
+            stream: Some("PETRA_LINE2".into()),
+            durable: None,
+        });
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_and_read() {
+        let bus = SignalBus::new();
+        let filter = SignalFilter::new(&["line2.speed".to_string()], Some("remote."));
+        let base = signal_subject("petra.line2", "");
+        let payload = serde_json::to_vec(&SignalMessage { value: Value::Float(3.5), timestamp: 0 }).unwrap();
+
+        apply(&bus, &base, &filter, "petra.line2.signals.line2.speed", &payload);
+        apply(&bus, &base, &filter, "petra.line2.signals.line2.count", &payload);
+        assert_eq!(bus.get("remote.line2.speed"), Some(Value::Float(3.5)));
+        assert_eq!(bus.get("remote.line2.count"), None);
+
+        let reply = answer_read(&bus, br#"["remote.line2.speed", "missing"]"#).unwrap();
+        let values: HashMap<String, Option<Value>> = serde_json::from_slice(&reply).unwrap();
+        assert_eq!(values["remote.line2.speed"], Some(Value::Float(3.5)));
+        assert_eq!(values["missing"], None);
+        assert!(answer_read(&bus, b"not json").is_err());
+    }
+}
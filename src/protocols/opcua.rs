@@ -1,11 +1,51 @@
 //! OPC UA protocol implementation
 //!
 //! This module provides OPC UA client capabilities.
+//!
+//! [`OpcuaClient`] receives values through subscriptions instead of polling.
+//! Configured items are grouped into one subscription per publishing
+//! interval and created as monitored items in batches of at most
+//! `max_items_per_request`, so large address lists stay within the limits
+//! servers put on a single request. Every item carries its own sampling
+//! interval, queue size and optional deadband filter:
+//!
+//! ```yaml
+//! protocols:
+//!   opcua:
+//!     endpoint: opc.tcp://plc:4840
+//!     publishing_interval_ms: 500
+//!     subscriptions:
+//!       - node_id: "ns=2;s=Line1.Speed"
+//!         signal: line1.speed
+//!         sampling_interval_ms: 100
+//!         queue_size: 5
+//!         deadband: { type: absolute, value: 0.5 }
+//! ```
+//!
+//! Received values keep the source timestamp the server sent with them,
+//! readable with [`SignalBus::get_with_meta`].
+//!
+//! When the session is lost, for example because the server restarted, the
+//! client waits `reconnect_interval_ms`, opens a new session and recreates
+//! all subscriptions and monitored items from the configuration.
 
-use crate::{Result, Value};
+use crate::config::{OpcuaConfig, OpcuaSubscription};
+use crate::signal::{SignalBus, WriteMeta};
+use crate::{PlcError, Result, Value};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use tracing::info;
+use opcua::client::prelude::*;
+use opcua::sync::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Keep-alive publishes a subscription may miss before the server drops it
+const MAX_KEEP_ALIVE_COUNT: u32 = 10;
+
+/// Subscription lifetime in publishing intervals, three times the keep-alive
+const LIFETIME_COUNT: u32 = 3 * MAX_KEEP_ALIVE_COUNT;
 
 pub struct OpcUaDriver {
     // Implementation details
@@ -48,3 +88,315 @@ pub async fn test_connection(_endpoint: &str, _node_id: Option<&str>) -> Result<
     info!("OPC UA test_connection stub called");
     Ok(())
 }
+
+// ============================================================================
+// SUBSCRIPTION PLAN
+// ============================================================================
+
+/// Deadband of a monitored item
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deadband {
+    Absolute(f64),
+    /// Percent of the item's engineering unit range
+    Percent(f64),
+}
+
+/// One configured item with its client handle
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedItem {
+    /// Identifies the item in data change notifications
+    pub handle: u32,
+    pub node_id: String,
+    pub signal: String,
+    pub sampling_interval_ms: u64,
+    pub queue_size: u32,
+    pub discard_oldest: bool,
+    pub deadband: Option<Deadband>,
+}
+
+/// Items sharing one subscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedSubscription {
+    pub publishing_interval_ms: u64,
+    pub items: Vec<PlannedItem>,
+}
+
+fn deadband(item: &OpcuaSubscription) -> Option<Deadband> {
+    item.deadband.as_ref().and_then(|d| match d.deadband_type.as_str() {
+        "absolute" => Some(Deadband::Absolute(d.value)),
+        "percent" => Some(Deadband::Percent(d.value)),
+        _ => None,
+    })
+}
+
+/// Subscriptions to create for `config`, one per publishing interval
+#[must_use]
+pub fn plan(config: &OpcuaConfig) -> Vec<PlannedSubscription> {
+    let mut groups: BTreeMap<u64, Vec<PlannedItem>> = BTreeMap::new();
+    for (handle, item) in (1..).zip(&config.subscriptions) {
+        let interval = item.publishing_interval_ms.unwrap_or(config.publishing_interval_ms);
+        groups.entry(interval).or_default().push(PlannedItem {
+            handle,
+            node_id: item.node_id.clone(),
+            signal: item.signal.clone(),
+            sampling_interval_ms: item.sampling_interval_ms,
+            queue_size: item.queue_size,
+            discard_oldest: item.discard_oldest,
+            deadband: deadband(item),
+        });
+    }
+    groups
+        .into_iter()
+        .map(|(publishing_interval_ms, items)| PlannedSubscription { publishing_interval_ms, items })
+        .collect()
+}
+
+/// Signal value of an OPC-UA variant, if the type maps to one
+#[must_use]
+pub fn value(variant: &Variant) -> Option<Value> {
+    Some(match variant {
+        Variant::Boolean(b) => Value::Bool(*b),
+        Variant::SByte(v) => Value::Integer(i64::from(*v)),
+        Variant::Byte(v) => Value::Integer(i64::from(*v)),
+        Variant::Int16(v) => Value::Integer(i64::from(*v)),
+        Variant::UInt16(v) => Value::Integer(i64::from(*v)),
+        Variant::Int32(v) => Value::Integer(i64::from(*v)),
+        Variant::UInt32(v) => Value::Integer(i64::from(*v)),
+        Variant::Int64(v) => Value::Integer(*v),
+        Variant::UInt64(v) => Value::Integer(i64::try_from(*v).ok()?),
+        Variant::Float(v) => Value::Float(f64::from(*v)),
+        Variant::Double(v) => Value::Float(*v),
+        #[cfg(feature = "extended-types")]
+        Variant::String(s) => Value::String(s.as_ref().to_string()),
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// CLIENT
+// ============================================================================
+
+/// OPC-UA client writing subscribed values to the signal bus
+pub struct OpcuaClient {
+    config: OpcuaConfig,
+    bus: SignalBus,
+    subscriptions: Vec<PlannedSubscription>,
+}
+
+impl OpcuaClient {
+    /// Client for the subscriptions in `config`
+    #[must_use]
+    pub fn new(config: OpcuaConfig, bus: SignalBus) -> Self {
+        let subscriptions = plan(&config);
+        Self { config, bus, subscriptions }
+    }
+
+    /// Keep a session with the server, recreating it whenever it is lost
+    ///
+    /// Blocks the calling thread: the OPC-UA stack runs its own runtime, so
+    /// this must be called from a dedicated thread rather than a Tokio task.
+    pub fn run(self) {
+        let retry = Duration::from_millis(self.config.reconnect_interval_ms);
+        loop {
+            match self.connect() {
+                Ok(session) => {
+                    info!("OPC-UA session with {} established", self.config.endpoint);
+                    // Returns once the connection is lost
+                    Session::run(session);
+                    warn!("OPC-UA session with {} lost, reconnecting", self.config.endpoint);
+                }
+                Err(e) => warn!("OPC-UA connection to {} failed: {}", self.config.endpoint, e),
+            }
+            std::thread::sleep(retry);
+        }
+    }
+
+    fn identity(&self) -> IdentityToken {
+        match &self.config.user_auth {
+            Some(auth) if auth.auth_type == "username" => IdentityToken::UserName(
+                auth.username.clone().unwrap_or_default(),
+                auth.password.clone().unwrap_or_default(),
+            ),
+            _ => IdentityToken::Anonymous,
+        }
+    }
+
+    /// Open a session and create every subscription
+    fn connect(&self) -> Result<Arc<RwLock<Session>>> {
+        let mut client = ClientBuilder::new()
+            .application_name("PETRA")
+            .application_uri("urn:petra:client")
+            .create_sample_keypair(true)
+            .trust_server_certs(true)
+            // Reconnection is handled by `run`, which also recreates the subscriptions
+            .session_retry_limit(0)
+            .client()
+            .ok_or_else(|| PlcError::OpcUa("Invalid OPC-UA client configuration".to_string()))?;
+
+        let policy = SecurityPolicy::from_str(&self.config.security_policy)
+            .map_err(|()| PlcError::OpcUa(format!("Unknown security policy {}", self.config.security_policy)))?;
+        let mode = match self.config.security_mode.as_str() {
+            "None" => MessageSecurityMode::None,
+            "Sign" => MessageSecurityMode::Sign,
+            "SignAndEncrypt" => MessageSecurityMode::SignAndEncrypt,
+            other => return Err(PlcError::OpcUa(format!("Unknown security mode {other}"))),
+        };
+        let endpoint: EndpointDescription =
+            (self.config.endpoint.as_str(), policy.to_str(), mode, UserTokenPolicy::anonymous()).into();
+        let session = client
+            .connect_to_endpoint(endpoint, self.identity())
+            .map_err(|status| PlcError::OpcUa(format!("Cannot connect to {}: {}", self.config.endpoint, status)))?;
+
+        {
+            let session = session.read();
+            for subscription in &self.subscriptions {
+                self.subscribe(&session, subscription)?;
+            }
+        }
+        Ok(session)
+    }
+
+    fn subscribe(&self, session: &Session, subscription: &PlannedSubscription) -> Result<()> {
+        let signals: HashMap<u32, String> =
+            subscription.items.iter().map(|item| (item.handle, item.signal.clone())).collect();
+        let bus = self.bus.clone();
+        let callback = DataChangeCallback::new(move |items: &[&MonitoredItem]| {
+            for item in items {
+                let Some(signal) = signals.get(&item.client_handle()) else {
+                    continue;
+                };
+                let data = item.last_value();
+                if data.status.is_some_and(|status| status.is_bad()) {
+                    debug!("OPC-UA value of {} is bad", signal);
+                    continue;
+                }
+                match data.value.as_ref().and_then(value) {
+                    Some(value) => {
+                        let mut meta = WriteMeta::from_source(Some("opcua"));
+                        if let Some(sampled) = data.source_timestamp.as_ref().filter(|t| !t.is_null()) {
+                            meta = meta.at(sampled.as_chrono().into());
+                        }
+                        if let Err(e) = bus.set_with_meta(signal, value, meta) {
+                            warn!("Failed to set {} from OPC-UA: {}", signal, e);
+                        }
+                    }
+                    None => debug!("OPC-UA value of {} has no signal type", signal),
+                }
+            }
+        });
+
+        #[allow(clippy::cast_precision_loss)]
+        let id = session
+            .create_subscription(
+                subscription.publishing_interval_ms as f64,
+                LIFETIME_COUNT,
+                MAX_KEEP_ALIVE_COUNT,
+                0,
+                0,
+                true,
+                callback,
+            )
+            .map_err(|status| PlcError::OpcUa(format!("Cannot create subscription: {status}")))?;
+
+        let batch_size = self.config.max_items_per_request.max(1);
+        for batch in subscription.items.chunks(batch_size) {
+            let requests: Vec<MonitoredItemCreateRequest> = batch.iter().filter_map(create_request).collect();
+            let results = session
+                .create_monitored_items(id, TimestampsToReturn::Both, &requests)
+                .map_err(|status| PlcError::OpcUa(format!("Cannot create monitored items: {status}")))?;
+            for (request, result) in requests.iter().zip(results) {
+                if result.status_code.is_bad() {
+                    warn!(
+                        "OPC-UA monitored item {} rejected: {}",
+                        request.item_to_monitor.node_id, result.status_code
+                    );
+                }
+            }
+        }
+        info!(
+            "OPC-UA subscription {} created: {} items every {} ms",
+            id,
+            subscription.items.len(),
+            subscription.publishing_interval_ms
+        );
+        Ok(())
+    }
+}
+
+/// Monitored item request for `item`, if its node id is valid
+fn create_request(item: &PlannedItem) -> Option<MonitoredItemCreateRequest> {
+    let node_id = match NodeId::from_str(&item.node_id) {
+        Ok(node_id) => node_id,
+        Err(_) => {
+            warn!("Invalid OPC-UA node id {}", item.node_id);
+            return None;
+        }
+    };
+    let filter = item.deadband.map_or_else(ExtensionObject::null, |deadband| {
+        let (deadband_type, deadband_value) = match deadband {
+            Deadband::Absolute(v) => (DeadbandType::Absolute, v),
+            Deadband::Percent(v) => (DeadbandType::Percent, v),
+        };
+        let filter = DataChangeFilter {
+            trigger: DataChangeTrigger::StatusValue,
+            deadband_type: deadband_type as u32,
+            deadband_value,
+        };
+        ExtensionObject::from_encodable(ObjectId::DataChangeFilter_Encoding_DefaultBinary, &filter)
+    });
+    #[allow(clippy::cast_precision_loss)]
+    Some(MonitoredItemCreateRequest {
+        item_to_monitor: ReadValueId {
+            node_id,
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            data_encoding: QualifiedName::null(),
+        },
+        monitoring_mode: MonitoringMode::Reporting,
+        requested_parameters: MonitoringParameters {
+            client_handle: item.handle,
+            sampling_interval: item.sampling_interval_ms as f64,
+            filter,
+            queue_size: item.queue_size,
+            discard_oldest: item.discard_oldest,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> OpcuaConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_items_are_grouped_by_publishing_interval() {
+        let config = config(
+            "endpoint: opc.tcp://plc:4840\n\
+             publishing_interval_ms: 500\n\
+             subscriptions:\n\
+             - { node_id: 'ns=2;s=A', signal: a }\n\
+             - { node_id: 'ns=2;s=B', signal: b, publishing_interval_ms: 5000, queue_size: 10 }\n\
+             - { node_id: 'ns=2;s=C', signal: c, deadband: { type: percent, value: 2.5 } }\n",
+        );
+        let planned = plan(&config);
+        assert_eq!(planned.len(), 2);
+        assert_eq!(planned[0].publishing_interval_ms, 500);
+        let handles: Vec<u32> = planned[0].items.iter().map(|i| i.handle).collect();
+        assert_eq!(handles, vec![1, 3]);
+        assert_eq!(planned[0].items[1].deadband, Some(Deadband::Percent(2.5)));
+        assert_eq!(planned[1].items[0].queue_size, 10);
+        assert!(planned[1].items[0].discard_oldest);
+        assert_eq!(config.max_items_per_request, 500);
+    }
+
+    #[test]
+    fn test_variants_map_to_values() {
+        assert_eq!(value(&Variant::UInt16(7)), Some(Value::Integer(7)));
+        assert_eq!(value(&Variant::Float(1.5)), Some(Value::Float(1.5)));
+        assert_eq!(value(&Variant::UInt64(u64::MAX)), None);
+        assert_eq!(value(&Variant::Empty), None);
+    }
+}
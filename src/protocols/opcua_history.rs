@@ -0,0 +1,442 @@
+//! OPC-UA historical access backed by the history storage
+//!
+//! Answers HistoryRead requests with `ReadRawModifiedDetails` (raw values,
+//! not modified ones) for the signal variables of the
+//! [OPC-UA server](super::opcua_server). The node id's string identifier is
+//! the signal name; values come from a [`HistorySource`], which the history
+//! manager implements through its query API.
+//!
+//! Time ranges follow OPC UA Part 11: the start time is included and the end
+//! time excluded. A start after the end, or no start at all, reads backwards
+//! from the end. With only one bound the request must limit the number of
+//! values per node.
+//!
+//! A node returns at most `max_history_values` values per call (fewer if the
+//! client asks for fewer). The rest is reached through a continuation point
+//! that encodes where the next page starts, so the server keeps no state
+//! between calls. Bounding values are not returned.
+
+use crate::error::{PlcError, Result};
+use crate::history::{HistoryEntry, HistoryManager, HistoryQuery};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use opcua::server::historical::HistoricalDataProvider;
+use opcua::server::prelude::*;
+use opcua::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tracing::warn;
+
+/// Recorded values of signals
+#[async_trait]
+pub trait HistorySource: Send + Sync {
+    /// Values of `signal` between `from` and `to` (both inclusive, either
+    /// open), oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage cannot be queried.
+    async fn values(
+        &self,
+        signal: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<HistoryEntry>>;
+}
+
+#[async_trait]
+impl HistorySource for HistoryManager {
+    async fn values(
+        &self,
+        signal: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<HistoryEntry>> {
+        let mut entries = self
+            .query(HistoryQuery {
+                signal_name: Some(signal.to_string()),
+                start_time: from,
+                end_time: to,
+                limit: None,
+            })
+            .await?;
+        entries.sort_by_key(|e| e.timestamp);
+        Ok(entries)
+    }
+}
+
+// ============================================================================
+// RAW READS
+// ============================================================================
+
+/// Time range and value limit of a raw read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawRead {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// Most values per node, 0 for no limit
+    pub values_per_node: usize,
+}
+
+impl RawRead {
+    /// Check the request is answerable
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] if a bound is missing and the number
+    /// of values is not limited.
+    pub fn validate(&self) -> Result<()> {
+        match (self.start, self.end) {
+            (Some(_), Some(_)) => Ok(()),
+            (None, None) => Err(PlcError::Validation("A start or end time is required".to_string())),
+            _ if self.values_per_node == 0 => Err(PlcError::Validation(
+                "A read with one time bound must limit the values per node".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether values are returned newest first
+    #[must_use]
+    pub fn reverse(&self) -> bool {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => start > end,
+            (None, _) => true,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Oldest and newest time to query
+    fn span(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => (Some(start.min(end)), Some(start.max(end))),
+            bounds => bounds,
+        }
+    }
+
+    /// Whether a value at `at` is in the range
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if start > end => at <= start && at > end,
+            (Some(start), Some(end)) => at >= start && at < end,
+            (Some(start), None) => at >= start,
+            (None, Some(end)) => at < end,
+            (None, None) => true,
+        }
+    }
+}
+
+/// Where the next page of a read starts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Continuation {
+    pub signal: String,
+    /// Timestamp of the last value returned
+    pub at: DateTime<Utc>,
+    /// Values at `at` already returned
+    pub skip: usize,
+}
+
+impl Continuation {
+    /// Opaque bytes handed to the client
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    /// Continuation point sent back by a client
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// Values returned for one node
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub values: Vec<HistoryEntry>,
+    pub continuation: Option<Continuation>,
+}
+
+/// Read one page of raw values of `signal`
+///
+/// # Errors
+///
+/// Returns an error if the request is invalid, the continuation point
+/// belongs to another signal or the source cannot be read.
+pub async fn read_raw(
+    source: &dyn HistorySource,
+    signal: &str,
+    request: &RawRead,
+    resume: Option<&Continuation>,
+    max_values: usize,
+) -> Result<Page> {
+    request.validate()?;
+    if resume.is_some_and(|resume| resume.signal != signal) {
+        return Err(PlcError::Validation(format!("Continuation point is not for {signal}")));
+    }
+
+    let (from, to) = request.span();
+    let mut values: Vec<HistoryEntry> = source
+        .values(signal, from, to)
+        .await?
+        .into_iter()
+        .filter(|e| request.contains(e.timestamp))
+        .collect();
+    let reverse = request.reverse();
+    if reverse {
+        values.reverse();
+    }
+
+    if let Some(resume) = resume {
+        let passed = |at: DateTime<Utc>| if reverse { at > resume.at } else { at < resume.at };
+        let first = values.iter().position(|e| !passed(e.timestamp)).unwrap_or(values.len());
+        values.drain(..first);
+        let same = values.iter().take_while(|e| e.timestamp == resume.at).count();
+        values.drain(..same.min(resume.skip));
+    }
+
+    let limit = match request.values_per_node {
+        0 => max_values,
+        n => n.min(max_values),
+    };
+    if values.len() <= limit {
+        return Ok(Page { values, continuation: None });
+    }
+    values.truncate(limit);
+
+    let continuation = values.last().map(|last| {
+        let at = last.timestamp;
+        let mut skip = values.iter().rev().take_while(|e| e.timestamp == at).count();
+        if let Some(resume) = resume.filter(|resume| resume.at == at) {
+            skip += resume.skip;
+        }
+        Continuation { signal: signal.to_string(), at, skip }
+    });
+    Ok(Page { values, continuation })
+}
+
+// ============================================================================
+// OPC-UA SERVICE
+// ============================================================================
+
+/// HistoryRead handler installed in the OPC-UA server
+pub struct HistoryProvider {
+    source: Arc<dyn HistorySource>,
+    /// Runtime the history source runs on
+    runtime: Handle,
+    namespace: u16,
+    max_values: usize,
+}
+
+impl HistoryProvider {
+    /// Provider for the signal variables in `namespace`
+    #[must_use]
+    pub fn new(source: Arc<dyn HistorySource>, runtime: Handle, namespace: u16, max_values: usize) -> Self {
+        Self { source, runtime, namespace, max_values: max_values.max(1) }
+    }
+
+    /// Signal of a variable node
+    fn signal(&self, node_id: &NodeId) -> Option<String> {
+        match &node_id.identifier {
+            Identifier::String(name) if node_id.namespace == self.namespace => Some(name.as_ref().to_string()),
+            _ => None,
+        }
+    }
+
+    fn read_node(&self, request: &RawRead, node: &HistoryReadValueId) -> HistoryReadResult {
+        let failed = |status_code: StatusCode| HistoryReadResult {
+            status_code,
+            continuation_point: ByteString::null(),
+            history_data: ExtensionObject::null(),
+        };
+        let Some(signal) = self.signal(&node.node_id) else {
+            return failed(StatusCode::BadNodeIdUnknown);
+        };
+        let resume = match node.continuation_point.value.as_deref() {
+            None | Some([]) => None,
+            Some(bytes) => match Continuation::decode(bytes) {
+                Some(resume) if resume.signal == signal => Some(resume),
+                _ => return failed(StatusCode::BadContinuationPointInvalid),
+            },
+        };
+
+        // The OPC-UA stack calls providers synchronously from inside its own
+        // runtime, where blocking on another runtime is not allowed
+        let read = || {
+            self.runtime.block_on(read_raw(
+                self.source.as_ref(),
+                &signal,
+                request,
+                resume.as_ref(),
+                self.max_values,
+            ))
+        };
+        let page = std::thread::scope(|scope| scope.spawn(read).join())
+            .unwrap_or_else(|_| Err(PlcError::Runtime("History read panicked".to_string())));
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("HistoryRead of {} failed: {}", signal, e);
+                return failed(StatusCode::BadHistoryOperationInvalid);
+            }
+        };
+
+        let data_values = page.values.iter().map(data_value).collect();
+        let history_data = HistoryData { data_values: Some(data_values) };
+        HistoryReadResult {
+            status_code: if page.continuation.is_some() { StatusCode::GoodMoreData } else { StatusCode::Good },
+            continuation_point: page
+                .continuation
+                .map_or_else(ByteString::null, |c| ByteString::from(c.encode())),
+            history_data: ExtensionObject::from_encodable(ObjectId::HistoryData_Encoding_DefaultBinary, &history_data),
+        }
+    }
+}
+
+impl HistoricalDataProvider for HistoryProvider {
+    fn read_raw_modified_details(
+        &self,
+        _address_space: Arc<RwLock<AddressSpace>>,
+        request: ReadRawModifiedDetails,
+        _timestamps_to_return: TimestampsToReturn,
+        release_continuation_points: bool,
+        nodes_to_read: &[HistoryReadValueId],
+    ) -> std::result::Result<Vec<HistoryReadResult>, StatusCode> {
+        if request.is_read_modified {
+            return Err(StatusCode::BadHistoryOperationUnsupported);
+        }
+        let time = |t: opcua::types::DateTime| (!t.is_null()).then(|| t.as_chrono());
+        let read = RawRead {
+            start: time(request.start_time),
+            end: time(request.end_time),
+            values_per_node: usize::try_from(request.num_values_per_node).unwrap_or(usize::MAX),
+        };
+        if read.validate().is_err() {
+            return Err(StatusCode::BadHistoryOperationInvalid);
+        }
+        // Continuation points hold no server state, releasing them is a no-op
+        if release_continuation_points {
+            return Ok(nodes_to_read
+                .iter()
+                .map(|_| HistoryReadResult {
+                    status_code: StatusCode::Good,
+                    continuation_point: ByteString::null(),
+                    history_data: ExtensionObject::null(),
+                })
+                .collect());
+        }
+        Ok(nodes_to_read.iter().map(|node| self.read_node(&read, node)).collect())
+    }
+}
+
+/// OPC-UA status of a recorded quality byte (192 and above is good)
+fn status(quality: Option<u8>) -> StatusCode {
+    match quality {
+        None | Some(192..) => StatusCode::Good,
+        Some(64..=191) => StatusCode::Uncertain,
+        Some(_) => StatusCode::Bad,
+    }
+}
+
+fn data_value(entry: &HistoryEntry) -> DataValue {
+    let timestamp = opcua::types::DateTime::from(entry.timestamp);
+    DataValue {
+        value: Some(super::opcua_server::variant(&entry.value)),
+        status: Some(status(entry.quality)),
+        source_timestamp: Some(timestamp),
+        source_picoseconds: None,
+        server_timestamp: Some(timestamp),
+        server_picoseconds: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Value;
+    use chrono::TimeZone;
+
+    struct Recorded(Vec<HistoryEntry>);
+
+    #[async_trait]
+    impl HistorySource for Recorded {
+        async fn values(
+            &self,
+            signal: &str,
+            from: Option<DateTime<Utc>>,
+            to: Option<DateTime<Utc>>,
+        ) -> Result<Vec<HistoryEntry>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|e| e.signal_name == signal)
+                .filter(|e| from.is_none_or(|from| e.timestamp >= from) && to.is_none_or(|to| e.timestamp <= to))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn at(second: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + second, 0).unwrap()
+    }
+
+    /// One value per second for 0..10 s, with a second value at 5 s
+    fn recorded() -> Recorded {
+        let mut entries: Vec<HistoryEntry> = (0..10)
+            .map(|s| HistoryEntry {
+                timestamp: at(s),
+                signal_name: "tank.level".to_string(),
+                value: Value::Integer(s),
+                quality: None,
+                metadata: None,
+            })
+            .collect();
+        entries.insert(6, HistoryEntry { value: Value::Integer(50), ..entries[5].clone() });
+        Recorded(entries)
+    }
+
+    fn ints(page: &Page) -> Vec<i64> {
+        page.values.iter().filter_map(|e| e.value.as_integer()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_resume_after_duplicate_timestamps() {
+        let source = recorded();
+        let request = RawRead { start: Some(at(2)), end: Some(at(8)), values_per_node: 0 };
+
+        let page = read_raw(&source, "tank.level", &request, None, 4).await.unwrap();
+        assert_eq!(ints(&page), vec![2, 3, 4, 5]);
+        let resume = Continuation::decode(&page.continuation.unwrap().encode()).unwrap();
+        assert_eq!((resume.at, resume.skip), (at(5), 1));
+
+        // The end time is excluded
+        let page = read_raw(&source, "tank.level", &request, Some(&resume), 4).await.unwrap();
+        assert_eq!(ints(&page), vec![50, 6, 7]);
+        assert!(page.continuation.is_none());
+
+        let other = Continuation { signal: "other".to_string(), ..resume };
+        assert!(read_raw(&source, "tank.level", &request, Some(&other), 4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_reads() {
+        let source = recorded();
+
+        // Start after end: newest first, start included
+        let request = RawRead { start: Some(at(7)), end: Some(at(4)), values_per_node: 0 };
+        let page = read_raw(&source, "tank.level", &request, None, 100).await.unwrap();
+        assert_eq!(ints(&page), vec![7, 6, 50, 5]);
+
+        // No start: the newest values before the end
+        let request = RawRead { start: None, end: Some(at(9)), values_per_node: 2 };
+        let page = read_raw(&source, "tank.level", &request, None, 100).await.unwrap();
+        assert_eq!(ints(&page), vec![8, 7]);
+        let page = read_raw(&source, "tank.level", &request, page.continuation.as_ref(), 100).await.unwrap();
+        assert_eq!(ints(&page), vec![6, 50]);
+
+        let unbounded = RawRead { start: Some(at(0)), end: None, values_per_node: 0 };
+        assert!(unbounded.validate().is_err());
+        assert_eq!(status(Some(100)), StatusCode::Uncertain);
+    }
+}
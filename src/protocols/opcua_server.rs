@@ -0,0 +1,193 @@
+//! OPC-UA server exposing signals
+//!
+//! Every exposed signal becomes a variable below an `Objects/Signals` folder,
+//! in the namespace configured as `namespace_uri` and with the signal name as
+//! its string node id (`ns=2;s=line1.speed`). Variable values are refreshed
+//! from the signal bus every `update_interval_ms`; the data type of a
+//! variable follows the signal value at startup.
+//!
+//! With the "history" feature and a history source attached
+//! ([`OpcuaServer::with_history`]), the variables are historizing and the
+//! server answers HistoryRead requests for raw values, see
+//! [`opcua_history`](super::opcua_history).
+//!
+//! The server offers the `None` security policy with anonymous login only;
+//! it is meant for trend and value access inside the plant network.
+
+use crate::config::OpcuaServerConfig;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use opcua::server::prelude::*;
+use tracing::{info, warn};
+
+/// Browse name of the folder holding the signal variables
+const SIGNALS_FOLDER: &str = "Signals";
+
+/// OPC-UA server serving the signals of one signal bus
+pub struct OpcuaServer {
+    config: OpcuaServerConfig,
+    bus: SignalBus,
+    #[cfg(feature = "history")]
+    history: Option<(std::sync::Arc<dyn super::opcua_history::HistorySource>, tokio::runtime::Handle)>,
+}
+
+impl OpcuaServer {
+    /// Server for `bus`
+    #[must_use]
+    pub fn new(config: OpcuaServerConfig, bus: SignalBus) -> Self {
+        Self {
+            config,
+            bus,
+            #[cfg(feature = "history")]
+            history: None,
+        }
+    }
+
+    /// Answer HistoryRead requests from `history`
+    ///
+    /// Must be called inside the Tokio runtime `history` runs on.
+    #[cfg(feature = "history")]
+    #[must_use]
+    pub fn with_history(mut self, history: std::sync::Arc<dyn super::opcua_history::HistorySource>) -> Self {
+        self.history = Some((history, tokio::runtime::Handle::current()));
+        self
+    }
+
+    /// Signals exposed as variables
+    fn signals(&self) -> Vec<String> {
+        if self.config.signals.is_empty() {
+            let mut names = self.bus.signal_names();
+            names.sort();
+            names
+        } else {
+            self.config.signals.clone()
+        }
+    }
+
+    /// Run the server until the process exits
+    ///
+    /// Blocks the calling thread: the OPC-UA stack runs its own runtime, so
+    /// this must be called from a dedicated thread rather than a Tokio task.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::OpcUa`] if the server cannot be created.
+    pub fn run(self) -> Result<()> {
+        let url = format!("opc.tcp://{}:{}/", self.config.host, self.config.port);
+        let server = ServerBuilder::new()
+            .application_name(self.config.application_name.as_str())
+            .application_uri(self.config.namespace_uri.as_str())
+            .product_uri(self.config.namespace_uri.as_str())
+            .create_sample_keypair(true)
+            .pki_dir(&self.config.pki_dir)
+            .host_and_port(self.config.host.as_str(), self.config.port)
+            .discovery_urls(vec![url.clone()])
+            .endpoint("none", ServerEndpoint::new_none("/", &[ANONYMOUS_USER_TOKEN_ID.into()]))
+            .server()
+            .ok_or_else(|| PlcError::OpcUa(format!("Invalid OPC-UA server configuration for {url}")))?;
+
+        #[cfg(feature = "history")]
+        let historizing = self.history.is_some();
+        #[cfg(not(feature = "history"))]
+        let historizing = false;
+        let signals = self.signals();
+        let namespace = {
+            let address_space = server.address_space();
+            let mut address_space = address_space.write();
+            let namespace = address_space
+                .register_namespace(&self.config.namespace_uri)
+                .map_err(|()| PlcError::OpcUa(format!("Cannot register namespace {}", self.config.namespace_uri)))?;
+            let folder = address_space
+                .add_folder(SIGNALS_FOLDER, SIGNALS_FOLDER, &NodeId::objects_folder_id())
+                .map_err(|()| PlcError::OpcUa("Cannot create the signals folder".to_string()))?;
+            for signal in &signals {
+                let Some(value) = self.bus.get(signal) else {
+                    warn!("OPC-UA server: signal '{}' does not exist", signal);
+                    continue;
+                };
+                add_variable(&mut address_space, &NodeId::new(namespace, signal.as_str()), signal, &value, &folder, historizing);
+            }
+            namespace
+        };
+
+        #[cfg(feature = "history")]
+        if let Some((history, runtime)) = self.history.clone() {
+            let provider =
+                super::opcua_history::HistoryProvider::new(history, runtime, namespace, self.config.max_history_values);
+            server.server_state().write().set_historical_data_provider(Box::new(provider));
+        }
+
+        let address_space = server.address_space();
+        let bus = self.bus.clone();
+        let nodes: Vec<(NodeId, String)> = signals
+            .into_iter()
+            .map(|signal| (NodeId::new(namespace, signal.as_str()), signal))
+            .collect();
+        let exposed = nodes.len();
+        server.add_polling_action(self.config.update_interval_ms, move || {
+            let now = DateTime::now();
+            let mut address_space = address_space.write();
+            for (node_id, signal) in &nodes {
+                if let Some(value) = bus.get(signal) {
+                    address_space.set_variable_value(node_id.clone(), variant(&value), &now, &now);
+                }
+            }
+        });
+
+        info!("OPC-UA server listening on {} ({} signals, history {})", url, exposed, historizing);
+        server.run();
+        Ok(())
+    }
+}
+
+fn add_variable(
+    address_space: &mut AddressSpace,
+    node_id: &NodeId,
+    signal: &str,
+    value: &Value,
+    folder: &NodeId,
+    historizing: bool,
+) {
+    let mut access = AccessLevel::CURRENT_READ;
+    let mut user_access = UserAccessLevel::CURRENT_READ;
+    if historizing {
+        access |= AccessLevel::HISTORY_READ;
+        user_access |= UserAccessLevel::HISTORY_READ;
+    }
+    VariableBuilder::new(node_id, signal, signal)
+        .data_type(data_type(value))
+        .value(variant(value))
+        .access_level(access)
+        .user_access_level(user_access)
+        .historizing(historizing)
+        .organized_by(folder)
+        .insert(address_space);
+}
+
+/// OPC-UA data type of a variable holding `value`
+const fn data_type(value: &Value) -> DataTypeId {
+    match value {
+        Value::Bool(_) => DataTypeId::Boolean,
+        Value::Integer(_) => DataTypeId::Int64,
+        Value::Float(_) => DataTypeId::Double,
+        #[allow(unreachable_patterns)]
+        _ => DataTypeId::String,
+    }
+}
+
+/// OPC-UA representation of a signal value
+///
+/// Extended values other than strings are sent as their text form.
+#[must_use]
+pub fn variant(value: &Value) -> Variant {
+    match value {
+        Value::Bool(b) => Variant::Boolean(*b),
+        Value::Integer(i) => Variant::Int64(*i),
+        Value::Float(f) => Variant::Double(*f),
+        #[cfg(feature = "extended-types")]
+        Value::String(s) => Variant::String(UAString::from(s.as_str())),
+        #[allow(unreachable_patterns)]
+        other => Variant::String(UAString::from(other.to_string())),
+    }
+}
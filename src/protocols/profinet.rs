@@ -0,0 +1,2676 @@
+//! PROFINET IO device over a raw Ethernet socket
+//!
+//! Lets a PROFINET IO controller, such as an S7-1500, exchange cyclic IO
+//! data with PETRA as with any other IO device:
+//!
+//! - **GSDML**: the device is described by a GSDML file that is imported
+//!   into the controller's engineering tool. The same file is read at
+//!   startup, and the configured device access point and the module of every
+//!   slot are looked up in it for their ident numbers and IO data lengths.
+//! - **Discovery**: DCP Identify, Get and Set requests are answered so the
+//!   controller finds the device by its station name. A name assigned with
+//!   DCP Set lasts until restart. The IP address belongs to the host, so DCP
+//!   requests to change it are refused.
+//! - **Connection**: the submodules in the controller's Connect request are
+//!   checked against the configured modules. Differences are reported in
+//!   the module diff and the affected submodules exchange no data.
+//!   Parameter records are accepted and ignored; once parameterization ends
+//!   the device signals application ready.
+//! - **Cyclic exchange**: input data is sent every update cycle from its
+//!   signals, and output data from the controller is written to signals as
+//!   it changes. If the controller's frames stop for longer than its
+//!   watchdog time the connection is dropped and outputs keep their last
+//!   values.
+//! - **Alarms**: an alarm signal turning on raises a diagnosis alarm, which
+//!   disappears again when the signal turns off, or a process alarm on its
+//!   slot.
+//!
+//! Inputs and outputs are named from the controller's side: `inputs` are
+//! sent by PETRA and `outputs` received. Entries are big-endian and belong
+//! to the first submodule of their slot.
+//!
+//! ```yaml
+//! protocols:
+//!   profinet:
+//!     interface: eth1
+//!     station_name: petra-line1
+//!     gsdml: config/GSDML-V2.41-Lithos-PETRA-20240601.xml
+//!     device_access_point: DAP1
+//!     slots:
+//!       - slot: 1
+//!         module: DI8
+//!         inputs:
+//!           - { signal: line1.start_button, offset: 0, bit: 0, type: bool }
+//!           - { signal: line1.stop_button, offset: 0, bit: 1, type: bool }
+//!       - slot: 2
+//!         module: AIO2
+//!         inputs:
+//!           - { signal: line1.temperature, offset: 0, type: float32 }
+//!         outputs:
+//!           - { signal: line1.speed_ref, offset: 0, type: int16 }
+//!     alarms:
+//!       - { signal: line1.overtemp, slot: 2, type: diagnosis }
+//!       - { signal: line1.batch_done, slot: 1, type: process }
+//! ```
+//!
+//! One controller connection (AR) is served at a time, with one input and
+//! one output IO CR of RT class 1. Connect requests must fit in a single
+//! RPC fragment, and update times below a few milliseconds are not
+//! supported. Opening the raw socket requires Linux and `CAP_NET_RAW`.
+
+#[cfg(target_os = "linux")]
+use super::raw_ethernet::{RawSocket, Transport};
+use crate::{PlcError, Result, SignalBus, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// PROFINET IO device configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfinetConfig {
+    /// Network interface connected to the controller
+    pub interface: String,
+
+    /// Name of station the controller addresses the device by
+    pub station_name: String,
+
+    /// GSDML file describing the device
+    pub gsdml: PathBuf,
+
+    /// ID of the device access point in the GSDML file
+    pub device_access_point: String,
+
+    /// Modules plugged into the device's slots
+    #[serde(default)]
+    pub slots: Vec<SlotConfig>,
+
+    /// Signals raising alarms
+    #[serde(default)]
+    pub alarms: Vec<AlarmConfig>,
+}
+
+/// Module plugged into a slot and its IO data mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotConfig {
+    /// Slot number, starting at 1 after the device access point
+    pub slot: u16,
+
+    /// ID of the module in the GSDML file
+    pub module: String,
+
+    /// Data sent to the controller
+    #[serde(default)]
+    pub inputs: Vec<IoEntry>,
+
+    /// Data received from the controller
+    #[serde(default)]
+    pub outputs: Vec<IoEntry>,
+}
+
+/// IO data item mapped to a signal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IoEntry {
+    /// Signal holding the item's value
+    pub signal: String,
+
+    /// Byte offset within the submodule's IO data
+    pub offset: u16,
+
+    /// Bit within the byte, for `bool` items
+    #[serde(default)]
+    pub bit: u8,
+
+    /// Encoding of the item
+    #[serde(rename = "type")]
+    pub data_type: IoType,
+}
+
+/// Encodings of IO data items, all big-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoType {
+    /// Single bit, read as `Bool`
+    Bool,
+    /// Signed 8-bit integer
+    Int8,
+    /// Unsigned 8-bit integer
+    Uint8,
+    /// Signed 16-bit integer
+    Int16,
+    /// Unsigned 16-bit integer
+    Uint16,
+    /// Signed 32-bit integer
+    Int32,
+    /// Unsigned 32-bit integer
+    Uint32,
+    /// IEEE 754 single precision float
+    Float32,
+}
+
+/// Signal raising an alarm on a slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlarmConfig {
+    /// Boolean signal; the alarm is raised when it turns on
+    pub signal: String,
+
+    /// Slot whose module reports the alarm
+    pub slot: u16,
+
+    /// Kind of alarm
+    #[serde(default, rename = "type")]
+    pub kind: AlarmKind,
+
+    /// Channel error type reported with diagnosis alarms
+    #[serde(default = "default_error_type")]
+    pub error_type: u16,
+}
+
+/// Alarms a signal can raise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmKind {
+    /// Channel diagnosis that appears and disappears with the signal
+    #[default]
+    Diagnosis,
+    /// Process alarm raised on every rising edge
+    Process,
+}
+
+/// Channel error type "Error"
+const fn default_error_type() -> u16 {
+    0x0009
+}
+
+impl ProfinetConfig {
+    /// Validate the station name, slots and mappings
+    ///
+    /// Module IDs and entry offsets are checked against the GSDML file when
+    /// the device starts.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an invalid station name, slot 0 or
+    /// duplicate slots, signals mapped twice or alarms on unconfigured
+    /// slots.
+    pub fn validate(&self) -> Result<()> {
+        if self.interface.is_empty() {
+            return Err(PlcError::Config("PROFINET interface must not be empty".to_string()));
+        }
+        if !is_valid_station_name(&self.station_name) {
+            return Err(PlcError::Config(format!(
+                "Invalid PROFINET station name '{}': use lowercase letters, digits, '-' and '.'",
+                self.station_name
+            )));
+        }
+        if self.device_access_point.is_empty() {
+            return Err(PlcError::Config(
+                "PROFINET device_access_point must not be empty".to_string(),
+            ));
+        }
+
+        let mut slots = HashSet::new();
+        let mut signals = HashSet::new();
+        for slot in &self.slots {
+            if slot.slot == 0 || !slots.insert(slot.slot) {
+                return Err(PlcError::Config(format!(
+                    "PROFINET slot {} is reserved for the access point or configured twice",
+                    slot.slot
+                )));
+            }
+            for entry in slot.inputs.iter().chain(&slot.outputs) {
+                if entry.bit > 7 {
+                    return Err(PlcError::Config(format!(
+                        "PROFINET entry '{}' has bit {} outside its byte",
+                        entry.signal, entry.bit
+                    )));
+                }
+                if !signals.insert(&entry.signal) {
+                    return Err(PlcError::Config(format!(
+                        "PROFINET maps signal '{}' more than once",
+                        entry.signal
+                    )));
+                }
+            }
+        }
+        for alarm in &self.alarms {
+            if !slots.contains(&alarm.slot) {
+                return Err(PlcError::Config(format!(
+                    "PROFINET alarm '{}' is on unconfigured slot {}",
+                    alarm.signal, alarm.slot
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` is a valid name of station: dot-separated labels of
+/// lowercase letters, digits and inner hyphens
+fn is_valid_station_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 240
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        })
+}
+
+impl IoType {
+    /// Bytes occupied in the IO data
+    #[must_use]
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Bool | Self::Int8 | Self::Uint8 => 1,
+            Self::Int16 | Self::Uint16 => 2,
+            Self::Int32 | Self::Uint32 | Self::Float32 => 4,
+        }
+    }
+
+    /// Read an item from `data`, which starts at the item's offset
+    fn decode(self, data: &[u8], bit: u8) -> Value {
+        let bytes = |n: usize| {
+            let mut buf = [0u8; 4];
+            buf[..n].copy_from_slice(&data[..n]);
+            buf
+        };
+        match self {
+            Self::Bool => Value::Bool(data[0] & (1 << bit) != 0),
+            Self::Int8 => Value::Integer(i64::from(i8::from_be_bytes([data[0]]))),
+            Self::Uint8 => Value::Integer(i64::from(data[0])),
+            Self::Int16 => Value::Integer(i64::from(i16::from_be_bytes([data[0], data[1]]))),
+            Self::Uint16 => Value::Integer(i64::from(u16::from_be_bytes([data[0], data[1]]))),
+            Self::Int32 => Value::Integer(i64::from(i32::from_be_bytes(bytes(4)))),
+            Self::Uint32 => Value::Integer(i64::from(u32::from_be_bytes(bytes(4)))),
+            Self::Float32 => Value::Float(f64::from(f32::from_be_bytes(bytes(4)))),
+        }
+    }
+
+    /// Write `value` into `data`, which starts at the item's offset
+    ///
+    /// Integers outside the item's range are saturated. Values that cannot
+    /// be converted leave the previous input in place.
+    fn encode(self, value: &Value, data: &mut [u8], bit: u8) {
+        fn saturate<T: TryFrom<i64>>(v: i64, min: T, max: T) -> T {
+            T::try_from(v).unwrap_or(if v < 0 { min } else { max })
+        }
+        let bytes: Vec<u8> = match self {
+            Self::Bool => {
+                let Some(on) = value.as_bool() else { return };
+                if on {
+                    data[0] |= 1 << bit;
+                } else {
+                    data[0] &= !(1 << bit);
+                }
+                return;
+            }
+            Self::Float32 => {
+                let Some(v) = value.as_float() else { return };
+                #[allow(clippy::cast_possible_truncation)]
+                let v = v as f32;
+                v.to_be_bytes().to_vec()
+            }
+            _ => {
+                let Some(v) = value.as_integer() else { return };
+                match self {
+                    Self::Int8 => saturate(v, i8::MIN, i8::MAX).to_be_bytes().to_vec(),
+                    Self::Uint8 => saturate(v, u8::MIN, u8::MAX).to_be_bytes().to_vec(),
+                    Self::Int16 => saturate(v, i16::MIN, i16::MAX).to_be_bytes().to_vec(),
+                    Self::Uint16 => saturate(v, u16::MIN, u16::MAX).to_be_bytes().to_vec(),
+                    Self::Int32 => saturate(v, i32::MIN, i32::MAX).to_be_bytes().to_vec(),
+                    _ => saturate(v, u32::MIN, u32::MAX).to_be_bytes().to_vec(),
+                }
+            }
+        };
+        data[..bytes.len()].copy_from_slice(&bytes);
+    }
+}
+
+// ============================================================================
+// GSDML
+// ============================================================================
+
+/// Device description read from a GSDML file
+#[derive(Debug, Clone)]
+pub struct Gsdml {
+    /// Vendor ID assigned by PI
+    pub vendor_id: u16,
+
+    /// Device ID assigned by the vendor
+    pub device_id: u16,
+
+    /// Vendor name reported in DCP responses
+    pub vendor_name: String,
+
+    /// Device access points by ID
+    pub access_points: HashMap<String, ModuleDefinition>,
+
+    /// Pluggable modules by ID
+    pub modules: HashMap<String, ModuleDefinition>,
+}
+
+/// Ident number and submodules of a module or device access point
+#[derive(Debug, Clone)]
+pub struct ModuleDefinition {
+    /// Module ident number
+    pub ident: u32,
+
+    /// Submodules in subslot order
+    pub submodules: Vec<SubmoduleDefinition>,
+}
+
+/// Ident number and IO data lengths of a submodule
+#[derive(Debug, Clone, Copy)]
+pub struct SubmoduleDefinition {
+    /// Subslot the submodule occupies
+    pub subslot: u16,
+
+    /// Submodule ident number
+    pub ident: u32,
+
+    /// Bytes of input data sent to the controller
+    pub input_length: u16,
+
+    /// Bytes of output data received from the controller
+    pub output_length: u16,
+}
+
+impl Gsdml {
+    /// Read and parse a GSDML file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Io`] if the file cannot be read and
+    /// [`PlcError::Config`] if it is not a valid GSDML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let xml = std::fs::read_to_string(path)?;
+        Self::parse(&xml)
+    }
+
+    /// Parse the device identity, access points and modules of a GSDML file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for malformed XML, missing ident numbers
+    /// or IO data items of unsupported types.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(xml)
+            .map_err(|e| PlcError::Config(format!("Invalid GSDML: {e}")))?;
+        let identity = doc
+            .descendants()
+            .find(|n| n.has_tag_name("DeviceIdentity"))
+            .ok_or_else(|| PlcError::Config("Invalid GSDML: no DeviceIdentity".to_string()))?;
+        let vendor_name = identity
+            .children()
+            .find(|n| n.has_tag_name("VendorName"))
+            .and_then(|n| n.attribute("Value"))
+            .unwrap_or("PETRA")
+            .to_string();
+
+        let mut access_points = HashMap::new();
+        for item in doc.descendants().filter(|n| n.has_tag_name("DeviceAccessPointItem")) {
+            let mut definition = module_definition(item)?;
+            // Interface and port submodules sit at fixed subslots
+            for system in item
+                .descendants()
+                .filter(|n| n.has_tag_name("InterfaceSubmoduleItem") || n.has_tag_name("PortSubmoduleItem"))
+            {
+                definition.submodules.push(SubmoduleDefinition {
+                    subslot: number16(system, "SubslotNumber")?,
+                    ident: number(system, "SubmoduleIdentNumber")?,
+                    input_length: 0,
+                    output_length: 0,
+                });
+            }
+            access_points.insert(item_id(item)?, definition);
+        }
+
+        let mut modules = HashMap::new();
+        for item in doc.descendants().filter(|n| n.has_tag_name("ModuleItem")) {
+            modules.insert(item_id(item)?, module_definition(item)?);
+        }
+
+        Ok(Self {
+            vendor_id: number16(identity, "VendorID")?,
+            device_id: number16(identity, "DeviceID")?,
+            vendor_name,
+            access_points,
+            modules,
+        })
+    }
+}
+
+fn item_id(item: roxmltree::Node<'_, '_>) -> Result<String> {
+    item.attribute("ID").map(str::to_string).ok_or_else(|| {
+        PlcError::Config(format!("Invalid GSDML: <{}> has no ID", item.tag_name().name()))
+    })
+}
+
+fn module_definition(item: roxmltree::Node<'_, '_>) -> Result<ModuleDefinition> {
+    let mut submodules = Vec::new();
+    let virtual_submodules = item.descendants().filter(|n| n.has_tag_name("VirtualSubmoduleItem"));
+    for (subslot, submodule) in (1..).zip(virtual_submodules) {
+        let length = |direction: &str| -> Result<u16> {
+            let mut total = 0u16;
+            let items = submodule
+                .descendants()
+                .filter(|n| n.has_tag_name(direction))
+                .flat_map(|io| io.children().filter(|n| n.has_tag_name("DataItem")));
+            for data_item in items {
+                total = total
+                    .checked_add(data_item_length(data_item)?)
+                    .ok_or_else(|| PlcError::Config("Invalid GSDML: IO data too long".to_string()))?;
+            }
+            Ok(total)
+        };
+        submodules.push(SubmoduleDefinition {
+            subslot,
+            ident: number(submodule, "SubmoduleIdentNumber")?,
+            input_length: length("Input")?,
+            output_length: length("Output")?,
+        });
+    }
+    Ok(ModuleDefinition {
+        ident: number(item, "ModuleIdentNumber")?,
+        submodules,
+    })
+}
+
+fn data_item_length(item: roxmltree::Node<'_, '_>) -> Result<u16> {
+    match item.attribute("DataType").unwrap_or_default() {
+        "Integer8" | "Unsigned8" => Ok(1),
+        "Integer16" | "Unsigned16" => Ok(2),
+        "Integer32" | "Unsigned32" | "Float32" => Ok(4),
+        "Integer64" | "Unsigned64" | "Float64" => Ok(8),
+        "OctetString" | "VisibleString" => number16(item, "Length"),
+        other => Err(PlcError::Config(format!("Invalid GSDML: unsupported data type '{other}'"))),
+    }
+}
+
+/// Read a decimal or `0x` hexadecimal attribute
+fn number(node: roxmltree::Node<'_, '_>, attribute: &str) -> Result<u32> {
+    let text = node.attribute(attribute).ok_or_else(|| {
+        PlcError::Config(format!("Invalid GSDML: <{}> has no {attribute}", node.tag_name().name()))
+    })?;
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| PlcError::Config(format!("Invalid GSDML: {attribute} '{text}' is not a number")))
+}
+
+fn number16(node: roxmltree::Node<'_, '_>, attribute: &str) -> Result<u16> {
+    let value = number(node, attribute)?;
+    u16::try_from(value).map_err(|_| PlcError::Config(format!("Invalid GSDML: {attribute} {value} is out of range")))
+}
+
+// ============================================================================
+// DEVICE MODEL
+// ============================================================================
+
+/// Submodule plugged into the device and its signal mapping
+#[derive(Debug, Clone)]
+struct Submodule {
+    slot: u16,
+    subslot: u16,
+    module_ident: u32,
+    ident: u32,
+    input_length: u16,
+    output_length: u16,
+    inputs: Vec<IoEntry>,
+    outputs: Vec<IoEntry>,
+}
+
+impl Submodule {
+    fn new(slot: u16, module: &ModuleDefinition, definition: &SubmoduleDefinition) -> Self {
+        Self {
+            slot,
+            subslot: definition.subslot,
+            module_ident: module.ident,
+            ident: definition.ident,
+            input_length: definition.input_length,
+            output_length: definition.output_length,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+/// Plug the access point into slot 0 and the configured modules after it
+fn plug_submodules(config: &ProfinetConfig, gsdml: &Gsdml) -> Result<Vec<Submodule>> {
+    let dap = gsdml.access_points.get(&config.device_access_point).ok_or_else(|| {
+        PlcError::Config(format!(
+            "PROFINET device access point '{}' not found in GSDML",
+            config.device_access_point
+        ))
+    })?;
+    let mut submodules: Vec<_> = dap.submodules.iter().map(|d| Submodule::new(0, dap, d)).collect();
+
+    for slot in &config.slots {
+        let module = gsdml.modules.get(&slot.module).ok_or_else(|| {
+            PlcError::Config(format!("PROFINET module '{}' of slot {} not found in GSDML", slot.module, slot.slot))
+        })?;
+        let Some((first, rest)) = module.submodules.split_first() else {
+            return Err(PlcError::Config(format!("PROFINET module '{}' has no submodules", slot.module)));
+        };
+        for (entries, length, direction) in [
+            (&slot.inputs, first.input_length, "input"),
+            (&slot.outputs, first.output_length, "output"),
+        ] {
+            for entry in entries {
+                if usize::from(entry.offset) + entry.data_type.size() > usize::from(length) {
+                    return Err(PlcError::Config(format!(
+                        "PROFINET {direction} '{}' lies outside the {length} bytes of module '{}'",
+                        entry.signal, slot.module
+                    )));
+                }
+            }
+        }
+        let mut submodule = Submodule::new(slot.slot, module, first);
+        submodule.inputs.clone_from(&slot.inputs);
+        submodule.outputs.clone_from(&slot.outputs);
+        submodules.push(submodule);
+        submodules.extend(rest.iter().map(|d| Submodule::new(slot.slot, module, d)));
+    }
+    Ok(submodules)
+}
+
+fn find_submodule(submodules: &[Submodule], slot: u16, subslot: u16) -> Option<&Submodule> {
+    submodules.iter().find(|s| s.slot == slot && s.subslot == subslot)
+}
+
+// ============================================================================
+// FRAMES
+// ============================================================================
+
+const ETHERTYPE: u16 = 0x8892;
+const VLAN_ETHERTYPE: u16 = 0x8100;
+const ETH_HEADER_LEN: usize = 14;
+const MIN_FRAME_LEN: usize = 60;
+
+/// Multicast address of DCP Identify requests
+const DCP_MULTICAST: [u8; 6] = [0x01, 0x0E, 0xCF, 0x00, 0x00, 0x00];
+
+const FRAME_ID_ALARM_HIGH: u16 = 0xFC01;
+const FRAME_ID_ALARM_LOW: u16 = 0xFE01;
+const FRAME_ID_DCP_GET_SET: u16 = 0xFEFD;
+const FRAME_ID_DCP_IDENTIFY: u16 = 0xFEFE;
+const FRAME_ID_DCP_IDENTIFY_RESPONSE: u16 = 0xFEFF;
+
+/// Provider or consumer state of a submodule's data
+const IO_STATE_GOOD: u8 = 0x80;
+const IO_STATE_BAD: u8 = 0x00;
+
+const DATA_STATUS_PRIMARY: u8 = 0x01;
+const DATA_STATUS_VALID: u8 = 0x04;
+const DATA_STATUS_RUN: u8 = 0x10;
+const DATA_STATUS_NO_PROBLEM: u8 = 0x20;
+
+fn len16(len: usize) -> u16 {
+    u16::try_from(len).unwrap_or(u16::MAX)
+}
+
+/// Wrap a payload, starting with its frame ID, in an Ethernet frame
+fn ethernet_frame(dst: [u8; 6], src: [u8; 6], payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity((ETH_HEADER_LEN + payload.len()).max(MIN_FRAME_LEN));
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&ETHERTYPE.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame.resize(frame.len().max(MIN_FRAME_LEN), 0);
+    frame
+}
+
+/// Split a received frame into its source, frame ID and the rest
+///
+/// The rest may carry Ethernet padding after the PDU.
+fn parse_frame(frame: &[u8]) -> Option<([u8; 6], u16, &[u8])> {
+    let mut ethertype_at = 12;
+    if frame.get(12..14)? == VLAN_ETHERTYPE.to_be_bytes() {
+        ethertype_at = 16;
+    }
+    let header = frame.get(ethertype_at..ethertype_at + 4)?;
+    if header[..2] != ETHERTYPE.to_be_bytes() {
+        return None;
+    }
+    let src = frame[6..12].try_into().ok()?;
+    Some((src, u16::from_be_bytes([header[2], header[3]]), &frame[ethertype_at + 4..]))
+}
+
+// ============================================================================
+// DCP
+// ============================================================================
+
+const DCP_GET: u8 = 3;
+const DCP_SET: u8 = 4;
+const DCP_IDENTIFY: u8 = 5;
+const DCP_REQUEST: u8 = 0;
+const DCP_RESPONSE: u8 = 1;
+const DCP_HEADER_LEN: usize = 10;
+
+/// DCP blocks as (option, suboption)
+const DCP_MAC: (u8, u8) = (1, 1);
+const DCP_IP: (u8, u8) = (1, 2);
+const DCP_VENDOR: (u8, u8) = (2, 1);
+const DCP_NAME: (u8, u8) = (2, 2);
+const DCP_DEVICE_ID: (u8, u8) = (2, 3);
+const DCP_ROLE: (u8, u8) = (2, 4);
+const DCP_OPTIONS: (u8, u8) = (2, 5);
+const DCP_START: (u8, u8) = (5, 1);
+const DCP_STOP: (u8, u8) = (5, 2);
+const DCP_SIGNAL: (u8, u8) = (5, 3);
+const DCP_RESULT: (u8, u8) = (5, 4);
+const DCP_ALL: (u8, u8) = (0xFF, 0xFF);
+
+const DCP_ERROR_OPTION: u8 = 0x01;
+const DCP_ERROR_SUBOPTION: u8 = 0x02;
+const DCP_ERROR_LOCAL: u8 = 0x05;
+
+/// Role bit of an IO device
+const DCP_ROLE_DEVICE: u8 = 0x01;
+
+fn dcp_block(out: &mut Vec<u8>, (option, suboption): (u8, u8), value: &[u8]) {
+    out.extend_from_slice(&[option, suboption]);
+    out.extend_from_slice(&len16(value.len()).to_be_bytes());
+    out.extend_from_slice(value);
+    if value.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn dcp_blocks(mut data: &[u8]) -> Vec<((u8, u8), &[u8])> {
+    let mut blocks = Vec::new();
+    while data.len() >= 4 {
+        let len = usize::from(u16::from_be_bytes([data[2], data[3]]));
+        let Some(value) = data.get(4..4 + len) else { break };
+        blocks.push(((data[0], data[1]), value));
+        data = data.get(4 + len + len % 2..).unwrap_or_default();
+    }
+    blocks
+}
+
+// ============================================================================
+// RPC
+// ============================================================================
+
+const RPC_PORT: u16 = 34964;
+const RPC_HEADER_LEN: usize = 80;
+const RPC_REQUEST: u8 = 0;
+const RPC_RESPONSE: u8 = 2;
+const RPC_LAST_FRAGMENT: u8 = 0x02;
+const RPC_FRAGMENT: u8 = 0x04;
+const RPC_NO_FACK: u8 = 0x08;
+const RPC_IDEMPOTENT: u8 = 0x20;
+const RPC_LITTLE_ENDIAN: u8 = 0x10;
+const NDR_HEADER_LEN: usize = 20;
+/// Largest response body accepted from the controller
+const NDR_ARGS_MAXIMUM: u32 = 1024;
+
+/// PROFINET IO device interface, served by the device
+const DEVICE_INTERFACE: Uuid = Uuid::from_u128(0xDEA0_0001_6C97_11D1_8271_00A0_2442_DF7D);
+/// PROFINET IO controller interface, called for application ready
+const CONTROLLER_INTERFACE: Uuid = Uuid::from_u128(0xDEA0_0002_6C97_11D1_8271_00A0_2442_DF7D);
+
+const OP_CONNECT: u16 = 0;
+const OP_RELEASE: u16 = 1;
+const OP_READ: u16 = 2;
+const OP_WRITE: u16 = 3;
+const OP_CONTROL: u16 = 4;
+
+const BLOCK_WRITE_REQ: u16 = 0x0008;
+const BLOCK_WRITE_RES: u16 = 0x8008;
+const BLOCK_AR_REQ: u16 = 0x0101;
+const BLOCK_IOCR_REQ: u16 = 0x0102;
+const BLOCK_ALARM_CR_REQ: u16 = 0x0103;
+const BLOCK_EXPECTED_SUBMODULE_REQ: u16 = 0x0104;
+const BLOCK_PRM_END_REQ: u16 = 0x0110;
+const BLOCK_APPLICATION_READY_REQ: u16 = 0x0112;
+const BLOCK_RELEASE_REQ: u16 = 0x0114;
+const BLOCK_AR_RES: u16 = 0x8101;
+const BLOCK_IOCR_RES: u16 = 0x8102;
+const BLOCK_ALARM_CR_RES: u16 = 0x8103;
+const BLOCK_MODULE_DIFF: u16 = 0x8104;
+const BLOCK_ALARM_HIGH: u16 = 0x0001;
+const BLOCK_ALARM_LOW: u16 = 0x0002;
+/// Response blocks are the request type with the top bit set
+const BLOCK_RESPONSE: u16 = 0x8000;
+
+const CONTROL_PRM_END: u16 = 0x0001;
+const CONTROL_APPLICATION_READY: u16 = 0x0002;
+const CONTROL_DONE: u16 = 0x0008;
+
+const AR_TYPE_SINGLE: u16 = 0x0001;
+const IOCR_INPUT: u16 = 0x0001;
+const IOCR_OUTPUT: u16 = 0x0002;
+const ALARM_CR_TYPE: u16 = 0x0001;
+const INDEX_WRITE_MULTIPLE: u16 = 0xE040;
+/// Size of write request and response headers, including the block header
+const WRITE_HEADER_LEN: usize = 64;
+
+const MODULE_NONE: u16 = 0;
+const MODULE_WRONG: u16 = 1;
+const MODULE_PROPER: u16 = 2;
+const SUBMODULE_WRONG: u16 = 0x9000;
+const SUBMODULE_NONE: u16 = 0x9800;
+
+/// Status of a successful call
+const PNIO_OK: [u8; 4] = [0; 4];
+/// Connect refused while another controller holds the connection
+const PNIO_CONNECT_BUSY: [u8; 4] = [0xDB, 0x81, 0x40, 0x04];
+/// Release naming an unknown connection
+const PNIO_RELEASE_UNKNOWN: [u8; 4] = [0xDC, 0x81, 0x40, 0x05];
+/// Control call naming an unknown connection
+const PNIO_CONTROL_UNKNOWN: [u8; 4] = [0xDD, 0x81, 0x40, 0x05];
+/// Read of a record the device does not provide
+const PNIO_READ_INVALID_INDEX: [u8; 4] = [0xDE, 0x80, 0xB0, 0x00];
+/// Write outside an established connection
+const PNIO_WRITE_STATE_CONFLICT: [u8; 4] = [0xDF, 0x80, 0xB5, 0x00];
+
+/// Connect refused for a faulty field of a request block
+const fn pnio_connect_error(block: u8, field: u8) -> [u8; 4] {
+    [0xDB, 0x81, block, field]
+}
+
+/// Header of a connectionless DCE/RPC packet
+#[derive(Debug, Clone)]
+struct RpcHeader {
+    packet_type: u8,
+    flags: u8,
+    little_endian: bool,
+    object: Uuid,
+    interface: Uuid,
+    activity: Uuid,
+    interface_version: u32,
+    sequence: u32,
+    opnum: u16,
+}
+
+impl RpcHeader {
+    /// Split a datagram into its header and body
+    fn parse(datagram: &[u8]) -> Result<(Self, &[u8])> {
+        let invalid = |reason: &str| PlcError::Runtime(format!("Invalid PROFINET RPC packet: {reason}"));
+        if datagram.len() < RPC_HEADER_LEN || datagram[0] != 4 {
+            return Err(invalid("not a DCE/RPC packet"));
+        }
+        let little_endian = datagram[4] & RPC_LITTLE_ENDIAN != 0;
+        let u16_at = |at: usize| {
+            let bytes = [datagram[at], datagram[at + 1]];
+            if little_endian {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_be_bytes(bytes)
+            }
+        };
+        let u32_at = |at: usize| {
+            let bytes = [datagram[at], datagram[at + 1], datagram[at + 2], datagram[at + 3]];
+            if little_endian {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            }
+        };
+        let uuid_at = |at: usize| {
+            let mut tail = [0u8; 8];
+            tail.copy_from_slice(&datagram[at + 8..at + 16]);
+            Uuid::from_fields(u32_at(at), u16_at(at + 4), u16_at(at + 6), &tail)
+        };
+        let body_len = usize::from(u16_at(74));
+        let body = datagram
+            .get(RPC_HEADER_LEN..RPC_HEADER_LEN + body_len)
+            .ok_or_else(|| invalid("truncated body"))?;
+        let header = Self {
+            packet_type: datagram[1],
+            flags: datagram[2],
+            little_endian,
+            object: uuid_at(8),
+            interface: uuid_at(24),
+            activity: uuid_at(40),
+            interface_version: u32_at(60),
+            sequence: u32_at(64),
+            opnum: u16_at(68),
+        };
+        Ok((header, body))
+    }
+
+    /// Encode the header, little-endian, followed by `body`
+    fn encode(&self, server_boot: u32, body: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(RPC_HEADER_LEN + body.len());
+        packet.extend_from_slice(&[4, self.packet_type, self.flags, 0, RPC_LITTLE_ENDIAN, 0, 0, 0]);
+        for uuid in [self.object, self.interface, self.activity] {
+            let (d1, d2, d3, d4) = uuid.as_fields();
+            packet.extend_from_slice(&d1.to_le_bytes());
+            packet.extend_from_slice(&d2.to_le_bytes());
+            packet.extend_from_slice(&d3.to_le_bytes());
+            packet.extend_from_slice(d4);
+        }
+        packet.extend_from_slice(&server_boot.to_le_bytes());
+        packet.extend_from_slice(&self.interface_version.to_le_bytes());
+        packet.extend_from_slice(&self.sequence.to_le_bytes());
+        packet.extend_from_slice(&self.opnum.to_le_bytes());
+        // No interface or activity hints
+        packet.extend_from_slice(&[0xFF; 4]);
+        packet.extend_from_slice(&len16(body.len()).to_le_bytes());
+        // Fragment number, authentication protocol, serial number
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+}
+
+/// Split a request body into its args maximum and PROFINET blocks
+fn ndr_request<'a>(header: &RpcHeader, body: &'a [u8]) -> Option<(u32, &'a [u8])> {
+    if body.len() < NDR_HEADER_LEN {
+        return None;
+    }
+    let args_maximum = header.read_u32(&body[0..4]);
+    let args_length = usize::try_from(header.read_u32(&body[4..8])).ok()?;
+    Some((args_maximum, body.get(NDR_HEADER_LEN..NDR_HEADER_LEN + args_length)?))
+}
+
+fn ndr_request_body(blocks: &[u8]) -> Vec<u8> {
+    let length = u32::try_from(blocks.len()).unwrap_or(u32::MAX);
+    let mut body = Vec::with_capacity(NDR_HEADER_LEN + blocks.len());
+    for field in [NDR_ARGS_MAXIMUM, length, NDR_ARGS_MAXIMUM, 0, length] {
+        body.extend_from_slice(&field.to_le_bytes());
+    }
+    body.extend_from_slice(blocks);
+    body
+}
+
+fn ndr_response_body(status: [u8; 4], args_maximum: u32, blocks: &[u8]) -> Vec<u8> {
+    let length = u32::try_from(blocks.len()).unwrap_or(u32::MAX);
+    let mut body = Vec::with_capacity(NDR_HEADER_LEN + blocks.len());
+    body.extend_from_slice(&status);
+    for field in [length, args_maximum, 0, length] {
+        body.extend_from_slice(&field.to_le_bytes());
+    }
+    body.extend_from_slice(blocks);
+    body
+}
+
+/// Cursor over big-endian block fields
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(PlcError::Runtime("Truncated PROFINET block".to_string()));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn uuid(&mut self) -> Result<Uuid> {
+        Ok(Uuid::from_slice(self.take(16)?).unwrap_or_default())
+    }
+
+    fn mac(&mut self) -> Result<[u8; 6]> {
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(self.take(6)?);
+        Ok(mac)
+    }
+}
+
+/// Split a sequence of blocks into their types and bodies after the version
+fn blocks(mut data: &[u8]) -> Result<Vec<(u16, &[u8])>> {
+    let mut blocks = Vec::new();
+    while data.len() >= 6 {
+        let kind = u16::from_be_bytes([data[0], data[1]]);
+        let end = 4 + usize::from(u16::from_be_bytes([data[2], data[3]]));
+        let body = data
+            .get(6..end)
+            .ok_or_else(|| PlcError::Runtime(format!("Truncated PROFINET block 0x{kind:04X}")))?;
+        blocks.push((kind, body));
+        data = &data[end..];
+    }
+    Ok(blocks)
+}
+
+/// Encode a block with version 1.0
+fn block(kind: u16, body: &[u8]) -> Vec<u8> {
+    let mut block = Vec::with_capacity(6 + body.len());
+    block.extend_from_slice(&kind.to_be_bytes());
+    block.extend_from_slice(&len16(body.len() + 2).to_be_bytes());
+    block.extend_from_slice(&[1, 0]);
+    block.extend_from_slice(body);
+    block
+}
+
+/// Control, application ready and release blocks share one layout
+fn control_block(kind: u16, ar_uuid: Uuid, session_key: u16, command: u16) -> Vec<u8> {
+    let mut body = vec![0, 0];
+    body.extend_from_slice(ar_uuid.as_bytes());
+    body.extend_from_slice(&session_key.to_be_bytes());
+    body.extend_from_slice(&[0, 0]);
+    body.extend_from_slice(&command.to_be_bytes());
+    body.extend_from_slice(&[0, 0]);
+    block(kind, &body)
+}
+
+/// Read the type, AR UUID, session key and command of a control block
+fn parse_control(data: &[u8]) -> Option<(u16, Uuid, u16, u16)> {
+    let (kind, body) = *blocks(data).ok()?.first()?;
+    let mut r = Reader { data: body };
+    r.take(2).ok()?;
+    let ar_uuid = r.uuid().ok()?;
+    let session_key = r.u16().ok()?;
+    r.take(2).ok()?;
+    Some((kind, ar_uuid, session_key, r.u16().ok()?))
+}
+
+/// Connection parameters from the controller's Connect request
+#[derive(Debug)]
+struct ConnectRequest {
+    ar_type: u16,
+    ar_uuid: Uuid,
+    session_key: u16,
+    controller_mac: [u8; 6],
+    controller_object: Uuid,
+    activity_timeout_factor: u16,
+    controller_name: String,
+    iocrs: Vec<Iocr>,
+    alarm_cr: Option<AlarmCrRequest>,
+    expected: Vec<ExpectedSubmodule>,
+}
+
+/// Cyclic IO communication relationship
+#[derive(Debug, Clone)]
+struct Iocr {
+    kind: u16,
+    reference: u16,
+    data_length: u16,
+    frame_id: u16,
+    send_clock_factor: u16,
+    reduction_ratio: u16,
+    watchdog_factor: u16,
+    /// IO data objects as (slot, subslot, frame offset)
+    data: Vec<(u16, u16, u16)>,
+    /// IO consumer states as (slot, subslot, frame offset)
+    iocs: Vec<(u16, u16, u16)>,
+}
+
+#[derive(Debug)]
+struct AlarmCrRequest {
+    controller_reference: u16,
+    timeout_factor: u16,
+    retries: u16,
+    max_data_length: u16,
+}
+
+/// Submodule the controller expects in a slot
+#[derive(Debug)]
+struct ExpectedSubmodule {
+    slot: u16,
+    module_ident: u32,
+    subslot: u16,
+    ident: u32,
+    input_length: u16,
+    output_length: u16,
+}
+
+impl ConnectRequest {
+    /// Parse the request blocks, or return the status refusing them
+    fn parse(data: &[u8]) -> std::result::Result<Self, [u8; 4]> {
+        let mut request = Self {
+            ar_type: 0,
+            ar_uuid: Uuid::nil(),
+            session_key: 0,
+            controller_mac: [0; 6],
+            controller_object: Uuid::nil(),
+            activity_timeout_factor: 0,
+            controller_name: String::new(),
+            iocrs: Vec::new(),
+            alarm_cr: None,
+            expected: Vec::new(),
+        };
+        let mut has_ar = false;
+        for (kind, body) in blocks(data).map_err(|_| pnio_connect_error(0x01, 1))? {
+            let mut r = Reader { data: body };
+            match kind {
+                BLOCK_AR_REQ => {
+                    request.parse_ar(&mut r).map_err(|_| pnio_connect_error(0x01, 1))?;
+                    has_ar = true;
+                }
+                BLOCK_IOCR_REQ => {
+                    request.iocrs.push(Iocr::parse(&mut r).map_err(|_| pnio_connect_error(0x02, 1))?);
+                }
+                BLOCK_ALARM_CR_REQ => {
+                    request.alarm_cr =
+                        Some(AlarmCrRequest::parse(&mut r).map_err(|_| pnio_connect_error(0x04, 1))?);
+                }
+                BLOCK_EXPECTED_SUBMODULE_REQ => {
+                    parse_expected(&mut r, &mut request.expected).map_err(|_| pnio_connect_error(0x03, 1))?;
+                }
+                other => debug!("Ignoring PROFINET connect block 0x{:04X}", other),
+            }
+        }
+        if !has_ar {
+            return Err(pnio_connect_error(0x01, 0));
+        }
+        Ok(request)
+    }
+
+    fn parse_ar(&mut self, r: &mut Reader<'_>) -> Result<()> {
+        self.ar_type = r.u16()?;
+        self.ar_uuid = r.uuid()?;
+        self.session_key = r.u16()?;
+        self.controller_mac = r.mac()?;
+        self.controller_object = r.uuid()?;
+        let _properties = r.u32()?;
+        self.activity_timeout_factor = r.u16()?;
+        let _udp_port = r.u16()?;
+        let name_len = usize::from(r.u16()?);
+        self.controller_name = String::from_utf8_lossy(r.take(name_len)?).into_owned();
+        Ok(())
+    }
+}
+
+impl Iocr {
+    fn parse(r: &mut Reader<'_>) -> Result<Self> {
+        let kind = r.u16()?;
+        let reference = r.u16()?;
+        let _lt = r.u16()?;
+        let _properties = r.u32()?;
+        let data_length = r.u16()?;
+        let frame_id = r.u16()?;
+        let send_clock_factor = r.u16()?;
+        let reduction_ratio = r.u16()?;
+        let _phase = r.u16()?;
+        let _sequence = r.u16()?;
+        let _frame_send_offset = r.u32()?;
+        let watchdog_factor = r.u16()?;
+        let _data_hold_factor = r.u16()?;
+        let _tag_header = r.u16()?;
+        let _multicast_mac = r.mac()?;
+        let mut iocr = Self {
+            kind,
+            reference,
+            data_length,
+            frame_id,
+            send_clock_factor,
+            reduction_ratio,
+            watchdog_factor,
+            data: Vec::new(),
+            iocs: Vec::new(),
+        };
+        for _ in 0..r.u16()? {
+            let _api = r.u32()?;
+            for list in [&mut iocr.data, &mut iocr.iocs] {
+                for _ in 0..r.u16()? {
+                    list.push((r.u16()?, r.u16()?, r.u16()?));
+                }
+            }
+        }
+        Ok(iocr)
+    }
+
+    /// Time between frames, in units of 31.25 µs
+    fn period(&self) -> Duration {
+        Duration::from_nanos(u64::from(self.send_clock_factor) * u64::from(self.reduction_ratio) * 31_250)
+    }
+}
+
+impl AlarmCrRequest {
+    fn parse(r: &mut Reader<'_>) -> Result<Self> {
+        let _alarm_cr_type = r.u16()?;
+        let _lt = r.u16()?;
+        let _properties = r.u32()?;
+        let timeout_factor = r.u16()?;
+        let retries = r.u16()?;
+        let controller_reference = r.u16()?;
+        let max_data_length = r.u16()?;
+        Ok(Self {
+            controller_reference,
+            timeout_factor,
+            retries,
+            max_data_length,
+        })
+    }
+}
+
+fn parse_expected(r: &mut Reader<'_>, expected: &mut Vec<ExpectedSubmodule>) -> Result<()> {
+    for _ in 0..r.u16()? {
+        let _api = r.u32()?;
+        let slot = r.u16()?;
+        let module_ident = r.u32()?;
+        let _module_properties = r.u16()?;
+        for _ in 0..r.u16()? {
+            let subslot = r.u16()?;
+            let ident = r.u32()?;
+            let properties = r.u16()?;
+            let mut submodule = ExpectedSubmodule {
+                slot,
+                module_ident,
+                subslot,
+                ident,
+                input_length: 0,
+                output_length: 0,
+            };
+            // Input and output submodules describe both directions
+            let descriptions = if properties & 0x03 == 0x03 { 2 } else { 1 };
+            for _ in 0..descriptions {
+                let direction = r.u16()?;
+                let length = r.u16()?;
+                let _iocs_length = r.u8()?;
+                let _iops_length = r.u8()?;
+                if direction == IOCR_OUTPUT {
+                    submodule.output_length = length;
+                } else {
+                    submodule.input_length = length;
+                }
+            }
+            expected.push(submodule);
+        }
+    }
+    Ok(())
+}
+
+/// Parsed header of a record write request
+struct WriteRequest {
+    sequence: u16,
+    ar_uuid: Uuid,
+    api: u32,
+    slot: u16,
+    subslot: u16,
+    index: u16,
+    length: u32,
+}
+
+impl WriteRequest {
+    fn parse(r: &mut Reader<'_>) -> Result<Self> {
+        if r.u16()? != BLOCK_WRITE_REQ {
+            return Err(PlcError::Runtime("Expected a PROFINET write request".to_string()));
+        }
+        r.take(4)?;
+        let sequence = r.u16()?;
+        let ar_uuid = r.uuid()?;
+        let api = r.u32()?;
+        let slot = r.u16()?;
+        let subslot = r.u16()?;
+        r.take(2)?;
+        let index = r.u16()?;
+        let length = r.u32()?;
+        r.take(24)?;
+        Ok(Self {
+            sequence,
+            ar_uuid,
+            api,
+            slot,
+            subslot,
+            index,
+            length,
+        })
+    }
+
+    fn response(&self, status: [u8; 4], length: u32) -> Vec<u8> {
+        let mut body = Vec::with_capacity(WRITE_HEADER_LEN);
+        body.extend_from_slice(&self.sequence.to_be_bytes());
+        body.extend_from_slice(self.ar_uuid.as_bytes());
+        body.extend_from_slice(&self.api.to_be_bytes());
+        body.extend_from_slice(&self.slot.to_be_bytes());
+        body.extend_from_slice(&self.subslot.to_be_bytes());
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(&self.index.to_be_bytes());
+        body.extend_from_slice(&length.to_be_bytes());
+        // Additional values
+        body.extend_from_slice(&[0; 4]);
+        body.extend_from_slice(&status);
+        body.extend_from_slice(&[0; 16]);
+        block(BLOCK_WRITE_RES, &body)
+    }
+}
+
+// ============================================================================
+// ALARMS
+// ============================================================================
+
+const RTA_DATA: u8 = 0x11;
+const RTA_ACK: u8 = 0x13;
+const RTA_TYPE_MASK: u8 = 0x0F;
+const RTA_TYPE_DATA: u8 = 0x01;
+const RTA_TYPE_ERR: u8 = 0x04;
+/// Window size 1, with TACK on data so every PDU is acknowledged
+const RTA_FLAGS_DATA: u8 = 0x11;
+const RTA_FLAGS_ACK: u8 = 0x01;
+
+/// Local alarm reference of the device's alarm CR
+const DEVICE_ALARM_REFERENCE: u16 = 0x0001;
+
+const ALARM_TYPE_DIAGNOSIS: u16 = 0x0001;
+const ALARM_TYPE_PROCESS: u16 = 0x0002;
+const ALARM_TYPE_DIAGNOSIS_DISAPPEARS: u16 = 0x000C;
+const ALARM_CHANNEL_DIAGNOSIS: u16 = 0x0800;
+const ALARM_SUBMODULE_DIAGNOSIS: u16 = 0x2000;
+const ALARM_AR_DIAGNOSIS: u16 = 0x8000;
+/// User structure identifier of channel diagnosis data
+const USI_CHANNEL_DIAGNOSIS: u16 = 0x8000;
+/// Channel number covering the whole submodule
+const CHANNEL_SUBMODULE: u16 = 0x8000;
+const CHANNEL_APPEARS: u16 = 0x0800;
+const CHANNEL_DISAPPEARS: u16 = 0x1000;
+
+/// Next RTA sequence number; 0xFFFF is the initial value before 0
+const fn next_sequence(sequence: u16) -> u16 {
+    if sequence >= 0x7FFF {
+        0
+    } else {
+        sequence + 1
+    }
+}
+
+/// Encode an RTA PDU, starting with its frame ID
+fn rta_pdu(frame_id: u16, controller_reference: u16, pdu_type: u8, send_seq: u16, ack_seq: u16, var: &[u8]) -> Vec<u8> {
+    let flags = if pdu_type == RTA_DATA { RTA_FLAGS_DATA } else { RTA_FLAGS_ACK };
+    let mut pdu = Vec::with_capacity(14 + var.len());
+    pdu.extend_from_slice(&frame_id.to_be_bytes());
+    pdu.extend_from_slice(&controller_reference.to_be_bytes());
+    pdu.extend_from_slice(&DEVICE_ALARM_REFERENCE.to_be_bytes());
+    pdu.extend_from_slice(&[pdu_type, flags]);
+    pdu.extend_from_slice(&send_seq.to_be_bytes());
+    pdu.extend_from_slice(&ack_seq.to_be_bytes());
+    pdu.extend_from_slice(&len16(var.len()).to_be_bytes());
+    pdu.extend_from_slice(var);
+    pdu
+}
+
+/// Alarms of one priority and their RTA sequence state
+struct AlarmQueue {
+    frame_id: u16,
+    /// Last sequence number sent
+    send_seq: u16,
+    /// Last sequence number received
+    ack_seq: u16,
+    /// Sequence number in the alarm specifier, 0 to 0x7FF
+    alarm_seq: u16,
+    queue: VecDeque<Vec<u8>>,
+    pending: Option<PendingAlarm>,
+}
+
+/// Alarm sent and not yet acknowledged
+struct PendingAlarm {
+    frame: Vec<u8>,
+    sent: Instant,
+    attempts: u16,
+}
+
+impl AlarmQueue {
+    const fn new(frame_id: u16) -> Self {
+        Self {
+            frame_id,
+            send_seq: 0xFFFF,
+            ack_seq: 0xFFFE,
+            alarm_seq: 0,
+            queue: VecDeque::new(),
+            pending: None,
+        }
+    }
+}
+
+/// Alarm communication relationship of a connection
+struct AlarmCr {
+    controller_reference: u16,
+    timeout: Duration,
+    retries: u16,
+    high: AlarmQueue,
+    low: AlarmQueue,
+    /// Last level of every alarm signal, set once the connection runs
+    levels: Option<HashMap<String, bool>>,
+}
+
+impl AlarmCr {
+    /// Frames to send now: queued alarms and retransmissions
+    ///
+    /// Returns `None` once an alarm has gone unacknowledged for all retries.
+    fn transmit(&mut self, dst: [u8; 6], src: [u8; 6], now: Instant) -> Option<Vec<Vec<u8>>> {
+        let mut frames = Vec::new();
+        for queue in [&mut self.high, &mut self.low] {
+            if let Some(pending) = &mut queue.pending {
+                if now.duration_since(pending.sent) >= self.timeout {
+                    if pending.attempts > self.retries {
+                        return None;
+                    }
+                    pending.attempts += 1;
+                    pending.sent = now;
+                    frames.push(pending.frame.clone());
+                }
+                continue;
+            }
+            let Some(notification) = queue.queue.pop_front() else {
+                continue;
+            };
+            queue.send_seq = next_sequence(queue.send_seq);
+            let pdu = rta_pdu(
+                queue.frame_id,
+                self.controller_reference,
+                RTA_DATA,
+                queue.send_seq,
+                queue.ack_seq,
+                &notification,
+            );
+            let frame = ethernet_frame(dst, src, &pdu);
+            queue.pending = Some(PendingAlarm {
+                frame: frame.clone(),
+                sent: now,
+                attempts: 1,
+            });
+            frames.push(frame);
+        }
+        Some(frames)
+    }
+}
+
+/// Encode an alarm notification block for `submodule`
+fn alarm_notification(
+    submodule: &Submodule,
+    alarm: &AlarmConfig,
+    appears: bool,
+    queue: &mut AlarmQueue,
+    diagnosis: (bool, bool),
+) -> Vec<u8> {
+    let (submodule_diagnosis, ar_diagnosis) = diagnosis;
+    let sequence = queue.alarm_seq;
+    queue.alarm_seq = (queue.alarm_seq + 1) & 0x07FF;
+
+    let (alarm_type, mut specifier) = match alarm.kind {
+        AlarmKind::Process => (ALARM_TYPE_PROCESS, sequence),
+        AlarmKind::Diagnosis if appears || submodule_diagnosis => {
+            (ALARM_TYPE_DIAGNOSIS, sequence | ALARM_CHANNEL_DIAGNOSIS)
+        }
+        AlarmKind::Diagnosis => (ALARM_TYPE_DIAGNOSIS_DISAPPEARS, sequence | ALARM_CHANNEL_DIAGNOSIS),
+    };
+    if submodule_diagnosis {
+        specifier |= ALARM_SUBMODULE_DIAGNOSIS;
+    }
+    if ar_diagnosis {
+        specifier |= ALARM_AR_DIAGNOSIS;
+    }
+
+    let mut body = Vec::with_capacity(32);
+    body.extend_from_slice(&alarm_type.to_be_bytes());
+    body.extend_from_slice(&0u32.to_be_bytes());
+    body.extend_from_slice(&submodule.slot.to_be_bytes());
+    body.extend_from_slice(&submodule.subslot.to_be_bytes());
+    body.extend_from_slice(&submodule.module_ident.to_be_bytes());
+    body.extend_from_slice(&submodule.ident.to_be_bytes());
+    body.extend_from_slice(&specifier.to_be_bytes());
+    if alarm.kind == AlarmKind::Diagnosis {
+        let properties = if appears { CHANNEL_APPEARS } else { CHANNEL_DISAPPEARS };
+        for field in [USI_CHANNEL_DIAGNOSIS, CHANNEL_SUBMODULE, properties, alarm.error_type] {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+    }
+    let kind = if queue.frame_id == FRAME_ID_ALARM_HIGH { BLOCK_ALARM_HIGH } else { BLOCK_ALARM_LOW };
+    block(kind, &body)
+}
+
+// ============================================================================
+// DEVICE
+// ============================================================================
+
+/// Time between checks of the sockets and the update cycle
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+const APPLICATION_READY_TIMEOUT: Duration = Duration::from_secs(1);
+const APPLICATION_READY_ATTEMPTS: u32 = 3;
+
+/// Message produced by the device
+#[derive(Debug)]
+enum Outgoing {
+    /// Raw Ethernet frame
+    Frame(Vec<u8>),
+    /// RPC response to a request received from the address
+    Reply(SocketAddr, Vec<u8>),
+    /// RPC request to the controller
+    Request(SocketAddr, Vec<u8>),
+}
+
+/// Progress of an established connection
+enum ArState {
+    /// Waiting for the controller to finish writing parameters
+    Parameterizing,
+    /// Application ready sent, waiting for the controller to confirm
+    ApplicationReady {
+        activity: Uuid,
+        request: Vec<u8>,
+        sent: Instant,
+        attempts: u32,
+    },
+    /// Exchanging data and alarms
+    Running,
+}
+
+/// Connection (AR) with the controller
+struct Ar {
+    uuid: Uuid,
+    session_key: u16,
+    controller_mac: [u8; 6],
+    controller: SocketAddr,
+    controller_object: Uuid,
+    input: Iocr,
+    output: Iocr,
+    alarms: AlarmCr,
+    /// Submodules matching the controller's expectation, as (slot, subslot)
+    accepted: HashSet<(u16, u16)>,
+    state: ArState,
+    /// Input data, kept between cycles
+    input_data: Vec<u8>,
+    /// Last output data received
+    output_data: Vec<u8>,
+    cycle_counter: u16,
+    next_send: Instant,
+    last_output: Option<Instant>,
+    /// Time allowed for the first output frame
+    startup_deadline: Instant,
+}
+
+impl Ar {
+    /// Pack the input data of all accepted submodules into a frame
+    fn input_frame(&mut self, submodules: &[Submodule], mac: [u8; 6], bus: &SignalBus) -> Vec<u8> {
+        for &(slot, subslot, offset) in &self.input.data {
+            let start = usize::from(offset);
+            let submodule = find_submodule(submodules, slot, subslot);
+            let good = submodule.is_some() && self.accepted.contains(&(slot, subslot));
+            let length = submodule.map_or(0, |s| usize::from(s.input_length));
+            if let Some(submodule) = submodule.filter(|_| good) {
+                for entry in &submodule.inputs {
+                    if let Some(value) = bus.get(&entry.signal) {
+                        let at = start + usize::from(entry.offset);
+                        entry.data_type.encode(&value, &mut self.input_data[at..], entry.bit);
+                    }
+                }
+            }
+            self.input_data[start + length] = if good { IO_STATE_GOOD } else { IO_STATE_BAD };
+        }
+        for &(slot, subslot, offset) in &self.input.iocs {
+            let good = self.accepted.contains(&(slot, subslot));
+            self.input_data[usize::from(offset)] = if good { IO_STATE_GOOD } else { IO_STATE_BAD };
+        }
+
+        let step = self.input.send_clock_factor.wrapping_mul(self.input.reduction_ratio);
+        self.cycle_counter = self.cycle_counter.wrapping_add(step);
+        let mut payload = Vec::with_capacity(self.input_data.len() + 6);
+        payload.extend_from_slice(&self.input.frame_id.to_be_bytes());
+        payload.extend_from_slice(&self.input_data);
+        payload.extend_from_slice(&self.cycle_counter.to_be_bytes());
+        payload.push(DATA_STATUS_PRIMARY | DATA_STATUS_VALID | DATA_STATUS_RUN | DATA_STATUS_NO_PROBLEM);
+        payload.push(0);
+        ethernet_frame(self.controller_mac, mac, &payload)
+    }
+
+    /// Write changed output data of accepted submodules to their signals
+    fn consume_outputs(&mut self, submodules: &[Submodule], payload: &[u8], bus: &SignalBus, now: Instant) {
+        let length = usize::from(self.output.data_length);
+        let (Some(data), Some(&data_status)) = (payload.get(..length), payload.get(length + 2)) else {
+            return;
+        };
+        self.last_output = Some(now);
+        if data_status & DATA_STATUS_VALID == 0 || data_status & DATA_STATUS_RUN == 0 {
+            return;
+        }
+        for &(slot, subslot, offset) in &self.output.data {
+            let Some(submodule) = find_submodule(submodules, slot, subslot) else {
+                continue;
+            };
+            if submodule.outputs.is_empty() || !self.accepted.contains(&(slot, subslot)) {
+                continue;
+            }
+            let start = usize::from(offset);
+            let end = start + usize::from(submodule.output_length);
+            // Includes the provider state, so data turning good is written
+            let Some(current) = data.get(start..=end) else {
+                continue;
+            };
+            if current[current.len() - 1] & IO_STATE_GOOD == 0 || self.output_data.get(start..=end) == Some(current) {
+                continue;
+            }
+            for entry in &submodule.outputs {
+                let value = entry.data_type.decode(&current[usize::from(entry.offset)..], entry.bit);
+                if let Err(e) = bus.set(&entry.signal, value) {
+                    warn!("Failed to write PROFINET output '{}': {}", entry.signal, e);
+                }
+            }
+        }
+        self.output_data.clear();
+        self.output_data.extend_from_slice(data);
+    }
+
+    /// Build the application ready request for the controller
+    fn application_ready(&self, server_boot: u32) -> (Uuid, Vec<u8>) {
+        let activity = Uuid::new_v4();
+        let header = RpcHeader {
+            packet_type: RPC_REQUEST,
+            flags: RPC_LAST_FRAGMENT | RPC_NO_FACK | RPC_IDEMPOTENT,
+            little_endian: true,
+            object: self.controller_object,
+            interface: CONTROLLER_INTERFACE,
+            activity,
+            interface_version: 1,
+            sequence: 0,
+            opnum: OP_CONTROL,
+        };
+        let block = control_block(
+            BLOCK_APPLICATION_READY_REQ,
+            self.uuid,
+            self.session_key,
+            CONTROL_APPLICATION_READY,
+        );
+        (activity, header.encode(server_boot, &ndr_request_body(&block)))
+    }
+}
+
+/// PROFINET IO device state: identity, plugged modules and connection
+pub struct ProfinetDevice {
+    interface: String,
+    station_name: String,
+    vendor_name: String,
+    vendor_id: u16,
+    device_id: u16,
+    mac: [u8; 6],
+    ip: Option<(Ipv4Addr, Ipv4Addr)>,
+    submodules: Vec<Submodule>,
+    alarms: Vec<AlarmConfig>,
+    server_boot: u32,
+    ar: Option<Ar>,
+    /// Last RPC response, resent if the controller repeats its request
+    last_reply: Option<(Uuid, u32, Vec<u8>)>,
+}
+
+impl ProfinetDevice {
+    /// Plug the configured modules of `gsdml` into a new device
+    ///
+    /// `mac` and `ip` are the interface's addresses, reported to DCP.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid or names
+    /// modules missing from the GSDML file.
+    pub fn new(
+        config: ProfinetConfig,
+        gsdml: &Gsdml,
+        mac: [u8; 6],
+        ip: Option<(Ipv4Addr, Ipv4Addr)>,
+    ) -> Result<Self> {
+        config.validate()?;
+        let submodules = plug_submodules(&config, gsdml)?;
+        let server_boot = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .and_then(|t| u32::try_from(t.as_secs()).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            interface: config.interface,
+            station_name: config.station_name,
+            vendor_name: gsdml.vendor_name.clone(),
+            vendor_id: gsdml.vendor_id,
+            device_id: gsdml.device_id,
+            mac,
+            ip,
+            submodules,
+            alarms: config.alarms,
+            server_boot,
+            ar: None,
+            last_reply: None,
+        })
+    }
+
+    /// Handle a received frame, returning the frame to answer with
+    fn handle_frame(&mut self, frame: &[u8], bus: &SignalBus, now: Instant) -> Option<Vec<u8>> {
+        let (src, frame_id, payload) = parse_frame(frame)?;
+        match frame_id {
+            FRAME_ID_DCP_IDENTIFY | FRAME_ID_DCP_GET_SET => self.handle_dcp(src, frame_id, payload),
+            FRAME_ID_ALARM_HIGH | FRAME_ID_ALARM_LOW => self.handle_rta(frame_id, payload),
+            _ => {
+                let ar = self.ar.as_mut()?;
+                if ar.output.frame_id == frame_id && ar.controller_mac == src {
+                    ar.consume_outputs(&self.submodules, payload, bus, now);
+                }
+                None
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // DCP
+    // ------------------------------------------------------------------
+
+    fn handle_dcp(&mut self, src: [u8; 6], frame_id: u16, payload: &[u8]) -> Option<Vec<u8>> {
+        let header = payload.get(..DCP_HEADER_LEN)?;
+        let (service, service_type) = (header[0], header[1]);
+        if service_type != DCP_REQUEST {
+            return None;
+        }
+        let data_len = usize::from(u16::from_be_bytes([header[8], header[9]]));
+        let data = payload.get(DCP_HEADER_LEN..DCP_HEADER_LEN + data_len)?;
+        let (response_id, blocks) = match (frame_id, service) {
+            (FRAME_ID_DCP_IDENTIFY, DCP_IDENTIFY) => {
+                if !self.identify_matches(data) {
+                    return None;
+                }
+                let mut blocks = Vec::new();
+                for kind in [DCP_NAME, DCP_VENDOR, DCP_DEVICE_ID, DCP_ROLE, DCP_OPTIONS, DCP_IP] {
+                    dcp_block(&mut blocks, kind, &self.dcp_value(kind).unwrap_or_default());
+                }
+                (FRAME_ID_DCP_IDENTIFY_RESPONSE, blocks)
+            }
+            (FRAME_ID_DCP_GET_SET, DCP_GET) => (FRAME_ID_DCP_GET_SET, self.dcp_get(data)),
+            (FRAME_ID_DCP_GET_SET, DCP_SET) => (FRAME_ID_DCP_GET_SET, self.dcp_set(data)),
+            _ => return None,
+        };
+
+        let mut response = Vec::with_capacity(2 + DCP_HEADER_LEN + blocks.len());
+        response.extend_from_slice(&response_id.to_be_bytes());
+        response.extend_from_slice(&[service, DCP_RESPONSE]);
+        // Transaction ID
+        response.extend_from_slice(&header[2..6]);
+        response.extend_from_slice(&[0, 0]);
+        response.extend_from_slice(&len16(blocks.len()).to_be_bytes());
+        response.extend_from_slice(&blocks);
+        Some(ethernet_frame(src, self.mac, &response))
+    }
+
+    fn identify_matches(&self, filter: &[u8]) -> bool {
+        dcp_blocks(filter).into_iter().all(|(kind, value)| match kind {
+            DCP_ALL => true,
+            DCP_NAME => value == self.station_name.as_bytes(),
+            DCP_DEVICE_ID => value == [self.vendor_id.to_be_bytes(), self.device_id.to_be_bytes()].concat(),
+            _ => false,
+        })
+    }
+
+    /// Block info followed by the value of a readable DCP block
+    fn dcp_value(&self, kind: (u8, u8)) -> Option<Vec<u8>> {
+        let mut value = vec![0, 0];
+        match kind {
+            DCP_MAC => value.extend_from_slice(&self.mac),
+            DCP_IP => {
+                let (ip, netmask) = self.ip.unwrap_or((Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED));
+                value[1] = u8::from(self.ip.is_some());
+                value.extend_from_slice(&ip.octets());
+                value.extend_from_slice(&netmask.octets());
+                value.extend_from_slice(&Ipv4Addr::UNSPECIFIED.octets());
+            }
+            DCP_VENDOR => value.extend_from_slice(self.vendor_name.as_bytes()),
+            DCP_NAME => value.extend_from_slice(self.station_name.as_bytes()),
+            DCP_DEVICE_ID => {
+                value.extend_from_slice(&self.vendor_id.to_be_bytes());
+                value.extend_from_slice(&self.device_id.to_be_bytes());
+            }
+            DCP_ROLE => value.extend_from_slice(&[DCP_ROLE_DEVICE, 0]),
+            DCP_OPTIONS => {
+                for (option, suboption) in [
+                    DCP_MAC, DCP_IP, DCP_VENDOR, DCP_NAME, DCP_DEVICE_ID, DCP_ROLE, DCP_OPTIONS, DCP_START,
+                    DCP_STOP, DCP_SIGNAL,
+                ] {
+                    value.extend_from_slice(&[option, suboption]);
+                }
+            }
+            _ => return None,
+        }
+        Some(value)
+    }
+
+    fn dcp_get(&self, data: &[u8]) -> Vec<u8> {
+        let mut blocks = Vec::new();
+        for pair in data.chunks_exact(2) {
+            let kind = (pair[0], pair[1]);
+            if let Some(value) = self.dcp_value(kind) {
+                dcp_block(&mut blocks, kind, &value);
+            } else {
+                let error = if matches!(kind.0, 1 | 2) { DCP_ERROR_SUBOPTION } else { DCP_ERROR_OPTION };
+                dcp_block(&mut blocks, DCP_RESULT, &[kind.0, kind.1, error]);
+            }
+        }
+        blocks
+    }
+
+    fn dcp_set(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut blocks = Vec::new();
+        for (kind, value) in dcp_blocks(data) {
+            // Skip the block qualifier
+            let value = value.get(2..).unwrap_or_default();
+            let error = match kind {
+                DCP_NAME => match std::str::from_utf8(value) {
+                    Ok(name) if is_valid_station_name(name) => {
+                        info!("PROFINET station name on {} set to '{}'", self.interface, name);
+                        self.station_name = name.to_string();
+                        0
+                    }
+                    _ => DCP_ERROR_LOCAL,
+                },
+                DCP_IP => {
+                    let current = self.ip.map(|(ip, netmask)| [ip.octets(), netmask.octets()].concat());
+                    if current.as_deref() == value.get(..8) {
+                        0
+                    } else {
+                        warn!(
+                            "Refusing PROFINET request to change the IP address of {}; configure it on the host",
+                            self.interface
+                        );
+                        DCP_ERROR_LOCAL
+                    }
+                }
+                DCP_START | DCP_STOP => 0,
+                DCP_SIGNAL => {
+                    info!("PROFINET controller asked '{}' to identify itself", self.station_name);
+                    0
+                }
+                (1 | 2 | 5, _) => DCP_ERROR_SUBOPTION,
+                _ => DCP_ERROR_OPTION,
+            };
+            dcp_block(&mut blocks, DCP_RESULT, &[kind.0, kind.1, error]);
+        }
+        blocks
+    }
+
+    // ------------------------------------------------------------------
+    // RPC
+    // ------------------------------------------------------------------
+
+    /// Handle an RPC request or a response to our own request
+    fn handle_rpc(&mut self, datagram: &[u8], from: SocketAddr, now: Instant) -> Vec<Outgoing> {
+        let (header, body) = match RpcHeader::parse(datagram) {
+            Ok(packet) => packet,
+            Err(e) => {
+                debug!("{}", e);
+                return Vec::new();
+            }
+        };
+        match header.packet_type {
+            RPC_REQUEST if header.interface == DEVICE_INTERFACE => self.handle_request(&header, body, from, now),
+            RPC_RESPONSE => {
+                self.handle_response(&header, body);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn handle_request(&mut self, header: &RpcHeader, body: &[u8], from: SocketAddr, now: Instant) -> Vec<Outgoing> {
+        if let Some((activity, sequence, reply)) = &self.last_reply {
+            if *activity == header.activity && *sequence == header.sequence {
+                return vec![Outgoing::Reply(from, reply.clone())];
+            }
+        }
+        if header.flags & RPC_FRAGMENT != 0 {
+            warn!("Fragmented PROFINET RPC request from {} is not supported", from);
+            return Vec::new();
+        }
+        let Some((args_maximum, blocks)) = ndr_request(header, body) else {
+            debug!("Malformed PROFINET RPC request from {}", from);
+            return Vec::new();
+        };
+
+        let mut request = None;
+        let (status, response_blocks) = match header.opnum {
+            OP_CONNECT => match ConnectRequest::parse(blocks).and_then(|r| self.connect(&r, from, now)) {
+                Ok(response) => (PNIO_OK, response),
+                Err(status) => (status, Vec::new()),
+            },
+            OP_RELEASE => self.release(blocks),
+            OP_READ => (PNIO_READ_INVALID_INDEX, Vec::new()),
+            OP_WRITE => self.write(blocks),
+            OP_CONTROL => {
+                let (status, response, application_ready) = self.control(blocks, now);
+                request = application_ready;
+                (status, response)
+            }
+            _ => return Vec::new(),
+        };
+
+        let reply_header = RpcHeader {
+            packet_type: RPC_RESPONSE,
+            flags: RPC_LAST_FRAGMENT | RPC_NO_FACK,
+            ..header.clone()
+        };
+        let reply = reply_header.encode(
+            self.server_boot,
+            &ndr_response_body(status, args_maximum, &response_blocks),
+        );
+        self.last_reply = Some((header.activity, header.sequence, reply.clone()));
+        let mut outgoing = vec![Outgoing::Reply(from, reply)];
+        outgoing.extend(request);
+        outgoing
+    }
+
+    fn handle_response(&mut self, header: &RpcHeader, body: &[u8]) {
+        let Some(ar) = self.ar.as_mut() else { return };
+        let ArState::ApplicationReady { activity, .. } = &ar.state else {
+            return;
+        };
+        if *activity != header.activity {
+            return;
+        }
+        match body.get(..4) {
+            Some(status) if status == PNIO_OK => {
+                info!("PROFINET connection with {} running", ar.controller);
+                ar.state = ArState::Running;
+            }
+            status => {
+                warn!("PROFINET controller {} refused application ready: {:02X?}", ar.controller, status);
+                self.ar = None;
+            }
+        }
+    }
+
+    fn connect(
+        &mut self,
+        request: &ConnectRequest,
+        from: SocketAddr,
+        now: Instant,
+    ) -> std::result::Result<Vec<u8>, [u8; 4]> {
+        if let Some(ar) = &self.ar {
+            if ar.uuid != request.ar_uuid && ar.controller_mac != request.controller_mac {
+                return Err(PNIO_CONNECT_BUSY);
+            }
+        }
+        if request.ar_type != AR_TYPE_SINGLE {
+            return Err(pnio_connect_error(0x01, 4));
+        }
+        let find = |kind| request.iocrs.iter().find(|c| c.kind == kind);
+        let (Some(input), Some(output)) = (find(IOCR_INPUT), find(IOCR_OUTPUT)) else {
+            return Err(pnio_connect_error(0x02, 4));
+        };
+        if request.iocrs.len() != 2 {
+            return Err(pnio_connect_error(0x02, 4));
+        }
+        if input.period().is_zero() || output.period().is_zero() {
+            return Err(pnio_connect_error(0x02, 10));
+        }
+        let alarm_cr = request.alarm_cr.as_ref().ok_or(pnio_connect_error(0x04, 0))?;
+
+        let (accepted, diff) = self.compare(&request.expected);
+
+        // Every accepted submodule's data and provider state must fit its frame
+        for (iocr, input_direction) in [(input, true), (output, false)] {
+            for &(slot, subslot, offset) in &iocr.data {
+                let length = find_submodule(&self.submodules, slot, subslot).map_or(0, |s| {
+                    if input_direction { s.input_length } else { s.output_length }
+                });
+                if usize::from(offset) + usize::from(length) >= usize::from(iocr.data_length) {
+                    return Err(pnio_connect_error(0x02, 21));
+                }
+            }
+            if iocr.iocs.iter().any(|&(_, _, offset)| offset >= iocr.data_length) {
+                return Err(pnio_connect_error(0x02, 21));
+            }
+        }
+
+        let mut response = Vec::new();
+        let mut ar_body = Vec::new();
+        ar_body.extend_from_slice(&request.ar_type.to_be_bytes());
+        ar_body.extend_from_slice(request.ar_uuid.as_bytes());
+        ar_body.extend_from_slice(&request.session_key.to_be_bytes());
+        ar_body.extend_from_slice(&self.mac);
+        ar_body.extend_from_slice(&ETHERTYPE.to_be_bytes());
+        response.extend(block(BLOCK_AR_RES, &ar_body));
+        for iocr in &request.iocrs {
+            let mut body = Vec::new();
+            for field in [iocr.kind, iocr.reference, iocr.frame_id] {
+                body.extend_from_slice(&field.to_be_bytes());
+            }
+            response.extend(block(BLOCK_IOCR_RES, &body));
+        }
+        let max_data_length = alarm_cr.max_data_length.min(200);
+        let mut body = Vec::new();
+        for field in [ALARM_CR_TYPE, DEVICE_ALARM_REFERENCE, max_data_length] {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        response.extend(block(BLOCK_ALARM_CR_RES, &body));
+        if !diff.is_empty() {
+            response.extend(module_diff_block(&diff));
+        }
+
+        let period = input.period();
+        info!(
+            "PROFINET controller '{}' connected on {} ({} µs cycle, {} submodule differences)",
+            request.controller_name,
+            self.interface,
+            period.as_micros(),
+            diff.values().map(|m| m.submodules.len().max(1)).sum::<usize>()
+        );
+        let activity_timeout = Duration::from_millis(u64::from(request.activity_timeout_factor) * 100);
+        self.ar = Some(Ar {
+            uuid: request.ar_uuid,
+            session_key: request.session_key,
+            controller_mac: request.controller_mac,
+            controller: SocketAddr::new(from.ip(), RPC_PORT),
+            controller_object: request.controller_object,
+            input: input.clone(),
+            output: output.clone(),
+            alarms: AlarmCr {
+                controller_reference: alarm_cr.controller_reference,
+                timeout: Duration::from_millis(u64::from(alarm_cr.timeout_factor.max(1)) * 100),
+                retries: alarm_cr.retries,
+                high: AlarmQueue::new(FRAME_ID_ALARM_HIGH),
+                low: AlarmQueue::new(FRAME_ID_ALARM_LOW),
+                levels: None,
+            },
+            accepted,
+            state: ArState::Parameterizing,
+            input_data: vec![0; usize::from(input.data_length)],
+            output_data: Vec::new(),
+            cycle_counter: 0,
+            next_send: now,
+            last_output: None,
+            startup_deadline: now + activity_timeout.max(Duration::from_secs(1)),
+        });
+        Ok(response)
+    }
+
+    /// Compare the expected submodules with the plugged ones
+    ///
+    /// Returns the matching submodules and the module diff by slot.
+    fn compare(&self, expected: &[ExpectedSubmodule]) -> (HashSet<(u16, u16)>, BTreeMap<u16, ModuleDiff>) {
+        let mut accepted = HashSet::new();
+        let mut diff: BTreeMap<u16, ModuleDiff> = BTreeMap::new();
+        for want in expected {
+            let Some(plugged) = self.submodules.iter().find(|s| s.slot == want.slot) else {
+                diff.entry(want.slot).or_insert_with(|| ModuleDiff::new(want.module_ident, MODULE_NONE));
+                continue;
+            };
+            let actual = find_submodule(&self.submodules, want.slot, want.subslot);
+            let state = if plugged.module_ident == want.module_ident {
+                MODULE_PROPER
+            } else {
+                MODULE_WRONG
+            };
+            let submodule_state = match actual {
+                _ if state == MODULE_WRONG => SUBMODULE_WRONG,
+                None => SUBMODULE_NONE,
+                Some(s) if s.ident != want.ident
+                    || s.input_length != want.input_length
+                    || s.output_length != want.output_length =>
+                {
+                    SUBMODULE_WRONG
+                }
+                Some(_) => {
+                    accepted.insert((want.slot, want.subslot));
+                    continue;
+                }
+            };
+            diff.entry(want.slot)
+                .or_insert_with(|| ModuleDiff::new(plugged.module_ident, state))
+                .submodules
+                .push((want.subslot, actual.map_or(want.ident, |s| s.ident), submodule_state));
+        }
+        (accepted, diff)
+    }
+
+    fn release(&mut self, blocks: &[u8]) -> ([u8; 4], Vec<u8>) {
+        let Some((BLOCK_RELEASE_REQ, ar_uuid, session_key, _)) = parse_control(blocks) else {
+            return (PNIO_RELEASE_UNKNOWN, Vec::new());
+        };
+        if self.ar.as_ref().is_none_or(|ar| ar.uuid != ar_uuid) {
+            return (PNIO_RELEASE_UNKNOWN, Vec::new());
+        }
+        info!("PROFINET connection released by the controller");
+        self.ar = None;
+        let response = control_block(BLOCK_RELEASE_REQ | BLOCK_RESPONSE, ar_uuid, session_key, CONTROL_DONE);
+        (PNIO_OK, response)
+    }
+
+    /// Accept parameter records; their contents are not used
+    fn write(&self, blocks: &[u8]) -> ([u8; 4], Vec<u8>) {
+        let mut r = Reader { data: blocks };
+        let Ok(request) = WriteRequest::parse(&mut r) else {
+            return (PNIO_WRITE_STATE_CONFLICT, Vec::new());
+        };
+        if self.ar.as_ref().is_none_or(|ar| ar.uuid != request.ar_uuid) {
+            return (PNIO_WRITE_STATE_CONFLICT, request.response(PNIO_WRITE_STATE_CONFLICT, 0));
+        }
+        if request.index != INDEX_WRITE_MULTIPLE {
+            log_record(&request);
+            return (PNIO_OK, request.response(PNIO_OK, request.length));
+        }
+
+        // Records of a multiple write are each padded to four bytes
+        let mut nested = Vec::new();
+        while let Ok(record) = WriteRequest::parse(&mut r) {
+            let length = usize::try_from(record.length).unwrap_or(usize::MAX);
+            let padded = (WRITE_HEADER_LEN + length).next_multiple_of(4) - WRITE_HEADER_LEN;
+            if r.take(padded.min(r.data.len())).is_err() {
+                break;
+            }
+            log_record(&record);
+            nested.extend(record.response(PNIO_OK, record.length));
+        }
+        let mut response = request.response(PNIO_OK, u32::try_from(nested.len()).unwrap_or(u32::MAX));
+        response.extend(nested);
+        (PNIO_OK, response)
+    }
+
+    /// Acknowledge control requests and signal application ready after
+    /// the end of parameterization
+    fn control(&mut self, blocks: &[u8], now: Instant) -> ([u8; 4], Vec<u8>, Option<Outgoing>) {
+        let Some((kind, ar_uuid, session_key, command)) = parse_control(blocks) else {
+            return (PNIO_CONTROL_UNKNOWN, Vec::new(), None);
+        };
+        let Some(ar) = self.ar.as_mut().filter(|ar| ar.uuid == ar_uuid) else {
+            return (PNIO_CONTROL_UNKNOWN, Vec::new(), None);
+        };
+        let response = control_block(kind | BLOCK_RESPONSE, ar_uuid, session_key, CONTROL_DONE);
+        if kind != BLOCK_PRM_END_REQ || command != CONTROL_PRM_END {
+            return (PNIO_OK, response, None);
+        }
+        let (activity, request) = ar.application_ready(self.server_boot);
+        let outgoing = Outgoing::Request(ar.controller, request.clone());
+        ar.state = ArState::ApplicationReady {
+            activity,
+            request,
+            sent: now,
+            attempts: 1,
+        };
+        (PNIO_OK, response, Some(outgoing))
+    }
+
+    // ------------------------------------------------------------------
+    // CYCLIC
+    // ------------------------------------------------------------------
+
+    /// Send due input frames, alarms and retries, and supervise the
+    /// connection
+    fn poll(&mut self, bus: &SignalBus, now: Instant) -> Vec<Outgoing> {
+        let mut outgoing = Vec::new();
+        let Some(ar) = self.ar.as_mut() else {
+            return outgoing;
+        };
+
+        let deadline = ar.last_output.map_or(ar.startup_deadline, |last| {
+            last + ar.output.period() * u32::from(ar.output.watchdog_factor.max(1))
+        });
+        if now > deadline {
+            warn!("PROFINET controller {} stopped sending output data, dropping the connection", ar.controller);
+            self.ar = None;
+            return outgoing;
+        }
+
+        if let ArState::ApplicationReady { request, sent, attempts, .. } = &mut ar.state {
+            if now.duration_since(*sent) >= APPLICATION_READY_TIMEOUT {
+                if *attempts >= APPLICATION_READY_ATTEMPTS {
+                    warn!("PROFINET controller {} did not confirm application ready", ar.controller);
+                    self.ar = None;
+                    return outgoing;
+                }
+                *attempts += 1;
+                *sent = now;
+                outgoing.push(Outgoing::Request(ar.controller, request.clone()));
+            }
+        }
+
+        if now >= ar.next_send {
+            outgoing.push(Outgoing::Frame(ar.input_frame(&self.submodules, self.mac, bus)));
+            let period = ar.input.period();
+            ar.next_send += period;
+            if ar.next_send < now {
+                ar.next_send = now + period;
+            }
+        }
+
+        if matches!(ar.state, ArState::Running) {
+            update_alarms(ar, &self.submodules, &self.alarms, bus);
+            if let Some(frames) = ar.alarms.transmit(ar.controller_mac, self.mac, now) {
+                outgoing.extend(frames.into_iter().map(Outgoing::Frame));
+            } else {
+                warn!("PROFINET controller {} did not acknowledge an alarm, dropping the connection", ar.controller);
+                self.ar = None;
+            }
+        }
+        outgoing
+    }
+
+    /// Handle an alarm PDU from the controller, returning its acknowledgement
+    fn handle_rta(&mut self, frame_id: u16, payload: &[u8]) -> Option<Vec<u8>> {
+        let ar = self.ar.as_mut()?;
+        let mut r = Reader { data: payload };
+        let dst = r.u16().ok()?;
+        let _src = r.u16().ok()?;
+        let pdu_type = r.u8().ok()?;
+        let _flags = r.u8().ok()?;
+        let send_seq = r.u16().ok()?;
+        let ack_seq = r.u16().ok()?;
+        if dst != DEVICE_ALARM_REFERENCE {
+            return None;
+        }
+        let queue = if frame_id == FRAME_ID_ALARM_HIGH { &mut ar.alarms.high } else { &mut ar.alarms.low };
+        if queue.pending.is_some() && ack_seq == queue.send_seq {
+            queue.pending = None;
+        }
+        match pdu_type & RTA_TYPE_MASK {
+            RTA_TYPE_DATA => {
+                // Carries the controller's acknowledgement of our alarm
+                debug!("PROFINET alarm acknowledged by {}", ar.controller);
+                queue.ack_seq = send_seq;
+                let ack = rta_pdu(frame_id, ar.alarms.controller_reference, RTA_ACK, queue.send_seq, send_seq, &[]);
+                Some(ethernet_frame(ar.controller_mac, self.mac, &ack))
+            }
+            RTA_TYPE_ERR => {
+                warn!("PROFINET controller {} aborted the connection", ar.controller);
+                self.ar = None;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Slot entry of a module diff block
+struct ModuleDiff {
+    ident: u32,
+    state: u16,
+    /// Differing submodules as (subslot, ident, state)
+    submodules: Vec<(u16, u32, u16)>,
+}
+
+impl ModuleDiff {
+    const fn new(ident: u32, state: u16) -> Self {
+        Self {
+            ident,
+            state,
+            submodules: Vec::new(),
+        }
+    }
+}
+
+fn module_diff_block(diff: &BTreeMap<u16, ModuleDiff>) -> Vec<u8> {
+    let mut body = Vec::new();
+    // One API
+    body.extend_from_slice(&1u16.to_be_bytes());
+    body.extend_from_slice(&0u32.to_be_bytes());
+    body.extend_from_slice(&len16(diff.len()).to_be_bytes());
+    for (slot, module) in diff {
+        body.extend_from_slice(&slot.to_be_bytes());
+        body.extend_from_slice(&module.ident.to_be_bytes());
+        body.extend_from_slice(&module.state.to_be_bytes());
+        body.extend_from_slice(&len16(module.submodules.len()).to_be_bytes());
+        for (subslot, ident, state) in &module.submodules {
+            body.extend_from_slice(&subslot.to_be_bytes());
+            body.extend_from_slice(&ident.to_be_bytes());
+            body.extend_from_slice(&state.to_be_bytes());
+        }
+    }
+    block(BLOCK_MODULE_DIFF, &body)
+}
+
+fn log_record(record: &WriteRequest) {
+    debug!(
+        "PROFINET record 0x{:04X} written to slot {} subslot 0x{:04X} ({} bytes)",
+        record.index, record.slot, record.subslot, record.length
+    );
+}
+
+/// Queue alarms for signals that changed since the last cycle
+///
+/// Diagnosis alarms already active when the connection starts are raised
+/// at once; process alarms only on later rising edges.
+fn update_alarms(ar: &mut Ar, submodules: &[Submodule], alarms: &[AlarmConfig], bus: &SignalBus) {
+    let level = |alarm: &AlarmConfig| bus.get(&alarm.signal).and_then(|v| v.as_bool()).unwrap_or(false);
+    let levels = ar.alarms.levels.get_or_insert_with(|| {
+        alarms
+            .iter()
+            .map(|a| (a.signal.clone(), a.kind == AlarmKind::Process && level(a)))
+            .collect()
+    });
+
+    let mut changed = Vec::new();
+    for alarm in alarms {
+        let active = level(alarm);
+        if levels.insert(alarm.signal.clone(), active) != Some(active) {
+            changed.push((alarm, active));
+        }
+    }
+    for (alarm, active) in changed {
+        if alarm.kind == AlarmKind::Process && !active {
+            continue;
+        }
+        let Some(submodule) = submodules.iter().find(|s| s.slot == alarm.slot) else {
+            continue;
+        };
+        let diagnosis_active = |same_slot: bool| {
+            alarms.iter().any(|a| {
+                a.kind == AlarmKind::Diagnosis
+                    && (!same_slot || a.slot == alarm.slot)
+                    && levels.get(&a.signal).copied().unwrap_or(false)
+            })
+        };
+        let diagnosis = (diagnosis_active(true), diagnosis_active(false));
+        let queue = match alarm.kind {
+            AlarmKind::Process => &mut ar.alarms.high,
+            AlarmKind::Diagnosis => &mut ar.alarms.low,
+        };
+        let notification = alarm_notification(submodule, alarm, active, queue, diagnosis);
+        queue.queue.push_back(notification);
+    }
+}
+
+/// Serve the controller on `config.interface` until the process exits
+///
+/// The GSDML file is read once at startup and the device runs on a blocking
+/// thread, polling the raw socket every millisecond.
+///
+/// # Errors
+///
+/// Returns an error if the GSDML file or module configuration is invalid,
+/// the sockets cannot be opened or the interface fails.
+pub async fn run(config: ProfinetConfig, bus: SignalBus) -> Result<()> {
+    let gsdml = Gsdml::load(&config.gsdml)?;
+    #[cfg(target_os = "linux")]
+    {
+        let socket = RawSocket::open(&config.interface, ETHERTYPE, POLL_INTERVAL)?;
+        socket.join_multicast(DCP_MULTICAST)?;
+        let device = ProfinetDevice::new(config, &gsdml, socket.mac_address()?, socket.ipv4_address()?)?;
+        info!(
+            "PROFINET device '{}' on {} with {} submodules",
+            device.station_name,
+            device.interface,
+            device.submodules.len()
+        );
+        tokio::task::spawn_blocking(move || serve(device, socket, &bus))
+            .await
+            .map_err(|e| PlcError::Runtime(format!("PROFINET device task failed: {e}")))?
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (config, bus, gsdml);
+        Err(PlcError::Config("PROFINET requires Linux raw sockets".to_string()))
+    }
+}
+
+/// Receive frames and RPC packets and answer them until the interface fails
+#[cfg(target_os = "linux")]
+fn serve<T: Transport>(mut device: ProfinetDevice, mut transport: T, bus: &SignalBus) -> Result<()> {
+    use std::net::UdpSocket;
+
+    let server = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, RPC_PORT))?;
+    server.set_nonblocking(true)?;
+    let client = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    client.set_nonblocking(true)?;
+
+    let mut frame = vec![0u8; 1536];
+    let mut datagram = vec![0u8; 65536];
+    loop {
+        let mut outgoing = Vec::new();
+        if let Some(len) = transport.recv(&mut frame)? {
+            let reply = device.handle_frame(&frame[..len], bus, Instant::now());
+            outgoing.extend(reply.map(Outgoing::Frame));
+        }
+        for socket in [&server, &client] {
+            loop {
+                match socket.recv_from(&mut datagram) {
+                    Ok((len, from)) => outgoing.extend(device.handle_rpc(&datagram[..len], from, Instant::now())),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        outgoing.extend(device.poll(bus, Instant::now()));
+
+        for message in outgoing {
+            match message {
+                Outgoing::Frame(frame) => transport.send(&frame)?,
+                Outgoing::Reply(to, packet) => {
+                    if let Err(e) = server.send_to(&packet, to) {
+                        warn!("Failed to answer PROFINET RPC request from {}: {}", to, e);
+                    }
+                }
+                Outgoing::Request(to, packet) => {
+                    if let Err(e) = client.send_to(&packet, to) {
+                        warn!("Failed to send PROFINET RPC request to {}: {}", to, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GSDML: &str = r#"<?xml version="1.0" encoding="iso-8859-1"?>
+<ISO15745Profile xmlns="http://www.profibus.com/GSDML/2003/11/DeviceProfile">
+  <ProfileBody>
+    <DeviceIdentity VendorID="0x0493" DeviceID="0x0001">
+      <VendorName Value="Lithos"/>
+    </DeviceIdentity>
+    <ApplicationProcess>
+      <DeviceAccessPointList>
+        <DeviceAccessPointItem ID="DAP1" ModuleIdentNumber="0x00000001">
+          <VirtualSubmoduleList>
+            <VirtualSubmoduleItem ID="DAP1-SUB" SubmoduleIdentNumber="0x00000001"/>
+          </VirtualSubmoduleList>
+          <SystemDefinedSubmoduleList>
+            <InterfaceSubmoduleItem ID="IF" SubslotNumber="32768" SubmoduleIdentNumber="0x00000002"/>
+            <PortSubmoduleItem ID="P1" SubslotNumber="32769" SubmoduleIdentNumber="0x00000003"/>
+          </SystemDefinedSubmoduleList>
+        </DeviceAccessPointItem>
+      </DeviceAccessPointList>
+      <ModuleList>
+        <ModuleItem ID="DI8" ModuleIdentNumber="0x00000010">
+          <VirtualSubmoduleList>
+            <VirtualSubmoduleItem ID="DI8-SUB" SubmoduleIdentNumber="0x00000011">
+              <IOData><Input><DataItem DataType="Unsigned8" TextId="DI"/></Input></IOData>
+            </VirtualSubmoduleItem>
+          </VirtualSubmoduleList>
+        </ModuleItem>
+        <ModuleItem ID="AO1" ModuleIdentNumber="0x00000020">
+          <VirtualSubmoduleList>
+            <VirtualSubmoduleItem ID="AO1-SUB" SubmoduleIdentNumber="0x00000021">
+              <IOData><Output><DataItem DataType="Integer16" TextId="AO"/></Output></IOData>
+            </VirtualSubmoduleItem>
+          </VirtualSubmoduleList>
+        </ModuleItem>
+      </ModuleList>
+    </ApplicationProcess>
+  </ProfileBody>
+</ISO15745Profile>"#;
+
+    const DEVICE_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const CONTROLLER_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+    fn device() -> ProfinetDevice {
+        let config: ProfinetConfig = serde_yaml::from_str(
+            r"
+interface: eth1
+station_name: petra-line1
+gsdml: petra.xml
+device_access_point: DAP1
+slots:
+  - slot: 1
+    module: DI8
+    inputs:
+      - { signal: di.start, offset: 0, bit: 0, type: bool }
+      - { signal: di.stop, offset: 0, bit: 1, type: bool }
+  - slot: 2
+    module: AO1
+    outputs:
+      - { signal: ao.speed, offset: 0, type: int16 }
+alarms:
+  - { signal: alarm.overtemp, slot: 1 }
+",
+        )
+        .unwrap();
+        let gsdml = Gsdml::parse(GSDML).unwrap();
+        ProfinetDevice::new(config, &gsdml, DEVICE_MAC, Some((Ipv4Addr::new(192, 168, 0, 10), Ipv4Addr::new(255, 255, 255, 0))))
+            .unwrap()
+    }
+
+    fn rpc_request(interface: Uuid, opnum: u16, blocks: &[u8]) -> Vec<u8> {
+        RpcHeader {
+            packet_type: RPC_REQUEST,
+            flags: RPC_LAST_FRAGMENT | RPC_IDEMPOTENT,
+            little_endian: true,
+            object: Uuid::nil(),
+            interface,
+            activity: Uuid::new_v4(),
+            interface_version: 1,
+            sequence: 0,
+            opnum,
+        }
+        .encode(0, &ndr_request_body(blocks))
+    }
+
+    fn reply_blocks(outgoing: &Outgoing) -> ([u8; 4], Vec<(u16, Vec<u8>)>) {
+        let Outgoing::Reply(_, packet) = outgoing else { panic!("expected a reply") };
+        let (_, body) = RpcHeader::parse(packet).unwrap();
+        let status = body[..4].try_into().unwrap();
+        let blocks = blocks(&body[NDR_HEADER_LEN..])
+            .unwrap()
+            .into_iter()
+            .map(|(kind, body)| (kind, body.to_vec()))
+            .collect();
+        (status, blocks)
+    }
+
+    fn iocr_block(kind: u16, frame_id: u16, data: &[(u16, u16, u16)], iocs: &[(u16, u16, u16)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for field in [kind, 1, ETHERTYPE] {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        body.extend_from_slice(&1u32.to_be_bytes());
+        // 40 bytes every 32 × 31.25 µs, watchdog after 3 cycles
+        for field in [40, frame_id, 32, 1, 1, 0] {
+            body.extend_from_slice(&u16::to_be_bytes(field));
+        }
+        body.extend_from_slice(&0u32.to_be_bytes());
+        for field in [3u16, 3, 0xC000] {
+            body.extend_from_slice(&field.to_be_bytes());
+        }
+        body.extend_from_slice(&[0; 6]);
+        body.extend_from_slice(&1u16.to_be_bytes());
+        body.extend_from_slice(&0u32.to_be_bytes());
+        for list in [data, iocs] {
+            body.extend_from_slice(&len16(list.len()).to_be_bytes());
+            for &(slot, subslot, offset) in list {
+                for field in [slot, subslot, offset] {
+                    body.extend_from_slice(&field.to_be_bytes());
+                }
+            }
+        }
+        block(BLOCK_IOCR_REQ, &body)
+    }
+
+    fn expected_block(slot: u16, module_ident: u32, submodules: &[(u16, u32, u16, u16)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&1u16.to_be_bytes());
+        body.extend_from_slice(&0u32.to_be_bytes());
+        body.extend_from_slice(&slot.to_be_bytes());
+        body.extend_from_slice(&module_ident.to_be_bytes());
+        body.extend_from_slice(&0u16.to_be_bytes());
+        body.extend_from_slice(&len16(submodules.len()).to_be_bytes());
+        for &(subslot, ident, direction, length) in submodules {
+            body.extend_from_slice(&subslot.to_be_bytes());
+            body.extend_from_slice(&ident.to_be_bytes());
+            // NO_IO submodules describe an empty input
+            body.extend_from_slice(&u16::from(length > 0).wrapping_mul(direction).to_be_bytes());
+            body.extend_from_slice(&direction.max(IOCR_INPUT).to_be_bytes());
+            body.extend_from_slice(&length.to_be_bytes());
+            body.extend_from_slice(&[1, 1]);
+        }
+        block(BLOCK_EXPECTED_SUBMODULE_REQ, &body)
+    }
+
+    fn connect_request(ar_uuid: Uuid) -> Vec<u8> {
+        let mut blocks = Vec::new();
+        let name = b"plc-1";
+        let mut ar = Vec::new();
+        ar.extend_from_slice(&AR_TYPE_SINGLE.to_be_bytes());
+        ar.extend_from_slice(ar_uuid.as_bytes());
+        ar.extend_from_slice(&7u16.to_be_bytes());
+        ar.extend_from_slice(&CONTROLLER_MAC);
+        ar.extend_from_slice(Uuid::from_u128(0xDEA0_0000_6C97_11D1_8271_0001_0001_002A).as_bytes());
+        ar.extend_from_slice(&0x11u32.to_be_bytes());
+        for field in [100u16, 0x8892, len16(name.len())] {
+            ar.extend_from_slice(&field.to_be_bytes());
+        }
+        ar.extend_from_slice(name);
+        blocks.extend(block(BLOCK_AR_REQ, &ar));
+
+        // Input frame: DAP provider states, DI8 data at 3 with its state
+        // at 4, AO1 consumer state at 5
+        blocks.extend(iocr_block(
+            IOCR_INPUT,
+            0x8001,
+            &[(0, 1, 0), (0, 0x8000, 1), (0, 0x8001, 2), (1, 1, 3)],
+            &[(2, 1, 5)],
+        ));
+        // Output frame: AO1 data at 0 with its state at 2
+        blocks.extend(iocr_block(
+            IOCR_OUTPUT,
+            0x8002,
+            &[(2, 1, 0)],
+            &[(0, 1, 3), (0, 0x8000, 4), (0, 0x8001, 5), (1, 1, 6)],
+        ));
+
+        let mut alarm = Vec::new();
+        for field in [ALARM_CR_TYPE, ETHERTYPE] {
+            alarm.extend_from_slice(&field.to_be_bytes());
+        }
+        alarm.extend_from_slice(&0u32.to_be_bytes());
+        for field in [1u16, 3, 0x0005, 200, 0xC000, 0xA000] {
+            alarm.extend_from_slice(&field.to_be_bytes());
+        }
+        blocks.extend(block(BLOCK_ALARM_CR_REQ, &alarm));
+
+        blocks.extend(expected_block(0, 1, &[(1, 1, 0, 0), (0x8000, 2, 0, 0), (0x8001, 3, 0, 0)]));
+        blocks.extend(expected_block(1, 0x10, &[(1, 0x11, IOCR_INPUT, 1)]));
+        blocks.extend(expected_block(2, 0x20, &[(1, 0x21, IOCR_OUTPUT, 2)]));
+        // Not plugged
+        blocks.extend(expected_block(3, 0x30, &[(1, 0x31, IOCR_INPUT, 1)]));
+        rpc_request(DEVICE_INTERFACE, OP_CONNECT, &blocks)
+    }
+
+    #[test]
+    fn test_connect_exchange_and_alarm() {
+        let bus = SignalBus::new();
+        let mut device = device();
+        let controller: SocketAddr = "192.168.0.1:49152".parse().unwrap();
+        let now = Instant::now();
+        let ar_uuid = Uuid::new_v4();
+
+        let outgoing = device.handle_rpc(&connect_request(ar_uuid), controller, now);
+        let (status, blocks) = reply_blocks(&outgoing[0]);
+        assert_eq!(status, PNIO_OK);
+        let kinds: Vec<u16> = blocks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [BLOCK_AR_RES, BLOCK_IOCR_RES, BLOCK_IOCR_RES, BLOCK_ALARM_CR_RES, BLOCK_MODULE_DIFF]);
+        // Only slot 3 differs: one module, not plugged
+        let diff = &blocks[4].1;
+        assert_eq!(diff[6..8], 1u16.to_be_bytes());
+        assert_eq!(diff[8..10], 3u16.to_be_bytes());
+        assert_eq!(diff[14..16], MODULE_NONE.to_be_bytes());
+
+        // Inputs go out with good provider and consumer states
+        bus.set("di.start", Value::Bool(true)).unwrap();
+        bus.set("di.stop", Value::Bool(true)).unwrap();
+        let outgoing = device.poll(&bus, now);
+        let [Outgoing::Frame(frame)] = outgoing.as_slice() else { panic!("expected an input frame") };
+        assert_eq!(frame[..6], CONTROLLER_MAC);
+        assert_eq!(frame[14..16], [0x80, 0x01]);
+        assert_eq!(frame[16..22], [IO_STATE_GOOD, IO_STATE_GOOD, IO_STATE_GOOD, 0x03, IO_STATE_GOOD, IO_STATE_GOOD]);
+        assert_eq!(frame[16 + 42], 0x35);
+
+        // Outputs are written to their signals
+        let mut output = vec![0x80, 0x02, 0x01, 0x2C, IO_STATE_GOOD];
+        output.resize(2 + 40, 0);
+        output.extend_from_slice(&[0, 32, 0x35, 0]);
+        device.handle_frame(&ethernet_frame(DEVICE_MAC, CONTROLLER_MAC, &output), &bus, now);
+        assert_eq!(bus.get("ao.speed"), Some(Value::Integer(300)));
+
+        // End of parameterization triggers application ready
+        let prm_end = control_block(BLOCK_PRM_END_REQ, ar_uuid, 7, CONTROL_PRM_END);
+        let outgoing = device.handle_rpc(&rpc_request(DEVICE_INTERFACE, OP_CONTROL, &prm_end), controller, now);
+        assert_eq!(reply_blocks(&outgoing[0]).0, PNIO_OK);
+        let Outgoing::Request(to, request) = &outgoing[1] else { panic!("expected application ready") };
+        assert_eq!(*to, "192.168.0.1:34964".parse().unwrap());
+        let (header, _) = RpcHeader::parse(request).unwrap();
+        assert_eq!(header.interface, CONTROLLER_INTERFACE);
+        let response = RpcHeader {
+            packet_type: RPC_RESPONSE,
+            ..header
+        }
+        .encode(0, &ndr_response_body(PNIO_OK, 0, &[]));
+        device.handle_rpc(&response, *to, now);
+        assert!(matches!(device.ar.as_ref().unwrap().state, ArState::Running));
+
+        // A diagnosis alarm appears on slot 1
+        bus.set("alarm.overtemp", Value::Bool(true)).unwrap();
+        let outgoing = device.poll(&bus, now);
+        let [Outgoing::Frame(alarm)] = outgoing.as_slice() else { panic!("expected an alarm frame") };
+        assert_eq!(alarm[14..16], FRAME_ID_ALARM_LOW.to_be_bytes());
+        assert_eq!(alarm[16..18], 0x0005u16.to_be_bytes());
+        assert_eq!(alarm[20], RTA_DATA);
+        assert_eq!(alarm[28..30], BLOCK_ALARM_LOW.to_be_bytes());
+        assert_eq!(alarm[34..36], ALARM_TYPE_DIAGNOSIS.to_be_bytes());
+        assert_eq!(alarm[40..42], 1u16.to_be_bytes());
+
+        // The controller acknowledges it and nothing is resent
+        let ack = rta_pdu(FRAME_ID_ALARM_LOW, DEVICE_ALARM_REFERENCE, RTA_ACK, 0xFFFF, 0, &[]);
+        device.handle_frame(&ethernet_frame(DEVICE_MAC, CONTROLLER_MAC, &ack), &bus, now);
+        assert!(device.ar.as_ref().unwrap().alarms.low.pending.is_none());
+
+        // Output frames stop and the watchdog drops the connection
+        device.poll(&bus, now + Duration::from_secs(1));
+        assert!(device.ar.is_none());
+    }
+
+    #[test]
+    fn test_dcp_identify_and_set_name() {
+        let mut device = device();
+        let bus = SignalBus::new();
+        let dcp = |frame_id: u16, service: u8, blocks: &[u8]| {
+            let mut payload = frame_id.to_be_bytes().to_vec();
+            payload.extend_from_slice(&[service, DCP_REQUEST, 0, 0, 0, 9, 0, 1]);
+            payload.extend_from_slice(&len16(blocks.len()).to_be_bytes());
+            payload.extend_from_slice(blocks);
+            ethernet_frame(DCP_MULTICAST, CONTROLLER_MAC, &payload)
+        };
+        let identify = |name: &str| {
+            let mut filter = Vec::new();
+            dcp_block(&mut filter, DCP_NAME, name.as_bytes());
+            dcp(FRAME_ID_DCP_IDENTIFY, DCP_IDENTIFY, &filter)
+        };
+
+        let mut all = Vec::new();
+        dcp_block(&mut all, DCP_ALL, &[]);
+        let response = device
+            .handle_frame(&dcp(FRAME_ID_DCP_IDENTIFY, DCP_IDENTIFY, &all), &bus, Instant::now())
+            .unwrap();
+        assert_eq!(response[..6], CONTROLLER_MAC);
+        assert_eq!(response[14..16], FRAME_ID_DCP_IDENTIFY_RESPONSE.to_be_bytes());
+        assert_eq!(response[18..22], [0, 0, 0, 9]);
+        let blocks = dcp_blocks(&response[26..]);
+        assert_eq!(blocks[0], (DCP_NAME, b"\0\0petra-line1".as_slice()));
+        assert!(blocks.contains(&(DCP_DEVICE_ID, [0, 0, 0x04, 0x93, 0, 1].as_slice())));
+        assert!(blocks.contains(&(DCP_IP, [0, 1, 192, 168, 0, 10, 255, 255, 255, 0, 0, 0, 0, 0].as_slice())));
+        assert!(device.handle_frame(&identify("petra-line2"), &bus, Instant::now()).is_none());
+
+        // Name is changed, an IP change is refused
+        let mut set = Vec::new();
+        dcp_block(&mut set, DCP_NAME, b"\0\x01petra-line2");
+        dcp_block(&mut set, DCP_IP, &[0, 1, 10, 0, 0, 5, 255, 0, 0, 0, 0, 0, 0, 0]);
+        let response = device
+            .handle_frame(&dcp(FRAME_ID_DCP_GET_SET, DCP_SET, &set), &bus, Instant::now())
+            .unwrap();
+        let blocks = dcp_blocks(&response[26..]);
+        assert_eq!(blocks[0], (DCP_RESULT, [2, 2, 0].as_slice()));
+        assert_eq!(blocks[1], (DCP_RESULT, [1, 2, DCP_ERROR_LOCAL].as_slice()));
+        assert!(device.handle_frame(&identify("petra-line2"), &bus, Instant::now()).is_some());
+        assert!(device.handle_frame(&identify("petra-line1"), &bus, Instant::now()).is_none());
+    }
+}
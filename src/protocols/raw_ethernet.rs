@@ -0,0 +1,255 @@
+//! Raw Ethernet access for fieldbuses that bypass IP
+//!
+//! `EtherCAT` and PROFINET real-time frames are plain Ethernet frames with
+//! their own `EtherType`. Both drivers exchange them through [`Transport`],
+//! which [`RawSocket`] implements with a Linux `AF_PACKET` socket bound to
+//! one interface. Opening it requires `CAP_NET_RAW`.
+
+use crate::Result;
+#[cfg(target_os = "linux")]
+use crate::PlcError;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, OwnedFd};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// Sends and receives raw Ethernet frames
+pub trait Transport: Send {
+    /// Send one frame
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame cannot be sent.
+    fn send(&mut self, frame: &[u8]) -> Result<()>;
+
+    /// Receive one frame into `buf`, or `None` if none arrived in time
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interface fails.
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>>;
+}
+
+/// `AF_PACKET` socket receiving one `EtherType` on one interface
+#[cfg(target_os = "linux")]
+pub struct RawSocket {
+    fd: OwnedFd,
+    #[cfg_attr(not(feature = "profinet"), allow(dead_code))]
+    interface: String,
+    #[cfg_attr(not(feature = "profinet"), allow(dead_code))]
+    ifindex: i32,
+}
+
+#[cfg(target_os = "linux")]
+impl RawSocket {
+    /// Open a socket for `ethertype` frames on `interface`
+    ///
+    /// Receives give up after `timeout` so callers can run their cycle.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Io`] if the interface does not exist or the
+    /// process lacks `CAP_NET_RAW`.
+    pub fn open(interface: &str, ethertype: u16, timeout: Duration) -> Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        let name = std::ffi::CString::new(interface)
+            .map_err(|_| PlcError::Config(format!("Invalid interface name '{interface}'")))?;
+        // SAFETY: `name` is a valid NUL-terminated string
+        let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let ifindex = i32::try_from(ifindex).map_err(|_| PlcError::Config(format!("Invalid interface '{interface}'")))?;
+
+        // SAFETY: plain socket creation, the result is checked below
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, i32::from(ethertype.to_be())) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: `fd` is a freshly created socket owned by nobody else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: sockaddr_ll is plain data, all-zero is a valid value
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = u16::try_from(libc::AF_PACKET).unwrap_or_default();
+        addr.sll_protocol = ethertype.to_be();
+        addr.sll_ifindex = ifindex;
+        // SAFETY: `addr` is a valid sockaddr_ll and the length matches it
+        let bound = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                std::ptr::addr_of!(addr).cast(),
+                u32::try_from(std::mem::size_of::<libc::sockaddr_ll>()).unwrap_or_default(),
+            )
+        };
+        if bound < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let timeval = libc::timeval {
+            tv_sec: libc::time_t::try_from(timeout.as_secs()).unwrap_or(libc::time_t::MAX),
+            tv_usec: libc::suseconds_t::from(timeout.subsec_micros()),
+        };
+        // SAFETY: `timeval` is valid for reads of its size
+        let set = unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                std::ptr::addr_of!(timeval).cast(),
+                u32::try_from(std::mem::size_of::<libc::timeval>()).unwrap_or_default(),
+            )
+        };
+        if set < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            fd,
+            interface: interface.to_string(),
+            ifindex,
+        })
+    }
+
+    /// Also receive frames sent to the multicast address `mac`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Io`] if the interface rejects the membership.
+    #[cfg(feature = "profinet")]
+    pub fn join_multicast(&self, mac: [u8; 6]) -> Result<()> {
+        let mut address = [0u8; 8];
+        address[..6].copy_from_slice(&mac);
+        let membership = libc::packet_mreq {
+            mr_ifindex: self.ifindex,
+            mr_type: u16::try_from(libc::PACKET_MR_MULTICAST).unwrap_or_default(),
+            mr_alen: 6,
+            mr_address: address,
+        };
+        // SAFETY: `membership` is valid for reads of its size
+        let set = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                libc::SOL_PACKET,
+                libc::PACKET_ADD_MEMBERSHIP,
+                std::ptr::addr_of!(membership).cast(),
+                u32::try_from(std::mem::size_of::<libc::packet_mreq>()).unwrap_or_default(),
+            )
+        };
+        if set < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Hardware address of the interface
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Io`] if the address cannot be read.
+    #[cfg(feature = "profinet")]
+    pub fn mac_address(&self) -> Result<[u8; 6]> {
+        let request = self.interface_request(libc::SIOCGIFHWADDR)?;
+        // SAFETY: SIOCGIFHWADDR fills the hardware address member
+        let hwaddr = unsafe { request.ifr_ifru.ifru_hwaddr };
+        let mut mac = [0u8; 6];
+        for (byte, data) in mac.iter_mut().zip(hwaddr.sa_data) {
+            *byte = data.to_ne_bytes()[0];
+        }
+        Ok(mac)
+    }
+
+    /// IPv4 address and netmask of the interface, if it has one
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Io`] if the interface cannot be queried.
+    #[cfg(feature = "profinet")]
+    pub fn ipv4_address(&self) -> Result<Option<(std::net::Ipv4Addr, std::net::Ipv4Addr)>> {
+        let read = |ioctl| -> Result<Option<std::net::Ipv4Addr>> {
+            match self.interface_request(ioctl) {
+                Ok(request) => {
+                    // SAFETY: both ioctls fill the address member with a
+                    // sockaddr_in for AF_INET sockets
+                    let addr: libc::sockaddr_in =
+                        unsafe { std::ptr::read_unaligned(std::ptr::addr_of!(request.ifr_ifru.ifru_addr).cast()) };
+                    Ok(Some(std::net::Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr))))
+                }
+                // No address assigned
+                Err(PlcError::Io(e)) if e.raw_os_error() == Some(libc::EADDRNOTAVAIL) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        let Some(address) = read(libc::SIOCGIFADDR)? else {
+            return Ok(None);
+        };
+        let netmask = read(libc::SIOCGIFNETMASK)?.unwrap_or(std::net::Ipv4Addr::UNSPECIFIED);
+        Ok(Some((address, netmask)))
+    }
+
+    #[cfg(feature = "profinet")]
+    fn interface_request(&self, ioctl: libc::c_ulong) -> Result<libc::ifreq> {
+        // SAFETY: ifreq is plain data, all-zero is a valid value
+        let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+        for (dst, src) in request.ifr_name.iter_mut().zip(self.interface.bytes().take(libc::IFNAMSIZ - 1)) {
+            *dst = libc::c_char::from_ne_bytes([src]);
+        }
+        // Interface addresses are only reported on AF_INET sockets
+        // SAFETY: plain socket creation, the result is checked below
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: `fd` is a freshly created socket owned by nobody else
+        let socket = unsafe { <OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd) };
+        // SAFETY: `request` is a valid ifreq for the duration of the call
+        if unsafe { libc::ioctl(socket.as_raw_fd(), ioctl, &raw mut request) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Transport for RawSocket {
+    fn send(&mut self, frame: &[u8]) -> Result<()> {
+        // SAFETY: `frame` is valid for reads of its length
+        let sent = unsafe { libc::send(self.fd.as_raw_fd(), frame.as_ptr().cast(), frame.len(), 0) };
+        if sent < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+        loop {
+            // SAFETY: sockaddr_ll is plain data, all-zero is a valid value
+            let mut from: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+            let mut from_len = u32::try_from(std::mem::size_of::<libc::sockaddr_ll>()).unwrap_or_default();
+            // SAFETY: `buf` is valid for writes of its length and `from`
+            // for writes of `from_len` bytes
+            let received = unsafe {
+                libc::recvfrom(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    0,
+                    std::ptr::addr_of_mut!(from).cast(),
+                    &raw mut from_len,
+                )
+            };
+            if received < 0 {
+                let e = std::io::Error::last_os_error();
+                return match e.kind() {
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => Ok(None),
+                    std::io::ErrorKind::Interrupted => continue,
+                    _ => Err(e.into()),
+                };
+            }
+            // The socket also sees the frames we send
+            if from.sll_pkttype != libc::PACKET_OUTGOING {
+                return Ok(Some(usize::try_from(received).unwrap_or_default()));
+            }
+        }
+    }
+}
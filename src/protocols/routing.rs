@@ -0,0 +1,683 @@
+// src/protocols/routing.rs
+//! Protocol-to-protocol routing (bridge mode)
+//!
+//! A route reads an address from one protocol driver, passes the value
+//! through a signal on the bus and writes it to an address of another
+//! driver, so PETRA can act as a gateway without any blocks:
+//!
+//! ```yaml
+//! protocols:
+//!   routes:
+//!     - name: tank1_level
+//!       from: { protocol: line1_plc, address: "40001" }
+//!       to: { protocol: broker, address: "site/tank1/level" }
+//!       transform: { scale: 0.1, offset: -40 }
+//!     - name: boiler_temp
+//!       from: { protocol: boiler_s7, address: "DB10.DBD4" }
+//!       to: { protocol: scada_opcua, address: "ns=2;s=Boiler.Temp" }
+//!       signal: boiler.temp
+//!       interval_ms: 250
+//!       transform: { expression: "round((x - 32) * 5 / 9)", integer: true }
+//! ```
+//!
+//! The routed value is stored in `signal`, `route.<name>` by default, so it
+//! can be trended, alarmed on and inspected like any other signal. It is
+//! only written to the target when it changes.
+//!
+//! Transforms apply to numeric values: `scale` and `offset` compute
+//! `x * scale + offset`; an `expression` over `x` may use `+ - * /`,
+//! parentheses and `abs`, `round`, `floor`, `ceil`, `sqrt`, `min`, `max`.
+//! The result is a float unless `integer` is set.
+//!
+//! Reads and writes go through [`ProtocolManager`](super::ProtocolManager),
+//! one request per driver for all routes with the same interval, and
+//! failover groups can be named as either end of a route.
+
+use super::ProtocolManager;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// One address routed from a source driver to a target driver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Unique route name
+    pub name: String,
+    /// Address the value is read from
+    pub from: RouteEndpoint,
+    /// Address the value is written to
+    pub to: RouteEndpoint,
+    /// Signal carrying the routed value; `route.<name>` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+    /// Conversion applied between source and target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformConfig>,
+    /// Time between two reads of the source
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+/// A protocol driver and one of its addresses
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RouteEndpoint {
+    /// Protocol driver or failover group
+    pub protocol: String,
+    /// Protocol-specific address
+    pub address: String,
+}
+
+/// Numeric conversion of a routed value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// Factor the value is multiplied by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Amount added after scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+    /// Expression over `x`, used instead of `scale` and `offset`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Round the result to an integer
+    #[serde(default)]
+    pub integer: bool,
+}
+
+const fn default_interval_ms() -> u64 {
+    1000
+}
+
+impl RouteConfig {
+    /// Signal the routed value is stored in
+    #[must_use]
+    pub fn signal_name(&self) -> String {
+        self.signal.clone().unwrap_or_else(|| format!("route.{}", self.name))
+    }
+
+    /// Validate the route
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty name, protocol or address,
+    /// a zero interval, a route back to its own source or an invalid
+    /// transform.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(PlcError::Config("Route name cannot be empty".to_string()));
+        }
+        for endpoint in [&self.from, &self.to] {
+            if endpoint.protocol.is_empty() || endpoint.address.is_empty() {
+                return Err(PlcError::Config(format!(
+                    "Route '{}' endpoints need a protocol and address",
+                    self.name
+                )));
+            }
+        }
+        if self.from == self.to {
+            return Err(PlcError::Config(format!(
+                "Route '{}' writes back to its own source",
+                self.name
+            )));
+        }
+        if self.interval_ms == 0 {
+            return Err(PlcError::Config(format!(
+                "Route '{}' interval_ms must be greater than 0",
+                self.name
+            )));
+        }
+        if let Some(transform) = &self.transform {
+            Transform::new(transform)
+                .map_err(|e| PlcError::Config(format!("Route '{}' transform: {e}", self.name)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Validate a routing table
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if a route is invalid, two routes share a
+/// name or signal, or two routes write the same target address.
+pub fn validate_routes(routes: &[RouteConfig]) -> Result<()> {
+    let mut names = HashSet::new();
+    let mut signals = HashSet::new();
+    let mut targets = HashSet::new();
+    for route in routes {
+        route.validate()?;
+        if !names.insert(route.name.as_str()) {
+            return Err(PlcError::Config(format!("Duplicate route name: '{}'", route.name)));
+        }
+        if !signals.insert(route.signal_name()) {
+            return Err(PlcError::Config(format!(
+                "Route '{}' uses a signal of another route",
+                route.name
+            )));
+        }
+        if !targets.insert(&route.to) {
+            return Err(PlcError::Config(format!(
+                "Address '{}' of '{}' is the target of more than one route",
+                route.to.address, route.to.protocol
+            )));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TRANSFORMS
+// ============================================================================
+
+/// Parsed expression over the routed value `x`
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Input,
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Self::Number(n) => *n,
+            Self::Input => x,
+            Self::Neg(e) => -e.eval(x),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ => a / b,
+                }
+            }
+            Self::Call(name, args) => {
+                let args: Vec<f64> = args.iter().map(|a| a.eval(x)).collect();
+                match (name.as_str(), args.as_slice()) {
+                    ("abs", [a]) => a.abs(),
+                    ("round", [a]) => a.round(),
+                    ("floor", [a]) => a.floor(),
+                    ("ceil", [a]) => a.ceil(),
+                    ("sqrt", [a]) => a.sqrt(),
+                    ("min", [a, b]) => a.min(*b),
+                    ("max", [a, b]) => a.max(*b),
+                    _ => f64::NAN,
+                }
+            }
+        }
+    }
+}
+
+/// Recursive-descent parser for transform expressions
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(source: &'a str) -> std::result::Result<Expr, String> {
+        let mut parser = Self {
+            chars: source.char_indices().peekable(),
+            source,
+        };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{c}'")),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn sum(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> std::result::Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> std::result::Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.chars.next();
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> std::result::Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                text.parse().map(Expr::Number).map_err(|_| format!("invalid number '{text}'"))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if name == "x" {
+                    return Ok(Expr::Input);
+                }
+                let arity = match name {
+                    "abs" | "round" | "floor" | "ceil" | "sqrt" => 1,
+                    "min" | "max" => 2,
+                    _ => return Err(format!("unknown name '{name}'")),
+                };
+                self.expect('(')?;
+                let mut args = vec![self.sum()?];
+                while self.peek() == Some(',') {
+                    self.chars.next();
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+                if args.len() != arity {
+                    return Err(format!("{name} takes {arity} argument(s)"));
+                }
+                Ok(Expr::Call(name.to_string(), args))
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let start = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        while self.chars.next_if(|&(_, c)| accept(c)).is_some() {}
+        let end = self.chars.peek().map_or(self.source.len(), |&(i, _)| i);
+        &self.source[start..end]
+    }
+
+    fn expect(&mut self, expected: char) -> std::result::Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected '{expected}', found '{c}'")),
+            None => Err(format!("expected '{expected}'")),
+        }
+    }
+}
+
+/// Compiled transform of a route
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    expr: Expr,
+    integer: bool,
+}
+
+impl Transform {
+    /// Compile a transform configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the expression does not parse or is
+    /// combined with `scale` or `offset`.
+    pub fn new(config: &TransformConfig) -> Result<Self> {
+        let expr = match &config.expression {
+            Some(_) if config.scale.is_some() || config.offset.is_some() => {
+                return Err(PlcError::Config(
+                    "expression cannot be combined with scale or offset".to_string(),
+                ))
+            }
+            Some(expression) => Parser::parse(expression)
+                .map_err(|e| PlcError::Config(format!("invalid expression '{expression}': {e}")))?,
+            None => Expr::Binary(
+                '+',
+                Box::new(Expr::Binary(
+                    '*',
+                    Box::new(Expr::Input),
+                    Box::new(Expr::Number(config.scale.unwrap_or(1.0))),
+                )),
+                Box::new(Expr::Number(config.offset.unwrap_or(0.0))),
+            ),
+        };
+        Ok(Self {
+            expr,
+            integer: config.integer,
+        })
+    }
+
+    /// Apply the transform to a numeric value
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Protocol`] for non-numeric values and results
+    /// that are not finite.
+    pub fn apply(&self, value: &Value) -> Result<Value> {
+        if !matches!(value, Value::Integer(_) | Value::Float(_)) {
+            return Err(PlcError::Protocol(format!(
+                "cannot transform {} value",
+                value.type_name()
+            )));
+        }
+        let x = value.as_float().unwrap_or(f64::NAN);
+        let result = self.expr.eval(x);
+        if !result.is_finite() {
+            return Err(PlcError::Protocol(format!("transform of {x} is not finite")));
+        }
+        if self.integer {
+            #[allow(clippy::cast_possible_truncation)]
+            let rounded = result.round() as i64;
+            Ok(Value::Integer(rounded))
+        } else {
+            Ok(Value::Float(result))
+        }
+    }
+}
+
+// ============================================================================
+// ROUTER
+// ============================================================================
+
+/// Counts of one routing cycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteStats {
+    /// Values read from sources
+    pub read: usize,
+    /// Values written to targets
+    pub written: usize,
+    /// Unchanged values not written again
+    pub unchanged: usize,
+    /// Failed requests and transforms
+    pub errors: usize,
+}
+
+#[derive(Debug)]
+struct Route {
+    name: String,
+    from: RouteEndpoint,
+    to: RouteEndpoint,
+    signal: String,
+    transform: Option<Transform>,
+    /// Last value written to the target
+    written: Option<Value>,
+}
+
+/// Routes sharing an interval, exchanged in one cycle
+#[derive(Debug)]
+pub struct Router {
+    interval: Duration,
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// Group validated routes into routers by interval
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if a transform does not compile.
+    pub fn from_config(routes: &[RouteConfig]) -> Result<Vec<Self>> {
+        let mut by_interval: BTreeMap<u64, Vec<Route>> = BTreeMap::new();
+        for config in routes {
+            let transform = config.transform.as_ref().map(Transform::new).transpose()?;
+            by_interval.entry(config.interval_ms).or_default().push(Route {
+                name: config.name.clone(),
+                from: config.from.clone(),
+                to: config.to.clone(),
+                signal: config.signal_name(),
+                transform,
+                written: None,
+            });
+        }
+        Ok(by_interval
+            .into_iter()
+            .map(|(interval_ms, routes)| Self {
+                interval: Duration::from_millis(interval_ms),
+                routes,
+            })
+            .collect())
+    }
+
+    /// Time between cycles
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Read every source, update the route signals and write changed values
+    pub async fn cycle(&mut self, manager: &ProtocolManager, bus: &SignalBus) -> RouteStats {
+        let mut stats = RouteStats::default();
+
+        let mut sources: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for route in &self.routes {
+            sources.entry(&route.from.protocol).or_default().push(route.from.address.clone());
+        }
+        let mut values: HashMap<(String, String), Value> = HashMap::new();
+        for (protocol, addresses) in sources {
+            match manager.read_from(protocol, &addresses).await {
+                Ok(read) => {
+                    stats.read += read.len();
+                    values.extend(read.into_iter().map(|(address, value)| ((protocol.to_string(), address), value)));
+                }
+                Err(e) => {
+                    stats.errors += 1;
+                    tracing::warn!("Routing: read from {} failed: {}", protocol, e);
+                }
+            }
+        }
+
+        let mut targets: BTreeMap<String, Vec<(usize, Value)>> = BTreeMap::new();
+        for (index, route) in self.routes.iter().enumerate() {
+            let key = (route.from.protocol.clone(), route.from.address.clone());
+            let Some(raw) = values.get(&key) else {
+                continue;
+            };
+            let value = match &route.transform {
+                Some(transform) => match transform.apply(raw) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        stats.errors += 1;
+                        tracing::warn!("Route {}: {}", route.name, e);
+                        continue;
+                    }
+                },
+                None => raw.clone(),
+            };
+            if let Err(e) = bus.set(&route.signal, value.clone()) {
+                tracing::warn!("Route {}: cannot update {}: {}", route.name, route.signal, e);
+            }
+            if route.written.as_ref() == Some(&value) {
+                stats.unchanged += 1;
+                continue;
+            }
+            targets.entry(route.to.protocol.clone()).or_default().push((index, value));
+        }
+
+        for (protocol, pending) in targets {
+            let request: HashMap<String, Value> = pending
+                .iter()
+                .map(|(index, value)| (self.routes[*index].to.address.clone(), value.clone()))
+                .collect();
+            match manager.write_to(&protocol, &request).await {
+                Ok(()) => {
+                    stats.written += pending.len();
+                    for (index, value) in pending {
+                        self.routes[index].written = Some(value);
+                    }
+                }
+                Err(e) => {
+                    stats.errors += 1;
+                    tracing::warn!("Routing: write to {} failed: {}", protocol, e);
+                }
+            }
+        }
+        stats
+    }
+
+    /// Route at the interval until the task is dropped
+    pub async fn run(mut self, manager: Arc<ProtocolManager>, bus: SignalBus) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let stats = self.cycle(&manager, &bus).await;
+            tracing::trace!("Routing cycle every {:?}: {:?}", self.interval, stats);
+        }
+    }
+}
+
+/// Run each router on its own task
+#[must_use]
+pub fn spawn_routers(
+    routers: Vec<Router>,
+    manager: &Arc<ProtocolManager>,
+    bus: &SignalBus,
+) -> Vec<tokio::task::JoinHandle<()>> {
+    routers
+        .into_iter()
+        .map(|router| {
+            tracing::info!("Routing {} addresses every {:?}", router.routes.len(), router.interval);
+            tokio::spawn(router.run(Arc::clone(manager), bus.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::ProtocolDriver;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Device holding a map of address values
+    struct MemoryDriver {
+        values: Arc<Mutex<HashMap<String, Value>>>,
+        writes: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl ProtocolDriver for MemoryDriver {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+            let values = self.values.lock().unwrap();
+            Ok(addresses
+                .iter()
+                .filter_map(|a| values.get(a).map(|v| (a.clone(), v.clone())))
+                .collect())
+        }
+
+        async fn write_values(&mut self, values: &HashMap<String, Value>) -> Result<()> {
+            *self.writes.lock().unwrap() += 1;
+            self.values.lock().unwrap().extend(values.clone());
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn protocol_name(&self) -> &'static str {
+            "memory"
+        }
+    }
+
+    fn transform(yaml: &str) -> Transform {
+        Transform::new(&serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_transforms_and_validation() {
+        let scaled = transform("{ scale: 0.1, offset: -40 }");
+        assert_eq!(scaled.apply(&Value::Integer(650)).unwrap(), Value::Float(25.0));
+
+        let expression = transform("{ expression: 'round((x - 32) * 5 / 9)', integer: true }");
+        assert_eq!(expression.apply(&Value::Float(212.4)).unwrap(), Value::Integer(100));
+        let clamped = transform("{ expression: 'max(0, min(-x, 10))' }");
+        assert_eq!(clamped.apply(&Value::Integer(-25)).unwrap(), Value::Float(10.0));
+        assert!(clamped.apply(&Value::Bool(true)).is_err());
+        assert!(transform("{ expression: '1 / x' }").apply(&Value::Integer(0)).is_err());
+
+        for bad in ["{ expression: 'x +' }", "{ expression: 'pow(x, 2)' }", "{ expression: 'x', scale: 2 }"] {
+            assert!(Transform::new(&serde_yaml::from_str(bad).unwrap()).is_err(), "{bad}");
+        }
+
+        let routes: Vec<RouteConfig> = serde_yaml::from_str(
+            "- { name: a, from: { protocol: plc, address: '1' }, to: { protocol: mqtt, address: t/a } }\n\
+             - { name: b, from: { protocol: plc, address: '2' }, to: { protocol: mqtt, address: t/a } }\n",
+        )
+        .unwrap();
+        assert_eq!(routes[0].signal_name(), "route.a");
+        assert!(validate_routes(&routes[..1]).is_ok());
+        assert!(validate_routes(&routes).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cycle_routes_changed_values_between_drivers() {
+        let bus = SignalBus::new();
+        let manager = ProtocolManager::new(bus.clone());
+        let source = Arc::new(Mutex::new(HashMap::from([
+            ("40001".to_string(), Value::Integer(650)),
+            ("40002".to_string(), Value::Bool(true)),
+        ])));
+        let target = Arc::new(Mutex::new(HashMap::new()));
+        let target_writes = Arc::new(Mutex::new(0));
+        manager
+            .add_driver("plc".to_string(), Box::new(MemoryDriver { values: source.clone(), writes: Arc::default() }))
+            .await
+            .unwrap();
+        manager
+            .add_driver("broker".to_string(), Box::new(MemoryDriver { values: target.clone(), writes: target_writes.clone() }))
+            .await
+            .unwrap();
+
+        let routes: Vec<RouteConfig> = serde_yaml::from_str(
+            "- name: level\n  from: { protocol: plc, address: '40001' }\n  to: { protocol: broker, address: tank/level }\n  \
+               transform: { scale: 0.1, offset: -40 }\n\
+             - name: pump\n  signal: pump.running\n  from: { protocol: plc, address: '40002' }\n  to: { protocol: broker, address: pump/running }\n",
+        )
+        .unwrap();
+        validate_routes(&routes).unwrap();
+        let mut routers = Router::from_config(&routes).unwrap();
+        assert_eq!(routers.len(), 1);
+        let router = &mut routers[0];
+
+        let stats = router.cycle(&manager, &bus).await;
+        assert_eq!((stats.read, stats.written, stats.errors), (2, 2, 0));
+        assert_eq!(bus.get("route.level"), Some(Value::Float(25.0)));
+        assert_eq!(target.lock().unwrap().get("pump/running"), Some(&Value::Bool(true)));
+        assert_eq!(*target_writes.lock().unwrap(), 1);
+
+        // Unchanged values are not written again
+        source.lock().unwrap().insert("40001".to_string(), Value::Integer(700));
+        let stats = router.cycle(&manager, &bus).await;
+        assert_eq!((stats.written, stats.unchanged), (1, 1));
+        assert_eq!(target.lock().unwrap().get("tank/level"), Some(&Value::Float(30.0)));
+        assert_eq!(bus.get("pump.running"), Some(Value::Bool(true)));
+    }
+}
@@ -1,50 +1,1153 @@
 //! S7 protocol implementation
 //!
-//! This module provides Siemens S7 communication capabilities.
+//! Reads Siemens S7-300/400/1200/1500 PLCs over ISO-on-TCP (RFC 1006) with
+//! the S7 communication protocol, configured under `protocols.s7`:
+//!
+//! ```yaml
+//! protocols:
+//!   s7:
+//!     timeout_ms: 2000
+//!     connections:
+//!       - name: press_line
+//!         ip: 10.0.3.15
+//!         rack: 0
+//!         slot: 1
+//!         poll_interval_ms: 250
+//!         pool_size: 2
+//!         tags:
+//!           - { signal: press.force, area: DB, db_number: 10, offset: 0, data_type: real }
+//!           - { signal: press.cycles, area: DB, db_number: 10, offset: 4, data_type: dint }
+//!           - { signal: press.running, area: MB, offset: 20, bit: 3, data_type: bool }
+//!         data_areas:
+//!           - { area: IB, offset: 0, length: 8, signal_prefix: press.inputs }
+//! ```
+//!
+//! Each byte of a data area is written to `<signal_prefix>.<address>`. Tags
+//! of standard access DBs can also be given by symbol, resolved against DB
+//! sources exported from TIA Portal (see [`s7_symbols`](super::s7_symbols)).
+//!
+//! # Request planning
+//!
+//! Tags are not read one at a time. Tags of the same DB or area that lie
+//! within `merge_gap` bytes of each other are combined into one read item,
+//! and items are packed into as few Read Var requests as the negotiated PDU
+//! size allows on both the request and the response side. With hundreds of
+//! tags a scan typically needs a handful of requests instead of one per tag.
+//! Members imported from a DB source are adjacent, so a whole DB is usually
+//! read with a few items.
+//!
+//! The requests of a scan are spread over `pool_size` connections, and each
+//! connection keeps as many requests outstanding as the PLC accepts parallel
+//! jobs (the negotiated `AmQ`), so a scan costs about one round trip per
+//! connection rather than one per request.
+//!
+//! # Runtime reconfiguration
+//!
+//! Each PLC is also available as an [`S7Driver`]. Reloading it with a new
+//! connection section applies the poll interval and tags from the next scan
+//! on; the open connections are kept unless the PLC's address, rack, slot,
+//! connection type, `pool_size` or `pdu_size` change.
 
-use crate::{Result, Value};
+use super::s7_symbols;
+use crate::config::{S7Config, S7Connection, Validatable};
+use crate::scan_budget::{self, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
 use async_trait::async_trait;
 use std::collections::HashMap;
-use tracing::info;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// TAGS
+// ============================================================================
+
+const AREA_INPUTS: u8 = 0x81;
+const AREA_OUTPUTS: u8 = 0x82;
+const AREA_FLAGS: u8 = 0x83;
+const AREA_DB: u8 = 0x84;
+
+/// Memory area and DB number of a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Location {
+    area: u8,
+    db: u16,
+}
+
+impl Location {
+    fn parse(area: &str, db_number: u16) -> Result<Self> {
+        let area = match area.to_uppercase().as_str() {
+            "DB" => AREA_DB,
+            "MB" => AREA_FLAGS,
+            "IB" => AREA_INPUTS,
+            "QB" => AREA_OUTPUTS,
+            other => return Err(PlcError::Config(format!("Invalid S7 area type: '{other}'"))),
+        };
+        let db = if area == AREA_DB { db_number } else { 0 };
+        Ok(Self { area, db })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataType {
+    Bool,
+    Byte,
+    Word,
+    Int,
+    DWord,
+    DInt,
+    Real,
+    LReal,
+}
+
+impl DataType {
+    fn parse(data_type: &str) -> Result<Self> {
+        match data_type.to_lowercase().as_str() {
+            "bool" => Ok(Self::Bool),
+            "byte" => Ok(Self::Byte),
+            "word" => Ok(Self::Word),
+            "int" => Ok(Self::Int),
+            "dword" => Ok(Self::DWord),
+            "dint" => Ok(Self::DInt),
+            "real" => Ok(Self::Real),
+            "lreal" => Ok(Self::LReal),
+            other => Err(PlcError::Config(format!("Invalid S7 data type '{other}'"))),
+        }
+    }
+
+    const fn size(self) -> u32 {
+        match self {
+            Self::Bool | Self::Byte => 1,
+            Self::Word | Self::Int => 2,
+            Self::DWord | Self::DInt | Self::Real => 4,
+            Self::LReal => 8,
+        }
+    }
+
+    /// Value of big-endian `bytes`, exactly [`Self::size`] long
+    fn decode(self, bytes: &[u8], bit: u8) -> Value {
+        let word = || [bytes[0], bytes[1]];
+        let dword = || [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Self::Bool => Value::Bool(bytes[0] >> bit & 1 == 1),
+            Self::Byte => Value::Integer(i64::from(bytes[0])),
+            Self::Word => Value::Integer(i64::from(u16::from_be_bytes(word()))),
+            Self::Int => Value::Integer(i64::from(i16::from_be_bytes(word()))),
+            Self::DWord => Value::Integer(i64::from(u32::from_be_bytes(dword()))),
+            Self::DInt => Value::Integer(i64::from(i32::from_be_bytes(dword()))),
+            Self::Real => Value::Float(f64::from(f32::from_be_bytes(dword()))),
+            Self::LReal => Value::Float(f64::from_be_bytes(bytes[..8].try_into().unwrap_or_default())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tag {
+    signal: String,
+    location: Location,
+    offset: u32,
+    bit: u8,
+    data_type: DataType,
+}
+
+/// Tags of a connection, with symbols resolved and data areas expanded to
+/// byte tags
+fn collect_tags(connection: &S7Connection) -> Result<Vec<Tag>> {
+    let mut tags = Vec::new();
+    for tag in s7_symbols::resolve_tags(connection)? {
+        tags.push(Tag {
+            location: Location::parse(&tag.area, tag.db_number)?,
+            offset: tag.offset,
+            bit: tag.bit,
+            data_type: DataType::parse(&tag.data_type)?,
+            signal: tag.signal,
+        });
+    }
+    for area in &connection.data_areas {
+        let location = Location::parse(&area.area, area.db_number)?;
+        for offset in area.offset..area.offset.saturating_add(area.length) {
+            tags.push(Tag {
+                signal: format!("{}.{offset}", area.signal_prefix),
+                location,
+                offset,
+                bit: 0,
+                data_type: DataType::Byte,
+            });
+        }
+    }
+    Ok(tags)
+}
+
+// ============================================================================
+// PLANNING
+// ============================================================================
+
+/// S7 header and Read Var function and item count of a request
+const REQUEST_HEADER: usize = 12;
+/// Variable specification of one item
+const REQUEST_ITEM: usize = 12;
+/// S7 header with error code and Read Var function and item count
+const RESPONSE_HEADER: usize = 14;
+/// Return code, transport size and length preceding an item's data
+const RESPONSE_ITEM: usize = 4;
+/// Items per request accepted by every CPU family
+const MAX_ITEMS: usize = 20;
+
+/// Contiguous bytes read as one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Item {
+    location: Location,
+    start: u32,
+    length: u32,
+}
+
+impl Item {
+    const fn end(&self) -> u32 {
+        self.start + self.length
+    }
+
+    /// Bytes the item occupies in a response, including the fill byte
+    /// after data of odd length
+    fn response_size(&self) -> usize {
+        let length = usize::try_from(self.length).unwrap_or(usize::MAX);
+        RESPONSE_ITEM + length + length % 2
+    }
+}
+
+/// How requests are built from tags
+#[derive(Debug, Clone, Copy)]
+struct PlanLimits {
+    pdu_size: u16,
+    /// Gap up to which neighbouring tags share an item, `None` for one item
+    /// per tag
+    merge_gap: Option<u32>,
+    max_items: usize,
+}
+
+/// Where a tag's bytes are found in the responses of a scan
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    tag: usize,
+    request: usize,
+    item: usize,
+    offset: usize,
+}
+
+/// Read requests covering every tag
+#[derive(Debug, Default)]
+struct Plan {
+    requests: Vec<Vec<Item>>,
+    placements: Vec<Placement>,
+}
+
+impl Plan {
+    fn new(tags: &[Tag], limits: PlanLimits) -> Self {
+        let pdu = usize::from(limits.pdu_size);
+        let max_item_bytes = u32::try_from(pdu - RESPONSE_HEADER - RESPONSE_ITEM).unwrap_or(u32::MAX) & !1;
+
+        // Combine neighbouring tags into items
+        let mut order: Vec<usize> = (0..tags.len()).collect();
+        order.sort_by_key(|&i| (tags[i].location, tags[i].offset));
+        let mut items: Vec<(Item, Vec<usize>)> = Vec::new();
+        for index in order {
+            let tag = &tags[index];
+            let end = tag.offset + tag.data_type.size();
+            if let Some((item, members)) = items.last_mut() {
+                let joins = item.location == tag.location
+                    && limits.merge_gap.is_some_and(|gap| tag.offset <= item.end() + gap)
+                    && end.max(item.end()) - item.start <= max_item_bytes;
+                if joins {
+                    item.length = end.max(item.end()) - item.start;
+                    members.push(index);
+                    continue;
+                }
+            }
+            let item = Item {
+                location: tag.location,
+                start: tag.offset,
+                length: tag.data_type.size(),
+            };
+            items.push((item, vec![index]));
+        }
+
+        // Pack items into requests, largest first, into the first request
+        // with room on both the request and the response side
+        let max_items = limits.max_items.min((pdu - REQUEST_HEADER) / REQUEST_ITEM).max(1);
+        let mut by_size: Vec<usize> = (0..items.len()).collect();
+        by_size.sort_by_key(|&i| std::cmp::Reverse(items[i].0.length));
+        let mut requests: Vec<(Vec<usize>, usize)> = Vec::new();
+        for index in by_size {
+            let size = items[index].0.response_size();
+            let fits = requests
+                .iter_mut()
+                .find(|(members, used)| members.len() < max_items && used + size <= pdu);
+            match fits {
+                Some((members, used)) => {
+                    members.push(index);
+                    *used += size;
+                }
+                None => requests.push((vec![index], RESPONSE_HEADER + size)),
+            }
+        }
+
+        let mut plan = Self::default();
+        for (request, (mut members, _)) in requests.into_iter().enumerate() {
+            members.sort_by_key(|&i| (items[i].0.location, items[i].0.start));
+            let mut request_items = Vec::with_capacity(members.len());
+            for (position, index) in members.into_iter().enumerate() {
+                let (item, tag_indices) = &items[index];
+                for &tag in tag_indices {
+                    plan.placements.push(Placement {
+                        tag,
+                        request,
+                        item: position,
+                        offset: usize::try_from(tags[tag].offset - item.start).unwrap_or(usize::MAX),
+                    });
+                }
+                request_items.push(*item);
+            }
+            plan.requests.push(request_items);
+        }
+        plan
+    }
+}
+
+// ============================================================================
+// WIRE FORMAT
+// ============================================================================
+
+const TPKT_VERSION: u8 = 3;
+const COTP_CONNECT_CONFIRM: u8 = 0xD0;
+const COTP_DATA: [u8; 3] = [0x02, 0xF0, 0x80];
+const S7_PROTOCOL_ID: u8 = 0x32;
+const ROSCTR_JOB: u8 = 0x01;
+const ROSCTR_ACK_DATA: u8 = 0x03;
+const FUNCTION_READ_VAR: u8 = 0x04;
+const FUNCTION_SETUP: u8 = 0xF0;
+const RETURN_SUCCESS: u8 = 0xFF;
+/// Parallel jobs proposed to the PLC
+const PROPOSED_JOBS: u16 = 8;
+
+fn s7_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Protocol(format!("S7: {e}"))
+}
+
+fn tpkt(payload: &[u8]) -> Vec<u8> {
+    let length = u16::try_from(payload.len() + 4).unwrap_or(u16::MAX);
+    let mut frame = vec![TPKT_VERSION, 0];
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// COTP connection request addressing the CPU in `rack` and `slot`
+fn connection_request(rack: u16, slot: u16, connection_type: &str) -> Vec<u8> {
+    let kind = match connection_type.to_uppercase().as_str() {
+        "OP" => 2,
+        "S7_BASIC" => 3,
+        _ => 1,
+    };
+    let remote = u8::try_from(rack * 0x20 + slot).unwrap_or(0);
+    tpkt(&[
+        0x11, 0xE0, 0x00, 0x00, 0x00, 0x01, 0x00, // CR, references, class 0
+        0xC0, 0x01, 0x0A, // TPDU size 1024
+        0xC1, 0x02, 0x01, 0x00, // calling TSAP
+        0xC2, 0x02, kind, remote, // called TSAP
+    ])
+}
+
+/// S7 job carried in a COTP data TPDU
+fn job(pdu_ref: u16, params: &[u8]) -> Vec<u8> {
+    let mut payload = COTP_DATA.to_vec();
+    payload.extend_from_slice(&[S7_PROTOCOL_ID, ROSCTR_JOB, 0, 0]);
+    payload.extend_from_slice(&pdu_ref.to_be_bytes());
+    payload.extend_from_slice(&u16::try_from(params.len()).unwrap_or(u16::MAX).to_be_bytes());
+    payload.extend_from_slice(&[0, 0]);
+    payload.extend_from_slice(params);
+    tpkt(&payload)
+}
+
+fn setup_request(pdu_ref: u16, pdu_size: u16) -> Vec<u8> {
+    let mut params = vec![FUNCTION_SETUP, 0];
+    params.extend_from_slice(&PROPOSED_JOBS.to_be_bytes());
+    params.extend_from_slice(&PROPOSED_JOBS.to_be_bytes());
+    params.extend_from_slice(&pdu_size.to_be_bytes());
+    job(pdu_ref, &params)
+}
+
+fn read_request(pdu_ref: u16, items: &[Item]) -> Vec<u8> {
+    let mut params = vec![FUNCTION_READ_VAR, u8::try_from(items.len()).unwrap_or(u8::MAX)];
+    for item in items {
+        // Variable specification, syntax ID S7ANY, transport size BYTE
+        params.extend_from_slice(&[0x12, 0x0A, 0x10, 0x02]);
+        params.extend_from_slice(&u16::try_from(item.length).unwrap_or(u16::MAX).to_be_bytes());
+        params.extend_from_slice(&item.location.db.to_be_bytes());
+        params.push(item.location.area);
+        params.extend_from_slice(&(item.start << 3).to_be_bytes()[1..]);
+    }
+    job(pdu_ref, &params)
+}
+
+/// TPKT payload of the next frame
+async fn read_frame(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut header = [0; 4];
+    stream.read_exact(&mut header).await?;
+    if header[0] != TPKT_VERSION {
+        return Err(s7_error("unexpected TPKT version"));
+    }
+    let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+    let mut payload = vec![0; length.saturating_sub(4)];
+    stream.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// S7 message of a COTP data TPDU
+struct Response<'a> {
+    pdu_ref: u16,
+    error: u16,
+    params: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Response<'a> {
+    fn parse(payload: &'a [u8]) -> Result<Self> {
+        let malformed = || s7_error("malformed response");
+        let s7 = payload.get(1 + usize::from(*payload.first().ok_or_else(malformed)?)..).ok_or_else(malformed)?;
+        if s7.len() < 12 || s7[0] != S7_PROTOCOL_ID || s7[1] != ROSCTR_ACK_DATA {
+            return Err(malformed());
+        }
+        let params_length = usize::from(u16::from_be_bytes([s7[6], s7[7]]));
+        let data_length = usize::from(u16::from_be_bytes([s7[8], s7[9]]));
+        let params = s7.get(12..12 + params_length).ok_or_else(malformed)?;
+        let data = s7.get(12 + params_length..12 + params_length + data_length).ok_or_else(malformed)?;
+        Ok(Self {
+            pdu_ref: u16::from_be_bytes([s7[4], s7[5]]),
+            error: u16::from_be_bytes([s7[10], s7[11]]),
+            params,
+            data,
+        })
+    }
+
+    /// Data of every item of a Read Var response
+    fn items(&self, requested: &[Item]) -> Result<Vec<Vec<u8>>> {
+        let mut rest = self.data;
+        let mut items = Vec::with_capacity(requested.len());
+        for (position, item) in requested.iter().enumerate() {
+            let header = rest.get(..RESPONSE_ITEM).ok_or_else(|| s7_error("truncated Read Var response"))?;
+            if header[0] != RETURN_SUCCESS {
+                return Err(s7_error(format!(
+                    "reading {} bytes at {} of area {:#04x} DB {} failed with code {:#04x}",
+                    item.length, item.start, item.location.area, item.location.db, header[0]
+                )));
+            }
+            let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+            // Lengths of bit, byte, word and dword transport sizes are in bits
+            let length = if matches!(header[1], 0x03..=0x05) { length.div_ceil(8) } else { length };
+            let data = rest
+                .get(RESPONSE_ITEM..RESPONSE_ITEM + length)
+                .ok_or_else(|| s7_error("truncated Read Var response"))?;
+            if length != usize::try_from(item.length).unwrap_or(usize::MAX) {
+                return Err(s7_error("Read Var response length does not match the request"));
+            }
+            items.push(data.to_vec());
+            let fill = usize::from(position + 1 < requested.len()) * (length % 2);
+            rest = rest.get(RESPONSE_ITEM + length + fill..).unwrap_or_default();
+        }
+        Ok(items)
+    }
+}
+
+// ============================================================================
+// CONNECTIONS
+// ============================================================================
+
+/// Established session with negotiated limits
+struct Connection {
+    stream: TcpStream,
+    pdu_size: u16,
+    max_jobs: usize,
+    next_ref: u16,
+    timeout: Duration,
+}
+
+impl Connection {
+    async fn open(connection: &S7Connection, timeout: Duration) -> Result<Self> {
+        let address = (connection.ip.as_str(), connection.port);
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(address))
+            .await
+            .map_err(|_| s7_error(format!("connecting to {} timed out", connection.ip)))??;
+        stream.set_nodelay(true)?;
+        let mut session = Self {
+            stream,
+            pdu_size: connection.pdu_size,
+            max_jobs: 1,
+            next_ref: 1,
+            timeout,
+        };
 
+        let request = connection_request(connection.rack, connection.slot, &connection.connection_type);
+        session.stream.write_all(&request).await?;
+        let confirm = session.receive().await?;
+        if confirm.get(1).map(|code| code & 0xF0) != Some(COTP_CONNECT_CONFIRM) {
+            return Err(s7_error(format!(
+                "rack {} slot {} refused the connection",
+                connection.rack, connection.slot
+            )));
+        }
+
+        let pdu_ref = session.pdu_ref();
+        session.stream.write_all(&setup_request(pdu_ref, connection.pdu_size)).await?;
+        let payload = session.receive().await?;
+        let response = Response::parse(&payload)?;
+        if response.error != 0 || response.params.len() < 8 {
+            return Err(s7_error(format!("communication setup refused ({:#06x})", response.error)));
+        }
+        let params = response.params;
+        let calling = u16::from_be_bytes([params[2], params[3]]);
+        let called = u16::from_be_bytes([params[4], params[5]]);
+        session.max_jobs = usize::from(calling.min(called).max(1));
+        session.pdu_size = u16::from_be_bytes([params[6], params[7]]).min(connection.pdu_size);
+        Ok(session)
+    }
+
+    fn pdu_ref(&mut self) -> u16 {
+        let pdu_ref = self.next_ref;
+        self.next_ref = self.next_ref.wrapping_add(1).max(1);
+        pdu_ref
+    }
+
+    async fn receive(&mut self) -> Result<Vec<u8>> {
+        tokio::time::timeout(self.timeout, read_frame(&mut self.stream))
+            .await
+            .map_err(|_| s7_error("response timed out"))?
+    }
+
+    /// Run `requests` with up to `max_jobs` outstanding, returning the item
+    /// data of each request in order
+    async fn read(&mut self, requests: &[Vec<Item>]) -> Result<Vec<Vec<Vec<u8>>>> {
+        let mut results = vec![Vec::new(); requests.len()];
+        let mut outstanding = HashMap::new();
+        let mut next = 0;
+        while next < requests.len() || !outstanding.is_empty() {
+            while next < requests.len() && outstanding.len() < self.max_jobs {
+                let pdu_ref = self.pdu_ref();
+                self.stream.write_all(&read_request(pdu_ref, &requests[next])).await?;
+                outstanding.insert(pdu_ref, next);
+                next += 1;
+            }
+            let payload = self.receive().await?;
+            let response = Response::parse(&payload)?;
+            let Some(index) = outstanding.remove(&response.pdu_ref) else {
+                debug!("Ignoring S7 response to unknown PDU {}", response.pdu_ref);
+                continue;
+            };
+            if response.error != 0 {
+                return Err(s7_error(format!("Read Var refused ({:#06x})", response.error)));
+            }
+            results[index] = response.items(&requests[index])?;
+        }
+        Ok(results)
+    }
+}
+
+/// Requests and time taken by one scan
+#[derive(Debug, Clone, Copy)]
+struct ScanStats {
+    requests: usize,
+    elapsed: Duration,
+}
+
+/// Tags of one PLC read over a pool of connections
+struct Poller {
+    name: String,
+    tags: Vec<Tag>,
+    merge_gap: u32,
+    plan: Plan,
+    connections: Vec<Connection>,
+}
+
+impl Poller {
+    fn new(connection: &S7Connection) -> Result<Self> {
+        Ok(Self {
+            name: connection.name.clone(),
+            tags: collect_tags(connection)?,
+            merge_gap: connection.merge_gap,
+            plan: Plan::default(),
+            connections: Vec::new(),
+        })
+    }
+
+    /// Open the pool and plan requests for the smallest negotiated PDU
+    async fn connect(&mut self, connection: &S7Connection, timeout: Duration) -> Result<()> {
+        self.connections.clear();
+        for _ in 0..connection.pool_size {
+            self.connections.push(Connection::open(connection, timeout).await?);
+        }
+        let pdu_size = self.replan(connection);
+        info!(
+            "S7 '{}' connected with {} connections, PDU {} bytes: {} tags in {} requests",
+            self.name,
+            self.connections.len(),
+            pdu_size,
+            self.tags.len(),
+            self.plan.requests.len()
+        );
+        Ok(())
+    }
+
+    /// Plan requests for the smallest PDU negotiated by the pool
+    fn replan(&mut self, connection: &S7Connection) -> u16 {
+        let pdu_size = self.connections.iter().map(|c| c.pdu_size).min().unwrap_or(connection.pdu_size);
+        self.plan = Plan::new(
+            &self.tags,
+            PlanLimits {
+                pdu_size,
+                merge_gap: Some(self.merge_gap),
+                max_items: MAX_ITEMS,
+            },
+        );
+        pdu_size
+    }
+
+    /// Switch from `current` to the tags of `update`, keeping the pool
+    /// unless the PLC has to be reached differently
+    fn reconfigure(&mut self, current: &S7Connection, update: &S7Connection) -> Result<()> {
+        self.tags = collect_tags(update)?;
+        self.merge_gap = update.merge_gap;
+        let same_endpoint = (&current.ip, current.port, current.rack, current.slot, &current.connection_type)
+            == (&update.ip, update.port, update.rack, update.slot, &update.connection_type)
+            && (current.pool_size, current.pdu_size) == (update.pool_size, update.pdu_size);
+        if same_endpoint {
+            self.replan(update);
+        } else {
+            self.connections.clear();
+        }
+        Ok(())
+    }
+
+    /// Read every tag once and write the values to `bus`
+    async fn scan(&mut self, bus: &SignalBus) -> Result<ScanStats> {
+        let started = Instant::now();
+        let pool = self.connections.len().max(1);
+        let mut tasks = JoinSet::new();
+        for (member, mut connection) in self.connections.drain(..).enumerate() {
+            let requests: Vec<Vec<Item>> = self.plan.requests.iter().skip(member).step_by(pool).cloned().collect();
+            tasks.spawn(async move {
+                let result = connection.read(&requests).await;
+                (member, connection, result)
+            });
+        }
+
+        let mut data = vec![Vec::new(); self.plan.requests.len()];
+        let mut failure = None;
+        while let Some(joined) = tasks.join_next().await {
+            let (member, connection, result) = joined.map_err(|e| PlcError::Runtime(format!("S7 read task failed: {e}")))?;
+            match result {
+                Ok(responses) => {
+                    for (position, items) in responses.into_iter().enumerate() {
+                        data[member + position * pool] = items;
+                    }
+                    self.connections.push(connection);
+                }
+                Err(e) => failure = Some(e),
+            }
+        }
+        if let Some(e) = failure {
+            self.connections.clear();
+            return Err(e);
+        }
+
+        scan_budget::measure(Subsystem::Protocols, || {
+            for placement in &self.plan.placements {
+                let tag = &self.tags[placement.tag];
+                let size = usize::try_from(tag.data_type.size()).unwrap_or(1);
+                let Some(bytes) = data[placement.request][placement.item].get(placement.offset..placement.offset + size)
+                else {
+                    continue;
+                };
+                if let Err(e) = bus.set(&tag.signal, tag.data_type.decode(bytes, tag.bit)) {
+                    warn!("S7 could not write '{}': {}", tag.signal, e);
+                }
+            }
+        });
+        Ok(ScanStats {
+            requests: self.plan.requests.len(),
+            elapsed: started.elapsed(),
+        })
+    }
+}
+
+fn poll_ticker(connection: &S7Connection) -> tokio::time::Interval {
+    let mut ticker = tokio::time::interval(Duration::from_millis(connection.poll_interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    ticker
+}
+
+/// Poll one PLC, switching to every configuration sent through `updates`
+async fn run_connection(mut updates: watch::Receiver<S7Connection>, timeout: Duration, bus: SignalBus) -> Result<()> {
+    let mut connection = updates.borrow_and_update().clone();
+    let mut poller = Poller::new(&connection)?;
+    let mut backoff = Duration::from_secs(1);
+    let mut ticker = poll_ticker(&connection);
+    let mut pending = None;
+    loop {
+        if let Some(update) = pending.take() {
+            match poller.reconfigure(&connection, &update) {
+                Ok(()) => {
+                    connection = update;
+                    ticker = poll_ticker(&connection);
+                    info!(
+                        "S7 '{}' reconfigured: {} tags every {} ms",
+                        connection.name,
+                        poller.tags.len(),
+                        connection.poll_interval_ms
+                    );
+                }
+                Err(e) => warn!("S7 '{}' keeps its previous configuration: {}", connection.name, e),
+            }
+        }
+        if poller.connections.is_empty() {
+            if let Err(e) = poller.connect(&connection, timeout).await {
+                warn!("S7 '{}' at {} unreachable: {}", connection.name, connection.ip, e);
+                tokio::select! {
+                    () = tokio::time::sleep(backoff) => {}
+                    Ok(()) = updates.changed() => pending = Some(updates.borrow().clone()),
+                }
+                backoff = (backoff * 2).min(Duration::from_mins(1));
+                continue;
+            }
+            backoff = Duration::from_secs(1);
+        }
+        tokio::select! {
+            _ = ticker.tick() => {}
+            Ok(()) = updates.changed() => {
+                pending = Some(updates.borrow().clone());
+                continue;
+            }
+        }
+        match poller.scan(&bus).await {
+            Ok(stats) => debug!(
+                "S7 '{}' scanned {} tags with {} requests in {:?}",
+                connection.name,
+                poller.tags.len(),
+                stats.requests,
+                stats.elapsed
+            ),
+            Err(e) => warn!("S7 '{}' scan failed, reconnecting: {}", connection.name, e),
+        }
+    }
+}
+
+/// Poll every configured PLC until the task is cancelled
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if the configuration is invalid.
+pub async fn run(config: S7Config, bus: SignalBus) -> Result<()> {
+    config.validate()?;
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut tasks = JoinSet::new();
+    for connection in config.connections {
+        let (_, updates) = watch::channel(connection);
+        tasks.spawn(run_connection(updates, timeout, bus.clone()));
+    }
+    while let Some(joined) = tasks.join_next().await {
+        joined.map_err(|e| PlcError::Runtime(format!("S7 connection task failed: {e}")))??;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// DRIVER
+// ============================================================================
+
+/// One PLC polled in the background, registered with the
+/// [`ProtocolManager`](super::ProtocolManager) so its poll rate and tags can
+/// be changed with [`reload_driver`](super::ProtocolManager::reload_driver)
+///
+/// Reads return the values of the last scan; the driver does not write.
 pub struct S7Driver {
-    // Implementation details
+    connection: S7Connection,
+    timeout: Duration,
+    bus: SignalBus,
+    updates: watch::Sender<S7Connection>,
+    task: Option<JoinHandle<Result<()>>>,
+}
+
+impl S7Driver {
+    /// Driver for `connection`, polling into `bus` once connected
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the connection is invalid.
+    pub fn new(connection: S7Connection, timeout: Duration, bus: SignalBus) -> Result<Self> {
+        validate(&connection)?;
+        let (updates, _) = watch::channel(connection.clone());
+        Ok(Self {
+            connection,
+            timeout,
+            bus,
+            updates,
+            task: None,
+        })
+    }
+
+    /// Drivers for every PLC of `config`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid.
+    pub fn from_config(config: &S7Config, bus: &SignalBus) -> Result<Vec<Self>> {
+        config.validate()?;
+        let timeout = Duration::from_millis(config.timeout_ms);
+        config
+            .connections
+            .iter()
+            .map(|connection| Self::new(connection.clone(), timeout, bus.clone()))
+            .collect()
+    }
+
+    /// Name the driver is registered under
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.connection.name
+    }
+}
+
+/// Check one connection, including its tags and symbol sources
+fn validate(connection: &S7Connection) -> Result<()> {
+    S7Config {
+        connections: vec![connection.clone()],
+        timeout_ms: 0,
+        retry_count: 0,
+    }
+    .validate()?;
+    collect_tags(connection).map(|_| ())
 }
 
 #[async_trait]
-impl crate::protocols::ProtocolDriver for S7Driver {
+impl super::ProtocolDriver for S7Driver {
     async fn connect(&mut self) -> Result<()> {
-        // TODO: Implement connection logic
+        if !self.is_connected() {
+            let updates = self.updates.subscribe();
+            self.task = Some(tokio::spawn(run_connection(updates, self.timeout, self.bus.clone())));
+        }
         Ok(())
     }
 
     async fn disconnect(&mut self) -> Result<()> {
-        // TODO: Implement disconnection logic
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
         Ok(())
     }
 
-    async fn read_values(&self, _addresses: &[String]) -> Result<HashMap<String, Value>> {
-        // TODO: Implement read logic
-        Ok(HashMap::new())
+    async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+        Ok(addresses
+            .iter()
+            .filter_map(|signal| self.bus.get(signal).map(|value| (signal.clone(), value)))
+            .collect())
     }
 
     async fn write_values(&mut self, _values: &HashMap<String, Value>) -> Result<()> {
-        // TODO: Implement write logic
-        Ok(())
+        Err(s7_error("writing to the PLC is not supported"))
     }
 
     fn is_connected(&self) -> bool {
-        false
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
     }
 
     fn protocol_name(&self) -> &'static str {
         "s7"
     }
+
+    /// Takes an S7 connection, e.g. with a new `poll_interval_ms` or
+    /// `tags`. The pool stays open unless the endpoint, `pool_size` or
+    /// `pdu_size` change.
+    async fn reconfigure(&mut self, config: &serde_yaml::Value) -> Result<()> {
+        let connection: S7Connection = serde_yaml::from_value(config.clone())
+            .map_err(|e| PlcError::Config(format!("Invalid S7 connection: {e}")))?;
+        if connection.name != self.connection.name {
+            return Err(PlcError::Config(format!(
+                "S7 connection '{}' cannot be renamed to '{}'",
+                self.connection.name, connection.name
+            )));
+        }
+        validate(&connection)?;
+        self.updates.send_replace(connection.clone());
+        self.connection = connection;
+        Ok(())
+    }
 }
 
-/// Test connectivity to an S7 PLC.
-/// This is a lightweight stub used by the command-line interface.
-pub async fn test_connection(_address: &str, _rack: u16, _slot: u16) -> Result<()> {
-    info!("S7 test_connection stub called");
+/// Test connectivity to an S7 PLC and report the negotiated limits
+///
+/// # Errors
+///
+/// Fails if the PLC cannot be reached or refuses the connection.
+pub async fn test_connection(address: &str, rack: u16, slot: u16) -> Result<()> {
+    let (ip, port) = match address.rsplit_once(':') {
+        Some((ip, port)) => (ip, port.parse().map_err(|_| PlcError::Config(format!("Invalid port in '{address}'")))?),
+        None => (address, 102),
+    };
+    let connection = S7Connection {
+        name: "test".to_string(),
+        ip: ip.to_string(),
+        rack,
+        slot,
+        connection_type: "PG".to_string(),
+        port,
+        poll_interval_ms: 1000,
+        pool_size: 1,
+        pdu_size: 960,
+        merge_gap: 0,
+        data_areas: Vec::new(),
+        tags: Vec::new(),
+        symbol_sources: Vec::new(),
+    };
+    let started = Instant::now();
+    let session = Connection::open(&connection, Duration::from_secs(5)).await?;
+    println!(
+        "Connected to S7 rack {rack} slot {slot} at {address} in {:.1} ms: PDU {} bytes, {} parallel jobs",
+        started.elapsed().as_secs_f64() * 1000.0,
+        session.pdu_size,
+        session.max_jobs
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::S7Tag;
+    use crate::protocols::ProtocolManager;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+
+    /// Byte at `offset` of every area of the simulated PLC
+    fn memory(offset: u32) -> u8 {
+        u8::try_from(offset * 7 % 251).unwrap()
+    }
+
+    /// Simulated PLC answering each Read Var after `latency`, counting the
+    /// requests it received
+    async fn plc(pdu_size: u16, jobs: u16, latency: Duration) -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let (mut reader, writer) = stream.into_split();
+                    let writer = Arc::new(Mutex::new(writer));
+                    loop {
+                        let mut header = [0; 4];
+                        if reader.read_exact(&mut header).await.is_err() {
+                            return;
+                        }
+                        let mut payload = vec![0; usize::from(u16::from_be_bytes([header[2], header[3]])) - 4];
+                        reader.read_exact(&mut payload).await.unwrap();
+                        if payload[1] == 0xE0 {
+                            let confirm = tpkt(&[0x06, COTP_CONNECT_CONFIRM, 0, 1, 0, 1, 0]);
+                            writer.lock().await.write_all(&confirm).await.unwrap();
+                            continue;
+                        }
+                        let s7 = &payload[3..];
+                        let pdu_ref = [s7[4], s7[5]];
+                        let params = &s7[10..];
+                        let (params, data) = if params[0] == FUNCTION_SETUP {
+                            let mut setup = vec![FUNCTION_SETUP, 0];
+                            setup.extend_from_slice(&jobs.to_be_bytes());
+                            setup.extend_from_slice(&jobs.to_be_bytes());
+                            setup.extend_from_slice(&pdu_size.to_be_bytes());
+                            (setup, Vec::new())
+                        } else {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            let count = usize::from(params[1]);
+                            let mut data = Vec::new();
+                            for spec in params[2..].chunks(12).take(count) {
+                                let length = u32::from(u16::from_be_bytes([spec[4], spec[5]]));
+                                let start = u32::from_be_bytes([0, spec[9], spec[10], spec[11]]) >> 3;
+                                data.extend_from_slice(&[RETURN_SUCCESS, 0x04]);
+                                data.extend_from_slice(&u16::try_from(length * 8).unwrap().to_be_bytes());
+                                data.extend((start..start + length).map(memory));
+                                if length % 2 == 1 {
+                                    data.push(0);
+                                }
+                            }
+                            (vec![FUNCTION_READ_VAR, params[1]], data)
+                        };
+                        let mut response = COTP_DATA.to_vec();
+                        response.extend_from_slice(&[S7_PROTOCOL_ID, ROSCTR_ACK_DATA, 0, 0, pdu_ref[0], pdu_ref[1]]);
+                        response.extend_from_slice(&u16::try_from(params.len()).unwrap().to_be_bytes());
+                        response.extend_from_slice(&u16::try_from(data.len()).unwrap().to_be_bytes());
+                        response.extend_from_slice(&[0, 0]);
+                        response.extend_from_slice(&params);
+                        response.extend_from_slice(&data);
+                        let writer = writer.clone();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(latency).await;
+                            writer.lock().await.write_all(&tpkt(&response)).await.unwrap();
+                        });
+                    }
+                });
+            }
+        });
+        (port, requests)
+    }
+
+    fn tag(signal: String, area: &str, db_number: u16, offset: u32, data_type: &str) -> S7Tag {
+        S7Tag {
+            signal,
+            symbol: None,
+            area: area.to_string(),
+            db_number,
+            offset,
+            bit: 0,
+            data_type: data_type.to_string(),
+        }
+    }
+
+    /// 150 reals, 25 ints far apart and 50 flag bits
+    fn connection(port: u16, pool_size: usize) -> S7Connection {
+        let mut tags: Vec<_> = (0..150).map(|i| tag(format!("db1.r{i}"), "DB", 1, i * 4, "real")).collect();
+        tags.extend((0..25).map(|i| tag(format!("db2.i{i}"), "DB", 2, i * 200, "int")));
+        tags.extend((0..50).map(|i| S7Tag {
+            bit: u8::try_from(i % 8).unwrap(),
+            ..tag(format!("m.b{i}"), "MB", 0, 10 + i / 8, "bool")
+        }));
+        S7Connection {
+            name: "plc".to_string(),
+            ip: "127.0.0.1".to_string(),
+            rack: 0,
+            slot: 1,
+            connection_type: "PG".to_string(),
+            port,
+            poll_interval_ms: 100,
+            pool_size,
+            pdu_size: 960,
+            merge_gap: 16,
+            data_areas: Vec::new(),
+            tags,
+            symbol_sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_plan_fits_pdu() {
+        let tags = collect_tags(&connection(102, 1)).unwrap();
+        let limits = PlanLimits {
+            pdu_size: 240,
+            merge_gap: Some(16),
+            max_items: MAX_ITEMS,
+        };
+        let plan = Plan::new(&tags, limits);
+        assert_eq!(plan.placements.len(), tags.len());
+        for request in &plan.requests {
+            assert!(REQUEST_HEADER + request.len() * REQUEST_ITEM <= 240);
+            assert!(RESPONSE_HEADER + request.iter().map(Item::response_size).sum::<usize>() <= 240);
+        }
+        // 600 bytes of reals need three 222 byte items; the ints are 200
+        // bytes apart and stay separate; the flag bits share one item
+        assert_eq!(plan.requests.iter().flatten().count(), 3 + 25 + 1);
+        assert!(plan.requests.len() <= 5);
+
+        let naive = Plan::new(&tags, PlanLimits { merge_gap: None, max_items: 1, ..limits });
+        assert_eq!(naive.requests.len(), tags.len());
+        assert_eq!(DataType::Real.decode(&1.5f32.to_be_bytes(), 0), Value::Float(1.5));
+        assert_eq!(DataType::LReal.decode(&(-2.25f64).to_be_bytes(), 0), Value::Float(-2.25));
+        assert_eq!(DataType::Int.decode(&[0xFF, 0xFE], 0), Value::Integer(-2));
+        assert_eq!(DataType::Bool.decode(&[0b1000], 3), Value::Bool(true));
+    }
+
+    #[tokio::test]
+    async fn test_packed_pipelined_scan_beats_tag_by_tag() {
+        let latency = Duration::from_millis(2);
+        let (port, requests) = plc(240, 4, latency).await;
+        let timeout = Duration::from_secs(2);
+
+        // One tag per request, one request at a time
+        let bus = SignalBus::new();
+        let config = connection(port, 1);
+        let mut naive = Poller::new(&config).unwrap();
+        naive.connect(&config, timeout).await.unwrap();
+        naive.connections[0].max_jobs = 1;
+        naive.plan = Plan::new(
+            &naive.tags,
+            PlanLimits {
+                pdu_size: 240,
+                merge_gap: None,
+                max_items: 1,
+            },
+        );
+        let slow = naive.scan(&bus).await.unwrap();
+        assert_eq!(requests.swap(0, Ordering::SeqCst), 225);
+
+        let bus = SignalBus::new();
+        let config = connection(port, 2);
+        let mut poller = Poller::new(&config).unwrap();
+        poller.connect(&config, timeout).await.unwrap();
+        assert_eq!(poller.connections[0].pdu_size, 240);
+        let fast = poller.scan(&bus).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), fast.requests);
+        assert!(fast.requests * 40 <= slow.requests, "{fast:?} vs {slow:?}");
+        assert!(fast.elapsed * 10 < slow.elapsed, "{fast:?} vs {slow:?}");
+
+        let real = f32::from_be_bytes([memory(40), memory(41), memory(42), memory(43)]);
+        assert_eq!(bus.get("db1.r10"), Some(Value::Float(f64::from(real))));
+        let int = i16::from_be_bytes([memory(4800), memory(4801)]);
+        assert_eq!(bus.get("db2.i24"), Some(Value::Integer(i64::from(int))));
+        assert_eq!(bus.get("m.b13"), Some(Value::Bool(memory(11) >> 5 & 1 == 1)));
+    }
+
+    /// Value of `signal` once a scan wrote it
+    async fn scanned(bus: &SignalBus, signal: &str) -> Value {
+        let poll = async {
+            loop {
+                if let Some(value) = bus.get(signal) {
+                    return value;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(2), poll).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reload_changes_poll_rate_and_tags() {
+        let (port, requests) = plc(240, 4, Duration::ZERO).await;
+        let bus = SignalBus::new();
+        let mut config = connection(port, 1);
+        config.poll_interval_ms = 60_000;
+        config.tags = vec![tag("first".to_string(), "DB", 1, 0, "int")];
+        let driver = S7Driver::new(config.clone(), Duration::from_secs(2), bus.clone()).unwrap();
+        let manager = ProtocolManager::new(bus.clone());
+        manager.add_driver("plc".to_string(), Box::new(driver)).await.unwrap();
+        manager.connect_all().await.unwrap();
+        scanned(&bus, "first").await;
+
+        // Rejected parameters leave the driver as it was
+        let mut invalid = config.clone();
+        invalid.tags[0].data_type = "string".to_string();
+        let result = manager.reload_driver("plc", &serde_yaml::to_value(&invalid).unwrap()).await;
+        assert!(matches!(result, Err(PlcError::Config(_))));
+
+        config.poll_interval_ms = 10;
+        config.tags = vec![tag("second".to_string(), "DB", 1, 8, "int")];
+        manager.reload_driver("plc", &serde_yaml::to_value(&config).unwrap()).await.unwrap();
+        let int = i16::from_be_bytes([memory(8), memory(9)]);
+        assert_eq!(scanned(&bus, "second").await, Value::Integer(i64::from(int)));
+        let before = requests.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(requests.load(Ordering::SeqCst) - before >= 5);
+        assert!(manager.is_connected("plc").await);
+    }
+}
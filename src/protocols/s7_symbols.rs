@@ -0,0 +1,750 @@
+//! Symbolic S7 addressing from TIA Portal DB sources
+//!
+//! TIA Portal exports data blocks as text sources ("Generate source from
+//! blocks", `.db` files). For a DB with standard (non-optimized) access the
+//! absolute address of every member follows from its declaration, so tags can
+//! name a member instead of an offset:
+//!
+//! ```yaml
+//! connections:
+//!   - name: press_line
+//!     ip: 10.0.3.15
+//!     rack: 0
+//!     slot: 1
+//!     symbol_sources:
+//!       - { file: plc/Press.db, db_number: 10 }
+//!       - { file: plc/Recipe.db, db_number: 11, signal_prefix: recipe }
+//!     tags:
+//!       - { signal: press.force, symbol: '"Press".Force' }
+//!       - { signal: press.motor_on, symbol: '"Press".Motor.On' }
+//! ```
+//!
+//! A source with a `signal_prefix` imports every readable member as
+//! `<signal_prefix>.<member path>`, with array indices as path segments
+//! (`recipe.Steps.3.Time`). Tags configured explicitly keep their own signal.
+//!
+//! Offsets are laid out with the standard access rules: bools are packed
+//! into bits, bytes and chars start on the next byte, every other type,
+//! array and struct starts on an even byte, and arrays and structs occupy an
+//! even number of bytes. UDTs declared in the same source file (export with
+//! dependent types) can be used as member types or as the type of the DB.
+//! Blocks with optimized access have no fixed layout and are rejected.
+
+use crate::config::{S7Connection, S7Tag};
+use crate::{PlcError, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::debug;
+
+/// Largest data block with standard access
+const MAX_DB_SIZE: u32 = 65_536;
+
+/// Nesting limit for structs and UDTs, which also stops recursive UDTs
+const MAX_DEPTH: usize = 16;
+
+// ============================================================================
+// SOURCE TOKENS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Keyword, identifier or literal
+    Word(String),
+    /// `"name"` of a block, type or member
+    Quoted(String),
+    /// `'text'`
+    Text(String),
+    /// `{ attribute := 'value'; ... }`
+    Attributes(String),
+    /// Operator or separator
+    Punct(String),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '#'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '(' && next == Some('*') {
+            i = (i + 2..chars.len().saturating_sub(1))
+                .find(|&j| chars[j] == '*' && chars[j + 1] == ')')
+                .ok_or_else(|| PlcError::Config("Unterminated comment in DB source".to_string()))?
+                + 2;
+        } else if matches!(c, '"' | '\'' | '{') {
+            let close = if c == '{' { '}' } else { c };
+            let end = (i + 1..chars.len())
+                .find(|&j| chars[j] == close)
+                .ok_or_else(|| PlcError::Config(format!("Unterminated {c} in DB source")))?;
+            let text: String = chars[i + 1..end].iter().collect();
+            tokens.push(match c {
+                '"' => Token::Quoted(text),
+                '\'' => Token::Text(text),
+                _ => Token::Attributes(text),
+            });
+            i = end + 1;
+        } else if is_word_char(c) {
+            let start = i;
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            // Decimal literals such as `VERSION : 0.1`, but not `0..7`
+            if chars[start].is_ascii_digit()
+                && chars.get(i) == Some(&'.')
+                && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+            {
+                i += 1;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else {
+            let pair: String = [c, next.unwrap_or(' ')].iter().collect();
+            if pair == ".." || pair == ":=" {
+                tokens.push(Token::Punct(pair));
+                i += 2;
+            } else {
+                tokens.push(Token::Punct(c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// ============================================================================
+// DECLARATIONS
+// ============================================================================
+
+#[derive(Debug, Clone)]
+enum Type {
+    /// Single value of `bits` bits, readable by the driver as `data_type`
+    Elementary {
+        name: String,
+        bits: u32,
+        data_type: Option<&'static str>,
+    },
+    Array {
+        dims: Vec<(i64, i64)>,
+        element: Box<Type>,
+    },
+    Struct(Vec<Member>),
+    Udt(String),
+}
+
+#[derive(Debug, Clone)]
+struct Member {
+    name: String,
+    ty: Type,
+}
+
+/// Size in bits and driver data type of an elementary type
+fn elementary(name: &str) -> Option<(u32, Option<&'static str>)> {
+    let found = match name.to_uppercase().as_str() {
+        "BOOL" => (1, Some("bool")),
+        "BYTE" | "CHAR" | "USINT" => (8, Some("byte")),
+        "SINT" => (8, None),
+        "WORD" | "UINT" | "DATE" | "S5TIME" => (16, Some("word")),
+        "INT" => (16, Some("int")),
+        "DWORD" | "UDINT" | "TIME_OF_DAY" | "TOD" => (32, Some("dword")),
+        "DINT" | "TIME" => (32, Some("dint")),
+        "REAL" => (32, Some("real")),
+        "LREAL" => (64, Some("lreal")),
+        "LWORD" | "LINT" | "ULINT" | "LTIME" | "DATE_AND_TIME" | "DT" => (64, None),
+        _ => return None,
+    };
+    Some(found)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn at_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if p == punct)
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<()> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == punct => Ok(()),
+            other => Err(unexpected(punct, other.as_ref())),
+        }
+    }
+
+    fn skip_attributes(&mut self) {
+        while matches!(self.peek(), Some(Token::Attributes(_))) {
+            self.pos += 1;
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(name) | Token::Quoted(name)) => Ok(name),
+            other => Err(unexpected("a name", other.as_ref())),
+        }
+    }
+
+    /// Members up to and including `END_STRUCT`
+    fn members(&mut self) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        while !self.at_keyword("END_STRUCT") {
+            if self.peek().is_none() {
+                return Err(PlcError::Config("DB source ends inside a STRUCT".to_string()));
+            }
+            let name = self.name()?;
+            self.skip_attributes();
+            self.expect(":")?;
+            let ty = self.declared_type()?;
+            if self.at_punct(":=") {
+                // Start values do not affect the layout
+                while !self.at_punct(";") && self.peek().is_some() {
+                    self.pos += 1;
+                }
+            }
+            self.expect(";")?;
+            members.push(Member { name, ty });
+        }
+        self.pos += 1;
+        Ok(members)
+    }
+
+    fn declared_type(&mut self) -> Result<Type> {
+        match self.next() {
+            Some(Token::Quoted(udt)) => Ok(Type::Udt(udt)),
+            Some(Token::Word(word)) => match word.to_uppercase().as_str() {
+                "STRUCT" => Ok(Type::Struct(self.members()?)),
+                "ARRAY" => {
+                    self.expect("[")?;
+                    let mut dims = vec![self.range()?];
+                    while self.at_punct(",") {
+                        self.pos += 1;
+                        dims.push(self.range()?);
+                    }
+                    self.expect("]")?;
+                    match self.next() {
+                        Some(Token::Word(of)) if of.eq_ignore_ascii_case("OF") => {}
+                        other => return Err(unexpected("OF", other.as_ref())),
+                    }
+                    Ok(Type::Array {
+                        dims,
+                        element: Box::new(self.declared_type()?),
+                    })
+                }
+                upper @ ("STRING" | "WSTRING") => {
+                    let length = if self.at_punct("[") {
+                        self.pos += 1;
+                        let length = self.integer()?;
+                        self.expect("]")?;
+                        u32::try_from(length).map_err(|_| PlcError::Config(format!("Invalid {word} length {length}")))?
+                    } else {
+                        254
+                    };
+                    // Maximum and actual length header, then the characters
+                    let bits = if upper == "STRING" { (length + 2) * 8 } else { (length + 2) * 16 };
+                    Ok(Type::Elementary {
+                        name: format!("{word}[{length}]"),
+                        bits,
+                        data_type: None,
+                    })
+                }
+                _ => {
+                    let (bits, data_type) = elementary(&word)
+                        .ok_or_else(|| PlcError::Config(format!("Unsupported data type '{word}' in DB source")))?;
+                    Ok(Type::Elementary { name: word, bits, data_type })
+                }
+            },
+            other => Err(unexpected("a data type", other.as_ref())),
+        }
+    }
+
+    fn range(&mut self) -> Result<(i64, i64)> {
+        let low = self.integer()?;
+        self.expect("..")?;
+        let high = self.integer()?;
+        if high < low {
+            return Err(PlcError::Config(format!("Invalid array bounds {low}..{high}")));
+        }
+        Ok((low, high))
+    }
+
+    fn integer(&mut self) -> Result<i64> {
+        let negative = self.at_punct("-");
+        if negative {
+            self.pos += 1;
+        }
+        match self.next() {
+            Some(Token::Word(digits)) => digits
+                .parse::<i64>()
+                .map(|n| if negative { -n } else { n })
+                .map_err(|_| PlcError::Config(format!("Expected a number in DB source, found '{digits}'"))),
+            other => Err(unexpected("a number", other.as_ref())),
+        }
+    }
+}
+
+fn unexpected(expected: &str, found: Option<&Token>) -> PlcError {
+    match found {
+        Some(token) => PlcError::Config(format!("Expected {expected} in DB source, found {token:?}")),
+        None => PlcError::Config(format!("Expected {expected} in DB source, found the end of the file")),
+    }
+}
+
+fn optimized_access(attributes: &str) -> bool {
+    let compact: String = attributes.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.to_lowercase().contains("s7_optimized_access:='true'")
+}
+
+// ============================================================================
+// LAYOUT
+// ============================================================================
+
+/// Member of a data block at its absolute address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Member path inside the block, e.g. `Motor.Speed` or `Flags[3]`
+    pub path: String,
+    /// Declared type
+    pub type_name: String,
+    /// Driver data type, if the driver can read the member
+    pub data_type: Option<&'static str>,
+    /// Byte offset in the block
+    pub offset: u32,
+    /// Bit in the byte, for bools
+    pub bit: u8,
+}
+
+struct Layout<'a> {
+    types: &'a HashMap<String, Vec<Member>>,
+    bits: u32,
+    symbols: Vec<Symbol>,
+}
+
+impl Layout<'_> {
+    fn align(&mut self, bytes: u32) {
+        let unit = bytes * 8;
+        self.bits = self.bits.div_ceil(unit) * unit;
+    }
+
+    fn place(&mut self, path: String, ty: &Type, depth: usize) -> Result<()> {
+        if self.bits > MAX_DB_SIZE * 8 {
+            return Err(PlcError::Config(format!("DB source is larger than {MAX_DB_SIZE} bytes")));
+        }
+        if depth > MAX_DEPTH {
+            return Err(PlcError::Config(format!("'{path}' is nested too deeply, or uses a recursive UDT")));
+        }
+        match ty {
+            Type::Elementary { name, bits, data_type } => {
+                match *bits {
+                    1 => {}
+                    8 => self.align(1),
+                    _ => self.align(2),
+                }
+                self.symbols.push(Symbol {
+                    path,
+                    type_name: name.clone(),
+                    data_type: *data_type,
+                    offset: self.bits / 8,
+                    bit: u8::try_from(self.bits % 8).unwrap_or_default(),
+                });
+                self.bits += bits;
+            }
+            Type::Array { dims, element } => {
+                let count = dims.iter().try_fold(1u64, |count, (low, high)| {
+                    count.checked_mul(high.abs_diff(*low) + 1)
+                });
+                if count.is_none_or(|count| count > u64::from(MAX_DB_SIZE) * 8) {
+                    return Err(PlcError::Config(format!("Array '{path}' is larger than a DB")));
+                }
+                self.align(2);
+                let mut index: Vec<i64> = dims.iter().map(|(low, _)| *low).collect();
+                loop {
+                    let indices: Vec<String> = index.iter().map(ToString::to_string).collect();
+                    self.place(format!("{path}[{}]", indices.join(",")), element, depth + 1)?;
+                    // Last index varies fastest
+                    let Some(dim) = (0..dims.len()).rev().find(|&d| index[d] < dims[d].1) else {
+                        break;
+                    };
+                    index[dim] += 1;
+                    for (d, value) in index.iter_mut().enumerate().skip(dim + 1) {
+                        *value = dims[d].0;
+                    }
+                }
+                self.align(2);
+            }
+            Type::Struct(members) => self.place_members(&path, members, depth)?,
+            Type::Udt(name) => {
+                let members = self
+                    .types
+                    .get(&name.to_lowercase())
+                    .ok_or_else(|| PlcError::Config(format!("Type \"{name}\" of '{path}' is not declared in the source")))?;
+                self.place_members(&path, members, depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn place_members(&mut self, path: &str, members: &[Member], depth: usize) -> Result<()> {
+        self.align(2);
+        for member in members {
+            let member_path = if path.is_empty() {
+                member.name.clone()
+            } else {
+                format!("{path}.{}", member.name)
+            };
+            self.place(member_path, &member.ty, depth + 1)?;
+        }
+        self.align(2);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// DATA BLOCKS
+// ============================================================================
+
+/// A data block parsed from its source, with the address of every member
+#[derive(Debug, Clone)]
+pub struct DataBlock {
+    /// Block name, without quotes
+    pub name: String,
+    /// Size of the block in bytes
+    pub size: u32,
+    /// Elementary members in address order
+    pub symbols: Vec<Symbol>,
+}
+
+impl DataBlock {
+    /// Read and parse a DB source file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] naming the file if it cannot be read
+    /// or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| PlcError::Config(format!("Cannot read S7 DB source '{}': {e}", path.display())))?;
+        Self::parse(&source).map_err(|e| match e {
+            PlcError::Config(message) => PlcError::Config(format!("{}: {message}", path.display())),
+            other => other,
+        })
+    }
+
+    /// Parse a source with one `DATA_BLOCK` and the UDTs it uses
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for syntax errors, unknown types, blocks
+    /// with optimized access and sources without exactly one data block.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let mut types = HashMap::new();
+        let mut block: Option<(String, Type)> = None;
+        while let Some(token) = parser.next() {
+            let Token::Word(keyword) = token else {
+                continue;
+            };
+            if keyword.eq_ignore_ascii_case("TYPE") {
+                let name = parser.name()?;
+                while !parser.at_keyword("STRUCT") {
+                    if parser.next().is_none() {
+                        return Err(PlcError::Config(format!("Type \"{name}\" has no STRUCT")));
+                    }
+                }
+                parser.pos += 1;
+                types.insert(name.to_lowercase(), parser.members()?);
+            } else if keyword.eq_ignore_ascii_case("DATA_BLOCK") {
+                let name = parser.name()?;
+                if let Some((first, _)) = &block {
+                    return Err(PlcError::Config(format!(
+                        "Source declares both \"{first}\" and \"{name}\"; export one data block per file"
+                    )));
+                }
+                let ty = loop {
+                    match parser.next() {
+                        Some(Token::Attributes(attributes)) if optimized_access(&attributes) => {
+                            return Err(PlcError::Config(format!(
+                                "\"{name}\" uses optimized block access and has no absolute addresses"
+                            )));
+                        }
+                        Some(Token::Word(word)) if word.eq_ignore_ascii_case("STRUCT") => {
+                            break Type::Struct(parser.members()?);
+                        }
+                        Some(Token::Quoted(udt)) => break Type::Udt(udt),
+                        Some(Token::Word(word)) if word.eq_ignore_ascii_case("BEGIN") => {
+                            return Err(PlcError::Config(format!("\"{name}\" declares no members")));
+                        }
+                        None => return Err(PlcError::Config(format!("\"{name}\" declares no members"))),
+                        Some(_) => {}
+                    }
+                };
+                block = Some((name, ty));
+            }
+        }
+
+        let (name, ty) = block.ok_or_else(|| PlcError::Config("Source contains no DATA_BLOCK".to_string()))?;
+        let mut layout = Layout {
+            types: &types,
+            bits: 0,
+            symbols: Vec::new(),
+        };
+        layout.place(String::new(), &ty, 0)?;
+        if layout.bits > MAX_DB_SIZE * 8 {
+            return Err(PlcError::Config(format!("\"{name}\" is larger than {MAX_DB_SIZE} bytes")));
+        }
+        Ok(Self {
+            name,
+            size: layout.bits / 8,
+            symbols: layout.symbols,
+        })
+    }
+
+    /// Member at `path`, ignoring case
+    #[must_use]
+    pub fn find(&self, path: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.path.eq_ignore_ascii_case(path))
+    }
+}
+
+/// Signal name for a member imported under `prefix`
+fn signal_name(prefix: &str, path: &str) -> String {
+    let mut signal = format!("{prefix}.");
+    for c in path.chars() {
+        match c {
+            '[' | ',' => signal.push('.'),
+            ']' => {}
+            c if c.is_alphanumeric() || c == '_' || c == '.' => signal.push(c),
+            _ => signal.push('_'),
+        }
+    }
+    signal
+}
+
+fn symbol_tag(signal: String, db_number: u16, symbol: &Symbol) -> Option<S7Tag> {
+    Some(S7Tag {
+        signal,
+        symbol: None,
+        area: "DB".to_string(),
+        db_number,
+        offset: symbol.offset,
+        bit: symbol.bit,
+        data_type: symbol.data_type?.to_string(),
+    })
+}
+
+/// Tags of a connection with symbols resolved to absolute addresses
+///
+/// Loads the connection's symbol sources, replaces each tag's `symbol` with
+/// the DB address it names and appends the members of sources that have a
+/// signal prefix.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] if a source cannot be loaded, a symbol is
+/// unknown or names a member of a type the driver cannot read.
+pub fn resolve_tags(connection: &S7Connection) -> Result<Vec<S7Tag>> {
+    let mut blocks = Vec::with_capacity(connection.symbol_sources.len());
+    for source in &connection.symbol_sources {
+        blocks.push((source, DataBlock::load(&source.file)?));
+    }
+    let index: HashMap<String, (u16, &Symbol)> = blocks
+        .iter()
+        .flat_map(|(source, block)| {
+            block
+                .symbols
+                .iter()
+                .map(|symbol| (format!("{}.{}", block.name, symbol.path).to_lowercase(), (source.db_number, symbol)))
+        })
+        .collect();
+
+    let mut tags = Vec::with_capacity(connection.tags.len());
+    for tag in &connection.tags {
+        let Some(reference) = &tag.symbol else {
+            tags.push(tag.clone());
+            continue;
+        };
+        let (db_number, symbol) = index.get(&reference.replace('"', "").to_lowercase()).ok_or_else(|| {
+            PlcError::Config(format!("S7 tag '{}' refers to unknown symbol {reference}", tag.signal))
+        })?;
+        tags.push(symbol_tag(tag.signal.clone(), *db_number, symbol).ok_or_else(|| {
+            PlcError::Config(format!(
+                "S7 tag '{}': {reference} is a {} and cannot be read",
+                tag.signal, symbol.type_name
+            ))
+        })?);
+    }
+
+    let configured: HashSet<String> = tags.iter().map(|tag| tag.signal.clone()).collect();
+    for (source, block) in &blocks {
+        let Some(prefix) = &source.signal_prefix else {
+            continue;
+        };
+        let before = tags.len();
+        tags.extend(
+            block
+                .symbols
+                .iter()
+                .map(|symbol| (signal_name(prefix, &symbol.path), symbol))
+                .filter(|(signal, _)| !configured.contains(signal))
+                .filter_map(|(signal, symbol)| symbol_tag(signal, source.db_number, symbol)),
+        );
+        debug!(
+            "S7 '{}' imported {} of {} members of \"{}\" as DB{}",
+            connection.name,
+            tags.len() - before,
+            block.symbols.len(),
+            block.name,
+            source.db_number
+        );
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::S7SymbolSource;
+
+    const UDT: &str = r#"
+TYPE "Axis"
+VERSION : 0.1
+   STRUCT
+      Enabled : Bool;
+      Position : Real;   // mm
+   END_STRUCT;
+
+END_TYPE
+"#;
+
+    const PRESS: &str = r#"
+DATA_BLOCK "Press"
+{ S7_Optimized_Access := 'FALSE' }
+AUTHOR : Lithos
+VERSION : 0.1
+NON_RETAIN
+   STRUCT
+      Force : Real;
+      Running { ExternalWritable := 'False'} : Bool;
+      Fault : Bool;
+      Mode : Byte;
+      Cycles : DInt := 0;
+      Motor : Struct
+         On : Bool;
+         Speed : Int;
+      END_STRUCT;
+      Flags : Array[0..9] of Bool;
+      Name : String[10];
+      Grid : Array[0..1, 1..2] of Int;
+      "Axis X" : "Axis";
+      Setpoint : LReal := 1.5;
+   END_STRUCT;
+
+BEGIN
+   Force := 0.0;
+   (* start values *)
+END_DATA_BLOCK
+"#;
+
+    #[test]
+    fn test_standard_access_layout() {
+        let block = DataBlock::parse(&format!("{UDT}{PRESS}")).unwrap();
+        assert_eq!(block.name, "Press");
+        let address = |path: &str| {
+            let symbol = block.find(path).unwrap_or_else(|| panic!("no {path}"));
+            (symbol.offset, symbol.bit)
+        };
+        assert_eq!(address("Force"), (0, 0));
+        assert_eq!(address("Running"), (4, 0));
+        assert_eq!(address("Fault"), (4, 1));
+        assert_eq!(address("Mode"), (5, 0));
+        assert_eq!(address("Cycles"), (6, 0));
+        assert_eq!(address("motor.on"), (10, 0));
+        assert_eq!(address("Motor.Speed"), (12, 0));
+        assert_eq!(address("Flags[0]"), (14, 0));
+        assert_eq!(address("Flags[9]"), (15, 1));
+        assert_eq!(address("Name"), (16, 0));
+        assert_eq!(address("Grid[0,1]"), (28, 0));
+        assert_eq!(address("Grid[1,2]"), (34, 0));
+        assert_eq!(address("Axis X.Enabled"), (36, 0));
+        assert_eq!(address("Axis X.Position"), (38, 0));
+        assert_eq!(address("Setpoint"), (42, 0));
+        assert_eq!(block.size, 50);
+        assert_eq!(block.find("Name").unwrap().data_type, None);
+
+        let optimized = PRESS.replace("'FALSE'", "'TRUE'");
+        assert!(DataBlock::parse(&format!("{UDT}{optimized}")).is_err());
+        assert!(DataBlock::parse(PRESS).is_err(), "Axis is not declared");
+    }
+
+    #[test]
+    fn test_symbols_resolve_to_db_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Press.db");
+        std::fs::write(&file, format!("{UDT}{PRESS}")).unwrap();
+        let connection: S7Connection = serde_yaml::from_str(
+            "name: press\nip: 127.0.0.1\nrack: 0\nslot: 1\n\
+             tags:\n  - { signal: press.force, symbol: '\"Press\".Force' }\n",
+        )
+        .unwrap();
+        let source = S7SymbolSource {
+            file,
+            db_number: 10,
+            signal_prefix: Some("db10".to_string()),
+        };
+        let connection = S7Connection {
+            symbol_sources: vec![source],
+            ..connection
+        };
+
+        let tags = resolve_tags(&connection).unwrap();
+        let force = &tags[0];
+        assert_eq!((force.signal.as_str(), force.db_number, force.offset), ("press.force", 10, 0));
+        assert_eq!(force.data_type, "real");
+        // Strings cannot be read and are not imported
+        assert_eq!(tags.iter().filter(|tag| tag.signal == "db10.Force").count(), 1);
+        assert!(tags.iter().all(|tag| tag.signal != "db10.Name"));
+        let flag = tags.iter().find(|tag| tag.signal == "db10.Flags.9").unwrap();
+        assert_eq!((flag.offset, flag.bit, flag.data_type.as_str()), (15, 1, "bool"));
+        assert!(tags.iter().any(|tag| tag.signal == "db10.Grid.1.2"));
+        assert!(tags.iter().any(|tag| tag.signal == "db10.Axis_X.Position"));
+
+        let unknown = S7Connection {
+            tags: vec![S7Tag {
+                symbol: Some("\"Press\".Missing".to_string()),
+                ..connection.tags[0].clone()
+            }],
+            ..connection
+        };
+        assert!(resolve_tags(&unknown).is_err());
+    }
+}
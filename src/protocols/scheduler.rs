@@ -0,0 +1,815 @@
+// src/protocols/scheduler.rs
+//! Scan-class polling of protocol addresses
+//!
+//! Large Modbus and S7 maps rarely need every address at the same rate. The
+//! scheduler assigns each point to a scan class with its own interval and
+//! polls the classes independently, so a slow class of configuration values
+//! never delays the fast class of process values:
+//!
+//! ```yaml
+//! protocols:
+//!   polling:
+//!     classes:
+//!       - { name: fast, interval_ms: 100 }
+//!       - { name: slow, interval_ms: 10000 }
+//!     max_batch: 64
+//!     points:
+//!       - { protocol: line1_plc, address: "DB1.DBD0", signal: line1.speed, class: fast }
+//!       - { protocol: line1_plc, address: "DB1.DBD4", signal: line1.setpoint, access: read_write, deadband: 0.5 }
+//!       - { protocol: line1_plc, address: "DB2.DBX0.0", signal: line1.recipe_loaded, class: slow }
+//!       - protocol: line1_plc
+//!         address: "DB1.DBD8"
+//!         signal: line1.max_pressure
+//!         access: write
+//!         verify: { tolerance: 0.01, timeout_ms: 2000, alarm_signal: line1.max_pressure_unverified }
+//! ```
+//!
+//! Without `classes` the scheduler uses `fast` (100 ms), `medium` (1 s) and
+//! `slow` (10 s); points default to `medium`. Each cycle of a class:
+//!
+//! - writes the signals of its writable points that moved by more than the
+//!   point's `deadband` since the value last written or read back, so noise
+//!   on a setpoint does not turn into bus traffic; a `read_write` point is
+//!   only written once its device value has been read
+//! - reads back the addresses of verified writes that have not been
+//!   confirmed yet; a value read back within the point's `tolerance`
+//!   confirms the write, and a write still unconfirmed after `timeout_ms`
+//!   publishes a `write_verify_failed` event, sets the optional
+//!   `alarm_signal` until a later write of the point is confirmed, and is
+//!   written again in the next cycle
+//! - reads its readable points with one request per driver, split into
+//!   requests of at most `max_batch` addresses, and stores the values in
+//!   their signals
+//!
+//! Reads and writes go through [`ProtocolManager`](super::ProtocolManager),
+//! so failover groups can be named as the `protocol` of a point.
+
+use super::ProtocolManager;
+use crate::error::{PlcError, Result};
+use crate::events::EventKind;
+use crate::signal::SignalBus;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Scan classes and the points polled in them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollingConfig {
+    /// Scan classes; `fast`, `medium` and `slow` when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<ScanClassConfig>,
+
+    /// Most addresses read or written in a single driver request
+    #[serde(default = "default_max_batch")]
+    pub max_batch: usize,
+
+    /// Polled addresses
+    pub points: Vec<PollPoint>,
+}
+
+/// A group of points polled at the same rate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanClassConfig {
+    /// Name points refer to the class by
+    pub name: String,
+    /// Time between the starts of two cycles
+    pub interval_ms: u64,
+}
+
+/// An address exchanged with a signal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollPoint {
+    /// Protocol driver or failover group the address belongs to
+    pub protocol: String,
+    /// Protocol address passed to the driver
+    pub address: String,
+    /// Signal holding the value
+    pub signal: String,
+    /// Scan class the point is polled in
+    #[serde(default = "default_class")]
+    pub class: String,
+    /// Direction of the exchange
+    #[serde(default)]
+    pub access: PointAccess,
+    /// Smallest change of a numeric signal that is written
+    #[serde(default)]
+    pub deadband: f64,
+    /// Read written values back to confirm the device took them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<WriteVerify>,
+}
+
+/// Read-back check of the values written to a point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteVerify {
+    /// Largest difference between a numeric value written and read back
+    #[serde(default)]
+    pub tolerance: f64,
+    /// Time the device has to report the written value
+    #[serde(default = "default_verify_timeout")]
+    pub timeout_ms: u64,
+    /// Boolean signal set while the point's last write is unconfirmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alarm_signal: Option<String>,
+}
+
+/// Direction values flow for a point
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointAccess {
+    /// Device to signal
+    #[default]
+    Read,
+    /// Signal to device
+    Write,
+    /// Read, and written when the signal changes locally
+    ReadWrite,
+}
+
+impl PointAccess {
+    const fn reads(self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    const fn writes(self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+}
+
+const fn default_max_batch() -> usize {
+    100
+}
+
+const fn default_verify_timeout() -> u64 {
+    1000
+}
+
+fn default_class() -> String {
+    "medium".to_string()
+}
+
+/// Classes used when none are configured
+fn default_classes() -> Vec<ScanClassConfig> {
+    [("fast", 100), ("medium", 1000), ("slow", 10_000)]
+        .into_iter()
+        .map(|(name, interval_ms)| ScanClassConfig {
+            name: name.to_string(),
+            interval_ms,
+        })
+        .collect()
+}
+
+impl PollingConfig {
+    /// Configured classes, or the defaults
+    #[must_use]
+    pub fn effective_classes(&self) -> Vec<ScanClassConfig> {
+        if self.classes.is_empty() {
+            default_classes()
+        } else {
+            self.classes.clone()
+        }
+    }
+
+    /// Validate classes and points
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for a repeated or zero-interval class, a
+    /// zero `max_batch`, a point with an empty field, an unknown class or a
+    /// negative deadband, an address listed twice for one protocol, or a
+    /// verified point that is not written or has an invalid check.
+    pub fn validate(&self) -> Result<()> {
+        let classes = self.effective_classes();
+        let mut names = HashSet::new();
+        for class in &classes {
+            if !names.insert(class.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate scan class '{}'", class.name)));
+            }
+            if class.interval_ms == 0 {
+                return Err(PlcError::Config(format!(
+                    "Scan class '{}' interval_ms must be greater than 0",
+                    class.name
+                )));
+            }
+        }
+        if self.max_batch == 0 {
+            return Err(PlcError::Config("Polling max_batch must be greater than 0".to_string()));
+        }
+
+        let mut addresses = HashSet::new();
+        for point in &self.points {
+            if point.protocol.is_empty() || point.address.is_empty() || point.signal.is_empty() {
+                return Err(PlcError::Config(
+                    "Polled points need a protocol, address and signal".to_string(),
+                ));
+            }
+            if !names.contains(point.class.as_str()) {
+                return Err(PlcError::Config(format!(
+                    "Point '{}' uses unknown scan class '{}'",
+                    point.signal, point.class
+                )));
+            }
+            if point.deadband.is_nan() || point.deadband < 0.0 {
+                return Err(PlcError::Config(format!(
+                    "Point '{}' has a negative deadband",
+                    point.signal
+                )));
+            }
+            if let Some(verify) = &point.verify {
+                Self::validate_verify(point, verify)?;
+            }
+            if !addresses.insert((point.protocol.as_str(), point.address.as_str())) {
+                return Err(PlcError::Config(format!(
+                    "Address '{}' of '{}' is polled more than once",
+                    point.address, point.protocol
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_verify(point: &PollPoint, verify: &WriteVerify) -> Result<()> {
+        if !point.access.writes() {
+            return Err(PlcError::Config(format!(
+                "Point '{}' verifies writes but is not written",
+                point.signal
+            )));
+        }
+        if verify.tolerance.is_nan() || verify.tolerance < 0.0 {
+            return Err(PlcError::Config(format!(
+                "Point '{}' has a negative verify tolerance",
+                point.signal
+            )));
+        }
+        if verify.timeout_ms == 0 {
+            return Err(PlcError::Config(format!(
+                "Point '{}' verify timeout_ms must be greater than 0",
+                point.signal
+            )));
+        }
+        if verify.alarm_signal.as_deref().is_some_and(str::is_empty) {
+            return Err(PlcError::Config(format!(
+                "Point '{}' has an empty verify alarm_signal",
+                point.signal
+            )));
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// SCHEDULER
+// ============================================================================
+
+/// Protocol and address of a point
+type PointKey = (String, String);
+
+/// Counts of one poll cycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CycleStats {
+    /// Read requests sent, read-backs of verified writes included
+    pub read_requests: usize,
+    /// Write requests sent
+    pub write_requests: usize,
+    /// Values written
+    pub written: usize,
+    /// Changed values held back by their deadband
+    pub suppressed: usize,
+    /// Failed requests
+    pub errors: usize,
+    /// Verified writes not read back within their timeout
+    pub verify_failures: usize,
+}
+
+/// A writable point
+#[derive(Debug, Clone)]
+struct WritePoint {
+    key: PointKey,
+    signal: String,
+    deadband: f64,
+    /// Read back too, so nothing is written before the device value is known
+    read_back: bool,
+    verify: Option<WriteVerify>,
+}
+
+/// A verified write waiting to be read back
+#[derive(Debug)]
+struct PendingVerify {
+    signal: String,
+    written: Value,
+    tolerance: f64,
+    deadline: Instant,
+    alarm_signal: Option<String>,
+    /// Latest value read back
+    read: Option<Value>,
+}
+
+/// Points of one scan class, batched per driver
+#[derive(Debug)]
+pub struct ScanClass {
+    name: String,
+    interval: Duration,
+    /// Read requests as protocol and addresses
+    reads: Vec<(String, Vec<String>)>,
+    signals: HashMap<PointKey, String>,
+    writes: Vec<WritePoint>,
+    max_batch: usize,
+    /// Last value known to be on the device, written or read back
+    device_values: HashMap<PointKey, Value>,
+    /// Verified writes not confirmed yet
+    verifying: HashMap<PointKey, PendingVerify>,
+}
+
+impl ScanClass {
+    fn new(config: &ScanClassConfig, points: &[&PollPoint], max_batch: usize) -> Self {
+        let mut by_protocol: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        let mut signals = HashMap::new();
+        let mut writes = Vec::new();
+        for point in points {
+            let key = (point.protocol.clone(), point.address.clone());
+            if point.access.reads() {
+                by_protocol.entry(&point.protocol).or_default().push(point.address.clone());
+                signals.insert(key.clone(), point.signal.clone());
+            }
+            if point.access.writes() {
+                writes.push(WritePoint {
+                    key,
+                    signal: point.signal.clone(),
+                    deadband: point.deadband,
+                    read_back: point.access.reads(),
+                    verify: point.verify.clone(),
+                });
+            }
+        }
+        let reads = by_protocol
+            .into_iter()
+            .flat_map(|(protocol, addresses)| {
+                addresses
+                    .chunks(max_batch)
+                    .map(|chunk| (protocol.to_string(), chunk.to_vec()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Self {
+            name: config.name.clone(),
+            interval: Duration::from_millis(config.interval_ms),
+            reads,
+            signals,
+            writes,
+            max_batch,
+            device_values: HashMap::new(),
+            verifying: HashMap::new(),
+        }
+    }
+
+    /// Name of the class
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time between cycles
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Signals the class stores read values in
+    pub fn read_signals(&self) -> impl Iterator<Item = &str> {
+        self.signals.values().map(String::as_str)
+    }
+
+    /// Driver requests of a cycle that writes and verifies every writable point
+    #[must_use]
+    pub fn max_requests(&self) -> usize {
+        let mut writes: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for point in &self.writes {
+            let (written, verified) = writes.entry(point.key.0.as_str()).or_default();
+            *written += 1;
+            *verified += usize::from(point.verify.is_some());
+        }
+        let batches = |n: usize| n.div_ceil(self.max_batch);
+        self.reads.len() + writes.values().map(|&(written, verified)| batches(written) + batches(verified)).sum::<usize>()
+    }
+
+    /// Whether `value` should be written over what the device holds
+    fn exceeds_deadband(previous: Option<&Value>, value: &Value, deadband: f64) -> bool {
+        match (previous, value) {
+            (None, _) => true,
+            (Some(old @ (Value::Float(_) | Value::Integer(_))), Value::Float(_) | Value::Integer(_)) => {
+                let delta = (value.as_float().unwrap_or(f64::NAN) - old.as_float().unwrap_or(f64::NAN)).abs();
+                // A NaN on either side always counts as a change
+                delta.is_nan() || delta > deadband
+            }
+            (Some(old), value) => old != value,
+        }
+    }
+
+    /// Write changed signals, check verified writes, then read the class's
+    /// addresses into signals
+    pub async fn poll(&mut self, manager: &ProtocolManager, bus: &SignalBus) -> CycleStats {
+        let mut stats = CycleStats::default();
+
+        let mut pending: BTreeMap<&str, Vec<(&WritePoint, Value)>> = BTreeMap::new();
+        for point in &self.writes {
+            let Some(value) = bus.get(&point.signal) else {
+                continue;
+            };
+            let previous = self.device_values.get(&point.key);
+            if point.read_back && previous.is_none() {
+                continue;
+            }
+            if Self::exceeds_deadband(previous, &value, point.deadband) {
+                pending.entry(point.key.0.as_str()).or_default().push((point, value));
+            } else if previous != Some(&value) {
+                stats.suppressed += 1;
+            }
+        }
+        let mut written = Vec::new();
+        let mut verifying = Vec::new();
+        for (protocol, values) in pending {
+            for chunk in values.chunks(self.max_batch) {
+                let request: HashMap<String, Value> =
+                    chunk.iter().map(|(point, value)| (point.key.1.clone(), value.clone())).collect();
+                stats.write_requests += 1;
+                match manager.write_to(protocol, &request).await {
+                    Ok(()) => {
+                        stats.written += chunk.len();
+                        written.extend(chunk.iter().map(|(point, value)| (point.key.clone(), value.clone())));
+                        let now = Instant::now();
+                        verifying.extend(chunk.iter().filter_map(|(point, value)| {
+                            let verify = point.verify.as_ref()?;
+                            let pending = PendingVerify {
+                                signal: point.signal.clone(),
+                                written: value.clone(),
+                                tolerance: verify.tolerance,
+                                deadline: now + Duration::from_millis(verify.timeout_ms),
+                                alarm_signal: verify.alarm_signal.clone(),
+                                read: None,
+                            };
+                            Some((point.key.clone(), pending))
+                        }));
+                    }
+                    Err(e) => {
+                        stats.errors += 1;
+                        tracing::warn!("Scan class {}: write to {} failed: {}", self.name, protocol, e);
+                    }
+                }
+            }
+        }
+        self.device_values.extend(written);
+        // A new write replaces the check of the one before
+        self.verifying.extend(verifying);
+        self.verify(manager, bus, &mut stats).await;
+
+        for (protocol, addresses) in &self.reads {
+            stats.read_requests += 1;
+            let values = match manager.read_from(protocol, addresses).await {
+                Ok(values) => values,
+                Err(e) => {
+                    stats.errors += 1;
+                    tracing::warn!("Scan class {}: read from {} failed: {}", self.name, protocol, e);
+                    continue;
+                }
+            };
+            for (address, value) in values {
+                let key = (protocol.clone(), address);
+                let Some(signal) = self.signals.get(&key) else {
+                    continue;
+                };
+                if let Err(e) = bus.set(signal, value.clone()) {
+                    tracing::warn!("Scan class {}: cannot update {}: {}", self.name, signal, e);
+                }
+                // A value read back is not written again
+                self.device_values.insert(key, value);
+            }
+        }
+        stats
+    }
+
+    /// Read back pending verified writes and settle those confirmed or due
+    async fn verify(&mut self, manager: &ProtocolManager, bus: &SignalBus, stats: &mut CycleStats) {
+        let mut by_protocol: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (protocol, address) in self.verifying.keys() {
+            by_protocol.entry(protocol).or_default().push(address.clone());
+        }
+        let mut read_back = Vec::new();
+        for (protocol, addresses) in by_protocol {
+            for chunk in addresses.chunks(self.max_batch) {
+                stats.read_requests += 1;
+                match manager.read_from(protocol, chunk).await {
+                    Ok(values) => {
+                        read_back.extend(values.into_iter().map(|(address, value)| ((protocol.to_string(), address), value)));
+                    }
+                    Err(e) => {
+                        stats.errors += 1;
+                        tracing::warn!("Scan class {}: read-back from {} failed: {}", self.name, protocol, e);
+                    }
+                }
+            }
+        }
+        for (key, value) in read_back {
+            if let Some(pending) = self.verifying.get_mut(&key) {
+                pending.read = Some(value);
+            }
+        }
+
+        let now = Instant::now();
+        let name = &self.name;
+        let device_values = &mut self.device_values;
+        self.verifying.retain(|key, pending| {
+            let confirmed = pending
+                .read
+                .as_ref()
+                .is_some_and(|read| !Self::exceeds_deadband(Some(read), &pending.written, pending.tolerance));
+            if !confirmed && now < pending.deadline {
+                return true;
+            }
+            if !confirmed {
+                stats.verify_failures += 1;
+                tracing::warn!(
+                    "Scan class {}: {} of {} ({}) did not read back {} in time",
+                    name, key.1, key.0, pending.signal, pending.written
+                );
+                manager.publish(EventKind::WriteVerifyFailed {
+                    protocol: key.0.clone(),
+                    address: key.1.clone(),
+                    signal: pending.signal.clone(),
+                    written: pending.written.clone(),
+                    read: pending.read.clone(),
+                });
+                // The device does not hold the value, so it is written again
+                match &pending.read {
+                    Some(read) => device_values.insert(key.clone(), read.clone()),
+                    None => device_values.remove(key),
+                };
+            }
+            if let Some(alarm) = &pending.alarm_signal {
+                if let Err(e) = bus.set(alarm, Value::Bool(!confirmed)) {
+                    tracing::warn!("Scan class {}: cannot update {}: {}", name, alarm, e);
+                }
+            }
+            false
+        });
+    }
+
+    /// Poll at the class interval until the task is dropped
+    pub async fn run(mut self, manager: Arc<ProtocolManager>, bus: SignalBus) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let stats = self.poll(&manager, &bus).await;
+            tracing::trace!("Scan class {} cycle: {:?}", self.name, stats);
+        }
+    }
+}
+
+/// Scan classes built from a polling configuration
+#[derive(Debug)]
+pub struct PollScheduler {
+    classes: Vec<ScanClass>,
+}
+
+impl PollScheduler {
+    /// Group the points of a validated configuration into their classes
+    #[must_use]
+    pub fn new(config: &PollingConfig) -> Self {
+        let classes = config
+            .effective_classes()
+            .iter()
+            .filter_map(|class| {
+                let points: Vec<_> = config.points.iter().filter(|p| p.class == class.name).collect();
+                (!points.is_empty()).then(|| ScanClass::new(class, &points, config.max_batch))
+            })
+            .collect();
+        Self { classes }
+    }
+
+    /// Classes with at least one point
+    #[must_use]
+    pub fn classes(&self) -> &[ScanClass] {
+        &self.classes
+    }
+
+    /// Poll every class on its own task
+    #[must_use]
+    pub fn spawn(self, manager: &Arc<ProtocolManager>, bus: &SignalBus) -> Vec<tokio::task::JoinHandle<()>> {
+        self.classes
+            .into_iter()
+            .map(|class| {
+                tracing::info!("Polling scan class {} every {:?}", class.name, class.interval);
+                tokio::spawn(class.run(Arc::clone(manager), bus.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::ProtocolDriver;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Device returning the address length as value and recording requests
+    struct CountingDriver {
+        reads: Arc<Mutex<Vec<usize>>>,
+        writes: Arc<Mutex<Vec<HashMap<String, Value>>>>,
+    }
+
+    #[async_trait]
+    impl ProtocolDriver for CountingDriver {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+            self.reads.lock().unwrap().push(addresses.len());
+            Ok(addresses
+                .iter()
+                .map(|a| (a.clone(), Value::Integer(i64::try_from(a.len()).unwrap())))
+                .collect())
+        }
+
+        async fn write_values(&mut self, values: &HashMap<String, Value>) -> Result<()> {
+            self.writes.lock().unwrap().push(values.clone());
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn protocol_name(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    fn point(address: &str, class: &str, access: PointAccess, deadband: f64) -> PollPoint {
+        PollPoint {
+            protocol: "plc".to_string(),
+            address: address.to_string(),
+            signal: format!("plc.{address}"),
+            class: class.to_string(),
+            access,
+            deadband,
+            verify: None,
+        }
+    }
+
+    #[test]
+    fn test_points_are_grouped_and_batched() {
+        let mut points: Vec<_> = (0..5).map(|i| point(&format!("fast{i}"), "fast", PointAccess::Read, 0.0)).collect();
+        points.push(point("slow0", "slow", PointAccess::Read, 0.0));
+        points.push(PollPoint {
+            protocol: "other".to_string(),
+            ..point("fast9", "fast", PointAccess::Read, 0.0)
+        });
+        let config = PollingConfig {
+            classes: Vec::new(),
+            max_batch: 2,
+            points,
+        };
+        config.validate().unwrap();
+
+        let scheduler = PollScheduler::new(&config);
+        let names: Vec<_> = scheduler.classes().iter().map(ScanClass::name).collect();
+        assert_eq!(names, ["fast", "slow"]);
+        let fast = &scheduler.classes()[0];
+        assert_eq!(fast.interval(), Duration::from_millis(100));
+        let batches: Vec<_> = fast.reads.iter().map(|(p, a)| (p.as_str(), a.len())).collect();
+        assert_eq!(batches, [("other", 1), ("plc", 2), ("plc", 2), ("plc", 1)]);
+
+        let mut invalid = config.clone();
+        invalid.points.push(point("x", "turbo", PointAccess::Read, 0.0));
+        assert!(invalid.validate().is_err());
+        let mut invalid = config;
+        invalid.points.push(point("fast0", "slow", PointAccess::Write, 0.0));
+        assert!(invalid.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cycle_reads_batches_and_suppresses_writes_within_deadband() {
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let bus = SignalBus::new();
+        let manager = ProtocolManager::new(bus.clone());
+        let driver = CountingDriver {
+            reads: reads.clone(),
+            writes: writes.clone(),
+        };
+        manager.add_driver("plc".to_string(), Box::new(driver)).await.unwrap();
+
+        let config = PollingConfig {
+            classes: vec![ScanClassConfig {
+                name: "fast".to_string(),
+                interval_ms: 50,
+            }],
+            max_batch: 2,
+            points: vec![
+                point("a", "fast", PointAccess::Read, 0.0),
+                point("bb", "fast", PointAccess::Read, 0.0),
+                point("ccc", "fast", PointAccess::Read, 0.0),
+                point("sp", "fast", PointAccess::Write, 0.5),
+            ],
+        };
+        let mut scheduler = PollScheduler::new(&config);
+        let class = &mut scheduler.classes[0];
+
+        bus.set("plc.sp", Value::Float(10.0)).unwrap();
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.read_requests, stats.write_requests, stats.written), (2, 1, 1));
+        assert_eq!(*reads.lock().unwrap(), [2, 1]);
+        assert_eq!(bus.get("plc.ccc"), Some(Value::Integer(3)));
+
+        // Within the deadband: nothing is written
+        bus.set("plc.sp", Value::Float(10.3)).unwrap();
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.write_requests, stats.suppressed), (0, 1));
+
+        // Beyond it, measured from the value last written
+        bus.set("plc.sp", Value::Float(10.6)).unwrap();
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.write_requests, stats.written, stats.errors), (1, 1, 0));
+        let writes = writes.lock().unwrap();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[1].get("sp"), Some(&Value::Float(10.6)));
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_write_raises_event_and_alarm() {
+        let bus = SignalBus::new();
+        let events = crate::events::EventLog::default();
+        let manager = ProtocolManager::new(bus.clone()).with_events(events.clone());
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let driver = CountingDriver {
+            reads: Arc::new(Mutex::new(Vec::new())),
+            writes: writes.clone(),
+        };
+        manager.add_driver("plc".to_string(), Box::new(driver)).await.unwrap();
+
+        // The device ignores writes and always reads back 2 for "sp"
+        let config = PollingConfig {
+            classes: Vec::new(),
+            max_batch: 10,
+            points: vec![PollPoint {
+                verify: Some(WriteVerify {
+                    tolerance: 0.5,
+                    timeout_ms: 20,
+                    alarm_signal: Some("plc.sp_unverified".to_string()),
+                }),
+                ..point("sp", "medium", PointAccess::Write, 0.0)
+            }],
+        };
+        config.validate().unwrap();
+        let mut scheduler = PollScheduler::new(&config);
+        let class = &mut scheduler.classes[0];
+
+        bus.set("plc.sp", Value::Float(2.4)).unwrap();
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.written, stats.read_requests, stats.verify_failures), (1, 1, 0));
+        assert_eq!(bus.get("plc.sp_unverified"), Some(Value::Bool(false)));
+
+        // Not confirmed, but still within the timeout
+        bus.set("plc.sp", Value::Float(10.0)).unwrap();
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.written, stats.verify_failures), (1, 0));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!((stats.written, stats.verify_failures), (0, 1));
+        assert_eq!(bus.get("plc.sp_unverified"), Some(Value::Bool(true)));
+        let failed = events.since(0).into_iter().find(|e| e.kind.name() == "write_verify_failed").unwrap();
+        assert_eq!(
+            failed.kind,
+            EventKind::WriteVerifyFailed {
+                protocol: "plc".to_string(),
+                address: "sp".to_string(),
+                signal: "plc.sp".to_string(),
+                written: Value::Float(10.0),
+                read: Some(Value::Integer(2)),
+            }
+        );
+
+        // The device still holds the old value, so the setpoint is written again
+        let stats = class.poll(&manager, &bus).await;
+        assert_eq!(stats.written, 1);
+        assert_eq!(writes.lock().unwrap().len(), 3);
+
+        let mut invalid = config;
+        invalid.points[0].access = PointAccess::Read;
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,533 @@
+// src/protocols/sim.rs
+//! Simulated protocol drivers for offline development
+//!
+//! Each entry under `protocols.sim` registers a [`ProtocolDriver`] with the
+//! protocol manager that answers reads from generated data instead of a
+//! device, so polling scan classes, routes and failover groups can be run
+//! end-to-end without hardware:
+//!
+//! ```yaml
+//! protocols:
+//!   sim:
+//!     - name: line1_plc
+//!       seed: 42
+//!       points:
+//!         - { address: speed, source: waveform, shape: sine, amplitude: 5, offset: 50, period_ms: 60000 }
+//!         - { address: temperature, source: random, min: 20, max: 80, step: 0.5 }
+//!         - { address: pressure, source: replay, file: data/pressure.csv }
+//!         - { address: setpoint, source: memory, initial: 42.0 }
+//!   polling:
+//!     points:
+//!       - { protocol: line1_plc, address: speed, signal: line1.speed, class: fast }
+//!       - { protocol: line1_plc, address: setpoint, signal: line1.setpoint, access: read_write }
+//! ```
+//!
+//! Point sources:
+//!
+//! - `waveform`: a `sine`, `square`, `triangle` or `sawtooth` wave of
+//!   `period_ms` around `offset`, shifted by `phase_deg`
+//! - `random`: uniform values between `min` and `max`, or with `step` a
+//!   random walk that moves at most `step` per read and stays in range;
+//!   `seed` makes the sequence repeatable
+//! - `replay`: a CSV recording with a `time_ms` column and one column per
+//!   value, played back against the time since connect. The column is
+//!   `column` or the point address; the recording repeats unless
+//!   `repeat: false`, in which case its last row is held
+//! - `memory`: holds the last value written, starting at `initial`
+//!
+//! Only `memory` points accept writes, which makes them the place for
+//! setpoints and verified writes.
+
+use super::ProtocolDriver;
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// A simulated device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimDriverConfig {
+    /// Name the driver is registered under
+    pub name: String,
+
+    /// Seed for `random` points; unseeded drivers differ on every run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Addresses the device answers
+    pub points: Vec<SimPointConfig>,
+}
+
+/// One simulated address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimPointConfig {
+    /// Address read and written through the driver
+    pub address: String,
+
+    /// Where the values come from
+    #[serde(flatten)]
+    pub source: SimSource,
+}
+
+/// Data source of a simulated address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum SimSource {
+    /// Periodic waveform
+    Waveform {
+        #[serde(default)]
+        shape: WaveShape,
+        #[serde(default = "default_amplitude")]
+        amplitude: f64,
+        #[serde(default)]
+        offset: f64,
+        #[serde(default = "default_period_ms")]
+        period_ms: u64,
+        #[serde(default)]
+        phase_deg: f64,
+    },
+    /// Uniform random values or a bounded random walk
+    Random {
+        min: f64,
+        max: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<f64>,
+    },
+    /// Playback of a CSV recording
+    Replay {
+        file: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        column: Option<String>,
+        #[serde(default = "default_repeat")]
+        repeat: bool,
+    },
+    /// Value holding the last write
+    Memory {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial: Option<serde_yaml::Value>,
+    },
+}
+
+/// Shape of a waveform point
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveShape {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+const fn default_amplitude() -> f64 {
+    1.0
+}
+
+const fn default_period_ms() -> u64 {
+    10_000
+}
+
+const fn default_repeat() -> bool {
+    true
+}
+
+impl SimDriverConfig {
+    /// Validate the device definition
+    ///
+    /// Replay files are only opened when the driver is created.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty name, no or repeated
+    /// addresses, a zero waveform period, an empty or inverted random range
+    /// or a negative random step.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(PlcError::Config("Simulated driver name cannot be empty".to_string()));
+        }
+        if self.points.is_empty() {
+            return Err(PlcError::Config(format!("Simulated driver '{}' has no points", self.name)));
+        }
+        let mut seen = HashSet::new();
+        for point in &self.points {
+            if !seen.insert(point.address.as_str()) {
+                return Err(PlcError::Config(format!(
+                    "Simulated driver '{}' lists address '{}' more than once",
+                    self.name, point.address
+                )));
+            }
+            let invalid = |reason: &str| {
+                PlcError::Config(format!("Simulated point {}/{}: {reason}", self.name, point.address))
+            };
+            match &point.source {
+                SimSource::Waveform { period_ms: 0, .. } => return Err(invalid("period_ms must be greater than 0")),
+                SimSource::Random { min, max, .. } if !(min.is_finite() && max.is_finite() && min <= max) => {
+                    return Err(invalid("min must not exceed max"));
+                }
+                SimSource::Random { step: Some(step), .. } if !(step.is_finite() && *step >= 0.0) => {
+                    return Err(invalid("step must not be negative"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate all simulated drivers and check their names are unique
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`] for an invalid driver or a repeated name.
+pub fn validate_drivers(drivers: &[SimDriverConfig]) -> Result<()> {
+    let mut names = HashSet::new();
+    for driver in drivers {
+        driver.validate()?;
+        if !names.insert(driver.name.as_str()) {
+            return Err(PlcError::Config(format!("Duplicate simulated driver name: '{}'", driver.name)));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// DATA SOURCES
+// ============================================================================
+
+impl WaveShape {
+    /// Value of a unit wave at `phase` in [0, 1)
+    fn sample(self, phase: f64) -> f64 {
+        match self {
+            Self::Sine => (phase * std::f64::consts::TAU).sin(),
+            Self::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Self::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Self::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// CSV recording of one column
+#[derive(Debug)]
+struct Recording {
+    /// Samples ordered by time since the start of the recording
+    samples: Vec<(Duration, Value)>,
+    repeat: bool,
+}
+
+impl Recording {
+    fn load(path: &Path, column: &str, repeat: bool) -> Result<Self> {
+        let error = |e: &dyn std::fmt::Display| {
+            PlcError::Config(format!("Cannot replay {}: {e}", path.display()))
+        };
+        let mut reader = csv::Reader::from_path(path).map_err(|e| error(&e))?;
+        let headers = reader.headers().map_err(|e| error(&e))?.clone();
+        let position = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| error(&format!("no '{name}' column")))
+        };
+        let (time, value) = (position("time_ms")?, position(column)?);
+
+        let mut samples = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| error(&e))?;
+            let at = record.get(time).unwrap_or_default().trim();
+            let at: u64 = at.parse().map_err(|_| error(&format!("invalid time_ms '{at}'")))?;
+            let cell = record.get(value).unwrap_or_default().trim();
+            let cell = parse_cell(cell).ok_or_else(|| error(&format!("invalid value '{cell}' in '{column}'")))?;
+            samples.push((Duration::from_millis(at), cell));
+        }
+        if samples.is_empty() {
+            return Err(error(&"recording has no rows"));
+        }
+        samples.sort_by_key(|(at, _)| *at);
+        Ok(Self { samples, repeat })
+    }
+
+    fn value_at(&self, elapsed: Duration) -> Value {
+        let length = self.samples[self.samples.len() - 1].0;
+        let at = if self.repeat && !length.is_zero() {
+            Duration::from_nanos(u64::try_from(elapsed.as_nanos() % length.as_nanos()).unwrap_or_default())
+        } else {
+            elapsed
+        };
+        let index = self.samples.partition_point(|(time, _)| *time <= at).saturating_sub(1);
+        self.samples[index].1.clone()
+    }
+}
+
+fn parse_cell(cell: &str) -> Option<Value> {
+    match cell {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => cell
+            .parse::<i64>()
+            .map(Value::Integer)
+            .or_else(|_| cell.parse::<f64>().map(Value::Float))
+            .ok(),
+    }
+}
+
+/// Runtime state of one point
+#[derive(Debug)]
+enum PointState {
+    Waveform { shape: WaveShape, amplitude: f64, offset: f64, period: Duration, phase: f64 },
+    Random { min: f64, max: f64, step: Option<f64>, last: f64 },
+    Replay(Recording),
+    Memory(Value),
+}
+
+impl PointState {
+    fn new(address: &str, source: &SimSource) -> Result<Self> {
+        Ok(match source {
+            SimSource::Waveform { shape, amplitude, offset, period_ms, phase_deg } => Self::Waveform {
+                shape: *shape,
+                amplitude: *amplitude,
+                offset: *offset,
+                period: Duration::from_millis(*period_ms),
+                phase: (phase_deg / 360.0).rem_euclid(1.0),
+            },
+            SimSource::Random { min, max, step } => Self::Random {
+                min: *min,
+                max: *max,
+                step: *step,
+                last: (min + max) / 2.0,
+            },
+            SimSource::Replay { file, column, repeat } => {
+                Self::Replay(Recording::load(file, column.as_deref().unwrap_or(address), *repeat)?)
+            }
+            SimSource::Memory { initial } => Self::Memory(match initial {
+                Some(initial) => crate::value::from_yaml_value(initial.clone())?,
+                None => Value::Float(0.0),
+            }),
+        })
+    }
+
+    fn read(&mut self, elapsed: Duration, rng: &mut StdRng) -> Value {
+        match self {
+            Self::Waveform { shape, amplitude, offset, period, phase } => {
+                let phase = (elapsed.as_secs_f64() / period.as_secs_f64() + *phase).fract();
+                Value::Float(*offset + *amplitude * shape.sample(phase))
+            }
+            Self::Random { min, max, step, last } => {
+                *last = match step {
+                    Some(step) => (*last + rng.gen_range(-*step..=*step)).clamp(*min, *max),
+                    None => rng.gen_range(*min..=*max),
+                };
+                Value::Float(*last)
+            }
+            Self::Replay(recording) => recording.value_at(elapsed),
+            Self::Memory(value) => value.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// DRIVER
+// ============================================================================
+
+#[derive(Debug)]
+struct SimState {
+    points: HashMap<String, PointState>,
+    rng: StdRng,
+}
+
+/// Driver answering reads from simulated points
+#[derive(Debug)]
+pub struct SimDriver {
+    name: String,
+    state: Mutex<SimState>,
+    connected_at: Option<Instant>,
+}
+
+impl SimDriver {
+    /// Create the driver, loading replay recordings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid or a
+    /// recording cannot be loaded.
+    pub fn new(config: &SimDriverConfig) -> Result<Self> {
+        Ok(Self {
+            name: config.name.clone(),
+            state: Mutex::new(Self::build(config)?),
+            connected_at: None,
+        })
+    }
+
+    fn build(config: &SimDriverConfig) -> Result<SimState> {
+        config.validate()?;
+        let points = config
+            .points
+            .iter()
+            .map(|point| Ok((point.address.clone(), PointState::new(&point.address, &point.source)?)))
+            .collect::<Result<_>>()?;
+        let rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        Ok(SimState { points, rng })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl ProtocolDriver for SimDriver {
+    async fn connect(&mut self) -> Result<()> {
+        self.connected_at = Some(Instant::now());
+        tracing::info!("Simulated driver {} connected", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.connected_at = None;
+        Ok(())
+    }
+
+    async fn read_values(&self, addresses: &[String]) -> Result<HashMap<String, Value>> {
+        let Some(connected_at) = self.connected_at else {
+            return Err(PlcError::Protocol(format!("Simulated driver {} is not connected", self.name)));
+        };
+        let elapsed = connected_at.elapsed();
+        let mut state = self.state();
+        let SimState { points, rng } = &mut *state;
+        addresses
+            .iter()
+            .map(|address| {
+                let point = points.get_mut(address).ok_or_else(|| {
+                    PlcError::Protocol(format!("Simulated driver {} has no address '{address}'", self.name))
+                })?;
+                Ok((address.clone(), point.read(elapsed, rng)))
+            })
+            .collect()
+    }
+
+    async fn write_values(&mut self, values: &HashMap<String, Value>) -> Result<()> {
+        if self.connected_at.is_none() {
+            return Err(PlcError::Protocol(format!("Simulated driver {} is not connected", self.name)));
+        }
+        let name = self.name.clone();
+        let state = self.state.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner);
+        for (address, value) in values {
+            match state.points.get_mut(address) {
+                Some(PointState::Memory(held)) => *held = value.clone(),
+                Some(_) => {
+                    return Err(PlcError::Protocol(format!("Simulated address {name}/{address} is read-only")));
+                }
+                None => {
+                    return Err(PlcError::Protocol(format!("Simulated driver {name} has no address '{address}'")));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected_at.is_some()
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "sim"
+    }
+
+    fn diagnostics(&self) -> HashMap<String, Value> {
+        let points = self.state().points.len();
+        HashMap::from([(
+            "points".to_string(),
+            Value::Integer(i64::try_from(points).unwrap_or(i64::MAX)),
+        )])
+    }
+
+    fn validate_address(&self, address: &str) -> Result<()> {
+        if self.state().points.contains_key(address) {
+            Ok(())
+        } else {
+            Err(PlcError::Validation(format!("Simulated driver {} has no address '{address}'", self.name)))
+        }
+    }
+
+    async fn reconfigure(&mut self, config: &serde_yaml::Value) -> Result<()> {
+        let config: SimDriverConfig = serde_yaml::from_value(config.clone())
+            .map_err(|e| PlcError::Config(format!("Invalid simulated driver configuration: {e}")))?;
+        *self.state.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner) = Self::build(&config)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn driver(yaml: &str) -> SimDriver {
+        SimDriver::new(&serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_waveforms_and_replay() {
+        assert!(WaveShape::Sine.sample(0.25) > 0.999);
+        assert!((WaveShape::Triangle.sample(0.5) - 1.0).abs() < 1e-9);
+        assert!((WaveShape::Sawtooth.sample(0.0) + 1.0).abs() < 1e-9);
+        assert!((WaveShape::Square.sample(0.75) + 1.0).abs() < 1e-9);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("pressure.csv");
+        std::fs::write(&file, "time_ms,pressure,running\n0,1.5,true\n100,2.5,false\n200,3,true\n").unwrap();
+        let recording = Recording::load(&file, "pressure", true).unwrap();
+        assert_eq!(recording.value_at(Duration::from_millis(50)), Value::Float(1.5));
+        assert_eq!(recording.value_at(Duration::from_millis(150)), Value::Float(2.5));
+        assert_eq!(recording.value_at(Duration::from_millis(250)), Value::Float(1.5));
+        let held = Recording::load(&file, "running", false).unwrap();
+        assert_eq!(held.value_at(Duration::from_secs(10)), Value::Bool(true));
+        assert!(Recording::load(&file, "missing", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_writes() {
+        let mut sim = driver(
+            r"
+name: line1_plc
+seed: 7
+points:
+  - { address: temperature, source: random, min: 20, max: 80, step: 0.5 }
+  - { address: speed, source: waveform, shape: square, amplitude: 5, offset: 50 }
+  - { address: setpoint, source: memory, initial: 42 }
+",
+        );
+        let addresses: Vec<String> = ["temperature", "speed", "setpoint"].map(String::from).to_vec();
+        assert!(sim.read_values(&addresses).await.is_err());
+        sim.connect().await.unwrap();
+
+        let mut last = 50.0;
+        for _ in 0..20 {
+            let values = sim.read_values(&addresses).await.unwrap();
+            let temperature = values["temperature"].as_float().unwrap();
+            assert!((20.0..=80.0).contains(&temperature) && (temperature - last).abs() <= 0.5);
+            last = temperature;
+            assert_eq!(values["speed"], Value::Float(55.0));
+            assert_eq!(values["setpoint"], Value::Integer(42));
+        }
+
+        sim.write_values(&HashMap::from([("setpoint".to_string(), Value::Float(12.5))])).await.unwrap();
+        let values = sim.read_values(&["setpoint".to_string()]).await.unwrap();
+        assert_eq!(values["setpoint"], Value::Float(12.5));
+        assert!(sim.write_values(&HashMap::from([("speed".to_string(), Value::Float(1.0))])).await.is_err());
+        assert!(sim.read_values(&["missing".to_string()]).await.is_err());
+    }
+}
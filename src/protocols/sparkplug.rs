@@ -0,0 +1,848 @@
+// src/protocols/sparkplug.rs
+//! Sparkplug B edge node
+//!
+//! Publishes signals as Sparkplug B metrics so SCADA hosts such as Ignition
+//! discover them automatically. The edge node follows the Sparkplug session
+//! rules:
+//!
+//! - On connect it publishes `NBIRTH` with every node metric and a `DBIRTH`
+//!   per device. Births carry metric names and numeric aliases; later
+//!   `NDATA`/`DDATA` messages only carry the alias to keep payloads small.
+//! - Data messages are report-by-exception: a metric is sent only when its
+//!   signal changed since the last publication.
+//! - `NDEATH` is registered as the MQTT last will and carries the `bdSeq`
+//!   of the session, which increments on every new connection.
+//! - With `primary_host_id` set, nothing is published until the host's
+//!   `STATE` message reports it online, and births are repeated whenever
+//!   the host comes back online.
+//! - An `NCMD` with `Node Control/Rebirth = true` republishes all births.
+//!
+//! ```yaml
+//! mqtt:
+//!   host: broker.local
+//!   client_id: petra-line1
+//!   sparkplug:
+//!     group_id: plant1
+//!     edge_node_id: line1
+//!     primary_host_id: ignition
+//!     metrics:
+//!       - { signal: line1.speed }
+//!       - { signal: filler.running, name: Running, device: filler }
+//! ```
+//!
+//! Payloads are encoded with a small built-in protobuf codec covering the
+//! scalar metric types. Metric writes through `NCMD`/`DCMD` are not applied;
+//! use the authenticated command channel for writes.
+
+use crate::{PlcError, Result, SignalBus, Value};
+use rumqttc::{AsyncClient, Event, LastWill, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+
+/// Sparkplug B topic namespace
+pub const NAMESPACE: &str = "spBv1.0";
+
+/// Node metric carrying the birth/death sequence number
+pub const BD_SEQ_METRIC: &str = "bdSeq";
+
+/// Node metric a host writes to request new births
+pub const REBIRTH_METRIC: &str = "Node Control/Rebirth";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Sparkplug B edge node configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparkplugConfig {
+    /// Sparkplug group the edge node belongs to
+    pub group_id: String,
+
+    /// Edge node identifier within the group
+    pub edge_node_id: String,
+
+    /// Primary host application whose `STATE` gates publishing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_host_id: Option<String>,
+
+    /// Interval for checking signals for changes
+    #[serde(default = "default_scan_interval_ms")]
+    pub scan_interval_ms: u64,
+
+    /// Signals published as metrics
+    pub metrics: Vec<SparkplugMetricConfig>,
+}
+
+/// Signal published as a Sparkplug metric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparkplugMetricConfig {
+    /// Source signal
+    pub signal: String,
+
+    /// Metric name, defaults to the signal name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Device the metric belongs to; node metrics when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+}
+
+impl SparkplugMetricConfig {
+    fn metric_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.signal)
+    }
+}
+
+const fn default_scan_interval_ms() -> u64 {
+    1000
+}
+
+fn validate_id(kind: &str, id: &str) -> Result<()> {
+    if id.is_empty() || id.contains(['/', '+', '#']) {
+        return Err(PlcError::Config(format!(
+            "Sparkplug {kind} '{id}' must be non-empty and cannot contain '/', '+' or '#'"
+        )));
+    }
+    Ok(())
+}
+
+impl SparkplugConfig {
+    /// Validate identifiers and metric names
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        validate_id("group_id", &self.group_id)?;
+        validate_id("edge_node_id", &self.edge_node_id)?;
+        if let Some(host) = &self.primary_host_id {
+            validate_id("primary_host_id", host)?;
+        }
+        if self.scan_interval_ms == 0 {
+            return Err(PlcError::Config("Sparkplug scan_interval_ms cannot be 0".to_string()));
+        }
+        if self.metrics.is_empty() {
+            return Err(PlcError::Config("Sparkplug configuration has no metrics".to_string()));
+        }
+
+        let mut names = HashSet::new();
+        for metric in &self.metrics {
+            if let Some(device) = &metric.device {
+                validate_id("device", device)?;
+            }
+            let name = metric.metric_name();
+            if name.is_empty() || name == BD_SEQ_METRIC || name.starts_with("Node Control/") {
+                return Err(PlcError::Config(format!("Invalid Sparkplug metric name '{name}'")));
+            }
+            if !names.insert((metric.device.as_deref(), name)) {
+                return Err(PlcError::Config(format!("Duplicate Sparkplug metric '{name}'")));
+            }
+        }
+        Ok(())
+    }
+
+    fn topic(&self, message: &str, device: Option<&str>) -> String {
+        match device {
+            Some(device) => format!(
+                "{NAMESPACE}/{}/{message}/{}/{device}",
+                self.group_id, self.edge_node_id
+            ),
+            None => format!("{NAMESPACE}/{}/{message}/{}", self.group_id, self.edge_node_id),
+        }
+    }
+}
+
+// ============================================================================
+// PAYLOAD
+// ============================================================================
+
+/// Sparkplug B metric data types used by the edge node
+pub mod data_type {
+    pub const INT8: u32 = 1;
+    pub const INT16: u32 = 2;
+    pub const INT32: u32 = 3;
+    pub const INT64: u32 = 4;
+    pub const UINT8: u32 = 5;
+    pub const UINT16: u32 = 6;
+    pub const UINT32: u32 = 7;
+    pub const UINT64: u32 = 8;
+    pub const FLOAT: u32 = 9;
+    pub const DOUBLE: u32 = 10;
+    pub const BOOLEAN: u32 = 11;
+    pub const STRING: u32 = 12;
+    pub const DATETIME: u32 = 13;
+    pub const TEXT: u32 = 14;
+}
+
+/// Metric value
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricValue {
+    /// Null value of the metric's declared type
+    Null,
+    /// Integer types
+    Int(i64),
+    /// Float and double
+    Float(f64),
+    /// Boolean
+    Boolean(bool),
+    /// String and text
+    String(String),
+}
+
+/// One Sparkplug metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    /// Metric name, present in births
+    pub name: Option<String>,
+    /// Numeric alias
+    pub alias: Option<u64>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: Option<u64>,
+    /// Sparkplug data type
+    pub datatype: u32,
+    /// Value
+    pub value: MetricValue,
+}
+
+impl Metric {
+    fn from_value(name: Option<&str>, alias: Option<u64>, timestamp: u64, value: Option<&Value>) -> Self {
+        #[allow(unreachable_patterns)]
+        let (datatype, value) = match value {
+            Some(Value::Bool(b)) => (data_type::BOOLEAN, MetricValue::Boolean(*b)),
+            Some(Value::Integer(i)) => (data_type::INT64, MetricValue::Int(*i)),
+            Some(Value::Float(f)) => (data_type::DOUBLE, MetricValue::Float(*f)),
+            Some(other) => (data_type::STRING, MetricValue::String(other.to_string())),
+            None => (data_type::DOUBLE, MetricValue::Null),
+        };
+        Self {
+            name: name.map(str::to_string),
+            alias,
+            timestamp: Some(timestamp),
+            datatype,
+            value,
+        }
+    }
+}
+
+/// Sparkplug B payload
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Payload {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: Option<u64>,
+    /// Metrics
+    pub metrics: Vec<Metric>,
+    /// Message sequence number, 0-255
+    pub seq: Option<u64>,
+}
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_64BIT: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_32BIT: u8 = 5;
+
+#[allow(clippy::cast_possible_truncation)]
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_key(out: &mut Vec<u8>, field: u32, wire: u8) {
+    put_varint(out, u64::from(field << 3 | u32::from(wire)));
+}
+
+fn put_bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(out, field, WIRE_LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+impl Metric {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(name) = &self.name {
+            put_bytes(&mut out, 1, name.as_bytes());
+        }
+        if let Some(alias) = self.alias {
+            put_key(&mut out, 2, WIRE_VARINT);
+            put_varint(&mut out, alias);
+        }
+        if let Some(timestamp) = self.timestamp {
+            put_key(&mut out, 3, WIRE_VARINT);
+            put_varint(&mut out, timestamp);
+        }
+        put_key(&mut out, 4, WIRE_VARINT);
+        put_varint(&mut out, u64::from(self.datatype));
+        match &self.value {
+            MetricValue::Null => {
+                put_key(&mut out, 7, WIRE_VARINT);
+                put_varint(&mut out, 1);
+            }
+            MetricValue::Int(i) => {
+                // Sparkplug carries signed values as two's complement
+                #[allow(clippy::cast_sign_loss)]
+                let bits = *i as u64;
+                if matches!(self.datatype, data_type::INT64 | data_type::UINT64 | data_type::DATETIME) {
+                    put_key(&mut out, 11, WIRE_VARINT);
+                    put_varint(&mut out, bits);
+                } else {
+                    put_key(&mut out, 10, WIRE_VARINT);
+                    put_varint(&mut out, bits & 0xFFFF_FFFF);
+                }
+            }
+            MetricValue::Float(f) => {
+                if self.datatype == data_type::FLOAT {
+                    put_key(&mut out, 12, WIRE_32BIT);
+                    #[allow(clippy::cast_possible_truncation)]
+                    out.extend_from_slice(&(*f as f32).to_le_bytes());
+                } else {
+                    put_key(&mut out, 13, WIRE_64BIT);
+                    out.extend_from_slice(&f.to_le_bytes());
+                }
+            }
+            MetricValue::Boolean(b) => {
+                put_key(&mut out, 14, WIRE_VARINT);
+                put_varint(&mut out, u64::from(*b));
+            }
+            MetricValue::String(s) => put_bytes(&mut out, 15, s.as_bytes()),
+        }
+        out
+    }
+}
+
+impl Payload {
+    /// Encode as a Sparkplug B protobuf message
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(timestamp) = self.timestamp {
+            put_key(&mut out, 1, WIRE_VARINT);
+            put_varint(&mut out, timestamp);
+        }
+        for metric in &self.metrics {
+            put_bytes(&mut out, 2, &metric.encode());
+        }
+        if let Some(seq) = self.seq {
+            put_key(&mut out, 3, WIRE_VARINT);
+            put_varint(&mut out, seq);
+        }
+        out
+    }
+
+    /// Decode a Sparkplug B protobuf message
+    ///
+    /// Metric metadata, properties, datasets and templates are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Protocol`] if the message is malformed.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut payload = Self::default();
+        let mut reader = Reader { bytes, position: 0 };
+        while let Some((field, field_value)) = reader.field()? {
+            match (field, field_value) {
+                (1, FieldValue::Varint(v)) => payload.timestamp = Some(v),
+                (2, FieldValue::Bytes(b)) => payload.metrics.push(decode_metric(b)?),
+                (3, FieldValue::Varint(v)) => payload.seq = Some(v),
+                _ => {}
+            }
+        }
+        Ok(payload)
+    }
+
+    /// Find a metric by name or alias
+    #[must_use]
+    pub fn metric(&self, name: &str, alias: Option<u64>) -> Option<&Metric> {
+        self.metrics.iter().find(|m| {
+            m.name.as_deref() == Some(name) || (alias.is_some() && m.alias == alias)
+        })
+    }
+}
+
+enum FieldValue<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Fixed32([u8; 4]),
+    Bytes(&'a [u8]),
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn truncated() -> PlcError {
+        PlcError::Protocol("Truncated Sparkplug payload".to_string())
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.position).ok_or_else(Self::truncated)?;
+            self.position += 1;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PlcError::Protocol("Invalid varint in Sparkplug payload".to_string()))
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(count).ok_or_else(Self::truncated)?;
+        let bytes = self.bytes.get(self.position..end).ok_or_else(Self::truncated)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> Result<Option<(u64, FieldValue<'a>)>> {
+        if self.position >= self.bytes.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 0x07 {
+            0 => FieldValue::Varint(self.varint()?),
+            1 => FieldValue::Fixed64(self.take(8)?.try_into().map_err(|_| Self::truncated())?),
+            2 => {
+                let length = usize::try_from(self.varint()?).map_err(|_| Self::truncated())?;
+                FieldValue::Bytes(self.take(length)?)
+            }
+            5 => FieldValue::Fixed32(self.take(4)?.try_into().map_err(|_| Self::truncated())?),
+            wire => {
+                return Err(PlcError::Protocol(format!(
+                    "Unsupported protobuf wire type {wire} in Sparkplug payload"
+                )));
+            }
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn decode_metric(bytes: &[u8]) -> Result<Metric> {
+    let mut metric = Metric {
+        name: None,
+        alias: None,
+        timestamp: None,
+        datatype: 0,
+        value: MetricValue::Null,
+    };
+    let mut reader = Reader { bytes, position: 0 };
+    while let Some((field, value)) = reader.field()? {
+        match (field, value) {
+            (1, FieldValue::Bytes(b)) => metric.name = Some(String::from_utf8_lossy(b).into_owned()),
+            (2, FieldValue::Varint(v)) => metric.alias = Some(v),
+            (3, FieldValue::Varint(v)) => metric.timestamp = Some(v),
+            (4, FieldValue::Varint(v)) => metric.datatype = u32::try_from(v).unwrap_or_default(),
+            (7, FieldValue::Varint(v)) if v != 0 => metric.value = MetricValue::Null,
+            (10, FieldValue::Varint(v)) => {
+                metric.value = MetricValue::Int(match metric.datatype {
+                    data_type::INT8 => i64::from(v as i8),
+                    data_type::INT16 => i64::from(v as i16),
+                    data_type::INT32 => i64::from(v as i32),
+                    _ => i64::from(v as u32),
+                });
+            }
+            (11, FieldValue::Varint(v)) => metric.value = MetricValue::Int(v as i64),
+            (12, FieldValue::Fixed32(b)) => metric.value = MetricValue::Float(f64::from(f32::from_le_bytes(b))),
+            (13, FieldValue::Fixed64(b)) => metric.value = MetricValue::Float(f64::from_le_bytes(b)),
+            (14, FieldValue::Varint(v)) => metric.value = MetricValue::Boolean(v != 0),
+            (15, FieldValue::Bytes(b)) => {
+                metric.value = MetricValue::String(String::from_utf8_lossy(b).into_owned());
+            }
+            _ => {}
+        }
+    }
+    Ok(metric)
+}
+
+/// Parse a primary host `STATE` message
+///
+/// Accepts the Sparkplug 3.0 JSON form (`{"online": true, ...}`) and the
+/// older plain `ONLINE`/`OFFLINE` text.
+#[must_use]
+pub fn parse_host_state(payload: &[u8]) -> Option<bool> {
+    #[derive(Deserialize)]
+    struct HostState {
+        online: bool,
+    }
+    if let Ok(state) = serde_json::from_slice::<HostState>(payload) {
+        return Some(state.online);
+    }
+    match std::str::from_utf8(payload).ok()?.trim() {
+        "ONLINE" => Some(true),
+        "OFFLINE" => Some(false),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// EDGE NODE
+// ============================================================================
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Edge node session state: aliases, sequence numbers and last values
+pub struct EdgeNode {
+    config: SparkplugConfig,
+    /// Alias per metric, in configuration order starting at 1
+    aliases: Vec<u64>,
+    bd_seq: u64,
+    seq: u8,
+    last_values: HashMap<usize, Value>,
+}
+
+impl EdgeNode {
+    /// Create an edge node
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid.
+    pub fn new(config: SparkplugConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            aliases: (1..=config.metrics.len() as u64).collect(),
+            config,
+            bd_seq: 0,
+            seq: 0,
+            last_values: HashMap::new(),
+        })
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        u64::from(seq)
+    }
+
+    fn devices(&self) -> BTreeMap<Option<&str>, Vec<usize>> {
+        let mut devices: BTreeMap<Option<&str>, Vec<usize>> = BTreeMap::new();
+        devices.entry(None).or_default();
+        for (i, metric) in self.config.metrics.iter().enumerate() {
+            devices.entry(metric.device.as_deref()).or_default().push(i);
+        }
+        devices
+    }
+
+    /// Topic and payload of the `NDEATH` last will for the current session
+    #[must_use]
+    pub fn death(&self) -> (String, Vec<u8>) {
+        let payload = Payload {
+            timestamp: Some(now_ms()),
+            metrics: vec![Metric {
+                name: Some(BD_SEQ_METRIC.to_string()),
+                alias: None,
+                timestamp: None,
+                datatype: data_type::INT64,
+                #[allow(clippy::cast_possible_wrap)]
+                value: MetricValue::Int(self.bd_seq as i64),
+            }],
+            seq: None,
+        };
+        (self.config.topic("NDEATH", None), payload.encode())
+    }
+
+    /// Start a new session, incrementing `bdSeq`
+    pub fn new_session(&mut self) {
+        self.bd_seq = (self.bd_seq + 1) % 256;
+    }
+
+    /// `NBIRTH` followed by one `DBIRTH` per device
+    pub fn births(&mut self, bus: &SignalBus) -> Vec<(String, Payload)> {
+        let timestamp = now_ms();
+        self.seq = 0;
+        self.last_values.clear();
+
+        let devices: Vec<(Option<String>, Vec<usize>)> = self
+            .devices()
+            .into_iter()
+            .map(|(device, metrics)| (device.map(str::to_string), metrics))
+            .collect();
+
+        let mut messages = Vec::new();
+        for (device, indices) in devices {
+            let mut metrics = Vec::new();
+            if device.is_none() {
+                #[allow(clippy::cast_possible_wrap)]
+                let bd_seq = Some(Value::Integer(self.bd_seq as i64));
+                metrics.push(Metric::from_value(Some(BD_SEQ_METRIC), None, timestamp, bd_seq.as_ref()));
+                metrics.push(Metric::from_value(
+                    Some(REBIRTH_METRIC),
+                    None,
+                    timestamp,
+                    Some(&Value::Bool(false)),
+                ));
+            }
+            for i in indices {
+                let config = &self.config.metrics[i];
+                let value = bus.get(&config.signal);
+                metrics.push(Metric::from_value(
+                    Some(config.metric_name()),
+                    Some(self.aliases[i]),
+                    timestamp,
+                    value.as_ref(),
+                ));
+                if let Some(value) = value {
+                    self.last_values.insert(i, value);
+                }
+            }
+            let message = if device.is_some() { "DBIRTH" } else { "NBIRTH" };
+            messages.push((
+                self.config.topic(message, device.as_deref()),
+                Payload {
+                    timestamp: Some(timestamp),
+                    metrics,
+                    seq: Some(self.next_seq()),
+                },
+            ));
+        }
+        messages
+    }
+
+    /// `NDATA`/`DDATA` messages for metrics whose signals changed
+    pub fn data(&mut self, bus: &SignalBus) -> Vec<(String, Payload)> {
+        let timestamp = now_ms();
+        let mut changed: BTreeMap<Option<String>, Vec<Metric>> = BTreeMap::new();
+        for (i, config) in self.config.metrics.iter().enumerate() {
+            let Some(value) = bus.get(&config.signal) else {
+                continue;
+            };
+            if self.last_values.get(&i) == Some(&value) {
+                continue;
+            }
+            changed
+                .entry(config.device.clone())
+                .or_default()
+                .push(Metric::from_value(None, Some(self.aliases[i]), timestamp, Some(&value)));
+            self.last_values.insert(i, value);
+        }
+
+        changed
+            .into_iter()
+            .map(|(device, metrics)| {
+                let message = if device.is_some() { "DDATA" } else { "NDATA" };
+                (
+                    self.config.topic(message, device.as_deref()),
+                    Payload {
+                        timestamp: Some(timestamp),
+                        metrics,
+                        seq: Some(self.next_seq()),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Whether an `NCMD` payload requests a rebirth
+    #[must_use]
+    pub fn is_rebirth_request(payload: &Payload) -> bool {
+        payload
+            .metric(REBIRTH_METRIC, None)
+            .is_some_and(|m| m.value == MetricValue::Boolean(true))
+    }
+}
+
+/// Run the Sparkplug edge node until the task is cancelled
+///
+/// Each connection is a new Sparkplug session with its own `bdSeq`, so the
+/// MQTT client is recreated after a connection error, on the next standby
+/// broker once the failure threshold is reached.
+///
+/// # Errors
+///
+/// Returns an error if the Sparkplug configuration is invalid or the MQTT
+/// TLS certificates cannot be loaded.
+pub async fn run(mqtt: crate::config::MqttConfig, config: SparkplugConfig, bus: SignalBus) -> Result<()> {
+    let mut node = EdgeNode::new(config.clone())?;
+    let ncmd_topic = config.topic("NCMD", None);
+    let state_topic = config
+        .primary_host_id
+        .as_ref()
+        .map(|host| format!("{NAMESPACE}/STATE/{host}"));
+
+    let mut failover = crate::protocols::mqtt_failover::BrokerFailover::new(&mqtt);
+    loop {
+        node.new_session();
+        let (death_topic, death_payload) = node.death();
+        let mut options = failover.current().connection_options()?;
+        options.set_last_will(LastWill::new(death_topic, death_payload, QoS::AtLeastOnce, false));
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+
+        let mut ticker = tokio::time::interval(Duration::from_millis(config.scan_interval_ms));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut host_online = state_topic.is_none();
+        let mut born = false;
+
+        let session: Result<()> = async {
+            loop {
+                let publish_births = tokio::select! {
+                    event = eventloop.poll() => match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            info!(
+                                "Sparkplug edge node '{}/{}' connected to {}",
+                                config.group_id,
+                                config.edge_node_id,
+                                failover.current().broker_url()
+                            );
+                            failover.connected();
+                            client.subscribe(&ncmd_topic, QoS::AtLeastOnce).await.map_err(mqtt_error)?;
+                            if let Some(state_topic) = &state_topic {
+                                client.subscribe(state_topic, QoS::AtLeastOnce).await.map_err(mqtt_error)?;
+                            }
+                            host_online
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            if Some(&publish.topic) == state_topic.as_ref() {
+                                let online = parse_host_state(&publish.payload).unwrap_or(false);
+                                let came_online = online && !host_online;
+                                host_online = online;
+                                if !online {
+                                    warn!("Sparkplug primary host offline, holding data");
+                                }
+                                came_online
+                            } else if publish.topic == ncmd_topic {
+                                match Payload::decode(&publish.payload) {
+                                    Ok(command) => EdgeNode::is_rebirth_request(&command),
+                                    Err(e) => {
+                                        warn!("Ignoring malformed NCMD: {}", e);
+                                        false
+                                    }
+                                }
+                            } else {
+                                false
+                            }
+                        }
+                        Ok(_) => false,
+                        Err(e) => return Err(mqtt_error(e)),
+                    },
+                    _ = ticker.tick() => {
+                        if born && host_online {
+                            let messages = crate::scan_budget::measure(
+                                crate::scan_budget::Subsystem::Protocols,
+                                || node.data(&bus),
+                            );
+                            publish_all(&client, messages).await?;
+                        }
+                        false
+                    }
+                };
+
+                if publish_births {
+                    debug!("Publishing Sparkplug births (bdSeq {})", node.bd_seq);
+                    publish_all(&client, node.births(&bus)).await?;
+                    born = true;
+                }
+            }
+        }
+        .await;
+
+        if let Err(e) = session {
+            error!("Sparkplug session ended: {}", e);
+            failover.failed();
+        }
+        tokio::time::sleep(Duration::from_secs(mqtt.reconnect_delay_secs)).await;
+    }
+}
+
+fn mqtt_error(e: impl std::fmt::Display) -> PlcError {
+    PlcError::Mqtt(e.to_string())
+}
+
+async fn publish_all(client: &AsyncClient, messages: Vec<(String, Payload)>) -> Result<()> {
+    for (topic, payload) in messages {
+        client
+            .publish(topic, QoS::AtMostOnce, false, payload.encode())
+            .await
+            .map_err(mqtt_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node() -> EdgeNode {
+        let config: SparkplugConfig = serde_yaml::from_str(
+            r"
+group_id: plant1
+edge_node_id: line1
+metrics:
+  - { signal: line1.speed }
+  - { signal: filler.running, name: Running, device: filler }
+",
+        )
+        .unwrap();
+        EdgeNode::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let payload = Payload {
+            timestamp: Some(1_700_000_000_000),
+            metrics: vec![
+                Metric::from_value(Some("speed"), Some(1), 5, Some(&Value::Float(12.5))),
+                Metric::from_value(None, Some(2), 5, Some(&Value::Integer(-3))),
+                Metric::from_value(Some("run"), None, 5, Some(&Value::Bool(true))),
+                Metric::from_value(Some("missing"), Some(4), 5, None),
+            ],
+            seq: Some(255),
+        };
+        assert_eq!(Payload::decode(&payload.encode()).unwrap(), payload);
+        assert!(Payload::decode(&[0x12, 0x05, 0x0A]).is_err());
+    }
+
+    #[test]
+    fn test_births_and_report_by_exception() {
+        let bus = SignalBus::new();
+        bus.set("line1.speed", Value::Float(1.0)).unwrap();
+        bus.set("filler.running", Value::Bool(false)).unwrap();
+        let mut node = node();
+        node.new_session();
+
+        let births = node.births(&bus);
+        assert_eq!(births[0].0, "spBv1.0/plant1/NBIRTH/line1");
+        assert_eq!(births[1].0, "spBv1.0/plant1/DBIRTH/line1/filler");
+        let nbirth = &births[0].1;
+        assert_eq!(nbirth.seq, Some(0));
+        assert_eq!(nbirth.metric(BD_SEQ_METRIC, None).unwrap().value, MetricValue::Int(1));
+        let speed = nbirth.metric("line1.speed", None).unwrap();
+        assert_eq!(speed.alias, Some(1));
+        assert_eq!(births[1].1.metric("Running", None).unwrap().alias, Some(2));
+
+        // Unchanged signals are not republished
+        assert!(node.data(&bus).is_empty());
+
+        bus.set("filler.running", Value::Bool(true)).unwrap();
+        let data = node.data(&bus);
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].0, "spBv1.0/plant1/DDATA/line1/filler");
+        let metric = &data[0].1.metrics[0];
+        assert_eq!((metric.name.as_deref(), metric.alias), (None, Some(2)));
+        assert_eq!(data[0].1.seq, Some(2));
+
+        let rebirth = Payload {
+            timestamp: None,
+            metrics: vec![Metric::from_value(Some(REBIRTH_METRIC), None, 0, Some(&Value::Bool(true)))],
+            seq: None,
+        };
+        assert!(EdgeNode::is_rebirth_request(&Payload::decode(&rebirth.encode()).unwrap()));
+    }
+
+    #[test]
+    fn test_host_state() {
+        assert_eq!(parse_host_state(br#"{"online":true,"timestamp":1}"#), Some(true));
+        assert_eq!(parse_host_state(b"OFFLINE"), Some(false));
+        assert_eq!(parse_host_state(b"garbage"), None);
+    }
+}
@@ -0,0 +1,711 @@
+// src/redundancy.rs
+//! Hot-standby redundancy for a pair of PETRA nodes
+//!
+//! Two nodes run the same configuration and talk over a dedicated TCP
+//! channel. One node is active and executes blocks; the other is standby,
+//! with block execution paused, and mirrors the active node's signal values
+//! and block internal state (timers, counters, integrators). When the
+//! standby stops hearing heartbeats it takes over from the last state it
+//! received, so outputs continue from the values the failed node left them
+//! at instead of jumping back to initial values.
+//!
+//! ```yaml
+//! redundancy:
+//!   node_id: plc-a
+//!   role: primary            # preferred role, the peer is `standby`
+//!   listen: "0.0.0.0:7400"
+//!   peer: "10.0.0.2:7400"
+//!   heartbeat_ms: 100
+//!   failover_timeout_ms: 500
+//!   sync_interval_ms: 100
+//!   exclude: ["local.*"]     # node-specific signals that are not mirrored
+//! ```
+//!
+//! # Roles
+//!
+//! A node starts paused and waits up to `failover_timeout_ms` for its peer.
+//! It becomes standby if the peer is already active, and active if the
+//! peer stays silent or outranks it. A standby takes over once heartbeats
+//! stop for `failover_timeout_ms`. If both nodes end up active after a
+//! network partition heals, the outranked node steps back to standby and is
+//! resynchronised. The preferred primary outranks the preferred standby,
+//! otherwise the lower `node_id` wins; a recovered primary does not take
+//! control back from an active standby.
+//!
+//! The channel carries one JSON message per line: heartbeats with the
+//! sender's role every `heartbeat_ms`, and from the active node a snapshot
+//! of signals and block states every `sync_interval_ms`.
+//!
+//! Published signals (prefix configurable, default `redundancy`):
+//!
+//! - `redundancy.active` - this node executes blocks
+//! - `redundancy.peer_ok` - heartbeats from the peer are arriving
+//! - `redundancy.failovers` - takeovers by this node since startup
+//!
+//! Protocol outputs that must only be driven by one node can be gated on
+//! `redundancy.active`.
+
+use crate::engine::{BlockControl, EngineControl};
+use crate::error::{PlcError, Result};
+use crate::events::{EventKind, EventLog};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Redundant pair configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedundancyConfig {
+    /// Name of this node, different from the peer's
+    pub node_id: String,
+
+    /// Role this node takes when both nodes start together
+    #[serde(default)]
+    pub role: PreferredRole,
+
+    /// Address the channel listens on for the peer
+    pub listen: String,
+
+    /// Address of the peer's channel
+    pub peer: String,
+
+    /// Interval between heartbeats
+    #[serde(default = "default_heartbeat_ms")]
+    pub heartbeat_ms: u64,
+
+    /// Silence after which the peer is considered failed
+    #[serde(default = "default_failover_timeout_ms")]
+    pub failover_timeout_ms: u64,
+
+    /// Interval between state snapshots sent by the active node
+    #[serde(default = "default_sync_interval_ms")]
+    pub sync_interval_ms: u64,
+
+    /// Patterns of signals that are not mirrored; `*` matches any run of
+    /// characters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+
+    /// Prefix of the published status signals
+    #[serde(default = "default_signal_prefix")]
+    pub signal_prefix: String,
+}
+
+/// Role a node prefers when both nodes start together
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferredRole {
+    #[default]
+    Primary,
+    Standby,
+}
+
+/// Current role of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Waiting to hear from the peer; blocks are not executed
+    Starting,
+    /// Executing blocks and sending state to the peer
+    Active,
+    /// Mirroring the peer's state; blocks are not executed
+    Standby,
+}
+
+impl Role {
+    /// Name used in events and the REST API
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::Active => "active",
+            Self::Standby => "standby",
+        }
+    }
+}
+
+const fn default_heartbeat_ms() -> u64 {
+    100
+}
+
+const fn default_failover_timeout_ms() -> u64 {
+    500
+}
+
+const fn default_sync_interval_ms() -> u64 {
+    100
+}
+
+fn default_signal_prefix() -> String {
+    "redundancy".to_string()
+}
+
+impl RedundancyConfig {
+    /// Validate addresses and timing
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.node_id.trim().is_empty() {
+            return Err(PlcError::Config("Redundancy node_id cannot be empty".to_string()));
+        }
+        self.listen.parse::<SocketAddr>().map_err(|e| {
+            PlcError::Config(format!("Invalid redundancy listen address '{}': {}", self.listen, e))
+        })?;
+        if !self.peer.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()) {
+            return Err(PlcError::Config(format!(
+                "Redundancy peer '{}' must be host:port",
+                self.peer
+            )));
+        }
+        if self.heartbeat_ms == 0 || self.sync_interval_ms == 0 {
+            return Err(PlcError::Config(
+                "Redundancy heartbeat_ms and sync_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        if self.failover_timeout_ms < self.heartbeat_ms.saturating_mul(3) {
+            return Err(PlcError::Config(format!(
+                "Redundancy failover_timeout_ms ({}) must be at least three heartbeats ({} ms)",
+                self.failover_timeout_ms,
+                self.heartbeat_ms.saturating_mul(3)
+            )));
+        }
+        Ok(())
+    }
+
+    fn mirrored(&self, signal: &str) -> bool {
+        let prefix = format!("{}.", self.signal_prefix);
+        !signal.starts_with(&prefix) && !self.exclude.iter().any(|p| matches(p, signal))
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((head, tail)) => {
+            let Some(mut rest) = name.strip_prefix(head) else {
+                return false;
+            };
+            loop {
+                if matches(tail, rest) {
+                    return true;
+                }
+                let mut chars = rest.chars();
+                if chars.next().is_none() {
+                    return false;
+                }
+                rest = chars.as_str();
+            }
+        }
+    }
+}
+
+// ============================================================================
+// MESSAGES
+// ============================================================================
+
+/// Message on the redundancy channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Heartbeat { node_id: String, preferred: PreferredRole, role: Role, scan_count: u64 },
+    State(Snapshot),
+}
+
+/// State of the active node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Snapshot {
+    scan_count: u64,
+    signals: HashMap<String, Value>,
+    blocks: HashMap<String, serde_json::Value>,
+}
+
+// ============================================================================
+// ROLE DECISIONS
+// ============================================================================
+
+/// What this node last heard from its peer
+#[derive(Debug, Clone)]
+struct Peer {
+    node_id: String,
+    preferred: PreferredRole,
+    role: Role,
+    scan_count: u64,
+    heard: Instant,
+}
+
+/// This node's role state, shared by the channel tasks
+#[derive(Debug)]
+struct Local {
+    node_id: String,
+    preferred: PreferredRole,
+    role: Role,
+    started: Instant,
+    peer: Option<Peer>,
+    failovers: u64,
+    synced_scan: Option<u64>,
+}
+
+impl Local {
+    fn outranks(&self, peer: &Peer) -> bool {
+        match (self.preferred, peer.preferred) {
+            (PreferredRole::Primary, PreferredRole::Standby) => true,
+            (PreferredRole::Standby, PreferredRole::Primary) => false,
+            _ => self.node_id < peer.node_id,
+        }
+    }
+
+    /// Role to move to, if any, with the reason
+    fn decide(&self, now: Instant, timeout: Duration) -> Option<(Role, &'static str)> {
+        let peer = self.peer.as_ref().filter(|p| now.duration_since(p.heard) < timeout);
+        match (self.role, peer) {
+            (Role::Starting, Some(peer)) if peer.role == Role::Active => Some((Role::Standby, "peer is active")),
+            (Role::Starting, _) if now.duration_since(self.started) < timeout => None,
+            (Role::Starting, None) => Some((Role::Active, "no peer answered")),
+            (Role::Starting, Some(peer)) => Some(if self.outranks(peer) {
+                (Role::Active, "outranks peer")
+            } else {
+                (Role::Standby, "peer outranks this node")
+            }),
+            (Role::Standby, None) => Some((Role::Active, "peer lost")),
+            (Role::Standby, Some(peer)) if peer.role == Role::Standby && self.outranks(peer) => {
+                Some((Role::Active, "peer is standby"))
+            }
+            (Role::Active, Some(peer)) if peer.role == Role::Active && !self.outranks(peer) => {
+                Some((Role::Standby, "peer outranks this node"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Status of this node and its peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedundancyStatus {
+    pub node_id: String,
+    pub preferred: PreferredRole,
+    pub role: Role,
+    /// Takeovers by this node since startup
+    pub failovers: u64,
+    /// Scan count of the last snapshot applied while standby
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_scan: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<PeerStatus>,
+}
+
+/// Last heartbeat received from the peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub node_id: String,
+    pub role: Role,
+    pub scan_count: u64,
+    /// Milliseconds since the heartbeat was received
+    pub heard_ms_ago: u64,
+    /// Heartbeats are arriving within the failover timeout
+    pub alive: bool,
+}
+
+// ============================================================================
+// REDUNDANCY
+// ============================================================================
+
+/// One node of a redundant pair
+///
+/// Clones share the node's state; [`run`](Self::run) drives it and
+/// [`status`](Self::status) reports it, e.g. to `/api/redundancy`.
+#[derive(Clone)]
+pub struct Redundancy {
+    config: Arc<RedundancyConfig>,
+    node: Arc<Mutex<Local>>,
+    bus: SignalBus,
+    control: EngineControl,
+    blocks: BlockControl,
+    events: EventLog,
+}
+
+impl Redundancy {
+    /// Create the node for an engine, given its handles
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid.
+    pub fn new(config: RedundancyConfig, bus: SignalBus, control: EngineControl, blocks: BlockControl) -> Result<Self> {
+        config.validate()?;
+        let node = Local {
+            node_id: config.node_id.clone(),
+            preferred: config.role,
+            role: Role::Starting,
+            started: Instant::now(),
+            peer: None,
+            failovers: 0,
+            synced_scan: None,
+        };
+        let events = control.events();
+        Ok(Self { config: Arc::new(config), node: Arc::new(Mutex::new(node)), bus, control, blocks, events })
+    }
+
+    fn node(&self) -> std::sync::MutexGuard<'_, Local> {
+        self.node.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Current role of this node
+    #[must_use]
+    pub fn role(&self) -> Role {
+        self.node().role
+    }
+
+    /// Roles, peer heartbeat and synchronisation progress
+    #[must_use]
+    pub fn status(&self) -> RedundancyStatus {
+        let timeout = Duration::from_millis(self.config.failover_timeout_ms);
+        let node = self.node();
+        RedundancyStatus {
+            node_id: node.node_id.clone(),
+            preferred: node.preferred,
+            role: node.role,
+            failovers: node.failovers,
+            synced_scan: node.synced_scan,
+            peer: node.peer.as_ref().map(|peer| {
+                let ago = peer.heard.elapsed();
+                PeerStatus {
+                    node_id: peer.node_id.clone(),
+                    role: peer.role,
+                    scan_count: peer.scan_count,
+                    heard_ms_ago: u64::try_from(ago.as_millis()).unwrap_or(u64::MAX),
+                    alive: ago < timeout,
+                }
+            }),
+        }
+    }
+
+    /// Pause the engine and run the channel until the task is cancelled
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the listen address cannot be bound.
+    pub async fn run(self) -> Result<()> {
+        self.control.pause().await;
+        self.publish_status();
+        let listener = TcpListener::bind(&self.config.listen).await?;
+        info!(
+            "Redundancy node '{}' listening on {}, peer {}",
+            self.config.node_id, self.config.listen, self.config.peer
+        );
+
+        let mut tasks = tokio::task::JoinSet::new();
+        tasks.spawn(self.clone().accept(listener));
+        tasks.spawn(self.clone().send());
+        tasks.spawn(self.clone().supervise());
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Err(e)) = result {
+                tasks.abort_all();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn accept(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, address) = listener.accept().await?;
+            debug!("Redundancy peer connected from {}", address);
+            let node = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = node.receive(stream).await {
+                    debug!("Redundancy connection from {} closed: {}", address, e);
+                }
+            });
+        }
+    }
+
+    async fn receive(&self, stream: TcpStream) -> Result<()> {
+        let mut lines = BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str::<Message>(&line) {
+                Ok(message) => self.handle(message).await,
+                Err(e) => warn!("Skipping malformed redundancy message: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, message: Message) {
+        match message {
+            Message::Heartbeat { node_id, preferred, role, scan_count } => {
+                self.node().peer = Some(Peer { node_id, preferred, role, scan_count, heard: Instant::now() });
+            }
+            Message::State(snapshot) => {
+                if self.role() == Role::Active {
+                    return;
+                }
+                for (name, value) in snapshot.signals {
+                    if self.config.mirrored(&name) {
+                        if let Err(e) = self.bus.set_with_source(&name, value, Some("redundancy")) {
+                            debug!("Could not mirror '{}': {}", name, e);
+                        }
+                    }
+                }
+                self.blocks.load_states(&snapshot.blocks).await;
+                self.node().synced_scan = Some(snapshot.scan_count);
+            }
+        }
+    }
+
+    async fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            scan_count: self.control.scan_count(),
+            signals: self.bus.snapshot().into_iter().filter(|(name, _)| self.config.mirrored(name)).collect(),
+            blocks: self.blocks.save_states().await,
+        }
+    }
+
+    /// Keep a connection to the peer and send heartbeats and snapshots
+    async fn send(self) -> Result<()> {
+        let heartbeat = Duration::from_millis(self.config.heartbeat_ms);
+        let sync = Duration::from_millis(self.config.sync_interval_ms);
+        loop {
+            let Ok(mut stream) = TcpStream::connect(&self.config.peer).await else {
+                tokio::time::sleep(heartbeat).await;
+                continue;
+            };
+            debug!("Connected to redundancy peer {}", self.config.peer);
+            let _ = stream.set_nodelay(true);
+            let mut ticker = tokio::time::interval(heartbeat.min(sync));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            let mut last_heartbeat: Option<Instant> = None;
+            let mut last_sync: Option<Instant> = None;
+            loop {
+                ticker.tick().await;
+                let mut messages = Vec::new();
+                if last_heartbeat.is_none_or(|t| t.elapsed() >= heartbeat) {
+                    let node = self.node();
+                    messages.push(Message::Heartbeat {
+                        node_id: node.node_id.clone(),
+                        preferred: node.preferred,
+                        role: node.role,
+                        scan_count: self.control.scan_count(),
+                    });
+                    last_heartbeat = Some(Instant::now());
+                }
+                if self.role() == Role::Active && last_sync.is_none_or(|t| t.elapsed() >= sync) {
+                    messages.push(Message::State(self.snapshot().await));
+                    last_sync = Some(Instant::now());
+                }
+                let mut payload = Vec::new();
+                for message in &messages {
+                    serde_json::to_writer(&mut payload, message)?;
+                    payload.push(b'\n');
+                }
+                if let Err(e) = stream.write_all(&payload).await {
+                    debug!("Redundancy peer {} disconnected: {}", self.config.peer, e);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Apply role decisions every heartbeat
+    async fn supervise(self) -> Result<()> {
+        let timeout = Duration::from_millis(self.config.failover_timeout_ms);
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.heartbeat_ms));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let change = {
+                let mut node = self.node();
+                let decision = node.decide(Instant::now(), timeout);
+                decision.map(|(role, reason)| {
+                    let from = std::mem::replace(&mut node.role, role);
+                    if from == Role::Standby && role == Role::Active {
+                        node.failovers += 1;
+                    }
+                    (from, role, reason)
+                })
+            };
+            if let Some((from, to, reason)) = change {
+                match to {
+                    Role::Active => {
+                        warn!("Redundancy node '{}' is now active: {}", self.config.node_id, reason);
+                        self.control.resume().await;
+                    }
+                    Role::Standby | Role::Starting => {
+                        warn!("Redundancy node '{}' is now standby: {}", self.config.node_id, reason);
+                        self.control.pause().await;
+                    }
+                }
+                self.events.publish(EventKind::RedundancyRoleChanged {
+                    from: from.name().to_string(),
+                    to: to.name().to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+            self.publish_status();
+        }
+    }
+
+    fn publish_status(&self) {
+        let status = self.status();
+        let prefix = &self.config.signal_prefix;
+        let failovers = i64::try_from(status.failovers).unwrap_or(i64::MAX);
+        for (name, value) in [
+            ("active", Value::Bool(status.role == Role::Active)),
+            ("peer_ok", Value::Bool(status.peer.is_some_and(|p| p.alive))),
+            ("failovers", Value::Integer(failovers)),
+        ] {
+            if let Err(e) = self.bus.set(format!("{prefix}.{name}"), value) {
+                debug!("Could not publish redundancy status '{}': {}", name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::engine::Engine;
+
+    fn node(node_id: &str, preferred: PreferredRole, role: Role) -> Local {
+        let started = Instant::now().checked_sub(Duration::from_secs(5)).unwrap();
+        Local { node_id: node_id.into(), preferred, role, started, peer: None, failovers: 0, synced_scan: None }
+    }
+
+    fn peer(node_id: &str, preferred: PreferredRole, role: Role, heard: Instant) -> Peer {
+        Peer { node_id: node_id.into(), preferred, role, scan_count: 0, heard }
+    }
+
+    #[test]
+    fn test_role_decisions() {
+        let timeout = Duration::from_millis(500);
+        let now = Instant::now();
+        let silent = now.checked_sub(Duration::from_secs(1)).unwrap();
+
+        // Startup: wait for the peer, defer to an active one, else take over
+        let mut a = node("a", PreferredRole::Standby, Role::Starting);
+        a.started = now;
+        assert_eq!(a.decide(now, timeout), None);
+        a.peer = Some(peer("b", PreferredRole::Primary, Role::Active, now));
+        assert_eq!(a.decide(now, timeout).map(|d| d.0), Some(Role::Standby));
+        let mut b = node("b", PreferredRole::Primary, Role::Starting);
+        assert_eq!(b.decide(now, timeout).map(|d| d.0), Some(Role::Active));
+        b.peer = Some(peer("a", PreferredRole::Standby, Role::Starting, now));
+        assert_eq!(b.decide(now, timeout).map(|d| d.0), Some(Role::Active));
+
+        // Standby takes over once heartbeats stop, and not before
+        let mut standby = node("a", PreferredRole::Standby, Role::Standby);
+        standby.peer = Some(peer("b", PreferredRole::Primary, Role::Active, now));
+        assert_eq!(standby.decide(now, timeout), None);
+        standby.peer = Some(peer("b", PreferredRole::Primary, Role::Active, silent));
+        assert_eq!(standby.decide(now, timeout), Some((Role::Active, "peer lost")));
+
+        // A returning primary stays standby; after a split brain the
+        // outranked node steps back
+        let mut returning = node("b", PreferredRole::Primary, Role::Standby);
+        returning.peer = Some(peer("a", PreferredRole::Standby, Role::Active, now));
+        assert_eq!(returning.decide(now, timeout), None);
+        let mut split = node("a", PreferredRole::Standby, Role::Active);
+        split.peer = Some(peer("b", PreferredRole::Primary, Role::Active, now));
+        assert_eq!(split.decide(now, timeout).map(|d| d.0), Some(Role::Standby));
+        let mut tie = node("a", PreferredRole::Primary, Role::Active);
+        tie.peer = Some(peer("b", PreferredRole::Primary, Role::Active, now));
+        assert_eq!(tie.decide(now, timeout), None);
+    }
+
+    const CONFIG: &str = "\
+scan_time_ms: 10
+max_scan_jitter_ms: 5
+signals:
+  - { name: running, type: bool }
+  - { name: clear, type: bool }
+  - { name: done, type: bool }
+  - { name: elapsed, type: int }
+blocks:
+  - { name: runtime, type: TONR, inputs: { in: running, reset: clear }, outputs: { out: done, elapsed: elapsed }, params: { preset_ms: 60000 } }
+";
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn test_standby_mirrors_state_and_takes_over() {
+        let (port_a, port_b) = (free_port(), free_port());
+        let config = |node_id: &str, role, listen: u16, peer: u16| RedundancyConfig {
+            node_id: node_id.into(),
+            role,
+            listen: format!("127.0.0.1:{listen}"),
+            peer: format!("127.0.0.1:{peer}"),
+            heartbeat_ms: 10,
+            failover_timeout_ms: 100,
+            sync_interval_ms: 10,
+            exclude: Vec::new(),
+            signal_prefix: default_signal_prefix(),
+        };
+        let start = |config: RedundancyConfig| {
+            let engine = Engine::new(serde_yaml::from_str::<Config>(CONFIG).unwrap()).unwrap();
+            let node =
+                Redundancy::new(config, engine.signal_bus().clone(), engine.engine_control(), engine.block_control())
+                    .unwrap();
+            (engine, node)
+        };
+        let (primary_engine, primary) = start(config("a", PreferredRole::Primary, port_a, port_b));
+        let (standby_engine, standby) = start(config("b", PreferredRole::Standby, port_b, port_a));
+        let primary_task = tokio::spawn(primary.clone().run());
+        tokio::spawn(standby.clone().run());
+
+        let wait = |node: Redundancy, role: Role| async move {
+            for _ in 0..200 {
+                if node.role() == role {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("node did not become {role:?}");
+        };
+        wait(primary.clone(), Role::Active).await;
+        wait(standby.clone(), Role::Standby).await;
+        assert!(standby_engine.engine_control().is_paused());
+
+        // Accumulate run time on the primary
+        let bus = primary_engine.signal_bus();
+        bus.set("running", Value::Bool(true)).unwrap();
+        primary_engine.execute_scan_cycle().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        primary_engine.execute_scan_cycle().await.unwrap();
+        let accumulated = bus.get_int("elapsed").unwrap();
+        assert!(accumulated >= 50);
+        let state = primary_engine.block_control().save_states().await;
+        for _ in 0..100 {
+            if standby_engine.block_control().save_states().await == state {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(standby_engine.block_control().save_states().await, state);
+        assert_eq!(standby_engine.signal_bus().get("elapsed"), Some(Value::Integer(accumulated)));
+
+        // The standby takes over and keeps accumulating from there
+        primary_task.abort();
+        primary_engine.engine_control().pause().await;
+        wait(standby.clone(), Role::Active).await;
+        assert!(!standby_engine.engine_control().is_paused());
+        assert_eq!(standby.status().failovers, 1);
+        standby_engine.execute_scan_cycle().await.unwrap();
+        let bus = standby_engine.signal_bus();
+        assert!(bus.get_int("elapsed").unwrap() >= accumulated);
+        assert_eq!(bus.get("redundancy.active"), Some(Value::Bool(true)));
+    }
+}
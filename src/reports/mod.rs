@@ -0,0 +1,748 @@
+// src/reports/mod.rs
+//! Scheduled report generation
+//!
+//! Reports aggregate signal history over a fixed period, such as the last
+//! hour, day or week, and render it as HTML, CSV or PDF. Each column
+//! reduces one signal with an aggregate:
+//!
+//! - `min`, `max`, `avg`, `sum`, `first`, `last`: plain sample statistics
+//! - `delta`: increase of a counter or meter, tolerating counter resets.
+//!   Use it for production counts and energy meters
+//! - `rising_edges`: how often a boolean signal switched on, such as alarm
+//!   activations
+//! - `time_true`: seconds a boolean signal was on
+//!
+//! Reports are rendered from [minijinja](https://docs.rs/minijinja)
+//! templates. Built-in templates are used unless a report names its own
+//! `template` file. A template receives `title`, `start`, `end`,
+//! `generated_at`, `columns`, `rows` and a pre-formatted `table_text`. PDF
+//! output renders the template as plain text onto monospaced A4 pages. CSV
+//! output is written directly and ignores templates.
+//!
+//! Finished reports are written to `output_dir`, mailed to recipients
+//! (feature `reports-email`) and uploaded to S3-compatible storage (feature
+//! `reports-s3`).
+//!
+//! ```yaml
+//! reports:
+//!   utc_offset_minutes: 60
+//!   output_dir: reports
+//!   definitions:
+//!     - name: daily_production
+//!       title: Daily production
+//!       period: daily
+//!       at: "06:00"
+//!       format: pdf
+//!       breakdown: hourly
+//!       columns:
+//!         - { signal: line1.good_count, label: Good parts, aggregate: delta, decimals: 0 }
+//!         - { signal: line1.energy_kwh, label: Energy, aggregate: delta, unit: kWh }
+//!         - { signal: line1.alarm_active, label: Alarms, aggregate: rising_edges, decimals: 0 }
+//!       delivery:
+//!         email: [production@example.com]
+//! ```
+
+mod delivery;
+mod render;
+
+pub use render::{ColumnHeader, ReportData, ReportRow};
+#[cfg(feature = "reports-email")]
+pub use delivery::SmtpConfig;
+#[cfg(feature = "reports-s3")]
+pub use delivery::S3Config;
+
+use crate::{PlcError, Result, SignalBus};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// How often the scheduler checks for due reports
+const SCHEDULE_CHECK_INTERVAL_S: u64 = 30;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Report subsystem configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportsConfig {
+    /// Offset of plant local time from UTC in minutes
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+
+    /// Directory where generated reports are stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+
+    /// How often report signals are sampled from the bus
+    #[serde(default = "default_sample_interval_ms")]
+    pub sample_interval_ms: u64,
+
+    /// SMTP server used for email delivery
+    #[cfg(feature = "reports-email")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<delivery::SmtpConfig>,
+
+    /// S3 bucket used for uploads
+    #[cfg(feature = "reports-s3")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<delivery::S3Config>,
+
+    /// Report definitions
+    pub definitions: Vec<ReportDefinition>,
+}
+
+fn default_sample_interval_ms() -> u64 {
+    10_000
+}
+
+/// One scheduled report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDefinition {
+    /// Unique name, also used for file names
+    pub name: String,
+
+    /// Heading shown in the report
+    #[serde(default)]
+    pub title: String,
+
+    /// Period covered by each report
+    pub period: ReportPeriod,
+
+    /// Local time at which periods end; only the minutes apply to hourly reports
+    #[serde(default = "default_at")]
+    pub at: NaiveTime,
+
+    /// Output format
+    #[serde(default)]
+    pub format: ReportFormat,
+
+    /// Template file replacing the built-in template
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+
+    /// Add one row per hour or day before the total
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<Breakdown>,
+
+    /// Report columns
+    pub columns: Vec<ReportColumn>,
+
+    /// Where finished reports go besides `output_dir`
+    #[serde(default)]
+    pub delivery: ReportDelivery,
+}
+
+fn default_at() -> NaiveTime {
+    NaiveTime::MIN
+}
+
+/// Period covered by a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    Hourly,
+    Daily,
+    /// Weeks end on Monday at `at`
+    Weekly,
+}
+
+impl ReportPeriod {
+    fn duration(self) -> Duration {
+        match self {
+            Self::Hourly => Duration::hours(1),
+            Self::Daily => Duration::days(1),
+            Self::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+/// Row breakdown within a report period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Breakdown {
+    Hourly,
+    Daily,
+}
+
+impl Breakdown {
+    fn duration(self) -> Duration {
+        match self {
+            Self::Hourly => Duration::hours(1),
+            Self::Daily => Duration::days(1),
+        }
+    }
+}
+
+/// Report output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Html,
+    Csv,
+    Pdf,
+}
+
+impl ReportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Csv => "csv",
+            Self::Pdf => "pdf",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Html => "text/html; charset=utf-8",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// One report column
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportColumn {
+    /// Signal to aggregate
+    pub signal: String,
+
+    /// Column heading, defaulting to the signal name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Reduction applied over each row's time range
+    pub aggregate: Aggregate,
+
+    /// Unit shown next to the heading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+
+    /// Factor applied to the aggregate, e.g. 1/60 to show `time_true` in minutes
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    /// Decimal places shown
+    #[serde(default = "default_decimals")]
+    pub decimals: usize,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_decimals() -> usize {
+    2
+}
+
+/// Reduction of a signal's samples to one value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Min,
+    Max,
+    Avg,
+    Sum,
+    First,
+    Last,
+    Delta,
+    RisingEdges,
+    TimeTrue,
+}
+
+/// Delivery targets of a report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportDelivery {
+    /// Email recipients
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email: Vec<String>,
+
+    /// Upload to the configured S3 bucket
+    #[serde(default)]
+    pub s3: bool,
+}
+
+impl ReportsConfig {
+    /// Validate report definitions and delivery settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for duplicate or unsafe report names,
+    /// reports without columns or targets, and delivery targets whose
+    /// feature or settings are missing.
+    pub fn validate(&self) -> Result<()> {
+        self.offset()?;
+        if self.sample_interval_ms == 0 {
+            return Err(PlcError::Config("Report sample interval must be positive".into()));
+        }
+        let mut names = HashSet::new();
+        for report in &self.definitions {
+            if report.name.is_empty()
+                || !report.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(PlcError::Config(format!(
+                    "Report name '{}' must be non-empty and use only letters, digits, '_' and '-'",
+                    report.name
+                )));
+            }
+            if !names.insert(report.name.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate report '{}'", report.name)));
+            }
+            if report.columns.is_empty() {
+                return Err(PlcError::Config(format!("Report '{}' has no columns", report.name)));
+            }
+            if let Some(column) = report.columns.iter().find(|c| !c.scale.is_finite()) {
+                return Err(PlcError::Config(format!(
+                    "Report '{}' column '{}' has an invalid scale",
+                    report.name, column.signal
+                )));
+            }
+            if matches!((report.period, report.breakdown), (ReportPeriod::Hourly, Some(_)))
+                || matches!((report.period, report.breakdown), (ReportPeriod::Daily, Some(Breakdown::Daily)))
+            {
+                return Err(PlcError::Config(format!(
+                    "Report '{}' breakdown must be shorter than its period",
+                    report.name
+                )));
+            }
+            if self.output_dir.is_none() && report.delivery.email.is_empty() && !report.delivery.s3 {
+                return Err(PlcError::Config(format!(
+                    "Report '{}' has no output_dir, email or s3 target",
+                    report.name
+                )));
+            }
+            self.validate_delivery(report)?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(any(feature = "reports-email", feature = "reports-s3")), allow(clippy::unused_self))]
+    fn validate_delivery(&self, report: &ReportDefinition) -> Result<()> {
+        if !report.delivery.email.is_empty() {
+            #[cfg(feature = "reports-email")]
+            if self.smtp.is_none() {
+                return Err(PlcError::Config(format!(
+                    "Report '{}' is mailed but no smtp server is configured",
+                    report.name
+                )));
+            }
+            #[cfg(not(feature = "reports-email"))]
+            return Err(PlcError::Config(format!(
+                "Report '{}' email delivery requires the reports-email feature",
+                report.name
+            )));
+        }
+        if report.delivery.s3 {
+            #[cfg(feature = "reports-s3")]
+            if self.s3.is_none() {
+                return Err(PlcError::Config(format!(
+                    "Report '{}' is uploaded but no s3 bucket is configured",
+                    report.name
+                )));
+            }
+            #[cfg(not(feature = "reports-s3"))]
+            return Err(PlcError::Config(format!(
+                "Report '{}' S3 delivery requires the reports-s3 feature",
+                report.name
+            )));
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> Result<FixedOffset> {
+        FixedOffset::east_opt(self.utc_offset_minutes * 60).ok_or_else(|| {
+            PlcError::Config(format!(
+                "Invalid report UTC offset: {} minutes",
+                self.utc_offset_minutes
+            ))
+        })
+    }
+
+    /// Signals referenced by any report
+    #[must_use]
+    pub fn signals(&self) -> Vec<String> {
+        let mut signals: Vec<String> = self
+            .definitions
+            .iter()
+            .flat_map(|r| r.columns.iter().map(|c| c.signal.clone()))
+            .collect();
+        signals.sort();
+        signals.dedup();
+        signals
+    }
+}
+
+// ============================================================================
+// DATA SOURCES
+// ============================================================================
+
+/// Source of historical samples for reports
+#[async_trait]
+pub trait ReportSource: Send + Sync {
+    /// Numeric samples of `signal` in `[start, end)`, oldest first
+    async fn samples(
+        &self,
+        signal: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>>;
+}
+
+#[cfg(feature = "history")]
+#[async_trait]
+impl ReportSource for crate::history::HistoryManager {
+    async fn samples(
+        &self,
+        signal: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>> {
+        let entries = self
+            .query(crate::history::HistoryQuery {
+                signal_name: Some(signal.to_string()),
+                start_time: Some(start),
+                end_time: Some(end),
+                limit: None,
+            })
+            .await?;
+        let mut samples: Vec<_> = entries
+            .into_iter()
+            .filter(|e| e.timestamp < end)
+            .filter_map(|e| e.value.as_float().map(|v| (e.timestamp, v)))
+            .collect();
+        samples.sort_by_key(|(at, _)| *at);
+        Ok(samples)
+    }
+}
+
+/// Timestamped samples of one signal, oldest first
+type Series = VecDeque<(DateTime<Utc>, f64)>;
+
+/// In-memory sampler of report signals
+///
+/// Used when no history store is available. Samples are kept for the
+/// longest report period, so reports only cover time since startup.
+pub struct SignalRecorder {
+    signals: Vec<String>,
+    retention: Duration,
+    samples: RwLock<HashMap<String, Series>>,
+}
+
+impl SignalRecorder {
+    /// Create a recorder for the signals and periods of `config`
+    pub fn new(config: &ReportsConfig) -> Self {
+        let retention = config
+            .definitions
+            .iter()
+            .map(|r| r.period.duration())
+            .max()
+            .unwrap_or_else(Duration::zero)
+            + Duration::hours(1);
+        Self { signals: config.signals(), retention, samples: RwLock::new(HashMap::new()) }
+    }
+
+    /// Record the current value of every report signal
+    pub async fn record(&self, bus: &SignalBus, now: DateTime<Utc>) {
+        let mut samples = self.samples.write().await;
+        for signal in &self.signals {
+            let Some(value) = bus.get(signal).and_then(|v| v.as_float()) else {
+                continue;
+            };
+            let series = samples.entry(signal.clone()).or_default();
+            series.push_back((now, value));
+            while series.front().is_some_and(|(at, _)| *at < now - self.retention) {
+                series.pop_front();
+            }
+        }
+    }
+
+    /// Sample the bus until the task is dropped
+    pub async fn run(recorder: Arc<Self>, bus: SignalBus, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            recorder.record(&bus, Utc::now()).await;
+        }
+    }
+}
+
+#[async_trait]
+impl ReportSource for SignalRecorder {
+    async fn samples(
+        &self,
+        signal: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>> {
+        Ok(self
+            .samples
+            .read()
+            .await
+            .get(signal)
+            .map(|series| series.iter().filter(|(at, _)| *at >= start && *at < end).copied().collect())
+            .unwrap_or_default())
+    }
+}
+
+// ============================================================================
+// SCHEDULER
+// ============================================================================
+
+/// A rendered report ready for delivery
+#[derive(Debug, Clone)]
+pub struct GeneratedReport {
+    /// File name, `<report>_<local end time>.<ext>`
+    pub file_name: String,
+
+    /// MIME type of `content`
+    pub content_type: &'static str,
+
+    /// Rendered report
+    pub content: Vec<u8>,
+
+    /// Data the report was rendered from
+    pub data: ReportData,
+}
+
+/// Generates due reports and delivers them
+pub struct ReportScheduler {
+    config: ReportsConfig,
+    offset: FixedOffset,
+    templates: HashMap<String, String>,
+    source: Arc<dyn ReportSource>,
+    last_end: HashMap<String, DateTime<Utc>>,
+}
+
+impl ReportScheduler {
+    /// Create a scheduler, loading custom templates
+    ///
+    /// Periods that ended before `now` are not generated retroactively.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or a template cannot
+    /// be read or compiled.
+    pub fn new(config: ReportsConfig, source: Arc<dyn ReportSource>, now: DateTime<Utc>) -> Result<Self> {
+        config.validate()?;
+        let offset = config.offset()?;
+        let mut templates = HashMap::new();
+        for report in &config.definitions {
+            if let Some(path) = &report.template {
+                let source = std::fs::read_to_string(path).map_err(|e| {
+                    PlcError::Config(format!("Failed to read report template {}: {e}", path.display()))
+                })?;
+                render::check_template(&report.name, report.format, &source)?;
+                templates.insert(report.name.clone(), source);
+            }
+        }
+        let last_end = config
+            .definitions
+            .iter()
+            .map(|r| (r.name.clone(), period_end(r, offset, now)))
+            .collect();
+        Ok(Self { config, offset, templates, source, last_end })
+    }
+
+    /// Periods that ended since the last check, as `(report index, start, end)`
+    ///
+    /// Only the latest missed period of each report is returned.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<(usize, DateTime<Utc>, DateTime<Utc>)> {
+        let mut due = Vec::new();
+        for (index, report) in self.config.definitions.iter().enumerate() {
+            let end = period_end(report, self.offset, now);
+            let last = self.last_end.entry(report.name.clone()).or_insert(end);
+            if end > *last {
+                *last = end;
+                due.push((index, end - report.period.duration(), end));
+            }
+        }
+        due
+    }
+
+    /// Render the report at `index` for `[start, end)`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data source fails or the template cannot be
+    /// rendered.
+    pub async fn generate(
+        &self,
+        index: usize,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<GeneratedReport> {
+        let report = self
+            .config
+            .definitions
+            .get(index)
+            .ok_or_else(|| PlcError::NotFound(format!("Report #{index}")))?;
+        let data = render::collect(report, self.source.as_ref(), self.offset, start, end, now).await?;
+        let content = render::render(report.format, self.templates.get(&report.name).map(String::as_str), &data)?;
+        Ok(GeneratedReport {
+            file_name: format!(
+                "{}_{}.{}",
+                report.name,
+                end.with_timezone(&self.offset).format("%Y%m%d_%H%M"),
+                report.format.extension()
+            ),
+            content_type: report.format.content_type(),
+            content,
+            data,
+        })
+    }
+
+    /// Deliver a generated report to every target of report `index`
+    ///
+    /// All targets are attempted; the first failure is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing, mailing or uploading fails.
+    pub async fn deliver(&self, index: usize, report: &GeneratedReport) -> Result<()> {
+        let Some(definition) = self.config.definitions.get(index) else {
+            return Err(PlcError::NotFound(format!("Report #{index}")));
+        };
+        let mut results = Vec::new();
+        if let Some(dir) = &self.config.output_dir {
+            results.push(delivery::write_to_disk(dir, report).await);
+        }
+        #[cfg(feature = "reports-email")]
+        if let (Some(smtp), false) = (&self.config.smtp, definition.delivery.email.is_empty()) {
+            results.push(delivery::send_email(smtp, &definition.delivery.email, report).await);
+        }
+        #[cfg(feature = "reports-s3")]
+        if let (Some(s3), true) = (&self.config.s3, definition.delivery.s3) {
+            results.push(delivery::upload_s3(s3, report).await);
+        }
+        debug!("Delivered report '{}' to {} targets", definition.name, results.len());
+        results.into_iter().collect()
+    }
+
+    /// Generate and deliver reports as their periods end
+    pub async fn run(mut self) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(SCHEDULE_CHECK_INTERVAL_S));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let now = Utc::now();
+            for (index, start, end) in self.due(now) {
+                let name = &self.config.definitions[index].name;
+                match self.generate(index, start, end, now).await {
+                    Ok(report) => match self.deliver(index, &report).await {
+                        Ok(()) => info!("Generated report {}", report.file_name),
+                        Err(e) => warn!("Failed to deliver report {}: {}", report.file_name, e),
+                    },
+                    Err(e) => warn!("Failed to generate report '{}': {}", name, e),
+                }
+            }
+        }
+    }
+}
+
+/// End of the latest period of `report` that ended at or before `now`
+fn period_end(report: &ReportDefinition, offset: FixedOffset, now: DateTime<Utc>) -> DateTime<Utc> {
+    let local = now.with_timezone(&offset).naive_local();
+    let candidate = match report.period {
+        ReportPeriod::Hourly => {
+            let hour = local.date().and_hms_opt(local.hour(), 0, 0).unwrap_or(local);
+            hour + Duration::minutes(i64::from(report.at.minute()))
+        }
+        ReportPeriod::Daily => local.date().and_time(report.at),
+        ReportPeriod::Weekly => {
+            local.date().and_time(report.at)
+                - Duration::days(i64::from(local.weekday().num_days_from_monday()))
+        }
+    };
+    let step = match report.period {
+        ReportPeriod::Hourly => Duration::hours(1),
+        ReportPeriod::Daily => Duration::days(1),
+        ReportPeriod::Weekly => Duration::weeks(1),
+    };
+    let end = if candidate > local { candidate - step } else { candidate };
+    DateTime::from_naive_utc_and_offset(end - offset, Utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn definition(period: ReportPeriod, at: &str) -> ReportDefinition {
+        ReportDefinition {
+            name: "test".into(),
+            title: "Test".into(),
+            period,
+            at: at.parse().unwrap(),
+            format: ReportFormat::Csv,
+            template: None,
+            breakdown: None,
+            columns: vec![ReportColumn {
+                signal: "count".into(),
+                label: None,
+                aggregate: Aggregate::Delta,
+                unit: None,
+                scale: 1.0,
+                decimals: 0,
+            }],
+            delivery: ReportDelivery::default(),
+        }
+    }
+
+    #[test]
+    fn test_period_end() {
+        let offset = FixedOffset::east_opt(3600).unwrap();
+        // Wednesday 2024-05-15 05:30 local
+        let now = Utc.with_ymd_and_hms(2024, 5, 15, 4, 30, 0).unwrap();
+
+        let daily = definition(ReportPeriod::Daily, "06:00");
+        assert_eq!(period_end(&daily, offset, now), Utc.with_ymd_and_hms(2024, 5, 14, 5, 0, 0).unwrap());
+
+        let hourly = definition(ReportPeriod::Hourly, "00:15");
+        assert_eq!(period_end(&hourly, offset, now), Utc.with_ymd_and_hms(2024, 5, 15, 4, 15, 0).unwrap());
+
+        let weekly = definition(ReportPeriod::Weekly, "00:00");
+        assert_eq!(period_end(&weekly, offset, now), Utc.with_ymd_and_hms(2024, 5, 12, 23, 0, 0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_due_and_generate() {
+        let config = ReportsConfig {
+            utc_offset_minutes: 0,
+            output_dir: Some(std::env::temp_dir()),
+            sample_interval_ms: 1000,
+            #[cfg(feature = "reports-email")]
+            smtp: None,
+            #[cfg(feature = "reports-s3")]
+            s3: None,
+            definitions: vec![definition(ReportPeriod::Hourly, "00:00")],
+        };
+        let recorder = Arc::new(SignalRecorder::new(&config));
+        let bus = SignalBus::new();
+        let start = Utc.with_ymd_and_hms(2024, 5, 15, 10, 0, 0).unwrap();
+        for (minute, count) in [(0, 100), (20, 150), (40, 5), (59, 30)] {
+            bus.set("count", crate::Value::Integer(count)).unwrap();
+            recorder.record(&bus, start + Duration::minutes(minute)).await;
+        }
+
+        let mut scheduler = ReportScheduler::new(config, recorder, start + Duration::minutes(1)).unwrap();
+        assert!(scheduler.due(start + Duration::minutes(59)).is_empty());
+        let due = scheduler.due(start + Duration::minutes(61));
+        assert_eq!(due, vec![(0, start, start + Duration::hours(1))]);
+        assert!(scheduler.due(start + Duration::minutes(62)).is_empty());
+
+        let report = scheduler.generate(0, start, start + Duration::hours(1), start).await.unwrap();
+        assert_eq!(report.file_name, "test_20240515_1100.csv");
+        // 100 -> 150, reset, 5 -> 30
+        assert_eq!(report.data.rows[0].values, vec![Some(80.0)]);
+        let csv = String::from_utf8(report.content).unwrap();
+        assert!(csv.lines().nth(1).unwrap().ends_with(",80"));
+    }
+}
@@ -0,0 +1,374 @@
+// src/reports/render.rs
+//! Report aggregation and rendering
+
+use super::{Aggregate, ReportDefinition, ReportFormat, ReportSource};
+use crate::{PlcError, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use minijinja::Environment;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Threshold above which a sample counts as `true`
+const TRUE_THRESHOLD: f64 = 0.5;
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{ title }}</title>
+<style>
+body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 4px 8px; }
+td.num { text-align: right; }
+tr.total td { font-weight: bold; }
+</style>
+</head>
+<body>
+<h1>{{ title }}</h1>
+<p>{{ start }} &ndash; {{ end }}</p>
+<table>
+<tr><th>Period</th>{% for column in columns %}<th>{{ column.label }}{% if column.unit %} [{{ column.unit }}]{% endif %}</th>{% endfor %}</tr>
+{% for row in rows %}<tr{% if loop.last %} class="total"{% endif %}><td>{{ row.label }}</td>{% for value in row.formatted %}<td class="num">{{ value }}</td>{% endfor %}</tr>
+{% endfor %}</table>
+<p>Generated {{ generated_at }}</p>
+</body>
+</html>
+"#;
+
+const TEXT_TEMPLATE: &str = "{{ title }}\n{{ start }} - {{ end }}\n\n{{ table_text }}\n\nGenerated {{ generated_at }}\n";
+
+/// Column heading passed to templates
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnHeader {
+    pub signal: String,
+    pub label: String,
+    pub unit: Option<String>,
+}
+
+/// One report row: a breakdown interval or the period total
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRow {
+    pub label: String,
+    /// Scaled aggregates, `None` where the signal had no samples
+    pub values: Vec<Option<f64>>,
+    /// Values formatted with each column's decimals
+    pub formatted: Vec<String>,
+}
+
+/// Template context of a report
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportData {
+    pub name: String,
+    pub title: String,
+    /// Period start in plant local time
+    pub start: String,
+    /// Period end in plant local time
+    pub end: String,
+    pub generated_at: String,
+    pub columns: Vec<ColumnHeader>,
+    /// Breakdown rows followed by the total row
+    pub rows: Vec<ReportRow>,
+    /// The table laid out as aligned plain text
+    pub table_text: String,
+}
+
+/// Query the source and aggregate every column of `report`
+pub(super) async fn collect(
+    report: &ReportDefinition,
+    source: &dyn ReportSource,
+    offset: FixedOffset,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<ReportData> {
+    let local = |at: DateTime<Utc>, format: &str| at.with_timezone(&offset).format(format).to_string();
+
+    let mut windows = Vec::new();
+    if let Some(breakdown) = report.breakdown {
+        let (step, format) = match breakdown {
+            super::Breakdown::Hourly => (breakdown.duration(), "%H:%M"),
+            super::Breakdown::Daily => (breakdown.duration(), "%Y-%m-%d"),
+        };
+        let mut from = start;
+        while from < end {
+            let to = (from + step).min(end);
+            windows.push((local(from, format), from, to));
+            from = to;
+        }
+    }
+    windows.push(("Total".to_string(), start, end));
+
+    let mut rows: Vec<ReportRow> = windows
+        .iter()
+        .map(|(label, _, _)| ReportRow { label: label.clone(), values: Vec::new(), formatted: Vec::new() })
+        .collect();
+    for column in &report.columns {
+        let samples = source.samples(&column.signal, start, end).await?;
+        for (row, (_, from, to)) in rows.iter_mut().zip(&windows) {
+            let lo = samples.partition_point(|(at, _)| at < from);
+            let hi = samples.partition_point(|(at, _)| at < to);
+            let value = aggregate(column.aggregate, &samples[lo..hi], *to).map(|v| v * column.scale);
+            row.formatted.push(value.map(|v| format!("{v:.*}", column.decimals)).unwrap_or_default());
+            row.values.push(value);
+        }
+    }
+
+    let columns: Vec<ColumnHeader> = report
+        .columns
+        .iter()
+        .map(|c| ColumnHeader {
+            signal: c.signal.clone(),
+            label: c.label.clone().unwrap_or_else(|| c.signal.clone()),
+            unit: c.unit.clone(),
+        })
+        .collect();
+    let table_text = table_text(&columns, &rows);
+    Ok(ReportData {
+        name: report.name.clone(),
+        title: if report.title.is_empty() { report.name.clone() } else { report.title.clone() },
+        start: local(start, "%Y-%m-%d %H:%M"),
+        end: local(end, "%Y-%m-%d %H:%M"),
+        generated_at: local(now, "%Y-%m-%d %H:%M:%S"),
+        columns,
+        rows,
+        table_text,
+    })
+}
+
+/// Reduce samples within a window ending at `end`
+pub(super) fn aggregate(kind: Aggregate, samples: &[(DateTime<Utc>, f64)], end: DateTime<Utc>) -> Option<f64> {
+    let values = samples.iter().map(|(_, v)| *v);
+    let (first, last) = (samples.first()?.1, samples.last()?.1);
+    Some(match kind {
+        Aggregate::Min => values.fold(f64::INFINITY, f64::min),
+        Aggregate::Max => values.fold(f64::NEG_INFINITY, f64::max),
+        #[allow(clippy::cast_precision_loss)]
+        Aggregate::Avg => values.sum::<f64>() / samples.len() as f64,
+        Aggregate::Sum => values.sum(),
+        Aggregate::First => first,
+        Aggregate::Last => last,
+        // A decrease is a counter reset; count from zero again
+        Aggregate::Delta => samples
+            .windows(2)
+            .map(|w| if w[1].1 >= w[0].1 { w[1].1 - w[0].1 } else { w[1].1 })
+            .sum(),
+        #[allow(clippy::cast_precision_loss)]
+        Aggregate::RisingEdges => samples
+            .windows(2)
+            .filter(|w| w[0].1 <= TRUE_THRESHOLD && w[1].1 > TRUE_THRESHOLD)
+            .count() as f64,
+        #[allow(clippy::cast_precision_loss)]
+        Aggregate::TimeTrue => samples
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, v))| *v > TRUE_THRESHOLD)
+            .map(|(i, (at, _))| {
+                let until = samples.get(i + 1).map_or(end, |(next, _)| *next);
+                (until - *at).num_milliseconds() as f64 / 1000.0
+            })
+            .sum(),
+    })
+}
+
+/// Lay out the report table as fixed-width text
+fn table_text(columns: &[ColumnHeader], rows: &[ReportRow]) -> String {
+    let mut headers = vec!["Period".to_string()];
+    headers.extend(columns.iter().map(|c| match &c.unit {
+        Some(unit) => format!("{} [{unit}]", c.label),
+        None => c.label.clone(),
+    }));
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        widths[0] = widths[0].max(row.label.chars().count());
+        for (width, value) in widths[1..].iter_mut().zip(&row.formatted) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let mut text = String::new();
+    let mut line = |cells: &[&str]| {
+        for (i, (cell, width)) in cells.iter().zip(&widths).enumerate() {
+            let _ = match i {
+                0 => write!(text, "{cell:<width$}"),
+                _ => write!(text, "  {cell:>width$}"),
+            };
+        }
+        text.push('\n');
+    };
+    line(&headers.iter().map(String::as_str).collect::<Vec<_>>());
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    line(&rule.iter().map(String::as_str).collect::<Vec<_>>());
+    for row in rows {
+        let mut cells = vec![row.label.as_str()];
+        cells.extend(row.formatted.iter().map(String::as_str));
+        line(&cells);
+    }
+    text.trim_end().to_string()
+}
+
+fn environment(format: ReportFormat, template: &str) -> Result<Environment<'_>> {
+    let mut env = Environment::new();
+    // The `.html` suffix turns on HTML auto-escaping
+    let name = if format == ReportFormat::Html { "report.html" } else { "report.txt" };
+    env.add_template(name, template)
+        .map_err(|e| PlcError::Config(format!("Invalid report template: {e}")))?;
+    Ok(env)
+}
+
+/// Compile a custom template to surface syntax errors at startup
+pub(super) fn check_template(report: &str, format: ReportFormat, template: &str) -> Result<()> {
+    environment(format, template)
+        .map(|_| ())
+        .map_err(|e| PlcError::Config(format!("Report '{report}': {e}")))
+}
+
+/// Render report data in `format`, using `template` instead of the built-in one
+pub(super) fn render(format: ReportFormat, template: Option<&str>, data: &ReportData) -> Result<Vec<u8>> {
+    if format == ReportFormat::Csv {
+        return Ok(csv(data).into_bytes());
+    }
+    let default = if format == ReportFormat::Html { HTML_TEMPLATE } else { TEXT_TEMPLATE };
+    let env = environment(format, template.unwrap_or(default))?;
+    let name = if format == ReportFormat::Html { "report.html" } else { "report.txt" };
+    let text = env
+        .get_template(name)
+        .and_then(|t| t.render(data))
+        .map_err(|e| PlcError::Runtime(format!("Failed to render report '{}': {e}", data.name)))?;
+    Ok(match format {
+        ReportFormat::Pdf => pdf(&text),
+        _ => text.into_bytes(),
+    })
+}
+
+fn csv(data: &ReportData) -> String {
+    let quote = |field: &str| {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    let mut out = String::from("period");
+    for column in &data.columns {
+        out.push(',');
+        out.push_str(&quote(&column.label));
+    }
+    out.push('\n');
+    for row in &data.rows {
+        out.push_str(&quote(&row.label));
+        for value in &row.formatted {
+            out.push(',');
+            out.push_str(value);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// ============================================================================
+// PDF
+// ============================================================================
+
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 40;
+const FONT_SIZE: u32 = 9;
+const LEADING: u32 = 11;
+/// Courier glyphs are 0.6 em wide
+const LINE_CHARS: usize = ((PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6)) as usize;
+const PAGE_LINES: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LEADING) as usize;
+
+/// Lay out plain text on A4 pages in Courier
+///
+/// Lines longer than the page width are cut off and characters outside
+/// ASCII are replaced, as the standard fonts carry no Unicode mapping.
+fn pdf(text: &str) -> Vec<u8> {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            line.chars()
+                .take(LINE_CHARS)
+                .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' })
+                .collect()
+        })
+        .collect();
+    let pages: Vec<&[String]> = if lines.is_empty() { vec![&[]] } else { lines.chunks(PAGE_LINES).collect() };
+
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    let mut kids = Vec::new();
+    for page in pages {
+        let mut content = format!(
+            "BT /F1 {FONT_SIZE} Tf {LEADING} TL {MARGIN} {} Td\n",
+            PAGE_HEIGHT - MARGIN - FONT_SIZE
+        );
+        for line in page {
+            let escaped = line.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+            let _ = writeln!(content, "({escaped}) Tj T*");
+        }
+        content.push_str("ET");
+        let page_id = objects.len() + 1;
+        kids.push(format!("{page_id} 0 R"));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_id + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{content}\nendstream", content.len()));
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len());
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        let _ = write!(out, "{} 0 obj\n{object}\nendobj\n", i + 1);
+    }
+    let xref = out.len();
+    let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(out, "{offset:010} 00000 n ");
+    }
+    let _ = write!(out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1);
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_aggregates() {
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let at = |s| t0 + Duration::seconds(s);
+        let alarm = [(at(0), 0.0), (at(10), 1.0), (at(40), 0.0), (at(50), 1.0)];
+        let end = at(60);
+
+        assert_eq!(aggregate(Aggregate::RisingEdges, &alarm, end), Some(2.0));
+        assert_eq!(aggregate(Aggregate::TimeTrue, &alarm, end), Some(40.0));
+        assert_eq!(aggregate(Aggregate::Avg, &alarm, end), Some(0.5));
+        assert_eq!(aggregate(Aggregate::Max, &alarm, end), Some(1.0));
+        assert_eq!(aggregate(Aggregate::Last, &[], end), None);
+    }
+
+    #[test]
+    fn test_pdf_layout() {
+        let text: String = (0..100).map(|i| format!("line ({i})\n")).collect();
+        let bytes = pdf(&text);
+        let pdf = String::from_utf8(bytes).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(line \\(0\\)) Tj"));
+
+        // Cross-reference offsets must point at their objects
+        let xref = pdf[pdf.find("xref\n").unwrap()..].lines().skip(3).take(5).map(|l| l[..10].parse::<usize>().unwrap());
+        for (i, offset) in xref.enumerate() {
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// src/retained.rs
+//! Retained signals persisted on write
+//!
+//! Signals configured with `retain: true` (or `retained: true`) keep their
+//! value across restarts. On their own they are saved with the block state,
+//! at its save interval, so a setpoint entered just before a power loss can
+//! be lost. With a `retained_store` section every write to a retained signal
+//! is persisted to an embedded sled database instead:
+//!
+//! ```yaml
+//! retained_store:
+//!   path: /var/lib/petra/retained
+//!   flush_interval_ms: 200
+//! signals:
+//!   - { name: oven.setpoint, type: float, initial: 180.0, retained: true }
+//! ```
+//!
+//! Writes are collected from a bus subscription, so writers never wait for
+//! the disk, and stored as one batch every `flush_interval_ms`; a signal
+//! written several times in between is stored once, with its latest value.
+//! At startup the stored values replace the initial values, after the block
+//! state is restored.
+
+use crate::config::Config;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::subscription::{Coalesce, SignalSubscription, SubscribeOptions};
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, trace, warn};
+
+/// Retained signal store settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct RetainedStoreConfig {
+    /// Database directory
+    pub path: PathBuf,
+
+    /// Longest time a write waits before it is persisted
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+const fn default_flush_interval_ms() -> u64 {
+    500
+}
+
+impl RetainedStoreConfig {
+    /// Validate the store settings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the path is empty or the flush
+    /// interval is zero.
+    pub fn validate(&self) -> Result<()> {
+        if self.path.as_os_str().is_empty() {
+            return Err(PlcError::Config("retained_store.path must not be empty".to_string()));
+        }
+        if self.flush_interval_ms == 0 {
+            return Err(PlcError::Config(
+                "retained_store.flush_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn storage_error(e: sled::Error) -> PlcError {
+    PlcError::Io(e.into())
+}
+
+fn retained_signals(config: &Config) -> BTreeSet<String> {
+    config.signals.iter().filter(|s| s.retain).map(|s| s.name.clone()).collect()
+}
+
+/// Write-behind store of the retained signals of a bus
+///
+/// Cloning yields another handle to the same store.
+#[derive(Debug, Clone)]
+pub struct RetainedStore {
+    db: sled::Db,
+    bus: SignalBus,
+    interval: Duration,
+    /// Writes to the retained signals not stored yet
+    changes: Arc<Mutex<Option<SignalSubscription>>>,
+}
+
+impl RetainedStore {
+    /// Open the store, write the values it holds for the retained signals
+    /// of `config` to `bus`, and start collecting their writes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or read.
+    pub fn open(store: &RetainedStoreConfig, config: &Config, bus: SignalBus) -> Result<Self> {
+        let db = sled::open(&store.path).map_err(storage_error)?;
+        let retained = Self {
+            db,
+            bus,
+            interval: Duration::from_millis(store.flush_interval_ms),
+            changes: Arc::default(),
+        };
+        let restored = retained.restore(&retained_signals(config))?;
+        info!("Restored {} retained signals from {}", restored, store.path.display());
+        retained.configure(config);
+        Ok(retained)
+    }
+
+    fn restore(&self, names: &BTreeSet<String>) -> Result<usize> {
+        let mut restored = 0;
+        for name in names {
+            let Some(bytes) = self.db.get(name).map_err(storage_error)? else {
+                continue;
+            };
+            let value = match serde_json::from_slice::<Value>(&bytes) {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("Ignoring unreadable retained value of '{}': {}", name, e);
+                    continue;
+                }
+            };
+            match self.bus.set(name, value) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("Failed to restore retained signal '{}': {}", name, e),
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Follow a reloaded configuration
+    ///
+    /// Pending writes are stored first. Values of signals no longer
+    /// retained are removed, so they start from their initial value again.
+    pub fn configure(&self, config: &Config) {
+        if let Err(e) = self.flush() {
+            warn!("Failed to store retained signals: {}", e);
+        }
+        let names = retained_signals(config);
+        let mut changes = self.lock();
+        for key in self.db.iter().keys().flatten() {
+            let name = String::from_utf8_lossy(&key);
+            if !names.contains(name.as_ref()) {
+                if let Err(e) = self.db.remove(&key) {
+                    warn!("Failed to remove retained value of '{}': {}", name, e);
+                }
+            }
+        }
+        // Latest-only coalescing keeps at most one pending write per signal
+        *changes = (!names.is_empty()).then(|| {
+            let options = SubscribeOptions::default().capacity(names.len()).coalesce(Coalesce::Latest);
+            self.bus.subscribe_with(&names, options)
+        });
+        debug!("Persisting writes of {} retained signals", names.len());
+    }
+
+    /// Store the writes collected since the last flush, returning how many
+    /// signals were stored
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be written; the writes taken
+    /// are lost, but a later write of the same signal is stored again.
+    pub fn flush(&self) -> Result<usize> {
+        let mut batch = sled::Batch::default();
+        let mut count = 0;
+        if let Some(changes) = self.lock().as_mut() {
+            while let Some(change) = changes.try_next() {
+                batch.insert(change.name.as_bytes(), serde_json::to_vec(&change.value)?);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.db.apply_batch(batch).map_err(storage_error)?;
+            self.db.flush().map_err(storage_error)?;
+            trace!("Stored {} retained signals", count);
+        }
+        Ok(count)
+    }
+
+    /// Flush every `flush_interval_ms` on a task of its own
+    #[must_use]
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(store.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let flushing = store.clone();
+                match tokio::task::spawn_blocking(move || flushing.flush()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => warn!("Failed to store retained signals: {}", e),
+                    Err(e) => warn!("Retained signal flush failed: {}", e),
+                }
+            }
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<SignalSubscription>> {
+        self.changes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(signals: &str) -> Config {
+        serde_yaml::from_str(&format!("signals:\n{signals}blocks: []\n")).unwrap()
+    }
+
+    fn store_config(dir: &tempfile::TempDir) -> RetainedStoreConfig {
+        RetainedStoreConfig { path: dir.path().join("retained"), flush_interval_ms: 100 }
+    }
+
+    #[test]
+    fn test_retained_values_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(
+            "  - { name: oven.setpoint, type: float, initial: 180.0, retained: true }\n\
+             \x20 - { name: oven.temp, type: float }\n",
+        );
+        {
+            let bus = SignalBus::new();
+            let store = RetainedStore::open(&store_config(&dir), &config, bus.clone()).unwrap();
+            bus.set("oven.setpoint", Value::Float(190.0)).unwrap();
+            bus.set("oven.setpoint", Value::Float(200.0)).unwrap();
+            bus.set("oven.temp", Value::Float(25.0)).unwrap();
+            assert_eq!(store.flush().unwrap(), 1);
+            assert_eq!(store.flush().unwrap(), 0);
+        }
+
+        let bus = SignalBus::new();
+        bus.set("oven.setpoint", Value::Float(180.0)).unwrap();
+        RetainedStore::open(&store_config(&dir), &config, bus.clone()).unwrap();
+        assert_eq!(bus.get("oven.setpoint"), Some(Value::Float(200.0)));
+        assert_eq!(bus.get("oven.temp"), None);
+    }
+
+    #[test]
+    fn test_reconfigure_forgets_signals_no_longer_retained() {
+        let dir = tempfile::tempdir().unwrap();
+        let bus = SignalBus::new();
+        let retained = config("  - { name: mode, type: int, retain: true }\n");
+        let store = RetainedStore::open(&store_config(&dir), &retained, bus.clone()).unwrap();
+        bus.set("mode", Value::Integer(3)).unwrap();
+
+        // Dropping the flag drops the stored value and stops collecting writes
+        store.configure(&config("  - { name: mode, type: int }\n"));
+        bus.set("mode", Value::Integer(4)).unwrap();
+        assert_eq!(store.flush().unwrap(), 0);
+        drop(store);
+
+        let bus = SignalBus::new();
+        RetainedStore::open(&store_config(&dir), &retained, bus.clone()).unwrap();
+        assert_eq!(bus.get("mode"), None);
+    }
+}
@@ -0,0 +1,403 @@
+// src/scan_budget.rs
+//! Scan-cycle time and allocation attribution per subsystem
+//!
+//! Subsystems wrap their work in [`measure`] (synchronous code) or
+//! [`instrument`] (futures). With the `scan-budget` feature the time spent
+//! and the allocations made inside those sections are added to per-subsystem
+//! counters; without it both are transparent pass-throughs, so call sites
+//! need no feature gates.
+//!
+//! Futures are charged only for the time spent inside `poll`, so protocol
+//! drivers waiting on the network are not blamed for the scan time they do
+//! not use. Allocations are counted per thread by [`CountingAllocator`],
+//! which the `petra` binary installs as the global allocator when the
+//! feature is enabled; library users who want allocation figures install it
+//! themselves:
+//!
+//! ```rust,ignore
+//! #[global_allocator]
+//! static ALLOCATOR: petra::scan_budget::CountingAllocator = petra::scan_budget::CountingAllocator;
+//! ```
+//!
+//! Sections should not nest, because the inner section would be counted for
+//! both subsystems.
+//!
+//! Reports are available from [`snapshot`], the `/api/budget` endpoints and
+//! `petra top`.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Subsystems that scan time and allocations are attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// Logic block execution in the scan cycle
+    Blocks,
+    /// Protocol drivers and MQTT handling
+    Protocols,
+    /// Historical data logging
+    History,
+    /// Alarm evaluation
+    Alarms,
+    /// OEE and downtime tracking
+    Analytics,
+}
+
+impl Subsystem {
+    /// All subsystems in reporting order
+    pub const ALL: [Self; 5] = [
+        Self::Blocks,
+        Self::Protocols,
+        Self::History,
+        Self::Alarms,
+        Self::Analytics,
+    ];
+
+    /// Name used in reports and metric labels
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Blocks => "blocks",
+            Self::Protocols => "protocols",
+            Self::History => "history",
+            Self::Alarms => "alarms",
+            Self::Analytics => "analytics",
+        }
+    }
+}
+
+/// Run `f`, attributing its time and allocations to `subsystem`
+#[inline]
+pub fn measure<R>(subsystem: Subsystem, f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "scan-budget")]
+    {
+        let start = tracking::Section::start();
+        let result = f();
+        start.finish(subsystem);
+        result
+    }
+    #[cfg(not(feature = "scan-budget"))]
+    {
+        let _ = subsystem;
+        f()
+    }
+}
+
+/// Wrap a future so the time spent polling it is attributed to `subsystem`
+#[inline]
+pub fn instrument<F: Future>(subsystem: Subsystem, future: F) -> Instrumented<F> {
+    Instrumented { future, subsystem }
+}
+
+/// Future returned by [`instrument`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Instrumented<F> {
+    future: F,
+    subsystem: Subsystem,
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned; it is never moved out of
+        // `self` and `Instrumented` has no `Drop` impl.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        measure(this.subsystem, || future.poll(cx))
+    }
+}
+
+/// Count one completed scan cycle
+#[inline]
+pub fn record_scan() {
+    #[cfg(feature = "scan-budget")]
+    tracking::SCANS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[cfg(feature = "scan-budget")]
+pub use tracking::{snapshot, BudgetSnapshot, CountingAllocator, SubsystemRate, SubsystemUsage};
+
+#[cfg(feature = "scan-budget")]
+mod tracking {
+    use super::Subsystem;
+    use serde::{Deserialize, Serialize};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::fmt::Write as _;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    struct Counters {
+        busy_ns: AtomicU64,
+        calls: AtomicU64,
+        allocations: AtomicU64,
+        alloc_bytes: AtomicU64,
+    }
+
+    impl Counters {
+        const fn new() -> Self {
+            Self {
+                busy_ns: AtomicU64::new(0),
+                calls: AtomicU64::new(0),
+                allocations: AtomicU64::new(0),
+                alloc_bytes: AtomicU64::new(0),
+            }
+        }
+    }
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: Counters = Counters::new();
+    static COUNTERS: [Counters; Subsystem::ALL.len()] = [ZERO; Subsystem::ALL.len()];
+    pub(super) static SCANS: AtomicU64 = AtomicU64::new(0);
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+    thread_local! {
+        static THREAD_ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+        static THREAD_ALLOC_BYTES: Cell<u64> = const { Cell::new(0) };
+    }
+
+    fn thread_allocations() -> (u64, u64) {
+        (
+            THREAD_ALLOCATIONS.try_with(Cell::get).unwrap_or(0),
+            THREAD_ALLOC_BYTES.try_with(Cell::get).unwrap_or(0),
+        )
+    }
+
+    /// Global allocator wrapper that counts allocations per thread
+    pub struct CountingAllocator;
+
+    // SAFETY: all allocation work is delegated to the system allocator; the
+    // thread-local counters are const-initialized and never allocate.
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = THREAD_ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+            let _ = THREAD_ALLOC_BYTES.try_with(|c| c.set(c.get() + layout.size() as u64));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let _ = THREAD_ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+            let _ = THREAD_ALLOC_BYTES.try_with(|c| c.set(c.get() + layout.size() as u64));
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = THREAD_ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+            let _ = THREAD_ALLOC_BYTES.try_with(|c| c.set(c.get() + new_size as u64));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    pub(super) struct Section {
+        started: Instant,
+        allocations: u64,
+        alloc_bytes: u64,
+    }
+
+    impl Section {
+        pub(super) fn start() -> Self {
+            let (allocations, alloc_bytes) = thread_allocations();
+            Self {
+                started: Instant::now(),
+                allocations,
+                alloc_bytes,
+            }
+        }
+
+        pub(super) fn finish(self, subsystem: Subsystem) {
+            let elapsed = u64::try_from(self.started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            let (allocations, alloc_bytes) = thread_allocations();
+
+            let counters = &COUNTERS[subsystem as usize];
+            counters.busy_ns.fetch_add(elapsed, Ordering::Relaxed);
+            counters.calls.fetch_add(1, Ordering::Relaxed);
+            counters
+                .allocations
+                .fetch_add(allocations.saturating_sub(self.allocations), Ordering::Relaxed);
+            counters
+                .alloc_bytes
+                .fetch_add(alloc_bytes.saturating_sub(self.alloc_bytes), Ordering::Relaxed);
+        }
+    }
+
+    /// Cumulative counters for one subsystem
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SubsystemUsage {
+        /// Subsystem name
+        pub name: String,
+        /// Time spent in measured sections
+        pub busy_ns: u64,
+        /// Number of measured sections
+        pub calls: u64,
+        /// Allocations made in measured sections
+        pub allocations: u64,
+        /// Bytes requested in measured sections
+        pub alloc_bytes: u64,
+    }
+
+    /// Cumulative counters for all subsystems
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BudgetSnapshot {
+        /// Monotonic time of the snapshot, relative to the first snapshot
+        pub elapsed_ns: u64,
+        /// Completed scan cycles
+        pub scans: u64,
+        /// Per-subsystem counters
+        pub subsystems: Vec<SubsystemUsage>,
+    }
+
+    type UsageField = fn(&SubsystemUsage) -> u64;
+
+    /// Usage rates between two snapshots
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SubsystemRate {
+        /// Subsystem name
+        pub name: String,
+        /// Share of wall time spent in the subsystem, in percent of one core
+        pub cpu_percent: f64,
+        /// Average time per scan cycle in microseconds
+        pub us_per_scan: f64,
+        /// Average allocations per scan cycle
+        pub allocs_per_scan: f64,
+        /// Bytes allocated per second
+        pub bytes_per_sec: f64,
+    }
+
+    /// Read the current counters
+    #[must_use]
+    pub fn snapshot() -> BudgetSnapshot {
+        let epoch = EPOCH.get_or_init(Instant::now);
+        BudgetSnapshot {
+            elapsed_ns: u64::try_from(epoch.elapsed().as_nanos()).unwrap_or(u64::MAX),
+            scans: SCANS.load(Ordering::Relaxed),
+            subsystems: Subsystem::ALL
+                .iter()
+                .map(|&subsystem| {
+                    let counters = &COUNTERS[subsystem as usize];
+                    SubsystemUsage {
+                        name: subsystem.as_str().to_string(),
+                        busy_ns: counters.busy_ns.load(Ordering::Relaxed),
+                        calls: counters.calls.load(Ordering::Relaxed),
+                        allocations: counters.allocations.load(Ordering::Relaxed),
+                        alloc_bytes: counters.alloc_bytes.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    impl BudgetSnapshot {
+        /// Rates since an earlier snapshot, busiest subsystem first
+        #[must_use]
+        #[allow(clippy::cast_precision_loss)]
+        pub fn rates_since(&self, earlier: &Self) -> Vec<SubsystemRate> {
+            let wall_ns = self.elapsed_ns.saturating_sub(earlier.elapsed_ns).max(1) as f64;
+            let scans = self.scans.saturating_sub(earlier.scans).max(1) as f64;
+
+            let mut rates: Vec<SubsystemRate> = self
+                .subsystems
+                .iter()
+                .map(|now| {
+                    let before = earlier.subsystems.iter().find(|s| s.name == now.name);
+                    let delta = |f: UsageField| {
+                        f(now).saturating_sub(before.map_or(0, f)) as f64
+                    };
+                    SubsystemRate {
+                        name: now.name.clone(),
+                        cpu_percent: delta(|s| s.busy_ns) / wall_ns * 100.0,
+                        us_per_scan: delta(|s| s.busy_ns) / scans / 1000.0,
+                        allocs_per_scan: delta(|s| s.allocations) / scans,
+                        bytes_per_sec: delta(|s| s.alloc_bytes) / wall_ns * 1e9,
+                    }
+                })
+                .collect();
+            rates.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+            rates
+        }
+
+        /// Render the counters in the Prometheus text exposition format
+        #[must_use]
+        pub fn to_prometheus(&self) -> String {
+            let mut out = String::new();
+            let families: [(&str, &str, UsageField); 4] = [
+                (
+                    "petra_subsystem_busy_nanoseconds_total",
+                    "Time spent in measured sections",
+                    |s| s.busy_ns,
+                ),
+                ("petra_subsystem_sections_total", "Number of measured sections", |s| s.calls),
+                (
+                    "petra_subsystem_allocations_total",
+                    "Allocations made in measured sections",
+                    |s| s.allocations,
+                ),
+                (
+                    "petra_subsystem_allocated_bytes_total",
+                    "Bytes allocated in measured sections",
+                    |s| s.alloc_bytes,
+                ),
+            ];
+            for (name, help, value) in families {
+                let _ = writeln!(out, "# HELP {name} {help}");
+                let _ = writeln!(out, "# TYPE {name} counter");
+                for subsystem in &self.subsystems {
+                    let _ = writeln!(
+                        out,
+                        "{name}{{subsystem=\"{}\"}} {}",
+                        subsystem.name,
+                        value(subsystem)
+                    );
+                }
+            }
+            let _ = writeln!(out, "# HELP petra_scans_total Completed scan cycles");
+            let _ = writeln!(out, "# TYPE petra_scans_total counter");
+            let _ = writeln!(out, "petra_scans_total {}", self.scans);
+            out
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::{instrument, measure, record_scan};
+        use super::*;
+
+        fn usage<'a>(snapshot: &'a BudgetSnapshot, subsystem: Subsystem) -> &'a SubsystemUsage {
+            snapshot
+                .subsystems
+                .iter()
+                .find(|s| s.name == subsystem.as_str())
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_sections_are_attributed() {
+            let before = snapshot();
+
+            measure(Subsystem::Analytics, || std::thread::sleep(std::time::Duration::from_millis(5)));
+            instrument(Subsystem::Analytics, async {
+                tokio::task::yield_now().await;
+            })
+            .await;
+            record_scan();
+
+            let after = snapshot();
+            let (b, a) = (usage(&before, Subsystem::Analytics), usage(&after, Subsystem::Analytics));
+            assert!(a.busy_ns - b.busy_ns >= 5_000_000);
+            // One sync section plus two polls of the async one
+            assert!(a.calls - b.calls >= 3);
+
+            let rates = after.rates_since(&before);
+            let analytics = rates.iter().find(|r| r.name == "analytics").unwrap();
+            assert!(analytics.cpu_percent > 0.0);
+            assert!(after.to_prometheus().contains("petra_subsystem_busy_nanoseconds_total{subsystem=\"analytics\"}"));
+        }
+    }
+}
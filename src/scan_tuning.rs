@@ -0,0 +1,482 @@
+// src/scan_tuning.rs
+//! Scan-time recommendations from offline measurements
+//!
+//! `petra dev tune-scan config.yaml` runs [`tune`]: the configured blocks are
+//! built against a signal bus holding the initial signal values and executed
+//! for a number of cycles without starting any protocol driver, timing every
+//! block. From these figures it recommends:
+//!
+//! - `scan_time_ms`, so that the 99th percentile scan uses at most half of
+//!   the cycle, rounded up to 1, 2 or 5 times a power of ten
+//! - parallel execution, when blocks that do not feed each other within a
+//!   scan can run side by side and shorten the scan by a fifth or more;
+//!   dependencies come from the blocks' configured inputs and outputs
+//! - an interval for every polling scan class (`protocols.polling`), from the
+//!   driver requests a cycle sends at an assumed round-trip latency and the
+//!   measured cost of storing the values in their signals
+//!
+//! Blocks only see the initial values, so configurations whose block cost
+//! depends on the process state should be tuned with representative
+//! `initial` values.
+
+use crate::config::{BlockConfig, Config};
+use crate::engine::Engine;
+use crate::error::Result;
+use crate::protocols::scheduler::PollScheduler;
+use crate::signal::SignalBus;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Share of the cycle the 99th percentile scan or class cycle may use
+const TARGET_UTILIZATION: f64 = 0.5;
+
+/// Scans run before measuring, so caches and lazy allocations settle
+const WARMUP_CYCLES: usize = 10;
+
+/// Cost of running one block on its own task with parallel execution
+const TASK_OVERHEAD_US: f64 = 20.0;
+
+/// Largest share of the sequential scan a parallel scan may take to be
+/// worth enabling
+const PARALLEL_GAIN: f64 = 0.8;
+
+/// Settings of a tuning run
+#[derive(Debug, Clone)]
+pub struct TuneOptions {
+    /// Measured scan cycles
+    pub cycles: usize,
+    /// Assumed round trip of one protocol request
+    pub request_latency: Duration,
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        Self {
+            cycles: 1000,
+            request_latency: Duration::from_millis(5),
+        }
+    }
+}
+
+/// Measured cost of one block
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockCost {
+    /// Block name
+    pub name: String,
+    /// Block type
+    pub block_type: String,
+    /// Blocks it waits for within a scan, counted along the longest chain
+    pub level: usize,
+    /// Average execution time (microseconds)
+    pub mean_us: f64,
+    /// 99th percentile execution time (microseconds)
+    pub p99_us: f64,
+    /// Longest execution time (microseconds)
+    pub max_us: f64,
+    /// Failed executions
+    pub errors: usize,
+}
+
+/// Estimated cost of one polling scan class
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassCost {
+    /// Class name
+    pub name: String,
+    /// Configured interval
+    pub interval_ms: u64,
+    /// Driver requests of a cycle that also writes every writable point
+    pub requests: usize,
+    /// 99th percentile time to store the values read (microseconds)
+    pub store_us: f64,
+    /// Estimated duration of a cycle
+    pub cycle_ms: f64,
+    /// Whether a cycle takes longer than the configured interval
+    pub overruns: bool,
+    /// Recommended interval
+    pub recommended_interval_ms: u64,
+}
+
+/// Measurements and recommendations of a tuning run
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanReport {
+    /// Measured scan cycles
+    pub cycles: usize,
+    /// Assumed round trip of one protocol request (milliseconds)
+    pub request_latency_ms: f64,
+    /// Configured scan time
+    pub scan_time_ms: u64,
+    /// 99th percentile of a sequential scan (microseconds)
+    pub sequential_p99_us: f64,
+    /// Estimated scan with parallel execution (microseconds)
+    pub parallel_estimate_us: f64,
+    /// Whether parallel execution is recommended
+    pub parallel_execution: bool,
+    /// Recommended scan time
+    pub recommended_scan_time_ms: u64,
+    /// Blocks in execution order
+    pub blocks: Vec<BlockCost>,
+    /// Polling scan classes
+    pub classes: Vec<ClassCost>,
+}
+
+/// Measure `config` offline and recommend scan settings
+///
+/// # Errors
+///
+/// Returns [`PlcError::Config`](crate::PlcError::Config) if the signals or
+/// blocks of `config` cannot be created.
+pub fn tune(config: &Config, options: &TuneOptions) -> Result<ScanReport> {
+    let bus = SignalBus::new().with_clock(config.clock.clone().unwrap_or_default().build());
+    Engine::initialize_signals(&bus, config)?;
+    let mut blocks = Engine::create_blocks(config)?;
+    let cycles = options.cycles.max(1);
+
+    let mut samples = vec![Vec::with_capacity(cycles); blocks.len()];
+    let mut scans = Vec::with_capacity(cycles);
+    let mut errors = vec![0; blocks.len()];
+    for cycle in 0..WARMUP_CYCLES + cycles {
+        let mut scan = Duration::ZERO;
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let start = Instant::now();
+            let result = block.execute(&bus);
+            let elapsed = start.elapsed();
+            if cycle < WARMUP_CYCLES {
+                continue;
+            }
+            if result.is_err() {
+                errors[i] += 1;
+            }
+            samples[i].push(elapsed);
+            scan += elapsed;
+        }
+        if cycle >= WARMUP_CYCLES {
+            scans.push(scan);
+        }
+    }
+
+    let levels = levels(&enabled_blocks(config));
+    let costs: Vec<BlockCost> = blocks
+        .iter()
+        .zip(samples)
+        .zip(levels)
+        .zip(errors)
+        .map(|(((block, samples), level), errors)| {
+            let total: Duration = samples.iter().sum();
+            BlockCost {
+                name: block.name().to_string(),
+                block_type: block.block_type().to_string(),
+                level,
+                mean_us: micros(total / u32::try_from(samples.len()).unwrap_or(u32::MAX)),
+                p99_us: micros(p99(samples.clone())),
+                max_us: micros(samples.iter().max().copied().unwrap_or_default()),
+                errors,
+            }
+        })
+        .collect();
+
+    let sequential_p99_us = micros(p99(scans));
+    let parallelizable: Vec<bool> = blocks.iter().map(|b| b.is_parallelizable()).collect();
+    let parallel_estimate_us = parallel_estimate(&costs, &parallelizable);
+    let parallel_execution = costs.len() > 1 && parallel_estimate_us < PARALLEL_GAIN * sequential_p99_us;
+    let needed_us = if parallel_execution { parallel_estimate_us } else { sequential_p99_us };
+
+    Ok(ScanReport {
+        cycles,
+        request_latency_ms: micros(options.request_latency) / 1000.0,
+        scan_time_ms: config.scan_time_ms,
+        sequential_p99_us,
+        parallel_estimate_us,
+        parallel_execution,
+        recommended_scan_time_ms: round_up_ms(needed_us / 1000.0 / TARGET_UTILIZATION),
+        blocks: costs,
+        classes: class_costs(config, &bus, cycles, options.request_latency),
+    })
+}
+
+/// Enabled block configurations in execution order, as the engine creates them
+fn enabled_blocks(config: &Config) -> Vec<&BlockConfig> {
+    let mut blocks: Vec<&BlockConfig> = config.blocks.iter().filter(|b| b.enabled).collect();
+    blocks.sort_by_key(|b| b.execution_order());
+    blocks
+}
+
+/// Dependency level of every block
+///
+/// A block depends on the last block before it that writes one of its
+/// inputs; signals written by later blocks are only read in the next scan
+/// and do not order the blocks.
+fn levels(blocks: &[&BlockConfig]) -> Vec<usize> {
+    let mut writers: HashMap<&str, usize> = HashMap::new();
+    let mut levels = Vec::with_capacity(blocks.len());
+    for (i, block) in blocks.iter().enumerate() {
+        let level = block
+            .inputs
+            .values()
+            .filter_map(|signal| writers.get(signal.as_str()))
+            .map(|&writer| levels[writer] + 1)
+            .max()
+            .unwrap_or(0);
+        levels.push(level);
+        for signal in block.outputs.values() {
+            writers.insert(signal, i);
+        }
+    }
+    levels
+}
+
+/// Scan time when each dependency level runs side by side
+///
+/// Blocks that cannot run in parallel are added on their own.
+fn parallel_estimate(costs: &[BlockCost], parallelizable: &[bool]) -> f64 {
+    let mut slowest: HashMap<usize, f64> = HashMap::new();
+    let mut serial = 0.0;
+    let mut tasks = 0.0;
+    for (cost, &parallel) in costs.iter().zip(parallelizable) {
+        if parallel {
+            let level = slowest.entry(cost.level).or_default();
+            *level = level.max(cost.p99_us);
+            tasks += 1.0;
+        } else {
+            serial += cost.p99_us;
+        }
+    }
+    slowest.values().sum::<f64>() + serial + tasks * TASK_OVERHEAD_US
+}
+
+/// Cost of every polling scan class
+fn class_costs(config: &Config, bus: &SignalBus, cycles: usize, latency: Duration) -> Vec<ClassCost> {
+    let Some(polling) = config.protocols.as_ref().and_then(|p| p.polling.as_ref()) else {
+        return Vec::new();
+    };
+    let scheduler = PollScheduler::new(polling);
+    scheduler
+        .classes()
+        .iter()
+        .map(|class| {
+            let values: Vec<_> = class
+                .read_signals()
+                .filter_map(|signal| Some((signal, bus.get(signal)?)))
+                .collect();
+            let stores = (0..cycles)
+                .map(|_| {
+                    let start = Instant::now();
+                    for (signal, value) in &values {
+                        let _ = bus.set(signal, value.clone());
+                    }
+                    start.elapsed()
+                })
+                .collect();
+            let store = p99(stores);
+            let requests = class.max_requests();
+            let cycle = latency * u32::try_from(requests).unwrap_or(u32::MAX) + store;
+            ClassCost {
+                name: class.name().to_string(),
+                interval_ms: u64::try_from(class.interval().as_millis()).unwrap_or(u64::MAX),
+                requests,
+                store_us: micros(store),
+                cycle_ms: micros(cycle) / 1000.0,
+                overruns: cycle > class.interval(),
+                recommended_interval_ms: round_up_ms(micros(cycle) / 1000.0 / TARGET_UTILIZATION),
+            }
+        })
+        .collect()
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
+
+fn p99(mut samples: Vec<Duration>) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    samples.sort_unstable();
+    let index = (samples.len() * 99).div_ceil(100) - 1;
+    samples[index]
+}
+
+/// Smallest of 1, 2 or 5 times a power of ten milliseconds that is at least `ms`
+fn round_up_ms(ms: f64) -> u64 {
+    let mut scale: u32 = 1;
+    while scale < 1_000_000_000 {
+        for step in [1, 2, 5] {
+            if f64::from(step * scale) >= ms {
+                return u64::from(step * scale);
+            }
+        }
+        scale *= 10;
+    }
+    u64::from(scale)
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Scan tuning over {} cycles (offline, {:.1} ms per protocol request assumed)",
+            self.cycles, self.request_latency_ms
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<24} {:<16} {:>5} {:>10} {:>10} {:>10} {:>6}",
+            "BLOCK", "TYPE", "LEVEL", "MEAN us", "P99 us", "MAX us", "ERRORS"
+        )?;
+        for block in &self.blocks {
+            writeln!(
+                f,
+                "{:<24} {:<16} {:>5} {:>10.2} {:>10.2} {:>10.2} {:>6}",
+                block.name, block.block_type, block.level, block.mean_us, block.p99_us, block.max_us, block.errors
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Sequential scan p99:    {:.2} us", self.sequential_p99_us)?;
+        writeln!(f, "Parallel scan estimate: {:.2} us", self.parallel_estimate_us)?;
+
+        if !self.classes.is_empty() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{:<16} {:>12} {:>9} {:>10} {:>10}",
+                "SCAN CLASS", "INTERVAL ms", "REQUESTS", "STORE us", "CYCLE ms"
+            )?;
+            for class in &self.classes {
+                writeln!(
+                    f,
+                    "{:<16} {:>12} {:>9} {:>10.2} {:>10.2}",
+                    class.name, class.interval_ms, class.requests, class.store_us, class.cycle_ms
+                )?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Recommendations:")?;
+        writeln!(
+            f,
+            "  scan_time_ms: {} (configured {})",
+            self.recommended_scan_time_ms, self.scan_time_ms
+        )?;
+        if self.parallel_execution {
+            writeln!(
+                f,
+                "  parallel execution: enable, the scan drops from {:.2} to about {:.2} us",
+                self.sequential_p99_us, self.parallel_estimate_us
+            )?;
+        } else {
+            writeln!(f, "  parallel execution: keep disabled, too little independent work")?;
+        }
+        for class in &self.classes {
+            let note = if class.overruns { ", overruns today" } else { "" };
+            writeln!(
+                f,
+                "  scan class {}: interval_ms {} (configured {}{})",
+                class.name, class.recommended_interval_ms, class.interval_ms, note
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_levels_follow_same_scan_dataflow() {
+        let config = config(
+            r"
+signals:
+  - { name: a, type: bool }
+  - { name: b, type: bool }
+  - { name: c, type: bool }
+  - { name: d, type: bool }
+blocks:
+  - { name: first, type: NOT, priority: 3, inputs: { in: a }, outputs: { out: b } }
+  - { name: second, type: NOT, priority: 2, inputs: { in: b }, outputs: { out: c } }
+  - { name: apart, type: NOT, priority: 2, inputs: { in: a }, outputs: { out: d } }
+  - { name: feedback, type: NOT, priority: 1, inputs: { in: c }, outputs: { out: a } }
+scan_time_ms: 100
+",
+        );
+        let blocks = enabled_blocks(&config);
+        let names: Vec<_> = blocks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["first", "second", "apart", "feedback"]);
+        // `feedback` writes `a` for the next scan only, so `first` stays at level 0
+        assert_eq!(levels(&blocks), [0, 1, 0, 2]);
+
+        let report = tune(&config, &TuneOptions { cycles: 50, ..TuneOptions::default() }).unwrap();
+        assert_eq!(report.blocks.len(), 4);
+        assert!(report.blocks.iter().all(|b| b.errors == 0 && b.p99_us >= 0.0));
+        assert!(report.recommended_scan_time_ms >= 1);
+        assert!(report.classes.is_empty());
+    }
+
+    #[test]
+    fn test_blocks_ordered_by_phase() {
+        let config = config(
+            r"
+signals:
+  - { name: a, type: bool }
+  - { name: b, type: bool }
+  - { name: c, type: bool }
+  - { name: d, type: bool }
+  - { name: e, type: bool }
+blocks:
+  - { name: watch, type: NOT, category: Diagnostics, priority: 9, inputs: { in: d }, outputs: { out: e } }
+  - { name: limit, type: NOT, phase: output_processing, inputs: { in: c }, outputs: { out: d } }
+  - { name: logic, type: NOT, category: Safety, inputs: { in: b }, outputs: { out: c } }
+  - { name: urgent, type: NOT, priority: 5, inputs: { in: b }, outputs: { out: c } }
+  - { name: scale, type: NOT, phase: input, inputs: { in: a }, outputs: { out: b } }
+scan_time_ms: 100
+",
+        );
+        let names: Vec<_> = enabled_blocks(&config).iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["scale", "urgent", "logic", "limit", "watch"]);
+        let created = crate::engine::Engine::create_blocks(&config).unwrap();
+        assert!(created.iter().map(|b| b.name()).eq(names));
+        assert!(serde_yaml::from_str::<Config>("blocks: [{ name: x, type: NOT, phase: cleanup }]").is_err());
+    }
+
+    #[test]
+    fn test_rounding_and_class_estimates() {
+        assert_eq!(round_up_ms(0.0), 1);
+        assert_eq!(round_up_ms(1.2), 2);
+        assert_eq!(round_up_ms(3.0), 5);
+        assert_eq!(round_up_ms(50.0), 50);
+        assert_eq!(round_up_ms(51.0), 100);
+
+        let config = config(
+            r#"
+signals:
+  - { name: plc.speed, type: float }
+  - { name: plc.setpoint, type: float }
+blocks: []
+scan_time_ms: 100
+protocols:
+  polling:
+    classes: [{ name: fast, interval_ms: 10 }]
+    max_batch: 1
+    points:
+      - { protocol: plc, address: "DB1.DBD0", signal: plc.speed, class: fast }
+      - { protocol: plc, address: "DB1.DBD4", signal: plc.setpoint, class: fast, access: write }
+"#,
+        );
+        let options = TuneOptions {
+            cycles: 20,
+            request_latency: Duration::from_millis(10),
+        };
+        let report = tune(&config, &options).unwrap();
+        let fast = &report.classes[0];
+        // One read and one write request of 10 ms each
+        assert_eq!((fast.name.as_str(), fast.requests), ("fast", 2));
+        assert!(fast.cycle_ms >= 20.0);
+        assert_eq!(fast.recommended_interval_ms, 50);
+        assert!(report.to_string().contains("overruns today"));
+    }
+}
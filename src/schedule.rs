@@ -0,0 +1,326 @@
+// src/schedule.rs
+//! Weekly time-of-day schedules
+//!
+//! A schedule switches something on during weekly wall-clock windows in an
+//! IANA time zone, so a 07:00 start stays at 07:00 local time across
+//! daylight saving changes. Date exceptions replace the weekly windows on
+//! holidays and other special days:
+//!
+//! ```yaml
+//! timezone: Europe/Berlin
+//! weekly:
+//!   - { days: [mon, tue, wed, thu, fri], start: "06:30", end: "18:00" }
+//!   - { days: [sat], start: "08:00", end: "12:00" }
+//! exceptions:
+//!   - { name: christmas, date: 2026-12-24, end_date: 2026-12-26 }   # off
+//!   - { name: stocktake, date: 2026-12-31, windows: [{ start: "08:00", end: "12:00" }] }
+//! ```
+//!
+//! A window whose end is not after its start runs past midnight and belongs
+//! to the day it starts on. An exception without windows keeps its dates
+//! off.
+//!
+//! SCHEDULE blocks publish their schedules in the [`Schedules`] table of
+//! the signal bus, where the web API reads and replaces them at runtime.
+
+use crate::{PlcError, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Wall-clock window within a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Local start time (`HH:MM`)
+    pub start: NaiveTime,
+    /// Local end time (`HH:MM`), exclusive
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    fn is_overnight(&self) -> bool {
+        self.end <= self.start
+    }
+}
+
+/// Window repeated on some days of every week
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeeklyWindow {
+    /// Days the window starts on (`mon`, `tuesday`, ...)
+    pub days: Vec<Weekday>,
+    /// Local start time (`HH:MM`)
+    pub start: NaiveTime,
+    /// Local end time (`HH:MM`), exclusive
+    pub end: NaiveTime,
+}
+
+/// Dates on which the weekly windows do not apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateException {
+    /// Label, e.g. the holiday's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// First date of the exception
+    pub date: NaiveDate,
+    /// Last date of the exception, inclusive; defaults to `date`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<NaiveDate>,
+    /// Windows replacing the weekly ones; none keeps the dates off
+    #[serde(default)]
+    pub windows: Vec<TimeWindow>,
+}
+
+impl DateException {
+    fn covers(&self, date: NaiveDate) -> bool {
+        date >= self.date && date <= self.end_date.unwrap_or(self.date)
+    }
+}
+
+/// Weekly schedule with date exceptions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// IANA time zone of the windows, e.g. `America/Chicago`
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    /// Windows repeated every week
+    #[serde(default)]
+    pub weekly: Vec<WeeklyWindow>,
+    /// Holidays and other dates with their own windows
+    #[serde(default)]
+    pub exceptions: Vec<DateException>,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+impl Schedule {
+    /// Check that weekly windows name their days and exceptions end after
+    /// they start
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first problem found.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(window) = self.weekly.iter().find(|window| window.days.is_empty()) {
+            return Err(PlcError::Config(format!(
+                "Weekly window {}-{} has no days",
+                window.start.format("%H:%M"),
+                window.end.format("%H:%M")
+            )));
+        }
+        if let Some(exception) = self.exceptions.iter().find(|e| e.end_date.is_some_and(|end| end < e.date)) {
+            return Err(PlcError::Config(format!(
+                "Exception '{}' ends before it starts",
+                exception.name.as_deref().unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Windows starting on the local date `date`
+    fn windows_on(&self, date: NaiveDate) -> Vec<TimeWindow> {
+        if let Some(exception) = self.exceptions.iter().find(|e| e.covers(date)) {
+            return exception.windows.clone();
+        }
+        self.weekly
+            .iter()
+            .filter(|window| window.days.contains(&date.weekday()))
+            .map(|window| TimeWindow {
+                start: window.start,
+                end: window.end,
+            })
+            .collect()
+    }
+
+    /// Whether the schedule is on at `at`
+    #[must_use]
+    pub fn is_on(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.timezone).naive_local();
+        let (today, time) = (local.date(), local.time());
+        let started_today = self
+            .windows_on(today)
+            .iter()
+            .any(|w| time >= w.start && (w.is_overnight() || time < w.end));
+        // Overnight windows from yesterday run until their end today
+        started_today
+            || today.pred_opt().is_some_and(|yesterday| {
+                self.windows_on(yesterday).iter().any(|w| w.is_overnight() && time < w.end)
+            })
+    }
+}
+
+/// Schedule of a block as configured and as it currently applies
+#[derive(Debug, Clone)]
+struct Entry {
+    configured: Arc<Schedule>,
+    current: Arc<Schedule>,
+}
+
+/// Schedule of a SCHEDULE block as reported by the web API
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleStatus {
+    /// Block name
+    pub name: String,
+    /// Schedule in effect
+    pub schedule: Schedule,
+    /// Whether the schedule was replaced at runtime
+    pub edited: bool,
+    /// Whether the schedule is on now
+    pub on: bool,
+}
+
+/// Schedules of the SCHEDULE blocks, keyed by block name
+///
+/// Blocks register their schedules on their first scan. Replacing a
+/// schedule takes effect on the block's next scan.
+#[derive(Debug, Default)]
+pub struct Schedules {
+    entries: DashMap<String, Entry>,
+}
+
+impl Schedules {
+    /// Register the schedule of block `name`
+    ///
+    /// `current` differs from `configured` when the block restored a
+    /// schedule edited before a restart.
+    pub fn register(&self, name: &str, configured: Arc<Schedule>, current: Arc<Schedule>) {
+        self.entries.insert(name.to_string(), Entry { configured, current });
+    }
+
+    /// Schedule currently in effect for block `name`
+    #[must_use]
+    pub fn current(&self, name: &str) -> Option<Arc<Schedule>> {
+        self.entries.get(name).map(|entry| Arc::clone(&entry.current))
+    }
+
+    fn status(name: &str, entry: &Entry, now: DateTime<Utc>) -> ScheduleStatus {
+        ScheduleStatus {
+            name: name.to_string(),
+            schedule: (*entry.current).clone(),
+            edited: !Arc::ptr_eq(&entry.current, &entry.configured),
+            on: entry.current.is_on(now),
+        }
+    }
+
+    /// Every registered schedule, by block name
+    #[must_use]
+    pub fn list(&self, now: DateTime<Utc>) -> Vec<ScheduleStatus> {
+        let entries: BTreeMap<String, Entry> =
+            self.entries.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect();
+        entries.iter().map(|(name, entry)| Self::status(name, entry, now)).collect()
+    }
+
+    /// Schedule of block `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] if no block registered `name`.
+    pub fn get(&self, name: &str, now: DateTime<Utc>) -> Result<ScheduleStatus> {
+        let entry = self.entry(name)?;
+        Ok(Self::status(name, &entry, now))
+    }
+
+    /// Replace the schedule of block `name` until the block is reconfigured
+    /// or the schedule is restored
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] if no block registered `name` and
+    /// [`PlcError::Config`] for an invalid schedule.
+    pub fn replace(&self, name: &str, schedule: Schedule, now: DateTime<Utc>) -> Result<ScheduleStatus> {
+        schedule.validate()?;
+        let mut entry = self
+            .entries
+            .get_mut(name)
+            .ok_or_else(|| PlcError::NotFound(format!("No schedule '{name}'")))?;
+        entry.current = Arc::new(schedule);
+        Ok(Self::status(name, &entry, now))
+    }
+
+    /// Return block `name` to its configured schedule
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::NotFound`] if no block registered `name`.
+    pub fn restore(&self, name: &str, now: DateTime<Utc>) -> Result<ScheduleStatus> {
+        let mut entry = self
+            .entries
+            .get_mut(name)
+            .ok_or_else(|| PlcError::NotFound(format!("No schedule '{name}'")))?;
+        entry.current = Arc::clone(&entry.configured);
+        Ok(Self::status(name, &entry, now))
+    }
+
+    fn entry(&self, name: &str) -> Result<Entry> {
+        self.entries
+            .get(name)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| PlcError::NotFound(format!("No schedule '{name}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const OFFICE: &str = r#"
+timezone: Europe/Berlin
+weekly:
+  - { days: [mon, tue, wed, thu, fri], start: "07:00", end: "18:00" }
+  - { days: [fri], start: "22:00", end: "02:00" }
+exceptions:
+  - { name: christmas, date: 2026-12-24, end_date: 2026-12-26 }
+  - { date: 2026-12-31, windows: [{ start: "08:00", end: "12:00" }] }
+"#;
+
+    fn berlin(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        chrono_tz::Europe::Berlin.with_ymd_and_hms(y, m, d, h, min, 0).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_weekly_windows_exceptions_and_daylight_saving() {
+        let schedule: Schedule = serde_yaml::from_str(OFFICE).unwrap();
+        schedule.validate().unwrap();
+
+        // Monday 2026-03-23 (CET) and Monday 2026-03-30 (CEST) open at 07:00 local
+        assert!(!schedule.is_on(berlin(2026, 3, 23, 6, 59)));
+        assert!(schedule.is_on(berlin(2026, 3, 23, 7, 0)));
+        assert!(schedule.is_on(berlin(2026, 3, 30, 7, 0)));
+        assert!(!schedule.is_on(berlin(2026, 3, 30, 18, 0)));
+        // Friday night runs into Saturday
+        assert!(schedule.is_on(berlin(2026, 3, 28, 1, 30)));
+        assert!(!schedule.is_on(berlin(2026, 3, 28, 2, 0)));
+
+        // Thursday 2026-12-24 is a holiday, Thursday 2026-12-31 a short day
+        assert!(!schedule.is_on(berlin(2026, 12, 24, 9, 0)));
+        assert!(schedule.is_on(berlin(2026, 12, 31, 9, 0)));
+        assert!(!schedule.is_on(berlin(2026, 12, 31, 13, 0)));
+    }
+
+    #[test]
+    fn test_table_replaces_and_restores_schedules() {
+        let schedules = Schedules::default();
+        let configured: Arc<Schedule> = Arc::new(serde_yaml::from_str(OFFICE).unwrap());
+        schedules.register("office", Arc::clone(&configured), Arc::clone(&configured));
+        let now = berlin(2026, 3, 28, 10, 0);
+        assert!(!schedules.get("office", now).unwrap().on);
+
+        let weekend: Schedule =
+            serde_yaml::from_str(r#"{ weekly: [{ days: [sat, sun], start: "00:00", end: "00:00" }] }"#).unwrap();
+        let status = schedules.replace("office", weekend, now).unwrap();
+        assert!(status.edited && status.on);
+        assert!(schedules.current("office").unwrap().is_on(now));
+
+        let status = schedules.restore("office", now).unwrap();
+        assert!(!status.edited && !status.on);
+        assert!(schedules.replace("lobby", Schedule::clone(&configured), now).is_err());
+        let invalid: Schedule =
+            serde_yaml::from_str(r#"{ weekly: [{ days: [], start: "07:00", end: "08:00" }] }"#).unwrap();
+        assert!(schedules.replace("office", invalid, now).is_err());
+    }
+}
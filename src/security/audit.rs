@@ -1 +1,644 @@
+//! Security audit logging
+//!
+//! Audit entries are appended as JSON lines so the log can be tailed, shipped
+//! by standard log collectors and read back by `petra security audit`.
+//!
+//! Operator actions on signals and alarms, whichever channel they arrive
+//! through, are recorded with their target, the value before and after the
+//! write and the operator's reason. Signals listed as critical only accept
+//! writes that carry a reason:
+//!
+//! ```yaml
+//! security:
+//!   audit:
+//!     log_file: logs/audit.jsonl
+//!     critical_signals: ["line1.setpoint.*", "boiler.pressure_limit"]
+//! ```
+//!
+//! Areas put in and taken out of maintenance, and signals forced and
+//! released, are recorded with the operator and reason.
+//!
+//! Logins and logouts of session-based clients are recorded too, tagged
+//! with their session id. Login history, failed attempts and the sessions
+//! still open are all derived from these entries, so they survive restarts
+//! of the web server and need no separate store.
 
+use crate::{PlcError, Result, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Audit log location used when none is configured
+pub const DEFAULT_AUDIT_LOG_PATH: &str = "logs/audit.jsonl";
+
+/// Audit action of an operator signal write
+pub const SIGNAL_WRITE_ACTION: &str = "signal.write";
+
+/// Audit action of an operator alarm acknowledgement
+pub const ALARM_ACK_ACTION: &str = "alarm.ack";
+
+/// Audit action of an operator putting an area in maintenance
+pub const MAINTENANCE_ENTER_ACTION: &str = "maintenance.enter";
+
+/// Audit action of an operator taking an area out of maintenance
+pub const MAINTENANCE_EXIT_ACTION: &str = "maintenance.exit";
+
+/// Audit action of an operator forcing a signal
+pub const SIGNAL_FORCE_ACTION: &str = "signal.force";
+
+/// Audit action of an operator releasing a forced signal
+pub const SIGNAL_UNFORCE_ACTION: &str = "signal.unforce";
+
+/// Audit action of a login attempt, successful or not
+pub const LOGIN_ACTION: &str = "session.login";
+
+/// Audit action of a session ending
+pub const LOGOUT_ACTION: &str = "session.logout";
+
+/// Entries returned by [`AuditLog::query`] when no limit is given
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// A single audited action
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the action was attempted
+    pub timestamp: DateTime<Utc>,
+    /// Authenticated user or client, `"unknown"` if authentication failed
+    pub user: String,
+    /// Origin of the request, e.g. a peer address or `"mqtt"`
+    pub source_ip: String,
+    /// Action name, e.g. `"signal.write"`
+    pub action: String,
+    /// Target and parameters of the action
+    pub details: String,
+    /// Whether the action was carried out
+    pub success: bool,
+    /// Signal or alarm an operator action was aimed at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Signal value before an operator write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<Value>,
+    /// Value an operator wrote or tried to write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<Value>,
+    /// Reason given by the operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Electronic signature the action was signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureRecord>,
+    /// Session a login or logout belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// Verified electronic signature as stored in the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureRecord {
+    /// User whose credentials were re-entered
+    pub signer: String,
+    /// What the signature attests, e.g. `"approved"`
+    pub meaning: String,
+}
+
+impl AuditEntry {
+    /// Entry for an operator write of `new_value` to `signal`
+    ///
+    /// `details` is filled in from the values and, on failure, the error.
+    #[must_use]
+    pub fn signal_write(
+        user: &str,
+        source: &str,
+        signal: &str,
+        old_value: Option<Value>,
+        new_value: Value,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) -> Self {
+        let mut details = match &old_value {
+            Some(old) => format!("{signal}: {old} -> {new_value}"),
+            None => format!("{signal}: {new_value}"),
+        };
+        if let Err(e) = result {
+            details = format!("{details}: rejected: {e}");
+        }
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: SIGNAL_WRITE_ACTION.to_string(),
+            details,
+            success: result.is_ok(),
+            target: Some(signal.to_string()),
+            old_value,
+            new_value: Some(new_value),
+            reason: reason.map(str::to_string),
+            signature: None,
+            session: None,
+        }
+    }
+
+    /// Attach the electronic signature the action was signed with
+    #[must_use]
+    pub fn with_signature(mut self, signature: Option<SignatureRecord>) -> Self {
+        self.signature = signature;
+        self
+    }
+
+    /// Entry for an operator acknowledgement of `alarm`
+    #[must_use]
+    pub fn alarm_ack(
+        user: &str,
+        source: &str,
+        alarm: &str,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: ALARM_ACK_ACTION.to_string(),
+            details: match result {
+                Ok(()) => alarm.to_string(),
+                Err(e) => format!("{alarm}: rejected: {e}"),
+            },
+            success: result.is_ok(),
+            target: Some(alarm.to_string()),
+            reason: reason.map(str::to_string),
+            ..Self::default()
+        }
+    }
+
+    /// Entry for `user` putting `area` in maintenance, or taking it out
+    #[must_use]
+    pub fn maintenance(
+        user: &str,
+        source: &str,
+        area: &str,
+        enter: bool,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) -> Self {
+        let (action, verb) = if enter {
+            (MAINTENANCE_ENTER_ACTION, "entered")
+        } else {
+            (MAINTENANCE_EXIT_ACTION, "left")
+        };
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: action.to_string(),
+            details: match result {
+                Ok(()) => format!("{area} {verb} maintenance"),
+                Err(e) => format!("{area}: rejected: {e}"),
+            },
+            success: result.is_ok(),
+            target: Some(area.to_string()),
+            reason: reason.map(str::to_string),
+            ..Self::default()
+        }
+    }
+
+    /// Entry for `user` forcing `signal` to `value`, or releasing it when
+    /// `value` is `None`
+    #[must_use]
+    pub fn force(
+        user: &str,
+        source: &str,
+        signal: &str,
+        old_value: Option<Value>,
+        value: Option<Value>,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) -> Self {
+        let (action, details) = match &value {
+            Some(value) => (SIGNAL_FORCE_ACTION, format!("{signal} forced to {value}")),
+            None => (SIGNAL_UNFORCE_ACTION, format!("{signal} released")),
+        };
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: action.to_string(),
+            details: match result {
+                Ok(()) => details,
+                Err(e) => format!("{signal}: rejected: {e}"),
+            },
+            success: result.is_ok(),
+            target: Some(signal.to_string()),
+            old_value,
+            new_value: value,
+            reason: reason.map(str::to_string),
+            ..Self::default()
+        }
+    }
+
+    /// Entry for a login attempt as `user`
+    ///
+    /// Failed attempts are recorded under the name the client tried, so
+    /// they can be counted per user, and carry no session.
+    #[must_use]
+    pub fn login(user: &str, source: &str, session: &str, result: std::result::Result<(), &str>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: LOGIN_ACTION.to_string(),
+            details: match result {
+                Ok(()) => format!("session {session} opened"),
+                Err(e) => format!("rejected: {e}"),
+            },
+            success: result.is_ok(),
+            session: result.is_ok().then(|| session.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Entry for the end of `user`'s `session`
+    #[must_use]
+    pub fn logout(user: &str, source: &str, session: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: user.to_string(),
+            source_ip: source.to_string(),
+            action: LOGOUT_ACTION.to_string(),
+            details: format!("session {session} closed"),
+            success: true,
+            session: Some(session.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A login that has not been followed by a logout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveSession {
+    /// Session id
+    pub session: String,
+    pub user: String,
+    /// Origin of the login
+    pub source_ip: String,
+    /// When the session was opened
+    pub since: DateTime<Utc>,
+}
+
+/// Login summary of one user, as shown on the security page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserActivity {
+    pub user: String,
+    /// Most recent successful login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_login: Option<DateTime<Utc>>,
+    /// Most recent failed login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failed_login: Option<DateTime<Utc>>,
+    /// Failed logins in the requested period
+    pub failed_attempts: usize,
+    /// Failed logins since the last successful one
+    pub consecutive_failures: usize,
+    /// Sessions currently open
+    pub active_sessions: usize,
+}
+
+/// Filters for [`AuditLog::query`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    /// Most entries returned, [`DEFAULT_QUERY_LIMIT`] if unset
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+    /// Signal or alarm name
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Earliest timestamp, inclusive
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Latest timestamp, exclusive
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// Only successful (`true`) or failed (`false`) actions
+    #[serde(default)]
+    pub success: Option<bool>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.user.as_ref().is_none_or(|u| &entry.user == u)
+            && self.action.as_ref().is_none_or(|a| &entry.action == a)
+            && self.target.as_ref().is_none_or(|t| entry.target.as_ref() == Some(t))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.success.is_none_or(|success| entry.success == success)
+    }
+}
+
+/// Append-only JSON lines audit log
+pub struct AuditLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+    critical_signals: Vec<String>,
+    /// Sessions opened before this are gone with the process that held them
+    opened: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// Open an audit log, creating its directory if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the parent directory cannot be created.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            path,
+            lock: Mutex::new(()),
+            critical_signals: Vec::new(),
+            opened: Utc::now(),
+        })
+    }
+
+    /// Require a reason for writes to signals matching `patterns`
+    ///
+    /// Patterns are signal names, optionally ending in `*` to match a prefix.
+    #[must_use]
+    pub fn with_critical_signals(mut self, patterns: Vec<String>) -> Self {
+        self.critical_signals = patterns;
+        self
+    }
+
+    /// Whether writes to `signal` must carry a reason
+    #[must_use]
+    pub fn is_critical(&self, signal: &str) -> bool {
+        self.critical_signals.iter().any(|pattern| {
+            pattern
+                .strip_suffix('*')
+                .map_or(pattern == signal, |prefix| signal.starts_with(prefix))
+        })
+    }
+
+    /// Check that a write to `signal` carries a reason if one is required
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] if `signal` is critical and `reason`
+    /// is missing or blank.
+    pub fn require_reason(&self, signal: &str, reason: Option<&str>) -> Result<()> {
+        if self.is_critical(signal) && reason.is_none_or(|r| r.trim().is_empty()) {
+            return Err(PlcError::Validation(format!(
+                "Signal '{signal}' is critical; writes require a reason"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Log file location
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be serialized or written.
+    pub fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Most recent entries matching the filters, newest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the log exists but cannot be read.
+    pub fn read(&self, limit: usize, user: Option<&str>, action: Option<&str>) -> Result<Vec<AuditEntry>> {
+        self.query(&AuditQuery {
+            limit: Some(limit),
+            user: user.map(str::to_string),
+            action: action.map(str::to_string),
+            ..AuditQuery::default()
+        })
+    }
+
+    /// Most recent entries matching `query`, newest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the log exists but cannot be read. Lines that
+    /// fail to parse are skipped.
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let mut entries = self.entries(|e| query.matches(e))?;
+        entries.reverse();
+        entries.truncate(query.limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+        Ok(entries)
+    }
+
+    /// Login attempts matching `query`, newest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the log exists but cannot be read.
+    pub fn logins(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        self.query(&AuditQuery {
+            action: Some(LOGIN_ACTION.to_string()),
+            ..query.clone()
+        })
+    }
+
+    /// Sessions opened since this log was opened and not yet closed,
+    /// newest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the log exists but cannot be read.
+    pub fn active_sessions(&self, user: Option<&str>) -> Result<Vec<ActiveSession>> {
+        let mut open: HashMap<String, ActiveSession> = HashMap::new();
+        for entry in self.entries(|e| e.timestamp >= self.opened && user.is_none_or(|u| e.user == u))? {
+            let Some(session) = entry.session else { continue };
+            match entry.action.as_str() {
+                LOGIN_ACTION if entry.success => {
+                    open.insert(
+                        session.clone(),
+                        ActiveSession {
+                            session,
+                            user: entry.user,
+                            source_ip: entry.source_ip,
+                            since: entry.timestamp,
+                        },
+                    );
+                }
+                LOGOUT_ACTION => {
+                    open.remove(&session);
+                }
+                _ => {}
+            }
+        }
+        let mut sessions: Vec<_> = open.into_values().collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.since));
+        Ok(sessions)
+    }
+
+    /// Login summary per user, counting failed attempts from `since`
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the log exists but cannot be read.
+    pub fn user_activity(&self, since: DateTime<Utc>) -> Result<Vec<UserActivity>> {
+        let mut users: BTreeMap<String, UserActivity> = BTreeMap::new();
+        for entry in self.entries(|e| e.action == LOGIN_ACTION)? {
+            let activity = users.entry(entry.user.clone()).or_insert_with(|| UserActivity {
+                user: entry.user.clone(),
+                ..UserActivity::default()
+            });
+            if entry.success {
+                activity.last_login = Some(entry.timestamp);
+                activity.consecutive_failures = 0;
+            } else {
+                activity.last_failed_login = Some(entry.timestamp);
+                activity.consecutive_failures += 1;
+                if entry.timestamp >= since {
+                    activity.failed_attempts += 1;
+                }
+            }
+        }
+        for session in self.active_sessions(None)? {
+            if let Some(activity) = users.get_mut(&session.user) {
+                activity.active_sessions += 1;
+            }
+        }
+        Ok(users.into_values().collect())
+    }
+
+    /// All parseable entries accepted by `filter`, oldest first
+    fn entries(&self, filter: impl Fn(&AuditEntry) -> bool) -> Result<Vec<AuditEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(std::result::Result::ok)
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+            .filter(|e| filter(e))
+            .collect())
+    }
+}
+
+/// Read the default audit log
+///
+/// # Errors
+///
+/// Returns an I/O error if the log exists but cannot be read.
+pub async fn get_audit_log(
+    limit: usize,
+    user: Option<&str>,
+    action: Option<&str>,
+) -> Result<Vec<AuditEntry>> {
+    AuditLog::new(DEFAULT_AUDIT_LOG_PATH)?.read(limit, user, action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_writes_are_queryable() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("audit.jsonl"))
+            .unwrap()
+            .with_critical_signals(vec!["line1.setpoint.*".to_string()]);
+
+        assert!(log.require_reason("line1.setpoint.temp", None).is_err());
+        assert!(log.require_reason("line1.setpoint.temp", Some("  ")).is_err());
+        assert!(log.require_reason("line1.setpoint.temp", Some("batch 42")).is_ok());
+        assert!(log.require_reason("line1.speed", None).is_ok());
+
+        log.record(&AuditEntry::signal_write(
+            "alice",
+            "10.0.0.5",
+            "line1.setpoint.temp",
+            Some(Value::Float(70.0)),
+            Value::Float(72.5),
+            Some("batch 42"),
+            Ok(()),
+        ))
+        .unwrap();
+        log.record(&AuditEntry::alarm_ack("bob", "websocket", "high_temp", None, Ok(())))
+            .unwrap();
+
+        let writes = log
+            .query(&AuditQuery {
+                target: Some("line1.setpoint.temp".to_string()),
+                ..AuditQuery::default()
+            })
+            .unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].old_value, Some(Value::Float(70.0)));
+        assert_eq!(writes[0].new_value, Some(Value::Float(72.5)));
+        assert_eq!(writes[0].reason.as_deref(), Some("batch 42"));
+
+        assert_eq!(log.read(10, None, Some(ALARM_ACK_ACTION)).unwrap()[0].user, "bob");
+    }
+
+    #[test]
+    fn test_login_history_and_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        // A session left open by an earlier process is not active any more
+        let earlier = AuditLog::new(&path).unwrap();
+        earlier.record(&AuditEntry::login("hmi1", "10.0.0.7", "s0", Ok(()))).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let log = AuditLog::new(&path).unwrap();
+        log.record(&AuditEntry::login("hmi1", "10.0.0.7", "s1", Err("Invalid client or token"))).unwrap();
+        log.record(&AuditEntry::login("hmi1", "10.0.0.7", "s2", Ok(()))).unwrap();
+        log.record(&AuditEntry::login("hmi2", "10.0.0.8", "s3", Ok(()))).unwrap();
+        log.record(&AuditEntry::login("hmi2", "10.0.0.8", "s4", Err("Invalid client or token"))).unwrap();
+        log.record(&AuditEntry::login("hmi2", "10.0.0.8", "s5", Err("Invalid client or token"))).unwrap();
+        log.record(&AuditEntry::logout("hmi2", "10.0.0.8", "s3")).unwrap();
+
+        let failed = log
+            .logins(&AuditQuery { success: Some(false), ..AuditQuery::default() })
+            .unwrap();
+        assert_eq!(failed.len(), 3);
+        assert!(failed.iter().all(|e| e.session.is_none()));
+        assert_eq!(log.logins(&AuditQuery { user: Some("hmi1".to_string()), ..AuditQuery::default() }).unwrap().len(), 3);
+
+        let sessions = log.active_sessions(None).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].session.as_str(), sessions[0].user.as_str()), ("s2", "hmi1"));
+        assert!(log.active_sessions(Some("hmi2")).unwrap().is_empty());
+
+        let activity = log.user_activity(Utc::now() - chrono::Duration::hours(1)).unwrap();
+        let users: Vec<_> = activity.iter().map(|a| a.user.as_str()).collect();
+        assert_eq!(users, ["hmi1", "hmi2"]);
+        assert_eq!((activity[0].failed_attempts, activity[0].consecutive_failures, activity[0].active_sessions), (1, 0, 1));
+        assert_eq!((activity[1].failed_attempts, activity[1].consecutive_failures, activity[1].active_sessions), (2, 2, 0));
+        assert!(activity[1].last_failed_login > activity[1].last_login);
+        assert_eq!(log.user_activity(Utc::now()).unwrap()[1].failed_attempts, 0);
+    }
+}
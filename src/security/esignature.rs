@@ -0,0 +1,256 @@
+//! Electronic signatures for critical operator actions
+//!
+//! Regulated plants (21 CFR Part 11 and similar) require that some actions
+//! are signed: the operator re-enters their password, states a reason and
+//! the meaning of the signature, and the signature is stored with the action
+//! in the audit log. Critical actions are configured as rules on signals.
+//! A rule without limits covers every write, which suits commands such as a
+//! recipe download trigger; a rule with limits only covers setpoints written
+//! outside the band:
+//!
+//! ```yaml
+//! security:
+//!   basic_auth:
+//!     users:
+//!       alice: "$2b$12$..."
+//!   audit:
+//!     log_file: logs/audit.jsonl
+//!   esignature:
+//!     rules:
+//!       - signal: "line1.setpoint.temp"
+//!         min: 50.0
+//!         max: 90.0
+//!       - signal: "recipe.download"
+//! ```
+//!
+//! Signers are the `basic_auth` users. Passwords are checked against their
+//! bcrypt hashes and are never written to the audit log.
+
+use super::audit::SignatureRecord;
+use crate::{PlcError, Result, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Critical actions that must be signed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ESignatureConfig {
+    /// Signal writes that require a signature
+    #[serde(default)]
+    pub rules: Vec<SignatureRule>,
+}
+
+/// Signals whose writes require a signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureRule {
+    /// Signal name, optionally ending in `*` to match a prefix
+    pub signal: String,
+
+    /// Writes below this value require a signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// Writes above this value require a signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+impl SignatureRule {
+    fn matches(&self, signal: &str) -> bool {
+        self.signal
+            .strip_suffix('*')
+            .map_or(self.signal == signal, |prefix| signal.starts_with(prefix))
+    }
+
+    /// Whether writing `value` falls under this rule
+    ///
+    /// Non-numeric values always do when limits are set, since they cannot
+    /// be shown to lie inside the band.
+    fn covers(&self, value: &Value) -> bool {
+        if self.min.is_none() && self.max.is_none() {
+            return true;
+        }
+        value.as_float().is_none_or(|v| {
+            self.min.is_some_and(|min| v < min) || self.max.is_some_and(|max| v > max)
+        })
+    }
+}
+
+impl ESignatureConfig {
+    /// Validate the rules
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an empty signal pattern or a rule
+    /// whose `min` exceeds its `max`.
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            if rule.signal.is_empty() {
+                return Err(PlcError::Config("E-signature rule has an empty signal".to_string()));
+            }
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(PlcError::Config(format!(
+                        "E-signature rule for '{}' has min {min} above max {max}",
+                        rule.signal
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Credentials and meaning an operator supplies to sign an action
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ESignature {
+    /// Signing user
+    pub user: String,
+    /// The user's password, re-entered for this action
+    pub password: String,
+    /// What the signature attests, e.g. `"approved"` or `"performed"`
+    pub meaning: String,
+}
+
+impl std::fmt::Debug for ESignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ESignature")
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .field("meaning", &self.meaning)
+            .finish()
+    }
+}
+
+/// Checks signatures against the configured rules and users
+#[derive(Debug, Clone)]
+pub struct ESignatureVerifier {
+    config: ESignatureConfig,
+    /// Username to bcrypt password hash
+    users: HashMap<String, String>,
+}
+
+impl ESignatureVerifier {
+    /// Create a verifier for the given signers
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the rules are invalid or there are no
+    /// users to sign with.
+    pub fn new(config: ESignatureConfig, users: HashMap<String, String>) -> Result<Self> {
+        config.validate()?;
+        if users.is_empty() {
+            return Err(PlcError::Config(
+                "Electronic signatures require basic_auth users".to_string(),
+            ));
+        }
+        Ok(Self { config, users })
+    }
+
+    /// Whether writing `value` to `signal` must be signed
+    #[must_use]
+    pub fn requires_signature(&self, signal: &str, value: &Value) -> bool {
+        self.config
+            .rules
+            .iter()
+            .any(|rule| rule.matches(signal) && rule.covers(value))
+    }
+
+    /// Verify the signature a write needs
+    ///
+    /// Returns the record to audit, or `None` if the write is not critical.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] if a required signature, reason or
+    /// meaning is missing and [`PlcError::AuthenticationFailed`] if the
+    /// credentials do not match.
+    pub fn check_write(
+        &self,
+        signal: &str,
+        value: &Value,
+        signature: Option<&ESignature>,
+        reason: Option<&str>,
+    ) -> Result<Option<SignatureRecord>> {
+        if !self.requires_signature(signal, value) {
+            return Ok(None);
+        }
+        let signature = signature.ok_or_else(|| {
+            PlcError::Validation(format!("Writing {value} to '{signal}' requires an electronic signature"))
+        })?;
+        if reason.is_none_or(|r| r.trim().is_empty()) {
+            return Err(PlcError::Validation(format!(
+                "Signed write to '{signal}' requires a reason"
+            )));
+        }
+        if signature.meaning.trim().is_empty() {
+            return Err(PlcError::Validation("Electronic signature requires a meaning".to_string()));
+        }
+        self.authenticate(signature)?;
+        Ok(Some(SignatureRecord {
+            signer: signature.user.clone(),
+            meaning: signature.meaning.clone(),
+        }))
+    }
+
+    fn authenticate(&self, signature: &ESignature) -> Result<()> {
+        // Same error for unknown users and wrong passwords
+        let valid = self
+            .users
+            .get(&signature.user)
+            .is_some_and(|hash| bcrypt::verify(&signature.password, hash).unwrap_or(false));
+        if valid {
+            Ok(())
+        } else {
+            Err(PlcError::AuthenticationFailed("Invalid signature credentials".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setpoint_outside_limits_needs_signature() {
+        let config: ESignatureConfig = serde_yaml::from_str(
+            r#"
+rules:
+  - { signal: "line1.setpoint.temp", min: 50.0, max: 90.0 }
+  - { signal: "recipe.*" }
+"#,
+        )
+        .unwrap();
+        let users = HashMap::from([("alice".to_string(), bcrypt::hash("s3cret", 4).unwrap())]);
+        let verifier = ESignatureVerifier::new(config, users).unwrap();
+
+        assert!(!verifier.requires_signature("line1.setpoint.temp", &Value::Float(70.0)));
+        assert!(verifier.requires_signature("line1.setpoint.temp", &Value::Float(95.0)));
+        assert!(verifier.requires_signature("recipe.download", &Value::Bool(true)));
+        assert!(!verifier.requires_signature("line1.speed", &Value::Float(1000.0)));
+
+        let high = Value::Float(95.0);
+        let signature = |password: &str| ESignature {
+            user: "alice".to_string(),
+            password: password.to_string(),
+            meaning: "approved".to_string(),
+        };
+        assert!(matches!(
+            verifier.check_write("line1.setpoint.temp", &high, None, Some("trial")),
+            Err(PlcError::Validation(_))
+        ));
+        assert!(matches!(
+            verifier.check_write("line1.setpoint.temp", &high, Some(&signature("s3cret")), None),
+            Err(PlcError::Validation(_))
+        ));
+        assert!(matches!(
+            verifier.check_write("line1.setpoint.temp", &high, Some(&signature("wrong")), Some("trial")),
+            Err(PlcError::AuthenticationFailed(_))
+        ));
+        let record = verifier
+            .check_write("line1.setpoint.temp", &high, Some(&signature("s3cret")), Some("trial"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.signer, "alice");
+        assert!(!format!("{:?}", signature("s3cret")).contains("s3cret"));
+    }
+}
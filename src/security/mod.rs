@@ -15,9 +15,15 @@ pub mod audit;
 #[cfg(feature = "signing")]
 pub mod signing;
 
+#[cfg(feature = "esignature")]
+pub mod esignature;
+
 pub mod cli;
 pub use cli::{generate_key, create_user};
 
+#[cfg(feature = "audit")]
+pub use audit::{get_audit_log, AuditEntry, AuditLog};
+
 /// Main security manager
 pub struct SecurityManager {
     // Add fields as needed
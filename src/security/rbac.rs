@@ -1 +1,135 @@
+//! Role-based access control
+//!
+//! Roles grant permissions of the form `<action>:<resource>`, where the
+//! resource may end in `*` to match a prefix:
+//!
+//! ```yaml
+//! roles:
+//!   operator:
+//!     permissions:
+//!       - "signal.write:line1.*"
+//!       - "block.reset:*"
+//!   viewer:
+//!     permissions: []
+//! ```
 
+use crate::{PlcError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Permission action for signal reads
+pub const READ_SIGNAL_PERMISSION: &str = "signal.read";
+
+/// Permission action for signal writes
+pub const WRITE_SIGNAL_PERMISSION: &str = "signal.write";
+
+/// Permission action for block resets
+pub const RESET_BLOCK_PERMISSION: &str = "block.reset";
+
+/// Permission action for alarm acknowledgement
+pub const ACK_ALARM_PERMISSION: &str = "alarm.ack";
+
+/// Permission action for engine start, stop and reload
+pub const ENGINE_CONTROL_PERMISSION: &str = "engine.control";
+
+/// Permission action for following the engine event stream, per event kind
+pub const READ_EVENTS_PERMISSION: &str = "event.read";
+
+/// Role definitions keyed by role name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RbacConfig {
+    /// Configured roles
+    #[serde(default)]
+    pub roles: HashMap<String, RoleDefinition>,
+}
+
+/// Permissions granted to a role
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoleDefinition {
+    /// Granted permissions, `<action>:<resource pattern>`
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl RbacConfig {
+    /// Check that every permission is well formed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for a permission without an action or
+    /// resource, or with a wildcard anywhere but the end.
+    pub fn validate(&self) -> Result<()> {
+        for (role, definition) in &self.roles {
+            for permission in &definition.permissions {
+                let valid = permission.split_once(':').is_some_and(|(action, resource)| {
+                    !action.is_empty()
+                        && !resource.is_empty()
+                        && !resource.trim_end_matches('*').contains('*')
+                });
+                if !valid {
+                    return Err(PlcError::Config(format!(
+                        "Invalid permission '{permission}' in role '{role}'"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `role` may perform `action` on `resource`
+    #[must_use]
+    pub fn is_allowed(&self, role: &str, action: &str, resource: &str) -> bool {
+        self.roles.get(role).is_some_and(|definition| {
+            definition.permissions.iter().any(|permission| {
+                permission.split_once(':').is_some_and(|(granted, pattern)| {
+                    granted == action && resource_matches(pattern, resource)
+                })
+            })
+        })
+    }
+
+    /// Require that `role` may perform `action` on `resource`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::AuthorizationDenied`] if no permission matches.
+    pub fn check(&self, role: &str, action: &str, resource: &str) -> Result<()> {
+        if self.is_allowed(role, action, resource) {
+            Ok(())
+        } else {
+            Err(PlcError::AuthorizationDenied(format!(
+                "Role '{role}' may not {action} '{resource}'"
+            )))
+        }
+    }
+}
+
+fn resource_matches(pattern: &str, resource: &str) -> bool {
+    pattern
+        .strip_suffix('*')
+        .map_or(pattern == resource, |prefix| resource.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_matching() {
+        let rbac: RbacConfig = serde_yaml::from_str(
+            r#"
+roles:
+  operator:
+    permissions: ["signal.write:line1.*", "block.reset:timer1"]
+"#,
+        )
+        .unwrap();
+        rbac.validate().unwrap();
+
+        assert!(rbac.is_allowed("operator", "signal.write", "line1.speed"));
+        assert!(!rbac.is_allowed("operator", "signal.write", "line2.speed"));
+        assert!(rbac.is_allowed("operator", "block.reset", "timer1"));
+        assert!(!rbac.is_allowed("operator", "block.reset", "timer2"));
+        assert!(rbac.check("viewer", "signal.write", "line1.speed").is_err());
+    }
+}
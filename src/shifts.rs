@@ -0,0 +1,223 @@
+// src/shifts.rs
+//! Production shift calendar
+//!
+//! Shift-based aggregation (OEE, downtime, energy) needs to know which shift a
+//! timestamp belongs to and when that shift instance started. Shifts are
+//! defined as daily wall-clock windows at a fixed UTC offset; a window whose
+//! end is before its start runs overnight.
+//!
+//! ```yaml
+//! shifts:
+//!   utc_offset_minutes: 60
+//!   definitions:
+//!     - { name: early, start: "06:00", end: "14:00" }
+//!     - { name: late,  start: "14:00", end: "22:00" }
+//!     - { name: night, start: "22:00", end: "06:00" }
+//! ```
+//!
+//! Time not covered by any definition is attributed to the implicit
+//! `unscheduled` shift so no production is silently dropped.
+
+use crate::{PlcError, Result};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Name of the shift used for time outside all configured shifts
+pub const UNSCHEDULED_SHIFT: &str = "unscheduled";
+
+/// A single daily shift window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShiftDefinition {
+    /// Shift name, e.g. "early"
+    pub name: String,
+    /// Wall-clock start time (`HH:MM`)
+    pub start: NaiveTime,
+    /// Wall-clock end time (`HH:MM`), exclusive
+    pub end: NaiveTime,
+}
+
+impl ShiftDefinition {
+    fn is_overnight(&self) -> bool {
+        self.end <= self.start
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.is_overnight() {
+            time >= self.start || time < self.end
+        } else {
+            time >= self.start && time < self.end
+        }
+    }
+}
+
+/// Shift calendar configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShiftCalendar {
+    /// Offset of plant local time from UTC in minutes
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+
+    /// Daily shift windows
+    #[serde(default)]
+    pub definitions: Vec<ShiftDefinition>,
+}
+
+/// A concrete occurrence of a shift
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShiftInstance {
+    /// Shift name
+    pub name: String,
+    /// Local calendar date the shift started on
+    pub date: NaiveDate,
+    /// Start of the shift instance in UTC
+    pub started_at: DateTime<Utc>,
+}
+
+impl ShiftCalendar {
+    /// Check that the offset is valid and shifts do not overlap
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for an out-of-range offset, duplicate
+    /// shift names or overlapping windows.
+    pub fn validate(&self) -> Result<()> {
+        self.offset()?;
+
+        for (i, a) in self.definitions.iter().enumerate() {
+            if a.start == a.end {
+                return Err(PlcError::Config(format!(
+                    "Shift '{}' has identical start and end",
+                    a.name
+                )));
+            }
+            for b in &self.definitions[i + 1..] {
+                if a.name == b.name {
+                    return Err(PlcError::Config(format!("Duplicate shift '{}'", a.name)));
+                }
+                if a.contains(b.start) || b.contains(a.start) {
+                    return Err(PlcError::Config(format!(
+                        "Shifts '{}' and '{}' overlap",
+                        a.name, b.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> Result<FixedOffset> {
+        FixedOffset::east_opt(self.utc_offset_minutes * 60).ok_or_else(|| {
+            PlcError::Config(format!(
+                "Invalid shift UTC offset: {} minutes",
+                self.utc_offset_minutes
+            ))
+        })
+    }
+
+    /// Determine the shift instance that contains `at`
+    ///
+    /// Without any definitions every local day is one `unscheduled` shift.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the UTC offset is invalid.
+    pub fn instance_at(&self, at: DateTime<Utc>) -> Result<ShiftInstance> {
+        let offset = self.offset()?;
+        let local = at.with_timezone(&offset);
+        let time = local.time();
+        let today = local.date_naive();
+
+        let to_utc = |date: NaiveDate, time: NaiveTime| {
+            (date.and_time(time) - Duration::minutes(i64::from(self.utc_offset_minutes)))
+                .and_utc()
+        };
+
+        if let Some(shift) = self.definitions.iter().find(|s| s.contains(time)) {
+            // An overnight shift observed after midnight started yesterday
+            let date = if shift.is_overnight() && time < shift.end {
+                today.pred_opt().unwrap_or(today)
+            } else {
+                today
+            };
+            return Ok(ShiftInstance {
+                name: shift.name.clone(),
+                date,
+                started_at: to_utc(date, shift.start),
+            });
+        }
+
+        // Unscheduled time starts at the most recent shift end (or midnight)
+        let started_at = self
+            .definitions
+            .iter()
+            .map(|s| {
+                if s.end <= time {
+                    to_utc(today, s.end)
+                } else {
+                    to_utc(today.pred_opt().unwrap_or(today), s.end)
+                }
+            })
+            .max()
+            .unwrap_or_else(|| to_utc(today, NaiveTime::MIN));
+
+        Ok(ShiftInstance {
+            name: UNSCHEDULED_SHIFT.to_string(),
+            date: today,
+            started_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn calendar() -> ShiftCalendar {
+        serde_yaml::from_str(
+            r#"
+utc_offset_minutes: 60
+definitions:
+  - { name: early, start: "06:00:00", end: "14:00:00" }
+  - { name: night, start: "22:00:00", end: "06:00:00" }
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_day_and_overnight_shifts() {
+        let cal = calendar();
+        cal.validate().unwrap();
+
+        // 07:30 local
+        let early = cal.instance_at(Utc.with_ymd_and_hms(2024, 3, 5, 6, 30, 0).unwrap()).unwrap();
+        assert_eq!(early.name, "early");
+        assert_eq!(early.started_at, Utc.with_ymd_and_hms(2024, 3, 5, 5, 0, 0).unwrap());
+
+        // 02:00 local belongs to the night shift that started the previous day
+        let night = cal.instance_at(Utc.with_ymd_and_hms(2024, 3, 5, 1, 0, 0).unwrap()).unwrap();
+        assert_eq!(night.name, "night");
+        assert_eq!(night.date, NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+    }
+
+    #[test]
+    fn test_unscheduled_gap() {
+        let cal = calendar();
+        // 15:00 local, between early end and night start
+        let gap = cal.instance_at(Utc.with_ymd_and_hms(2024, 3, 5, 14, 0, 0).unwrap()).unwrap();
+        assert_eq!(gap.name, UNSCHEDULED_SHIFT);
+        assert_eq!(gap.started_at, Utc.with_ymd_and_hms(2024, 3, 5, 13, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_overlap_rejected() {
+        let mut cal = calendar();
+        cal.definitions.push(ShiftDefinition {
+            name: "late".into(),
+            start: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+        });
+        assert!(cal.validate().is_err());
+    }
+}
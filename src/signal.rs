@@ -39,23 +39,30 @@
 #![warn(missing_docs)]
 
 use crate::{
+    clock::{Clock, SystemClock},
+    config::SignalGroupConfig,
     error::{PlcError, Result},
+    snapshot::{SignalSnapshot, Snapshots},
+    subscription::{SignalChange, SignalSubscription, SubscribeOptions, Subscribers},
     value::Value,
 };
-use dashmap::DashMap;
-use std::collections::HashMap;
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{
     atomic::{AtomicU64, Ordering},
     Arc,
 };
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, trace, warn};
 
 // Feature-gated imports for enhanced functionality
 #[cfg(feature = "enhanced-monitoring")]
 use std::collections::VecDeque;
 #[cfg(feature = "enhanced-monitoring")]
-use std::time::{Duration, Instant};
+use std::time::Instant;
+use std::time::Duration;
 
 #[cfg(feature = "signal-validation")]
 use crate::validation::Validator;
@@ -173,6 +180,288 @@ impl Default for SignalStats {
     }
 }
 
+/// Restrictions on writes to one signal
+/// 
+/// Enforced by the bus for every write, whichever component makes it:
+/// 
+/// ```yaml
+/// signals:
+///   - name: pump1.speed_setpoint
+///     type: float
+///     write_policy: { min: 0.0, max: 1450.0, max_writes_per_sec: 2 }
+/// ```
+/// 
+/// Numeric values outside `min`..`max` are clamped to the limit. NaN and
+/// infinite floats are rejected unless `allow_non_finite` is set. With
+/// `max_writes_per_sec`, a write that changes the value sooner than
+/// `1 / max_writes_per_sec` after the previous change is rejected; writing
+/// the current value again is always accepted.
+/// 
+/// Two filters keep insignificant updates of noisy inputs away from
+/// history, MQTT and subscribers:
+/// 
+/// ```yaml
+/// write_policy:
+///   min: 0.0
+///   max: 250.0
+///   deadband: { type: percent, value: 0.5 }
+///   max_rate_per_sec: 20.0
+/// ```
+/// 
+/// A numeric write within the `deadband` of the current value is dropped
+/// without error and leaves the value, its timestamp and its subscribers
+/// alone. With `max_rate_per_sec`, a change is limited to that many units
+/// per second since the previous change, so a spike moves the value only
+/// part of the way.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+pub struct WritePolicy {
+    /// Lowest value stored; lower writes are clamped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    
+    /// Highest value stored; higher writes are clamped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    
+    /// Most value changes accepted per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_writes_per_sec: Option<f64>,
+    
+    /// Store NaN and infinite floats instead of rejecting them
+    #[serde(default)]
+    pub allow_non_finite: bool,
+    
+    /// Smallest change stored; smaller changes are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadband: Option<Deadband>,
+    
+    /// Largest change per second; faster changes are slowed to this rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rate_per_sec: Option<f64>,
+}
+
+/// Smallest change of a numeric signal that is stored
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-validation", derive(schemars::JsonSchema))]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Deadband {
+    /// Change in engineering units
+    Absolute(f64),
+    /// Change in percent of the policy's `min`..`max` range
+    Percent(f64),
+}
+
+impl WritePolicy {
+    /// Check the limits of the policy of `signal` are consistent
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::Config`] if a limit is not finite, `min`
+    /// exceeds `max`, the write rate or rate of change is not positive, or
+    /// the deadband is negative or in percent without both limits.
+    pub fn validate(&self, signal: &str) -> Result<()> {
+        if self.min.iter().chain(&self.max).any(|limit| !limit.is_finite()) {
+            return Err(PlcError::Config(format!("Signal '{signal}' write policy limits must be finite")));
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(PlcError::Config(format!(
+                    "Signal '{signal}' write policy min {min} exceeds max {max}"
+                )));
+            }
+        }
+        if self.max_writes_per_sec.is_some_and(|rate| !(rate.is_finite() && rate > 0.0)) {
+            return Err(PlcError::Config(format!(
+                "Signal '{signal}' write policy max_writes_per_sec must be positive"
+            )));
+        }
+        if self.max_rate_per_sec.is_some_and(|rate| !(rate.is_finite() && rate > 0.0)) {
+            return Err(PlcError::Config(format!(
+                "Signal '{signal}' write policy max_rate_per_sec must be positive"
+            )));
+        }
+        match self.deadband {
+            Some(Deadband::Absolute(band) | Deadband::Percent(band)) if !(band.is_finite() && band >= 0.0) => {
+                return Err(PlcError::Config(format!("Signal '{signal}' deadband must not be negative")));
+            }
+            Some(Deadband::Percent(_)) if self.min.is_none() || self.max.is_none() => {
+                return Err(PlcError::Config(format!(
+                    "Signal '{signal}' percent deadband needs both min and max"
+                )));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    
+    /// Whether a change from `current` to `value` is within the deadband
+    fn within_deadband(&self, value: &Value, current: &Value) -> bool {
+        let Some(deadband) = self.deadband else {
+            return false;
+        };
+        let (Some(value), Some(current)) = (numeric(value), numeric(current)) else {
+            return false;
+        };
+        let band = match deadband {
+            Deadband::Absolute(band) => band,
+            Deadband::Percent(percent) => {
+                let (min, max) = (self.min.unwrap_or_default(), self.max.unwrap_or_default());
+                (max - min) * percent / 100.0
+            }
+        };
+        (value - current).abs() < band
+    }
+    
+    /// `value` moved from `current` by at most the rate of change allowed
+    /// in `elapsed`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)] // the step is within the integer change
+    fn limit_rate(&self, value: Value, current: Option<&Value>, elapsed: Option<Duration>) -> Value {
+        let (Some(rate), Some(current), Some(elapsed)) = (self.max_rate_per_sec, current, elapsed) else {
+            return value;
+        };
+        let step = rate * elapsed.as_secs_f64();
+        match (value, current) {
+            (Value::Float(f), Value::Float(c)) if (f - c).abs() > step => Value::Float(c + step.copysign(f - c)),
+            (Value::Integer(i), Value::Integer(c)) if i.saturating_sub(*c).unsigned_abs() as f64 > step => {
+                Value::Integer(c + (step as i64) * i.saturating_sub(*c).signum())
+            }
+            (value, _) => value,
+        }
+    }
+    
+    /// The value to store for `value`, clamped to the limits
+    #[allow(clippy::match_wildcard_for_single_variants)] // more variants with extended-types
+    fn apply(&self, name: &str, value: Value) -> Result<Value> {
+        match value {
+            Value::Float(f) if !f.is_finite() => {
+                if self.allow_non_finite {
+                    Ok(value)
+                } else {
+                    Err(PlcError::Validation(format!("Signal '{name}' rejects non-finite value {f}")))
+                }
+            }
+            Value::Float(f) => Ok(Value::Float(self.clamp(f))),
+            Value::Integer(i) => {
+                let (min, max) = self.integer_limits();
+                Ok(Value::Integer(i.clamp(min, max)))
+            }
+            other => Ok(other),
+        }
+    }
+    
+    fn clamp(&self, f: f64) -> f64 {
+        let f = self.min.map_or(f, |min| f.max(min));
+        self.max.map_or(f, |max| f.min(max))
+    }
+    
+    #[allow(clippy::cast_possible_truncation)] // saturating float to integer casts
+    fn integer_limits(&self) -> (i64, i64) {
+        let min = self.min.map_or(i64::MIN, |min| min.ceil() as i64);
+        let max = self.max.map_or(i64::MAX, |max| max.floor() as i64);
+        (min, max.max(min))
+    }
+    
+    /// Shortest time between two accepted changes
+    fn min_interval(&self) -> Option<Duration> {
+        self.max_writes_per_sec.map(|rate| Duration::from_secs_f64(1.0 / rate))
+    }
+}
+
+/// Value of a numeric signal for deadband comparisons
+#[allow(clippy::match_wildcard_for_single_variants)] // more variants with extended-types
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(f) => Some(*f),
+        #[allow(clippy::cast_precision_loss)]
+        Value::Integer(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+/// Write policy of a signal and the time of the last change it accepted
+#[derive(Debug)]
+struct PolicyState {
+    policy: WritePolicy,
+    last_change: Option<std::time::Instant>,
+}
+
+/// When and by whom the current value of a signal was written
+///
+/// Recorded by the bus for every write. Writers that know when the value
+/// was sampled, such as protocol drivers receiving device timestamps, pass
+/// it with [`SignalBus::set_with_meta`]; other writes are stamped with the
+/// time of the write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteMeta {
+    /// Time the value was sampled, or written if the writer did not say
+    pub timestamp: SystemTime,
+
+    /// Component that wrote the value (e.g. "opcua", "kafka", "mqtt-command")
+    pub source: Option<String>,
+
+    /// Sequence number the source gave the value, if it numbers them
+    pub sequence: Option<u64>,
+}
+
+impl WriteMeta {
+    /// Metadata of a write made now by `source`
+    #[must_use]
+    pub fn from_source(source: Option<&str>) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            source: source.map(str::to_string),
+            sequence: None,
+        }
+    }
+
+    /// Stamp the value with the time it was sampled
+    #[must_use]
+    pub fn at(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Number the value in the sequence of its source
+    #[must_use]
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// The timestamp in milliseconds since the Unix epoch
+    #[must_use]
+    pub fn unix_millis(&self) -> i64 {
+        match self.timestamp.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_millis()).unwrap_or(i64::MAX),
+            Err(e) => i64::try_from(e.duration().as_millis()).map_or(i64::MIN, |before| -before),
+        }
+    }
+}
+
+/// Source recorded for values written by [`SignalBus::force`]
+pub const FORCE_SOURCE: &str = "force";
+
+/// A signal pinned to a value for commissioning, see [`SignalBus::force`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForcedSignal {
+    /// Signal name
+    pub signal: String,
+
+    /// Value the signal is held at
+    pub value: Value,
+
+    /// Who forced the signal
+    pub user: String,
+
+    /// Why the signal was forced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the signal was forced
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
 /// Signal change event for reactive programming
 #[cfg(feature = "signal-events")]
 #[derive(Debug, Clone)]
@@ -186,11 +475,14 @@ pub struct SignalChangeEvent {
     /// New value
     pub new_value: Value,
     
-    /// Timestamp when change occurred
+    /// Timestamp of the new value, see [`WriteMeta::timestamp`]
     pub timestamp: SystemTime,
-    
+
     /// Source of the change (e.g., "block", "protocol", "manual")
     pub source: Option<String>,
+
+    /// Sequence number the source gave the new value
+    pub sequence: Option<u64>,
 }
 
 /// Internal signal data structure
@@ -204,7 +496,13 @@ struct SignalData {
     
     /// Access statistics
     stats: SignalStats,
-    
+
+    /// Read statistics, counted under the shared map lock
+    reads: ReadCounter,
+
+    /// Timestamp and source of the current value
+    meta: WriteMeta,
+
     /// Last validation result
     #[cfg(feature = "signal-validation")]
     last_validation: Option<bool>,
@@ -219,10 +517,50 @@ impl SignalData {
                 created_at: SystemTime::now(),
                 ..Default::default()
             },
+            reads: ReadCounter::default(),
+            meta: WriteMeta::from_source(None),
             #[cfg(feature = "signal-validation")]
             last_validation: None,
         }
     }
+
+    /// Access statistics including reads
+    fn stats(&self) -> SignalStats {
+        let mut stats = self.stats.clone();
+        stats.read_count = self.reads.count.load(Ordering::Relaxed);
+        stats.last_read = match self.reads.last_nanos.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(UNIX_EPOCH + Duration::from_nanos(nanos)),
+        };
+        stats
+    }
+}
+
+/// Read count and time of last read, updated without exclusive access
+#[derive(Debug, Default)]
+struct ReadCounter {
+    count: AtomicU64,
+    /// Nanoseconds since the Unix epoch, 0 if never read
+    last_nanos: AtomicU64,
+}
+
+impl ReadCounter {
+    fn record(&self) {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.last_nanos.store(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Clone for ReadCounter {
+    fn clone(&self) -> Self {
+        Self {
+            count: AtomicU64::new(self.count.load(Ordering::Relaxed)),
+            last_nanos: AtomicU64::new(self.last_nanos.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 // ============================================================================
@@ -298,6 +636,32 @@ pub struct SignalBus {
     /// Performance monitoring data
     #[cfg(feature = "enhanced-monitoring")]
     operation_times: Arc<std::sync::Mutex<VecDeque<(String, Duration)>>>,
+    
+    /// Write policies keyed by signal name
+    policies: Arc<DashMap<String, PolicyState>>,
+    
+    /// Time source for timers and simulations running on this bus
+    clock: Arc<dyn Clock>,
+    
+    /// Change subscriptions, see [`subscribe`](Self::subscribe)
+    subscribers: Arc<Subscribers>,
+    
+    /// Forced signals keyed by signal name
+    forces: Arc<DashMap<String, ForcedSignal>>,
+    
+    /// Signal groups and their snapshots, see
+    /// [`snapshot_group`](Self::snapshot_group)
+    snapshots: Arc<Snapshots>,
+    
+    /// Signals that missed their expected update, see [`crate::staleness`]
+    stale: Arc<DashSet<String>>,
+    
+    /// Target signal of each alias, see [`configure_aliases`](Self::configure_aliases)
+    aliases: Arc<DashMap<String, String>>,
+    
+    /// Schedules of the SCHEDULE blocks, see [`crate::schedule`]
+    #[cfg(feature = "schedules")]
+    schedules: Arc<crate::schedule::Schedules>,
 }
 
 impl SignalBus {
@@ -321,6 +685,16 @@ impl SignalBus {
             
             #[cfg(feature = "enhanced-monitoring")]
             operation_times: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(1000))),
+            
+            policies: Arc::new(DashMap::new()),
+            clock: Arc::new(SystemClock),
+            subscribers: Arc::default(),
+            forces: Arc::new(DashMap::new()),
+            snapshots: Arc::default(),
+            stale: Arc::new(DashSet::new()),
+            aliases: Arc::new(DashMap::new()),
+            #[cfg(feature = "schedules")]
+            schedules: Arc::default(),
         }
     }
     
@@ -344,9 +718,51 @@ impl SignalBus {
             
             #[cfg(feature = "enhanced-monitoring")]
             operation_times: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(1000))),
+            
+            policies: Arc::new(DashMap::new()),
+            clock: Arc::new(SystemClock),
+            subscribers: Arc::default(),
+            forces: Arc::new(DashMap::new()),
+            snapshots: Arc::default(),
+            stale: Arc::new(DashSet::new()),
+            aliases: Arc::new(DashMap::new()),
+            #[cfg(feature = "schedules")]
+            schedules: Arc::default(),
         }
     }
     
+    /// Use `clock` as the time source of this bus
+    /// 
+    /// Clones share the clock of the bus they were made from, so the clock
+    /// is set before the bus is handed to the engine and other components.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Time source of this bus
+    #[must_use]
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+    
+    /// Schedules of the SCHEDULE blocks running on this bus
+    #[cfg(feature = "schedules")]
+    #[must_use]
+    pub fn schedules(&self) -> &Arc<crate::schedule::Schedules> {
+        &self.schedules
+    }
+    
+    /// Current monotonic time of the bus clock
+    /// 
+    /// Timer blocks measure their presets against this instead of
+    /// [`Instant::now`](std::time::Instant::now).
+    #[must_use]
+    pub fn now(&self) -> std::time::Instant {
+        self.clock.now()
+    }
+    
     // ========================================================================
     // CORE SIGNAL OPERATIONS
     // ========================================================================
@@ -379,13 +795,67 @@ impl SignalBus {
     }
     
     /// Set a signal value with source tracking
+    /// 
+    /// The value is subject to the signal's [`WritePolicy`], if it has one.
     pub fn set_with_source(
         &self,
         name: impl AsRef<str>,
         value: Value,
-        _source: Option<&str>,
+        source: Option<&str>,
     ) -> Result<()> {
+        self.set_with_meta(name, value, WriteMeta::from_source(source))
+    }
+    
+    /// Set a signal value with its timestamp, source and sequence number
+    /// 
+    /// Used by writers that know when the value was sampled, so the device
+    /// timestamp is kept instead of the time of the write. The metadata is
+    /// read back with [`get_with_meta`](Self::get_with_meta). The value is
+    /// subject to the signal's [`WritePolicy`], if it has one.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error if the name or value is invalid or the write
+    /// policy rejects the value.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// # use petra::{SignalBus, Value};
+    /// # use petra::signal::WriteMeta;
+    /// # use std::time::{Duration, SystemTime};
+    /// # let bus = SignalBus::new();
+    /// let sampled = SystemTime::now() - Duration::from_secs(2);
+    /// bus.set_with_meta("flow", Value::Float(3.2), WriteMeta::from_source(Some("opcua")).at(sampled))?;
+    /// 
+    /// let (_, meta) = bus.get_with_meta("flow").unwrap();
+    /// assert_eq!(meta.timestamp, sampled);
+    /// # Ok::<(), petra::PlcError>(())
+    /// ```
+    pub fn set_with_meta(&self, name: impl AsRef<str>, value: Value, meta: WriteMeta) -> Result<()> {
+        let name = self.resolve_alias(name.as_ref());
         let name = name.as_ref();
+        if self.is_forced(name) {
+            trace!("Signal '{}' is forced, ignoring write of {:?}", name, value);
+            return Ok(());
+        }
+        if !self.policies.contains_key(name) {
+            return self.store(name, &value, meta);
+        }
+        let current = self.signals.get(name).map(|entry| entry.value.clone());
+        if self.suppresses(name, &value, current.as_ref()) {
+            return Ok(());
+        }
+        let (value, changed_at) = self.police(name, value, current.as_ref())?;
+        self.store(name, &value, meta)?;
+        if let Some(at) = changed_at {
+            self.record_change(name, at);
+        }
+        Ok(())
+    }
+    
+    /// Store a value that already passed the write policy
+    fn store(&self, name: &str, value: &Value, meta: WriteMeta) -> Result<()> {
         let now = SystemTime::now();
         
         #[cfg(feature = "enhanced-monitoring")]
@@ -397,13 +867,16 @@ impl SignalBus {
         // Validate value if validator is set
         #[cfg(feature = "signal-validation")]
         if let Some(validator) = &self.validator {
-            validator.validate(&value).map_err(|e| {
+            validator.validate(value).map_err(|e| {
                 PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
             })?;
         }
         
         #[allow(unused_variables)]
         let old_value = self.signals.get(name).map(|entry| entry.value.clone());
+        #[cfg(feature = "signal-events")]
+        let (timestamp, source, sequence) = (meta.timestamp, meta.source.clone(), meta.sequence);
+        let subscribed = self.subscribers.wants(name).then(|| meta.clone());
         
         // Update or insert signal
         match self.signals.get_mut(name) {
@@ -412,6 +885,7 @@ impl SignalBus {
                 entry.value = value.clone();
                 entry.stats.write_count += 1;
                 entry.stats.last_write = Some(now);
+                entry.meta = meta;
                 
                 #[cfg(feature = "signal-validation")]
                 {
@@ -420,7 +894,8 @@ impl SignalBus {
             }
             None => {
                 // Create new signal
-                let signal_data = SignalData::new(value.clone(), None);
+                let mut signal_data = SignalData::new(value.clone(), None);
+                signal_data.meta = meta;
                 self.signals.insert(name.to_string(), signal_data);
                 debug!("Created new signal: {}", name);
             }
@@ -429,6 +904,10 @@ impl SignalBus {
         // Track global statistics
         self.total_operations.fetch_add(1, Ordering::Relaxed);
         
+        if let Some(meta) = subscribed {
+            self.subscribers.publish(&SignalChange { name: name.to_string(), value: value.clone(), meta });
+        }
+        
         // Record operation time for monitoring
         #[cfg(feature = "enhanced-monitoring")]
         {
@@ -447,9 +926,10 @@ impl SignalBus {
             let event = SignalChangeEvent {
                 signal_name: name.to_string(),
                 old_value,
-                new_value: value,
-                timestamp: now,
-                source: _source.map(|s| s.to_string()),
+                new_value: value.clone(),
+                timestamp,
+                source,
+                sequence,
             };
             
             // Non-blocking send - if no receivers, that's fine
@@ -479,12 +959,11 @@ impl SignalBus {
     /// # Ok::<(), petra::PlcError>(())
     /// ```
     pub fn get(&self, name: impl AsRef<str>) -> Option<Value> {
+        let name = self.resolve_alias(name.as_ref());
         let name = name.as_ref();
         let result = self.signals.get(name).map(|entry| {
             // Update read statistics
-            let mut entry = entry.clone();
-            entry.stats.read_count += 1;
-            entry.stats.last_read = Some(SystemTime::now());
+            entry.reads.record();
             
             entry.value.clone()
         });
@@ -497,6 +976,20 @@ impl SignalBus {
         result
     }
     
+    /// Get a signal value with the timestamp, source and sequence number
+    /// of the write that stored it
+    /// 
+    /// Returns None if the signal doesn't exist.
+    pub fn get_with_meta(&self, name: impl AsRef<str>) -> Option<(Value, WriteMeta)> {
+        let name = self.resolve_alias(name.as_ref());
+        let name = name.as_ref();
+        let result = self.signals.get(name).map(|entry| (entry.value.clone(), entry.meta.clone()));
+        if result.is_some() {
+            self.total_operations.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+    
     /// Get a signal value or return an error if not found
     /// 
     /// This is useful when a signal is expected to exist and missing signals
@@ -544,51 +1037,69 @@ impl SignalBus {
     where
         F: FnOnce(Option<Value>) -> Value,
     {
+        let name = self.resolve_alias(name.as_ref());
         let name = name.as_ref();
         let now = SystemTime::now();
         
         // Validate signal name
         self.validate_signal_name(name)?;
         
-        let new_value = match self.signals.get_mut(name) {
-            Some(mut entry) => {
-                let old_value = entry.value.clone();
-                let new_value = update_fn(Some(old_value.clone()));
-                
-                // Validate new value if validator is set
-                #[cfg(feature = "signal-validation")]
-                if let Some(validator) = &self.validator {
-                    validator.validate(&new_value).map_err(|e| {
-                        PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
-                    })?;
-                }
-                
-                entry.value = new_value.clone();
-                entry.stats.update_count += 1;
-                entry.stats.last_write = Some(now);
-                
-                new_value
+        if let Some(forced) = self.forces.get(name) {
+            trace!("Signal '{}' is forced, ignoring update", name);
+            return Ok(forced.value.clone());
+        }
+        
+        let (new_value, changed_at) = if let Some(mut entry) = self.signals.get_mut(name) {
+            let old_value = entry.value.clone();
+            let new_value = update_fn(Some(old_value.clone()));
+            if self.suppresses(name, &new_value, Some(&old_value)) {
+                return Ok(old_value);
             }
-            None => {
-                let new_value = update_fn(None);
-                
-                // Validate new value if validator is set
-                #[cfg(feature = "signal-validation")]
-                if let Some(validator) = &self.validator {
-                    validator.validate(&new_value).map_err(|e| {
-                        PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
-                    })?;
-                }
-                
-                let signal_data = SignalData::new(new_value.clone(), None);
-                self.signals.insert(name.to_string(), signal_data);
-                debug!("Created new signal via update: {}", name);
-                
-                new_value
+            let (new_value, changed_at) = self.police(name, new_value, Some(&old_value))?;
+            
+            // Validate new value if validator is set
+            #[cfg(feature = "signal-validation")]
+            if let Some(validator) = &self.validator {
+                validator.validate(&new_value).map_err(|e| {
+                    PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
+                })?;
+            }
+            
+            entry.value = new_value.clone();
+            entry.stats.update_count += 1;
+            entry.stats.last_write = Some(now);
+            entry.meta = WriteMeta::from_source(None);
+            
+            (new_value, changed_at)
+        } else {
+            let (new_value, changed_at) = self.police(name, update_fn(None), None)?;
+            
+            // Validate new value if validator is set
+            #[cfg(feature = "signal-validation")]
+            if let Some(validator) = &self.validator {
+                validator.validate(&new_value).map_err(|e| {
+                    PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
+                })?;
             }
+            
+            let signal_data = SignalData::new(new_value.clone(), None);
+            self.signals.insert(name.to_string(), signal_data);
+            debug!("Created new signal via update: {}", name);
+            
+            (new_value, changed_at)
         };
+        if let Some(at) = changed_at {
+            self.record_change(name, at);
+        }
         
         self.total_operations.fetch_add(1, Ordering::Relaxed);
+        if self.subscribers.wants(name) {
+            self.subscribers.publish(&SignalChange {
+                name: name.to_string(),
+                value: new_value.clone(),
+                meta: WriteMeta::from_source(None),
+            });
+        }
         trace!("Updated signal '{}' = {:?}", name, new_value);
         
         Ok(new_value)
@@ -798,6 +1309,88 @@ impl SignalBus {
         Ok(())
     }
     
+    /// Write multiple signals as a single all-or-nothing transaction
+    /// 
+    /// Every name and value is validated before anything is written. If a
+    /// write still fails part way through, the signals already written are
+    /// restored to their previous values, and removed if the transaction
+    /// created them. Either every update takes effect or none does.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns the first validation or write error; no signal is changed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// # use petra::{SignalBus, Value};
+    /// # let bus = SignalBus::new();
+    /// bus.write_transaction([
+    ///     ("setpoint", Value::Float(72.0)),
+    ///     ("mode", Value::Integer(2)),
+    /// ])?;
+    /// 
+    /// // An invalid name rejects the whole transaction
+    /// assert!(bus.write_transaction([("setpoint", Value::Float(80.0)), ("", Value::Bool(true))]).is_err());
+    /// assert_eq!(bus.get("setpoint"), Some(Value::Float(72.0)));
+    /// # Ok::<(), petra::PlcError>(())
+    /// ```
+    pub fn write_transaction<I, K>(&self, updates: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, Value)>,
+        K: AsRef<str>,
+    {
+        let updates: Vec<(String, Value)> = updates
+            .into_iter()
+            .map(|(name, value)| (self.resolve_alias(name.as_ref()).into_owned(), value))
+            .collect();
+        
+        let mut policed = Vec::with_capacity(updates.len());
+        for (name, value) in &updates {
+            let name = name.as_str();
+            self.validate_signal_name(name)?;
+            
+            #[cfg(feature = "signal-validation")]
+            if let Some(validator) = &self.validator {
+                validator.validate(value).map_err(|e| {
+                    PlcError::Validation(format!("Signal '{}' validation failed: {}", name, e))
+                })?;
+            }
+            
+            let current = self.signals.get(name).map(|entry| entry.value.clone());
+            if self.suppresses(name, value, current.as_ref()) {
+                continue;
+            }
+            let (value, changed_at) = self.police(name, value.clone(), current.as_ref())?;
+            policed.push((name, value, changed_at));
+        }
+        
+        let mut applied: Vec<(&str, Option<(Value, WriteMeta)>)> = Vec::with_capacity(updates.len());
+        let mut changes = Vec::new();
+        for (name, value, changed_at) in policed.into_iter().filter(|(name, ..)| !self.is_forced(name)) {
+            let previous = self.get_with_meta(name);
+            if let Err(e) = self.store(name, &value, WriteMeta::from_source(None)) {
+                for (name, previous) in applied.into_iter().rev() {
+                    match previous {
+                        Some((previous, meta)) => {
+                            let _ = self.store(name, &previous, meta);
+                        }
+                        None => {
+                            self.remove(name);
+                        }
+                    }
+                }
+                return Err(e);
+            }
+            applied.push((name, previous));
+            changes.extend(changed_at.map(|at| (name, at)));
+        }
+        for (name, at) in changes {
+            self.record_change(name, at);
+        }
+        Ok(())
+    }
+    
     /// Read multiple signals in a single operation
     /// 
     /// Returns a HashMap with signal names as keys and their values.
@@ -832,6 +1425,152 @@ impl SignalBus {
         results
     }
     
+    // ========================================================================
+    // WRITE POLICIES
+    // ========================================================================
+    
+    /// Enforce `policy` on every later write to `name`
+    /// 
+    /// Replaces any policy the signal already has. The current value is
+    /// left as it is.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::Config`] if the policy is invalid.
+    pub fn set_write_policy(&self, name: impl AsRef<str>, policy: WritePolicy) -> Result<()> {
+        let name = name.as_ref();
+        policy.validate(name)?;
+        self.policies.insert(name.to_string(), PolicyState { policy, last_change: None });
+        Ok(())
+    }
+    
+    /// Write policy of `name`, if it has one
+    #[must_use]
+    pub fn write_policy(&self, name: impl AsRef<str>) -> Option<WritePolicy> {
+        self.policies.get(name.as_ref()).map(|state| state.policy.clone())
+    }
+    
+    /// Lift the write policy of `name`, returning it
+    pub fn remove_write_policy(&self, name: impl AsRef<str>) -> Option<WritePolicy> {
+        self.policies.remove(name.as_ref()).map(|(_, state)| state.policy)
+    }
+    
+    /// Apply the write policy of `name` to a write of `value` over `current`
+    /// 
+    /// Leaves the policy state untouched. Returns the value to store and,
+    /// when the write starts a new rate-limit window, the time to hand to
+    /// [`record_change`](Self::record_change) once it has been stored.
+    fn police(&self, name: &str, value: Value, current: Option<&Value>) -> Result<(Value, Option<std::time::Instant>)> {
+        let Some(state) = self.policies.get(name) else {
+            return Ok((value, None));
+        };
+        let value = state.policy.apply(name, value)?;
+        if current == Some(&value) {
+            return Ok((value, None));
+        }
+        if state.policy.min_interval().is_none() && state.policy.max_rate_per_sec.is_none() {
+            return Ok((value, None));
+        }
+        let now = self.clock.now();
+        let elapsed = state.last_change.map(|last| now.saturating_duration_since(last));
+        if let Some(interval) = state.policy.min_interval() {
+            if elapsed.is_some_and(|elapsed| elapsed < interval) {
+                debug!("Signal '{}' write rejected by rate limit", name);
+                return Err(PlcError::Validation(format!(
+                    "Signal '{name}' accepts at most one change every {interval:?}"
+                )));
+            }
+        }
+        Ok((state.policy.limit_rate(value, current, elapsed), Some(now)))
+    }
+    
+    /// Start the rate-limit window of `name` at `at`
+    fn record_change(&self, name: &str, at: std::time::Instant) {
+        if let Some(mut state) = self.policies.get_mut(name) {
+            state.last_change = Some(at);
+        }
+    }
+    
+    /// Whether the deadband of `name` drops a write of `value` over
+    /// `current`
+    fn suppresses(&self, name: &str, value: &Value, current: Option<&Value>) -> bool {
+        let (Some(state), Some(current)) = (self.policies.get(name), current) else {
+            return false;
+        };
+        let suppressed = state
+            .policy
+            .apply(name, value.clone())
+            .is_ok_and(|value| state.policy.within_deadband(&value, current));
+        if suppressed {
+            trace!("Signal '{}' write of {:?} within deadband of {:?}", name, value, current);
+        }
+        suppressed
+    }
+    
+    // ========================================================================
+    // FORCING
+    // ========================================================================
+    
+    /// Pin `name` to `value` until [`unforce`](Self::unforce)d
+    /// 
+    /// Used during commissioning to hold a signal regardless of what
+    /// blocks, protocols or operators write: later writes are accepted but
+    /// leave the value alone. The value bypasses the signal's write policy;
+    /// an integer forced onto a float signal is stored as a float. Forcing
+    /// a forced signal again replaces its value.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::SignalNotFound`] if the signal does not exist.
+    pub fn force(&self, name: impl AsRef<str>, value: Value, user: &str, reason: Option<&str>) -> Result<ForcedSignal> {
+        let name = self.resolve_alias(name.as_ref());
+        let name = name.as_ref();
+        let current = self.signals.get(name).map(|entry| entry.value.clone());
+        let value = match (current, value) {
+            (None, _) => return Err(PlcError::SignalNotFound(name.to_string())),
+            #[allow(clippy::cast_precision_loss)]
+            (Some(Value::Float(_)), Value::Integer(i)) => Value::Float(i as f64),
+            (Some(_), value) => value,
+        };
+        let forced = ForcedSignal {
+            signal: name.to_string(),
+            value: value.clone(),
+            user: user.to_string(),
+            reason: reason.map(str::to_string),
+            since: chrono::Utc::now(),
+        };
+        self.forces.insert(name.to_string(), forced.clone());
+        self.store(name, &value, WriteMeta::from_source(Some(FORCE_SOURCE)))?;
+        debug!("Forced signal '{}' to {:?}", name, forced.value);
+        Ok(forced)
+    }
+    
+    /// Release a forced signal, returning how it was forced
+    /// 
+    /// The signal keeps the forced value until it is next written.
+    pub fn unforce(&self, name: impl AsRef<str>) -> Option<ForcedSignal> {
+        let name = self.resolve_alias(name.as_ref());
+        let name = name.as_ref();
+        self.forces.remove(name).map(|(_, forced)| {
+            debug!("Released forced signal '{}'", name);
+            forced
+        })
+    }
+    
+    /// Whether `name` is forced
+    #[must_use]
+    pub fn is_forced(&self, name: impl AsRef<str>) -> bool {
+        !self.forces.is_empty() && self.forces.contains_key(name.as_ref())
+    }
+    
+    /// Every forced signal, by name
+    #[must_use]
+    pub fn forced_signals(&self) -> Vec<ForcedSignal> {
+        let mut forced: Vec<ForcedSignal> = self.forces.iter().map(|entry| entry.value().clone()).collect();
+        forced.sort_by(|a, b| a.signal.cmp(&b.signal));
+        forced
+    }
+    
     // ========================================================================
     // SIGNAL METADATA MANAGEMENT
     // ========================================================================
@@ -867,7 +1606,7 @@ impl SignalBus {
     
     /// Get access statistics for a signal
     pub fn get_stats(&self, name: impl AsRef<str>) -> Option<SignalStats> {
-        self.signals.get(name.as_ref()).map(|entry| entry.stats.clone())
+        self.signals.get(name.as_ref()).map(|entry| entry.stats())
     }
     
     // ========================================================================
@@ -881,9 +1620,13 @@ impl SignalBus {
     
     /// Remove a signal from the bus
     /// 
-    /// Returns the removed signal data if it existed.
+    /// Returns the removed signal data if it existed. The signal's write
+    /// policy and force are removed with it.
     pub fn remove(&self, name: impl AsRef<str>) -> Option<(Value, SignalMetadata)> {
         let name = name.as_ref();
+        self.policies.remove(name);
+        self.forces.remove(name);
+        self.stale.remove(name);
         self.signals.remove(name).map(|(_, signal_data)| {
             debug!("Removed signal: {}", name);
             (signal_data.value, signal_data.metadata)
@@ -896,6 +1639,8 @@ impl SignalBus {
     pub fn clear(&self) {
         let count = self.signals.len();
         self.signals.clear();
+        self.forces.clear();
+        self.stale.clear();
         debug!("Cleared {} signals from bus", count);
     }
     
@@ -991,13 +1736,136 @@ impl SignalBus {
                     (
                         signal_data.value.clone(),
                         signal_data.metadata.clone(),
-                        signal_data.stats.clone(),
+                        signal_data.stats(),
                     ),
                 )
             })
             .collect()
     }
     
+    // ========================================================================
+    // CHANGE SUBSCRIPTIONS
+    // ========================================================================
+    
+    /// Subscribe to the changes of the signals matching `pattern`
+    /// 
+    /// `pattern` is a glob where `*` matches any run of characters and `?`
+    /// one character. The subscription is a stream buffering up to 1024
+    /// changes, see [`subscription`](crate::subscription).
+    #[must_use]
+    pub fn subscribe(&self, pattern: &str) -> SignalSubscription {
+        self.subscribe_with([pattern], SubscribeOptions::default())
+    }
+    
+    /// Subscribe to the changes of the signals matching any of `patterns`
+    /// with the buffering and coalescing in `options`
+    #[must_use]
+    pub fn subscribe_with<I, K>(&self, patterns: I, options: SubscribeOptions) -> SignalSubscription
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let patterns = patterns.into_iter().map(|pattern| pattern.as_ref().to_string()).collect();
+        self.subscribers.subscribe(patterns, options)
+    }
+    
+    // ========================================================================
+    // STALENESS
+    // ========================================================================
+    
+    /// Whether `name` missed its expected update interval
+    /// 
+    /// Set by the [`StalenessMonitor`](crate::staleness::StalenessMonitor)
+    /// for signals configured with `update_frequency_ms`.
+    #[must_use]
+    pub fn is_stale(&self, name: impl AsRef<str>) -> bool {
+        self.stale.contains(name.as_ref())
+    }
+    
+    /// Every stale signal, sorted
+    #[must_use]
+    pub fn stale_signals(&self) -> Vec<String> {
+        let mut stale: Vec<String> = self.stale.iter().map(|name| name.key().clone()).collect();
+        stale.sort();
+        stale
+    }
+    
+    /// Flag `name` as stale or updated, returning whether the flag changed
+    pub(crate) fn set_stale(&self, name: &str, stale: bool) -> bool {
+        if stale {
+            self.stale.insert(name.to_string())
+        } else {
+            self.stale.remove(name).is_some()
+        }
+    }
+    
+    // ========================================================================
+    // SIGNAL ALIASES
+    // ========================================================================
+    
+    /// Replace the aliases, keyed by alias
+    /// 
+    /// [`get`](Self::get), [`get_with_meta`](Self::get_with_meta), the
+    /// `set` methods, [`force`](Self::force) and [`unforce`](Self::unforce)
+    /// act on the target signal when given an alias. Aliases never shadow
+    /// a signal of the same name as long as the configuration is valid.
+    pub fn configure_aliases(&self, aliases: &BTreeMap<String, String>) {
+        self.aliases.retain(|alias, _| aliases.contains_key(alias));
+        for (alias, target) in aliases {
+            self.aliases.insert(alias.clone(), target.clone());
+        }
+    }
+    
+    /// Signal `name` refers to: the target of an alias, otherwise `name`
+    #[must_use]
+    pub fn resolve_alias<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.aliases.is_empty() {
+            return Cow::Borrowed(name);
+        }
+        self.aliases.get(name).map_or(Cow::Borrowed(name), |target| Cow::Owned(target.clone()))
+    }
+    
+    /// Every alias with its target, sorted by alias
+    #[must_use]
+    pub fn aliases(&self) -> BTreeMap<String, String> {
+        self.aliases.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect()
+    }
+    
+    // ========================================================================
+    // SIGNAL GROUPS
+    // ========================================================================
+    
+    /// Replace the signal groups [`snapshot_group`](Self::snapshot_group)
+    /// serves
+    /// 
+    /// Snapshots of groups that are kept unchanged stay available.
+    pub fn configure_groups(&self, groups: &[SignalGroupConfig]) {
+        self.snapshots.configure(groups);
+    }
+    
+    /// Names of the configured signal groups, sorted
+    #[must_use]
+    pub fn signal_groups(&self) -> Vec<String> {
+        self.snapshots.groups()
+    }
+    
+    /// Values of `group` taken together at the end of the last scan cycle
+    /// 
+    /// Until the engine completed a cycle the values are read when called,
+    /// see [`snapshot`](crate::snapshot).
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::NotFound`] for an unknown group.
+    pub fn snapshot_group(&self, group: &str) -> Result<SignalSnapshot> {
+        self.snapshots.get(self, group)
+    }
+    
+    /// Snapshot every signal group after scan cycle `scan`
+    pub(crate) fn capture_snapshots(&self, scan: u64) {
+        self.snapshots.capture(self, scan);
+    }
+    
     // ========================================================================
     // EVENT SYSTEM (feature-gated)
     // ========================================================================
@@ -1007,7 +1875,7 @@ impl SignalBus {
     /// Returns a receiver that will receive events for all signal changes.
     /// This enables reactive programming patterns and real-time monitoring.
     #[cfg(feature = "signal-events")]
-    pub fn subscribe(&self) -> broadcast::Receiver<SignalChangeEvent> {
+    pub fn subscribe_events(&self) -> broadcast::Receiver<SignalChangeEvent> {
         self.event_sender.subscribe()
     }
     
@@ -1061,7 +1929,7 @@ impl SignalBus {
         let mut total_conversion_errors = 0u64;
         
         for entry in self.signals.iter() {
-            let signal_stats = &entry.stats;
+            let signal_stats = entry.stats();
             total_reads += signal_stats.read_count;
             total_writes += signal_stats.write_count;
             total_updates += signal_stats.update_count;
@@ -1165,6 +2033,16 @@ impl Clone for SignalBus {
             
             #[cfg(feature = "enhanced-monitoring")]
             operation_times: Arc::clone(&self.operation_times),
+            
+            policies: Arc::clone(&self.policies),
+            clock: Arc::clone(&self.clock),
+            subscribers: Arc::clone(&self.subscribers),
+            forces: Arc::clone(&self.forces),
+            snapshots: Arc::clone(&self.snapshots),
+            stale: Arc::clone(&self.stale),
+            aliases: Arc::clone(&self.aliases),
+            #[cfg(feature = "schedules")]
+            schedules: Arc::clone(&self.schedules),
         }
     }
 }
@@ -1257,6 +2135,18 @@ mod tests {
         }
         
         assert_eq!(bus.get_float("sum").unwrap(), 12.5);
+        
+        // Updates through an alias read and write the target
+        bus.configure_aliases(&BTreeMap::from([("count".to_string(), "counter".to_string())]));
+        let result = bus.update("count", |old| {
+            match old {
+                Some(Value::Integer(n)) => Value::Integer(n + 1),
+                _ => Value::Integer(1),
+            }
+        }).unwrap();
+        assert_eq!(result, Value::Integer(11));
+        assert_eq!(bus.get_integer("counter").unwrap(), 11);
+        assert!(!bus.exists("count"));
     }
     
     #[test]
@@ -1279,6 +2169,12 @@ mod tests {
         assert_eq!(bus.get_float("pressure").unwrap(), 101.3);
         assert_eq!(bus.get_bool("status").unwrap(), true);
         
+        // A transaction with an invalid name writes nothing
+        assert!(bus
+            .write_transaction([("temp1", Value::Float(30.0)), ("", Value::Bool(false))])
+            .is_err());
+        assert_eq!(bus.get_float("temp1").unwrap(), 25.0);
+        
         // Test batch read
         let signal_names = vec!["temp1", "temp2", "pressure", "status", "nonexistent"];
         let values = bus.read_batch(signal_names);
@@ -1379,7 +2275,7 @@ mod tests {
         
         // Test pattern matching
         let plc1_signals = bus.find_signals("plc1.*");
-        assert_eq!(plc1_signals.len(), 4);
+        assert_eq!(plc1_signals.len(), 3);
         
         let temperature_signals = bus.find_signals("*.temperature");
         assert_eq!(temperature_signals.len(), 2);
@@ -1480,11 +2376,14 @@ mod tests {
         ));
         
         // Type mismatch
-        bus.set("string_signal", Value::from("hello")).unwrap();
-        assert!(matches!(
-            bus.get_integer("string_signal"),
-            Err(PlcError::TypeMismatch { .. })
-        ));
+        #[cfg(feature = "extended-types")]
+        {
+            bus.set("string_signal", Value::from("hello")).unwrap();
+            assert!(matches!(
+                bus.get_integer("string_signal"),
+                Err(PlcError::TypeMismatch { .. })
+            ));
+        }
         
         // Overflow protection
         bus.set("large_float", Value::Float(f64::INFINITY)).unwrap();
@@ -1501,6 +2400,7 @@ mod tests {
         // Perform some operations
         bus.set("signal1", Value::Integer(1)).unwrap();
         bus.set("signal2", Value::Integer(2)).unwrap();
+        bus.set("signal2", Value::Integer(3)).unwrap();
         bus.get("signal1");
         bus.get("signal2");
         bus.update("signal1", |old| {
@@ -1550,7 +2450,7 @@ mod tests {
     #[tokio::test]
     async fn test_signal_events() {
         let bus = SignalBus::new();
-        let mut receiver = bus.subscribe();
+        let mut receiver = bus.subscribe_events();
         
         // Set a signal in another task
         let bus_clone = bus.clone();
@@ -1570,4 +2470,173 @@ mod tests {
         assert_eq!(event.source, Some("test".to_string()));
         assert!(event.old_value.is_none());
     }
+    
+    #[test]
+    fn test_write_policy_clamps_and_rejects_non_finite() {
+        let bus = SignalBus::new();
+        bus.set("pump.setpoint", Value::Float(10.0)).unwrap();
+        let policy = WritePolicy { min: Some(0.0), max: Some(100.5), ..Default::default() };
+        bus.set_write_policy("pump.setpoint", policy.clone()).unwrap();
+        bus.set_write_policy("pump.mode", policy).unwrap();
+        
+        bus.set("pump.setpoint", Value::Float(250.0)).unwrap();
+        assert_eq!(bus.get("pump.setpoint"), Some(Value::Float(100.5)));
+        bus.update("pump.setpoint", |_| Value::Float(-3.0)).unwrap();
+        assert_eq!(bus.get("pump.setpoint"), Some(Value::Float(0.0)));
+        bus.write_transaction([("pump.mode", Value::Integer(101))]).unwrap();
+        assert_eq!(bus.get("pump.mode"), Some(Value::Integer(100)));
+        
+        assert!(bus.set("pump.setpoint", Value::Float(f64::NAN)).is_err());
+        assert!(bus.set("pump.setpoint", Value::Float(f64::INFINITY)).is_err());
+        assert_eq!(bus.get("pump.setpoint"), Some(Value::Float(0.0)));
+        
+        let invalid = WritePolicy { min: Some(5.0), max: Some(1.0), ..Default::default() };
+        assert!(bus.set_write_policy("pump.setpoint", invalid).is_err());
+    }
+    
+    #[test]
+    fn test_write_policy_rate_limit() {
+        let clock = Arc::new(crate::clock::SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let policy = WritePolicy { max_writes_per_sec: Some(2.0), ..Default::default() };
+        bus.set_write_policy("valve.position", policy).unwrap();
+        
+        bus.set("valve.position", Value::Float(1.0)).unwrap();
+        assert!(bus.set("valve.position", Value::Float(2.0)).is_err());
+        // Repeating the current value is not a change
+        bus.set("valve.position", Value::Float(1.0)).unwrap();
+        assert!(bus.write_transaction([("valve.position", Value::Float(2.0))]).is_err());
+        
+        clock.advance(Duration::from_millis(500));
+        bus.set("valve.position", Value::Float(2.0)).unwrap();
+        assert_eq!(bus.get("valve.position"), Some(Value::Float(2.0)));
+    }
+
+    #[test]
+    fn test_rejected_transaction_keeps_rate_limit_window() {
+        let clock = Arc::new(crate::clock::SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let policy = WritePolicy { max_writes_per_sec: Some(2.0), ..Default::default() };
+        bus.set_write_policy("valve.position", policy.clone()).unwrap();
+        bus.set_write_policy("pump.speed", policy).unwrap();
+        bus.set("pump.speed", Value::Float(1.0)).unwrap();
+
+        assert!(bus
+            .write_transaction([("valve.position", Value::Float(1.0)), ("pump.speed", Value::Float(2.0))])
+            .is_err());
+        assert_eq!(bus.get("valve.position"), None);
+        // The rejected transaction did not start the window of item 1
+        bus.set("valve.position", Value::Float(1.0)).unwrap();
+        assert_eq!(bus.get("valve.position"), Some(Value::Float(1.0)));
+    }
+
+    #[test]
+    fn test_write_policy_filters() {
+        let clock = Arc::new(crate::clock::SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        let deadband = WritePolicy {
+            min: Some(0.0),
+            max: Some(200.0),
+            deadband: Some(Deadband::Percent(1.0)),
+            ..Default::default()
+        };
+        bus.set_write_policy("tank.level", deadband).unwrap();
+        let mut changes = bus.subscribe("tank.level");
+        
+        bus.set("tank.level", Value::Float(50.0)).unwrap();
+        bus.set("tank.level", Value::Float(51.5)).unwrap();
+        bus.update("tank.level", |_| Value::Float(48.5)).unwrap();
+        assert_eq!(bus.get("tank.level"), Some(Value::Float(50.0)));
+        // Drift adds up against the stored value
+        bus.set("tank.level", Value::Float(52.0)).unwrap();
+        assert_eq!(bus.get("tank.level"), Some(Value::Float(52.0)));
+        let values: Vec<Value> = std::iter::from_fn(|| changes.try_next()).map(|change| change.value).collect();
+        assert_eq!(values, [Value::Float(50.0), Value::Float(52.0)]);
+        
+        let rate = WritePolicy { max_rate_per_sec: Some(10.0), ..Default::default() };
+        bus.set_write_policy("valve.position", rate).unwrap();
+        bus.set("valve.position", Value::Integer(0)).unwrap();
+        clock.advance(Duration::from_millis(500));
+        bus.set("valve.position", Value::Integer(100)).unwrap();
+        assert_eq!(bus.get("valve.position"), Some(Value::Integer(5)));
+        clock.advance(Duration::from_secs(10));
+        bus.set("valve.position", Value::Integer(100)).unwrap();
+        assert_eq!(bus.get("valve.position"), Some(Value::Integer(100)));
+        
+        let invalid = WritePolicy { deadband: Some(Deadband::Percent(1.0)), ..Default::default() };
+        assert!(bus.set_write_policy("flow", invalid).is_err());
+    }
+    
+    #[test]
+    fn test_write_meta() {
+        let bus = SignalBus::new();
+        let sampled = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let meta = WriteMeta::from_source(Some("opcua")).at(sampled).with_sequence(7);
+        bus.set_with_meta("line.speed", Value::Float(1.5), meta.clone()).unwrap();
+        assert_eq!(bus.get_with_meta("line.speed"), Some((Value::Float(1.5), meta)));
+        assert_eq!(bus.get_with_meta("line.speed").unwrap().1.unix_millis(), 1_700_000_000_123);
+        
+        // A plain write is stamped with the time of the write
+        bus.set_with_source("line.speed", Value::Float(2.0), Some("hmi")).unwrap();
+        let (_, meta) = bus.get_with_meta("line.speed").unwrap();
+        assert!(meta.timestamp > sampled);
+        assert_eq!((meta.source.as_deref(), meta.sequence), (Some("hmi"), None));
+        
+        // A rejected transaction keeps the metadata
+        assert!(bus.write_transaction([("line.speed", Value::Float(3.0)), ("", Value::Bool(true))]).is_err());
+        assert_eq!(bus.get_with_meta("line.speed").unwrap().1, meta);
+        assert!(bus.get_with_meta("line.missing").is_none());
+    }
+    
+    #[test]
+    fn test_forced_signal_ignores_writes() {
+        let bus = SignalBus::new();
+        bus.set("valve.open", Value::Bool(false)).unwrap();
+        bus.set("tank.level", Value::Float(1.0)).unwrap();
+        assert!(bus.force("missing", Value::Bool(true), "commissioning", None).is_err());
+        
+        let forced = bus.force("tank.level", Value::Integer(3), "alice", Some("loop check")).unwrap();
+        assert_eq!(forced.value, Value::Float(3.0));
+        bus.force("valve.open", Value::Bool(true), "alice", None).unwrap();
+        
+        bus.set("tank.level", Value::Float(7.0)).unwrap();
+        bus.update("tank.level", |_| Value::Float(8.0)).unwrap();
+        bus.write_transaction([("tank.level", Value::Float(9.0)), ("valve.open", Value::Bool(false))]).unwrap();
+        assert_eq!(bus.get("tank.level"), Some(Value::Float(3.0)));
+        assert_eq!(bus.get("valve.open"), Some(Value::Bool(true)));
+        assert_eq!(bus.get_with_meta("tank.level").unwrap().1.source.as_deref(), Some(FORCE_SOURCE));
+        
+        let names: Vec<String> = bus.forced_signals().into_iter().map(|f| f.signal).collect();
+        assert_eq!(names, ["tank.level", "valve.open"]);
+        assert_eq!(bus.unforce("tank.level").unwrap().user, "alice");
+        assert!(!bus.is_forced("tank.level"));
+        bus.set("tank.level", Value::Float(7.0)).unwrap();
+        assert_eq!(bus.get("tank.level"), Some(Value::Float(7.0)));
+    }
+    
+    #[test]
+    fn test_aliases_resolve_to_target() {
+        let bus = SignalBus::new();
+        bus.set("tank1.level", Value::Float(1.0)).unwrap();
+        bus.configure_aliases(&BTreeMap::from([("feed.level".to_string(), "tank1.level".to_string())]));
+        
+        assert_eq!(bus.get("feed.level"), Some(Value::Float(1.0)));
+        bus.set("feed.level", Value::Float(2.0)).unwrap();
+        assert_eq!(bus.get("tank1.level"), Some(Value::Float(2.0)));
+        assert!(!bus.exists("feed.level"));
+        bus.force("feed.level", Value::Float(5.0), "alice", None).unwrap();
+        assert!(bus.is_forced("tank1.level"));
+        assert!(bus.unforce("feed.level").is_some());
+        
+        // Transactions write and police the target
+        bus.set_write_policy("tank1.level", WritePolicy { max: Some(10.0), ..Default::default() }).unwrap();
+        bus.write_transaction([("feed.level", Value::Float(12.0)), ("feed.flow", Value::Float(0.5))]).unwrap();
+        assert_eq!(bus.get("tank1.level"), Some(Value::Float(10.0)));
+        assert!(!bus.exists("feed.level"));
+        assert_eq!(bus.get("feed.flow"), Some(Value::Float(0.5)));
+        
+        bus.configure_aliases(&BTreeMap::new());
+        assert!(bus.aliases().is_empty());
+        assert_eq!(bus.get("feed.level"), None);
+    }
 }
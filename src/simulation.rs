@@ -0,0 +1,422 @@
+//! Simulated signal overrides for partial hardware-in-the-loop testing
+//!
+//! The `simulation` section lists signals that are generated instead of
+//! read from field devices. It only takes effect when the active environment
+//! profile (`--profile`, or the `PETRA_PROFILE` environment variable) is one
+//! of its `profiles`, so the same configuration runs against real hardware
+//! in production and against a partial rig in the lab:
+//!
+//! ```yaml
+//! simulation:
+//!   profiles: [sim]
+//!   update_interval_ms: 100
+//!   signals:
+//!     - { signal: tank.level, pattern: sine, offset: 50.0, amplitude: 20.0, period_ms: 60000 }
+//!     - { signal: line1.count, pattern: ramp, min: 0.0, max: 1000.0, period_ms: 600000 }
+//!     - { signal: pump.running, pattern: square, period_ms: 10000 }
+//!     - { signal: ambient.temp, pattern: random, min: 19.5, max: 20.5 }
+//!     - { signal: door.closed, pattern: constant, value: true }
+//! ```
+//!
+//! When active, [`SimulationConfig::apply`] removes the simulated signals
+//! from the protocol mappings that bind individual signals (Modbus
+//! registers, OPC-UA subscriptions, DNP3 master points and `EtherCAT` PDO
+//! entries), leaving every other mapping in place. S7 data areas are bound
+//! by prefix and are not changed. The [`Simulator`] then writes the
+//! generated values, converted to each signal's current type: booleans are
+//! true for non-zero values and integers are rounded.
+
+use crate::config::ProtocolConfig;
+use crate::scan_budget::{measure, Subsystem};
+use crate::{PlcError, Result, SignalBus, Value};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::f64::consts::TAU;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Environment variable naming the active profile when `--profile` is not given
+pub const PROFILE_ENV: &str = "PETRA_PROFILE";
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Simulated signals and the profiles that enable them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// Profiles in which the overrides are active
+    #[serde(default = "default_profiles")]
+    pub profiles: Vec<String>,
+
+    /// How often simulated values are written
+    #[serde(default = "default_update_interval_ms")]
+    pub update_interval_ms: u64,
+
+    /// Signals to generate
+    pub signals: Vec<SimulatedSignal>,
+}
+
+/// A signal and the pattern that generates it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedSignal {
+    /// Signal to override
+    pub signal: String,
+
+    /// Generated waveform
+    #[serde(flatten)]
+    pub pattern: Pattern,
+}
+
+/// Waveforms a simulated signal can follow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum Pattern {
+    /// Fixed value
+    Constant {
+        /// Value in YAML form, e.g. `true` or `42.0`
+        value: serde_yaml::Value,
+    },
+    /// `offset + amplitude * sin(2π t / period)`
+    Sine {
+        /// Centre value
+        #[serde(default)]
+        offset: f64,
+        /// Peak deviation from `offset`
+        amplitude: f64,
+        /// Length of one cycle
+        period_ms: u64,
+    },
+    /// Rises linearly from `min` to `max` and jumps back every period
+    Ramp {
+        /// Value at the start of each period
+        min: f64,
+        /// Value approached at the end of each period
+        max: f64,
+        /// Length of one ramp
+        period_ms: u64,
+    },
+    /// Alternates between `high` and `low`
+    Square {
+        /// Value in the first part of each period
+        #[serde(default = "default_high")]
+        high: f64,
+        /// Value in the rest of each period
+        #[serde(default)]
+        low: f64,
+        /// Length of one cycle
+        period_ms: u64,
+        /// Fraction of the period spent at `high`
+        #[serde(default = "default_duty_cycle")]
+        duty_cycle: f64,
+    },
+    /// Uniformly distributed values between `min` and `max`
+    Random {
+        /// Lower bound
+        min: f64,
+        /// Upper bound
+        max: f64,
+    },
+}
+
+fn default_profiles() -> Vec<String> {
+    vec!["sim".to_string()]
+}
+
+const fn default_update_interval_ms() -> u64 {
+    100
+}
+
+const fn default_high() -> f64 {
+    1.0
+}
+
+const fn default_duty_cycle() -> f64 {
+    0.5
+}
+
+impl SimulationConfig {
+    /// Validate the simulated signals
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for duplicate signals, zero periods,
+    /// inverted ranges or a duty cycle outside 0..=1.
+    pub fn validate(&self) -> Result<()> {
+        if self.update_interval_ms == 0 {
+            return Err(PlcError::Config(
+                "Simulation update_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        let mut signals = HashSet::new();
+        for simulated in &self.signals {
+            let name = &simulated.signal;
+            if !signals.insert(name) {
+                return Err(PlcError::Config(format!("Signal '{name}' is simulated more than once")));
+            }
+            let valid = match &simulated.pattern {
+                Pattern::Constant { value } => crate::value::from_yaml_value(value.clone()).is_ok(),
+                Pattern::Sine { period_ms, .. } => *period_ms > 0,
+                Pattern::Ramp { min, max, period_ms } => *period_ms > 0 && min <= max,
+                Pattern::Square { period_ms, duty_cycle, .. } => {
+                    *period_ms > 0 && (0.0..=1.0).contains(duty_cycle)
+                }
+                Pattern::Random { min, max } => min <= max,
+            };
+            if !valid {
+                return Err(PlcError::Config(format!(
+                    "Simulated signal '{name}' has an invalid {:?} pattern",
+                    simulated.pattern
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the overrides apply under `profile`
+    #[must_use]
+    pub fn is_active(&self, profile: Option<&str>) -> bool {
+        profile.is_some_and(|profile| self.profiles.iter().any(|p| p == profile))
+    }
+
+    /// Unbind the simulated signals from per-signal protocol mappings
+    ///
+    /// Returns the number of mappings removed.
+    #[cfg_attr(
+        not(any(
+            feature = "s7-support",
+            feature = "modbus-support",
+            feature = "opcua-support",
+            feature = "dnp3-support",
+            feature = "ethercat",
+            feature = "profinet",
+            feature = "coap",
+            feature = "http"
+        )),
+        allow(unused_variables, unused_mut)
+    )]
+    pub fn apply(&self, protocols: &mut ProtocolConfig) -> usize {
+        let simulated: HashSet<&str> = self.signals.iter().map(|s| s.signal.as_str()).collect();
+        let mut removed = 0;
+
+        #[cfg(feature = "s7-support")]
+        if let Some(s7) = &mut protocols.s7 {
+            for connection in &mut s7.connections {
+                removed += unbind(&mut connection.tags, &simulated, |t| &t.signal);
+            }
+        }
+
+        #[cfg(feature = "modbus-support")]
+        if let Some(modbus) = &mut protocols.modbus {
+            for connection in &mut modbus.connections {
+                removed += unbind(&mut connection.registers, &simulated, |r| &r.signal);
+            }
+        }
+
+        #[cfg(feature = "opcua-support")]
+        if let Some(opcua) = &mut protocols.opcua {
+            removed += unbind(&mut opcua.subscriptions, &simulated, |s| &s.signal);
+        }
+
+        #[cfg(feature = "dnp3-support")]
+        if let Some(dnp3) = &mut protocols.dnp3 {
+            for master in &mut dnp3.masters {
+                removed += unbind(&mut master.points, &simulated, |p| &p.signal);
+            }
+        }
+
+        #[cfg(feature = "ethercat")]
+        if let Some(ethercat) = &mut protocols.ethercat {
+            for slave in &mut ethercat.slaves {
+                for data in [&mut slave.outputs, &mut slave.inputs].into_iter().flatten() {
+                    removed += unbind(&mut data.entries, &simulated, |e| &e.signal);
+                }
+            }
+        }
+
+        #[cfg(feature = "profinet")]
+        if let Some(profinet) = &mut protocols.profinet {
+            for slot in &mut profinet.slots {
+                removed += unbind(&mut slot.inputs, &simulated, |e| &e.signal);
+                removed += unbind(&mut slot.outputs, &simulated, |e| &e.signal);
+            }
+        }
+
+        #[cfg(feature = "coap")]
+        if let Some(coap) = &mut protocols.coap {
+            for device in &mut coap.devices {
+                removed += unbind(&mut device.resources, &simulated, |r| &r.signal);
+            }
+        }
+
+        #[cfg(feature = "http")]
+        if let Some(http) = &mut protocols.http {
+            for endpoint in &mut http.endpoints {
+                removed += unbind(&mut endpoint.values, &simulated, |v| &v.signal);
+            }
+        }
+
+        removed
+    }
+}
+
+/// Drop the mappings bound to simulated signals, returning how many were dropped
+#[cfg(any(
+    feature = "s7-support",
+    feature = "modbus-support",
+    feature = "opcua-support",
+    feature = "dnp3-support",
+    feature = "ethercat",
+    feature = "profinet",
+    feature = "coap",
+    feature = "http"
+))]
+fn unbind<T>(mappings: &mut Vec<T>, simulated: &HashSet<&str>, signal: impl Fn(&T) -> &String) -> usize {
+    let before = mappings.len();
+    mappings.retain(|m| !simulated.contains(signal(m).as_str()));
+    before - mappings.len()
+}
+
+impl Pattern {
+    /// Value of the waveform `elapsed` after the simulation started
+    fn sample(&self, elapsed: Duration, rng: &mut impl Rng) -> Value {
+        let phase = |period_ms: u64| {
+            #[allow(clippy::cast_precision_loss)]
+            let period = period_ms as f64;
+            (elapsed.as_secs_f64() * 1000.0 % period) / period
+        };
+        let value = match self {
+            Self::Constant { value } => {
+                return crate::value::from_yaml_value(value.clone()).unwrap_or(Value::Float(0.0));
+            }
+            Self::Sine { offset, amplitude, period_ms } => offset + amplitude * (TAU * phase(*period_ms)).sin(),
+            Self::Ramp { min, max, period_ms } => min + (max - min) * phase(*period_ms),
+            Self::Square { high, low, period_ms, duty_cycle } => {
+                if phase(*period_ms) < *duty_cycle {
+                    *high
+                } else {
+                    *low
+                }
+            }
+            Self::Random { min, max } => rng.gen_range(*min..=*max),
+        };
+        Value::Float(value)
+    }
+}
+
+/// Convert a generated value to the type the signal already holds
+fn coerce(value: Value, current: Option<&Value>) -> Value {
+    let Some(v) = value.as_float() else {
+        return value;
+    };
+    match current {
+        Some(Value::Bool(_)) => Value::Bool(v != 0.0),
+        #[allow(clippy::cast_possible_truncation)]
+        Some(Value::Integer(_)) => Value::Integer(v.round() as i64),
+        _ => value,
+    }
+}
+
+// ============================================================================
+// SIMULATOR
+// ============================================================================
+
+/// Writes the simulated signals to the bus
+///
+/// Patterns advance with the bus clock, so a simulated clock speeds them
+/// up along with the timer blocks.
+pub struct Simulator {
+    config: SimulationConfig,
+    start: OnceLock<Instant>,
+}
+
+impl Simulator {
+    /// Create a simulator starting at its first update
+    #[must_use]
+    pub fn new(config: SimulationConfig) -> Self {
+        Self {
+            config,
+            start: OnceLock::new(),
+        }
+    }
+
+    /// Write every simulated signal once
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a signal cannot be written.
+    pub fn update(&self, bus: &SignalBus) -> Result<()> {
+        let now = bus.now();
+        let start = *self.start.get_or_init(|| now);
+        self.update_at(bus, now.saturating_duration_since(start))
+    }
+
+    fn update_at(&self, bus: &SignalBus, elapsed: Duration) -> Result<()> {
+        let mut rng = rand::thread_rng();
+        for simulated in &self.config.signals {
+            let value = simulated.pattern.sample(elapsed, &mut rng);
+            let value = coerce(value, bus.get(&simulated.signal).as_ref());
+            bus.set(&simulated.signal, value)?;
+        }
+        Ok(())
+    }
+
+    /// Update the signals every `update_interval_ms` until the task is cancelled
+    pub async fn run(self, bus: SignalBus) {
+        info!("Simulating {} signals", self.config.signals.len());
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.update_interval_ms));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            if let Err(e) = measure(Subsystem::Protocols, || self.update(&bus)) {
+                warn!("Simulation update failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_follow_signal_types_and_profile() {
+        let config: SimulationConfig = serde_yaml::from_str(
+            r"
+signals:
+  - { signal: tank.level, pattern: sine, offset: 50.0, amplitude: 20.0, period_ms: 1000 }
+  - { signal: line1.count, pattern: ramp, min: 0.0, max: 100.0, period_ms: 1000 }
+  - { signal: pump.running, pattern: square, period_ms: 1000, duty_cycle: 0.25 }
+  - { signal: ambient.temp, pattern: random, min: 19.5, max: 20.5 }
+  - { signal: door.closed, pattern: constant, value: true }
+",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert!(config.is_active(Some("sim")));
+        assert!(!config.is_active(Some("production")));
+        assert!(!config.is_active(None));
+
+        let bus = SignalBus::new();
+        bus.set("line1.count", Value::Integer(0)).unwrap();
+        bus.set("pump.running", Value::Bool(false)).unwrap();
+        let simulator = Simulator::new(config);
+
+        simulator.update_at(&bus, Duration::from_millis(250)).unwrap();
+        assert_eq!(bus.get("tank.level"), Some(Value::Float(70.0)));
+        assert_eq!(bus.get("line1.count"), Some(Value::Integer(25)));
+        assert_eq!(bus.get("pump.running"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("door.closed"), Some(Value::Bool(true)));
+        let temp = bus.get_float("ambient.temp").unwrap();
+        assert!((19.5..=20.5).contains(&temp));
+
+        simulator.update_at(&bus, Duration::from_millis(1100)).unwrap();
+        assert_eq!(bus.get("pump.running"), Some(Value::Bool(true)));
+
+        let invalid: SimulationConfig =
+            serde_yaml::from_str("signals: [{ signal: x, pattern: ramp, min: 2.0, max: 1.0, period_ms: 10 }]").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
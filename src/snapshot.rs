@@ -0,0 +1,151 @@
+// src/snapshot.rs
+//! Signal group snapshots
+//!
+//! Signals written one at a time can be read half-updated: a report reading
+//! a machine's position, force and cycle count while the scan is writing
+//! them may pair the new position with the old force. Named groups in the
+//! `signal_groups` configuration section avoid that:
+//!
+//! ```yaml
+//! signal_groups:
+//!   - name: press1
+//!     signals: [press1.position, press1.force, press1.cycle_count]
+//! ```
+//!
+//! At the end of every main scan cycle the engine copies each group's
+//! values, under the same lock that orders the write-backs of scan tasks,
+//! so [`SignalBus::snapshot_group`] returns values that all belong to one
+//! cycle. Before the first cycle, or on a bus without an engine, a snapshot is
+//! read from the bus when requested and carries no scan number.
+//!
+//! [`SignalBus::snapshot_group`]: crate::signal::SignalBus::snapshot_group
+
+use crate::config::SignalGroupConfig;
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Values of one signal group taken at the same point in the scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalSnapshot {
+    /// Group name
+    pub group: String,
+    /// Scan cycle the values were taken after, if taken by the engine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<u64>,
+    /// When the values were taken
+    pub taken_at: DateTime<Utc>,
+    /// Value of each group member present on the bus
+    pub values: BTreeMap<String, Value>,
+}
+
+impl SignalSnapshot {
+    fn take(bus: &SignalBus, group: &str, signals: &[String], scan: Option<u64>) -> Self {
+        Self {
+            group: group.to_string(),
+            scan,
+            taken_at: Utc::now(),
+            values: signals.iter().filter_map(|name| Some((name.clone(), bus.get(name)?))).collect(),
+        }
+    }
+}
+
+/// Group definitions and latest snapshots of a bus and its clones
+#[derive(Debug, Default)]
+pub(crate) struct Snapshots {
+    groups: RwLock<BTreeMap<String, Vec<String>>>,
+    latest: RwLock<HashMap<String, Arc<SignalSnapshot>>>,
+}
+
+impl Snapshots {
+    /// Replace the group definitions, dropping the snapshots of groups that
+    /// were removed or changed
+    pub(crate) fn configure(&self, groups: &[SignalGroupConfig]) {
+        let groups: BTreeMap<String, Vec<String>> =
+            groups.iter().map(|group| (group.name.clone(), group.signals.clone())).collect();
+        let mut current = self.groups.write().unwrap_or_else(PoisonError::into_inner);
+        let mut latest = self.latest.write().unwrap_or_else(PoisonError::into_inner);
+        latest.retain(|name, _| groups.get(name) == current.get(name));
+        *current = groups;
+    }
+
+    /// Names of the configured groups
+    pub(crate) fn groups(&self) -> Vec<String> {
+        self.groups.read().unwrap_or_else(PoisonError::into_inner).keys().cloned().collect()
+    }
+
+    /// Take a snapshot of every group after scan `scan`
+    pub(crate) fn capture(&self, bus: &SignalBus, scan: u64) {
+        let groups = self.groups.read().unwrap_or_else(PoisonError::into_inner);
+        if groups.is_empty() {
+            return;
+        }
+        let taken: Vec<(String, Arc<SignalSnapshot>)> = groups
+            .iter()
+            .map(|(name, signals)| (name.clone(), Arc::new(SignalSnapshot::take(bus, name, signals, Some(scan)))))
+            .collect();
+        self.latest.write().unwrap_or_else(PoisonError::into_inner).extend(taken);
+    }
+
+    /// Latest snapshot of `group`, or one read from the bus now if none was
+    /// taken yet
+    pub(crate) fn get(&self, bus: &SignalBus, group: &str) -> Result<SignalSnapshot> {
+        if let Some(snapshot) = self.latest.read().unwrap_or_else(PoisonError::into_inner).get(group) {
+            return Ok(SignalSnapshot::clone(snapshot));
+        }
+        let groups = self.groups.read().unwrap_or_else(PoisonError::into_inner);
+        let signals = groups.get(group).ok_or_else(|| PlcError::NotFound(format!("Unknown signal group '{group}'")))?;
+        Ok(SignalSnapshot::take(bus, group, signals, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(name: &str, signals: &[&str]) -> SignalGroupConfig {
+        SignalGroupConfig { name: name.to_string(), signals: signals.iter().map(|s| (*s).to_string()).collect() }
+    }
+
+    #[test]
+    fn test_snapshot_holds_values_of_last_capture() {
+        let bus = SignalBus::new();
+        bus.configure_groups(&[group("press1", &["press1.position", "press1.force"])]);
+        bus.set("press1.position", Value::Float(10.0)).unwrap();
+        bus.set("press1.force", Value::Float(1.5)).unwrap();
+
+        // Read from the bus until the engine captured the group
+        let live = bus.snapshot_group("press1").unwrap();
+        assert_eq!(live.scan, None);
+        assert_eq!(live.values["press1.position"], Value::Float(10.0));
+
+        bus.capture_snapshots(7);
+        bus.set("press1.position", Value::Float(11.0)).unwrap();
+        let snapshot = bus.snapshot_group("press1").unwrap();
+        assert_eq!(snapshot.scan, Some(7));
+        assert_eq!(snapshot.values["press1.position"], Value::Float(10.0));
+        assert_eq!(snapshot.values["press1.force"], Value::Float(1.5));
+
+        assert!(matches!(bus.snapshot_group("press2"), Err(PlcError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_reconfigure_drops_changed_groups() {
+        let bus = SignalBus::new();
+        bus.set("a", Value::Integer(1)).unwrap();
+        bus.set("b", Value::Integer(2)).unwrap();
+        bus.configure_groups(&[group("one", &["a"]), group("two", &["b"])]);
+        bus.capture_snapshots(1);
+
+        bus.configure_groups(&[group("one", &["a"]), group("two", &["a", "b"])]);
+        assert_eq!(bus.snapshot_group("one").unwrap().scan, Some(1));
+        let two = bus.snapshot_group("two").unwrap();
+        assert_eq!(two.scan, None);
+        assert_eq!(two.values.len(), 2);
+        assert_eq!(bus.signal_groups(), ["one", "two"]);
+    }
+}
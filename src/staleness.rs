@@ -0,0 +1,226 @@
+// src/staleness.rs
+//! Stale signal detection
+//!
+//! A signal with `update_frequency_ms` in its configuration is expected to
+//! be written at least that often, typically by the protocol driver polling
+//! it. When a signal goes longer than that without a write, rewrites of the
+//! same value included, the monitor flags it as stale:
+//!
+//! - [`SignalBus::is_stale`] reports it and the web API shows its quality
+//!   as `uncertain`
+//! - the boolean signal `signal.<name>.stale` is set
+//! - the count is reported in engine statistics, the Prometheus metrics
+//!   and `/health`
+//!
+//! The next write clears the flag. Monitoring starts when the engine
+//! starts, so every signal gets a full interval before it can go stale.
+//!
+//! [`SignalBus::is_stale`]: crate::signal::SignalBus::is_stale
+
+use crate::config::Config;
+use crate::signal::SignalBus;
+use crate::subscription::{Coalesce, SignalSubscription, SubscribeOptions};
+use crate::value::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Name of the signal flagging `signal` as stale
+#[must_use]
+pub fn stale_signal(signal: &str) -> String {
+    format!("signal.{signal}.stale")
+}
+
+/// Shortest time between two checks
+const MIN_CHECK_PERIOD: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Default)]
+struct State {
+    /// Longest time between writes of each monitored signal
+    expected: BTreeMap<String, Duration>,
+    /// Writes to the monitored signals
+    changes: Option<SignalSubscription>,
+    /// Bus time of the last write of each monitored signal
+    last_update: HashMap<String, Instant>,
+}
+
+/// Monitor of the expected update intervals of a bus's signals
+///
+/// Cloning yields another handle to the same monitor.
+#[derive(Debug, Clone)]
+pub struct StalenessMonitor {
+    bus: SignalBus,
+    state: Arc<Mutex<State>>,
+}
+
+impl StalenessMonitor {
+    /// Monitor the signals of `config` that have an `update_frequency_ms`
+    #[must_use]
+    pub fn new(config: &Config, bus: SignalBus) -> Self {
+        let monitor = Self { bus, state: Arc::default() };
+        monitor.configure(config);
+        monitor
+    }
+
+    /// Follow a reloaded configuration
+    ///
+    /// Signals that stay monitored keep their last update; signals no
+    /// longer monitored lose their stale flag and signal.
+    pub fn configure(&self, config: &Config) {
+        let expected: BTreeMap<String, Duration> = config
+            .signals
+            .iter()
+            .filter_map(|signal| Some((signal.name.clone(), Duration::from_millis(signal.update_frequency_ms?))))
+            .collect();
+        let mut state = self.lock();
+        for name in state.expected.keys().filter(|name| !expected.contains_key(*name)) {
+            self.bus.set_stale(name, false);
+            self.bus.remove(stale_signal(name));
+        }
+        let now = self.bus.now();
+        state.last_update.retain(|name, _| expected.contains_key(name));
+        for name in expected.keys() {
+            if !state.last_update.contains_key(name) {
+                state.last_update.insert(name.clone(), now);
+                self.publish(name, false);
+            }
+        }
+        state.changes = (!expected.is_empty()).then(|| {
+            self.bus.subscribe_with(expected.keys(), SubscribeOptions::default().coalesce(Coalesce::Latest))
+        });
+        state.expected = expected;
+    }
+
+    /// Restart every signal's interval, so time spent before the engine
+    /// started does not count
+    pub fn restart(&self) {
+        let mut state = self.lock();
+        if let Some(changes) = state.changes.as_mut() {
+            while changes.try_next().is_some() {}
+        }
+        let now = self.bus.now();
+        for last in state.last_update.values_mut() {
+            *last = now;
+        }
+    }
+
+    /// Account for the writes since the last check and flag the signals
+    /// that missed their interval, returning how many are stale
+    pub fn check(&self) -> usize {
+        let mut state = self.lock();
+        let now = self.bus.now();
+        let mut written = Vec::new();
+        if let Some(changes) = state.changes.as_mut() {
+            while let Some(change) = changes.try_next() {
+                written.push(change.name);
+            }
+        }
+        for name in written {
+            state.last_update.insert(name, now);
+        }
+
+        let mut count = 0;
+        for (name, expected) in &state.expected {
+            let since = state.last_update.get(name).map_or(Duration::ZERO, |last| now.saturating_duration_since(*last));
+            let is_stale = since > *expected;
+            count += usize::from(is_stale);
+            if self.bus.set_stale(name, is_stale) {
+                if is_stale {
+                    warn!("Signal '{}' is stale: no update for {:?}, expected every {:?}", name, since, expected);
+                } else {
+                    info!("Signal '{}' is updated again", name);
+                }
+                self.publish(name, is_stale);
+            }
+        }
+        count
+    }
+
+    /// Time between two checks: a quarter of the shortest interval
+    #[must_use]
+    pub fn period(&self) -> Option<Duration> {
+        self.lock().expected.values().min().map(|shortest| (*shortest / 4).max(MIN_CHECK_PERIOD))
+    }
+
+    /// Check the signals on a task of its own
+    #[must_use]
+    pub fn spawn(&self) -> JoinHandle<()> {
+        self.restart();
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            loop {
+                // A reload can change the period
+                tokio::time::sleep(monitor.period().unwrap_or(Duration::from_secs(1))).await;
+                monitor.check();
+            }
+        })
+    }
+
+    fn publish(&self, name: &str, stale: bool) {
+        if let Err(e) = self.bus.set(stale_signal(name), Value::Bool(stale)) {
+            warn!("Failed to publish staleness of '{}': {}", name, e);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+
+    fn monitor(signals: &str) -> (StalenessMonitor, SignalBus, Arc<SimulatedClock>) {
+        let config: Config = serde_yaml::from_str(&format!("signals:\n{signals}blocks: []\n")).unwrap();
+        let clock = Arc::new(SimulatedClock::stepped());
+        let bus = SignalBus::new().with_clock(clock.clone());
+        (StalenessMonitor::new(&config, bus.clone()), bus, clock)
+    }
+
+    #[test]
+    fn test_flags_signals_missing_their_interval() {
+        let (monitor, bus, clock) = monitor(
+            "  - { name: tank.level, type: float, update_frequency_ms: 1000 }\n\
+             \x20 - { name: pump.run, type: bool, update_frequency_ms: 200 }\n\
+             \x20 - { name: mode, type: int }\n",
+        );
+        assert_eq!(monitor.period(), Some(Duration::from_millis(50)));
+        assert_eq!(bus.get("signal.pump.run.stale"), Some(Value::Bool(false)));
+
+        clock.advance(Duration::from_millis(150));
+        bus.set("pump.run", Value::Bool(true)).unwrap();
+        assert_eq!(monitor.check(), 0);
+
+        clock.advance(Duration::from_millis(300));
+        assert_eq!(monitor.check(), 1);
+        assert!(bus.is_stale("pump.run"));
+        assert!(!bus.is_stale("tank.level"));
+        assert_eq!(bus.get("signal.pump.run.stale"), Some(Value::Bool(true)));
+
+        clock.advance(Duration::from_millis(600));
+        // Writing the same value again counts as an update
+        bus.set("pump.run", Value::Bool(true)).unwrap();
+        assert_eq!(monitor.check(), 1);
+        assert_eq!(bus.stale_signals(), ["tank.level"]);
+        assert_eq!(bus.get("signal.pump.run.stale"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("signal.mode.stale"), None);
+    }
+
+    #[test]
+    fn test_reconfigure_drops_unmonitored_signals() {
+        let (monitor, bus, clock) = monitor("  - { name: flow, type: float, update_frequency_ms: 100 }\n");
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(monitor.check(), 1);
+
+        let config: Config = serde_yaml::from_str("signals:\n  - { name: flow, type: float }\nblocks: []\n").unwrap();
+        monitor.configure(&config);
+        assert!(!bus.is_stale("flow"));
+        assert_eq!(bus.get("signal.flow.stale"), None);
+        assert_eq!(monitor.period(), None);
+        assert_eq!(monitor.check(), 0);
+    }
+}
@@ -0,0 +1,285 @@
+// src/startup.rs
+//! Staged startup
+//!
+//! `petra run` brings a configuration up in dependency order instead of all
+//! at once:
+//!
+//! 1. `storage` - persisted state is restored and the stores other parts
+//!    write to are opened
+//! 2. `protocols` - client certificates are provisioned, redundancy joins
+//!    its peer and the protocol drivers connect
+//! 3. `blocks` - the first scan cycle runs, so outputs hold computed values
+//!    before anything serves them, and the analytics start
+//! 4. `web` - the web and gRPC APIs start listening
+//!
+//! Each stage's initialization runs under its own timeout and is retried
+//! after a delay when it fails or times out; startup stops at the first
+//! stage that runs out of attempts.
+//!
+//! ```yaml
+//! startup:
+//!   protocols: { timeout_ms: 60000, retries: 5, retry_delay_ms: 2000 }
+//!   web: { retries: 10 }     # wait for the old process to free the port
+//! ```
+//!
+//! The stage is published as the integer signal `system.startup_stage`
+//! (see [`StartupStage::number`]), reaching 5 once the engine runs.
+
+use crate::error::{PlcError, Result};
+use crate::signal::SignalBus;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Signal holding the [`StartupStage::number`] of the current stage
+pub const STAGE_SIGNAL: &str = "system.startup_stage";
+
+/// Step of the startup sequence, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupStage {
+    Storage,
+    Protocols,
+    Blocks,
+    Web,
+    /// Startup finished, the engine is scanning
+    Running,
+}
+
+impl StartupStage {
+    /// Value of [`STAGE_SIGNAL`] during the stage, from 1 for `storage` to
+    /// 5 for `running`
+    #[must_use]
+    pub const fn number(self) -> i64 {
+        match self {
+            Self::Storage => 1,
+            Self::Protocols => 2,
+            Self::Blocks => 3,
+            Self::Web => 4,
+            Self::Running => 5,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Storage => "storage",
+            Self::Protocols => "protocols",
+            Self::Blocks => "blocks",
+            Self::Web => "web",
+            Self::Running => "running",
+        }
+    }
+}
+
+impl fmt::Display for StartupStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Timeout and retry policy of the stages
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupConfig {
+    pub storage: StageConfig,
+    pub protocols: StageConfig,
+    pub blocks: StageConfig,
+    pub web: StageConfig,
+}
+
+/// Timeout and retry policy of one stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageConfig {
+    /// Time one attempt may take (milliseconds)
+    pub timeout_ms: u64,
+    /// Attempts after the first that failed or timed out
+    pub retries: u32,
+    /// Pause between attempts (milliseconds)
+    pub retry_delay_ms: u64,
+}
+
+impl Default for StageConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 30_000, retries: 0, retry_delay_ms: 1_000 }
+    }
+}
+
+impl StartupConfig {
+    /// Validate the stage policies
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for a zero stage timeout.
+    pub fn validate(&self) -> Result<()> {
+        for stage in [StartupStage::Storage, StartupStage::Protocols, StartupStage::Blocks, StartupStage::Web] {
+            if self.stage(stage).timeout_ms == 0 {
+                return Err(PlcError::Config(format!("Startup stage '{stage}' needs a timeout")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Policy of `stage`; `running` has no initialization and uses the
+    /// defaults
+    #[must_use]
+    pub fn stage(&self, stage: StartupStage) -> StageConfig {
+        match stage {
+            StartupStage::Storage => self.storage.clone(),
+            StartupStage::Protocols => self.protocols.clone(),
+            StartupStage::Blocks => self.blocks.clone(),
+            StartupStage::Web => self.web.clone(),
+            StartupStage::Running => StageConfig::default(),
+        }
+    }
+}
+
+/// Drives the stages in order and publishes the current one
+pub struct Startup {
+    config: StartupConfig,
+    bus: SignalBus,
+    current: Option<StartupStage>,
+}
+
+impl Startup {
+    #[must_use]
+    pub fn new(config: StartupConfig, bus: SignalBus) -> Self {
+        Self { config, bus, current: None }
+    }
+
+    /// Stage startup is in, `None` before the first one
+    #[must_use]
+    pub fn current(&self) -> Option<StartupStage> {
+        self.current
+    }
+
+    /// Enter `stage` and run its initialization under the stage's policy
+    ///
+    /// `init` is called again for every retry, so it must not leave
+    /// anything running when it fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Runtime`] if the stages are entered out of order
+    /// or the last attempt fails or times out.
+    pub async fn stage<T, F, Fut>(&mut self, stage: StartupStage, mut init: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.enter(stage)?;
+        let policy = self.config.stage(stage);
+        let timeout = Duration::from_millis(policy.timeout_ms);
+        let start = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let error = match tokio::time::timeout(timeout, init()).await {
+                Ok(Ok(value)) => {
+                    info!("Startup stage '{}' completed in {:?}", stage, start.elapsed());
+                    return Ok(value);
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => format!("timed out after {timeout:?}"),
+            };
+            if attempt == policy.retries {
+                return Err(PlcError::Runtime(format!(
+                    "Startup stage '{}' failed after {} attempt(s): {}",
+                    stage,
+                    attempt + 1,
+                    error
+                )));
+            }
+            attempt += 1;
+            warn!(
+                "Startup stage '{}' failed ({}), retrying in {} ms ({}/{})",
+                stage, error, policy.retry_delay_ms, attempt, policy.retries
+            );
+            tokio::time::sleep(Duration::from_millis(policy.retry_delay_ms)).await;
+        }
+    }
+
+    /// Mark startup as finished
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Runtime`] if startup already finished.
+    pub fn finish(&mut self) -> Result<()> {
+        self.enter(StartupStage::Running)?;
+        info!("Startup finished");
+        Ok(())
+    }
+
+    fn enter(&mut self, stage: StartupStage) -> Result<()> {
+        if self.current.is_some_and(|current| current >= stage) {
+            return Err(PlcError::Runtime(format!(
+                "Startup stage '{}' entered after '{}'",
+                stage,
+                self.current.map_or("", StartupStage::as_str)
+            )));
+        }
+        self.current = Some(stage);
+        info!("Startup stage '{}'", stage);
+        if let Err(e) = self.bus.set(STAGE_SIGNAL, Value::Integer(stage.number())) {
+            warn!("Failed to publish startup stage: {}", e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn startup(yaml: &str) -> (Startup, SignalBus) {
+        let config: StartupConfig = serde_yaml::from_str(yaml).unwrap();
+        config.validate().unwrap();
+        let bus = SignalBus::new();
+        (Startup::new(config, bus.clone()), bus)
+    }
+
+    #[tokio::test]
+    async fn test_stages_retry_and_publish() {
+        let (mut startup, bus) = startup("storage: { retries: 2, retry_delay_ms: 1 }\n");
+        let attempts = AtomicU32::new(0);
+        let value = startup
+            .stage(StartupStage::Storage, || async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 | 1 => Err(PlcError::Runtime("locked".to_string())),
+                    n => Ok(n),
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 2);
+        assert_eq!(bus.get(STAGE_SIGNAL), Some(Value::Integer(1)));
+
+        startup.stage(StartupStage::Blocks, || async { Ok(()) }).await.unwrap();
+        assert!(startup.stage(StartupStage::Protocols, || async { Ok(()) }).await.is_err());
+        startup.finish().unwrap();
+        assert_eq!(startup.current(), Some(StartupStage::Running));
+        assert_eq!(bus.get(STAGE_SIGNAL), Some(Value::Integer(5)));
+    }
+
+    #[tokio::test]
+    async fn test_stage_times_out() {
+        let (mut startup, _bus) = startup("protocols: { timeout_ms: 10, retries: 1, retry_delay_ms: 1 }\n");
+        let attempts = AtomicU32::new(0);
+        let error = startup
+            .stage(StartupStage::Protocols, || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert!(error.to_string().contains("'protocols' failed after 2 attempt(s): timed out"));
+
+        assert!(serde_yaml::from_str::<StartupConfig>("web: { timeout_ms: 0 }\n").unwrap().validate().is_err());
+    }
+}
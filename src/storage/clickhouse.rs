@@ -165,6 +165,68 @@ impl ClickHouseStorage {
         }
     }
     
+    /// `WHERE` clause selecting rows between `start` and `end`
+    fn range_filter(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> String {
+        let mut conditions = vec!["1".to_string()];
+        if let Some(start) = start {
+            conditions.push(format!("timestamp >= parseDateTime64BestEffort('{}', 9)", start.to_rfc3339()));
+        }
+        if let Some(end) = end {
+            conditions.push(format!("timestamp <= parseDateTime64BestEffort('{}', 9)", end.to_rfc3339()));
+        }
+        conditions.join(" AND ")
+    }
+    
+    /// Signals and timestamps stored in more than one row, with the number of rows
+    pub async fn duplicate_rows(
+        &self,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(String, DateTime<Utc>, u64)>> {
+        let query = format!(
+            "SELECT signal, toUnixTimestamp64Nano(timestamp), count() AS copies FROM {}.{} WHERE {} \
+             GROUP BY signal, timestamp HAVING copies > 1 ORDER BY signal, timestamp",
+            self.database, self.table, Self::range_filter(start, end)
+        );
+        let rows = self.client.query(&query).fetch_all::<(String, i64, u64)>().await
+            .map_err(|e| PlcError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("ClickHouse duplicate check failed: {}", e)
+            )))?;
+        Ok(rows.into_iter()
+            .map(|(signal, nanos, copies)| (signal, DateTime::from_timestamp_nanos(nanos), copies))
+            .collect())
+    }
+    
+    /// Rows per signal between `start` and `end`
+    pub async fn row_counts(
+        &self,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<std::collections::BTreeMap<String, u64>> {
+        let query = format!(
+            "SELECT signal, count() FROM {}.{} WHERE {} GROUP BY signal",
+            self.database, self.table, Self::range_filter(start, end)
+        );
+        let rows = self.client.query(&query).fetch_all::<(String, u64)>().await
+            .map_err(|e| PlcError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("ClickHouse row count failed: {}", e)
+            )))?;
+        Ok(rows.into_iter().collect())
+    }
+    
+    /// Drop rows repeating a signal and timestamp, keeping one of each
+    pub async fn deduplicate(&self) -> Result<()> {
+        let query = format!(
+            "OPTIMIZE TABLE {}.{} FINAL DEDUPLICATE BY signal, timestamp",
+            self.database, self.table
+        );
+        self.execute_with_retry(&query).await?;
+        info!("Deduplicated {}.{}", self.database, self.table);
+        Ok(())
+    }
+    
     fn prepare_batch(&self, entries: &[(DateTime<Utc>, String, Value)]) -> Vec<SignalRow> {
         entries.iter().map(|(ts, signal, value)| {
             let (value_type, value_bool, value_int, value_float) = match value {
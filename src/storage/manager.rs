@@ -1,6 +1,6 @@
 // src/storage/manager.rs - Complete implementation
 use super::*;
-use crate::{error::*, value::Value, signal::SignalBus};
+use crate::{error::*, value::Value, signal::SignalBus, subscription::SignalChange};
 use super::remote::{RemoteStorage, S3Storage};
 use super::clickhouse::ClickHouseStorage;
 use std::sync::Arc;
@@ -22,7 +22,7 @@ pub struct StorageManager {
     bus: SignalBus,
     buffer: Arc<RwLock<Vec<(DateTime<Utc>, String, Value)>>>,
     retry_queue: Arc<RwLock<VecDeque<PathBuf>>>,
-    signal_change_rx: Option<mpsc::Receiver<(String, Value)>>,
+    signal_change_rx: Option<mpsc::Receiver<SignalChange>>,
     running: Arc<RwLock<bool>>,
     last_wal_seq: Arc<RwLock<u64>>,
     remote_healthy: Arc<RwLock<bool>>,
@@ -105,7 +105,7 @@ impl StorageManager {
         })
     }
 
-    pub fn set_signal_change_channel(&mut self, rx: mpsc::Receiver<(String, Value)>) {
+    pub fn set_signal_change_channel(&mut self, rx: mpsc::Receiver<SignalChange>) {
         self.signal_change_rx = Some(rx);
     }
 
@@ -125,14 +125,14 @@ impl StorageManager {
 
         while *self.running.read() {
             tokio::select! {
-                Some((name, value)) = async {
+                Some(change) = async {
                     if let Some(rx) = &mut self.signal_change_rx {
                         rx.recv().await
                     } else {
                         None
                     }
                 } => {
-                    self.handle_signal_change(name, value).await?;
+                    self.handle_signal_change(change).await?;
                 }
 
                 _ = flush_interval.tick() => {
@@ -190,8 +190,11 @@ impl StorageManager {
         Ok(())
     }
 
-    async fn handle_signal_change(&self, name: String, value: Value) -> Result<()> {
-        let timestamp = Utc::now();
+    async fn handle_signal_change(&self, change: SignalChange) -> Result<()> {
+        // Stored under the time the value was written to the bus, which is
+        // the device timestamp for values a driver received with one
+        let timestamp: DateTime<Utc> = change.meta.timestamp.into();
+        let SignalChange { name, value, .. } = change;
 
         // Write to WAL first for durability
         let seq = self.wal.append(&name, value.clone(), timestamp.timestamp_nanos())?;
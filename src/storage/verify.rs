@@ -0,0 +1,514 @@
+//! History data integrity verification
+//!
+//! Backs `petra storage verify`. The history files under `history.data_dir`
+//! (and its `archive` directory) are checked for:
+//!
+//! - checksums: every file must match the SHA-256 sidecar written with it
+//! - ordering: the samples of each signal must not go back in time within a
+//!   file
+//! - duplicates: a signal may have only one sample per timestamp across all
+//!   files; copies with a different value are reported as conflicts
+//!
+//! With `--repair` out-of-order files are sorted, duplicate copies are
+//! removed from the later file and missing checksums are written. Files
+//! that cannot be parsed or no longer match their checksum are moved to
+//! `quarantine/` instead of being deleted, and conflicting samples are left
+//! for an operator to resolve.
+//!
+//! With `history.clickhouse` configured, the signals table is checked for
+//! duplicate rows and its per-signal row counts are compared with the local
+//! files. The write-ahead log only holds entries in memory while the engine
+//! runs, so there is nothing on disk to verify for it.
+//!
+//! ```bash
+//! petra storage verify --start-time 2024-05-01T00:00:00Z --end-time 2024-05-02T00:00:00Z --repair
+//! ```
+
+use crate::history::{checksum, checksum_path, HistoryEntry, CHECKSUM_EXTENSION};
+use crate::{PlcError, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory damaged files are moved to by a repair
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Time range and mode of a verification
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    /// Earliest sample checked, inclusive
+    pub start: Option<DateTime<Utc>>,
+    /// Latest sample checked, inclusive
+    pub end: Option<DateTime<Utc>>,
+    /// Fix what can be fixed instead of only reporting it
+    pub repair: bool,
+}
+
+impl VerifyOptions {
+    fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| timestamp >= start) && self.end.is_none_or(|end| timestamp <= end)
+    }
+}
+
+/// Kind of problem found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The file cannot be read or parsed
+    Unreadable,
+    /// The file has no checksum file
+    MissingChecksum,
+    /// The file no longer matches its checksum
+    ChecksumMismatch,
+    /// A signal's samples go back in time
+    OutOfOrder,
+    /// The same sample is stored more than once
+    Duplicate,
+    /// Samples of one signal and timestamp with different values
+    Conflict,
+    /// Row counts of a remote backend differ from the local files
+    CountMismatch,
+}
+
+impl IssueKind {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Unreadable => "unreadable",
+            Self::MissingChecksum => "missing checksum",
+            Self::ChecksumMismatch => "checksum mismatch",
+            Self::OutOfOrder => "out of order",
+            Self::Duplicate => "duplicate",
+            Self::Conflict => "conflict",
+            Self::CountMismatch => "count mismatch",
+        }
+    }
+}
+
+/// One problem found by a verification
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// File or backend the problem was found in
+    pub source: String,
+    pub signal: Option<String>,
+    pub detail: String,
+    /// Whether `--repair` fixed it
+    pub repaired: bool,
+}
+
+/// Result of a verification
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub files_checked: usize,
+    pub entries_checked: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl VerifyReport {
+    /// Issues left after any repair
+    pub fn unresolved(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|i| !i.repaired)
+    }
+
+    /// Whether nothing is left to fix
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.unresolved().next().is_none()
+    }
+
+    fn push(&mut self, kind: IssueKind, source: &Path, signal: Option<&str>, detail: String, repaired: bool) {
+        self.issues.push(Issue {
+            kind,
+            source: source.display().to_string(),
+            signal: signal.map(str::to_string),
+            detail,
+            repaired,
+        });
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Checked {} entries in {} files", self.entries_checked, self.files_checked)?;
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for issue in &self.issues {
+            let count = counts.entry(issue.kind.name()).or_default();
+            count.0 += 1;
+            count.1 += usize::from(issue.repaired);
+        }
+        for (kind, (found, repaired)) in counts {
+            writeln!(f, "  {kind:<18} {found} found, {repaired} repaired")?;
+        }
+        for issue in self.unresolved() {
+            let signal = issue.signal.as_deref().map(|s| format!(" {s}:")).unwrap_or_default();
+            writeln!(f, "  [{}] {}{signal} {}", issue.kind.name(), issue.source, issue.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed history file
+struct HistoryFile {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+    /// Needs rewriting when repairing
+    dirty: bool,
+}
+
+/// History files under `dir`, oldest first
+fn history_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for dir in [dir.to_path_buf(), dir.join("archive")] {
+        let read = match std::fs::read_dir(&dir) {
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in read {
+            let path = entry?.path();
+            let is_history = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("history_"));
+            let extension = path.extension().and_then(|e| e.to_str());
+            if is_history && matches!(extension, Some("json" | "parquet")) {
+                files.push(path);
+            }
+        }
+    }
+    // Names carry the write time, so this is also write order
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+/// Move `path` and its checksum file out of the way
+fn quarantine(data_dir: &Path, path: &Path) -> Result<()> {
+    let dir = data_dir.join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let name = path
+        .file_name()
+        .ok_or_else(|| PlcError::Runtime(format!("Invalid history file path {}", path.display())))?;
+    std::fs::rename(path, dir.join(name))?;
+    let sidecar = checksum_path(path);
+    if sidecar.exists() {
+        let mut sidecar_name = name.to_os_string();
+        sidecar_name.push(format!(".{CHECKSUM_EXTENSION}"));
+        std::fs::rename(sidecar, dir.join(sidecar_name))?;
+    }
+    warn!("Quarantined {}", path.display());
+    Ok(())
+}
+
+/// Replace the contents of `path` and its checksum
+fn rewrite(path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    let data = serde_json::to_vec(entries)?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, &data)?;
+    std::fs::rename(&tmp, path)?;
+    std::fs::write(checksum_path(path), checksum(&data))?;
+    Ok(())
+}
+
+/// Check file level integrity, returning the parsed file if it can be used
+fn check_file(data_dir: &Path, path: &Path, options: &VerifyOptions, report: &mut VerifyReport) -> Result<Option<HistoryFile>> {
+    let data = std::fs::read(path)?;
+    let entries = match serde_json::from_slice::<Vec<HistoryEntry>>(&data) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if options.repair {
+                quarantine(data_dir, path)?;
+            }
+            report.push(IssueKind::Unreadable, path, None, e.to_string(), options.repair);
+            return Ok(None);
+        }
+    };
+    if !entries.iter().any(|e| options.contains(e.timestamp)) {
+        return Ok(None);
+    }
+    report.files_checked += 1;
+
+    match std::fs::read_to_string(checksum_path(path)) {
+        Ok(expected) if expected.trim() == checksum(&data) => {}
+        Ok(_) => {
+            if options.repair {
+                quarantine(data_dir, path)?;
+            }
+            report.push(
+                IssueKind::ChecksumMismatch,
+                path,
+                None,
+                "contents changed since the file was written".to_string(),
+                options.repair,
+            );
+            return Ok(None);
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if options.repair {
+                std::fs::write(checksum_path(path), checksum(&data))?;
+            }
+            report.push(IssueKind::MissingChecksum, path, None, "no checksum file".to_string(), options.repair);
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut file = HistoryFile {
+        path: path.to_path_buf(),
+        entries,
+        dirty: false,
+    };
+    let mut latest: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    let mut out_of_order = BTreeMap::new();
+    for entry in &file.entries {
+        let previous = latest.entry(entry.signal_name.as_str()).or_insert(entry.timestamp);
+        if entry.timestamp < *previous {
+            *out_of_order.entry(entry.signal_name.clone()).or_insert(0usize) += 1;
+        } else {
+            *previous = entry.timestamp;
+        }
+    }
+    for (signal, count) in out_of_order {
+        report.push(
+            IssueKind::OutOfOrder,
+            path,
+            Some(&signal),
+            format!("{count} samples earlier than the one before"),
+            options.repair,
+        );
+        file.dirty = true;
+    }
+    if file.dirty {
+        file.entries.sort_by_key(|e| e.timestamp);
+    }
+    Ok(Some(file))
+}
+
+/// Verify the history files under `data_dir`
+///
+/// # Errors
+///
+/// Returns an I/O error if the directory or a file cannot be read, or a
+/// repair cannot be written. Problems with the data itself are reported in
+/// the [`VerifyReport`].
+pub fn verify_history(data_dir: &Path, options: &VerifyOptions) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    let mut files = Vec::new();
+    for path in history_files(data_dir)? {
+        if let Some(file) = check_file(data_dir, &path, options, &mut report)? {
+            files.push(file);
+        }
+    }
+
+    // The first copy of a sample, in write order, is the one kept
+    let mut seen: HashMap<(String, DateTime<Utc>), (String, crate::Value)> = HashMap::new();
+    for file in &mut files {
+        let mut kept = Vec::with_capacity(file.entries.len());
+        for entry in std::mem::take(&mut file.entries) {
+            if !options.contains(entry.timestamp) {
+                kept.push(entry);
+                continue;
+            }
+            report.entries_checked += 1;
+            let key = (entry.signal_name.clone(), entry.timestamp);
+            match seen.get(&key) {
+                Some((_, value)) if *value == entry.value => {
+                    report.push(
+                        IssueKind::Duplicate,
+                        &file.path,
+                        Some(&entry.signal_name),
+                        format!("sample at {} stored more than once", entry.timestamp.to_rfc3339()),
+                        options.repair,
+                    );
+                    file.dirty = true;
+                }
+                Some((first, value)) => {
+                    report.push(
+                        IssueKind::Conflict,
+                        &file.path,
+                        Some(&entry.signal_name),
+                        format!(
+                            "{} at {} conflicts with {} in {}",
+                            entry.value,
+                            entry.timestamp.to_rfc3339(),
+                            value,
+                            first
+                        ),
+                        false,
+                    );
+                    kept.push(entry);
+                }
+                None => {
+                    seen.insert(key, (file.path.display().to_string(), entry.value.clone()));
+                    kept.push(entry);
+                }
+            }
+        }
+        file.entries = kept;
+    }
+
+    if options.repair {
+        for file in files.iter().filter(|f| f.dirty) {
+            if file.entries.is_empty() {
+                quarantine(data_dir, &file.path)?;
+            } else {
+                rewrite(&file.path, &file.entries)?;
+            }
+            info!("Repaired {}", file.path.display());
+        }
+    }
+    Ok(report)
+}
+
+/// Samples per signal in the local files, for comparison with a remote
+/// backend
+///
+/// # Errors
+///
+/// Returns an I/O error if the directory or a file cannot be read.
+pub fn signal_counts(data_dir: &Path, options: &VerifyOptions) -> Result<BTreeMap<String, u64>> {
+    let mut counts = BTreeMap::new();
+    for path in history_files(data_dir)? {
+        let Ok(entries) = serde_json::from_slice::<Vec<HistoryEntry>>(&std::fs::read(&path)?) else {
+            continue;
+        };
+        for entry in entries.into_iter().filter(|e| options.contains(e.timestamp)) {
+            *counts.entry(entry.signal_name).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Verify a ClickHouse backend against the local files
+///
+/// # Errors
+///
+/// Returns an error if ClickHouse cannot be queried or a local file cannot
+/// be read.
+#[cfg(feature = "clickhouse")]
+pub async fn verify_clickhouse(
+    storage: &super::clickhouse::ClickHouseStorage,
+    data_dir: &Path,
+    options: &VerifyOptions,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    let source = Path::new("clickhouse");
+    let duplicates = storage.duplicate_rows(options.start, options.end).await?;
+    if !duplicates.is_empty() && options.repair {
+        storage.deduplicate().await?;
+    }
+    for (signal, timestamp, copies) in duplicates {
+        report.push(
+            IssueKind::Duplicate,
+            source,
+            Some(&signal),
+            format!("{copies} rows at {}", timestamp.to_rfc3339()),
+            options.repair,
+        );
+    }
+
+    let local = signal_counts(data_dir, options)?;
+    let remote = storage.row_counts(options.start, options.end).await?;
+    let signals: std::collections::BTreeSet<_> = local.keys().chain(remote.keys()).collect();
+    for signal in signals {
+        let (l, r) = (local.get(signal).copied().unwrap_or(0), remote.get(signal).copied().unwrap_or(0));
+        if l != r {
+            report.push(
+                IssueKind::CountMismatch,
+                source,
+                Some(signal),
+                format!("{r} rows, {l} samples in local files"),
+                false,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn entry(signal: &str, second: i64, value: f64) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: DateTime::from_timestamp(1_700_000_000 + second, 0).unwrap(),
+            signal_name: signal.to_string(),
+            value: Value::Float(value),
+            quality: None,
+            metadata: None,
+        }
+    }
+
+    fn write(dir: &Path, name: &str, entries: &[HistoryEntry], with_checksum: bool) -> PathBuf {
+        let path = dir.join(name);
+        let data = serde_json::to_vec(entries).unwrap();
+        std::fs::write(&path, &data).unwrap();
+        if with_checksum {
+            std::fs::write(checksum_path(&path), checksum(&data)).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_clean_history_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "history_1.json", &[entry("a", 0, 1.0), entry("b", 0, 2.0), entry("a", 1, 1.5)], true);
+        write(dir.path(), "history_2.json", &[entry("a", 2, 1.0)], true);
+
+        let report = verify_history(dir.path(), &VerifyOptions::default()).unwrap();
+        assert!(report.is_clean(), "{report}");
+        assert_eq!((report.files_checked, report.entries_checked), (2, 4));
+
+        // Only files with samples in range are checked
+        let options = VerifyOptions {
+            start: Some(DateTime::from_timestamp(1_700_000_002, 0).unwrap()),
+            ..VerifyOptions::default()
+        };
+        let report = verify_history(dir.path(), &options).unwrap();
+        assert_eq!((report.files_checked, report.entries_checked), (1, 1));
+    }
+
+    #[test]
+    fn test_issues_are_reported_and_repaired() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "history_1.json", &[entry("a", 0, 1.0), entry("a", 5, 2.0)], true);
+        let later = write(
+            dir.path(),
+            "history_2.json",
+            &[entry("a", 7, 3.0), entry("a", 6, 2.5), entry("a", 5, 2.0), entry("a", 0, 9.0)],
+            false,
+        );
+        let tampered = write(dir.path(), "history_3.json", &[entry("b", 1, 1.0)], true);
+        std::fs::write(&tampered, serde_json::to_vec(&[entry("b", 1, 4.0)]).unwrap()).unwrap();
+        std::fs::write(dir.path().join("history_4.json"), b"[{\"timestamp\":").unwrap();
+
+        let report = verify_history(dir.path(), &VerifyOptions::default()).unwrap();
+        let kinds: Vec<_> = report.issues.iter().map(|i| i.kind).collect();
+        for kind in [
+            IssueKind::Unreadable,
+            IssueKind::MissingChecksum,
+            IssueKind::ChecksumMismatch,
+            IssueKind::OutOfOrder,
+            IssueKind::Duplicate,
+            IssueKind::Conflict,
+        ] {
+            assert!(kinds.contains(&kind), "{kind:?} not found in {report}");
+        }
+        assert!(report.issues.iter().all(|i| !i.repaired));
+
+        let repair = VerifyOptions { repair: true, ..VerifyOptions::default() };
+        let report = verify_history(dir.path(), &repair).unwrap();
+        let unresolved: Vec<_> = report.unresolved().map(|i| i.kind).collect();
+        assert_eq!(unresolved, vec![IssueKind::Conflict]);
+        assert!(dir.path().join(QUARANTINE_DIR).join("history_3.json").exists());
+        assert!(dir.path().join(QUARANTINE_DIR).join("history_4.json").exists());
+
+        let repaired: Vec<HistoryEntry> = serde_json::from_slice(&std::fs::read(&later).unwrap()).unwrap();
+        let seconds: Vec<_> = repaired.iter().map(|e| e.timestamp.timestamp() - 1_700_000_000).collect();
+        assert_eq!(seconds, vec![0, 6, 7]);
+
+        // Only the conflict, which needs an operator, is left
+        let report = verify_history(dir.path(), &VerifyOptions::default()).unwrap();
+        let kinds: Vec<_> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, vec![IssueKind::Conflict]);
+        assert!(!report.is_clean());
+    }
+}
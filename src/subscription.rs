@@ -0,0 +1,306 @@
+// src/subscription.rs
+//! Signal change subscriptions
+//!
+//! [`SignalBus::subscribe`] returns a [`SignalSubscription`], a stream of the
+//! changes written to every signal matching a pattern, so consumers wait for
+//! changes instead of polling the bus every cycle. Patterns are globs where
+//! `*` matches any run of characters, including dots, and `?` matches one
+//! character:
+//!
+//! ```rust
+//! # use petra::{SignalBus, Value};
+//! # use futures::StreamExt;
+//! # async fn example() -> petra::Result<()> {
+//! let bus = SignalBus::new();
+//! let mut changes = bus.subscribe("line1.*");
+//! bus.set("line1.speed", Value::Float(42.0))?;
+//! let change = changes.next().await.unwrap();
+//! assert_eq!(change.name, "line1.speed");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Writers never wait for subscribers. Each subscription buffers at most
+//! [`SubscribeOptions::capacity`] changes and, when the buffer is full,
+//! drops the oldest pending change; [`SignalSubscription::dropped`] counts
+//! them. With [`Coalesce::Latest`] a pending change is replaced by a newer
+//! change of the same signal, so a slow consumer sees every signal's latest
+//! value instead of a backlog. Dropping the subscription unsubscribes.
+//!
+//! [`SignalBus::subscribe`]: crate::signal::SignalBus::subscribe
+
+use crate::signal::WriteMeta;
+use crate::value::Value;
+use futures::Stream;
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::task::{Context, Poll, Waker};
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any run of
+/// characters and `?` matches one character
+#[must_use]
+pub fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star;
+                    n = tried + 1;
+                    backtrack = Some((star, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A change written to a subscribed signal
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalChange {
+    /// Signal name
+    pub name: String,
+    /// Value written
+    pub value: Value,
+    /// Timestamp and source of the write
+    pub meta: WriteMeta,
+}
+
+/// How a subscription treats changes that arrive before the pending ones
+/// were taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Coalesce {
+    /// Deliver every change in write order
+    #[default]
+    Every,
+    /// Keep only the latest pending change of each signal, in the order the
+    /// signals first changed
+    Latest,
+}
+
+/// Buffering of a subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeOptions {
+    /// Most changes pending; the oldest is dropped to make room
+    pub capacity: usize,
+    pub coalesce: Coalesce,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        Self { capacity: 1024, coalesce: Coalesce::Every }
+    }
+}
+
+impl SubscribeOptions {
+    #[must_use]
+    pub const fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    #[must_use]
+    pub const fn coalesce(mut self, coalesce: Coalesce) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    queue: VecDeque<SignalChange>,
+    /// Changes taken from the front of `queue` so far
+    taken: usize,
+    /// Position of the pending change of each signal, counted like
+    /// `taken`, with [`Coalesce::Latest`]
+    latest: HashMap<String, usize>,
+    dropped: u64,
+    waker: Option<Waker>,
+}
+
+impl Pending {
+    fn pop_front(&mut self) -> Option<SignalChange> {
+        let change = self.queue.pop_front()?;
+        self.taken += 1;
+        self.latest.remove(&change.name);
+        Some(change)
+    }
+}
+
+/// One subscription as seen by the bus
+#[derive(Debug)]
+struct Subscriber {
+    patterns: Vec<String>,
+    options: SubscribeOptions,
+    pending: Mutex<Pending>,
+}
+
+impl Subscriber {
+    fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern_matches(pattern, name))
+    }
+
+    fn push(&self, change: &SignalChange) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.options.coalesce == Coalesce::Latest {
+            if let Some(&position) = pending.latest.get(&change.name) {
+                let index = position - pending.taken;
+                pending.queue[index] = change.clone();
+                return;
+            }
+        }
+        if pending.queue.len() >= self.options.capacity.max(1) && pending.pop_front().is_some() {
+            pending.dropped += 1;
+        }
+        if self.options.coalesce == Coalesce::Latest {
+            let position = pending.taken + pending.queue.len();
+            pending.latest.insert(change.name.clone(), position);
+        }
+        pending.queue.push_back(change.clone());
+        if let Some(waker) = pending.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Subscriptions of a bus and its clones
+#[derive(Debug, Default)]
+pub(crate) struct Subscribers {
+    list: RwLock<Vec<Arc<Subscriber>>>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(self: &Arc<Self>, patterns: Vec<String>, options: SubscribeOptions) -> SignalSubscription {
+        let subscriber = Arc::new(Subscriber { patterns, options, pending: Mutex::default() });
+        self.list.write().unwrap_or_else(PoisonError::into_inner).push(Arc::clone(&subscriber));
+        SignalSubscription { subscriber, subscribers: Arc::clone(self) }
+    }
+
+    /// Whether a subscription wants changes of `name`
+    pub(crate) fn wants(&self, name: &str) -> bool {
+        let list = self.list.read().unwrap_or_else(PoisonError::into_inner);
+        !list.is_empty() && list.iter().any(|subscriber| subscriber.matches(name))
+    }
+
+    /// Hand a change to every subscription it matches
+    pub(crate) fn publish(&self, change: &SignalChange) {
+        for subscriber in self.list.read().unwrap_or_else(PoisonError::into_inner).iter() {
+            if subscriber.matches(&change.name) {
+                subscriber.push(change);
+            }
+        }
+    }
+}
+
+/// Stream of the changes of the signals matching a subscription's patterns
+///
+/// The stream never ends; dropping it unsubscribes.
+#[derive(Debug)]
+pub struct SignalSubscription {
+    subscriber: Arc<Subscriber>,
+    subscribers: Arc<Subscribers>,
+}
+
+impl SignalSubscription {
+    /// Changes dropped because the buffer was full
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.subscriber.pending.lock().unwrap_or_else(PoisonError::into_inner).dropped
+    }
+
+    /// Take the next pending change without waiting
+    pub fn try_next(&mut self) -> Option<SignalChange> {
+        self.subscriber.pending.lock().unwrap_or_else(PoisonError::into_inner).pop_front()
+    }
+}
+
+impl Stream for SignalSubscription {
+    type Item = SignalChange;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<SignalChange>> {
+        let mut pending = self.subscriber.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(change) = pending.pop_front() {
+            return Poll::Ready(Some(change));
+        }
+        pending.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for SignalSubscription {
+    fn drop(&mut self) {
+        self.subscribers
+            .list
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| !Arc::ptr_eq(subscriber, &self.subscriber));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::SignalBus;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("line1.*", "line1.speed"));
+        assert!(pattern_matches("*.speed", "line1.speed"));
+        assert!(pattern_matches("line?.speed", "line2.speed"));
+        assert!(pattern_matches("a*b*c", "a_b_b_c"));
+        assert!(pattern_matches("*", "anything"));
+        assert!(!pattern_matches("a*b*c", "a_c"));
+        assert!(!pattern_matches("line?.speed", "line10.speed"));
+        assert!(!pattern_matches("line.mode", "line.mode2"));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_buffers_and_coalesces() {
+        let bus = SignalBus::new();
+        let mut every = bus.subscribe_with(["tank.*"], SubscribeOptions::default().capacity(3));
+        let mut latest = bus.subscribe_with(
+            ["tank.*", "pump.run"],
+            SubscribeOptions::default().coalesce(Coalesce::Latest),
+        );
+
+        bus.set("tank.level", Value::Float(1.0)).unwrap();
+        bus.set("tank.temp", Value::Float(20.0)).unwrap();
+        bus.set("other.level", Value::Float(9.0)).unwrap();
+        bus.set("tank.level", Value::Float(2.0)).unwrap();
+        bus.set("pump.run", Value::Bool(true)).unwrap();
+        bus.set("tank.level", Value::Float(3.0)).unwrap();
+
+        // The oldest of four changes did not fit
+        let values: Vec<Value> = (0..3).filter_map(|_| every.try_next()).map(|change| change.value).collect();
+        assert_eq!(values, [Value::Float(20.0), Value::Float(2.0), Value::Float(3.0)]);
+        assert_eq!(every.dropped(), 1);
+        assert!(every.try_next().is_none());
+
+        let first = latest.next().await.unwrap();
+        assert_eq!((first.name.as_str(), first.value), ("tank.level", Value::Float(3.0)));
+        assert_eq!(latest.next().await.unwrap().name, "tank.temp");
+        assert_eq!(latest.next().await.unwrap().name, "pump.run");
+        assert_eq!(latest.dropped(), 0);
+
+        // A waiting consumer is woken by the next write
+        let writer = bus.clone();
+        tokio::spawn(async move { writer.set("pump.run", Value::Bool(false)).unwrap() });
+        let change = tokio::time::timeout(std::time::Duration::from_secs(1), latest.next()).await.unwrap().unwrap();
+        assert_eq!(change.value, Value::Bool(false));
+    }
+}
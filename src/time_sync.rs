@@ -0,0 +1,432 @@
+// src/time_sync.rs
+//! Clock synchronization state and monitoring
+//!
+//! Timestamps on history samples and alarm records are only comparable across
+//! systems while the local clock is disciplined by NTP or PTP. This module
+//! keeps a process-wide "clock synchronized" flag that record producers
+//! consult, and, with the `time-sync` feature, a monitor that maintains it.
+//!
+//! Timers and the scan scheduler measure intervals with the monotonic clock
+//! ([`std::time::Instant`] and `tokio::time`), so wall-clock steps applied by
+//! a sync daemon never shorten or stretch a timer preset or a scan period. The
+//! monitor reports such steps by comparing wall-clock and monotonic progress,
+//! so the affected records can be found later.
+//!
+//! ```yaml
+//! time_sync:
+//!   source: { type: ntp, server: "ntp.plant.local:123" }
+//!   check_interval_s: 60
+//!   max_offset_ms: 50
+//! ```
+//!
+//! Published signals (prefix configurable, default `time_sync`):
+//!
+//! - `time_sync.offset_ms` - local clock minus reference, in milliseconds
+//! - `time_sync.synchronized` - offset within `max_offset_ms` and the last
+//!   successful check is recent
+//! - `time_sync.step_count` - wall-clock steps detected since startup
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+static CLOCK_SYNCHRONIZED: AtomicBool = AtomicBool::new(true);
+
+/// Whether the local clock is currently considered synchronized
+///
+/// Always `true` unless a time sync monitor is running and has found the clock
+/// out of tolerance or has lost its reference.
+#[must_use]
+pub fn clock_synchronized() -> bool {
+    CLOCK_SYNCHRONIZED.load(Ordering::Relaxed)
+}
+
+#[cfg(feature = "time-sync")]
+fn set_clock_synchronized(synchronized: bool) {
+    CLOCK_SYNCHRONIZED.store(synchronized, Ordering::Relaxed);
+}
+
+/// Detects wall-clock steps by comparing against the monotonic clock
+#[derive(Debug, Clone)]
+pub struct ClockStepDetector {
+    last_wall: SystemTime,
+    last_mono: Instant,
+    threshold: Duration,
+}
+
+impl ClockStepDetector {
+    /// Create a detector that ignores drift below `threshold`
+    #[must_use]
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            last_wall: SystemTime::now(),
+            last_mono: Instant::now(),
+            threshold,
+        }
+    }
+
+    /// Check for a step since the previous call
+    ///
+    /// Returns the step in milliseconds, negative for a backwards step.
+    pub fn check(&mut self) -> Option<f64> {
+        self.observe(SystemTime::now(), Instant::now())
+    }
+
+    fn observe(&mut self, wall: SystemTime, mono: Instant) -> Option<f64> {
+        let mono_elapsed = mono.duration_since(self.last_mono).as_secs_f64();
+        let wall_elapsed = match wall.duration_since(self.last_wall) {
+            Ok(forward) => forward.as_secs_f64(),
+            Err(backward) => -backward.duration().as_secs_f64(),
+        };
+        self.last_wall = wall;
+        self.last_mono = mono;
+
+        let step = wall_elapsed - mono_elapsed;
+        (step.abs() >= self.threshold.as_secs_f64()).then_some(step * 1000.0)
+    }
+}
+
+#[cfg(feature = "time-sync")]
+pub use monitor::{TimeSource, TimeSyncConfig, TimeSyncMonitor};
+
+#[cfg(feature = "time-sync")]
+mod monitor {
+    use super::{set_clock_synchronized, ClockStepDetector};
+    use crate::{PlcError, Result, SignalBus, Value};
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use tokio::net::UdpSocket;
+    use tracing::{info, warn};
+
+    /// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+    const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+
+    /// Time sync monitor configuration
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TimeSyncConfig {
+        /// Where the reference time comes from
+        pub source: TimeSource,
+
+        /// Seconds between offset checks
+        #[serde(default = "default_check_interval_s")]
+        pub check_interval_s: u64,
+
+        /// Largest offset still considered synchronized
+        #[serde(default = "default_max_offset_ms")]
+        pub max_offset_ms: f64,
+
+        /// How long the last good check stays valid when the source is unreachable
+        #[serde(default = "default_holdover_s")]
+        pub holdover_s: u64,
+
+        /// Wall-clock jumps at least this large are reported as steps
+        #[serde(default = "default_step_threshold_ms")]
+        pub step_threshold_ms: u64,
+
+        /// Timeout for a single query
+        #[serde(default = "default_timeout_ms")]
+        pub timeout_ms: u64,
+
+        /// Prefix of the published signals
+        #[serde(default = "default_signal_prefix")]
+        pub signal_prefix: String,
+    }
+
+    /// Reference time source
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum TimeSource {
+        /// Query an NTP server directly (SNTP)
+        Ntp {
+            /// Server address as `host:port`
+            server: String,
+        },
+        /// Run a program that prints the current offset in seconds
+        ///
+        /// Intended for PTP setups, e.g. a wrapper around `pmc` reading
+        /// `master_offset` from `ptp4l`, or for `chronyc` on NTP hosts.
+        Command {
+            /// Program to execute
+            program: String,
+            /// Program arguments
+            #[serde(default)]
+            args: Vec<String>,
+        },
+    }
+
+    impl TimeSyncConfig {
+        /// Validate intervals and thresholds
+        ///
+        /// # Errors
+        ///
+        /// Returns [`PlcError::Config`] describing the first invalid setting.
+        pub fn validate(&self) -> Result<()> {
+            if self.check_interval_s == 0 {
+                return Err(PlcError::Config("Time sync check interval cannot be 0".to_string()));
+            }
+            if self.max_offset_ms <= 0.0 {
+                return Err(PlcError::Config("Time sync max_offset_ms must be positive".to_string()));
+            }
+            if self.holdover_s < self.check_interval_s {
+                return Err(PlcError::Config(
+                    "Time sync holdover must be at least one check interval".to_string(),
+                ));
+            }
+            match &self.source {
+                TimeSource::Ntp { server } if server.is_empty() => {
+                    Err(PlcError::Config("Time sync NTP server cannot be empty".to_string()))
+                }
+                TimeSource::Command { program, .. } if program.is_empty() => {
+                    Err(PlcError::Config("Time sync command cannot be empty".to_string()))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    const fn default_check_interval_s() -> u64 {
+        60
+    }
+
+    const fn default_max_offset_ms() -> f64 {
+        100.0
+    }
+
+    const fn default_holdover_s() -> u64 {
+        300
+    }
+
+    const fn default_step_threshold_ms() -> u64 {
+        1000
+    }
+
+    const fn default_timeout_ms() -> u64 {
+        2000
+    }
+
+    fn default_signal_prefix() -> String {
+        "time_sync".to_string()
+    }
+
+    /// Monitors the clock offset and maintains the synchronized flag
+    pub struct TimeSyncMonitor {
+        config: TimeSyncConfig,
+        detector: ClockStepDetector,
+        last_good: Option<Instant>,
+        step_count: i64,
+    }
+
+    impl TimeSyncMonitor {
+        /// Create a monitor
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the configuration is invalid.
+        pub fn new(config: TimeSyncConfig) -> Result<Self> {
+            config.validate()?;
+            Ok(Self {
+                detector: ClockStepDetector::new(Duration::from_millis(config.step_threshold_ms)),
+                config,
+                last_good: None,
+                step_count: 0,
+            })
+        }
+
+        /// Measure the offset once, update the flag and publish signals
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the signals cannot be written. Source failures
+        /// are logged and handled through the holdover period.
+        pub async fn check(&mut self, bus: &SignalBus) -> Result<()> {
+            if let Some(step_ms) = self.detector.check() {
+                self.step_count += 1;
+                warn!("Wall clock stepped by {:.0} ms", step_ms);
+            }
+
+            let offset_ms = match self.query_offset().await {
+                Ok(offset_ms) => Some(offset_ms),
+                Err(e) => {
+                    warn!("Time sync query failed: {}", e);
+                    None
+                }
+            };
+
+            if offset_ms.is_some_and(|o| o.abs() <= self.config.max_offset_ms) {
+                self.last_good = Some(Instant::now());
+            } else if offset_ms.is_some() {
+                self.last_good = None;
+            }
+            let synchronized = self
+                .last_good
+                .is_some_and(|t| t.elapsed() <= Duration::from_secs(self.config.holdover_s));
+
+            if synchronized != super::clock_synchronized() {
+                if synchronized {
+                    info!("Clock synchronized (offset {:.1} ms)", offset_ms.unwrap_or_default());
+                } else {
+                    warn!("Clock not synchronized, new records will be flagged");
+                }
+            }
+            set_clock_synchronized(synchronized);
+
+            let prefix = &self.config.signal_prefix;
+            let mut updates = vec![
+                (format!("{prefix}.synchronized"), Value::Bool(synchronized)),
+                (format!("{prefix}.step_count"), Value::Integer(self.step_count)),
+            ];
+            if let Some(offset_ms) = offset_ms {
+                updates.push((format!("{prefix}.offset_ms"), Value::Float(offset_ms)));
+            }
+            bus.write_batch(updates)
+        }
+
+        async fn query_offset(&self) -> Result<f64> {
+            let timeout = Duration::from_millis(self.config.timeout_ms);
+            let query = async {
+                match &self.config.source {
+                    TimeSource::Ntp { server } => sntp_offset_ms(server).await,
+                    TimeSource::Command { program, args } => command_offset_ms(program, args).await,
+                }
+            };
+            tokio::time::timeout(timeout, query)
+                .await
+                .map_err(|_| PlcError::Runtime("Time sync query timed out".to_string()))?
+        }
+
+        /// Run periodic checks until the task is cancelled
+        pub async fn run(mut self, bus: SignalBus) {
+            let mut ticker = tokio::time::interval(Duration::from_secs(self.config.check_interval_s));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.check(&bus).await {
+                    warn!("Time sync update failed: {}", e);
+                }
+            }
+        }
+    }
+
+    fn unix_seconds(time: SystemTime) -> f64 {
+        time.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+    }
+
+    fn read_ntp_timestamp(bytes: &[u8]) -> f64 {
+        let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        f64::from(seconds) - NTP_UNIX_OFFSET + f64::from(fraction) / 4_294_967_296.0
+    }
+
+    fn write_ntp_timestamp(unix: f64, out: &mut [u8]) {
+        let ntp = unix + NTP_UNIX_OFFSET;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (seconds, fraction) = (ntp as u32, (ntp.fract() * 4_294_967_296.0) as u32);
+        out[..4].copy_from_slice(&seconds.to_be_bytes());
+        out[4..8].copy_from_slice(&fraction.to_be_bytes());
+    }
+
+    /// Compute the clock offset from an SNTP response
+    fn sntp_offset_from_response(request: &[u8; 48], response: &[u8], t4: f64) -> Result<f64> {
+        if response.len() < 48 {
+            return Err(PlcError::Protocol("Short NTP response".to_string()));
+        }
+        let leap = response[0] >> 6;
+        let mode = response[0] & 0x07;
+        let stratum = response[1];
+        if mode != 4 || stratum == 0 || leap == 3 {
+            return Err(PlcError::Protocol(format!(
+                "NTP server unsynchronized or refused (mode {mode}, stratum {stratum}, leap {leap})"
+            )));
+        }
+        // The server echoes our transmit timestamp as the originate timestamp
+        if response[24..32] != request[40..48] {
+            return Err(PlcError::Protocol("NTP response does not match request".to_string()));
+        }
+
+        let t1 = read_ntp_timestamp(&request[40..48]);
+        let t2 = read_ntp_timestamp(&response[32..40]);
+        let t3 = read_ntp_timestamp(&response[40..48]);
+        // Reference minus local, negated so positive means the local clock is ahead
+        Ok(-((t2 - t1) + (t3 - t4)) / 2.0 * 1000.0)
+    }
+
+    async fn sntp_offset_ms(server: &str) -> Result<f64> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(server).await?;
+
+        let mut request = [0u8; 48];
+        request[0] = 0x23; // LI 0, version 4, mode 3 (client)
+        write_ntp_timestamp(unix_seconds(SystemTime::now()), &mut request[40..48]);
+        socket.send(&request).await?;
+
+        let mut response = [0u8; 48];
+        let len = socket.recv(&mut response).await?;
+        let t4 = unix_seconds(SystemTime::now());
+        sntp_offset_from_response(&request, &response[..len], t4)
+    }
+
+    async fn command_offset_ms(program: &str, args: &[String]) -> Result<f64> {
+        let output = tokio::process::Command::new(program).args(args).output().await?;
+        if !output.status.success() {
+            return Err(PlcError::Runtime(format!(
+                "Time sync command '{program}' exited with {}",
+                output.status
+            )));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let seconds: f64 = stdout.trim().parse().map_err(|_| {
+            PlcError::Runtime(format!("Time sync command printed '{}', expected seconds", stdout.trim()))
+        })?;
+        Ok(seconds * 1000.0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_sntp_offset_calculation() {
+            let t1 = 1_700_000_000.0;
+            let mut request = [0u8; 48];
+            request[0] = 0x23;
+            write_ntp_timestamp(t1, &mut request[40..48]);
+
+            // Local clock 0.5 s behind the server, 20 ms each way
+            let mut response = [0u8; 48];
+            response[0] = 0x24;
+            response[1] = 2;
+            response[24..32].copy_from_slice(&request[40..48]);
+            write_ntp_timestamp(t1 + 0.52, &mut response[32..40]);
+            write_ntp_timestamp(t1 + 0.521, &mut response[40..48]);
+            let t4 = t1 + 0.041;
+
+            let offset = sntp_offset_from_response(&request, &response, t4).unwrap();
+            assert!((offset + 500.0).abs() < 1.0, "offset {offset}");
+
+            response[1] = 0;
+            assert!(sntp_offset_from_response(&request, &response, t4).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_detection() {
+        let mut detector = ClockStepDetector::new(Duration::from_millis(500));
+        let wall = detector.last_wall;
+        let mono = detector.last_mono;
+
+        // Normal progress
+        assert!(detector
+            .observe(wall + Duration::from_secs(10), mono + Duration::from_secs(10))
+            .is_none());
+
+        // Wall clock stepped back by 5 s while 1 s passed
+        let step = detector
+            .observe(wall + Duration::from_secs(6), mono + Duration::from_secs(11))
+            .unwrap();
+        assert!((step + 5000.0).abs() < 1.0);
+    }
+}
@@ -1005,6 +1005,164 @@ impl From<HashMap<String, Value>> for Value {
     }
 }
 
+// ============================================================================
+// ARRAY AND OBJECT OPERATIONS (feature-gated)
+// ============================================================================
+
+/// Position of `index` in a sequence of `len` elements, counting from the
+/// end when negative
+#[cfg(feature = "extended-types")]
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let index = if index < 0 { len + index } else { index };
+    (0..len).contains(&index).then(|| usize::try_from(index).ok()).flatten()
+}
+
+#[cfg(feature = "extended-types")]
+impl Value {
+    /// Element `index` of an array, counting from the end when negative
+    /// 
+    /// Returns None for other types and indexes out of range.
+    /// 
+    /// ```rust
+    /// # use petra::Value;
+    /// let temps = Value::from(vec![Value::Float(20.5), Value::Float(21.0), Value::Float(22.5)]);
+    /// assert_eq!(temps.index(1), Some(&Value::Float(21.0)));
+    /// assert_eq!(temps.index(-1), Some(&Value::Float(22.5)));
+    /// assert_eq!(temps.index(3), None);
+    /// ```
+    #[must_use]
+    pub fn index(&self, index: i64) -> Option<&Value> {
+        let array = self.as_array()?;
+        array.get(resolve_index(index, array.len())?)
+    }
+    
+    /// Elements `start..end` of an array, counting from the end when
+    /// negative and to the end of the array without `end`
+    /// 
+    /// Bounds beyond the array are clamped to it, so the slice may be
+    /// empty. Returns None for other types.
+    #[must_use]
+    pub fn slice(&self, start: i64, end: Option<i64>) -> Option<Value> {
+        let array = self.as_array()?;
+        let len = i64::try_from(array.len()).ok()?;
+        let clamp = |bound: i64| {
+            let bound = if bound < 0 { len + bound } else { bound };
+            usize::try_from(bound.clamp(0, len)).unwrap_or_default()
+        };
+        let (start, end) = (clamp(start), clamp(end.unwrap_or(len)));
+        Some(Value::Array(array.get(start..end).unwrap_or_default().to_vec()))
+    }
+    
+    /// Field `name` of an object
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.as_object()?.get(name)
+    }
+    
+    /// Value at a dotted `path` through nested objects and arrays
+    /// 
+    /// Each segment names an object field or, on an array, an element
+    /// index, negative from the end. An empty path is the value itself.
+    /// 
+    /// ```rust
+    /// # use petra::Value;
+    /// # use std::collections::HashMap;
+    /// let motor = Value::from(HashMap::from([
+    ///     ("speed".to_string(), Value::Float(1450.0)),
+    ///     ("temps".to_string(), Value::from(vec![Value::Float(61.0), Value::Float(64.5)])),
+    /// ]));
+    /// let payload = Value::from(HashMap::from([("motor".to_string(), motor)]));
+    /// assert_eq!(payload.path("motor.speed"), Some(&Value::Float(1450.0)));
+    /// assert_eq!(payload.path("motor.temps.-1"), Some(&Value::Float(64.5)));
+    /// assert_eq!(payload.path("motor.current"), None);
+    /// ```
+    #[must_use]
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        path.split('.').try_fold(self, |value, segment| match value {
+            Self::Array(_) => value.index(segment.parse().ok()?),
+            _ => value.field(segment),
+        })
+    }
+    
+    /// Apply `op` element by element
+    /// 
+    /// Two arrays are combined pairwise and must have the same length; an
+    /// array and a scalar combine the scalar with every element; two
+    /// scalars are passed to `op` as they are.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns [`PlcError::Validation`] for arrays of different lengths and
+    /// the first error `op` returns.
+    pub fn zip_with<F>(&self, rhs: &Value, op: F) -> Result<Value>
+    where
+        F: Fn(Value, Value) -> Result<Value>,
+    {
+        match (self, rhs) {
+            (Self::Array(a), Self::Array(b)) => {
+                if a.len() != b.len() {
+                    return Err(PlcError::Validation(format!(
+                        "Element-wise operation on arrays of length {} and {}", a.len(), b.len()
+                    )));
+                }
+                a.iter().zip(b).map(|(a, b)| op(a.clone(), b.clone())).collect::<Result<_>>().map(Self::Array)
+            }
+            (Self::Array(a), b) => a.iter().map(|a| op(a.clone(), b.clone())).collect::<Result<_>>().map(Self::Array),
+            (a, Self::Array(b)) => b.iter().map(|b| op(a.clone(), b.clone())).collect::<Result<_>>().map(Self::Array),
+            (a, b) => op(a.clone(), b.clone()),
+        }
+    }
+}
+
+#[cfg(feature = "value-arithmetic")]
+impl Value {
+    /// Element-wise sum, see [`zip_with`](Self::zip_with)
+    /// 
+    /// Unlike `+`, which appends arrays, adds arrays element by element.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error for arrays of different lengths and elements that
+    /// cannot be added.
+    pub fn add_elementwise(&self, rhs: &Value) -> Result<Value> {
+        self.zip_with(rhs, |a, b| a + b)
+    }
+    
+    /// Element-wise difference, see [`zip_with`](Self::zip_with)
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error for arrays of different lengths and non-numeric
+    /// elements.
+    pub fn sub_elementwise(&self, rhs: &Value) -> Result<Value> {
+        self.zip_with(rhs, |a, b| a - b)
+    }
+    
+    /// Element-wise product, see [`zip_with`](Self::zip_with)
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error for arrays of different lengths and non-numeric
+    /// elements.
+    pub fn mul_elementwise(&self, rhs: &Value) -> Result<Value> {
+        self.zip_with(rhs, |a, b| a * b)
+    }
+    
+    /// Element-wise quotient, see [`zip_with`](Self::zip_with)
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error for arrays of different lengths, non-numeric
+    /// elements and division by zero.
+    pub fn div_elementwise(&self, rhs: &Value) -> Result<Value> {
+        self.zip_with(rhs, |a, b| a / b)
+    }
+}
+
 // ============================================================================
 // ARITHMETIC OPERATIONS (feature-gated)
 // ============================================================================
@@ -1158,7 +1316,7 @@ pub fn from_yaml_value(yaml: serde_yaml::Value) -> Result<Value> {
                 Value::from_str(&s)
             }
         }
-        serde_yaml::Value::Sequence(_seq) => {
+        serde_yaml::Value::Sequence(seq) => {
             #[cfg(feature = "extended-types")]
             {
                 let values: Result<Vec<Value>> = seq
@@ -1169,12 +1327,13 @@ pub fn from_yaml_value(yaml: serde_yaml::Value) -> Result<Value> {
             }
             #[cfg(not(feature = "extended-types"))]
             {
+                drop(seq);
                 Err(PlcError::Validation(
                     "Arrays not supported without extended-types feature".to_string()
                 ))
             }
         }
-        serde_yaml::Value::Mapping(_map) => {
+        serde_yaml::Value::Mapping(map) => {
             #[cfg(feature = "extended-types")]
             {
                 let mut object = HashMap::new();
@@ -1189,6 +1348,7 @@ pub fn from_yaml_value(yaml: serde_yaml::Value) -> Result<Value> {
             }
             #[cfg(not(feature = "extended-types"))]
             {
+                drop(map);
                 Err(PlcError::Validation(
                     "Objects not supported without extended-types feature".to_string()
                 ))
@@ -1354,6 +1514,39 @@ mod tests {
         assert!(obj_val.as_object().unwrap().contains_key("key"));
     }
     
+    #[cfg(feature = "extended-types")]
+    #[test]
+    fn test_array_and_object_access() {
+        let array = Value::Array((1..=5).map(Value::Integer).collect());
+        assert_eq!(array.index(-2), Some(&Value::Integer(4)));
+        assert_eq!(array.index(-6), None);
+        assert_eq!(array.slice(1, Some(3)), Some(Value::Array(vec![Value::Integer(2), Value::Integer(3)])));
+        assert_eq!(array.slice(-2, None), Some(Value::Array(vec![Value::Integer(4), Value::Integer(5)])));
+        assert_eq!(array.slice(4, Some(100)), Some(Value::Array(vec![Value::Integer(5)])));
+        assert_eq!(array.slice(3, Some(1)), Some(Value::Array(Vec::new())));
+        assert_eq!(Value::Integer(1).slice(0, None), None);
+        
+        let object = Value::Object(HashMap::from([("values".to_string(), array)]));
+        assert_eq!(object.path("values.0"), Some(&Value::Integer(1)));
+        assert_eq!(object.path("values.x"), None);
+        assert_eq!(object.path(""), Some(&object));
+        
+        let doubled = Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+            .zip_with(&Value::Integer(2), |a, b| Ok(Value::Integer(a.as_integer().unwrap() * b.as_integer().unwrap())));
+        assert_eq!(doubled.unwrap(), Value::Array(vec![Value::Integer(2), Value::Integer(4)]));
+        assert!(Value::Array(Vec::new()).zip_with(&Value::Array(vec![Value::Bool(true)]), |a, _| Ok(a)).is_err());
+    }
+    
+    #[cfg(feature = "value-arithmetic")]
+    #[test]
+    fn test_elementwise_arithmetic() {
+        let a = Value::Array(vec![Value::Float(1.0), Value::Integer(4)]);
+        let b = Value::Array(vec![Value::Float(0.5), Value::Integer(2)]);
+        assert_eq!(a.add_elementwise(&b).unwrap(), Value::Array(vec![Value::Float(1.5), Value::Integer(6)]));
+        assert_eq!(a.div_elementwise(&Value::Integer(2)).unwrap(), Value::Array(vec![Value::Float(0.5), Value::Float(2.0)]));
+        assert!(a.div_elementwise(&Value::Integer(0)).is_err());
+    }
+    
     #[cfg(feature = "quality-codes")]
     #[test]
     fn test_quality_codes() {
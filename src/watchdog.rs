@@ -0,0 +1,485 @@
+// src/watchdog.rs
+//! Scan watchdog with heartbeat output and fail-safe actions
+//!
+//! The engine feeds the watchdog after every healthy scan cycle. Each feed
+//! toggles a heartbeat signal; mapped to a Modbus coil, an MQTT topic or a
+//! digital output through the protocol driving it, the heartbeat lets an
+//! external watchdog relay drop out when PETRA stops scanning. Scan tasks
+//! feed the watchdog after each of their cycles too. When the main scan or
+//! any task completes no cycle for `timeout_ms` the watchdog trips on its
+//! own task:
+//!
+//! 1. the heartbeat stops,
+//! 2. the `safe_state` values are forced onto their signals,
+//! 3. the `shutdown_blocks` run once on the bus, and
+//! 4. with `latch` set, block execution is paused so a scan loop that
+//!    comes back cannot drive the outputs out of the safe state again.
+//!
+//! ```yaml
+//! watchdog:
+//!   timeout_ms: 500
+//!   heartbeat: plc.heartbeat
+//!   safe_state:
+//!     valve_open: false
+//!     pump_speed: 0.0
+//!   shutdown_blocks:
+//!     - name: raise_alarm
+//!       type: NOT
+//!       inputs: { in: valve_open }
+//!       outputs: { out: stall_alarm }
+//!   latch: true
+//! ```
+//!
+//! A latched trip holds the safe state until `watchdog.reset` is set to
+//! `true`, which resumes block execution and the heartbeat. Without
+//! `latch` the watchdog clears itself on the next healthy cycle.
+//!
+//! Published signals (prefix configurable, default `watchdog`):
+//!
+//! - `watchdog.tripped` - the watchdog tripped and was not reset yet
+//! - `watchdog.trips` - trips since startup
+//! - `watchdog.reset` - set to `true` to reset a latched trip
+
+use crate::blocks::{create_block, Block};
+use crate::config::{BlockConfig, Validatable};
+use crate::engine::EngineControl;
+use crate::error::{PlcError, Result};
+use crate::events::{EventKind, EventLog};
+use crate::signal::SignalBus;
+use crate::value::{from_yaml_value, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Watchdog configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Time without a healthy scan cycle after which the watchdog trips
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Boolean signal toggled after every healthy scan cycle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat: Option<String>,
+
+    /// Values forced onto signals when the watchdog trips
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub safe_state: BTreeMap<String, serde_yaml::Value>,
+
+    /// Blocks executed once, after the safe state is applied, when the
+    /// watchdog trips; they are not part of any scan
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shutdown_blocks: Vec<BlockConfig>,
+
+    /// Pause block execution on a trip and hold the safe state until reset
+    #[serde(default = "default_latch")]
+    pub latch: bool,
+
+    /// Prefix of the published status signals
+    #[serde(default = "default_signal_prefix")]
+    pub signal_prefix: String,
+}
+
+const fn default_timeout_ms() -> u64 {
+    1_000
+}
+
+const fn default_latch() -> bool {
+    true
+}
+
+fn default_signal_prefix() -> String {
+    "watchdog".to_string()
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_timeout_ms(),
+            heartbeat: None,
+            safe_state: BTreeMap::new(),
+            shutdown_blocks: Vec::new(),
+            latch: default_latch(),
+            signal_prefix: default_signal_prefix(),
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Validate the timeout, safe state and shutdown blocks
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.timeout_ms == 0 {
+            return Err(PlcError::Config("Watchdog timeout_ms must be greater than 0".to_string()));
+        }
+        if self.heartbeat.as_ref().is_some_and(|signal| signal.trim().is_empty()) {
+            return Err(PlcError::Config("Watchdog heartbeat signal cannot be empty".to_string()));
+        }
+        self.safe_values()?;
+        let mut names = HashSet::new();
+        for block in &self.shutdown_blocks {
+            if !names.insert(&block.name) {
+                return Err(PlcError::Config(format!(
+                    "Duplicate watchdog shutdown block name: '{}'",
+                    block.name
+                )));
+            }
+            block.validate()?;
+        }
+        Ok(())
+    }
+
+    fn safe_values(&self) -> Result<Vec<(String, Value)>> {
+        self.safe_state
+            .iter()
+            .map(|(signal, value)| {
+                let value = from_yaml_value(value.clone()).map_err(|e| {
+                    PlcError::Config(format!("Invalid watchdog safe state for '{signal}': {e}"))
+                })?;
+                Ok((signal.clone(), value))
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// WATCHDOG
+// ============================================================================
+
+/// Supervises the scan loop and the scan tasks and applies the fail-safe
+/// actions
+///
+/// Cloning yields another handle to the same watchdog.
+#[derive(Clone)]
+pub struct Watchdog {
+    config: Arc<WatchdogConfig>,
+    bus: SignalBus,
+    events: EventLog,
+    safe_state: Arc<Vec<(String, Value)>>,
+    shutdown_blocks: Arc<tokio::sync::Mutex<Vec<Box<dyn Block>>>>,
+    last_feed: Arc<Mutex<Instant>>,
+    /// Last healthy cycle of each supervised scan task
+    task_feeds: Arc<Mutex<HashMap<String, Instant>>>,
+    tripped: Arc<AtomicBool>,
+    beat: Arc<AtomicBool>,
+    trips: Arc<AtomicU64>,
+}
+
+impl Watchdog {
+    /// Create a watchdog and publish its status signals
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] if the configuration is invalid or a
+    /// shutdown block cannot be created.
+    pub fn new(config: WatchdogConfig, bus: SignalBus, events: EventLog) -> Result<Self> {
+        config.validate()?;
+        let safe_state = config.safe_values()?;
+        let shutdown_blocks = config
+            .shutdown_blocks
+            .iter()
+            .map(|block| {
+                create_block(block).map_err(|e| {
+                    PlcError::Config(format!("Failed to create watchdog shutdown block '{}': {}", block.name, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let watchdog = Self {
+            config: Arc::new(config),
+            bus,
+            events,
+            safe_state: Arc::new(safe_state),
+            shutdown_blocks: Arc::new(tokio::sync::Mutex::new(shutdown_blocks)),
+            last_feed: Arc::new(Mutex::new(Instant::now())),
+            task_feeds: Arc::default(),
+            tripped: Arc::new(AtomicBool::new(false)),
+            beat: Arc::new(AtomicBool::new(false)),
+            trips: Arc::new(AtomicU64::new(0)),
+        };
+        watchdog.publish("tripped", Value::Bool(false));
+        watchdog.publish("trips", Value::Integer(0));
+        watchdog.publish("reset", Value::Bool(false));
+        Ok(watchdog)
+    }
+
+    /// Time without a healthy cycle after which the watchdog trips
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.config.timeout_ms)
+    }
+
+    /// Whether the watchdog tripped and was not reset yet
+    #[must_use]
+    pub fn is_tripped(&self) -> bool {
+        self.tripped.load(Ordering::Acquire)
+    }
+
+    /// Trips since startup
+    #[must_use]
+    pub fn trips(&self) -> u64 {
+        self.trips.load(Ordering::Relaxed)
+    }
+
+    /// Supervise the scan task `task` as well as the main scan
+    ///
+    /// Its timeout starts now; the task reports its cycles through
+    /// [`feed_task`](Self::feed_task).
+    pub fn supervise(&self, task: &str) {
+        self.lock_task_feeds().insert(task.to_string(), Instant::now());
+    }
+
+    /// Report a healthy scan cycle
+    ///
+    /// Toggles the heartbeat. After a latched trip the heartbeat stays
+    /// stopped and the safe state is applied again instead, so nothing
+    /// written in the meantime moves the outputs; an unlatched trip is
+    /// cleared once no supervised task is stalled either.
+    pub fn feed(&self) {
+        *self.last_feed.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+        if self.is_tripped() {
+            if self.config.latch {
+                self.apply_safe_state();
+                return;
+            }
+            if self.stalest().1 > self.timeout() {
+                return;
+            }
+            self.clear("scan cycles resumed");
+        }
+        if let Some(heartbeat) = &self.config.heartbeat {
+            let beat = !self.beat.fetch_xor(true, Ordering::Relaxed);
+            if let Err(e) = self.bus.set(heartbeat, Value::Bool(beat)) {
+                warn!("Failed to write watchdog heartbeat '{}': {}", heartbeat, e);
+            }
+        }
+    }
+
+    /// Report a healthy cycle of the supervised scan task `task`
+    pub fn feed_task(&self, task: &str) {
+        if let Some(last) = self.lock_task_feeds().get_mut(task) {
+            *last = Instant::now();
+        }
+    }
+
+    /// Check the scan loop and the supervised tasks once, tripping the
+    /// watchdog if one of them stalled and resetting a latched trip that
+    /// was requested through the reset signal
+    pub async fn check(&self, control: &EngineControl) {
+        if self.is_tripped() {
+            if self.bus.get(self.signal("reset")).is_some_and(|v| v.as_bool() == Some(true)) {
+                self.reset(control).await;
+            }
+            return;
+        }
+        let (task, stalled) = self.stalest();
+        if stalled > self.timeout() {
+            self.trip(task.as_deref(), stalled, control).await;
+        }
+    }
+
+    /// Reset a trip and resume block execution
+    pub async fn reset(&self, control: &EngineControl) {
+        if !self.is_tripped() {
+            return;
+        }
+        self.restart_timeout();
+        self.clear("reset");
+        if self.config.latch {
+            control.resume().await;
+        }
+    }
+
+    /// Supervise the scan loop on a task of its own, checking four times
+    /// per timeout
+    ///
+    /// The timeout starts over, so time spent between creating the
+    /// watchdog and starting the engine does not count as a stall.
+    #[must_use]
+    pub fn spawn(&self, control: EngineControl) -> JoinHandle<()> {
+        self.restart_timeout();
+        let watchdog = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(watchdog.timeout() / 4);
+            loop {
+                ticker.tick().await;
+                watchdog.check(&control).await;
+            }
+        })
+    }
+
+    /// Scan task that went longest without a healthy cycle, `None` for
+    /// the main scan, and the time since that cycle
+    fn stalest(&self) -> (Option<String>, Duration) {
+        let main = self.last_feed.lock().unwrap_or_else(PoisonError::into_inner).elapsed();
+        self.lock_task_feeds()
+            .iter()
+            .map(|(task, last)| (Some(task.clone()), last.elapsed()))
+            .fold((None, main), |stalest, task| if task.1 > stalest.1 { task } else { stalest })
+    }
+
+    fn restart_timeout(&self) {
+        let now = Instant::now();
+        *self.last_feed.lock().unwrap_or_else(PoisonError::into_inner) = now;
+        self.lock_task_feeds().values_mut().for_each(|last| *last = now);
+    }
+
+    fn lock_task_feeds(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        self.task_feeds.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn trip(&self, task: Option<&str>, stalled: Duration, control: &EngineControl) {
+        self.tripped.store(true, Ordering::Release);
+        let trips = self.trips.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(task) = task {
+            error!("Watchdog tripped: no healthy cycle of task '{}' for {:?}", task, stalled);
+        } else {
+            error!("Watchdog tripped: no healthy scan cycle for {:?}", stalled);
+        }
+
+        if self.config.latch {
+            control.pause().await;
+        }
+        self.apply_safe_state();
+        for block in self.shutdown_blocks.lock().await.iter_mut() {
+            if let Err(e) = block.execute(&self.bus) {
+                error!("Watchdog shutdown block '{}' failed: {}", block.name(), e);
+            }
+        }
+
+        self.publish("tripped", Value::Bool(true));
+        self.publish("trips", Value::Integer(i64::try_from(trips).unwrap_or(i64::MAX)));
+        self.events.publish(EventKind::WatchdogTripped {
+            stalled_ms: u64::try_from(stalled.as_millis()).unwrap_or(u64::MAX),
+        });
+    }
+
+    fn clear(&self, reason: &str) {
+        self.tripped.store(false, Ordering::Release);
+        self.publish("tripped", Value::Bool(false));
+        self.publish("reset", Value::Bool(false));
+        info!("Watchdog cleared: {}", reason);
+        self.events.publish(EventKind::WatchdogReset { reason: reason.to_string() });
+    }
+
+    fn apply_safe_state(&self) {
+        for (signal, value) in self.safe_state.iter() {
+            if let Err(e) = self.bus.set(signal, value.clone()) {
+                error!("Failed to force '{}' to its safe state: {}", signal, e);
+            }
+        }
+    }
+
+    fn signal(&self, name: &str) -> String {
+        format!("{}.{}", self.config.signal_prefix, name)
+    }
+
+    fn publish(&self, name: &str, value: Value) {
+        if let Err(e) = self.bus.set(self.signal(name), value) {
+            warn!("Failed to publish watchdog signal '{}': {}", name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::Config;
+
+    fn watchdog(yaml: &str) -> (Watchdog, EngineControl, SignalBus) {
+        let config: WatchdogConfig = serde_yaml::from_str(yaml).unwrap();
+        let engine = Engine::new(Config { blocks: Vec::new(), ..Config::example_basic().unwrap() }).unwrap();
+        let bus = engine.signal_bus().clone();
+        let watchdog = Watchdog::new(config, bus.clone(), engine.events()).unwrap();
+        (watchdog, engine.engine_control(), bus)
+    }
+
+    #[tokio::test]
+    async fn test_latched_trip_forces_safe_state_until_reset() {
+        let (watchdog, control, bus) = watchdog(
+            "timeout_ms: 10\nheartbeat: hb\nsafe_state: { valve: false, speed: 0.0 }\n\
+             shutdown_blocks:\n  - { name: horn, type: NOT, inputs: { in: valve }, outputs: { out: horn } }\n",
+        );
+        watchdog.feed();
+        assert_eq!(bus.get("hb"), Some(Value::Bool(true)));
+        watchdog.feed();
+        assert_eq!(bus.get("hb"), Some(Value::Bool(false)));
+
+        bus.set("valve", Value::Bool(true)).unwrap();
+        bus.set("speed", Value::Float(40.0)).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        watchdog.check(&control).await;
+        assert!(watchdog.is_tripped());
+        assert!(control.is_paused());
+        assert_eq!(bus.get("valve"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("speed"), Some(Value::Float(0.0)));
+        assert_eq!(bus.get("horn"), Some(Value::Bool(true)));
+        assert_eq!(bus.get("watchdog.trips"), Some(Value::Integer(1)));
+
+        // A latched trip holds the safe state and the heartbeat
+        bus.set("valve", Value::Bool(true)).unwrap();
+        watchdog.feed();
+        assert_eq!(bus.get("valve"), Some(Value::Bool(false)));
+        assert_eq!(bus.get("hb"), Some(Value::Bool(false)));
+
+        bus.set("watchdog.reset", Value::Bool(true)).unwrap();
+        watchdog.check(&control).await;
+        assert!(!watchdog.is_tripped());
+        assert!(!control.is_paused());
+        assert_eq!(bus.get("watchdog.reset"), Some(Value::Bool(false)));
+    }
+
+    #[tokio::test]
+    async fn test_unlatched_trip_clears_on_feed() {
+        let (watchdog, control, bus) = watchdog("timeout_ms: 10\nlatch: false\nsafe_state: { valve: false }\n");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        watchdog.check(&control).await;
+        assert!(watchdog.is_tripped());
+        assert!(!control.is_paused());
+        assert_eq!(bus.get("watchdog.tripped"), Some(Value::Bool(true)));
+
+        watchdog.feed();
+        assert!(!watchdog.is_tripped());
+        assert_eq!(bus.get("watchdog.tripped"), Some(Value::Bool(false)));
+
+        assert!(serde_yaml::from_str::<WatchdogConfig>("timeout_ms: 0\n").unwrap().validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_task_trips() {
+        let (watchdog, control, _bus) = watchdog("timeout_ms: 100\nlatch: false\n");
+        watchdog.supervise("slow");
+        watchdog.supervise("fast");
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        watchdog.feed();
+        watchdog.feed_task("fast");
+        watchdog.check(&control).await;
+        assert!(!watchdog.is_tripped());
+
+        // The main scan and one task keep cycling; the stalest task trips
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        watchdog.feed();
+        watchdog.feed_task("fast");
+        watchdog.check(&control).await;
+        assert!(watchdog.is_tripped());
+
+        // Healthy main scan cycles do not clear the trip of a stalled task
+        watchdog.feed();
+        assert!(watchdog.is_tripped());
+        watchdog.feed_task("slow");
+        watchdog.feed();
+        assert!(!watchdog.is_tripped());
+    }
+}
@@ -0,0 +1,32 @@
+//! Audit trail queries
+//!
+//! `GET /api/audit` returns recorded operator actions, newest first. The
+//! query parameters `user`, `action`, `target`, `since` and `until` (RFC 3339)
+//! filter the entries and `limit` caps them, 100 by default:
+//!
+//! ```text
+//! GET /api/audit?action=signal.write&target=line1.setpoint&since=2024-05-01T00:00:00Z
+//! ```
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+use super::AppState;
+use crate::security::audit::{AuditEntry, AuditQuery};
+use crate::{PlcError, Result};
+
+/// `GET /api/audit`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] when no audit log is configured, or an I/O
+/// error if the log cannot be read.
+pub async fn query(State(state): State<AppState>, Query(query): Query<AuditQuery>) -> Result<Json<Vec<AuditEntry>>> {
+    let audit = state
+        .audit
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Audit log is not configured".to_string()))?;
+    Ok(Json(audit.query(&query)?))
+}
@@ -0,0 +1,317 @@
+//! Bulk signal writes
+//!
+//! `POST /api/signals:batch` writes many signals in one request. Every item
+//! is checked against its signal configuration (type, engineering range and
+//! validation rules) before anything is written; the accepted values are then
+//! applied together through [`SignalBus::write_transaction`](crate::SignalBus::write_transaction),
+//! so a batch either takes effect completely or not at all:
+//!
+//! ```json
+//! {
+//!   "writes": [
+//!     { "signal": "line1.speed", "value": { "type": "Float", "value": 42.5 } },
+//!     { "signal": "line1.enable", "value": { "type": "Bool", "value": true } }
+//!   ]
+//! }
+//! ```
+//!
+//! An optional top-level `reason` is recorded with every write in the audit
+//! log; writes to critical signals are rejected without one. A `signature`
+//! signs the whole batch when any of its writes must be signed.
+//!
+//! The response lists a result for each item in request order. When any item
+//! is rejected the status is `422` and the remaining items are reported as
+//! `not_applied`.
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+use super::{Approval, AppState, Attestation};
+use crate::config::SignalConfig;
+use crate::{PlcError, Result, Value};
+
+/// Route suffix selecting the batch write
+///
+/// The router reads `:` as the start of a path parameter, so the route is
+/// registered as `/api/signals:action` and the captured suffix checked here.
+const BATCH_ACTION: &str = ":batch";
+
+/// Most writes accepted in a single batch
+pub const MAX_BATCH_WRITES: usize = 1000;
+
+/// Request body of `POST /api/signals:batch`
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchWriteRequest {
+    pub writes: Vec<BatchWrite>,
+    #[serde(flatten)]
+    pub attestation: Attestation,
+}
+
+/// One signal write within a batch
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchWrite {
+    pub signal: String,
+    pub value: Value,
+}
+
+/// Outcome of one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    /// The value was written
+    Applied,
+    /// The item failed validation
+    Rejected,
+    /// The item was valid but the batch was not applied
+    NotApplied,
+}
+
+/// Result reported for one item
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItemResult {
+    pub signal: String,
+    pub status: BatchItemStatus,
+    /// Value as written, after conversion to the signal type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response body of `POST /api/signals:batch`
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchWriteResponse {
+    /// Whether the writes were applied
+    pub applied: bool,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Validate every write against the configured signals
+///
+/// `check_reason` is asked whether each signal may be written with the
+/// batch's reason. Returns the per-item results and, if every item passed,
+/// the values to write.
+fn check_writes(
+    signals: &[SignalConfig],
+    writes: Vec<BatchWrite>,
+    check_reason: impl Fn(&str) -> Result<()>,
+) -> (Vec<BatchItemResult>, Option<Vec<(String, Value)>>) {
+    let configs: HashMap<&str, &SignalConfig> =
+        signals.iter().map(|s| (s.name.as_str(), s)).collect();
+    let mut seen = HashSet::new();
+    let mut all_valid = true;
+
+    let results: Vec<BatchItemResult> = writes
+        .into_iter()
+        .map(|write| {
+            let checked = if seen.insert(write.signal.clone()) {
+                configs
+                    .get(write.signal.as_str())
+                    .ok_or_else(|| format!("Signal '{}' is not configured", write.signal))
+                    .and_then(|config| config.check_write(write.value).map_err(|e| e.to_string()))
+                    .and_then(|value| check_reason(&write.signal).map(|()| value).map_err(|e| e.to_string()))
+            } else {
+                Err(format!("Signal '{}' appears more than once", write.signal))
+            };
+            match checked {
+                Ok(value) => BatchItemResult {
+                    signal: write.signal,
+                    status: BatchItemStatus::Applied,
+                    value: Some(value),
+                    error: None,
+                },
+                Err(error) => {
+                    all_valid = false;
+                    BatchItemResult {
+                        signal: write.signal,
+                        status: BatchItemStatus::Rejected,
+                        value: None,
+                        error: Some(error),
+                    }
+                }
+            }
+        })
+        .collect();
+
+    let updates = all_valid.then(|| {
+        results
+            .iter()
+            .filter_map(|r| r.value.clone().map(|v| (r.signal.clone(), v)))
+            .collect()
+    });
+    (results, updates)
+}
+
+/// Mark every item that was not itself rejected as not applied
+fn mark_not_applied(results: &mut [BatchItemResult], error: Option<&str>) {
+    for result in results.iter_mut().filter(|r| r.status == BatchItemStatus::Applied) {
+        result.status = BatchItemStatus::NotApplied;
+        result.error = error.map(str::to_string);
+    }
+}
+
+/// `POST /api/signals:batch`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for any other suffix and
+/// [`PlcError::Validation`] for an empty batch or one larger than
+/// [`MAX_BATCH_WRITES`].
+pub async fn write_signals(
+    Path(action): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(request): Json<BatchWriteRequest>,
+) -> Result<(StatusCode, Json<BatchWriteResponse>)> {
+    if action != BATCH_ACTION {
+        return Err(PlcError::NotFound(format!("/api/signals{action}")));
+    }
+    if request.writes.is_empty() {
+        return Err(PlcError::Validation("Batch contains no writes".to_string()));
+    }
+    if request.writes.len() > MAX_BATCH_WRITES {
+        return Err(PlcError::Validation(format!(
+            "Batch contains {} writes, at most {MAX_BATCH_WRITES} are allowed",
+            request.writes.len()
+        )));
+    }
+
+    let attestation = request.attestation;
+    let reason = attestation.reason.as_deref();
+    let (mut results, updates) = check_writes(&state.config.read().await.signals, request.writes, |signal| {
+        state.check_reason(signal, reason)
+    });
+    let Some(updates) = updates else {
+        mark_not_applied(&mut results, None);
+        warn!("Rejected batch write of {} signals", results.len());
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(BatchWriteResponse { applied: false, results }),
+        ));
+    };
+
+    let old_values: Vec<Option<Value>> = updates.iter().map(|(name, _)| state.signal_bus.get(name)).collect();
+    let source = peer.ip().to_string();
+    let audit = |approval: &Approval, error: Option<&str>| {
+        for ((name, value), old_value) in updates.iter().zip(&old_values) {
+            let result = error.map_or(Ok(()), Err);
+            state.audit_write(
+                super::ANONYMOUS_USER,
+                &source,
+                name,
+                old_value.clone(),
+                value,
+                &attestation,
+                approval,
+                result,
+            );
+        }
+    };
+
+    let writes: Vec<(&str, &Value)> = updates.iter().map(|(name, value)| (name.as_str(), value)).collect();
+    let approval = match state.approve(&writes, &attestation) {
+        Ok(approval) => approval,
+        Err(e) => {
+            audit(&Approval::default(), Some(&e.to_string()));
+            return Err(e);
+        }
+    };
+    let error = state
+        .signal_bus
+        .write_transaction(updates.clone())
+        .err()
+        .map(|e| e.to_string());
+    audit(&approval, error.as_deref());
+
+    if let Some(error) = error {
+        mark_not_applied(&mut results, Some(&error));
+        warn!("Batch write failed and was rolled back: {}", error);
+        return Ok((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(BatchWriteResponse { applied: false, results }),
+        ));
+    }
+
+    info!("Applied batch write of {} signals", results.len());
+    Ok((StatusCode::OK, Json(BatchWriteResponse { applied: true, results })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writes(json: &str) -> Vec<BatchWrite> {
+        serde_json::from_str::<BatchWriteRequest>(json).unwrap().writes
+    }
+
+    #[test]
+    fn test_batch_validation() {
+        let signals: Vec<SignalConfig> = serde_yaml::from_str(
+            "- name: speed\n  type: float\n- name: enable\n  type: bool\n",
+        )
+        .unwrap();
+
+        // Integers are converted for float signals
+        let (results, updates) = check_writes(
+            &signals,
+            writes(
+                r#"{"writes": [
+                    {"signal": "speed", "value": {"type": "Integer", "value": 40}},
+                    {"signal": "enable", "value": {"type": "Bool", "value": true}}
+                ]}"#,
+            ),
+            |_| Ok(()),
+        );
+        assert!(results.iter().all(|r| r.status == BatchItemStatus::Applied));
+        assert_eq!(
+            updates.unwrap(),
+            vec![
+                ("speed".to_string(), Value::Float(40.0)),
+                ("enable".to_string(), Value::Bool(true)),
+            ]
+        );
+
+        // One bad item rejects the batch
+        let (mut results, updates) = check_writes(
+            &signals,
+            writes(
+                r#"{"writes": [
+                    {"signal": "speed", "value": {"type": "Float", "value": 1.5}},
+                    {"signal": "enable", "value": {"type": "Float", "value": 1.0}},
+                    {"signal": "missing", "value": {"type": "Bool", "value": true}},
+                    {"signal": "speed", "value": {"type": "Float", "value": 2.5}}
+                ]}"#,
+            ),
+            |_| Ok(()),
+        );
+        assert!(updates.is_none());
+        mark_not_applied(&mut results, None);
+        let statuses: Vec<_> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BatchItemStatus::NotApplied,
+                BatchItemStatus::Rejected,
+                BatchItemStatus::Rejected,
+                BatchItemStatus::Rejected,
+            ]
+        );
+        assert!(results[1].error.as_deref().unwrap().contains("expects bool"));
+
+        // Critical signals without a reason are rejected
+        let (results, updates) = check_writes(
+            &signals,
+            writes(r#"{"writes": [{"signal": "speed", "value": {"type": "Float", "value": 1.5}}]}"#),
+            |signal| Err(PlcError::Validation(format!("Signal '{signal}' is critical; writes require a reason"))),
+        );
+        assert!(updates.is_none());
+        assert!(results[0].error.as_deref().unwrap().contains("critical"));
+    }
+}
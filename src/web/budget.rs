@@ -0,0 +1,21 @@
+//! Scan budget endpoints
+//!
+//! Cumulative per-subsystem time and allocation counters, as JSON for
+//! `petra top` and in the Prometheus text format for scraping.
+
+use axum::{http::header, response::IntoResponse, Json};
+
+use crate::scan_budget::{snapshot, BudgetSnapshot};
+
+/// Current cumulative counters
+pub async fn get_budget() -> Json<BudgetSnapshot> {
+    Json(snapshot())
+}
+
+/// Current cumulative counters in the Prometheus text exposition format
+pub async fn get_budget_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        snapshot().to_prometheus(),
+    )
+}
@@ -0,0 +1,166 @@
+// src/web/coalesce.rs
+//
+// Rate-limited signal groups for WebSocket clients on slow links. A client
+// subscribes a named group of signals with the shortest time between two
+// updates it wants:
+//
+// ```json
+// {"type": "subscribe_group", "group": "line1", "signals": ["line1.speed", "line1.count"], "min_interval_ms": 2000}
+// ```
+//
+// The server samples the signals at its normal update rate but sends at most
+// one `group_update` per interval, holding only the latest value of each
+// signal that changed and how many changes were folded into it:
+//
+// ```json
+// {"type": "group_update", "group": "line1", "timestamp": 1718000000000,
+//  "values": {"line1.count": 1042}, "changes": {"line1.count": 17}, "unchanged": 1}
+// ```
+//
+// The first update of a group carries every signal. Intervals below
+// `MIN_GROUP_INTERVAL` are raised to it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use crate::signal::SignalBus;
+use crate::Value;
+
+/// Shortest interval a group can be updated at
+pub const MIN_GROUP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval used when a subscription does not name one
+pub const DEFAULT_GROUP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Coalesced values of a group sent in one message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupUpdate {
+    /// Group name chosen by the client
+    pub group: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Latest value of each changed signal
+    pub values: BTreeMap<String, Value>,
+    /// Changes seen per signal since the previous update
+    pub changes: BTreeMap<String, u32>,
+    /// Signals of the group that did not change
+    pub unchanged: usize,
+}
+
+/// A subscribed group and the changes not yet sent
+#[derive(Debug)]
+pub struct SignalGroup {
+    name: String,
+    signals: Vec<String>,
+    interval: Duration,
+    /// Value last sampled per signal
+    sampled: HashMap<String, Value>,
+    /// Changes since the last update
+    changes: HashMap<String, u32>,
+    last_sent: Option<Instant>,
+}
+
+impl SignalGroup {
+    /// Group `signals`, sent at most once per `interval`
+    #[must_use]
+    pub fn new(name: String, signals: Vec<String>, interval: Option<Duration>) -> Self {
+        Self {
+            name,
+            signals,
+            interval: interval.unwrap_or(DEFAULT_GROUP_INTERVAL).max(MIN_GROUP_INTERVAL),
+            sampled: HashMap::new(),
+            changes: HashMap::new(),
+            last_sent: None,
+        }
+    }
+
+    /// Time between two updates
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Record the signals that changed since the last sample
+    pub fn sample(&mut self, bus: &SignalBus, same: impl Fn(&Value, &Value) -> bool) {
+        for signal in &self.signals {
+            let Some(value) = bus.get(signal) else {
+                continue;
+            };
+            if self.sampled.get(signal).is_some_and(|last| same(last, &value)) {
+                continue;
+            }
+            *self.changes.entry(signal.clone()).or_default() += 1;
+            self.sampled.insert(signal.clone(), value);
+        }
+    }
+
+    /// Update to send if the interval has passed and something changed
+    pub fn flush(&mut self, now: Instant, timestamp: u64) -> Option<GroupUpdate> {
+        if self.last_sent.is_some_and(|sent| now.duration_since(sent) < self.interval) {
+            return None;
+        }
+        if self.changes.is_empty() {
+            return None;
+        }
+        self.last_sent = Some(now);
+        let changes: BTreeMap<String, u32> = self.changes.drain().collect();
+        let values = changes
+            .keys()
+            .filter_map(|signal| self.sampled.get(signal).map(|v| (signal.clone(), v.clone())))
+            .collect();
+        Some(GroupUpdate {
+            group: self.name.clone(),
+            timestamp,
+            values,
+            unchanged: self.signals.len().saturating_sub(changes.len()),
+            changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_coalesced_until_the_interval_passes() {
+        let bus = SignalBus::new();
+        bus.set("a", Value::Integer(1)).unwrap();
+        bus.set("b", Value::Bool(false)).unwrap();
+        let mut group = SignalGroup::new(
+            "line1".to_string(),
+            vec!["a".to_string(), "b".to_string(), "missing".to_string()],
+            Some(Duration::from_millis(500)),
+        );
+        let start = Instant::now();
+
+        // The first update carries every signal
+        group.sample(&bus, |x, y| x == y);
+        let update = group.flush(start, 1).unwrap();
+        assert_eq!(update.values.len(), 2);
+        assert_eq!(update.unchanged, 1);
+
+        // Three changes within the interval become one value
+        for i in 2..=4 {
+            bus.set("a", Value::Integer(i)).unwrap();
+            group.sample(&bus, |x, y| x == y);
+        }
+        assert!(group.flush(start + Duration::from_millis(200), 2).is_none());
+        let update = group.flush(start + Duration::from_millis(500), 3).unwrap();
+        assert_eq!(update.values, BTreeMap::from([("a".to_string(), Value::Integer(4))]));
+        assert_eq!(update.changes["a"], 3);
+        assert_eq!(update.unchanged, 2);
+
+        // Nothing changed: nothing is sent
+        group.sample(&bus, |x, y| x == y);
+        assert!(group.flush(start + Duration::from_secs(5), 4).is_none());
+    }
+
+    #[test]
+    fn test_interval_is_limited() {
+        let group = SignalGroup::new("g".to_string(), Vec::new(), Some(Duration::from_millis(1)));
+        assert_eq!(group.interval(), MIN_GROUP_INTERVAL);
+        let group = SignalGroup::new("g".to_string(), Vec::new(), None);
+        assert_eq!(group.interval(), DEFAULT_GROUP_INTERVAL);
+    }
+}
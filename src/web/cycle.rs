@@ -0,0 +1,39 @@
+//! Scan cycle breakdown endpoints
+//!
+//! Percentiles of the main scan's phase, cycle and jitter histograms, as
+//! JSON for `petra engine stats` and as Prometheus summaries for scraping.
+
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+
+use super::AppState;
+use crate::engine::{CycleBreakdown, CycleTimings};
+use crate::{PlcError, Result};
+
+fn cycle_timings(state: &AppState) -> Result<&CycleTimings> {
+    state
+        .cycle_timings
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Cycle timings are not available".to_string()))
+}
+
+/// Phase, cycle and jitter percentiles since startup
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the server has no engine attached.
+pub async fn get_breakdown(State(state): State<AppState>) -> Result<Json<CycleBreakdown>> {
+    Ok(Json(cycle_timings(&state)?.breakdown()))
+}
+
+/// Phase, cycle and jitter percentiles in the Prometheus text exposition
+/// format
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the server has no engine attached.
+pub async fn get_breakdown_metrics(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        cycle_timings(&state)?.breakdown().to_prometheus(),
+    ))
+}
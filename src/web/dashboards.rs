@@ -0,0 +1,443 @@
+//! Runtime process monitoring dashboards
+//!
+//! Dashboards are collections of widgets bound to signals. The frontend
+//! renders them and subscribes to the bound signals over the WebSocket API
+//! (`subscribe_signals`), so the server only stores layouts and checks that
+//! every binding points at a configured signal.
+//!
+//! Layouts are stored per user. The user is taken from the `X-Petra-User`
+//! header; requests without it share the `default` user. When
+//! `web.dashboard_dir` is configured each user's dashboards are written to
+//! `<dashboard_dir>/<user>.json`.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::request::Parts,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use super::AppState;
+use crate::{PlcError, Result};
+
+/// Header carrying the user a dashboard request is made for
+pub const USER_HEADER: &str = "x-petra-user";
+
+const DEFAULT_USER: &str = "default";
+
+// ============================================================================
+// LAYOUT MODEL
+// ============================================================================
+
+/// Grid position and size of a widget
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct WidgetPosition {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// Widget-specific bindings and display options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetKind {
+    /// Time trend of one or more numeric signals
+    Trend {
+        signals: Vec<String>,
+        #[serde(default = "default_trend_window")]
+        window_secs: u32,
+    },
+    /// Single numeric value against a range
+    Gauge {
+        signal: String,
+        min: f64,
+        max: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unit: Option<String>,
+    },
+    /// On/off indicator for a boolean signal
+    BooleanIndicator {
+        signal: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        true_label: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        false_label: Option<String>,
+    },
+    /// Banner listing the active signals out of a set of alarm signals
+    AlarmBanner { signals: Vec<String> },
+}
+
+/// A widget placed on a dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Widget {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub position: WidgetPosition,
+    #[serde(flatten)]
+    pub kind: WidgetKind,
+}
+
+impl Widget {
+    /// Signals this widget reads
+    pub fn signals(&self) -> Vec<&str> {
+        match &self.kind {
+            WidgetKind::Trend { signals, .. } | WidgetKind::AlarmBanner { signals } => {
+                signals.iter().map(String::as_str).collect()
+            }
+            WidgetKind::Gauge { signal, .. } | WidgetKind::BooleanIndicator { signal, .. } => {
+                vec![signal.as_str()]
+            }
+        }
+    }
+}
+
+/// A named dashboard layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dashboard {
+    pub name: String,
+    #[serde(default)]
+    pub widgets: Vec<Widget>,
+    /// Milliseconds since the Unix epoch of the last save
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl Dashboard {
+    /// All signals bound by the dashboard's widgets, without duplicates
+    ///
+    /// This is the list the frontend passes to `subscribe_signals`.
+    pub fn signals(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.widgets
+            .iter()
+            .flat_map(Widget::signals)
+            .filter(|s| seen.insert(*s))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Check widget ids and signal bindings
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] for duplicate widget ids, gauges with
+    /// an empty range, or bindings to signals not in `known_signals`.
+    pub fn validate(&self, known_signals: &HashSet<&str>) -> Result<()> {
+        let mut ids = HashSet::new();
+        for widget in &self.widgets {
+            if !ids.insert(widget.id.as_str()) {
+                return Err(PlcError::Validation(format!(
+                    "Dashboard '{}' has duplicate widget id '{}'",
+                    self.name, widget.id
+                )));
+            }
+
+            if let WidgetKind::Gauge { min, max, .. } = &widget.kind {
+                if min >= max {
+                    return Err(PlcError::Validation(format!(
+                        "Gauge '{}' has min {} >= max {}",
+                        widget.id, min, max
+                    )));
+                }
+            }
+
+            if let Some(unknown) = widget
+                .signals()
+                .into_iter()
+                .find(|s| !known_signals.contains(s))
+            {
+                return Err(PlcError::Validation(format!(
+                    "Widget '{}' is bound to unknown signal '{}'",
+                    widget.id, unknown
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_trend_window() -> u32 {
+    300
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+/// Server-side store of dashboards keyed by user and dashboard name
+pub struct DashboardStore {
+    dir: Option<PathBuf>,
+    users: RwLock<HashMap<String, BTreeMap<String, Dashboard>>>,
+}
+
+impl DashboardStore {
+    /// Create a store that keeps layouts in memory only
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            users: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Create a store backed by one JSON file per user in `dir`
+    ///
+    /// Existing files are loaded eagerly; unreadable files are skipped with a
+    /// warning so one corrupt layout does not take the web server down.
+    #[must_use]
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let mut users = HashMap::new();
+
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                let Some(user) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                match std::fs::read_to_string(&path)
+                    .map_err(PlcError::from)
+                    .and_then(|s| serde_json::from_str(&s).map_err(PlcError::from))
+                {
+                    Ok(dashboards) => {
+                        users.insert(user.to_string(), dashboards);
+                    }
+                    Err(e) => warn!("Skipping dashboard file '{}': {}", path.display(), e),
+                }
+            }
+        }
+
+        debug!("Loaded dashboards for {} users from {}", users.len(), dir.display());
+        Self {
+            dir: Some(dir),
+            users: RwLock::new(users),
+        }
+    }
+
+    /// List a user's dashboards
+    pub async fn list(&self, user: &str) -> Vec<Dashboard> {
+        self.users
+            .read()
+            .await
+            .get(user)
+            .map(|d| d.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get one of a user's dashboards
+    pub async fn get(&self, user: &str, name: &str) -> Option<Dashboard> {
+        self.users
+            .read()
+            .await
+            .get(user)
+            .and_then(|d| d.get(name).cloned())
+    }
+
+    /// Insert or replace a dashboard
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user's layout file cannot be written.
+    pub async fn put(&self, user: &str, mut dashboard: Dashboard) -> Result<Dashboard> {
+        dashboard.updated_at = now_millis();
+        let mut users = self.users.write().await;
+        let dashboards = users.entry(user.to_string()).or_default();
+        dashboards.insert(dashboard.name.clone(), dashboard.clone());
+        self.persist(user, dashboards)?;
+        Ok(dashboard)
+    }
+
+    /// Delete a dashboard, returning whether it existed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user's layout file cannot be written.
+    pub async fn delete(&self, user: &str, name: &str) -> Result<bool> {
+        let mut users = self.users.write().await;
+        let Some(dashboards) = users.get_mut(user) else {
+            return Ok(false);
+        };
+        let removed = dashboards.remove(name).is_some();
+        if removed {
+            self.persist(user, dashboards)?;
+        }
+        Ok(removed)
+    }
+
+    fn persist(&self, user: &str, dashboards: &BTreeMap<String, Dashboard>) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(dashboards)?;
+        std::fs::write(dir.join(format!("{user}.json")), json)?;
+        Ok(())
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+// ============================================================================
+// HANDLERS
+// ============================================================================
+
+/// User a dashboard request is made on behalf of
+pub struct DashboardUser(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for DashboardUser {
+    type Rejection = PlcError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        let Some(value) = parts.headers.get(USER_HEADER) else {
+            return Ok(Self(DEFAULT_USER.to_string()));
+        };
+        let user = value
+            .to_str()
+            .map_err(|_| PlcError::Validation("Invalid user header".to_string()))?;
+
+        // The user id becomes a file name, so keep it to a safe alphabet
+        if user.is_empty()
+            || user.len() > 64
+            || !user
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            || user.starts_with('.')
+        {
+            return Err(PlcError::Validation(format!("Invalid user id '{user}'")));
+        }
+        Ok(Self(user.to_string()))
+    }
+}
+
+/// List the current user's dashboards
+pub async fn list_dashboards(
+    State(state): State<AppState>,
+    DashboardUser(user): DashboardUser,
+) -> Json<Vec<Dashboard>> {
+    Json(state.dashboards.list(&user).await)
+}
+
+/// Get one of the current user's dashboards
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the dashboard does not exist.
+pub async fn get_dashboard(
+    State(state): State<AppState>,
+    DashboardUser(user): DashboardUser,
+    Path(name): Path<String>,
+) -> Result<Json<Dashboard>> {
+    state
+        .dashboards
+        .get(&user, &name)
+        .await
+        .map(Json)
+        .ok_or_else(|| PlcError::NotFound(format!("Dashboard '{name}'")))
+}
+
+/// Save one of the current user's dashboards
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] if the layout binds unknown signals or
+/// is otherwise invalid, or an I/O error if it cannot be persisted.
+pub async fn save_dashboard(
+    State(state): State<AppState>,
+    DashboardUser(user): DashboardUser,
+    Path(name): Path<String>,
+    Json(mut dashboard): Json<Dashboard>,
+) -> Result<Json<Dashboard>> {
+    dashboard.name = name;
+    {
+        let config = state.config.read().await;
+        let known: HashSet<&str> = config.signals.iter().map(|s| s.name.as_str()).collect();
+        dashboard.validate(&known)?;
+    }
+    Ok(Json(state.dashboards.put(&user, dashboard).await?))
+}
+
+/// Delete one of the current user's dashboards
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the dashboard does not exist.
+pub async fn delete_dashboard(
+    State(state): State<AppState>,
+    DashboardUser(user): DashboardUser,
+    Path(name): Path<String>,
+) -> Result<()> {
+    if state.dashboards.delete(&user, &name).await? {
+        Ok(())
+    } else {
+        Err(PlcError::NotFound(format!("Dashboard '{name}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dashboard() -> Dashboard {
+        serde_json::from_value(serde_json::json!({
+            "name": "line1",
+            "widgets": [
+                { "id": "t1", "type": "trend", "signals": ["temp", "pressure"] },
+                { "id": "g1", "type": "gauge", "signal": "temp", "min": 0.0, "max": 100.0 },
+                { "id": "b1", "type": "boolean_indicator", "signal": "running" },
+                { "id": "a1", "type": "alarm_banner", "signals": ["high_temp"] }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dashboard_signals_deduplicated() {
+        assert_eq!(
+            dashboard().signals(),
+            vec!["temp", "pressure", "running", "high_temp"]
+        );
+    }
+
+    #[test]
+    fn test_dashboard_validation() {
+        let known: HashSet<&str> = ["temp", "pressure", "running", "high_temp"].into();
+        assert!(dashboard().validate(&known).is_ok());
+
+        let partial: HashSet<&str> = ["temp", "pressure"].into();
+        assert!(matches!(
+            dashboard().validate(&partial),
+            Err(PlcError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_store_is_per_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DashboardStore::with_dir(dir.path());
+        store.put("alice", dashboard()).await.unwrap();
+
+        assert_eq!(store.list("alice").await.len(), 1);
+        assert!(store.list("bob").await.is_empty());
+
+        let reloaded = DashboardStore::with_dir(dir.path());
+        assert!(reloaded.get("alice", "line1").await.is_some());
+        assert!(reloaded.delete("alice", "line1").await.unwrap());
+        assert!(!reloaded.delete("alice", "line1").await.unwrap());
+    }
+}
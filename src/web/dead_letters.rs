@@ -0,0 +1,78 @@
+//! Dead-letter REST endpoints
+//!
+//! Writes the protocol manager gave up on, for operators to review: a
+//! letter is retried once the device is reachable again, or discarded when
+//! the value should no longer be written.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+use super::AppState;
+use crate::protocols::dead_letter::DeadLetter;
+use crate::protocols::ProtocolManager;
+use crate::{PlcError, Result};
+
+fn manager(state: &AppState) -> Result<&Arc<ProtocolManager>> {
+    state
+        .protocols
+        .as_ref()
+        .filter(|m| m.dead_letters().is_some())
+        .ok_or_else(|| PlcError::NotFound("Dead letters are not configured".to_string()))
+}
+
+fn letter(manager: &ProtocolManager, id: u64) -> Result<DeadLetter> {
+    manager
+        .dead_letters()
+        .and_then(|queue| queue.get(id))
+        .ok_or_else(|| PlcError::NotFound(format!("Dead letter {id}")))
+}
+
+/// Held writes, oldest first
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if dead letters are not configured.
+pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<DeadLetter>>> {
+    let manager = manager(&state)?;
+    Ok(Json(manager.dead_letters().map(|q| q.letters()).unwrap_or_default()))
+}
+
+/// One held write
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown letter.
+pub async fn get(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<DeadLetter>> {
+    Ok(Json(letter(manager(&state)?, id)?))
+}
+
+/// Write a held value now
+///
+/// Returns the delivered letter, which is no longer held.
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown letter, or the error of
+/// the failed write, after which the letter stays held.
+pub async fn retry(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<DeadLetter>> {
+    let manager = manager(&state)?;
+    let held = letter(manager, id)?;
+    manager.retry_dead_letter(id).await?;
+    Ok(Json(held))
+}
+
+/// Drop a held write without writing it
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown letter.
+pub async fn discard(State(state): State<AppState>, Path(id): Path<u64>) -> Result<Json<DeadLetter>> {
+    let manager = manager(&state)?;
+    match manager.dead_letters() {
+        Some(queue) => Ok(Json(queue.discard(id)?)),
+        None => Err(PlcError::NotFound(format!("Dead letter {id}"))),
+    }
+}
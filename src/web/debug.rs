@@ -0,0 +1,79 @@
+//! Online debugging endpoints
+//!
+//! Pause the engine, step single scan cycles with the inputs and outputs of
+//! every block, and halt on signal changes with watchpoints. Used by
+//! `petra debug` and the designer.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use super::AppState;
+use crate::engine::{DebugStatus, Debugger, StepTrace, Watchpoint};
+use crate::{PlcError, Result};
+
+fn debugger(state: &AppState) -> Result<&Debugger> {
+    state
+        .debug
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Debugging is not available".to_string()))
+}
+
+/// Paused state, watchpoints and the last step
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the server has no engine to debug.
+pub async fn status(State(state): State<AppState>) -> Result<Json<DebugStatus>> {
+    Ok(Json(debugger(&state)?.status()))
+}
+
+/// Suspend block execution
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the server has no engine to debug.
+pub async fn pause(State(state): State<AppState>) -> Result<Json<DebugStatus>> {
+    let debugger = debugger(&state)?;
+    debugger.pause().await;
+    Ok(Json(debugger.status()))
+}
+
+/// Resume block execution
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the server has no engine to debug.
+pub async fn resume(State(state): State<AppState>) -> Result<Json<DebugStatus>> {
+    let debugger = debugger(&state)?;
+    debugger.resume().await;
+    Ok(Json(debugger.status()))
+}
+
+/// Execute one scan cycle of the paused engine
+///
+/// # Errors
+///
+/// Returns [`PlcError::Runtime`] if the engine is not paused.
+pub async fn step(State(state): State<AppState>) -> Result<Json<StepTrace>> {
+    Ok(Json(debugger(&state)?.step().await?))
+}
+
+/// Halt the engine when a signal changes
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown signal.
+pub async fn watch(State(state): State<AppState>, Path(signal): Path<String>) -> Result<Json<Watchpoint>> {
+    Ok(Json(debugger(&state)?.watch(&signal)?))
+}
+
+/// Remove a watchpoint
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if the signal is not watched.
+pub async fn unwatch(State(state): State<AppState>, Path(signal): Path<String>) -> Result<Json<Watchpoint>> {
+    Ok(Json(debugger(&state)?.unwatch(&signal)?))
+}
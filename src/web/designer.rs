@@ -0,0 +1,285 @@
+//! Backend endpoints for a visual block diagram editor
+//!
+//! These handlers give an editor the block registry, live validation against
+//! the same rules the engine applies at startup, storage for the editor layout
+//! and a deploy action that hot-reloads the running engine. The
+//! `petra-designer` frontend does not call them yet.
+//!
+//! - `GET /api/blocks/types` lists the block types of this build
+//! - `POST /api/config/validate` reports errors and lint findings
+//! - `POST /api/config/deploy` validates, reloads and persists a configuration
+//! - `GET`/`PUT /api/designer/layout` reads and replaces the editor layout
+//!
+//! Layout metadata is kept in `Config::metadata` under [`LAYOUT_METADATA_KEY`]
+//! so it is saved in the same YAML file as the configuration it describes.
+//! Deploys and layout changes are made on behalf of the user named in the
+//! `x-petra-user` header, which is required, and are logged.
+
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::Serialize;
+use tracing::info;
+
+use super::dashboards::USER_HEADER;
+use super::AppState;
+use crate::config::{LintResult, LintSeverity};
+use crate::{Config, PlcError};
+
+/// Metadata key under which the editor layout is persisted
+pub const LAYOUT_METADATA_KEY: &str = "designer_layout";
+
+/// Result of validating a configuration from the editor
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    /// `true` when the configuration can be deployed
+    pub valid: bool,
+    /// Hard validation errors
+    pub errors: Vec<String>,
+    /// Lint findings, including warnings that do not block a deploy
+    pub lint: Vec<LintResult>,
+}
+
+/// Result of a deploy request
+#[derive(Debug, Serialize)]
+pub struct DeployResponse {
+    /// Whether the configuration was written back to its YAML file
+    pub persisted: bool,
+    /// Number of blocks in the deployed configuration
+    pub blocks: usize,
+}
+
+fn user(headers: &HeaderMap) -> Result<String, PlcError> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| PlcError::Validation(format!("Designer changes require the {USER_HEADER} header")))
+}
+
+/// List block types the engine can instantiate in this build
+pub async fn block_types() -> Json<Vec<&'static str>> {
+    Json(crate::blocks::get_available_block_types())
+}
+
+/// Validate a configuration without applying it
+pub async fn validate_config(Json(config): Json<Config>) -> Json<ValidationReport> {
+    Json(validation_report(&config))
+}
+
+/// Get the stored editor layout
+pub async fn get_layout(State(state): State<AppState>) -> Json<serde_yaml::Value> {
+    let config = state.config.read().await;
+    Json(
+        config
+            .metadata
+            .get(LAYOUT_METADATA_KEY)
+            .cloned()
+            .unwrap_or(serde_yaml::Value::Null),
+    )
+}
+
+/// Replace the stored editor layout
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user, or an error if the
+/// configuration file cannot be written.
+pub async fn update_layout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(layout): Json<serde_yaml::Value>,
+) -> Result<(), PlcError> {
+    let user = user(&headers)?;
+    let mut config = state.config.write().await;
+    config
+        .metadata
+        .insert(LAYOUT_METADATA_KEY.to_string(), layout);
+
+    if let Some(path) = &state.config_path {
+        config.save_to_file(path)?;
+    }
+    info!("Designer layout updated by {}", user);
+    Ok(())
+}
+
+/// Validate, hot-reload and persist a configuration
+///
+/// Nothing is changed unless the running engine accepts the configuration.
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user or for invalid
+/// configurations, [`PlcError::Config`] when there is no engine to reload,
+/// or the underlying error if the reload or the write to disk fails.
+pub async fn deploy_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut config): Json<Config>,
+) -> Result<Json<DeployResponse>, PlcError> {
+    let user = user(&headers)?;
+    let report = validation_report(&config);
+    if !report.valid {
+        return Err(PlcError::Validation(report.errors.join("; ")));
+    }
+
+    // Keep the current layout if the editor did not send one
+    {
+        let current = state.config.read().await;
+        if let Some(layout) = current.metadata.get(LAYOUT_METADATA_KEY) {
+            config
+                .metadata
+                .entry(LAYOUT_METADATA_KEY.to_string())
+                .or_insert_with(|| layout.clone());
+        }
+    }
+
+    reload(&state, &config).await?;
+
+    let persisted = match &state.config_path {
+        Some(path) => {
+            config.save_to_file(path)?;
+            true
+        }
+        None => false,
+    };
+
+    let blocks = config.blocks.len();
+    *state.config.write().await = config;
+
+    info!(
+        "Designer deploy by {}: {} blocks (persisted: {})",
+        user, blocks, persisted
+    );
+    Ok(Json(DeployResponse { persisted, blocks }))
+}
+
+/// Apply `config` to the running engine
+#[cfg(feature = "hot-reload")]
+async fn reload(state: &AppState, config: &Config) -> Result<(), PlcError> {
+    let handle = state
+        .reload
+        .as_ref()
+        .ok_or_else(|| PlcError::Config("Deploying requires a running engine to reload".to_string()))?;
+    handle.apply(config).await.map(drop)
+}
+
+#[cfg(not(feature = "hot-reload"))]
+#[allow(clippy::unused_async)]
+async fn reload(_state: &AppState, _config: &Config) -> Result<(), PlcError> {
+    Err(PlcError::Config("Deploying requires the hot-reload feature".to_string()))
+}
+
+fn validation_report(config: &Config) -> ValidationReport {
+    let mut errors = Vec::new();
+    if let Err(e) = config.validate() {
+        errors.push(e.to_string());
+    }
+
+    let lint = match config.lint() {
+        Ok(results) => results,
+        Err(e) => {
+            errors.push(e.to_string());
+            Vec::new()
+        }
+    };
+    errors.extend(
+        lint.iter()
+            .filter(|r| r.severity == LintSeverity::Error)
+            .map(|r| r.message.clone()),
+    );
+
+    ValidationReport {
+        valid: errors.is_empty(),
+        errors,
+        lint,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SignalBus;
+    use std::sync::Arc;
+
+    fn headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_HEADER, "alice".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_validate_config() {
+        let Json(report) = validate_config(Json(Config::example_basic().unwrap())).await;
+        assert!(report.valid, "{:?}", report.errors);
+
+        let invalid = Config { scan_time_ms: 0, ..Config::example_basic().unwrap() };
+        let Json(report) = validate_config(Json(invalid)).await;
+        assert!(!report.valid);
+        assert!(report.errors.iter().any(|e| e.contains("Scan time")));
+    }
+
+    #[tokio::test]
+    async fn test_deploy_needs_user_and_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("petra.yaml");
+        let state = AppState::new(Arc::new(SignalBus::new()), Config::example_basic().unwrap()).with_config_path(&path);
+        let deployed = Config { scan_time_ms: 250, ..Config::example_basic().unwrap() };
+
+        let result = deploy_config(State(state.clone()), HeaderMap::new(), Json(deployed.clone())).await;
+        assert!(matches!(result, Err(PlcError::Validation(_))));
+        let invalid = Config { scan_time_ms: 0, ..deployed.clone() };
+        let result = deploy_config(State(state.clone()), headers(), Json(invalid)).await;
+        assert!(matches!(result, Err(PlcError::Validation(_))));
+
+        // Without an engine to reload nothing is changed
+        let result = deploy_config(State(state.clone()), headers(), Json(deployed)).await;
+        assert!(matches!(result, Err(PlcError::Config(_))));
+        assert_eq!(state.config.read().await.scan_time_ms, 100);
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[tokio::test]
+    async fn test_deploy_reloads_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("petra.yaml");
+        let mut config = Config::example_basic().unwrap();
+        config.blocks.clear();
+        let engine = crate::Engine::new(config.clone()).unwrap();
+        let state = AppState::new(Arc::new(SignalBus::new()), config.clone())
+            .with_config_path(&path)
+            .with_reload(engine.reload_handle());
+        state.config.write().await.metadata.insert(LAYOUT_METADATA_KEY.to_string(), "kept".into());
+
+        let mut deployed = config;
+        deployed.signals.push(serde_yaml::from_str("{ name: designer.added, type: bool, initial: true }").unwrap());
+        let Json(response) = deploy_config(State(state.clone()), headers(), Json(deployed)).await.unwrap();
+        assert_eq!(response.blocks, 0);
+        assert!(response.persisted);
+        assert_eq!(engine.signal_bus().get("designer.added"), Some(crate::Value::Bool(true)));
+
+        let saved = Config::from_file(&path).unwrap();
+        assert!(saved.blocks.is_empty());
+        assert_eq!(saved.metadata.get(LAYOUT_METADATA_KEY), Some(&"kept".into()));
+    }
+
+    #[tokio::test]
+    async fn test_layout_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("petra.yaml");
+        let state = AppState::new(Arc::new(SignalBus::new()), Config::example_basic().unwrap()).with_config_path(&path);
+        let Json(layout) = get_layout(State(state.clone())).await;
+        assert_eq!(layout, serde_yaml::Value::Null);
+
+        let layout: serde_yaml::Value = serde_yaml::from_str("nodes: { heartbeat_generator: { x: 40, y: 80 } }").unwrap();
+        let result = update_layout(State(state.clone()), HeaderMap::new(), Json(layout.clone())).await;
+        assert!(matches!(result, Err(PlcError::Validation(_))));
+        update_layout(State(state.clone()), headers(), Json(layout.clone())).await.unwrap();
+
+        let Json(stored) = get_layout(State(state)).await;
+        assert_eq!(stored, layout);
+        let saved = Config::from_file(&path).unwrap();
+        assert_eq!(saved.metadata.get(LAYOUT_METADATA_KEY), Some(&layout));
+    }
+}
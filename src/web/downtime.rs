@@ -0,0 +1,91 @@
+//! Downtime REST endpoints
+//!
+//! Event listing and operator reason assignment for the downtime manager,
+//! plus the Pareto aggregation used for loss reporting.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::AppState;
+use crate::downtime::{DowntimeEvent, ParetoEntry, ReasonAssignment, ReasonCode, SharedDowntimeManager};
+use crate::{PlcError, Result};
+
+fn manager(state: &AppState) -> Result<&SharedDowntimeManager> {
+    state
+        .downtime
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Downtime tracking is not configured".to_string()))
+}
+
+/// Filters for the event list
+#[derive(Debug, Deserialize)]
+pub struct EventQuery {
+    /// Only events for this asset
+    pub asset: Option<String>,
+    /// Only events without a reason code
+    #[serde(default)]
+    pub unassigned: bool,
+}
+
+/// Filters for the Pareto report
+#[derive(Debug, Deserialize)]
+pub struct ParetoQuery {
+    /// Only events for this asset
+    pub asset: Option<String>,
+    /// Only events that started at or after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Downtime events, newest first
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if downtime tracking is not configured.
+pub async fn list_events(
+    State(state): State<AppState>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<Vec<DowntimeEvent>>> {
+    let manager = manager(&state)?.read().await;
+    Ok(Json(manager.events(query.asset.as_deref(), query.unassigned)))
+}
+
+/// Assign an operator reason code to an event
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown event and
+/// [`PlcError::Validation`] for an unknown reason code.
+pub async fn assign_reason(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(assignment): Json<ReasonAssignment>,
+) -> Result<Json<DowntimeEvent>> {
+    let event = manager(&state)?.write().await.assign_reason(id, assignment)?;
+    Ok(Json(event))
+}
+
+/// Configured reason codes
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if downtime tracking is not configured.
+pub async fn list_reasons(State(state): State<AppState>) -> Result<Json<Vec<ReasonCode>>> {
+    Ok(Json(manager(&state)?.read().await.reason_codes().to_vec()))
+}
+
+/// Downtime totals per reason code, largest first
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if downtime tracking is not configured.
+pub async fn pareto(
+    State(state): State<AppState>,
+    Query(query): Query<ParetoQuery>,
+) -> Result<Json<Vec<ParetoEntry>>> {
+    let manager = manager(&state)?.read().await;
+    Ok(Json(manager.pareto(query.asset.as_deref(), query.since, Utc::now())))
+}
@@ -0,0 +1,79 @@
+//! Energy monitoring REST endpoints
+//!
+//! Running hour, day and shift totals per meter and the completed period
+//! records used for energy reviews.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::AppState;
+use crate::energy::{EnergyPeriod, EnergyRecord, MeterSnapshot, SharedEnergyManager};
+use crate::{PlcError, Result};
+
+fn manager(state: &AppState) -> Result<&SharedEnergyManager> {
+    state
+        .energy
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Energy monitoring is not configured".to_string()))
+}
+
+/// Filters for the record list
+#[derive(Debug, Deserialize)]
+pub struct RecordQuery {
+    /// Only records of this period kind
+    pub period: Option<EnergyPeriod>,
+    /// Only periods that ended at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only periods that ended at or before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Running totals for all meters
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if energy monitoring is not configured.
+pub async fn list_meters(State(state): State<AppState>) -> Result<Json<Vec<MeterSnapshot>>> {
+    Ok(Json(manager(&state)?.read().await.snapshots()))
+}
+
+/// Running totals for one meter
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if energy monitoring is not configured or
+/// the meter has no data yet.
+pub async fn get_meter(
+    State(state): State<AppState>,
+    Path(meter): Path<String>,
+) -> Result<Json<MeterSnapshot>> {
+    manager(&state)?
+        .read()
+        .await
+        .snapshot(&meter)
+        .map(Json)
+        .ok_or_else(|| PlcError::NotFound(format!("Energy meter '{meter}'")))
+}
+
+/// Completed period records for one meter
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if energy monitoring is not configured or
+/// the meter does not exist.
+pub async fn get_records(
+    State(state): State<AppState>,
+    Path(meter): Path<String>,
+    Query(query): Query<RecordQuery>,
+) -> Result<Json<Vec<EnergyRecord>>> {
+    manager(&state)?
+        .read()
+        .await
+        .records(&meter, query.period, query.since, query.until)
+        .map(Json)
+        .ok_or_else(|| PlcError::NotFound(format!("Energy meter '{meter}'")))
+}
@@ -0,0 +1,47 @@
+//! Engine event history
+//!
+//! `GET /api/events` returns the retained engine events, oldest first.
+//! `after` skips events up to and including that sequence number, so a client
+//! polling the endpoint passes the last sequence it has seen:
+//!
+//! ```text
+//! GET /api/events?after=42
+//! ```
+//!
+//! WebSocket clients follow the same stream live with a `subscribe_events`
+//! message.
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+
+use super::AppState;
+use crate::events::EngineEvent;
+use crate::{PlcError, Result};
+
+/// Query parameters of `GET /api/events`
+#[derive(Debug, Default, Deserialize)]
+pub struct EventsQuery {
+    /// Last sequence number already received
+    #[serde(default)]
+    pub after: u64,
+}
+
+/// `GET /api/events`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] when the server was started without an
+/// engine event stream.
+pub async fn list_events(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Json<Vec<EngineEvent>>> {
+    let events = state
+        .events
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Event stream is not available".to_string()))?;
+    Ok(Json(events.since(query.after)))
+}
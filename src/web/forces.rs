@@ -0,0 +1,100 @@
+//! Forced signal REST endpoints
+//!
+//! - `GET /api/forces` lists every forced signal with who forced it and why
+//! - `PUT /api/signals/:name/force` with a `{"value": ..., "reason": "..."}`
+//!   body pins a signal to a value, ignoring writes from blocks and
+//!   protocols until it is released
+//! - `DELETE /api/signals/:name/force` releases it
+//!
+//! Forced signals report the [`FORCED_QUALITY`](super::FORCED_QUALITY)
+//! quality. Forces are made on behalf of the user named in the
+//! `x-petra-user` header, which is required, and are audited with the peer
+//! address.
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+use super::dashboards::USER_HEADER;
+use super::AppState;
+use crate::signal::ForcedSignal;
+use crate::{PlcError, Result, Value};
+
+fn user(headers: &HeaderMap) -> Result<String> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| PlcError::Validation(format!("Forcing signals requires the {USER_HEADER} header")))
+}
+
+/// Body of `PUT /api/signals/:name/force`
+#[derive(Debug, Deserialize)]
+pub struct ForceRequest {
+    pub value: Value,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// `GET /api/forces`
+pub async fn list(State(state): State<AppState>) -> Json<Vec<ForcedSignal>> {
+    Json(state.signal_bus.forced_signals())
+}
+
+/// `PUT /api/signals/:name/force`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user and
+/// [`PlcError::SignalNotFound`] for an unknown signal.
+pub async fn force(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<ForceRequest>,
+) -> Result<Json<ForcedSignal>> {
+    let user = user(&headers)?;
+    let old_value = state.signal_bus.get(&name);
+    let result = state.signal_bus.force(&name, request.value.clone(), &user, request.reason.as_deref());
+    let error = result.as_ref().err().map(ToString::to_string);
+    state.audit_force(
+        &user,
+        &peer.ip().to_string(),
+        &name,
+        old_value,
+        Some(request.value),
+        request.reason.as_deref(),
+        error.as_deref().map_or(Ok(()), Err),
+    );
+    Ok(Json(result?))
+}
+
+/// `DELETE /api/signals/:name/force`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user and
+/// [`PlcError::NotFound`] if the signal is not forced.
+pub async fn unforce(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Json<ForcedSignal>> {
+    let user = user(&headers)?;
+    let forced = state
+        .signal_bus
+        .unforce(&name)
+        .ok_or_else(|| PlcError::NotFound(format!("Signal '{name}' is not forced")));
+    let error = forced.as_ref().err().map(ToString::to_string);
+    let old_value = forced.as_ref().ok().map(|f| f.value.clone());
+    state.audit_force(&user, &peer.ip().to_string(), &name, old_value, None, None, error.as_deref().map_or(Ok(()), Err));
+    Ok(Json(forced?))
+}
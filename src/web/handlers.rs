@@ -1,6 +1,8 @@
-use axum::{extract::{Path, State}, Json};
+use axum::{extract::{ConnectInfo, Path, State}, http::header, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use crate::display::{DisplayFormatter, FormattedValue};
 use crate::{Value, PlcError};
 use super::AppState;
 
@@ -9,16 +11,26 @@ pub struct HealthResponse {
     status: String,
     version: String,
     uptime: u64,
+    /// Signals that missed their expected update
+    stale_signals: usize,
 }
 
-pub async fn health() -> Json<HealthResponse> {
+/// `GET /health`, degraded while signals are stale
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    let stale_signals = state.signal_bus.stale_signals().len();
     Json(HealthResponse {
-        status: "healthy".to_string(),
+        status: if stale_signals == 0 { "healthy" } else { "degraded" }.to_string(),
         version: crate::VERSION.to_string(),
         uptime: 0,
+        stale_signals,
     })
 }
 
+/// `GET /api/features`
+pub async fn get_features() -> Json<crate::features::FeatureReport> {
+    Json(crate::features::current().to_report())
+}
+
 pub async fn get_signals(State(state): State<AppState>) -> Result<Json<HashMap<String, Value>>, PlcError> {
     let signals = state.signal_bus.get_all_signals()?;
     Ok(Json(signals))
@@ -29,14 +41,50 @@ pub async fn get_signal(Path(name): Path<String>, State(state): State<AppState>)
     Ok(Json(value))
 }
 
+/// `GET /api/signal-groups/:name/snapshot`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown group.
+pub async fn get_group_snapshot(Path(name): Path<String>, State(state): State<AppState>) -> Result<Json<crate::snapshot::SignalSnapshot>, PlcError> {
+    Ok(Json(state.signal_bus.snapshot_group(&name)?))
+}
+
+async fn display_formatter(state: &AppState) -> DisplayFormatter {
+    DisplayFormatter::from_signals(&state.config.read().await.signals)
+}
+
+pub async fn get_signals_formatted(State(state): State<AppState>) -> Result<Json<BTreeMap<String, FormattedValue>>, PlcError> {
+    let signals = state.signal_bus.get_all_signals()?;
+    Ok(Json(display_formatter(&state).await.render_all(signals)))
+}
+
+pub async fn get_signal_formatted(Path(name): Path<String>, State(state): State<AppState>) -> Result<Json<FormattedValue>, PlcError> {
+    let value = state.signal_bus.get(&name).ok_or_else(|| PlcError::SignalNotFound(name.clone()))?;
+    Ok(Json(display_formatter(&state).await.render(&name, value)))
+}
+
+/// Markdown table of all signals with units and labels applied
+pub async fn get_signal_report(State(state): State<AppState>) -> Result<impl IntoResponse, PlcError> {
+    let signals = state.signal_bus.get_all_signals()?;
+    let report = display_formatter(&state).await.report(signals);
+    Ok(([(header::CONTENT_TYPE, "text/markdown; charset=utf-8")], report))
+}
+
 #[derive(Deserialize)]
 pub struct SetSignalRequest {
     value: Value,
+    #[serde(flatten)]
+    attestation: super::Attestation,
 }
 
-pub async fn set_signal(Path(name): Path<String>, State(state): State<AppState>, Json(req): Json<SetSignalRequest>) -> Result<(), PlcError> {
-    state.signal_bus.set(&name, req.value)?;
-    Ok(())
+pub async fn set_signal(
+    Path(name): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Json(req): Json<SetSignalRequest>,
+) -> Result<(), PlcError> {
+    state.operator_write(super::ANONYMOUS_USER, &peer.ip().to_string(), &name, &req.value, &req.attestation)
 }
 
 pub async fn get_config(State(state): State<AppState>) -> Result<Json<crate::Config>, PlcError> {
@@ -0,0 +1,83 @@
+//! Maintenance REST endpoints
+//!
+//! Rule status and work item handling for the condition-based maintenance
+//! manager: technicians acknowledge raised items and complete them once the
+//! work is done, which resets the rule's usage baseline.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use super::AppState;
+use crate::maintenance::{RuleStatus, SharedMaintenanceManager, WorkItem, WorkItemUpdate};
+use crate::{PlcError, Result};
+
+fn manager(state: &AppState) -> Result<&SharedMaintenanceManager> {
+    state
+        .maintenance
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Maintenance rules are not configured".to_string()))
+}
+
+/// Filters for the work item list
+#[derive(Debug, Deserialize)]
+pub struct WorkItemQuery {
+    /// Only work items for this asset
+    pub asset: Option<String>,
+    /// Only work items that are not completed
+    #[serde(default)]
+    pub open: bool,
+}
+
+/// Latest evaluation of every maintenance rule
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if maintenance rules are not configured.
+pub async fn list_rules(State(state): State<AppState>) -> Result<Json<Vec<RuleStatus>>> {
+    Ok(Json(manager(&state)?.read().await.rule_statuses()))
+}
+
+/// Work items, newest first
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if maintenance rules are not configured.
+pub async fn list_work_items(
+    State(state): State<AppState>,
+    Query(query): Query<WorkItemQuery>,
+) -> Result<Json<Vec<WorkItem>>> {
+    let manager = manager(&state)?.read().await;
+    Ok(Json(manager.work_items(query.asset.as_deref(), query.open)))
+}
+
+/// Acknowledge a work item
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown work item and
+/// [`PlcError::Validation`] if it is already completed.
+pub async fn acknowledge(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(update): Json<WorkItemUpdate>,
+) -> Result<Json<WorkItem>> {
+    Ok(Json(manager(&state)?.write().await.acknowledge(id, update)?))
+}
+
+/// Complete a work item
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] for an unknown work item and
+/// [`PlcError::Validation`] if it is already completed.
+pub async fn complete(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Json(update): Json<WorkItemUpdate>,
+) -> Result<Json<WorkItem>> {
+    Ok(Json(manager(&state)?.write().await.complete(id, update, Utc::now())?))
+}
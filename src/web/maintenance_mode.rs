@@ -0,0 +1,98 @@
+//! Maintenance mode REST endpoints
+//!
+//! - `GET /api/maintenance-mode` lists the configured areas and who put
+//!   each one in maintenance
+//! - `POST /api/maintenance-mode/:area/enter` with an optional
+//!   `{"reason": "..."}` body takes an area out of service
+//! - `POST /api/maintenance-mode/:area/exit` returns it to service
+//!
+//! Changes are made on behalf of the user named in the `x-petra-user`
+//! header, which is required, and are audited with the peer address.
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+use super::dashboards::USER_HEADER;
+use super::AppState;
+use crate::maintenance_mode::{AreaStatus, MaintenanceEntry, MaintenanceMode};
+use crate::{PlcError, Result};
+
+fn maintenance_mode(state: &AppState) -> Result<&MaintenanceMode> {
+    state
+        .maintenance_mode
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("Maintenance mode is not configured".to_string()))
+}
+
+fn user(headers: &HeaderMap) -> Result<String> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| PlcError::Validation(format!("Maintenance mode changes require the {USER_HEADER} header")))
+}
+
+/// Body of `POST /api/maintenance-mode/:area/enter`
+#[derive(Debug, Default, Deserialize)]
+pub struct EnterRequest {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// `GET /api/maintenance-mode`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if maintenance mode is not configured.
+pub async fn list_areas(State(state): State<AppState>) -> Result<Json<Vec<AreaStatus>>> {
+    Ok(Json(maintenance_mode(&state)?.areas()))
+}
+
+/// `POST /api/maintenance-mode/:area/enter`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user or if the area is
+/// already in maintenance, and [`PlcError::NotFound`] for an unknown area.
+pub async fn enter(
+    State(state): State<AppState>,
+    Path(area): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    request: Option<Json<EnterRequest>>,
+) -> Result<Json<MaintenanceEntry>> {
+    let mode = maintenance_mode(&state)?;
+    let user = user(&headers)?;
+    let reason = request.and_then(|Json(r)| r.reason);
+    let result = mode.enter(&area, &user, reason.as_deref());
+    let error = result.as_ref().err().map(ToString::to_string);
+    state.audit_maintenance(&user, &peer.ip().to_string(), &area, true, reason.as_deref(), error.as_deref().map_or(Ok(()), Err));
+    Ok(Json(result?))
+}
+
+/// `POST /api/maintenance-mode/:area/exit`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user or if the area is not in
+/// maintenance, and [`PlcError::NotFound`] for an unknown area.
+pub async fn exit(
+    State(state): State<AppState>,
+    Path(area): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceEntry>> {
+    let mode = maintenance_mode(&state)?;
+    let user = user(&headers)?;
+    let result = mode.exit(&area, &user);
+    let error = result.as_ref().err().map(ToString::to_string);
+    state.audit_maintenance(&user, &peer.ip().to_string(), &area, false, None, error.as_deref().map_or(Ok(()), Err));
+    Ok(Json(result?))
+}
@@ -1,10 +1,13 @@
-use crate::{PlcError, Result, SignalBus};
+use crate::{PlcError, Result, SignalBus, Value};
 use axum::{
-    extract::{State, WebSocketUpgrade},
+    extract::{ConnectInfo, State, WebSocketUpgrade},
     response::IntoResponse,
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
@@ -13,46 +16,631 @@ use tower_http::services::ServeDir;
 mod static_files;
 use static_files::spa_fallback;
 
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "audit")]
+pub mod security;
+pub mod batch;
+pub mod coalesce;
+#[cfg(feature = "scan-budget")]
+pub mod budget;
+#[cfg(feature = "enhanced-monitoring")]
+pub mod cycle;
+pub mod dashboards;
+pub mod dead_letters;
+pub mod debug;
+pub mod designer;
+#[cfg(feature = "downtime")]
+pub mod downtime;
+#[cfg(feature = "energy")]
+pub mod energy;
+pub mod events;
+pub mod forces;
+#[cfg(feature = "maintenance")]
+pub mod maintenance;
+#[cfg(feature = "maintenance-mode")]
+pub mod maintenance_mode;
 pub mod handlers;
+#[cfg(feature = "oee")]
+pub mod oee;
+pub mod protocols;
+#[cfg(feature = "redundancy")]
+pub mod redundancy;
+#[cfg(feature = "schedules")]
+pub mod schedules;
 pub mod websocket;
 
+/// Quality reported for a signal pinned with [`SignalBus::force`]
+pub const FORCED_QUALITY: &str = "forced";
+
+/// Quality reported for a signal that missed its `update_frequency_ms`
+pub const STALE_QUALITY: &str = "uncertain";
+
+/// User recorded for REST requests, which carry no identity
+pub const ANONYMOUS_USER: &str = "anonymous";
+
+/// Reason and electronic signature an operator sends with a write
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Attestation {
+    /// Why the value is changed, required for critical signals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Re-entered credentials for writes that must be signed
+    #[cfg(feature = "esignature")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<crate::security::esignature::ESignature>,
+}
+
+/// Reason and signature checks an operator write has passed
+#[derive(Debug, Default)]
+pub(crate) struct Approval {
+    /// Verified signature, if the write had to be signed
+    #[cfg(feature = "esignature")]
+    signature: Option<crate::security::audit::SignatureRecord>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub signal_bus: Arc<SignalBus>,
     pub config: Arc<RwLock<crate::Config>>,
+    /// Per-user dashboard layouts
+    pub dashboards: Arc<dashboards::DashboardStore>,
+    /// YAML file that designer deploys are written back to
+    pub config_path: Option<PathBuf>,
+    /// Handle used by designer deploys to reload the running engine
+    #[cfg(feature = "hot-reload")]
+    pub reload: Option<crate::engine::ReloadHandle>,
+    /// OEE manager backing the `/api/oee` endpoints
+    #[cfg(feature = "oee")]
+    pub oee: Option<crate::oee::SharedOeeManager>,
+    /// Downtime manager backing the `/api/downtime` endpoints
+    #[cfg(feature = "downtime")]
+    pub downtime: Option<crate::downtime::SharedDowntimeManager>,
+    /// Maintenance manager backing the `/api/maintenance` endpoints
+    #[cfg(feature = "maintenance")]
+    pub maintenance: Option<crate::maintenance::SharedMaintenanceManager>,
+    /// Areas operators can take out of service through `/api/maintenance-mode`
+    #[cfg(feature = "maintenance-mode")]
+    pub maintenance_mode: Option<crate::maintenance_mode::MaintenanceMode>,
+    /// Energy manager backing the `/api/energy` endpoints
+    #[cfg(feature = "energy")]
+    pub energy: Option<crate::energy::SharedEnergyManager>,
+    /// Alarm manager used for WebSocket acknowledgements
+    #[cfg(feature = "alarms")]
+    pub alarms: Option<crate::alarms::SharedAlarmManager>,
+    /// Audit log for operator writes and acknowledgements
+    #[cfg(feature = "audit")]
+    pub audit: Option<Arc<crate::security::AuditLog>>,
+    /// Signature rules and signers for critical writes
+    #[cfg(feature = "esignature")]
+    pub esignature: Option<Arc<crate::security::esignature::ESignatureVerifier>>,
+    /// Engine event stream behind `/api/events` and WebSocket event subscriptions
+    pub events: Option<crate::events::EventLog>,
+    /// Protocol manager whose dead letters `/api/dead-letters` serves
+    pub protocols: Option<Arc<crate::protocols::ProtocolManager>>,
+    /// Engine debugger behind the `/api/debug` endpoints
+    pub debug: Option<crate::engine::Debugger>,
+    /// Scan phase histograms behind `/api/engine/cycle`
+    #[cfg(feature = "enhanced-monitoring")]
+    pub cycle_timings: Option<crate::engine::CycleTimings>,
+    /// Redundant pair node behind `/api/redundancy`
+    #[cfg(feature = "redundancy")]
+    pub redundancy: Option<crate::redundancy::Redundancy>,
+}
+
+impl AppState {
+    #[must_use]
+    pub fn new(signal_bus: Arc<SignalBus>, config: crate::Config) -> Self {
+        let dashboards = match config.web.as_ref().and_then(|w| w.dashboard_dir.as_ref()) {
+            Some(dir) => dashboards::DashboardStore::with_dir(dir),
+            None => dashboards::DashboardStore::in_memory(),
+        };
+        Self {
+            signal_bus,
+            dashboards: Arc::new(dashboards),
+            config: Arc::new(RwLock::new(config)),
+            config_path: None,
+            #[cfg(feature = "hot-reload")]
+            reload: None,
+            #[cfg(feature = "oee")]
+            oee: None,
+            #[cfg(feature = "downtime")]
+            downtime: None,
+            #[cfg(feature = "maintenance")]
+            maintenance: None,
+            #[cfg(feature = "maintenance-mode")]
+            maintenance_mode: None,
+            #[cfg(feature = "energy")]
+            energy: None,
+            #[cfg(feature = "alarms")]
+            alarms: None,
+            #[cfg(feature = "audit")]
+            audit: None,
+            #[cfg(feature = "esignature")]
+            esignature: None,
+            events: None,
+            protocols: None,
+            debug: None,
+            #[cfg(feature = "enhanced-monitoring")]
+            cycle_timings: None,
+            #[cfg(feature = "redundancy")]
+            redundancy: None,
+        }
+    }
+
+    /// Persist configuration changes made through the API to `path`
+    #[must_use]
+    pub fn with_config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Apply deployed configurations to a running engine
+    #[cfg(feature = "hot-reload")]
+    #[must_use]
+    pub fn with_reload(mut self, handle: crate::engine::ReloadHandle) -> Self {
+        self.reload = Some(handle);
+        self
+    }
+
+    /// Serve OEE results from `manager`
+    #[cfg(feature = "oee")]
+    #[must_use]
+    pub fn with_oee(mut self, manager: crate::oee::SharedOeeManager) -> Self {
+        self.oee = Some(manager);
+        self
+    }
+
+    /// Serve downtime events and reason assignment from `manager`
+    #[cfg(feature = "downtime")]
+    #[must_use]
+    pub fn with_downtime(mut self, manager: crate::downtime::SharedDowntimeManager) -> Self {
+        self.downtime = Some(manager);
+        self
+    }
+
+    /// Serve maintenance rules and work items from `manager`
+    #[cfg(feature = "maintenance")]
+    #[must_use]
+    pub fn with_maintenance(mut self, manager: crate::maintenance::SharedMaintenanceManager) -> Self {
+        self.maintenance = Some(manager);
+        self
+    }
+
+    /// Let operators put the areas of `mode` in maintenance and report the
+    /// quality of their signals accordingly
+    #[cfg(feature = "maintenance-mode")]
+    #[must_use]
+    pub fn with_maintenance_mode(mut self, mode: crate::maintenance_mode::MaintenanceMode) -> Self {
+        self.maintenance_mode = Some(mode);
+        self
+    }
+
+    /// Serve energy totals and period records from `manager`
+    #[cfg(feature = "energy")]
+    #[must_use]
+    pub fn with_energy(mut self, manager: crate::energy::SharedEnergyManager) -> Self {
+        self.energy = Some(manager);
+        self
+    }
+
+    /// Let authorized WebSocket clients acknowledge alarms in `manager`
+    #[cfg(feature = "alarms")]
+    #[must_use]
+    pub fn with_alarms(mut self, manager: crate::alarms::SharedAlarmManager) -> Self {
+        self.alarms = Some(manager);
+        self
+    }
+
+    /// Record operator actions in `audit` and serve them on `/api/audit`
+    #[cfg(feature = "audit")]
+    #[must_use]
+    pub fn with_audit(mut self, audit: Arc<crate::security::AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Serve signed writes, checking signatures with `verifier`
+    #[cfg(feature = "esignature")]
+    #[must_use]
+    pub fn with_esignature(mut self, verifier: Arc<crate::security::esignature::ESignatureVerifier>) -> Self {
+        self.esignature = Some(verifier);
+        self
+    }
+
+    /// Serve the engine's event stream
+    #[must_use]
+    pub fn with_events(mut self, events: crate::events::EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Let operators pause, step and watch the engine
+    #[must_use]
+    pub fn with_debug(mut self, debugger: crate::engine::Debugger) -> Self {
+        self.debug = Some(debugger);
+        self
+    }
+
+    /// Serve the scan phase histograms of the engine
+    #[cfg(feature = "enhanced-monitoring")]
+    #[must_use]
+    pub fn with_cycle_timings(mut self, timings: crate::engine::CycleTimings) -> Self {
+        self.cycle_timings = Some(timings);
+        self
+    }
+
+    /// Report the role of this node of a redundant pair
+    #[cfg(feature = "redundancy")]
+    #[must_use]
+    pub fn with_redundancy(mut self, node: crate::redundancy::Redundancy) -> Self {
+        self.redundancy = Some(node);
+        self
+    }
+
+    /// Serve the dead letters of `manager` and reconfigure its drivers
+    #[must_use]
+    pub fn with_protocols(mut self, manager: Arc<crate::protocols::ProtocolManager>) -> Self {
+        self.protocols = Some(manager);
+        self
+    }
+
+    /// Require a reason for writes to critical signals
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] if `signal` is critical and no
+    /// reason was given.
+    pub(crate) fn check_reason(&self, signal: &str, reason: Option<&str>) -> Result<()> {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            return audit.require_reason(signal, reason);
+        }
+        let _ = (signal, reason);
+        Ok(())
+    }
+
+    /// Check the reason and signature an operator gave for `writes`
+    ///
+    /// The signature is verified once, against the first write that needs
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Validation`] for a missing reason or signature and
+    /// [`PlcError::AuthenticationFailed`] for wrong signature credentials.
+    pub(crate) fn approve(&self, writes: &[(&str, &Value)], attestation: &Attestation) -> Result<Approval> {
+        let reason = attestation.reason.as_deref();
+        for (signal, _) in writes {
+            self.check_reason(signal, reason)?;
+        }
+        #[cfg(feature = "esignature")]
+        if let Some(verifier) = &self.esignature {
+            if let Some((signal, value)) = writes.iter().find(|(s, v)| verifier.requires_signature(s, v)) {
+                let signature = verifier.check_write(signal, value, attestation.signature.as_ref(), reason)?;
+                return Ok(Approval { signature });
+            }
+        }
+        Ok(Approval::default())
+    }
+
+    /// Record an operator write in the audit log, if one is configured
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn audit_write(
+        &self,
+        user: &str,
+        source: &str,
+        signal: &str,
+        old_value: Option<Value>,
+        new_value: &Value,
+        attestation: &Attestation,
+        approval: &Approval,
+        result: std::result::Result<(), &str>,
+    ) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::signal_write(
+                user,
+                source,
+                signal,
+                old_value,
+                new_value.clone(),
+                attestation.reason.as_deref(),
+                result,
+            );
+            #[cfg(feature = "esignature")]
+            let entry = entry.with_signature(approval.signature.clone());
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for {}: {}", signal, e);
+            }
+            return;
+        }
+        let _ = (user, source, signal, old_value, new_value, attestation, approval, result);
+    }
+
+    /// Record an operator alarm acknowledgement in the audit log, if one is configured
+    pub(crate) fn audit_ack(
+        &self,
+        user: &str,
+        source: &str,
+        alarm: &str,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::alarm_ack(user, source, alarm, reason, result);
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for {}: {}", alarm, e);
+            }
+            return;
+        }
+        let _ = (user, source, alarm, reason, result);
+    }
+
+    /// Record an area entering or leaving maintenance in the audit log, if
+    /// one is configured
+    #[cfg(feature = "maintenance-mode")]
+    pub(crate) fn audit_maintenance(
+        &self,
+        user: &str,
+        source: &str,
+        area: &str,
+        enter: bool,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::maintenance(user, source, area, enter, reason, result);
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for maintenance of {}: {}", area, e);
+            }
+            return;
+        }
+        let _ = (user, source, area, enter, reason, result);
+    }
+
+    /// Record `user` forcing `signal` to `value`, or releasing it, in the
+    /// audit log, if one is configured
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn audit_force(
+        &self,
+        user: &str,
+        source: &str,
+        signal: &str,
+        old_value: Option<Value>,
+        value: Option<Value>,
+        reason: Option<&str>,
+        result: std::result::Result<(), &str>,
+    ) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::force(user, source, signal, old_value, value, reason, result);
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for force of {}: {}", signal, e);
+            }
+            return;
+        }
+        let _ = (user, source, signal, old_value, value, reason, result);
+    }
+
+    /// Quality reported to clients for `signal`
+    pub(crate) fn signal_quality(&self, signal: &str) -> &'static str {
+        if self.signal_bus.is_forced(signal) {
+            return FORCED_QUALITY;
+        }
+        #[cfg(feature = "maintenance-mode")]
+        if self.maintenance_mode.as_ref().is_some_and(|mode| mode.signal_area(signal).is_some()) {
+            return crate::maintenance_mode::MAINTENANCE_QUALITY;
+        }
+        if self.signal_bus.is_stale(signal) {
+            return STALE_QUALITY;
+        }
+        "good"
+    }
+
+    /// Record a login attempt in the audit log, if one is configured
+    pub(crate) fn audit_login(&self, user: &str, source: &str, session: &str, result: std::result::Result<(), &str>) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::login(user, source, session, result);
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for login of {}: {}", user, e);
+            }
+            return;
+        }
+        let _ = (user, source, session, result);
+    }
+
+    /// Record the end of a session in the audit log, if one is configured
+    pub(crate) fn audit_logout(&self, user: &str, source: &str, session: &str) {
+        #[cfg(feature = "audit")]
+        if let Some(audit) = &self.audit {
+            let entry = crate::security::AuditEntry::logout(user, source, session);
+            if let Err(e) = audit.record(&entry) {
+                tracing::error!("Failed to write audit entry for logout of {}: {}", user, e);
+            }
+            return;
+        }
+        let _ = (user, source, session);
+    }
+
+    /// Write a signal on behalf of an operator
+    ///
+    /// Critical signals require a reason and, with signature rules
+    /// configured, a valid electronic signature. The attempt is audited with
+    /// the value before and after the write.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason, signature or signal bus error.
+    pub(crate) fn operator_write(
+        &self,
+        user: &str,
+        source: &str,
+        signal: &str,
+        value: &Value,
+        attestation: &Attestation,
+    ) -> Result<()> {
+        let old_value = self.signal_bus.get(signal);
+        let (approval, result) = match self.approve(&[(signal, value)], attestation) {
+            Ok(approval) => {
+                let result = self.signal_bus.set(signal, value.clone());
+                (approval, result)
+            }
+            Err(e) => (Approval::default(), Err(e)),
+        };
+        let error = result.as_ref().err().map(ToString::to_string);
+        self.audit_write(
+            user,
+            source,
+            signal,
+            old_value,
+            value,
+            attestation,
+            &approval,
+            error.as_deref().map_or(Ok(()), Err),
+        );
+        result
+    }
 }
 
 pub async fn create_server(signal_bus: Arc<SignalBus>, config: crate::Config) -> Result<()> {
-    let state = AppState {
-        signal_bus,
-        config: Arc::new(RwLock::new(config)),
-    };
+    serve(AppState::new(signal_bus, config)).await
+}
+
+pub async fn serve(state: AppState) -> Result<()> {
+    serve_on(bind().await?, state).await
+}
+
+/// Bind the web server's listener
+///
+/// Separate from [`serve_on`] so startup can wait for the port before
+/// serving.
+pub async fn bind() -> Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind("0.0.0.0:8080")
+        .await
+        .map_err(|e| PlcError::WebServer(e.to_string()))
+}
 
+/// Serve the web API on a listener from [`bind`]
+pub async fn serve_on(listener: tokio::net::TcpListener, state: AppState) -> Result<()> {
     let app = Router::new()
         .route("/health", get(handlers::health))
+        .route("/api/features", get(handlers::get_features))
         .route("/api/signals", get(handlers::get_signals))
         .route("/api/signals/:name", get(handlers::get_signal))
         .route("/api/signals/:name", post(handlers::set_signal))
+        .route("/api/signals:action", post(batch::write_signals))
+        .route("/api/signal-groups/:name/snapshot", get(handlers::get_group_snapshot))
+        .route("/api/display/signals", get(handlers::get_signals_formatted))
+        .route("/api/display/signals/:name", get(handlers::get_signal_formatted))
+        .route("/api/display/report", get(handlers::get_signal_report))
         .route("/api/config", get(handlers::get_config))
         .route("/api/config", post(handlers::update_config))
-        .route("/ws", get(websocket_handler))
+        .route("/api/config/validate", post(designer::validate_config))
+        .route("/api/config/deploy", post(designer::deploy_config))
+        .route("/api/designer/layout", get(designer::get_layout))
+        .route("/api/designer/layout", put(designer::update_layout))
+        .route("/api/blocks/types", get(designer::block_types))
+        .route("/api/events", get(events::list_events))
+        .route("/api/debug", get(debug::status))
+        .route("/api/debug/pause", post(debug::pause))
+        .route("/api/debug/resume", post(debug::resume))
+        .route("/api/debug/step", post(debug::step))
+        .route("/api/debug/watchpoints/:signal", put(debug::watch).delete(debug::unwatch))
+        .route("/api/dead-letters", get(dead_letters::list))
+        .route("/api/dead-letters/:id", get(dead_letters::get).delete(dead_letters::discard))
+        .route("/api/dead-letters/:id/retry", post(dead_letters::retry))
+        .route("/api/protocols/:name/config", put(protocols::reconfigure))
+        .route("/api/forces", get(forces::list))
+        .route("/api/signals/:name/force", put(forces::force).delete(forces::unforce))
+        .route("/api/dashboards", get(dashboards::list_dashboards))
+        .route(
+            "/api/dashboards/:name",
+            get(dashboards::get_dashboard)
+                .put(dashboards::save_dashboard)
+                .delete(dashboards::delete_dashboard),
+        )
+        .route("/ws", get(websocket_handler));
+
+    #[cfg(feature = "audit")]
+    let app = app
+        .route("/api/audit", get(audit::query))
+        .route("/api/security/logins", get(security::logins))
+        .route("/api/security/sessions", get(security::sessions))
+        .route("/api/security/users", get(security::users));
+
+    #[cfg(feature = "oee")]
+    let app = app
+        .route("/api/oee", get(oee::list_lines))
+        .route("/api/oee/:line", get(oee::get_line))
+        .route("/api/oee/:line/shifts", get(oee::get_shifts));
+
+    #[cfg(feature = "downtime")]
+    let app = app
+        .route("/api/downtime/events", get(downtime::list_events))
+        .route("/api/downtime/events/:id/reason", put(downtime::assign_reason))
+        .route("/api/downtime/reasons", get(downtime::list_reasons))
+        .route("/api/downtime/pareto", get(downtime::pareto));
+
+    #[cfg(feature = "maintenance")]
+    let app = app
+        .route("/api/maintenance/rules", get(maintenance::list_rules))
+        .route("/api/maintenance/work-items", get(maintenance::list_work_items))
+        .route("/api/maintenance/work-items/:id/acknowledge", post(maintenance::acknowledge))
+        .route("/api/maintenance/work-items/:id/complete", post(maintenance::complete));
+
+    #[cfg(feature = "maintenance-mode")]
+    let app = app
+        .route("/api/maintenance-mode", get(maintenance_mode::list_areas))
+        .route("/api/maintenance-mode/:area/enter", post(maintenance_mode::enter))
+        .route("/api/maintenance-mode/:area/exit", post(maintenance_mode::exit));
+
+    #[cfg(feature = "schedules")]
+    let app = app
+        .route("/api/schedules", get(schedules::list))
+        .route("/api/schedules/:name", get(schedules::get).put(schedules::replace).delete(schedules::restore));
+
+    #[cfg(feature = "energy")]
+    let app = app
+        .route("/api/energy", get(energy::list_meters))
+        .route("/api/energy/:meter", get(energy::get_meter))
+        .route("/api/energy/:meter/records", get(energy::get_records));
+
+    #[cfg(feature = "redundancy")]
+    let app = app.route("/api/redundancy", get(redundancy::status));
+
+    #[cfg(feature = "scan-budget")]
+    let app = app
+        .route("/api/budget", get(budget::get_budget))
+        .route("/api/budget/metrics", get(budget::get_budget_metrics));
+
+    #[cfg(feature = "enhanced-monitoring")]
+    let app = app
+        .route("/api/engine/cycle", get(cycle::get_breakdown))
+        .route("/api/engine/cycle/metrics", get(cycle::get_breakdown_metrics));
+
+    let app = app
         .nest_service("/", ServeDir::new("petra-designer/dist"))
         .fallback(spa_fallback)
         .layer(CorsLayer::permissive())
         .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080")
-        .await
-        .map_err(|e| PlcError::WebServer(e.to_string()))?;
     println!("Web server listening on {}", listener.local_addr().unwrap());
 
-    axum::serve(listener, app.into_make_service())
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .map_err(|e| PlcError::WebServer(e.to_string()))?;
 
     Ok(())
 }
 
-async fn websocket_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| websocket::handle_socket(socket, state))
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| websocket::handle_socket(socket, state, peer))
 }
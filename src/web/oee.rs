@@ -0,0 +1,65 @@
+//! OEE REST endpoints
+//!
+//! Read-only views of the OEE manager: current shift results per line and the
+//! completed shift history used for reporting.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use super::AppState;
+use crate::oee::{LineSnapshot, SharedOeeManager, ShiftRecord};
+use crate::{PlcError, Result};
+
+fn manager(state: &AppState) -> Result<&SharedOeeManager> {
+    state
+        .oee
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound("OEE tracking is not configured".to_string()))
+}
+
+/// Current shift results for all lines
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if OEE tracking is not configured.
+pub async fn list_lines(State(state): State<AppState>) -> Result<Json<Vec<LineSnapshot>>> {
+    Ok(Json(manager(&state)?.read().await.snapshots()))
+}
+
+/// Current shift results for one line
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if OEE tracking is not configured or the
+/// line has no data yet.
+pub async fn get_line(
+    State(state): State<AppState>,
+    Path(line): Path<String>,
+) -> Result<Json<LineSnapshot>> {
+    manager(&state)?
+        .read()
+        .await
+        .snapshot(&line)
+        .map(Json)
+        .ok_or_else(|| PlcError::NotFound(format!("OEE line '{line}'")))
+}
+
+/// Completed shift records for one line
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if OEE tracking is not configured or the
+/// line does not exist.
+pub async fn get_shifts(
+    State(state): State<AppState>,
+    Path(line): Path<String>,
+) -> Result<Json<Vec<ShiftRecord>>> {
+    manager(&state)?
+        .read()
+        .await
+        .shift_history(&line)
+        .map(Json)
+        .ok_or_else(|| PlcError::NotFound(format!("OEE line '{line}'")))
+}
@@ -0,0 +1,78 @@
+//! Protocol driver REST endpoints
+//!
+//! - `PUT /api/protocols/:name/config` with the driver's configuration
+//!   section, e.g. an S7 connection with a new `poll_interval_ms` or `tags`,
+//!   applies it while the engine keeps running (see
+//!   [`ProtocolManager::reload_driver`](crate::protocols::ProtocolManager::reload_driver)).
+//!   Modbus RTU lines are named after their serial port, URL-encoded
+//!   (`%2Fdev%2FttyUSB0`), and take the list of the line's connections
+//!
+//! Changes are made on behalf of the user named in the `x-petra-user`
+//! header, which is required, and are logged.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use tracing::info;
+
+use super::dashboards::USER_HEADER;
+use super::AppState;
+use crate::{PlcError, Result};
+
+fn user(headers: &HeaderMap) -> Result<String> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| PlcError::Validation(format!("Driver changes require the {USER_HEADER} header")))
+}
+
+/// `PUT /api/protocols/:name/config`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user, [`PlcError::NotFound`]
+/// for an unknown driver, [`PlcError::Config`] for rejected parameters and
+/// [`PlcError::Protocol`] if the driver could not be reloaded in time.
+pub async fn reconfigure(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(config): Json<serde_json::Value>,
+) -> Result<()> {
+    let user = user(&headers)?;
+    let manager = state
+        .protocols
+        .as_ref()
+        .ok_or_else(|| PlcError::NotFound(format!("Protocol driver '{name}' not found")))?;
+    let config = serde_yaml::to_value(config).map_err(|e| PlcError::Config(format!("Invalid driver configuration: {e}")))?;
+    manager.reload_driver(&name, &config).await?;
+    info!("Protocol driver '{}' reconfigured by {}", name, user);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, SignalBus};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_reconfigure_needs_user_and_driver() {
+        let state = AppState::new(Arc::new(SignalBus::new()), Config::example_basic().unwrap());
+        let config = Json(serde_json::json!({ "poll_interval_ms": 100 }));
+        let result = reconfigure(State(state.clone()), Path("plc".to_string()), HeaderMap::new(), config.clone()).await;
+        assert!(matches!(result, Err(PlcError::Validation(_))));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_HEADER, "alice".parse().unwrap());
+        let manager = Arc::new(crate::protocols::ProtocolManager::new(SignalBus::new()));
+        let state = state.with_protocols(manager);
+        let result = reconfigure(State(state), Path("plc".to_string()), headers, config).await;
+        assert!(matches!(result, Err(PlcError::NotFound(_))));
+    }
+}
@@ -0,0 +1,23 @@
+//! Redundancy status endpoint
+//!
+//! Role of this node of a redundant pair and what it last heard from its
+//! peer, for HMIs and monitoring to show which node is in control.
+
+use axum::{extract::State, Json};
+
+use super::AppState;
+use crate::redundancy::RedundancyStatus;
+use crate::{PlcError, Result};
+
+/// Role, failovers and peer heartbeat
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if redundancy is not configured.
+pub async fn status(State(state): State<AppState>) -> Result<Json<RedundancyStatus>> {
+    state
+        .redundancy
+        .as_ref()
+        .map(|node| Json(node.status()))
+        .ok_or_else(|| PlcError::NotFound("Redundancy is not configured".to_string()))
+}
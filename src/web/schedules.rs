@@ -0,0 +1,87 @@
+//! Schedule REST endpoints
+//!
+//! - `GET /api/schedules` lists the schedules of the SCHEDULE blocks, each
+//!   with whether it is on now and whether it was edited at runtime
+//! - `GET /api/schedules/:name` returns the schedule of one block
+//! - `PUT /api/schedules/:name` with a schedule body (`timezone`, `weekly`,
+//!   `exceptions`) replaces it from the block's next scan
+//! - `DELETE /api/schedules/:name` restores the configured schedule
+//!
+//! Changes are made on behalf of the user named in the `x-petra-user`
+//! header, which is required, and are logged.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+use super::dashboards::USER_HEADER;
+use super::AppState;
+use crate::schedule::{Schedule, ScheduleStatus};
+use crate::{PlcError, Result};
+
+fn user(headers: &HeaderMap) -> Result<String> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| PlcError::Validation(format!("Schedule changes require the {USER_HEADER} header")))
+}
+
+fn now(state: &AppState) -> DateTime<Utc> {
+    state.signal_bus.clock().system_time().into()
+}
+
+/// `GET /api/schedules`
+pub async fn list(State(state): State<AppState>) -> Json<Vec<ScheduleStatus>> {
+    Json(state.signal_bus.schedules().list(now(&state)))
+}
+
+/// `GET /api/schedules/:name`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] if no SCHEDULE block has that name.
+pub async fn get(State(state): State<AppState>, Path(name): Path<String>) -> Result<Json<ScheduleStatus>> {
+    Ok(Json(state.signal_bus.schedules().get(&name, now(&state))?))
+}
+
+/// `PUT /api/schedules/:name`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user, [`PlcError::NotFound`]
+/// for an unknown block and [`PlcError::Config`] for an invalid schedule.
+pub async fn replace(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(schedule): Json<Schedule>,
+) -> Result<Json<ScheduleStatus>> {
+    let user = user(&headers)?;
+    let status = state.signal_bus.schedules().replace(&name, schedule, now(&state))?;
+    info!("Schedule '{}' replaced by {}", name, user);
+    Ok(Json(status))
+}
+
+/// `DELETE /api/schedules/:name`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Validation`] without a user and
+/// [`PlcError::NotFound`] for an unknown block.
+pub async fn restore(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ScheduleStatus>> {
+    let user = user(&headers)?;
+    let status = state.signal_bus.schedules().restore(&name, now(&state))?;
+    info!("Schedule '{}' restored to its configuration by {}", name, user);
+    Ok(Json(status))
+}
@@ -0,0 +1,83 @@
+//! Login history and sessions for the security page
+//!
+//! All three endpoints read the audit log:
+//!
+//! - `GET /api/security/logins` lists login attempts, newest first. It takes
+//!   the `/api/audit` filters; `success=false` selects failed attempts.
+//! - `GET /api/security/sessions` lists open sessions, optionally of one
+//!   `user`.
+//! - `GET /api/security/users` summarises logins per user, counting failed
+//!   attempts since `since` (RFC 3339), the last 24 hours by default.
+//!
+//! ```text
+//! GET /api/security/logins?user=hmi1&success=false&limit=20
+//! ```
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::AppState;
+use crate::security::audit::{ActiveSession, AuditEntry, AuditLog, AuditQuery, UserActivity};
+use crate::{PlcError, Result};
+
+/// Failed attempts are counted over this window unless `since` is given
+const DEFAULT_ACTIVITY_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
+/// Query of `GET /api/security/sessions`
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionQuery {
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// Query of `GET /api/security/users`
+#[derive(Debug, Default, Deserialize)]
+pub struct ActivityQuery {
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+fn audit_log(state: &AppState) -> Result<&AuditLog> {
+    state
+        .audit
+        .as_deref()
+        .ok_or_else(|| PlcError::NotFound("Audit log is not configured".to_string()))
+}
+
+/// `GET /api/security/logins`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] when no audit log is configured, or an I/O
+/// error if the log cannot be read.
+pub async fn logins(State(state): State<AppState>, Query(query): Query<AuditQuery>) -> Result<Json<Vec<AuditEntry>>> {
+    Ok(Json(audit_log(&state)?.logins(&query)?))
+}
+
+/// `GET /api/security/sessions`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] when no audit log is configured, or an I/O
+/// error if the log cannot be read.
+pub async fn sessions(
+    State(state): State<AppState>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Vec<ActiveSession>>> {
+    Ok(Json(audit_log(&state)?.active_sessions(query.user.as_deref())?))
+}
+
+/// `GET /api/security/users`
+///
+/// # Errors
+///
+/// Returns [`PlcError::NotFound`] when no audit log is configured, or an I/O
+/// error if the log cannot be read.
+pub async fn users(State(state): State<AppState>, Query(query): Query<ActivityQuery>) -> Result<Json<Vec<UserActivity>>> {
+    let since = query.since.unwrap_or_else(|| Utc::now() - DEFAULT_ACTIVITY_WINDOW);
+    Ok(Json(audit_log(&state)?.user_activity(since)?))
+}
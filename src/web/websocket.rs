@@ -1,14 +1,174 @@
 // src/web/websocket.rs
+//
+// Clients subscribe to signal updates and, once authenticated, may write
+// signals and acknowledge alarms. Authentication is configured under
+// `web.websocket_auth`; each client presents its token in an `auth` message
+// and every later write is checked against its role:
+//
+// ```yaml
+// web:
+//   websocket_auth:
+//     clients:
+//       - { id: hmi1, token: "change-me-please!", role: operator }
+//     rbac:
+//       roles:
+//         operator:
+//           permissions: ["signal.write:line1.*", "alarm.ack:*"]
+// ```
+//
+// Rejected writes are answered with a `rejected` message carrying the
+// client's optional request `id`. Writes and acknowledgements may carry a
+// `reason`, which is recorded in the audit log and required for critical
+// signals; writes that must be signed also carry a `signature`. Without
+// `websocket_auth` signal writes stay open as before and alarm
+// acknowledgement is unavailable.
+//
+// Every `auth` attempt is audited with the peer address. A successful one
+// opens a session, which ends when the client authenticates again or the
+// socket closes; `/api/security` reports these from the audit log.
+//
+// A `subscribe_events` message follows the engine event stream, replaying
+// retained events after its optional `after` sequence number first. Events
+// arrive as `event` messages; each subscription is a separate stream, so a
+// client sends it once per connection.
+//
+// Dashboards on slow links can use `subscribe_group` instead of single
+// signal subscriptions; see `coalesce` for the rate-limited group updates.
 
 use axum::extract::ws::{Message, WebSocket};
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval, Duration};
+use crate::events::EngineEvent;
+use crate::subscription::{Coalesce, SubscribeOptions};
 use crate::Value;
+use super::coalesce::{GroupUpdate, SignalGroup};
 use super::AppState;
+#[cfg(feature = "rbac")]
+use crate::security::rbac::{RbacConfig, ACK_ALARM_PERMISSION, WRITE_SIGNAL_PERMISSION};
+#[cfg(feature = "rbac")]
+use crate::PlcError;
+
+/// Token-authenticated WebSocket clients and their roles
+#[cfg(feature = "rbac")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebSocketAuthConfig {
+    /// Clients allowed to authenticate
+    #[serde(default)]
+    pub clients: Vec<WebSocketClient>,
+
+    /// Roles and their permissions
+    #[serde(default)]
+    pub rbac: RbacConfig,
+}
+
+/// A WebSocket client allowed to write
+#[cfg(feature = "rbac")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketClient {
+    /// Client name sent in the `auth` message
+    pub id: String,
+    /// Shared bearer token
+    pub token: String,
+    /// Role checked against the RBAC configuration
+    pub role: String,
+}
+
+#[cfg(feature = "rbac")]
+impl WebSocketAuthConfig {
+    /// Validate clients and roles
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlcError::Config`] for duplicate clients, short tokens or
+    /// unknown roles.
+    pub fn validate(&self) -> crate::Result<()> {
+        self.rbac.validate()?;
+
+        let mut ids = HashSet::new();
+        for client in &self.clients {
+            if !ids.insert(client.id.as_str()) {
+                return Err(PlcError::Config(format!("Duplicate WebSocket client '{}'", client.id)));
+            }
+            if client.token.len() < 16 {
+                return Err(PlcError::Config(format!(
+                    "WebSocket client '{}' token must be at least 16 characters",
+                    client.id
+                )));
+            }
+            if !self.rbac.roles.contains_key(&client.role) {
+                return Err(PlcError::Config(format!(
+                    "WebSocket client '{}' has unknown role '{}'",
+                    client.id, client.role
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Session for `client` if `token` matches
+    fn authenticate(&self, client: &str, token: &str) -> Option<Session> {
+        self.clients
+            .iter()
+            .find(|c| c.id == client && constant_time_eq(c.token.as_bytes(), token.as_bytes()))
+            .map(|c| Session {
+                id: uuid::Uuid::new_v4().to_string(),
+                client: c.id.clone(),
+                role: c.role.clone(),
+            })
+    }
+
+    /// Require that `session` may perform `operation` on `target`
+    fn check(&self, session: Option<&Session>, operation: Operation, target: &str) -> std::result::Result<(), String> {
+        let session = session.ok_or_else(|| "Not authenticated".to_string())?;
+        self.rbac
+            .check(&session.role, operation.permission(), target)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "rbac")]
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Identity established by a successful `auth` message
+#[derive(Debug, Clone)]
+struct Session {
+    /// Id the login and logout are audited under
+    id: String,
+    client: String,
+    #[cfg_attr(not(feature = "rbac"), allow(dead_code))]
+    role: String,
+}
+
+/// Write operations subject to permission checks
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    SetSignal,
+    AckAlarm,
+}
+
+impl Operation {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::SetSignal => "set_signal",
+            Self::AckAlarm => "ack_alarm",
+        }
+    }
+
+    #[cfg(feature = "rbac")]
+    const fn permission(self) -> &'static str {
+        match self {
+            Self::SetSignal => WRITE_SIGNAL_PERMISSION,
+            Self::AckAlarm => ACK_ALARM_PERMISSION,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -22,8 +182,44 @@ pub enum ClientMessage {
     #[serde(rename = "unsubscribe_signal")]
     UnsubscribeSignal { signal: String },
 
+    #[serde(rename = "subscribe_group")]
+    SubscribeGroup {
+        group: String,
+        signals: Vec<String>,
+        #[serde(default)]
+        min_interval_ms: Option<u64>,
+    },
+
+    #[serde(rename = "unsubscribe_group")]
+    UnsubscribeGroup { group: String },
+
+    #[serde(rename = "subscribe_events")]
+    SubscribeEvents {
+        #[serde(default)]
+        after: Option<u64>,
+    },
+
+    #[serde(rename = "auth")]
+    Auth { client: String, token: String },
+
     #[serde(rename = "set_signal")]
-    SetSignal { signal: String, value: Value },
+    SetSignal {
+        signal: String,
+        value: Value,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(flatten)]
+        attestation: super::Attestation,
+    },
+
+    #[serde(rename = "ack_alarm")]
+    AckAlarm {
+        alarm: String,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        reason: Option<String>,
+    },
 
     #[serde(rename = "ping")]
     Ping { timestamp: Option<u64> },
@@ -38,6 +234,12 @@ pub enum ServerMessage {
         data: SignalUpdateData,
     },
 
+    #[serde(rename = "event")]
+    Event(EngineEvent),
+
+    #[serde(rename = "group_update")]
+    GroupUpdate(GroupUpdate),
+
     #[serde(rename = "error")]
     Error { error: String },
 
@@ -46,6 +248,26 @@ pub enum ServerMessage {
     
     #[serde(rename = "connected")]
     Connected { version: String },
+
+    #[serde(rename = "authenticated")]
+    Authenticated { client: String, role: String },
+
+    #[serde(rename = "accepted")]
+    Accepted {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        action: String,
+        target: String,
+    },
+
+    #[serde(rename = "rejected")]
+    Rejected {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        action: String,
+        target: String,
+        reason: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -59,10 +281,12 @@ pub struct SignalUpdateData {
 
 struct ClientState {
     subscriptions: Arc<RwLock<HashSet<String>>>,
+    groups: Arc<RwLock<HashMap<String, SignalGroup>>>,
     tx: mpsc::Sender<String>,
 }
 
-pub async fn handle_socket(socket: WebSocket, state: AppState) {
+pub async fn handle_socket(socket: WebSocket, state: AppState, peer: SocketAddr) {
+    let source = peer.ip().to_string();
     let (mut sender, mut receiver) = socket.split();
     let (tx, mut rx) = mpsc::channel(100);
     
@@ -75,85 +299,94 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     // Client state
     let client_state = ClientState {
         subscriptions: Arc::new(RwLock::new(HashSet::new())),
+        groups: Arc::new(RwLock::new(HashMap::new())),
         tx: tx.clone(),
     };
 
     // Spawn task to handle incoming messages
     let state_clone = state.clone();
     let client_state_clone = client_state.subscriptions.clone();
+    let groups_clone = client_state.groups.clone();
     let tx_clone = tx.clone();
     
     tokio::spawn(async move {
+        let mut session: Option<Session> = None;
         while let Some(msg) = receiver.next().await {
             if let Ok(msg) = msg {
                 match msg {
                     Message::Text(text) => {
-                        handle_client_message(text, &state_clone, &client_state_clone, &tx_clone).await;
+                        handle_client_message(text, &state_clone, &source, &client_state_clone, &groups_clone, &tx_clone, &mut session).await;
                     }
                     Message::Close(_) => break,
                     _ => {}
                 }
             }
         }
+        if let Some(session) = session {
+            state_clone.audit_logout(&session.client, &source, &session.id);
+        }
     });
 
     // Spawn task to send signal updates
     let state_clone = state.clone();
     let subscriptions = client_state.subscriptions.clone();
+    let groups = client_state.groups.clone();
     let tx_clone = tx.clone();
     
     tokio::spawn(async move {
-        let mut ticker = interval(Duration::from_millis(50)); // 20Hz update rate for better responsiveness
-        let mut last_values: std::collections::HashMap<String, Value> = std::collections::HashMap::new();
-        let mut update_count = 0;
+        // Changes of subscribed signals are pushed by the bus; the ticker
+        // paces group updates and catches quality changes without a write,
+        // such as a signal put into maintenance
+        let mut changes = state_clone
+            .signal_bus
+            .subscribe_with(["*"], SubscribeOptions::default().capacity(4096).coalesce(Coalesce::Latest));
+        let mut ticker = interval(Duration::from_millis(50));
+        let mut last_values: HashMap<String, (Value, &'static str)> = HashMap::new();
         
         loop {
-            ticker.tick().await;
-            
-            let subs = subscriptions.read().await;
-            if subs.is_empty() {
-                continue;
-            }
-            
-            // Log subscription count periodically
-            update_count += 1;
-            if update_count % 100 == 0 { // Every 5 seconds at 20Hz
-                println!("WebSocket: Monitoring {} subscribed signals", subs.len());
-            }
-            
-            let mut updates_sent = 0;
-            
-            for signal_name in subs.iter() {
-                if let Some(current_value) = state_clone.signal_bus.get(signal_name) {
-                    let should_send = match last_values.get(signal_name) {
-                        Some(last_value) => !values_equal(last_value, &current_value),
-                        None => true,
-                    };
-                    
-                    if should_send {
-                        updates_sent += 1;
-                        last_values.insert(signal_name.clone(), current_value.clone());
-                        
-                        let update = ServerMessage::SignalUpdate {
-                            data: SignalUpdateData {
-                                signal: signal_name.clone(),
-                                value: current_value,
-                                timestamp: get_timestamp(),
-                                quality: Some("good".to_string()),
-                            },
-                        };
-                        
-                        if tx_clone.send(serde_json::to_string(&update).unwrap()).await.is_err() {
-                            println!("WebSocket: Client disconnected");
-                            return; // Client disconnected
-                        }
+            let updates: Vec<(String, Value, u64)> = tokio::select! {
+                Some(change) = changes.next() => {
+                    if !subscriptions.read().await.contains(&change.name) {
+                        continue;
                     }
+                    let timestamp = u64::try_from(change.meta.unix_millis()).unwrap_or_default();
+                    vec![(change.name, change.value, timestamp)]
                 }
-            }
+                _ = ticker.tick() => {
+                    if !send_group_updates(&groups, &state_clone, &tx_clone).await {
+                        return; // Client disconnected
+                    }
+                    let subs = subscriptions.read().await;
+                    last_values
+                        .iter()
+                        .filter(|(signal, (_, quality))| subs.contains(*signal) && *quality != state_clone.signal_quality(signal))
+                        .map(|(signal, (value, _))| (signal.clone(), value.clone(), get_timestamp()))
+                        .collect()
+                }
+            };
             
-            // Log updates periodically for debugging
-            if updates_sent > 0 && update_count % 20 == 0 { // Every second at 20Hz
-                println!("WebSocket: Sent {} signal updates", updates_sent);
+            for (signal, value, timestamp) in updates {
+                let quality = state_clone.signal_quality(&signal);
+                if last_values
+                    .get(&signal)
+                    .is_some_and(|(last_value, last_quality)| values_equal(last_value, &value) && *last_quality == quality)
+                {
+                    continue;
+                }
+                last_values.insert(signal.clone(), (value.clone(), quality));
+                
+                let update = ServerMessage::SignalUpdate {
+                    data: SignalUpdateData {
+                        signal,
+                        value,
+                        timestamp,
+                        quality: Some(quality.to_string()),
+                    },
+                };
+                if tx_clone.send(serde_json::to_string(&update).unwrap()).await.is_err() {
+                    println!("WebSocket: Client disconnected");
+                    return; // Client disconnected
+                }
             }
         }
     });
@@ -169,8 +402,11 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
 async fn handle_client_message(
     text: String,
     state: &AppState,
+    source: &str,
     subscriptions: &Arc<RwLock<HashSet<String>>>,
+    groups: &Arc<RwLock<HashMap<String, SignalGroup>>>,
     tx: &mpsc::Sender<String>,
+    session: &mut Option<Session>,
 ) {
     let msg: Result<ClientMessage, _> = serde_json::from_str(&text);
 
@@ -183,10 +419,10 @@ async fn handle_client_message(
             if let Some(value) = state.signal_bus.get(&signal) {
                 let update = ServerMessage::SignalUpdate {
                     data: SignalUpdateData {
+                        quality: Some(state.signal_quality(&signal).to_string()),
                         signal,
                         value,
                         timestamp: get_timestamp(),
-                        quality: Some("good".to_string()),
                     },
                 };
                 let _ = tx.send(serde_json::to_string(&update).unwrap()).await;
@@ -203,10 +439,10 @@ async fn handle_client_message(
                 if let Some(value) = state.signal_bus.get(&signal) {
                     let update = ServerMessage::SignalUpdate {
                         data: SignalUpdateData {
+                            quality: Some(state.signal_quality(&signal).to_string()),
                             signal,
                             value,
                             timestamp: get_timestamp(),
-                            quality: Some("good".to_string()),
                         },
                     };
                     let _ = tx.send(serde_json::to_string(&update).unwrap()).await;
@@ -218,21 +454,111 @@ async fn handle_client_message(
             println!("WebSocket: Unsubscribe from signal: {}", signal);
             subscriptions.write().await.remove(&signal);
         }
-        
-        Ok(ClientMessage::SetSignal { signal, value }) => {
-            println!("WebSocket: Set signal {} = {:?}", signal, value);
-            match state.signal_bus.set(&signal, value.clone()) {
-                Ok(_) => {
-                    // Don't send immediate update - let the update loop handle it
-                    // This prevents duplicate updates
-                }
-                Err(e) => {
-                    let error_msg = ServerMessage::Error { 
-                        error: format!("Failed to set signal: {}", e) 
+
+        Ok(ClientMessage::SubscribeGroup { group, signals, min_interval_ms }) => {
+            let group_state = SignalGroup::new(group.clone(), signals, min_interval_ms.map(Duration::from_millis));
+            println!("WebSocket: Subscribe to group {} every {:?}", group, group_state.interval());
+            // Replacing a group resends all of its values
+            groups.write().await.insert(group, group_state);
+        }
+
+        Ok(ClientMessage::UnsubscribeGroup { group }) => {
+            println!("WebSocket: Unsubscribe from group: {group}");
+            groups.write().await.remove(&group);
+        }
+
+        Ok(ClientMessage::SubscribeEvents { after }) => {
+            let Some(events) = &state.events else {
+                let error_msg = ServerMessage::Error {
+                    error: "Event stream is not available".to_string(),
+                };
+                let _ = tx.send(serde_json::to_string(&error_msg).expect("server messages serialize")).await;
+                return;
+            };
+            println!("WebSocket: Subscribe to events after {after:?}");
+            let mut subscription = events.subscribe(after);
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let event = tokio::select! {
+                        event = subscription.next() => event,
+                        () = tx.closed() => None,
+                    };
+                    let Some(event) = event else {
+                        break;
                     };
-                    let _ = tx.send(serde_json::to_string(&error_msg).unwrap()).await;
+                    let msg = serde_json::to_string(&ServerMessage::Event(event)).expect("server messages serialize");
+                    if tx.send(msg).await.is_err() {
+                        break;
+                    }
                 }
+            });
+        }
+        
+        Ok(ClientMessage::Auth { client, token }) => {
+            // Authenticating again ends the current session either way
+            if let Some(previous) = session.take() {
+                state.audit_logout(&previous.client, source, &previous.id);
             }
+            let reply = match authenticate(state, &client, &token).await {
+                Ok(authenticated) => {
+                    println!("WebSocket: Client '{}' authenticated as '{}'", authenticated.client, authenticated.role);
+                    state.audit_login(&authenticated.client, source, &authenticated.id, Ok(()));
+                    let reply = ServerMessage::Authenticated {
+                        client: authenticated.client.clone(),
+                        role: authenticated.role.clone(),
+                    };
+                    *session = Some(authenticated);
+                    reply
+                }
+                Err(reason) => {
+                    println!("WebSocket: Authentication failed for '{client}': {reason}");
+                    state.audit_login(&client, source, "", Err(&reason));
+                    ServerMessage::Rejected {
+                        id: None,
+                        action: "auth".to_string(),
+                        target: client,
+                        reason,
+                    }
+                }
+            };
+            let _ = tx.send(serde_json::to_string(&reply).expect("server messages serialize")).await;
+        }
+
+        Ok(ClientMessage::SetSignal { signal, value, id, attestation }) => {
+            println!("WebSocket: Set signal {} = {:?}", signal, value);
+            let user = session.as_ref().map_or(super::ANONYMOUS_USER, |s| s.client.as_str());
+            let result = match authorize(state, session.as_ref(), Operation::SetSignal, &signal).await {
+                // Don't send a signal update - the update loop reports the new value
+                Ok(()) => state
+                    .operator_write(user, source, &signal, &value, &attestation)
+                    .map_err(|e| format!("Failed to set signal: {e}")),
+                Err(denied) => {
+                    let old_value = state.signal_bus.get(&signal);
+                    state.audit_write(
+                        user,
+                        source,
+                        &signal,
+                        old_value,
+                        &value,
+                        &attestation,
+                        &super::Approval::default(),
+                        Err(&denied),
+                    );
+                    Err(denied)
+                }
+            };
+            send_result(tx, id, Operation::SetSignal, signal, result).await;
+        }
+
+        Ok(ClientMessage::AckAlarm { alarm, id, reason }) => {
+            let result = match authorize(state, session.as_ref(), Operation::AckAlarm, &alarm).await {
+                Ok(()) => acknowledge_alarm(state, &alarm, session.as_ref()).await,
+                Err(denied) => Err(denied),
+            };
+            let user = session.as_ref().map_or(super::ANONYMOUS_USER, |s| s.client.as_str());
+            state.audit_ack(user, source, &alarm, reason.as_deref(), result.as_ref().copied().map_err(String::as_str));
+            send_result(tx, id, Operation::AckAlarm, alarm, result).await;
         }
         
         Ok(ClientMessage::Ping { timestamp }) => {
@@ -251,6 +577,107 @@ async fn handle_client_message(
     }
 }
 
+/// Sample every group and send the updates that are due
+///
+/// Returns `false` once the client has disconnected.
+async fn send_group_updates(
+    groups: &RwLock<HashMap<String, SignalGroup>>,
+    state: &AppState,
+    tx: &mpsc::Sender<String>,
+) -> bool {
+    let mut groups = groups.write().await;
+    let now = std::time::Instant::now();
+    for group in groups.values_mut() {
+        group.sample(&state.signal_bus, values_equal);
+        let Some(update) = group.flush(now, get_timestamp()) else {
+            continue;
+        };
+        let msg = serde_json::to_string(&ServerMessage::GroupUpdate(update)).expect("server messages serialize");
+        if tx.send(msg).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Look up the client presenting `token` in the configured WebSocket clients
+#[cfg_attr(not(feature = "rbac"), allow(clippy::unused_async))]
+async fn authenticate(state: &AppState, client: &str, token: &str) -> std::result::Result<Session, String> {
+    #[cfg(feature = "rbac")]
+    {
+        let config = state.config.read().await;
+        if let Some(auth) = config.web.as_ref().and_then(|w| w.websocket_auth.as_ref()) {
+            return auth
+                .authenticate(client, token)
+                .ok_or_else(|| "Invalid client or token".to_string());
+        }
+    }
+    let _ = (state, client, token);
+    Err("WebSocket authentication is not configured".to_string())
+}
+
+/// Check that the connection may perform `operation` on `target`
+#[cfg_attr(not(feature = "rbac"), allow(clippy::unused_async))]
+async fn authorize(
+    state: &AppState,
+    session: Option<&Session>,
+    operation: Operation,
+    target: &str,
+) -> std::result::Result<(), String> {
+    #[cfg(feature = "rbac")]
+    {
+        let config = state.config.read().await;
+        if let Some(auth) = config.web.as_ref().and_then(|w| w.websocket_auth.as_ref()) {
+            return auth.check(session, operation, target);
+        }
+    }
+    let _ = (state, session, target);
+    match operation {
+        // Without authentication configured signal writes stay open
+        Operation::SetSignal => Ok(()),
+        Operation::AckAlarm => Err("WebSocket authentication is not configured".to_string()),
+    }
+}
+
+#[cfg_attr(not(feature = "alarms"), allow(clippy::unused_async))]
+async fn acknowledge_alarm(
+    state: &AppState,
+    alarm: &str,
+    session: Option<&Session>,
+) -> std::result::Result<(), String> {
+    let user = session.map_or("websocket", |s| s.client.as_str());
+    #[cfg(feature = "alarms")]
+    if let Some(alarms) = &state.alarms {
+        println!("WebSocket: Alarm {alarm} acknowledged by {user}");
+        return alarms
+            .write()
+            .await
+            .acknowledge_alarm(alarm, user)
+            .await
+            .map_err(|e| format!("Failed to acknowledge alarm: {e}"));
+    }
+    let _ = (state, alarm, user);
+    Err("Alarm manager is not available".to_string())
+}
+
+async fn send_result(
+    tx: &mpsc::Sender<String>,
+    id: Option<String>,
+    operation: Operation,
+    target: String,
+    result: std::result::Result<(), String>,
+) {
+    let action = operation.name().to_string();
+    let reply = match result {
+        Ok(()) => ServerMessage::Accepted { id, action, target },
+        Err(reason) => {
+            println!("WebSocket: Rejected {action} on {target}: {reason}");
+            ServerMessage::Rejected { id, action, target, reason }
+        }
+    };
+    let _ = tx.send(serde_json::to_string(&reply).expect("server messages serialize")).await;
+}
+
 fn get_timestamp() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -282,3 +709,167 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_messages_carry_request_id() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"ack_alarm","alarm":"high_temp","id":"r1"}"#).unwrap();
+        assert!(matches!(msg, ClientMessage::AckAlarm { ref alarm, id: Some(ref id), .. } if alarm == "high_temp" && id == "r1"));
+
+        // Existing clients send set_signal without an id
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"set_signal","signal":"a","value":{"type":"Bool","value":true}}"#).unwrap();
+        assert!(matches!(msg, ClientMessage::SetSignal { id: None, .. }));
+
+        let reply = ServerMessage::Rejected {
+            id: Some("r1".to_string()),
+            action: "ack_alarm".to_string(),
+            target: "high_temp".to_string(),
+            reason: "Not authenticated".to_string(),
+        };
+        let json = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json["type"], "rejected");
+        assert_eq!(json["id"], "r1");
+    }
+
+    #[test]
+    fn test_event_messages() {
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"subscribe_events","after":41}"#).unwrap();
+        assert!(matches!(msg, ClientMessage::SubscribeEvents { after: Some(41) }));
+        let msg: ClientMessage = serde_json::from_str(r#"{"type":"subscribe_events"}"#).unwrap();
+        assert!(matches!(msg, ClientMessage::SubscribeEvents { after: None }));
+
+        let log = crate::events::EventLog::default();
+        log.publish(crate::events::EventKind::BlockRecovered { block: "pid".to_string() });
+        let event = log.since(0).remove(0);
+        let json = serde_json::to_value(ServerMessage::Event(event)).unwrap();
+        assert_eq!(json["type"], "event");
+        assert_eq!(json["event"], "block_recovered");
+        assert_eq!(json["sequence"], 1);
+        assert_eq!(json["block"], "pid");
+    }
+
+    #[test]
+    fn test_group_messages() {
+        let msg: ClientMessage = serde_json::from_str(
+            r#"{"type":"subscribe_group","group":"line1","signals":["a","b"],"min_interval_ms":2000}"#,
+        )
+        .unwrap();
+        assert!(matches!(msg, ClientMessage::SubscribeGroup { min_interval_ms: Some(2000), ref signals, .. } if signals.len() == 2));
+
+        let update = GroupUpdate {
+            group: "line1".to_string(),
+            timestamp: 1,
+            values: [("a".to_string(), Value::Integer(4))].into(),
+            changes: [("a".to_string(), 3)].into(),
+            unchanged: 1,
+        };
+        let json = serde_json::to_value(ServerMessage::GroupUpdate(update)).unwrap();
+        assert_eq!(json["type"], "group_update");
+        assert_eq!(json["changes"]["a"], 3);
+    }
+
+    #[cfg(feature = "rbac")]
+    #[test]
+    fn test_role_scoped_permissions() {
+        let auth: WebSocketAuthConfig = serde_yaml::from_str(
+            r#"
+clients:
+  - { id: hmi1, token: "0123456789abcdef", role: operator }
+rbac:
+  roles:
+    operator:
+      permissions: ["signal.write:line1.*", "alarm.ack:*"]
+"#,
+        )
+        .unwrap();
+        auth.validate().unwrap();
+
+        assert!(auth.authenticate("hmi1", "wrong-token-0000").is_none());
+        let session = auth.authenticate("hmi1", "0123456789abcdef").unwrap();
+        assert_eq!(session.role, "operator");
+
+        assert!(auth.check(Some(&session), Operation::SetSignal, "line1.speed").is_ok());
+        assert!(auth.check(Some(&session), Operation::SetSignal, "line2.speed").is_err());
+        assert!(auth.check(Some(&session), Operation::AckAlarm, "high_temp").is_ok());
+        assert!(auth.check(None, Operation::SetSignal, "line1.speed").is_err());
+    }
+
+    #[cfg(all(feature = "rbac", feature = "alarms"))]
+    #[tokio::test]
+    async fn test_ack_alarm_permission() {
+        let bus = crate::SignalBus::new();
+        bus.set("tank.level", Value::Float(95.0)).unwrap();
+        let alarm: crate::alarms::AlarmConfig = serde_yaml::from_str(
+            r#"
+name: high_level
+description: Tank level high
+tag_name: LT-101
+signal: tank.level
+condition: { type: High, threshold: 90.0 }
+priority: High
+consequence: Overflow
+corrective_action: Stop the feed pump
+max_response_time: 5
+classification: Process
+enabled: true
+setpoint: 90.0
+units: "%"
+area: tank
+equipment: tank1
+"#,
+        )
+        .unwrap();
+        let mut alarms = crate::alarms::AlarmManager::new(vec![alarm], bus.clone()).unwrap();
+        alarms.process().await.unwrap();
+
+        let mut config = crate::Config::example_basic().unwrap();
+        config.web = Some(
+            serde_yaml::from_str(
+                r#"
+websocket_auth:
+  clients:
+    - { id: hmi1, token: "0123456789abcdef", role: operator }
+    - { id: panel, token: "fedcba9876543210", role: viewer }
+  rbac:
+    roles:
+      operator:
+        permissions: ["alarm.ack:*"]
+      viewer:
+        permissions: ["signal.write:*"]
+"#,
+            )
+            .unwrap(),
+        );
+        let alarms = Arc::new(RwLock::new(alarms));
+        let state = AppState::new(Arc::new(bus), config).with_alarms(Arc::clone(&alarms));
+
+        let subscriptions = Arc::new(RwLock::new(HashSet::new()));
+        let groups = Arc::new(RwLock::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut session = None;
+        let mut send = async |text: &str| {
+            handle_client_message(text.to_string(), &state, "test", &subscriptions, &groups, &tx, &mut session).await;
+            serde_json::from_str::<serde_json::Value>(&rx.recv().await.unwrap()).unwrap()
+        };
+        let ack = r#"{"type":"ack_alarm","alarm":"high_level","id":"r1"}"#;
+
+        // Unauthenticated and unauthorized clients are rejected
+        assert_eq!(send(ack).await["type"], "rejected");
+        assert_eq!(send(r#"{"type":"auth","client":"panel","token":"fedcba9876543210"}"#).await["type"], "authenticated");
+        let reply = send(ack).await;
+        assert_eq!(reply["type"], "rejected");
+        assert_eq!(reply["id"], "r1");
+        assert_eq!(alarms.read().await.get_active_alarms()[0].state, crate::alarms::AlarmState::Unacknowledged);
+
+        // An operator acknowledges it once
+        assert_eq!(send(r#"{"type":"auth","client":"hmi1","token":"0123456789abcdef"}"#).await["type"], "authenticated");
+        assert_eq!(send(ack).await["type"], "accepted");
+        assert_eq!(alarms.read().await.get_active_alarms()[0].state, crate::alarms::AlarmState::Acknowledged);
+        assert_eq!(send(ack).await["type"], "rejected");
+    }
+}
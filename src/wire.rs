@@ -0,0 +1,558 @@
+// src/wire.rs - Versioned message schema for external integrators
+//
+// Systems consuming PETRA data over Kafka, NATS or gRPC code against three
+// messages: signal updates, alarm events and history batches. They are
+// defined here as Rust types, each with a descriptor of its fields from
+// which the protobuf definition and the JSON Schemas are generated:
+//
+// ```bash
+// petra schema --output schema/wire/v1          # write the package
+// petra schema --output schema/wire/v1 --check  # fail if it is outdated
+// ```
+//
+// The published copy lives in `schema/wire/v1`. Tests compare each type's
+// serde encoding with its descriptor and the published files with the
+// generator, so the types, the descriptors and the package cannot drift
+// apart unnoticed.
+//
+// Compatibility follows protobuf rules: fields are only added, with new
+// numbers; a removed field's number is never reused. Breaking changes get a
+// new major version with its own package (`petra.wire.v2`).
+
+use crate::error::{PlcError, Result};
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Version of the message package
+pub const WIRE_VERSION: &str = "1.0.0";
+
+/// Protobuf package of the messages
+pub const PROTO_PACKAGE: &str = "petra.wire.v1";
+
+/// Prefix of the `$id` of every JSON Schema
+const SCHEMA_ID_PREFIX: &str = "urn:petra:wire:v1";
+
+// ============================================================================
+// MESSAGES
+// ============================================================================
+
+/// New value of one signal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalUpdate {
+    pub signal: String,
+    pub value: Value,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+}
+
+/// Alarm lifecycle transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmEventKind {
+    Raised,
+    Acknowledged,
+    ReturnedToNormal,
+    Shelved,
+    Unshelved,
+}
+
+impl AlarmEventKind {
+    /// Every kind, in protobuf number order
+    pub const ALL: [Self; 5] = [
+        Self::Raised,
+        Self::Acknowledged,
+        Self::ReturnedToNormal,
+        Self::Shelved,
+        Self::Unshelved,
+    ];
+}
+
+/// Change in the state of an alarm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmEvent {
+    pub alarm: String,
+    pub signal: String,
+    pub kind: AlarmEventKind,
+    /// ISA-18.2 priority, 1 (highest) to 4
+    pub priority: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    pub message: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    /// Operator who acknowledged or shelved the alarm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Recorded samples of a time range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryBatch {
+    /// Instance that recorded the samples
+    pub source: String,
+    /// Counts up from 1 per source; a gap means a batch was lost
+    pub sequence: u64,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub samples: Vec<SignalUpdate>,
+}
+
+// ============================================================================
+// DESCRIPTORS
+// ============================================================================
+
+/// Type of a message field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    UInt32,
+    Int64,
+    UInt64,
+    String,
+    /// Signal value
+    Value,
+    /// Enumeration described by [`ENUMS`]
+    Enum(&'static str),
+    /// Message described by [`MESSAGES`]
+    Message(&'static str),
+}
+
+/// Presence of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Required,
+    /// Left out of JSON when absent
+    Optional,
+    Repeated,
+}
+
+/// One field of a message
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    /// Protobuf field number, never reused
+    pub number: u32,
+    pub field_type: FieldType,
+    pub label: Label,
+    pub doc: &'static str,
+}
+
+/// Fields of a message
+#[derive(Debug, Clone, Copy)]
+pub struct MessageDescriptor {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub fields: &'static [Field],
+}
+
+/// Values of an enumeration, as `(json name, protobuf number)`
+#[derive(Debug, Clone, Copy)]
+pub struct EnumDescriptor {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub values: &'static [(&'static str, u32)],
+}
+
+const fn field(name: &'static str, number: u32, field_type: FieldType, label: Label, doc: &'static str) -> Field {
+    Field { name, number, field_type, label, doc }
+}
+
+/// Descriptor of [`SignalUpdate`]
+pub const SIGNAL_UPDATE: MessageDescriptor = MessageDescriptor {
+    name: "SignalUpdate",
+    doc: "New value of one signal",
+    fields: &[
+        field("signal", 1, FieldType::String, Label::Required, "Signal name"),
+        field("value", 2, FieldType::Value, Label::Required, "Value after the change"),
+        field("timestamp_ms", 3, FieldType::Int64, Label::Required, "Milliseconds since the Unix epoch"),
+    ],
+};
+
+/// Descriptor of [`AlarmEventKind`]
+pub const ALARM_EVENT_KIND: EnumDescriptor = EnumDescriptor {
+    name: "AlarmEventKind",
+    doc: "Alarm lifecycle transition",
+    values: &[
+        ("raised", 1),
+        ("acknowledged", 2),
+        ("returned_to_normal", 3),
+        ("shelved", 4),
+        ("unshelved", 5),
+    ],
+};
+
+/// Descriptor of [`AlarmEvent`]
+pub const ALARM_EVENT: MessageDescriptor = MessageDescriptor {
+    name: "AlarmEvent",
+    doc: "Change in the state of an alarm",
+    fields: &[
+        field("alarm", 1, FieldType::String, Label::Required, "Alarm name"),
+        field("signal", 2, FieldType::String, Label::Required, "Monitored signal"),
+        field("kind", 3, FieldType::Enum("AlarmEventKind"), Label::Required, "Transition that happened"),
+        field("priority", 4, FieldType::UInt32, Label::Required, "ISA-18.2 priority, 1 (highest) to 4"),
+        field("value", 5, FieldType::Value, Label::Optional, "Signal value at the transition"),
+        field("message", 6, FieldType::String, Label::Required, "Operator message"),
+        field("timestamp_ms", 7, FieldType::Int64, Label::Required, "Milliseconds since the Unix epoch"),
+        field("user", 8, FieldType::String, Label::Optional, "Operator who acknowledged or shelved the alarm"),
+    ],
+};
+
+/// Descriptor of [`HistoryBatch`]
+pub const HISTORY_BATCH: MessageDescriptor = MessageDescriptor {
+    name: "HistoryBatch",
+    doc: "Recorded samples of a time range",
+    fields: &[
+        field("source", 1, FieldType::String, Label::Required, "Instance that recorded the samples"),
+        field("sequence", 2, FieldType::UInt64, Label::Required, "Counts up from 1 per source; a gap means a batch was lost"),
+        field("start_ms", 3, FieldType::Int64, Label::Required, "Start of the range, milliseconds since the Unix epoch"),
+        field("end_ms", 4, FieldType::Int64, Label::Required, "End of the range, milliseconds since the Unix epoch"),
+        field("samples", 5, FieldType::Message("SignalUpdate"), Label::Repeated, "Samples in time order"),
+    ],
+};
+
+/// Every message of the package
+pub const MESSAGES: &[MessageDescriptor] = &[SIGNAL_UPDATE, ALARM_EVENT, HISTORY_BATCH];
+
+/// Every enumeration of the package
+pub const ENUMS: &[EnumDescriptor] = &[ALARM_EVENT_KIND];
+
+// ============================================================================
+// PROTOBUF
+// ============================================================================
+
+const VALUE_PROTO: &str = "\
+// Signal value, encoded in JSON as {\"type\": \"Float\", \"value\": 1.5}
+message Value {
+  oneof kind {
+    bool bool_value = 1;
+    int64 int_value = 2;
+    double float_value = 3;
+    string string_value = 4;
+  }
+}
+";
+
+const fn proto_type(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Bool => "bool",
+        FieldType::UInt32 => "uint32",
+        FieldType::Int64 => "int64",
+        FieldType::UInt64 => "uint64",
+        FieldType::String => "string",
+        FieldType::Value => "Value",
+        FieldType::Enum(name) | FieldType::Message(name) => name,
+    }
+}
+
+/// `AlarmEventKind` as `ALARM_EVENT_KIND`
+fn screaming_snake(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// `AlarmEvent` as `alarm_event`
+fn snake(name: &str) -> String {
+    screaming_snake(name).to_lowercase()
+}
+
+/// The `.proto` file of the package
+#[must_use]
+pub fn proto_file() -> String {
+    let mut out = format!(
+        "// PETRA wire messages, version {WIRE_VERSION}\n\
+         //\n\
+         // Generated by `petra schema` from src/wire.rs; do not edit.\n\n\
+         syntax = \"proto3\";\n\n\
+         package {PROTO_PACKAGE};\n\n{VALUE_PROTO}"
+    );
+    for descriptor in ENUMS {
+        let prefix = screaming_snake(descriptor.name);
+        let _ = writeln!(out, "\n// {}\nenum {} {{", descriptor.doc, descriptor.name);
+        let _ = writeln!(out, "  {prefix}_UNSPECIFIED = 0;");
+        for (value, number) in descriptor.values {
+            let _ = writeln!(out, "  {prefix}_{} = {number};", value.to_uppercase());
+        }
+        out.push_str("}\n");
+    }
+    for descriptor in MESSAGES {
+        let _ = writeln!(out, "\n// {}\nmessage {} {{", descriptor.doc, descriptor.name);
+        for field in descriptor.fields {
+            let label = match field.label {
+                Label::Required => "",
+                Label::Optional => "optional ",
+                Label::Repeated => "repeated ",
+            };
+            let _ = writeln!(out, "  // {}", field.doc);
+            let _ = writeln!(
+                out,
+                "  {label}{} {} = {};",
+                proto_type(field.field_type),
+                field.name,
+                field.number
+            );
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+// ============================================================================
+// JSON SCHEMA
+// ============================================================================
+
+fn value_schema() -> serde_json::Value {
+    let variant = |name: &str, value: serde_json::Value| {
+        json!({
+            "properties": { "type": { "const": name }, "value": value },
+        })
+    };
+    json!({
+        "description": "Signal value; String needs the extended-types feature",
+        "type": "object",
+        "required": ["type", "value"],
+        "oneOf": [
+            variant("Bool", json!({ "type": "boolean" })),
+            variant("Integer", json!({ "type": "integer" })),
+            variant("Float", json!({ "type": "number" })),
+            variant("String", json!({ "type": "string" })),
+        ],
+    })
+}
+
+fn field_schema(field: &Field, defs: &mut serde_json::Map<String, serde_json::Value>) -> serde_json::Value {
+    let mut schema = match field.field_type {
+        FieldType::Bool => json!({ "type": "boolean" }),
+        FieldType::UInt32 | FieldType::UInt64 => json!({ "type": "integer", "minimum": 0 }),
+        FieldType::Int64 => json!({ "type": "integer" }),
+        FieldType::String => json!({ "type": "string" }),
+        FieldType::Value => {
+            defs.insert("Value".to_string(), value_schema());
+            json!({ "$ref": "#/$defs/Value" })
+        }
+        FieldType::Enum(name) => {
+            if let Some(descriptor) = ENUMS.iter().find(|e| e.name == name) {
+                let values: Vec<&str> = descriptor.values.iter().map(|(value, _)| *value).collect();
+                defs.insert(
+                    name.to_string(),
+                    json!({ "description": descriptor.doc, "type": "string", "enum": values }),
+                );
+            }
+            json!({ "$ref": format!("#/$defs/{name}") })
+        }
+        FieldType::Message(name) => {
+            if let Some(descriptor) = MESSAGES.iter().find(|m| m.name == name) {
+                let schema = object_schema(descriptor, defs);
+                defs.insert(name.to_string(), schema);
+            }
+            json!({ "$ref": format!("#/$defs/{name}") })
+        }
+    };
+    if field.label == Label::Repeated {
+        schema = json!({ "type": "array", "items": schema });
+    }
+    schema["description"] = json!(field.doc);
+    schema
+}
+
+fn object_schema(descriptor: &MessageDescriptor, defs: &mut serde_json::Map<String, serde_json::Value>) -> serde_json::Value {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::new();
+    for field in descriptor.fields {
+        properties.insert(field.name.to_string(), field_schema(field, defs));
+        if field.label != Label::Optional {
+            required.push(field.name);
+        }
+    }
+    json!({
+        "description": descriptor.doc,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Objects with their keys in sorted order, whatever map type `serde_json`
+/// was built with
+fn canonical(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(entries.into_iter().map(|(k, v)| (k, canonical(v))).collect())
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(canonical).collect()),
+        other => other,
+    }
+}
+
+/// JSON Schema (draft 2020-12) of a message's serde encoding
+#[must_use]
+pub fn json_schema(descriptor: &MessageDescriptor) -> serde_json::Value {
+    let mut defs = serde_json::Map::new();
+    let mut schema = object_schema(descriptor, &mut defs);
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["$id"] = json!(format!("{SCHEMA_ID_PREFIX}:{}", descriptor.name));
+    schema["title"] = json!(descriptor.name);
+    schema["version"] = json!(WIRE_VERSION);
+    if !defs.is_empty() {
+        schema["$defs"] = serde_json::Value::Object(defs);
+    }
+    canonical(schema)
+}
+
+// ============================================================================
+// PACKAGE
+// ============================================================================
+
+/// File names and contents of the package
+#[must_use]
+pub fn files() -> Vec<(String, String)> {
+    let mut files = vec![("petra_wire.proto".to_string(), proto_file())];
+    for descriptor in MESSAGES {
+        files.push((
+            format!("{}.schema.json", snake(descriptor.name)),
+            format!("{:#}\n", json_schema(descriptor)),
+        ));
+    }
+    files
+}
+
+/// Write the package into `dir`
+///
+/// # Errors
+///
+/// Returns [`PlcError::Io`] if a file cannot be written.
+pub fn write_package(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (name, contents) in files() {
+        std::fs::write(dir.join(name), contents)?;
+    }
+    Ok(())
+}
+
+/// Names of the files in `dir` that are missing or differ from the package
+///
+/// # Errors
+///
+/// Returns [`PlcError::Io`] if a file exists but cannot be read.
+pub fn outdated_files(dir: &Path) -> Result<Vec<String>> {
+    let mut outdated = Vec::new();
+    for (name, contents) in files() {
+        match std::fs::read_to_string(dir.join(&name)) {
+            Ok(existing) if existing == contents => {}
+            Ok(_) => outdated.push(name),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => outdated.push(name),
+            Err(e) => return Err(PlcError::Io(e)),
+        }
+    }
+    Ok(outdated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `json` is what `field` describes
+    fn conforms(field: &Field, json: &serde_json::Value) -> bool {
+        let single = |json: &serde_json::Value| match field.field_type {
+            FieldType::Bool => json.is_boolean(),
+            FieldType::UInt32 | FieldType::UInt64 => json.is_u64(),
+            FieldType::Int64 => json.is_i64(),
+            FieldType::String => json.is_string(),
+            FieldType::Value => json["type"].is_string() && !json["value"].is_null(),
+            FieldType::Enum(name) => ENUMS
+                .iter()
+                .any(|e| e.name == name && e.values.iter().any(|(v, _)| json.as_str() == Some(*v))),
+            FieldType::Message(_) => json.is_object(),
+        };
+        match field.label {
+            Label::Repeated => json.as_array().is_some_and(|items| items.iter().all(single)),
+            Label::Required | Label::Optional => single(json),
+        }
+    }
+
+    /// The serde encoding of `message` has exactly the fields of `descriptor`
+    fn assert_matches<T: Serialize>(descriptor: &MessageDescriptor, message: &T) {
+        let json = serde_json::to_value(message).unwrap();
+        let object = json.as_object().unwrap();
+        for field in descriptor.fields {
+            let value = object.get(field.name).unwrap_or_else(|| panic!("{}.{} missing", descriptor.name, field.name));
+            assert!(conforms(field, value), "{}.{} is {value}", descriptor.name, field.name);
+        }
+        for key in object.keys() {
+            assert!(
+                descriptor.fields.iter().any(|f| f.name == key),
+                "{}.{key} is not described",
+                descriptor.name
+            );
+        }
+        let mut numbers: Vec<u32> = descriptor.fields.iter().map(|f| f.number).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        assert_eq!(numbers.len(), descriptor.fields.len(), "{} reuses a field number", descriptor.name);
+    }
+
+    #[test]
+    fn test_types_match_descriptors() {
+        let update = SignalUpdate {
+            signal: "line1.speed".to_string(),
+            value: Value::Float(42.5),
+            timestamp_ms: 1_700_000_000_000,
+        };
+        assert_matches(&SIGNAL_UPDATE, &update);
+        assert_matches(
+            &ALARM_EVENT,
+            &AlarmEvent {
+                alarm: "tank_high".to_string(),
+                signal: "tank.level".to_string(),
+                kind: AlarmEventKind::Acknowledged,
+                priority: 2,
+                value: Some(Value::Integer(97)),
+                message: "Tank level high".to_string(),
+                timestamp_ms: 1_700_000_000_500,
+                user: Some("operator".to_string()),
+            },
+        );
+        assert_matches(
+            &HISTORY_BATCH,
+            &HistoryBatch {
+                source: "line1".to_string(),
+                sequence: 7,
+                start_ms: 1_700_000_000_000,
+                end_ms: 1_700_000_060_000,
+                samples: vec![update],
+            },
+        );
+
+        let kinds: Vec<_> = AlarmEventKind::ALL.iter().map(|k| serde_json::to_value(k).unwrap()).collect();
+        let described: Vec<_> = ALARM_EVENT_KIND.values.iter().map(|(v, _)| json!(v)).collect();
+        assert_eq!(kinds, described);
+    }
+
+    #[test]
+    fn test_published_package_is_current() {
+        let published = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema/wire/v1");
+        assert_eq!(
+            outdated_files(&published).unwrap(),
+            Vec::<String>::new(),
+            "regenerate with `petra schema --output schema/wire/v1`"
+        );
+
+        let proto = proto_file();
+        assert!(proto.contains("package petra.wire.v1;"));
+        assert!(proto.contains("  ALARM_EVENT_KIND_RETURNED_TO_NORMAL = 3;"));
+        assert!(proto.contains("  repeated SignalUpdate samples = 5;"));
+        let schema = json_schema(&HISTORY_BATCH);
+        assert_eq!(schema["$defs"]["SignalUpdate"]["required"], json!(["signal", "value", "timestamp_ms"]));
+        assert_eq!(schema["properties"]["samples"]["items"]["$ref"], "#/$defs/SignalUpdate");
+    }
+}